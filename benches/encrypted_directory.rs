@@ -0,0 +1,64 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use tantivy::directory::{
+    Directory, EncryptedDirectory, FileSlice, MmapDirectory, TerminatingWrite,
+};
+
+const FILE_LEN: usize = 16 * 1_024 * 1_024;
+const NUM_READS: usize = 10_000;
+
+/// Writes a file of random bytes in `directory`, and opens it.
+fn write_file(directory: &dyn Directory) -> FileSlice {
+    let path = Path::new("data");
+    let mut rng = StdRng::from_seed([1u8; 32]);
+    let data: Vec<u8> = (0..FILE_LEN).map(|_| rng.gen()).collect();
+    let mut wrt = directory.open_write(path).unwrap();
+    wrt.write_all(&data).unwrap();
+    wrt.terminate().unwrap();
+    directory.open_read(path).unwrap()
+}
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let mmap_tempdir = tempfile::TempDir::new().unwrap();
+    let mmap_directory = MmapDirectory::open(mmap_tempdir.path()).unwrap();
+    let encrypted_tempdir = tempfile::TempDir::new().unwrap();
+    let encrypted_directory = EncryptedDirectory::new(
+        Arc::new(MmapDirectory::open(encrypted_tempdir.path()).unwrap()),
+        [7u8; 32],
+    );
+    let files = vec![
+        ("mmap", write_file(&mmap_directory)),
+        ("encrypted", write_file(&encrypted_directory)),
+    ];
+
+    // Small scattered reads, as done by the term dictionary or the fast fields.
+    let mut rng = StdRng::from_seed([2u8; 32]);
+    let offsets: Vec<usize> = (0..NUM_READS)
+        .map(|_| rng.gen_range(0..FILE_LEN - 64))
+        .collect();
+    for (name, file_slice) in &files {
+        c.bench_function(&format!("{}-scattered-read", name), |b| {
+            b.iter(|| {
+                for &offset in &offsets {
+                    black_box(file_slice.read_bytes_slice(offset..offset + 64).unwrap());
+                }
+            })
+        });
+    }
+    for (name, file_slice) in &files {
+        c.bench_function(&format!("{}-full-read", name), |b| {
+            b.iter(|| black_box(file_slice.read_bytes().unwrap()))
+        });
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = criterion_benchmark
+}
+criterion_main!(benches);
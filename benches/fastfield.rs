@@ -0,0 +1,57 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tantivy::fastfield::{DynamicFastFieldReader, FastFieldReader};
+use tantivy::DocId;
+
+const NUM_DOCS: u32 = 100_000;
+
+/// Returns a reader over random values, which are bitpacked.
+fn bitpacked_reader() -> DynamicFastFieldReader<u64> {
+    let mut rng = StdRng::from_seed([1u8; 32]);
+    let vals: Vec<u64> = (0..NUM_DOCS).map(|_| rng.gen_range(0..1_000_000)).collect();
+    let reader = DynamicFastFieldReader::from(vals);
+    assert!(matches!(reader, DynamicFastFieldReader::Bitpacked(_)));
+    reader
+}
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let reader = bitpacked_reader();
+    let mut buffer = vec![0u64; NUM_DOCS as usize];
+    c.bench_function("bitpacked-get-contiguous", |b| {
+        b.iter(|| {
+            for (doc, out) in (0..NUM_DOCS).zip(buffer.iter_mut()) {
+                *out = reader.get(doc);
+            }
+            black_box(&buffer);
+        })
+    });
+    c.bench_function("bitpacked-get-range", |b| {
+        b.iter(|| {
+            reader.get_range(0, &mut buffer[..]);
+            black_box(&buffer);
+        })
+    });
+
+    let mut rng = StdRng::from_seed([2u8; 32]);
+    let mut docs: Vec<DocId> = (0..NUM_DOCS).filter(|_| rng.gen_bool(0.3)).collect();
+    docs.sort_unstable();
+    let mut buffer = vec![0u64; docs.len()];
+    c.bench_function("bitpacked-get-scattered", |b| {
+        b.iter(|| {
+            for (doc, out) in docs.iter().zip(buffer.iter_mut()) {
+                *out = reader.get(*doc);
+            }
+            black_box(&buffer);
+        })
+    });
+    c.bench_function("bitpacked-get-batch", |b| {
+        b.iter(|| {
+            reader.get_batch(&docs, &mut buffer[..]);
+            black_box(&buffer);
+        })
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
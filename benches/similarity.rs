@@ -0,0 +1,87 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use tantivy::collector::{Count, TopDocs};
+use tantivy::query::{Bm25Similarity, BooleanQuery, ClassicTfIdf, Explanation, Query, Similarity};
+use tantivy::schema::{Schema, TEXT};
+use tantivy::{doc, Index, Score, Term};
+
+const ALICE_TXT: &str = include_str!("alice.txt");
+
+/// BM25 with its default parameters, hidden behind a custom similarity, to measure the
+/// cost of the dynamic dispatch.
+struct DynBm25(Bm25Similarity);
+
+impl Similarity for DynBm25 {
+    fn term_weight(&self, term_doc_freqs: &[u64], total_num_docs: u64) -> Explanation {
+        self.0.term_weight(term_doc_freqs, total_num_docs)
+    }
+
+    fn doc_normalization(&self, fieldnorm: u32, average_fieldnorm: Score) -> Score {
+        self.0.doc_normalization(fieldnorm, average_fieldnorm)
+    }
+
+    fn score(&self, term_weight: Score, term_freq: u32, doc_normalization: Score) -> Score {
+        self.0.score(term_weight, term_freq, doc_normalization)
+    }
+
+    fn explain(
+        &self,
+        term_weight: &Explanation,
+        term_freq: u32,
+        fieldnorm: u32,
+        average_fieldnorm: Score,
+    ) -> Explanation {
+        self.0
+            .explain(term_weight, term_freq, fieldnorm, average_fieldnorm)
+    }
+}
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let mut schema_builder = Schema::builder();
+    let text = schema_builder.add_text_field("text", TEXT);
+    let index = Index::create_in_ram(schema_builder.build());
+    let mut index_writer = index.writer_with_num_threads(1, 50_000_000).unwrap();
+    for _ in 0..20 {
+        for line in ALICE_TXT.lines() {
+            index_writer.add_document(doc!(text => line));
+        }
+    }
+    index_writer.commit().unwrap();
+    let searcher = index.reader().unwrap().searcher();
+    let query = BooleanQuery::new_multiterms_query(
+        ["the", "queen", "alice", "rabbit"]
+            .iter()
+            .map(|word| Term::from_field_text(text, word))
+            .collect(),
+    );
+    for name in ["bm25", "bm25-params", "bm25-dyn", "tfidf"] {
+        match name {
+            "bm25" => index.set_similarity(text, Bm25Similarity::default()),
+            "bm25-params" => index.set_similarity(text, Bm25Similarity::new(1.5, 0.3)),
+            "bm25-dyn" => index.set_similarity(text, DynBm25(Bm25Similarity::default())),
+            _ => index.set_similarity(text, ClassicTfIdf),
+        }
+        c.bench_function(&format!("{}-top10", name), |b| {
+            b.iter(|| searcher.search(&query, &TopDocs::with_limit(10)).unwrap())
+        });
+        c.bench_function(&format!("{}-score-all", name), |b| {
+            b.iter(|| {
+                let weight = query.weight(&searcher, true).unwrap();
+                let mut total_score = 0.0;
+                for segment_reader in searcher.segment_readers() {
+                    weight
+                        .for_each(segment_reader, &mut |_, score| total_score += score)
+                        .unwrap();
+                }
+                total_score
+            })
+        });
+    }
+    assert!(searcher.search(&query, &Count).unwrap() > 0);
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = criterion_benchmark
+}
+criterion_main!(benches);
@@ -102,6 +102,37 @@ impl BitUnpacker {
         let val_shifted = (val_unshifted_unmasked >> bit_shift) as u64;
         val_shifted & mask
     }
+
+    /// Fills `output` with the values at the indexes `start_idx..start_idx + output.len()`.
+    ///
+    /// This is faster than calling `get` for each of the indexes, as the data
+    /// is sliced once for the whole range.
+    pub fn get_range(&self, start_idx: u64, output: &mut [u64], data: &[u8]) {
+        if self.num_bits == 0 || output.is_empty() {
+            for val in output.iter_mut() {
+                *val = 0u64;
+            }
+            return;
+        }
+        let num_bits = self.num_bits;
+        let start_addr_in_bits = start_idx * num_bits;
+        let start_addr = (start_addr_in_bits >> 3) as usize;
+        let last_addr_in_bits = (start_idx + output.len() as u64 - 1) * num_bits;
+        let stop_addr = (last_addr_in_bits >> 3) as usize + 8;
+        debug_assert!(
+            stop_addr <= data.len(),
+            "The fast field field should have been padded with 7 bytes."
+        );
+        let data = &data[start_addr..stop_addr];
+        let mut addr_in_bits = start_addr_in_bits & 7;
+        for val in output.iter_mut() {
+            let addr = (addr_in_bits >> 3) as usize;
+            let bit_shift = addr_in_bits & 7;
+            let bytes: [u8; 8] = (&data[addr..addr + 8]).try_into().unwrap();
+            *val = (u64::from_le_bytes(bytes) >> bit_shift) & self.mask;
+            addr_in_bits += num_bits;
+        }
+    }
 }
 
 #[cfg(test)]
@@ -131,6 +162,26 @@ mod test {
         }
     }
 
+    fn test_bitpacker_get_range_util(len: usize, num_bits: u8) {
+        let (bitunpacker, vals, data) = create_fastfield_bitpacker(len, num_bits);
+        for start in 0..len.min(70) {
+            for stop in start..len.min(start + 70) {
+                let mut output = vec![1u64; stop - start];
+                bitunpacker.get_range(start as u64, &mut output, &data);
+                assert_eq!(&output[..], &vals[start..stop]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitpacker_get_range() {
+        test_bitpacker_get_range_util(10, 3);
+        test_bitpacker_get_range_util(10, 0);
+        test_bitpacker_get_range_util(100, 1);
+        test_bitpacker_get_range_util(100, 14);
+        test_bitpacker_get_range_util(100, 63);
+    }
+
     #[test]
     fn test_bitpacker() {
         test_bitpacker_util(10, 3);
@@ -33,6 +33,18 @@ impl TinySet {
         TinySet(0u64)
     }
 
+    /// Returns a `TinySet` containing all of the elements of `[0, 64[`.
+    pub fn full() -> TinySet {
+        TinySet::empty().complement()
+    }
+
+    /// Creates a `TinySet` from its raw 64-bit representation,
+    /// where bit `i` is set iff `i` belongs to the set.
+    #[inline]
+    pub fn from_u64(bits: u64) -> TinySet {
+        TinySet(bits)
+    }
+
     pub fn clear(&mut self) {
         self.0 = 0u64;
     }
@@ -190,6 +202,23 @@ impl BitSet {
         self.max_value
     }
 
+    /// Intersects the `BitSet` with another set, given as a sequence of
+    /// `TinySet`s, one per bucket.
+    ///
+    /// Buckets for which `other` does not yield any `TinySet` are cleared.
+    pub fn intersect_update<I: IntoIterator<Item = TinySet>>(&mut self, other: I) {
+        let mut other_it = other.into_iter();
+        let mut len = 0;
+        for tinyset in self.tinysets.iter_mut() {
+            *tinyset = match other_it.next() {
+                Some(other_tinyset) => tinyset.intersect(other_tinyset),
+                None => TinySet::empty(),
+            };
+            len += tinyset.len() as usize;
+        }
+        self.len = len;
+    }
+
     /// Returns the tiny bitset representing the
     /// the set restricted to the number range from
     /// `bucket * 64` to `(bucket + 1) * 64`.
@@ -328,6 +357,22 @@ mod tests {
         sample_with_seed(n, ratio, 4)
     }
 
+    #[test]
+    fn test_bitset_intersect_update() {
+        let mut bitset = BitSet::with_max_value(200);
+        for el in [1u32, 3, 64, 65, 130, 199].iter().cloned() {
+            bitset.insert(el);
+        }
+        bitset.intersect_update(vec![TinySet::from_u64(0b1010), TinySet::full()]);
+        assert_eq!(bitset.len(), 4);
+        assert!(bitset.contains(1));
+        assert!(bitset.contains(3));
+        assert!(bitset.contains(64));
+        assert!(bitset.contains(65));
+        assert!(!bitset.contains(130));
+        assert!(!bitset.contains(199));
+    }
+
     #[test]
     fn test_bitset_clear() {
         let mut bitset = BitSet::with_max_value(1_000);
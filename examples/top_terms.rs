@@ -0,0 +1,70 @@
+// # Listing the most frequent terms of a field
+//
+// This example shows how to stream the term dictionary of a field,
+// to list its most frequent terms across all of the segments of a searcher,
+// and the terms starting with a prefix, as an autocomplete would.
+
+// ---
+// Importing tantivy...
+use std::collections::HashMap;
+use tantivy::schema::*;
+use tantivy::{doc, Index};
+
+fn main() -> tantivy::Result<()> {
+    let mut schema_builder = Schema::builder();
+    let title = schema_builder.add_text_field("title", TEXT);
+    let schema = schema_builder.build();
+    let index = Index::create_in_ram(schema);
+
+    // We commit twice, in order to get two segments, each with its own
+    // term dictionary.
+    let mut index_writer = index.writer_with_num_threads(1, 50_000_000)?;
+    index_writer.add_document(doc!(title => "The Old Man and the Sea"));
+    index_writer.add_document(doc!(title => "Of Mice and Men"));
+    index_writer.commit()?;
+    index_writer.add_document(doc!(title => "The Sea Wolf"));
+    index_writer.add_document(doc!(title => "The Modern Prometheus"));
+    index_writer.commit()?;
+
+    let searcher = index.reader()?.searcher();
+
+    // The term dictionary of a segment associates each term of a field with a `TermInfo`,
+    // holding its document frequency in the segment.
+    //
+    // Note that the document frequency also counts the deleted documents of the segment.
+    let mut doc_freqs: HashMap<String, u32> = HashMap::new();
+    for segment_reader in searcher.segment_readers() {
+        let inverted_index = segment_reader.inverted_index(title)?;
+        let mut term_stream = inverted_index.terms().stream()?;
+        while let Some((term_bytes, term_info)) = term_stream.next() {
+            let term = String::from_utf8_lossy(term_bytes).to_string();
+            *doc_freqs.entry(term).or_insert(0) += term_info.doc_freq;
+        }
+    }
+    let mut top_terms: Vec<(String, u32)> = doc_freqs.into_iter().collect();
+    top_terms.sort_by(|(left_term, left_freq), (right_term, right_freq)| {
+        right_freq.cmp(left_freq).then(left_term.cmp(right_term))
+    });
+    for (term, doc_freq) in top_terms.iter().take(3) {
+        println!("{}: {} documents", term, doc_freq);
+    }
+    // the: 3 documents
+    // and: 2 documents
+    // sea: 2 documents
+
+    // The terms starting with a prefix can be streamed without going through the
+    // whole dictionary.
+    for segment_reader in searcher.segment_readers() {
+        let inverted_index = segment_reader.inverted_index(title)?;
+        let mut term_stream = inverted_index.terms().prefix_range("m").into_stream()?;
+        while let Some((term_bytes, _term_info)) = term_stream.next() {
+            println!("{}", String::from_utf8_lossy(term_bytes));
+        }
+    }
+    // man
+    // men
+    // mice
+    // modern
+
+    Ok(())
+}
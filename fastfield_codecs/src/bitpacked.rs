@@ -37,6 +37,12 @@ impl<'data> FastFieldCodecReader for BitpackedFastFieldReader {
     fn get_u64(&self, doc: u64, data: &[u8]) -> u64 {
         self.min_value_u64 + self.bit_unpacker.get(doc, data)
     }
+    fn get_range_u64(&self, start: u64, output: &mut [u64], data: &[u8]) {
+        self.bit_unpacker.get_range(start, output, data);
+        for out in output.iter_mut() {
+            *out += self.min_value_u64;
+        }
+    }
     #[inline]
     fn min_value(&self) -> u64 {
         self.min_value_u64
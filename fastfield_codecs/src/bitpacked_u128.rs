@@ -0,0 +1,182 @@
+use common::BinarySerializable;
+use std::io::{self, Write};
+use tantivy_bitpacker::compute_num_bits;
+use tantivy_bitpacker::BitPacker;
+use tantivy_bitpacker::BitUnpacker;
+
+/// Size of the footer: the min value and the amplitude as `u128`, and the number of values.
+const FOOTER_LEN: usize = 16 + 16 + 8;
+
+/// Returns the number of bits used to bitpack the low and the high 64 bits
+/// of values in `0..=amplitude`.
+fn compute_num_bits_u128(amplitude: u128) -> (u8, u8) {
+    let high = (amplitude >> 64) as u64;
+    if high == 0 {
+        (compute_num_bits(amplitude as u64), 0)
+    } else {
+        (64, compute_num_bits(high))
+    }
+}
+
+/// Returns the number of bytes of a block of `num_vals` values bitpacked
+/// over `num_bits` bits, including its padding.
+fn block_len(num_vals: u64, num_bits: u8) -> usize {
+    ((num_vals * u64::from(num_bits) + 7) / 8) as usize + 7
+}
+
+/// Reader for `u128` values serialized with the
+/// [`BitpackedU128FastFieldSerializer`](./struct.BitpackedU128FastFieldSerializer.html).
+#[derive(Clone)]
+pub struct BitpackedU128FastFieldReader {
+    low_bit_unpacker: BitUnpacker,
+    high_bit_unpacker: BitUnpacker,
+    /// Offset of the block of the high bits.
+    high_offset: usize,
+    min_value: u128,
+    max_value: u128,
+    num_vals: u64,
+}
+
+impl BitpackedU128FastFieldReader {
+    /// Reads the footer of the data and returns the reader.
+    pub fn open_from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < FOOTER_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "u128 fast field data is too short",
+            ));
+        }
+        let (_data, mut footer) = bytes.split_at(bytes.len() - FOOTER_LEN);
+        let min_value = u128::deserialize(&mut footer)?;
+        let amplitude = u128::deserialize(&mut footer)?;
+        let num_vals = u64::deserialize(&mut footer)?;
+        let (low_num_bits, high_num_bits) = compute_num_bits_u128(amplitude);
+        Ok(BitpackedU128FastFieldReader {
+            low_bit_unpacker: BitUnpacker::new(low_num_bits),
+            high_bit_unpacker: BitUnpacker::new(high_num_bits),
+            high_offset: block_len(num_vals, low_num_bits),
+            min_value,
+            max_value: min_value + amplitude,
+            num_vals,
+        })
+    }
+
+    /// Returns the value at the given position.
+    #[inline]
+    pub fn get_u128(&self, idx: u64, data: &[u8]) -> u128 {
+        let low = self.low_bit_unpacker.get(idx, data);
+        let high = self.high_bit_unpacker.get(idx, &data[self.high_offset..]);
+        self.min_value + ((u128::from(high) << 64) | u128::from(low))
+    }
+
+    /// Returns the minimum value.
+    pub fn min_value(&self) -> u128 {
+        self.min_value
+    }
+
+    /// Returns the maximum value.
+    pub fn max_value(&self) -> u128 {
+        self.max_value
+    }
+
+    /// Returns the number of values.
+    pub fn num_vals(&self) -> u64 {
+        self.num_vals
+    }
+}
+
+/// Serializer for `u128` values.
+///
+/// The serializer bitpacks `(val - min_value)`, the low 64 bits and the high 64 bits
+/// in two separate blocks, so that values fitting in fewer bits still take little space.
+/// Since `u128` values are not mapped, the order of the values is preserved.
+pub struct BitpackedU128FastFieldSerializer;
+
+impl BitpackedU128FastFieldSerializer {
+    /// Serializes the values into `write`.
+    ///
+    /// The values are iterated over three times.
+    pub fn serialize(
+        write: &mut impl Write,
+        vals: impl Iterator<Item = u128> + Clone,
+    ) -> io::Result<()> {
+        let (min_value, max_value, num_vals) = vals.clone().fold(
+            (u128::MAX, u128::MIN, 0u64),
+            |(min_value, max_value, num_vals), val| {
+                (min_value.min(val), max_value.max(val), num_vals + 1)
+            },
+        );
+        let min_value = if num_vals == 0 { 0 } else { min_value };
+        let amplitude = max_value.saturating_sub(min_value);
+        let (low_num_bits, high_num_bits) = compute_num_bits_u128(amplitude);
+        let mut bit_packer = BitPacker::new();
+        for val in vals.clone() {
+            bit_packer.write((val - min_value) as u64, low_num_bits, write)?;
+        }
+        bit_packer.close(write)?;
+        for val in vals {
+            bit_packer.write(((val - min_value) >> 64) as u64, high_num_bits, write)?;
+        }
+        bit_packer.close(write)?;
+        min_value.serialize(write)?;
+        amplitude.serialize(write)?;
+        num_vals.serialize(write)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_and_validate(data: &[u128]) -> Vec<u8> {
+        let mut out = vec![];
+        BitpackedU128FastFieldSerializer::serialize(&mut out, data.iter().cloned()).unwrap();
+        let reader = BitpackedU128FastFieldReader::open_from_bytes(&out).unwrap();
+        assert_eq!(reader.num_vals(), data.len() as u64);
+        for (idx, val) in data.iter().enumerate() {
+            assert_eq!(reader.get_u128(idx as u64, &out), *val);
+        }
+        if let (Some(min), Some(max)) = (data.iter().min(), data.iter().max()) {
+            assert_eq!(reader.min_value(), *min);
+            assert_eq!(reader.max_value(), *max);
+        }
+        out
+    }
+
+    #[test]
+    fn test_bitpacked_u128_small_amplitude() {
+        let base = 0xffff_0000_0000_0000_0000_0000_0000_0000u128;
+        let data: Vec<u128> = (0..100u128).map(|i| base + i * 3).collect();
+        let out = create_and_validate(&data);
+        // the values fit in 9 bits, and no high bits are stored.
+        assert_eq!(
+            out.len(),
+            block_len(100, 9) + block_len(100, 0) + FOOTER_LEN
+        );
+    }
+
+    #[test]
+    fn test_bitpacked_u128_large_amplitude() {
+        create_and_validate(&[u128::MAX, 0, 1 << 64, (1 << 64) - 1, 1 << 100, 42]);
+        create_and_validate(&[u128::MAX, u128::MAX - (1 << 70)]);
+    }
+
+    #[test]
+    fn test_bitpacked_u128_edge_cases() {
+        create_and_validate(&[]);
+        create_and_validate(&[7]);
+        create_and_validate(&[u128::MAX]);
+        create_and_validate(&[3, 3, 3]);
+    }
+
+    #[test]
+    fn test_bitpacked_u128_rand() {
+        for _ in 0..100 {
+            let data: Vec<u128> = (0..1 + rand::random::<u8>() as usize)
+                .map(|_| rand::random::<u128>() >> rand::random::<u8>().min(127))
+                .collect();
+            create_and_validate(&data);
+        }
+    }
+}
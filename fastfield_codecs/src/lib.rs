@@ -6,6 +6,7 @@ use std::io;
 use std::io::Write;
 
 pub mod bitpacked;
+pub mod bitpacked_u128;
 pub mod linearinterpol;
 pub mod multilinearinterpol;
 
@@ -15,6 +16,27 @@ pub trait FastFieldCodecReader: Sized {
 
     fn get_u64(&self, doc: u64, data: &[u8]) -> u64;
 
+    /// Fills `output` with the values at the positions `start..start + output.len()`.
+    ///
+    /// Codecs should override it to decode the values in one go.
+    fn get_range_u64(&self, start: u64, output: &mut [u64], data: &[u8]) {
+        for (pos, out) in (start..).zip(output.iter_mut()) {
+            *out = self.get_u64(pos, data);
+        }
+    }
+
+    /// Fills `output` with the values at the given `positions`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `positions` and `output` do not have the same length.
+    fn get_batch_u64(&self, positions: &[u32], output: &mut [u64], data: &[u8]) {
+        assert_eq!(positions.len(), output.len());
+        for (pos, out) in positions.iter().zip(output.iter_mut()) {
+            *out = self.get_u64(u64::from(*pos), data);
+        }
+    }
+
     fn min_value(&self) -> u64;
     fn max_value(&self) -> u64;
 }
@@ -118,6 +140,18 @@ mod tests {
                 );
             }
         }
+        for start in (0..data.len()).step_by(97) {
+            let stop = data.len().min(start + 1100);
+            let mut output = vec![0u64; stop - start];
+            reader.get_range_u64(start as u64, &mut output, &out);
+            assert_eq!(&output[..], &data[start..stop], "in data set {}", name);
+        }
+        let positions: Vec<u32> = (0..data.len() as u32).rev().step_by(3).collect();
+        let mut output = vec![0u64; positions.len()];
+        reader.get_batch_u64(&positions, &mut output, &out);
+        for (pos, val) in positions.iter().zip(output.iter()) {
+            assert_eq!(*val, data[*pos as usize], "in data set {}", name);
+        }
         let actual_compression = data.len() as f32 / out.len() as f32;
         (estimation, actual_compression)
     }
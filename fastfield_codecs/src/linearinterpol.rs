@@ -80,6 +80,13 @@ impl FastFieldCodecReader for LinearInterpolFastFieldReader {
         let calculated_value = get_calculated_value(self.footer.first_val, doc, self.slope);
         (calculated_value + self.bit_unpacker.get(doc, data)) - self.footer.offset
     }
+    fn get_range_u64(&self, start: u64, output: &mut [u64], data: &[u8]) {
+        self.bit_unpacker.get_range(start, output, data);
+        for (doc, out) in (start..).zip(output.iter_mut()) {
+            let calculated_value = get_calculated_value(self.footer.first_val, doc, self.slope);
+            *out = (calculated_value + *out) - self.footer.offset;
+        }
+    }
 
     #[inline]
     fn min_value(&self) -> u64 {
@@ -174,6 +174,28 @@ impl FastFieldCodecReader for MultiLinearInterpolFastFieldReader {
         (calculated_value + diff) - interpolation.positive_val_offset
     }
 
+    fn get_range_u64(&self, start: u64, mut output: &mut [u64], data: &[u8]) {
+        let mut doc = start;
+        while !output.is_empty() {
+            let interpolation = get_interpolation_function(doc, &self.footer.interpolations);
+            let start_in_chunk = doc - interpolation.start_pos;
+            let chunk_len = ((CHUNK_SIZE - start_in_chunk) as usize).min(output.len());
+            let (chunk_output, remaining_output) = output.split_at_mut(chunk_len);
+            interpolation.bit_unpacker.get_range(
+                start_in_chunk,
+                chunk_output,
+                &data[interpolation.data_start_offset as usize..],
+            );
+            for (pos, out) in (start_in_chunk..).zip(chunk_output.iter_mut()) {
+                let calculated_value =
+                    get_calculated_value(interpolation.value_start_pos, pos, interpolation.slope);
+                *out = (calculated_value + *out) - interpolation.positive_val_offset;
+            }
+            doc += chunk_len as u64;
+            output = remaining_output;
+        }
+    }
+
     #[inline]
     fn min_value(&self) -> u64 {
         self.footer.min_value
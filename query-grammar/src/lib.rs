@@ -4,12 +4,36 @@ mod user_input_ast;
 use combine::parser::Parser;
 
 pub use crate::occur::Occur;
-use crate::query_grammar::parse_to_ast;
+use crate::query_grammar::{find_invalid_operator_value, parse_to_ast, parse_to_ast_lenient};
 pub use crate::user_input_ast::{UserInputAst, UserInputBound, UserInputLeaf, UserInputLiteral};
 
-pub struct Error;
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The query is not valid.
+    Syntax,
+    /// The value of a boost is not a positive number, e.g. `^abc`.
+    InvalidBoost(String),
+    /// The distance of a fuzzy term is not a valid distance, e.g. `~3`.
+    InvalidFuzzyDistance(String),
+    /// A double quote is not matched, e.g. `"hello`.
+    UnbalancedQuotes,
+    /// A parenthesis is not matched, e.g. `(a OR b`.
+    UnbalancedParentheses,
+    /// A part of the query could not be parsed, and was degraded to its words.
+    InvalidFragment(String),
+}
 
 pub fn parse_query(query: &str) -> Result<UserInputAst, Error> {
-    let (user_input_ast, _remaining) = parse_to_ast().parse(query).map_err(|_| Error)?;
+    let (user_input_ast, _remaining) = parse_to_ast()
+        .parse(query)
+        .map_err(|_| find_invalid_operator_value(query).unwrap_or(Error::Syntax))?;
     Ok(user_input_ast)
 }
+
+/// Parses the query, recovering from its syntax errors instead of failing.
+///
+/// The returned errors describe the recoveries that took place,
+/// and are empty if the query is valid.
+pub fn parse_query_lenient(query: &str) -> (UserInputAst, Vec<Error>) {
+    parse_to_ast_lenient(query)
+}
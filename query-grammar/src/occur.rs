@@ -14,6 +14,9 @@ pub enum Occur {
     /// Document that contain the term are excluded from the
     /// search.
     MustNot,
+    /// Document without the term are excluded from the search,
+    /// like for `Must`, but the term does not contribute to the score.
+    Filter,
 }
 
 impl Occur {
@@ -21,11 +24,13 @@ impl Occur {
     /// - `Should` => '?',
     /// - `Must` => '+'
     /// - `Not` => '-'
+    /// - `Filter` => '#'
     fn to_char(self) -> char {
         match self {
             Occur::Should => '?',
             Occur::Must => '+',
             Occur::MustNot => '-',
+            Occur::Filter => '#',
         }
     }
 
@@ -34,9 +39,12 @@ impl Occur {
         match (left, right) {
             (Occur::Should, _) => right,
             (Occur::Must, Occur::MustNot) => Occur::MustNot,
+            (Occur::Must, Occur::Filter) => Occur::Filter,
             (Occur::Must, _) => Occur::Must,
             (Occur::MustNot, Occur::MustNot) => Occur::Must,
             (Occur::MustNot, _) => Occur::MustNot,
+            (Occur::Filter, Occur::MustNot) => Occur::MustNot,
+            (Occur::Filter, _) => Occur::Filter,
         }
     }
 }
@@ -68,5 +76,15 @@ mod test {
         );
         assert_eq!(Occur::compose(Occur::MustNot, Occur::Must), Occur::MustNot);
         assert_eq!(Occur::compose(Occur::MustNot, Occur::MustNot), Occur::Must);
+        assert_eq!(Occur::compose(Occur::Must, Occur::Filter), Occur::Filter);
+        assert_eq!(Occur::compose(Occur::Filter, Occur::Must), Occur::Filter);
+        assert_eq!(
+            Occur::compose(Occur::Filter, Occur::MustNot),
+            Occur::MustNot
+        );
+        assert_eq!(
+            Occur::compose(Occur::MustNot, Occur::Filter),
+            Occur::MustNot
+        );
     }
 }
@@ -1,15 +1,20 @@
 use super::user_input_ast::{UserInputAst, UserInputBound, UserInputLeaf, UserInputLiteral};
-use crate::Occur;
+use crate::{Error, Occur};
 use combine::parser::char::{char, digit, space, spaces, string};
 use combine::parser::range::{take_while, take_while1};
 use combine::parser::repeat::escaped;
 use combine::parser::Parser;
 use combine::{
-    attempt, choice, eof, many, many1, one_of, optional, parser, satisfy, skip_many1, value,
+    attempt, choice, eof, many, many1, not_followed_by, one_of, optional, parser, satisfy,
+    skip_many1, value,
 };
 use combine::{error::StringStreamError, parser::combinator::recognize};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use std::ops::Range;
+
+/// Maximum Levenshtein distance of a fuzzy term.
+pub(crate) const MAX_FUZZY_DISTANCE: u8 = 2;
 
 // Note: '-' char is only forbidden at the beginning of a field name, would be clearer to add it to special characters.
 const SPECIAL_CHARS: &[char] = &[
@@ -41,15 +46,18 @@ fn field_name<'a>() -> impl Parser<&'a str, Output = String> {
     })
 }
 
+/// Returns true if the char can appear in a word, after its first char.
+pub(crate) fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && ![':', '^', '~', '{', '}', '"', '[', ']', '(', ')'].contains(&c)
+}
+
 fn word<'a>() -> impl Parser<&'a str, Output = String> {
     (
         satisfy(|c: char| {
             !c.is_whitespace()
-                && !['-', '^', '`', ':', '{', '}', '"', '[', ']', '(', ')'].contains(&c)
+                && !['-', '^', '~', '`', ':', '{', '}', '"', '[', ']', '(', ')'].contains(&c)
         }),
-        many(satisfy(|c: char| {
-            !c.is_whitespace() && ![':', '^', '{', '}', '"', '[', ']', '(', ')'].contains(&c)
-        })),
+        many(satisfy(is_word_char)),
     )
         .map(|(s1, s2): (char, String)| format!("{}{}", s1, s2))
         .and_then(|s: String| match s.as_str() {
@@ -58,6 +66,21 @@ fn word<'a>() -> impl Parser<&'a str, Output = String> {
         })
 }
 
+/// Parses an IPv6 address, possibly followed by the prefix length of a CIDR block,
+/// e.g. `2001:db8::1` or `2001:db8::/32`.
+///
+/// Colons are not allowed in words, so the parser only accepts the hexadecimal digits,
+/// dots and slashes found in IPv6 addresses, and requires at least one colon.
+/// Rejecting invalid addresses is delegated to the logical AST computation code.
+fn ipv6_addr<'a>() -> impl Parser<&'a str, Output = String> {
+    take_while1(|c: char| c.is_ascii_hexdigit() || [':', '.', '/'].contains(&c)).and_then(
+        |s: &str| match s.contains(':') {
+            true => Ok(s.to_string()),
+            false => Err(StringStreamError::UnexpectedParse),
+        },
+    )
+}
+
 /// Parses a date time according to rfc3339
 /// 2015-08-02T18:54:42+02
 /// 2021-04-13T19:46:26.266051969+00:00
@@ -114,26 +137,67 @@ fn date_time<'a>() -> impl Parser<&'a str, Output = String> {
     recognize((date, char('T'), time))
 }
 
-fn term_val<'a>() -> impl Parser<&'a str, Output = String> {
-    let phrase = char('"').with(many1(satisfy(|c| c != '"'))).skip(char('"'));
-    phrase.or(word())
+/// Parses the optional slop of a phrase, e.g. `~2`.
+///
+/// The slop must be a non-negative integer.
+fn slop_val<'a>() -> impl Parser<&'a str, Output = u32> {
+    let slop = (char('~'), many1(digit())).and_then(|(_, slop): (_, String)| {
+        slop.parse::<u32>()
+            .map_err(|_| StringStreamError::UnexpectedParse)
+    });
+    optional(slop).map(|slop_opt| slop_opt.unwrap_or(0))
+}
+
+/// Parses the optional distance of a fuzzy term, e.g. `~1`.
+///
+/// The distance must be at most `MAX_FUZZY_DISTANCE`.
+fn fuzzy_distance_val<'a>() -> impl Parser<&'a str, Output = Option<u8>> {
+    optional(
+        (char('~'), many1(digit())).and_then(|(_, distance): (_, String)| {
+            match distance.parse::<u8>() {
+                Ok(distance) if distance <= MAX_FUZZY_DISTANCE => Ok(distance),
+                _ => Err(StringStreamError::UnexpectedParse),
+            }
+        }),
+    )
+}
+
+fn term_val<'a>() -> impl Parser<&'a str, Output = UserInputLiteral> {
+    let phrase = (
+        char('"').with(many1(satisfy(|c| c != '"'))).skip(char('"')),
+        slop_val(),
+    )
+        .map(|(phrase, slop)| UserInputLiteral {
+            field_name: None,
+            phrase,
+            quoted: true,
+            slop,
+            fuzzy_distance: None,
+        });
+    let word_with_fuzzy_distance =
+        (word(), fuzzy_distance_val()).map(|(word, fuzzy_distance)| UserInputLiteral {
+            fuzzy_distance,
+            ..UserInputLiteral::unquoted(word)
+        });
+    phrase.or(word_with_fuzzy_distance)
 }
 
 fn term_query<'a>() -> impl Parser<&'a str, Output = UserInputLiteral> {
-    let term_val_with_field = negative_number().or(term_val());
-    (field_name(), term_val_with_field).map(|(field_name, phrase)| UserInputLiteral {
+    let term_val_with_field = negative_number()
+        .or(attempt(
+            ipv6_addr().skip(not_followed_by(satisfy(is_word_char))),
+        ))
+        .map(UserInputLiteral::unquoted)
+        .or(term_val());
+    (field_name(), term_val_with_field).map(|(field_name, literal)| UserInputLiteral {
         field_name: Some(field_name),
-        phrase,
+        ..literal
     })
 }
 
 fn literal<'a>() -> impl Parser<&'a str, Output = UserInputLeaf> {
-    let term_default_field = term_val().map(|phrase| UserInputLiteral {
-        field_name: None,
-        phrase,
-    });
     attempt(term_query())
-        .or(term_default_field)
+        .or(term_val())
         .map(UserInputLeaf::from)
 }
 
@@ -163,6 +227,7 @@ fn spaces1<'a>() -> impl Parser<&'a str, Output = ()> {
 fn range<'a>() -> impl Parser<&'a str, Output = UserInputLeaf> {
     let range_term_val = || {
         attempt(date_time())
+            .or(attempt(ipv6_addr()))
             .or(word())
             .or(negative_number())
             .or(char('*').with(value("*".to_string())))
@@ -241,7 +306,15 @@ fn leaf<'a>() -> impl Parser<&'a str, Output = UserInputAst> {
         char('(')
             .with(ast())
             .skip(char(')'))
-            .or(char('*').map(|_| UserInputAst::from(UserInputLeaf::All)))
+            .or(attempt(
+                (field_name(), char('(').with(ast()).skip(char(')')))
+                    .map(|(field_name, ast)| UserInputAst::FieldGroup(field_name, Box::new(ast))),
+            ))
+            .or(attempt(
+                char('*')
+                    .skip(not_followed_by(satisfy(is_word_char)))
+                    .map(|_| UserInputAst::from(UserInputLeaf::All)),
+            ))
             .or(attempt(
                 string("NOT").skip(spaces1()).with(leaf()).map(negate),
             ))
@@ -340,7 +413,7 @@ pub fn ast<'a>() -> impl Parser<&'a str, Output = UserInputAst> {
             if subqueries.len() == 1 {
                 let (occur_opt, ast) = subqueries.into_iter().next().unwrap();
                 match occur_opt.unwrap_or(Occur::Should) {
-                    Occur::Must | Occur::Should => ast,
+                    Occur::Must | Occur::Should | Occur::Filter => ast,
                     Occur::MustNot => UserInputAst::Clause(vec![(Some(Occur::MustNot), ast)]),
                 }
             } else {
@@ -352,12 +425,212 @@ pub fn ast<'a>() -> impl Parser<&'a str, Output = UserInputAst> {
     spaces().with(expr).skip(spaces())
 }
 
+/// Looks for a boost or a fuzzy distance with an invalid value, outside of double quotes.
+///
+/// It is used to report a more helpful error than a syntax error, once the query
+/// failed to parse. The slop of phrases is not checked.
+pub(crate) fn find_invalid_operator_value(query: &str) -> Option<Error> {
+    find_invalid_operator(query).map(|(_, error)| error)
+}
+
+/// Same as `find_invalid_operator_value`, also returning the byte range
+/// of the invalid operator and of its value.
+fn find_invalid_operator(query: &str) -> Option<(Range<usize>, Error)> {
+    let mut chars = query.char_indices();
+    let mut in_quotes = false;
+    let mut after_quotes = false;
+    while let Some((offset, c)) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                in_quotes = false;
+                after_quotes = true;
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            '\\' => {
+                chars.next();
+            }
+            '^' | '~' => {
+                let value_start = offset + c.len_utf8();
+                let value_len = query[value_start..]
+                    .find(|c| !is_word_char(c))
+                    .unwrap_or(query.len() - value_start);
+                let value = &query[value_start..value_start + value_len];
+                let token_range = offset..value_start + value_len;
+                let token = query[token_range.clone()].to_string();
+                if c == '^' && positive_float_number().skip(eof()).parse(value).is_err() {
+                    return Some((token_range, Error::InvalidBoost(token)));
+                }
+                let is_valid_distance = !value.is_empty()
+                    && value.chars().all(|c| c.is_ascii_digit())
+                    && value
+                        .parse::<u8>()
+                        .map(|distance| distance <= MAX_FUZZY_DISTANCE)
+                        .unwrap_or(false);
+                if c == '~' && !after_quotes && !is_valid_distance {
+                    return Some((token_range, Error::InvalidFuzzyDistance(token)));
+                }
+            }
+            _ => {}
+        }
+        after_quotes = false;
+    }
+    None
+}
+
 pub fn parse_to_ast<'a>() -> impl Parser<&'a str, Output = UserInputAst> {
     spaces()
         .with(optional(ast()).skip(eof()))
         .map(|opt_ast| opt_ast.unwrap_or_else(UserInputAst::empty_query))
 }
 
+fn parse_strict(query: &str) -> Option<UserInputAst> {
+    parse_to_ast().parse(query).ok().map(|(ast, _)| ast)
+}
+
+/// Removes the unmatched double quote of the query, if any.
+fn balance_quotes(query: &str) -> Option<String> {
+    let mut chars = query.char_indices();
+    let mut opening_quote: Option<usize> = None;
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' if opening_quote.is_some() => opening_quote = None,
+            '"' => opening_quote = Some(offset),
+            _ => {}
+        }
+    }
+    let offset = opening_quote?;
+    Some(format!("{}{}", &query[..offset], &query[offset + 1..]))
+}
+
+/// Removes the unmatched closing parentheses of the query, and closes
+/// the unmatched opening ones at its end.
+fn balance_parentheses(query: &str) -> Option<String> {
+    let mut balanced = String::with_capacity(query.len());
+    let mut chars = query.chars();
+    let mut in_quotes = false;
+    let mut depth = 0usize;
+    let mut is_modified = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                balanced.push(c);
+                balanced.extend(chars.next());
+                continue;
+            }
+            '"' => in_quotes = !in_quotes,
+            '(' if !in_quotes => depth += 1,
+            ')' if !in_quotes => {
+                if depth == 0 {
+                    is_modified = true;
+                    continue;
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+        balanced.push(c);
+    }
+    if depth > 0 {
+        is_modified = true;
+        balanced.push_str(&")".repeat(depth));
+    }
+    if is_modified {
+        Some(balanced)
+    } else {
+        None
+    }
+}
+
+/// Splits the query on whitespaces and parentheses, outside of double quotes.
+fn split_fragments(query: &str) -> Vec<&str> {
+    let mut fragments = Vec::new();
+    let mut fragment_start = 0;
+    let mut chars = query.char_indices();
+    let mut in_quotes = false;
+    while let Some((offset, c)) = chars.next() {
+        let is_separator = match c {
+            '\\' => {
+                chars.next();
+                false
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                false
+            }
+            _ => !in_quotes && (c.is_whitespace() || c == '(' || c == ')'),
+        };
+        if is_separator {
+            fragments.push(&query[fragment_start..offset]);
+            fragment_start = offset + c.len_utf8();
+        }
+    }
+    fragments.push(&query[fragment_start..]);
+    fragments.retain(|fragment| !fragment.is_empty());
+    fragments
+}
+
+/// Parses the query, recovering from its syntax errors rather than failing.
+///
+/// Invalid boosts and fuzzy distances are removed, unmatched quotes and parentheses
+/// are balanced. If the query is still invalid, it is split into fragments which
+/// are parsed independently, each of them as an optional clause. The fragments
+/// that are still invalid are degraded to their words, stripped from the special
+/// characters.
+///
+/// Every recovery is recorded as an error.
+pub(crate) fn parse_to_ast_lenient(query: &str) -> (UserInputAst, Vec<Error>) {
+    if let Some(ast) = parse_strict(query) {
+        return (ast, Vec::new());
+    }
+    let mut errors = Vec::new();
+    let mut query = query.to_string();
+    while let Some((token_range, error)) = find_invalid_operator(&query) {
+        query.replace_range(token_range, "");
+        errors.push(error);
+    }
+    if let Some(balanced_query) = balance_quotes(&query) {
+        query = balanced_query;
+        errors.push(Error::UnbalancedQuotes);
+    }
+    if let Some(balanced_query) = balance_parentheses(&query) {
+        query = balanced_query;
+        errors.push(Error::UnbalancedParentheses);
+    }
+    if !errors.is_empty() {
+        if let Some(ast) = parse_strict(&query) {
+            return (ast, errors);
+        }
+    }
+    let mut clauses = Vec::new();
+    for fragment in split_fragments(&query) {
+        if ["AND", "OR", "NOT"].contains(&fragment) {
+            continue;
+        }
+        if let Some(ast) = parse_strict(fragment) {
+            clauses.push((None, ast));
+            continue;
+        }
+        errors.push(Error::InvalidFragment(fragment.to_string()));
+        let words = fragment
+            .split(|c: char| SPECIAL_CHARS.contains(&c) || c == '-')
+            .filter(|word| !word.is_empty());
+        for word in words {
+            let literal = UserInputLiteral::unquoted(word.to_string());
+            clauses.push((None, UserInputAst::from(UserInputLeaf::Literal(literal))));
+        }
+    }
+    if errors.is_empty() {
+        errors.push(Error::Syntax);
+    }
+    (UserInputAst::Clause(clauses), errors)
+}
+
 #[cfg(test)]
 mod test {
 
@@ -504,6 +777,24 @@ mod test {
         test_parse_query_to_ast_helper("weight: <= 70.5", "\"weight\":{\"*\" TO \"70.5\"]");
     }
 
+    #[test]
+    fn test_parse_ip_addrs() {
+        test_parse_query_to_ast_helper("ip:10.0.0.1", "\"ip\":\"10.0.0.1\"");
+        test_parse_query_to_ast_helper("ip:10.0.0.0/16", "\"ip\":\"10.0.0.0/16\"");
+        test_parse_query_to_ast_helper("ip:2001:db8::1", "\"ip\":\"2001:db8::1\"");
+        test_parse_query_to_ast_helper("ip:2001:db8::/32", "\"ip\":\"2001:db8::/32\"");
+        test_parse_query_to_ast_helper(
+            "ip:[2001:db8:: TO 2001:db8::ffff]",
+            "\"ip\":[\"2001:db8::\" TO \"2001:db8::ffff\"]",
+        );
+        test_parse_query_to_ast_helper(
+            "ip:>=::ffff:10.0.0.0",
+            "\"ip\":[\"::ffff:10.0.0.0\" TO \"*\"}",
+        );
+        // words containing colons are not ip addresses.
+        test_is_parse_err("ip:2001:db8::xyz");
+    }
+
     #[test]
     fn test_occur_leaf() {
         let ((occur, ast), _) = super::occur_leaf().parse("+abc").unwrap();
@@ -610,6 +901,178 @@ mod test {
         assert_eq!(res6, expected_flexible_dates);
     }
 
+    #[test]
+    fn test_parse_query_quoted() {
+        let quoted = |query: &str| -> Vec<bool> {
+            match parse_to_ast().parse(query).unwrap().0 {
+                UserInputAst::Leaf(leaf) => match *leaf {
+                    UserInputLeaf::Literal(literal) => vec![literal.quoted],
+                    _ => panic!("expected a literal"),
+                },
+                UserInputAst::Clause(clauses) => clauses
+                    .into_iter()
+                    .map(|(_, ast)| match ast {
+                        UserInputAst::Leaf(leaf) => match *leaf {
+                            UserInputLeaf::Literal(literal) => literal.quoted,
+                            _ => panic!("expected a literal"),
+                        },
+                        _ => panic!("expected a leaf"),
+                    })
+                    .collect(),
+                _ => panic!("expected a leaf or a clause"),
+            }
+        };
+        assert_eq!(quoted("abc"), vec![false]);
+        assert_eq!(quoted("\"abc\""), vec![true]);
+        assert_eq!(quoted("title:jo*n title:\"jo*n\""), vec![false, true]);
+        assert_eq!(quoted("title:-1"), vec![false]);
+    }
+
+    #[test]
+    fn test_parse_query_wildcards() {
+        test_parse_query_to_ast_helper("jo*n", "\"jo*n\"");
+        test_parse_query_to_ast_helper("*on", "\"*on\"");
+        test_parse_query_to_ast_helper("?on", "\"?on\"");
+        test_parse_query_to_ast_helper("user:*on", "\"user\":\"*on\"");
+        test_parse_query_to_ast_helper("*", "*");
+        test_parse_query_to_ast_helper("(*)", "*");
+        test_parse_query_to_ast_helper("* a", "(** *\"a\")");
+    }
+
+    #[test]
+    fn test_parse_query_field_group() {
+        test_parse_query_to_ast_helper("title:(a)", "\"title\":[\"a\"]");
+        test_parse_query_to_ast_helper(
+            "title:(rust lucene -java \"exact phrase\")",
+            "\"title\":[(*\"rust\" *\"lucene\" -\"java\" *\"exact phrase\")]",
+        );
+        test_parse_query_to_ast_helper(
+            "title:(a body:b) c",
+            "(*\"title\":[(*\"a\" *\"body\":\"b\")] *\"c\")",
+        );
+        test_parse_query_to_ast_helper(
+            "title:(a body:(b c))^2",
+            "(\"title\":[(*\"a\" *\"body\":[(*\"b\" *\"c\")])])^2",
+        );
+        test_parse_query_to_ast_helper(
+            "title:(a OR b) AND c",
+            "(+\"title\":[(?\"a\" ?\"b\")] +\"c\")",
+        );
+        test_parse_query_to_ast_helper("title:([a TO b])", "\"title\":[[\"a\" TO \"b\"]]");
+        test_is_parse_err("title:()");
+        test_is_parse_err("title:(a");
+    }
+
+    #[test]
+    fn test_parse_query_fuzzy() {
+        test_parse_query_to_ast_helper("abc~1", "\"abc\"~1");
+        test_parse_query_to_ast_helper("abc~0", "\"abc\"~0");
+        test_parse_query_to_ast_helper("title:rust~2", "\"title\":\"rust\"~2");
+        test_parse_query_to_ast_helper("title:rust~2^2", "(\"title\":\"rust\"~2)^2");
+        test_parse_query_to_ast_helper("(a~1 OR b) AND c~2", "(+(?\"a\"~1 ?\"b\") +\"c\"~2)");
+        test_is_parse_err("abc~3");
+        test_is_parse_err("abc~");
+        test_is_parse_err("abc~a");
+        test_is_parse_err("a~b");
+    }
+
+    #[test]
+    fn test_find_invalid_operator_value() {
+        let find = |query: &str| find_invalid_operator_value(query);
+        assert_eq!(find("a~1 b^2.5 \"c d\"~3"), None);
+        assert_eq!(
+            find("a~3"),
+            Some(Error::InvalidFuzzyDistance("~3".to_string()))
+        );
+        assert_eq!(
+            find("(title:a~abc OR b)"),
+            Some(Error::InvalidFuzzyDistance("~abc".to_string()))
+        );
+        assert_eq!(find("a^abc"), Some(Error::InvalidBoost("^abc".to_string())));
+        assert_eq!(
+            find("\"a b\"^-1"),
+            Some(Error::InvalidBoost("^-1".to_string()))
+        );
+        assert_eq!(find("\"a^b c~d\""), None);
+        assert_eq!(find("\"a b\"~x"), None);
+        assert_eq!(crate::parse_query("a^abc").err(), find("a^abc"));
+        assert_eq!(crate::parse_query("a^2^3").err(), Some(Error::Syntax));
+    }
+
+    #[test]
+    fn test_parse_query_lenient() {
+        let parse = |query: &str| -> (String, Vec<Error>) {
+            let (ast, errors) = parse_to_ast_lenient(query);
+            (format!("{:?}", ast), errors)
+        };
+        assert_eq!(parse("a AND b"), ("(+\"a\" +\"b\")".to_string(), vec![]));
+        assert_eq!(
+            parse("title:(a OR b"),
+            (
+                "\"title\":[(?\"a\" ?\"b\")]".to_string(),
+                vec![Error::UnbalancedParentheses]
+            )
+        );
+        assert_eq!(
+            parse("a) b"),
+            (
+                "(*\"a\" *\"b\")".to_string(),
+                vec![Error::UnbalancedParentheses]
+            )
+        );
+        assert_eq!(
+            parse("a \"b c"),
+            (
+                "(*\"a\" *\"b\" *\"c\")".to_string(),
+                vec![Error::UnbalancedQuotes]
+            )
+        );
+        assert_eq!(
+            parse("a~3 b^x"),
+            (
+                "(*\"a\" *\"b\")".to_string(),
+                vec![
+                    Error::InvalidFuzzyDistance("~3".to_string()),
+                    Error::InvalidBoost("^x".to_string())
+                ]
+            )
+        );
+        assert_eq!(
+            parse("a title: b"),
+            (
+                "(*\"a\" *\"title\" *\"b\")".to_string(),
+                vec![Error::InvalidFragment("title:".to_string())]
+            )
+        );
+        assert_eq!(
+            parse("a OR b c:[1 TO"),
+            (
+                "(*\"a\" *\"b\" *\"c\" *\"1\" *\"TO\")".to_string(),
+                vec![Error::InvalidFragment("c:[1".to_string())]
+            )
+        );
+        assert_eq!(
+            parse(":::"),
+            (
+                "<emptyclause>".to_string(),
+                vec![Error::InvalidFragment(":::".to_string())]
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_query_phrase_slop() {
+        test_parse_query_to_ast_helper("\"a b\"~0", "\"a b\"");
+        test_parse_query_to_ast_helper("\"a b\"~2", "\"a b\"~2");
+        test_parse_query_to_ast_helper("title:\"a b\"~2", "\"title\":\"a b\"~2");
+        test_parse_query_to_ast_helper("\"a b\"~2^2", "(\"a b\"~2)^2");
+        test_parse_query_to_ast_helper("a~2", "\"a\"~2");
+        test_is_parse_err("\"a b\"~");
+        test_is_parse_err("\"a b\"~-1");
+        test_is_parse_err("\"a b\"~a");
+        test_is_parse_err("\"a b\"~99999999999");
+    }
+
     #[test]
     fn test_parse_query_to_triming_spaces() {
         test_parse_query_to_ast_helper("   abc", "\"abc\"");
@@ -40,14 +40,40 @@ impl Debug for UserInputLeaf {
 pub struct UserInputLiteral {
     pub field_name: Option<String>,
     pub phrase: String,
+    /// Whether the phrase was enclosed in double quotes.
+    pub quoted: bool,
+    /// Maximum number of position moves allowed between the terms of the phrase.
+    pub slop: u32,
+    /// Maximum Levenshtein distance to the term, if it is a fuzzy term.
+    pub fuzzy_distance: Option<u8>,
+}
+
+impl UserInputLiteral {
+    /// Creates a literal for a single word, without a field name.
+    pub fn unquoted(phrase: String) -> UserInputLiteral {
+        UserInputLiteral {
+            field_name: None,
+            phrase,
+            quoted: false,
+            slop: 0,
+            fuzzy_distance: None,
+        }
+    }
 }
 
 impl fmt::Debug for UserInputLiteral {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match self.field_name {
-            Some(ref field_name) => write!(formatter, "\"{}\":\"{}\"", field_name, self.phrase),
-            None => write!(formatter, "\"{}\"", self.phrase),
+            Some(ref field_name) => write!(formatter, "\"{}\":\"{}\"", field_name, self.phrase)?,
+            None => write!(formatter, "\"{}\"", self.phrase)?,
         }
+        if self.slop > 0 {
+            write!(formatter, "~{}", self.slop)?;
+        }
+        if let Some(fuzzy_distance) = self.fuzzy_distance {
+            write!(formatter, "~{}", fuzzy_distance)?;
+        }
+        Ok(())
     }
 }
 
@@ -88,6 +114,10 @@ pub enum UserInputAst {
     Clause(Vec<(Option<Occur>, UserInputAst)>),
     Leaf(Box<UserInputLeaf>),
     Boost(Box<UserInputAst>, f64),
+    /// A parenthesized group scoped to a field, e.g. `title:(a OR b)`.
+    ///
+    /// Its terms search this field, unless they are given a field explicitly.
+    FieldGroup(String, Box<UserInputAst>),
 }
 
 impl UserInputAst {
@@ -166,6 +196,9 @@ impl fmt::Debug for UserInputAst {
             }
             UserInputAst::Leaf(ref subquery) => write!(formatter, "{:?}", subquery),
             UserInputAst::Boost(ref leaf, boost) => write!(formatter, "({:?})^{}", leaf, boost),
+            UserInputAst::FieldGroup(ref field_name, ref ast) => {
+                write!(formatter, "\"{}\":[{:?}]", field_name, ast)
+            }
         }
     }
 }
@@ -0,0 +1,544 @@
+use std::hash::Hasher;
+
+use common::BitSet;
+use fnv::FnvHasher;
+
+use crate::collector::{Collector, SegmentCollector};
+use crate::fastfield::{FacetReader, MultiValuedFastFieldReader, OptionalFastFieldReader};
+use crate::schema::{Cardinality, Field, FieldType};
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+const DEFAULT_PRECISION: u8 = 12;
+const MIN_PRECISION: u8 = 4;
+const MAX_PRECISION: u8 = 18;
+
+/// Finalizer of MurmurHash3, mixing the bits of `key` so that each bit of the
+/// result depends on all the bits of `key`.
+#[inline]
+fn fmix64(mut key: u64) -> u64 {
+    key ^= key >> 33;
+    key = key.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    key ^= key >> 33;
+    key = key.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    key ^= key >> 33;
+    key
+}
+
+#[inline]
+fn hash_u64(val: u64) -> u64 {
+    fmix64(val)
+}
+
+#[inline]
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(bytes);
+    fmix64(hasher.finish())
+}
+
+/// HyperLogLog sketch, estimating the number of distinct values it was given.
+///
+/// The values are hashed on 64 bits, and the cardinality is estimated with the
+/// improved estimator of Otmar Ertl, which is accurate for small and large
+/// cardinalities alike without any bias correction table. The relative standard
+/// error is about `1.04 / sqrt(2^precision)`, e.g. 1.6% for the default precision of 12.
+///
+/// The hashes are deterministic, so that the sketches computed on different indexes
+/// can be merged, for instance after sending them over the network as
+/// [bytes](#method.to_bytes).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> HyperLogLog {
+        HyperLogLog::new(DEFAULT_PRECISION)
+    }
+}
+
+impl HyperLogLog {
+    /// Creates an empty sketch of `2^precision` registers.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `precision` is not within `[4, 18]`.
+    pub fn new(precision: u8) -> HyperLogLog {
+        assert!(
+            (MIN_PRECISION..=MAX_PRECISION).contains(&precision),
+            "The precision must be within [{}, {}].",
+            MIN_PRECISION,
+            MAX_PRECISION
+        );
+        HyperLogLog {
+            precision,
+            registers: vec![0u8; 1 << precision],
+        }
+    }
+
+    /// Returns the precision of the sketch.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    #[inline]
+    fn insert_hash(&mut self, hash: u64) {
+        let register = (hash >> (64 - self.precision)) as usize;
+        // the remaining bits are shifted to the left, and a sentinel bit caps the
+        // rank to `64 - precision + 1`.
+        let remaining_bits = (hash << self.precision) | (1 << (self.precision - 1));
+        let rank = remaining_bits.leading_zeros() as u8 + 1;
+        if self.registers[register] < rank {
+            self.registers[register] = rank;
+        }
+    }
+
+    /// Adds a `u64` value to the sketch.
+    pub fn insert_u64(&mut self, val: u64) {
+        self.insert_hash(hash_u64(val));
+    }
+
+    /// Adds a value given as bytes to the sketch.
+    pub fn insert_bytes(&mut self, bytes: &[u8]) {
+        self.insert_hash(hash_bytes(bytes));
+    }
+
+    /// Merges the values of `other` into the sketch.
+    ///
+    /// Returns an error if the sketches do not have the same precision.
+    pub fn merge(&mut self, other: &HyperLogLog) -> crate::Result<()> {
+        if self.precision != other.precision {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Cannot merge sketches of precisions {} and {}.",
+                self.precision, other.precision
+            )));
+        }
+        for (register, &other_register) in self.registers.iter_mut().zip(&other.registers) {
+            if *register < other_register {
+                *register = other_register;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the estimated number of distinct values of the sketch.
+    pub fn estimate(&self) -> u64 {
+        let num_registers = self.registers.len() as f64;
+        let max_rank = 64 - self.precision as usize;
+        // histogram of the ranks.
+        let mut rank_counts = vec![0u32; max_rank + 2];
+        for &register in &self.registers {
+            rank_counts[register as usize] += 1;
+        }
+        let mut z = num_registers * tau(1.0 - f64::from(rank_counts[max_rank + 1]) / num_registers);
+        for &rank_count in rank_counts[1..=max_rank].iter().rev() {
+            z = 0.5 * (z + f64::from(rank_count));
+        }
+        z += num_registers * sigma(f64::from(rank_counts[0]) / num_registers);
+        let alpha = 0.5 / std::f64::consts::LN_2;
+        (alpha * num_registers * num_registers / z).round() as u64
+    }
+
+    /// Serializes the sketch: its precision, followed by its registers.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.registers.len());
+        bytes.push(self.precision);
+        bytes.extend_from_slice(&self.registers);
+        bytes
+    }
+
+    /// Deserializes a sketch serialized by [`to_bytes`](#method.to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> crate::Result<HyperLogLog> {
+        let invalid_bytes =
+            || TantivyError::InvalidArgument("The bytes are not a HyperLogLog sketch.".to_string());
+        let (&precision, registers) = bytes.split_first().ok_or_else(invalid_bytes)?;
+        if !(MIN_PRECISION..=MAX_PRECISION).contains(&precision)
+            || registers.len() != 1 << precision
+            || registers
+                .iter()
+                .any(|&register| register as usize > 64 - precision as usize + 1)
+        {
+            return Err(invalid_bytes());
+        }
+        Ok(HyperLogLog {
+            precision,
+            registers: registers.to_vec(),
+        })
+    }
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let previous_z = z;
+        z += x * y;
+        y += y;
+        if previous_z == z {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let previous_z = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if previous_z == z {
+            return z / 3.0;
+        }
+    }
+}
+
+/// Estimates the number of distinct values of a fast field or a facet field over the
+/// matching documents, with a [`HyperLogLog`](./struct.HyperLogLog.html) sketch.
+///
+/// The fast fields are u64, i64, f64, bool or date fast fields, single or multi-valued.
+/// For facet fields, the facets themselves are hashed rather than their ordinals, which
+/// are local to the segments.
+///
+/// ```rust
+/// use tantivy::collector::CardinalityCollector;
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, FAST};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let user_id = schema_builder.add_u64_field("user_id", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// for i in 0..1_000u64 {
+///     index_writer.add_document(doc!(user_id => i % 100));
+/// }
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let sketch = searcher.search(&AllQuery, &CardinalityCollector::for_field(user_id))?;
+/// let estimate = sketch.estimate();
+/// assert!((95..=105).contains(&estimate));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct CardinalityCollector {
+    field: Field,
+    precision: u8,
+}
+
+impl CardinalityCollector {
+    /// Creates a collector of the distinct values of `field`, with a sketch of the
+    /// default precision of 12.
+    ///
+    /// Searching returns an error if `field` is neither a fast field nor a facet field.
+    pub fn for_field(field: Field) -> CardinalityCollector {
+        CardinalityCollector {
+            field,
+            precision: DEFAULT_PRECISION,
+        }
+    }
+
+    /// Sets the precision of the sketch.
+    ///
+    /// A sketch of precision `p` takes `2^p` bytes, and its relative standard error is
+    /// about `1.04 / sqrt(2^p)`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `precision` is not within `[4, 18]`.
+    pub fn with_precision(mut self, precision: u8) -> CardinalityCollector {
+        assert!(
+            (MIN_PRECISION..=MAX_PRECISION).contains(&precision),
+            "The precision must be within [{}, {}].",
+            MIN_PRECISION,
+            MAX_PRECISION
+        );
+        self.precision = precision;
+        self
+    }
+}
+
+enum ValuesReader {
+    Single(OptionalFastFieldReader<u64>),
+    Multi {
+        ff_reader: MultiValuedFastFieldReader<u64>,
+        vals: Vec<u64>,
+    },
+    /// The ordinals of the facets are recorded, and their facets are hashed once
+    /// at harvest.
+    Facet {
+        facet_reader: Box<FacetReader>,
+        facet_ords: BitSet,
+        doc_facet_ords: Vec<u64>,
+    },
+}
+
+/// Segment collector associated to `CardinalityCollector`.
+pub struct CardinalitySegmentCollector {
+    values_reader: ValuesReader,
+    sketch: HyperLogLog,
+}
+
+impl Collector for CardinalityCollector {
+    type Fruit = HyperLogLog;
+    type Child = CardinalitySegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<CardinalitySegmentCollector> {
+        let field_entry = reader.schema().get_field_entry(self.field);
+        let cardinality = match field_entry.field_type() {
+            FieldType::U64(options)
+            | FieldType::I64(options)
+            | FieldType::F64(options)
+            | FieldType::Bool(options) => options.get_fastfield_cardinality(),
+            FieldType::Date(options) => options.get_fastfield_cardinality(),
+            FieldType::HierarchicalFacet(_) => {
+                let facet_reader = reader.facet_reader(self.field)?;
+                let facet_ords = BitSet::with_max_value(facet_reader.num_facets() as u32);
+                return Ok(CardinalitySegmentCollector {
+                    values_reader: ValuesReader::Facet {
+                        facet_reader: Box::new(facet_reader),
+                        facet_ords,
+                        doc_facet_ords: Vec::new(),
+                    },
+                    sketch: HyperLogLog::new(self.precision),
+                });
+            }
+            _ => None,
+        };
+        let fast_fields = reader.fast_fields();
+        let values_reader = match cardinality {
+            Some(Cardinality::SingleValue) => {
+                ValuesReader::Single(fast_fields.typed_optional_fast_field_reader(self.field)?)
+            }
+            Some(Cardinality::MultiValues) => ValuesReader::Multi {
+                ff_reader: fast_fields.u64s_lenient(self.field)?,
+                vals: Vec::new(),
+            },
+            None => {
+                return Err(TantivyError::SchemaError(format!(
+                    "Field {:?} is neither a fast field nor a facet field.",
+                    field_entry.name()
+                )));
+            }
+        };
+        Ok(CardinalitySegmentCollector {
+            values_reader,
+            sketch: HyperLogLog::new(self.precision),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_sketches: Vec<HyperLogLog>) -> crate::Result<HyperLogLog> {
+        let mut sketch = HyperLogLog::new(self.precision);
+        for segment_sketch in &segment_sketches {
+            sketch.merge(segment_sketch)?;
+        }
+        Ok(sketch)
+    }
+}
+
+impl SegmentCollector for CardinalitySegmentCollector {
+    type Fruit = HyperLogLog;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        match &mut self.values_reader {
+            ValuesReader::Single(ff_reader) => {
+                if let Some(val) = ff_reader.get_opt(doc) {
+                    self.sketch.insert_u64(val);
+                }
+            }
+            ValuesReader::Multi { ff_reader, vals } => {
+                ff_reader.get_vals(doc, vals);
+                for &val in vals.iter() {
+                    self.sketch.insert_u64(val);
+                }
+            }
+            ValuesReader::Facet {
+                facet_reader,
+                facet_ords,
+                doc_facet_ords,
+            } => {
+                facet_reader.facet_ords(doc, doc_facet_ords);
+                for &facet_ord in doc_facet_ords.iter() {
+                    facet_ords.insert(facet_ord as u32);
+                }
+            }
+        }
+    }
+
+    fn harvest(mut self) -> HyperLogLog {
+        if let ValuesReader::Facet {
+            facet_reader,
+            facet_ords,
+            ..
+        } = &self.values_reader
+        {
+            let num_buckets = (facet_ords.max_value() + 63) / 64;
+            let sorted_facet_ords: Vec<u64> = (0..num_buckets)
+                .flat_map(|bucket| {
+                    facet_ords
+                        .tinyset(bucket)
+                        .into_iter()
+                        .map(move |facet_ord| u64::from(bucket * 64 + facet_ord))
+                })
+                .collect();
+            let sketch = &mut self.sketch;
+            // the ordinals come from the dictionary, which was checked at indexing.
+            let _ = facet_reader
+                .facet_dict()
+                .sorted_ords_to_term_cb(&sorted_facet_ords, |_, facet_bytes| {
+                    sketch.insert_bytes(facet_bytes)
+                });
+        }
+        self.sketch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CardinalityCollector, HyperLogLog};
+    use crate::query::{AllQuery, TermQuery};
+    use crate::schema::{Cardinality, Facet, IndexRecordOption, IntOptions};
+    use crate::schema::{Schema, FAST, INDEXED, STRING};
+    use crate::{doc, Index, Term};
+
+    fn assert_estimate_near(estimate: u64, exact: u64, max_relative_error: f64) {
+        let relative_error = (estimate as f64 - exact as f64).abs() / exact as f64;
+        assert!(
+            relative_error <= max_relative_error,
+            "estimate {} is too far from {}",
+            estimate,
+            exact
+        );
+    }
+
+    #[test]
+    fn test_hyperloglog_accuracy() {
+        for &precision in &[10u8, 12, 14] {
+            // about 4 standard errors.
+            let max_relative_error = 4.0 * 1.04 / f64::from(1u32 << precision).sqrt();
+            for &num_vals in &[10u64, 1_000, 10_000, 100_000, 1_000_000] {
+                let mut sketch = HyperLogLog::new(precision);
+                for val in 0..num_vals {
+                    sketch.insert_u64(val);
+                    sketch.insert_u64(val / 2);
+                }
+                assert_estimate_near(sketch.estimate(), num_vals, max_relative_error);
+            }
+        }
+        assert_eq!(HyperLogLog::new(12).estimate(), 0);
+    }
+
+    #[test]
+    fn test_hyperloglog_merge_and_bytes() -> crate::Result<()> {
+        let mut left = HyperLogLog::new(12);
+        let mut right = HyperLogLog::new(12);
+        let mut all = HyperLogLog::new(12);
+        for val in 0..50_000u64 {
+            if val % 3 == 0 {
+                left.insert_u64(val);
+            } else {
+                right.insert_u64(val);
+            }
+            all.insert_u64(val);
+        }
+        let right = HyperLogLog::from_bytes(&right.to_bytes())?;
+        left.merge(&right)?;
+        assert_eq!(left, all);
+        assert!(left.merge(&HyperLogLog::new(10)).is_err());
+        assert!(HyperLogLog::from_bytes(&[]).is_err());
+        assert!(HyperLogLog::from_bytes(&[12, 1, 2]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cardinality_collector() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let category = schema_builder.add_text_field("category", STRING);
+        let user = schema_builder.add_u64_field("user", FAST | INDEXED);
+        let tags = schema_builder.add_i64_field(
+            "tags",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for segment in 0..3u64 {
+            for i in 0..20_000u64 {
+                let category_value = if i % 2 == 0 { "even" } else { "odd" };
+                index_writer.add_document(doc!(
+                    category => category_value,
+                    // the users overlap across the segments.
+                    user => segment * 5_000 + i,
+                    tags => -((i % 300) as i64),
+                    tags => (i % 700) as i64,
+                ));
+            }
+            index_writer.commit()?;
+        }
+        index_writer.delete_term(Term::from_field_u64(user, 0));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+
+        let sketch = searcher.search(&AllQuery, &CardinalityCollector::for_field(user))?;
+        assert_estimate_near(sketch.estimate(), 30_000 - 1, 0.05);
+        let sketch = searcher.search(
+            &AllQuery,
+            &CardinalityCollector::for_field(user).with_precision(16),
+        )?;
+        assert_estimate_near(sketch.estimate(), 30_000 - 1, 0.02);
+        let query = TermQuery::new(
+            Term::from_field_text(category, "even"),
+            IndexRecordOption::Basic,
+        );
+        let sketch = searcher.search(&query, &CardinalityCollector::for_field(user))?;
+        assert_estimate_near(sketch.estimate(), 15_000 - 1, 0.05);
+        // 300 negative or null tags, and 699 positive ones.
+        let sketch = searcher.search(&AllQuery, &CardinalityCollector::for_field(tags))?;
+        assert_estimate_near(sketch.estimate(), 999, 0.05);
+        assert!(searcher
+            .search(&AllQuery, &CardinalityCollector::for_field(category))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cardinality_collector_facets() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let facet = schema_builder.add_facet_field("facet", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        // the facets have different ordinals in each segment.
+        for &(start, end) in &[(0, 60), (40, 100)] {
+            for i in start..end {
+                let facet_path = format!("/category/{}", i);
+                index_writer.add_document(doc!(facet => Facet::from(facet_path.as_str())));
+            }
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let sketch = searcher.search(&AllQuery, &CardinalityCollector::for_field(facet))?;
+        assert_estimate_near(sketch.estimate(), 100, 0.05);
+        Ok(())
+    }
+}
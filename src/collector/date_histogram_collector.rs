@@ -0,0 +1,1188 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use chrono::{
+    Datelike, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, Offset, SecondsFormat,
+    TimeZone, Timelike, Utc,
+};
+
+use crate::collector::sub_collector::SubCollectorFactory;
+use crate::collector::{Collector, Count, SegmentCollector, DEFAULT_MAX_BUCKET_COUNT};
+use crate::fastfield::{FastValue, MultiValuedFastFieldReader, OptionalFastFieldReader};
+use crate::schema::{Cardinality, Field, FieldType};
+use crate::{DateTime, DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+/// Number of days from 0001-01-01 to 1970-01-01.
+const UNIX_EPOCH_DAYS_FROM_CE: i64 = 719_163;
+/// The timestamps are clamped to about 250_000 years around 1970, within the range of
+/// the chrono dates.
+const MAX_MILLIS: i64 = 8_000_000_000_000_000;
+
+/// Width of the buckets of a [`DateHistogramCollector`](./struct.DateHistogramCollector.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DateInterval {
+    /// Buckets of a fixed number of milliseconds.
+    Fixed(i64),
+    /// Calendar weeks, starting on Monday.
+    Week,
+    /// Calendar months.
+    Month,
+    /// Calendar quarters, starting in January, April, July and October.
+    Quarter,
+    /// Calendar years.
+    Year,
+}
+
+impl DateInterval {
+    /// Parses a fixed interval, given as a positive integer followed by the unit `ms`,
+    /// `s`, `m`, `h` or `d` (e.g. `30s`, `5m`, `1h`, `1d`), or a calendar interval:
+    /// `week`, `month`, `quarter` or `year`.
+    pub fn parse(interval: &str) -> crate::Result<DateInterval> {
+        match interval {
+            "week" => return Ok(DateInterval::Week),
+            "month" => return Ok(DateInterval::Month),
+            "quarter" => return Ok(DateInterval::Quarter),
+            "year" => return Ok(DateInterval::Year),
+            _ => {}
+        }
+        let invalid_interval =
+            || TantivyError::InvalidArgument(format!("Invalid date interval {:?}.", interval));
+        let unit_start = interval
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid_interval)?;
+        let (num_units, unit) = interval.split_at(unit_start);
+        let num_units: i64 = num_units.parse().map_err(|_| invalid_interval())?;
+        let unit_millis = match unit {
+            "ms" => 1,
+            "s" => 1_000,
+            "m" => 60_000,
+            "h" => 3_600_000,
+            "d" => MILLIS_PER_DAY,
+            _ => return Err(invalid_interval()),
+        };
+        if num_units == 0 {
+            return Err(invalid_interval());
+        }
+        num_units
+            .checked_mul(unit_millis)
+            .map(DateInterval::Fixed)
+            .ok_or_else(invalid_interval)
+    }
+}
+
+/// Bucket of the histogram computed by a
+/// [`DateHistogramCollector`](./struct.DateHistogramCollector.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DateHistogramBucket<TFruit> {
+    /// Start of the bucket, as a timestamp in milliseconds.
+    pub key: i64,
+    /// Start of the bucket in the timezone of the collector, with the offset from UTC
+    /// of this date, in the RFC 3339 format.
+    pub key_as_string: String,
+    /// Number of documents having a date in the bucket.
+    pub doc_count: u64,
+    /// Fruit of the inner collector over the documents having a date in the bucket.
+    pub fruit: TFruit,
+}
+
+/// The offsets from UTC of a [`TimeZone`], as fixed offsets.
+trait BucketTimeZone: Send + Sync {
+    fn offset_from_utc(&self, utc: &NaiveDateTime) -> FixedOffset;
+
+    fn offset_from_local(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset>;
+}
+
+impl<Tz> BucketTimeZone for Tz
+where
+    Tz: TimeZone + Send + Sync,
+{
+    fn offset_from_utc(&self, utc: &NaiveDateTime) -> FixedOffset {
+        self.offset_from_utc_datetime(utc).fix()
+    }
+
+    fn offset_from_local(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+        match self.offset_from_local_datetime(local) {
+            LocalResult::None => LocalResult::None,
+            LocalResult::Single(offset) => LocalResult::Single(offset.fix()),
+            LocalResult::Ambiguous(earliest, latest) => {
+                LocalResult::Ambiguous(earliest.fix(), latest.fix())
+            }
+        }
+    }
+}
+
+fn naive_from_millis(millis: i64) -> NaiveDateTime {
+    let millis = millis.clamp(-MAX_MILLIS, MAX_MILLIS);
+    let days = millis.div_euclid(MILLIS_PER_DAY) + UNIX_EPOCH_DAYS_FROM_CE;
+    let millis_of_day = millis.rem_euclid(MILLIS_PER_DAY);
+    let date = i32::try_from(days)
+        .ok()
+        .and_then(NaiveDate::from_num_days_from_ce_opt)
+        .expect("The clamped timestamp is in the range of the dates.");
+    let time = NaiveTime::from_num_seconds_from_midnight_opt(
+        (millis_of_day / 1_000) as u32,
+        (millis_of_day % 1_000) as u32 * 1_000_000,
+    )
+    .expect("The time of the day is valid.");
+    date.and_time(time)
+}
+
+fn millis_from_date(date: NaiveDate) -> i64 {
+    (i64::from(date.num_days_from_ce()) - UNIX_EPOCH_DAYS_FROM_CE) * MILLIS_PER_DAY
+}
+
+fn millis_from_naive(date_time: &NaiveDateTime) -> i64 {
+    millis_from_date(date_time.date())
+        + i64::from(date_time.num_seconds_from_midnight()) * 1_000
+        + i64::from(date_time.nanosecond() / 1_000_000)
+}
+
+fn offset_millis(offset: FixedOffset) -> i64 {
+    i64::from(offset.local_minus_utc()) * 1_000
+}
+
+/// Truncates the timestamps to the start of their bucket, in the local time of a
+/// timezone.
+#[derive(Clone)]
+struct DateRounding {
+    interval: DateInterval,
+    timezone: Arc<dyn BucketTimeZone>,
+}
+
+impl DateRounding {
+    fn offset_at(&self, millis: i64) -> FixedOffset {
+        self.timezone.offset_from_utc(&naive_from_millis(millis))
+    }
+
+    /// Returns the local time of the timestamp `millis`, as the timestamp of this date
+    /// and time in UTC.
+    fn utc_to_local(&self, millis: i64) -> i64 {
+        millis + offset_millis(self.offset_at(millis))
+    }
+
+    /// Returns the first timestamp whose local time is `local_millis`, or is after it if
+    /// the offset from UTC increases at this local time.
+    fn local_to_utc(&self, local_millis: i64) -> i64 {
+        match self
+            .timezone
+            .offset_from_local(&naive_from_millis(local_millis))
+        {
+            LocalResult::Single(offset) | LocalResult::Ambiguous(offset, _) => {
+                local_millis - offset_millis(offset)
+            }
+            LocalResult::None => {
+                // The local time is skipped: the first timestamp after it is the change of
+                // offset. The offsets from UTC are less than a day.
+                let (mut low, mut high) =
+                    (local_millis - MILLIS_PER_DAY, local_millis + MILLIS_PER_DAY);
+                while low < high {
+                    let middle = low + (high - low) / 2;
+                    if self.utc_to_local(middle) < local_millis {
+                        low = middle + 1;
+                    } else {
+                        high = middle;
+                    }
+                }
+                low
+            }
+        }
+    }
+
+    /// Returns the start of the local bucket of the local time `local_millis`.
+    fn truncate_local(&self, local_millis: i64) -> i64 {
+        let num_months = match self.interval {
+            DateInterval::Fixed(interval_millis) => {
+                return local_millis.div_euclid(interval_millis) * interval_millis;
+            }
+            DateInterval::Week => {
+                // 1970-01-01 is a Thursday.
+                let days = local_millis.div_euclid(MILLIS_PER_DAY);
+                return (days - (days + 3).rem_euclid(7)) * MILLIS_PER_DAY;
+            }
+            DateInterval::Month => 1,
+            DateInterval::Quarter => 3,
+            DateInterval::Year => 12,
+        };
+        let local_date = naive_from_millis(local_millis).date();
+        let month0 = local_date.month0() / num_months * num_months;
+        month_start(local_date.year(), month0)
+    }
+
+    /// Returns the start of the local bucket following the local bucket starting at
+    /// `local_start`.
+    fn next_local(&self, local_start: i64) -> i64 {
+        let num_months = match self.interval {
+            DateInterval::Fixed(interval_millis) => return local_start + interval_millis,
+            DateInterval::Week => return local_start + 7 * MILLIS_PER_DAY,
+            DateInterval::Month => 1,
+            DateInterval::Quarter => 3,
+            DateInterval::Year => 12,
+        };
+        let local_date = naive_from_millis(local_start).date();
+        month_start(local_date.year(), local_date.month0() + num_months)
+    }
+
+    /// Returns the start of the bucket of the timestamp `millis`.
+    ///
+    /// The local times which are repeated when the offset from UTC decreases belong to
+    /// the bucket of their first occurrence.
+    fn bucket_start(&self, millis: i64) -> i64 {
+        self.local_to_utc(self.truncate_local(self.utc_to_local(millis)))
+    }
+
+    /// Returns the start of the bucket following the bucket starting at `bucket_start`.
+    fn next_bucket_start(&self, bucket_start: i64) -> i64 {
+        let local_start = self.truncate_local(self.utc_to_local(bucket_start));
+        self.local_to_utc(self.next_local(local_start))
+            .max(bucket_start + 1)
+    }
+
+    /// Formats the timestamp `millis` in the RFC 3339 format, with the offset from UTC
+    /// of the timezone at this timestamp.
+    fn format(&self, millis: i64) -> String {
+        let utc = naive_from_millis(millis);
+        self.timezone
+            .offset_from_utc(&utc)
+            .from_utc_datetime(&utc)
+            .to_rfc3339_opts(SecondsFormat::Millis, true)
+    }
+}
+
+/// Returns the local timestamp of the first day of the month `month0`, counted from the
+/// January of `year`.
+fn month_start(year: i32, month0: u32) -> i64 {
+    let date = NaiveDate::from_ymd_opt(year + (month0 / 12) as i32, month0 % 12 + 1, 1)
+        .expect("The first day of a month of the range is valid.");
+    millis_from_naive(&date.and_hms_opt(0, 0, 0).expect("Midnight is valid."))
+}
+
+/// Counts the documents per date bucket of a date fast field, for fixed or calendar
+/// intervals, and runs an inner collector in each bucket.
+///
+/// The dates are converted to the local time of the timezone of the collector before
+/// being truncated to the start of their bucket, so that e.g. the days start at midnight
+/// in this timezone. The timezone can be any chrono `TimeZone`, e.g. a `FixedOffset`
+/// or a timezone of `chrono-tz`, whose daylight saving time is applied: a day bucket
+/// lasts 23 or 25 hours when the offset from UTC changes during the day.
+///
+/// A document is counted once in each bucket in which one of its dates falls.
+/// By default, only the buckets of at least one document are returned, in increasing
+/// order.
+///
+/// ```rust
+/// use tantivy::chrono::FixedOffset;
+/// use tantivy::collector::{DateHistogramCollector, DateInterval};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, FAST};
+/// use tantivy::{doc, DateTime, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let timestamp = schema_builder.add_date_field("timestamp", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// for date in ["2021-01-31T22:00:00Z", "2021-01-31T23:30:00Z", "2021-03-10T08:00:00Z"] {
+///     let date: DateTime = date.parse().unwrap();
+///     index_writer.add_document(doc!(timestamp => date));
+/// }
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let collector = DateHistogramCollector::new(timestamp, DateInterval::parse("month")?)
+///     .with_timezone(FixedOffset::east_opt(3_600).unwrap())
+///     .min_doc_count(0);
+/// let buckets = searcher.search(&AllQuery, &collector)?;
+/// let buckets: Vec<(&str, u64)> = buckets
+///     .iter()
+///     .map(|bucket| (bucket.key_as_string.as_str(), bucket.doc_count))
+///     .collect();
+/// assert_eq!(
+///     buckets,
+///     vec![
+///         ("2021-01-01T00:00:00.000+01:00", 1),
+///         ("2021-02-01T00:00:00.000+01:00", 1),
+///         ("2021-03-01T00:00:00.000+01:00", 1),
+///     ]
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub struct DateHistogramCollector<TCollector> {
+    field: Field,
+    rounding: DateRounding,
+    collector: Arc<TCollector>,
+    extended_bounds: Option<(DateTime, DateTime)>,
+    min_doc_count: u64,
+    max_bucket_count: u64,
+}
+
+impl DateHistogramCollector<Count> {
+    /// Creates a collector of the histogram of the date fast field `field`, in UTC.
+    ///
+    /// Searching returns an error if `field` is not a date fast field.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if a fixed `interval` is not positive.
+    pub fn new(field: Field, interval: DateInterval) -> DateHistogramCollector<Count> {
+        if let DateInterval::Fixed(interval_millis) = interval {
+            assert!(interval_millis > 0, "The interval must be positive.");
+        }
+        DateHistogramCollector {
+            field,
+            rounding: DateRounding {
+                interval,
+                timezone: Arc::new(Utc),
+            },
+            collector: Arc::new(Count),
+            extended_bounds: None,
+            min_doc_count: 1,
+            max_bucket_count: DEFAULT_MAX_BUCKET_COUNT,
+        }
+    }
+}
+
+impl<TCollector: Collector + 'static> DateHistogramCollector<TCollector> {
+    /// Runs `collector` in each of the buckets, instead of the collector of the current
+    /// buckets.
+    ///
+    /// The empty buckets get the fruit of `collector` over no segment.
+    pub fn with_sub_collector<TSubCollector: Collector + 'static>(
+        self,
+        collector: TSubCollector,
+    ) -> DateHistogramCollector<TSubCollector> {
+        DateHistogramCollector {
+            field: self.field,
+            rounding: self.rounding,
+            collector: Arc::new(collector),
+            extended_bounds: self.extended_bounds,
+            min_doc_count: self.min_doc_count,
+            max_bucket_count: self.max_bucket_count,
+        }
+    }
+
+    /// Sets the timezone in which the dates are truncated to their bucket.
+    pub fn with_timezone<Tz>(mut self, timezone: Tz) -> DateHistogramCollector<TCollector>
+    where
+        Tz: TimeZone + Send + Sync + 'static,
+    {
+        self.rounding.timezone = Arc::new(timezone);
+        self
+    }
+
+    /// Returns the buckets from the bucket of `min` to the bucket of `max`, even if they
+    /// are empty, when the collector returns the empty buckets.
+    pub fn extended_bounds(
+        mut self,
+        min: DateTime,
+        max: DateTime,
+    ) -> DateHistogramCollector<TCollector> {
+        self.extended_bounds = Some((min, max));
+        self
+    }
+
+    /// Only returns the buckets of at least `min_doc_count` documents.
+    ///
+    /// If `min_doc_count` is `0`, the empty buckets between the first and the last
+    /// non-empty buckets, or the extended bounds, are returned.
+    ///
+    /// Defaults to `1`.
+    pub fn min_doc_count(mut self, min_doc_count: u64) -> DateHistogramCollector<TCollector> {
+        self.min_doc_count = min_doc_count;
+        self
+    }
+
+    /// Sets the maximum number of buckets, including the empty buckets.
+    ///
+    /// Searching returns a `TantivyError::BucketLimitExceeded` error if a segment, or
+    /// the merged histogram, has more buckets. The collection of a segment stops creating
+    /// buckets once the limit is reached, and so does the listing of the empty buckets.
+    ///
+    /// Defaults to `65_535`.
+    pub fn with_max_bucket_count(
+        mut self,
+        max_bucket_count: u64,
+    ) -> DateHistogramCollector<TCollector> {
+        self.max_bucket_count = max_bucket_count;
+        self
+    }
+}
+
+enum DatesReader {
+    Single(OptionalFastFieldReader<u64>),
+    Multi {
+        ff_reader: MultiValuedFastFieldReader<u64>,
+        vals: Vec<u64>,
+    },
+}
+
+struct SegmentDateHistogramBucket<TSegmentCollector> {
+    doc_count: u64,
+    segment_collector: TSegmentCollector,
+}
+
+/// The buckets of a segment, by increasing bucket start.
+pub struct SegmentDateHistogram<TFruit> {
+    buckets: Vec<(i64, u64, TFruit)>,
+    error: Option<TantivyError>,
+}
+
+/// Segment collector associated to `DateHistogramCollector`.
+pub struct DateHistogramSegmentCollector<TCollector: Collector> {
+    rounding: DateRounding,
+    dates_reader: DatesReader,
+    buckets: BTreeMap<i64, SegmentDateHistogramBucket<TCollector::Child>>,
+    sub_collector_factory: SubCollectorFactory<TCollector>,
+    /// The bounds of the last bucket, which the next dates are likely to fall in.
+    last_bucket: (i64, i64),
+    doc_bucket_starts: Vec<i64>,
+    max_bucket_count: u64,
+    error: Option<TantivyError>,
+}
+
+impl<TCollector: Collector> DateHistogramSegmentCollector<TCollector> {
+    #[inline]
+    fn bucket_start(&mut self, val: u64) -> i64 {
+        // dates are read as timestamps in microseconds.
+        let millis = i64::from_u64(val).div_euclid(1_000);
+        let (start, end) = self.last_bucket;
+        if start <= millis && millis < end {
+            return start;
+        }
+        let start = self.rounding.bucket_start(millis);
+        self.last_bucket = (start, self.rounding.next_bucket_start(start));
+        start
+    }
+
+    #[inline]
+    fn add_doc(&mut self, bucket_start: i64, doc: DocId, score: Score) {
+        if !self.buckets.contains_key(&bucket_start) {
+            if self.buckets.len() as u64 >= self.max_bucket_count {
+                self.error = Some(TantivyError::BucketLimitExceeded(self.max_bucket_count));
+                return;
+            }
+            match self.sub_collector_factory.for_bucket() {
+                Ok(segment_collector) => {
+                    self.buckets.insert(
+                        bucket_start,
+                        SegmentDateHistogramBucket {
+                            doc_count: 0,
+                            segment_collector,
+                        },
+                    );
+                }
+                Err(error) => {
+                    self.error = Some(error);
+                    return;
+                }
+            }
+        }
+        let bucket = self.buckets.get_mut(&bucket_start).unwrap();
+        bucket.doc_count += 1;
+        bucket.segment_collector.collect(doc, score);
+    }
+}
+
+impl<TCollector: Collector + 'static> Collector for DateHistogramCollector<TCollector> {
+    type Fruit = Vec<DateHistogramBucket<TCollector::Fruit>>;
+    type Child = DateHistogramSegmentCollector<TCollector>;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<DateHistogramSegmentCollector<TCollector>> {
+        let field_entry = reader.schema().get_field_entry(self.field);
+        let cardinality = match field_entry.field_type() {
+            FieldType::Date(options) => options.get_fastfield_cardinality(),
+            _ => None,
+        };
+        let fast_fields = reader.fast_fields();
+        let dates_reader = match cardinality {
+            Some(Cardinality::SingleValue) => {
+                DatesReader::Single(fast_fields.typed_optional_fast_field_reader(self.field)?)
+            }
+            Some(Cardinality::MultiValues) => DatesReader::Multi {
+                ff_reader: fast_fields.u64s_lenient(self.field)?,
+                vals: Vec::new(),
+            },
+            None => {
+                return Err(TantivyError::SchemaError(format!(
+                    "Field {:?} is not a date fast field.",
+                    field_entry.name()
+                )));
+            }
+        };
+        Ok(DateHistogramSegmentCollector {
+            rounding: self.rounding.clone(),
+            dates_reader,
+            buckets: BTreeMap::new(),
+            sub_collector_factory: SubCollectorFactory::new(
+                Arc::clone(&self.collector),
+                segment_local_id,
+                reader,
+            ),
+            last_bucket: (0, 0),
+            doc_bucket_starts: Vec::new(),
+            max_bucket_count: self.max_bucket_count,
+            error: None,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.collector.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<SegmentDateHistogram<<TCollector::Child as SegmentCollector>::Fruit>>,
+    ) -> crate::Result<Vec<DateHistogramBucket<TCollector::Fruit>>> {
+        let bucket_limit_exceeded = || TantivyError::BucketLimitExceeded(self.max_bucket_count);
+        let mut merged_buckets: BTreeMap<i64, (u64, Vec<_>)> = BTreeMap::new();
+        for segment_fruit in segment_fruits {
+            if let Some(error) = segment_fruit.error {
+                return Err(error);
+            }
+            for (bucket_start, doc_count, child_fruit) in segment_fruit.buckets {
+                let (merged_doc_count, child_fruits) =
+                    merged_buckets.entry(bucket_start).or_default();
+                *merged_doc_count += doc_count;
+                child_fruits.push(child_fruit);
+            }
+        }
+        if merged_buckets.len() as u64 > self.max_bucket_count {
+            return Err(bucket_limit_exceeded());
+        }
+        if self.min_doc_count == 0 {
+            let mut first = merged_buckets.keys().next().cloned();
+            let mut last = merged_buckets.keys().next_back().cloned();
+            if let Some((min, max)) = self.extended_bounds.as_ref() {
+                let min_start = self.rounding.bucket_start(min.timestamp_millis());
+                let max_start = self.rounding.bucket_start(max.timestamp_millis());
+                first = Some(first.map_or(min_start, |first| first.min(min_start)));
+                last = Some(last.map_or(max_start, |last| last.max(max_start)));
+            }
+            if let (Some(first), Some(last)) = (first, last) {
+                let mut bucket_start = first;
+                while bucket_start <= last {
+                    merged_buckets.entry(bucket_start).or_default();
+                    if merged_buckets.len() as u64 > self.max_bucket_count {
+                        return Err(bucket_limit_exceeded());
+                    }
+                    bucket_start = self.rounding.next_bucket_start(bucket_start);
+                }
+            }
+        }
+        let mut buckets = Vec::with_capacity(merged_buckets.len());
+        for (key, (doc_count, child_fruits)) in merged_buckets {
+            if doc_count < self.min_doc_count {
+                continue;
+            }
+            buckets.push(DateHistogramBucket {
+                key,
+                key_as_string: self.rounding.format(key),
+                doc_count,
+                fruit: self.collector.merge_fruits(child_fruits)?,
+            });
+        }
+        Ok(buckets)
+    }
+}
+
+impl<TCollector: Collector + 'static> SegmentCollector
+    for DateHistogramSegmentCollector<TCollector>
+{
+    type Fruit = SegmentDateHistogram<<TCollector::Child as SegmentCollector>::Fruit>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        if self.error.is_some() {
+            return;
+        }
+        match &mut self.dates_reader {
+            DatesReader::Single(ff_reader) => {
+                if let Some(val) = ff_reader.get_opt(doc) {
+                    let bucket_start = self.bucket_start(val);
+                    self.add_doc(bucket_start, doc, score);
+                }
+            }
+            DatesReader::Multi { ff_reader, vals } => {
+                ff_reader.get_vals(doc, vals);
+                let mut vals = std::mem::take(vals);
+                // the values are kept in their insertion order: sorting them brings the
+                // values of a bucket together.
+                vals.sort_unstable();
+                let mut doc_bucket_starts = std::mem::take(&mut self.doc_bucket_starts);
+                doc_bucket_starts.clear();
+                for &val in &vals {
+                    let bucket_start = self.bucket_start(val);
+                    if doc_bucket_starts.last() != Some(&bucket_start) {
+                        doc_bucket_starts.push(bucket_start);
+                    }
+                }
+                // the local times repeated when the offset from UTC decreases may bring
+                // the dates back to a previous bucket.
+                doc_bucket_starts.sort_unstable();
+                doc_bucket_starts.dedup();
+                for &bucket_start in &doc_bucket_starts {
+                    self.add_doc(bucket_start, doc, score);
+                }
+                self.doc_bucket_starts = doc_bucket_starts;
+                if let DatesReader::Multi { vals: buffer, .. } = &mut self.dates_reader {
+                    *buffer = vals;
+                }
+            }
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        if let Some(error) = self.error {
+            return SegmentDateHistogram {
+                buckets: Vec::new(),
+                error: Some(error),
+            };
+        }
+        let buckets = self
+            .buckets
+            .into_iter()
+            .map(|(bucket_start, bucket)| {
+                (
+                    bucket_start,
+                    bucket.doc_count,
+                    bucket.segment_collector.harvest(),
+                )
+            })
+            .collect();
+        SegmentDateHistogram {
+            buckets,
+            error: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DateHistogramBucket, DateHistogramCollector, DateInterval};
+    use crate::chrono::{
+        Datelike, Duration, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc,
+    };
+    use crate::collector::{Count, TopDocs};
+    use crate::query::AllQuery;
+    use crate::schema::{Cardinality, DateOptions, Schema, FAST, TEXT};
+    use crate::{doc, DateTime, Index, TantivyError};
+
+    /// The timezone of Paris, with the daylight saving time of the European Union: the
+    /// offset from UTC is +02:00 from 01:00 UTC on the last Sunday of March to 01:00 UTC
+    /// on the last Sunday of October, and +01:00 otherwise.
+    #[derive(Clone, Copy)]
+    struct CentralEuropeanTime;
+
+    impl CentralEuropeanTime {
+        fn offset_at(utc: &NaiveDateTime) -> FixedOffset {
+            let last_sunday_at_one = |month: u32| {
+                let last_day = NaiveDate::from_ymd_opt(utc.year(), month, 31).unwrap();
+                let last_sunday =
+                    last_day - Duration::days(i64::from(last_day.weekday().num_days_from_sunday()));
+                last_sunday.and_hms_opt(1, 0, 0).unwrap()
+            };
+            let hours = if last_sunday_at_one(3) <= *utc && *utc < last_sunday_at_one(10) {
+                2
+            } else {
+                1
+            };
+            FixedOffset::east_opt(hours * 3_600).unwrap()
+        }
+    }
+
+    impl TimeZone for CentralEuropeanTime {
+        type Offset = FixedOffset;
+
+        fn from_offset(_offset: &FixedOffset) -> CentralEuropeanTime {
+            CentralEuropeanTime
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            // the earliest timestamp has the greatest offset.
+            let offsets: Vec<FixedOffset> = [2, 1]
+                .iter()
+                .map(|hours| FixedOffset::east_opt(hours * 3_600).unwrap())
+                .filter(|&offset| Self::offset_at(&(*local - offset)) == offset)
+                .collect();
+            match offsets[..] {
+                [offset] => LocalResult::Single(offset),
+                [earliest, latest] => LocalResult::Ambiguous(earliest, latest),
+                _ => LocalResult::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            Self::offset_at(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            Self::offset_at(utc)
+        }
+    }
+
+    fn keys_as_strings<TFruit>(buckets: &[DateHistogramBucket<TFruit>]) -> Vec<(&str, u64)> {
+        buckets
+            .iter()
+            .map(|bucket| (bucket.key_as_string.as_str(), bucket.doc_count))
+            .collect()
+    }
+
+    fn create_index(dates: &[&[DateTime]]) -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_date_field("date", FAST);
+        schema_builder.add_date_field(
+            "dates",
+            DateOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        let schema = index.schema();
+        let date = schema.get_field("date").unwrap();
+        let multi_dates = schema.get_field("dates").unwrap();
+        let mut index_writer = index.writer_for_tests()?;
+        for segment_dates in dates {
+            for &date_value in segment_dates.iter() {
+                index_writer.add_document(doc!(
+                    date => date_value,
+                    multi_dates => date_value + chrono::Duration::minutes(1),
+                    multi_dates => date_value,
+                    multi_dates => date_value + chrono::Duration::minutes(2),
+                ));
+            }
+            index_writer.commit()?;
+        }
+        Ok(index)
+    }
+
+    #[test]
+    fn test_date_interval_parse() {
+        assert_eq!(
+            DateInterval::parse("30s").unwrap(),
+            DateInterval::Fixed(30_000)
+        );
+        assert_eq!(
+            DateInterval::parse("5m").unwrap(),
+            DateInterval::Fixed(300_000)
+        );
+        assert_eq!(
+            DateInterval::parse("1h").unwrap(),
+            DateInterval::Fixed(3_600_000)
+        );
+        assert_eq!(
+            DateInterval::parse("1d").unwrap(),
+            DateInterval::Fixed(86_400_000)
+        );
+        assert_eq!(
+            DateInterval::parse("quarter").unwrap(),
+            DateInterval::Quarter
+        );
+        for invalid_interval in &["", "1", "h", "0s", "1y", "-1h", "1.5h"] {
+            assert!(DateInterval::parse(invalid_interval).is_err());
+        }
+    }
+
+    #[test]
+    fn test_date_histogram_fixed_interval() -> crate::Result<()> {
+        let index = create_index(&[
+            &[
+                Utc.ymd(2021, 5, 3).and_hms(10, 15, 0),
+                Utc.ymd(2021, 5, 3).and_hms(10, 59, 0),
+            ],
+            &[
+                Utc.ymd(2021, 5, 3).and_hms(13, 0, 0),
+                Utc.ymd(2021, 5, 3).and_hms(10, 30, 0),
+            ],
+        ])?;
+        let schema = index.schema();
+        let date = schema.get_field("date").unwrap();
+        let multi_dates = schema.get_field("dates").unwrap();
+        let searcher = index.reader()?.searcher();
+
+        let collector = DateHistogramCollector::new(date, DateInterval::parse("1h")?);
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            buckets[0].key,
+            Utc.ymd(2021, 5, 3).and_hms(10, 0, 0).timestamp_millis()
+        );
+        assert_eq!(
+            keys_as_strings(&buckets),
+            vec![
+                ("2021-05-03T10:00:00.000Z", 3),
+                ("2021-05-03T13:00:00.000Z", 1)
+            ]
+        );
+
+        let collector = DateHistogramCollector::new(date, DateInterval::parse("1h")?)
+            .with_timezone(FixedOffset::west_opt(30 * 60).unwrap())
+            .min_doc_count(0);
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_as_strings(&buckets),
+            vec![
+                ("2021-05-03T09:00:00.000-00:30", 1),
+                ("2021-05-03T10:00:00.000-00:30", 2),
+                ("2021-05-03T11:00:00.000-00:30", 0),
+                ("2021-05-03T12:00:00.000-00:30", 1),
+            ]
+        );
+
+        // the document of 10:59 has dates in two buckets, the other documents have both
+        // their dates in the same bucket.
+        let collector = DateHistogramCollector::new(multi_dates, DateInterval::parse("1h")?);
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_as_strings(&buckets),
+            vec![
+                ("2021-05-03T10:00:00.000Z", 3),
+                ("2021-05-03T11:00:00.000Z", 1),
+                ("2021-05-03T13:00:00.000Z", 1),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_histogram_bounds_and_min_doc_count() -> crate::Result<()> {
+        let index = create_index(&[&[
+            Utc.ymd(2021, 5, 5).and_hms(10, 0, 0),
+            Utc.ymd(2021, 5, 5).and_hms(11, 0, 0),
+            Utc.ymd(2021, 5, 7).and_hms(12, 0, 0),
+        ]])?;
+        let date = index.schema().get_field("date").unwrap();
+        let searcher = index.reader()?.searcher();
+
+        let collector = DateHistogramCollector::new(date, DateInterval::parse("1d")?)
+            .extended_bounds(
+                Utc.ymd(2021, 5, 4).and_hms(12, 0, 0),
+                Utc.ymd(2021, 5, 6).and_hms(0, 0, 0),
+            )
+            .min_doc_count(0);
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_as_strings(&buckets),
+            vec![
+                ("2021-05-04T00:00:00.000Z", 0),
+                ("2021-05-05T00:00:00.000Z", 2),
+                ("2021-05-06T00:00:00.000Z", 0),
+                ("2021-05-07T00:00:00.000Z", 1),
+            ]
+        );
+
+        let collector =
+            DateHistogramCollector::new(date, DateInterval::parse("1d")?).min_doc_count(2);
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_as_strings(&buckets),
+            vec![("2021-05-05T00:00:00.000Z", 2)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_histogram_calendar_intervals_with_timezone() -> crate::Result<()> {
+        // the daylight saving time of Europe/Paris starts on 2021-03-28 and ends on
+        // 2021-10-31: the fixed offset of the collector applies all year long.
+        let index = create_index(&[
+            &[
+                Utc.ymd(2021, 3, 31).and_hms(22, 30, 0),
+                Utc.ymd(2021, 3, 31).and_hms(23, 30, 0),
+            ],
+            &[
+                Utc.ymd(2021, 10, 31).and_hms(23, 30, 0),
+                Utc.ymd(2021, 12, 31).and_hms(23, 30, 0),
+            ],
+        ])?;
+        let date = index.schema().get_field("date").unwrap();
+        let searcher = index.reader()?.searcher();
+        let paris_winter_time = FixedOffset::east_opt(3_600).unwrap();
+
+        let collector =
+            DateHistogramCollector::new(date, DateInterval::Month).with_timezone(paris_winter_time);
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_as_strings(&buckets),
+            vec![
+                ("2021-03-01T00:00:00.000+01:00", 1),
+                ("2021-04-01T00:00:00.000+01:00", 1),
+                ("2021-11-01T00:00:00.000+01:00", 1),
+                ("2022-01-01T00:00:00.000+01:00", 1),
+            ]
+        );
+        assert_eq!(
+            buckets[1].key,
+            Utc.ymd(2021, 3, 31).and_hms(23, 0, 0).timestamp_millis()
+        );
+
+        // with the summer time offset, the date of 2021-03-31T22:30:00Z is in April.
+        let collector = DateHistogramCollector::new(date, DateInterval::Month)
+            .with_timezone(FixedOffset::east_opt(2 * 3_600).unwrap());
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_as_strings(&buckets),
+            vec![
+                ("2021-04-01T00:00:00.000+02:00", 2),
+                ("2021-11-01T00:00:00.000+02:00", 1),
+                ("2022-01-01T00:00:00.000+02:00", 1),
+            ]
+        );
+
+        let collector = DateHistogramCollector::new(date, DateInterval::Quarter)
+            .with_timezone(paris_winter_time)
+            .min_doc_count(0);
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_as_strings(&buckets),
+            vec![
+                ("2021-01-01T00:00:00.000+01:00", 1),
+                ("2021-04-01T00:00:00.000+01:00", 1),
+                ("2021-07-01T00:00:00.000+01:00", 0),
+                ("2021-10-01T00:00:00.000+01:00", 1),
+                ("2022-01-01T00:00:00.000+01:00", 1),
+            ]
+        );
+
+        let collector = DateHistogramCollector::new(date, DateInterval::Year);
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_as_strings(&buckets),
+            vec![("2021-01-01T00:00:00.000Z", 4)]
+        );
+
+        // 2021-03-31 is a Wednesday.
+        let collector =
+            DateHistogramCollector::new(date, DateInterval::Week).with_timezone(paris_winter_time);
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_as_strings(&buckets)[0],
+            ("2021-03-29T00:00:00.000+01:00", 2)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_histogram_daylight_saving_time() -> crate::Result<()> {
+        // in 2021, the summer time of Paris starts on 2021-03-28 at 02:00, and ends on
+        // 2021-10-31 at 03:00, when 02:00 to 03:00 is repeated.
+        let index = create_index(&[
+            &[
+                Utc.ymd(2021, 3, 28).and_hms(21, 30, 0),
+                Utc.ymd(2021, 3, 28).and_hms(22, 30, 0),
+                Utc.ymd(2021, 3, 31).and_hms(22, 30, 0),
+            ],
+            &[
+                Utc.ymd(2021, 10, 31).and_hms(0, 30, 0),
+                Utc.ymd(2021, 10, 31).and_hms(1, 30, 0),
+                Utc.ymd(2021, 10, 31).and_hms(2, 30, 0),
+            ],
+        ])?;
+        let date = index.schema().get_field("date").unwrap();
+        let searcher = index.reader()?.searcher();
+
+        let collector = DateHistogramCollector::new(date, DateInterval::Month)
+            .with_timezone(CentralEuropeanTime)
+            .min_doc_count(0);
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(buckets.len(), 8);
+        assert_eq!(
+            keys_as_strings(&buckets[..2]),
+            vec![
+                ("2021-03-01T00:00:00.000+01:00", 2),
+                ("2021-04-01T00:00:00.000+02:00", 1),
+            ]
+        );
+        assert_eq!(
+            buckets[1].key,
+            Utc.ymd(2021, 3, 31).and_hms(22, 0, 0).timestamp_millis()
+        );
+        assert_eq!(
+            keys_as_strings(&buckets[7..]),
+            vec![("2021-10-01T00:00:00.000+02:00", 3)]
+        );
+
+        // the days start at midnight, the day of the start of the summer time lasts 23
+        // hours, and the day of its end 25 hours.
+        let collector = DateHistogramCollector::new(date, DateInterval::parse("1d")?)
+            .with_timezone(CentralEuropeanTime)
+            .min_doc_count(0);
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(buckets.len(), 218);
+        assert!(buckets
+            .iter()
+            .all(|bucket| bucket.key_as_string.contains("T00:00:00.000+0")));
+        assert_eq!(
+            keys_as_strings(&buckets[..2]),
+            vec![
+                ("2021-03-28T00:00:00.000+01:00", 1),
+                ("2021-03-29T00:00:00.000+02:00", 1),
+            ]
+        );
+        assert_eq!(buckets[1].key - buckets[0].key, 23 * 3_600_000);
+        assert_eq!(
+            keys_as_strings(&buckets[216..]),
+            vec![
+                ("2021-10-30T00:00:00.000+02:00", 0),
+                ("2021-10-31T00:00:00.000+02:00", 3),
+            ]
+        );
+
+        // the two dates of 02:30 fall in the bucket of the first 02:00.
+        let collector = DateHistogramCollector::new(date, DateInterval::parse("1h")?)
+            .with_timezone(CentralEuropeanTime);
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_as_strings(&buckets),
+            vec![
+                ("2021-03-28T23:00:00.000+02:00", 1),
+                ("2021-03-29T00:00:00.000+02:00", 1),
+                ("2021-04-01T00:00:00.000+02:00", 1),
+                ("2021-10-31T02:00:00.000+02:00", 2),
+                ("2021-10-31T03:00:00.000+01:00", 1),
+            ]
+        );
+        assert_eq!(
+            buckets[3].key,
+            Utc.ymd(2021, 10, 31).and_hms(0, 0, 0).timestamp_millis()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_histogram_skipped_local_times() -> crate::Result<()> {
+        // 02:00 to 03:00 does not exist on 2021-03-28 in Paris.
+        let index = create_index(&[&[
+            Utc.ymd(2021, 3, 28).and_hms(0, 45, 0),
+            Utc.ymd(2021, 3, 28).and_hms(1, 15, 0),
+        ]])?;
+        let date = index.schema().get_field("date").unwrap();
+        let searcher = index.reader()?.searcher();
+        let collector = DateHistogramCollector::new(date, DateInterval::parse("30m")?)
+            .with_timezone(CentralEuropeanTime)
+            .min_doc_count(0);
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_as_strings(&buckets),
+            vec![
+                ("2021-03-28T01:30:00.000+01:00", 1),
+                ("2021-03-28T03:00:00.000+02:00", 1),
+            ]
+        );
+        assert_eq!(buckets[1].key - buckets[0].key, 30 * 60_000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_histogram_sub_collector() -> crate::Result<()> {
+        let index = create_index(&[
+            &[
+                Utc.ymd(2021, 5, 3).and_hms(10, 15, 0),
+                Utc.ymd(2021, 5, 3).and_hms(10, 59, 0),
+            ],
+            &[
+                Utc.ymd(2021, 5, 3).and_hms(13, 0, 0),
+                Utc.ymd(2021, 5, 5).and_hms(10, 30, 0),
+            ],
+        ])?;
+        let schema = index.schema();
+        let date = schema.get_field("date").unwrap();
+        let multi_dates = schema.get_field("dates").unwrap();
+        let searcher = index.reader()?.searcher();
+
+        let collector = DateHistogramCollector::new(date, DateInterval::parse("1d")?)
+            .min_doc_count(0)
+            .with_sub_collector(DateHistogramCollector::new(
+                multi_dates,
+                DateInterval::parse("1h")?,
+            ));
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_as_strings(&buckets),
+            vec![
+                ("2021-05-03T00:00:00.000Z", 3),
+                ("2021-05-04T00:00:00.000Z", 0),
+                ("2021-05-05T00:00:00.000Z", 1),
+            ]
+        );
+        assert_eq!(
+            keys_as_strings(&buckets[0].fruit),
+            vec![
+                ("2021-05-03T10:00:00.000Z", 2),
+                ("2021-05-03T11:00:00.000Z", 1),
+                ("2021-05-03T13:00:00.000Z", 1),
+            ]
+        );
+        assert!(buckets[1].fruit.is_empty());
+        assert_eq!(
+            keys_as_strings(&buckets[2].fruit),
+            vec![("2021-05-05T10:00:00.000Z", 1)]
+        );
+
+        let collector = DateHistogramCollector::new(date, DateInterval::Month)
+            .with_sub_collector(TopDocs::with_limit(10));
+        let buckets = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].fruit.len(), 4);
+
+        let collector = DateHistogramCollector::new(date, DateInterval::Month).with_sub_collector(
+            DateHistogramCollector::new(date, DateInterval::parse("1h")?).with_max_bucket_count(2),
+        );
+        assert!(matches!(
+            searcher.search(&AllQuery, &collector),
+            Err(TantivyError::BucketLimitExceeded(2))
+        ));
+        let collector =
+            DateHistogramCollector::new(date, DateInterval::Month).with_sub_collector(Count);
+        assert_eq!(searcher.search(&AllQuery, &collector)?[0].fruit, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_histogram_bucket_limit() -> crate::Result<()> {
+        let index = create_index(&[
+            &[Utc.ymd(2000, 1, 1).and_hms(0, 0, 0)],
+            &[Utc.ymd(2021, 1, 1).and_hms(0, 0, 0)],
+        ])?;
+        let date = index.schema().get_field("date").unwrap();
+        let searcher = index.reader()?.searcher();
+
+        let collector =
+            DateHistogramCollector::new(date, DateInterval::parse("1ms")?).min_doc_count(0);
+        assert!(matches!(
+            searcher.search(&AllQuery, &collector),
+            Err(TantivyError::BucketLimitExceeded(65_535))
+        ));
+        // each segment has a single bucket, but not the merged histogram.
+        let collector =
+            DateHistogramCollector::new(date, DateInterval::Year).with_max_bucket_count(1);
+        assert!(matches!(
+            searcher.search(&AllQuery, &collector),
+            Err(TantivyError::BucketLimitExceeded(1))
+        ));
+        let collector = DateHistogramCollector::new(date, DateInterval::Year)
+            .min_doc_count(0)
+            .with_max_bucket_count(22);
+        assert_eq!(searcher.search(&AllQuery, &collector)?.len(), 22);
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_histogram_wrong_field() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "hello"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let collector = DateHistogramCollector::new(title, DateInterval::Month);
+        assert!(searcher.search(&AllQuery, &collector).is_err());
+        Ok(())
+    }
+}
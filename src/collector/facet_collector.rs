@@ -139,6 +139,9 @@ fn facet_depth(facet_bytes: &[u8]) -> usize {
 ///             (&Facet::from("/category/biography"), 1),
 ///             (&Facet::from("/category/fiction"), 3)
 ///         ]);
+///
+///         // This counts the documents having a facet under `/category`
+///         assert_eq!(facet_counts.total_count("/category"), 4);
 ///     }
 ///
 ///     {
@@ -187,6 +190,12 @@ pub struct FacetSegmentCollector {
     counts: Vec<u64>,
     // collapse facet_id -> facet_ord
     collapse_facet_ords: Vec<u64>,
+    // collapse facet_id -> root id
+    collapse_roots: Vec<usize>,
+    // root id -> root facet
+    roots: Vec<Facet>,
+    // root id -> number of documents having a facet under the root
+    root_counts: Vec<u64>,
 }
 
 enum SkipResult {
@@ -273,9 +282,12 @@ impl Collector for FacetCollector {
         let mut collapse_mapping = Vec::new();
         let mut counts = Vec::new();
         let mut collapse_facet_ords = Vec::new();
+        let mut collapse_roots = Vec::new();
+        let mut roots = Vec::new();
 
         let mut collapse_facet_it = self.facets.iter().peekable();
         collapse_facet_ords.push(0);
+        collapse_roots.push(usize::MAX);
         {
             let mut facet_streamer = facet_reader.facet_dict().range().into_stream()?;
             if facet_streamer.advance() {
@@ -286,6 +298,10 @@ impl Collector for FacetCollector {
                     match skip_result {
                         SkipResult::Found => {
                             // we reach a facet we decided to collapse.
+                            let root_id = roots.len();
+                            if let Some(&root) = collapse_facet_it.peek() {
+                                roots.push(root.clone());
+                            }
                             let collapse_depth = facet_depth(facet_streamer.key());
                             let mut collapsed_id = 0;
                             collapse_mapping.push(0);
@@ -297,6 +313,7 @@ impl Collector for FacetCollector {
                                 if depth == collapse_depth + 1 {
                                     collapsed_id = collapse_facet_ords.len();
                                     collapse_facet_ords.push(facet_streamer.term_ord());
+                                    collapse_roots.push(root_id);
                                 }
                                 collapse_mapping.push(collapsed_id);
                             }
@@ -314,6 +331,7 @@ impl Collector for FacetCollector {
         }
 
         counts.resize(collapse_facet_ords.len(), 0);
+        let root_counts = vec![0; roots.len()];
 
         Ok(FacetSegmentCollector {
             reader: facet_reader,
@@ -321,6 +339,9 @@ impl Collector for FacetCollector {
             collapse_mapping,
             counts,
             collapse_facet_ords,
+            collapse_roots,
+            roots,
+            root_counts,
         })
     }
 
@@ -330,12 +351,19 @@ impl Collector for FacetCollector {
 
     fn merge_fruits(&self, segments_facet_counts: Vec<FacetCounts>) -> crate::Result<FacetCounts> {
         let mut facet_counts: BTreeMap<Facet, u64> = BTreeMap::new();
+        let mut root_counts: BTreeMap<Facet, u64> = BTreeMap::new();
         for segment_facet_counts in segments_facet_counts {
             for (facet, count) in segment_facet_counts.facet_counts {
                 *(facet_counts.entry(facet).or_insert(0)) += count;
             }
+            for (root, count) in segment_facet_counts.root_counts {
+                *(root_counts.entry(root).or_insert(0)) += count;
+            }
         }
-        Ok(FacetCounts { facet_counts })
+        Ok(FacetCounts {
+            facet_counts,
+            root_counts,
+        })
     }
 }
 
@@ -345,6 +373,7 @@ impl SegmentCollector for FacetSegmentCollector {
     fn collect(&mut self, doc: DocId, _: Score) {
         self.reader.facet_ords(doc, &mut self.facet_ords_buf);
         let mut previous_collapsed_ord: usize = usize::MAX;
+        let mut previous_root: usize = usize::MAX;
         for &facet_ord in &self.facet_ords_buf {
             let collapsed_ord = self.collapse_mapping[facet_ord as usize];
             self.counts[collapsed_ord] += if collapsed_ord == previous_collapsed_ord {
@@ -353,6 +382,12 @@ impl SegmentCollector for FacetSegmentCollector {
                 1
             };
             previous_collapsed_ord = collapsed_ord;
+            // the facets under a root have consecutive ordinals.
+            let root = self.collapse_roots[collapsed_ord];
+            if root != usize::MAX && root != previous_root {
+                self.root_counts[root] += 1;
+                previous_root = root;
+            }
         }
     }
 
@@ -360,23 +395,42 @@ impl SegmentCollector for FacetSegmentCollector {
     ///
     /// This method does not just return the counters,
     /// it also translates the facet ordinals of the last segment.
+    ///
+    /// The ordinals of the counted facets are increasing, so that they are all
+    /// translated in a single pass over the facet dictionary.
     fn harvest(self) -> FacetCounts {
         let mut facet_counts = BTreeMap::new();
-        let facet_dict = self.reader.facet_dict();
-        for (collapsed_facet_ord, count) in self.counts.iter().cloned().enumerate() {
-            if count == 0 {
-                continue;
-            }
-            let mut facet = vec![];
-            let facet_ord = self.collapse_facet_ords[collapsed_facet_ord];
-            // TODO handle errors.
-            if facet_dict.ord_to_term(facet_ord as u64, &mut facet).is_ok() {
-                if let Ok(facet) = Facet::from_encoded(facet) {
+        let (counted_facet_ords, facet_ord_counts): (Vec<u64>, Vec<u64>) = self
+            .counts
+            .iter()
+            .cloned()
+            .enumerate()
+            .filter(|&(collapsed_facet_ord, count)| collapsed_facet_ord != 0 && count > 0)
+            .map(|(collapsed_facet_ord, count)| {
+                (self.collapse_facet_ords[collapsed_facet_ord], count)
+            })
+            .unzip();
+        let mut facet_ord_counts_it = facet_ord_counts.into_iter();
+        // TODO handle errors.
+        let _ = self.reader.facet_dict().sorted_ords_to_term_cb(
+            &counted_facet_ords,
+            |_, facet_bytes| {
+                let count = facet_ord_counts_it.next().unwrap_or(0);
+                if let Ok(facet) = Facet::from_encoded(facet_bytes.to_vec()) {
                     facet_counts.insert(facet, count);
                 }
-            }
+            },
+        );
+        let root_counts = self
+            .roots
+            .into_iter()
+            .zip(self.root_counts)
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        FacetCounts {
+            facet_counts,
+            root_counts,
         }
-        FacetCounts { facet_counts }
     }
 }
 
@@ -384,6 +438,7 @@ impl SegmentCollector for FacetSegmentCollector {
 /// the facet counts for all the segments.
 pub struct FacetCounts {
     facet_counts: BTreeMap<Facet, u64>,
+    root_counts: BTreeMap<Facet, u64>,
 }
 
 pub struct FacetChildIterator<'a> {
@@ -420,7 +475,25 @@ impl FacetCounts {
         FacetChildIterator { underlying }
     }
 
+    /// Returns the number of documents having a facet under the facet `root`.
+    ///
+    /// `root` must have been added to the `FacetCollector`, so this is the number of
+    /// documents counted in any of the facets returned by `get(root)`, each document
+    /// being counted once. This makes it possible to compute the proportion of the
+    /// documents under `root` for each of these facets.
+    pub fn total_count<T>(&self, root: T) -> u64
+    where
+        Facet: From<T>,
+    {
+        self.root_counts
+            .get(&Facet::from(root))
+            .cloned()
+            .unwrap_or(0)
+    }
+
     /// Returns a vector of top `k` facets with their counts, sorted highest-to-lowest by counts.
+    ///
+    /// The facets with the same count are sorted by increasing facet path.
     /// See the documentation for `FacetCollector` for a usage example.
     pub fn top_k<T>(&self, facet: T, k: usize) -> Vec<(&Facet, u64)>
     where
@@ -693,6 +766,49 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_facet_collector_total_count() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let facet_field = schema_builder.add_facet_field("facet", INDEXED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(
+            facet_field => Facet::from("/category/fiction/fantasy"),
+            facet_field => Facet::from("/category/fiction/horror"),
+            facet_field => Facet::from("/category/biography"),
+            facet_field => Facet::from("/lang/en"),
+        ));
+        index_writer.add_document(doc!(facet_field => Facet::from("/lang/fr")));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(
+            facet_field => Facet::from("/category/fiction/sci-fi"),
+            facet_field => Facet::from("/lang/en"),
+        ));
+        index_writer.add_document(doc!(facet_field => Facet::from("/category")));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+
+        let mut facet_collector = FacetCollector::for_field(facet_field);
+        facet_collector.add_facet("/category");
+        facet_collector.add_facet("/lang");
+        let counts = searcher.search(&AllQuery, &facet_collector)?;
+        assert_eq!(
+            counts.top_k("/category", 1),
+            vec![(&Facet::from("/category/fiction"), 2)]
+        );
+        assert_eq!(
+            counts.top_k("/lang", 2),
+            vec![(&Facet::from("/lang/en"), 2), (&Facet::from("/lang/fr"), 1)]
+        );
+        // the document with the facet `/category` itself is not under `/category`.
+        assert_eq!(counts.total_count("/category"), 2);
+        assert_eq!(counts.total_count("/lang"), 3);
+        assert_eq!(counts.total_count("/category/fiction"), 0);
+        Ok(())
+    }
 }
 
 #[cfg(all(test, feature = "unstable"))]
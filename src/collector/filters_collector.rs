@@ -0,0 +1,310 @@
+use common::BitSet;
+
+use crate::collector::{Collector, SegmentCollector};
+use crate::query::{Query, Weight};
+use crate::{DocId, Score, Searcher, SegmentOrdinal, SegmentReader};
+
+/// Bucket computed by a [`FiltersCollector`](./struct.FiltersCollector.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilterBucket<TFruit> {
+    /// Name of the filter of the bucket, or of the other bucket.
+    pub name: String,
+    /// Number of documents in the bucket.
+    pub doc_count: u64,
+    /// Fruit of the inner collector over the documents in the bucket.
+    pub fruit: TFruit,
+}
+
+/// Splits the matching documents into named buckets, one per filter query, and runs a
+/// collector over the documents of each bucket.
+///
+/// A document goes into the bucket of each filter it matches, so the buckets may
+/// overlap. The documents matching none of the filters can be collected into an
+/// [other bucket](#method.with_other_bucket). For a single filter, the collector then
+/// computes the fruit of the inner collector over the matching documents passing the
+/// filter.
+///
+/// The filters are evaluated once per segment, into a bitset of the documents they
+/// match, regardless of the query. Filters that are cheap to evaluate over the whole
+/// segment, like term or range queries, should be preferred.
+///
+/// ```rust
+/// use tantivy::collector::{Count, FiltersCollector, StatsCollector};
+/// use tantivy::query::{AllQuery, QueryParser};
+/// use tantivy::schema::{Schema, FAST, STRING};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let status = schema_builder.add_text_field("status", STRING);
+/// let price = schema_builder.add_u64_field("price", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(status => "in_stock", price => 30u64));
+/// index_writer.add_document(doc!(status => "in_stock", status => "on_sale", price => 20u64));
+/// index_writer.add_document(doc!(status => "clearance", price => 5u64));
+/// index_writer.add_document(doc!(price => 100u64));
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let query_parser = QueryParser::for_index(&index, vec![status]);
+/// let collector = FiltersCollector::new(StatsCollector::for_field(price))
+///     .add_filter("in_stock", &query_parser.parse_query("in_stock")?, &searcher)?
+///     .add_filter("on_sale", &query_parser.parse_query("on_sale")?, &searcher)?
+///     .with_other_bucket("other");
+/// let buckets = searcher.search(&AllQuery, &collector)?;
+/// assert_eq!(buckets[0].name, "in_stock");
+/// assert_eq!(buckets[0].doc_count, 2);
+/// assert_eq!(buckets[0].fruit.mean, Some(25.0));
+/// assert_eq!(buckets[1].fruit.mean, Some(20.0));
+/// assert_eq!(buckets[2].name, "other");
+/// assert_eq!(buckets[2].fruit.max, Some(100.0));
+///
+/// let single_filter = FiltersCollector::new(Count)
+///     .add_filter("clearance", &query_parser.parse_query("clearance")?, &searcher)?;
+/// let buckets = searcher.search(&AllQuery, &single_filter)?;
+/// assert_eq!(buckets[0].fruit, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct FiltersCollector<TCollector> {
+    collector: TCollector,
+    filters: Vec<(String, Box<dyn Weight>)>,
+    other_bucket: Option<String>,
+}
+
+impl<TCollector: Collector> FiltersCollector<TCollector> {
+    /// Creates a collector running `collector` in each bucket. The filters are then
+    /// added with [`add_filter`](#method.add_filter).
+    pub fn new(collector: TCollector) -> FiltersCollector<TCollector> {
+        FiltersCollector {
+            collector,
+            filters: Vec::new(),
+            other_bucket: None,
+        }
+    }
+
+    /// Adds a bucket named `name` for the documents matching `filter`.
+    ///
+    /// The filter is prepared for the segments of `searcher`, which must be the searcher
+    /// running the collector.
+    pub fn add_filter(
+        mut self,
+        name: &str,
+        filter: &dyn Query,
+        searcher: &Searcher,
+    ) -> crate::Result<FiltersCollector<TCollector>> {
+        self.filters
+            .push((name.to_string(), filter.weight(searcher, false)?));
+        Ok(self)
+    }
+
+    /// Adds a last bucket named `name` for the documents matching none of the filters.
+    pub fn with_other_bucket(mut self, name: &str) -> FiltersCollector<TCollector> {
+        self.other_bucket = Some(name.to_string());
+        self
+    }
+}
+
+impl<TCollector: Collector> Collector for FiltersCollector<TCollector> {
+    type Fruit = Vec<FilterBucket<TCollector::Fruit>>;
+    type Child = FiltersSegmentCollector<TCollector::Child>;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        let mut buckets = Vec::with_capacity(self.filters.len());
+        for (_, weight) in &self.filters {
+            let mut filter_docs = BitSet::with_max_value(reader.max_doc());
+            weight.for_each(reader, &mut |doc, _| filter_docs.insert(doc))?;
+            buckets.push(SegmentFilterBucket {
+                filter_docs,
+                doc_count: 0,
+                segment_collector: self.collector.for_segment(segment_local_id, reader)?,
+            });
+        }
+        let other_bucket = if self.other_bucket.is_some() {
+            Some((0, self.collector.for_segment(segment_local_id, reader)?))
+        } else {
+            None
+        };
+        Ok(FiltersSegmentCollector {
+            buckets,
+            other_bucket,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.collector.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<Vec<(u64, <TCollector::Child as SegmentCollector>::Fruit)>>,
+    ) -> crate::Result<Self::Fruit> {
+        let names = self
+            .filters
+            .iter()
+            .map(|(name, _)| name)
+            .chain(self.other_bucket.as_ref());
+        let num_buckets = self.filters.len() + self.other_bucket.iter().count();
+        let mut bucket_fruits: Vec<(u64, Vec<_>)> = (0..num_buckets)
+            .map(|_| (0, Vec::with_capacity(segment_fruits.len())))
+            .collect();
+        for segment_buckets in segment_fruits {
+            for ((doc_count, child_fruits), (segment_doc_count, child_fruit)) in
+                bucket_fruits.iter_mut().zip(segment_buckets)
+            {
+                *doc_count += segment_doc_count;
+                child_fruits.push(child_fruit);
+            }
+        }
+        names
+            .zip(bucket_fruits)
+            .map(|(name, (doc_count, child_fruits))| {
+                Ok(FilterBucket {
+                    name: name.clone(),
+                    doc_count,
+                    fruit: self.collector.merge_fruits(child_fruits)?,
+                })
+            })
+            .collect()
+    }
+}
+
+struct SegmentFilterBucket<TSegmentCollector> {
+    filter_docs: BitSet,
+    doc_count: u64,
+    segment_collector: TSegmentCollector,
+}
+
+/// Segment collector associated to `FiltersCollector`.
+pub struct FiltersSegmentCollector<TSegmentCollector> {
+    buckets: Vec<SegmentFilterBucket<TSegmentCollector>>,
+    other_bucket: Option<(u64, TSegmentCollector)>,
+}
+
+impl<TSegmentCollector: SegmentCollector> SegmentCollector
+    for FiltersSegmentCollector<TSegmentCollector>
+{
+    /// The document count and the fruit of the inner segment collector of each bucket,
+    /// the other bucket being the last one.
+    type Fruit = Vec<(u64, TSegmentCollector::Fruit)>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        let mut matches_a_filter = false;
+        for bucket in &mut self.buckets {
+            if bucket.filter_docs.contains(doc) {
+                matches_a_filter = true;
+                bucket.doc_count += 1;
+                bucket.segment_collector.collect(doc, score);
+            }
+        }
+        if !matches_a_filter {
+            if let Some((doc_count, segment_collector)) = &mut self.other_bucket {
+                *doc_count += 1;
+                segment_collector.collect(doc, score);
+            }
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.buckets
+            .into_iter()
+            .map(|bucket| (bucket.doc_count, bucket.segment_collector))
+            .chain(self.other_bucket)
+            .map(|(doc_count, segment_collector)| (doc_count, segment_collector.harvest()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FiltersCollector;
+    use crate::collector::{Count, TopDocs};
+    use crate::query::{QueryParser, RangeQuery, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, FAST, INDEXED, STRING, TEXT};
+    use crate::{doc, DocAddress, Index, Term};
+
+    #[test]
+    fn test_filters_collector() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let status = schema_builder.add_text_field("status", STRING);
+        let price = schema_builder.add_u64_field("price", FAST | INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "red shoes", status => "in_stock", price => 30u64));
+        index_writer.add_document(doc!(title => "blue shoes", status => "on_sale", price => 5u64));
+        index_writer.add_document(doc!(title => "red socks", status => "in_stock", price => 2u64));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(title => "green shoes", price => 80u64));
+        index_writer.add_document(doc!(title => "shoes", status => "in_stock", price => 8u64));
+        index_writer.add_document(doc!(title => "old shoes", status => "in_stock", price => 1u64));
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_u64(price, 1u64));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+
+        let in_stock = TermQuery::new(
+            Term::from_field_text(status, "in_stock"),
+            IndexRecordOption::Basic,
+        );
+        let cheap = RangeQuery::new_u64(price, 0..10);
+        let collector = FiltersCollector::new(Count)
+            .add_filter("in_stock", &in_stock, &searcher)?
+            .add_filter("cheap", &cheap, &searcher)?
+            .with_other_bucket("other");
+        let query = QueryParser::for_index(&index, vec![title]).parse_query("shoes")?;
+        let buckets = searcher.search(&query, &collector)?;
+        let counts: Vec<(&str, u64, usize)> = buckets
+            .iter()
+            .map(|bucket| (bucket.name.as_str(), bucket.doc_count, bucket.fruit))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![("in_stock", 2, 2), ("cheap", 2, 2), ("other", 1, 1)]
+        );
+
+        // the inner collector is given the scores of the query.
+        let collector = FiltersCollector::new(TopDocs::with_limit(1))
+            .add_filter("in_stock", &in_stock, &searcher)?;
+        let buckets = searcher.search(&query, &collector)?;
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].fruit.len(), 1);
+        let second_segment_ord = searcher
+            .segment_readers()
+            .iter()
+            .position(|segment_reader| segment_reader.num_deleted_docs() == 1)
+            .unwrap();
+        assert_eq!(
+            buckets[0].fruit[0].1,
+            DocAddress::new(second_segment_ord as u32, 1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_filters_collector_without_filters() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "hello"));
+        index_writer.add_document(doc!(title => "world"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = QueryParser::for_index(&index, vec![title]).parse_query("hello")?;
+        let buckets = searcher.search(&query, &FiltersCollector::new(Count))?;
+        assert!(buckets.is_empty());
+        let collector = FiltersCollector::new(Count).with_other_bucket("all");
+        let buckets = searcher.search(&query, &collector)?;
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].name, "all");
+        assert_eq!(buckets[0].fruit, 1);
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use crate::collector::{Collector, SegmentCollector};
-use crate::fastfield::{DynamicFastFieldReader, FastFieldReader, FastValue};
+use crate::fastfield::{FastValue, OptionalFastFieldReader};
 use crate::schema::{Field, Type};
 use crate::{DocId, Score};
 use fastdivide::DividerU64;
@@ -16,6 +16,9 @@ use fastdivide::DividerU64;
 ///
 /// See also [`HistogramCollector::new()`].
 ///
+/// Documents without a value for the field are not counted, unless a substitute value
+/// is given with [`HistogramCollector::missing()`].
+///
 /// # Warning
 ///
 /// f64 field. are not supported.
@@ -25,6 +28,7 @@ pub struct HistogramCollector {
     num_buckets: usize,
     divider: DividerU64,
     field: Field,
+    missing: Option<u64>,
 }
 
 impl HistogramCollector {
@@ -39,6 +43,9 @@ impl HistogramCollector {
     /// Together, this parameters define a partition of `[min_value, min_value + num_buckets * bucket_width)`
     /// into `num_buckets` intervals of width bucket that we call `bucket`.
     ///
+    /// For date fields, `bucket_width` is expressed in seconds whatever the precision of
+    /// the field, and the dates are bucketed after being truncated to this precision.
+    ///
     /// # Disclaimer
     /// This function panics if the field given is of type f64.
     pub fn new<TFastValue: FastValue>(
@@ -49,13 +56,26 @@ impl HistogramCollector {
     ) -> HistogramCollector {
         let fast_type = TFastValue::to_type();
         assert!(fast_type == Type::U64 || fast_type == Type::I64 || fast_type == Type::Date);
+        // dates are read as timestamps in microseconds.
+        let bucket_width = if fast_type == Type::Date {
+            bucket_width * 1_000_000
+        } else {
+            bucket_width
+        };
         HistogramCollector {
             min_value: min_value.to_u64(),
             num_buckets,
             field,
             divider: DividerU64::divide_by(bucket_width),
+            missing: None,
         }
     }
+
+    /// Counts the documents without a value for the field as if their value was `missing`.
+    pub fn missing<TFastValue: FastValue>(mut self, missing: TFastValue) -> HistogramCollector {
+        self.missing = Some(missing.to_u64());
+        self
+    }
 }
 
 struct HistogramComputer {
@@ -84,15 +104,17 @@ impl HistogramComputer {
 }
 pub struct SegmentHistogramCollector {
     histogram_computer: HistogramComputer,
-    ff_reader: DynamicFastFieldReader<u64>,
+    ff_reader: OptionalFastFieldReader<u64>,
+    missing: Option<u64>,
 }
 
 impl SegmentCollector for SegmentHistogramCollector {
     type Fruit = Vec<u64>;
 
     fn collect(&mut self, doc: DocId, _score: Score) {
-        let value = self.ff_reader.get(doc);
-        self.histogram_computer.add_value(value);
+        if let Some(value) = self.ff_reader.get_opt(doc).or(self.missing) {
+            self.histogram_computer.add_value(value);
+        }
     }
 
     fn harvest(self) -> Self::Fruit {
@@ -109,7 +131,9 @@ impl Collector for HistogramCollector {
         _segment_local_id: crate::SegmentOrdinal,
         segment: &crate::SegmentReader,
     ) -> crate::Result<Self::Child> {
-        let ff_reader = segment.fast_fields().u64_lenient(self.field)?;
+        let ff_reader = segment
+            .fast_fields()
+            .typed_optional_fast_field_reader(self.field)?;
         Ok(SegmentHistogramCollector {
             histogram_computer: HistogramComputer {
                 counts: vec![0; self.num_buckets],
@@ -117,6 +141,7 @@ impl Collector for HistogramCollector {
                 divider: self.divider,
             },
             ff_reader,
+            missing: self.missing,
         })
     }
 
@@ -240,6 +265,33 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_histogram_missing_values() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let val_field = schema_builder.add_i64_field("val_field", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut writer = index.writer_with_num_threads(1, 4_000_000)?;
+        writer.add_document(doc!(val_field=>12i64));
+        writer.add_document(doc!());
+        writer.add_document(doc!(val_field=>-12i64));
+        writer.add_document(doc!());
+        writer.commit()?;
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let histogram_collector = HistogramCollector::new(val_field, -20i64, 10u64, 4);
+        assert_eq!(
+            searcher.search(&AllQuery, &histogram_collector)?,
+            vec![1, 0, 0, 1]
+        );
+        let histogram_collector = histogram_collector.missing(-5i64);
+        assert_eq!(
+            searcher.search(&AllQuery, &histogram_collector)?,
+            vec![1, 2, 0, 1]
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_histogram_merge() -> crate::Result<()> {
         let mut schema_builder = Schema::builder();
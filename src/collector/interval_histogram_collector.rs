@@ -0,0 +1,411 @@
+use std::collections::BTreeMap;
+
+use crate::collector::numeric_values::NumericValuesReader;
+use crate::collector::{Collector, SegmentCollector, DEFAULT_MAX_BUCKET_COUNT};
+use crate::schema::Field;
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// Hard bounds of an [`IntervalHistogramCollector`](./struct.IntervalHistogramCollector.html).
+///
+/// Only the values within `[min, max]` are bucketed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HistogramBounds {
+    /// Smallest value bucketed.
+    pub min: f64,
+    /// Greatest value bucketed.
+    pub max: f64,
+}
+
+/// Histogram computed by an [`IntervalHistogramCollector`](./struct.IntervalHistogramCollector.html).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IntervalHistogram {
+    /// The start of the non-empty buckets, in increasing order, and their count of values.
+    ///
+    /// The empty buckets between them are only listed if they were requested with
+    /// [`IntervalHistogramCollector::fill_empty_buckets`](./struct.IntervalHistogramCollector.html#method.fill_empty_buckets).
+    pub buckets: Vec<(f64, u64)>,
+    /// Number of values smaller than the hard bounds, if they were requested with
+    /// [`IntervalHistogramCollector::count_outliers`](./struct.IntervalHistogramCollector.html#method.count_outliers).
+    pub underflow: Option<u64>,
+    /// Number of values greater than the hard bounds, if they were requested with
+    /// [`IntervalHistogramCollector::count_outliers`](./struct.IntervalHistogramCollector.html#method.count_outliers).
+    pub overflow: Option<u64>,
+}
+
+/// Builds an histogram of the values of a u64, i64, f64 or date fast field, with
+/// buckets of a fixed width.
+///
+/// The value `val` goes into the bucket `floor((val - offset) / interval)`, which starts
+/// at `offset + floor((val - offset) / interval) * interval`. Unlike
+/// [`HistogramCollector`](./struct.HistogramCollector.html), the range of the buckets is
+/// not given in advance: only the buckets of the collected values are created, unless
+/// hard bounds are set.
+///
+/// Documents without a value are ignored, unless a [missing value](#method.with_missing)
+/// is set, and each value of the multi-valued fast fields
+/// is added to its bucket. For date fields, the values, the interval, the offset and the
+/// bounds are timestamps in seconds.
+///
+/// ```rust
+/// use tantivy::collector::{HistogramBounds, IntervalHistogramCollector};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, FAST};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let price = schema_builder.add_f64_field("price", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// for val in [3.5, 12.0, 18.0, 41.0, 1_000.0] {
+///     index_writer.add_document(doc!(price => val));
+/// }
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let bounds = HistogramBounds { min: 0.0, max: 100.0 };
+/// let collector = IntervalHistogramCollector::new(price, 10.0, Some(bounds)).count_outliers();
+/// let histogram = searcher.search(&AllQuery, &collector)?;
+/// assert_eq!(histogram.buckets, vec![(0.0, 1), (10.0, 2), (40.0, 1)]);
+/// assert_eq!(histogram.overflow, Some(1));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct IntervalHistogramCollector {
+    field: Field,
+    interval: f64,
+    offset: f64,
+    bounds: Option<HistogramBounds>,
+    fill_empty_buckets: bool,
+    count_outliers: bool,
+    missing: Option<f64>,
+    max_bucket_count: u64,
+}
+
+impl IntervalHistogramCollector {
+    /// Creates a collector of the histogram of `field` with buckets of width `interval`.
+    ///
+    /// If `bounds` are given, the values out of them are not bucketed, which caps the
+    /// number of buckets when some values are outliers.
+    ///
+    /// Searching returns an error if `field` is not a u64, i64, f64 or date fast field.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `interval` is not positive.
+    pub fn new(
+        field: Field,
+        interval: f64,
+        bounds: Option<HistogramBounds>,
+    ) -> IntervalHistogramCollector {
+        assert!(interval > 0.0, "The interval must be positive.");
+        IntervalHistogramCollector {
+            field,
+            interval,
+            offset: 0.0,
+            bounds,
+            fill_empty_buckets: false,
+            count_outliers: false,
+            missing: None,
+            max_bucket_count: DEFAULT_MAX_BUCKET_COUNT,
+        }
+    }
+
+    /// Shifts the start of the buckets by `offset`.
+    ///
+    /// Defaults to `0`.
+    pub fn offset(mut self, offset: f64) -> IntervalHistogramCollector {
+        self.offset = offset;
+        self
+    }
+
+    /// Lists the empty buckets between the first and the last non-empty buckets.
+    ///
+    /// Without hard bounds, a single outlier may then create a lot of buckets.
+    pub fn fill_empty_buckets(mut self) -> IntervalHistogramCollector {
+        self.fill_empty_buckets = true;
+        self
+    }
+
+    /// Counts the values out of the hard bounds in the `underflow` and `overflow`
+    /// buckets.
+    pub fn count_outliers(mut self) -> IntervalHistogramCollector {
+        self.count_outliers = true;
+        self
+    }
+
+    /// Adds the documents without a value to the bucket of the value `missing`.
+    ///
+    /// The value `missing` is subject to the hard bounds like the other values.
+    pub fn with_missing(mut self, missing: f64) -> IntervalHistogramCollector {
+        self.missing = Some(missing);
+        self
+    }
+
+    /// Sets the maximum number of buckets, including the empty buckets.
+    ///
+    /// Searching returns a `TantivyError::BucketLimitExceeded` error if a segment, or
+    /// the merged histogram, has more buckets. The collection of a segment stops creating
+    /// buckets once the limit is reached, and the empty buckets are only listed once their
+    /// number is known to be within the limit.
+    ///
+    /// Defaults to `65_535`.
+    pub fn with_max_bucket_count(mut self, max_bucket_count: u64) -> IntervalHistogramCollector {
+        self.max_bucket_count = max_bucket_count;
+        self
+    }
+
+    fn bucket_start(&self, bucket: i64) -> f64 {
+        self.offset + bucket as f64 * self.interval
+    }
+}
+
+/// Counts of the buckets of a segment, by bucket number.
+pub struct SegmentIntervalHistogram {
+    counts: BTreeMap<i64, u64>,
+    underflow: u64,
+    overflow: u64,
+    bucket_limit_exceeded: bool,
+}
+
+pub struct IntervalHistogramSegmentCollector {
+    values_reader: NumericValuesReader,
+    interval: f64,
+    offset: f64,
+    bounds: Option<HistogramBounds>,
+    max_bucket_count: u64,
+    histogram: SegmentIntervalHistogram,
+}
+
+impl Collector for IntervalHistogramCollector {
+    type Fruit = IntervalHistogram;
+    type Child = IntervalHistogramSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<IntervalHistogramSegmentCollector> {
+        Ok(IntervalHistogramSegmentCollector {
+            values_reader: NumericValuesReader::open(reader, self.field, self.missing)?,
+            interval: self.interval,
+            offset: self.offset,
+            bounds: self.bounds,
+            max_bucket_count: self.max_bucket_count,
+            histogram: SegmentIntervalHistogram {
+                counts: BTreeMap::new(),
+                underflow: 0,
+                overflow: 0,
+                bucket_limit_exceeded: false,
+            },
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_histograms: Vec<SegmentIntervalHistogram>,
+    ) -> crate::Result<IntervalHistogram> {
+        let mut counts = BTreeMap::new();
+        let (mut underflow, mut overflow) = (0, 0);
+        for segment_histogram in segment_histograms {
+            if segment_histogram.bucket_limit_exceeded {
+                return Err(TantivyError::BucketLimitExceeded(self.max_bucket_count));
+            }
+            for (bucket, count) in segment_histogram.counts {
+                *counts.entry(bucket).or_insert(0) += count;
+            }
+            underflow += segment_histogram.underflow;
+            overflow += segment_histogram.overflow;
+        }
+        if counts.len() as u64 > self.max_bucket_count {
+            return Err(TantivyError::BucketLimitExceeded(self.max_bucket_count));
+        }
+        let buckets = match (counts.keys().next(), counts.keys().next_back()) {
+            (Some(&first), Some(&last)) if self.fill_empty_buckets => {
+                if (last as i128 - first as i128) >= i128::from(self.max_bucket_count) {
+                    return Err(TantivyError::BucketLimitExceeded(self.max_bucket_count));
+                }
+                (first..=last)
+                    .map(|bucket| {
+                        let count = counts.get(&bucket).cloned().unwrap_or(0);
+                        (self.bucket_start(bucket), count)
+                    })
+                    .collect()
+            }
+            _ => counts
+                .into_iter()
+                .map(|(bucket, count)| (self.bucket_start(bucket), count))
+                .collect(),
+        };
+        let (underflow, overflow) = if self.count_outliers {
+            (Some(underflow), Some(overflow))
+        } else {
+            (None, None)
+        };
+        Ok(IntervalHistogram {
+            buckets,
+            underflow,
+            overflow,
+        })
+    }
+}
+
+impl SegmentCollector for IntervalHistogramSegmentCollector {
+    type Fruit = SegmentIntervalHistogram;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        let (interval, offset, bounds) = (self.interval, self.offset, self.bounds);
+        let max_bucket_count = self.max_bucket_count;
+        let histogram = &mut self.histogram;
+        self.values_reader.for_each_value(doc, |val| {
+            if val.is_nan() {
+                return;
+            }
+            if let Some(bounds) = bounds {
+                if val < bounds.min {
+                    histogram.underflow += 1;
+                    return;
+                }
+                if val > bounds.max {
+                    histogram.overflow += 1;
+                    return;
+                }
+            }
+            let bucket = ((val - offset) / interval).floor() as i64;
+            if let Some(count) = histogram.counts.get_mut(&bucket) {
+                *count += 1;
+            } else if (histogram.counts.len() as u64) < max_bucket_count {
+                histogram.counts.insert(bucket, 1);
+            } else {
+                histogram.bucket_limit_exceeded = true;
+            }
+        });
+    }
+
+    fn harvest(self) -> SegmentIntervalHistogram {
+        self.histogram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{HistogramBounds, IntervalHistogramCollector};
+    use crate::chrono::{TimeZone, Utc};
+    use crate::query::AllQuery;
+    use crate::schema::{Cardinality, IntOptions, Schema, FAST};
+    use crate::{doc, Index, TantivyError};
+
+    #[test]
+    fn test_interval_histogram() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let val = schema_builder.add_i64_field("val", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(val => -7i64));
+        index_writer.add_document(doc!(val => 12i64));
+        index_writer.add_document(doc!());
+        index_writer.commit()?;
+        index_writer.add_document(doc!(val => 14i64));
+        index_writer.add_document(doc!(val => 31i64));
+        index_writer.add_document(doc!(val => 1_000_000i64));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+
+        let collector = IntervalHistogramCollector::new(val, 10.0, None);
+        let histogram = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            histogram.buckets,
+            vec![(-10.0, 1), (10.0, 2), (30.0, 1), (1_000_000.0, 1)]
+        );
+        assert_eq!(histogram.underflow, None);
+
+        let collector = IntervalHistogramCollector::new(val, 10.0, None).with_missing(15.0);
+        let histogram = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            histogram.buckets,
+            vec![(-10.0, 1), (10.0, 3), (30.0, 1), (1_000_000.0, 1)]
+        );
+
+        let bounds = HistogramBounds {
+            min: 0.0,
+            max: 40.0,
+        };
+        let collector = IntervalHistogramCollector::new(val, 10.0, Some(bounds))
+            .offset(2.0)
+            .fill_empty_buckets()
+            .count_outliers();
+        let histogram = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(histogram.buckets, vec![(12.0, 2), (22.0, 1)]);
+        assert_eq!(histogram.underflow, Some(1));
+        assert_eq!(histogram.overflow, Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_interval_histogram_bucket_limit() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let val = schema_builder.add_f64_field("val", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..1_000 {
+            index_writer.add_document(doc!(val => f64::from(i)));
+        }
+        index_writer.add_document(doc!(val => 1e12));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        // listing the empty buckets would create a trillion buckets.
+        let start = Instant::now();
+        let collector = IntervalHistogramCollector::new(val, 1.0, None).fill_empty_buckets();
+        assert!(matches!(
+            searcher.search(&AllQuery, &collector),
+            Err(TantivyError::BucketLimitExceeded(65_535))
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let collector = IntervalHistogramCollector::new(val, 1.0, None).with_max_bucket_count(100);
+        assert!(matches!(
+            searcher.search(&AllQuery, &collector),
+            Err(TantivyError::BucketLimitExceeded(100))
+        ));
+        let collector =
+            IntervalHistogramCollector::new(val, 1.0, None).with_max_bucket_count(1_001);
+        assert_eq!(searcher.search(&AllQuery, &collector)?.buckets.len(), 1_001);
+        Ok(())
+    }
+
+    #[test]
+    fn test_interval_histogram_multivalued_and_date() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let vals = schema_builder.add_u64_field(
+            "vals",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let date = schema_builder.add_date_field("date", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer
+            .add_document(doc!(vals => 1u64, vals => 5u64, date => Utc.timestamp(3_600, 0)));
+        index_writer.add_document(doc!(vals => 2u64, date => Utc.timestamp(3 * 3_600 + 1, 0)));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let collector = IntervalHistogramCollector::new(vals, 2.0, None).fill_empty_buckets();
+        let histogram = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(histogram.buckets, vec![(0.0, 1), (2.0, 1), (4.0, 1)]);
+        let collector = IntervalHistogramCollector::new(date, 3_600.0, None).fill_empty_buckets();
+        let histogram = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            histogram.buckets,
+            vec![(3_600.0, 1), (7_200.0, 0), (10_800.0, 1)]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use crate::collector::{Collector, SegmentCollector};
+use crate::fastfield::{FacetReader, MultiValuedFastFieldReader, OptionalFastFieldReader};
+use crate::schema::{Cardinality, Field, FieldType};
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// Bucket computed by a [`MissingCollector`](./struct.MissingCollector.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct MissingBucket<TFruit> {
+    /// Number of matching documents without a value.
+    pub doc_count: u64,
+    /// Fruit of the inner collector over the matching documents without a value.
+    pub fruit: TFruit,
+}
+
+/// Runs a collector over the matching documents without a value for a fast field or a
+/// facet field.
+///
+/// Within a [`MultiCollector`](./struct.MultiCollector.html), it tells how many
+/// documents were ignored by the collectors of the values of the field, e.g. a
+/// [`StatsCollector`](./struct.StatsCollector.html): its count of values and the count
+/// of the missing bucket add up to the number of matching documents, for a
+/// single-valued field.
+///
+/// ```rust
+/// use tantivy::collector::{Count, MissingCollector, MultiCollector, StatsCollector};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, FAST};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let price = schema_builder.add_u64_field("price", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(price => 30u64));
+/// index_writer.add_document(doc!());
+/// index_writer.add_document(doc!(price => 10u64));
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let mut collectors = MultiCollector::new();
+/// let count_handle = collectors.add_collector(Count);
+/// let stats_handle = collectors.add_collector(StatsCollector::for_field(price));
+/// let missing_handle = collectors.add_collector(MissingCollector::new(price, Count));
+/// let mut multi_fruit = searcher.search(&AllQuery, &collectors)?;
+/// assert_eq!(count_handle.extract(&mut multi_fruit), 3);
+/// assert_eq!(stats_handle.extract(&mut multi_fruit).count, 2);
+/// assert_eq!(missing_handle.extract(&mut multi_fruit).doc_count, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct MissingCollector<TCollector> {
+    field: Field,
+    collector: TCollector,
+}
+
+impl<TCollector: Collector> MissingCollector<TCollector> {
+    /// Creates a collector running `collector` over the documents without a value for
+    /// `field`.
+    ///
+    /// Searching returns an error if `field` is neither a u64, i64, f64, bool or date
+    /// fast field, nor a facet field.
+    pub fn new(field: Field, collector: TCollector) -> MissingCollector<TCollector> {
+        MissingCollector { field, collector }
+    }
+}
+
+/// Tells whether the documents of a segment have a value.
+enum PresenceReader {
+    Single(OptionalFastFieldReader<u64>),
+    Multi(MultiValuedFastFieldReader<u64>),
+    Facet {
+        facet_reader: Box<FacetReader>,
+        facet_ords: Vec<u64>,
+    },
+}
+
+impl PresenceReader {
+    fn open(reader: &SegmentReader, field: Field) -> crate::Result<PresenceReader> {
+        let field_entry = reader.schema().get_field_entry(field);
+        let cardinality = match field_entry.field_type() {
+            FieldType::U64(options)
+            | FieldType::I64(options)
+            | FieldType::F64(options)
+            | FieldType::Bool(options) => options.get_fastfield_cardinality(),
+            FieldType::Date(options) => options.get_fastfield_cardinality(),
+            FieldType::HierarchicalFacet(_) => {
+                return Ok(PresenceReader::Facet {
+                    facet_reader: Box::new(reader.facet_reader(field)?),
+                    facet_ords: Vec::new(),
+                });
+            }
+            _ => None,
+        };
+        let fast_fields = reader.fast_fields();
+        match cardinality {
+            Some(Cardinality::SingleValue) => Ok(PresenceReader::Single(
+                fast_fields.typed_optional_fast_field_reader(field)?,
+            )),
+            Some(Cardinality::MultiValues) => {
+                Ok(PresenceReader::Multi(fast_fields.u64s_lenient(field)?))
+            }
+            None => Err(TantivyError::SchemaError(format!(
+                "Field {:?} is neither a u64, i64, f64, bool or date fast field, nor a facet \
+                 field.",
+                field_entry.name()
+            ))),
+        }
+    }
+
+    #[inline]
+    fn has_value(&mut self, doc: DocId) -> bool {
+        match self {
+            PresenceReader::Single(ff_reader) => ff_reader.get_opt(doc).is_some(),
+            PresenceReader::Multi(ff_reader) => ff_reader.num_vals(doc) > 0,
+            PresenceReader::Facet {
+                facet_reader,
+                facet_ords,
+            } => {
+                facet_reader.facet_ords(doc, facet_ords);
+                !facet_ords.is_empty()
+            }
+        }
+    }
+}
+
+impl<TCollector: Collector> Collector for MissingCollector<TCollector> {
+    type Fruit = MissingBucket<TCollector::Fruit>;
+    type Child = MissingSegmentCollector<TCollector::Child>;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        Ok(MissingSegmentCollector {
+            presence_reader: PresenceReader::open(reader, self.field)?,
+            doc_count: 0,
+            segment_collector: self.collector.for_segment(segment_local_id, reader)?,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.collector.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<(u64, <TCollector::Child as SegmentCollector>::Fruit)>,
+    ) -> crate::Result<Self::Fruit> {
+        let mut doc_count = 0;
+        let child_fruits = segment_fruits
+            .into_iter()
+            .map(|(segment_doc_count, child_fruit)| {
+                doc_count += segment_doc_count;
+                child_fruit
+            })
+            .collect();
+        Ok(MissingBucket {
+            doc_count,
+            fruit: self.collector.merge_fruits(child_fruits)?,
+        })
+    }
+}
+
+/// Segment collector associated to `MissingCollector`.
+pub struct MissingSegmentCollector<TSegmentCollector> {
+    presence_reader: PresenceReader,
+    doc_count: u64,
+    segment_collector: TSegmentCollector,
+}
+
+impl<TSegmentCollector: SegmentCollector> SegmentCollector
+    for MissingSegmentCollector<TSegmentCollector>
+{
+    /// The number of documents without a value, and the fruit of the inner segment
+    /// collector.
+    type Fruit = (u64, TSegmentCollector::Fruit);
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        if !self.presence_reader.has_value(doc) {
+            self.doc_count += 1;
+            self.segment_collector.collect(doc, score);
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        (self.doc_count, self.segment_collector.harvest())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MissingCollector;
+    use crate::collector::{Count, MultiCollector, StatsCollector, TopDocs};
+    use crate::query::AllQuery;
+    use crate::schema::{Cardinality, Facet, IntOptions, Schema, FAST, INDEXED, TEXT};
+    use crate::{doc, Index};
+
+    #[test]
+    fn test_missing_collector() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let price = schema_builder.add_f64_field("price", FAST);
+        let rating = schema_builder.add_u64_field("rating", FAST);
+        let tags = schema_builder.add_i64_field(
+            "tags",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let category = schema_builder.add_facet_field("category", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(price => 3.5, rating => 4u64, tags => 1i64, tags => 2i64));
+        index_writer.add_document(doc!(title => "none"));
+        index_writer.add_document(doc!(rating => 2u64, category => Facet::from("/shoes")));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(price => 10.0, tags => 3i64));
+        index_writer.add_document(doc!(rating => 5u64, category => Facet::from("/socks")));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+
+        let mut collectors = MultiCollector::new();
+        let count_handle = collectors.add_collector(Count);
+        let stats_handle = collectors.add_collector(StatsCollector::for_field(price));
+        let missing_handle = collectors.add_collector(MissingCollector::new(
+            price,
+            StatsCollector::for_field(rating),
+        ));
+        let mut multi_fruit = searcher.search(&AllQuery, &collectors)?;
+        let count = count_handle.extract(&mut multi_fruit) as u64;
+        let stats = stats_handle.extract(&mut multi_fruit);
+        let missing = missing_handle.extract(&mut multi_fruit);
+        assert_eq!(stats.count + missing.doc_count, count);
+        assert_eq!(missing.doc_count, 3);
+        assert_eq!(missing.fruit.count, 2);
+        assert_eq!(missing.fruit.mean, Some(3.5));
+
+        // with a missing value, all the documents are counted.
+        let collector = StatsCollector::for_field(price).with_missing(0.0);
+        let stats = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(stats.count, count);
+        assert_eq!(stats.sum, 13.5);
+        assert_eq!(stats.min, Some(0.0));
+
+        let missing = searcher.search(&AllQuery, &MissingCollector::new(tags, Count))?;
+        assert_eq!(missing.doc_count, 3);
+        assert_eq!(missing.fruit, 3);
+        let collector = MissingCollector::new(category, TopDocs::with_limit(10));
+        let missing = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(missing.doc_count, 3);
+        assert_eq!(missing.fruit.len(), 3);
+
+        assert!(searcher
+            .search(&AllQuery, &MissingCollector::new(title, Count))
+            .is_err());
+        Ok(())
+    }
+}
@@ -96,6 +96,35 @@ pub use self::count_collector::Count;
 mod histogram_collector;
 pub use histogram_collector::HistogramCollector;
 
+mod interval_histogram_collector;
+pub use self::interval_histogram_collector::{
+    HistogramBounds, IntervalHistogram, IntervalHistogramCollector,
+};
+
+mod numeric_values;
+
+/// Default maximum number of buckets of the histogram collectors.
+const DEFAULT_MAX_BUCKET_COUNT: u64 = 65_535;
+
+mod sub_collector;
+
+mod terms_collector;
+pub use self::terms_collector::{Terms, TermsBucket, TermsCollector, TermsOrder};
+
+mod date_histogram_collector;
+pub use self::date_histogram_collector::{
+    DateHistogramBucket, DateHistogramCollector, DateInterval,
+};
+
+mod cardinality_collector;
+pub use self::cardinality_collector::{CardinalityCollector, HyperLogLog};
+
+mod percentiles_collector;
+pub use self::percentiles_collector::{Percentiles, PercentilesCollector, TDigest};
+
+mod stats_collector;
+pub use self::stats_collector::{Stats, StatsCollector};
+
 mod multi_collector;
 pub use self::multi_collector::MultiCollector;
 
@@ -104,6 +133,22 @@ mod top_collector;
 mod top_score_collector;
 pub use self::top_score_collector::TopDocs;
 
+mod top_docs_by_field;
+pub use self::top_docs_by_field::{
+    EarlyTerminatedTopDocs, EarlyTerminatingTopDocsByField, TopDocsByField,
+};
+
+mod top_docs_by_group;
+pub use self::top_docs_by_group::{DocGroup, GroupKey, TopDocsByGroup};
+
+mod top_docs_sorted_by;
+pub use self::top_docs_sorted_by::{
+    SortBy, SortValue, TopDocsSortedBy, TopDocsSortedBySegmentCollector,
+};
+
+mod top_hits_collector;
+pub use self::top_hits_collector::{TopHit, TopHitsCollector, TopHitsSegmentCollector};
+
 mod custom_score_top_collector;
 pub use self::custom_score_top_collector::{CustomScorer, CustomSegmentScorer};
 
@@ -121,6 +166,17 @@ pub use self::docset_collector::DocSetCollector;
 mod filter_collector_wrapper;
 pub use self::filter_collector_wrapper::FilterCollector;
 
+mod missing_collector;
+pub use self::missing_collector::{MissingBucket, MissingCollector, MissingSegmentCollector};
+
+mod filters_collector;
+pub use self::filters_collector::{FilterBucket, FiltersCollector, FiltersSegmentCollector};
+
+mod time_limited_collector;
+pub use self::time_limited_collector::{
+    TimeLimitedCollector, TimeLimitedFruit, TimeLimitedSegmentCollector,
+};
+
 /// `Fruit` is the type for the result of our collection.
 /// e.g. `usize` for the `Count` collector.
 pub trait Fruit: Send + downcast_rs::Downcast {}
@@ -0,0 +1,100 @@
+use crate::fastfield::{FastValue, MultiValuedFastFieldReader, OptionalFastFieldReader};
+use crate::schema::{Cardinality, Field, FieldType, Type};
+use crate::{DocId, SegmentReader, TantivyError};
+
+enum ValuesReader {
+    Single(OptionalFastFieldReader<u64>),
+    Multi {
+        ff_reader: MultiValuedFastFieldReader<u64>,
+        vals: Vec<u64>,
+    },
+}
+
+/// Reads the values of a u64, i64, f64 or date fast field as `f64`, whatever
+/// its cardinality.
+///
+/// The values of date fields are timestamps in seconds.
+pub(crate) struct NumericValuesReader {
+    values_reader: ValuesReader,
+    value_type: Type,
+    missing: Option<f64>,
+}
+
+impl NumericValuesReader {
+    /// Opens the reader of the values of `field`. The documents without a value are
+    /// given the value `missing`, if any.
+    pub(crate) fn open(
+        reader: &SegmentReader,
+        field: Field,
+        missing: Option<f64>,
+    ) -> crate::Result<NumericValuesReader> {
+        let field_entry = reader.schema().get_field_entry(field);
+        let field_type = field_entry.field_type();
+        let cardinality = match field_type {
+            FieldType::U64(options) | FieldType::I64(options) | FieldType::F64(options) => {
+                options.get_fastfield_cardinality()
+            }
+            FieldType::Date(options) => options.get_fastfield_cardinality(),
+            _ => None,
+        };
+        let fast_fields = reader.fast_fields();
+        let values_reader = match cardinality {
+            Some(Cardinality::SingleValue) => {
+                ValuesReader::Single(fast_fields.typed_optional_fast_field_reader(field)?)
+            }
+            Some(Cardinality::MultiValues) => ValuesReader::Multi {
+                ff_reader: fast_fields.u64s_lenient(field)?,
+                vals: Vec::new(),
+            },
+            None => {
+                return Err(TantivyError::SchemaError(format!(
+                    "Field {:?} is not a u64, i64, f64 or date fast field.",
+                    field_entry.name()
+                )));
+            }
+        };
+        Ok(NumericValuesReader {
+            values_reader,
+            value_type: field_type.value_type(),
+            missing,
+        })
+    }
+
+    /// Calls `visitor` with each value of `doc`, or with the `missing` value if `doc` has
+    /// no value.
+    #[inline]
+    pub(crate) fn for_each_value(&mut self, doc: DocId, mut visitor: impl FnMut(f64)) {
+        let value_type = self.value_type;
+        match &mut self.values_reader {
+            ValuesReader::Single(ff_reader) => {
+                if let Some(val) = ff_reader.get_opt(doc) {
+                    visitor(to_f64(val, value_type));
+                    return;
+                }
+            }
+            ValuesReader::Multi { ff_reader, vals } => {
+                ff_reader.get_vals(doc, vals);
+                if !vals.is_empty() {
+                    for &val in vals.iter() {
+                        visitor(to_f64(val, value_type));
+                    }
+                    return;
+                }
+            }
+        }
+        if let Some(missing) = self.missing {
+            visitor(missing);
+        }
+    }
+}
+
+#[inline]
+fn to_f64(val: u64, value_type: Type) -> f64 {
+    match value_type {
+        Type::U64 => val as f64,
+        Type::I64 => i64::from_u64(val) as f64,
+        Type::F64 => f64::from_u64(val),
+        // dates are read as timestamps in microseconds.
+        _ => i64::from_u64(val) as f64 / 1_000_000.0,
+    }
+}
@@ -0,0 +1,587 @@
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+
+use crate::collector::numeric_values::NumericValuesReader;
+use crate::collector::{Collector, SegmentCollector};
+use crate::schema::Field;
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader};
+
+const DEFAULT_COMPRESSION: f64 = 100.0;
+const DEFAULT_PERCENTS: [f64; 7] = [1.0, 5.0, 25.0, 50.0, 75.0, 95.0, 99.0];
+
+/// Centroid of a `TDigest`: the mean of `weight` values close to each other.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+fn cmp_centroids(left: &Centroid, right: &Centroid) -> Ordering {
+    left.mean
+        .partial_cmp(&right.mean)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| {
+            left.weight
+                .partial_cmp(&right.weight)
+                .unwrap_or(Ordering::Equal)
+        })
+}
+
+/// Weighted average of `x1` and `x2`, kept within `[x1, x2]` despite the rounding errors.
+fn weighted_average(x1: f64, w1: f64, x2: f64, w2: f64) -> f64 {
+    let average = (x1 * w1 + x2 * w2) / (w1 + w2);
+    average.max(x1).min(x2)
+}
+
+/// t-digest sketch, estimating the quantiles of the values it was given.
+///
+/// The values are summarized by at most about `compression` centroids, which are
+/// smaller near the extremes of the distribution. The error is thus relative to the
+/// quantile: with the default compression of 100, the rank of the estimated median is
+/// typically within 0.2% of the exact rank, and the error is lower for the 1st and 99th
+/// percentiles. The minimum and the maximum are exact.
+///
+/// Merging digests gives the same result whatever their order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TDigest {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    /// Values not merged into the centroids yet.
+    buffer: Vec<f64>,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for TDigest {
+    fn default() -> TDigest {
+        TDigest::new(DEFAULT_COMPRESSION)
+    }
+}
+
+impl TDigest {
+    /// Creates an empty digest.
+    ///
+    /// The higher the `compression`, the more accurate and the larger the digest.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `compression` is lower than `1`.
+    pub fn new(compression: f64) -> TDigest {
+        assert!(compression >= 1.0, "The compression must be at least 1.");
+        TDigest {
+            compression,
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Returns the compression of the digest.
+    pub fn compression(&self) -> f64 {
+        self.compression
+    }
+
+    /// Returns the number of values added to the digest.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Adds a value to the digest. `NaN` values are ignored.
+    pub fn add(&mut self, val: f64) {
+        if val.is_nan() {
+            return;
+        }
+        self.count += 1;
+        self.min = self.min.min(val);
+        self.max = self.max.max(val);
+        self.buffer.push(val);
+        if self.buffer.len() as f64 >= 5.0 * self.compression {
+            self.compress(Vec::new());
+        }
+    }
+
+    /// Merges the values summarized by `other` into the digest.
+    pub fn merge(&mut self, other: &TDigest) {
+        self.merge_all(std::iter::once(other));
+    }
+
+    fn merge_all<'a>(&mut self, others: impl Iterator<Item = &'a TDigest>) {
+        let mut centroids = Vec::new();
+        for other in others {
+            self.count += other.count;
+            self.min = self.min.min(other.min);
+            self.max = self.max.max(other.max);
+            centroids.extend_from_slice(&other.centroids);
+            centroids.extend(
+                other
+                    .buffer
+                    .iter()
+                    .map(|&mean| Centroid { mean, weight: 1.0 }),
+            );
+        }
+        self.compress(centroids);
+    }
+
+    /// Merges the buffered values and `centroids` into the centroids of the digest.
+    ///
+    /// The centroids are bounded with the `k1` scale function of Ted Dunning's
+    /// "Computing extremely accurate quantiles using t-digests".
+    fn compress(&mut self, mut centroids: Vec<Centroid>) {
+        centroids.append(&mut self.centroids);
+        centroids.extend(
+            self.buffer
+                .drain(..)
+                .map(|mean| Centroid { mean, weight: 1.0 }),
+        );
+        if centroids.is_empty() {
+            return;
+        }
+        centroids.sort_by(cmp_centroids);
+        let total_weight: f64 = centroids.iter().map(|centroid| centroid.weight).sum();
+        let compression = self.compression;
+        let q_limit = |q: f64| {
+            let k = compression / (2.0 * PI) * (2.0 * q - 1.0).asin() + 1.0;
+            if k >= compression / 4.0 {
+                1.0
+            } else {
+                ((k * 2.0 * PI / compression).sin() + 1.0) / 2.0
+            }
+        };
+        let mut centroids = centroids.into_iter();
+        let mut current = centroids.next().unwrap();
+        let mut weight_so_far = 0.0;
+        let mut limit = q_limit(0.0);
+        for centroid in centroids {
+            let q = (weight_so_far + current.weight + centroid.weight) / total_weight;
+            if q <= limit {
+                let weight = current.weight + centroid.weight;
+                current.mean += (centroid.mean - current.mean) * centroid.weight / weight;
+                current.weight = weight;
+            } else {
+                weight_so_far += current.weight;
+                self.centroids.push(current);
+                limit = q_limit(weight_so_far / total_weight);
+                current = centroid;
+            }
+        }
+        self.centroids.push(current);
+    }
+
+    /// Estimates the quantile `q` of the values, with `q` between `0` and `1`.
+    ///
+    /// Returns `None` if no value was added to the digest.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        if !self.buffer.is_empty() {
+            let mut digest = self.clone();
+            digest.compress(Vec::new());
+            return digest.quantile(q);
+        }
+        let q = q.clamp(0.0, 1.0);
+        let centroids = &self.centroids;
+        let total_weight = self.count as f64;
+        let index = q * total_weight;
+        if index < 1.0 {
+            return Some(self.min);
+        }
+        if index > total_weight - 1.0 {
+            return Some(self.max);
+        }
+        let first = centroids[0];
+        if first.weight > 1.0 && index < first.weight / 2.0 {
+            return Some(
+                self.min + (index - 1.0) / (first.weight / 2.0 - 1.0) * (first.mean - self.min),
+            );
+        }
+        let last = centroids[centroids.len() - 1];
+        if last.weight > 1.0 && total_weight - index <= last.weight / 2.0 {
+            return Some(
+                self.max
+                    - (total_weight - index - 1.0) / (last.weight / 2.0 - 1.0)
+                        * (self.max - last.mean),
+            );
+        }
+        // interpolates between the centers of the centroids, where half of their weight
+        // is reached. The singletons are their exact value.
+        let mut weight_so_far = first.weight / 2.0;
+        for pair in centroids.windows(2) {
+            let (left, right) = (pair[0], pair[1]);
+            let delta_weight = (left.weight + right.weight) / 2.0;
+            if weight_so_far + delta_weight > index {
+                let mut left_unit = 0.0;
+                if left.weight == 1.0 {
+                    if index - weight_so_far < 0.5 {
+                        return Some(left.mean);
+                    }
+                    left_unit = 0.5;
+                }
+                let mut right_unit = 0.0;
+                if right.weight == 1.0 {
+                    if weight_so_far + delta_weight - index <= 0.5 {
+                        return Some(right.mean);
+                    }
+                    right_unit = 0.5;
+                }
+                let z1 = index - weight_so_far - left_unit;
+                let z2 = weight_so_far + delta_weight - index - right_unit;
+                return Some(weighted_average(left.mean, z2, right.mean, z1));
+            }
+            weight_so_far += delta_weight;
+        }
+        Some(last.mean)
+    }
+}
+
+/// Percentiles computed by a [`PercentilesCollector`](./struct.PercentilesCollector.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Percentiles {
+    /// The requested percents, in the requested order, and their estimated percentile.
+    ///
+    /// The percentiles are `None` if no value was collected.
+    pub values: Vec<(f64, Option<f64>)>,
+    /// The digest of the collected values, which can estimate other percentiles.
+    pub digest: TDigest,
+}
+
+impl Percentiles {
+    /// Returns the estimated percentile of `percent`, if it was requested and some values
+    /// were collected.
+    pub fn get(&self, percent: f64) -> Option<f64> {
+        self.values
+            .iter()
+            .find(|(requested_percent, _)| *requested_percent == percent)
+            .and_then(|(_, percentile)| *percentile)
+    }
+}
+
+/// Estimates some percentiles of the values of a u64, i64, f64 or date fast field over
+/// the matching documents, with a [`TDigest`](./struct.TDigest.html).
+///
+/// Documents without a value are ignored, unless a [missing value](#method.with_missing)
+/// is set, and each value of the multi-valued fast fields
+/// is collected. For date fields, the values are timestamps in seconds.
+///
+/// By default, the 1st, 5th, 25th, 50th, 75th, 95th and 99th percentiles are estimated.
+///
+/// ```rust
+/// use tantivy::collector::PercentilesCollector;
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, FAST};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let latency = schema_builder.add_u64_field("latency", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// for val in 1..=1_000u64 {
+///     index_writer.add_document(doc!(latency => val));
+/// }
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let collector = PercentilesCollector::for_field(latency).with_percents(&[50.0, 99.0]);
+/// let percentiles = searcher.search(&AllQuery, &collector)?;
+/// let median = percentiles.get(50.0).unwrap();
+/// assert!((495.0..=505.0).contains(&median));
+/// let p99 = percentiles.get(99.0).unwrap();
+/// assert!((989.0..=991.0).contains(&p99));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PercentilesCollector {
+    field: Field,
+    percents: Vec<f64>,
+    compression: f64,
+    missing: Option<f64>,
+}
+
+impl PercentilesCollector {
+    /// Creates a collector of the default percentiles of the values of `field`.
+    ///
+    /// Searching returns an error if `field` is not a u64, i64, f64 or date fast field.
+    pub fn for_field(field: Field) -> PercentilesCollector {
+        PercentilesCollector {
+            field,
+            percents: DEFAULT_PERCENTS.to_vec(),
+            compression: DEFAULT_COMPRESSION,
+            missing: None,
+        }
+    }
+
+    /// Sets the percents of the estimated percentiles.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if a percent is not between `0` and `100`.
+    pub fn with_percents(mut self, percents: &[f64]) -> PercentilesCollector {
+        assert!(
+            percents
+                .iter()
+                .all(|percent| (0.0..=100.0).contains(percent)),
+            "The percents must be between 0 and 100."
+        );
+        self.percents = percents.to_vec();
+        self
+    }
+
+    /// Sets the compression of the digest.
+    ///
+    /// Defaults to `100`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `compression` is lower than `1`.
+    pub fn with_compression(mut self, compression: f64) -> PercentilesCollector {
+        assert!(compression >= 1.0, "The compression must be at least 1.");
+        self.compression = compression;
+        self
+    }
+
+    /// Counts the documents without a value as if they had the value `missing`.
+    ///
+    /// For date fields, `missing` is a timestamp in seconds.
+    pub fn with_missing(mut self, missing: f64) -> PercentilesCollector {
+        self.missing = Some(missing);
+        self
+    }
+}
+
+pub struct PercentilesSegmentCollector {
+    values_reader: NumericValuesReader,
+    digest: TDigest,
+}
+
+impl Collector for PercentilesCollector {
+    type Fruit = Percentiles;
+    type Child = PercentilesSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<PercentilesSegmentCollector> {
+        Ok(PercentilesSegmentCollector {
+            values_reader: NumericValuesReader::open(reader, self.field, self.missing)?,
+            digest: TDigest::new(self.compression),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_digests: Vec<TDigest>) -> crate::Result<Percentiles> {
+        let mut digest = TDigest::new(self.compression);
+        digest.merge_all(segment_digests.iter());
+        let values = self
+            .percents
+            .iter()
+            .map(|&percent| (percent, digest.quantile(percent / 100.0)))
+            .collect();
+        Ok(Percentiles { values, digest })
+    }
+}
+
+impl SegmentCollector for PercentilesSegmentCollector {
+    type Fruit = TDigest;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        let digest = &mut self.digest;
+        self.values_reader
+            .for_each_value(doc, |val| digest.add(val));
+    }
+
+    fn harvest(self) -> TDigest {
+        self.digest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::{PercentilesCollector, TDigest};
+    use crate::collector::Collector;
+    use crate::query::AllQuery;
+    use crate::schema::{Cardinality, IntOptions, Schema, FAST, TEXT};
+    use crate::{doc, Index};
+
+    /// Returns the fraction of the sorted `vals` lower than or equal to `estimate`.
+    fn rank(sorted_vals: &[f64], estimate: f64) -> f64 {
+        let num_lower = sorted_vals.partition_point(|&val| val <= estimate);
+        num_lower as f64 / sorted_vals.len() as f64
+    }
+
+    fn assert_rank_errors(digest: &TDigest, vals: &mut Vec<f64>) {
+        vals.sort_by(|left, right| left.partial_cmp(right).unwrap());
+        for &(q, max_error) in &[
+            (0.01, 0.002),
+            (0.05, 0.005),
+            (0.25, 0.01),
+            (0.5, 0.01),
+            (0.75, 0.01),
+            (0.95, 0.005),
+            (0.99, 0.002),
+        ] {
+            let estimate = digest.quantile(q).unwrap();
+            let error = (rank(vals, estimate) - q).abs();
+            assert!(error <= max_error, "q={} error={}", q, error);
+        }
+        assert_eq!(digest.quantile(0.0), vals.first().cloned());
+        assert_eq!(digest.quantile(1.0), vals.last().cloned());
+    }
+
+    #[test]
+    fn test_tdigest_accuracy() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut uniform_digest = TDigest::default();
+        let mut uniform_vals = Vec::new();
+        let mut skewed_digest = TDigest::default();
+        let mut skewed_vals = Vec::new();
+        for _ in 0..100_000 {
+            let val: f64 = rng.gen_range(-1_000.0..1_000.0);
+            uniform_digest.add(val);
+            uniform_vals.push(val);
+            // exponential distribution, like latencies.
+            let val = -10.0 * (1.0 - rng.gen::<f64>()).ln();
+            skewed_digest.add(val);
+            skewed_vals.push(val);
+        }
+        assert_eq!(uniform_digest.count(), 100_000);
+        assert_rank_errors(&uniform_digest, &mut uniform_vals);
+        assert_rank_errors(&skewed_digest, &mut skewed_vals);
+        assert!(uniform_digest.centroids.len() <= 100);
+    }
+
+    #[test]
+    fn test_tdigest_small() {
+        let mut digest = TDigest::new(50.0);
+        assert_eq!(digest.quantile(0.5), None);
+        digest.add(3.0);
+        assert_eq!(digest.quantile(0.2), Some(3.0));
+        assert_eq!(digest.quantile(0.9), Some(3.0));
+        for val in &[1.0, 2.0, 4.0, 5.0, f64::NAN] {
+            digest.add(*val);
+        }
+        assert_eq!(digest.count(), 5);
+        assert_eq!(digest.quantile(0.5), Some(3.0));
+        assert_eq!(digest.quantile(0.0), Some(1.0));
+        assert_eq!(digest.quantile(1.0), Some(5.0));
+    }
+
+    #[test]
+    fn test_tdigest_merge_order() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let digests: Vec<TDigest> = (0..5)
+            .map(|i| {
+                let mut digest = TDigest::default();
+                for _ in 0..(1_000 * (i + 1)) {
+                    digest.add(rng.gen_range(0.0..(i as f64 + 1.0) * 50.0));
+                }
+                digest
+            })
+            .collect();
+        let collector = PercentilesCollector::for_field(crate::schema::Field::from_field_id(0));
+        let percentiles = collector.merge_fruits(digests.clone()).unwrap();
+        let reversed_digests = digests.into_iter().rev().collect();
+        let reversed_percentiles = collector.merge_fruits(reversed_digests).unwrap();
+        assert_eq!(percentiles, reversed_percentiles);
+        assert_eq!(percentiles.digest.count(), 15_000);
+
+        let mut left = TDigest::default();
+        left.add(1.0);
+        left.add(2.0);
+        let mut right = TDigest::default();
+        right.add(2.0);
+        right.add(10.0);
+        let mut left_right = left.clone();
+        left_right.merge(&right);
+        let mut right_left = right.clone();
+        right_left.merge(&left);
+        assert_eq!(left_right, right_left);
+        assert_eq!(left_right.count(), 4);
+    }
+
+    #[test]
+    fn test_percentiles_collector() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let val = schema_builder.add_i64_field("val", FAST);
+        let vals = schema_builder.add_f64_field(
+            "vals",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let mut all_vals = Vec::new();
+        for i in 0..2_000i64 {
+            let shuffled = (i * 7_919) % 2_000 - 1_000;
+            index_writer.add_document(
+                doc!(val => shuffled, vals => shuffled as f64, vals => 0.5 * shuffled as f64),
+            );
+            all_vals.push(shuffled as f64);
+            all_vals.push(0.5 * shuffled as f64);
+            if i % 500 == 499 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.add_document(doc!(title => "no value"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 5);
+
+        let percentiles = searcher.search(&AllQuery, &PercentilesCollector::for_field(val))?;
+        let percents: Vec<f64> = percentiles
+            .values
+            .iter()
+            .map(|(percent, _)| *percent)
+            .collect();
+        assert_eq!(percents, vec![1.0, 5.0, 25.0, 50.0, 75.0, 95.0, 99.0]);
+        assert_eq!(percentiles.digest.count(), 2_000);
+        let median = percentiles.get(50.0).unwrap();
+        assert!((-10.0..=10.0).contains(&median), "median={}", median);
+        let p99 = percentiles.get(99.0).unwrap();
+        assert!((976.0..=984.0).contains(&p99), "p99={}", p99);
+        assert_eq!(percentiles.get(42.0), None);
+
+        let collector = PercentilesCollector::for_field(vals)
+            .with_percents(&[0.0, 90.0, 100.0])
+            .with_compression(200.0);
+        let percentiles = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(percentiles.digest.compression(), 200.0);
+        assert_eq!(percentiles.get(0.0), Some(-1_000.0));
+        assert_eq!(percentiles.get(100.0), Some(999.0));
+        assert_rank_errors(&percentiles.digest, &mut all_vals);
+
+        assert!(searcher
+            .search(&AllQuery, &PercentilesCollector::for_field(title))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_percentiles_collector_empty() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let val = schema_builder.add_u64_field("val", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!());
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let percentiles = searcher.search(&AllQuery, &PercentilesCollector::for_field(val))?;
+        assert_eq!(percentiles.values.len(), 7);
+        assert!(percentiles
+            .values
+            .iter()
+            .all(|(_, percentile)| percentile.is_none()));
+        Ok(())
+    }
+}
@@ -0,0 +1,376 @@
+use crate::collector::numeric_values::NumericValuesReader;
+use crate::collector::{Collector, SegmentCollector};
+use crate::schema::Field;
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader};
+
+/// Statistics over the values of a numeric fast field, computed by a
+/// [`StatsCollector`](./struct.StatsCollector.html).
+///
+/// The values are converted to `f64`. The values of date fields are timestamps in seconds,
+/// with a fractional part for the sub-second precisions.
+///
+/// If no value was collected, `count`, `sum` and `sum_of_squares` are `0`, and the other
+/// statistics are `None`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Number of collected values.
+    pub count: u64,
+    /// Sum of the collected values.
+    pub sum: f64,
+    /// Smallest collected value.
+    pub min: Option<f64>,
+    /// Greatest collected value.
+    pub max: Option<f64>,
+    /// Mean of the collected values.
+    pub mean: Option<f64>,
+    /// Population variance of the collected values.
+    ///
+    /// It is updated with Welford's algorithm, and merged across segments with the
+    /// parallel algorithm of Chan et al., so that it stays accurate for values with a
+    /// large mean and a small variance.
+    pub variance: Option<f64>,
+    /// Sum of the squares of the collected values.
+    pub sum_of_squares: f64,
+}
+
+impl Stats {
+    /// Returns the population standard deviation of the collected values.
+    pub fn std_deviation(&self) -> Option<f64> {
+        self.variance.map(f64::sqrt)
+    }
+
+    /// Returns the bounds `mean - sigma * std_deviation` and
+    /// `mean + sigma * std_deviation`, out of which the values may be considered as
+    /// outliers.
+    pub fn std_deviation_bounds(&self, sigma: f64) -> Option<(f64, f64)> {
+        let mean = self.mean?;
+        let std_deviation = self.std_deviation()?;
+        Some((mean - sigma * std_deviation, mean + sigma * std_deviation))
+    }
+
+    /// Adds a value to the statistics.
+    ///
+    /// The mean and variance are updated with Welford's algorithm.
+    fn add(&mut self, val: f64) {
+        self.count += 1;
+        self.sum += val;
+        self.sum_of_squares += val * val;
+        self.min = Some(self.min.map_or(val, |min| min.min(val)));
+        self.max = Some(self.max.map_or(val, |max| max.max(val)));
+        let count = self.count as f64;
+        let mean = self.mean.unwrap_or(0.0);
+        let m2 = self.variance.unwrap_or(0.0) * (count - 1.0);
+        let delta = val - mean;
+        let new_mean = mean + delta / count;
+        self.mean = Some(new_mean);
+        self.variance = Some((m2 + delta * (val - new_mean)) / count);
+    }
+
+    /// Merges the statistics of another set of values.
+    fn merge(&mut self, other: &Stats) {
+        let (mean, other_mean) = match (self.mean, other.mean) {
+            (_, None) => return,
+            (None, _) => {
+                *self = other.clone();
+                return;
+            }
+            (Some(mean), Some(other_mean)) => (mean, other_mean),
+        };
+        let (count, other_count) = (self.count as f64, other.count as f64);
+        let total_count = count + other_count;
+        let m2 = self.variance.unwrap_or(0.0) * count
+            + other.variance.unwrap_or(0.0) * other_count
+            + (other_mean - mean).powi(2) * count * other_count / total_count;
+        self.count += other.count;
+        self.sum += other.sum;
+        self.sum_of_squares += other.sum_of_squares;
+        self.min = self
+            .min
+            .zip(other.min)
+            .map(|(min, other_min)| min.min(other_min));
+        self.max = self
+            .max
+            .zip(other.max)
+            .map(|(max, other_max)| max.max(other_max));
+        self.mean = Some(mean + (other_mean - mean) * other_count / total_count);
+        self.variance = Some(m2 / total_count);
+    }
+}
+
+/// Computes the [`Stats`](./struct.Stats.html) of the values of a u64, i64, f64 or date
+/// fast field over the matching documents.
+///
+/// Documents without a value are ignored, unless a [missing value](#method.with_missing)
+/// is set. For multi-valued fast fields, the statistics
+/// are computed over all the values of the documents: a document with three values
+/// counts for three values.
+///
+/// ```rust
+/// use tantivy::collector::{Count, MultiCollector, StatsCollector};
+/// use tantivy::query::QueryParser;
+/// use tantivy::schema::{Schema, FAST, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let price = schema_builder.add_u64_field("price", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "red shoes", price => 30u64));
+/// index_writer.add_document(doc!(title => "blue shoes", price => 50u64));
+/// index_writer.add_document(doc!(title => "red socks", price => 5u64));
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let query = QueryParser::for_index(&index, vec![title]).parse_query("shoes")?;
+/// let mut collectors = MultiCollector::new();
+/// let count_handle = collectors.add_collector(Count);
+/// let stats_handle = collectors.add_collector(StatsCollector::for_field(price));
+/// let mut multi_fruit = searcher.search(&query, &collectors)?;
+/// assert_eq!(count_handle.extract(&mut multi_fruit), 2);
+/// let stats = stats_handle.extract(&mut multi_fruit);
+/// assert_eq!(stats.min, Some(30.0));
+/// assert_eq!(stats.max, Some(50.0));
+/// assert_eq!(stats.mean, Some(40.0));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct StatsCollector {
+    field: Field,
+    missing: Option<f64>,
+}
+
+impl StatsCollector {
+    /// Creates a collector of the statistics of the values of `field`.
+    ///
+    /// Searching returns an error if `field` is not a u64, i64, f64 or date fast field.
+    pub fn for_field(field: Field) -> StatsCollector {
+        StatsCollector {
+            field,
+            missing: None,
+        }
+    }
+
+    /// Counts the documents without a value as if they had the value `missing`.
+    ///
+    /// For date fields, `missing` is a timestamp in seconds.
+    pub fn with_missing(mut self, missing: f64) -> StatsCollector {
+        self.missing = Some(missing);
+        self
+    }
+}
+
+pub struct StatsSegmentCollector {
+    values_reader: NumericValuesReader,
+    stats: Stats,
+}
+
+impl Collector for StatsCollector {
+    type Fruit = Stats;
+    type Child = StatsSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<StatsSegmentCollector> {
+        Ok(StatsSegmentCollector {
+            values_reader: NumericValuesReader::open(reader, self.field, self.missing)?,
+            stats: Stats::default(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_stats: Vec<Stats>) -> crate::Result<Stats> {
+        let mut stats = Stats::default();
+        for segment_stats in &segment_stats {
+            stats.merge(segment_stats);
+        }
+        Ok(stats)
+    }
+}
+
+impl SegmentCollector for StatsSegmentCollector {
+    type Fruit = Stats;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        let stats = &mut self.stats;
+        self.values_reader.for_each_value(doc, |val| stats.add(val));
+    }
+
+    fn harvest(self) -> Stats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Stats, StatsCollector};
+    use crate::chrono::{TimeZone, Utc};
+    use crate::collector::{Count, MultiCollector};
+    use crate::query::{AllQuery, TermQuery};
+    use crate::schema::{
+        Cardinality, IndexRecordOption, IntOptions, Schema, FAST, INDEXED, STRING,
+    };
+    use crate::{doc, Index, Term};
+
+    fn assert_stats_eq(stats: &Stats, count: u64, values: &[f64]) {
+        assert_eq!(stats.count, count);
+        let sum: f64 = values.iter().sum();
+        let mean = sum / values.len() as f64;
+        let variance =
+            values.iter().map(|val| (val - mean).powi(2)).sum::<f64>() / values.len() as f64;
+        assert!((stats.sum - sum).abs() < 1e-9);
+        let sum_of_squares: f64 = values.iter().map(|val| val * val).sum();
+        assert!((stats.sum_of_squares - sum_of_squares).abs() < 1e-9);
+        assert!((stats.mean.unwrap() - mean).abs() < 1e-9);
+        assert!((stats.variance.unwrap() - variance).abs() < 1e-9);
+        assert_eq!(
+            stats.min,
+            values.iter().cloned().reduce(f64::min),
+            "wrong min"
+        );
+        assert_eq!(
+            stats.max,
+            values.iter().cloned().reduce(f64::max),
+            "wrong max"
+        );
+    }
+
+    #[test]
+    fn test_stats_collector() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let category = schema_builder.add_text_field("category", STRING);
+        let val_u64 = schema_builder.add_u64_field("val_u64", FAST | INDEXED);
+        let val_i64 = schema_builder.add_i64_field("val_i64", FAST);
+        let val_f64 = schema_builder.add_f64_field("val_f64", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer
+            .add_document(doc!(category => "a", val_u64 => 3u64, val_i64 => -3i64, val_f64 => 0.5));
+        index_writer.add_document(doc!(category => "a", val_u64 => 8u64));
+        index_writer.add_document(
+            doc!(category => "b", val_u64 => 100u64, val_i64 => 10i64, val_f64 => -1.5),
+        );
+        index_writer.commit()?;
+        index_writer
+            .add_document(doc!(category => "a", val_u64 => 1u64, val_i64 => 7i64, val_f64 => 2.25));
+        index_writer.add_document(doc!(category => "b", val_u64 => 50u64));
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_u64(val_u64, 100u64));
+        index_writer.commit()?;
+
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let stats = searcher.search(&AllQuery, &StatsCollector::for_field(val_u64))?;
+        assert_stats_eq(&stats, 4, &[3.0, 8.0, 1.0, 50.0]);
+        let stats = searcher.search(&AllQuery, &StatsCollector::for_field(val_i64))?;
+        assert_stats_eq(&stats, 2, &[-3.0, 7.0]);
+        let stats = searcher.search(&AllQuery, &StatsCollector::for_field(val_f64))?;
+        assert_stats_eq(&stats, 2, &[0.5, 2.25]);
+
+        let query = TermQuery::new(
+            Term::from_field_text(category, "a"),
+            IndexRecordOption::Basic,
+        );
+        let mut collectors = MultiCollector::new();
+        let count_handle = collectors.add_collector(Count);
+        let stats_handle = collectors.add_collector(StatsCollector::for_field(val_u64));
+        let mut multi_fruit = searcher.search(&query, &collectors)?;
+        assert_eq!(count_handle.extract(&mut multi_fruit), 3);
+        assert_stats_eq(&stats_handle.extract(&mut multi_fruit), 3, &[3.0, 8.0, 1.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats_numeric_stability() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let val = schema_builder.add_f64_field("val", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let offset = 1e12;
+        let deltas: Vec<f64> = (0..3_000).map(|i| f64::from(i % 7) * 0.5).collect();
+        for (i, delta) in deltas.iter().enumerate() {
+            index_writer.add_document(doc!(val => offset + delta));
+            if i % 1_000 == 999 {
+                index_writer.commit()?;
+            }
+        }
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let stats = searcher.search(&AllQuery, &StatsCollector::for_field(val))?;
+
+        // the naive formula `sum_of_squares / count - mean^2` cancels out every digit of
+        // the variance at this offset.
+        let mean_delta = deltas.iter().sum::<f64>() / deltas.len() as f64;
+        let variance = deltas
+            .iter()
+            .map(|delta| (delta - mean_delta).powi(2))
+            .sum::<f64>()
+            / deltas.len() as f64;
+        assert!((stats.mean.unwrap() - (offset + mean_delta)).abs() < 1e-3);
+        assert!((stats.variance.unwrap() - variance).abs() < 1e-4 * variance);
+        let std_deviation = stats.std_deviation().unwrap();
+        assert!((std_deviation - variance.sqrt()).abs() < 1e-4);
+        let (lower_bound, upper_bound) = stats.std_deviation_bounds(2.0).unwrap();
+        assert!((upper_bound - lower_bound - 4.0 * std_deviation).abs() < 1e-3);
+        assert!(
+            (stats.sum_of_squares / (offset * offset * deltas.len() as f64) - 1.0).abs() < 1e-9
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats_collector_empty() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let category = schema_builder.add_text_field("category", STRING);
+        let val = schema_builder.add_u64_field("val", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(category => "a", val => 3u64));
+        index_writer.add_document(doc!(category => "b"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(category, "b"),
+            IndexRecordOption::Basic,
+        );
+        let stats = searcher.search(&query, &StatsCollector::for_field(val))?;
+        assert_eq!(stats, Stats::default());
+        assert_eq!(stats.count, 0);
+        assert_eq!(stats.min, None);
+        assert_eq!(stats.mean, None);
+        assert_eq!(stats.std_deviation_bounds(2.0), None);
+        assert!(searcher
+            .search(&AllQuery, &StatsCollector::for_field(category))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats_collector_multivalued_and_date() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let vals = schema_builder.add_i64_field(
+            "vals",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let date = schema_builder.add_date_field("date", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(vals => 1i64, vals => -4i64, date => Utc.timestamp(10, 0)));
+        index_writer.add_document(doc!(date => Utc.timestamp(-20, 0)));
+        index_writer.add_document(doc!(vals => 6i64));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let stats = searcher.search(&AllQuery, &StatsCollector::for_field(vals))?;
+        assert_stats_eq(&stats, 3, &[1.0, -4.0, 6.0]);
+        let stats = searcher.search(&AllQuery, &StatsCollector::for_field(date))?;
+        assert_stats_eq(&stats, 2, &[10.0, -20.0]);
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use crate::collector::Collector;
+use crate::{SegmentOrdinal, SegmentReader};
+
+/// Creates the segment collectors of a sub collector, as the buckets of a bucketing
+/// collector are discovered during the collection of a segment.
+pub(crate) struct SubCollectorFactory<TCollector> {
+    collector: Arc<TCollector>,
+    segment_local_id: SegmentOrdinal,
+    reader: SegmentReader,
+}
+
+impl<TCollector: Collector> SubCollectorFactory<TCollector> {
+    pub fn new(
+        collector: Arc<TCollector>,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> SubCollectorFactory<TCollector> {
+        SubCollectorFactory {
+            collector,
+            segment_local_id,
+            reader: reader.clone(),
+        }
+    }
+
+    /// Creates the segment collector of a new bucket.
+    pub fn for_bucket(&self) -> crate::Result<TCollector::Child> {
+        self.collector
+            .for_segment(self.segment_local_id, &self.reader)
+    }
+}
@@ -0,0 +1,561 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::collector::sub_collector::SubCollectorFactory;
+use crate::collector::{Collector, Count, SegmentCollector, DEFAULT_MAX_BUCKET_COUNT};
+use crate::error::DataCorruption;
+use crate::fastfield::FacetReader;
+use crate::schema::{Facet, Field, FieldType};
+use crate::termdict::TermOrdinal;
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// Order of the buckets of a [`TermsCollector`](./struct.TermsCollector.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TermsOrder {
+    /// By decreasing number of documents, the default.
+    CountDesc,
+    /// By increasing number of documents.
+    CountAsc,
+    /// By increasing term.
+    KeyAsc,
+    /// By decreasing term.
+    KeyDesc,
+}
+
+impl TermsOrder {
+    /// Compares two buckets, given their number of documents and their terms.
+    ///
+    /// The buckets having the same number of documents are sorted by increasing term.
+    fn compare<TKey: Ord>(self, left: (u64, TKey), right: (u64, TKey)) -> Ordering {
+        match self {
+            TermsOrder::CountDesc => right.0.cmp(&left.0).then(left.1.cmp(&right.1)),
+            TermsOrder::CountAsc => left.0.cmp(&right.0).then(left.1.cmp(&right.1)),
+            TermsOrder::KeyAsc => left.1.cmp(&right.1),
+            TermsOrder::KeyDesc => right.1.cmp(&left.1),
+        }
+    }
+}
+
+/// Bucket computed by a [`TermsCollector`](./struct.TermsCollector.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TermsBucket<TFruit> {
+    /// Term of the bucket, i.e. the path of a facet.
+    pub key: String,
+    /// Number of documents having the term.
+    pub doc_count: u64,
+    /// Fruit of the inner collector over the documents having the term.
+    pub fruit: TFruit,
+}
+
+/// Result of a [`TermsCollector`](./struct.TermsCollector.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Terms<TFruit> {
+    /// The buckets of the most frequent terms, or of the first terms, depending
+    /// on the order.
+    pub buckets: Vec<TermsBucket<TFruit>>,
+    /// Number of documents counted in the buckets of the other terms. A document
+    /// having several terms is counted once per term.
+    pub sum_other_doc_count: u64,
+}
+
+/// Counts the documents per term of a facet field, and runs a collector over the
+/// documents of the buckets of the most frequent terms.
+///
+/// The facet fields are the only fields storing the ordinals of their terms in a fast
+/// field, so that the terms of a document are counted without reading their bytes.
+/// The terms are the full paths of the facets of the documents, e.g. `/country/fr`
+/// rather than one of its ancestors.
+///
+/// Each segment counts its documents per term ordinal, and only keeps its
+/// [`shard_size`](#method.with_shard_size) best candidates, whose ordinals are then
+/// resolved into their terms in one pass over the dictionary. The candidates of the
+/// segments are merged by term, which makes the counts approximate: a term which is not
+/// a candidate of a segment is not counted in this segment. The bigger the shard size,
+/// the more accurate the counts.
+///
+/// ```rust
+/// use tantivy::collector::{StatsCollector, TermsCollector};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Facet, Schema, FAST, INDEXED};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let country = schema_builder.add_facet_field("country", INDEXED);
+/// let price = schema_builder.add_u64_field("price", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// for (country_path, price_val) in [("/fr", 10u64), ("/fr", 20u64), ("/de", 5u64), ("/it", 8u64)] {
+///     index_writer.add_document(doc!(
+///         country => Facet::from(country_path),
+///         price => price_val,
+///     ));
+/// }
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let collector = TermsCollector::new(country, StatsCollector::for_field(price)).with_size(2);
+/// let terms = searcher.search(&AllQuery, &collector)?;
+/// assert_eq!(terms.buckets[0].key, "/fr");
+/// assert_eq!(terms.buckets[0].doc_count, 2);
+/// assert_eq!(terms.buckets[0].fruit.mean, Some(15.0));
+/// assert_eq!(terms.buckets[1].key, "/de");
+/// assert_eq!(terms.sum_other_doc_count, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct TermsCollector<TCollector> {
+    field: Field,
+    collector: Arc<TCollector>,
+    size: usize,
+    shard_size: Option<usize>,
+    order: TermsOrder,
+    min_doc_count: u64,
+    max_bucket_count: u64,
+}
+
+impl TermsCollector<Count> {
+    /// Creates a collector counting the documents per term of the facet field `field`.
+    pub fn for_field(field: Field) -> TermsCollector<Count> {
+        TermsCollector::new(field, Count)
+    }
+}
+
+impl<TCollector: Collector + 'static> TermsCollector<TCollector> {
+    /// Creates a collector counting the documents per term of the facet field `field`,
+    /// and running `collector` in each of the returned buckets.
+    ///
+    /// By default, the ten most frequent terms are returned.
+    pub fn new(field: Field, collector: TCollector) -> TermsCollector<TCollector> {
+        TermsCollector {
+            field,
+            collector: Arc::new(collector),
+            size: 10,
+            shard_size: None,
+            order: TermsOrder::CountDesc,
+            min_doc_count: 1,
+            max_bucket_count: DEFAULT_MAX_BUCKET_COUNT,
+        }
+    }
+
+    /// Sets the number of buckets returned.
+    pub fn with_size(mut self, size: usize) -> TermsCollector<TCollector> {
+        self.size = size;
+        self
+    }
+
+    /// Sets the number of candidate buckets kept by each segment.
+    ///
+    /// Defaults to `size * 1.5 + 10`, and cannot be less than `size`.
+    pub fn with_shard_size(mut self, shard_size: usize) -> TermsCollector<TCollector> {
+        self.shard_size = Some(shard_size);
+        self
+    }
+
+    /// Sets the order of the buckets, which also decides which buckets are returned.
+    ///
+    /// Ordering by increasing number of documents makes the counts much less accurate
+    /// than the other orders, as the least frequent terms of the segments are often
+    /// frequent in other segments.
+    pub fn order(mut self, order: TermsOrder) -> TermsCollector<TCollector> {
+        self.order = order;
+        self
+    }
+
+    /// Only returns the buckets of at least `min_doc_count` documents.
+    ///
+    /// Defaults to `1`.
+    pub fn min_doc_count(mut self, min_doc_count: u64) -> TermsCollector<TCollector> {
+        self.min_doc_count = min_doc_count;
+        self
+    }
+
+    /// Sets the maximum number of distinct terms counted by a segment.
+    ///
+    /// Searching returns a `TantivyError::BucketLimitExceeded` error if the documents of
+    /// a segment have more terms. The collection of a segment stops creating buckets
+    /// once the limit is reached.
+    ///
+    /// Defaults to `65_535`.
+    pub fn with_max_bucket_count(mut self, max_bucket_count: u64) -> TermsCollector<TCollector> {
+        self.max_bucket_count = max_bucket_count;
+        self
+    }
+
+    fn shard_size(&self) -> usize {
+        let default_shard_size = self.size.saturating_add(self.size / 2).saturating_add(10);
+        self.shard_size.unwrap_or(default_shard_size).max(self.size)
+    }
+}
+
+struct SegmentTermsBucket<TSegmentCollector> {
+    doc_count: u64,
+    segment_collector: TSegmentCollector,
+}
+
+/// The candidate buckets of a segment, keyed by the bytes of their term.
+pub struct SegmentTerms<TFruit> {
+    buckets: Vec<(Vec<u8>, u64, TFruit)>,
+    /// Number of documents of all of the buckets of the segment, including the buckets
+    /// which are not candidates.
+    sum_doc_count: u64,
+    error: Option<TantivyError>,
+}
+
+/// Segment collector associated to `TermsCollector`.
+pub struct TermsSegmentCollector<TCollector: Collector> {
+    facet_reader: FacetReader,
+    doc_facet_ords: Vec<TermOrdinal>,
+    buckets: HashMap<TermOrdinal, SegmentTermsBucket<TCollector::Child>>,
+    sub_collector_factory: SubCollectorFactory<TCollector>,
+    shard_size: usize,
+    order: TermsOrder,
+    max_bucket_count: u64,
+    error: Option<TantivyError>,
+}
+
+impl<TCollector: Collector + 'static> Collector for TermsCollector<TCollector> {
+    type Fruit = Terms<TCollector::Fruit>;
+    type Child = TermsSegmentCollector<TCollector>;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<TermsSegmentCollector<TCollector>> {
+        let field_entry = reader.schema().get_field_entry(self.field);
+        if !matches!(field_entry.field_type(), FieldType::HierarchicalFacet(_)) {
+            return Err(TantivyError::SchemaError(format!(
+                "Field {:?} is not a facet field.",
+                field_entry.name()
+            )));
+        }
+        Ok(TermsSegmentCollector {
+            facet_reader: reader.facet_reader(self.field)?,
+            doc_facet_ords: Vec::new(),
+            buckets: HashMap::new(),
+            sub_collector_factory: SubCollectorFactory::new(
+                Arc::clone(&self.collector),
+                segment_local_id,
+                reader,
+            ),
+            shard_size: self.shard_size(),
+            order: self.order,
+            max_bucket_count: self.max_bucket_count,
+            error: None,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.collector.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<SegmentTerms<<TCollector::Child as SegmentCollector>::Fruit>>,
+    ) -> crate::Result<Terms<TCollector::Fruit>> {
+        let mut merged_buckets: HashMap<Vec<u8>, (u64, Vec<_>)> = HashMap::new();
+        let mut sum_doc_count = 0;
+        for segment_fruit in segment_fruits {
+            if let Some(error) = segment_fruit.error {
+                return Err(error);
+            }
+            sum_doc_count += segment_fruit.sum_doc_count;
+            for (term, doc_count, child_fruit) in segment_fruit.buckets {
+                let (merged_doc_count, child_fruits) = merged_buckets.entry(term).or_default();
+                *merged_doc_count += doc_count;
+                child_fruits.push(child_fruit);
+            }
+        }
+        let mut merged_buckets: Vec<(Vec<u8>, (u64, Vec<_>))> = merged_buckets
+            .into_iter()
+            .filter(|(_, (doc_count, _))| *doc_count >= self.min_doc_count)
+            .collect();
+        merged_buckets.sort_unstable_by(
+            |(left_term, (left_count, _)), (right_term, (right_count, _))| {
+                self.order
+                    .compare((*left_count, left_term), (*right_count, right_term))
+            },
+        );
+        merged_buckets.truncate(self.size);
+        let mut buckets = Vec::with_capacity(merged_buckets.len());
+        for (term, (doc_count, child_fruits)) in merged_buckets {
+            let facet = Facet::from_encoded(term)
+                .map_err(|utf8_err| DataCorruption::comment_only(utf8_err.to_string()))?;
+            buckets.push(TermsBucket {
+                key: facet.to_path_string(),
+                doc_count,
+                fruit: self.collector.merge_fruits(child_fruits)?,
+            });
+        }
+        let bucket_doc_count: u64 = buckets.iter().map(|bucket| bucket.doc_count).sum();
+        Ok(Terms {
+            buckets,
+            sum_other_doc_count: sum_doc_count.saturating_sub(bucket_doc_count),
+        })
+    }
+}
+
+impl<TCollector: Collector + 'static> SegmentCollector for TermsSegmentCollector<TCollector> {
+    type Fruit = SegmentTerms<<TCollector::Child as SegmentCollector>::Fruit>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        if self.error.is_some() {
+            return;
+        }
+        self.facet_reader.facet_ords(doc, &mut self.doc_facet_ords);
+        self.doc_facet_ords.sort_unstable();
+        self.doc_facet_ords.dedup();
+        for &facet_ord in &self.doc_facet_ords {
+            if !self.buckets.contains_key(&facet_ord) {
+                if self.buckets.len() as u64 >= self.max_bucket_count {
+                    self.error = Some(TantivyError::BucketLimitExceeded(self.max_bucket_count));
+                    return;
+                }
+                match self.sub_collector_factory.for_bucket() {
+                    Ok(segment_collector) => {
+                        self.buckets.insert(
+                            facet_ord,
+                            SegmentTermsBucket {
+                                doc_count: 0,
+                                segment_collector,
+                            },
+                        );
+                    }
+                    Err(error) => {
+                        self.error = Some(error);
+                        return;
+                    }
+                }
+            }
+            let bucket = self.buckets.get_mut(&facet_ord).unwrap();
+            bucket.doc_count += 1;
+            bucket.segment_collector.collect(doc, score);
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        let sum_doc_count = self.buckets.values().map(|bucket| bucket.doc_count).sum();
+        if let Some(error) = self.error {
+            return SegmentTerms {
+                buckets: Vec::new(),
+                sum_doc_count,
+                error: Some(error),
+            };
+        }
+        // The ordinals are sorted like their terms.
+        let mut buckets: Vec<(TermOrdinal, SegmentTermsBucket<_>)> =
+            self.buckets.into_iter().collect();
+        let order = self.order;
+        buckets.sort_unstable_by(|(left_ord, left_bucket), (right_ord, right_bucket)| {
+            order.compare(
+                (left_bucket.doc_count, *left_ord),
+                (right_bucket.doc_count, *right_ord),
+            )
+        });
+        buckets.truncate(self.shard_size);
+        buckets.sort_unstable_by_key(|(facet_ord, _)| *facet_ord);
+        let facet_ords: Vec<TermOrdinal> =
+            buckets.iter().map(|(facet_ord, _)| *facet_ord).collect();
+        let mut terms = Vec::with_capacity(facet_ords.len());
+        let all_found = self
+            .facet_reader
+            .facet_dict()
+            .sorted_ords_to_term_cb(&facet_ords, |_, term| terms.push(term.to_vec()));
+        let error = match all_found {
+            Ok(true) => None,
+            Ok(false) => Some(TantivyError::InvalidArgument(format!(
+                "Some of the facet ordinals {:?} are out of bounds.",
+                facet_ords
+            ))),
+            Err(io_error) => Some(io_error.into()),
+        };
+        if error.is_some() {
+            return SegmentTerms {
+                buckets: Vec::new(),
+                sum_doc_count,
+                error,
+            };
+        }
+        let buckets = terms
+            .into_iter()
+            .zip(buckets)
+            .map(|(term, (_, bucket))| (term, bucket.doc_count, bucket.segment_collector.harvest()))
+            .collect();
+        SegmentTerms {
+            buckets,
+            sum_doc_count,
+            error: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TermsCollector, TermsOrder};
+    use crate::collector::{Count, StatsCollector};
+    use crate::query::{AllQuery, TermQuery};
+    use crate::schema::{Facet, IndexRecordOption, Schema, FAST, INDEXED, STRING};
+    use crate::{doc, Index, TantivyError, Term};
+
+    fn keys_and_counts<TFruit>(terms: &super::Terms<TFruit>) -> Vec<(&str, u64)> {
+        terms
+            .buckets
+            .iter()
+            .map(|bucket| (bucket.key.as_str(), bucket.doc_count))
+            .collect()
+    }
+
+    #[test]
+    fn test_terms_collector() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let country = schema_builder.add_facet_field("country", INDEXED);
+        let price = schema_builder.add_u64_field("price", FAST | INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        // the ordinals of the terms differ between the segments.
+        for (country_path, price_val) in [("/fr", 10u64), ("/de", 20), ("/fr", 30), ("/it", 1)] {
+            index_writer.add_document(doc!(
+                country => Facet::from(country_path),
+                price => price_val,
+            ));
+        }
+        index_writer.commit()?;
+        for (country_path, price_val) in [("/es", 5u64), ("/de", 6), ("/de", 7), ("/fr", 8)] {
+            index_writer.add_document(doc!(
+                country => Facet::from(country_path),
+                price => price_val,
+            ));
+        }
+        index_writer.add_document(doc!(
+            country => Facet::from("/be"),
+            country => Facet::from("/nl"),
+            country => Facet::from("/nl"),
+            price => 1u64,
+        ));
+        index_writer.add_document(doc!(price => 2u64));
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_u64(price, 1u64));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+
+        let terms = searcher.search(&AllQuery, &TermsCollector::for_field(country))?;
+        assert_eq!(
+            keys_and_counts(&terms),
+            vec![("/de", 3), ("/fr", 3), ("/es", 1)]
+        );
+        assert_eq!(terms.buckets[0].fruit, 3);
+        assert_eq!(terms.sum_other_doc_count, 0);
+
+        let collector = TermsCollector::new(country, StatsCollector::for_field(price)).with_size(1);
+        let terms = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(keys_and_counts(&terms), vec![("/de", 3)]);
+        assert_eq!(terms.buckets[0].fruit.sum, 33.0);
+        assert_eq!(terms.sum_other_doc_count, 4);
+
+        let collector = TermsCollector::for_field(country).order(TermsOrder::KeyDesc);
+        let terms = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_and_counts(&terms),
+            vec![("/fr", 3), ("/es", 1), ("/de", 3)]
+        );
+        let collector = TermsCollector::for_field(country).order(TermsOrder::CountAsc);
+        let terms = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            keys_and_counts(&terms),
+            vec![("/es", 1), ("/de", 3), ("/fr", 3)]
+        );
+        let collector = TermsCollector::for_field(country)
+            .order(TermsOrder::KeyAsc)
+            .min_doc_count(2);
+        let terms = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(keys_and_counts(&terms), vec![("/de", 3), ("/fr", 3)]);
+        assert_eq!(terms.sum_other_doc_count, 1);
+
+        let query = TermQuery::new(
+            Term::from_facet(country, &Facet::from("/fr")),
+            IndexRecordOption::Basic,
+        );
+        let terms = searcher.search(&query, &TermsCollector::for_field(country))?;
+        assert_eq!(keys_and_counts(&terms), vec![("/fr", 3)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_terms_collector_shard_size() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let tag = schema_builder.add_facet_field("tag", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        // `/b` is the most frequent term overall, but never the most frequent of a segment.
+        for segment_tags in [
+            ["/a", "/a", "/a", "/b", "/b"],
+            ["/c", "/c", "/c", "/b", "/b"],
+        ] {
+            for segment_tag in segment_tags {
+                index_writer.add_document(doc!(tag => Facet::from(segment_tag)));
+            }
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        let collector = TermsCollector::for_field(tag)
+            .with_size(1)
+            .with_shard_size(1);
+        let terms = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(keys_and_counts(&terms), vec![("/a", 3)]);
+        assert_eq!(terms.sum_other_doc_count, 7);
+        let collector = TermsCollector::for_field(tag).with_size(1);
+        let terms = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(keys_and_counts(&terms), vec![("/b", 4)]);
+        assert_eq!(terms.sum_other_doc_count, 6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_terms_collector_nested_and_limits() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let country = schema_builder.add_facet_field("country", INDEXED);
+        let city = schema_builder.add_facet_field("city", INDEXED);
+        let name = schema_builder.add_text_field("name", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for (country_path, city_path) in [
+            ("/fr", "/paris"),
+            ("/fr", "/lyon"),
+            ("/fr", "/paris"),
+            ("/de", "/berlin"),
+        ] {
+            index_writer.add_document(doc!(
+                country => Facet::from(country_path),
+                city => Facet::from(city_path),
+            ));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let collector = TermsCollector::new(country, TermsCollector::for_field(city));
+        let terms = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(keys_and_counts(&terms), vec![("/fr", 3), ("/de", 1)]);
+        assert_eq!(
+            keys_and_counts(&terms.buckets[0].fruit),
+            vec![("/paris", 2), ("/lyon", 1)]
+        );
+        assert_eq!(
+            keys_and_counts(&terms.buckets[1].fruit),
+            vec![("/berlin", 1)]
+        );
+
+        let collector = TermsCollector::new(city, Count).with_max_bucket_count(2);
+        assert!(matches!(
+            searcher.search(&AllQuery, &collector),
+            Err(TantivyError::BucketLimitExceeded(2))
+        ));
+        let collector = TermsCollector::for_field(name);
+        assert!(matches!(
+            searcher.search(&AllQuery, &collector),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+}
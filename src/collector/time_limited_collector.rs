@@ -0,0 +1,430 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::collector::{Collector, SegmentCollector};
+use crate::query::Weight;
+use crate::{DocId, DocSet, Score, SegmentOrdinal, SegmentReader, TERMINATED};
+
+const DEFAULT_CHECK_INTERVAL: u64 = 256;
+
+/// Budget shared by the segment collectors of a `TimeLimitedCollector`.
+struct SearchBudget {
+    deadline: Instant,
+    max_collected_docs: Option<u64>,
+    num_collected_docs: AtomicU64,
+    timed_out: AtomicBool,
+    max_collected_docs_reached: AtomicBool,
+}
+
+impl SearchBudget {
+    /// Returns true iff the deadline is exceeded, and records it for the other segments.
+    fn check_deadline(&self) -> bool {
+        if self.timed_out.load(Ordering::Relaxed) {
+            return true;
+        }
+        if Instant::now() >= self.deadline {
+            self.timed_out.store(true, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    /// Counts a collected document, and returns false if it exceeds the maximum number
+    /// of collected documents.
+    fn admit_doc(&self) -> bool {
+        let max_collected_docs = match self.max_collected_docs {
+            Some(max_collected_docs) => max_collected_docs,
+            None => return true,
+        };
+        if self.num_collected_docs.fetch_add(1, Ordering::Relaxed) < max_collected_docs {
+            return true;
+        }
+        self.max_collected_docs_reached
+            .store(true, Ordering::Relaxed);
+        false
+    }
+}
+
+/// Wraps a collector to stop the collection once a time budget is spent, or once a
+/// given number of documents have been collected.
+///
+/// The deadline is checked every 256 collected documents by default, so that the
+/// overhead of the wrapper stays negligible. Once it is exceeded, the segment being
+/// collected is stopped, the remaining segments are skipped, and the fruit of the
+/// inner collector is computed from the documents collected so far.
+///
+/// The segment collection only stops early when the `TimeLimitedCollector` is given to
+/// the searcher. Within a [`MultiCollector`](./struct.MultiCollector.html), the
+/// documents are no longer passed to the inner collector, but the query is still
+/// evaluated until the end of the segments.
+///
+/// The time budget starts when the collector is created, so a new collector should be
+/// created for each search.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use tantivy::collector::{Count, TimeLimitedCollector};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// for _ in 0..100 {
+///     index_writer.add_document(doc!(title => "The Name of the Wind"));
+/// }
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let collector = TimeLimitedCollector::wrap(Count, Duration::from_millis(100))
+///     .with_max_collected_docs(10);
+/// let count = searcher.search(&AllQuery, &collector)?;
+/// assert!(count.is_partial());
+/// assert!(!count.is_timed_out());
+/// assert_eq!(count.into_inner(), 10);
+/// # Ok(())
+/// # }
+/// ```
+pub struct TimeLimitedCollector<TCollector> {
+    collector: TCollector,
+    budget: Arc<SearchBudget>,
+    check_interval: u64,
+}
+
+impl<TCollector: Collector> TimeLimitedCollector<TCollector> {
+    /// Wraps `collector` to stop the collection after `time_limit`.
+    pub fn wrap(collector: TCollector, time_limit: Duration) -> TimeLimitedCollector<TCollector> {
+        TimeLimitedCollector {
+            collector,
+            budget: Arc::new(SearchBudget {
+                deadline: Instant::now() + time_limit,
+                max_collected_docs: None,
+                num_collected_docs: AtomicU64::new(0),
+                timed_out: AtomicBool::new(false),
+                max_collected_docs_reached: AtomicBool::new(false),
+            }),
+            check_interval: DEFAULT_CHECK_INTERVAL,
+        }
+    }
+
+    /// Stops the collection after `max_collected_docs` documents, across all the segments.
+    pub fn with_max_collected_docs(
+        self,
+        max_collected_docs: u64,
+    ) -> TimeLimitedCollector<TCollector> {
+        let budget = SearchBudget {
+            deadline: self.budget.deadline,
+            max_collected_docs: Some(max_collected_docs),
+            num_collected_docs: AtomicU64::new(0),
+            timed_out: AtomicBool::new(false),
+            max_collected_docs_reached: AtomicBool::new(false),
+        };
+        TimeLimitedCollector {
+            budget: Arc::new(budget),
+            ..self
+        }
+    }
+
+    /// Checks the deadline every `num_docs` collected documents, instead of every 256.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `num_docs` is 0.
+    pub fn with_check_interval(mut self, num_docs: u64) -> TimeLimitedCollector<TCollector> {
+        assert!(num_docs > 0, "The check interval must be positive.");
+        self.check_interval = num_docs;
+        self
+    }
+}
+
+/// Fruit of a `TimeLimitedCollector`, marking whether the fruit of the inner collector
+/// was computed from a part of the matching documents.
+#[derive(Debug)]
+pub struct TimeLimitedFruit<TFruit> {
+    fruit: TFruit,
+    timed_out: bool,
+    max_collected_docs_reached: bool,
+    num_completed_segments: usize,
+    num_segments: usize,
+}
+
+impl<TFruit> TimeLimitedFruit<TFruit> {
+    /// Returns true iff the collection was stopped by the deadline.
+    pub fn is_timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Returns true iff the collection was stopped, by the deadline or by the maximum
+    /// number of collected documents.
+    pub fn is_partial(&self) -> bool {
+        self.timed_out || self.max_collected_docs_reached
+    }
+
+    /// Returns the number of segments collected until their end.
+    pub fn num_completed_segments(&self) -> usize {
+        self.num_completed_segments
+    }
+
+    /// Returns the number of segments of the searcher.
+    pub fn num_segments(&self) -> usize {
+        self.num_segments
+    }
+
+    /// Returns the fruit of the inner collector.
+    pub fn fruit(&self) -> &TFruit {
+        &self.fruit
+    }
+
+    /// Returns the fruit of the inner collector.
+    pub fn into_inner(self) -> TFruit {
+        self.fruit
+    }
+}
+
+impl<TCollector: Collector> Collector for TimeLimitedCollector<TCollector> {
+    type Fruit = TimeLimitedFruit<TCollector::Fruit>;
+    type Child = TimeLimitedSegmentCollector<TCollector::Child>;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        let segment_collector = self.collector.for_segment(segment_local_id, reader)?;
+        Ok(TimeLimitedSegmentCollector {
+            segment_collector,
+            stopped: self.budget.check_deadline(),
+            budget: self.budget.clone(),
+            check_interval: self.check_interval,
+            num_docs_before_check: self.check_interval,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.collector.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<(<TCollector::Child as SegmentCollector>::Fruit, bool)>,
+    ) -> crate::Result<Self::Fruit> {
+        let num_segments = segment_fruits.len();
+        let mut num_completed_segments = 0;
+        let child_fruits = segment_fruits
+            .into_iter()
+            .map(|(child_fruit, completed)| {
+                if completed {
+                    num_completed_segments += 1;
+                }
+                child_fruit
+            })
+            .collect();
+        Ok(TimeLimitedFruit {
+            fruit: self.collector.merge_fruits(child_fruits)?,
+            timed_out: self.budget.timed_out.load(Ordering::Relaxed),
+            max_collected_docs_reached: self
+                .budget
+                .max_collected_docs_reached
+                .load(Ordering::Relaxed),
+            num_completed_segments,
+            num_segments,
+        })
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        let mut segment_collector = self.for_segment(segment_ord, reader)?;
+        if segment_collector.stopped {
+            return Ok(segment_collector.harvest());
+        }
+        let mut scorer = weight.scorer(reader, 1.0)?;
+        let mut doc = scorer.doc();
+        while doc != TERMINATED && !segment_collector.stopped {
+            if !reader.is_deleted(doc) {
+                segment_collector.collect(doc, scorer.score());
+            }
+            doc = scorer.advance();
+        }
+        Ok(segment_collector.harvest())
+    }
+}
+
+/// Segment collector associated to `TimeLimitedCollector`.
+pub struct TimeLimitedSegmentCollector<TSegmentCollector> {
+    segment_collector: TSegmentCollector,
+    budget: Arc<SearchBudget>,
+    stopped: bool,
+    check_interval: u64,
+    num_docs_before_check: u64,
+}
+
+impl<TSegmentCollector: SegmentCollector> SegmentCollector
+    for TimeLimitedSegmentCollector<TSegmentCollector>
+{
+    /// The fruit of the inner segment collector, and whether the segment was collected
+    /// until its end.
+    type Fruit = (TSegmentCollector::Fruit, bool);
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        if self.stopped {
+            return;
+        }
+        self.num_docs_before_check -= 1;
+        if self.num_docs_before_check == 0 {
+            self.num_docs_before_check = self.check_interval;
+            if self.budget.check_deadline() {
+                self.stopped = true;
+                return;
+            }
+        }
+        if !self.budget.admit_doc() {
+            self.stopped = true;
+            return;
+        }
+        self.segment_collector.collect(doc, score);
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        (self.segment_collector.harvest(), !self.stopped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::TimeLimitedCollector;
+    use crate::collector::{Collector, Count, MultiCollector, SegmentCollector};
+    use crate::query::AllQuery;
+    use crate::schema::{Schema, TEXT};
+    use crate::{doc, DocId, Index, Score, SegmentOrdinal, SegmentReader};
+
+    /// Counts the documents, sleeping for a millisecond for each of them.
+    struct SlowCount;
+
+    struct SlowSegmentCount(usize);
+
+    impl Collector for SlowCount {
+        type Fruit = usize;
+        type Child = SlowSegmentCount;
+
+        fn for_segment(
+            &self,
+            _segment_local_id: SegmentOrdinal,
+            _reader: &SegmentReader,
+        ) -> crate::Result<SlowSegmentCount> {
+            Ok(SlowSegmentCount(0))
+        }
+
+        fn requires_scoring(&self) -> bool {
+            false
+        }
+
+        fn merge_fruits(&self, segment_counts: Vec<usize>) -> crate::Result<usize> {
+            Ok(segment_counts.into_iter().sum())
+        }
+    }
+
+    impl SegmentCollector for SlowSegmentCount {
+        type Fruit = usize;
+
+        fn collect(&mut self, _doc: DocId, _score: Score) {
+            thread::sleep(Duration::from_millis(1));
+            self.0 += 1;
+        }
+
+        fn harvest(self) -> usize {
+            self.0
+        }
+    }
+
+    fn create_index(num_docs_per_segment: &[usize]) -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for &num_docs in num_docs_per_segment {
+            for _ in 0..num_docs {
+                index_writer.add_document(doc!(title => "hello"));
+            }
+            index_writer.commit()?;
+        }
+        Ok(index)
+    }
+
+    #[test]
+    fn test_time_limited_collector_deadline() -> crate::Result<()> {
+        let index = create_index(&[10, 1_000, 10])?;
+        let searcher = index.reader()?.searcher();
+        // the segments are collected in order by the single threaded executor, and
+        // the collection stops in the large segment.
+        let num_small_segments_first = searcher
+            .segment_readers()
+            .iter()
+            .take_while(|segment_reader| segment_reader.max_doc() == 10)
+            .count();
+        let collector = TimeLimitedCollector::wrap(SlowCount, Duration::from_millis(200))
+            .with_check_interval(1);
+        let count = searcher.search(&AllQuery, &collector)?;
+        assert!(count.is_timed_out());
+        assert!(count.is_partial());
+        assert_eq!(count.num_completed_segments(), num_small_segments_first);
+        assert_eq!(count.num_segments(), 3);
+        assert!(*count.fruit() > 10 * num_small_segments_first);
+        assert!(*count.fruit() < 500);
+
+        let collector = TimeLimitedCollector::wrap(SlowCount, Duration::from_secs(60));
+        let count = searcher.search(&AllQuery, &collector)?;
+        assert!(!count.is_partial());
+        assert_eq!(count.num_completed_segments(), 3);
+        assert_eq!(count.into_inner(), 1_020);
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_limited_collector_max_collected_docs() -> crate::Result<()> {
+        let index = create_index(&[10, 10, 10])?;
+        let searcher = index.reader()?.searcher();
+        let collector =
+            TimeLimitedCollector::wrap(Count, Duration::from_secs(60)).with_max_collected_docs(25);
+        let count = searcher.search(&AllQuery, &collector)?;
+        assert!(count.is_partial());
+        assert!(!count.is_timed_out());
+        assert_eq!(count.num_completed_segments(), 2);
+        assert_eq!(count.into_inner(), 25);
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_limited_collector_in_multi_collector() -> crate::Result<()> {
+        let index = create_index(&[10, 10, 10])?;
+        let searcher = index.reader()?.searcher();
+        let mut collectors = MultiCollector::new();
+        let count_handle = collectors.add_collector(Count);
+        let limited_count_handle = collectors.add_collector(
+            TimeLimitedCollector::wrap(Count, Duration::from_secs(60)).with_max_collected_docs(5),
+        );
+        let mut multi_fruit = searcher.search(&AllQuery, &collectors)?;
+        assert_eq!(count_handle.extract(&mut multi_fruit), 30);
+        let limited_count = limited_count_handle.extract(&mut multi_fruit);
+        assert!(limited_count.is_partial());
+        assert_eq!(limited_count.into_inner(), 5);
+
+        let mut collectors = MultiCollector::new();
+        let count_handle = collectors.add_collector(Count);
+        let collector = TimeLimitedCollector::wrap(collectors, Duration::from_secs(60))
+            .with_max_collected_docs(12);
+        let mut multi_fruit = searcher.search(&AllQuery, &collector)?.into_inner();
+        assert_eq!(count_handle.extract(&mut multi_fruit), 12);
+        Ok(())
+    }
+}
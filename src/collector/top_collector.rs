@@ -4,7 +4,6 @@ use crate::SegmentOrdinal;
 use crate::SegmentReader;
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
-use std::marker::PhantomData;
 
 /// Contains a feature (field, score, etc.) of a document along with the document address.
 ///
@@ -58,7 +57,8 @@ impl<T: PartialOrd, D: PartialOrd> Eq for ComparableDoc<T, D> {}
 pub(crate) struct TopCollector<T> {
     pub limit: usize,
     pub offset: usize,
-    _marker: PhantomData<T>,
+    /// The last hit of the previous page, if any.
+    pub after: Option<(T, DocAddress)>,
 }
 
 impl<T> TopCollector<T>
@@ -76,7 +76,7 @@ where
         Self {
             limit,
             offset: 0,
-            _marker: PhantomData,
+            after: None,
         }
     }
 
@@ -89,6 +89,13 @@ where
         self
     }
 
+    /// Only collects the documents following `last_hit` in the order of the collector,
+    /// i.e. by decreasing feature, and by increasing `DocAddress` for a tie.
+    pub fn after(mut self, last_hit: (T, DocAddress)) -> TopCollector<T> {
+        self.after = Some(last_hit);
+        self
+    }
+
     pub fn merge_fruits(
         &self,
         children: Vec<Vec<(T, DocAddress)>>,
@@ -116,16 +123,35 @@ where
             .collect())
     }
 
-    pub(crate) fn for_segment<F: PartialOrd>(
+    pub(crate) fn for_segment(
         &self,
         segment_id: SegmentOrdinal,
         _: &SegmentReader,
-    ) -> TopSegmentCollector<F> {
-        TopSegmentCollector::new(segment_id, self.limit + self.offset)
+    ) -> TopSegmentCollector<T> {
+        let mut segment_collector = TopSegmentCollector::new(segment_id, self.limit + self.offset);
+        segment_collector.cursor = self.segment_cursor(segment_id);
+        segment_collector
+    }
+
+    /// Returns the cursor of the segment, if the collector only collects the documents
+    /// following a hit.
+    pub(crate) fn segment_cursor(&self, segment_ord: SegmentOrdinal) -> Option<SegmentCursor<T>> {
+        self.after
+            .as_ref()
+            .map(|(feature, doc_address)| SegmentCursor {
+                feature: feature.clone(),
+                first_doc_on_tie: match segment_ord.cmp(&doc_address.segment_ord) {
+                    Ordering::Less => None,
+                    Ordering::Equal => doc_address.doc_id.checked_add(1),
+                    Ordering::Greater => Some(0),
+                },
+            })
     }
 
     /// Create a new TopCollector with the same limit and offset.
     ///
+    /// The last hit of the previous page is not kept, as its feature is of another type.
+    ///
     /// Ideally we would use Into but the blanket implementation seems to cause the Scorer traits
     /// to fail.
     #[doc(hidden)]
@@ -133,7 +159,31 @@ where
         TopCollector {
             limit: self.limit,
             offset: self.offset,
-            _marker: PhantomData,
+            after: None,
+        }
+    }
+}
+
+/// The last hit of the previous page, as seen from a segment.
+#[derive(Clone)]
+pub(crate) struct SegmentCursor<T> {
+    feature: T,
+    /// The first document of the segment following the hit if they have the same feature,
+    /// or `None` if no document of the segment does.
+    first_doc_on_tie: Option<DocId>,
+}
+
+impl<T: PartialOrd> SegmentCursor<T> {
+    /// Returns true iff the document follows the last hit of the previous page.
+    #[inline]
+    pub(crate) fn is_followed_by(&self, doc: DocId, feature: &T) -> bool {
+        match feature.partial_cmp(&self.feature) {
+            Some(Ordering::Less) => true,
+            Some(Ordering::Equal) => self
+                .first_doc_on_tie
+                .map(|first_doc| doc >= first_doc)
+                .unwrap_or(false),
+            _ => false,
         }
     }
 }
@@ -148,14 +198,16 @@ pub(crate) struct TopSegmentCollector<T> {
     limit: usize,
     heap: BinaryHeap<ComparableDoc<T, DocId>>,
     segment_ord: u32,
+    cursor: Option<SegmentCursor<T>>,
 }
 
 impl<T: PartialOrd> TopSegmentCollector<T> {
-    fn new(segment_ord: SegmentOrdinal, limit: usize) -> TopSegmentCollector<T> {
+    pub(crate) fn new(segment_ord: SegmentOrdinal, limit: usize) -> TopSegmentCollector<T> {
         TopSegmentCollector {
             limit,
             heap: BinaryHeap::with_capacity(limit),
             segment_ord,
+            cursor: None,
         }
     }
 }
@@ -185,20 +237,36 @@ impl<T: PartialOrd + Clone> TopSegmentCollector<T> {
         self.heap.len() >= self.limit
     }
 
+    /// Returns true iff a document of the given feature would enter the top documents,
+    /// regardless of the last hit of the previous page.
+    #[inline]
+    pub(crate) fn is_competitive(&self, feature: &T) -> bool {
+        match self.heap.peek() {
+            Some(head) if self.at_capacity() => head.feature < *feature,
+            _ => true,
+        }
+    }
+
     /// Collects a document scored by the given feature
     ///
     /// It collects documents until it has reached the max capacity. Once it reaches capacity, it
     /// will compare the lowest scoring item with the given one and keep whichever is greater.
+    ///
+    /// Returns false iff the document is ignored because it does not follow the last hit
+    /// of the previous page.
     #[inline]
-    pub fn collect(&mut self, doc: DocId, feature: T) {
+    pub fn collect(&mut self, doc: DocId, feature: T) -> bool {
+        if let Some(cursor) = &self.cursor {
+            if !cursor.is_followed_by(doc, &feature) {
+                return false;
+            }
+        }
         if self.at_capacity() {
-            // It's ok to unwrap as long as a limit of 0 is forbidden.
-            if let Some(limit_feature) = self.heap.peek().map(|head| head.feature.clone()) {
-                if limit_feature < feature {
-                    if let Some(mut head) = self.heap.peek_mut() {
-                        head.feature = feature;
-                        head.doc = doc;
-                    }
+            if self.is_competitive(&feature) {
+                // It's ok to unwrap as long as a limit of 0 is forbidden.
+                if let Some(mut head) = self.heap.peek_mut() {
+                    head.feature = feature;
+                    head.doc = doc;
                 }
             }
         } else {
@@ -206,6 +274,7 @@ impl<T: PartialOrd + Clone> TopSegmentCollector<T> {
             // element.
             self.heap.push(ComparableDoc { feature, doc });
         }
+        true
     }
 }
 
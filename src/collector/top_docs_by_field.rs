@@ -0,0 +1,371 @@
+use crate::collector::top_collector::{TopCollector, TopSegmentCollector};
+use crate::collector::{Collector, SegmentCollector};
+use crate::fastfield::OptionalFastFieldReader;
+use crate::query::Weight;
+use crate::schema::Field;
+use crate::{DocAddress, DocId, DocSet, Score, SegmentOrdinal, SegmentReader, TERMINATED};
+
+/// Collector of the top documents ranked by the value of a u64 fast field.
+///
+/// It is built by [`TopDocs::order_by_u64_field`](./struct.TopDocs.html#method.order_by_u64_field).
+pub struct TopDocsByField {
+    field: Field,
+    collector: TopCollector<u64>,
+}
+
+impl TopDocsByField {
+    pub(crate) fn new(field: Field, collector: TopCollector<u64>) -> TopDocsByField {
+        TopDocsByField { field, collector }
+    }
+
+    /// Only collects the documents following `last_hit`, the last hit of the previous page.
+    ///
+    /// The documents are sorted by decreasing value, and by increasing `DocAddress` for a
+    /// tie. See [`TopDocs::after`](./struct.TopDocs.html#method.after).
+    pub fn after(mut self, last_hit: (u64, DocAddress)) -> TopDocsByField {
+        self.collector = self.collector.after(last_hit);
+        self
+    }
+
+    /// Stops the collection of the segments sorted by the field in descending order
+    /// as soon as the top documents are collected.
+    ///
+    /// The documents of such a segment are visited by decreasing value, so that the
+    /// `limit + offset` first matching documents are the top documents of the segment.
+    /// The other segments are collected entirely.
+    ///
+    /// The top documents are the same as without early termination, but the number of
+    /// visited documents is only a lower bound of the number of matching documents.
+    pub fn with_early_termination(self) -> EarlyTerminatingTopDocsByField {
+        EarlyTerminatingTopDocsByField(self)
+    }
+}
+
+impl Collector for TopDocsByField {
+    type Fruit = Vec<(u64, DocAddress)>;
+
+    type Child = TopDocsByFieldSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        // We interpret this field as u64, regardless of its type, that way,
+        // we avoid needless conversion. Regardless of the fast field type, the
+        // mapping is monotonic, so it is sufficient to compute our top-K docs.
+        //
+        // The conversion will then happen only on the top-K docs.
+        let ff_reader = reader
+            .fast_fields()
+            .typed_optional_fast_field_reader(self.field)?;
+        Ok(TopDocsByFieldSegmentCollector {
+            ff_reader,
+            segment_collector: self.collector.for_segment(segment_local_id, reader),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Self::Fruit>) -> crate::Result<Self::Fruit> {
+        self.collector.merge_fruits(segment_fruits)
+    }
+}
+
+/// Segment collector associated to `TopDocsByField`.
+pub struct TopDocsByFieldSegmentCollector {
+    ff_reader: OptionalFastFieldReader<u64>,
+    segment_collector: TopSegmentCollector<u64>,
+}
+
+impl TopDocsByFieldSegmentCollector {
+    /// Collects the document, and returns false iff it is ignored because it does not
+    /// follow the last hit of the previous page.
+    fn collect_doc(&mut self, doc: DocId) -> bool {
+        // The documents without a value are ranked last.
+        let value = self.ff_reader.get_opt(doc).unwrap_or(0u64);
+        self.segment_collector.collect(doc, value)
+    }
+}
+
+impl SegmentCollector for TopDocsByFieldSegmentCollector {
+    type Fruit = Vec<(u64, DocAddress)>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        self.collect_doc(doc);
+    }
+
+    fn harvest(self) -> Vec<(u64, DocAddress)> {
+        self.segment_collector.harvest()
+    }
+}
+
+/// Top documents collected by an `EarlyTerminatingTopDocsByField`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EarlyTerminatedTopDocs {
+    /// The top documents, with the value of their fast field.
+    pub top_docs: Vec<(u64, DocAddress)>,
+    /// The number of matching documents visited.
+    ///
+    /// It is the number of matching documents iff `terminated_early` is false.
+    pub num_visited_docs: u64,
+    /// True iff the collection of some segments stopped before all of their matching
+    /// documents were visited.
+    pub terminated_early: bool,
+}
+
+/// Collector of the top documents ranked by the value of a u64 fast field,
+/// which stops the collection of the segments sorted by the field.
+///
+/// It is built by [`TopDocsByField::with_early_termination`](./struct.TopDocsByField.html#method.with_early_termination).
+pub struct EarlyTerminatingTopDocsByField(TopDocsByField);
+
+impl EarlyTerminatingTopDocsByField {
+    /// Returns true iff the documents of the segment are sorted by decreasing values of
+    /// the field.
+    ///
+    /// The documents without a value are ranked last but are sorted by the default value,
+    /// so segments having documents without a value are not considered sorted.
+    fn is_sorted_by_field_desc(&self, reader: &SegmentReader) -> crate::Result<bool> {
+        let sort_by_field = match reader.sort_by_field() {
+            Some(sort_by_field) if sort_by_field.order.is_desc() => sort_by_field,
+            _ => return Ok(false),
+        };
+        if reader.schema().get_field(&sort_by_field.field) != Some(self.0.field) {
+            return Ok(false);
+        }
+        let ff_reader = reader
+            .fast_fields()
+            .typed_optional_fast_field_reader::<u64>(self.0.field)?;
+        Ok(!ff_reader.has_missing_vals())
+    }
+}
+
+impl Collector for EarlyTerminatingTopDocsByField {
+    type Fruit = EarlyTerminatedTopDocs;
+
+    type Child = EarlyTerminatingSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        Ok(EarlyTerminatingSegmentCollector {
+            segment_collector: self.0.for_segment(segment_local_id, reader)?,
+            num_visited_docs: 0,
+            terminated_early: false,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Self::Fruit>) -> crate::Result<Self::Fruit> {
+        let num_visited_docs = segment_fruits
+            .iter()
+            .map(|fruit| fruit.num_visited_docs)
+            .sum();
+        let terminated_early = segment_fruits.iter().any(|fruit| fruit.terminated_early);
+        let top_docs = self.0.merge_fruits(
+            segment_fruits
+                .into_iter()
+                .map(|fruit| fruit.top_docs)
+                .collect(),
+        )?;
+        Ok(EarlyTerminatedTopDocs {
+            top_docs,
+            num_visited_docs,
+            terminated_early,
+        })
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<Self::Fruit> {
+        let mut segment_collector = self.for_segment(segment_ord, reader)?;
+        let num_docs = (self.0.collector.limit + self.0.collector.offset) as u64;
+        if !self.is_sorted_by_field_desc(reader)? {
+            weight.for_each(reader, &mut |doc, score| {
+                if !reader.is_deleted(doc) {
+                    segment_collector.collect(doc, score);
+                }
+            })?;
+            return Ok(segment_collector.harvest());
+        }
+        let mut scorer = weight.scorer(reader, 1.0)?;
+        let mut num_collected_docs = 0;
+        let mut doc = scorer.doc();
+        while doc != TERMINATED {
+            if !reader.is_deleted(doc) {
+                if num_collected_docs == num_docs {
+                    segment_collector.terminated_early = true;
+                    break;
+                }
+                if segment_collector.collect_doc(doc) {
+                    num_collected_docs += 1;
+                }
+            }
+            doc = scorer.advance();
+        }
+        Ok(segment_collector.harvest())
+    }
+}
+
+/// Segment collector associated to `EarlyTerminatingTopDocsByField`.
+pub struct EarlyTerminatingSegmentCollector {
+    segment_collector: TopDocsByFieldSegmentCollector,
+    num_visited_docs: u64,
+    terminated_early: bool,
+}
+
+impl EarlyTerminatingSegmentCollector {
+    fn collect_doc(&mut self, doc: DocId) -> bool {
+        self.num_visited_docs += 1;
+        self.segment_collector.collect_doc(doc)
+    }
+}
+
+impl SegmentCollector for EarlyTerminatingSegmentCollector {
+    type Fruit = EarlyTerminatedTopDocs;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        self.collect_doc(doc);
+    }
+
+    fn harvest(self) -> EarlyTerminatedTopDocs {
+        EarlyTerminatedTopDocs {
+            top_docs: self.segment_collector.harvest(),
+            num_visited_docs: self.num_visited_docs,
+            terminated_early: self.terminated_early,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::{Count, TopDocs};
+    use crate::query::{AllQuery, Query, QueryParser};
+    use crate::schema::{Schema, Term, FAST, TEXT};
+    use crate::{DocAddress, Index, IndexSettings, IndexSortByField, Order};
+
+    #[test]
+    fn test_top_docs_by_field_early_termination() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let city = schema_builder.add_text_field("city", TEXT);
+        let timestamp = schema_builder.add_u64_field("timestamp", FAST);
+        let settings = IndexSettings {
+            sort_by_field: Some(IndexSortByField {
+                field: "timestamp".to_string(),
+                order: Order::Desc,
+            }),
+            ..Default::default()
+        };
+        let index = Index::builder()
+            .schema(schema_builder.build())
+            .settings(settings)
+            .create_in_ram()?;
+        let mut index_writer = index.writer_for_tests()?;
+        for ts in 0u64..20 {
+            let city_name = if ts % 2 == 0 { "paris" } else { "tokyo" };
+            index_writer
+                .add_document(doc!(city => format!("{} t{}", city_name, ts), timestamp => ts));
+        }
+        index_writer.commit()?;
+        for ts in 20u64..30 {
+            index_writer.add_document(doc!(city => format!("paris t{}", ts), timestamp => ts * 3));
+        }
+        // the deleted documents are interleaved with the sorted order.
+        index_writer.delete_term(Term::from_field_text(city, "t18"));
+        index_writer.delete_term(Term::from_field_text(city, "t17"));
+        index_writer.delete_term(Term::from_field_text(city, "t14"));
+        index_writer.delete_term(Term::from_field_text(city, "t20"));
+        index_writer.delete_term(Term::from_field_text(city, "t29"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let query_parser = QueryParser::for_index(&index, vec![city]);
+        let paris_query = query_parser.parse_query("paris")?;
+        let queries: Vec<&dyn Query> = vec![&AllQuery, paris_query.as_ref()];
+        for query in queries {
+            let num_hits = searcher.search(query, &Count)? as u64;
+            for (limit, offset) in [(1, 0), (3, 0), (3, 4), (12, 0), (40, 0)] {
+                let top_docs = TopDocs::with_limit(limit)
+                    .and_offset(offset)
+                    .order_by_u64_field(timestamp);
+                let expected: Vec<(u64, DocAddress)> = searcher.search(query, &top_docs)?;
+                let early_terminated =
+                    searcher.search(query, &top_docs.with_early_termination())?;
+                assert_eq!(early_terminated.top_docs, expected);
+                assert!(early_terminated.num_visited_docs <= num_hits);
+                assert_eq!(
+                    early_terminated.terminated_early,
+                    early_terminated.num_visited_docs < num_hits
+                );
+            }
+        }
+        // paging with early termination returns the same documents.
+        let all_hits = searcher.search(
+            &AllQuery,
+            &TopDocs::with_limit(100).order_by_u64_field(timestamp),
+        )?;
+        let mut hits: Vec<(u64, DocAddress)> = Vec::new();
+        loop {
+            let mut top_docs = TopDocs::with_limit(3).order_by_u64_field(timestamp);
+            if let Some(last_hit) = hits.last() {
+                top_docs = top_docs.after(*last_hit);
+            }
+            let page = searcher.search(&AllQuery, &top_docs.with_early_termination())?;
+            if page.top_docs.is_empty() {
+                break;
+            }
+            hits.extend(page.top_docs);
+        }
+        assert_eq!(hits, all_hits);
+        let top_docs = TopDocs::with_limit(2)
+            .order_by_u64_field(timestamp)
+            .with_early_termination();
+        let early_terminated = searcher.search(&AllQuery, &top_docs)?;
+        assert!(early_terminated.terminated_early);
+        assert_eq!(early_terminated.num_visited_docs, 4);
+        assert_eq!(
+            early_terminated
+                .top_docs
+                .iter()
+                .map(|(ts, _)| *ts)
+                .collect::<Vec<u64>>(),
+            vec![84, 81]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_docs_by_field_early_termination_unsorted_index() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let timestamp = schema_builder.add_u64_field("timestamp", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for ts in [3u64, 17, 5, 11] {
+            index_writer.add_document(doc!(timestamp => ts));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let top_docs = TopDocs::with_limit(2)
+            .order_by_u64_field(timestamp)
+            .with_early_termination();
+        let early_terminated = searcher.search(&AllQuery, &top_docs)?;
+        assert!(!early_terminated.terminated_early);
+        assert_eq!(early_terminated.num_visited_docs, 4);
+        assert_eq!(
+            early_terminated.top_docs,
+            vec![(17, DocAddress::new(0, 1)), (11, DocAddress::new(0, 3))]
+        );
+        Ok(())
+    }
+}
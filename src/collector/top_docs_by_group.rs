@@ -0,0 +1,430 @@
+use crate::collector::top_collector::{TopCollector, TopSegmentCollector};
+use crate::collector::{Collector, SegmentCollector};
+use crate::fastfield::{FacetReader, OptionalFastFieldReader};
+use crate::schema::{Facet, Field, FieldType};
+use crate::{DocAddress, DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Key of a group of documents of a `TopDocsByGroup` collector.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GroupKey {
+    /// The u64 representation of the value of the fast field of the documents.
+    Value(u64),
+    /// The facet of the documents.
+    Facet(Facet),
+    /// The documents without a value, only collected if the collector was built
+    /// `with_missing_group()`.
+    Missing,
+}
+
+/// A group of documents sharing the same value, with its top documents.
+#[derive(Clone, Debug)]
+pub struct DocGroup {
+    /// The key of the group.
+    pub key: GroupKey,
+    /// The top documents of the group, sorted by decreasing score.
+    pub top_docs: Vec<(Score, DocAddress)>,
+}
+
+impl DocGroup {
+    fn best_hit(&self) -> &(Score, DocAddress) {
+        // groups are only created with at least one document.
+        &self.top_docs[0]
+    }
+}
+
+/// Collector of the top documents of each group of documents sharing the value of a field.
+///
+/// The documents are grouped by the value of a single-valued fast field, or by their
+/// facets, in which case a document is collected in the group of each of its facets.
+/// Values of i64, f64 and date fields are grouped by their u64 representation.
+///
+/// The collector returns the `groups_limit` groups having the best hits, sorted by
+/// decreasing score of their best hit. Each group holds its top `docs_per_group` documents.
+/// As for `TopDocs`, ties are broken by increasing `DocAddress`, and the groups having
+/// the same best hit are sorted by their key.
+///
+/// The documents without a value are not collected, unless the collector is built
+/// `with_missing_group()`.
+///
+/// ```rust
+/// use tantivy::collector::{GroupKey, TopDocsByGroup};
+/// use tantivy::query::QueryParser;
+/// use tantivy::schema::{Schema, FAST, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let brand = schema_builder.add_u64_field("brand", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "blue shoes", brand => 1u64));
+/// index_writer.add_document(doc!(title => "red shoes", brand => 2u64));
+/// index_writer.add_document(doc!(title => "shoes shoes", brand => 1u64));
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let query = QueryParser::for_index(&index, vec![title]).parse_query("shoes")?;
+/// let groups = searcher.search(&query, &TopDocsByGroup::new(brand, 10, 3))?;
+/// assert_eq!(groups.len(), 2);
+/// assert_eq!(groups[0].key, GroupKey::Value(1));
+/// assert_eq!(groups[0].top_docs.len(), 2);
+/// assert_eq!(groups[1].key, GroupKey::Value(2));
+/// # Ok(())
+/// # }
+/// ```
+pub struct TopDocsByGroup {
+    group_field: Field,
+    groups_limit: usize,
+    docs_collector: TopCollector<Score>,
+    collect_missing: bool,
+}
+
+impl TopDocsByGroup {
+    /// Creates a collector of the top `docs_per_group` documents of the
+    /// `groups_limit` best groups of documents sharing the value of `group_field`.
+    ///
+    /// # Panics
+    /// The method panics if `groups_limit` or `docs_per_group` is 0.
+    pub fn new(group_field: Field, groups_limit: usize, docs_per_group: usize) -> TopDocsByGroup {
+        if groups_limit < 1 {
+            panic!("Limit must be strictly greater than 0.");
+        }
+        TopDocsByGroup {
+            group_field,
+            groups_limit,
+            docs_collector: TopCollector::with_limit(docs_per_group),
+            collect_missing: false,
+        }
+    }
+
+    /// Collects the documents without a value in a group of key `GroupKey::Missing`.
+    pub fn with_missing_group(mut self) -> TopDocsByGroup {
+        self.collect_missing = true;
+        self
+    }
+}
+
+impl Collector for TopDocsByGroup {
+    type Fruit = Vec<DocGroup>;
+
+    type Child = TopDocsByGroupSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        let field_entry = reader.schema().get_field_entry(self.group_field);
+        let group_reader = match field_entry.field_type() {
+            FieldType::HierarchicalFacet(_) => {
+                GroupReader::Facet(Box::new(reader.facet_reader(self.group_field)?))
+            }
+            _ if field_entry.is_fast() => GroupReader::Value(
+                reader
+                    .fast_fields()
+                    .typed_optional_fast_field_reader(self.group_field)?,
+            ),
+            _ => {
+                return Err(TantivyError::SchemaError(format!(
+                    "Field {:?} is not a fast field.",
+                    field_entry.name()
+                )));
+            }
+        };
+        Ok(TopDocsByGroupSegmentCollector {
+            group_reader,
+            groups: HashMap::new(),
+            missing_group: None,
+            collect_missing: self.collect_missing,
+            segment_ord: segment_local_id,
+            docs_per_group: self.docs_collector.limit,
+            facet_ords: Vec::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Vec<DocGroup>>) -> crate::Result<Vec<DocGroup>> {
+        // the facet of a group was resolved in each segment, as the facet ordinals
+        // are local to the segments.
+        let mut segment_top_docs_by_key: HashMap<GroupKey, Vec<Vec<(Score, DocAddress)>>> =
+            HashMap::new();
+        for doc_group in segment_fruits.into_iter().flatten() {
+            segment_top_docs_by_key
+                .entry(doc_group.key)
+                .or_default()
+                .push(doc_group.top_docs);
+        }
+        let mut doc_groups = segment_top_docs_by_key
+            .into_iter()
+            .map(|(key, segment_top_docs)| {
+                let top_docs = self.docs_collector.merge_fruits(segment_top_docs)?;
+                Ok(DocGroup { key, top_docs })
+            })
+            .collect::<crate::Result<Vec<DocGroup>>>()?;
+        doc_groups.sort_by(|left, right| {
+            let (left_score, left_doc) = left.best_hit();
+            let (right_score, right_doc) = right.best_hit();
+            right_score
+                .partial_cmp(left_score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| left_doc.cmp(right_doc))
+                .then_with(|| left.key.cmp(&right.key))
+        });
+        doc_groups.truncate(self.groups_limit);
+        Ok(doc_groups)
+    }
+}
+
+enum GroupReader {
+    Value(OptionalFastFieldReader<u64>),
+    Facet(Box<FacetReader>),
+}
+
+/// Segment collector associated to `TopDocsByGroup`.
+pub struct TopDocsByGroupSegmentCollector {
+    group_reader: GroupReader,
+    /// Top documents by the value of the fast field, or by the ordinal of the facet.
+    groups: HashMap<u64, TopSegmentCollector<Score>>,
+    missing_group: Option<TopSegmentCollector<Score>>,
+    collect_missing: bool,
+    segment_ord: SegmentOrdinal,
+    docs_per_group: usize,
+    facet_ords: Vec<u64>,
+}
+
+impl TopDocsByGroupSegmentCollector {
+    fn collect_in_group(&mut self, group: u64, doc: DocId, score: Score) {
+        let (segment_ord, docs_per_group) = (self.segment_ord, self.docs_per_group);
+        self.groups
+            .entry(group)
+            .or_insert_with(|| TopSegmentCollector::new(segment_ord, docs_per_group))
+            .collect(doc, score);
+    }
+
+    fn collect_missing(&mut self, doc: DocId, score: Score) {
+        if !self.collect_missing {
+            return;
+        }
+        let (segment_ord, docs_per_group) = (self.segment_ord, self.docs_per_group);
+        self.missing_group
+            .get_or_insert_with(|| TopSegmentCollector::new(segment_ord, docs_per_group))
+            .collect(doc, score);
+    }
+}
+
+impl SegmentCollector for TopDocsByGroupSegmentCollector {
+    type Fruit = Vec<DocGroup>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        match &self.group_reader {
+            GroupReader::Value(ff_reader) => match ff_reader.get_opt(doc) {
+                Some(value) => self.collect_in_group(value, doc, score),
+                None => self.collect_missing(doc, score),
+            },
+            GroupReader::Facet(facet_reader) => {
+                let mut facet_ords = std::mem::take(&mut self.facet_ords);
+                facet_reader.facet_ords(doc, &mut facet_ords);
+                if facet_ords.is_empty() {
+                    self.collect_missing(doc, score);
+                }
+                for &facet_ord in &facet_ords {
+                    self.collect_in_group(facet_ord, doc, score);
+                }
+                self.facet_ords = facet_ords;
+            }
+        }
+    }
+
+    fn harvest(self) -> Vec<DocGroup> {
+        let mut group_reader = self.group_reader;
+        let mut doc_groups: Vec<DocGroup> = self
+            .groups
+            .into_iter()
+            .filter_map(|(group, segment_collector)| {
+                let key = match &mut group_reader {
+                    GroupReader::Value(_) => GroupKey::Value(group),
+                    GroupReader::Facet(facet_reader) => {
+                        let mut facet = Facet::root();
+                        facet_reader.facet_from_ord(group, &mut facet).ok()?;
+                        GroupKey::Facet(facet)
+                    }
+                };
+                Some(DocGroup {
+                    key,
+                    top_docs: segment_collector.harvest(),
+                })
+            })
+            .collect();
+        if let Some(missing_group) = self.missing_group {
+            doc_groups.push(DocGroup {
+                key: GroupKey::Missing,
+                top_docs: missing_group.harvest(),
+            });
+        }
+        doc_groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DocGroup, GroupKey, TopDocsByGroup};
+    use crate::collector::TopDocs;
+    use crate::fastfield::FastFieldReader;
+    use crate::query::{Query, QueryParser, TermQuery};
+    use crate::schema::{Facet, IndexRecordOption, Schema, Term, FAST, INDEXED, TEXT};
+    use crate::{DocAddress, Index, Score};
+
+    fn keys(doc_groups: &[DocGroup]) -> Vec<GroupKey> {
+        doc_groups
+            .iter()
+            .map(|doc_group| doc_group.key.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_top_docs_by_group_value() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let brand = schema_builder.add_i64_field("brand", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "shoes", brand => -1i64));
+        index_writer.add_document(doc!(title => "shoes shoes shoes", brand => 2i64));
+        index_writer.add_document(doc!(title => "shoes and socks", brand => -1i64));
+        index_writer.add_document(doc!(title => "shoes", brand => 3i64));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(title => "shoes shoes", brand => -1i64));
+        index_writer.add_document(doc!(title => "shoes shoes shoes shoes"));
+        index_writer.add_document(doc!(title => "shoes", brand => 2i64));
+        index_writer.add_document(doc!(title => "deleted shoes shoes shoes", brand => 3i64));
+        index_writer.delete_term(Term::from_field_text(title, "deleted"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = QueryParser::for_index(&index, vec![title]).parse_query("shoes")?;
+        let scores = searcher.search(&query, &TopDocs::with_limit(10))?;
+        let score_of = |doc_address: DocAddress| -> Score {
+            scores
+                .iter()
+                .find(|(_, doc)| *doc == doc_address)
+                .map(|(score, _)| *score)
+                .unwrap()
+        };
+
+        let doc_groups = searcher.search(&query, &TopDocsByGroup::new(brand, 10, 2))?;
+        let brand_key = |brand_value: i64| GroupKey::Value(common::i64_to_u64(brand_value));
+        assert_eq!(
+            keys(&doc_groups),
+            vec![brand_key(2), brand_key(-1), brand_key(3)]
+        );
+        for doc_group in &doc_groups {
+            assert!(doc_group.top_docs.len() <= 2);
+            for (score, doc) in &doc_group.top_docs {
+                assert_eq!(*score, score_of(*doc));
+                let brand_value = searcher
+                    .segment_reader(doc.segment_ord)
+                    .fast_fields()
+                    .i64(brand)?
+                    .get(doc.doc_id);
+                assert_eq!(doc_group.key, brand_key(brand_value));
+            }
+            assert!(doc_group.top_docs.windows(2).all(|w| w[0].0 >= w[1].0));
+        }
+        // the group of brand -1 holds its two best documents, from both segments.
+        assert_eq!(doc_groups[1].top_docs.len(), 2);
+        assert_ne!(
+            doc_groups[1].top_docs[0].1.segment_ord,
+            doc_groups[1].top_docs[1].1.segment_ord
+        );
+        // the deleted document does not count for the group of brand 3.
+        assert_eq!(doc_groups[2].top_docs.len(), 1);
+
+        let doc_groups = searcher.search(&query, &TopDocsByGroup::new(brand, 2, 1))?;
+        assert_eq!(keys(&doc_groups), vec![brand_key(2), brand_key(-1)]);
+
+        let doc_groups = searcher.search(
+            &query,
+            &TopDocsByGroup::new(brand, 10, 1).with_missing_group(),
+        )?;
+        assert_eq!(doc_groups[0].key, GroupKey::Missing);
+        assert_eq!(doc_groups.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_docs_by_group_ties() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let brand = schema_builder.add_u64_field("brand", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for brand_value in [5u64, 4, 5, 4, 3] {
+            index_writer.add_document(doc!(title => "shoes", brand => brand_value));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(title, "shoes"),
+            IndexRecordOption::Basic,
+        );
+        let doc_groups = searcher.search(&query, &TopDocsByGroup::new(brand, 2, 1))?;
+        // the groups are sorted by increasing address of their best document.
+        assert_eq!(
+            keys(&doc_groups),
+            vec![GroupKey::Value(5), GroupKey::Value(4)]
+        );
+        let top_docs: Vec<DocAddress> = doc_groups
+            .iter()
+            .map(|doc_group| doc_group.top_docs[0].1)
+            .collect();
+        assert_eq!(top_docs, vec![DocAddress::new(0, 0), DocAddress::new(0, 1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_docs_by_group_facet() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let category = schema_builder.add_facet_field("category", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(
+            title => "shoes shoes shoes",
+            category => Facet::from("/shoes"),
+        ));
+        index_writer.add_document(doc!(title => "shoes without category"));
+        index_writer.commit()?;
+        // the facet ordinals differ in this segment.
+        index_writer.add_document(doc!(
+            title => "shoes shoes",
+            category => Facet::from("/sales"),
+            category => Facet::from("/shoes"),
+        ));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query: Box<dyn Query> =
+            QueryParser::for_index(&index, vec![title]).parse_query("shoes")?;
+        let doc_groups = searcher.search(&query, &TopDocsByGroup::new(category, 10, 5))?;
+        assert_eq!(
+            keys(&doc_groups),
+            vec![
+                GroupKey::Facet(Facet::from("/shoes")),
+                GroupKey::Facet(Facet::from("/sales"))
+            ]
+        );
+        // the document with two facets is collected in both groups.
+        assert_eq!(doc_groups[0].top_docs.len(), 2);
+        assert_eq!(doc_groups[1].top_docs, doc_groups[0].top_docs[1..]);
+        let doc_groups = searcher.search(
+            &query,
+            &TopDocsByGroup::new(category, 10, 5).with_missing_group(),
+        )?;
+        assert_eq!(doc_groups.len(), 3);
+        Ok(())
+    }
+}
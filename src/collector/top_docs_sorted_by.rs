@@ -0,0 +1,485 @@
+use crate::collector::top_collector::{TopCollector, TopSegmentCollector};
+use crate::collector::{Collector, SegmentCollector, TopHitsCollector};
+use crate::fastfield::{FastValue, OptionalFastFieldReader};
+use crate::schema::{Field, Type};
+use crate::TantivyError;
+use crate::{DateTime, DocAddress, DocId, Order, Score, SegmentOrdinal, SegmentReader};
+
+/// A component of the sort of a [`TopDocsSortedBy`](./struct.TopDocsSortedBy.html) collector.
+#[derive(Clone, Debug)]
+pub struct SortBy {
+    key: SortKey,
+    order: Order,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum SortKey {
+    Field(Field),
+    Score,
+}
+
+impl SortBy {
+    /// Sorts the documents by the value of a single-valued u64, i64, f64, bool or date
+    /// fast field.
+    ///
+    /// The documents without a value are sorted last, regardless of the order.
+    pub fn field(field: Field, order: Order) -> SortBy {
+        SortBy {
+            key: SortKey::Field(field),
+            order,
+        }
+    }
+
+    /// Sorts the documents by their score.
+    pub fn score(order: Order) -> SortBy {
+        SortBy {
+            key: SortKey::Score,
+            order,
+        }
+    }
+}
+
+/// The value of a document for a component of the sort.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SortValue {
+    /// The value of a u64 field.
+    U64(u64),
+    /// The value of an i64 field.
+    I64(i64),
+    /// The value of a f64 field.
+    F64(f64),
+    /// The value of a bool field.
+    Bool(bool),
+    /// The value of a date field.
+    Date(DateTime),
+    /// The score of the document.
+    Score(Score),
+    /// The document has no value for the field.
+    Missing,
+}
+
+/// Collector of the top documents sorted by several fast fields and the score,
+/// each in its own order.
+///
+/// The documents are compared by the first component of the sort, then by the next
+/// components for a tie, and by increasing `DocAddress` if all of them are equal.
+///
+/// It is built by [`TopDocs::order_by`](./struct.TopDocs.html#method.order_by).
+///
+/// ```rust
+/// use tantivy::collector::{SortBy, SortValue, TopDocs};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Schema, FAST};
+/// use tantivy::{doc, DocAddress, Index, Order};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let price = schema_builder.add_u64_field("price", FAST);
+/// let rating = schema_builder.add_f64_field("rating", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(price => 20u64, rating => 4.5));
+/// index_writer.add_document(doc!(price => 10u64, rating => 3.0));
+/// index_writer.add_document(doc!(price => 10u64, rating => 4.0));
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let top_docs = TopDocs::with_limit(2).order_by(vec![
+///     SortBy::field(price, Order::Asc),
+///     SortBy::field(rating, Order::Desc),
+/// ]);
+/// let hits = searcher.search(&AllQuery, &top_docs)?;
+/// assert_eq!(
+///     hits,
+///     vec![
+///         (vec![SortValue::U64(10), SortValue::F64(4.0)], DocAddress::new(0, 2)),
+///         (vec![SortValue::U64(10), SortValue::F64(3.0)], DocAddress::new(0, 1)),
+///     ]
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub struct TopDocsSortedBy {
+    sort_by: Vec<SortBy>,
+    collector: TopCollector<Vec<u64>>,
+}
+
+impl TopDocsSortedBy {
+    pub(crate) fn new(sort_by: Vec<SortBy>, collector: TopCollector<Vec<u64>>) -> TopDocsSortedBy {
+        TopDocsSortedBy { sort_by, collector }
+    }
+
+    /// Also returns the values of the fast `fields` for each hit.
+    ///
+    /// See [`TopHitsCollector`](./struct.TopHitsCollector.html).
+    pub fn with_fast_field_values(self, fields: Vec<Field>) -> TopHitsCollector {
+        TopHitsCollector::new(self, fields)
+    }
+}
+
+/// Maps a u64 value to a sort key, so that the greater sort keys come first.
+fn to_sort_key(val: u64, order: &Order) -> u64 {
+    if order.is_asc() {
+        !val
+    } else {
+        val
+    }
+}
+
+/// Reads the sort keys of a component of the sort for the documents of a segment.
+///
+/// The values are mapped to u64 sort keys, so that a document is compared
+/// to the others with the lexicographic order of its sort keys.
+enum SortKeyReader {
+    /// Fields have two sort keys: `1` if the document has a value, `0` otherwise, and
+    /// the sort key of the value.
+    Field {
+        ff_reader: OptionalFastFieldReader<u64>,
+        value_type: Type,
+        order: Order,
+    },
+    Score(Order),
+}
+
+impl SortKeyReader {
+    fn open(sort_by: &SortBy, reader: &SegmentReader) -> crate::Result<SortKeyReader> {
+        let field = match sort_by.key {
+            SortKey::Field(field) => field,
+            SortKey::Score => return Ok(SortKeyReader::Score(sort_by.order.clone())),
+        };
+        let field_entry = reader.schema().get_field_entry(field);
+        let value_type = field_entry.field_type().value_type();
+        if !field_entry.is_fast()
+            || !matches!(
+                value_type,
+                Type::U64 | Type::I64 | Type::F64 | Type::Bool | Type::Date
+            )
+        {
+            return Err(TantivyError::SchemaError(format!(
+                "Field {:?} is not a u64, i64, f64, bool or date fast field.",
+                field_entry.name()
+            )));
+        }
+        // the u64 representation of the values preserves their order.
+        let ff_reader = reader
+            .fast_fields()
+            .typed_optional_fast_field_reader(field)?;
+        Ok(SortKeyReader::Field {
+            ff_reader,
+            value_type,
+            order: sort_by.order.clone(),
+        })
+    }
+
+    #[inline]
+    fn push_sort_keys(&self, doc: DocId, score: Score, sort_keys: &mut Vec<u64>) {
+        match self {
+            SortKeyReader::Field {
+                ff_reader, order, ..
+            } => match ff_reader.get_opt(doc) {
+                Some(val) => sort_keys.extend_from_slice(&[1, to_sort_key(val, order)]),
+                None => sort_keys.extend_from_slice(&[0, 0]),
+            },
+            SortKeyReader::Score(order) => {
+                sort_keys.push(to_sort_key(common::f64_to_u64(f64::from(score)), order));
+            }
+        }
+    }
+
+    /// Decodes the value of the component of the sort from the sort keys, and returns
+    /// the remaining sort keys.
+    fn decode<'a>(&self, sort_keys: &'a [u64], output: &mut Vec<SortValue>) -> &'a [u64] {
+        match self {
+            SortKeyReader::Field {
+                value_type, order, ..
+            } => {
+                let (has_val, sort_key) = (sort_keys[0], sort_keys[1]);
+                if has_val == 0 {
+                    output.push(SortValue::Missing);
+                } else {
+                    // the mapping of `to_sort_key` is an involution.
+                    let val = to_sort_key(sort_key, order);
+                    output.push(match value_type {
+                        Type::U64 => SortValue::U64(val),
+                        Type::I64 => SortValue::I64(i64::from_u64(val)),
+                        Type::F64 => SortValue::F64(f64::from_u64(val)),
+                        Type::Bool => SortValue::Bool(bool::from_u64(val)),
+                        _ => SortValue::Date(DateTime::from_u64(val)),
+                    });
+                }
+                &sort_keys[2..]
+            }
+            SortKeyReader::Score(order) => {
+                let val = to_sort_key(sort_keys[0], order);
+                output.push(SortValue::Score(common::u64_to_f64(val) as Score));
+                &sort_keys[1..]
+            }
+        }
+    }
+}
+
+impl Collector for TopDocsSortedBy {
+    type Fruit = Vec<(Vec<SortValue>, DocAddress)>;
+
+    type Child = TopDocsSortedBySegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        let sort_key_readers = self
+            .sort_by
+            .iter()
+            .map(|sort_by| SortKeyReader::open(sort_by, reader))
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(TopDocsSortedBySegmentCollector {
+            sort_key_readers,
+            segment_collector: self.collector.for_segment(segment_local_id, reader),
+            sort_keys: Vec::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.sort_by
+            .iter()
+            .any(|sort_by| matches!(sort_by.key, SortKey::Score))
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Vec<SortedHit>>) -> crate::Result<Self::Fruit> {
+        let hits = segment_fruits
+            .into_iter()
+            .flatten()
+            .map(|hit| (hit, ()))
+            .collect();
+        Ok(self
+            .merge_hits(hits)
+            .map(|(hit, ())| (hit.sort_values, hit.doc_address))
+            .collect())
+    }
+}
+
+impl TopDocsSortedBy {
+    /// Sorts the hits of all the segments, given with some attached data, and returns
+    /// the requested page of hits.
+    pub(crate) fn merge_hits<T>(
+        &self,
+        mut hits: Vec<(SortedHit, T)>,
+    ) -> impl Iterator<Item = (SortedHit, T)> {
+        // the hits are ordered by decreasing sort keys, and by increasing `DocAddress`.
+        hits.sort_by(|(left, _), (right, _)| {
+            right
+                .sort_keys
+                .cmp(&left.sort_keys)
+                .then_with(|| left.doc_address.cmp(&right.doc_address))
+        });
+        hits.into_iter()
+            .skip(self.collector.offset)
+            .take(self.collector.limit)
+    }
+}
+
+/// A hit of a segment, with its sort keys and the sort values decoded from them.
+pub struct SortedHit {
+    sort_keys: Vec<u64>,
+    pub(crate) sort_values: Vec<SortValue>,
+    pub(crate) doc_address: DocAddress,
+}
+
+/// Segment collector associated to `TopDocsSortedBy`.
+pub struct TopDocsSortedBySegmentCollector {
+    sort_key_readers: Vec<SortKeyReader>,
+    segment_collector: TopSegmentCollector<Vec<u64>>,
+    /// Buffer of the sort keys of the document being collected.
+    sort_keys: Vec<u64>,
+}
+
+impl SegmentCollector for TopDocsSortedBySegmentCollector {
+    type Fruit = Vec<SortedHit>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        self.sort_keys.clear();
+        for sort_key_reader in &self.sort_key_readers {
+            sort_key_reader.push_sort_keys(doc, score, &mut self.sort_keys);
+        }
+        // the sort keys are only allocated for the documents entering the top documents.
+        if self.segment_collector.is_competitive(&self.sort_keys) {
+            self.segment_collector.collect(doc, self.sort_keys.clone());
+        }
+    }
+
+    fn harvest(self) -> Vec<SortedHit> {
+        let sort_key_readers = self.sort_key_readers;
+        self.segment_collector
+            .harvest()
+            .into_iter()
+            .map(|(sort_keys, doc_address)| {
+                let mut sort_values = Vec::with_capacity(sort_key_readers.len());
+                let mut remaining_sort_keys = &sort_keys[..];
+                for sort_key_reader in &sort_key_readers {
+                    remaining_sort_keys =
+                        sort_key_reader.decode(remaining_sort_keys, &mut sort_values);
+                }
+                SortedHit {
+                    sort_keys,
+                    sort_values,
+                    doc_address,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SortBy, SortValue};
+    use crate::collector::TopDocs;
+    use crate::query::{AllQuery, QueryParser};
+    use crate::schema::{Schema, FAST, TEXT};
+    use crate::{DocAddress, Index, Order, Score};
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_top_docs_sorted_by_fields() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let price = schema_builder.add_i64_field("price", FAST);
+        let rating = schema_builder.add_f64_field("rating", FAST);
+        let date = schema_builder.add_date_field("date", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let day = |day: i64| Utc.timestamp(day * 86_400, 0);
+        index_writer.add_document(doc!(price => 10i64, rating => 4.0, date => day(1)));
+        index_writer.add_document(doc!(price => -5i64, rating => 1.5, date => day(2)));
+        index_writer.add_document(doc!(price => 10i64, rating => 4.0, date => day(3)));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(price => 10i64, rating => 4.5, date => day(4)));
+        index_writer.add_document(doc!(rating => 5.0, date => day(5)));
+        index_writer.add_document(doc!(price => 10i64, rating => 4.0, date => day(1)));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let top_docs = |limit: usize, offset: usize, sort_by: Vec<SortBy>| {
+            let collector = TopDocs::with_limit(limit)
+                .and_offset(offset)
+                .order_by(sort_by);
+            searcher.search(&AllQuery, &collector).map(|hits| {
+                hits.into_iter()
+                    .map(|(sort_values, doc_address)| {
+                        let date = match sort_values.last() {
+                            Some(SortValue::Date(date)) => date.timestamp() / 86_400,
+                            _ => unreachable!(),
+                        };
+                        (sort_values[0].clone(), date, doc_address)
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+        let price_desc_rating_asc_date_asc = vec![
+            SortBy::field(price, Order::Desc),
+            SortBy::field(rating, Order::Asc),
+            SortBy::field(date, Order::Asc),
+        ];
+        let hits = top_docs(10, 0, price_desc_rating_asc_date_asc.clone())?;
+        assert_eq!(
+            hits.iter()
+                .map(|hit| (hit.0.clone(), hit.1))
+                .collect::<Vec<_>>(),
+            vec![
+                (SortValue::I64(10), 1),
+                (SortValue::I64(10), 1),
+                (SortValue::I64(10), 3),
+                (SortValue::I64(10), 4),
+                (SortValue::I64(-5), 2),
+                // the document without a price is sorted last.
+                (SortValue::Missing, 5),
+            ]
+        );
+        // the two documents with the same values are sorted by `DocAddress`.
+        assert_eq!(hits[0].2, DocAddress::new(0, 0));
+        assert_eq!(hits[1].2, DocAddress::new(1, 2));
+        assert_eq!(top_docs(3, 2, price_desc_rating_asc_date_asc)?, hits[2..5]);
+
+        let hits = top_docs(
+            10,
+            0,
+            vec![
+                SortBy::field(price, Order::Asc),
+                SortBy::field(date, Order::Desc),
+            ],
+        )?;
+        assert_eq!(
+            hits.iter()
+                .map(|hit| (hit.0.clone(), hit.1))
+                .collect::<Vec<_>>(),
+            vec![
+                (SortValue::I64(-5), 2),
+                (SortValue::I64(10), 4),
+                (SortValue::I64(10), 3),
+                (SortValue::I64(10), 1),
+                (SortValue::I64(10), 1),
+                (SortValue::Missing, 5),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_docs_sorted_by_field_and_score() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let brand = schema_builder.add_u64_field("brand", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "shoes", brand => 2u64));
+        index_writer.add_document(doc!(title => "shoes shoes", brand => 1u64));
+        index_writer.add_document(doc!(title => "shoes shoes", brand => 2u64));
+        index_writer.add_document(doc!(title => "shoes and socks", brand => 2u64));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = QueryParser::for_index(&index, vec![title]).parse_query("shoes")?;
+        let scores = searcher.search(&query, &TopDocs::with_limit(10))?;
+        let score_of = |doc_id: u32| -> Score {
+            scores
+                .iter()
+                .find(|(_, doc_address)| doc_address.doc_id == doc_id)
+                .unwrap()
+                .0
+        };
+        for score_order in [Order::Desc, Order::Asc] {
+            let collector = TopDocs::with_limit(10).order_by(vec![
+                SortBy::field(brand, Order::Desc),
+                SortBy::score(score_order.clone()),
+            ]);
+            let hits = searcher.search(&query, &collector)?;
+            let docs: Vec<u32> = hits
+                .iter()
+                .map(|(_, doc_address)| doc_address.doc_id)
+                .collect();
+            if score_order.is_desc() {
+                assert_eq!(docs, vec![2, 0, 3, 1]);
+            } else {
+                assert_eq!(docs, vec![3, 0, 2, 1]);
+            }
+            for (sort_values, doc_address) in hits {
+                assert_eq!(
+                    sort_values[1],
+                    SortValue::Score(score_of(doc_address.doc_id))
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_docs_sorted_by_wrong_field() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "shoes"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let collector = TopDocs::with_limit(1).order_by(vec![SortBy::field(title, Order::Asc)]);
+        assert!(searcher.search(&AllQuery, &collector).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,273 @@
+use crate::collector::top_docs_sorted_by::SortedHit;
+use crate::collector::{
+    Collector, SegmentCollector, SortValue, TopDocsSortedBy, TopDocsSortedBySegmentCollector,
+};
+use crate::fastfield::{FastValue, MultiValuedFastFieldReader, OptionalFastFieldReader};
+use crate::schema::{Cardinality, Field, FieldType, Type, Value};
+use crate::{DateTime, DocAddress, DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// A hit of a [`TopHitsCollector`](./struct.TopHitsCollector.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopHit {
+    /// The values of the document for the components of the sort.
+    pub sort_values: Vec<SortValue>,
+    /// The address of the document.
+    pub doc_address: DocAddress,
+    /// The values of the document for each of the requested fast fields, in the same
+    /// order as the fields.
+    pub fast_field_values: Vec<Vec<Value>>,
+}
+
+/// Collector of the top documents sorted by several fast fields and the score, along
+/// with the values of some fast fields of each hit.
+///
+/// The hits are sorted and paginated like the ones of a
+/// [`TopDocsSortedBy`](./struct.TopDocsSortedBy.html), from which the collector is
+/// built with
+/// [`with_fast_field_values`](./struct.TopDocsSortedBy.html#method.with_fast_field_values).
+/// The values are read from the fast fields while collecting each segment, so the
+/// stored documents do not have to be loaded. The fast fields may be single or
+/// multi-valued u64, i64, f64, bool or date fields.
+///
+/// Inside a bucketing collector such as
+/// [`FiltersCollector`](./struct.FiltersCollector.html), it returns the top hits of
+/// each bucket, keeping at most `offset + limit` hits per bucket and per segment.
+///
+/// ```rust
+/// use tantivy::collector::{FiltersCollector, SortBy, TopDocs};
+/// use tantivy::query::{AllQuery, QueryParser};
+/// use tantivy::schema::{Schema, Value, FAST, STRING};
+/// use tantivy::{doc, Index, Order};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let category = schema_builder.add_text_field("category", STRING);
+/// let price = schema_builder.add_u64_field("price", FAST);
+/// let rating = schema_builder.add_f64_field("rating", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(category => "shoes", price => 30u64, rating => 4.5));
+/// index_writer.add_document(doc!(category => "shoes", price => 50u64, rating => 3.0));
+/// index_writer.add_document(doc!(category => "socks", price => 5u64, rating => 4.0));
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let query_parser = QueryParser::for_index(&index, vec![category]);
+/// let top_rated = TopDocs::with_limit(1)
+///     .order_by(vec![SortBy::field(rating, Order::Desc)])
+///     .with_fast_field_values(vec![price]);
+/// let collector = FiltersCollector::new(top_rated)
+///     .add_filter("shoes", &query_parser.parse_query("shoes")?, &searcher)?
+///     .add_filter("socks", &query_parser.parse_query("socks")?, &searcher)?;
+/// let buckets = searcher.search(&AllQuery, &collector)?;
+/// assert_eq!(buckets[0].fruit[0].fast_field_values, vec![vec![Value::U64(30)]]);
+/// assert_eq!(buckets[1].fruit[0].fast_field_values, vec![vec![Value::U64(5)]]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct TopHitsCollector {
+    top_docs: TopDocsSortedBy,
+    fields: Vec<Field>,
+}
+
+impl TopHitsCollector {
+    pub(crate) fn new(top_docs: TopDocsSortedBy, fields: Vec<Field>) -> TopHitsCollector {
+        TopHitsCollector { top_docs, fields }
+    }
+}
+
+/// Reads the values of a fast field of the hits.
+enum FastFieldValuesReader {
+    Single(OptionalFastFieldReader<u64>, Type),
+    Multi(MultiValuedFastFieldReader<u64>, Type),
+}
+
+impl FastFieldValuesReader {
+    fn open(reader: &SegmentReader, field: Field) -> crate::Result<FastFieldValuesReader> {
+        let field_entry = reader.schema().get_field_entry(field);
+        let field_type = field_entry.field_type();
+        let cardinality = match field_type {
+            FieldType::U64(options)
+            | FieldType::I64(options)
+            | FieldType::F64(options)
+            | FieldType::Bool(options) => options.get_fastfield_cardinality(),
+            FieldType::Date(options) => options.get_fastfield_cardinality(),
+            _ => None,
+        };
+        let fast_fields = reader.fast_fields();
+        let value_type = field_type.value_type();
+        match cardinality {
+            Some(Cardinality::SingleValue) => Ok(FastFieldValuesReader::Single(
+                fast_fields.typed_optional_fast_field_reader(field)?,
+                value_type,
+            )),
+            Some(Cardinality::MultiValues) => Ok(FastFieldValuesReader::Multi(
+                fast_fields.u64s_lenient(field)?,
+                value_type,
+            )),
+            None => Err(TantivyError::SchemaError(format!(
+                "Field {:?} is not a u64, i64, f64, bool or date fast field.",
+                field_entry.name()
+            ))),
+        }
+    }
+
+    fn values(&self, doc: DocId) -> Vec<Value> {
+        match self {
+            FastFieldValuesReader::Single(ff_reader, value_type) => ff_reader
+                .get_opt(doc)
+                .map(|val| to_value(val, *value_type))
+                .into_iter()
+                .collect(),
+            FastFieldValuesReader::Multi(ff_reader, value_type) => {
+                let mut vals = Vec::new();
+                ff_reader.get_vals(doc, &mut vals);
+                vals.into_iter()
+                    .map(|val| to_value(val, *value_type))
+                    .collect()
+            }
+        }
+    }
+}
+
+fn to_value(val: u64, value_type: Type) -> Value {
+    match value_type {
+        Type::U64 => Value::U64(val),
+        Type::I64 => Value::I64(i64::from_u64(val)),
+        Type::F64 => Value::F64(f64::from_u64(val)),
+        Type::Bool => Value::Bool(bool::from_u64(val)),
+        _ => Value::Date(DateTime::from_u64(val)),
+    }
+}
+
+impl Collector for TopHitsCollector {
+    type Fruit = Vec<TopHit>;
+    type Child = TopHitsSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<TopHitsSegmentCollector> {
+        let values_readers = self
+            .fields
+            .iter()
+            .map(|&field| FastFieldValuesReader::open(reader, field))
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(TopHitsSegmentCollector {
+            segment_collector: self.top_docs.for_segment(segment_local_id, reader)?,
+            values_readers,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.top_docs.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<Vec<(SortedHit, Vec<Vec<Value>>)>>,
+    ) -> crate::Result<Vec<TopHit>> {
+        let hits = segment_fruits.into_iter().flatten().collect();
+        Ok(self
+            .top_docs
+            .merge_hits(hits)
+            .map(|(hit, fast_field_values)| TopHit {
+                sort_values: hit.sort_values,
+                doc_address: hit.doc_address,
+                fast_field_values,
+            })
+            .collect())
+    }
+}
+
+/// Segment collector associated to `TopHitsCollector`.
+pub struct TopHitsSegmentCollector {
+    segment_collector: TopDocsSortedBySegmentCollector,
+    values_readers: Vec<FastFieldValuesReader>,
+}
+
+impl SegmentCollector for TopHitsSegmentCollector {
+    type Fruit = Vec<(SortedHit, Vec<Vec<Value>>)>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        self.segment_collector.collect(doc, score);
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        let values_readers = self.values_readers;
+        self.segment_collector
+            .harvest()
+            .into_iter()
+            .map(|hit| {
+                let doc = hit.doc_address.doc_id;
+                let fast_field_values = values_readers
+                    .iter()
+                    .map(|values_reader| values_reader.values(doc))
+                    .collect();
+                (hit, fast_field_values)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::{SortBy, SortValue, TopDocs};
+    use crate::query::{AllQuery, QueryParser};
+    use crate::schema::{Cardinality, IntOptions, Schema, Value, FAST, TEXT};
+    use crate::{doc, DocAddress, Index, Order};
+
+    #[test]
+    fn test_top_hits_collector() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let price = schema_builder.add_i64_field("price", FAST);
+        let tags = schema_builder.add_u64_field(
+            "tags",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let in_stock = schema_builder.add_bool_field("in_stock", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "shoes", price => 10i64, tags => 3u64));
+        index_writer.add_document(
+            doc!(title => "shoes", price => -2i64, tags => 2u64, tags => 1u64, in_stock => true),
+        );
+        index_writer.commit()?;
+        index_writer.add_document(doc!(title => "shoes", price => 10i64, in_stock => false));
+        index_writer.add_document(doc!(title => "socks", price => 20i64));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let collector = TopDocs::with_limit(2)
+            .and_offset(1)
+            .order_by(vec![SortBy::field(price, Order::Desc)])
+            .with_fast_field_values(vec![tags, in_stock, price]);
+        let query = QueryParser::for_index(&index, vec![title]).parse_query("shoes")?;
+        let hits = searcher.search(&query, &collector)?;
+        assert_eq!(hits.len(), 2);
+        // the two documents with the price 10 are sorted by `DocAddress`, the second one
+        // being in the second segment.
+        assert_eq!(hits[0].doc_address, DocAddress::new(1, 0));
+        assert_eq!(hits[0].sort_values, vec![SortValue::I64(10)]);
+        assert_eq!(
+            hits[1].fast_field_values,
+            vec![
+                vec![Value::U64(2), Value::U64(1)],
+                vec![Value::Bool(true)],
+                vec![Value::I64(-2)]
+            ]
+        );
+
+        let collector = TopDocs::with_limit(10)
+            .order_by(vec![SortBy::score(Order::Desc)])
+            .with_fast_field_values(vec![price]);
+        let hits = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(hits.len(), 4);
+        let collector = TopDocs::with_limit(10)
+            .order_by(vec![SortBy::score(Order::Desc)])
+            .with_fast_field_values(vec![title]);
+        assert!(searcher.search(&AllQuery, &collector).is_err());
+        Ok(())
+    }
+}
@@ -2,9 +2,9 @@ use super::Collector;
 use crate::collector::top_collector::{ComparableDoc, TopCollector};
 use crate::collector::tweak_score_top_collector::TweakedScoreTopCollector;
 use crate::collector::{
-    CustomScorer, CustomSegmentScorer, ScoreSegmentTweaker, ScoreTweaker, SegmentCollector,
+    CustomScorer, CustomSegmentScorer, ScoreSegmentTweaker, ScoreTweaker, SegmentCollector, SortBy,
+    TopDocsByField, TopDocsSortedBy,
 };
-use crate::fastfield::{DynamicFastFieldReader, FastFieldReader};
 use crate::query::Weight;
 use crate::schema::Field;
 use crate::DocAddress;
@@ -40,6 +40,43 @@ where
         segment_local_id: crate::SegmentOrdinal,
         segment: &SegmentReader,
     ) -> crate::Result<Self::Child> {
+        self.check_field_type(segment)?;
+        self.collector.for_segment(segment_local_id, segment)
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.collector.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+    ) -> crate::Result<Self::Fruit> {
+        let raw_result = self.collector.merge_fruits(segment_fruits)?;
+        let transformed_result = raw_result
+            .into_iter()
+            .map(|(score, doc_address)| (TFastValue::from_u64(score), doc_address))
+            .collect::<Vec<_>>();
+        Ok(transformed_result)
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        self.check_field_type(reader)?;
+        self.collector.collect_segment(weight, segment_ord, reader)
+    }
+}
+
+impl<TCollector, TFastValue> FastFieldConvertCollector<TCollector, TFastValue>
+where
+    TCollector: Collector<Fruit = Vec<(u64, DocAddress)>>,
+    TFastValue: FastValue,
+{
+    fn check_field_type(&self, segment: &SegmentReader) -> crate::Result<()> {
         let schema = segment.schema();
         let field_entry = schema.get_field_entry(self.field);
         if !field_entry.is_fast() {
@@ -58,23 +95,7 @@ where
                 requested_type
             )));
         }
-        self.collector.for_segment(segment_local_id, segment)
-    }
-
-    fn requires_scoring(&self) -> bool {
-        self.collector.requires_scoring()
-    }
-
-    fn merge_fruits(
-        &self,
-        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
-    ) -> crate::Result<Self::Fruit> {
-        let raw_result = self.collector.merge_fruits(segment_fruits)?;
-        let transformed_result = raw_result
-            .into_iter()
-            .map(|(score, doc_address)| (TFastValue::from_u64(score), doc_address))
-            .collect::<Vec<_>>();
-        Ok(transformed_result)
+        Ok(())
     }
 }
 
@@ -128,36 +149,6 @@ impl fmt::Debug for TopDocs {
     }
 }
 
-struct ScorerByFastFieldReader {
-    ff_reader: DynamicFastFieldReader<u64>,
-}
-
-impl CustomSegmentScorer<u64> for ScorerByFastFieldReader {
-    fn score(&mut self, doc: DocId) -> u64 {
-        self.ff_reader.get(doc)
-    }
-}
-
-struct ScorerByField {
-    field: Field,
-}
-
-impl CustomScorer<u64> for ScorerByField {
-    type Child = ScorerByFastFieldReader;
-
-    fn segment_scorer(&self, segment_reader: &SegmentReader) -> crate::Result<Self::Child> {
-        // We interpret this field as u64, regardless of its type, that way,
-        // we avoid needless conversion. Regardless of the fast field type, the
-        // mapping is monotonic, so it is sufficient to compute our top-K docs.
-        //
-        // The conversion will then happen only on the top-K docs.
-        let ff_reader = segment_reader
-            .fast_fields()
-            .typed_fast_field_reader(self.field)?;
-        Ok(ScorerByFastFieldReader { ff_reader })
-    }
-}
-
 impl TopDocs {
     /// Creates a top score collector, with a number of documents equal to "limit".
     ///
@@ -208,6 +199,45 @@ impl TopDocs {
         TopDocs(self.0.and_offset(offset))
     }
 
+    /// Only collects the documents following `last_hit`, the last hit of the previous page.
+    ///
+    /// The documents are sorted by decreasing score, and by increasing `DocAddress` for a
+    /// tie, so that paging with the same `Searcher` neither skips nor repeats documents.
+    /// Unlike an offset, the memory used does not grow with the number of pages.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tantivy::collector::TopDocs;
+    /// use tantivy::query::QueryParser;
+    /// use tantivy::schema::{Schema, TEXT};
+    /// use tantivy::{doc, DocAddress, Index};
+    ///
+    /// # fn main() -> tantivy::Result<()> {
+    /// let mut schema_builder = Schema::builder();
+    /// let title = schema_builder.add_text_field("title", TEXT);
+    /// let index = Index::create_in_ram(schema_builder.build());
+    /// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+    /// index_writer.add_document(doc!(title => "The Diary of Muadib"));
+    /// index_writer.add_document(doc!(title => "The Diary of a Young Girl"));
+    /// index_writer.add_document(doc!(title => "The Diary of Lena Mukhina"));
+    /// index_writer.commit()?;
+    ///
+    /// let searcher = index.reader()?.searcher();
+    /// let query = QueryParser::for_index(&index, vec![title]).parse_query("diary")?;
+    /// let first_page = searcher.search(&query, &TopDocs::with_limit(2))?;
+    /// assert_eq!(first_page.len(), 2);
+    /// let last_hit = first_page[1];
+    /// let second_page = searcher.search(&query, &TopDocs::with_limit(2).after(last_hit))?;
+    /// assert_eq!(second_page.len(), 1);
+    /// assert_eq!(second_page[0].1, DocAddress::new(0, 1));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn after(self, last_hit: (Score, DocAddress)) -> TopDocs {
+        TopDocs(self.0.after(last_hit))
+    }
+
     /// Set top-K to rank documents by a given fast field.
     ///
     /// If the field is not a fast or does not exist, this method returns successfully (it is not aware of any schema).
@@ -216,6 +246,8 @@ impl TopDocs {
     /// If the field is a FAST field but not a u64 field, search will return successfully but it will return
     /// returns a monotonic u64-representation (ie. the order is still correct) of the requested field type.
     ///
+    /// Documents without a value for the field are ranked last, as if their value was `0`.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -276,15 +308,24 @@ impl TopDocs {
     /// }
     /// ```
     ///
+    /// When the index is sorted by the field in descending order,
+    /// [`.with_early_termination()`](./struct.TopDocsByField.html#method.with_early_termination)
+    /// stops the collection of each segment once its top documents are collected.
+    ///
     /// # See also
     ///
     /// To confortably work with `u64`s, `i64`s, `f64`s, or `date`s, please refer to
     /// [.order_by_fast_field(...)](#method.order_by_fast_field) method.
-    pub fn order_by_u64_field(
-        self,
-        field: Field,
-    ) -> impl Collector<Fruit = Vec<(u64, DocAddress)>> {
-        CustomScoreTopCollector::new(ScorerByField { field }, self.0.into_tscore())
+    pub fn order_by_u64_field(self, field: Field) -> TopDocsByField {
+        TopDocsByField::new(field, self.0.into_tscore())
+    }
+
+    /// Set top-K to rank documents by several fast fields and the score,
+    /// each in its own order.
+    ///
+    /// See [`TopDocsSortedBy`](./struct.TopDocsSortedBy.html).
+    pub fn order_by(self, sort_by: Vec<SortBy>) -> TopDocsSortedBy {
+        TopDocsSortedBy::new(sort_by, self.0.into_tscore())
     }
 
     /// Set top-K to rank documents by a given fast field.
@@ -298,6 +339,9 @@ impl TopDocs {
     /// Implementation-wise, for performance reason, tantivy will manipulate the u64 representation of your fast
     /// field until the last moment.
     ///
+    /// Documents without a value for the field are ranked last, and are returned with the
+    /// value whose u64-representation is `0`, e.g. `i64::MIN` for an `i64` field.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -628,11 +672,18 @@ impl Collector for TopDocs {
     ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
         let heap_len = self.0.limit + self.0.offset;
         let mut heap: BinaryHeap<ComparableDoc<Score, DocId>> = BinaryHeap::with_capacity(heap_len);
+        let cursor = self.0.segment_cursor(segment_ord);
+        let follows_cursor = |doc: DocId, score: Score| {
+            cursor
+                .as_ref()
+                .map(|cursor| cursor.is_followed_by(doc, &score))
+                .unwrap_or(true)
+        };
 
         if let Some(delete_bitset) = reader.delete_bitset() {
             let mut threshold = Score::MIN;
             weight.for_each_pruning(threshold, reader, &mut |doc, score| {
-                if delete_bitset.is_deleted(doc) {
+                if delete_bitset.is_deleted(doc) || !follows_cursor(doc, score) {
                     return threshold;
                 }
                 let heap_item = ComparableDoc {
@@ -652,6 +703,12 @@ impl Collector for TopDocs {
             })?;
         } else {
             weight.for_each_pruning(Score::MIN, reader, &mut |doc, score| {
+                if !follows_cursor(doc, score) {
+                    if heap.len() == heap_len {
+                        return heap.peek().map(|el| el.feature).unwrap_or(Score::MIN);
+                    }
+                    return Score::MIN;
+                }
                 let heap_item = ComparableDoc {
                     feature: score,
                     doc,
@@ -707,11 +764,13 @@ mod tests {
     use super::TopDocs;
     use crate::collector::Collector;
     use crate::query::{AllQuery, Query, QueryParser};
+    use crate::schema::Term;
     use crate::schema::{Field, Schema, FAST, STORED, TEXT};
     use crate::Index;
     use crate::IndexWriter;
     use crate::Score;
     use crate::{DocAddress, DocId, SegmentReader};
+    use crate::{IndexSettings, IndexSortByField, Order};
 
     fn make_index() -> Index {
         let mut schema_builder = Schema::builder();
@@ -815,6 +874,88 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_top_collector_after() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let rating = schema_builder.add_u64_field("rating", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for segment in 0..3u64 {
+            for i in 0..20u64 {
+                // many documents share the same score and the same rating.
+                let body = match i % 3 {
+                    0 => "apple",
+                    1 => "apple apple banana",
+                    _ => "apple banana cherry",
+                };
+                let deleted = if (segment + i) % 7 == 0 {
+                    " deleted"
+                } else {
+                    ""
+                };
+                index_writer.add_document(doc!(
+                    text => format!("{}{}", body, deleted),
+                    rating => (i * segment) % 5,
+                ));
+            }
+            index_writer.commit()?;
+        }
+        index_writer.delete_term(Term::from_field_text(text, "deleted"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let query = QueryParser::for_index(&index, vec![text]).parse_query("apple banana")?;
+
+        let all_hits = searcher.search(&query, &TopDocs::with_limit(100))?;
+        assert!(all_hits.len() > 40);
+        for page_len in [1, 3, 7] {
+            let mut hits: Vec<(Score, DocAddress)> = Vec::new();
+            loop {
+                let top_docs = match hits.last() {
+                    Some(last_hit) => TopDocs::with_limit(page_len).after(*last_hit),
+                    None => TopDocs::with_limit(page_len),
+                };
+                let page = searcher.search(&query, &top_docs)?;
+                if page.is_empty() {
+                    break;
+                }
+                hits.extend(page);
+            }
+            assert_eq!(hits, all_hits);
+        }
+
+        let all_hits =
+            searcher.search(&query, &TopDocs::with_limit(100).order_by_u64_field(rating))?;
+        for page_len in [1, 4] {
+            let mut hits: Vec<(u64, DocAddress)> = Vec::new();
+            loop {
+                let mut top_docs = TopDocs::with_limit(page_len).order_by_u64_field(rating);
+                if let Some(last_hit) = hits.last() {
+                    top_docs = top_docs.after(*last_hit);
+                }
+                let page = searcher.search(&query, &top_docs)?;
+                if page.is_empty() {
+                    break;
+                }
+                hits.extend(page);
+            }
+            assert_eq!(hits, all_hits);
+        }
+
+        // the pages of a searcher are not affected by a merge of its segments.
+        let first_page = searcher.search(&query, &TopDocs::with_limit(10))?;
+        let segment_ids = index.searchable_segment_ids()?;
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        let second_page = searcher.search(&query, &TopDocs::with_limit(10).after(first_page[9]))?;
+        assert_eq!(
+            second_page[..],
+            searcher.search(&query, &TopDocs::with_limit(20))?[10..]
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_top_collector_stable_sorting() {
         let index = make_index();
@@ -945,6 +1086,60 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_top_field_collector_sorted_index() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let city = schema_builder.add_text_field("city", TEXT);
+        let timestamp = schema_builder.add_i64_field("timestamp", FAST);
+        let settings = IndexSettings {
+            sort_by_field: Some(IndexSortByField {
+                field: "timestamp".to_string(),
+                order: Order::Desc,
+            }),
+            ..Default::default()
+        };
+        let index = Index::builder()
+            .schema(schema_builder.build())
+            .settings(settings)
+            .create_in_ram()?;
+        let mut index_writer = index.writer_for_tests()?;
+        for ts in [3i64, 17, -5, 11] {
+            index_writer.add_document(doc!(city => format!("paris {}", ts), timestamp => ts));
+        }
+        index_writer.commit()?;
+        for ts in [8i64, 21, 2] {
+            index_writer.add_document(doc!(city => format!("tokyo {}", ts), timestamp => ts));
+        }
+        // a document without a value is ranked last.
+        index_writer.add_document(doc!(city => "tokyo"));
+        index_writer.delete_term(Term::from_field_text(city, "17"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        for segment_reader in searcher.segment_readers() {
+            let sort_by_field = segment_reader.sort_by_field().unwrap();
+            assert_eq!(sort_by_field.field, "timestamp");
+            assert!(sort_by_field.order.is_desc());
+        }
+        let top_timestamps = |query: &dyn Query, limit: usize, offset: usize| {
+            let top_collector = TopDocs::with_limit(limit)
+                .and_offset(offset)
+                .order_by_fast_field::<i64>(timestamp);
+            searcher
+                .search(query, &top_collector)
+                .map(|top_docs| top_docs.into_iter().map(|(ts, _)| ts).collect::<Vec<i64>>())
+        };
+        assert_eq!(top_timestamps(&AllQuery, 3, 0)?, vec![21, 11, 8]);
+        assert_eq!(top_timestamps(&AllQuery, 3, 2)?, vec![8, 3, 2]);
+        assert_eq!(
+            top_timestamps(&AllQuery, 10, 0)?,
+            vec![21, 11, 8, 3, 2, -5, i64::MIN]
+        );
+        let query_parser = QueryParser::for_index(&index, vec![city]);
+        let paris_query = query_parser.parse_query("paris")?;
+        assert_eq!(top_timestamps(&paris_query, 2, 0)?, vec![11, 3]);
+        Ok(())
+    }
+
     #[test]
     fn test_top_field_collector_f64() -> crate::Result<()> {
         let mut schema_builder = Schema::builder();
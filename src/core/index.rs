@@ -1,10 +1,17 @@
 use super::{segment::Segment, IndexSettings};
+use crate::core::index_disk_usage::{disk_usage, IndexDiskUsage};
+use crate::core::index_preload::preload;
+use crate::core::index_snapshot::{create_snapshot, IndexSnapshot};
+use crate::core::index_validation::{validate_index, ValidationOptions, ValidationReport};
 use crate::core::Executor;
 use crate::core::IndexMeta;
+use crate::core::PreparedCommitMeta;
 use crate::core::SegmentId;
 use crate::core::SegmentMeta;
 use crate::core::SegmentMetaInventory;
 use crate::core::META_FILEPATH;
+use crate::core::PREPARED_COMMITS_FILEPATH;
+use crate::core::{segment_replication, SearchableSegmentFiles, SegmentFile};
 use crate::directory::error::OpenReadError;
 use crate::directory::ManagedDirectory;
 #[cfg(feature = "mmap")]
@@ -15,20 +22,20 @@ use crate::error::DataCorruption;
 use crate::error::TantivyError;
 use crate::indexer::index_writer::{HEAP_SIZE_MIN, MAX_NUM_THREAD};
 use crate::indexer::segment_updater::save_new_metas;
+use crate::query::{Bm25Similarity, Similarity};
 use crate::reader::IndexReader;
 use crate::reader::IndexReaderBuilder;
 use crate::schema::Field;
 use crate::schema::FieldType;
 use crate::schema::Schema;
+use crate::schema::TextFieldIndexing;
 use crate::tokenizer::{TextAnalyzer, TokenizerManager};
 use crate::IndexWriter;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-
-#[cfg(feature = "mmap")]
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 fn load_metas(
     directory: &dyn Directory,
@@ -112,6 +119,7 @@ impl IndexBuilder {
     /// The index will be allocated in anonymous memory.
     /// This should only be used for unit tests.
     pub fn create_in_ram(self) -> Result<Index, TantivyError> {
+        self.index_settings.validate()?;
         let ram_directory = RamDirectory::create();
         Ok(self
             .create(ram_directory)
@@ -166,6 +174,19 @@ impl IndexBuilder {
     ///
     /// If a directory previously existed, it will be erased.
     fn create<Dir: Directory>(self, dir: Dir) -> crate::Result<Index> {
+        self.index_settings.validate()?;
+        let schema = self.get_expect_schema()?;
+        if let Some(field_name) = self
+            .index_settings
+            .similarities
+            .keys()
+            .find(|field_name| schema.get_field(field_name).is_none())
+        {
+            return Err(TantivyError::SchemaError(format!(
+                "A similarity is set for the unknown field {:?}.",
+                field_name
+            )));
+        }
         let directory = ManagedDirectory::wrap(dir)?;
         save_new_metas(
             self.get_expect_schema()?,
@@ -187,7 +208,12 @@ pub struct Index {
     settings: IndexSettings,
     executor: Arc<Executor>,
     tokenizers: TokenizerManager,
+    similarities: Arc<RwLock<HashMap<Field, Arc<dyn Similarity>>>>,
     inventory: SegmentMetaInventory,
+    // Segments which could not be opened by a reader, with their meta to keep their files.
+    quarantined_segments: Arc<RwLock<HashMap<SegmentId, SegmentMeta>>>,
+    // Segments being written by a merge.
+    merge_target_segments: Arc<RwLock<HashSet<SegmentId>>>,
 }
 
 impl Index {
@@ -287,13 +313,30 @@ impl Index {
         inventory: SegmentMetaInventory,
     ) -> Index {
         let schema = metas.schema.clone();
+        let mut similarities: HashMap<Field, Arc<dyn Similarity>> = HashMap::new();
+        for (field_name, similarity) in &metas.index_settings.similarities {
+            match schema.get_field(field_name) {
+                Some(field) => {
+                    similarities.insert(field, Arc::new(*similarity));
+                }
+                None => {
+                    warn!(
+                        "The similarity of the unknown field {:?} is ignored.",
+                        field_name
+                    );
+                }
+            }
+        }
         Index {
             settings: metas.index_settings.clone(),
             directory,
             schema,
             tokenizers: TokenizerManager::default(),
+            similarities: Arc::new(RwLock::new(similarities)),
             executor: Arc::new(Executor::single_thread()),
             inventory,
+            quarantined_segments: Arc::default(),
+            merge_target_segments: Arc::default(),
         }
     }
 
@@ -302,15 +345,63 @@ impl Index {
         &self.tokenizers
     }
 
+    /// Sets the `Similarity` used to score the given field.
+    ///
+    /// The similarity only affects scoring: changing it does not require reindexing.
+    /// It applies to all of the weights created afterwards, including the ones created
+    /// from a `Searcher` that was acquired before this call.
+    ///
+    /// The built-in similarities are saved in the `IndexSettings` by the next commit of
+    /// an `IndexWriter` created from this `Index`, or from one of its clones, and are
+    /// restored when the index is opened. Custom similarities are not saved, and need
+    /// to be set again after opening the index.
+    ///
+    /// This makes it possible, for instance, to configure the BM25 parameters
+    /// of a field with `Bm25Similarity::new(k1, b)`.
+    pub fn set_similarity<S: Similarity>(&self, field: Field, similarity: S) {
+        self.similarities
+            .write()
+            .expect("Acquiring the lock should never fail")
+            .insert(field, Arc::new(similarity));
+    }
+
+    /// Returns the `Similarity` used to score the given field.
+    ///
+    /// Unless configured with [`.set_similarity()`](#method.set_similarity), or in the
+    /// `IndexSettings`, this is `Bm25Similarity::default()`.
+    pub fn similarity(&self, field: Field) -> Arc<dyn Similarity> {
+        self.similarities
+            .read()
+            .expect("Acquiring the lock should never fail")
+            .get(&field)
+            .cloned()
+            .unwrap_or_else(|| Arc::new(Bm25Similarity::default()))
+    }
+
     /// Helper to access the tokenizer associated to a specific field.
     pub fn tokenizer_for_field(&self, field: Field) -> crate::Result<TextAnalyzer> {
+        self.tokenizer_for_field_with(field, TextFieldIndexing::tokenizer)
+    }
+
+    /// Helper to access the tokenizer used to process the queries on a specific field.
+    ///
+    /// See [`TextFieldIndexing::set_search_tokenizer()`](../schema/struct.TextFieldIndexing.html#method.set_search_tokenizer).
+    pub fn search_tokenizer_for_field(&self, field: Field) -> crate::Result<TextAnalyzer> {
+        self.tokenizer_for_field_with(field, TextFieldIndexing::search_tokenizer)
+    }
+
+    fn tokenizer_for_field_with(
+        &self,
+        field: Field,
+        tokenizer_name: fn(&TextFieldIndexing) -> &str,
+    ) -> crate::Result<TextAnalyzer> {
         let field_entry = self.schema.get_field_entry(field);
         let field_type = field_entry.field_type();
         let tokenizer_manager: &TokenizerManager = self.tokenizers();
         let tokenizer_name_opt: Option<TextAnalyzer> = match field_type {
             FieldType::Str(text_options) => text_options
                 .get_indexing_options()
-                .map(|text_indexing_options| text_indexing_options.tokenizer().to_string())
+                .map(|text_indexing_options| tokenizer_name(text_indexing_options).to_string())
                 .and_then(|tokenizer_name| tokenizer_manager.get(&tokenizer_name)),
             _ => None,
         };
@@ -346,6 +437,19 @@ impl Index {
         Index::open(mmap_directory)
     }
 
+    /// Restores the snapshot of `snapshot_dir` in `dest_dir`, and opens it.
+    ///
+    /// The files are copied, and their checksum is verified against the manifest of the
+    /// snapshot: a `DataCorruption` error is returned for the first file which does not
+    /// match. The `meta.json` is written last, so that `dest_dir` does not contain an index
+    /// if the restore fails.
+    ///
+    /// `dest_dir` must not already contain an index.
+    #[cfg(feature = "mmap")]
+    pub fn restore_snapshot(snapshot_dir: &Path, dest_dir: &Path) -> crate::Result<Index> {
+        crate::core::index_snapshot::restore_snapshot(snapshot_dir, dest_dir)
+    }
+
     /// Returns the list of the segment metas tracked by the index.
     ///
     /// Such segments can of course be part of the index,
@@ -355,6 +459,67 @@ impl Index {
         self.inventory.all()
     }
 
+    /// Replaces the segments which could not be opened by a reader.
+    ///
+    /// The quarantined segments are not merged, and their files are kept, as their
+    /// `SegmentMeta` lives, until they are released.
+    pub(crate) fn set_quarantined_segments(&self, segment_metas: Vec<SegmentMeta>) {
+        *self
+            .quarantined_segments
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = segment_metas
+            .into_iter()
+            .map(|segment_meta| (segment_meta.id(), segment_meta))
+            .collect();
+    }
+
+    /// Returns the ids of the segments which could not be opened by a reader.
+    pub(crate) fn quarantined_segment_ids(&self) -> HashSet<SegmentId> {
+        self.quarantined_segments
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// Returns true if the segment could not be opened by a reader.
+    ///
+    /// The quarantine is shared by the clones of the index, i.e. by a reader and a writer
+    /// created from the same `Index`.
+    pub(crate) fn is_quarantined(&self, segment_id: SegmentId) -> bool {
+        self.quarantined_segments
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .contains_key(&segment_id)
+    }
+
+    /// Records that a merge writes the segment `segment_id`, or that it ended if
+    /// `is_merging` is false.
+    pub(crate) fn set_merge_target(&self, segment_id: SegmentId, is_merging: bool) {
+        let mut merge_target_segments = self
+            .merge_target_segments
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if is_merging {
+            merge_target_segments.insert(segment_id);
+        } else {
+            merge_target_segments.remove(&segment_id);
+        }
+    }
+
+    /// Returns the ids of the segments being written by a merge.
+    pub(crate) fn merge_target_segment_ids(&self) -> HashSet<SegmentId> {
+        self.merge_target_segments
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub(crate) fn inventory(&self) -> &SegmentMetaInventory {
+        &self.inventory
+    }
+
     /// Creates a new segment_meta (Advanced user only).
     ///
     /// As long as the `SegmentMeta` lives, the files associated with the
@@ -378,6 +543,37 @@ impl Index {
         load_metas(self.directory(), &self.inventory)
     }
 
+    /// Returns the commits prepared with
+    /// [`IndexWriter::prepare_commit_with_id`](./struct.IndexWriter.html#method.prepare_commit_with_id)
+    /// which were neither finalized nor aborted, including the ones prepared before
+    /// the index was reopened.
+    pub fn list_prepared_commits(&self) -> crate::Result<Vec<PreparedCommitMeta>> {
+        let prepared_commits_data = match self.directory().atomic_read(&PREPARED_COMMITS_FILEPATH) {
+            Ok(prepared_commits_data) => prepared_commits_data,
+            Err(OpenReadError::FileDoesNotExist(_)) => {
+                return Ok(Vec::new());
+            }
+            Err(err) => {
+                return Err(err.into());
+            }
+        };
+        let prepared_commits_string = String::from_utf8(prepared_commits_data).map_err(|_| {
+            DataCorruption::new(
+                PREPARED_COMMITS_FILEPATH.to_path_buf(),
+                "Prepared commits file does not contain valid utf8 file.".to_string(),
+            )
+        })?;
+        PreparedCommitMeta::deserialize_list(&prepared_commits_string, &self.inventory).map_err(
+            |e| {
+                DataCorruption::new(
+                    PREPARED_COMMITS_FILEPATH.to_path_buf(),
+                    format!("Prepared commits file cannot be deserialized. {:?}", e),
+                )
+                .into()
+            },
+        )
+    }
+
     /// Open a new index writer. Attempts to acquire a lockfile.
     ///
     /// The lockfile should be deleted on drop, but it is possible
@@ -403,6 +599,7 @@ impl Index {
         num_threads: usize,
         overall_heap_size_in_bytes: usize,
     ) -> crate::Result<IndexWriter> {
+        self.settings.validate()?;
         let directory_lock = self
             .directory
             .acquire_lock(&INDEX_WRITER_LOCK)
@@ -462,6 +659,27 @@ impl Index {
         &self.settings
     }
 
+    /// Returns the index settings to save in the `meta.json`, with the built-in
+    /// similarities currently set on the fields.
+    pub(crate) fn settings_to_save(&self) -> IndexSettings {
+        let mut settings = self.settings.clone();
+        settings.similarities = self
+            .similarities
+            .read()
+            .expect("Acquiring the lock should never fail")
+            .iter()
+            .filter_map(|(&field, similarity)| {
+                let builtin = similarity.as_builtin()?;
+                if builtin.is_default_bm25() {
+                    return None;
+                }
+                let field_name = self.schema.get_field_name(field).to_string();
+                Some((field_name, builtin))
+            })
+            .collect();
+        settings
+    }
+
     /// Accessor to the index settings
     ///
     pub fn settings_mut(&mut self) -> &mut IndexSettings {
@@ -541,6 +759,123 @@ impl Index {
         }
         Ok(damaged_files)
     }
+
+    /// Verifies the files and the structure of the searchable segments.
+    ///
+    /// For each segment, the checksums of all of its files are verified. If they are all
+    /// intact, the segment is opened and its files are cross-checked: the delete bitset,
+    /// the doc store blocks, the fast field columns and the field norms against its
+    /// `max_doc`, and the term dictionary against the postings and positions files.
+    /// With `ValidationOptions::full`, all of the posting lists are read too.
+    ///
+    /// All of the problems found are listed in the report, with the segment and, if
+    /// possible, the file they belong to. An error is only returned if the `meta.json`
+    /// cannot be read, or if the directory fails.
+    pub fn validate(&self, options: ValidationOptions) -> crate::Result<ValidationReport> {
+        validate_index(self, options)
+    }
+
+    /// Reads the files of the searchable segments, so that their pages are in memory,
+    /// and returns the number of bytes read.
+    ///
+    /// With `None`, all of the files are read. With some fields, only the parts of the
+    /// postings, positions, term dictionary, fast fields, field norms and term vectors
+    /// files belonging to these fields are read.
+    ///
+    /// This is meant to warm up an index stored in a `MmapDirectory` before the first
+    /// queries, which would otherwise pay for the page faults.
+    pub fn preload(&self, fields: Option<&[Field]>) -> crate::Result<u64> {
+        preload(self, fields)
+    }
+
+    /// Returns the size of the files of the index, split between the files of the last
+    /// commit, the other files still in use, the files being written by the merges, and
+    /// the files which will be deleted by the next garbage collection.
+    ///
+    /// The merges and the uncommitted segments are only known within the process of the
+    /// `IndexWriter`, and to the clones of the `Index` it was created from.
+    pub fn disk_usage(&self) -> crate::Result<IndexDiskUsage> {
+        disk_usage(self)
+    }
+
+    /// Returns the `meta.json` of the last commit, and the files of its segments.
+    ///
+    /// This is meant to replicate the index: the files are returned as they are stored,
+    /// with their footer, and can be sent to a replica along with their serialized
+    /// `SegmentMeta`, to be imported with [`Index::import_segment`](#method.import_segment).
+    /// The files of a segment never change, but its delete file, which can be sent again
+    /// with [`Index::import_segment_deletes`](#method.import_segment_deletes).
+    ///
+    /// The files are not garbage collected by an `IndexWriter` created from this index, or
+    /// one of its clones, as long as the returned object lives.
+    pub fn searchable_segment_files(&self) -> crate::Result<SearchableSegmentFiles> {
+        segment_replication::searchable_segment_files(self)
+    }
+
+    /// Imports a segment of another index, replicated with
+    /// [`Index::searchable_segment_files`](#method.searchable_segment_files), and publishes
+    /// it, retiring the segments of `retired_segment_ids`, e.g. the segments which were
+    /// merged into the imported segment.
+    ///
+    /// The checksums of the files are verified before they are written to the directory.
+    /// The `meta.json` is then updated atomically, and the files of the retired segments are
+    /// deleted, unless they are still used by the readers of this index.
+    ///
+    /// The index must have the same schema as the index the segment comes from, and must not
+    /// have an `IndexWriter`: a `LockFailure` error is returned otherwise.
+    pub fn import_segment(
+        &self,
+        files: &[SegmentFile],
+        segment_meta: &SegmentMeta,
+        retired_segment_ids: &[SegmentId],
+    ) -> crate::Result<()> {
+        segment_replication::import_segment(self, files, segment_meta, retired_segment_ids)
+    }
+
+    /// Imports the delete file of a segment which was already imported with
+    /// [`Index::import_segment`](#method.import_segment), and publishes it.
+    ///
+    /// As for `import_segment`, the checksum of the file is verified, and the index must
+    /// not have an `IndexWriter`. Importing the same deletes twice does nothing.
+    pub fn import_segment_deletes(
+        &self,
+        delete_file: &SegmentFile,
+        segment_meta: &SegmentMeta,
+    ) -> crate::Result<()> {
+        segment_replication::import_segment_deletes(self, delete_file, segment_meta)
+    }
+
+    /// Creates a snapshot of the last commit in `snapshot_dir`.
+    ///
+    /// The `meta.json` and the files of its segments are hard linked in `snapshot_dir`
+    /// if the index is stored on the same filesystem, or copied otherwise. A manifest,
+    /// listing the files with their checksum, is written last.
+    ///
+    /// The files of the snapshot are not garbage collected by an `IndexWriter` created
+    /// from this index, or one of its clones, as long as the returned `IndexSnapshot`
+    /// lives.
+    ///
+    /// The snapshot can be restored with
+    /// [`Index::restore_snapshot`](#method.restore_snapshot).
+    pub fn create_snapshot(&self, snapshot_dir: &Path) -> crate::Result<IndexSnapshot> {
+        create_snapshot(self, snapshot_dir, None)
+    }
+
+    /// Creates a snapshot of the last commit in `snapshot_dir`, reusing the files of the
+    /// snapshot of `previous_snapshot_dir`.
+    ///
+    /// The files having the same name and the same checksum as a file of the previous
+    /// snapshot are hard linked from the previous snapshot, instead of the index, so that
+    /// usually only the segments created since the previous snapshot are copied when
+    /// the snapshots are on another filesystem.
+    /// The snapshot does not depend on the previous one, which can then be deleted.
+    pub fn create_incremental_snapshot(
+        &self,
+        snapshot_dir: &Path,
+        previous_snapshot_dir: &Path,
+    ) -> crate::Result<IndexSnapshot> {
+        create_snapshot(self, snapshot_dir, Some(previous_snapshot_dir))
+    }
 }
 
 impl fmt::Debug for Index {
@@ -551,8 +886,11 @@ impl fmt::Debug for Index {
 
 #[cfg(test)]
 mod tests {
+    use crate::collector::Count;
+    use crate::indexer::NoMergePolicy;
+    use crate::query::TermQuery;
     use crate::schema::Field;
-    use crate::schema::{Schema, INDEXED, TEXT};
+    use crate::schema::{IndexRecordOption, Schema, INDEXED, TEXT};
     use crate::IndexReader;
     use crate::ReloadPolicy;
     use crate::{
@@ -560,6 +898,8 @@ mod tests {
         IndexSettings,
     };
     use crate::{Directory, Index};
+    use crate::{SegmentComponent, SegmentLoadPolicy, TantivyError, Term};
+    use futures::executor::block_on;
 
     #[test]
     fn test_indexer_for_field() {
@@ -803,4 +1143,76 @@ mod tests {
             mem_right_after_commit
         );
     }
+
+    #[test]
+    fn test_reader_skips_corrupted_segments() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create(
+            RamDirectory::create(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut writer = index.writer_for_tests()?;
+        writer.set_merge_policy(Box::new(NoMergePolicy));
+        for &text in &["hello a", "hello b", "hello c"] {
+            writer.add_document(doc!(text_field => text));
+            writer.add_document(doc!(text_field => text));
+            writer.commit()?;
+        }
+        let segment_metas = index.searchable_segment_metas()?;
+        assert_eq!(segment_metas.len(), 3);
+        let corrupted_segment_id = segment_metas[1].id();
+        let store_path = segment_metas[1].relative_path(SegmentComponent::Store);
+        // The segment metas must not be kept alive by the test.
+        drop(segment_metas);
+        let mut store_data = index.directory().atomic_read(&store_path)?;
+        store_data[0] ^= 1;
+        index.directory().atomic_write(&store_path, &store_data)?;
+
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .segment_load_policy(SegmentLoadPolicy::SkipCorrupted)
+            .try_into()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        assert!(searcher
+            .segment_readers()
+            .iter()
+            .all(|segment_reader| segment_reader.segment_id() != corrupted_segment_id));
+        let hello_query = TermQuery::new(
+            Term::from_field_text(text_field, "hello"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&hello_query, &Count)?, 4);
+        let quarantined_segments = reader.quarantined_segments();
+        assert_eq!(quarantined_segments.len(), 1);
+        assert_eq!(quarantined_segments[0].0, corrupted_segment_id);
+        assert!(matches!(
+            quarantined_segments[0].1,
+            TantivyError::DataCorruption(_)
+        ));
+
+        // The quarantined segment is neither merged nor garbage collected.
+        writer.add_document(doc!(text_field => "hello d"));
+        writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        assert!(matches!(
+            block_on(writer.merge(&segment_ids)),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        writer.delete_all_documents()?;
+        writer.commit()?;
+        assert!(index.searchable_segment_ids()?.is_empty());
+        block_on(writer.garbage_collect_files())?;
+        assert!(index.directory().exists(&store_path)?);
+
+        // The segment is released once it is no longer in the index.
+        reader.reload()?;
+        assert!(reader.quarantined_segments().is_empty());
+        block_on(writer.garbage_collect_files())?;
+        assert!(!index.directory().exists(&store_path)?);
+        Ok(())
+    }
 }
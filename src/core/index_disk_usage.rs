@@ -0,0 +1,143 @@
+use crate::core::{Index, SegmentId};
+use crate::directory::error::OpenReadError;
+use common::HasLen;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// The size of the files of an index, returned by
+/// [`Index::disk_usage`](./struct.Index.html#method.disk_usage).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct IndexDiskUsage {
+    /// Size of the files of the segments of the last commit, with their deletes.
+    pub live_segments_num_bytes: u64,
+    /// Size of the files of the other segments which are still in use: the segments of
+    /// the `IndexWriter` which are not committed yet, the segments of the prepared
+    /// commits, and the segments of previous commits still used by a searcher or a
+    /// snapshot.
+    pub uncommitted_segments_num_bytes: u64,
+    /// Size of the files written so far by the running merges.
+    pub merge_temporaries_num_bytes: u64,
+    /// Size of the files which are no longer used, and will be deleted by the next
+    /// garbage collection.
+    pub garbage_num_bytes: u64,
+}
+
+impl IndexDiskUsage {
+    /// Returns the size of all of the files of the index.
+    pub fn total_num_bytes(&self) -> u64 {
+        self.live_segments_num_bytes
+            + self.uncommitted_segments_num_bytes
+            + self.merge_temporaries_num_bytes
+            + self.garbage_num_bytes
+    }
+}
+
+fn segment_id_of_file(path: &Path) -> Option<SegmentId> {
+    path.to_str()
+        .and_then(|filename| filename.split('.').next())
+        .and_then(|uuid_string| SegmentId::from_uuid_string(uuid_string).ok())
+}
+
+pub(crate) fn disk_usage(index: &Index) -> crate::Result<IndexDiskUsage> {
+    // The metas are read before listing the files, so that the files created meanwhile
+    // are not mistaken for garbage.
+    let committed_files: HashSet<PathBuf> = index
+        .load_metas()?
+        .segments
+        .iter()
+        .flat_map(|segment_meta| segment_meta.list_files())
+        .collect();
+    let mut used_files: HashSet<PathBuf> = index
+        .list_all_segment_metas()
+        .iter()
+        .flat_map(|segment_meta| segment_meta.list_files())
+        .collect();
+    for prepared_commit in index.list_prepared_commits()? {
+        for segment_meta in &prepared_commit.index_meta.segments {
+            used_files.extend(segment_meta.list_files());
+        }
+    }
+    let merge_target_segment_ids = index.merge_target_segment_ids();
+
+    let mut disk_usage = IndexDiskUsage::default();
+    let directory = index.directory();
+    for path in directory.list_managed_files() {
+        let num_bytes = match directory.open_read_with_footer(&path) {
+            Ok(file_slice) => file_slice.len() as u64,
+            // The file was deleted since it was listed.
+            Err(OpenReadError::FileDoesNotExist(_)) => continue,
+            Err(open_read_error) => return Err(open_read_error.into()),
+        };
+        let segment_id_opt = segment_id_of_file(&path);
+        if committed_files.contains(&path) || segment_id_opt.is_none() {
+            disk_usage.live_segments_num_bytes += num_bytes;
+        } else if segment_id_opt
+            .map(|segment_id| merge_target_segment_ids.contains(&segment_id))
+            .unwrap_or(false)
+        {
+            disk_usage.merge_temporaries_num_bytes += num_bytes;
+        } else if used_files.contains(&path) {
+            disk_usage.uncommitted_segments_num_bytes += num_bytes;
+        } else {
+            disk_usage.garbage_num_bytes += num_bytes;
+        }
+    }
+    Ok(disk_usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{SegmentComponent, SegmentId};
+    use crate::directory::{Directory, TerminatingWrite};
+    use crate::indexer::NoMergePolicy;
+    use crate::schema::{Schema, TEXT};
+    use crate::{doc, Index};
+    use std::io::Write;
+
+    #[test]
+    fn test_disk_usage() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_document(doc!(text => "hello"));
+        index_writer.commit()?;
+        let disk_usage = index.disk_usage()?;
+        assert!(disk_usage.live_segments_num_bytes > 0);
+        assert_eq!(disk_usage.uncommitted_segments_num_bytes, 0);
+        assert_eq!(disk_usage.merge_temporaries_num_bytes, 0);
+        assert_eq!(disk_usage.garbage_num_bytes, 0);
+
+        index_writer.add_document(doc!(text => "happy tax payer"));
+        let prepared_commit = index_writer.prepare_commit()?;
+        let disk_usage_prepared = index.disk_usage()?;
+        assert_eq!(
+            disk_usage_prepared.live_segments_num_bytes,
+            disk_usage.live_segments_num_bytes
+        );
+        assert!(disk_usage_prepared.uncommitted_segments_num_bytes > 0);
+        prepared_commit.commit()?;
+
+        // A file of a segment the index does not know is garbage, unless a merge is
+        // writing the segment.
+        let orphan_meta = index.new_segment_meta(SegmentId::generate_random(), 0);
+        let orphan_path = orphan_meta.relative_path(SegmentComponent::Postings);
+        drop(orphan_meta);
+        let mut wrt = index.directory().open_write(&orphan_path)?;
+        wrt.write_all(b"orphan")?;
+        wrt.terminate()?;
+        let garbage_num_bytes = index.disk_usage()?.garbage_num_bytes;
+        assert!(garbage_num_bytes > 0);
+        let orphan_segment_id = super::segment_id_of_file(&orphan_path).unwrap();
+        index.set_merge_target(orphan_segment_id, true);
+        let disk_usage_merging = index.disk_usage()?;
+        assert_eq!(disk_usage_merging.garbage_num_bytes, 0);
+        assert_eq!(
+            disk_usage_merging.merge_temporaries_num_bytes,
+            garbage_num_bytes
+        );
+        index.set_merge_target(orphan_segment_id, false);
+        Ok(())
+    }
+}
@@ -1,9 +1,13 @@
 use super::SegmentComponent;
+use crate::directory::Durability;
+use crate::query::BuiltinSimilarity;
 use crate::schema::Schema;
 use crate::Opstamp;
+use crate::TantivyError;
 use crate::{core::SegmentId, store::Compressor};
 use census::{Inventory, TrackedObject};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::{collections::HashSet, sync::atomic::AtomicBool};
 use std::{fmt, sync::Arc};
@@ -35,6 +39,7 @@ impl SegmentMetaInventory {
             max_doc,
             include_temp_doc_store: Arc::new(AtomicBool::new(true)),
             deletes: None,
+            num_bytes: None,
         };
         SegmentMeta::from(self.inventory.track(inner))
     }
@@ -44,6 +49,10 @@ impl SegmentMetaInventory {
 ///
 /// For instance the number of docs it contains,
 /// how many are deleted, etc.
+///
+/// A deserialized `SegmentMeta` is not tracked by any index: it is meant to be sent to
+/// a replica, to import the segment with
+/// [`Index::import_segment`](../struct.Index.html#method.import_segment).
 #[derive(Clone)]
 pub struct SegmentMeta {
     tracked: TrackedObject<InnerSegmentMeta>,
@@ -67,6 +76,16 @@ impl serde::Serialize for SegmentMeta {
     }
 }
 
+impl<'de> serde::Deserialize<'de> for SegmentMeta {
+    fn deserialize<D>(deserializer: D) -> Result<SegmentMeta, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let inner_segment_meta = InnerSegmentMeta::deserialize(deserializer)?;
+        Ok(inner_segment_meta.track(&SegmentMetaInventory::default()))
+    }
+}
+
 impl From<TrackedObject<InnerSegmentMeta>> for SegmentMeta {
     fn from(tracked: TrackedObject<InnerSegmentMeta>) -> SegmentMeta {
         SegmentMeta { tracked }
@@ -138,6 +157,7 @@ impl SegmentMeta {
             SegmentComponent::FastFields => ".fast".to_string(),
             SegmentComponent::FieldNorms => ".fieldnorm".to_string(),
             SegmentComponent::Delete => format!(".{}.del", self.delete_opstamp().unwrap_or(0)),
+            SegmentComponent::TermVectors => ".termvec".to_string(),
         });
         PathBuf::from(path)
     }
@@ -156,6 +176,14 @@ impl SegmentMeta {
         self.max_doc() - self.num_deleted_docs()
     }
 
+    /// Returns the size of the files of the segment in bytes, deletes excluded.
+    ///
+    /// The size is `None` for the segments written by a version of tantivy that did not
+    /// record it.
+    pub fn num_bytes(&self) -> Option<u64> {
+        self.tracked.num_bytes
+    }
+
     /// Returns the `Opstamp` of the last delete operation
     /// taken in account in this segment.
     pub fn delete_opstamp(&self) -> Option<Opstamp> {
@@ -171,6 +199,18 @@ impl SegmentMeta {
         self.num_deleted_docs() > 0
     }
 
+    /// Returns a copy of the segment meta, tracked by `inventory`.
+    pub(crate) fn track(&self, inventory: &SegmentMetaInventory) -> SegmentMeta {
+        InnerSegmentMeta {
+            segment_id: self.tracked.segment_id,
+            max_doc: self.tracked.max_doc,
+            deletes: self.tracked.deletes.clone(),
+            include_temp_doc_store: default_temp_store(),
+            num_bytes: self.tracked.num_bytes,
+        }
+        .track(inventory)
+    }
+
     /// Updates the max_doc value from the `SegmentMeta`.
     ///
     /// This method is only used when updating `max_doc` from 0
@@ -183,6 +223,19 @@ impl SegmentMeta {
             max_doc,
             deletes: None,
             include_temp_doc_store: Arc::new(AtomicBool::new(true)),
+            num_bytes: None,
+        });
+        SegmentMeta { tracked }
+    }
+
+    /// Updates the size of the files of the segment, once the segment is written.
+    pub(crate) fn with_num_bytes(self, num_bytes: u64) -> SegmentMeta {
+        let tracked = self.tracked.map(move |inner_meta| InnerSegmentMeta {
+            segment_id: inner_meta.segment_id,
+            max_doc: inner_meta.max_doc,
+            deletes: inner_meta.deletes.clone(),
+            include_temp_doc_store: inner_meta.include_temp_doc_store.clone(),
+            num_bytes: Some(num_bytes),
         });
         SegmentMeta { tracked }
     }
@@ -198,6 +251,7 @@ impl SegmentMeta {
             max_doc: inner_meta.max_doc,
             include_temp_doc_store: Arc::new(AtomicBool::new(true)),
             deletes: Some(delete_meta),
+            num_bytes: inner_meta.num_bytes,
         });
         SegmentMeta { tracked }
     }
@@ -213,6 +267,8 @@ struct InnerSegmentMeta {
     #[serde(skip)]
     #[serde(default = "default_temp_store")]
     pub(crate) include_temp_doc_store: Arc<AtomicBool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    num_bytes: Option<u64>,
 }
 fn default_temp_store() -> Arc<AtomicBool> {
     Arc::new(AtomicBool::new(false))
@@ -230,7 +286,7 @@ impl InnerSegmentMeta {
 ///
 /// Contains settings which are applied on the whole
 /// index, like presort documents.
-#[derive(Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct IndexSettings {
     /// Sorts the documents by information
     /// provided in `IndexSortByField`
@@ -239,6 +295,117 @@ pub struct IndexSettings {
     /// The `Compressor` used to compress the doc store.
     #[serde(default)]
     pub docstore_compression: Compressor,
+    /// The compression level of the doc store, if the `Compressor` supports
+    /// several levels. The default level of the compressor is used if `None`.
+    ///
+    /// Only brotli and zstd support compression levels, from 0 to 11 and from 1 to 22
+    /// respectively.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docstore_compress_level: Option<i32>,
+    /// The number of bytes of documents compressed together in a block of the doc store.
+    ///
+    /// Larger blocks compress better, but fetching a document requires decompressing
+    /// its whole block.
+    #[serde(default = "default_docstore_blocksize")]
+    pub docstore_blocksize: usize,
+    /// The size in bytes of the dictionary of the doc store, or `None` to compress the blocks
+    /// without a dictionary.
+    ///
+    /// The dictionary is trained on documents sampled from the first blocks of each segment,
+    /// including merged segments, which helps compressing small documents sharing a lot of
+    /// content. Only lz4 and zstd support dictionaries, of at most 64KB and 1MB respectively.
+    /// As lz4 only looks 64KB back, its dictionary should be small compared to the block size.
+    ///
+    /// When segments are merged, the merged segment is compressed with its own dictionary,
+    /// trained on its first documents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docstore_dictionary_size: Option<usize>,
+    /// Which writes of a commit are synced to the disk.
+    ///
+    /// Unlike the other settings, the durability is not saved in the `meta.json`: it only
+    /// applies to the writers of the `Index` it is set on, and is `Durability::Full` when
+    /// an index is opened. See [`Durability`](../directory/enum.Durability.html) for the
+    /// guarantees of each level.
+    #[serde(skip)]
+    pub durability: Durability,
+    /// The maximum size in bytes of the segments of the index, or `None` for no limit.
+    ///
+    /// Once the segments of an `IndexWriter`, committed or not, and the files written by
+    /// its running merges exceed this size,
+    /// [`IndexWriter::try_add_document`](../struct.IndexWriter.html#method.try_add_document),
+    /// `try_update_document`, `try_run` and `add_documents` return
+    /// `TantivyError::IndexFull`. The infallible `add_document`, `update_document` and
+    /// `run` do not enforce the limit, nor do the deletes. The merges which would not fit within the
+    /// limit are not started: as the merged segments are only deleted once the merge
+    /// ends, a merge requires as many bytes as its segments.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_index_size_bytes: Option<u64>,
+    /// The similarities of the fields, by field name, for the fields not scored with
+    /// the default BM25 similarity.
+    ///
+    /// The similarities set with
+    /// [`Index::set_similarity`](../struct.Index.html#method.set_similarity) are saved
+    /// here at the next commit, unless they are custom similarities.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub similarities: BTreeMap<String, BuiltinSimilarity>,
+}
+
+fn default_docstore_blocksize() -> usize {
+    16_384
+}
+
+impl Default for IndexSettings {
+    fn default() -> IndexSettings {
+        IndexSettings {
+            sort_by_field: None,
+            docstore_compression: Compressor::default(),
+            docstore_compress_level: None,
+            docstore_blocksize: default_docstore_blocksize(),
+            docstore_dictionary_size: None,
+            durability: Durability::default(),
+            max_index_size_bytes: None,
+            similarities: BTreeMap::new(),
+        }
+    }
+}
+
+impl IndexSettings {
+    /// Returns an error if the doc store settings are not supported.
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        if self.docstore_blocksize == 0 {
+            return Err(TantivyError::InvalidArgument(
+                "The block size of the doc store must be strictly positive.".to_string(),
+            ));
+        }
+        if let Some(dictionary_size) = self.docstore_dictionary_size {
+            let max_dictionary_size =
+                self.docstore_compression
+                    .max_dictionary_size()
+                    .ok_or_else(|| {
+                        TantivyError::InvalidArgument(format!(
+                            "The {:?} compressor does not support dictionaries.",
+                            self.docstore_compression
+                        ))
+                    })?;
+            if dictionary_size == 0 || dictionary_size > max_dictionary_size {
+                return Err(TantivyError::InvalidArgument(format!(
+                    "The size of the dictionary of the doc store must be between 1 and {}, got {}.",
+                    max_dictionary_size, dictionary_size
+                )));
+            }
+        }
+        if let Some(compress_level) = self.docstore_compress_level {
+            self.docstore_compression
+                .check_compress_level(compress_level)
+                .map_err(TantivyError::InvalidArgument)?;
+        }
+        for similarity in self.similarities.values() {
+            similarity
+                .validate()
+                .map_err(TantivyError::InvalidArgument)?;
+        }
+        Ok(())
+    }
 }
 /// Settings to presort the documents in an index
 ///
@@ -253,7 +420,7 @@ pub struct IndexSortByField {
     pub order: Order,
 }
 /// The order to sort by
-#[derive(Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub enum Order {
     /// Ascending Order
     Asc,
@@ -349,6 +516,11 @@ impl IndexMeta {
         let untracked_meta_json: UntrackedIndexMeta = serde_json::from_str(meta_json)?;
         Ok(untracked_meta_json.track(inventory))
     }
+
+    /// Returns the payload associated to the last commit, if any.
+    pub fn payload(&self) -> Option<&str> {
+        self.payload.as_deref()
+    }
 }
 
 impl fmt::Debug for IndexMeta {
@@ -362,6 +534,54 @@ impl fmt::Debug for IndexMeta {
     }
 }
 
+/// A commit prepared with
+/// [`IndexWriter::prepare_commit_with_id`](../struct.IndexWriter.html#method.prepare_commit_with_id),
+/// which was neither finalized nor aborted.
+///
+/// The prepared commits are saved in the `.prepared_commits.json` file, and survive
+/// a restart of the process.
+#[derive(Clone, Debug, Serialize)]
+pub struct PreparedCommitMeta {
+    /// The id given when preparing the commit, e.g. the id of a transaction of another system.
+    pub external_id: String,
+    /// Opstamp of the commit on top of which the commit was prepared.
+    ///
+    /// The prepared commit can only be finalized if the index was not committed since.
+    pub base_opstamp: Opstamp,
+    /// The `IndexMeta` saved in `meta.json` when the prepared commit is finalized.
+    pub index_meta: IndexMeta,
+}
+
+#[derive(Deserialize)]
+struct UntrackedPreparedCommitMeta {
+    external_id: String,
+    base_opstamp: Opstamp,
+    index_meta: UntrackedIndexMeta,
+}
+
+impl PreparedCommitMeta {
+    /// Returns the opstamp of the prepared commit.
+    pub fn opstamp(&self) -> Opstamp {
+        self.index_meta.opstamp
+    }
+
+    pub(crate) fn deserialize_list(
+        prepared_commits_json: &str,
+        inventory: &SegmentMetaInventory,
+    ) -> serde_json::Result<Vec<PreparedCommitMeta>> {
+        let untracked_prepared_commits: Vec<UntrackedPreparedCommitMeta> =
+            serde_json::from_str(prepared_commits_json)?;
+        Ok(untracked_prepared_commits
+            .into_iter()
+            .map(|untracked_prepared_commit| PreparedCommitMeta {
+                external_id: untracked_prepared_commit.external_id,
+                base_opstamp: untracked_prepared_commit.base_opstamp,
+                index_meta: untracked_prepared_commit.index_meta.track(inventory),
+            })
+            .collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -394,7 +614,7 @@ mod tests {
         let json = serde_json::ser::to_string(&index_metas).expect("serialization failed");
         assert_eq!(
             json,
-            r#"{"index_settings":{"sort_by_field":{"field":"text","order":"Asc"},"docstore_compression":"lz4"},"segments":[],"schema":[{"name":"text","type":"text","options":{"indexing":{"record":"position","tokenizer":"default"},"stored":false}}],"opstamp":0}"#
+            r#"{"index_settings":{"sort_by_field":{"field":"text","order":"Asc"},"docstore_compression":"lz4","docstore_blocksize":16384},"segments":[],"schema":[{"name":"text","type":"text","options":{"indexing":{"record":"position","tokenizer":"default"},"stored":false}}],"opstamp":0}"#
         );
     }
 }
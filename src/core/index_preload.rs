@@ -0,0 +1,107 @@
+use crate::core::{Index, Segment, SegmentComponent};
+use crate::directory::error::OpenReadError;
+use crate::directory::{CompositeFile, FileSlice};
+use crate::schema::Field;
+use common::HasLen;
+
+/// Components split by field, which can be preloaded for a subset of the fields.
+const FIELD_COMPONENTS: [SegmentComponent; 6] = [
+    SegmentComponent::Postings,
+    SegmentComponent::Positions,
+    SegmentComponent::Terms,
+    SegmentComponent::FastFields,
+    SegmentComponent::FieldNorms,
+    SegmentComponent::TermVectors,
+];
+
+/// Size of the pages touched by the preload.
+const PAGE_SIZE: usize = 4_096;
+
+/// Reads a byte of each page of the file slice, and returns its length.
+fn touch(file_slice: &FileSlice) -> crate::Result<u64> {
+    let bytes = file_slice.read_bytes()?;
+    let data = bytes.as_slice();
+    for offset in (0..data.len()).step_by(PAGE_SIZE) {
+        // The read must not be optimized away, for the page to be faulted in.
+        unsafe {
+            std::ptr::read_volatile(&data[offset]);
+        }
+    }
+    Ok(data.len() as u64)
+}
+
+/// Opens a component of the segment, or returns `None` if its file does not exist or
+/// is empty.
+fn open_component(
+    segment: &Segment,
+    component: SegmentComponent,
+) -> crate::Result<Option<FileSlice>> {
+    match segment.open_read(component) {
+        Ok(file_slice) if !file_slice.is_empty() => Ok(Some(file_slice)),
+        Ok(_) | Err(OpenReadError::FileDoesNotExist(_)) => Ok(None),
+        Err(open_read_error) => Err(open_read_error.into()),
+    }
+}
+
+pub(crate) fn preload(index: &Index, fields: Option<&[Field]>) -> crate::Result<u64> {
+    let mut num_bytes = 0u64;
+    for segment in index.searchable_segments()? {
+        match fields {
+            None => {
+                for &component in SegmentComponent::iterator() {
+                    if component == SegmentComponent::TempStore {
+                        continue;
+                    }
+                    if let Some(file_slice) = open_component(&segment, component)? {
+                        num_bytes += touch(&file_slice)?;
+                    }
+                }
+            }
+            Some(fields) => {
+                for &component in &FIELD_COMPONENTS {
+                    let file_slice = match open_component(&segment, component)? {
+                        Some(file_slice) => file_slice,
+                        None => continue,
+                    };
+                    let composite_file = CompositeFile::open(&file_slice)?;
+                    for &field in fields {
+                        for field_slice in composite_file.open_read_all(field) {
+                            num_bytes += touch(&field_slice)?;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(num_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::{Schema, FAST, STORED, TEXT};
+    use crate::{doc, Index};
+
+    #[test]
+    fn test_preload() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT | STORED);
+        let num = schema_builder.add_u64_field("num", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..100u64 {
+            index_writer.add_document(doc!(text => format!("hello {}", i), num => i));
+        }
+        index_writer.commit()?;
+        let all_num_bytes = index.preload(None)?;
+        let text_num_bytes = index.preload(Some(&[text]))?;
+        let num_num_bytes = index.preload(Some(&[num]))?;
+        let fields_num_bytes = index.preload(Some(&[text, num]))?;
+        assert!(text_num_bytes > 0);
+        assert!(num_num_bytes > 0);
+        assert_eq!(fields_num_bytes, text_num_bytes + num_num_bytes);
+        // The preload of all the files also reads the doc store and the footers of the
+        // composite files.
+        assert!(all_num_bytes > fields_num_bytes);
+        Ok(())
+    }
+}
@@ -0,0 +1,404 @@
+use crate::core::{Index, IndexMeta, SegmentMeta, META_FILEPATH};
+use crate::directory::error::OpenReadError;
+use crate::directory::{Directory, META_LOCK};
+use crate::error::DataCorruption;
+use crate::{Opstamp, TantivyError};
+use crc32fast::Hasher;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// The manifest lists the files of a snapshot, with their checksum.
+///
+/// It is written last: a snapshot directory without a manifest is incomplete.
+const SNAPSHOT_MANIFEST_FILENAME: &str = "snapshot.json";
+
+/// A file of a snapshot.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    /// Path of the file, relative to the index and to the snapshot directories.
+    pub path: PathBuf,
+    /// Size of the file.
+    pub num_bytes: u64,
+    /// CRC32 checksum of the whole file.
+    pub checksum: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotManifest {
+    opstamp: Opstamp,
+    files: Vec<SnapshotFile>,
+}
+
+/// A snapshot of an index, created by
+/// [`Index::create_snapshot`](./struct.Index.html#method.create_snapshot).
+///
+/// As long as the snapshot lives, the files of its segments are not garbage collected
+/// by the `IndexWriter` of the index.
+pub struct IndexSnapshot {
+    opstamp: Opstamp,
+    files: Vec<SnapshotFile>,
+    num_reused_files: usize,
+    // Keeps the files of the segments from being garbage collected.
+    _segment_metas: Vec<SegmentMeta>,
+}
+
+impl IndexSnapshot {
+    /// Returns the opstamp of the commit of the snapshot.
+    pub fn opstamp(&self) -> Opstamp {
+        self.opstamp
+    }
+
+    /// Returns the files of the snapshot, including the `meta.json`.
+    pub fn files(&self) -> &[SnapshotFile] {
+        &self.files
+    }
+
+    /// Returns the number of files taken from the previous snapshot of an incremental
+    /// snapshot.
+    pub fn num_reused_files(&self) -> usize {
+        self.num_reused_files
+    }
+}
+
+fn compute_checksum(data: &[u8]) -> u32 {
+    let mut hasher = Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+fn checksum_mismatch(path: PathBuf) -> TantivyError {
+    DataCorruption::new(path, "Checksum mismatch".to_string()).into()
+}
+
+fn read_manifest(snapshot_dir: &Path) -> crate::Result<SnapshotManifest> {
+    let manifest_path = snapshot_dir.join(SNAPSHOT_MANIFEST_FILENAME);
+    let manifest_data = fs::read(&manifest_path).map_err(|io_error| {
+        if io_error.kind() == io::ErrorKind::NotFound {
+            TantivyError::InvalidArgument(format!(
+                "{:?} is not a snapshot directory, or the snapshot is incomplete.",
+                snapshot_dir
+            ))
+        } else {
+            io_error.into()
+        }
+    })?;
+    serde_json::from_slice(&manifest_data).map_err(|err| {
+        DataCorruption::new(
+            manifest_path,
+            format!("The snapshot manifest cannot be deserialized: {:?}", err),
+        )
+        .into()
+    })
+}
+
+fn write_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(data)?;
+    file.sync_all()
+}
+
+/// Hard links `src` to `dest`, or copies it if the filesystem does not allow it.
+fn link_or_copy(src: &Path, dest: &Path) -> io::Result<()> {
+    if fs::hard_link(src, dest).is_err() {
+        fs::copy(src, dest)?;
+        File::open(dest)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Reads the `meta.json` and tracks its segments, while holding the meta lock so that
+/// they cannot be garbage collected in the meantime.
+pub(crate) fn pin_last_commit(index: &Index) -> crate::Result<(Vec<u8>, IndexMeta)> {
+    let directory = index.directory();
+    let _meta_lock = directory.acquire_lock(&META_LOCK)?;
+    let meta_data = directory.atomic_read(&META_FILEPATH)?;
+    let index_meta = std::str::from_utf8(&meta_data)
+        .ok()
+        .and_then(|meta_json| IndexMeta::deserialize(meta_json, index.inventory()).ok())
+        .ok_or_else(|| {
+            DataCorruption::new(
+                META_FILEPATH.to_path_buf(),
+                "Meta file cannot be deserialized.".to_string(),
+            )
+        })?;
+    Ok((meta_data, index_meta))
+}
+
+pub(crate) fn create_snapshot(
+    index: &Index,
+    snapshot_dir: &Path,
+    previous_snapshot_dir: Option<&Path>,
+) -> crate::Result<IndexSnapshot> {
+    if snapshot_dir.join(SNAPSHOT_MANIFEST_FILENAME).exists() {
+        return Err(TantivyError::InvalidArgument(format!(
+            "{:?} already contains a snapshot.",
+            snapshot_dir
+        )));
+    }
+    let previous_files: HashMap<PathBuf, SnapshotFile> =
+        if let Some(previous_snapshot_dir) = previous_snapshot_dir {
+            read_manifest(previous_snapshot_dir)?
+                .files
+                .into_iter()
+                .map(|file| (file.path.clone(), file))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+    fs::create_dir_all(snapshot_dir)?;
+    let (meta_data, index_meta) = pin_last_commit(index)?;
+    let directory = index.directory();
+    let mut files = Vec::new();
+    let mut num_reused_files = 0;
+    for segment_meta in &index_meta.segments {
+        for path in segment_meta.list_files() {
+            // The positions and the term vectors files are optional.
+            if !directory.exists(&path)? {
+                continue;
+            }
+            let data = directory
+                .open_read_with_footer(&path)?
+                .read_bytes()
+                .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.clone()))?;
+            let file = SnapshotFile {
+                path: path.clone(),
+                num_bytes: data.len() as u64,
+                checksum: compute_checksum(data.as_slice()),
+            };
+            let dest = snapshot_dir.join(&path);
+            match (previous_snapshot_dir, previous_files.get(&path)) {
+                (Some(previous_snapshot_dir), Some(previous_file)) if *previous_file == file => {
+                    link_or_copy(&previous_snapshot_dir.join(&path), &dest)?;
+                    num_reused_files += 1;
+                }
+                _ => {
+                    if let Some(src) = directory.local_file_path(&path) {
+                        link_or_copy(&src, &dest)?;
+                    } else {
+                        write_file(&dest, data.as_slice())?;
+                    }
+                }
+            }
+            files.push(file);
+        }
+    }
+    write_file(&snapshot_dir.join(*META_FILEPATH), &meta_data)?;
+    files.push(SnapshotFile {
+        path: META_FILEPATH.to_path_buf(),
+        num_bytes: meta_data.len() as u64,
+        checksum: compute_checksum(&meta_data),
+    });
+    let manifest = SnapshotManifest {
+        opstamp: index_meta.opstamp,
+        files,
+    };
+    let manifest_path = snapshot_dir.join(SNAPSHOT_MANIFEST_FILENAME);
+    let temp_manifest_path = manifest_path.with_extension("json.tmp");
+    write_file(&temp_manifest_path, &serde_json::to_vec_pretty(&manifest)?)?;
+    fs::rename(&temp_manifest_path, &manifest_path)?;
+    Ok(IndexSnapshot {
+        opstamp: manifest.opstamp,
+        files: manifest.files,
+        num_reused_files,
+        _segment_metas: index_meta.segments,
+    })
+}
+
+/// Copies the file of the snapshot to the writer, and verifies its checksum.
+fn copy_verified<W: Write>(
+    snapshot_dir: &Path,
+    file: &SnapshotFile,
+    writer: &mut W,
+) -> crate::Result<()> {
+    let src = snapshot_dir.join(&file.path);
+    let mut reader = File::open(&src)?;
+    let mut hasher = Hasher::new();
+    let mut num_bytes = 0u64;
+    let mut buffer = vec![0u8; 1 << 16];
+    loop {
+        let read_len = reader.read(&mut buffer)?;
+        if read_len == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read_len]);
+        writer.write_all(&buffer[..read_len])?;
+        num_bytes += read_len as u64;
+    }
+    if num_bytes != file.num_bytes || hasher.finalize() != file.checksum {
+        return Err(checksum_mismatch(src));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "mmap")]
+pub(crate) fn restore_snapshot(snapshot_dir: &Path, dest_dir: &Path) -> crate::Result<Index> {
+    use crate::directory::{ManagedDirectory, MmapDirectory, TerminatingWrite};
+
+    let manifest = read_manifest(snapshot_dir)?;
+    fs::create_dir_all(dest_dir)?;
+    let directory = MmapDirectory::open(dest_dir)?;
+    if Index::exists(&directory)? {
+        return Err(TantivyError::IndexAlreadyExists);
+    }
+    let managed_directory = ManagedDirectory::wrap(directory.clone())?;
+    let mut meta_data = None;
+    for file in &manifest.files {
+        if file.path == *META_FILEPATH {
+            let mut data = Vec::new();
+            copy_verified(snapshot_dir, file, &mut data)?;
+            meta_data = Some(data);
+            continue;
+        }
+        // The files of the snapshot already have their footer, they are written as is.
+        managed_directory.register_file_as_managed(&file.path)?;
+        let mut writer = directory.open_write(&file.path)?;
+        copy_verified(snapshot_dir, file, &mut writer)?;
+        writer.terminate()?;
+    }
+    let meta_data = meta_data.ok_or_else(|| {
+        DataCorruption::new(
+            snapshot_dir.join(SNAPSHOT_MANIFEST_FILENAME),
+            "The snapshot has no meta.json.".to_string(),
+        )
+    })?;
+    // The `meta.json` is written last, so that the destination directory does not contain
+    // an index if the restore fails.
+    directory.atomic_write(&META_FILEPATH, &meta_data)?;
+    Index::open(directory)
+}
+
+#[cfg(feature = "mmap")]
+#[cfg(test)]
+mod tests {
+    use crate::collector::Count;
+    use crate::core::META_FILEPATH;
+    use crate::directory::RamDirectory;
+    use crate::indexer::NoMergePolicy;
+    use crate::query::AllQuery;
+    use crate::schema::{Field, Schema, STRING};
+    use crate::{Index, IndexSettings, IndexWriter, TantivyError, Term};
+    use futures::executor::block_on;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn create_index(index: Index) -> crate::Result<(IndexWriter, Field)> {
+        let text_field = index.schema().get_field("text").unwrap();
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit()?;
+        Ok((index_writer, text_field))
+    }
+
+    fn schema() -> Schema {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("text", STRING);
+        schema_builder.build()
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() -> crate::Result<()> {
+        let index_dir = TempDir::new()?;
+        let index = Index::create_in_dir(index_dir.path(), schema())?;
+        let (mut index_writer, text_field) = create_index(index.clone())?;
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit()?;
+
+        let snapshot_dir = TempDir::new()?;
+        let snapshot = index.create_snapshot(snapshot_dir.path())?;
+        assert_eq!(snapshot.opstamp(), index.load_metas()?.opstamp);
+        assert!(snapshot
+            .files()
+            .iter()
+            .any(|file| file.path == *META_FILEPATH));
+        let segment_file_path = snapshot.files()[0].path.clone();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let metadata = fs::metadata(snapshot_dir.path().join(&segment_file_path))?;
+            assert_eq!(metadata.nlink(), 2);
+        }
+
+        // The files of the snapshot are not garbage collected until it is dropped.
+        let segment_ids = index.searchable_segment_ids()?;
+        block_on(index_writer.merge(&segment_ids))?;
+        block_on(index_writer.garbage_collect_files())?;
+        assert!(index_dir.path().join(&segment_file_path).exists());
+        drop(snapshot);
+        block_on(index_writer.garbage_collect_files())?;
+        assert!(!index_dir.path().join(&segment_file_path).exists());
+        assert!(snapshot_dir.path().join(&segment_file_path).exists());
+
+        let restore_dir = TempDir::new()?;
+        let restored_index = Index::restore_snapshot(snapshot_dir.path(), restore_dir.path())?;
+        let searcher = restored_index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        assert_eq!(searcher.search(&AllQuery, &Count)?, 2);
+        assert!(restored_index
+            .directory()
+            .list_managed_files()
+            .contains(&segment_file_path));
+        assert!(matches!(
+            Index::restore_snapshot(snapshot_dir.path(), restore_dir.path()),
+            Err(TantivyError::IndexAlreadyExists)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_corrupted_snapshot() -> crate::Result<()> {
+        let index = Index::create(RamDirectory::create(), schema(), IndexSettings::default())?;
+        create_index(index.clone())?;
+        let snapshot_dir = TempDir::new()?;
+        let snapshot = index.create_snapshot(snapshot_dir.path())?;
+        let segment_file_path = snapshot_dir.path().join(&snapshot.files()[0].path);
+        let mut data = fs::read(&segment_file_path)?;
+        data[0] ^= 1;
+        fs::write(&segment_file_path, &data)?;
+
+        let restore_dir = TempDir::new()?;
+        assert!(matches!(
+            Index::restore_snapshot(snapshot_dir.path(), restore_dir.path()),
+            Err(TantivyError::DataCorruption(_))
+        ));
+        assert!(!restore_dir.path().join(*META_FILEPATH).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_incremental_snapshot() -> crate::Result<()> {
+        let index = Index::create(RamDirectory::create(), schema(), IndexSettings::default())?;
+        let (mut index_writer, text_field) = create_index(index.clone())?;
+        let previous_snapshot_dir = TempDir::new()?;
+        let previous_snapshot = index.create_snapshot(previous_snapshot_dir.path())?;
+        assert!(matches!(
+            index.create_snapshot(previous_snapshot_dir.path()),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit()?;
+
+        let snapshot_dir = TempDir::new()?;
+        let snapshot =
+            index.create_incremental_snapshot(snapshot_dir.path(), previous_snapshot_dir.path())?;
+        // All of the files of the previous snapshot but the `meta.json` are reused.
+        assert_eq!(
+            snapshot.num_reused_files(),
+            previous_snapshot.files().len() - 1
+        );
+        assert!(snapshot.files().len() > previous_snapshot.files().len());
+
+        // The snapshot does not depend on the previous one.
+        drop(previous_snapshot_dir);
+        let restore_dir = TempDir::new()?;
+        let restored_index = Index::restore_snapshot(snapshot_dir.path(), restore_dir.path())?;
+        let searcher = restored_index.reader()?.searcher();
+        assert_eq!(searcher.search(&AllQuery, &Count)?, 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,464 @@
+use crate::core::{Index, Segment, SegmentComponent, SegmentId, SegmentReader};
+use crate::directory::error::OpenReadError;
+use crate::directory::Directory;
+use crate::TantivyError;
+use std::path::{Path, PathBuf};
+
+/// Options of [`Index::validate`](./struct.Index.html#method.validate).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ValidationOptions {
+    /// If true, all of the postings and positions are read, in order to detect truncated
+    /// or inconsistent posting lists.
+    ///
+    /// This reads the whole inverted index, and is therefore slow on large indexes.
+    pub full: bool,
+}
+
+/// The kind of a [`ValidationProblem`](./struct.ValidationProblem.html).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValidationProblemKind {
+    /// A file of the segment does not exist.
+    MissingFile,
+    /// The checksum of the file does not match its content.
+    ChecksumMismatch,
+    /// The file, or the segment, cannot be read.
+    Unreadable,
+    /// The content of the file is inconsistent with the `meta.json`, or with the other
+    /// files of the segment.
+    Inconsistent,
+}
+
+/// A problem found by [`Index::validate`](./struct.Index.html#method.validate).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidationProblem {
+    /// The segment with the problem.
+    pub segment_id: SegmentId,
+    /// The file with the problem, if the problem can be attributed to a file.
+    pub path: Option<PathBuf>,
+    /// The kind of the problem.
+    pub kind: ValidationProblemKind,
+    /// A description of the problem.
+    pub description: String,
+}
+
+/// The result of [`Index::validate`](./struct.Index.html#method.validate), listing all of
+/// the problems found in the segments of the index.
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    /// The number of segments which were validated.
+    pub num_segments: usize,
+    /// The number of files whose checksum was verified.
+    pub num_files: usize,
+    /// The problems found.
+    pub problems: Vec<ValidationProblem>,
+}
+
+impl ValidationReport {
+    /// Returns true if no problem was found.
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Returns the problems found in the segment `segment_id`.
+    pub fn segment_problems(
+        &self,
+        segment_id: SegmentId,
+    ) -> impl Iterator<Item = &ValidationProblem> + '_ {
+        self.problems
+            .iter()
+            .filter(move |problem| problem.segment_id == segment_id)
+    }
+
+    /// Returns the problems found in the file `path`.
+    pub fn file_problems<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> impl Iterator<Item = &'a ValidationProblem> + 'a {
+        self.problems
+            .iter()
+            .filter(move |problem| problem.path.as_deref() == Some(path))
+    }
+}
+
+struct SegmentValidator<'a> {
+    segment: Segment,
+    report: &'a mut ValidationReport,
+}
+
+impl<'a> SegmentValidator<'a> {
+    fn add_problem(
+        &mut self,
+        component: Option<SegmentComponent>,
+        kind: ValidationProblemKind,
+        description: String,
+    ) {
+        let path = component.map(|component| self.segment.relative_path(component));
+        self.report.problems.push(ValidationProblem {
+            segment_id: self.segment.id(),
+            path,
+            kind,
+            description,
+        });
+    }
+
+    /// Verifies the checksum of the files of the segment.
+    ///
+    /// Returns false if a file is missing or corrupted.
+    fn validate_files(&mut self) -> crate::Result<bool> {
+        let meta = self.segment.meta().clone();
+        let directory = self.segment.index().directory().clone();
+        let mut is_valid = true;
+        for &component in SegmentComponent::iterator() {
+            let is_required = match component {
+                SegmentComponent::TempStore => continue,
+                SegmentComponent::Delete => {
+                    if !meta.has_deletes() {
+                        continue;
+                    }
+                    true
+                }
+                // The segments written by older versions of tantivy may not have them.
+                SegmentComponent::Positions | SegmentComponent::TermVectors => false,
+                _ => true,
+            };
+            let path = meta.relative_path(component);
+            if !directory.exists(&path)? {
+                if is_required {
+                    self.add_problem(
+                        Some(component),
+                        ValidationProblemKind::MissingFile,
+                        format!("The file {:?} does not exist.", path),
+                    );
+                    is_valid = false;
+                }
+                continue;
+            }
+            self.report.num_files += 1;
+            match directory.validate_checksum(&path) {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.add_problem(
+                        Some(component),
+                        ValidationProblemKind::ChecksumMismatch,
+                        format!("The checksum of the file {:?} does not match.", path),
+                    );
+                    is_valid = false;
+                }
+                Err(OpenReadError::FileDoesNotExist(_)) => {
+                    self.add_problem(
+                        Some(component),
+                        ValidationProblemKind::MissingFile,
+                        format!("The file {:?} does not exist.", path),
+                    );
+                    is_valid = false;
+                }
+                Err(err) => {
+                    self.add_problem(
+                        Some(component),
+                        ValidationProblemKind::Unreadable,
+                        err.to_string(),
+                    );
+                    is_valid = false;
+                }
+            }
+        }
+        Ok(is_valid)
+    }
+
+    /// Opens the segment, and checks the consistency of its files.
+    fn validate_structure(&mut self, options: ValidationOptions) {
+        let segment_reader = match SegmentReader::open(&self.segment) {
+            Ok(segment_reader) => segment_reader,
+            Err(err) => {
+                self.add_problem(
+                    None,
+                    ValidationProblemKind::Unreadable,
+                    format!("The segment cannot be opened: {}", err),
+                );
+                return;
+            }
+        };
+        let meta = self.segment.meta().clone();
+        let max_doc = meta.max_doc();
+
+        if let Some(delete_bitset) = segment_reader.delete_bitset() {
+            if delete_bitset.num_deleted() != meta.num_deleted_docs() as usize {
+                self.add_problem(
+                    Some(SegmentComponent::Delete),
+                    ValidationProblemKind::Inconsistent,
+                    format!(
+                        "The delete bitset has {} deleted docs, but the meta has {}.",
+                        delete_bitset.num_deleted(),
+                        meta.num_deleted_docs()
+                    ),
+                );
+            }
+        }
+
+        match segment_reader.get_store_reader() {
+            Ok(store_reader) => {
+                if let Some(problem) = store_reader.check_block_index(max_doc) {
+                    self.add_problem(
+                        Some(SegmentComponent::Store),
+                        ValidationProblemKind::Inconsistent,
+                        problem,
+                    );
+                }
+            }
+            Err(err) => {
+                self.add_problem(
+                    Some(SegmentComponent::Store),
+                    ValidationProblemKind::Unreadable,
+                    format!("The store cannot be opened: {}", err),
+                );
+            }
+        }
+
+        for problem in segment_reader.fast_fields().check_column_lengths() {
+            self.add_problem(
+                Some(SegmentComponent::FastFields),
+                ValidationProblemKind::Inconsistent,
+                problem,
+            );
+        }
+
+        let schema = segment_reader.schema().clone();
+        for (field, field_entry) in schema.fields() {
+            if !field_entry.is_indexed() {
+                continue;
+            }
+            match segment_reader.get_fieldnorms_reader(field) {
+                Ok(fieldnorm_reader) => {
+                    if fieldnorm_reader.num_docs() != max_doc {
+                        self.add_problem(
+                            Some(SegmentComponent::FieldNorms),
+                            ValidationProblemKind::Inconsistent,
+                            format!(
+                                "The field norms of the field {:?} have {} docs, expected \
+                                 max_doc={}.",
+                                field_entry.name(),
+                                fieldnorm_reader.num_docs(),
+                                max_doc
+                            ),
+                        );
+                    }
+                }
+                // The field does not record field norms.
+                Err(TantivyError::SchemaError(_)) => {}
+                Err(err) => {
+                    self.add_problem(
+                        Some(SegmentComponent::FieldNorms),
+                        ValidationProblemKind::Unreadable,
+                        err.to_string(),
+                    );
+                }
+            }
+            match segment_reader.inverted_index(field) {
+                Ok(inverted_index) => {
+                    if let Some((component, problem)) = inverted_index.check(max_doc, options.full)
+                    {
+                        self.add_problem(
+                            Some(component),
+                            ValidationProblemKind::Inconsistent,
+                            format!("Field {:?}: {}", field_entry.name(), problem),
+                        );
+                    }
+                }
+                Err(err) => {
+                    self.add_problem(
+                        Some(SegmentComponent::Postings),
+                        ValidationProblemKind::Unreadable,
+                        format!(
+                            "The inverted index of the field {:?} cannot be opened: {}",
+                            field_entry.name(),
+                            err
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn validate_index(
+    index: &Index,
+    options: ValidationOptions,
+) -> crate::Result<ValidationReport> {
+    let mut report = ValidationReport::default();
+    for segment in index.searchable_segments()? {
+        report.num_segments += 1;
+        let mut segment_validator = SegmentValidator {
+            segment,
+            report: &mut report,
+        };
+        // Reading corrupted files may panic: the structure of the segment is only
+        // checked if all of its files are intact.
+        if segment_validator.validate_files()? {
+            segment_validator.validate_structure(options);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ValidationOptions, ValidationProblemKind};
+    use crate::core::{Index, SegmentComponent, SegmentMeta, META_FILEPATH};
+    use crate::directory::{BundleDirectory, Directory, FileSlice, RamDirectory};
+    use crate::schema::{Cardinality, IntOptions, Schema, FAST, STORED, TEXT};
+    use crate::{doc, IndexSettings, IndexSortByField, Order, Term};
+    use futures::executor::block_on;
+
+    fn create_index(directory: &RamDirectory) -> crate::Result<(Index, Vec<SegmentMeta>)> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let num_field = schema_builder.add_u64_field("num", FAST);
+        let nums_field = schema_builder.add_u64_field(
+            "nums",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..20u64 {
+            index_writer.add_document(doc!(
+                text_field => format!("hello {}", i % 3),
+                num_field => i,
+                nums_field => i,
+                nums_field => i + 1,
+            ));
+        }
+        index_writer.commit()?;
+        for i in 0..10u64 {
+            index_writer.add_document(doc!(text_field => "world", num_field => i));
+        }
+        index_writer.delete_term(Term::from_field_text(text_field, "1"));
+        index_writer.commit()?;
+        let segment_metas = index.searchable_segment_metas()?;
+        assert_eq!(segment_metas.len(), 2);
+        Ok((index, segment_metas))
+    }
+
+    #[test]
+    fn test_validate_valid_index() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let (index, _) = create_index(&directory)?;
+        // The index is validated from a bundle too.
+        let mut bundle = Vec::new();
+        BundleDirectory::pack(&directory, &mut bundle)?;
+        let bundle_index = Index::open(BundleDirectory::open(FileSlice::from(bundle))?)?;
+        for index in &[index, bundle_index] {
+            for &full in &[false, true] {
+                let report = index.validate(ValidationOptions { full })?;
+                assert!(report.is_valid(), "{:?}", report.problems);
+                assert_eq!(report.num_segments, 2);
+                assert!(report.num_files >= 12);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_merged_sorted_index() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let num_field = schema_builder.add_u64_field("num", FAST);
+        let index = Index::builder()
+            .schema(schema_builder.build())
+            .settings(IndexSettings {
+                sort_by_field: Some(IndexSortByField {
+                    field: "num".to_string(),
+                    order: Order::Desc,
+                }),
+                ..Default::default()
+            })
+            .create_in_ram()?;
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..30u64 {
+            index_writer
+                .add_document(doc!(text_field => format!("hello {}", i % 4), num_field => i));
+            if i % 10 == 9 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.delete_term(Term::from_field_text(text_field, "2"));
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        let report = index.validate(ValidationOptions { full: true })?;
+        assert!(report.is_valid(), "{:?}", report.problems);
+        assert_eq!(report.num_segments, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_corrupted_files() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let (index, segment_metas) = create_index(&directory)?;
+        let store_path = segment_metas[0].relative_path(SegmentComponent::Store);
+        let mut store_data = directory.atomic_read(&store_path)?;
+        store_data[0] ^= 1;
+        directory.atomic_write(&store_path, &store_data)?;
+        let fast_fields_path = segment_metas[1].relative_path(SegmentComponent::FastFields);
+        directory.delete(&fast_fields_path).unwrap();
+
+        let report = index.validate(ValidationOptions { full: true })?;
+        assert_eq!(report.problems.len(), 2);
+        let store_problems: Vec<_> = report.file_problems(&store_path).collect();
+        assert_eq!(store_problems.len(), 1);
+        assert_eq!(store_problems[0].segment_id, segment_metas[0].id());
+        assert_eq!(
+            store_problems[0].kind,
+            ValidationProblemKind::ChecksumMismatch
+        );
+        let fast_fields_problems: Vec<_> = report.file_problems(&fast_fields_path).collect();
+        assert_eq!(fast_fields_problems.len(), 1);
+        assert_eq!(fast_fields_problems[0].segment_id, segment_metas[1].id());
+        assert_eq!(
+            fast_fields_problems[0].kind,
+            ValidationProblemKind::MissingFile
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_inconsistent_max_doc() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let (_, segment_metas) = create_index(&directory)?;
+        // The checksums are valid, but the segment does not have as many docs as the
+        // `meta.json` says.
+        assert_eq!(segment_metas[0].max_doc(), 20);
+        let meta_json = String::from_utf8(directory.atomic_read(&META_FILEPATH)?).unwrap();
+        assert_eq!(meta_json.matches(r#""max_doc": 20,"#).count(), 1);
+        let meta_json = meta_json.replace(r#""max_doc": 20,"#, r#""max_doc": 21,"#);
+        directory.atomic_write(&META_FILEPATH, meta_json.as_bytes())?;
+
+        let index = Index::open(directory)?;
+        let report = index.validate(ValidationOptions::default())?;
+        assert!(!report.is_valid());
+        assert!(report
+            .problems
+            .iter()
+            .all(|problem| problem.segment_id == segment_metas[0].id()
+                && problem.kind == ValidationProblemKind::Inconsistent));
+        // there is one problem per fast field.
+        for &(component, num_problems) in &[
+            (SegmentComponent::Store, 1),
+            (SegmentComponent::FastFields, 2),
+            (SegmentComponent::FieldNorms, 1),
+        ] {
+            let path = segment_metas[0].relative_path(component);
+            assert_eq!(
+                report.file_problems(&path).count(),
+                num_problems,
+                "{:?}",
+                path
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,13 +1,16 @@
 use std::io;
 
+use crate::core::SegmentComponent;
 use crate::directory::FileSlice;
+use crate::docset::{DocSet, TERMINATED};
 use crate::positions::PositionReader;
 use crate::postings::TermInfo;
-use crate::postings::{BlockSegmentPostings, SegmentPostings};
+use crate::postings::{BlockSegmentPostings, Postings, SegmentPostings};
 use crate::schema::IndexRecordOption;
 use crate::schema::Term;
 use crate::termdict::TermDictionary;
-use common::BinarySerializable;
+use crate::DocId;
+use common::{BinarySerializable, HasLen};
 
 /// The inverted index reader is in charge of accessing
 /// the inverted index associated to a specific field.
@@ -148,10 +151,126 @@ impl InvertedIndexReader {
                 None
             }
         };
-        Ok(SegmentPostings::from_block_postings(
-            block_postings,
-            position_reader,
-        ))
+        Ok(
+            SegmentPostings::from_block_postings(block_postings, position_reader)
+                .with_payloads(self.record_option.has_payloads()),
+        )
+    }
+
+    /// Checks that the term infos of the term dictionary point to valid ranges of the
+    /// postings and positions files, and, if `read_postings` is true, reads all of the
+    /// postings to check their doc ids and their number of positions.
+    ///
+    /// Returns the component at fault and the description of the first problem found, if any.
+    pub(crate) fn check(
+        &self,
+        max_doc: DocId,
+        read_postings: bool,
+    ) -> Option<(SegmentComponent, String)> {
+        let mut stream = match self.termdict.stream() {
+            Ok(stream) => stream,
+            Err(err) => {
+                return Some((
+                    SegmentComponent::Terms,
+                    format!("The term dictionary cannot be read: {}", err),
+                ));
+            }
+        };
+        let mut postings_end = 0;
+        let mut positions_end = 0;
+        while let Some((term, term_info)) = stream.next() {
+            let term = String::from_utf8_lossy(term);
+            if term_info.doc_freq == 0 || term_info.doc_freq > max_doc {
+                return Some((
+                    SegmentComponent::Terms,
+                    format!(
+                        "The term {:?} has a doc freq of {}, with max_doc={}.",
+                        term, term_info.doc_freq, max_doc
+                    ),
+                ));
+            }
+            if term_info.postings_range.start < postings_end
+                || term_info.postings_range.end > self.postings_file_slice.len()
+            {
+                return Some((
+                    SegmentComponent::Postings,
+                    format!(
+                        "The postings of the term {:?} are out of bounds: {:?} (len={}).",
+                        term,
+                        term_info.postings_range,
+                        self.postings_file_slice.len()
+                    ),
+                ));
+            }
+            postings_end = term_info.postings_range.end;
+            if self.record_option.has_positions() {
+                if term_info.positions_range.start < positions_end
+                    || term_info.positions_range.end > self.positions_file_slice.len()
+                {
+                    return Some((
+                        SegmentComponent::Positions,
+                        format!(
+                            "The positions of the term {:?} are out of bounds: {:?} (len={}).",
+                            term,
+                            term_info.positions_range,
+                            self.positions_file_slice.len()
+                        ),
+                    ));
+                }
+                positions_end = term_info.positions_range.end;
+            }
+            if read_postings {
+                if let Err(problem) = self.check_postings(term_info, max_doc) {
+                    return Some((
+                        SegmentComponent::Postings,
+                        format!(
+                            "The postings of the term {:?} are invalid: {}",
+                            term, problem
+                        ),
+                    ));
+                }
+            }
+        }
+        None
+    }
+
+    fn check_postings(&self, term_info: &TermInfo, max_doc: DocId) -> Result<(), String> {
+        let mut postings = self
+            .read_postings_from_terminfo(term_info, self.record_option)
+            .map_err(|err| err.to_string())?;
+        let mut positions = Vec::new();
+        let mut num_docs = 0u32;
+        let mut previous_doc = None;
+        let mut doc = postings.doc();
+        while doc != TERMINATED {
+            if doc >= max_doc || matches!(previous_doc, Some(previous_doc) if doc <= previous_doc) {
+                return Err(format!(
+                    "the doc {} follows the doc {:?} (max_doc={}).",
+                    doc, previous_doc, max_doc
+                ));
+            }
+            if self.record_option.has_positions() {
+                postings.positions(&mut positions);
+                if positions.len() != postings.term_freq() as usize {
+                    return Err(format!(
+                        "the doc {} has {} positions, but a term freq of {}.",
+                        doc,
+                        positions.len(),
+                        postings.term_freq()
+                    ));
+                }
+            }
+            num_docs += 1;
+            previous_doc = Some(doc);
+            doc = postings.advance();
+        }
+        if num_docs != term_info.doc_freq {
+            return Err(format!(
+                "{} docs were read, but the doc freq is {}.",
+                num_docs, term_info.doc_freq
+            ));
+        }
+        Ok(())
     }
 
     /// Returns the total number of tokens recorded for all documents
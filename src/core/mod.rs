@@ -1,24 +1,36 @@
 mod executor;
 pub mod index;
+mod index_disk_usage;
 mod index_meta;
+mod index_preload;
+mod index_snapshot;
+mod index_validation;
 mod inverted_index_reader;
 pub mod searcher;
 mod segment;
 mod segment_component;
 mod segment_id;
 mod segment_reader;
+mod segment_replication;
 
 pub use self::executor::Executor;
 pub use self::index::{Index, IndexBuilder};
+pub use self::index_disk_usage::IndexDiskUsage;
 pub use self::index_meta::{
-    IndexMeta, IndexSettings, IndexSortByField, Order, SegmentMeta, SegmentMetaInventory,
+    IndexMeta, IndexSettings, IndexSortByField, Order, PreparedCommitMeta, SegmentMeta,
+    SegmentMetaInventory,
+};
+pub use self::index_snapshot::{IndexSnapshot, SnapshotFile};
+pub use self::index_validation::{
+    ValidationOptions, ValidationProblem, ValidationProblemKind, ValidationReport,
 };
 pub use self::inverted_index_reader::InvertedIndexReader;
-pub use self::searcher::Searcher;
+pub use self::searcher::{CollectionStatistics, Searcher, TermStatistics};
 pub use self::segment::Segment;
 pub use self::segment_component::SegmentComponent;
 pub use self::segment_id::SegmentId;
 pub use self::segment_reader::SegmentReader;
+pub use self::segment_replication::{SearchableSegmentFiles, SegmentFile, SegmentFiles};
 
 use once_cell::sync::Lazy;
 use std::path::Path;
@@ -33,3 +45,10 @@ pub static META_FILEPATH: Lazy<&'static Path> = Lazy::new(|| Path::new("meta.jso
 /// Removing this file is safe, but will prevent the garbage collection of all of the file that
 /// are currently in the directory
 pub static MANAGED_FILEPATH: Lazy<&'static Path> = Lazy::new(|| Path::new(".managed.json"));
+
+/// The prepared commits file lists the commits prepared with an external id, which were
+/// neither finalized nor aborted.
+///
+/// As the file is not managed, it is never garbage collected.
+pub static PREPARED_COMMITS_FILEPATH: Lazy<&'static Path> =
+    Lazy::new(|| Path::new(".prepared_commits.json"));
@@ -2,17 +2,48 @@ use crate::collector::Collector;
 use crate::core::Executor;
 
 use crate::core::SegmentReader;
-use crate::query::Query;
-use crate::schema::Document;
-use crate::schema::Schema;
+use crate::docset::{DocSet, TERMINATED};
+use crate::postings::Postings;
+use crate::query::{FilterCache, FilterCacheStats, Query};
 use crate::schema::Term;
+use crate::schema::{Document, Field};
+use crate::schema::{FieldType, IndexRecordOption, Schema};
 use crate::space_usage::SearcherSpaceUsage;
-use crate::store::StoreReader;
+use crate::store::{CacheStats, StoreReader};
 use crate::DocAddress;
 use crate::Index;
 
+use std::sync::Arc;
 use std::{fmt, io};
 
+/// Statistics of a term, summed over all of the segments of a searcher.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TermStatistics {
+    /// Number of documents containing the term.
+    pub doc_freq: u64,
+    /// Number of occurrences of the term in all of the documents.
+    ///
+    /// `None` if the field does not record term frequencies, or if some of the segments
+    /// were written before the total term frequencies were recorded.
+    pub total_term_freq: Option<u64>,
+}
+
+/// Statistics of a field, summed over all of the segments of a searcher.
+///
+/// The deleted documents are included in the statistics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CollectionStatistics {
+    /// Number of documents with at least one token in the field.
+    ///
+    /// The tokens are only counted for the text fields: for the other fields,
+    /// all of the documents are counted.
+    pub doc_count: u64,
+    /// Number of tokens in the field, over all of the documents.
+    pub sum_total_term_freq: u64,
+    /// Sum of the document frequencies of all of the terms of the field.
+    pub sum_doc_freq: u64,
+}
+
 /// Holds a list of `SegmentReader`s ready for search.
 ///
 /// It guarantees that the `Segment` will not be removed before
@@ -23,6 +54,8 @@ pub struct Searcher {
     index: Index,
     segment_readers: Vec<SegmentReader>,
     store_readers: Vec<StoreReader>,
+    payload: Option<String>,
+    filter_cache: Option<Arc<FilterCache>>,
 }
 
 impl Searcher {
@@ -31,17 +64,59 @@ impl Searcher {
         schema: Schema,
         index: Index,
         segment_readers: Vec<SegmentReader>,
+        payload: Option<String>,
     ) -> io::Result<Searcher> {
         let store_readers: Vec<StoreReader> = segment_readers
             .iter()
             .map(SegmentReader::get_store_reader)
             .collect::<io::Result<Vec<_>>>()?;
-        Ok(Searcher {
+        Ok(Searcher::with_store_readers(
             schema,
             index,
             segment_readers,
             store_readers,
-        })
+            payload,
+        ))
+    }
+
+    /// Creates a new `Searcher` reading the documents with the given store readers, one
+    /// per segment reader.
+    pub(crate) fn with_store_readers(
+        schema: Schema,
+        index: Index,
+        segment_readers: Vec<SegmentReader>,
+        store_readers: Vec<StoreReader>,
+        payload: Option<String>,
+    ) -> Searcher {
+        assert_eq!(segment_readers.len(), store_readers.len());
+        Searcher {
+            schema,
+            index,
+            segment_readers,
+            store_readers,
+            payload,
+            filter_cache: None,
+        }
+    }
+
+    /// Sets the cache of the filters of the queries run by the searcher.
+    pub(crate) fn with_filter_cache(mut self, filter_cache: Option<Arc<FilterCache>>) -> Searcher {
+        self.filter_cache = filter_cache;
+        self
+    }
+
+    pub(crate) fn filter_cache(&self) -> Option<&Arc<FilterCache>> {
+        self.filter_cache.as_ref()
+    }
+
+    /// Returns the statistics of the filter cache of the reader of this searcher, or
+    /// `None` if it has no filter cache.
+    ///
+    /// The statistics are shared by all the searchers of the reader.
+    pub fn filter_cache_stats(&self) -> Option<FilterCacheStats> {
+        self.filter_cache
+            .as_ref()
+            .map(|filter_cache| filter_cache.stats())
     }
 
     /// Returns the `Index` associated to the `Searcher`
@@ -49,6 +124,12 @@ impl Searcher {
         &self.index
     }
 
+    /// Returns the payload of the commit this `Searcher` was loaded from,
+    /// if any.
+    pub fn payload(&self) -> Option<&str> {
+        self.payload.as_deref()
+    }
+
     /// Fetches a document from tantivy's store given a `DocAddress`.
     ///
     /// The searcher uses the segment ordinal to route the
@@ -58,6 +139,51 @@ impl Searcher {
         store_reader.get(doc_address.doc_id)
     }
 
+    /// Fetches a document asynchronously from tantivy's store given a `DocAddress`.
+    ///
+    /// See [`StoreReader::get_async`](../store/struct.StoreReader.html#method.get_async).
+    pub async fn doc_async(&self, doc_address: DocAddress) -> crate::Result<Document> {
+        let store_reader = &self.store_readers[doc_address.segment_ord as usize];
+        store_reader.get_async(doc_address.doc_id).await
+    }
+
+    /// Fetches the values of some `fields` of a document given its `DocAddress`.
+    ///
+    /// The values of the other stored fields are skipped without being decoded.
+    pub fn doc_fields(&self, doc_address: DocAddress, fields: &[Field]) -> crate::Result<Document> {
+        let store_reader = &self.store_readers[doc_address.segment_ord as usize];
+        store_reader.get_fields(doc_address.doc_id, fields)
+    }
+
+    /// Fetches the documents of several `DocAddress`es, returned in the same order as
+    /// `doc_addresses`.
+    ///
+    /// The documents are read by segment and in the order of their `DocId`, so that the
+    /// documents of a same block of the store are read one after the other, and the
+    /// block is only decompressed once.
+    pub fn docs(&self, doc_addresses: &[DocAddress]) -> crate::Result<Vec<Document>> {
+        let mut sorted_positions: Vec<usize> = (0..doc_addresses.len()).collect();
+        sorted_positions.sort_by_key(|&position| doc_addresses[position]);
+        let mut docs: Vec<Option<Document>> = vec![None; doc_addresses.len()];
+        for position in sorted_positions {
+            docs[position] = Some(self.doc(doc_addresses[position])?);
+        }
+        Ok(docs.into_iter().flatten().collect())
+    }
+
+    /// Returns the statistics of the block caches of the store readers of the searcher,
+    /// summed over all of its segments.
+    ///
+    /// The searchers of an [`IndexReader`](./struct.IndexReader.html) share their store
+    /// readers, so the statistics include the documents fetched by all of them.
+    pub fn doc_store_cache_stats(&self) -> CacheStats {
+        let mut cache_stats = CacheStats::default();
+        for store_reader in &self.store_readers {
+            cache_stats += store_reader.cache_stats();
+        }
+        cache_stats
+    }
+
     /// Access the schema associated to the index of this searcher.
     pub fn schema(&self) -> &Schema {
         &self.schema
@@ -83,6 +209,113 @@ impl Searcher {
         Ok(total_doc_freq)
     }
 
+    /// Returns the statistics of a term, summed over all of the segments.
+    ///
+    /// Like [`doc_freq(...)`](#method.doc_freq), the deleted documents are counted.
+    pub fn term_statistics(&self, term: &Term) -> crate::Result<TermStatistics> {
+        let mut term_statistics = TermStatistics {
+            doc_freq: 0,
+            total_term_freq: Some(0),
+        };
+        for segment_reader in &self.segment_readers {
+            let inverted_index = segment_reader.inverted_index(term.field())?;
+            if let Some(term_info) = inverted_index.get_term_info(term)? {
+                term_statistics.doc_freq += u64::from(term_info.doc_freq);
+                term_statistics.total_term_freq = term_statistics
+                    .total_term_freq
+                    .zip(term_info.total_term_freq)
+                    .map(|(left, right)| left + right);
+            }
+        }
+        if !self.has_freqs(term.field()) {
+            term_statistics.total_term_freq = None;
+        }
+        Ok(term_statistics)
+    }
+
+    /// Returns the statistics of a term, summed over all of the segments, without
+    /// counting the deleted documents.
+    ///
+    /// This method scans through the posting lists of the segments with deletes
+    /// (this is a rather expensive operation).
+    pub fn term_statistics_given_deletes(&self, term: &Term) -> crate::Result<TermStatistics> {
+        let has_freqs = self.has_freqs(term.field());
+        let mut term_statistics = TermStatistics {
+            doc_freq: 0,
+            total_term_freq: if has_freqs { Some(0) } else { None },
+        };
+        for segment_reader in &self.segment_readers {
+            let inverted_index = segment_reader.inverted_index(term.field())?;
+            let term_info = if let Some(term_info) = inverted_index.get_term_info(term)? {
+                term_info
+            } else {
+                continue;
+            };
+            let delete_bitset = if let Some(delete_bitset) = segment_reader.delete_bitset() {
+                delete_bitset
+            } else {
+                term_statistics.doc_freq += u64::from(term_info.doc_freq);
+                term_statistics.total_term_freq = term_statistics
+                    .total_term_freq
+                    .zip(term_info.total_term_freq)
+                    .map(|(left, right)| left + right);
+                continue;
+            };
+            let mut postings = inverted_index
+                .read_postings_from_terminfo(&term_info, IndexRecordOption::WithFreqs)?;
+            let mut total_term_freq = 0u64;
+            while postings.doc() != TERMINATED {
+                if delete_bitset.is_alive(postings.doc()) {
+                    term_statistics.doc_freq += 1;
+                    total_term_freq += u64::from(postings.term_freq());
+                }
+                postings.advance();
+            }
+            if let Some(sum_total_term_freq) = term_statistics.total_term_freq.as_mut() {
+                *sum_total_term_freq += total_term_freq;
+            }
+        }
+        Ok(term_statistics)
+    }
+
+    /// Returns the statistics of a field, summed over all of the segments.
+    ///
+    /// The terms of the field are streamed in order to sum their document
+    /// frequencies.
+    pub fn collection_statistics(&self, field: Field) -> crate::Result<CollectionStatistics> {
+        let counts_tokens = matches!(
+            self.schema.get_field_entry(field).field_type(),
+            FieldType::Str(_) | FieldType::JsonObject(_)
+        );
+        let mut collection_statistics = CollectionStatistics::default();
+        for segment_reader in &self.segment_readers {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            collection_statistics.sum_total_term_freq += inverted_index.total_num_tokens();
+            let mut term_stream = inverted_index.terms().stream()?;
+            while let Some((_, term_info)) = term_stream.next() {
+                collection_statistics.sum_doc_freq += u64::from(term_info.doc_freq);
+            }
+            collection_statistics.doc_count += if counts_tokens {
+                let fieldnorm_reader = segment_reader.get_fieldnorms_reader(field)?;
+                (0..segment_reader.max_doc())
+                    .filter(|&doc| fieldnorm_reader.fieldnorm_id(doc) > 0)
+                    .count() as u64
+            } else {
+                u64::from(segment_reader.max_doc())
+            };
+        }
+        Ok(collection_statistics)
+    }
+
+    fn has_freqs(&self, field: Field) -> bool {
+        self.schema
+            .get_field_entry(field)
+            .field_type()
+            .get_index_record_option()
+            .map(|record_option| record_option.has_freq())
+            .unwrap_or(false)
+    }
+
     /// Return the list of segment readers
     pub fn segment_readers(&self) -> &[SegmentReader] {
         &self.segment_readers
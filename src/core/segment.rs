@@ -5,16 +5,22 @@ use crate::core::SegmentMeta;
 use crate::directory::error::{OpenReadError, OpenWriteError};
 use crate::directory::Directory;
 use crate::directory::{FileSlice, WritePtr};
+use crate::indexer::merge_operation::{MergeProgress, MergeWrite};
 use crate::schema::Schema;
 use crate::Opstamp;
+use common::HasLen;
 use std::fmt;
+use std::io::BufWriter;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 /// A segment is a piece of the index.
 #[derive(Clone)]
 pub struct Segment {
     index: Index,
     meta: SegmentMeta,
+    // Tracks the writes of the segment, if it is the output of a merge.
+    merge_progress: Option<Arc<MergeProgress>>,
 }
 
 impl fmt::Debug for Segment {
@@ -26,7 +32,19 @@ impl fmt::Debug for Segment {
 impl Segment {
     /// Creates a new segment given an `Index` and a `SegmentId`
     pub(crate) fn for_index(index: Index, meta: SegmentMeta) -> Segment {
-        Segment { index, meta }
+        Segment {
+            index,
+            meta,
+            merge_progress: None,
+        }
+    }
+
+    /// Tracks and throttles the writes of the segment as the output of a merge.
+    pub(crate) fn with_merge_progress(self, merge_progress: Arc<MergeProgress>) -> Segment {
+        Segment {
+            merge_progress: Some(merge_progress),
+            ..self
+        }
     }
 
     /// Returns the index the segment belongs to.
@@ -52,6 +70,7 @@ impl Segment {
         Segment {
             index: self.index,
             meta: self.meta.with_max_doc(max_doc),
+            merge_progress: self.merge_progress,
         }
     }
 
@@ -60,6 +79,7 @@ impl Segment {
         Segment {
             index: self.index,
             meta: self.meta.with_delete_meta(num_deleted_docs, opstamp),
+            merge_progress: self.merge_progress,
         }
     }
 
@@ -76,6 +96,21 @@ impl Segment {
         self.meta.relative_path(component)
     }
 
+    /// Returns the size of the files of the segment in bytes, excluding its deletes
+    /// and its temporary store.
+    pub(crate) fn num_bytes(&self) -> u64 {
+        SegmentComponent::iterator()
+            .filter(|component| {
+                !matches!(
+                    component,
+                    SegmentComponent::TempStore | SegmentComponent::Delete
+                )
+            })
+            .filter_map(|component| self.open_read(*component).ok())
+            .map(|file| file.len() as u64)
+            .sum()
+    }
+
     /// Open one of the component file for a *regular* read.
     pub fn open_read(&self, component: SegmentComponent) -> Result<FileSlice, OpenReadError> {
         let path = self.relative_path(component);
@@ -85,7 +120,15 @@ impl Segment {
     /// Open one of the component file for *regular* write.
     pub fn open_write(&mut self, component: SegmentComponent) -> Result<WritePtr, OpenWriteError> {
         let path = self.relative_path(component);
-        let write = self.index.directory_mut().open_write(&path)?;
+        let durability = self.index.settings().durability;
+        let write = self
+            .index
+            .directory_mut()
+            .open_write_with_durability(&path, durability)?;
+        if let Some(merge_progress) = self.merge_progress.as_ref() {
+            let merge_write = MergeWrite::wrap(write, merge_progress.clone());
+            return Ok(BufWriter::new(Box::new(merge_write)));
+        }
         Ok(write)
     }
 }
@@ -1,10 +1,11 @@
+use std::path::Path;
 use std::slice;
 
 /// Enum describing each component of a tantivy segment.
 /// Each component is stored in its own file,
 /// using the pattern `segment_uuid`.`component_extension`,
 /// except the delete component that takes an `segment_uuid`.`delete_opstamp`.`component_extension`
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum SegmentComponent {
     /// Postings (or inverted list). Sorted lists of document ids, associated to terms
     Postings,
@@ -26,12 +27,15 @@ pub enum SegmentComponent {
     TempStore,
     /// Bitset describing which document of the segment is deleted.
     Delete,
+    /// Terms, positions and offsets of the tokens of each document, for the
+    /// fields storing term vectors.
+    TermVectors,
 }
 
 impl SegmentComponent {
     /// Iterates through the components.
     pub fn iterator() -> slice::Iter<'static, SegmentComponent> {
-        static SEGMENT_COMPONENTS: [SegmentComponent; 8] = [
+        static SEGMENT_COMPONENTS: [SegmentComponent; 9] = [
             SegmentComponent::Postings,
             SegmentComponent::Positions,
             SegmentComponent::FastFields,
@@ -40,7 +44,27 @@ impl SegmentComponent {
             SegmentComponent::Store,
             SegmentComponent::TempStore,
             SegmentComponent::Delete,
+            SegmentComponent::TermVectors,
         ];
         SEGMENT_COMPONENTS.iter()
     }
+
+    /// Returns the component of the file at `path`, given its extension.
+    pub(crate) fn from_path(path: &Path) -> Option<SegmentComponent> {
+        let file_name = path.file_name()?.to_str()?;
+        if file_name.ends_with(".store.temp") {
+            return Some(SegmentComponent::TempStore);
+        }
+        match path.extension()?.to_str()? {
+            "idx" => Some(SegmentComponent::Postings),
+            "pos" => Some(SegmentComponent::Positions),
+            "term" => Some(SegmentComponent::Terms),
+            "store" => Some(SegmentComponent::Store),
+            "fast" => Some(SegmentComponent::FastFields),
+            "fieldnorm" => Some(SegmentComponent::FieldNorms),
+            "del" => Some(SegmentComponent::Delete),
+            "termvec" => Some(SegmentComponent::TermVectors),
+            _ => None,
+        }
+    }
 }
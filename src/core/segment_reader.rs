@@ -1,3 +1,4 @@
+use crate::core::IndexSortByField;
 use crate::core::InvertedIndexReader;
 use crate::core::Segment;
 use crate::core::SegmentComponent;
@@ -15,7 +16,10 @@ use crate::schema::{Field, IndexRecordOption};
 use crate::space_usage::SegmentSpaceUsage;
 use crate::store::StoreReader;
 use crate::termdict::TermDictionary;
+use crate::termvector::{has_term_vectors, TermVectorReader, TermVectorReaders};
 use crate::DocId;
+use crate::Opstamp;
+use crate::TantivyError;
 use fail::fail_point;
 use std::fmt;
 use std::sync::Arc;
@@ -45,10 +49,13 @@ pub struct SegmentReader {
     positions_composite: CompositeFile,
     fast_fields_readers: Arc<FastFieldReaders>,
     fieldnorm_readers: FieldNormReaders,
+    term_vector_readers: TermVectorReaders,
 
     store_file: FileSlice,
     delete_bitset_opt: Option<DeleteBitSet>,
+    delete_opstamp: Option<Opstamp>,
     schema: Schema,
+    sort_by_field: Option<IndexSortByField>,
 }
 
 impl SegmentReader {
@@ -135,11 +142,36 @@ impl SegmentReader {
         })
     }
 
+    /// Accessor to the segment's term vectors of a field.
+    ///
+    /// Returns a `SchemaError` if the field does not store term vectors.
+    pub fn term_vectors(&self, field: Field) -> crate::Result<TermVectorReader> {
+        let field_entry = self.schema.get_field_entry(field);
+        if !has_term_vectors(field_entry.field_type()) {
+            return Err(crate::TantivyError::SchemaError(format!(
+                "Field {:?} does not store term vectors.",
+                field_entry.name()
+            )));
+        }
+        Ok(self
+            .term_vector_readers
+            .get_field(field)?
+            .unwrap_or_else(TermVectorReader::empty))
+    }
+
     /// Accessor to the segment's `StoreReader`.
     pub fn get_store_reader(&self) -> io::Result<StoreReader> {
         StoreReader::open(self.store_file.clone())
     }
 
+    /// Returns a `StoreReader` caching up to `cache_capacity` decompressed blocks.
+    pub(crate) fn get_store_reader_with_cache_capacity(
+        &self,
+        cache_capacity: usize,
+    ) -> io::Result<StoreReader> {
+        StoreReader::open_with_cache_capacity(self.store_file.clone(), cache_capacity)
+    }
+
     /// Open a new segment for reading.
     pub fn open(segment: &Segment) -> crate::Result<SegmentReader> {
         let termdict_file = segment.open_read(SegmentComponent::Terms)?;
@@ -164,15 +196,39 @@ impl SegmentReader {
 
         let fast_fields_data = segment.open_read(SegmentComponent::FastFields)?;
         let fast_fields_composite = CompositeFile::open(&fast_fields_data)?;
-        let fast_field_readers =
-            Arc::new(FastFieldReaders::new(schema.clone(), fast_fields_composite));
+        let fast_field_readers = Arc::new(FastFieldReaders::new(
+            schema.clone(),
+            segment.meta().max_doc(),
+            fast_fields_composite,
+        ));
 
         let fieldnorm_data = segment.open_read(SegmentComponent::FieldNorms)?;
         let fieldnorm_readers = FieldNormReaders::open(fieldnorm_data)?;
 
+        let term_vector_readers =
+            if let Ok(term_vectors_data) = segment.open_read(SegmentComponent::TermVectors) {
+                TermVectorReaders::open(term_vectors_data)?
+            } else {
+                TermVectorReaders::empty()
+            };
+
         let delete_bitset_opt = if segment.meta().has_deletes() {
-            let delete_data = segment.open_read(SegmentComponent::Delete)?;
-            let delete_bitset = DeleteBitSet::open(delete_data)?;
+            let delete_path = segment.relative_path(SegmentComponent::Delete);
+            let (delete_data, version) = segment
+                .index()
+                .directory()
+                .open_read_with_version(&delete_path)?;
+            let delete_bitset = DeleteBitSet::open_with_index_format_version(
+                delete_data,
+                segment.meta().max_doc(),
+                version.index_format_version,
+            )
+            .map_err(|err| match err {
+                TantivyError::DataCorruption(data_corruption) => {
+                    TantivyError::DataCorruption(data_corruption.with_filepath(delete_path))
+                }
+                err => err,
+            })?;
             Some(delete_bitset)
         } else {
             None
@@ -186,11 +242,14 @@ impl SegmentReader {
             postings_composite,
             fast_fields_readers: fast_field_readers,
             fieldnorm_readers,
+            term_vector_readers,
             segment_id: segment.id(),
             store_file,
             delete_bitset_opt,
+            delete_opstamp: segment.meta().delete_opstamp(),
             positions_composite,
             schema,
+            sort_by_field: segment.index().settings().sort_by_field.clone(),
         })
     }
 
@@ -267,6 +326,15 @@ impl SegmentReader {
         Ok(inv_idx_reader)
     }
 
+    /// Returns the field and the order by which the documents of the segment are sorted,
+    /// if the index is sorted.
+    ///
+    /// The documents of the segments of a sorted index are sorted by the values of the
+    /// fast field, as recorded for documents without a value.
+    pub fn sort_by_field(&self) -> Option<&IndexSortByField> {
+        self.sort_by_field.as_ref()
+    }
+
     /// Returns the segment id
     pub fn segment_id(&self) -> SegmentId {
         self.segment_id
@@ -278,6 +346,11 @@ impl SegmentReader {
         self.delete_bitset_opt.as_ref()
     }
 
+    /// Returns the opstamp of the last delete operation applied to the segment, if any.
+    pub(crate) fn delete_opstamp(&self) -> Option<Opstamp> {
+        self.delete_opstamp
+    }
+
     /// Returns true iff the `doc` is marked
     /// as deleted.
     pub fn is_deleted(&self, doc: DocId) -> bool {
@@ -288,7 +361,13 @@ impl SegmentReader {
 
     /// Returns an iterator that will iterate over the alive document ids
     pub fn doc_ids_alive(&self) -> impl Iterator<Item = DocId> + '_ {
-        (0u32..self.max_doc).filter(move |doc| !self.is_deleted(*doc))
+        let doc_id_iter: Box<dyn Iterator<Item = DocId> + '_> =
+            if let Some(delete_bitset) = self.delete_bitset_opt.as_ref() {
+                Box::new(delete_bitset.iter_alive(self.max_doc))
+            } else {
+                Box::new(0u32..self.max_doc)
+            };
+        doc_id_iter
     }
 
     /// Summarize total space usage of this segment.
@@ -300,6 +379,7 @@ impl SegmentReader {
             self.positions_composite.space_usage(),
             self.fast_fields_readers.space_usage(),
             self.fieldnorm_readers.space_usage(),
+            self.term_vector_readers.space_usage(),
             self.get_store_reader()?.space_usage(),
             self.delete_bitset_opt
                 .as_ref()
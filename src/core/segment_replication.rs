@@ -0,0 +1,434 @@
+use crate::core::index_snapshot::pin_last_commit;
+use crate::core::{Index, SegmentComponent, SegmentId, SegmentMeta, META_FILEPATH};
+use crate::directory::error::OpenReadError;
+use crate::directory::{Directory, FileSlice, Footer, TerminatingWrite, INDEX_WRITER_LOCK};
+use crate::error::DataCorruption;
+use crate::indexer::segment_updater::save_metas;
+use crate::{Opstamp, TantivyError};
+use crc32fast::Hasher;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// A file of a segment, as it is stored in the directory, i.e. including its footer.
+#[derive(Clone, Debug)]
+pub struct SegmentFile {
+    /// Path of the file, relative to the directory.
+    pub path: PathBuf,
+    /// Content of the file.
+    pub data: FileSlice,
+}
+
+/// The files of a committed segment.
+#[derive(Clone, Debug)]
+pub struct SegmentFiles {
+    /// Meta of the segment.
+    pub segment_meta: SegmentMeta,
+    /// Files of the segment, including its delete file if it has deletes.
+    pub files: Vec<SegmentFile>,
+}
+
+impl SegmentFiles {
+    /// Returns the delete file of the segment, if it has deletes.
+    ///
+    /// The delete file is the only file of a segment which changes between two commits.
+    /// It can be imported independently, with
+    /// [`Index::import_segment_deletes`](./struct.Index.html#method.import_segment_deletes).
+    pub fn delete_file(&self) -> Option<&SegmentFile> {
+        if !self.segment_meta.has_deletes() {
+            return None;
+        }
+        let delete_path = self.segment_meta.relative_path(SegmentComponent::Delete);
+        self.files.iter().find(|file| file.path == delete_path)
+    }
+}
+
+/// The files of the segments of the last commit, returned by
+/// [`Index::searchable_segment_files`](./struct.Index.html#method.searchable_segment_files).
+///
+/// The files are not garbage collected by an `IndexWriter` created from the index as long
+/// as this object lives.
+#[derive(Clone, Debug)]
+pub struct SearchableSegmentFiles {
+    /// Content of the `meta.json` of the commit.
+    pub meta_json: Vec<u8>,
+    /// Opstamp of the commit.
+    pub opstamp: Opstamp,
+    /// Files of each of the segments of the commit.
+    pub segments: Vec<SegmentFiles>,
+}
+
+pub(crate) fn searchable_segment_files(index: &Index) -> crate::Result<SearchableSegmentFiles> {
+    let (meta_json, index_meta) = pin_last_commit(index)?;
+    let directory = index.directory();
+    let mut segments = Vec::with_capacity(index_meta.segments.len());
+    for segment_meta in index_meta.segments {
+        let mut paths: Vec<PathBuf> = segment_meta.list_files().into_iter().collect();
+        paths.sort();
+        let mut files = Vec::with_capacity(paths.len());
+        for path in paths {
+            // The positions and the term vectors files are optional.
+            if !directory.exists(&path)? {
+                continue;
+            }
+            let data = directory.open_read_with_footer(&path)?;
+            files.push(SegmentFile { path, data });
+        }
+        segments.push(SegmentFiles {
+            segment_meta,
+            files,
+        });
+    }
+    Ok(SearchableSegmentFiles {
+        meta_json,
+        opstamp: index_meta.opstamp,
+        segments,
+    })
+}
+
+/// Verifies the checksum of the file against the one of its footer.
+fn check_segment_file(file: &SegmentFile) -> crate::Result<()> {
+    let (footer, body) = Footer::extract_footer(file.data.clone())
+        .map_err(|io_error| OpenReadError::wrap_io_error(io_error, file.path.clone()))?;
+    footer
+        .is_compatible()
+        .map_err(TantivyError::IncompatibleIndex)?;
+    let body_bytes = body
+        .read_bytes()
+        .map_err(|io_error| OpenReadError::wrap_io_error(io_error, file.path.clone()))?;
+    let mut hasher = Hasher::new();
+    hasher.update(body_bytes.as_slice());
+    if hasher.finalize() != footer.crc() {
+        return Err(DataCorruption::new(file.path.clone(), "Checksum mismatch".to_string()).into());
+    }
+    Ok(())
+}
+
+fn install_segment_file(index: &Index, file: &SegmentFile) -> crate::Result<()> {
+    let data = file
+        .data
+        .read_bytes()
+        .map_err(|io_error| OpenReadError::wrap_io_error(io_error, file.path.clone()))?;
+    let mut writer = index.directory().open_write_with_footer(&file.path)?;
+    writer.write_all(data.as_slice())?;
+    writer.terminate()?;
+    Ok(())
+}
+
+/// Deletes the files which are not used by the index anymore, i.e. the files of the
+/// retired segments and the obsolete delete files.
+fn garbage_collect_files(index: &Index) -> crate::Result<()> {
+    let mut living_files: HashSet<PathBuf> = HashSet::new();
+    for prepared_commit in index.list_prepared_commits()? {
+        for segment_meta in &prepared_commit.index_meta.segments {
+            living_files.extend(segment_meta.list_files());
+        }
+    }
+    let mut directory = index.directory().clone();
+    directory.garbage_collect(|| {
+        living_files.extend(
+            index
+                .list_all_segment_metas()
+                .into_iter()
+                .flat_map(|segment_meta| segment_meta.list_files()),
+        );
+        living_files.insert(META_FILEPATH.to_path_buf());
+        living_files
+    })?;
+    Ok(())
+}
+
+/// Publishes the segments of the index returned by `update_segments`.
+///
+/// The writer lock is held, so that the index cannot be modified in the meantime.
+fn publish_segments<F>(index: &Index, update_segments: F) -> crate::Result<()>
+where
+    F: FnOnce(Vec<SegmentMeta>) -> crate::Result<Vec<SegmentMeta>>,
+{
+    let _writer_lock = index.directory().acquire_lock(&INDEX_WRITER_LOCK)?;
+    let mut index_meta = index.load_metas()?;
+    let mut segment_metas = update_segments(index_meta.segments)?;
+    // The segments are sorted as in `SegmentUpdater::save_metas`.
+    segment_metas.sort_by_key(|segment_meta| -(segment_meta.max_doc() as i32));
+    index_meta.segments = segment_metas;
+    save_metas(&index_meta, index.directory())?;
+    garbage_collect_files(index)
+}
+
+fn invalid_argument(message: String) -> TantivyError {
+    TantivyError::InvalidArgument(message)
+}
+
+pub(crate) fn import_segment(
+    index: &Index,
+    files: &[SegmentFile],
+    segment_meta: &SegmentMeta,
+    retired_segment_ids: &[SegmentId],
+) -> crate::Result<()> {
+    let segment_files = segment_meta.list_files();
+    if let Some(file) = files
+        .iter()
+        .find(|file| !segment_files.contains(&file.path))
+    {
+        return Err(invalid_argument(format!(
+            "{:?} is not a file of the segment {}.",
+            file.path,
+            segment_meta.id().short_uuid_string()
+        )));
+    }
+    let mut required_components = vec![
+        SegmentComponent::Postings,
+        SegmentComponent::Terms,
+        SegmentComponent::Store,
+        SegmentComponent::FastFields,
+        SegmentComponent::FieldNorms,
+    ];
+    if segment_meta.has_deletes() {
+        required_components.push(SegmentComponent::Delete);
+    }
+    for component in required_components {
+        let path = segment_meta.relative_path(component);
+        if !files.iter().any(|file| file.path == path) {
+            return Err(invalid_argument(format!(
+                "The file {:?} of the segment is missing.",
+                path
+            )));
+        }
+    }
+    for file in files {
+        check_segment_file(file)?;
+    }
+    let segment_meta = segment_meta.track(index.inventory());
+    publish_segments(index, |mut segment_metas| {
+        if segment_metas
+            .iter()
+            .any(|committed_segment_meta| committed_segment_meta.id() == segment_meta.id())
+        {
+            return Err(invalid_argument(format!(
+                "The segment {} was already imported.",
+                segment_meta.id().short_uuid_string()
+            )));
+        }
+        for retired_segment_id in retired_segment_ids {
+            if !segment_metas
+                .iter()
+                .any(|committed_segment_meta| committed_segment_meta.id() == *retired_segment_id)
+            {
+                return Err(invalid_argument(format!(
+                    "The retired segment {} is not in the index.",
+                    retired_segment_id.short_uuid_string()
+                )));
+            }
+        }
+        for file in files {
+            install_segment_file(index, file)?;
+        }
+        segment_metas.retain(|committed_segment_meta| {
+            !retired_segment_ids.contains(&committed_segment_meta.id())
+        });
+        segment_metas.push(segment_meta);
+        Ok(segment_metas)
+    })
+}
+
+pub(crate) fn import_segment_deletes(
+    index: &Index,
+    delete_file: &SegmentFile,
+    segment_meta: &SegmentMeta,
+) -> crate::Result<()> {
+    let delete_opstamp = segment_meta.delete_opstamp().ok_or_else(|| {
+        invalid_argument(format!(
+            "The segment {} has no deletes.",
+            segment_meta.id().short_uuid_string()
+        ))
+    })?;
+    if delete_file.path != segment_meta.relative_path(SegmentComponent::Delete) {
+        return Err(invalid_argument(format!(
+            "{:?} is not the delete file of the segment {}.",
+            delete_file.path,
+            segment_meta.id().short_uuid_string()
+        )));
+    }
+    check_segment_file(delete_file)?;
+    publish_segments(index, |segment_metas| {
+        let mut is_imported = false;
+        let mut updated_segment_metas = Vec::with_capacity(segment_metas.len());
+        for committed_segment_meta in segment_metas {
+            if committed_segment_meta.id() != segment_meta.id() {
+                updated_segment_metas.push(committed_segment_meta);
+                continue;
+            }
+            is_imported = true;
+            if committed_segment_meta.max_doc() != segment_meta.max_doc() {
+                return Err(invalid_argument(format!(
+                    "The segment {} does not have the same number of documents.",
+                    segment_meta.id().short_uuid_string()
+                )));
+            }
+            match committed_segment_meta.delete_opstamp() {
+                Some(committed_delete_opstamp) if committed_delete_opstamp > delete_opstamp => {
+                    return Err(invalid_argument(format!(
+                        "The segment {} has more recent deletes.",
+                        segment_meta.id().short_uuid_string()
+                    )));
+                }
+                Some(committed_delete_opstamp) if committed_delete_opstamp == delete_opstamp => {
+                    // The deletes were already imported.
+                    updated_segment_metas.push(committed_segment_meta);
+                }
+                _ => {
+                    install_segment_file(index, delete_file)?;
+                    updated_segment_metas.push(
+                        committed_segment_meta
+                            .with_delete_meta(segment_meta.num_deleted_docs(), delete_opstamp),
+                    );
+                }
+            }
+        }
+        if !is_imported {
+            return Err(invalid_argument(format!(
+                "The segment {} is not in the index.",
+                segment_meta.id().short_uuid_string()
+            )));
+        }
+        Ok(updated_segment_metas)
+    })
+}
+
+#[cfg(feature = "mmap")]
+#[cfg(test)]
+mod tests {
+    use super::{SegmentFile, SegmentFiles};
+    use crate::collector::TopDocs;
+    use crate::directory::FileSlice;
+    use crate::indexer::NoMergePolicy;
+    use crate::query::TermQuery;
+    use crate::schema::{Field, IndexRecordOption, Schema, STORED, STRING};
+    use crate::{Index, IndexWriter, ReloadPolicy, SegmentMeta, TantivyError, Term};
+    use futures::executor::block_on;
+    use tempfile::TempDir;
+
+    /// Returns the texts of the documents matching the terms.
+    fn search(index: &Index, field: Field) -> crate::Result<Vec<Vec<String>>> {
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let searcher = reader.searcher();
+        let mut results = Vec::new();
+        for &text in &["a", "b", "c", "d"] {
+            let query =
+                TermQuery::new(Term::from_field_text(field, text), IndexRecordOption::Basic);
+            let mut texts = Vec::new();
+            for (_, doc_address) in searcher.search(&query, &TopDocs::with_limit(10))? {
+                let doc = searcher.doc(doc_address)?;
+                texts.push(doc.get_first(field).unwrap().text().unwrap().to_string());
+            }
+            texts.sort();
+            results.push(texts);
+        }
+        Ok(results)
+    }
+
+    /// Sends the segment to the replica, as bytes and json.
+    fn transport(segment_files: &SegmentFiles) -> crate::Result<(Vec<SegmentFile>, SegmentMeta)> {
+        let mut files = Vec::new();
+        for file in &segment_files.files {
+            files.push(SegmentFile {
+                path: file.path.clone(),
+                data: FileSlice::from(file.data.read_bytes()?.as_slice().to_vec()),
+            });
+        }
+        let segment_meta_json = serde_json::to_string(&segment_files.segment_meta)?;
+        Ok((files, serde_json::from_str(&segment_meta_json)?))
+    }
+
+    fn add_commit(
+        index_writer: &mut IndexWriter,
+        field: Field,
+        texts: &[&str],
+    ) -> crate::Result<()> {
+        for &text in texts {
+            index_writer.add_document(doc!(field => text));
+        }
+        index_writer.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_replicate_segments() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", STRING | STORED);
+        let schema = schema_builder.build();
+        let primary_dir = TempDir::new()?;
+        let primary = Index::create_in_dir(primary_dir.path(), schema.clone())?;
+        let replica_dir = TempDir::new()?;
+        let replica = Index::create_in_dir(replica_dir.path(), schema)?;
+        let mut index_writer = primary.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        add_commit(&mut index_writer, text_field, &["a", "b"])?;
+        add_commit(&mut index_writer, text_field, &["b", "c", "d"])?;
+        index_writer.delete_term(Term::from_field_text(text_field, "d"));
+        add_commit(&mut index_writer, text_field, &["c"])?;
+
+        let searchable_segment_files = primary.searchable_segment_files()?;
+        assert_eq!(searchable_segment_files.segments.len(), 3);
+        for segment_files in &searchable_segment_files.segments {
+            let (files, segment_meta) = transport(segment_files)?;
+            replica.import_segment(&files, &segment_meta, &[])?;
+        }
+        assert_eq!(search(&replica, text_field)?, search(&primary, text_field)?);
+        let (files, segment_meta) = transport(&searchable_segment_files.segments[0])?;
+        assert!(matches!(
+            replica.import_segment(&files, &segment_meta, &[]),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+
+        // The deletes are shipped without the rest of the segment.
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit()?;
+        let searchable_segment_files = primary.searchable_segment_files()?;
+        for segment_files in &searchable_segment_files.segments {
+            if let Some(delete_file) = segment_files.delete_file() {
+                // The deletes of the segment with "d" were already imported.
+                let (_, segment_meta) = transport(segment_files)?;
+                replica.import_segment_deletes(delete_file, &segment_meta)?;
+            }
+        }
+        assert_eq!(search(&replica, text_field)?, search(&primary, text_field)?);
+        assert_eq!(search(&replica, text_field)?[0], Vec::<String>::new());
+
+        // The merged segments are retired.
+        let segment_ids = primary.searchable_segment_ids()?;
+        let merged_segment_meta = block_on(index_writer.merge(&segment_ids))?;
+        let searchable_segment_files = primary.searchable_segment_files()?;
+        assert_eq!(searchable_segment_files.segments.len(), 1);
+        let (mut files, segment_meta) = transport(&searchable_segment_files.segments[0])?;
+        assert_eq!(segment_meta.id(), merged_segment_meta.id());
+        let mut corrupted_data = files[0].data.read_bytes()?.as_slice().to_vec();
+        corrupted_data[0] ^= 1;
+        let data = std::mem::replace(&mut files[0].data, FileSlice::from(corrupted_data));
+        assert!(matches!(
+            replica.import_segment(&files, &segment_meta, &segment_ids),
+            Err(TantivyError::DataCorruption(_))
+        ));
+        files[0].data = data;
+        replica.import_segment(&files, &segment_meta, &segment_ids)?;
+        assert_eq!(replica.searchable_segment_ids()?, vec![segment_meta.id()]);
+        assert_eq!(search(&replica, text_field)?, search(&primary, text_field)?);
+        assert!(replica_dir.path().join(&files[0].path).exists());
+        // The files of the retired segments are deleted.
+        for segment_id in &segment_ids {
+            let store_path = format!("{}.store", segment_id.uuid_string());
+            assert!(!replica_dir.path().join(store_path).exists());
+        }
+
+        // The segments cannot be imported while the replica has a writer.
+        let _replica_writer = replica.writer_for_tests()?;
+        assert!(matches!(
+            replica.import_segment(&files, &segment_meta, &[]),
+            Err(TantivyError::LockFailure(..))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,441 @@
+use crate::core::{IndexMeta, SegmentMetaInventory, META_FILEPATH};
+use crate::directory::error::{DeleteError, LockError, OpenReadError, OpenWriteError};
+use crate::directory::{
+    Directory, DirectoryLock, FileHandle, FileSlice, Lock, WatchCallback, WatchHandle, WritePtr,
+    INDEX_WRITER_LOCK,
+};
+use crate::error::DataCorruption;
+use common::{BinarySerializable, CountingWriter, HasLen, VInt};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Identifies a bundle, at the very end of its footer.
+const BUNDLE_MAGIC_NUMBER: u32 = 0x7461_6e62;
+
+fn unsupported(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "The bundle directory is read-only, {:?} cannot be written.",
+            path
+        ),
+    )
+}
+
+/// A read-only directory, serving the files of an index packed in a single file
+/// with [`BundleDirectory::pack`](#method.pack).
+///
+/// The bundle is opened from a `FileSlice`, e.g. of a memory mapped file or of
+/// `OwnedBytes`, and its files are slices of it.
+///
+/// All of the write operations fail with an `io::ErrorKind::Unsupported` error,
+/// and so does the creation of an `IndexWriter`.
+#[derive(Clone)]
+pub struct BundleDirectory {
+    data: FileSlice,
+    file_ranges: Arc<HashMap<PathBuf, Range<usize>>>,
+}
+
+impl fmt::Debug for BundleDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BundleDirectory({} files)", self.file_ranges.len())
+    }
+}
+
+impl BundleDirectory {
+    /// Packs the index of the `source` directory in `dest`.
+    ///
+    /// The `meta.json` and the files of the segments of the last commit are written one
+    /// after the other, followed by a footer listing their paths and lengths.
+    ///
+    /// The files of the segments are read as they are stored, with their footer: `source`
+    /// must be the directory the index was created in, e.g. a `MmapDirectory`, and not
+    /// the `ManagedDirectory` returned by `Index::directory()`.
+    pub fn pack<W: Write>(source: &dyn Directory, dest: &mut W) -> crate::Result<()> {
+        let meta_data = source.atomic_read(&META_FILEPATH)?;
+        let index_meta = std::str::from_utf8(&meta_data)
+            .ok()
+            .and_then(|meta_json| {
+                IndexMeta::deserialize(meta_json, &SegmentMetaInventory::default()).ok()
+            })
+            .ok_or_else(|| {
+                DataCorruption::new(
+                    META_FILEPATH.to_path_buf(),
+                    "Meta file cannot be deserialized.".to_string(),
+                )
+            })?;
+        let mut paths: Vec<PathBuf> = index_meta
+            .segments
+            .iter()
+            .flat_map(|segment_meta| segment_meta.list_files())
+            .collect();
+        paths.sort();
+        let mut dest = CountingWriter::wrap(dest);
+        let mut files: Vec<(PathBuf, u64)> = Vec::with_capacity(paths.len() + 1);
+        dest.write_all(&meta_data)?;
+        files.push((META_FILEPATH.to_path_buf(), meta_data.len() as u64));
+        for path in paths {
+            // The positions and the term vectors files are optional.
+            if !source.exists(&path)? {
+                continue;
+            }
+            let data = source
+                .open_read(&path)?
+                .read_bytes()
+                .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.clone()))?;
+            dest.write_all(data.as_slice())?;
+            files.push((path, data.len() as u64));
+        }
+        let footer_start = dest.written_bytes();
+        VInt(files.len() as u64).serialize(&mut dest)?;
+        for (path, num_bytes) in files {
+            let path_str = path.to_str().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("The path {:?} is not valid utf8.", path),
+                )
+            })?;
+            path_str.to_string().serialize(&mut dest)?;
+            VInt(num_bytes).serialize(&mut dest)?;
+        }
+        let footer_len = (dest.written_bytes() - footer_start) as u32;
+        footer_len.serialize(&mut dest)?;
+        BUNDLE_MAGIC_NUMBER.serialize(&mut dest)?;
+        dest.flush()?;
+        Ok(())
+    }
+
+    /// Opens the bundle of `data`.
+    pub fn open(data: FileSlice) -> io::Result<BundleDirectory> {
+        let not_a_bundle =
+            || io::Error::new(io::ErrorKind::InvalidData, "The file is not a bundle.");
+        let end = data.len();
+        if end < 8 {
+            return Err(not_a_bundle());
+        }
+        let tail_data = data.slice_from(end - 8).read_bytes()?;
+        let mut tail = tail_data.as_slice();
+        let footer_len = u32::deserialize(&mut tail)? as usize;
+        if u32::deserialize(&mut tail)? != BUNDLE_MAGIC_NUMBER || footer_len > end - 8 {
+            return Err(not_a_bundle());
+        }
+        let footer_start = end - 8 - footer_len;
+        let footer_data = data.slice(footer_start..end - 8).read_bytes()?;
+        let mut footer = footer_data.as_slice();
+        // The footer is not trusted: the lengths are checked against the bytes which are
+        // actually there before allocating anything.
+        let read_len = |footer: &mut &[u8]| {
+            VInt::deserialize(footer)
+                .ok()
+                .and_then(|len| usize::try_from(len.0).ok())
+                .ok_or_else(not_a_bundle)
+        };
+        let num_files = read_len(&mut footer)?;
+        // Each file takes at least two bytes: the length of its path and its number of
+        // bytes.
+        if num_files > footer.len() / 2 {
+            return Err(not_a_bundle());
+        }
+        let mut file_ranges = HashMap::with_capacity(num_files);
+        let mut offset = 0usize;
+        for _ in 0..num_files {
+            let path_len = read_len(&mut footer)?;
+            if path_len > footer.len() {
+                return Err(not_a_bundle());
+            }
+            let (path_bytes, footer_rest) = footer.split_at(path_len);
+            let path = std::str::from_utf8(path_bytes).map_err(|_| not_a_bundle())?;
+            footer = footer_rest;
+            let num_bytes = read_len(&mut footer)?;
+            let file_end = offset
+                .checked_add(num_bytes)
+                .filter(|&file_end| file_end <= footer_start)
+                .ok_or_else(not_a_bundle)?;
+            file_ranges.insert(PathBuf::from(path), offset..file_end);
+            offset = file_end;
+        }
+        Ok(BundleDirectory {
+            data: data.slice(0..footer_start),
+            file_ranges: Arc::new(file_ranges),
+        })
+    }
+
+    /// Returns the paths of the files of the bundle.
+    pub fn list_files(&self) -> Vec<PathBuf> {
+        self.file_ranges.keys().cloned().collect()
+    }
+}
+
+impl Directory for BundleDirectory {
+    fn get_file_handle(&self, path: &Path) -> Result<Box<dyn FileHandle>, OpenReadError> {
+        let file_slice = self.open_read(path)?;
+        Ok(Box::new(file_slice))
+    }
+
+    fn open_read(&self, path: &Path) -> Result<FileSlice, OpenReadError> {
+        self.file_ranges
+            .get(path)
+            .map(|range| self.data.slice(range.clone()))
+            .ok_or_else(|| OpenReadError::FileDoesNotExist(path.to_path_buf()))
+    }
+
+    fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+        Err(DeleteError::IoError {
+            io_error: unsupported(path),
+            filepath: path.to_path_buf(),
+        })
+    }
+
+    fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+        Ok(self.file_ranges.contains_key(path))
+    }
+
+    fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+        Err(OpenWriteError::wrap_io_error(
+            unsupported(path),
+            path.to_path_buf(),
+        ))
+    }
+
+    fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+        let bytes = self
+            .open_read(path)?
+            .read_bytes()
+            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
+        Ok(bytes.as_slice().to_owned())
+    }
+
+    fn atomic_write(&self, path: &Path, _data: &[u8]) -> io::Result<()> {
+        Err(unsupported(path))
+    }
+
+    fn acquire_lock(&self, lock: &Lock) -> Result<DirectoryLock, LockError> {
+        // The index cannot be modified: only the readers' locks are granted, and there
+        // is nothing to protect them from.
+        if lock.filepath == INDEX_WRITER_LOCK.filepath {
+            return Err(LockError::IoError(unsupported(&lock.filepath)));
+        }
+        Ok(DirectoryLock::from(Box::new(())))
+    }
+
+    fn watch(&self, _watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+        // The bundle never changes.
+        Ok(WatchHandle::empty())
+    }
+
+    fn watch_path(
+        &self,
+        _path: &Path,
+        _watch_callback: WatchCallback,
+    ) -> crate::Result<WatchHandle> {
+        Ok(WatchHandle::empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BundleDirectory, BUNDLE_MAGIC_NUMBER};
+    use crate::collector::TopDocs;
+    use crate::directory::{Directory, FileSlice, RamDirectory};
+    use crate::query::{PhraseQuery, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, FAST, STORED, TEXT};
+    use crate::{DocAddress, Index, IndexSettings, ReloadPolicy, Term};
+    use common::{BinarySerializable, VInt};
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    fn create_index(directory: &RamDirectory) -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let num_field = schema_builder.add_u64_field("num", FAST | STORED);
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..20u64 {
+            let text = format!("hello happy {} world", i % 3);
+            index_writer.add_document(doc!(text_field => text, num_field => i));
+            if i == 9 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.delete_term(Term::from_field_text(text_field, "2"));
+        index_writer.commit()?;
+        Ok(index)
+    }
+
+    /// Returns the documents matched by a few queries, ordered by the `num` fast field.
+    fn search(index: &Index) -> crate::Result<Vec<Vec<String>>> {
+        let schema = index.schema();
+        let text_field = schema.get_field("text").unwrap();
+        let num_field = schema.get_field("num").unwrap();
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let searcher = reader.searcher();
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "1"),
+            IndexRecordOption::Basic,
+        );
+        let phrase_query = PhraseQuery::new(vec![
+            Term::from_field_text(text_field, "happy"),
+            Term::from_field_text(text_field, "0"),
+        ]);
+        let collector = TopDocs::with_limit(20).order_by_u64_field(num_field);
+        let mut results = Vec::new();
+        for doc_addresses in vec![
+            searcher.search(&term_query, &collector)?,
+            searcher.search(&phrase_query, &collector)?,
+        ] {
+            let docs = doc_addresses
+                .into_iter()
+                .map(|(_, doc_address): (u64, DocAddress)| {
+                    Ok(schema.to_json(&searcher.doc(doc_address)?))
+                })
+                .collect::<crate::Result<Vec<String>>>()?;
+            results.push(docs);
+        }
+        Ok(results)
+    }
+
+    #[test]
+    fn test_bundle_directory() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let index = create_index(&directory)?;
+        let mut bundle = Vec::new();
+        BundleDirectory::pack(&directory, &mut bundle)?;
+        let bundle_directory = BundleDirectory::open(FileSlice::from(bundle))?;
+        let bundle_index = Index::open(bundle_directory.clone())?;
+        let results = search(&bundle_index)?;
+        assert_eq!(results[0].len(), 7);
+        assert_eq!(results[1].len(), 7);
+        assert_eq!(results, search(&index)?);
+
+        let meta_path = Path::new("meta.json");
+        assert_eq!(
+            bundle_directory.atomic_read(meta_path)?,
+            directory.atomic_read(meta_path)?
+        );
+        let file_path = bundle_directory
+            .list_files()
+            .into_iter()
+            .find(|path| path.as_path() != meta_path)
+            .unwrap();
+        assert_eq!(
+            bundle_directory
+                .open_read(&file_path)?
+                .read_bytes()?
+                .as_slice(),
+            directory.open_read(&file_path)?.read_bytes()?.as_slice()
+        );
+        assert!(!bundle_directory.exists(Path::new("missing"))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundle_directory_is_read_only() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        create_index(&directory)?;
+        let mut bundle = Vec::new();
+        BundleDirectory::pack(&directory, &mut bundle)?;
+        let bundle_directory = BundleDirectory::open(FileSlice::from(bundle))?;
+        let meta_path = Path::new("meta.json");
+        assert_eq!(
+            bundle_directory
+                .atomic_write(meta_path, b"")
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::Unsupported
+        );
+        assert!(bundle_directory.open_write(Path::new("new")).is_err());
+        assert!(bundle_directory.delete(meta_path).is_err());
+        assert!(Index::open(bundle_directory)?.writer_for_tests().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_invalid_bundle() {
+        for data in vec![vec![], vec![0u8; 100]] {
+            let err = BundleDirectory::open(FileSlice::from(data)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    fn bundle_with_footer(num_data_bytes: usize, footer: &[u8]) -> FileSlice {
+        let mut bundle = vec![0u8; num_data_bytes];
+        bundle.extend_from_slice(footer);
+        (footer.len() as u32).serialize(&mut bundle).unwrap();
+        BUNDLE_MAGIC_NUMBER.serialize(&mut bundle).unwrap();
+        FileSlice::from(bundle)
+    }
+
+    #[test]
+    fn test_open_corrupted_bundle() {
+        let mut valid_footer = Vec::new();
+        VInt(1).serialize(&mut valid_footer).unwrap();
+        "file".to_string().serialize(&mut valid_footer).unwrap();
+        VInt(10).serialize(&mut valid_footer).unwrap();
+        let bundle_directory =
+            BundleDirectory::open(bundle_with_footer(10, &valid_footer)).unwrap();
+        assert_eq!(bundle_directory.list_files(), vec![PathBuf::from("file")]);
+
+        let mut footers = Vec::new();
+        // a number of files which cannot fit in the footer.
+        let mut footer = Vec::new();
+        VInt(u64::MAX).serialize(&mut footer).unwrap();
+        footers.push(footer);
+        // a path longer than the footer.
+        let mut footer = Vec::new();
+        VInt(1).serialize(&mut footer).unwrap();
+        VInt(1 << 40).serialize(&mut footer).unwrap();
+        footer.extend_from_slice(b"file");
+        footers.push(footer);
+        // a file past the data of the bundle.
+        let mut footer = Vec::new();
+        VInt(1).serialize(&mut footer).unwrap();
+        "file".to_string().serialize(&mut footer).unwrap();
+        VInt(11).serialize(&mut footer).unwrap();
+        footers.push(footer);
+        // file sizes overflowing their sum.
+        let mut footer = Vec::new();
+        VInt(2).serialize(&mut footer).unwrap();
+        "first".to_string().serialize(&mut footer).unwrap();
+        VInt(10).serialize(&mut footer).unwrap();
+        "second".to_string().serialize(&mut footer).unwrap();
+        VInt(u64::MAX - 5).serialize(&mut footer).unwrap();
+        footers.push(footer);
+        // a truncated footer.
+        footers.push(valid_footer[..valid_footer.len() - 1].to_vec());
+        for footer in footers {
+            let err = BundleDirectory::open(bundle_with_footer(10, &footer)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_bundle_directory_mmap() -> crate::Result<()> {
+        use crate::directory::MmapDirectory;
+        use std::fs::File;
+
+        let directory = RamDirectory::create();
+        let index = create_index(&directory)?;
+        let tempdir = tempfile::TempDir::new()?;
+        let mut bundle_file = File::create(tempdir.path().join("index.bundle"))?;
+        BundleDirectory::pack(&directory, &mut bundle_file)?;
+        drop(bundle_file);
+        let bundle_data =
+            MmapDirectory::open(tempdir.path())?.open_read(Path::new("index.bundle"))?;
+        let bundle_index = Index::open(BundleDirectory::open(bundle_data)?)?;
+        assert_eq!(search(&bundle_index)?, search(&index)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,814 @@
+use crate::directory::error::{DeleteError, LockError, OpenReadError, OpenWriteError};
+use crate::directory::{
+    Directory, DirectoryLock, Durability, FileHandle, Lock, OwnedBytes, WatchCallback, WatchHandle,
+    WritePtr,
+};
+use common::HasLen;
+use futures::future::BoxFuture;
+use lru::LruCache;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Configuration of the cache of a [`CachingDirectory`](struct.CachingDirectory.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CacheConfig {
+    /// Files are read and cached by blocks of `block_size` bytes.
+    pub block_size: usize,
+    /// Maximum number of bytes of the blocks kept in memory.
+    pub max_num_bytes: usize,
+    /// If set, the blocks evicted from the memory are written in a directory created
+    /// in `spill_directory`, and read from there rather than from the inner directory.
+    ///
+    /// The directory is removed when the cache is dropped.
+    pub spill_directory: Option<PathBuf>,
+    /// Maximum number of bytes of the blocks kept in the spill directory.
+    pub max_spill_num_bytes: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            block_size: 64 * 1_024,
+            max_num_bytes: 64 * 1_024 * 1_024,
+            spill_directory: None,
+            max_spill_num_bytes: 1_024 * 1_024 * 1_024,
+        }
+    }
+}
+
+/// Statistics of the cache of a [`CachingDirectory`](struct.CachingDirectory.html).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CachingDirectoryStats {
+    /// Number of blocks read from the memory.
+    pub hits: u64,
+    /// Number of blocks read from the spill directory.
+    pub spill_hits: u64,
+    /// Number of blocks read from the inner directory.
+    pub misses: u64,
+    /// Number of blocks evicted from the memory.
+    pub evicted: u64,
+    /// Number of blocks in memory.
+    pub num_blocks: usize,
+    /// Number of bytes of the blocks in memory.
+    pub num_bytes: usize,
+}
+
+// The generation of a path is incremented whenever the file is written or deleted,
+// so that the blocks of its previous content are never served to a new file handle.
+type BlockKey = (PathBuf, u64, usize);
+
+struct MemoryBlocks {
+    blocks: LruCache<BlockKey, OwnedBytes>,
+    num_bytes: usize,
+}
+
+struct SpilledBlocks {
+    // The id of the file of each block, and its number of bytes.
+    blocks: LruCache<BlockKey, (u64, usize)>,
+    num_bytes: usize,
+    next_file_id: u64,
+}
+
+struct SpillDirectory {
+    path: PathBuf,
+    max_num_bytes: usize,
+    blocks: Mutex<SpilledBlocks>,
+}
+
+impl SpillDirectory {
+    fn create(parent: &Path, max_num_bytes: usize) -> io::Result<SpillDirectory> {
+        let path = parent.join(format!("tantivy-cache-{}", Uuid::new_v4().to_simple()));
+        fs::create_dir_all(&path)?;
+        Ok(SpillDirectory {
+            path,
+            max_num_bytes,
+            blocks: Mutex::new(SpilledBlocks {
+                blocks: LruCache::unbounded(),
+                num_bytes: 0,
+                next_file_id: 0,
+            }),
+        })
+    }
+
+    fn block_path(&self, file_id: u64) -> PathBuf {
+        self.path.join(format!("{}.block", file_id))
+    }
+
+    fn read(&self, key: &BlockKey) -> Option<OwnedBytes> {
+        let file_id = self.blocks.lock().unwrap().blocks.get(key)?.0;
+        match fs::read(self.block_path(file_id)) {
+            Ok(data) => Some(OwnedBytes::new(data)),
+            Err(io_error) => {
+                warn!("Failed to read a spilled block: {:?}", io_error);
+                None
+            }
+        }
+    }
+
+    fn write(&self, key: BlockKey, data: &OwnedBytes) {
+        if data.len() > self.max_num_bytes {
+            return;
+        }
+        let mut blocks = self.blocks.lock().unwrap();
+        if blocks.blocks.contains(&key) {
+            return;
+        }
+        let file_id = blocks.next_file_id;
+        blocks.next_file_id += 1;
+        if let Err(io_error) = fs::write(self.block_path(file_id), data.as_slice()) {
+            warn!("Failed to spill a block: {:?}", io_error);
+            return;
+        }
+        blocks.blocks.put(key, (file_id, data.len()));
+        blocks.num_bytes += data.len();
+        while blocks.num_bytes > self.max_num_bytes {
+            if let Some((_, (file_id, num_bytes))) = blocks.blocks.pop_lru() {
+                blocks.num_bytes -= num_bytes;
+                let _ = fs::remove_file(self.block_path(file_id));
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn invalidate(&self, path: &Path) {
+        let mut blocks = self.blocks.lock().unwrap();
+        let keys: Vec<BlockKey> = blocks
+            .blocks
+            .iter()
+            .filter(|(key, _)| key.0 == path)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            if let Some((file_id, num_bytes)) = blocks.blocks.pop(&key) {
+                blocks.num_bytes -= num_bytes;
+                let _ = fs::remove_file(self.block_path(file_id));
+            }
+        }
+    }
+}
+
+impl Drop for SpillDirectory {
+    fn drop(&mut self) {
+        if let Err(io_error) = fs::remove_dir_all(&self.path) {
+            warn!(
+                "Failed to remove the spill directory {:?}: {:?}",
+                self.path, io_error
+            );
+        }
+    }
+}
+
+struct BlockCache {
+    block_size: usize,
+    max_num_bytes: usize,
+    memory: Mutex<MemoryBlocks>,
+    spill: Option<SpillDirectory>,
+    generations: Mutex<HashMap<PathBuf, u64>>,
+    hits: AtomicU64,
+    spill_hits: AtomicU64,
+    misses: AtomicU64,
+    evicted: AtomicU64,
+}
+
+impl BlockCache {
+    fn generation(&self, path: &Path) -> u64 {
+        self.generations
+            .lock()
+            .unwrap()
+            .get(path)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn get(&self, key: &BlockKey) -> Option<OwnedBytes> {
+        if let Some(block) = self.memory.lock().unwrap().blocks.get(key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Some(block.clone());
+        }
+        let block = self.spill.as_ref()?.read(key)?;
+        self.spill_hits.fetch_add(1, Ordering::Relaxed);
+        self.insert(key.clone(), block.clone());
+        Some(block)
+    }
+
+    fn insert(&self, key: BlockKey, block: OwnedBytes) {
+        if block.len() > self.max_num_bytes {
+            return;
+        }
+        let mut evicted_blocks = Vec::new();
+        {
+            let mut memory = self.memory.lock().unwrap();
+            memory.num_bytes += block.len();
+            if let Some(previous_block) = memory.blocks.put(key, block) {
+                memory.num_bytes -= previous_block.len();
+            }
+            while memory.num_bytes > self.max_num_bytes {
+                if let Some((evicted_key, evicted_block)) = memory.blocks.pop_lru() {
+                    memory.num_bytes -= evicted_block.len();
+                    evicted_blocks.push((evicted_key, evicted_block));
+                } else {
+                    break;
+                }
+            }
+        }
+        self.evicted
+            .fetch_add(evicted_blocks.len() as u64, Ordering::Relaxed);
+        // The blocks are spilled once the memory lock is released.
+        if let Some(spill) = self.spill.as_ref() {
+            for (evicted_key, evicted_block) in evicted_blocks {
+                spill.write(evicted_key, &evicted_block);
+            }
+        }
+    }
+
+    fn invalidate(&self, path: &Path) {
+        *self
+            .generations
+            .lock()
+            .unwrap()
+            .entry(path.to_path_buf())
+            .or_insert(0) += 1;
+        {
+            let mut memory = self.memory.lock().unwrap();
+            let keys: Vec<BlockKey> = memory
+                .blocks
+                .iter()
+                .filter(|(key, _)| key.0 == path)
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in keys {
+                if let Some(block) = memory.blocks.pop(&key) {
+                    memory.num_bytes -= block.len();
+                }
+            }
+        }
+        if let Some(spill) = self.spill.as_ref() {
+            spill.invalidate(path);
+        }
+    }
+
+    fn stats(&self) -> CachingDirectoryStats {
+        let memory = self.memory.lock().unwrap();
+        CachingDirectoryStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            spill_hits: self.spill_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evicted: self.evicted.load(Ordering::Relaxed),
+            num_blocks: memory.blocks.len(),
+            num_bytes: memory.num_bytes,
+        }
+    }
+}
+
+struct CachingFileHandle {
+    path: PathBuf,
+    generation: u64,
+    num_bytes: usize,
+    inner: Box<dyn FileHandle>,
+    cache: Arc<BlockCache>,
+}
+
+impl fmt::Debug for CachingFileHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CachingFileHandle({:?}, {:?})", self.path, self.inner)
+    }
+}
+
+impl HasLen for CachingFileHandle {
+    fn len(&self) -> usize {
+        self.num_bytes
+    }
+}
+
+impl CachingFileHandle {
+    fn block_key(&self, block_id: usize) -> BlockKey {
+        (self.path.clone(), self.generation, block_id)
+    }
+
+    fn block_range(&self, block_ids: Range<usize>) -> Range<usize> {
+        let block_size = self.cache.block_size;
+        block_ids.start * block_size..self.num_bytes.min(block_ids.end * block_size)
+    }
+
+    /// Returns the blocks spanning `range`: the cached ones, and the ranges of the
+    /// ids of the consecutive missing ones, to be fetched with a single read.
+    fn lookup_blocks(&self, range: &Range<usize>) -> Vec<Result<OwnedBytes, Range<usize>>> {
+        let block_size = self.cache.block_size;
+        let first_block_id = range.start / block_size;
+        let end_block_id = (range.end + block_size - 1) / block_size;
+        let mut blocks = Vec::with_capacity(end_block_id - first_block_id);
+        let mut missing_start = None;
+        for block_id in first_block_id..end_block_id {
+            match self.cache.get(&self.block_key(block_id)) {
+                Some(block) => {
+                    if let Some(start) = missing_start.take() {
+                        blocks.push(Err(start..block_id));
+                    }
+                    blocks.push(Ok(block));
+                }
+                None => {
+                    missing_start.get_or_insert(block_id);
+                }
+            }
+        }
+        if let Some(start) = missing_start {
+            blocks.push(Err(start..end_block_id));
+        }
+        blocks
+    }
+
+    /// Splits the `data` of the blocks `block_ids` read from the inner file handle,
+    /// and caches them.
+    fn split_blocks(&self, block_ids: Range<usize>, data: OwnedBytes) -> Vec<OwnedBytes> {
+        let block_size = self.cache.block_size;
+        self.cache
+            .misses
+            .fetch_add(block_ids.len() as u64, Ordering::Relaxed);
+        let num_blocks = block_ids.len();
+        let mut blocks = Vec::with_capacity(num_blocks);
+        for (i, block_id) in block_ids.enumerate() {
+            let start = i * block_size;
+            let mut block = data.slice(start..data.len().min(start + block_size));
+            if num_blocks > 1 {
+                // Otherwise the evicted blocks would not free the bytes of the read.
+                block = OwnedBytes::new(block.as_slice().to_vec());
+            }
+            self.cache.insert(self.block_key(block_id), block.clone());
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    /// Returns the bytes of `range`, given the blocks spanning it.
+    fn assemble(&self, range: Range<usize>, mut blocks: Vec<OwnedBytes>) -> OwnedBytes {
+        let offset = range.start / self.cache.block_size * self.cache.block_size;
+        let local_range = range.start - offset..range.end - offset;
+        if blocks.len() == 1 {
+            return blocks.pop().unwrap().slice(local_range);
+        }
+        let mut data = Vec::with_capacity(blocks.iter().map(|block| block.len()).sum());
+        for block in &blocks {
+            data.extend_from_slice(block.as_slice());
+        }
+        OwnedBytes::new(data).slice(local_range)
+    }
+}
+
+impl FileHandle for CachingFileHandle {
+    fn read_bytes(&self, range: Range<usize>) -> io::Result<OwnedBytes> {
+        if range.start >= range.end {
+            return Ok(OwnedBytes::empty());
+        }
+        let mut blocks = Vec::new();
+        for lookup in self.lookup_blocks(&range) {
+            match lookup {
+                Ok(block) => blocks.push(block),
+                Err(block_ids) => {
+                    let data = self.inner.read_bytes(self.block_range(block_ids.clone()))?;
+                    blocks.extend(self.split_blocks(block_ids, data));
+                }
+            }
+        }
+        Ok(self.assemble(range, blocks))
+    }
+
+    fn read_bytes_async(&self, range: Range<usize>) -> BoxFuture<'_, io::Result<OwnedBytes>> {
+        Box::pin(async move {
+            if range.start >= range.end {
+                return Ok(OwnedBytes::empty());
+            }
+            let mut blocks = Vec::new();
+            for lookup in self.lookup_blocks(&range) {
+                match lookup {
+                    Ok(block) => blocks.push(block),
+                    Err(block_ids) => {
+                        let data = self
+                            .inner
+                            .read_bytes_async(self.block_range(block_ids.clone()))
+                            .await?;
+                        blocks.extend(self.split_blocks(block_ids, data));
+                    }
+                }
+            }
+            Ok(self.assemble(range, blocks))
+        })
+    }
+}
+
+/// A directory wrapping a slow `Directory`, e.g. backed by an object storage, and
+/// caching the bytes read from its files.
+///
+/// Files are read by blocks of [`CacheConfig::block_size`](struct.CacheConfig.html)
+/// bytes, kept in memory in a LRU cache and optionally spilled to a local directory.
+/// The blocks of a file are invalidated when it is written or deleted through
+/// the `CachingDirectory`.
+///
+/// Atomic reads, e.g. of `meta.json`, are never cached.
+#[derive(Clone)]
+pub struct CachingDirectory {
+    inner: Arc<dyn Directory>,
+    cache: Arc<BlockCache>,
+}
+
+impl fmt::Debug for CachingDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CachingDirectory({:?})", self.inner)
+    }
+}
+
+impl CachingDirectory {
+    /// Wraps `inner`, with a cache configured by `cache`.
+    ///
+    /// Fails if the spill directory cannot be created.
+    pub fn new(inner: Arc<dyn Directory>, cache: CacheConfig) -> io::Result<CachingDirectory> {
+        if cache.block_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The block size must be strictly positive.",
+            ));
+        }
+        let spill = cache
+            .spill_directory
+            .as_ref()
+            .map(|spill_directory| {
+                SpillDirectory::create(spill_directory, cache.max_spill_num_bytes)
+            })
+            .transpose()?;
+        Ok(CachingDirectory {
+            inner,
+            cache: Arc::new(BlockCache {
+                block_size: cache.block_size,
+                max_num_bytes: cache.max_num_bytes,
+                memory: Mutex::new(MemoryBlocks {
+                    blocks: LruCache::unbounded(),
+                    num_bytes: 0,
+                }),
+                spill,
+                generations: Mutex::new(HashMap::new()),
+                hits: AtomicU64::new(0),
+                spill_hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+                evicted: AtomicU64::new(0),
+            }),
+        })
+    }
+
+    /// Returns the statistics of the cache.
+    pub fn stats(&self) -> CachingDirectoryStats {
+        self.cache.stats()
+    }
+}
+
+impl Directory for CachingDirectory {
+    fn get_file_handle(&self, path: &Path) -> Result<Box<dyn FileHandle>, OpenReadError> {
+        let generation = self.cache.generation(path);
+        let inner = self.inner.get_file_handle(path)?;
+        Ok(Box::new(CachingFileHandle {
+            path: path.to_path_buf(),
+            generation,
+            num_bytes: inner.len(),
+            inner,
+            cache: self.cache.clone(),
+        }))
+    }
+
+    fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+        self.cache.invalidate(path);
+        self.inner.delete(path)
+    }
+
+    fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+        self.inner.exists(path)
+    }
+
+    fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+        self.cache.invalidate(path);
+        self.inner.open_write(path)
+    }
+
+    fn open_write_with_durability(
+        &self,
+        path: &Path,
+        durability: Durability,
+    ) -> Result<WritePtr, OpenWriteError> {
+        self.cache.invalidate(path);
+        self.inner.open_write_with_durability(path, durability)
+    }
+
+    fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+        self.inner.atomic_read(path)
+    }
+
+    fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.cache.invalidate(path);
+        self.inner.atomic_write(path, data)
+    }
+
+    fn atomic_write_with_durability(
+        &self,
+        path: &Path,
+        data: &[u8],
+        durability: Durability,
+    ) -> io::Result<()> {
+        self.cache.invalidate(path);
+        self.inner
+            .atomic_write_with_durability(path, data, durability)
+    }
+
+    fn acquire_lock(&self, lock: &Lock) -> Result<DirectoryLock, LockError> {
+        self.inner.acquire_lock(lock)
+    }
+
+    fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+        self.inner.watch(watch_callback)
+    }
+
+    fn watch_path(&self, path: &Path, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+        self.inner.watch_path(path, watch_callback)
+    }
+
+    fn local_file_path(&self, path: &Path) -> Option<PathBuf> {
+        self.inner.local_file_path(path)
+    }
+
+    fn advise_merge_read(&self, path: &Path) {
+        self.inner.advise_merge_read(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheConfig, CachingDirectory};
+    use crate::directory::error::OpenReadError;
+    use crate::directory::error::{DeleteError, OpenWriteError};
+    use crate::directory::WritePtr;
+    use crate::directory::{
+        Directory, FileHandle, OwnedBytes, RamDirectory, TerminatingWrite, WatchCallback,
+        WatchHandle,
+    };
+    use crate::schema::{Schema, STORED, TEXT};
+    use crate::{doc, DocAddress, Index, IndexSettings, Term};
+    use common::HasLen;
+    use futures::executor::block_on;
+    use std::io::{self, Write};
+    use std::ops::Range;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// A directory counting the reads of its files.
+    #[derive(Clone, Debug, Default)]
+    struct CountingDirectory {
+        directory: RamDirectory,
+        num_reads: Arc<AtomicUsize>,
+    }
+
+    #[derive(Debug)]
+    struct CountingFileHandle {
+        inner: Box<dyn FileHandle>,
+        num_reads: Arc<AtomicUsize>,
+    }
+
+    impl HasLen for CountingFileHandle {
+        fn len(&self) -> usize {
+            self.inner.len()
+        }
+    }
+
+    impl FileHandle for CountingFileHandle {
+        fn read_bytes(&self, range: Range<usize>) -> io::Result<OwnedBytes> {
+            self.num_reads.fetch_add(1, Ordering::SeqCst);
+            self.inner.read_bytes(range)
+        }
+    }
+
+    impl Directory for CountingDirectory {
+        fn get_file_handle(&self, path: &Path) -> Result<Box<dyn FileHandle>, OpenReadError> {
+            Ok(Box::new(CountingFileHandle {
+                inner: self.directory.get_file_handle(path)?,
+                num_reads: self.num_reads.clone(),
+            }))
+        }
+        fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+            self.directory.delete(path)
+        }
+        fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+            self.directory.exists(path)
+        }
+        fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+            self.directory.open_write(path)
+        }
+        fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+            self.directory.atomic_read(path)
+        }
+        fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+            self.directory.atomic_write(path, data)
+        }
+        fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+            self.directory.watch(watch_callback)
+        }
+    }
+
+    fn write_file(directory: &dyn Directory, path: &Path, data: &[u8]) -> io::Result<()> {
+        let mut wrt = directory.open_write(path).unwrap();
+        wrt.write_all(data)?;
+        wrt.terminate()
+    }
+
+    fn small_cache(max_num_bytes: usize) -> CacheConfig {
+        CacheConfig {
+            block_size: 4,
+            max_num_bytes,
+            ..CacheConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_caching_directory_reads_blocks() -> io::Result<()> {
+        let inner = CountingDirectory::default();
+        let directory = CachingDirectory::new(Arc::new(inner.clone()), small_cache(1_000))?;
+        let path = Path::new("file");
+        let data: Vec<u8> = (0..30u8).collect();
+        write_file(&directory, path, &data)?;
+        let file_slice = directory.open_read(path).unwrap();
+        assert_eq!(file_slice.len(), 30);
+        assert_eq!(file_slice.read_bytes_slice(5..7)?.as_slice(), &data[5..7]);
+        assert_eq!(inner.num_reads.load(Ordering::SeqCst), 1);
+        let stats = directory.stats();
+        assert_eq!((stats.hits, stats.misses), (0, 1));
+        // The block 1 is cached, the blocks 2 and 3 are fetched with a single read.
+        assert_eq!(file_slice.read_bytes_slice(6..15)?.as_slice(), &data[6..15]);
+        assert_eq!(inner.num_reads.load(Ordering::SeqCst), 2);
+        assert_eq!(file_slice.read_bytes_slice(4..30)?.as_slice(), &data[4..30]);
+        assert_eq!(inner.num_reads.load(Ordering::SeqCst), 3);
+        let stats = directory.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evicted), (4, 7, 0));
+        assert_eq!((stats.num_blocks, stats.num_bytes), (7, 26));
+        assert!(file_slice.read_bytes_slice(10..10)?.is_empty());
+        // A new file handle reads the cached blocks.
+        let file_slice = directory.open_read(path).unwrap();
+        assert_eq!(file_slice.read_bytes()?.as_slice(), &data[..]);
+        assert_eq!(inner.num_reads.load(Ordering::SeqCst), 4);
+        assert_eq!(directory.stats().misses, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_caching_directory_read_async() -> io::Result<()> {
+        let inner = CountingDirectory::default();
+        let directory = CachingDirectory::new(Arc::new(inner.clone()), small_cache(1_000))?;
+        let path = Path::new("file");
+        let data: Vec<u8> = (0..30u8).collect();
+        write_file(&directory, path, &data)?;
+        let file_slice = directory.open_read(path).unwrap();
+        let bytes = block_on(file_slice.read_bytes_slice_async(5..15))?;
+        assert_eq!(bytes.as_slice(), &data[5..15]);
+        assert_eq!(file_slice.read_bytes_slice(6..10)?.as_slice(), &data[6..10]);
+        assert_eq!(inner.num_reads.load(Ordering::SeqCst), 1);
+        let stats = directory.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_caching_directory_invalidation() -> io::Result<()> {
+        let inner = CountingDirectory::default();
+        let directory = CachingDirectory::new(Arc::new(inner), small_cache(1_000))?;
+        let path = Path::new("file");
+        write_file(&directory, path, b"abcdefgh")?;
+        let file_slice = directory.open_read(path).unwrap();
+        assert_eq!(file_slice.read_bytes()?.as_slice(), b"abcdefgh");
+        directory.delete(path).unwrap();
+        assert_eq!(directory.stats().num_blocks, 0);
+        write_file(&directory, path, b"ijklmn")?;
+        assert_eq!(
+            directory.open_read(path).unwrap().read_bytes()?.as_slice(),
+            b"ijklmn"
+        );
+        // The file handle opened before the deletion keeps reading the previous content.
+        assert_eq!(file_slice.read_bytes()?.as_slice(), b"abcdefgh");
+        directory.atomic_write(path, b"opqr")?;
+        assert_eq!(directory.atomic_read(path).unwrap(), b"opqr");
+        assert_eq!(
+            directory.open_read(path).unwrap().read_bytes()?.as_slice(),
+            b"opqr"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_caching_directory_atomic_read_bypasses_cache() -> io::Result<()> {
+        let inner = CountingDirectory::default();
+        let directory = CachingDirectory::new(Arc::new(inner.clone()), small_cache(1_000))?;
+        let path = Path::new("meta.json");
+        directory.atomic_write(path, b"first")?;
+        assert_eq!(directory.atomic_read(path).unwrap(), b"first");
+        // Written behind the back of the cache.
+        inner.atomic_write(path, b"second")?;
+        assert_eq!(directory.atomic_read(path).unwrap(), b"second");
+        assert_eq!(directory.stats().num_blocks, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_caching_directory_eviction() -> io::Result<()> {
+        let inner = CountingDirectory::default();
+        let directory = CachingDirectory::new(Arc::new(inner.clone()), small_cache(8))?;
+        let path = Path::new("file");
+        let data: Vec<u8> = (0..16u8).collect();
+        write_file(&directory, path, &data)?;
+        let file_slice = directory.open_read(path).unwrap();
+        assert_eq!(file_slice.read_bytes()?.as_slice(), &data[..]);
+        let stats = directory.stats();
+        assert_eq!((stats.misses, stats.evicted), (4, 2));
+        assert_eq!((stats.num_blocks, stats.num_bytes), (2, 8));
+        // The blocks 2 and 3 are still cached.
+        assert_eq!(file_slice.read_bytes_slice(8..16)?.as_slice(), &data[8..16]);
+        assert_eq!(directory.stats().hits, 2);
+        assert_eq!(file_slice.read_bytes_slice(0..4)?.as_slice(), &data[0..4]);
+        assert_eq!(directory.stats().misses, 5);
+        Ok(())
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_caching_directory_spill() -> io::Result<()> {
+        let spill_directory = tempfile::TempDir::new()?;
+        let inner = CountingDirectory::default();
+        let cache_config = CacheConfig {
+            spill_directory: Some(spill_directory.path().to_path_buf()),
+            ..small_cache(8)
+        };
+        let directory = CachingDirectory::new(Arc::new(inner.clone()), cache_config)?;
+        let path = Path::new("file");
+        let data: Vec<u8> = (0..16u8).collect();
+        write_file(&directory, path, &data)?;
+        let file_slice = directory.open_read(path).unwrap();
+        assert_eq!(file_slice.read_bytes()?.as_slice(), &data[..]);
+        assert_eq!(inner.num_reads.load(Ordering::SeqCst), 1);
+        // The evicted blocks are read from the spill directory.
+        assert_eq!(file_slice.read_bytes_slice(0..8)?.as_slice(), &data[0..8]);
+        assert_eq!(inner.num_reads.load(Ordering::SeqCst), 1);
+        assert_eq!(directory.stats().spill_hits, 2);
+        let num_spill_directories = || spill_directory.path().read_dir().unwrap().count();
+        assert_eq!(num_spill_directories(), 1);
+        directory.delete(path).unwrap();
+        write_file(&directory, path, b"new content")?;
+        assert_eq!(
+            directory.open_read(path).unwrap().read_bytes()?.as_slice(),
+            b"new content"
+        );
+        drop(file_slice);
+        drop(directory);
+        assert_eq!(num_spill_directories(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_caching_directory_index() -> crate::Result<()> {
+        let inner = CountingDirectory::default();
+        let cache_config = CacheConfig {
+            block_size: 256,
+            ..CacheConfig::default()
+        };
+        let directory = CachingDirectory::new(Arc::new(inner), cache_config)?;
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..100 {
+            index_writer.add_document(doc!(text_field => format!("hello {}", i % 7)));
+        }
+        index_writer.commit()?;
+        let num_hits = || -> crate::Result<u64> {
+            let searcher = index.reader()?.searcher();
+            let term = Term::from_field_text(text_field, "3");
+            searcher.doc_freq(&term)
+        };
+        assert_eq!(num_hits()?, 14);
+        let misses = directory.stats().misses;
+        assert!(misses > 0);
+        assert_eq!(num_hits()?, 14);
+        assert_eq!(directory.stats().misses, misses);
+        assert!(directory.stats().hits > 0);
+        let searcher = index.reader()?.searcher();
+        let doc_address = DocAddress::new(0, 10);
+        assert_eq!(
+            block_on(searcher.doc_async(doc_address))?.field_values(),
+            searcher.doc(doc_address)?.field_values()
+        );
+        Ok(())
+    }
+}
@@ -171,6 +171,14 @@ impl CompositeFile {
             .map(|byte_range| self.data.slice(byte_range.clone()))
     }
 
+    /// Returns the `FileSlice`s associated to a given `Field`, for all of its idx.
+    pub(crate) fn open_read_all(&self, field: Field) -> impl Iterator<Item = FileSlice> + '_ {
+        self.offsets_index
+            .iter()
+            .filter(move |(file_addr, _)| file_addr.field == field)
+            .map(move |(_, byte_range)| self.data.slice(byte_range.clone()))
+    }
+
     pub fn space_usage(&self) -> PerFieldSpaceUsage {
         let mut fields = HashMap::new();
         for (&field_addr, byte_range) in &self.offsets_index {
@@ -1,3 +1,4 @@
+use crate::core::META_FILEPATH;
 use crate::directory::directory_lock::Lock;
 use crate::directory::error::LockError;
 use crate::directory::error::{DeleteError, OpenReadError, OpenWriteError};
@@ -99,6 +100,38 @@ fn retry_policy(is_blocking: bool) -> RetryPolicy {
     }
 }
 
+/// Defines which writes of a commit are synced to the disk, with `fsync`.
+///
+/// Whatever the durability, a crash of the process loses nothing which was committed:
+/// the written files are in the page cache of the operating system. The durability only
+/// matters after a crash of the operating system, or a power loss.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Durability {
+    /// The files of the segments are synced when they are terminated, then the `meta.json`
+    /// is synced before atomically replacing the previous one, and the directory is synced.
+    ///
+    /// After a crash, the index opens on its last commit. This is the default.
+    #[default]
+    Full,
+    /// Only the `meta.json` and the directory are synced.
+    ///
+    /// After a crash, the index opens on its last commit, but the files of its segments
+    /// may not have been entirely written to the disk yet. This only saves the `fsync` of
+    /// the segment files on the filesystems writing the data of the files before the
+    /// renames of the files: the other ones may end up with a `meta.json` referencing
+    /// incomplete segments, as detected by `Index::validate_checksum`.
+    MetaOnly,
+    /// Nothing is synced.
+    ///
+    /// After a crash, the commits which were not written to the disk yet are lost, and
+    /// the index opens on an earlier commit: as the `meta.json` is replaced atomically,
+    /// the commits written to the disk are never corrupted. To that end, the garbage
+    /// collection does not delete any file until a commit is synced, so that the files of
+    /// the merged segments are kept until then. This is meant for bulk loads into an index
+    /// which can be rebuilt.
+    None,
+}
+
 /// Write-once read many (WORM) abstraction for where
 /// tantivy's data should be stored.
 ///
@@ -160,6 +193,18 @@ pub trait Directory: DirectoryClone + fmt::Debug + Send + Sync + 'static {
     /// The file may not previously exist.
     fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError>;
 
+    /// Opens a writer like `open_write`, which only syncs the file when it is terminated
+    /// if the `durability` is `Durability::Full`.
+    ///
+    /// By default, the `durability` is ignored, and the writer of `open_write` is returned.
+    fn open_write_with_durability(
+        &self,
+        path: &Path,
+        _durability: Durability,
+    ) -> Result<WritePtr, OpenWriteError> {
+        self.open_write(path)
+    }
+
     /// Reads the full content file that has been written using
     /// atomic_write.
     ///
@@ -176,6 +221,19 @@ pub trait Directory: DirectoryClone + fmt::Debug + Send + Sync + 'static {
     /// The file may or may not previously exist.
     fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()>;
 
+    /// Atomically replaces the content of a file like `atomic_write`, syncing the file and
+    /// the directory unless the `durability` is `Durability::None`.
+    ///
+    /// By default, the `durability` is ignored, and `atomic_write` is called.
+    fn atomic_write_with_durability(
+        &self,
+        path: &Path,
+        data: &[u8],
+        _durability: Durability,
+    ) -> io::Result<()> {
+        self.atomic_write(path, data)
+    }
+
     /// Acquire a lock in the given directory.
     ///
     /// The method is blocking or not depending on the `Lock` object.
@@ -214,6 +272,39 @@ pub trait Directory: DirectoryClone + fmt::Debug + Send + Sync + 'static {
     /// `OnCommit` `ReloadPolicy`. Not implementing watch in a `Directory` only prevents the
     /// `OnCommit` `ReloadPolicy` to work properly.
     fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle>;
+
+    /// Registers a callback that will be called whenever a change on the file at `path` is
+    /// detected, including its creation.
+    ///
+    /// As for `.watch()`, the file is watched for the lifetime of the returned
+    /// `WatchHandle`. For a file written with [Directory::open_write], the callback may be
+    /// called before the file is complete.
+    ///
+    /// By default, only the `meta.json` file can be watched, with `.watch()`, and an
+    /// `InvalidArgument` error is returned for the other files.
+    fn watch_path(&self, path: &Path, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+        if path == *META_FILEPATH {
+            return self.watch(watch_callback);
+        }
+        Err(crate::TantivyError::InvalidArgument(format!(
+            "Watching {:?} is not supported by this directory.",
+            path
+        )))
+    }
+
+    /// Returns the path of the file on the local filesystem, if the directory stores
+    /// its files there.
+    ///
+    /// It makes it possible to hard link the files, e.g. when creating a snapshot of the
+    /// index. By default, `None` is returned.
+    fn local_file_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// Hints that the file is about to be read by a merge, once and sequentially.
+    ///
+    /// By default, this is a no-op.
+    fn advise_merge_read(&self, _path: &Path) {}
 }
 
 /// DirectoryClone
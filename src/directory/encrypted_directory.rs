@@ -0,0 +1,663 @@
+use crate::directory::error::{DeleteError, LockError, OpenReadError, OpenWriteError};
+use crate::directory::{
+    AntiCallToken, Directory, DirectoryLock, Durability, FileHandle, Lock, OwnedBytes,
+    TerminatingWrite, WatchCallback, WatchHandle, WritePtr,
+};
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{Key, Tag, XChaCha20Poly1305, XNonce};
+use common::HasLen;
+use futures::future::BoxFuture;
+use std::fmt;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Identifies an encrypted file, at the very beginning of its header.
+const MAGIC_NUMBER: [u8; 4] = *b"TENC";
+const NONCE_PREFIX_LEN: usize = 16;
+const TAG_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC_NUMBER.len() + NONCE_PREFIX_LEN + TAG_LEN;
+
+/// Number of bytes of plaintext of the chunks, encrypted and authenticated separately.
+///
+/// The chunks are small, as fast fields and term dictionaries are read by small
+/// scattered ranges.
+const CHUNK_SIZE: usize = 1_024;
+const ENCRYPTED_CHUNK_SIZE: usize = CHUNK_SIZE + TAG_LEN;
+
+/// The chunk id of the empty message authenticated in the header, which no chunk of
+/// data ever has.
+const KEY_CHECK_CHUNK_ID: u64 = u64::MAX;
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Encrypts and authenticates the chunks of a file with XChaCha20-Poly1305.
+///
+/// The nonce of a chunk is the random nonce prefix of the file followed by the id of
+/// the chunk, so that a nonce is never reused with the key of the directory. The
+/// associated data binds each chunk to the path of the file, to its position, and to
+/// whether it is the last chunk, so that chunks cannot be moved between or within
+/// files, and the truncation of a file is detected.
+#[derive(Clone)]
+struct FileCipher {
+    cipher: XChaCha20Poly1305,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    path: Vec<u8>,
+}
+
+impl FileCipher {
+    fn new(key: &[u8; 32], nonce_prefix: [u8; NONCE_PREFIX_LEN], path: &Path) -> FileCipher {
+        FileCipher {
+            cipher: XChaCha20Poly1305::new(Key::from_slice(key)),
+            nonce_prefix,
+            path: path.to_string_lossy().as_bytes().to_vec(),
+        }
+    }
+
+    fn nonce(&self, chunk_id: u64) -> XNonce {
+        let mut nonce = XNonce::default();
+        nonce[..NONCE_PREFIX_LEN].copy_from_slice(&self.nonce_prefix);
+        nonce[NONCE_PREFIX_LEN..].copy_from_slice(&chunk_id.to_le_bytes());
+        nonce
+    }
+
+    fn associated_data(&self, chunk_id: u64, is_last: bool) -> Vec<u8> {
+        let mut associated_data = Vec::with_capacity(self.path.len() + 17);
+        associated_data.extend_from_slice(&(self.path.len() as u64).to_le_bytes());
+        associated_data.extend_from_slice(&self.path);
+        associated_data.extend_from_slice(&chunk_id.to_le_bytes());
+        associated_data.push(is_last as u8);
+        associated_data
+    }
+
+    /// Encrypts the chunk `chunk_id` in place, and returns its tag.
+    fn seal(&self, chunk_id: u64, is_last: bool, chunk: &mut [u8]) -> Tag {
+        self.cipher
+            .encrypt_in_place_detached(
+                &self.nonce(chunk_id),
+                &self.associated_data(chunk_id, is_last),
+                chunk,
+            )
+            .expect("A chunk is not too long to be encrypted.")
+    }
+
+    /// Authenticates and decrypts the chunk `chunk_id` in place.
+    fn open(&self, chunk_id: u64, is_last: bool, chunk: &mut [u8], tag: &[u8]) -> bool {
+        tag.len() == TAG_LEN
+            && self
+                .cipher
+                .decrypt_in_place_detached(
+                    &self.nonce(chunk_id),
+                    &self.associated_data(chunk_id, is_last),
+                    chunk,
+                    Tag::from_slice(tag),
+                )
+                .is_ok()
+    }
+
+    /// Returns the tag of an empty message, telling whether a file is opened with the
+    /// key and path it was written with.
+    fn key_check(&self) -> Tag {
+        self.seal(KEY_CHECK_CHUNK_ID, true, &mut [])
+    }
+}
+
+/// Returns the header of a new file, and its cipher.
+fn new_header(key: &[u8; 32], path: &Path) -> (Vec<u8>, FileCipher) {
+    // The nonce prefix is drawn from the OS random number generator: the nonces of
+    // the files are never reused.
+    let nonce_prefix = *Uuid::new_v4().as_bytes();
+    let cipher = FileCipher::new(key, nonce_prefix, path);
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(&MAGIC_NUMBER);
+    header.extend_from_slice(&nonce_prefix);
+    header.extend_from_slice(&cipher.key_check());
+    (header, cipher)
+}
+
+/// Checks the header of a file, and returns its cipher.
+fn open_header(key: &[u8; 32], header: &[u8], path: &Path) -> io::Result<FileCipher> {
+    if header.len() < HEADER_LEN || header[..MAGIC_NUMBER.len()] != MAGIC_NUMBER {
+        return Err(invalid_data(format!(
+            "The file {:?} is not encrypted by an EncryptedDirectory.",
+            path
+        )));
+    }
+    let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+    nonce_prefix.copy_from_slice(&header[MAGIC_NUMBER.len()..HEADER_LEN - TAG_LEN]);
+    let cipher = FileCipher::new(key, nonce_prefix, path);
+    if !cipher.open(
+        KEY_CHECK_CHUNK_ID,
+        true,
+        &mut [],
+        &header[HEADER_LEN - TAG_LEN..HEADER_LEN],
+    ) {
+        return Err(invalid_data(format!(
+            "The file {:?} was encrypted with another key, or under another path.",
+            path
+        )));
+    }
+    Ok(cipher)
+}
+
+/// Returns the number of bytes of plaintext of an encrypted file of `num_bytes` bytes.
+fn plaintext_len(num_bytes: usize, path: &Path) -> io::Result<usize> {
+    let num_chunk_bytes = num_bytes.saturating_sub(HEADER_LEN);
+    let num_full_chunks = num_chunk_bytes / ENCRYPTED_CHUNK_SIZE;
+    let last_chunk_len = num_chunk_bytes % ENCRYPTED_CHUNK_SIZE;
+    if last_chunk_len == 0 && num_full_chunks > 0 {
+        return Ok(num_full_chunks * CHUNK_SIZE);
+    }
+    if last_chunk_len < TAG_LEN {
+        return Err(invalid_data(format!(
+            "The encrypted file {:?} is truncated.",
+            path
+        )));
+    }
+    Ok(num_full_chunks * CHUNK_SIZE + last_chunk_len - TAG_LEN)
+}
+
+fn encrypt(key: &[u8; 32], path: &Path, data: &[u8]) -> Vec<u8> {
+    let (mut encrypted, cipher) = new_header(key, path);
+    let num_chunks = (data.len() + CHUNK_SIZE - 1) / CHUNK_SIZE;
+    encrypted.reserve(data.len() + num_chunks.max(1) * TAG_LEN);
+    let mut chunks: Vec<&[u8]> = data.chunks(CHUNK_SIZE).collect();
+    if chunks.is_empty() {
+        chunks.push(&[]);
+    }
+    let last_chunk_id = chunks.len() - 1;
+    for (chunk_id, chunk) in chunks.into_iter().enumerate() {
+        let start = encrypted.len();
+        encrypted.extend_from_slice(chunk);
+        let tag = cipher.seal(
+            chunk_id as u64,
+            chunk_id == last_chunk_id,
+            &mut encrypted[start..],
+        );
+        encrypted.extend_from_slice(&tag);
+    }
+    encrypted
+}
+
+/// Writer encrypting the data written in the file, chunk by chunk.
+struct EncryptedWriter {
+    writer: WritePtr,
+    cipher: FileCipher,
+    // A full chunk is only sealed once the next byte is written, as the last
+    // chunk is authenticated as such.
+    chunk: Vec<u8>,
+    chunk_id: u64,
+}
+
+impl EncryptedWriter {
+    fn seal_chunk(&mut self, is_last: bool) -> io::Result<()> {
+        let tag = self.cipher.seal(self.chunk_id, is_last, &mut self.chunk);
+        self.writer.write_all(&self.chunk)?;
+        self.writer.write_all(&tag)?;
+        self.chunk.clear();
+        self.chunk_id += 1;
+        Ok(())
+    }
+}
+
+impl Write for EncryptedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.chunk.len() == CHUNK_SIZE {
+            self.seal_chunk(false)?;
+        }
+        let num_bytes = buf.len().min(CHUNK_SIZE - self.chunk.len());
+        self.chunk.extend_from_slice(&buf[..num_bytes]);
+        Ok(num_bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl TerminatingWrite for EncryptedWriter {
+    fn terminate_ref(&mut self, token: AntiCallToken) -> io::Result<()> {
+        self.seal_chunk(true)?;
+        self.writer.terminate_ref(token)
+    }
+}
+
+struct EncryptedFileHandle {
+    path: PathBuf,
+    inner: Box<dyn FileHandle>,
+    cipher: FileCipher,
+    num_bytes: usize,
+}
+
+impl fmt::Debug for EncryptedFileHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EncryptedFileHandle({:?}, {:?})", self.path, self.inner)
+    }
+}
+
+impl HasLen for EncryptedFileHandle {
+    fn len(&self) -> usize {
+        self.num_bytes
+    }
+}
+
+impl EncryptedFileHandle {
+    /// Returns the range of the encrypted chunks spanning the plaintext `range`.
+    fn encrypted_range(&self, range: &Range<usize>) -> Range<usize> {
+        let first_chunk_id = range.start / CHUNK_SIZE;
+        let end_chunk_id = (range.end + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        HEADER_LEN + first_chunk_id * ENCRYPTED_CHUNK_SIZE
+            ..self
+                .inner
+                .len()
+                .min(HEADER_LEN + end_chunk_id * ENCRYPTED_CHUNK_SIZE)
+    }
+
+    /// Authenticates and decrypts the `encrypted` chunks spanning `range`.
+    fn decrypt(&self, range: Range<usize>, encrypted: OwnedBytes) -> io::Result<OwnedBytes> {
+        let first_chunk_id = range.start / CHUNK_SIZE;
+        let last_chunk_id = self.num_bytes.saturating_sub(1) / CHUNK_SIZE;
+        let mut data = Vec::with_capacity(encrypted.len());
+        for (i, encrypted_chunk) in encrypted.chunks(ENCRYPTED_CHUNK_SIZE).enumerate() {
+            let chunk_id = first_chunk_id + i;
+            let (ciphertext, tag) = encrypted_chunk.split_at(encrypted_chunk.len() - TAG_LEN);
+            let start = data.len();
+            data.extend_from_slice(ciphertext);
+            if !self.cipher.open(
+                chunk_id as u64,
+                chunk_id == last_chunk_id,
+                &mut data[start..],
+                tag,
+            ) {
+                return Err(invalid_data(format!(
+                    "The chunk {} of the encrypted file {:?} is corrupted.",
+                    chunk_id, self.path
+                )));
+            }
+        }
+        let offset = first_chunk_id * CHUNK_SIZE;
+        Ok(OwnedBytes::new(data).slice(range.start - offset..range.end - offset))
+    }
+}
+
+impl FileHandle for EncryptedFileHandle {
+    fn read_bytes(&self, range: Range<usize>) -> io::Result<OwnedBytes> {
+        if range.start >= range.end {
+            return Ok(OwnedBytes::empty());
+        }
+        let encrypted = self.inner.read_bytes(self.encrypted_range(&range))?;
+        self.decrypt(range, encrypted)
+    }
+
+    fn read_bytes_async(&self, range: Range<usize>) -> BoxFuture<'_, io::Result<OwnedBytes>> {
+        Box::pin(async move {
+            if range.start >= range.end {
+                return Ok(OwnedBytes::empty());
+            }
+            let encrypted = self
+                .inner
+                .read_bytes_async(self.encrypted_range(&range))
+                .await?;
+            self.decrypt(range, encrypted)
+        })
+    }
+}
+
+/// A directory encrypting the files of an inner directory, e.g. a `MmapDirectory`.
+///
+/// The files written with `open_write` and `atomic_write`, including `meta.json`, are
+/// split in chunks of 1KB encrypted and authenticated with XChaCha20-Poly1305, under the
+/// key of the directory. The nonces of each file start with a random prefix stored in
+/// the header of the file, and each chunk is bound to the path of its file, its
+/// position and whether it is the last chunk of the file.
+///
+/// A range of a file is read by decrypting the chunks spanning it only. Reading a
+/// file with another key, a corrupted, truncated or renamed file, fails with an
+/// `io::ErrorKind::InvalidData` error. The key of an index cannot be rotated.
+///
+/// The decrypted bytes are not cached: the directory can be wrapped in a
+/// [`CachingDirectory`](struct.CachingDirectory.html) to keep them in memory.
+#[derive(Clone)]
+pub struct EncryptedDirectory {
+    inner: Arc<dyn Directory>,
+    key: Arc<[u8; 32]>,
+}
+
+impl fmt::Debug for EncryptedDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EncryptedDirectory({:?})", self.inner)
+    }
+}
+
+impl EncryptedDirectory {
+    /// Wraps `inner`, encrypting its files with `key`.
+    pub fn new(inner: Arc<dyn Directory>, key: [u8; 32]) -> EncryptedDirectory {
+        EncryptedDirectory {
+            inner,
+            key: Arc::new(key),
+        }
+    }
+}
+
+impl Directory for EncryptedDirectory {
+    fn get_file_handle(&self, path: &Path) -> Result<Box<dyn FileHandle>, OpenReadError> {
+        let inner = self.inner.get_file_handle(path)?;
+        let open = || -> io::Result<EncryptedFileHandle> {
+            let header = inner.read_bytes(0..HEADER_LEN.min(inner.len()))?;
+            let cipher = open_header(&self.key, header.as_slice(), path)?;
+            let num_bytes = plaintext_len(inner.len(), path)?;
+            if num_bytes == 0 {
+                // No range of the file will ever be read, its empty last chunk is
+                // authenticated here.
+                let tag = inner.read_bytes(HEADER_LEN..inner.len())?;
+                if !cipher.open(0, true, &mut [], tag.as_slice()) {
+                    return Err(invalid_data(format!(
+                        "The chunk 0 of the encrypted file {:?} is corrupted.",
+                        path
+                    )));
+                }
+            }
+            Ok(EncryptedFileHandle {
+                path: path.to_path_buf(),
+                inner,
+                cipher,
+                num_bytes,
+            })
+        };
+        let file_handle = open()
+            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
+        Ok(Box::new(file_handle))
+    }
+
+    fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+        self.inner.delete(path)
+    }
+
+    fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+        self.inner.exists(path)
+    }
+
+    fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+        self.open_write_with_durability(path, Durability::Full)
+    }
+
+    fn open_write_with_durability(
+        &self,
+        path: &Path,
+        durability: Durability,
+    ) -> Result<WritePtr, OpenWriteError> {
+        let mut writer = self.inner.open_write_with_durability(path, durability)?;
+        let (header, cipher) = new_header(&self.key, path);
+        writer
+            .write_all(&header)
+            .map_err(|io_error| OpenWriteError::wrap_io_error(io_error, path.to_path_buf()))?;
+        Ok(BufWriter::new(Box::new(EncryptedWriter {
+            writer,
+            cipher,
+            chunk: Vec::with_capacity(CHUNK_SIZE),
+            chunk_id: 0,
+        })))
+    }
+
+    fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+        let file_handle = self.get_file_handle(path)?;
+        let data = file_handle
+            .read_bytes(0..file_handle.len())
+            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
+        Ok(data.as_slice().to_owned())
+    }
+
+    fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.inner
+            .atomic_write(path, &encrypt(&self.key, path, data))
+    }
+
+    fn atomic_write_with_durability(
+        &self,
+        path: &Path,
+        data: &[u8],
+        durability: Durability,
+    ) -> io::Result<()> {
+        self.inner
+            .atomic_write_with_durability(path, &encrypt(&self.key, path, data), durability)
+    }
+
+    fn acquire_lock(&self, lock: &Lock) -> Result<DirectoryLock, LockError> {
+        self.inner.acquire_lock(lock)
+    }
+
+    fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+        self.inner.watch(watch_callback)
+    }
+
+    fn watch_path(&self, path: &Path, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+        self.inner.watch_path(path, watch_callback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EncryptedDirectory, CHUNK_SIZE, ENCRYPTED_CHUNK_SIZE, HEADER_LEN};
+    use crate::collector::TopDocs;
+    use crate::directory::error::OpenReadError;
+    use crate::directory::{Directory, RamDirectory, TerminatingWrite};
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, FAST, STORED, TEXT};
+    use crate::{doc, Index, IndexSettings, Term};
+    use common::HasLen;
+    use std::io::{self, Write};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    const KEY: [u8; 32] = [7u8; 32];
+
+    fn write_file(directory: &dyn Directory, path: &Path, data: &[u8]) -> io::Result<()> {
+        let mut wrt = directory.open_write(path).unwrap();
+        wrt.write_all(data)?;
+        wrt.terminate()
+    }
+
+    fn io_error_kind(open_read_error: OpenReadError) -> io::ErrorKind {
+        match open_read_error {
+            OpenReadError::IoError { io_error, .. } => io_error.kind(),
+            _ => panic!("Expected an io error, got {:?}", open_read_error),
+        }
+    }
+
+    #[test]
+    fn test_encrypted_directory_read_ranges() -> io::Result<()> {
+        let inner = RamDirectory::create();
+        let directory = EncryptedDirectory::new(Arc::new(inner.clone()), KEY);
+        let path = Path::new("file");
+        for &num_bytes in &[0, 1, CHUNK_SIZE - 1, CHUNK_SIZE, 3 * CHUNK_SIZE + 17] {
+            let data: Vec<u8> = (0..num_bytes).map(|i| (i % 251) as u8).collect();
+            write_file(&directory, path, &data)?;
+            let num_chunks = ((num_bytes + CHUNK_SIZE - 1) / CHUNK_SIZE).max(1);
+            assert_eq!(
+                inner.open_read(path).unwrap().len(),
+                HEADER_LEN + num_bytes + num_chunks * (ENCRYPTED_CHUNK_SIZE - CHUNK_SIZE)
+            );
+            let file_slice = directory.open_read(path).unwrap();
+            assert_eq!(file_slice.len(), num_bytes);
+            assert_eq!(file_slice.read_bytes()?.as_slice(), &data[..]);
+            for &(start, end) in &[(0, 1), (1, 100), (CHUNK_SIZE - 2, CHUNK_SIZE + 2), (5, 5)] {
+                if end <= num_bytes {
+                    assert_eq!(
+                        file_slice.read_bytes_slice(start..end)?.as_slice(),
+                        &data[start..end]
+                    );
+                }
+            }
+            directory.delete(path).unwrap();
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_directory_ciphertext() -> io::Result<()> {
+        let inner = RamDirectory::create();
+        let directory = EncryptedDirectory::new(Arc::new(inner.clone()), KEY);
+        let data = vec![0u8; 1_000];
+        write_file(&directory, Path::new("first"), &data)?;
+        write_file(&directory, Path::new("second"), &data)?;
+        let first = inner.open_read(Path::new("first")).unwrap().read_bytes()?;
+        let second = inner.open_read(Path::new("second")).unwrap().read_bytes()?;
+        // The same data is encrypted with different keys in each file.
+        assert!(first.as_slice()[HEADER_LEN..].iter().any(|&byte| byte != 0));
+        assert_ne!(
+            &first.as_slice()[HEADER_LEN..],
+            &second.as_slice()[HEADER_LEN..]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_directory_atomic_write() -> io::Result<()> {
+        let inner = RamDirectory::create();
+        let directory = EncryptedDirectory::new(Arc::new(inner.clone()), KEY);
+        let path = Path::new("meta.json");
+        let data = vec![3u8; 2 * CHUNK_SIZE + 1];
+        directory.atomic_write(path, &data)?;
+        assert_eq!(directory.atomic_read(path).unwrap(), data);
+        assert_ne!(inner.atomic_read(path).unwrap()[HEADER_LEN..], data[..]);
+        directory.atomic_write(path, b"")?;
+        assert!(directory.atomic_read(path).unwrap().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_directory_wrong_key() -> io::Result<()> {
+        let inner = RamDirectory::create();
+        let directory = EncryptedDirectory::new(Arc::new(inner.clone()), KEY);
+        let path = Path::new("file");
+        write_file(&directory, path, b"hello")?;
+        let other_directory = EncryptedDirectory::new(Arc::new(inner.clone()), [8u8; 32]);
+        let open_read_error = other_directory.open_read(path).unwrap_err();
+        assert!(format!("{}", open_read_error).contains("was encrypted with another key"));
+        assert_eq!(io_error_kind(open_read_error), io::ErrorKind::InvalidData);
+        // A plain file is not read as garbage either.
+        inner.atomic_write(Path::new("plain"), b"a plain file with no header at all")?;
+        let open_read_error = directory.atomic_read(Path::new("plain")).unwrap_err();
+        assert!(format!("{}", open_read_error).contains("is not encrypted"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_directory_corruption() -> io::Result<()> {
+        let inner = RamDirectory::create();
+        let directory = EncryptedDirectory::new(Arc::new(inner.clone()), KEY);
+        let path = Path::new("file");
+        let data = vec![5u8; 2 * CHUNK_SIZE + 10];
+        write_file(&directory, path, &data)?;
+        let mut encrypted = inner.atomic_read(path).unwrap();
+        encrypted[HEADER_LEN + ENCRYPTED_CHUNK_SIZE + 3] ^= 1;
+        inner.atomic_write(path, &encrypted)?;
+        let file_slice = directory.open_read(path).unwrap();
+        // The first chunk is intact, the second one is not.
+        assert_eq!(file_slice.read_bytes_slice(0..10)?.as_slice(), &data[0..10]);
+        let io_error = file_slice
+            .read_bytes_slice(CHUNK_SIZE..CHUNK_SIZE + 1)
+            .unwrap_err();
+        assert_eq!(io_error.kind(), io::ErrorKind::InvalidData);
+        assert!(format!("{}", io_error).contains("The chunk 1 of the encrypted file"));
+        // A file truncated at the end of a chunk is detected.
+        inner.atomic_write(path, &encrypted[..HEADER_LEN + ENCRYPTED_CHUNK_SIZE])?;
+        let file_slice = directory.open_read(path).unwrap();
+        assert!(file_slice.read_bytes().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_directory_moved_chunks() -> io::Result<()> {
+        let inner = RamDirectory::create();
+        let directory = EncryptedDirectory::new(Arc::new(inner.clone()), KEY);
+        let data: Vec<u8> = (0..3 * CHUNK_SIZE)
+            .map(|i| (i / CHUNK_SIZE) as u8)
+            .collect();
+        write_file(&directory, Path::new("first"), &data)?;
+        write_file(&directory, Path::new("second"), &data)?;
+        let first = inner.atomic_read(Path::new("first")).unwrap();
+        let second = inner.atomic_read(Path::new("second")).unwrap();
+
+        // A file is bound to its path.
+        inner.atomic_write(Path::new("renamed"), &first)?;
+        let open_read_error = directory.open_read(Path::new("renamed")).unwrap_err();
+        assert!(format!("{}", open_read_error).contains("under another path"));
+        assert_eq!(io_error_kind(open_read_error), io::ErrorKind::InvalidData);
+
+        // A chunk is bound to its file, and to its position in the file.
+        let chunk = |encrypted: &[u8], chunk_id: usize| {
+            let start = HEADER_LEN + chunk_id * ENCRYPTED_CHUNK_SIZE;
+            encrypted[start..start + ENCRYPTED_CHUNK_SIZE].to_vec()
+        };
+        let mut swapped = first[..HEADER_LEN].to_vec();
+        swapped.extend(chunk(&first, 1));
+        swapped.extend(chunk(&first, 0));
+        swapped.extend(chunk(&first, 2));
+        let mut spliced = first[..HEADER_LEN].to_vec();
+        spliced.extend(chunk(&second, 0));
+        spliced.extend(chunk(&first, 1));
+        spliced.extend(chunk(&first, 2));
+        for encrypted in vec![swapped, spliced] {
+            inner.atomic_write(Path::new("first"), &encrypted)?;
+            let file_slice = directory.open_read(Path::new("first")).unwrap();
+            let io_error = file_slice.read_bytes_slice(0..1).unwrap_err();
+            assert!(format!("{}", io_error).contains("The chunk 0 of the encrypted file"));
+            assert_eq!(
+                file_slice
+                    .read_bytes_slice(2 * CHUNK_SIZE..3 * CHUNK_SIZE)?
+                    .as_slice(),
+                &data[2 * CHUNK_SIZE..]
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_directory_index() -> crate::Result<()> {
+        let inner = RamDirectory::create();
+        let directory = EncryptedDirectory::new(Arc::new(inner.clone()), KEY);
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let num_field = schema_builder.add_u64_field("num", FAST);
+        let index = Index::create(directory, schema_builder.build(), IndexSettings::default())?;
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..1_000u64 {
+            index_writer
+                .add_document(doc!(text_field => format!("hello {}", i % 7), num_field => i));
+        }
+        index_writer.commit()?;
+        drop(index_writer);
+        drop(index);
+
+        let index = Index::open(EncryptedDirectory::new(Arc::new(inner.clone()), KEY))?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(text_field, "3"),
+            IndexRecordOption::Basic,
+        );
+        let top_docs = searcher.search(
+            &query,
+            &TopDocs::with_limit(3).order_by_u64_field(num_field),
+        )?;
+        let nums: Vec<u64> = top_docs.iter().map(|(num, _)| *num).collect();
+        assert_eq!(nums, vec![997, 990, 983]);
+        let doc = searcher.doc(top_docs[0].1)?;
+        assert_eq!(doc.get_first(text_field).unwrap().text(), Some("hello 3"));
+
+        assert!(
+            !String::from_utf8_lossy(&inner.atomic_read(Path::new("meta.json"))?)
+                .contains("segments")
+        );
+        let err = Index::open(EncryptedDirectory::new(Arc::new(inner), [0u8; 32])).unwrap_err();
+        assert!(format!("{}", err).contains("was encrypted with another key"));
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@ use stable_deref_trait::StableDeref;
 
 use crate::directory::OwnedBytes;
 use common::HasLen;
+use futures::future::BoxFuture;
 use std::fmt;
 use std::ops::Range;
 use std::sync::{Arc, Weak};
@@ -23,6 +24,16 @@ pub trait FileHandle: 'static + Send + Sync + HasLen + fmt::Debug {
     ///
     /// This method may panic if the range requested is invalid.
     fn read_bytes(&self, range: Range<usize>) -> io::Result<OwnedBytes>;
+
+    /// Reads a slice of bytes asynchronously.
+    ///
+    /// By default, the bytes are read with `read_bytes`: a `FileHandle` reading its
+    /// data from a remote storage should override it, to avoid blocking the executor.
+    ///
+    /// This method may panic if the range requested is invalid.
+    fn read_bytes_async(&self, range: Range<usize>) -> BoxFuture<'_, io::Result<OwnedBytes>> {
+        Box::pin(async move { self.read_bytes(range) })
+    }
 }
 
 impl FileHandle for &'static [u8] {
@@ -116,6 +127,27 @@ impl FileSlice {
             .read_bytes(self.range.start + range.start..self.range.start + range.end)
     }
 
+    /// Returns a `OwnedBytes` with all of the data in the `FileSlice`, reading it
+    /// asynchronously.
+    pub async fn read_bytes_async(&self) -> io::Result<OwnedBytes> {
+        self.data.read_bytes_async(self.range.clone()).await
+    }
+
+    /// Reads a specific slice of data asynchronously.
+    ///
+    /// This is equivalent to running `file_slice.slice(from, to).read_bytes_async()`.
+    pub async fn read_bytes_slice_async(&self, range: Range<usize>) -> io::Result<OwnedBytes> {
+        assert!(
+            range.end <= self.len(),
+            "end of requested range exceeds the fileslice length ({} > {})",
+            range.end,
+            self.len()
+        );
+        self.data
+            .read_bytes_async(self.range.start + range.start..self.range.start + range.end)
+            .await
+    }
+
     /// Splits the FileSlice at the given offset and return two file slices.
     /// `file_slice[..split_offset]` and `file_slice[split_offset..]`.
     ///
@@ -161,6 +193,10 @@ impl FileHandle for FileSlice {
     fn read_bytes(&self, range: Range<usize>) -> io::Result<OwnedBytes> {
         self.read_bytes_slice(range)
     }
+
+    fn read_bytes_async(&self, range: Range<usize>) -> BoxFuture<'_, io::Result<OwnedBytes>> {
+        Box::pin(self.read_bytes_slice_async(range))
+    }
 }
 
 impl HasLen for FileSlice {
@@ -235,6 +271,16 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_slice_read_slice_async() -> io::Result<()> {
+        let slice = FileSlice::new(Box::new(&b"abcdef"[..])).slice_from(1);
+        let bytes = futures::executor::block_on(slice.read_bytes_slice_async(1..4))?;
+        assert_eq!(bytes.as_ref(), b"cde");
+        let bytes = futures::executor::block_on(slice.slice(2..4).read_bytes_async())?;
+        assert_eq!(bytes.as_ref(), b"de");
+        Ok(())
+    }
+
     #[test]
     #[should_panic(expected = "end of requested range exceeds the fileslice length (10 > 6)")]
     fn test_slice_read_slice_invalid_range_exceeds() {
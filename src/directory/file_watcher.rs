@@ -2,7 +2,7 @@ use crate::directory::{WatchCallback, WatchCallbackList, WatchHandle};
 use crc32fast::Hasher;
 use std::fs;
 use std::io;
-use std::io::BufRead;
+use std::io::Read;
 use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -41,7 +41,7 @@ impl FileWatcher {
         let state = self.state.clone();
 
         thread::Builder::new()
-            .name("thread-tantivy-meta-file-watcher".to_string())
+            .name("thread-tantivy-file-watcher".to_string())
             .spawn(move || {
                 let mut current_checksum = None;
 
@@ -49,7 +49,7 @@ impl FileWatcher {
                     if let Ok(checksum) = FileWatcher::compute_checksum(&path) {
                         // `None.unwrap_or_else(|| !checksum) != checksum` evaluates to `true`
                         if current_checksum.unwrap_or_else(|| !checksum) != checksum {
-                            info!("Meta file {:?} was modified", path);
+                            info!("Watched file {:?} was modified", path);
                             current_checksum = Some(checksum);
                             futures::executor::block_on(callbacks.broadcast());
                         }
@@ -58,7 +58,7 @@ impl FileWatcher {
                     thread::sleep(POLLING_INTERVAL);
                 }
             })
-            .expect("Failed to spawn meta file watcher thread");
+            .expect("Failed to spawn file watcher thread");
     }
 
     pub fn watch(&self, callback: WatchCallback) -> WatchHandle {
@@ -67,19 +67,31 @@ impl FileWatcher {
         handle
     }
 
+    /// Returns true if none of the callbacks of the watcher has a live `WatchHandle`.
+    pub fn is_unwatched(&self) -> bool {
+        self.callbacks.is_empty()
+    }
+
     fn compute_checksum(path: &Path) -> Result<u32, io::Error> {
-        let reader = match fs::File::open(path) {
-            Ok(f) => io::BufReader::new(f),
+        let mut file = match fs::File::open(path) {
+            Ok(f) => f,
             Err(e) => {
-                warn!("Failed to open meta file {:?}: {:?}", path, e);
+                // The watched file may not be created yet.
+                if e.kind() != io::ErrorKind::NotFound {
+                    warn!("Failed to open watched file {:?}: {:?}", path, e);
+                }
                 return Err(e);
             }
         };
 
         let mut hasher = Hasher::new();
-
-        for line in reader.lines() {
-            hasher.update(line?.as_bytes())
+        let mut buffer = [0u8; 8_192];
+        loop {
+            let num_bytes = file.read(&mut buffer)?;
+            if num_bytes == 0 {
+                break;
+            }
+            hasher.update(&buffer[..num_bytes]);
         }
 
         Ok(hasher.finalize())
@@ -98,6 +110,7 @@ mod tests {
     use std::mem;
 
     use crate::directory::mmap_directory::atomic_write;
+    use crate::directory::Durability;
 
     use super::*;
 
@@ -125,18 +138,18 @@ mod tests {
         assert_eq!(counter.load(Ordering::SeqCst), 0);
         assert_eq!(state.load(Ordering::SeqCst), 1);
 
-        atomic_write(&tmp_file, b"foo")?;
+        atomic_write(&tmp_file, b"foo", Durability::None)?;
         assert_eq!(rx.recv_timeout(timeout), Ok(1));
 
-        atomic_write(&tmp_file, b"foo")?;
+        atomic_write(&tmp_file, b"foo", Durability::None)?;
         assert!(rx.recv_timeout(timeout).is_err());
 
-        atomic_write(&tmp_file, b"bar")?;
+        atomic_write(&tmp_file, b"bar", Durability::None)?;
         assert_eq!(rx.recv_timeout(timeout), Ok(2));
 
         mem::drop(watcher);
 
-        atomic_write(&tmp_file, b"qux")?;
+        atomic_write(&tmp_file, b"qux", Durability::None)?;
         thread::sleep(Duration::from_millis(10));
         assert_eq!(counter.load(Ordering::SeqCst), 2);
         assert_eq!(state.load(Ordering::SeqCst), 2);
@@ -168,12 +181,12 @@ mod tests {
         assert_eq!(counter.load(Ordering::SeqCst), 0);
         assert_eq!(state.load(Ordering::SeqCst), 1);
 
-        atomic_write(&tmp_file, b"foo")?;
+        atomic_write(&tmp_file, b"foo", Durability::None)?;
         assert_eq!(rx.recv_timeout(timeout), Ok(1));
 
         mem::drop(handle);
 
-        atomic_write(&tmp_file, b"qux")?;
+        atomic_write(&tmp_file, b"qux", Durability::None)?;
         assert_eq!(counter.load(Ordering::SeqCst), 1);
         assert_eq!(state.load(Ordering::SeqCst), 1);
 
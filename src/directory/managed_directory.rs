@@ -1,14 +1,15 @@
-use crate::core::MANAGED_FILEPATH;
+use crate::core::{MANAGED_FILEPATH, META_FILEPATH};
 use crate::directory::error::{DeleteError, LockError, OpenReadError, OpenWriteError};
 use crate::directory::footer::{Footer, FooterProxy};
 use crate::directory::GarbageCollectionResult;
 use crate::directory::Lock;
 use crate::directory::META_LOCK;
-use crate::directory::{DirectoryLock, FileHandle};
+use crate::directory::{DirectoryLock, Durability, FileHandle};
 use crate::directory::{FileSlice, WritePtr};
 use crate::directory::{WatchCallback, WatchHandle};
 use crate::error::DataCorruption;
 use crate::Directory;
+use crate::Version;
 
 use crc32fast::Hasher;
 use std::collections::HashSet;
@@ -48,6 +49,10 @@ pub struct ManagedDirectory {
 #[derive(Debug, Default)]
 struct MetaInformation {
     managed_paths: HashSet<PathBuf>,
+    // True if the last `meta.json` was written with `Durability::None`: the `meta.json`
+    // on the disk may still be an earlier one, referencing files which are not living
+    // anymore.
+    is_meta_unsynced: bool,
 }
 
 /// Saves the file containing the list of existing files
@@ -55,10 +60,11 @@ struct MetaInformation {
 fn save_managed_paths(
     directory: &dyn Directory,
     wlock: &RwLockWriteGuard<'_, MetaInformation>,
+    durability: Durability,
 ) -> io::Result<()> {
     let mut w = serde_json::to_vec(&wlock.managed_paths)?;
     writeln!(&mut w)?;
-    directory.atomic_write(&MANAGED_FILEPATH, &w[..])?;
+    directory.atomic_write_with_durability(&MANAGED_FILEPATH, &w[..], durability)?;
     Ok(())
 }
 
@@ -79,6 +85,7 @@ impl ManagedDirectory {
                     directory: Box::new(directory),
                     meta_informations: Arc::new(RwLock::new(MetaInformation {
                         managed_paths: managed_files,
+                        is_meta_unsynced: false,
                     })),
                 })
             }
@@ -109,6 +116,10 @@ impl ManagedDirectory {
     /// If a file cannot be deleted (for permission reasons for instance)
     /// an error is simply logged, and the file remains in the list of managed
     /// files.
+    ///
+    /// No file is deleted while the last `meta.json` was written with `Durability::None`,
+    /// as the files of the earlier `meta.json`, which may be the one on the disk, are
+    /// not living files anymore.
     pub fn garbage_collect<L: FnOnce() -> HashSet<PathBuf>>(
         &mut self,
         get_living_files: L,
@@ -131,6 +142,13 @@ impl ManagedDirectory {
                 .meta_informations
                 .read()
                 .expect("Managed directory rlock poisoned in garbage collect.");
+            if meta_informations_rlock.is_meta_unsynced {
+                info!("Garbage collection skipped until the meta.json is synced");
+                return Ok(GarbageCollectionResult {
+                    deleted_files: Vec::new(),
+                    failed_to_delete_files: Vec::new(),
+                });
+            }
 
             // The point of this second "file" lock is to enforce the following scenario
             // 1) process B tries to load a new set of searcher.
@@ -192,7 +210,11 @@ impl ManagedDirectory {
             for delete_file in &deleted_files {
                 managed_paths_write.remove(delete_file);
             }
-            save_managed_paths(self.directory.as_mut(), &meta_informations_wlock)?;
+            save_managed_paths(
+                self.directory.as_mut(),
+                &meta_informations_wlock,
+                Durability::Full,
+            )?;
         }
 
         Ok(GarbageCollectionResult {
@@ -212,7 +234,17 @@ impl ManagedDirectory {
     /// File starting by "." are reserved to locks.
     /// They are not managed and cannot be subjected
     /// to garbage collection.
-    fn register_file_as_managed(&self, filepath: &Path) -> io::Result<()> {
+    pub(crate) fn register_file_as_managed(&self, filepath: &Path) -> io::Result<()> {
+        self.register_file_as_managed_with_durability(filepath, Durability::Full)
+    }
+
+    /// Registers the file as managed, syncing the list of the managed files according to
+    /// `durability`: if it is lost after a crash, the file is not garbage collected.
+    fn register_file_as_managed_with_durability(
+        &self,
+        filepath: &Path,
+        durability: Durability,
+    ) -> io::Result<()> {
         // Files starting by "." (e.g. lock files) are not managed.
         if !is_managed(filepath) {
             return Ok(());
@@ -223,11 +255,42 @@ impl ManagedDirectory {
             .expect("Managed file lock poisoned");
         let has_changed = meta_wlock.managed_paths.insert(filepath.to_owned());
         if has_changed {
-            save_managed_paths(self.directory.as_ref(), &meta_wlock)?;
+            save_managed_paths(self.directory.as_ref(), &meta_wlock, durability)?;
         }
         Ok(())
     }
 
+    /// Opens the file, and returns the version of tantivy it was written with.
+    pub(crate) fn open_read_with_version(
+        &self,
+        path: &Path,
+    ) -> result::Result<(FileSlice, Version), OpenReadError> {
+        let file_slice = self.directory.open_read(path)?;
+        let (footer, reader) = Footer::extract_footer(file_slice)
+            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
+        footer.is_compatible()?;
+        Ok((reader, footer.version))
+    }
+
+    /// Opens the file as it is stored, including its footer.
+    pub(crate) fn open_read_with_footer(
+        &self,
+        path: &Path,
+    ) -> result::Result<FileSlice, OpenReadError> {
+        self.directory.open_read(path)
+    }
+
+    /// Opens a writer for a file which already has its footer, e.g. a file copied from
+    /// another index.
+    pub(crate) fn open_write_with_footer(
+        &self,
+        path: &Path,
+    ) -> result::Result<WritePtr, OpenWriteError> {
+        self.register_file_as_managed(path)
+            .map_err(|io_error| OpenWriteError::wrap_io_error(io_error, path.to_path_buf()))?;
+        self.directory.open_write(path)
+    }
+
     /// Verify checksum of a managed file
     pub fn validate_checksum(&self, path: &Path) -> result::Result<bool, OpenReadError> {
         let reader = self.directory.open_read(path)?;
@@ -267,19 +330,24 @@ impl Directory for ManagedDirectory {
     }
 
     fn open_read(&self, path: &Path) -> result::Result<FileSlice, OpenReadError> {
-        let file_slice = self.directory.open_read(path)?;
-        let (footer, reader) = Footer::extract_footer(file_slice)
-            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
-        footer.is_compatible()?;
-        Ok(reader)
+        self.open_read_with_version(path)
+            .map(|(reader, _version)| reader)
     }
 
     fn open_write(&self, path: &Path) -> result::Result<WritePtr, OpenWriteError> {
-        self.register_file_as_managed(path)
+        self.open_write_with_durability(path, Durability::Full)
+    }
+
+    fn open_write_with_durability(
+        &self,
+        path: &Path,
+        durability: Durability,
+    ) -> result::Result<WritePtr, OpenWriteError> {
+        self.register_file_as_managed_with_durability(path, durability)
             .map_err(|io_error| OpenWriteError::wrap_io_error(io_error, path.to_path_buf()))?;
         Ok(io::BufWriter::new(Box::new(FooterProxy::new(
             self.directory
-                .open_write(path)?
+                .open_write_with_durability(path, durability)?
                 .into_inner()
                 .map_err(|_| ())
                 .expect("buffer should be empty"),
@@ -287,8 +355,25 @@ impl Directory for ManagedDirectory {
     }
 
     fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
-        self.register_file_as_managed(path)?;
-        self.directory.atomic_write(path, data)
+        self.atomic_write_with_durability(path, data, Durability::Full)
+    }
+
+    fn atomic_write_with_durability(
+        &self,
+        path: &Path,
+        data: &[u8],
+        durability: Durability,
+    ) -> io::Result<()> {
+        self.register_file_as_managed_with_durability(path, durability)?;
+        self.directory
+            .atomic_write_with_durability(path, data, durability)?;
+        if path == *META_FILEPATH {
+            self.meta_informations
+                .write()
+                .expect("Managed file lock poisoned")
+                .is_meta_unsynced = durability == Durability::None;
+        }
+        Ok(())
     }
 
     fn atomic_read(&self, path: &Path) -> result::Result<Vec<u8>, OpenReadError> {
@@ -310,6 +395,18 @@ impl Directory for ManagedDirectory {
     fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
         self.directory.watch(watch_callback)
     }
+
+    fn watch_path(&self, path: &Path, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+        self.directory.watch_path(path, watch_callback)
+    }
+
+    fn local_file_path(&self, path: &Path) -> Option<PathBuf> {
+        self.directory.local_file_path(path)
+    }
+
+    fn advise_merge_read(&self, path: &Path) {
+        self.directory.advise_merge_read(path)
+    }
 }
 
 impl Clone for ManagedDirectory {
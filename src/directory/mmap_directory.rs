@@ -1,9 +1,10 @@
-use crate::core::META_FILEPATH;
+use crate::core::{SegmentComponent, META_FILEPATH};
 use crate::directory::error::LockError;
 use crate::directory::error::{DeleteError, OpenDirectoryError, OpenReadError, OpenWriteError};
 use crate::directory::file_watcher::FileWatcher;
 use crate::directory::Directory;
 use crate::directory::DirectoryLock;
+use crate::directory::Durability;
 use crate::directory::Lock;
 use crate::directory::WatchCallback;
 use crate::directory::WatchHandle;
@@ -14,17 +15,18 @@ use fs2::FileExt;
 use memmap2::Mmap;
 use serde::{Deserialize, Serialize};
 use stable_deref_trait::StableDeref;
+use std::collections::{HashMap, HashSet};
 use std::convert::From;
 use std::fmt;
 use std::fs::OpenOptions;
 use std::fs::{self, File};
 use std::io::{self, Seek, SeekFrom};
 use std::io::{BufWriter, Read, Write};
+use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::result;
 use std::sync::Arc;
-use std::sync::RwLock;
-use std::{collections::HashMap, ops::Deref};
+use std::sync::{Mutex, RwLock};
 use tempfile::TempDir;
 
 /// Create a default io error given a string.
@@ -34,7 +36,7 @@ pub(crate) fn make_io_err(msg: String) -> io::Error {
 
 /// Returns None iff the file exists, can be read, but is empty (and hence
 /// cannot be mmapped)
-fn open_mmap(full_path: &Path) -> result::Result<Option<Mmap>, OpenReadError> {
+fn open_mmap(full_path: &Path, populate: bool) -> result::Result<Option<Mmap>, OpenReadError> {
     let file = File::open(full_path).map_err(|io_err| {
         if io_err.kind() == io::ErrorKind::NotFound {
             OpenReadError::FileDoesNotExist(full_path.to_path_buf())
@@ -52,13 +54,101 @@ fn open_mmap(full_path: &Path) -> result::Result<Option<Mmap>, OpenReadError> {
         // instead.
         return Ok(None);
     }
+    let mut mmap_options = memmap2::MmapOptions::new();
+    if populate {
+        mmap_options.populate();
+    }
     unsafe {
-        memmap2::Mmap::map(&file)
+        mmap_options
+            .map(&file)
             .map(Some)
             .map_err(|io_err| OpenReadError::wrap_io_error(io_err, full_path.to_path_buf()))
     }
 }
 
+/// Access pattern of a memory mapped file, advised to the kernel with `madvise`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MmapAdvice {
+    /// No specific access pattern, `MADV_NORMAL`.
+    Normal,
+    /// The file is read in random order, `MADV_RANDOM`: its pages are not read ahead.
+    Random,
+    /// The file is read once and sequentially, `MADV_SEQUENTIAL`: its pages are read
+    /// ahead aggressively, and may be released soon after they are read.
+    Sequential,
+    /// The file will be read soon, `MADV_WILLNEED`: its pages are read ahead in the
+    /// background.
+    WillNeed,
+    /// The file will not be read soon, `MADV_DONTNEED`: the pages of the mapping are
+    /// released, and read again from the page cache or the disk when accessed.
+    DontNeed,
+}
+
+#[cfg(unix)]
+fn madvise(data: &[u8], advice: MmapAdvice) -> io::Result<()> {
+    let advice = match advice {
+        MmapAdvice::Normal => libc::MADV_NORMAL,
+        MmapAdvice::Random => libc::MADV_RANDOM,
+        MmapAdvice::Sequential => libc::MADV_SEQUENTIAL,
+        MmapAdvice::WillNeed => libc::MADV_WILLNEED,
+        MmapAdvice::DontNeed => libc::MADV_DONTNEED,
+    };
+    // `data` is a whole mapping, which starts on a page boundary. The advices never
+    // alter the content of the file-backed mapping.
+    let ret = unsafe { libc::madvise(data.as_ptr() as *mut libc::c_void, data.len(), advice) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn madvise(_data: &[u8], _advice: MmapAdvice) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "madvise is not available on this platform.",
+    ))
+}
+
+/// Options of a [`MmapDirectory`](struct.MmapDirectory.html), advising the kernel on
+/// the access pattern of its files.
+///
+/// The advices are applied with `madvise` once a file is memory mapped. They are
+/// ignored on the platforms without `madvise`.
+#[derive(Clone, Debug, Default)]
+pub struct MmapOptions {
+    advices: HashMap<SegmentComponent, MmapAdvice>,
+    populated: HashSet<SegmentComponent>,
+    merge_advice: Option<MmapAdvice>,
+}
+
+impl MmapOptions {
+    /// Advises `advice` for the files of the `component` of the segments, e.g.
+    /// `MmapAdvice::WillNeed` for the fast fields and the term dictionaries, and
+    /// `MmapAdvice::Random` for the doc store.
+    pub fn advice(mut self, component: SegmentComponent, advice: MmapAdvice) -> MmapOptions {
+        self.advices.insert(component, advice);
+        self
+    }
+
+    /// Populates the mappings of the files of the `component` of the segments, reading
+    /// them entirely when they are opened, with `MAP_POPULATE`.
+    ///
+    /// This is only supported on Linux, and ignored on the other platforms.
+    pub fn populate(mut self, component: SegmentComponent) -> MmapOptions {
+        self.populated.insert(component);
+        self
+    }
+
+    /// Advises `advice` for the files of the segments being merged, once the merge
+    /// starts, e.g. `MmapAdvice::Sequential`.
+    pub fn merge_advice(mut self, advice: MmapAdvice) -> MmapOptions {
+        self.merge_advice = Some(advice);
+        self
+    }
+}
+
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct CacheCounters {
     // Number of time the cache prevents to call `mmap`
@@ -109,8 +199,19 @@ impl MmapCache {
         }
     }
 
+    fn get_cached_mmap(&self, full_path: &Path) -> Option<ArcBytes> {
+        self.cache.get(full_path)?.upgrade()
+    }
+
     // Returns None if the file exists but as a len of 0 (and hence is not mmappable).
-    fn get_mmap(&mut self, full_path: &Path) -> Result<Option<ArcBytes>, OpenReadError> {
+    //
+    // `on_mmap` is called with the data of a file that was not mapped yet.
+    fn get_mmap(
+        &mut self,
+        full_path: &Path,
+        populate: bool,
+        on_mmap: impl FnOnce(&[u8]),
+    ) -> Result<Option<ArcBytes>, OpenReadError> {
         if let Some(mmap_weak) = self.cache.get(full_path) {
             if let Some(mmap_arc) = mmap_weak.upgrade() {
                 self.counters.hit += 1;
@@ -119,8 +220,9 @@ impl MmapCache {
         }
         self.cache.remove(full_path);
         self.counters.miss += 1;
-        let mmap_opt = open_mmap(full_path)?;
+        let mmap_opt = open_mmap(full_path, populate)?;
         Ok(mmap_opt.map(|mmap| {
+            on_mmap(&mmap);
             let mmap_arc: ArcBytes = Arc::new(mmap);
             let mmap_weak = Arc::downgrade(&mmap_arc);
             self.cache.insert(full_path.to_owned(), mmap_weak);
@@ -151,14 +253,26 @@ struct MmapDirectoryInner {
     mmap_cache: RwLock<MmapCache>,
     _temp_directory: Option<TempDir>,
     watcher: FileWatcher,
+    // Watchers of the files other than `meta.json`.
+    path_watchers: RwLock<HashMap<PathBuf, FileWatcher>>,
+    options: MmapOptions,
+    // The advice applied last to the mapping of each file.
+    applied_advices: Mutex<HashMap<PathBuf, MmapAdvice>>,
 }
 
 impl MmapDirectoryInner {
-    fn new(root_path: PathBuf, temp_directory: Option<TempDir>) -> MmapDirectoryInner {
+    fn new(
+        root_path: PathBuf,
+        temp_directory: Option<TempDir>,
+        options: MmapOptions,
+    ) -> MmapDirectoryInner {
         MmapDirectoryInner {
             mmap_cache: Default::default(),
+            options,
+            applied_advices: Default::default(),
             _temp_directory: temp_directory,
             watcher: FileWatcher::new(&root_path.join(*META_FILEPATH)),
+            path_watchers: Default::default(),
             root_path,
         }
     }
@@ -166,6 +280,19 @@ impl MmapDirectoryInner {
     fn watch(&self, callback: WatchCallback) -> WatchHandle {
         self.watcher.watch(callback)
     }
+
+    fn watch_path(&self, path: &Path, callback: WatchCallback) -> WatchHandle {
+        if path == *META_FILEPATH {
+            return self.watch(callback);
+        }
+        let mut path_watchers = self.path_watchers.write().unwrap();
+        // Dropping the watchers without callbacks stops their thread.
+        path_watchers.retain(|_, watcher| !watcher.is_unwatched());
+        path_watchers
+            .entry(path.to_path_buf())
+            .or_insert_with(|| FileWatcher::new(&self.root_path.join(path)))
+            .watch(callback)
+    }
 }
 
 impl fmt::Debug for MmapDirectory {
@@ -175,8 +302,12 @@ impl fmt::Debug for MmapDirectory {
 }
 
 impl MmapDirectory {
-    fn new(root_path: PathBuf, temp_directory: Option<TempDir>) -> MmapDirectory {
-        let inner = MmapDirectoryInner::new(root_path, temp_directory);
+    fn new(
+        root_path: PathBuf,
+        temp_directory: Option<TempDir>,
+        options: MmapOptions,
+    ) -> MmapDirectory {
+        let inner = MmapDirectoryInner::new(root_path, temp_directory, options);
         MmapDirectory {
             inner: Arc::new(inner),
         }
@@ -191,6 +322,7 @@ impl MmapDirectory {
         Ok(MmapDirectory::new(
             tempdir.path().to_path_buf(),
             Some(tempdir),
+            MmapOptions::default(),
         ))
     }
 
@@ -199,6 +331,18 @@ impl MmapDirectory {
     /// Returns an error if the `directory_path` does not
     /// exist or if it is not a directory.
     pub fn open<P: AsRef<Path>>(directory_path: P) -> Result<MmapDirectory, OpenDirectoryError> {
+        MmapDirectory::open_with_options(directory_path, MmapOptions::default())
+    }
+
+    /// Opens a MmapDirectory in a directory, advising the kernel on the access pattern
+    /// of its files according to `options`.
+    ///
+    /// Returns an error if the `directory_path` does not
+    /// exist or if it is not a directory.
+    pub fn open_with_options<P: AsRef<Path>>(
+        directory_path: P,
+        options: MmapOptions,
+    ) -> Result<MmapDirectory, OpenDirectoryError> {
         let directory_path: &Path = directory_path.as_ref();
         if !directory_path.exists() {
             Err(OpenDirectoryError::DoesNotExist(PathBuf::from(
@@ -209,7 +353,11 @@ impl MmapDirectory {
                 directory_path,
             )))
         } else {
-            Ok(MmapDirectory::new(PathBuf::from(directory_path), None))
+            Ok(MmapDirectory::new(
+                PathBuf::from(directory_path),
+                None,
+                options,
+            ))
         }
     }
 
@@ -246,6 +394,37 @@ impl MmapDirectory {
         Ok(())
     }
 
+    /// Applies `advice` to the mapping of the file at `path`.
+    fn advise(&self, path: &Path, data: &[u8], advice: MmapAdvice) {
+        match madvise(data, advice) {
+            Ok(()) => {
+                self.inner
+                    .applied_advices
+                    .lock()
+                    .unwrap()
+                    .insert(path.to_path_buf(), advice);
+            }
+            Err(io_error) => {
+                debug!(
+                    "Failed to advise {:?} for {:?}: {:?}",
+                    advice, path, io_error
+                );
+            }
+        }
+    }
+
+    /// Returns the advice applied last to the mapping of the file at `path`, if any.
+    ///
+    /// This is mostly useful to check the effect of the `MmapOptions`.
+    pub fn applied_advice(&self, path: &Path) -> Option<MmapAdvice> {
+        self.inner
+            .applied_advices
+            .lock()
+            .unwrap()
+            .get(path)
+            .copied()
+    }
+
     /// Returns some statistical information
     /// about the Mmap cache.
     ///
@@ -281,29 +460,39 @@ impl Drop for ReleaseLockFile {
 }
 
 /// This Write wraps a File, but has the specificity of
-/// call `sync_all` on flush.
-struct SafeFileWriter(File);
+/// call `sync_all` on flush, unless it was created with
+/// `Durability::MetaOnly` or `Durability::None`.
+struct SafeFileWriter {
+    file: File,
+    sync: bool,
+}
 
 impl SafeFileWriter {
-    fn new(file: File) -> SafeFileWriter {
-        SafeFileWriter(file)
+    fn new(file: File, durability: Durability) -> SafeFileWriter {
+        SafeFileWriter {
+            file,
+            sync: durability == Durability::Full,
+        }
     }
 }
 
 impl Write for SafeFileWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.0.write(buf)
+        self.file.write(buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.0.flush()?;
-        self.0.sync_all()
+        self.file.flush()?;
+        if self.sync {
+            self.file.sync_all()?;
+        }
+        Ok(())
     }
 }
 
 impl Seek for SafeFileWriter {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        self.0.seek(pos)
+        self.file.seek(pos)
     }
 }
 
@@ -326,7 +515,10 @@ impl Deref for MmapArc {
 unsafe impl StableDeref for MmapArc {}
 
 /// Writes a file in an atomic manner.
-pub(crate) fn atomic_write(path: &Path, content: &[u8]) -> io::Result<()> {
+///
+/// The content of the file is synced before the file is renamed, unless the `durability`
+/// is `Durability::None`.
+pub(crate) fn atomic_write(path: &Path, content: &[u8], durability: Durability) -> io::Result<()> {
     // We create the temporary file in the same directory as the target file.
     // Indeed the canonical temp directory and the target file might sit in different
     // filesystem, in which case the atomic write may actually not work.
@@ -339,6 +531,9 @@ pub(crate) fn atomic_write(path: &Path, content: &[u8]) -> io::Result<()> {
     let mut tempfile = tempfile::Builder::new().tempfile_in(&parent_path)?;
     tempfile.write_all(content)?;
     tempfile.flush()?;
+    if durability != Durability::None {
+        tempfile.as_file().sync_all()?;
+    }
     tempfile.into_temp_path().persist(path)?;
     Ok(())
 }
@@ -358,8 +553,18 @@ impl Directory for MmapDirectory {
             OpenReadError::wrap_io_error(io_err, path.to_path_buf())
         })?;
 
+        let component_opt = SegmentComponent::from_path(path);
+        let options = &self.inner.options;
+        let advice = component_opt.and_then(|component| options.advices.get(&component).copied());
+        let populate = component_opt
+            .map(|component| options.populated.contains(&component))
+            .unwrap_or(false);
         let owned_bytes = mmap_cache
-            .get_mmap(&full_path)?
+            .get_mmap(&full_path, populate, |data| {
+                if let Some(advice) = advice {
+                    self.advise(path, data, advice);
+                }
+            })?
             .map(|mmap_arc| {
                 let mmap_arc_obj = MmapArc(mmap_arc);
                 OwnedBytes::new(mmap_arc_obj)
@@ -373,6 +578,7 @@ impl Directory for MmapDirectory {
     /// removed before the file is deleted.
     fn delete(&self, path: &Path) -> result::Result<(), DeleteError> {
         let full_path = self.resolve_path(path);
+        self.inner.applied_advices.lock().unwrap().remove(path);
         match fs::remove_file(&full_path) {
             Ok(_) => self.sync_directory().map_err(|e| DeleteError::IoError {
                 io_error: e,
@@ -397,6 +603,14 @@ impl Directory for MmapDirectory {
     }
 
     fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+        self.open_write_with_durability(path, Durability::Full)
+    }
+
+    fn open_write_with_durability(
+        &self,
+        path: &Path,
+        durability: Durability,
+    ) -> Result<WritePtr, OpenWriteError> {
         debug!("Open Write {:?}", path);
         let full_path = self.resolve_path(path);
 
@@ -419,10 +633,12 @@ impl Directory for MmapDirectory {
 
         // Apparetntly, on some filesystem syncing the parent
         // directory is required.
-        self.sync_directory()
-            .map_err(|io_err| OpenWriteError::wrap_io_error(io_err, path.to_path_buf()))?;
+        if durability == Durability::Full {
+            self.sync_directory()
+                .map_err(|io_err| OpenWriteError::wrap_io_error(io_err, path.to_path_buf()))?;
+        }
 
-        let writer = SafeFileWriter::new(file);
+        let writer = SafeFileWriter::new(file, durability);
         Ok(BufWriter::new(Box::new(writer)))
     }
 
@@ -447,9 +663,21 @@ impl Directory for MmapDirectory {
     }
 
     fn atomic_write(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        self.atomic_write_with_durability(path, content, Durability::Full)
+    }
+
+    fn atomic_write_with_durability(
+        &self,
+        path: &Path,
+        content: &[u8],
+        durability: Durability,
+    ) -> io::Result<()> {
         debug!("Atomic Write {:?}", path);
         let full_path = self.resolve_path(path);
-        atomic_write(&full_path, content)?;
+        atomic_write(&full_path, content, durability)?;
+        if durability == Durability::None {
+            return Ok(());
+        }
         self.sync_directory()
     }
 
@@ -476,6 +704,32 @@ impl Directory for MmapDirectory {
     fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
         Ok(self.inner.watch(watch_callback))
     }
+
+    /// The file is polled, and the callbacks are triggered when its content changes.
+    fn watch_path(&self, path: &Path, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+        Ok(self.inner.watch_path(path, watch_callback))
+    }
+
+    fn local_file_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(self.resolve_path(path))
+    }
+
+    /// Applies the merge advice of the `MmapOptions` to the file, if it is mapped.
+    fn advise_merge_read(&self, path: &Path) {
+        let advice = match self.inner.options.merge_advice {
+            Some(advice) => advice,
+            None => return,
+        };
+        let mmap_opt = self
+            .inner
+            .mmap_cache
+            .read()
+            .expect("Mmap cache lock is poisoned.")
+            .get_cached_mmap(&self.resolve_path(path));
+        if let Some(mmap) = mmap_opt {
+            self.advise(path, &mmap, advice);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -485,11 +739,12 @@ mod tests {
     // The following tests are specific to the MmapDirectory
 
     use super::*;
-    use crate::indexer::LogMergePolicy;
+    use crate::core::{Segment, SegmentId};
+    use crate::indexer::{LogMergePolicy, NoMergePolicy};
     use crate::Index;
     use crate::ReloadPolicy;
     use crate::{
-        schema::{Schema, SchemaBuilder, TEXT},
+        schema::{Schema, SchemaBuilder, FAST, STORED, TEXT},
         IndexSettings,
     };
     use common::HasLen;
@@ -628,4 +883,92 @@ mod tests {
         }
         assert!(mmap_directory.get_cache_info().mmapped.is_empty());
     }
+
+    #[test]
+    fn test_write_with_durability() -> crate::Result<()> {
+        let mmap_directory = MmapDirectory::create_from_tempdir()?;
+        for (i, &durability) in [Durability::Full, Durability::MetaOnly, Durability::None]
+            .iter()
+            .enumerate()
+        {
+            let path = PathBuf::from(format!("file_{}", i));
+            let mut wrt = mmap_directory.open_write_with_durability(&path, durability)?;
+            wrt.write_all(b"abc")?;
+            wrt.terminate()?;
+            assert_eq!(
+                mmap_directory.open_read(&path)?.read_bytes()?.as_slice(),
+                b"abc"
+            );
+            let atomic_path = PathBuf::from(format!("atomic_{}", i));
+            mmap_directory.atomic_write_with_durability(&atomic_path, b"def", durability)?;
+            assert_eq!(mmap_directory.atomic_read(&atomic_path)?, b"def");
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_mmap_options_advices() -> crate::Result<()> {
+        let tempdir = tempfile::TempDir::new()?;
+        let options = MmapOptions::default()
+            .advice(SegmentComponent::FastFields, MmapAdvice::WillNeed)
+            .advice(SegmentComponent::Terms, MmapAdvice::WillNeed)
+            .advice(SegmentComponent::Store, MmapAdvice::Random)
+            .populate(SegmentComponent::FastFields)
+            .merge_advice(MmapAdvice::Sequential);
+        let mmap_directory = MmapDirectory::open_with_options(tempdir.path(), options)?;
+        let mut schema_builder: SchemaBuilder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let num_field = schema_builder.add_u64_field("num", FAST);
+        let index = Index::create(
+            mmap_directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for i in 0..2u64 {
+            index_writer.add_document(doc!(text_field => "abc", num_field => i));
+            index_writer.commit()?;
+        }
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let searcher = reader.searcher();
+        let segments = index.searchable_segments()?;
+        assert_eq!(segments.len(), 2);
+        for segment in &segments {
+            let advice =
+                |component| mmap_directory.applied_advice(&segment.relative_path(component));
+            assert_eq!(
+                advice(SegmentComponent::FastFields),
+                Some(MmapAdvice::WillNeed)
+            );
+            assert_eq!(advice(SegmentComponent::Terms), Some(MmapAdvice::WillNeed));
+            assert_eq!(advice(SegmentComponent::Store), Some(MmapAdvice::Random));
+            assert_eq!(advice(SegmentComponent::Postings), None);
+        }
+
+        let segment_ids: Vec<SegmentId> = segments.iter().map(Segment::id).collect();
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        // The searcher still uses the merged segments, whose files are mapped.
+        assert_eq!(searcher.segment_readers().len(), 2);
+        for segment in &segments {
+            for component in [SegmentComponent::Postings, SegmentComponent::Store] {
+                let path = segment.relative_path(component);
+                assert_eq!(
+                    mmap_directory.applied_advice(&path),
+                    Some(MmapAdvice::Sequential)
+                );
+            }
+        }
+        reader.reload()?;
+        let merged_segment = &index.searchable_segments()?[0];
+        assert_eq!(
+            mmap_directory.applied_advice(&merged_segment.relative_path(SegmentComponent::Store)),
+            Some(MmapAdvice::Random)
+        );
+        Ok(())
+    }
 }
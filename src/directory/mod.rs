@@ -7,6 +7,11 @@ WORM directory abstraction.
 #[cfg(feature = "mmap")]
 mod mmap_directory;
 
+#[cfg(feature = "encryption")]
+mod encrypted_directory;
+
+mod bundle_directory;
+mod caching_directory;
 mod directory;
 mod directory_lock;
 mod file_slice;
@@ -22,12 +27,15 @@ pub mod error;
 
 mod composite_file;
 
+pub use self::bundle_directory::BundleDirectory;
+pub use self::caching_directory::{CacheConfig, CachingDirectory, CachingDirectoryStats};
 pub(crate) use self::composite_file::{CompositeFile, CompositeWrite};
 pub use self::directory::DirectoryLock;
-pub use self::directory::{Directory, DirectoryClone};
+pub use self::directory::{Directory, DirectoryClone, Durability};
 pub use self::directory_lock::{Lock, INDEX_WRITER_LOCK, META_LOCK};
 pub(crate) use self::file_slice::{ArcBytes, WeakArcBytes};
 pub use self::file_slice::{FileHandle, FileSlice};
+pub(crate) use self::footer::Footer;
 pub use self::owned_bytes::OwnedBytes;
 pub use self::ram_directory::RamDirectory;
 pub use self::watch_event_router::{WatchCallback, WatchCallbackList, WatchHandle};
@@ -51,7 +59,10 @@ pub struct GarbageCollectionResult {
 }
 
 #[cfg(feature = "mmap")]
-pub use self::mmap_directory::MmapDirectory;
+pub use self::mmap_directory::{MmapAdvice, MmapDirectory, MmapOptions};
+
+#[cfg(feature = "encryption")]
+pub use self::encrypted_directory::EncryptedDirectory;
 
 pub use self::managed_directory::ManagedDirectory;
 
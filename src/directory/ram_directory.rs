@@ -77,7 +77,13 @@ impl Write for VecWriter {
 
 impl TerminatingWrite for VecWriter {
     fn terminate_ref(&mut self, _: AntiCallToken) -> io::Result<()> {
-        self.flush()
+        self.flush()?;
+        self.shared_directory
+            .fs
+            .read()
+            .unwrap()
+            .broadcast_path(&self.path);
+        Ok(())
     }
 }
 
@@ -85,6 +91,7 @@ impl TerminatingWrite for VecWriter {
 struct InnerDirectory {
     fs: HashMap<PathBuf, FileSlice>,
     watch_router: WatchCallbackList,
+    path_watch_routers: HashMap<PathBuf, WatchCallbackList>,
 }
 
 impl InnerDirectory {
@@ -115,6 +122,23 @@ impl InnerDirectory {
         self.watch_router.subscribe(watch_handle)
     }
 
+    fn watch_path(&mut self, path: &Path, watch_callback: WatchCallback) -> WatchHandle {
+        self.path_watch_routers
+            .retain(|_, watch_router| !watch_router.is_empty());
+        self.path_watch_routers
+            .entry(path.to_path_buf())
+            .or_default()
+            .subscribe(watch_callback)
+    }
+
+    /// Triggers the callbacks watching the file at `path`, if any.
+    fn broadcast_path(&self, path: &Path) {
+        if let Some(watch_router) = self.path_watch_routers.get(path) {
+            // The callbacks are called in the background.
+            std::mem::drop(watch_router.broadcast());
+        }
+    }
+
     fn total_mem_usage(&self) -> usize {
         self.fs.values().map(|f| f.len()).sum()
     }
@@ -231,6 +255,8 @@ impl Directory for RamDirectory {
 
         if path == *META_FILEPATH {
             let _ = self.fs.write().unwrap().watch_router.broadcast();
+        } else {
+            self.fs.read().unwrap().broadcast_path(path);
         }
         Ok(())
     }
@@ -238,14 +264,25 @@ impl Directory for RamDirectory {
     fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
         Ok(self.fs.write().unwrap().watch(watch_callback))
     }
+
+    /// The callbacks are triggered when the file is written with `atomic_write`, or when
+    /// its writer is terminated.
+    fn watch_path(&self, path: &Path, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+        if path == *META_FILEPATH {
+            return self.watch(watch_callback);
+        }
+        Ok(self.fs.write().unwrap().watch_path(path, watch_callback))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::RamDirectory;
+    use crate::directory::{TerminatingWrite, WatchCallback};
     use crate::Directory;
     use std::io::Write;
     use std::path::Path;
+    use std::time::Duration;
 
     #[test]
     fn test_persist() {
@@ -263,4 +300,25 @@ mod tests {
         assert_eq!(directory_copy.atomic_read(path_atomic).unwrap(), msg_atomic);
         assert_eq!(directory_copy.atomic_read(path_seq).unwrap(), msg_seq);
     }
+
+    #[test]
+    fn test_watch_path_open_write() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let path = Path::new("a.idx");
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let _handle = directory.watch_path(
+            path,
+            WatchCallback::new(move || {
+                tx.send(()).unwrap();
+            }),
+        )?;
+        let mut wrt = directory.open_write(path)?;
+        wrt.write_all(b"foo")?;
+        wrt.flush()?;
+        // The callbacks are only triggered once the file is complete.
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+        wrt.terminate()?;
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_ok());
+        Ok(())
+    }
 }
@@ -62,6 +62,41 @@ mod mmap_directory_tests {
         let directory = make_directory();
         super::test_watch(&directory);
     }
+
+    #[test]
+    fn test_watch_path() {
+        let directory = make_directory();
+        super::test_watch_path(&directory);
+    }
+
+    #[test]
+    fn test_watch_debounced() {
+        use super::*;
+        let directory = make_directory();
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let directory_clone = directory.clone();
+        let _handle = directory
+            .watch(
+                WatchCallback::new(move || {
+                    let meta = directory_clone.atomic_read(Path::new("meta.json")).unwrap();
+                    tx.send(meta).unwrap();
+                })
+                .debounced(Duration::from_secs(1)),
+            )
+            .unwrap();
+        for i in 0..10 {
+            directory
+                .atomic_write(Path::new("meta.json"), format!("{}", i).as_bytes())
+                .unwrap();
+        }
+        // The file is polled, so the number of changes detected depends on the timing,
+        // but the writes are coalesced, and the last one is always delivered.
+        let metas: Vec<Vec<u8>> =
+            std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(2)).ok()).collect();
+        assert!(!metas.is_empty());
+        assert!(metas.len() <= 2);
+        assert_eq!(metas.last().unwrap(), b"9");
+    }
 }
 
 mod ram_directory_tests {
@@ -116,6 +151,41 @@ mod ram_directory_tests {
         let directory = make_directory();
         super::test_watch(&directory);
     }
+
+    #[test]
+    fn test_watch_path() {
+        let directory = make_directory();
+        super::test_watch_path(&directory);
+    }
+
+    #[test]
+    fn test_watch_debounced() {
+        use super::*;
+        let directory = make_directory();
+        let counter: Arc<AtomicUsize> = Default::default();
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let counter_clone = counter.clone();
+        let _handle = directory
+            .watch(
+                WatchCallback::new(move || {
+                    let val = counter_clone.fetch_add(1, SeqCst);
+                    tx.send(val + 1).unwrap();
+                })
+                .debounced(Duration::from_millis(300)),
+            )
+            .unwrap();
+        for i in 0..10 {
+            directory
+                .atomic_write(Path::new("meta.json"), format!("{}", i).as_bytes())
+                .unwrap();
+        }
+        // The first commit is delivered right away, the next ones once the interval
+        // has elapsed.
+        assert_eq!(rx.recv_timeout(Duration::from_millis(250)), Ok(1));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok(2));
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+        assert_eq!(counter.load(SeqCst), 2);
+    }
 }
 
 #[test]
@@ -219,6 +289,39 @@ fn test_watch(directory: &dyn Directory) {
     assert!(rx.recv_timeout(timeout).is_err());
 }
 
+fn test_watch_path(directory: &dyn Directory) {
+    let counter: Arc<AtomicUsize> = Default::default();
+    let (tx, rx) = crossbeam::channel::unbounded();
+    let timeout = Duration::from_millis(500);
+
+    let handle = directory
+        .watch_path(
+            Path::new("a.idx"),
+            WatchCallback::new(move || {
+                let val = counter.fetch_add(1, SeqCst);
+                tx.send(val + 1).unwrap();
+            }),
+        )
+        .unwrap();
+
+    assert!(directory.atomic_write(Path::new("a.idx"), b"foo").is_ok());
+    assert_eq!(rx.recv_timeout(timeout), Ok(1));
+
+    assert!(directory.atomic_write(Path::new("b.idx"), b"foo").is_ok());
+    assert!(directory
+        .atomic_write(Path::new("meta.json"), b"foo")
+        .is_ok());
+    assert!(rx.recv_timeout(timeout).is_err());
+
+    assert!(directory.atomic_write(Path::new("a.idx"), b"bar").is_ok());
+    assert_eq!(rx.recv_timeout(timeout), Ok(2));
+
+    mem::drop(handle);
+
+    assert!(directory.atomic_write(Path::new("a.idx"), b"qux").is_ok());
+    assert!(rx.recv_timeout(timeout).is_err());
+}
+
 fn test_lock_non_blocking(directory: &dyn Directory) {
     {
         let lock_a_res = directory.acquire_lock(&Lock {
@@ -1,8 +1,10 @@
 use futures::channel::oneshot;
 use futures::{Future, TryFutureExt};
 use std::sync::Arc;
-use std::sync::RwLock;
 use std::sync::Weak;
+use std::sync::{Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// Cloneable wrapper for callbacks registered when watching files of a `Directory`.
 #[derive(Clone)]
@@ -14,11 +16,83 @@ impl WatchCallback {
         WatchCallback(Arc::new(op))
     }
 
+    /// Wraps the callback so that it is called at most once per `interval`.
+    ///
+    /// The first change is delivered right away. The changes detected less than `interval`
+    /// after a call are coalesced into a single call, made once the `interval` has elapsed,
+    /// so the last change is always delivered.
+    /// This call is not made if the `WatchHandle` was dropped in the meantime.
+    pub fn debounced(self, interval: Duration) -> WatchCallback {
+        let debouncer = Arc::new(Debouncer {
+            callback: self,
+            interval,
+            state: Mutex::default(),
+        });
+        WatchCallback::new(move || Debouncer::trigger(&debouncer))
+    }
+
     fn call(&self) {
         self.0()
     }
 }
 
+#[derive(Default)]
+struct DebouncerState {
+    last_call: Option<Instant>,
+    is_call_scheduled: bool,
+}
+
+struct Debouncer {
+    callback: WatchCallback,
+    interval: Duration,
+    state: Mutex<DebouncerState>,
+}
+
+impl Debouncer {
+    fn trigger(debouncer: &Arc<Debouncer>) {
+        let mut state = debouncer.state.lock().unwrap();
+        if state.is_call_scheduled {
+            return;
+        }
+        let now = Instant::now();
+        let delay = state
+            .last_call
+            .map(|last_call| (last_call + debouncer.interval).saturating_duration_since(now))
+            .unwrap_or_default();
+        if delay == Duration::default() {
+            state.last_call = Some(now);
+            drop(state);
+            debouncer.callback.call();
+            return;
+        }
+        state.is_call_scheduled = true;
+        drop(state);
+        // The scheduled call only keeps a weak reference, so that it is cancelled
+        // when the `WatchHandle` is dropped.
+        let debouncer_weak = Arc::downgrade(debouncer);
+        let spawn_res = thread::Builder::new()
+            .name("watch-debounce".to_string())
+            .spawn(move || {
+                thread::sleep(delay);
+                if let Some(debouncer) = debouncer_weak.upgrade() {
+                    {
+                        let mut state = debouncer.state.lock().unwrap();
+                        state.is_call_scheduled = false;
+                        state.last_call = Some(Instant::now());
+                    }
+                    debouncer.callback.call();
+                }
+            });
+        if let Err(err) = spawn_res {
+            error!(
+                "Failed to spawn thread to call debounced watch callback. Cause: {:?}",
+                err
+            );
+            debouncer.state.lock().unwrap().is_call_scheduled = false;
+        }
+    }
+}
+
 /// Helper struct to implement the watch method in `Directory` implementations.
 ///
 /// It registers callbacks (See `.subscribe(...)`) and
@@ -59,6 +133,15 @@ impl WatchCallbackList {
         WatchHandle::new(watch_callback_arc)
     }
 
+    /// Returns true if none of the subscribed callbacks has a live `WatchHandle`.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.router
+            .read()
+            .unwrap()
+            .iter()
+            .all(|watch_callback_weak| watch_callback_weak.strong_count() == 0)
+    }
+
     fn list_callback(&self) -> Vec<WatchCallback> {
         let mut callbacks: Vec<WatchCallback> = vec![];
         let mut router_wlock = self.router.write().unwrap();
@@ -108,6 +191,7 @@ mod tests {
     use std::mem;
     use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
+    use std::time::Duration;
 
     #[test]
     fn test_watch_event_router_simple() {
@@ -186,4 +270,49 @@ mod tests {
         block_on(watch_event_router.broadcast());
         assert_eq!(2, counter.load(Ordering::SeqCst));
     }
+
+    #[test]
+    fn test_debounced_callback() {
+        let counter: Arc<AtomicUsize> = Default::default();
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let counter_clone = counter.clone();
+        let callback = WatchCallback::new(move || {
+            counter_clone.fetch_add(1, Ordering::SeqCst);
+            tx.send(()).unwrap();
+        })
+        .debounced(Duration::from_millis(100));
+        // The first call is delivered right away.
+        callback.call();
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        // The next ones are coalesced into a single trailing call.
+        for _ in 0..10 {
+            callback.call();
+        }
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_debounced_callback_cancelled_on_drop() {
+        let watch_event_router = WatchCallbackList::default();
+        let counter: Arc<AtomicUsize> = Default::default();
+        let counter_clone = counter.clone();
+        let handle = watch_event_router.subscribe(
+            WatchCallback::new(move || {
+                counter_clone.fetch_add(1, Ordering::SeqCst);
+            })
+            .debounced(Duration::from_millis(50)),
+        );
+        block_on(watch_event_router.broadcast());
+        block_on(watch_event_router.broadcast());
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        assert!(!watch_event_router.is_empty());
+        mem::drop(handle);
+        assert!(watch_event_router.is_empty());
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
 }
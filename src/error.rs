@@ -30,6 +30,12 @@ impl DataCorruption {
         }
     }
 
+    /// Attaches the path of the corrupted file to the error.
+    pub(crate) fn with_filepath(mut self, filepath: PathBuf) -> DataCorruption {
+        self.filepath = Some(filepath);
+        self
+    }
+
     /// Creates a `DataCorruption` Error, when the filepath is irrelevant.
     pub fn comment_only<TStr: ToString>(comment: TStr) -> DataCorruption {
         DataCorruption {
@@ -95,6 +101,15 @@ pub enum TantivyError {
     /// Index incompatible with current version of tantivy
     #[error("{0:?}")]
     IncompatibleIndex(Incompatibility),
+    /// A collector would have created more buckets than its limit.
+    #[error("The limit of {0} buckets was exceeded")]
+    BucketLimitExceeded(u64),
+    /// A merge was cancelled before its end.
+    #[error("The merge was cancelled")]
+    MergeCancelled,
+    /// The size of the index in bytes, and its quota, which it exceeds.
+    #[error("The index uses {0} bytes, exceeding its quota of {1} bytes")]
+    IndexFull(u64, u64),
 }
 
 impl From<DataCorruption> for TantivyError {
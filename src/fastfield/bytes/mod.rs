@@ -6,9 +6,13 @@ pub use self::writer::BytesFastFieldWriter;
 
 #[cfg(test)]
 mod tests {
+    use super::BytesFastFieldReader;
+    use crate::directory::FileSlice;
+    use crate::fastfield::DynamicFastFieldReader;
     use crate::schema::{BytesOptions, IndexRecordOption, Schema, Value};
     use crate::{query::TermQuery, schema::FAST, schema::INDEXED, schema::STORED};
     use crate::{DocAddress, DocSet, Index, Searcher, Term};
+    use futures::executor::block_on;
     use std::ops::Deref;
 
     #[test]
@@ -114,6 +118,56 @@ mod tests {
         let field = searcher.schema().get_field("string_bytes").unwrap();
         let fast_field_reader = fast_fields.bytes(field).unwrap();
         assert_eq!(fast_field_reader.get_bytes(0u32), b"tantivy");
+        assert_eq!(fast_field_reader.num_vals(0u32), 2);
+        assert_eq!(fast_field_reader.num_bytes(0u32), 13);
+        let vals: Vec<&[u8]> = fast_field_reader.get_vals(0u32).collect();
+        assert_eq!(vals, &[&b"tantivy"[..], &b"lucene"[..]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fast_bytes_multivalue_merge() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let field = schema_builder.add_bytes_field("bytes", FAST | INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(field => b"a".as_ref(), field => b"".as_ref()));
+        index_writer.add_document(doc!());
+        index_writer.commit()?;
+        index_writer.add_document(doc!(field => b"bc".as_ref()));
+        index_writer.add_document(doc!(field => b"de".as_ref(), field => b"fgh".as_ref()));
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_bytes(field, b"bc"));
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        block_on(index_writer.merge(&segment_ids))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let bytes_reader = searcher.segment_reader(0).fast_fields().bytes(field)?;
+        let vals = |doc| bytes_reader.get_vals(doc).collect::<Vec<&[u8]>>();
+        assert_eq!(vals(0), &[&b"a"[..], &b""[..]]);
+        assert!(vals(1).is_empty());
+        assert_eq!(bytes_reader.get_bytes(1), b"");
+        assert_eq!(vals(2), &[&b"de"[..], &b"fgh"[..]]);
+        assert_eq!(bytes_reader.total_num_vals(), 4);
+        assert_eq!(bytes_reader.total_num_bytes(), 6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytes_single_value_format() -> crate::Result<()> {
+        // segments written before bytes fast fields were multivalued
+        // only had the bytes offset of each doc.
+        let idx_reader = DynamicFastFieldReader::from(vec![0u64, 3, 3, 5]);
+        let values = FileSlice::from(b"abcde".to_vec());
+        let bytes_reader = BytesFastFieldReader::open(idx_reader, None, values, 3)?;
+        assert_eq!(bytes_reader.get_bytes(0), b"abc");
+        assert_eq!(bytes_reader.get_bytes(1), b"");
+        assert_eq!(bytes_reader.get_vals(2).collect::<Vec<_>>(), &[&b"de"[..]]);
+        assert_eq!(bytes_reader.num_vals(1), 1);
+        assert_eq!(bytes_reader.num_bytes(2), 2);
+        assert_eq!(bytes_reader.total_num_vals(), 3);
         Ok(())
     }
 }
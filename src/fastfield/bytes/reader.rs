@@ -1,49 +1,108 @@
+use std::ops::Range;
+
 use crate::directory::FileSlice;
 use crate::directory::OwnedBytes;
-use crate::fastfield::{BitpackedFastFieldReader, FastFieldReader, MultiValueLength};
+use crate::fastfield::{DynamicFastFieldReader, FastFieldReader, MultiValueLength};
 use crate::DocId;
 
 /// Reader for byte array fast fields
 ///
-/// The reader is implemented as a `u64` fast field and a separate collection of bytes.
+/// The reader is implemented as two `u64` fast fields and a separate collection of bytes.
+///
+/// The `values` are the concatenated list of all values for all documents.
 ///
-/// The `vals_reader` will access the concatenated list of all values for all documents.
+/// The `val_offsets_reader` associates, for each value, the offset of its first byte.
 ///
 /// The `idx_reader` associates, for each document, the index of its first value.
 ///
-/// Reading the value for a document is done by reading the start index for it,
-/// and the start index for the next document, and keeping the bytes in between.
+/// Reading the values for a document is done by reading the index of its first value,
+/// and the index of the first value of the next document, and keeping the values in between.
+///
+/// Segments written before bytes fast fields were multivalued have no `val_offsets_reader`.
+/// They have one value per document, and their `idx_reader` associates, for each document,
+/// the offset of the first byte of its value.
 #[derive(Clone)]
 pub struct BytesFastFieldReader {
-    idx_reader: BitpackedFastFieldReader<u64>,
+    idx_reader: DynamicFastFieldReader<u64>,
+    val_offsets_reader: Option<DynamicFastFieldReader<u64>>,
     values: OwnedBytes,
+    max_doc: DocId,
+}
+
+/// Returns `start..stop`, where `start` and `stop` are the values of `reader`
+/// at `idx` and `idx + 1`.
+fn bounds(reader: &DynamicFastFieldReader<u64>, idx: u64) -> Range<u64> {
+    reader.get_u64(idx)..reader.get_u64(idx + 1)
 }
 
 impl BytesFastFieldReader {
     pub(crate) fn open(
-        idx_reader: BitpackedFastFieldReader<u64>,
+        idx_reader: DynamicFastFieldReader<u64>,
+        val_offsets_reader: Option<DynamicFastFieldReader<u64>>,
         values_file: FileSlice,
+        max_doc: DocId,
     ) -> crate::Result<BytesFastFieldReader> {
         let values = values_file.read_bytes()?;
-        Ok(BytesFastFieldReader { idx_reader, values })
+        Ok(BytesFastFieldReader {
+            idx_reader,
+            val_offsets_reader,
+            values,
+            max_doc,
+        })
+    }
+
+    /// Returns the range of the indexes of the values associated to the given `doc`.
+    fn vals_range(&self, doc: DocId) -> Range<u64> {
+        if self.val_offsets_reader.is_some() {
+            bounds(&self.idx_reader, u64::from(doc))
+        } else {
+            u64::from(doc)..u64::from(doc) + 1
+        }
     }
 
-    fn range(&self, doc: DocId) -> (usize, usize) {
-        let start = self.idx_reader.get(doc) as usize;
-        let stop = self.idx_reader.get(doc + 1) as usize;
-        (start, stop)
+    fn val_offsets_reader(&self) -> &DynamicFastFieldReader<u64> {
+        self.val_offsets_reader.as_ref().unwrap_or(&self.idx_reader)
     }
 
-    /// Returns the bytes associated to the given `doc`
+    /// Returns the bytes of the first value associated to the given `doc`,
+    /// or an empty slice if there are none.
     pub fn get_bytes(&self, doc: DocId) -> &[u8] {
-        let (start, stop) = self.range(doc);
-        &self.values.as_slice()[start..stop]
+        self.get_vals(doc).next().unwrap_or(&[])
+    }
+
+    /// Returns an iterator over the values associated to the given `doc`.
+    ///
+    /// The values are borrowed from the reader and no allocation is made.
+    pub fn get_vals(&self, doc: DocId) -> impl Iterator<Item = &[u8]> {
+        let val_offsets_reader = self.val_offsets_reader();
+        self.vals_range(doc).map(move |val| {
+            let range = bounds(val_offsets_reader, val);
+            &self.values.as_slice()[range.start as usize..range.end as usize]
+        })
+    }
+
+    /// Returns the number of values associated to the given `doc`
+    pub fn num_vals(&self, doc: DocId) -> usize {
+        let range = self.vals_range(doc);
+        (range.end - range.start) as usize
+    }
+
+    /// Returns the overall number of values in this bytes fast field.
+    pub fn total_num_vals(&self) -> u64 {
+        if self.val_offsets_reader.is_some() {
+            self.idx_reader.max_value()
+        } else {
+            u64::from(self.max_doc)
+        }
     }
 
-    /// Returns the length of the bytes associated to the given `doc`
+    /// Returns the overall length of the values associated to the given `doc`
     pub fn num_bytes(&self, doc: DocId) -> usize {
-        let (start, stop) = self.range(doc);
-        stop - start
+        let vals_range = self.vals_range(doc);
+        let val_offsets_reader = self.val_offsets_reader();
+        let start = val_offsets_reader.get_u64(vals_range.start);
+        let stop = val_offsets_reader.get_u64(vals_range.end);
+        (stop - start) as usize
     }
 
     /// Returns the overall number of bytes in this bytes fast field.
@@ -54,9 +113,9 @@ impl BytesFastFieldReader {
 
 impl MultiValueLength for BytesFastFieldReader {
     fn get_len(&self, doc_id: DocId) -> u64 {
-        self.num_bytes(doc_id) as u64
+        self.num_vals(doc_id) as u64
     }
     fn get_total_len(&self) -> u64 {
-        self.total_num_bytes() as u64
+        self.total_num_vals()
     }
 }
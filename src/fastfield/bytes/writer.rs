@@ -1,4 +1,5 @@
 use std::io;
+use std::ops::Range;
 
 use crate::schema::{Document, Field, Value};
 use crate::DocId;
@@ -6,7 +7,7 @@ use crate::{
     fastfield::serializer::CompositeFastFieldSerializer, indexer::doc_id_mapping::DocIdMapping,
 };
 
-/// Writer for byte array (as in, any number of bytes per document) fast fields
+/// Writer for byte array (as in, any number of byte arrays per document) fast fields
 ///
 /// This `BytesFastFieldWriter` is only useful for advanced user.
 /// The normal way to get your associated bytes in your index
@@ -20,10 +21,14 @@ use crate::{
 /// [`.get_bytes_writer(...)`](./struct.FastFieldsWriter.html#method.get_bytes_writer).
 ///
 /// Once acquired, writing is done by calling `.add_document_val(&[u8])`
-/// once per document, even if there are no bytes associated to it.
+/// or `.add_document_vals(&[&[u8]])` once per document,
+/// even if there are no bytes associated to it.
 pub struct BytesFastFieldWriter {
     field: Field,
     vals: Vec<u8>,
+    /// For each value, the offset of its first byte in `vals`.
+    val_index: Vec<u64>,
+    /// For each document, the index of its first value in `val_index`.
     doc_index: Vec<u64>,
 }
 
@@ -33,13 +38,15 @@ impl BytesFastFieldWriter {
         BytesFastFieldWriter {
             field,
             vals: Vec::new(),
+            val_index: Vec::new(),
             doc_index: Vec::new(),
         }
     }
 
     /// The memory used (inclusive childs)
     pub fn mem_usage(&self) -> usize {
-        self.vals.capacity() + self.doc_index.capacity() * std::mem::size_of::<u64>()
+        self.vals.capacity()
+            + (self.val_index.capacity() + self.doc_index.capacity()) * std::mem::size_of::<u64>()
     }
     /// Access the field associated to the `BytesFastFieldWriter`
     pub fn field(&self) -> Field {
@@ -48,7 +55,13 @@ impl BytesFastFieldWriter {
 
     /// Finalize the current document.
     pub(crate) fn next_doc(&mut self) {
-        self.doc_index.push(self.vals.len() as u64);
+        self.doc_index.push(self.val_index.len() as u64);
+    }
+
+    /// Pushes a new value to the current document.
+    fn add_val(&mut self, val: &[u8]) {
+        self.val_index.push(self.vals.len() as u64);
+        self.vals.extend_from_slice(val);
     }
 
     /// Shift to the next document and add all of the
@@ -57,8 +70,7 @@ impl BytesFastFieldWriter {
         self.next_doc();
         for field_value in doc.get_all(self.field) {
             if let Value::Bytes(ref bytes) = field_value {
-                self.vals.extend_from_slice(bytes);
-                return;
+                self.add_val(bytes);
             }
         }
     }
@@ -68,13 +80,23 @@ impl BytesFastFieldWriter {
     /// The method returns the `DocId` of the document that was
     /// just written.
     pub fn add_document_val(&mut self, val: &[u8]) -> DocId {
+        self.add_document_vals(&[val])
+    }
+
+    /// Register all of the values associated to a document.
+    ///
+    /// The method returns the `DocId` of the document that was
+    /// just written.
+    pub fn add_document_vals(&mut self, vals: &[&[u8]]) -> DocId {
         let doc = self.doc_index.len() as DocId;
         self.next_doc();
-        self.vals.extend_from_slice(val);
+        for val in vals {
+            self.add_val(val);
+        }
         doc
     }
 
-    /// Returns an iterator over values per doc_id in ascending doc_id order.
+    /// Returns an iterator over the values of the docs in ascending doc_id order.
     ///
     /// Normally the order is simply iterating self.doc_id_index.
     /// With doc_id_map it accounts for the new mapping, returning values in the order of the
@@ -82,7 +104,7 @@ impl BytesFastFieldWriter {
     fn get_ordered_values<'a: 'b, 'b>(
         &'a self,
         doc_id_map: Option<&'b DocIdMapping>,
-    ) -> impl Iterator<Item = &'b [u8]> {
+    ) -> impl Iterator<Item = Range<usize>> + 'b {
         let doc_id_iter: Box<dyn Iterator<Item = u32>> = if let Some(doc_id_map) = doc_id_map {
             Box::new(doc_id_map.iter_old_doc_ids())
         } else {
@@ -92,15 +114,26 @@ impl BytesFastFieldWriter {
         doc_id_iter.map(move |doc_id| self.get_values_for_doc_id(doc_id))
     }
 
-    /// returns all values for a doc_ids
-    fn get_values_for_doc_id(&self, doc_id: u32) -> &[u8] {
+    /// returns the range of the indexes in `val_index` of the values of a doc_id
+    fn get_values_for_doc_id(&self, doc_id: u32) -> Range<usize> {
         let start_pos = self.doc_index[doc_id as usize] as usize;
         let end_pos = self
             .doc_index
             .get(doc_id as usize + 1)
             .cloned()
-            .unwrap_or(self.vals.len() as u64) as usize; // special case, last doc_id has no offset information
-        &self.vals[start_pos..end_pos]
+            .unwrap_or(self.val_index.len() as u64) as usize; // special case, last doc_id has no offset information
+        start_pos..end_pos
+    }
+
+    /// returns the bytes of the values in the given range of `val_index`
+    fn get_bytes_for_values(&self, vals: Range<usize>) -> &[u8] {
+        let offset = |val: usize| {
+            self.val_index
+                .get(val)
+                .cloned()
+                .unwrap_or(self.vals.len() as u64) as usize
+        };
+        &self.vals[offset(vals.start)..offset(vals.end)]
     }
 
     /// Serializes the fast field values by pushing them to the `FastFieldSerializer`.
@@ -109,23 +142,36 @@ impl BytesFastFieldWriter {
         serializer: &mut CompositeFastFieldSerializer,
         doc_id_map: Option<&DocIdMapping>,
     ) -> io::Result<()> {
-        // writing the offset index
+        // writing the doc index
+        let num_vals = self.val_index.len() as u64;
         let mut doc_index_serializer =
-            serializer.new_u64_fast_field_with_idx(self.field, 0, self.vals.len() as u64, 0)?;
-        let mut offset = 0;
+            serializer.new_u64_fast_field_with_idx(self.field, 0, num_vals, 0)?;
+        let mut val_idx = 0;
         for vals in self.get_ordered_values(doc_id_map) {
-            doc_index_serializer.add_val(offset)?;
-            offset += vals.len() as u64;
+            doc_index_serializer.add_val(val_idx)?;
+            val_idx += vals.len() as u64;
         }
-        doc_index_serializer.add_val(self.vals.len() as u64)?;
+        doc_index_serializer.add_val(num_vals)?;
         doc_index_serializer.close_field()?;
+        // writing the value offsets
+        let mut val_index_serializer =
+            serializer.new_u64_fast_field_with_idx(self.field, 0, self.vals.len() as u64, 2)?;
+        let mut offset = 0;
+        for vals in self.get_ordered_values(doc_id_map) {
+            for val in vals {
+                val_index_serializer.add_val(offset)?;
+                offset += self.get_bytes_for_values(val..val + 1).len() as u64;
+            }
+        }
+        val_index_serializer.add_val(self.vals.len() as u64)?;
+        val_index_serializer.close_field()?;
         // writing the values themselves
         let mut value_serializer = serializer.new_bytes_fast_field_with_idx(self.field, 1);
         // the else could be removed, but this is faster (difference not benchmarked)
         if let Some(doc_id_map) = doc_id_map {
             for vals in self.get_ordered_values(Some(doc_id_map)) {
                 // sort values in case of remapped doc_ids?
-                value_serializer.write_all(vals)?;
+                value_serializer.write_all(self.get_bytes_for_values(vals))?;
             }
         } else {
             value_serializer.write_all(&self.vals)?;
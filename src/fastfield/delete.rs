@@ -1,16 +1,64 @@
 use crate::directory::FileSlice;
-use crate::directory::OwnedBytes;
 use crate::directory::WritePtr;
+use crate::error::DataCorruption;
 use crate::space_usage::ByteCount;
 use crate::DocId;
-use common::BitSet;
 use common::HasLen;
+use common::{read_u32_vint, write_u32_vint, BitSet, TinySet};
+use crc32fast::Hasher;
 use std::io;
 use std::io::Write;
+use std::iter;
+use std::sync::Arc;
+
+// Delete files are serialized as `[format tag][payload][crc32 of tag and payload]`.
+// Files written by older versions of tantivy have neither a format tag nor a checksum.
+//
+// A legacy file is a plain bitset, and any file may happen to look like one. Legacy
+// files are therefore only accepted from indexes written before
+// `TAGGED_INDEX_FORMAT_VERSION`, and only when the format tag and the checksum do
+// not validate.
+
+/// First index format version whose delete files always have a format tag and a checksum.
+pub(crate) const TAGGED_INDEX_FORMAT_VERSION: u32 = 6;
+
+/// Format tag of a delete file storing a plain bitset.
+const DENSE_FORMAT_TAG: u8 = 1u8;
+/// Format tag of a delete file storing the sorted list of deleted docs,
+/// delta-encoded as vints.
+const SPARSE_FORMAT_TAG: u8 = 2u8;
+
+const CHECKSUM_NUM_BYTES: usize = 4;
+
+/// The sparse format is only selected if it is at least
+/// `SPARSE_SIZE_RATIO` times smaller than the dense format.
+const SPARSE_SIZE_RATIO: usize = 4;
+
+/// Number of bytes of a delete file written using the legacy (untagged) dense format.
+fn num_dense_bytes(max_doc: DocId) -> usize {
+    (max_doc as usize + 7) / 8
+}
+
+fn deleted_docs(delete_bitset: &BitSet, max_doc: u32) -> impl Iterator<Item = DocId> + '_ {
+    let num_buckets = (max_doc + 63) / 64;
+    (0..num_buckets)
+        .flat_map(move |bucket| {
+            delete_bitset
+                .tinyset(bucket)
+                .into_iter()
+                .map(move |lower| bucket * 64 + lower)
+        })
+        .take_while(move |&doc| doc < max_doc)
+}
 
 /// Write a delete `BitSet`
 ///
 /// where `delete_bitset` is the set of deleted `DocId`.
+///
+/// Depending on the density of deleted documents, the bitset is either
+/// serialized as a plain bitset, or as the sorted list of deleted `DocId`s.
+/// A CRC32 checksum is appended, and verified when the bitset is opened.
+///
 /// Warning: this function does not call terminate. The caller is in charge of
 /// closing the writer properly.
 pub fn write_delete_bitset(
@@ -18,31 +66,155 @@ pub fn write_delete_bitset(
     max_doc: u32,
     writer: &mut WritePtr,
 ) -> io::Result<()> {
-    let mut byte = 0u8;
-    let mut shift = 0u8;
-    for doc in 0..max_doc {
-        if delete_bitset.contains(doc) {
-            byte |= 1 << shift;
-        }
-        if shift == 7 {
-            writer.write_all(&[byte])?;
-            shift = 0;
-            byte = 0;
-        } else {
-            shift += 1;
+    let num_dense_bytes = num_dense_bytes(max_doc);
+    let mut sparse_bytes: Vec<u8> = Vec::new();
+    let mut previous_doc = 0u32;
+    for doc in deleted_docs(delete_bitset, max_doc) {
+        write_u32_vint(doc - previous_doc, &mut sparse_bytes)?;
+        previous_doc = doc;
+        if sparse_num_bytes(&sparse_bytes) > num_dense_bytes {
+            break;
         }
     }
-    if max_doc % 8 > 0 {
-        writer.write_all(&[byte])?;
+    let mut hasher = Hasher::new();
+    let mut write = |bytes: &[u8]| -> io::Result<()> {
+        hasher.update(bytes);
+        writer.write_all(bytes)
+    };
+    if sparse_num_bytes(&sparse_bytes) <= num_dense_bytes {
+        write(&[SPARSE_FORMAT_TAG])?;
+        write(&sparse_bytes[..])?;
+    } else {
+        write(&[DENSE_FORMAT_TAG])?;
+        let mut byte = 0u8;
+        let mut shift = 0u8;
+        for doc in 0..max_doc {
+            if delete_bitset.contains(doc) {
+                byte |= 1 << shift;
+            }
+            if shift == 7 {
+                write(&[byte])?;
+                shift = 0;
+                byte = 0;
+            } else {
+                shift += 1;
+            }
+        }
+        if max_doc % 8 > 0 {
+            write(&[byte])?;
+        }
     }
+    let checksum = hasher.finalize();
+    writer.write_all(&checksum.to_le_bytes())?;
     Ok(())
 }
 
+/// Size of the sparse delete file, scaled by `SPARSE_SIZE_RATIO`, so that it
+/// can be compared with the size of the legacy dense format.
+fn sparse_num_bytes(sparse_bytes: &[u8]) -> usize {
+    (1 + sparse_bytes.len() + CHECKSUM_NUM_BYTES) * SPARSE_SIZE_RATIO
+}
+
+#[derive(Clone)]
+enum DeleteBitSetRepr {
+    /// One bit per document, packed in words.
+    Dense(Arc<[u64]>),
+    /// Sorted list of the deleted documents.
+    Sparse(Arc<[DocId]>),
+}
+
 /// Set of deleted `DocId`s.
+///
+/// Delete files are written either as a plain bitset, or as the sorted list of
+/// deleted documents when there are very few of them.
+///
+/// Once opened, a dense bitset is copied into a buffer of `u64` words, so that
+/// lookups and batch operations work on aligned words. A sparse bitset is kept
+/// as a sorted list, unless it would use more memory than its dense
+/// counterpart.
 #[derive(Clone)]
 pub struct DeleteBitSet {
-    data: OwnedBytes,
+    repr: DeleteBitSetRepr,
+    max_doc: DocId,
     num_deleted: usize,
+    num_bytes: usize,
+}
+
+fn bytes_to_words(bytes: &[u8]) -> Vec<u64> {
+    bytes
+        .chunks(8)
+        .map(|chunk| {
+            let mut buf = [0u8; 8];
+            buf[..chunk.len()].copy_from_slice(chunk);
+            u64::from_le_bytes(buf)
+        })
+        .collect()
+}
+
+fn num_words(max_doc: DocId) -> usize {
+    (max_doc as usize + 63) / 64
+}
+
+fn docs_to_words(docs: &[DocId], max_doc: DocId) -> Vec<u64> {
+    let mut words = vec![0u64; num_words(max_doc)];
+    for &doc in docs {
+        words[(doc / 64) as usize] |= 1u64 << (doc % 64);
+    }
+    words
+}
+
+/// Checks the format tag and, if `verify_checksum` is set, the checksum of a
+/// delete file.
+///
+/// Returns the tag followed by the payload, or the reason why the file is invalid.
+fn validate_tagged(bytes: &[u8], max_doc: DocId, verify_checksum: bool) -> Result<&[u8], String> {
+    let num_bytes = bytes.len();
+    if num_bytes < 1 + CHECKSUM_NUM_BYTES {
+        return Err(format!(
+            "Delete bitset is too short (len={}, max_doc={})",
+            num_bytes, max_doc
+        ));
+    }
+    let (data, checksum_bytes) = bytes.split_at(num_bytes - CHECKSUM_NUM_BYTES);
+    if data[0] != DENSE_FORMAT_TAG && data[0] != SPARSE_FORMAT_TAG {
+        return Err(format!(
+            "Unknown delete bitset format (len={}, max_doc={})",
+            num_bytes, max_doc
+        ));
+    }
+    if verify_checksum {
+        let mut checksum_buf = [0u8; CHECKSUM_NUM_BYTES];
+        checksum_buf.copy_from_slice(checksum_bytes);
+        let expected_checksum = u32::from_le_bytes(checksum_buf);
+        let mut hasher = Hasher::new();
+        hasher.update(data);
+        let checksum = hasher.finalize();
+        if checksum != expected_checksum {
+            return Err(format!(
+                "Delete bitset checksum mismatch (expected={}, actual={})",
+                expected_checksum, checksum
+            ));
+        }
+    }
+    Ok(data)
+}
+
+fn decode_sparse(mut data: &[u8], max_doc: DocId) -> crate::Result<Vec<DocId>> {
+    let mut docs = Vec::new();
+    let mut doc = 0u32;
+    while !data.is_empty() {
+        doc += read_u32_vint(&mut data);
+        if doc >= max_doc || (!docs.is_empty() && docs.last() == Some(&doc)) {
+            return Err(crate::TantivyError::DataCorruption(
+                DataCorruption::comment_only(format!(
+                    "Invalid deleted doc {} in sparse delete bitset (max_doc={})",
+                    doc, max_doc
+                )),
+            ));
+        }
+        docs.push(doc);
+    }
+    Ok(docs)
 }
 
 impl DeleteBitSet {
@@ -61,23 +233,194 @@ impl DeleteBitSet {
         write_delete_bitset(&bitset, max_doc, &mut wrt).unwrap();
         wrt.terminate().unwrap();
         let file = directory.open_read(path).unwrap();
-        Self::open(file).unwrap()
+        Self::open(file, max_doc).unwrap()
+    }
+
+    /// Opens a delete bitset given its file and the `max_doc` of its segment.
+    ///
+    /// The file is expected to be written by this version of tantivy. Returns a
+    /// `DataCorruption` error if its format tag is unknown, or if its checksum does
+    /// not match its content.
+    pub fn open(file: FileSlice, max_doc: DocId) -> crate::Result<DeleteBitSet> {
+        Self::open_with_checksum_check(file, max_doc, true, false)
     }
 
-    /// Opens a delete bitset given its file.
-    pub fn open(file: FileSlice) -> crate::Result<DeleteBitSet> {
+    /// Opens a delete bitset written with the given index format version.
+    ///
+    /// Before `TAGGED_INDEX_FORMAT_VERSION`, a file whose format tag and checksum do
+    /// not validate is read as a legacy bitset if it has the legacy size.
+    pub(crate) fn open_with_index_format_version(
+        file: FileSlice,
+        max_doc: DocId,
+        index_format_version: u32,
+    ) -> crate::Result<DeleteBitSet> {
+        let accept_legacy = index_format_version < TAGGED_INDEX_FORMAT_VERSION;
+        Self::open_with_checksum_check(file, max_doc, true, accept_legacy)
+    }
+
+    /// Opens a delete bitset without verifying its checksum.
+    ///
+    /// This is meant for recovery tooling. The resulting bitset may be wrong.
+    /// A file of the legacy size is read as a legacy bitset, as a file in the
+    /// tagged format never has that size.
+    pub fn open_lenient(file: FileSlice, max_doc: DocId) -> crate::Result<DeleteBitSet> {
+        Self::open_with_checksum_check(file, max_doc, false, true)
+    }
+
+    fn open_with_checksum_check(
+        file: FileSlice,
+        max_doc: DocId,
+        verify_checksum: bool,
+        accept_legacy: bool,
+    ) -> crate::Result<DeleteBitSet> {
         let bytes = file.read_bytes()?;
-        let num_deleted: usize = bytes
-            .as_slice()
-            .iter()
-            .map(|b| b.count_ones() as usize)
-            .sum();
+        let num_bytes = bytes.len();
+        let is_legacy_size = num_bytes == num_dense_bytes(max_doc);
+        let tagged_data = if !verify_checksum && accept_legacy && is_legacy_size {
+            Err(format!(
+                "Delete bitset has the legacy size (len={})",
+                num_bytes
+            ))
+        } else {
+            validate_tagged(bytes.as_slice(), max_doc, verify_checksum)
+        };
+        let repr = match tagged_data {
+            Ok(data) => match data[0] {
+                DENSE_FORMAT_TAG => DeleteBitSetRepr::Dense(bytes_to_words(&data[1..]).into()),
+                _ => {
+                    let docs = decode_sparse(&data[1..], max_doc)?;
+                    // The sorted list uses 32 bits per deleted doc.
+                    if docs.len() * 32 > max_doc as usize {
+                        DeleteBitSetRepr::Dense(docs_to_words(&docs, max_doc).into())
+                    } else {
+                        DeleteBitSetRepr::Sparse(docs.into())
+                    }
+                }
+            },
+            Err(_) if accept_legacy && is_legacy_size => {
+                // Legacy format, without any format tag.
+                DeleteBitSetRepr::Dense(bytes_to_words(bytes.as_slice()).into())
+            }
+            Err(comment) => {
+                return Err(crate::TantivyError::DataCorruption(
+                    DataCorruption::comment_only(comment),
+                ));
+            }
+        };
+        let num_deleted: usize = match &repr {
+            DeleteBitSetRepr::Dense(words) => {
+                words.iter().map(|word| word.count_ones() as usize).sum()
+            }
+            DeleteBitSetRepr::Sparse(docs) => docs.len(),
+        };
         Ok(DeleteBitSet {
-            data: bytes,
+            repr,
+            max_doc,
             num_deleted,
+            num_bytes,
         })
     }
 
+    /// Creates an in-memory bitset from its words.
+    fn from_words(words: Vec<u64>, max_doc: DocId) -> DeleteBitSet {
+        let num_deleted: usize = words.iter().map(|word| word.count_ones() as usize).sum();
+        DeleteBitSet {
+            repr: DeleteBitSetRepr::Dense(words.into()),
+            max_doc,
+            num_deleted,
+            num_bytes: num_dense_bytes(max_doc),
+        }
+    }
+
+    /// Sets the bits of the deleted documents in `words`.
+    ///
+    /// `words` is expected to be large enough to hold all of
+    /// this bitset's documents.
+    fn union_into(&self, words: &mut [u64]) {
+        match &self.repr {
+            DeleteBitSetRepr::Dense(self_words) => {
+                for (word, self_word) in words.iter_mut().zip(self_words.iter()) {
+                    *word |= *self_word;
+                }
+            }
+            DeleteBitSetRepr::Sparse(docs) => {
+                for &doc in docs.iter() {
+                    words[(doc / 64) as usize] |= 1u64 << (doc % 64);
+                }
+            }
+        }
+    }
+
+    /// Returns the union of all of the given bitsets, computed in one pass.
+    ///
+    /// Bitsets may have different `max_doc`. The shorter ones are padded with
+    /// alive documents, and the result has the largest `max_doc`.
+    pub fn union_all(delete_bitsets: &[&DeleteBitSet]) -> DeleteBitSet {
+        let max_doc = delete_bitsets
+            .iter()
+            .map(|delete_bitset| delete_bitset.max_doc)
+            .max()
+            .unwrap_or(0u32);
+        let mut words = vec![0u64; num_words(max_doc)];
+        for delete_bitset in delete_bitsets {
+            delete_bitset.union_into(&mut words[..]);
+        }
+        DeleteBitSet::from_words(words, max_doc)
+    }
+
+    /// Returns the set of documents deleted in `self`, but not in `other`.
+    ///
+    /// The result has the same `max_doc` as `self`.
+    pub fn difference(&self, other: &DeleteBitSet) -> DeleteBitSet {
+        let words: Vec<u64> = (0..num_words(self.max_doc))
+            .map(|word_id| self.word(word_id) & !other.word(word_id))
+            .collect();
+        DeleteBitSet::from_words(words, self.max_doc)
+    }
+
+    /// Returns the set of documents deleted both in `self` and in `other`.
+    ///
+    /// The result has the same `max_doc` as `self`.
+    pub fn intersection(&self, other: &DeleteBitSet) -> DeleteBitSet {
+        let words: Vec<u64> = (0..num_words(self.max_doc))
+            .map(|word_id| self.word(word_id) & other.word(word_id))
+            .collect();
+        DeleteBitSet::from_words(words, self.max_doc)
+    }
+
+    /// Returns the word containing the deleted bits of the documents
+    /// `[word_id * 64, (word_id + 1) * 64)`.
+    ///
+    /// Documents beyond the end of the bitset are considered alive.
+    #[inline]
+    fn word(&self, word_id: usize) -> u64 {
+        match &self.repr {
+            DeleteBitSetRepr::Dense(words) => words.get(word_id).cloned().unwrap_or(0u64),
+            DeleteBitSetRepr::Sparse(docs) => {
+                let word_start = word_id as u64 * 64;
+                let start = docs.partition_point(|&doc| u64::from(doc) < word_start);
+                docs[start..]
+                    .iter()
+                    .take_while(|&&doc| u64::from(doc) < word_start + 64)
+                    .fold(0u64, |word, &doc| word | (1u64 << (doc % 64)))
+            }
+        }
+    }
+
+    /// Returns the id of the first word >= `word_id` that may contain
+    /// a deleted document.
+    fn next_word_with_deletes(&self, word_id: u32) -> u32 {
+        match &self.repr {
+            DeleteBitSetRepr::Dense(_) => word_id,
+            DeleteBitSetRepr::Sparse(docs) => {
+                let start = docs.partition_point(|&doc| u64::from(doc) < u64::from(word_id) * 64);
+                docs.get(start)
+                    .map(|&doc| doc / 64)
+                    .unwrap_or(u32::MAX / 64 + 1)
+            }
+        }
+    }
+
     /// Returns true iff the document is still "alive". In other words, if it has not been deleted.
     pub fn is_alive(&self, doc: DocId) -> bool {
         !self.is_deleted(doc)
@@ -86,10 +429,92 @@ impl DeleteBitSet {
     /// Returns true iff the document has been marked as deleted.
     #[inline]
     pub fn is_deleted(&self, doc: DocId) -> bool {
-        let byte_offset = doc / 8u32;
-        let b: u8 = self.data.as_slice()[byte_offset as usize];
-        let shift = (doc & 7u32) as u8;
-        b & (1u8 << shift) != 0
+        match &self.repr {
+            DeleteBitSetRepr::Dense(words) => {
+                let word: u64 = words[(doc / 64u32) as usize];
+                word & (1u64 << (doc % 64u32)) != 0
+            }
+            DeleteBitSetRepr::Sparse(docs) => docs.binary_search(&doc).is_ok(),
+        }
+    }
+
+    /// Returns the number of alive documents within `[start, end)`.
+    pub fn num_alive_in_range(&self, start: DocId, end: DocId) -> u32 {
+        if start >= end {
+            return 0;
+        }
+        let num_deleted = match &self.repr {
+            DeleteBitSetRepr::Dense(_) => {
+                let first_word = (start / 64u32) as usize;
+                let last_word = ((end - 1) / 64u32) as usize;
+                let mut num_deleted = 0u32;
+                for word_id in first_word..=last_word {
+                    let mut word = self.word(word_id);
+                    if word_id == first_word {
+                        word &= !0u64 << (start % 64u32);
+                    }
+                    if word_id == last_word {
+                        let num_bits = end - last_word as u32 * 64u32;
+                        if num_bits < 64 {
+                            word &= (1u64 << num_bits) - 1;
+                        }
+                    }
+                    num_deleted += word.count_ones();
+                }
+                num_deleted
+            }
+            DeleteBitSetRepr::Sparse(docs) => {
+                let start_idx = docs.partition_point(|&doc| doc < start);
+                let end_idx = docs.partition_point(|&doc| doc < end);
+                (end_idx - start_idx) as u32
+            }
+        };
+        end - start - num_deleted
+    }
+
+    /// Returns the smallest alive `DocId` greater or equal to `doc`.
+    ///
+    /// The returned value may be greater or equal to `max_doc`.
+    /// In that case, all of the documents within `[doc, max_doc)`
+    /// are deleted.
+    pub fn first_alive_after(&self, doc: DocId) -> DocId {
+        let mut word_id = (doc / 64u32) as usize;
+        let mut alive = !self.word(word_id) & (!0u64 << (doc % 64u32));
+        while alive == 0 {
+            word_id += 1;
+            alive = !self.word(word_id);
+        }
+        word_id as u32 * 64u32 + alive.trailing_zeros()
+    }
+
+    /// Removes all of the deleted documents from `bitset`.
+    ///
+    /// In other words, `bitset` is intersected with the set of alive documents.
+    pub fn intersect_with_bitset(&self, bitset: &mut BitSet) {
+        match &self.repr {
+            DeleteBitSetRepr::Dense(words) => {
+                let alive_tinysets = words
+                    .iter()
+                    .map(|&word| TinySet::from_u64(!word))
+                    .chain(iter::repeat(TinySet::full()));
+                bitset.intersect_update(alive_tinysets);
+            }
+            DeleteBitSetRepr::Sparse(_) => {
+                let alive_tinysets = (0..).map(|word_id| TinySet::from_u64(!self.word(word_id)));
+                bitset.intersect_update(alive_tinysets);
+            }
+        }
+    }
+
+    /// Returns an iterator over the deleted `DocId`s, in increasing order.
+    pub fn iter_deleted(&self) -> DocIdIter<'_> {
+        DocIdIter::new(self, false, self.max_doc)
+    }
+
+    /// Returns an iterator over the alive `DocId`s within `[0, max_doc)`,
+    /// in increasing order.
+    pub fn iter_alive(&self, max_doc: DocId) -> DocIdIter<'_> {
+        DocIdIter::new(self, true, max_doc)
     }
 
     /// The number of deleted docs
@@ -98,7 +523,65 @@ impl DeleteBitSet {
     }
     /// Summarize total space usage of this bitset.
     pub fn space_usage(&self) -> ByteCount {
-        self.data.len()
+        self.num_bytes
+    }
+}
+
+/// Iterator over the deleted (or alive) `DocId`s of a `DeleteBitSet`.
+///
+/// The bitset is processed one 64-bit word at a time, so that
+/// words without any matching document are skipped in one go.
+pub struct DocIdIter<'a> {
+    delete_bitset: &'a DeleteBitSet,
+    invert: bool,
+    max_doc: DocId,
+    // index of the next word to load.
+    next_word: u32,
+    // remaining bits of the current word, relative to `(next_word - 1) * 64`.
+    current: u64,
+}
+
+impl<'a> DocIdIter<'a> {
+    fn new(delete_bitset: &'a DeleteBitSet, invert: bool, max_doc: DocId) -> DocIdIter<'a> {
+        DocIdIter {
+            delete_bitset,
+            invert,
+            max_doc,
+            next_word: 0,
+            current: 0u64,
+        }
+    }
+
+    fn load_word(&self, word_id: u32) -> u64 {
+        let mut bits = self.delete_bitset.word(word_id as usize);
+        if self.invert {
+            bits = !bits;
+        }
+        let num_bits_remaining = self.max_doc - word_id * 64;
+        if num_bits_remaining < 64 {
+            bits &= (1u64 << num_bits_remaining) - 1;
+        }
+        bits
+    }
+}
+
+impl<'a> Iterator for DocIdIter<'a> {
+    type Item = DocId;
+
+    fn next(&mut self) -> Option<DocId> {
+        while self.current == 0 {
+            if !self.invert {
+                self.next_word = self.delete_bitset.next_word_with_deletes(self.next_word);
+            }
+            if u64::from(self.next_word) * 64 >= u64::from(self.max_doc) {
+                return None;
+            }
+            self.current = self.load_word(self.next_word);
+            self.next_word += 1;
+        }
+        let lowest = self.current.trailing_zeros();
+        self.current &= self.current - 1;
+        Some((self.next_word - 1) * 64 + lowest)
     }
 }
 
@@ -110,8 +593,9 @@ impl HasLen for DeleteBitSet {
 
 #[cfg(test)]
 mod tests {
-    use super::DeleteBitSet;
-    use common::HasLen;
+    use super::{write_delete_bitset, DeleteBitSet, TAGGED_INDEX_FORMAT_VERSION};
+    use crate::directory::FileSlice;
+    use common::{BitSet, HasLen};
 
     #[test]
     fn test_delete_bitset_empty() {
@@ -141,4 +625,251 @@ mod tests {
         }
         assert_eq!(delete_bitset.len(), 2);
     }
+
+    #[test]
+    fn test_delete_bitset_iter_empty() {
+        let delete_bitset = DeleteBitSet::for_test(&[], 0);
+        assert_eq!(delete_bitset.iter_deleted().next(), None);
+        assert_eq!(delete_bitset.iter_alive(0).next(), None);
+        let delete_bitset = DeleteBitSet::for_test(&[], 10);
+        assert_eq!(delete_bitset.iter_deleted().next(), None);
+        assert_eq!(
+            delete_bitset.iter_alive(10).collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_delete_bitset_iter() {
+        for &max_doc in &[1u32, 7, 8, 9, 63, 64, 65, 130, 1_000] {
+            let deleted: Vec<u32> = (0..max_doc).filter(|doc| doc % 3 == 1).collect();
+            let delete_bitset = DeleteBitSet::for_test(&deleted, max_doc);
+            assert_eq!(delete_bitset.iter_deleted().collect::<Vec<_>>(), deleted);
+            let alive: Vec<u32> = (0..max_doc).filter(|doc| doc % 3 != 1).collect();
+            assert_eq!(delete_bitset.iter_alive(max_doc).collect::<Vec<_>>(), alive);
+        }
+    }
+
+    #[test]
+    fn test_delete_bitset_iter_all_deleted() {
+        let deleted: Vec<u32> = (0..133).collect();
+        let delete_bitset = DeleteBitSet::for_test(&deleted, 133);
+        assert_eq!(delete_bitset.iter_alive(133).next(), None);
+        assert_eq!(delete_bitset.iter_deleted().collect::<Vec<_>>(), deleted);
+    }
+
+    #[test]
+    fn test_delete_bitset_num_alive_in_range() {
+        for &max_doc in &[1u32, 7, 9, 63, 64, 65, 127, 129, 300] {
+            let deleted: Vec<u32> = (0..max_doc).filter(|doc| doc % 5 == 2).collect();
+            let delete_bitset = DeleteBitSet::for_test(&deleted, max_doc);
+            for start in 0..max_doc {
+                for &end in &[start, start + 1, (start + 70).min(max_doc), max_doc] {
+                    let expected = (start..end)
+                        .filter(|&doc| delete_bitset.is_alive(doc))
+                        .count() as u32;
+                    assert_eq!(delete_bitset.num_alive_in_range(start, end), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_delete_bitset_first_alive_after() {
+        let max_doc = 200u32;
+        let deleted: Vec<u32> = (3..140).chain(150..200).collect();
+        let delete_bitset = DeleteBitSet::for_test(&deleted, max_doc);
+        assert_eq!(delete_bitset.first_alive_after(0), 0);
+        assert_eq!(delete_bitset.first_alive_after(2), 2);
+        assert_eq!(delete_bitset.first_alive_after(3), 140);
+        assert_eq!(delete_bitset.first_alive_after(140), 140);
+        assert_eq!(delete_bitset.first_alive_after(145), 145);
+        assert!(delete_bitset.first_alive_after(150) >= max_doc);
+    }
+
+    #[test]
+    fn test_delete_bitset_intersect_with_bitset() {
+        for &max_doc in &[5u32, 8, 63, 64, 65, 1_000] {
+            let deleted: Vec<u32> = (0..max_doc).filter(|doc| doc % 3 == 0).collect();
+            let delete_bitset = DeleteBitSet::for_test(&deleted, max_doc);
+            let mut bitset = BitSet::with_max_value(max_doc);
+            for doc in (0..max_doc).filter(|doc| doc % 2 == 0) {
+                bitset.insert(doc);
+            }
+            delete_bitset.intersect_with_bitset(&mut bitset);
+            let expected: Vec<u32> = (0..max_doc)
+                .filter(|doc| doc % 2 == 0 && doc % 3 != 0)
+                .collect();
+            for doc in 0..max_doc {
+                assert_eq!(bitset.contains(doc), expected.contains(&doc));
+            }
+            assert_eq!(bitset.len(), expected.len());
+        }
+    }
+
+    fn open_raw(data: &[u8], max_doc: u32) -> crate::Result<DeleteBitSet> {
+        DeleteBitSet::open(FileSlice::from(data.to_vec()), max_doc)
+    }
+
+    fn open_legacy(data: &[u8], max_doc: u32) -> crate::Result<DeleteBitSet> {
+        DeleteBitSet::open_with_index_format_version(
+            FileSlice::from(data.to_vec()),
+            max_doc,
+            TAGGED_INDEX_FORMAT_VERSION - 1,
+        )
+    }
+
+    #[test]
+    fn test_delete_bitset_legacy_format() {
+        let data = [0b0000_0010, 0b0000_0010];
+        let delete_bitset = open_legacy(&data, 10).unwrap();
+        assert_eq!(delete_bitset.iter_deleted().collect::<Vec<_>>(), vec![1, 9]);
+        assert_eq!(delete_bitset.len(), 2);
+        assert_eq!(delete_bitset.space_usage(), 2);
+        assert!(matches!(
+            open_raw(&data, 10),
+            Err(crate::TantivyError::DataCorruption(_))
+        ));
+        let lenient_delete_bitset =
+            DeleteBitSet::open_lenient(FileSlice::from(data.to_vec()), 10).unwrap();
+        assert_eq!(lenient_delete_bitset.len(), 2);
+    }
+
+    #[test]
+    fn test_delete_bitset_tagged_format_in_legacy_index() {
+        let data = serialize_for_test(&[1, 9], 10);
+        let delete_bitset = open_legacy(&data, 10).unwrap();
+        assert_eq!(delete_bitset.iter_deleted().collect::<Vec<_>>(), vec![1, 9]);
+    }
+
+    #[test]
+    fn test_delete_bitset_truncated_to_legacy_size() {
+        let max_doc = 1_000;
+        let deleted: Vec<u32> = (0..max_doc).step_by(3).collect();
+        let data = serialize_for_test(&deleted, max_doc);
+        let legacy_num_bytes = (max_doc as usize + 7) / 8;
+        assert!(data.len() > legacy_num_bytes);
+        let truncated_data = &data[..legacy_num_bytes];
+        assert!(matches!(
+            open_raw(truncated_data, max_doc),
+            Err(crate::TantivyError::DataCorruption(_))
+        ));
+    }
+
+    #[test]
+    fn test_delete_bitset_sparse_format() {
+        let max_doc = 20_000_000;
+        let delete_bitset = DeleteBitSet::for_test(&[3, 1_000_000, 19_999_999], max_doc);
+        assert!(delete_bitset.space_usage() < 20);
+        assert_eq!(delete_bitset.len(), 3);
+        assert!(delete_bitset.is_deleted(3));
+        assert!(delete_bitset.is_alive(4));
+        assert!(delete_bitset.is_deleted(19_999_999));
+        assert_eq!(
+            delete_bitset.iter_deleted().collect::<Vec<_>>(),
+            vec![3, 1_000_000, 19_999_999]
+        );
+        assert_eq!(delete_bitset.num_alive_in_range(0, max_doc), max_doc - 3);
+        assert_eq!(delete_bitset.first_alive_after(3), 4);
+        assert_eq!(
+            delete_bitset.iter_alive(6).collect::<Vec<_>>(),
+            vec![0, 1, 2, 4, 5]
+        );
+    }
+
+    #[test]
+    fn test_delete_bitset_sparse_and_dense_agree() {
+        for &max_doc in &[0u32, 1, 63, 100, 1_000, 10_000] {
+            for &step in &[1u32, 7, 100, 3_000] {
+                let deleted: Vec<u32> = (0..max_doc).step_by(step as usize).collect();
+                let delete_bitset = DeleteBitSet::for_test(&deleted, max_doc);
+                assert_eq!(delete_bitset.len(), deleted.len());
+                assert_eq!(delete_bitset.iter_deleted().collect::<Vec<_>>(), deleted);
+                for doc in 0..max_doc {
+                    assert_eq!(delete_bitset.is_deleted(doc), doc % step == 0);
+                }
+                let mut bitset = BitSet::with_max_value(max_doc);
+                for doc in 0..max_doc {
+                    bitset.insert(doc);
+                }
+                delete_bitset.intersect_with_bitset(&mut bitset);
+                assert_eq!(bitset.len(), max_doc as usize - deleted.len());
+            }
+        }
+    }
+
+    fn serialize_for_test(docs: &[u32], max_doc: u32) -> Vec<u8> {
+        use crate::directory::{Directory, RamDirectory, TerminatingWrite};
+        use std::path::Path;
+        let mut bitset = BitSet::with_max_value(max_doc);
+        for &doc in docs {
+            bitset.insert(doc);
+        }
+        let directory = RamDirectory::create();
+        let path = Path::new("dummydeletebitset");
+        let mut wrt = directory.open_write(path).unwrap();
+        write_delete_bitset(&bitset, max_doc, &mut wrt).unwrap();
+        wrt.terminate().unwrap();
+        directory.atomic_read(path).unwrap()
+    }
+
+    #[test]
+    fn test_delete_bitset_checksum_mismatch() {
+        for &(docs, max_doc) in &[(&[1u32, 9][..], 10u32), (&[3u32][..], 100_000u32)] {
+            let mut data = serialize_for_test(docs, max_doc);
+            assert!(open_raw(&data, max_doc).is_ok());
+            let mid = data.len() / 2;
+            data[mid] ^= 0b0000_0100;
+            assert!(matches!(
+                open_raw(&data, max_doc),
+                Err(crate::TantivyError::DataCorruption(_))
+            ));
+            assert!(DeleteBitSet::open_lenient(FileSlice::from(data), max_doc).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_delete_bitset_unknown_format() {
+        let mut data = vec![17u8, 0u8, 0u8];
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&data);
+        data.extend_from_slice(&hasher.finalize().to_le_bytes());
+        assert!(matches!(
+            open_raw(&data, 10),
+            Err(crate::TantivyError::DataCorruption(_))
+        ));
+    }
+
+    #[test]
+    fn test_delete_bitset_union_all() {
+        let left = DeleteBitSet::for_test(&[1, 5, 70], 100);
+        let right = DeleteBitSet::for_test(&[5, 6, 130, 199], 200);
+        let sparse = DeleteBitSet::for_test(&[70, 100_000], 200_000);
+        let union = DeleteBitSet::union_all(&[&left, &right, &sparse]);
+        assert_eq!(
+            union.iter_deleted().collect::<Vec<_>>(),
+            vec![1, 5, 6, 70, 130, 199, 100_000]
+        );
+        assert_eq!(union.num_deleted(), 7);
+        assert!(union.is_alive(199_999));
+        let empty_union = DeleteBitSet::union_all(&[]);
+        assert_eq!(empty_union.num_deleted(), 0);
+        assert_eq!(empty_union.iter_deleted().next(), None);
+    }
+
+    #[test]
+    fn test_delete_bitset_difference_and_intersection() {
+        let checkpoint = DeleteBitSet::for_test(&[1, 5, 70], 100);
+        let current = DeleteBitSet::for_test(&[1, 5, 6, 70, 130, 199], 200);
+        let newly_deleted = current.difference(&checkpoint);
+        assert_eq!(
+            newly_deleted.iter_deleted().collect::<Vec<_>>(),
+            vec![6, 130, 199]
+        );
+        assert_eq!(newly_deleted.num_deleted(), 3);
+        assert_eq!(checkpoint.difference(&current).num_deleted(), 0);
+        let common = current.intersection(&checkpoint);
+        assert_eq!(common.iter_deleted().collect::<Vec<_>>(), vec![1, 5, 70]);
+        assert_eq!(common.num_deleted(), 3);
+    }
 }
@@ -4,6 +4,7 @@ use crate::schema::Facet;
 use crate::termdict::TermDictionary;
 use crate::termdict::TermOrdinal;
 use crate::DocId;
+use crate::TantivyError;
 use std::str;
 
 /// The facet reader makes it possible to access the list of
@@ -74,6 +75,59 @@ impl FacetReader {
         Ok(())
     }
 
+    /// Returns the ordinal of the facet given as text, e.g. `/category/shoes`,
+    /// or `None` if no document of the segment has this facet.
+    ///
+    /// This makes it possible to filter documents on their facet ordinals, which are
+    /// segment local, instead of resolving their facets.
+    pub fn str_to_ord(&self, facet_str: &str) -> crate::Result<Option<TermOrdinal>> {
+        let facet = Facet::from_text(facet_str)
+            .map_err(|err| TantivyError::InvalidArgument(err.to_string()))?;
+        Ok(self.term_dict.term_ord(facet.encoded_str())?)
+    }
+
+    /// Fills `output` with the facets associated to the given ordinals, as text.
+    ///
+    /// The `i`-th facet of `output` is the facet of `ords[i]`. The ordinals are
+    /// sorted internally, so that the dictionary is streamed once instead of being
+    /// looked up for each ordinal, which is much faster for large batches.
+    pub fn ord_to_str_batch(
+        &self,
+        ords: &[TermOrdinal],
+        output: &mut Vec<String>,
+    ) -> crate::Result<()> {
+        let mut positions: Vec<usize> = (0..ords.len()).collect();
+        positions.sort_unstable_by_key(|&position| ords[position]);
+        let sorted_ords: Vec<TermOrdinal> =
+            positions.iter().map(|&position| ords[position]).collect();
+        output.clear();
+        output.resize(ords.len(), String::new());
+        let mut utf8_error = None;
+        let mut positions_it = positions.iter();
+        let all_found = self
+            .term_dict
+            .sorted_ords_to_term_cb(&sorted_ords, |_, term| {
+                let position = *positions_it.next().unwrap();
+                match str::from_utf8(term) {
+                    Ok(facet_str) => {
+                        output[position] =
+                            Facet::from_encoded_string(facet_str.to_string()).to_path_string();
+                    }
+                    Err(err) => utf8_error = Some(err),
+                }
+            })?;
+        if let Some(utf8_error) = utf8_error {
+            return Err(DataCorruption::comment_only(utf8_error.to_string()).into());
+        }
+        if !all_found {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Some of the facet ordinals {:?} are out of bounds.",
+                ords
+            )));
+        }
+        Ok(())
+    }
+
     /// Return the list of facet ordinals associated to a document.
     pub fn facet_ords(&self, doc: DocId, output: &mut Vec<u64>) {
         self.term_ords.get_vals(doc, output);
@@ -180,6 +234,58 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_facet_ords_batch_resolution() -> crate::Result<()> {
+        let mut schema_builder = SchemaBuilder::default();
+        let facet_field = schema_builder.add_facet_field("facet", INDEXED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(crate::indexer::NoMergePolicy));
+        for facet in ["/x/c", "/x/a", "/x/c"] {
+            index_writer.add_document(doc!(facet_field=>Facet::from_text(facet).unwrap()));
+        }
+        index_writer.commit()?;
+        for facet in ["/x/b", "/w", "/x/c"] {
+            index_writer.add_document(doc!(facet_field=>Facet::from_text(facet).unwrap()));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let mut ords_of_x_c = Vec::new();
+        for segment_reader in searcher.segment_readers() {
+            let mut facet_reader = segment_reader.facet_reader(facet_field)?;
+            let ord_of_x_c = facet_reader.str_to_ord("/x/c")?.unwrap();
+            ords_of_x_c.push(ord_of_x_c);
+            assert_eq!(facet_reader.str_to_ord("/y")?, None);
+            assert!(facet_reader.str_to_ord("y").is_err());
+            // the deepest facet of each document, in reverse order.
+            let mut ords = Vec::new();
+            let mut facet_ords = Vec::new();
+            for doc in (0..segment_reader.max_doc()).rev() {
+                facet_reader.facet_ords(doc, &mut facet_ords);
+                ords.push(*facet_ords.iter().max().unwrap());
+            }
+            ords.push(ord_of_x_c);
+            let mut facets = vec!["stale".to_string()];
+            facet_reader.ord_to_str_batch(&ords, &mut facets)?;
+            assert_eq!(facets.len(), ords.len());
+            let mut facet = Facet::root();
+            for (ord, facet_str) in ords.iter().zip(facets.iter()) {
+                facet_reader.facet_from_ord(*ord, &mut facet)?;
+                assert_eq!(facet_str, &facet.to_path_string());
+            }
+            assert_eq!(facets.last().unwrap(), "/x/c");
+            let num_facets = facet_reader.num_facets() as u64;
+            assert!(facet_reader
+                .ord_to_str_batch(&[0, num_facets], &mut facets)
+                .is_err());
+        }
+        ords_of_x_c.sort_unstable();
+        assert_eq!(ords_of_x_c, vec![3, 4]);
+        Ok(())
+    }
+
     #[test]
     fn test_facet_not_populated_for_all_docs() -> crate::Result<()> {
         let mut schema_builder = SchemaBuilder::default();
@@ -21,23 +21,29 @@ memory usage is directly linear with the amplitude of the
 values stored.
 
 Read access performance is comparable to that of an array lookup.
+
+Documents without a value are assigned a default value in single-valued
+fast fields. The readers returned by `FastFieldReaders::u64_opt` and its
+siblings tell them apart from the documents having a value.
 */
 
 pub use self::bytes::{BytesFastFieldReader, BytesFastFieldWriter};
 pub use self::delete::write_delete_bitset;
-pub use self::delete::DeleteBitSet;
+pub use self::delete::{DeleteBitSet, DocIdIter};
 pub use self::error::{FastFieldNotAvailableError, Result};
 pub use self::facet_reader::FacetReader;
 pub use self::multivalued::{MultiValuedFastFieldReader, MultiValuedFastFieldWriter};
-pub(crate) use self::reader::BitpackedFastFieldReader;
 pub use self::reader::DynamicFastFieldReader;
 pub use self::reader::FastFieldReader;
+pub use self::reader::OptionalFastFieldReader;
 pub use self::readers::FastFieldReaders;
 pub use self::serializer::CompositeFastFieldSerializer;
 pub use self::serializer::FastFieldDataAccess;
 pub use self::serializer::FastFieldStats;
+pub use self::u128_field::{U128FastFieldReader, U128FastFieldWriter};
 pub use self::writer::{FastFieldsWriter, IntFastFieldWriter};
 use crate::schema::Cardinality;
+use crate::schema::DatePrecision;
 use crate::schema::FieldType;
 use crate::schema::Value;
 use crate::DocId;
@@ -51,9 +57,11 @@ mod delete;
 mod error;
 mod facet_reader;
 mod multivalued;
+mod null_index;
 mod reader;
 mod readers;
 mod serializer;
+mod u128_field;
 mod writer;
 
 /// Trait for `BytesFastFieldReader` and `MultiValuedFastFieldReader` to return the length of data
@@ -65,7 +73,7 @@ pub trait MultiValueLength {
     fn get_total_len(&self) -> u64;
 }
 
-/// Trait for types that are allowed for fast fields: (u64, i64 and f64).
+/// Trait for types that are allowed for fast fields: (u64, i64, f64, bool and date).
 pub trait FastValue: Clone + Copy + Send + Sync + PartialOrd + 'static {
     /// Converts a value from u64
     ///
@@ -173,14 +181,45 @@ impl FastValue for f64 {
     }
 }
 
+impl FastValue for bool {
+    fn from_u64(val: u64) -> Self {
+        val != 0
+    }
+
+    fn to_u64(&self) -> u64 {
+        u64::from(*self)
+    }
+
+    fn fast_field_cardinality(field_type: &FieldType) -> Option<Cardinality> {
+        match *field_type {
+            FieldType::Bool(ref integer_options) => integer_options.get_fastfield_cardinality(),
+            _ => None,
+        }
+    }
+
+    fn as_u64(&self) -> u64 {
+        u64::from(*self)
+    }
+
+    fn to_type() -> Type {
+        Type::Bool
+    }
+}
+
+/// Dates are mapped to their timestamp in microseconds, whatever the precision of
+/// their fast field column.
 impl FastValue for crate::DateTime {
     fn from_u64(timestamp_u64: u64) -> Self {
-        let timestamp_i64 = i64::from_u64(timestamp_u64);
-        crate::DateTime::from_utc(NaiveDateTime::from_timestamp(timestamp_i64, 0), Utc)
+        let timestamp_micros = i64::from_u64(timestamp_u64);
+        let secs = timestamp_micros.div_euclid(1_000_000);
+        let nsecs = timestamp_micros.rem_euclid(1_000_000) as u32 * 1_000;
+        crate::DateTime::from_utc(NaiveDateTime::from_timestamp(secs, nsecs), Utc)
     }
 
     fn to_u64(&self) -> u64 {
-        self.timestamp().to_u64()
+        DatePrecision::Microseconds
+            .truncated_timestamp(self)
+            .to_u64()
     }
 
     fn fast_field_cardinality(field_type: &FieldType) -> Option<Cardinality> {
@@ -191,7 +230,9 @@ impl FastValue for crate::DateTime {
     }
 
     fn as_u64(&self) -> u64 {
-        self.timestamp().as_u64()
+        DatePrecision::Microseconds
+            .truncated_timestamp(self)
+            .as_u64()
     }
 
     fn to_type() -> Type {
@@ -199,13 +240,19 @@ impl FastValue for crate::DateTime {
     }
 }
 
-fn value_to_u64(value: &Value) -> u64 {
+/// Returns the `u64` recorded in fast field columns for the given value.
+///
+/// Dates are truncated to `date_precision`, and recorded in its unit.
+fn value_to_u64(value: &Value, date_precision: DatePrecision) -> u64 {
     match *value {
         Value::U64(ref val) => *val,
         Value::I64(ref val) => common::i64_to_u64(*val),
         Value::F64(ref val) => common::f64_to_u64(*val),
-        Value::Date(ref datetime) => common::i64_to_u64(datetime.timestamp()),
-        _ => panic!("Expected a u64/i64/f64 field, got {:?} ", value),
+        Value::Bool(ref val) => u64::from(*val),
+        Value::Date(ref datetime) => {
+            common::i64_to_u64(date_precision.truncated_timestamp(datetime))
+        }
+        _ => panic!("Expected a u64/i64/f64/bool field, got {:?} ", value),
     }
 }
 
@@ -219,7 +266,7 @@ mod tests {
     use crate::schema::Field;
     use crate::schema::Schema;
     use crate::schema::FAST;
-    use crate::schema::{Document, IntOptions};
+    use crate::schema::{DateOptions, Document, IntOptions, STORED};
     use crate::{Index, SegmentId, SegmentReader};
     use common::HasLen;
     use once_cell::sync::Lazy;
@@ -423,6 +470,15 @@ mod tests {
             for i in 0..100 {
                 assert_eq!(buffer[i], -100i64 + 53i64 + i as i64);
             }
+            let mut buffer = vec![0i64; 1000];
+            fast_field_reader.get_range(9_000, &mut buffer[..]);
+            assert!(buffer.iter().cloned().eq(8_900i64..9_900i64));
+            let docs: Vec<DocId> = (0..10_100).rev().step_by(7).collect();
+            let mut buffer = vec![0i64; docs.len()];
+            fast_field_reader.get_batch(&docs, &mut buffer[..]);
+            for (doc, val) in docs.iter().zip(buffer.iter()) {
+                assert_eq!(*val, -100i64 + *doc as i64);
+            }
         }
         Ok(())
     }
@@ -522,6 +578,221 @@ mod tests {
         assert_eq!(reader.searcher().segment_readers().len(), 1);
     }
 
+    #[test]
+    fn test_optional_fast_field() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let dense_field = schema_builder.add_u64_field("dense", FAST);
+        let sparse_field = schema_builder.add_i64_field("sparse", FAST);
+        let id_field = schema_builder.add_u64_field("id", FAST | crate::schema::INDEXED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer
+            .add_document(doc!(dense_field => 1u64, sparse_field => -3i64, id_field => 0u64));
+        index_writer.add_document(doc!(dense_field => 2u64, id_field => 1u64));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(dense_field => 3u64, id_field => 2u64));
+        index_writer
+            .add_document(doc!(dense_field => 4u64, sparse_field => 5i64, id_field => 3u64));
+        index_writer.commit()?;
+        index_writer
+            .add_document(doc!(dense_field => 5u64, sparse_field => 7i64, id_field => 4u64));
+        index_writer.commit()?;
+
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let segment_reader = searcher
+            .segment_readers()
+            .iter()
+            .find(|segment_reader| segment_reader.fast_fields().u64(id_field).unwrap().get(0) == 0)
+            .unwrap();
+        let dense_reader = segment_reader.fast_fields().u64_opt(dense_field)?;
+        assert!(!dense_reader.has_missing_vals());
+        assert!(dense_reader.exists(1));
+        let sparse_reader = segment_reader.fast_fields().i64_opt(sparse_field)?;
+        assert!(sparse_reader.has_missing_vals());
+        assert_eq!(sparse_reader.get_opt(0), Some(-3i64));
+        assert_eq!(sparse_reader.get_opt(1), None);
+        assert!(!sparse_reader.exists(1));
+        assert_eq!(sparse_reader.first_or_default(1), 0i64);
+        assert!(segment_reader.fast_fields().i64_opt(dense_field).is_err());
+        // the fully-dense segments have no null index.
+        let dense_segment_reader = searcher
+            .segment_readers()
+            .iter()
+            .find(|segment_reader| segment_reader.max_doc() == 1)
+            .unwrap();
+        assert!(!dense_segment_reader
+            .fast_fields()
+            .i64_opt(sparse_field)?
+            .has_missing_vals());
+
+        // the null index is preserved by merges.
+        index_writer.delete_term(crate::Term::from_field_u64(id_field, 0));
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        let id_reader = segment_reader.fast_fields().u64(id_field)?;
+        let sparse_reader = segment_reader.fast_fields().i64_opt(sparse_field)?;
+        let mut vals: Vec<(u64, Option<i64>)> = (0..segment_reader.max_doc())
+            .map(|doc| (id_reader.get(doc), sparse_reader.get_opt(doc)))
+            .collect();
+        vals.sort();
+        assert_eq!(vals, vec![(1, None), (2, None), (3, Some(5)), (4, Some(7))]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_optional_fast_field_sorted_index() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let sort_field = schema_builder.add_u64_field("sort", FAST);
+        let sparse_field = schema_builder.add_u64_field("sparse", FAST);
+        let schema = schema_builder.build();
+        let index = Index::builder()
+            .schema(schema)
+            .settings(crate::IndexSettings {
+                sort_by_field: Some(crate::IndexSortByField {
+                    field: "sort".to_string(),
+                    order: crate::Order::Asc,
+                }),
+                ..Default::default()
+            })
+            .create_in_ram()?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(sort_field => 3u64, sparse_field => 30u64));
+        index_writer.add_document(doc!(sort_field => 1u64));
+        index_writer.add_document(doc!(sort_field => 2u64, sparse_field => 20u64));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let sparse_reader = searcher
+            .segment_reader(0)
+            .fast_fields()
+            .u64_opt(sparse_field)?;
+        assert_eq!(sparse_reader.get_opt(0), None);
+        assert_eq!(sparse_reader.get_opt(1), Some(20));
+        assert_eq!(sparse_reader.get_opt(2), Some(30));
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by_optional_fast_field() -> crate::Result<()> {
+        use crate::collector::TopDocs;
+        use crate::query::AllQuery;
+        let mut schema_builder = Schema::builder();
+        let rating_field = schema_builder.add_i64_field("rating", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!());
+        index_writer.add_document(doc!(rating_field => -2i64));
+        index_writer.add_document(doc!(rating_field => 3i64));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let top_docs: Vec<(i64, crate::DocAddress)> = searcher.search(
+            &AllQuery,
+            &TopDocs::with_limit(3).order_by_fast_field(rating_field),
+        )?;
+        let docs: Vec<u32> = top_docs
+            .iter()
+            .map(|(_, doc_address)| doc_address.doc_id)
+            .collect();
+        // the document without a rating is ranked last.
+        assert_eq!(docs, vec![2, 1, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fast_field_codec_type() -> crate::Result<()> {
+        use crate::schema::{Cardinality, FastFieldCodecType};
+        let mut schema_builder = Schema::builder();
+        let auto_field = schema_builder.add_u64_field("auto", FAST);
+        let forced_options = IntOptions::default()
+            .set_fast(Cardinality::SingleValue)
+            .set_fast_codec(FastFieldCodecType::Bitpacked);
+        let forced_field = schema_builder.add_u64_field("forced", forced_options);
+        let linear_options = IntOptions::default()
+            .set_fast(Cardinality::SingleValue)
+            .set_fast_codec(FastFieldCodecType::LinearInterpol);
+        let linear_field = schema_builder.add_u64_field("linear", linear_options);
+        let multi_field = schema_builder.add_u64_field(
+            "multi",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        // the values are monotonic, and the linear interpolation is chosen automatically.
+        for i in 0u64..1_000u64 {
+            index_writer.add_document(doc!(
+                auto_field => 1_000_000 + i * 1_000,
+                forced_field => 1_000_000 + i * 1_000,
+                linear_field => i,
+            ));
+        }
+        index_writer.commit()?;
+        // two values are too few for a linear interpolation.
+        index_writer.add_document(doc!(linear_field => 3u64));
+        index_writer.add_document(doc!(linear_field => 1u64));
+        index_writer.commit()?;
+
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let large_segment_reader = searcher
+            .segment_readers()
+            .iter()
+            .find(|segment_reader| segment_reader.max_doc() == 1_000)
+            .unwrap();
+        let fast_fields = large_segment_reader.fast_fields();
+        assert_eq!(
+            fast_fields.codec_type(auto_field)?,
+            FastFieldCodecType::LinearInterpol
+        );
+        assert_eq!(
+            fast_fields.codec_type(forced_field)?,
+            FastFieldCodecType::Bitpacked
+        );
+        assert!(
+            fast_fields.compressed_size(auto_field)? < fast_fields.compressed_size(forced_field)?
+        );
+        let forced_reader = fast_fields.u64(forced_field)?;
+        assert_eq!(forced_reader.get(999), 1_000_000 + 999 * 1_000);
+        assert!(fast_fields.codec_type(multi_field).is_err());
+
+        let small_segment_reader = searcher
+            .segment_readers()
+            .iter()
+            .find(|segment_reader| segment_reader.max_doc() == 2)
+            .unwrap();
+        let fast_fields = small_segment_reader.fast_fields();
+        assert_eq!(
+            fast_fields.codec_type(linear_field)?,
+            FastFieldCodecType::Bitpacked
+        );
+        assert_eq!(fast_fields.u64(linear_field)?.get(0), 3u64);
+
+        // the codec is also used by merges.
+        let segment_ids = index.searchable_segment_ids()?;
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let fast_fields = searcher.segment_reader(0).fast_fields();
+        assert_eq!(
+            fast_fields.codec_type(forced_field)?,
+            FastFieldCodecType::Bitpacked
+        );
+        assert_eq!(
+            fast_fields.codec_type(linear_field)?,
+            FastFieldCodecType::LinearInterpol
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_default_datetime() {
         assert_eq!(crate::DateTime::make_zero().timestamp(), 0i64);
@@ -529,7 +800,7 @@ mod tests {
 
     #[test]
     fn test_datefastfield() {
-        use crate::fastfield::FastValue;
+        use chrono::TimeZone;
         let mut schema_builder = Schema::builder();
         let date_field = schema_builder.add_date_field("date", FAST);
         let multi_date_field = schema_builder.add_date_field(
@@ -541,16 +812,16 @@ mod tests {
         let mut index_writer = index.writer_for_tests().unwrap();
         index_writer.set_merge_policy(Box::new(NoMergePolicy));
         index_writer.add_document(doc!(
-            date_field => crate::DateTime::from_u64(1i64.to_u64()),
-            multi_date_field => crate::DateTime::from_u64(2i64.to_u64()),
-            multi_date_field => crate::DateTime::from_u64(3i64.to_u64())
+            date_field => Utc.timestamp(1, 0),
+            multi_date_field => Utc.timestamp(2, 0),
+            multi_date_field => Utc.timestamp(3, 0)
         ));
         index_writer.add_document(doc!(
-            date_field => crate::DateTime::from_u64(4i64.to_u64())
+            date_field => Utc.timestamp(4, 0)
         ));
         index_writer.add_document(doc!(
-            multi_date_field => crate::DateTime::from_u64(5i64.to_u64()),
-            multi_date_field => crate::DateTime::from_u64(6i64.to_u64())
+            multi_date_field => Utc.timestamp(5, 0),
+            multi_date_field => Utc.timestamp(6, 0)
         ));
         index_writer.commit().unwrap();
         let reader = index.reader().unwrap();
@@ -581,6 +852,127 @@ mod tests {
             assert_eq!(dates[1].timestamp(), 6i64);
         }
     }
+
+    #[test]
+    fn test_date_fast_field_precision() -> crate::Result<()> {
+        use chrono::TimeZone;
+        let mut schema_builder = Schema::builder();
+        let seconds_field = schema_builder.add_date_field("seconds", FAST | STORED);
+        let millis_field = schema_builder.add_date_field(
+            "millis",
+            DateOptions::from(FAST).set_precision(DatePrecision::Milliseconds),
+        );
+        let micros_field = schema_builder.add_date_field(
+            "micros",
+            DateOptions::default()
+                .set_fast(Cardinality::MultiValues)
+                .set_precision(DatePrecision::Microseconds),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let date = Utc.timestamp(1_600_000_000, 123_456_789);
+        index_writer.add_document(doc!(
+            seconds_field => date,
+            millis_field => date,
+            micros_field => date,
+            micros_field => Utc.timestamp(-1, 999_999_999),
+        ));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(millis_field => Utc.timestamp(7, 0)));
+        index_writer.commit()?;
+        let check = |searcher: &crate::Searcher| -> crate::Result<()> {
+            // finds the first document, whose position depends on the merge.
+            let doc_address = searcher
+                .segment_readers()
+                .iter()
+                .enumerate()
+                .flat_map(|(segment_ord, segment_reader)| {
+                    let millis_reader = segment_reader.fast_fields().date(millis_field).unwrap();
+                    (0..segment_reader.max_doc())
+                        .filter(move |doc| millis_reader.get(*doc).timestamp() != 7)
+                        .map(move |doc| crate::DocAddress::new(segment_ord as u32, doc))
+                })
+                .next()
+                .unwrap();
+            let doc = doc_address.doc_id;
+            let fast_fields = searcher
+                .segment_reader(doc_address.segment_ord)
+                .fast_fields();
+            assert_eq!(
+                fast_fields.date(seconds_field)?.get(doc),
+                Utc.timestamp(1_600_000_000, 0)
+            );
+            assert_eq!(
+                fast_fields.date(millis_field)?.get(doc),
+                Utc.timestamp(1_600_000_000, 123_000_000)
+            );
+            let mut dates = Vec::new();
+            fast_fields.dates(micros_field)?.get_vals(doc, &mut dates);
+            assert_eq!(
+                dates,
+                vec![
+                    Utc.timestamp(1_600_000_000, 123_456_000),
+                    Utc.timestamp(-1, 999_999_000)
+                ]
+            );
+            // the doc store keeps the original date.
+            let stored_doc = searcher.doc(doc_address)?;
+            assert_eq!(
+                stored_doc.get_first(seconds_field),
+                Some(&Value::Date(date))
+            );
+            Ok(())
+        };
+        check(&index.reader()?.searcher())?;
+        let segment_ids = index.searchable_segment_ids()?;
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        let reader = index.reader()?;
+        assert_eq!(reader.searcher().segment_readers().len(), 1);
+        check(&reader.searcher())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_boolfastfield() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let bool_field = schema_builder.add_bool_field("bool", FAST);
+        let multi_bool_field = schema_builder.add_bool_field(
+            "multi_bool",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(
+            bool_field => true,
+            multi_bool_field => false,
+            multi_bool_field => true
+        ));
+        index_writer.add_document(doc!(bool_field => false));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(multi_bool_field => true));
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let fast_fields = searcher.segment_reader(0).fast_fields();
+        let bool_fast_field = fast_fields.bool(bool_field)?;
+        let bools_fast_field = fast_fields.bools(multi_bool_field)?;
+        assert!(bool_fast_field.get(0u32));
+        assert!(!bool_fast_field.get(1u32));
+        assert!(!bool_fast_field.get(2u32));
+        let mut bools = vec![];
+        bools_fast_field.get_vals(0u32, &mut bools);
+        assert_eq!(bools, vec![false, true]);
+        bools_fast_field.get_vals(1u32, &mut bools);
+        assert!(bools.is_empty());
+        bools_fast_field.get_vals(2u32, &mut bools);
+        assert_eq!(bools, vec![true]);
+        assert!(fast_fields.u64(bool_field).is_err());
+        Ok(())
+    }
 }
 
 #[cfg(all(test, feature = "unstable"))]
@@ -1,7 +1,7 @@
 use crate::fastfield::serializer::BitpackedFastFieldSerializerLegacy;
 use crate::fastfield::CompositeFastFieldSerializer;
 use crate::postings::UnorderedTermId;
-use crate::schema::{Document, Field};
+use crate::schema::{DatePrecision, Document, Field};
 use crate::termdict::TermOrdinal;
 use crate::DocId;
 use crate::{fastfield::value_to_u64, indexer::doc_id_mapping::DocIdMapping};
@@ -35,6 +35,7 @@ pub struct MultiValuedFastFieldWriter {
     vals: Vec<UnorderedTermId>,
     doc_index: Vec<u64>,
     is_facet: bool,
+    date_precision: DatePrecision,
 }
 
 impl MultiValuedFastFieldWriter {
@@ -45,9 +46,15 @@ impl MultiValuedFastFieldWriter {
             vals: Vec::new(),
             doc_index: Vec::new(),
             is_facet,
+            date_precision: DatePrecision::default(),
         }
     }
 
+    /// Sets the precision to which the date values are truncated.
+    pub(crate) fn set_date_precision(&mut self, date_precision: DatePrecision) {
+        self.date_precision = date_precision;
+    }
+
     /// The memory used (inclusive childs)
     pub fn mem_usage(&self) -> usize {
         self.vals.capacity() * std::mem::size_of::<UnorderedTermId>()
@@ -77,7 +84,7 @@ impl MultiValuedFastFieldWriter {
         if !self.is_facet {
             for field_value in doc.field_values() {
                 if field_value.field() == self.field {
-                    self.add_val(value_to_u64(field_value.value()));
+                    self.add_val(value_to_u64(field_value.value(), self.date_precision));
                 }
             }
         }
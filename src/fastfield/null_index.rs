@@ -0,0 +1,73 @@
+use std::io::{self, Write};
+
+use crate::directory::FileSlice;
+use crate::directory::OwnedBytes;
+use crate::DocId;
+
+/// Writes the null index of a single-valued fast field.
+///
+/// The null index is a bitset of the documents having a value: the bit `doc % 8`
+/// of the byte `doc / 8` is set iff the document `doc` has a value.
+pub(crate) fn write_null_index(
+    has_vals: impl Iterator<Item = bool>,
+    write: &mut impl Write,
+) -> io::Result<()> {
+    let mut byte = 0u8;
+    let mut num_bits = 0;
+    for has_val in has_vals {
+        if has_val {
+            byte |= 1 << num_bits;
+        }
+        num_bits += 1;
+        if num_bits == 8 {
+            write.write_all(&[byte])?;
+            byte = 0;
+            num_bits = 0;
+        }
+    }
+    if num_bits > 0 {
+        write.write_all(&[byte])?;
+    }
+    Ok(())
+}
+
+/// Records which documents have a value in a single-valued fast field.
+///
+/// It is only serialized for the fast fields in which some documents have no value.
+#[derive(Clone)]
+pub(crate) struct NullIndex {
+    data: OwnedBytes,
+}
+
+impl NullIndex {
+    pub(crate) fn open(file: FileSlice) -> crate::Result<NullIndex> {
+        let data = file.read_bytes()?;
+        Ok(NullIndex { data })
+    }
+
+    /// Returns true iff the given `doc` has a value.
+    #[inline]
+    pub(crate) fn exists(&self, doc: DocId) -> bool {
+        let byte = self.data.as_slice()[(doc / 8) as usize];
+        byte & (1 << (doc % 8)) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_null_index, NullIndex};
+    use crate::directory::FileSlice;
+
+    #[test]
+    fn test_null_index() -> crate::Result<()> {
+        let has_vals: Vec<bool> = (0..100).map(|doc| doc % 3 == 0 || doc == 97).collect();
+        let mut buffer = Vec::new();
+        write_null_index(has_vals.iter().cloned(), &mut buffer)?;
+        assert_eq!(buffer.len(), 13);
+        let null_index = NullIndex::open(FileSlice::from(buffer))?;
+        for (doc, has_val) in has_vals.iter().enumerate() {
+            assert_eq!(null_index.exists(doc as u32), *has_val);
+        }
+        Ok(())
+    }
+}
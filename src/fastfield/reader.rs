@@ -1,11 +1,12 @@
+use super::null_index::NullIndex;
 use super::FastValue;
 use crate::directory::CompositeFile;
 use crate::directory::FileSlice;
 use crate::directory::OwnedBytes;
 use crate::directory::{Directory, RamDirectory, WritePtr};
 use crate::fastfield::{CompositeFastFieldSerializer, FastFieldsWriter};
-use crate::schema::Schema;
 use crate::schema::FAST;
+use crate::schema::{FastFieldCodecType, Schema};
 use crate::DocId;
 use common::BinarySerializable;
 use fastfield_codecs::bitpacked::BitpackedFastFieldReader as BitpackedReader;
@@ -19,6 +20,10 @@ use fastfield_codecs::FastFieldCodecSerializer;
 use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::path::Path;
+use tantivy_bitpacker::compute_num_bits;
+
+/// Number of values decoded at once by `get_range` and `get_batch`.
+const BATCH_LEN: usize = 128;
 
 /// FastFieldReader is the trait to access fast field data.
 pub trait FastFieldReader<Item: FastValue>: Clone {
@@ -44,7 +49,28 @@ pub trait FastFieldReader<Item: FastValue>: Clone {
     ///
     /// May panic if `start + output.len()` is greater than
     /// the segment's `maxdoc`.
-    fn get_range(&self, start: u64, output: &mut [Item]);
+    fn get_range(&self, start: u64, output: &mut [Item]) {
+        for (doc, out) in (start as DocId..).zip(output.iter_mut()) {
+            *out = self.get(doc);
+        }
+    }
+
+    /// Fills an output buffer with the fast field values
+    /// associated with the given `docs`.
+    ///
+    /// The readers of tantivy decode the values in batches, which is
+    /// faster than calling `get` for each document.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `docs` and `output` do not have the same length, and
+    /// may panic if a `doc` is greater than the segment's `maxdoc`.
+    fn get_batch(&self, docs: &[DocId], output: &mut [Item]) {
+        assert_eq!(docs.len(), output.len());
+        for (doc, out) in docs.iter().zip(output.iter_mut()) {
+            *out = self.get(*doc);
+        }
+    }
 
     /// Returns the minimum value for this fast field.
     ///
@@ -77,6 +103,17 @@ pub enum DynamicFastFieldReader<Item: FastValue> {
 impl<Item: FastValue> DynamicFastFieldReader<Item> {
     /// Returns correct the reader wrapped in the `DynamicFastFieldReader` enum for the data.
     pub fn open(file: FileSlice) -> crate::Result<DynamicFastFieldReader<Item>> {
+        Self::open_with_scale(file, 1)
+    }
+
+    /// Same as `open`, but the stored values, read as `i64`, are multiplied by `scale`.
+    ///
+    /// This is used to map the timestamps of a date column, recorded in the unit of its
+    /// precision, to microseconds.
+    pub(crate) fn open_with_scale(
+        file: FileSlice,
+        scale: i64,
+    ) -> crate::Result<DynamicFastFieldReader<Item>> {
         let mut bytes = file.read_bytes()?;
         let id = bytes.read_u8();
 
@@ -85,13 +122,15 @@ impl<Item: FastValue> DynamicFastFieldReader<Item> {
                 DynamicFastFieldReader::Bitpacked(FastFieldReaderCodecWrapper::<
                     Item,
                     BitpackedReader,
-                >::open_from_bytes(bytes)?)
+                >::open_from_bytes(bytes)?
+                .with_scale(scale))
             }
             LinearInterpolFastFieldSerializer::ID => {
                 DynamicFastFieldReader::LinearInterpol(FastFieldReaderCodecWrapper::<
                     Item,
                     LinearInterpolFastFieldReader,
-                >::open_from_bytes(bytes)?)
+                >::open_from_bytes(bytes)?
+                .with_scale(scale))
             }
             MultiLinearInterpolFastFieldSerializer::ID => {
                 DynamicFastFieldReader::MultiLinearInterpol(FastFieldReaderCodecWrapper::<
@@ -99,7 +138,8 @@ impl<Item: FastValue> DynamicFastFieldReader<Item> {
                     MultiLinearInterpolFastFieldReader,
                 >::open_from_bytes(
                     bytes
-                )?)
+                )?
+                .with_scale(scale))
             }
             _ => {
                 panic!(
@@ -110,6 +150,43 @@ impl<Item: FastValue> DynamicFastFieldReader<Item> {
         };
         Ok(reader)
     }
+
+    /// Returns the codec of the fast field.
+    pub fn codec_type(&self) -> FastFieldCodecType {
+        match self {
+            Self::Bitpacked(_) => FastFieldCodecType::Bitpacked,
+            Self::LinearInterpol(_) => FastFieldCodecType::LinearInterpol,
+            Self::MultiLinearInterpol(_) => FastFieldCodecType::MultiLinearInterpol,
+        }
+    }
+
+    /// Returns true if the column holds `num_vals` values.
+    ///
+    /// The bitpacked codec does not record its number of values: the length of its data
+    /// is checked instead.
+    pub(crate) fn has_num_vals(&self, num_vals: u64) -> bool {
+        match self {
+            Self::Bitpacked(reader) => {
+                let amplitude = reader.reader.max_value_u64 - reader.reader.min_value_u64;
+                let num_bits = u64::from(compute_num_bits(amplitude));
+                // The bitpacked values are followed by 7 bytes of padding, and a footer
+                // storing the min value and the amplitude.
+                let num_bytes = (num_vals * num_bits + 7) / 8 + 7 + 16;
+                num_bytes == reader.bytes.len() as u64
+            }
+            Self::LinearInterpol(reader) => reader.reader.footer.num_vals == num_vals,
+            Self::MultiLinearInterpol(reader) => reader.reader.footer.num_vals == num_vals,
+        }
+    }
+
+    /// Returns the value at the given index, which, unlike a `DocId`, may not fit in a `u32`.
+    pub(crate) fn get_u64(&self, idx: u64) -> Item {
+        match self {
+            Self::Bitpacked(reader) => reader.get_u64(idx),
+            Self::LinearInterpol(reader) => reader.get_u64(idx),
+            Self::MultiLinearInterpol(reader) => reader.get_u64(idx),
+        }
+    }
 }
 
 impl<Item: FastValue> FastFieldReader<Item> for DynamicFastFieldReader<Item> {
@@ -127,6 +204,13 @@ impl<Item: FastValue> FastFieldReader<Item> for DynamicFastFieldReader<Item> {
             Self::MultiLinearInterpol(reader) => reader.get_range(start, output),
         }
     }
+    fn get_batch(&self, docs: &[DocId], output: &mut [Item]) {
+        match self {
+            Self::Bitpacked(reader) => reader.get_batch(docs, output),
+            Self::LinearInterpol(reader) => reader.get_batch(docs, output),
+            Self::MultiLinearInterpol(reader) => reader.get_batch(docs, output),
+        }
+    }
     fn min_value(&self) -> Item {
         match self {
             Self::Bitpacked(reader) => reader.min_value(),
@@ -143,6 +227,85 @@ impl<Item: FastValue> FastFieldReader<Item> for DynamicFastFieldReader<Item> {
     }
 }
 
+/// Reader for a single-valued fast field in which some documents may have no value.
+///
+/// The documents without a value are recorded in a null index, which is only serialized
+/// if the fast field has some of them. Fully-dense fast fields, including the ones written
+/// before null indexes existed, therefore have no overhead.
+///
+/// A default value, i.e. `0`, `0.0`, `false` or the Unix epoch depending on the
+/// type of the field, is stored for the documents without a value. It is returned by
+/// `get` and `first_or_default`, and accounted for by `min_value` and `max_value`.
+#[derive(Clone)]
+pub struct OptionalFastFieldReader<Item: FastValue> {
+    vals_reader: DynamicFastFieldReader<Item>,
+    null_index: Option<NullIndex>,
+}
+
+impl<Item: FastValue> OptionalFastFieldReader<Item> {
+    pub(crate) fn open(
+        vals_reader: DynamicFastFieldReader<Item>,
+        null_index: Option<NullIndex>,
+    ) -> OptionalFastFieldReader<Item> {
+        OptionalFastFieldReader {
+            vals_reader,
+            null_index,
+        }
+    }
+
+    /// Returns true iff the given `doc` has a value.
+    #[inline]
+    pub fn exists(&self, doc: DocId) -> bool {
+        self.null_index
+            .as_ref()
+            .map(|null_index| null_index.exists(doc))
+            .unwrap_or(true)
+    }
+
+    /// Returns the value associated to the given `doc`, or `None` if it has no value.
+    #[inline]
+    pub fn get_opt(&self, doc: DocId) -> Option<Item> {
+        if self.exists(doc) {
+            Some(self.vals_reader.get(doc))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the value associated to the given `doc`, or the default value
+    /// if it has no value.
+    ///
+    /// This is the value returned by the readers which are not aware of missing values,
+    /// like `FastFieldReaders::u64`.
+    #[inline]
+    pub fn first_or_default(&self, doc: DocId) -> Item {
+        self.vals_reader.get(doc)
+    }
+
+    /// Returns true iff some documents have no value.
+    pub fn has_missing_vals(&self) -> bool {
+        self.null_index.is_some()
+    }
+}
+
+impl<Item: FastValue> FastFieldReader<Item> for OptionalFastFieldReader<Item> {
+    fn get(&self, doc: DocId) -> Item {
+        self.first_or_default(doc)
+    }
+    fn get_range(&self, start: u64, output: &mut [Item]) {
+        self.vals_reader.get_range(start, output)
+    }
+    fn get_batch(&self, docs: &[DocId], output: &mut [Item]) {
+        self.vals_reader.get_batch(docs, output)
+    }
+    fn min_value(&self) -> Item {
+        self.vals_reader.min_value()
+    }
+    fn max_value(&self) -> Item {
+        self.vals_reader.max_value()
+    }
+}
+
 /// Wrapper for accessing a fastfield.
 ///
 /// Holds the data and the codec to the read the data.
@@ -151,6 +314,8 @@ impl<Item: FastValue> FastFieldReader<Item> for DynamicFastFieldReader<Item> {
 pub struct FastFieldReaderCodecWrapper<Item: FastValue, CodecReader> {
     reader: CodecReader,
     bytes: OwnedBytes,
+    /// Factor applied to the stored values, read as `i64`.
+    scale: i64,
     _phantom: PhantomData<Item>,
 }
 
@@ -172,11 +337,28 @@ impl<Item: FastValue, C: FastFieldCodecReader> FastFieldReaderCodecWrapper<Item,
         Ok(FastFieldReaderCodecWrapper {
             reader,
             bytes,
+            scale: 1,
             _phantom: PhantomData,
         })
     }
+
+    fn with_scale(mut self, scale: i64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    #[inline]
+    fn to_item(&self, val: u64) -> Item {
+        if self.scale == 1 {
+            Item::from_u64(val)
+        } else {
+            let val = common::u64_to_i64(val).saturating_mul(self.scale);
+            Item::from_u64(common::i64_to_u64(val))
+        }
+    }
+
     pub(crate) fn get_u64(&self, doc: u64) -> Item {
-        Item::from_u64(self.reader.get_u64(doc, self.bytes.as_slice()))
+        self.to_item(self.reader.get_u64(doc, self.bytes.as_slice()))
     }
 
     /// Internally `multivalued` also use SingleValue Fast fields.
@@ -192,8 +374,17 @@ impl<Item: FastValue, C: FastFieldCodecReader> FastFieldReaderCodecWrapper<Item,
     ///
     /// See `get_range` for an actual documentation about this method.
     pub(crate) fn get_range_u64(&self, start: u64, output: &mut [Item]) {
-        for (i, out) in output.iter_mut().enumerate() {
-            *out = self.get_u64(start + (i as u64));
+        let mut buffer = [0u64; BATCH_LEN];
+        for (chunk_start, output_chunk) in (start..)
+            .step_by(BATCH_LEN)
+            .zip(output.chunks_mut(BATCH_LEN))
+        {
+            let buffer = &mut buffer[..output_chunk.len()];
+            self.reader
+                .get_range_u64(chunk_start, buffer, self.bytes.as_slice());
+            for (out, val) in output_chunk.iter_mut().zip(buffer.iter()) {
+                *out = self.to_item(*val);
+            }
         }
     }
 }
@@ -230,13 +421,26 @@ impl<Item: FastValue, C: FastFieldCodecReader + Clone> FastFieldReader<Item>
         self.get_range_u64(start, output);
     }
 
+    fn get_batch(&self, docs: &[DocId], output: &mut [Item]) {
+        assert_eq!(docs.len(), output.len());
+        let mut buffer = [0u64; BATCH_LEN];
+        for (docs_chunk, output_chunk) in docs.chunks(BATCH_LEN).zip(output.chunks_mut(BATCH_LEN)) {
+            let buffer = &mut buffer[..output_chunk.len()];
+            self.reader
+                .get_batch_u64(docs_chunk, buffer, self.bytes.as_slice());
+            for (out, val) in output_chunk.iter_mut().zip(buffer.iter()) {
+                *out = self.to_item(*val);
+            }
+        }
+    }
+
     /// Returns the minimum value for this fast field.
     ///
     /// The max value does not take in account of possible
     /// deleted document, and should be considered as an upper bound
     /// of the actual maximum value.
     fn min_value(&self) -> Item {
-        Item::from_u64(self.reader.min_value())
+        self.to_item(self.reader.min_value())
     }
 
     /// Returns the maximum value for this fast field.
@@ -245,12 +449,10 @@ impl<Item: FastValue, C: FastFieldCodecReader + Clone> FastFieldReader<Item>
     /// deleted document, and should be considered as an upper bound
     /// of the actual maximum value.
     fn max_value(&self) -> Item {
-        Item::from_u64(self.reader.max_value())
+        self.to_item(self.reader.max_value())
     }
 }
 
-pub(crate) type BitpackedFastFieldReader<Item> = FastFieldReaderCodecWrapper<Item, BitpackedReader>;
-
 impl<Item: FastValue> From<Vec<Item>> for DynamicFastFieldReader<Item> {
     fn from(vals: Vec<Item>) -> DynamicFastFieldReader<Item> {
         let mut schema_builder = Schema::builder();
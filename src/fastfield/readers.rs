@@ -1,13 +1,16 @@
 use crate::directory::CompositeFile;
 use crate::directory::FileSlice;
+use crate::fastfield::FastFieldNotAvailableError;
 use crate::fastfield::MultiValuedFastFieldReader;
-use crate::fastfield::{BitpackedFastFieldReader, FastFieldNotAvailableError};
-use crate::fastfield::{BytesFastFieldReader, FastValue};
-use crate::schema::{Cardinality, Field, FieldType, Schema};
+use crate::fastfield::{BytesFastFieldReader, FastValue, U128FastFieldReader};
+use crate::schema::{Cardinality, FastFieldCodecType, Field, FieldType, Schema};
 use crate::space_usage::PerFieldSpaceUsage;
+use crate::DocId;
 use crate::TantivyError;
+use common::HasLen;
 
-use super::reader::DynamicFastFieldReader;
+use super::null_index::NullIndex;
+use super::reader::{DynamicFastFieldReader, OptionalFastFieldReader};
 
 /// Provides access to all of the BitpackedFastFieldReader.
 ///
@@ -16,13 +19,19 @@ use super::reader::DynamicFastFieldReader;
 #[derive(Clone)]
 pub struct FastFieldReaders {
     schema: Schema,
+    max_doc: DocId,
     fast_fields_composite: CompositeFile,
+    /// If false, the timestamps of date fields are read in the unit of their precision
+    /// instead of being mapped to microseconds.
+    map_date_values: bool,
 }
 #[derive(Eq, PartialEq, Debug)]
 enum FastType {
     I64,
     U64,
     F64,
+    Bool,
+    IpAddr,
     Date,
 }
 
@@ -37,6 +46,12 @@ fn type_and_cardinality(field_type: &FieldType) -> Option<(FastType, Cardinality
         FieldType::F64(options) => options
             .get_fastfield_cardinality()
             .map(|cardinality| (FastType::F64, cardinality)),
+        FieldType::Bool(options) => options
+            .get_fastfield_cardinality()
+            .map(|cardinality| (FastType::Bool, cardinality)),
+        FieldType::IpAddr(options) => options
+            .get_fastfield_cardinality()
+            .map(|cardinality| (FastType::IpAddr, cardinality)),
         FieldType::Date(options) => options
             .get_fastfield_cardinality()
             .map(|cardinality| (FastType::Date, cardinality)),
@@ -46,10 +61,52 @@ fn type_and_cardinality(field_type: &FieldType) -> Option<(FastType, Cardinality
 }
 
 impl FastFieldReaders {
-    pub(crate) fn new(schema: Schema, fast_fields_composite: CompositeFile) -> FastFieldReaders {
+    pub(crate) fn new(
+        schema: Schema,
+        max_doc: DocId,
+        fast_fields_composite: CompositeFile,
+    ) -> FastFieldReaders {
         FastFieldReaders {
             schema,
+            max_doc,
             fast_fields_composite,
+            map_date_values: true,
+        }
+    }
+
+    /// Returns readers of the values as they are stored.
+    ///
+    /// The timestamps of date fields are not mapped to microseconds, but read in the unit
+    /// of the precision of the field, which is what merges copy over.
+    pub(crate) fn stored_values(&self) -> FastFieldReaders {
+        FastFieldReaders {
+            map_date_values: false,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the factor mapping the stored values of the column `idx` of `field`
+    /// to their `u64` representation.
+    ///
+    /// This is `1`, but for the timestamps of date fields which are mapped to microseconds.
+    fn value_scale(&self, field: Field, idx: usize) -> i64 {
+        if !self.map_date_values {
+            return 1;
+        }
+        match self.schema.get_field_entry(field).field_type() {
+            FieldType::Date(options) => {
+                // The values of multi-valued fields come after their offsets.
+                let values_idx = match options.get_fastfield_cardinality() {
+                    Some(Cardinality::MultiValues) => 1,
+                    _ => 0,
+                };
+                if idx == values_idx {
+                    options.get_precision().micros_per_unit()
+                } else {
+                    1
+                }
+            }
+            _ => 1,
         }
     }
 
@@ -57,6 +114,65 @@ impl FastFieldReaders {
         self.fast_fields_composite.space_usage()
     }
 
+    /// Checks that the columns of the fast fields have one value per document, or, for
+    /// the multi-valued fast fields, that the offsets have one value per document
+    /// plus one, and that the values match the last offset.
+    ///
+    /// The ip address fast fields are not checked.
+    /// Returns the description of the problems found, at most one per field.
+    pub(crate) fn check_column_lengths(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (field, field_entry) in self.schema.fields() {
+            let cardinality = match type_and_cardinality(field_entry.field_type()) {
+                Some((FastType::IpAddr, _)) | None => continue,
+                Some((_, cardinality)) => cardinality,
+            };
+            let column_reader = |idx: usize| {
+                self.fast_field_data(field, idx)
+                    .and_then(DynamicFastFieldReader::<u64>::open)
+            };
+            let max_doc = u64::from(self.max_doc);
+            let problem = match cardinality {
+                Cardinality::SingleValue => match column_reader(0) {
+                    Ok(vals_reader) if vals_reader.has_num_vals(max_doc) => None,
+                    Ok(_) => Some(format!("does not have {} values", max_doc)),
+                    Err(err) => Some(format!("cannot be opened: {}", err)),
+                },
+                Cardinality::MultiValues => match (column_reader(0), column_reader(1)) {
+                    (Ok(idx_reader), Ok(vals_reader)) => {
+                        if !idx_reader.has_num_vals(max_doc + 1) {
+                            Some(format!("does not have {} offsets", max_doc + 1))
+                        } else {
+                            let num_vals = idx_reader.get_u64(max_doc);
+                            if vals_reader.has_num_vals(num_vals) {
+                                None
+                            } else {
+                                Some(format!("does not have {} values", num_vals))
+                            }
+                        }
+                    }
+                    (Err(err), _) | (_, Err(err)) => Some(format!("cannot be opened: {}", err)),
+                },
+            };
+            if let Some(problem) = problem {
+                problems.push(format!(
+                    "The fast field {:?} {}.",
+                    field_entry.name(),
+                    problem
+                ));
+            }
+        }
+        problems
+    }
+
+    /// Returns the data of all the columns of the fast field `field`, e.g. the offsets and
+    /// the values of a multi-valued fast field.
+    pub(crate) fn all_fast_field_data(&self, field: Field) -> Vec<FileSlice> {
+        (0..)
+            .map_while(|idx| self.fast_fields_composite.open_read_with_idx(field, idx))
+            .collect()
+    }
+
     fn fast_field_data(&self, field: Field, idx: usize) -> crate::Result<FileSlice> {
         self.fast_fields_composite
             .open_read_with_idx(field, idx)
@@ -105,7 +221,7 @@ impl FastFieldReaders {
         index: usize,
     ) -> crate::Result<DynamicFastFieldReader<TFastValue>> {
         let fast_field_slice = self.fast_field_data(field, index)?;
-        DynamicFastFieldReader::open(fast_field_slice)
+        DynamicFastFieldReader::open_with_scale(fast_field_slice, self.value_scale(field, index))
     }
     pub(crate) fn typed_fast_field_reader<TFastValue: FastValue>(
         &self,
@@ -114,6 +230,30 @@ impl FastFieldReaders {
         self.typed_fast_field_reader_with_idx(field, 0)
     }
 
+    /// Returns the reader of a single-valued fast field, aware of the documents without a value.
+    ///
+    /// Like `typed_fast_field_reader`, the type of the field is not checked.
+    pub(crate) fn typed_optional_fast_field_reader<TFastValue: FastValue>(
+        &self,
+        field: Field,
+    ) -> crate::Result<OptionalFastFieldReader<TFastValue>> {
+        let vals_reader = self.typed_fast_field_reader(field)?;
+        let is_single_valued =
+            type_and_cardinality(self.schema.get_field_entry(field).field_type())
+                .map(|(_, cardinality)| cardinality == Cardinality::SingleValue)
+                .unwrap_or(false);
+        // The null index is only serialized if some documents have no value.
+        let null_index = if is_single_valued {
+            self.fast_fields_composite
+                .open_read_with_idx(field, 1)
+                .map(NullIndex::open)
+                .transpose()?
+        } else {
+            None
+        };
+        Ok(OptionalFastFieldReader::open(vals_reader, null_index))
+    }
+
     pub(crate) fn typed_fast_field_multi_reader<TFastValue: FastValue>(
         &self,
         field: Field,
@@ -163,6 +303,105 @@ impl FastFieldReaders {
         self.typed_fast_field_reader(field)
     }
 
+    /// Returns the `bool` fast field reader reader associated to `field`.
+    ///
+    /// If `field` is not a bool fast field, this method returns an Error.
+    pub fn bool(&self, field: Field) -> crate::Result<DynamicFastFieldReader<bool>> {
+        self.check_type(field, FastType::Bool, Cardinality::SingleValue)?;
+        self.typed_fast_field_reader(field)
+    }
+
+    /// Returns the `u64` fast field reader associated to `field`, aware of the documents
+    /// without a value.
+    ///
+    /// If `field` is not a u64 fast field, this method returns an Error.
+    pub fn u64_opt(&self, field: Field) -> crate::Result<OptionalFastFieldReader<u64>> {
+        self.check_type(field, FastType::U64, Cardinality::SingleValue)?;
+        self.typed_optional_fast_field_reader(field)
+    }
+
+    /// Returns the `i64` fast field reader associated to `field`, aware of the documents
+    /// without a value.
+    ///
+    /// If `field` is not a i64 fast field, this method returns an Error.
+    pub fn i64_opt(&self, field: Field) -> crate::Result<OptionalFastFieldReader<i64>> {
+        self.check_type(field, FastType::I64, Cardinality::SingleValue)?;
+        self.typed_optional_fast_field_reader(field)
+    }
+
+    /// Returns the `f64` fast field reader associated to `field`, aware of the documents
+    /// without a value.
+    ///
+    /// If `field` is not a f64 fast field, this method returns an Error.
+    pub fn f64_opt(&self, field: Field) -> crate::Result<OptionalFastFieldReader<f64>> {
+        self.check_type(field, FastType::F64, Cardinality::SingleValue)?;
+        self.typed_optional_fast_field_reader(field)
+    }
+
+    /// Returns the `bool` fast field reader associated to `field`, aware of the documents
+    /// without a value.
+    ///
+    /// If `field` is not a bool fast field, this method returns an Error.
+    pub fn bool_opt(&self, field: Field) -> crate::Result<OptionalFastFieldReader<bool>> {
+        self.check_type(field, FastType::Bool, Cardinality::SingleValue)?;
+        self.typed_optional_fast_field_reader(field)
+    }
+
+    /// Returns the `crate::DateTime` fast field reader associated to `field`, aware of the
+    /// documents without a value.
+    ///
+    /// If `field` is not a date fast field, this method returns an Error.
+    pub fn date_opt(
+        &self,
+        field: Field,
+    ) -> crate::Result<OptionalFastFieldReader<crate::DateTime>> {
+        self.check_type(field, FastType::Date, Cardinality::SingleValue)?;
+        self.typed_optional_fast_field_reader(field)
+    }
+
+    fn check_single_valued(&self, field: Field) -> crate::Result<()> {
+        let field_entry = self.schema.get_field_entry(field);
+        match type_and_cardinality(field_entry.field_type()) {
+            Some((FastType::IpAddr, _)) | None => Err(crate::TantivyError::SchemaError(format!(
+                "Field {:?} is not an integer fast field.",
+                field_entry.name()
+            ))),
+            Some((_, Cardinality::MultiValues)) => Err(crate::TantivyError::SchemaError(format!(
+                "Field {:?} is of cardinality {:?}, expected {:?}.",
+                field_entry.name(),
+                Cardinality::MultiValues,
+                Cardinality::SingleValue
+            ))),
+            Some((_, Cardinality::SingleValue)) => Ok(()),
+        }
+    }
+
+    /// Returns the codec of the single-valued fast field `field`.
+    ///
+    /// If `field` is not a single-valued integer fast field, this method returns an Error.
+    pub fn codec_type(&self, field: Field) -> crate::Result<FastFieldCodecType> {
+        self.check_single_valued(field)?;
+        let reader: DynamicFastFieldReader<u64> = self.typed_fast_field_reader(field)?;
+        Ok(reader.codec_type())
+    }
+
+    /// Returns the number of bytes of the compressed values of the single-valued
+    /// fast field `field`.
+    ///
+    /// If `field` is not a single-valued integer fast field, this method returns an Error.
+    pub fn compressed_size(&self, field: Field) -> crate::Result<usize> {
+        self.check_single_valued(field)?;
+        Ok(self.fast_field_data(field, 0)?.len())
+    }
+
+    /// Returns the ip address fast field reader associated to `field`.
+    ///
+    /// If `field` is not an ip address fast field, this method returns an Error.
+    pub fn ip_addr(&self, field: Field) -> crate::Result<U128FastFieldReader> {
+        self.check_type(field, FastType::IpAddr, Cardinality::SingleValue)?;
+        U128FastFieldReader::open(self.fast_field_data(field, 0)?)
+    }
+
     /// Returns a `u64s` multi-valued fast field reader reader associated to `field`.
     ///
     /// If `field` is not a u64 multi-valued fast field, this method returns an Error.
@@ -195,6 +434,14 @@ impl FastFieldReaders {
         self.typed_fast_field_multi_reader(field)
     }
 
+    /// Returns a `bools` multi-valued fast field reader reader associated to `field`.
+    ///
+    /// If `field` is not a bool multi-valued fast field, this method returns an Error.
+    pub fn bools(&self, field: Field) -> crate::Result<MultiValuedFastFieldReader<bool>> {
+        self.check_type(field, FastType::Bool, Cardinality::MultiValues)?;
+        self.typed_fast_field_multi_reader(field)
+    }
+
     /// Returns a `crate::DateTime` multi-valued fast field reader reader associated to `field`.
     ///
     /// If `field` is not a `crate::DateTime` multi-valued fast field, this method returns an Error.
@@ -219,9 +466,16 @@ impl FastFieldReaders {
                 )));
             }
             let fast_field_idx_file = self.fast_field_data(field, 0)?;
-            let idx_reader = BitpackedFastFieldReader::open(fast_field_idx_file)?;
+            let idx_reader = DynamicFastFieldReader::open(fast_field_idx_file)?;
             let data = self.fast_field_data(field, 1)?;
-            BytesFastFieldReader::open(idx_reader, data)
+            // The value offsets are missing in the segments written before
+            // bytes fast fields were multivalued.
+            let val_offsets_reader = self
+                .fast_fields_composite
+                .open_read_with_idx(field, 2)
+                .map(DynamicFastFieldReader::open)
+                .transpose()?;
+            BytesFastFieldReader::open(idx_reader, val_offsets_reader, data, self.max_doc)
         } else {
             Err(FastFieldNotAvailableError::new(field_entry).into())
         }
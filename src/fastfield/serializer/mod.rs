@@ -1,10 +1,12 @@
 use crate::directory::CompositeWrite;
 use crate::directory::WritePtr;
-use crate::schema::Field;
+use crate::fastfield::null_index::write_null_index;
+use crate::schema::{FastFieldCodecType, Field};
 use common::BinarySerializable;
 use common::CountingWriter;
 pub use fastfield_codecs::bitpacked::BitpackedFastFieldSerializer;
 pub use fastfield_codecs::bitpacked::BitpackedFastFieldSerializerLegacy;
+use fastfield_codecs::bitpacked_u128::BitpackedU128FastFieldSerializer;
 use fastfield_codecs::linearinterpol::LinearInterpolFastFieldSerializer;
 use fastfield_codecs::multilinearinterpol::MultiLinearInterpolFastFieldSerializer;
 pub use fastfield_codecs::FastFieldCodecSerializer;
@@ -50,6 +52,15 @@ fn codec_estimation<T: FastFieldCodecSerializer, A: FastFieldDataAccess>(
     estimations.push((ratio, name, id));
 }
 
+/// Returns the id of the codec, which prefixes the data of the fast fields.
+pub(crate) fn codec_id(codec_type: FastFieldCodecType) -> u8 {
+    match codec_type {
+        FastFieldCodecType::Bitpacked => BitpackedFastFieldSerializer::ID,
+        FastFieldCodecType::LinearInterpol => LinearInterpolFastFieldSerializer::ID,
+        FastFieldCodecType::MultiLinearInterpol => MultiLinearInterpolFastFieldSerializer::ID,
+    }
+}
+
 impl CompositeFastFieldSerializer {
     /// Constructor
     pub fn from_write(write: WritePtr) -> io::Result<CompositeFastFieldSerializer> {
@@ -85,6 +96,32 @@ impl CompositeFastFieldSerializer {
         data_iter_1: impl Iterator<Item = u64>,
         data_iter_2: impl Iterator<Item = u64>,
         idx: usize,
+    ) -> io::Result<()> {
+        self.create_u64_fast_field_with_codec_and_idx(
+            field,
+            None,
+            stats,
+            fastfield_accessor,
+            data_iter_1,
+            data_iter_2,
+            idx,
+        )
+    }
+
+    /// Serialize data into a new u64 fast field, using the given codec.
+    ///
+    /// If no codec is given, or if the given codec cannot be used for the data,
+    /// the best compression codec will be chosen automatically.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_u64_fast_field_with_codec_and_idx(
+        &mut self,
+        field: Field,
+        codec_type: Option<FastFieldCodecType>,
+        stats: FastFieldStats,
+        fastfield_accessor: impl FastFieldDataAccess,
+        data_iter_1: impl Iterator<Item = u64>,
+        data_iter_2: impl Iterator<Item = u64>,
+        idx: usize,
     ) -> io::Result<()> {
         let field_write = self.composite_write.for_field_with_idx(field, idx);
 
@@ -115,7 +152,21 @@ impl CompositeFastFieldSerializer {
         // removing nan values for codecs with broken calculations, and max values which disables codecs
         estimations.retain(|estimation| !estimation.0.is_nan() && estimation.0 != f32::MAX);
         estimations.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-        let (_ratio, name, id) = estimations[0];
+        let requested_estimation = codec_type.and_then(|codec_type| {
+            let requested_id = codec_id(codec_type);
+            let requested_estimation = estimations
+                .iter()
+                .find(|estimation| estimation.2 == requested_id)
+                .cloned();
+            if requested_estimation.is_none() {
+                warn!(
+                    "fast field codec {:?} cannot be used for field_id {:?}, choosing the codec automatically",
+                    codec_type, field
+                );
+            }
+            requested_estimation
+        });
+        let (_ratio, name, id) = requested_estimation.unwrap_or(estimations[0]);
         debug!(
             "choosing fast field codec {} for field_id {:?}",
             name, field
@@ -158,6 +209,35 @@ impl CompositeFastFieldSerializer {
         Ok(())
     }
 
+    /// Serialize data into a new u128 fast field.
+    ///
+    /// The values are iterated over several times.
+    pub fn create_u128_fast_field_with_idx(
+        &mut self,
+        field: Field,
+        vals: impl Iterator<Item = u128> + Clone,
+        idx: usize,
+    ) -> io::Result<()> {
+        let field_write = self.composite_write.for_field_with_idx(field, idx);
+        BitpackedU128FastFieldSerializer::serialize(field_write, vals)?;
+        field_write.flush()?;
+        Ok(())
+    }
+
+    /// Serialize the null index of a single-valued fast field, given whether each
+    /// document has a value.
+    pub fn create_null_index_with_idx(
+        &mut self,
+        field: Field,
+        has_vals: impl Iterator<Item = bool>,
+        idx: usize,
+    ) -> io::Result<()> {
+        let field_write = self.composite_write.for_field_with_idx(field, idx);
+        write_null_index(has_vals, field_write)?;
+        field_write.flush()?;
+        Ok(())
+    }
+
     /// Start serializing a new u64 fast field
     pub fn new_u64_fast_field(
         &mut self,
@@ -0,0 +1,88 @@
+mod reader;
+mod writer;
+
+pub use self::reader::U128FastFieldReader;
+pub use self::writer::U128FastFieldWriter;
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::TopDocs;
+    use crate::fastfield::FastFieldReader;
+    use crate::query::AllQuery;
+    use crate::schema::ip_addr_to_ipv6;
+    use crate::schema::{Document, Schema, FAST, INDEXED, STORED};
+    use crate::{DocId, Index, SegmentReader};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn test_ip_addr_fast_field() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let ip = schema_builder.add_ip_addr_field("ip", FAST | STORED);
+        let id = schema_builder.add_u64_field("id", INDEXED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let ip_addrs: Vec<IpAddr> = vec![
+            IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)),
+            IpAddr::V6("2001:db8::1".parse().unwrap()),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+        ];
+        for (i, ip_addr) in ip_addrs.iter().enumerate() {
+            let mut doc = Document::new();
+            doc.add_ip_addr(ip, *ip_addr);
+            doc.add_u64(id, i as u64);
+            index_writer.add_document(doc);
+            index_writer.commit()?;
+        }
+        // a document without an ip address gets the unspecified address.
+        index_writer.add_document(doc!(id => 3u64));
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        let ip_reader = segment_reader.fast_fields().ip_addr(ip)?;
+        let id_reader = segment_reader.fast_fields().u64(id)?;
+        for doc in 0..segment_reader.max_doc() {
+            let expected = ip_addrs
+                .get(id_reader.get(doc) as usize)
+                .map(|ip_addr| ip_addr_to_ipv6(*ip_addr))
+                .unwrap_or(Ipv6Addr::UNSPECIFIED);
+            assert_eq!(ip_reader.get_ip_addr(doc), expected);
+        }
+        assert_eq!(ip_reader.min_value(), 0);
+        assert_eq!(
+            ip_reader.max_value(),
+            u128::from("2001:db8::1".parse::<Ipv6Addr>().unwrap())
+        );
+
+        // documents can be sorted by their ip address.
+        let top_docs = searcher.search(
+            &AllQuery,
+            &TopDocs::with_limit(4).custom_score(move |segment_reader: &SegmentReader| {
+                let ip_reader = segment_reader.fast_fields().ip_addr(ip).unwrap();
+                move |doc: DocId| ip_reader.get(doc)
+            }),
+        )?;
+        let sorted_ip_addrs: Vec<Option<Ipv6Addr>> = top_docs
+            .iter()
+            .map(|(_, doc_address)| {
+                let doc = searcher.doc(*doc_address).unwrap();
+                doc.get_first(ip).and_then(|value| value.ip_addr_value())
+            })
+            .collect();
+        assert_eq!(
+            sorted_ip_addrs,
+            vec![
+                Some("2001:db8::1".parse().unwrap()),
+                Some(Ipv4Addr::new(192, 168, 0, 1).to_ipv6_mapped()),
+                Some(Ipv4Addr::new(10, 0, 0, 1).to_ipv6_mapped()),
+                None,
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use std::net::Ipv6Addr;
+
+use fastfield_codecs::bitpacked_u128::BitpackedU128FastFieldReader;
+
+use crate::directory::FileSlice;
+use crate::directory::OwnedBytes;
+use crate::DocId;
+
+/// Reader for `u128` fast fields, e.g. ip address fast fields.
+///
+/// The values are bitpacked, and ip addresses are stored as their `u128` representation,
+/// which preserves their order.
+#[derive(Clone)]
+pub struct U128FastFieldReader {
+    reader: BitpackedU128FastFieldReader,
+    data: OwnedBytes,
+}
+
+impl U128FastFieldReader {
+    pub(crate) fn open(file: FileSlice) -> crate::Result<U128FastFieldReader> {
+        let data = file.read_bytes()?;
+        let reader = BitpackedU128FastFieldReader::open_from_bytes(data.as_slice())?;
+        Ok(U128FastFieldReader { reader, data })
+    }
+
+    /// Returns the value associated to the given `doc`.
+    pub fn get(&self, doc: DocId) -> u128 {
+        self.reader.get_u128(u64::from(doc), self.data.as_slice())
+    }
+
+    /// Returns the ip address associated to the given `doc`.
+    pub fn get_ip_addr(&self, doc: DocId) -> Ipv6Addr {
+        Ipv6Addr::from(self.get(doc))
+    }
+
+    /// Returns the minimum value for this fast field.
+    ///
+    /// The min value does not take in account of possible
+    /// deleted document, and should be considered as a lower bound
+    /// of the actual mimimum value.
+    pub fn min_value(&self) -> u128 {
+        self.reader.min_value()
+    }
+
+    /// Returns the maximum value for this fast field.
+    ///
+    /// The max value does not take in account of possible
+    /// deleted document, and should be considered as an upper bound
+    /// of the actual maximum value.
+    pub fn max_value(&self) -> u128 {
+        self.reader.max_value()
+    }
+}
@@ -0,0 +1,63 @@
+use std::io;
+
+use crate::fastfield::CompositeFastFieldSerializer;
+use crate::indexer::doc_id_mapping::DocIdMapping;
+use crate::schema::{Document, Field, Value};
+
+/// Writer for `u128` fast fields, e.g. ip address fast fields.
+///
+/// The writer keeps the values in memory until the segment is serialized,
+/// as the number of bits required for bitpacking is only known once all of
+/// the values have been seen.
+///
+/// Documents without a value get the value `0`, i.e. the ip address `::`.
+pub struct U128FastFieldWriter {
+    field: Field,
+    vals: Vec<u128>,
+}
+
+impl U128FastFieldWriter {
+    /// Creates a new `U128FastFieldWriter`
+    pub fn new(field: Field) -> Self {
+        U128FastFieldWriter {
+            field,
+            vals: Vec::new(),
+        }
+    }
+
+    /// The memory used (inclusive childs)
+    pub fn mem_usage(&self) -> usize {
+        self.vals.capacity() * std::mem::size_of::<u128>()
+    }
+
+    /// Access the field associated to the `U128FastFieldWriter`
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    /// Extracts the fast field value from the document
+    /// (or use the default value) and records it.
+    pub fn add_document(&mut self, doc: &Document) {
+        let val = match doc.get_first(self.field) {
+            Some(Value::IpAddr(ip_addr)) => u128::from(*ip_addr),
+            _ => 0,
+        };
+        self.vals.push(val);
+    }
+
+    /// Serializes the fast field values by pushing them to the `FastFieldSerializer`.
+    pub fn serialize(
+        &self,
+        serializer: &mut CompositeFastFieldSerializer,
+        doc_id_map: Option<&DocIdMapping>,
+    ) -> io::Result<()> {
+        if let Some(doc_id_map) = doc_id_map {
+            let vals = doc_id_map
+                .iter_old_doc_ids()
+                .map(|doc| self.vals[doc as usize]);
+            serializer.create_u128_fast_field_with_idx(self.field, vals, 0)
+        } else {
+            serializer.create_u128_fast_field_with_idx(self.field, self.vals.iter().cloned(), 0)
+        }
+    }
+}
@@ -1,11 +1,14 @@
 use super::multivalued::MultiValuedFastFieldWriter;
 use super::serializer::FastFieldStats;
 use super::FastFieldDataAccess;
-use crate::fastfield::{BytesFastFieldWriter, CompositeFastFieldSerializer};
+use crate::fastfield::{BytesFastFieldWriter, CompositeFastFieldSerializer, U128FastFieldWriter};
 use crate::indexer::doc_id_mapping::DocIdMapping;
 use crate::postings::UnorderedTermId;
-use crate::schema::{Cardinality, Document, Field, FieldEntry, FieldType, Schema};
+use crate::schema::{
+    Cardinality, DatePrecision, Document, FastFieldCodecType, Field, FieldEntry, FieldType, Schema,
+};
 use crate::termdict::TermOrdinal;
+use crate::DocId;
 use common;
 use fnv::FnvHashMap;
 use std::collections::HashMap;
@@ -17,6 +20,36 @@ pub struct FastFieldsWriter {
     single_value_writers: Vec<IntFastFieldWriter>,
     multi_values_writers: Vec<MultiValuedFastFieldWriter>,
     bytes_value_writers: Vec<BytesFastFieldWriter>,
+    u128_value_writers: Vec<U128FastFieldWriter>,
+}
+
+/// Returns the cardinality, the codec and the date precision of an integer, float,
+/// bool or date fast field, or `None` if the field is not one of them.
+fn int_fast_field_options(
+    field_type: &FieldType,
+) -> Option<(Cardinality, Option<FastFieldCodecType>, DatePrecision)> {
+    match field_type {
+        FieldType::I64(ref int_options)
+        | FieldType::U64(ref int_options)
+        | FieldType::F64(ref int_options)
+        | FieldType::Bool(ref int_options) => {
+            let cardinality = int_options.get_fastfield_cardinality()?;
+            Some((
+                cardinality,
+                int_options.get_fast_codec(),
+                DatePrecision::default(),
+            ))
+        }
+        FieldType::Date(ref date_options) => {
+            let cardinality = date_options.get_fastfield_cardinality()?;
+            Some((
+                cardinality,
+                date_options.get_fast_codec(),
+                date_options.get_precision(),
+            ))
+        }
+        _ => None,
+    }
 }
 
 fn fast_field_default_value(field_entry: &FieldEntry) -> u64 {
@@ -33,25 +66,34 @@ impl FastFieldsWriter {
         let mut single_value_writers = Vec::new();
         let mut multi_values_writers = Vec::new();
         let mut bytes_value_writers = Vec::new();
+        let mut u128_value_writers = Vec::new();
 
         for (field, field_entry) in schema.fields() {
+            if let Some((cardinality, codec_type, date_precision)) =
+                int_fast_field_options(field_entry.field_type())
+            {
+                match cardinality {
+                    Cardinality::SingleValue => {
+                        let mut fast_field_writer = IntFastFieldWriter::new(field);
+                        let default_value = fast_field_default_value(field_entry);
+                        fast_field_writer.set_val_if_missing(default_value);
+                        fast_field_writer.set_codec_type(codec_type);
+                        fast_field_writer.set_date_precision(date_precision);
+                        single_value_writers.push(fast_field_writer);
+                    }
+                    Cardinality::MultiValues => {
+                        let mut fast_field_writer = MultiValuedFastFieldWriter::new(field, false);
+                        fast_field_writer.set_date_precision(date_precision);
+                        multi_values_writers.push(fast_field_writer);
+                    }
+                }
+                continue;
+            }
             match field_entry.field_type() {
-                FieldType::I64(ref int_options)
-                | FieldType::U64(ref int_options)
-                | FieldType::F64(ref int_options)
-                | FieldType::Date(ref int_options) => {
-                    match int_options.get_fastfield_cardinality() {
-                        Some(Cardinality::SingleValue) => {
-                            let mut fast_field_writer = IntFastFieldWriter::new(field);
-                            let default_value = fast_field_default_value(field_entry);
-                            fast_field_writer.set_val_if_missing(default_value);
-                            single_value_writers.push(fast_field_writer);
-                        }
-                        Some(Cardinality::MultiValues) => {
-                            let fast_field_writer = MultiValuedFastFieldWriter::new(field, false);
-                            multi_values_writers.push(fast_field_writer);
-                        }
-                        None => {}
+                FieldType::IpAddr(ref int_options) => {
+                    if int_options.is_fast() {
+                        let fast_field_writer = U128FastFieldWriter::new(field);
+                        u128_value_writers.push(fast_field_writer);
                     }
                 }
                 FieldType::HierarchicalFacet(_) => {
@@ -71,6 +113,7 @@ impl FastFieldsWriter {
             single_value_writers,
             multi_values_writers,
             bytes_value_writers,
+            u128_value_writers,
         }
     }
 
@@ -90,6 +133,11 @@ impl FastFieldsWriter {
                 .iter()
                 .map(|w| w.mem_usage())
                 .sum::<usize>()
+            + self
+                .u128_value_writers
+                .iter()
+                .map(|w| w.mem_usage())
+                .sum::<usize>()
     }
 
     /// Get the `FastFieldWriter` associated to a field.
@@ -144,6 +192,9 @@ impl FastFieldsWriter {
         for field_writer in &mut self.bytes_value_writers {
             field_writer.add_document(doc);
         }
+        for field_writer in &mut self.u128_value_writers {
+            field_writer.add_document(doc);
+        }
     }
 
     /// Serializes all of the `FastFieldWriter`s by pushing them in
@@ -165,6 +216,9 @@ impl FastFieldsWriter {
         for field_writer in &self.bytes_value_writers {
             field_writer.serialize(serializer, doc_id_map)?;
         }
+        for field_writer in &self.u128_value_writers {
+            field_writer.serialize(serializer, doc_id_map)?;
+        }
         Ok(())
     }
 }
@@ -184,11 +238,17 @@ impl FastFieldsWriter {
 /// Both u64, i64 and f64 use the same writer.
 /// i64 and f64 are just remapped to the `0..2^64 - 1`
 /// using `common::i64_to_u64` and `common::f64_to_u64`.
+///
+/// The documents without a value are recorded, and serialized
+/// as the null index of the fast field.
 pub struct IntFastFieldWriter {
     field: Field,
     vals: BlockedBitpacker,
     val_count: usize,
+    docs_without_val: Vec<DocId>,
     val_if_missing: u64,
+    codec_type: Option<FastFieldCodecType>,
+    date_precision: DatePrecision,
     val_min: u64,
     val_max: u64,
 }
@@ -200,7 +260,10 @@ impl IntFastFieldWriter {
             field,
             vals: BlockedBitpacker::new(),
             val_count: 0,
+            docs_without_val: Vec::new(),
             val_if_missing: 0u64,
+            codec_type: None,
+            date_precision: DatePrecision::default(),
             val_min: u64::max_value(),
             val_max: 0,
         }
@@ -208,7 +271,7 @@ impl IntFastFieldWriter {
 
     /// The memory used (inclusive childs)
     pub fn mem_usage(&self) -> usize {
-        self.vals.mem_usage()
+        self.vals.mem_usage() + self.docs_without_val.capacity() * std::mem::size_of::<DocId>()
     }
 
     /// Returns the field that this writer is targetting.
@@ -224,6 +287,18 @@ impl IntFastFieldWriter {
         self.val_if_missing = val_if_missing;
     }
 
+    /// Sets the codec used to serialize the values.
+    ///
+    /// If `None`, the codec is chosen automatically.
+    fn set_codec_type(&mut self, codec_type: Option<FastFieldCodecType>) {
+        self.codec_type = codec_type;
+    }
+
+    /// Sets the precision to which the date values are truncated.
+    fn set_date_precision(&mut self, date_precision: DatePrecision) {
+        self.date_precision = date_precision;
+    }
+
     /// Records a new value.
     ///
     /// The n-th value being recorded is implicitely
@@ -242,30 +317,26 @@ impl IntFastFieldWriter {
         self.val_count += 1;
     }
 
-    /// Extract the value associated to the fast field for
-    /// this document.
+    /// Extract the fast field value from the document
+    /// (or use the default value) and records it.
     ///
     /// i64 and f64 are remapped to u64 using the logic
     /// in `common::i64_to_u64` and `common::f64_to_u64`.
     ///
     /// If the value is missing, then the default value is used
-    /// instead.
+    /// instead, and the document is recorded as having no value.
     /// If the document has more than one value for the given field,
     /// only the first one is taken in account.
-    fn extract_val(&self, doc: &Document) -> u64 {
+    pub fn add_document(&mut self, doc: &Document) {
         match doc.get_first(self.field) {
-            Some(v) => super::value_to_u64(v),
-            None => self.val_if_missing,
+            Some(v) => self.add_val(super::value_to_u64(v, self.date_precision)),
+            None => {
+                self.docs_without_val.push(self.val_count as DocId);
+                self.add_val(self.val_if_missing);
+            }
         }
     }
 
-    /// Extract the fast field value from the document
-    /// (or use the default value) and records it.
-    pub fn add_document(&mut self, doc: &Document) {
-        let val = self.extract_val(doc);
-        self.add_val(val);
-    }
-
     /// get iterator over the data
     pub(crate) fn iter(&self) -> impl Iterator<Item = u64> + '_ {
         self.vals.iter()
@@ -296,22 +367,35 @@ impl IntFastFieldWriter {
             let iter = doc_id_map
                 .iter_old_doc_ids()
                 .map(|doc_id| self.vals.get(doc_id as usize));
-            serializer.create_auto_detect_u64_fast_field(
+            serializer.create_u64_fast_field_with_codec_and_idx(
                 self.field,
+                self.codec_type,
                 stats,
                 fastfield_accessor,
                 iter.clone(),
                 iter,
+                0,
             )?;
         } else {
-            serializer.create_auto_detect_u64_fast_field(
+            serializer.create_u64_fast_field_with_codec_and_idx(
                 self.field,
+                self.codec_type,
                 stats,
                 fastfield_accessor,
                 self.vals.iter(),
                 self.vals.iter(),
+                0,
             )?;
         };
+        if !self.docs_without_val.is_empty() {
+            let has_vals = (0..self.val_count as DocId).map(|doc| {
+                let old_doc = doc_id_map
+                    .map(|doc_id_map| doc_id_map.get_old_doc_id(doc))
+                    .unwrap_or(doc);
+                self.docs_without_val.binary_search(&old_doc).is_err()
+            });
+            serializer.create_null_index_with_idx(self.field, has_vals, 1)?;
+        }
         Ok(())
     }
 }
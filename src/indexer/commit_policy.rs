@@ -0,0 +1,170 @@
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Defines when an `IndexWriter` commits automatically, from its own thread.
+///
+/// See [`IndexWriter::set_commit_policy`](../struct.IndexWriter.html#method.set_commit_policy).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CommitPolicy {
+    /// The `IndexWriter` only commits when `commit()` is called. This is the default.
+    #[default]
+    Manual,
+    /// Commits the pending operations once the given duration has elapsed since the
+    /// last commit.
+    ///
+    /// Nothing is committed if there are no pending operations.
+    Interval(Duration),
+    /// Commits the pending operations once the given number of documents have been
+    /// added since the last commit.
+    DocCount(u64),
+}
+
+struct TriggerState {
+    policy: CommitPolicy,
+    // The number of documents added, and of operations, since the last commit.
+    num_docs: u64,
+    num_operations: u64,
+    last_commit: Instant,
+    is_stopped: bool,
+}
+
+/// Tracks the operations since the last commit, to tell the auto-commit thread
+/// when a commit is due according to the `CommitPolicy`.
+pub(crate) struct CommitTrigger {
+    state: Mutex<TriggerState>,
+    condvar: Condvar,
+}
+
+impl Default for CommitTrigger {
+    fn default() -> Self {
+        CommitTrigger {
+            state: Mutex::new(TriggerState {
+                policy: CommitPolicy::Manual,
+                num_docs: 0,
+                num_operations: 0,
+                last_commit: Instant::now(),
+                is_stopped: false,
+            }),
+            condvar: Condvar::new(),
+        }
+    }
+}
+
+impl CommitTrigger {
+    pub fn policy(&self) -> CommitPolicy {
+        self.state.lock().unwrap().policy
+    }
+
+    pub fn set_policy(&self, policy: CommitPolicy) {
+        self.state.lock().unwrap().policy = policy;
+        self.condvar.notify_all();
+    }
+
+    /// Records an operation adding `num_docs` documents.
+    pub fn record_operation(&self, num_docs: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.num_docs += num_docs;
+        state.num_operations += 1;
+        if let CommitPolicy::DocCount(max_num_docs) = state.policy {
+            if state.num_docs >= max_num_docs {
+                self.condvar.notify_all();
+            }
+        }
+    }
+
+    /// Resets the count of the operations and the timer, after a commit.
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.num_docs = 0;
+        state.num_operations = 0;
+        state.last_commit = Instant::now();
+    }
+
+    /// Stops the auto-commit thread.
+    pub fn stop(&self) {
+        self.state.lock().unwrap().is_stopped = true;
+        self.condvar.notify_all();
+    }
+
+    /// Blocks until a commit is due.
+    ///
+    /// Returns false if the trigger was stopped.
+    pub fn wait_for_commit(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.is_stopped {
+                return false;
+            }
+            match state.policy {
+                CommitPolicy::Manual => {
+                    state = self.condvar.wait(state).unwrap();
+                }
+                CommitPolicy::Interval(interval) => {
+                    let elapsed = state.last_commit.elapsed();
+                    if elapsed < interval {
+                        state = self
+                            .condvar
+                            .wait_timeout(state, interval - elapsed)
+                            .unwrap()
+                            .0;
+                    } else if state.num_operations > 0 {
+                        return true;
+                    } else {
+                        // there is nothing to commit, the next commit is due after
+                        // another interval.
+                        state.last_commit = Instant::now();
+                    }
+                }
+                CommitPolicy::DocCount(max_num_docs) => {
+                    if state.num_docs >= max_num_docs {
+                        return true;
+                    }
+                    state = self.condvar.wait(state).unwrap();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommitPolicy, CommitTrigger};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_commit_trigger_doc_count() {
+        let commit_trigger = Arc::new(CommitTrigger::default());
+        commit_trigger.set_policy(CommitPolicy::DocCount(3));
+        let waiting_trigger = commit_trigger.clone();
+        let waiting_thread = thread::spawn(move || waiting_trigger.wait_for_commit());
+        commit_trigger.record_operation(2);
+        commit_trigger.record_operation(0);
+        commit_trigger.record_operation(1);
+        assert!(waiting_thread.join().unwrap());
+        commit_trigger.reset();
+        commit_trigger.stop();
+        assert!(!commit_trigger.wait_for_commit());
+    }
+
+    #[test]
+    fn test_commit_trigger_interval() {
+        let commit_trigger = CommitTrigger::default();
+        commit_trigger.set_policy(CommitPolicy::Interval(Duration::from_millis(20)));
+        commit_trigger.record_operation(0);
+        let start = Instant::now();
+        assert!(commit_trigger.wait_for_commit());
+        assert!(start.elapsed() >= Duration::from_millis(15));
+    }
+
+    #[test]
+    fn test_commit_trigger_stop() {
+        let commit_trigger = Arc::new(CommitTrigger::default());
+        let waiting_trigger = commit_trigger.clone();
+        let waiting_thread = thread::spawn(move || waiting_trigger.wait_for_commit());
+        commit_trigger.record_operation(1_000);
+        commit_trigger.stop();
+        assert!(!waiting_thread.join().unwrap());
+    }
+}
@@ -1,4 +1,4 @@
-use super::operation::DeleteOperation;
+use super::operation::{DeleteOperation, DeleteTarget};
 use crate::Opstamp;
 
 use std::ops::DerefMut;
@@ -82,6 +82,21 @@ impl DeleteQueue {
             .push(delete_operation);
     }
 
+    // Appends a new delete operation, stamped with the opstamp returned
+    // by `stamp`.
+    //
+    // The stamp is generated while holding the lock on the queue, so that
+    // concurrent delete operations are appended in increasing opstamp order.
+    pub fn push_stamped<F: FnOnce() -> Opstamp>(&self, stamp: F, target: DeleteTarget) -> Opstamp {
+        let mut wlock = self
+            .inner
+            .write()
+            .expect("Failed to acquire write lock on delete queue writer");
+        let opstamp = stamp();
+        wlock.writer.push(DeleteOperation { opstamp, target });
+        opstamp
+    }
+
     // DeleteQueue is a linked list of blocks of
     // delete operations.
     //
@@ -247,7 +262,7 @@ impl DeleteCursor {
 #[cfg(test)]
 mod tests {
 
-    use super::{DeleteOperation, DeleteQueue};
+    use super::{DeleteOperation, DeleteQueue, DeleteTarget};
     use crate::schema::{Field, Term};
 
     #[test]
@@ -258,7 +273,7 @@ mod tests {
             let field = Field::from_field_id(1u32);
             DeleteOperation {
                 opstamp: i as u64,
-                term: Term::from_field_u64(field, i as u64),
+                target: DeleteTarget::Term(Term::from_field_u64(field, i as u64)),
             }
         };
 
@@ -5,7 +5,7 @@
 use super::{merger::SegmentReaderWithOrdinal, SegmentWriter};
 use crate::{
     schema::{Field, Schema},
-    DocId, IndexSortByField, Order, TantivyError,
+    DocId, IndexSortByField, Order, SegmentId, TantivyError,
 };
 use std::{cmp::Reverse, ops::Index};
 
@@ -77,6 +77,74 @@ impl DocIdMapping {
     }
 }
 
+// The new doc_id of the deleted documents in a `MergeDocMapping`.
+const DELETED_DOC: DocId = DocId::MAX;
+
+/// Mapping from the doc_ids of the merged segments to the doc_ids of the
+/// segment resulting from a merge.
+///
+/// The merged segments are identified by their `SegmentId`, or by their ordinal,
+/// which is their position in [`segment_ids()`](#method.segment_ids).
+/// The deleted documents are not part of the merged segment, and map to `None`.
+/// If the index is sorted, the new doc_ids follow the sort order.
+///
+/// The mapping takes 4 bytes per document of the merged segments.
+pub struct MergeDocMapping {
+    segment_ids: Vec<SegmentId>,
+    // For each merged segment, the new doc_id of each old doc_id.
+    old_doc_id_to_new: Vec<Vec<DocId>>,
+}
+
+impl MergeDocMapping {
+    pub(crate) fn from_segment_doc_id_mapping(
+        doc_id_mapping: &SegmentDocidMapping,
+        segment_ids: Vec<SegmentId>,
+        max_docs: &[DocId],
+    ) -> MergeDocMapping {
+        let mut old_doc_id_to_new: Vec<Vec<DocId>> = max_docs
+            .iter()
+            .map(|&max_doc| vec![DELETED_DOC; max_doc as usize])
+            .collect();
+        for (new_doc_id, (old_doc_id, reader_with_ordinal)) in doc_id_mapping.iter().enumerate() {
+            old_doc_id_to_new[reader_with_ordinal.ordinal as usize][*old_doc_id as usize] =
+                new_doc_id as DocId;
+        }
+        MergeDocMapping {
+            segment_ids,
+            old_doc_id_to_new,
+        }
+    }
+
+    /// Returns the ids of the merged segments, in the order of their ordinal.
+    ///
+    /// The segments whose documents were all deleted are not included.
+    pub fn segment_ids(&self) -> &[SegmentId] {
+        &self.segment_ids
+    }
+
+    /// Returns the new doc_id of the document `old_doc_id` of the merged segment
+    /// with the ordinal `segment_ord`, or `None` if the document was deleted.
+    ///
+    /// # Panics
+    /// Panics if the segment ordinal or the doc_id are out of bounds.
+    pub fn map(&self, segment_ord: u32, old_doc_id: DocId) -> Option<DocId> {
+        Some(self.old_doc_id_to_new[segment_ord as usize][old_doc_id as usize])
+            .filter(|&new_doc_id| new_doc_id != DELETED_DOC)
+    }
+
+    /// Returns the new doc_id of the document `old_doc_id` of the merged segment
+    /// `segment_id`, or `None` if the document was deleted or the segment is not
+    /// one of the segments of the mapping.
+    pub fn map_segment_doc(&self, segment_id: SegmentId, old_doc_id: DocId) -> Option<DocId> {
+        let segment_ord = self.segment_ids.iter().position(|&id| id == segment_id)?;
+        let old_doc_id_to_new = &self.old_doc_id_to_new[segment_ord];
+        old_doc_id_to_new
+            .get(old_doc_id as usize)
+            .cloned()
+            .filter(|&new_doc_id| new_doc_id != DELETED_DOC)
+    }
+}
+
 pub(crate) fn expect_field_id_for_sort_field(
     schema: &Schema,
     sort_by_field: &IndexSortByField,
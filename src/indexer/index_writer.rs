@@ -1,7 +1,9 @@
+use super::commit_policy::{CommitPolicy, CommitTrigger};
 use super::operation::{AddOperation, UserOperation};
 use super::segment_updater::SegmentUpdater;
 use super::PreparedCommit;
 use crate::core::Index;
+use crate::core::Searcher;
 use crate::core::Segment;
 use crate::core::SegmentComponent;
 use crate::core::SegmentId;
@@ -14,14 +16,18 @@ use crate::error::TantivyError;
 use crate::fastfield::write_delete_bitset;
 use crate::indexer::delete_queue::{DeleteCursor, DeleteQueue};
 use crate::indexer::doc_opstamp_mapping::DocToOpstampMapping;
-use crate::indexer::operation::DeleteOperation;
+use crate::indexer::operation::{DeleteOperation, DeleteTarget};
 use crate::indexer::stamper::Stamper;
+use crate::indexer::IndexWriterEvents;
+use crate::indexer::MergeOperationInfo;
 use crate::indexer::MergePolicy;
 use crate::indexer::SegmentEntry;
 use crate::indexer::SegmentWriter;
+use crate::query::Query;
 use crate::schema::Document;
 use crate::schema::IndexRecordOption;
 use crate::schema::Term;
+use crate::schema::Value;
 use crate::Opstamp;
 use common::BitSet;
 use crossbeam::channel;
@@ -29,11 +35,13 @@ use futures::executor::block_on;
 use futures::future::Future;
 use smallvec::smallvec;
 use smallvec::SmallVec;
+use std::iter::Peekable;
 use std::mem;
 use std::ops::Range;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::Duration;
 
 // Size of the margin for the heap. A segment is closed when the remaining memory
 // in the heap goes below MARGIN_IN_BYTES.
@@ -60,6 +68,27 @@ type OperationGroup = SmallVec<[AddOperation; 4]>;
 type OperationSender = channel::Sender<OperationGroup>;
 type OperationReceiver = channel::Receiver<OperationGroup>;
 
+/// Gives a rough lower bound of the memory required to index
+/// a group of operations.
+fn estimate_mem_usage(add_operations: &[AddOperation]) -> usize {
+    add_operations
+        .iter()
+        .flat_map(|add_operation| add_operation.document.field_values())
+        .map(|field_value| match field_value.value() {
+            Value::Str(text) => text.len(),
+            Value::PreTokStr(pre_tokenized) => pre_tokenized.text.len(),
+            Value::Facet(facet) => facet.encoded_str().len(),
+            Value::Bytes(bytes) => bytes.len(),
+            Value::JsonObject(json_object) => {
+                serde_json::to_vec(json_object).map_or(0, |json| json.len())
+            }
+            Value::U64(_) | Value::I64(_) | Value::F64(_) | Value::Date(_) => 8,
+            Value::Bool(_) => 1,
+            Value::IpAddr(_) => 16,
+        })
+        .sum()
+}
+
 /// `IndexWriter` is the user entry-point to add document to an index.
 ///
 /// It manages a small number of indexing thread, as well as a shared
@@ -73,51 +102,272 @@ pub struct IndexWriter {
 
     index: Index,
 
-    heap_size_in_bytes_per_thread: usize,
+    workers: Arc<IndexingWorkers>,
 
-    workers_join_handle: Vec<JoinHandle<crate::Result<()>>>,
+    segment_updater: SegmentUpdater,
 
-    operation_receiver: OperationReceiver,
-    operation_sender: OperationSender,
+    delete_queue: DeleteQueue,
 
+    stamper: Stamper,
+    committed_opstamp: Opstamp,
+
+    auto_commit_join_handle: Option<JoinHandle<()>>,
+}
+
+/// The indexing worker threads, and the channel of the operations they index.
+///
+/// They are shared with the auto-commit thread, which restarts them to commit.
+struct IndexingWorkers {
+    index: Index,
     segment_updater: SegmentUpdater,
+    delete_queue: DeleteQueue,
+    stamper: Stamper,
+    heap_size_in_bytes_per_thread: usize,
+    num_threads: usize,
+    operation_sender: RwLock<OperationSender>,
+    state: Mutex<WorkersState>,
+    commit_trigger: CommitTrigger,
+}
 
+struct WorkersState {
+    join_handles: Vec<JoinHandle<crate::Result<()>>>,
+    operation_receiver: OperationReceiver,
     worker_id: usize,
+    // True while a commit prepared with `IndexWriter::prepare_commit` is pending,
+    // or while a prepared commit is finalized.
+    is_commit_prepared: bool,
+}
 
-    num_threads: usize,
+impl IndexingWorkers {
+    /// Sends a group of operations to the indexing workers.
+    ///
+    /// If the indexing pipeline is full, this call may block.
+    fn send(&self, operations: OperationGroup) {
+        let num_docs = operations.len() as u64;
+        let send_result = self.operation_sender.read().unwrap().send(operations);
+        if let Err(e) = send_result {
+            panic!("Failed to index document. Sending to indexing channel failed. This probably means all of the indexing threads have panicked. {:?}", e);
+        }
+        self.commit_trigger.record_operation(num_docs);
+    }
 
-    delete_queue: DeleteQueue,
+    fn start(&self) -> crate::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        for _ in 0..self.num_threads {
+            self.add_indexing_worker(&mut state)?;
+        }
+        Ok(())
+    }
 
-    stamper: Stamper,
-    committed_opstamp: Opstamp,
+    /// Spawns a new worker thread for indexing.
+    /// The thread consumes documents from the pipeline.
+    fn add_indexing_worker(&self, state: &mut WorkersState) -> crate::Result<()> {
+        let document_receiver_clone = state.operation_receiver.clone();
+        let mut segment_updater = self.segment_updater.clone();
+
+        let mut delete_cursor = self.delete_queue.cursor();
+
+        let mem_budget = self.heap_size_in_bytes_per_thread;
+        let index = self.index.clone();
+        let join_handle: JoinHandle<crate::Result<()>> = thread::Builder::new()
+            .name(format!("thrd-tantivy-index{}", state.worker_id))
+            .spawn(move || {
+                // The iterator outlives the segments, as `index_documents` may leave
+                // a peeked group of documents for the next segment.
+                let mut document_iterator = document_receiver_clone.into_iter().peekable();
+                loop {
+                    // the peeking here is to avoid
+                    // creating a new segment's files
+                    // if no document are available.
+                    //
+                    // this is a valid guarantee as the
+                    // peeked document now belongs to
+                    // our local iterator.
+                    if let Some(operations) = document_iterator.peek() {
+                        if let Some(first) = operations.first() {
+                            delete_cursor.skip_to(first.opstamp);
+                        } else {
+                            return Ok(());
+                        }
+                    } else {
+                        // No more documents.
+                        // Happens when there is a commit, or if the `IndexWriter`
+                        // was dropped.
+                        return Ok(());
+                    }
+                    let segment = index.new_segment();
+                    index_documents(
+                        mem_budget,
+                        segment,
+                        &mut document_iterator,
+                        &mut segment_updater,
+                        delete_cursor.clone(),
+                    )?;
+                }
+            })?;
+        state.worker_id += 1;
+        state.join_handles.push(join_handle);
+        Ok(())
+    }
+
+    /// Closes the current document channel send.
+    /// and replace all the channels by new ones.
+    ///
+    /// The current workers will keep on indexing
+    /// the pending document and stop
+    /// when no documents are remaining.
+    ///
+    /// Returns the former segment_ready channel.
+    fn recreate_document_channel(&self, state: &mut WorkersState) -> OperationReceiver {
+        let (document_sender, document_receiver): (OperationSender, OperationReceiver) =
+            channel::bounded(PIPELINE_MAX_SIZE_IN_DOCS);
+        *self.operation_sender.write().unwrap() = document_sender;
+        mem::replace(&mut state.operation_receiver, document_receiver)
+    }
+
+    /// Flushes the pending documents by restarting the workers, and returns the
+    /// opstamp of the commit.
+    fn cut_commit(&self, state: &mut WorkersState) -> crate::Result<Opstamp> {
+        // this will drop the current document channel
+        // and recreate a new one.
+        self.recreate_document_channel(state);
+
+        let former_workers_join_handle = std::mem::take(&mut state.join_handles);
+
+        for worker_handle in former_workers_join_handle {
+            let indexing_worker_result = worker_handle
+                .join()
+                .map_err(|e| TantivyError::ErrorInThread(format!("{:?}", e)))?;
+            indexing_worker_result?;
+            self.add_indexing_worker(state)?;
+        }
+        self.commit_trigger.reset();
+        Ok(self.stamper.stamp())
+    }
+
+    fn prepare_commit(&self) -> crate::Result<Opstamp> {
+        let mut state = self.state.lock().unwrap();
+        let commit_opstamp = self.cut_commit(&mut state)?;
+        state.is_commit_prepared = true;
+        Ok(commit_opstamp)
+    }
+
+    fn end_prepared_commit(&self) {
+        self.state.lock().unwrap().is_commit_prepared = false;
+    }
+
+    /// Suspends the auto-commits, as if a commit was prepared.
+    ///
+    /// Once this returns, no auto-commit is running.
+    fn suspend_auto_commits(&self) {
+        self.state.lock().unwrap().is_commit_prepared = true;
+    }
+
+    /// Commits the pending operations, unless a commit is prepared.
+    ///
+    /// The commit blocks the explicit commits until it is saved.
+    fn auto_commit(&self) -> crate::Result<Option<Opstamp>> {
+        let mut state = self.state.lock().unwrap();
+        if state.is_commit_prepared {
+            // the operations are part of the prepared commit.
+            self.commit_trigger.reset();
+            return Ok(None);
+        }
+        let commit_opstamp = self.cut_commit(&mut state)?;
+        info!("Auto-committing {}", commit_opstamp);
+        block_on(self.segment_updater.schedule_commit(commit_opstamp, None))?;
+        Ok(Some(commit_opstamp))
+    }
+
+    /// Stops the workers once they have indexed the pending documents.
+    fn stop(&self) -> crate::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        // this will stop the indexing thread,
+        // dropping the last reference to the segment_updater.
+        let (sender, _receiver) = channel::bounded(1);
+        *self.operation_sender.write().unwrap() = sender;
+
+        let former_workers_handles = std::mem::take(&mut state.join_handles);
+        for join_handle in former_workers_handles {
+            join_handle
+                .join()
+                .expect("Indexing Worker thread panicked")
+                .map_err(|_| {
+                    TantivyError::ErrorInThread("Error in indexing worker thread.".into())
+                })?;
+        }
+        Ok(())
+    }
+}
+
+/// Marks all of the documents of `docset` as deleted, provided they were
+/// added before the delete operation.
+///
+/// Returns true iff at least one document was marked as deleted.
+fn delete_docset(
+    delete_bitset: &mut BitSet,
+    docset: &mut dyn DocSet,
+    doc_opstamps: &DocToOpstampMapping,
+    delete_opstamp: Opstamp,
+) -> bool {
+    let mut might_have_changed = false;
+    let mut doc_matching_delete_op = docset.doc();
+    while doc_matching_delete_op != TERMINATED {
+        // A delete operation should only affect
+        // document that were inserted before it.
+        if doc_opstamps.is_deleted(doc_matching_delete_op, delete_opstamp) {
+            delete_bitset.insert(doc_matching_delete_op);
+            might_have_changed = true;
+        }
+        doc_matching_delete_op = docset.advance();
+    }
+    might_have_changed
 }
 
 fn compute_deleted_bitset(
     delete_bitset: &mut BitSet,
+    segment: &Segment,
     segment_reader: &SegmentReader,
     delete_cursor: &mut DeleteCursor,
     doc_opstamps: &DocToOpstampMapping,
     target_opstamp: Opstamp,
 ) -> crate::Result<bool> {
     let mut might_have_changed = false;
+    // The searcher is only required by delete queries,
+    // and is created lazily.
+    let mut searcher_opt: Option<Searcher> = None;
     while let Some(delete_op) = delete_cursor.get() {
         if delete_op.opstamp > target_opstamp {
             break;
         }
-
-        // A delete operation should only affect
-        // document that were inserted before it.
-        let inverted_index = segment_reader.inverted_index(delete_op.term.field())?;
-        if let Some(mut docset) =
-            inverted_index.read_postings(&delete_op.term, IndexRecordOption::Basic)?
-        {
-            let mut doc_matching_deleted_term = docset.doc();
-            while doc_matching_deleted_term != TERMINATED {
-                if doc_opstamps.is_deleted(doc_matching_deleted_term, delete_op.opstamp) {
-                    delete_bitset.insert(doc_matching_deleted_term);
-                    might_have_changed = true;
+        match &delete_op.target {
+            DeleteTarget::Term(term) => {
+                let inverted_index = segment_reader.inverted_index(term.field())?;
+                if let Some(mut docset) =
+                    inverted_index.read_postings(term, IndexRecordOption::Basic)?
+                {
+                    might_have_changed |=
+                        delete_docset(delete_bitset, &mut docset, doc_opstamps, delete_op.opstamp);
+                }
+            }
+            DeleteTarget::Query(query) => {
+                if searcher_opt.is_none() {
+                    searcher_opt = Some(Searcher::new(
+                        segment.schema(),
+                        segment.index().clone(),
+                        vec![segment_reader.clone()],
+                        None,
+                    )?);
                 }
-                doc_matching_deleted_term = docset.advance();
+                let searcher = searcher_opt.as_ref().unwrap();
+                let weight = query.weight(searcher, false)?;
+                let mut scorer = weight.scorer(segment_reader, 1.0)?;
+                might_have_changed |= delete_docset(
+                    delete_bitset,
+                    scorer.as_mut(),
+                    doc_opstamps,
+                    delete_op.opstamp,
+                );
             }
         }
         delete_cursor.advance();
@@ -158,20 +408,16 @@ pub(crate) fn advance_deletes(
 
     compute_deleted_bitset(
         &mut delete_bitset,
+        &segment,
         &segment_reader,
         segment_entry.delete_cursor(),
         &DocToOpstampMapping::None,
         target_opstamp,
     )?;
 
-    // TODO optimize
-    // It should be possible to do something smarter by manipulation bitsets directly
-    // to compute this union.
     if let Some(seg_delete_bitset) = segment_reader.delete_bitset() {
-        for doc in 0u32..max_doc {
-            if seg_delete_bitset.is_deleted(doc) {
-                delete_bitset.insert(doc);
-            }
+        for doc in seg_delete_bitset.iter_deleted() {
+            delete_bitset.insert(doc);
         }
     }
 
@@ -191,14 +437,29 @@ pub(crate) fn advance_deletes(
 fn index_documents(
     memory_budget: usize,
     segment: Segment,
-    grouped_document_iterator: &mut dyn Iterator<Item = OperationGroup>,
+    grouped_document_iterator: &mut Peekable<channel::IntoIter<OperationGroup>>,
     segment_updater: &mut SegmentUpdater,
     mut delete_cursor: DeleteCursor,
 ) -> crate::Result<bool> {
     let schema = segment.schema();
 
     let mut segment_writer = SegmentWriter::for_segment(memory_budget, segment.clone(), &schema)?;
-    for document_group in grouped_document_iterator {
+    while let Some(document_group) = grouped_document_iterator.peek() {
+        // Groups are never split across segments. If the next group is not
+        // expected to fit in the current segment, it is left for the next one.
+        if segment_writer.max_doc() > 0
+            && segment_writer.mem_usage() + estimate_mem_usage(document_group)
+                >= memory_budget - MARGIN_IN_BYTES
+        {
+            info!(
+                "Buffer limit reached before the next group of documents, flushing segment with maxdoc={}.",
+                segment_writer.max_doc()
+            );
+            break;
+        }
+        let document_group = grouped_document_iterator
+            .next()
+            .expect("the group was just peeked");
         for doc in document_group {
             segment_writer.add_document(doc, &schema)?;
         }
@@ -229,7 +490,10 @@ fn index_documents(
     let delete_bitset_opt =
         apply_deletes(&segment_with_max_doc, &mut delete_cursor, &doc_opstamps)?;
 
-    let meta = segment_with_max_doc.meta().clone();
+    let meta = segment_with_max_doc
+        .meta()
+        .clone()
+        .with_num_bytes(segment_with_max_doc.num_bytes());
     meta.untrack_temp_docstore();
     // update segment_updater inventory to remove tempstore
     let segment_entry = SegmentEntry::new(meta, delete_cursor, delete_bitset_opt);
@@ -262,6 +526,7 @@ fn apply_deletes(
     let mut deleted_bitset = BitSet::with_max_value(max_doc);
     let may_have_deletes = compute_deleted_bitset(
         &mut deleted_bitset,
+        segment,
         &segment_reader,
         &mut delete_cursor,
         &doc_to_opstamps,
@@ -318,52 +583,54 @@ impl IndexWriter {
         let segment_updater =
             SegmentUpdater::create(index.clone(), stamper.clone(), &delete_queue.cursor())?;
 
-        let mut index_writer = IndexWriter {
+        let workers = Arc::new(IndexingWorkers {
+            index: index.clone(),
+            segment_updater: segment_updater.clone(),
+            delete_queue: delete_queue.clone(),
+            stamper: stamper.clone(),
+            heap_size_in_bytes_per_thread,
+            num_threads,
+            operation_sender: RwLock::new(document_sender),
+            state: Mutex::new(WorkersState {
+                join_handles: vec![],
+                operation_receiver: document_receiver,
+                worker_id: 0,
+                is_commit_prepared: false,
+            }),
+            commit_trigger: CommitTrigger::default(),
+        });
+        workers.start()?;
+
+        Ok(IndexWriter {
             _directory_lock: Some(directory_lock),
 
-            heap_size_in_bytes_per_thread,
             index: index.clone(),
 
-            operation_receiver: document_receiver,
-            operation_sender: document_sender,
+            workers,
 
             segment_updater,
 
-            workers_join_handle: vec![],
-            num_threads,
-
             delete_queue,
 
             committed_opstamp: current_opstamp,
             stamper,
 
-            worker_id: 0,
-        };
-        index_writer.start_workers()?;
-        Ok(index_writer)
+            auto_commit_join_handle: None,
+        })
     }
 
-    fn drop_sender(&mut self) {
-        let (sender, _receiver) = channel::bounded(1);
-        self.operation_sender = sender;
+    fn stop_auto_commit(&mut self) {
+        self.workers.commit_trigger.stop();
+        if let Some(join_handle) = self.auto_commit_join_handle.take() {
+            let _ = join_handle.join();
+        }
     }
 
     /// If there are some merging threads, blocks until they all finish their work and
     /// then drop the `IndexWriter`.
     pub fn wait_merging_threads(mut self) -> crate::Result<()> {
-        // this will stop the indexing thread,
-        // dropping the last reference to the segment_updater.
-        self.drop_sender();
-
-        let former_workers_handles = std::mem::take(&mut self.workers_join_handle);
-        for join_handle in former_workers_handles {
-            join_handle
-                .join()
-                .expect("Indexing Worker thread panicked")
-                .map_err(|_| {
-                    TantivyError::ErrorInThread("Error in indexing worker thread.".into())
-                })?;
-        }
+        self.stop_auto_commit();
+        self.workers.stop()?;
 
         let result = self
             .segment_updater
@@ -377,6 +644,31 @@ impl IndexWriter {
         result
     }
 
+    /// Waits for the merging threads for at most `timeout`, cancels the remaining
+    /// merges, and then drops the `IndexWriter`.
+    ///
+    /// The cancelled merges leave the index in its state before the merge.
+    /// Returns true iff some merges were cancelled.
+    pub fn wait_merging_threads_with_timeout(mut self, timeout: Duration) -> crate::Result<bool> {
+        self.stop_auto_commit();
+        self.workers.stop()?;
+        Ok(self
+            .segment_updater
+            .wait_merging_thread_with_timeout(timeout))
+    }
+
+    /// Cancels all of the running merges, as well as the merges waiting for a
+    /// merge thread, without waiting for them to stop.
+    ///
+    /// The cancelled merges stop at their next cancellation check, delete the files
+    /// they have written, and leave the index in its state before the merge. Their
+    /// futures resolve to `TantivyError::MergeCancelled`.
+    ///
+    /// The merges scheduled after the call are not cancelled.
+    pub fn abort_merges(&self) {
+        self.segment_updater.abort_merges();
+    }
+
     #[doc(hidden)]
     pub fn add_segment(&self, segment_meta: SegmentMeta) -> crate::Result<()> {
         let delete_cursor = self.delete_queue.cursor();
@@ -396,57 +688,6 @@ impl IndexWriter {
         self.index.new_segment()
     }
 
-    /// Spawns a new worker thread for indexing.
-    /// The thread consumes documents from the pipeline.
-    fn add_indexing_worker(&mut self) -> crate::Result<()> {
-        let document_receiver_clone = self.operation_receiver.clone();
-        let mut segment_updater = self.segment_updater.clone();
-
-        let mut delete_cursor = self.delete_queue.cursor();
-
-        let mem_budget = self.heap_size_in_bytes_per_thread;
-        let index = self.index.clone();
-        let join_handle: JoinHandle<crate::Result<()>> = thread::Builder::new()
-            .name(format!("thrd-tantivy-index{}", self.worker_id))
-            .spawn(move || {
-                loop {
-                    let mut document_iterator =
-                        document_receiver_clone.clone().into_iter().peekable();
-
-                    // the peeking here is to avoid
-                    // creating a new segment's files
-                    // if no document are available.
-                    //
-                    // this is a valid guarantee as the
-                    // peeked document now belongs to
-                    // our local iterator.
-                    if let Some(operations) = document_iterator.peek() {
-                        if let Some(first) = operations.first() {
-                            delete_cursor.skip_to(first.opstamp);
-                        } else {
-                            return Ok(());
-                        }
-                    } else {
-                        // No more documents.
-                        // Happens when there is a commit, or if the `IndexWriter`
-                        // was dropped.
-                        return Ok(());
-                    }
-                    let segment = index.new_segment();
-                    index_documents(
-                        mem_budget,
-                        segment,
-                        &mut document_iterator,
-                        &mut segment_updater,
-                        delete_cursor.clone(),
-                    )?;
-                }
-            })?;
-        self.worker_id += 1;
-        self.workers_join_handle.push(join_handle);
-        Ok(())
-    }
-
     /// Accessor to the merge policy.
     pub fn get_merge_policy(&self) -> Arc<dyn MergePolicy> {
         self.segment_updater.get_merge_policy()
@@ -457,11 +698,51 @@ impl IndexWriter {
         self.segment_updater.set_merge_policy(merge_policy);
     }
 
-    fn start_workers(&mut self) -> crate::Result<()> {
-        for _ in 0..self.num_threads {
-            self.add_indexing_worker()?;
-        }
-        Ok(())
+    /// Returns the maximum number of merges suggested by the merge policy that
+    /// may run concurrently.
+    pub fn max_concurrent_merges(&self) -> usize {
+        self.segment_updater.max_concurrent_merges()
+    }
+
+    /// Sets the maximum number of merges suggested by the merge policy that
+    /// may run concurrently. Defaults to the number of merge threads.
+    ///
+    /// The limit applies to the merges scheduled after the call, and `0` suspends
+    /// the merges suggested by the merge policy.
+    /// The merges requested through [`IndexWriter::merge`](#method.merge) are not limited,
+    /// but count as running merges.
+    pub fn set_max_concurrent_merges(&self, max_concurrent_merges: usize) {
+        self.segment_updater
+            .set_max_concurrent_merges(max_concurrent_merges);
+    }
+
+    /// Returns the maximum number of bytes written per second by a merge, if the
+    /// merges are throttled.
+    pub fn merge_throttle(&self) -> Option<u64> {
+        self.segment_updater.merge_throttle()
+    }
+
+    /// Throttles the writes of each merge to `max_bytes_per_sec` bytes per second,
+    /// or removes the throttle if `None`.
+    ///
+    /// This prevents the merges from saturating the disk during bulk indexing.
+    /// The throttle applies to the merges started after the call.
+    pub fn set_merge_throttle(&self, max_bytes_per_sec: Option<u64>) {
+        self.segment_updater.set_merge_throttle(max_bytes_per_sec);
+    }
+
+    /// Returns the merges currently running, with the number of bytes they have
+    /// written so far.
+    pub fn running_merges(&self) -> Vec<MergeOperationInfo> {
+        self.segment_updater.running_merges()
+    }
+
+    /// Registers a listener of the lifecycle events of the index writer: flushes,
+    /// commits, merges and deletions of segments.
+    ///
+    /// See [`IndexWriterEvents`](./trait.IndexWriterEvents.html).
+    pub fn add_event_listener(&self, listener: Arc<dyn IndexWriterEvents>) {
+        self.segment_updater.add_event_listener(listener);
     }
 
     /// Detects and removes the files that are not used by the index anymore.
@@ -527,22 +808,6 @@ impl IndexWriter {
         async move { segment_updater.start_merge(merge_operation)?.await }
     }
 
-    /// Closes the current document channel send.
-    /// and replace all the channels by new ones.
-    ///
-    /// The current workers will keep on indexing
-    /// the pending document and stop
-    /// when no documents are remaining.
-    ///
-    /// Returns the former segment_ready channel.
-    #[allow(unused_must_use)]
-    fn recreate_document_channel(&mut self) -> OperationReceiver {
-        let (document_sender, document_receiver): (OperationSender, OperationReceiver) =
-            channel::bounded(PIPELINE_MAX_SIZE_IN_DOCS);
-        mem::replace(&mut self.operation_sender, document_sender);
-        mem::replace(&mut self.operation_receiver, document_receiver)
-    }
-
     /// Rollback to the last commit
     ///
     /// This cancels all of the updates that
@@ -551,12 +816,23 @@ impl IndexWriter {
     /// state as it was after the last commit.
     ///
     /// The opstamp at the last commit is returned.
+    ///
+    /// The commit policy is kept, and its timer and document count are reset.
     pub fn rollback(&mut self) -> crate::Result<Opstamp> {
         info!("Rolling back to opstamp {}", self.committed_opstamp);
+        // no auto-commit can happen from now on.
+        self.stop_auto_commit();
         // marks the segment updater as killed. From now on, all
         // segment updates will be ignored.
         self.segment_updater.kill();
-        let document_receiver = self.operation_receiver.clone();
+        let document_receiver = self
+            .workers
+            .state
+            .lock()
+            .unwrap()
+            .operation_receiver
+            .clone();
+        let commit_policy = self.commit_policy();
 
         // take the directory lock to create a new index_writer.
         let directory_lock = self
@@ -566,8 +842,8 @@ impl IndexWriter {
 
         let new_index_writer: IndexWriter = IndexWriter::new(
             &self.index,
-            self.num_threads,
-            self.heap_size_in_bytes_per_thread,
+            self.workers.num_threads,
+            self.workers.heap_size_in_bytes_per_thread,
             directory_lock,
         )?;
 
@@ -584,6 +860,7 @@ impl IndexWriter {
         // was dropped with the index_writer.
         for _ in document_receiver {}
 
+        self.set_commit_policy(commit_policy)?;
         Ok(self.committed_opstamp)
     }
 
@@ -621,21 +898,9 @@ impl IndexWriter {
         // committed segments.
         info!("Preparing commit");
 
-        // this will drop the current document channel
-        // and recreate a new one.
-        self.recreate_document_channel();
-
-        let former_workers_join_handle = std::mem::take(&mut self.workers_join_handle);
-
-        for worker_handle in former_workers_join_handle {
-            let indexing_worker_result = worker_handle
-                .join()
-                .map_err(|e| TantivyError::ErrorInThread(format!("{:?}", e)))?;
-            indexing_worker_result?;
-            self.add_indexing_worker()?;
-        }
-
-        let commit_opstamp = self.stamper.stamp();
+        // The auto-commits are suspended until the prepared commit is
+        // committed, aborted or dropped.
+        let commit_opstamp = self.workers.prepare_commit()?;
         let prepared_commit = PreparedCommit::new(self, commit_opstamp);
         info!("Prepared commit {}", commit_opstamp);
         Ok(prepared_commit)
@@ -659,10 +924,149 @@ impl IndexWriter {
         self.prepare_commit()?.commit()
     }
 
+    /// Commits all of the pending changes and attaches
+    /// the given `payload` to the commit.
+    ///
+    /// The payload is written in `meta.json` together
+    /// with the list of segments, and can be read back
+    /// using [`IndexMeta::payload()`](../struct.IndexMeta.html#method.payload)
+    /// or [`Searcher::payload()`](../struct.Searcher.html#method.payload).
+    ///
+    /// This is a shorthand for `prepare_commit()`,
+    /// `PreparedCommit::set_payload()` and `PreparedCommit::commit()`.
+    pub fn commit_with_payload(&mut self, payload: &str) -> crate::Result<Opstamp> {
+        let mut prepared_commit = self.prepare_commit()?;
+        prepared_commit.set_payload(payload);
+        prepared_commit.commit()
+    }
+
+    /// Prepares a commit which survives a restart of the process, e.g. to coordinate
+    /// the commit with the transaction `external_id` of another system.
+    ///
+    /// Like `prepare_commit()`, this flushes the pending documents. The resulting
+    /// segments are saved in a prepared commits file, and are not garbage collected
+    /// until the prepared commit is finalized or aborted. The prepared commits can be
+    /// listed with [`Index::list_prepared_commits()`](../struct.Index.html#method.list_prepared_commits),
+    /// including after the index is reopened.
+    ///
+    /// The returned `PreparedCommit` can be committed or aborted, which is equivalent to
+    /// calling `finalize_prepared(external_id)` or `abort_prepared(external_id)`.
+    /// If it is dropped instead, the commit stays prepared.
+    ///
+    /// Returns an error if a commit was already prepared with the same `external_id`.
+    pub fn prepare_commit_with_id(&mut self, external_id: &str) -> crate::Result<PreparedCommit> {
+        info!("Preparing commit {:?}", external_id);
+        let commit_opstamp = self.workers.prepare_commit()?;
+        if let Err(err) = block_on(
+            self.segment_updater
+                .schedule_prepare_commit(commit_opstamp, external_id.to_string()),
+        ) {
+            self.workers.end_prepared_commit();
+            return Err(err);
+        }
+        info!(
+            "Prepared commit {} with id {:?}",
+            commit_opstamp, external_id
+        );
+        Ok(PreparedCommit::with_external_id(
+            self,
+            commit_opstamp,
+            external_id.to_string(),
+        ))
+    }
+
+    /// Finalizes the commit prepared with `prepare_commit_with_id(external_id)`,
+    /// possibly by another `IndexWriter` before the index was reopened.
+    ///
+    /// The `meta.json` of the prepared commit is saved, and the `IndexWriter` is reloaded
+    /// from it: as with `rollback()`, the operations which were not committed are
+    /// discarded.
+    ///
+    /// Returns an error if there is no such prepared commit, or if the index was committed
+    /// since the commit was prepared. The opstamp of the prepared commit is returned.
+    pub fn finalize_prepared(&mut self, external_id: &str) -> crate::Result<Opstamp> {
+        self.finalize_prepared_with_payload(external_id, None)
+    }
+
+    pub(crate) fn finalize_prepared_with_payload(
+        &mut self,
+        external_id: &str,
+        payload: Option<String>,
+    ) -> crate::Result<Opstamp> {
+        info!("Finalizing prepared commit {:?}", external_id);
+        // an auto-commit would overwrite the finalized commit.
+        self.workers.suspend_auto_commits();
+        let finalize_result = block_on(
+            self.segment_updater
+                .schedule_finalize_prepared(external_id.to_string(), payload),
+        );
+        if let Err(err) = finalize_result {
+            self.workers.end_prepared_commit();
+            return Err(err);
+        }
+        self.rollback()
+    }
+
+    /// Aborts the commit prepared with `prepare_commit_with_id(external_id)`,
+    /// possibly by another `IndexWriter` before the index was reopened.
+    ///
+    /// As with `rollback()`, the operations which were not committed are discarded.
+    /// The files of the prepared commit are then garbage collected.
+    ///
+    /// Returns an error if there is no such prepared commit, otherwise returns the
+    /// opstamp of the last commit.
+    pub fn abort_prepared(&mut self, external_id: &str) -> crate::Result<Opstamp> {
+        info!("Aborting prepared commit {:?}", external_id);
+        block_on(
+            self.segment_updater
+                .schedule_abort_prepared(external_id.to_string()),
+        )?;
+        let committed_opstamp = self.rollback()?;
+        block_on(self.garbage_collect_files())?;
+        Ok(committed_opstamp)
+    }
+
     pub(crate) fn segment_updater(&self) -> &SegmentUpdater {
         &self.segment_updater
     }
 
+    pub(crate) fn end_prepared_commit(&self) {
+        self.workers.end_prepared_commit();
+    }
+
+    /// Returns the policy of the automatic commits.
+    pub fn commit_policy(&self) -> CommitPolicy {
+        self.workers.commit_trigger.policy()
+    }
+
+    /// Sets when the `IndexWriter` commits automatically, from its own thread.
+    ///
+    /// The auto-commits do not have a payload, and are reported by
+    /// [`IndexWriterEvents::on_commit`](./trait.IndexWriterEvents.html#method.on_commit).
+    /// The explicit commits reset the timer and the document count of the policy,
+    /// and no auto-commit happens while a commit is prepared: the pending operations
+    /// are committed, or rolled back, with the prepared commit.
+    pub fn set_commit_policy(&mut self, commit_policy: CommitPolicy) -> crate::Result<()> {
+        self.workers.commit_trigger.set_policy(commit_policy);
+        if commit_policy == CommitPolicy::Manual || self.auto_commit_join_handle.is_some() {
+            return Ok(());
+        }
+        let workers = self.workers.clone();
+        let join_handle = thread::Builder::new()
+            .name("thrd-tantivy-autocommit".to_string())
+            .spawn(move || {
+                while workers.commit_trigger.wait_for_commit() {
+                    if let Err(err) = workers.auto_commit() {
+                        error!("Auto-commit failed: {:?}", err);
+                        // the operations will be committed with the next commit.
+                        workers.commit_trigger.reset();
+                    }
+                }
+            })?;
+        self.auto_commit_join_handle = Some(join_handle);
+        Ok(())
+    }
+
     /// Delete all documents containing a given term.
     ///
     /// Delete operation only affects documents that
@@ -672,12 +1076,69 @@ impl IndexWriter {
     /// Like adds, the deletion itself will be visible
     /// only after calling `commit()`.
     pub fn delete_term(&self, term: Term) -> Opstamp {
-        let opstamp = self.stamper.stamp();
-        let delete_operation = DeleteOperation { opstamp, term };
-        self.delete_queue.push(delete_operation);
+        let opstamp = self
+            .delete_queue
+            .push_stamped(|| self.stamper.stamp(), DeleteTarget::Term(term));
+        self.workers.commit_trigger.record_operation(0);
         opstamp
     }
 
+    /// Delete all documents matching a given query.
+    ///
+    /// The query is executed against each segment when its deletes
+    /// are applied, without any scoring.
+    ///
+    /// Just like `delete_term`, the delete operation only affects
+    /// documents that were added in previous commits, and documents
+    /// that were added previously in the same commit.
+    ///
+    /// Like adds, the deletion itself will be visible
+    /// only after calling `commit()`.
+    pub fn delete_query(&self, query: Box<dyn Query>) -> Opstamp {
+        let opstamp = self
+            .delete_queue
+            .push_stamped(|| self.stamper.stamp(), DeleteTarget::Query(query));
+        self.workers.commit_trigger.record_operation(0);
+        opstamp
+    }
+
+    /// Replaces all of the documents containing a given term by `document`.
+    ///
+    /// This is the atomic equivalent of calling `delete_term(term)` followed
+    /// by `add_document(document)`: both operations share the same opstamp,
+    /// and are made visible by the same `commit()`.
+    ///
+    /// When several updates for the same term race against each other,
+    /// only the document of the update with the highest opstamp survives.
+    ///
+    /// If the indexing pipeline is full, this call may block.
+    ///
+    /// Like `add_document`, this method does not enforce
+    /// `IndexSettings::max_index_size_bytes`: see
+    /// [`try_update_document`](#method.try_update_document).
+    pub fn update_document(&self, term: Term, document: Document) -> Opstamp {
+        let opstamp = self
+            .delete_queue
+            .push_stamped(|| self.stamper.stamp(), DeleteTarget::Term(term));
+        // A delete operation only affects documents with a strictly lower opstamp,
+        // so the new document is not deleted by its own update.
+        let add_operation = AddOperation { opstamp, document };
+        self.workers.send(smallvec![add_operation]);
+        opstamp
+    }
+
+    /// Replaces all of the documents containing a given term by `document`, unless
+    /// the index exceeds its size limit.
+    ///
+    /// # Errors
+    /// Like [`try_add_document`](#method.try_add_document), returns
+    /// `TantivyError::IndexFull` if the index exceeds its size limit. Nothing is
+    /// deleted then.
+    pub fn try_update_document(&self, term: Term, document: Document) -> crate::Result<Opstamp> {
+        self.check_index_size()?;
+        Ok(self.update_document(term, document))
+    }
+
     /// Returns the opstamp of the last successful commit.
     ///
     /// This is, for instance, the opstamp the index will
@@ -696,16 +1157,93 @@ impl IndexWriter {
     /// The opstamp is an increasing `u64` that can
     /// be used by the client to align commits with its own
     /// document queue.
+    ///
+    /// This method does not enforce `IndexSettings::max_index_size_bytes`, and neither
+    /// do `update_document` and `run`: the writers of an index with a size limit use
+    /// [`try_add_document`](#method.try_add_document),
+    /// [`try_update_document`](#method.try_update_document),
+    /// [`try_run`](#method.try_run) and [`add_documents`](#method.add_documents).
     pub fn add_document(&self, document: Document) -> Opstamp {
         let opstamp = self.stamper.stamp();
         let add_operation = AddOperation { opstamp, document };
-        let send_result = self.operation_sender.send(smallvec![add_operation]);
-        if let Err(e) = send_result {
-            panic!("Failed to index document. Sending to indexing channel failed. This probably means all of the indexing threads have panicked. {:?}", e);
-        }
+        self.workers.send(smallvec![add_operation]);
         opstamp
     }
 
+    /// Adds a document, unless the index exceeds its size limit.
+    ///
+    /// Unlike [`add_document`](#method.add_document), which ignores it, this method
+    /// enforces `IndexSettings::max_index_size_bytes`.
+    ///
+    /// # Errors
+    /// Returns `TantivyError::IndexFull` if the segments of the writer, committed or
+    /// not, and the files written by its running merges exceed
+    /// `IndexSettings::max_index_size_bytes`. The documents still in the memory of the
+    /// indexing threads are not counted.
+    pub fn try_add_document(&self, document: Document) -> crate::Result<Opstamp> {
+        self.check_index_size()?;
+        Ok(self.add_document(document))
+    }
+
+    /// Returns `TantivyError::IndexFull` if the index exceeds
+    /// `IndexSettings::max_index_size_bytes`.
+    fn check_index_size(&self) -> crate::Result<()> {
+        if let Some(max_index_size_bytes) = self.index.settings().max_index_size_bytes {
+            let index_num_bytes = self.segment_updater.index_num_bytes();
+            if index_num_bytes > max_index_size_bytes {
+                return Err(TantivyError::IndexFull(
+                    index_num_bytes,
+                    max_index_size_bytes,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a block of documents, sharing a single opstamp.
+    ///
+    /// The documents of the block are guaranteed to be
+    /// - indexed in the same segment, with contiguous `DocId`s,
+    ///   following the order of `documents`.
+    /// - committed atomically: either all of them or none of them
+    ///   end up in the index.
+    ///
+    /// This makes it possible to index parent/child documents as a block.
+    ///
+    /// If the indexing pipeline is full, this call may block.
+    ///
+    /// # Errors
+    /// As a block cannot be split across segments, it needs to fit in the memory
+    /// budget of a single indexing thread. A block exceeding it returns a
+    /// `TantivyError::InvalidArgument`.
+    ///
+    /// Like [`try_add_document`](#method.try_add_document), returns
+    /// `TantivyError::IndexFull` if the index exceeds its size limit.
+    pub fn add_documents(&self, documents: Vec<Document>) -> crate::Result<Opstamp> {
+        let opstamp = self.stamper.stamp();
+        if documents.is_empty() {
+            return Ok(opstamp);
+        }
+        self.check_index_size()?;
+        let add_operations: OperationGroup = documents
+            .into_iter()
+            .map(|document| AddOperation { opstamp, document })
+            .collect();
+        let block_mem_usage = estimate_mem_usage(&add_operations);
+        let heap_size_in_bytes_per_thread = self.workers.heap_size_in_bytes_per_thread;
+        if block_mem_usage >= heap_size_in_bytes_per_thread - MARGIN_IN_BYTES {
+            return Err(TantivyError::InvalidArgument(format!(
+                "The block of {} documents requires at least {} bytes, which exceeds the \
+                 memory budget of an indexing thread ({} bytes).",
+                add_operations.len(),
+                block_mem_usage,
+                heap_size_in_bytes_per_thread - MARGIN_IN_BYTES
+            )));
+        }
+        self.workers.send(add_operations);
+        Ok(opstamp)
+    }
+
     /// Gets a range of stamps from the stamper and "pops" the last stamp
     /// from the range returning a tuple of the last optstamp and the popped
     /// range.
@@ -739,6 +1277,9 @@ impl IndexWriter {
     /// Like adds and deletes (see `IndexWriter.add_document` and
     /// `IndexWriter.delete_term`), the changes made by calling `run` will be
     /// visible to readers only after calling `commit()`.
+    ///
+    /// Like `add_document`, this method does not enforce
+    /// `IndexSettings::max_index_size_bytes`: see [`try_run`](#method.try_run).
     pub fn run(&self, user_operations: Vec<UserOperation>) -> Opstamp {
         let count = user_operations.len() as u64;
         if count == 0 {
@@ -751,7 +1292,10 @@ impl IndexWriter {
         for (user_op, opstamp) in user_operations.into_iter().zip(stamps) {
             match user_op {
                 UserOperation::Delete(term) => {
-                    let delete_operation = DeleteOperation { opstamp, term };
+                    let delete_operation = DeleteOperation {
+                        opstamp,
+                        target: DeleteTarget::Term(term),
+                    };
                     self.delete_queue.push(delete_operation);
                 }
                 UserOperation::Add(document) => {
@@ -760,22 +1304,37 @@ impl IndexWriter {
                 }
             }
         }
-        let send_result = self.operation_sender.send(adds);
-        if let Err(e) = send_result {
-            panic!("Failed to index document. Sending to indexing channel failed. This probably means all of the indexing threads have panicked. {:?}", e);
-        };
+        self.workers.send(adds);
 
         batch_opstamp
     }
+
+    /// Runs a group of document operations, like [`run`](#method.run), unless it adds
+    /// documents while the index exceeds its size limit.
+    ///
+    /// # Errors
+    /// Like [`try_add_document`](#method.try_add_document), returns
+    /// `TantivyError::IndexFull` if the group contains an add operation and the index
+    /// exceeds its size limit. None of the operations are run then. A group of deletes
+    /// only is always run, as it does not grow the index.
+    pub fn try_run(&self, user_operations: Vec<UserOperation>) -> crate::Result<Opstamp> {
+        if user_operations
+            .iter()
+            .any(|user_op| matches!(user_op, UserOperation::Add(_)))
+        {
+            self.check_index_size()?;
+        }
+        Ok(self.run(user_operations))
+    }
 }
 
 impl Drop for IndexWriter {
     fn drop(&mut self) {
+        self.stop_auto_commit();
         self.segment_updater.kill();
-        self.drop_sender();
-        for work in self.workers_join_handle.drain(..) {
-            let _ = work.join();
-        }
+        // the result of the running merges would be discarded.
+        self.segment_updater.abort_merges();
+        let _ = self.workers.stop();
     }
 }
 
@@ -783,6 +1342,10 @@ impl Drop for IndexWriter {
 mod tests {
     use std::collections::HashMap;
     use std::collections::HashSet;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
 
     use futures::executor::block_on;
     use proptest::prelude::*;
@@ -790,11 +1353,14 @@ mod tests {
     use proptest::strategy::Strategy;
 
     use super::super::operation::UserOperation;
-    use crate::collector::TopDocs;
+    use crate::collector::{Count, TopDocs};
     use crate::directory::error::LockError;
+    use crate::directory::{Directory, RamDirectory};
     use crate::error::*;
     use crate::fastfield::FastFieldReader;
+    use crate::indexer::merge_policy::tests::MergeWheneverPossible;
     use crate::indexer::NoMergePolicy;
+    use crate::indexer::{CommitPolicy, IndexWriterEvents, MergeDocMapping};
     use crate::query::QueryParser;
     use crate::query::TermQuery;
     use crate::schema::Cardinality;
@@ -806,10 +1372,14 @@ mod tests {
     use crate::schema::TEXT;
     use crate::schema::{self, IndexRecordOption, FAST, INDEXED, STRING};
     use crate::DocAddress;
+    use crate::DocId;
     use crate::Index;
+    use crate::Opstamp;
     use crate::ReloadPolicy;
+    use crate::Searcher;
     use crate::Term;
     use crate::{IndexSettings, IndexSortByField, Order};
+    use crate::{SegmentId, SegmentMeta};
 
     const LOREM: &str = "Doc Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed \
              do eiusmod tempor incididunt ut labore et dolore magna aliqua. \
@@ -970,7 +1540,7 @@ mod tests {
         assert_eq!(
             format!("{:?}", index_writer.get_merge_policy()),
             "LogMergePolicy { min_num_segments: 8, max_docs_before_merge: 10000000, min_layer_size: 10000, \
-             level_log_size: 0.75 }"
+             level_log_size: 0.75, del_docs_ratio_before_merge: 1.0 }"
         );
         let merge_policy = Box::new(NoMergePolicy::default());
         index_writer.set_merge_policy(merge_policy);
@@ -980,6 +1550,621 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_max_concurrent_merges() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            assert_eq!(index_writer.max_concurrent_merges(), 4);
+            index_writer.set_max_concurrent_merges(0);
+            index_writer.set_merge_policy(Box::new(MergeWheneverPossible));
+            for _ in 0..3 {
+                index_writer.add_document(doc!(text_field => "a"));
+                index_writer.commit()?;
+            }
+            index_writer.wait_merging_threads()?;
+        }
+        assert_eq!(index.searchable_segment_ids()?.len(), 3);
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.set_max_concurrent_merges(1);
+            index_writer.set_merge_policy(Box::new(MergeWheneverPossible));
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.commit()?;
+            index_writer.wait_merging_threads()?;
+        }
+        assert_eq!(index.searchable_segment_ids()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_throttle() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for _ in 0..2 {
+            index_writer.add_document(doc!(text_field => "hello happy tax payer"));
+            index_writer.commit()?;
+        }
+        assert!(index_writer.merge_throttle().is_none());
+        index_writer.set_merge_throttle(Some(1_000));
+        assert_eq!(index_writer.merge_throttle(), Some(1_000));
+        assert!(index_writer.running_merges().is_empty());
+
+        let segment_ids = index.searchable_segment_ids()?;
+        let merge_future = index_writer.merge(&segment_ids);
+        let merge_thread = std::thread::spawn(move || block_on(merge_future));
+        let start = std::time::Instant::now();
+        let mut running_merges = index_writer.running_merges();
+        while running_merges.is_empty() && start.elapsed().as_secs() < 10 {
+            std::thread::yield_now();
+            running_merges = index_writer.running_merges();
+        }
+        assert_eq!(running_merges.len(), 1);
+        let mut running_segment_ids = running_merges[0].segment_ids().to_vec();
+        running_segment_ids.sort();
+        let mut expected_segment_ids = segment_ids.clone();
+        expected_segment_ids.sort();
+        assert_eq!(running_segment_ids, expected_segment_ids);
+
+        let segment_meta = merge_thread.join().unwrap()?;
+        assert_eq!(segment_meta.num_docs(), 2);
+        // the merge writes more than 300 bytes at 1000 bytes per second.
+        assert!(segment_meta.num_bytes().unwrap() > 300);
+        assert!(start.elapsed().as_millis() >= 250);
+        index_writer.wait_merging_threads()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_add_document_index_full() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let settings = IndexSettings {
+            max_index_size_bytes: Some(10),
+            ..Default::default()
+        };
+        let index = Index::builder()
+            .schema(schema_builder.build())
+            .settings(settings)
+            .create_in_ram()?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.try_add_document(doc!(text_field => "hello happy tax payer"))?;
+        index_writer.commit()?;
+        let index_num_bytes = match index_writer.try_add_document(doc!(text_field => "hello")) {
+            Err(TantivyError::IndexFull(index_num_bytes, 10)) => index_num_bytes,
+            res => panic!("Expected IndexFull, got {:?}", res),
+        };
+        assert!(index_num_bytes > 10);
+        assert!(matches!(
+            index_writer.add_documents(vec![doc!(text_field => "hello")]),
+            Err(TantivyError::IndexFull(_, 10))
+        ));
+        let hello = Term::from_field_text(text_field, "hello");
+        assert!(matches!(
+            index_writer.try_update_document(hello.clone(), doc!(text_field => "hello")),
+            Err(TantivyError::IndexFull(_, 10))
+        ));
+        assert!(matches!(
+            index_writer.try_run(vec![
+                UserOperation::Delete(hello.clone()),
+                UserOperation::Add(doc!(text_field => "hello")),
+            ]),
+            Err(TantivyError::IndexFull(_, 10))
+        ));
+        index_writer.commit()?;
+        assert_eq!(index.reader()?.searcher().num_docs(), 1);
+        // deleting documents is always possible.
+        index_writer.try_run(vec![UserOperation::Delete(hello.clone())])?;
+        index_writer.commit()?;
+        assert_eq!(index.reader()?.searcher().num_docs(), 0);
+        // `add_document`, `update_document` and `run` do not enforce the limit.
+        index_writer.add_document(doc!(text_field => "hello"));
+        index_writer.update_document(hello.clone(), doc!(text_field => "hello tax payer"));
+        index_writer.run(vec![UserOperation::Add(doc!(text_field => "happy"))]);
+        index_writer.commit()?;
+        assert_eq!(index.reader()?.searcher().num_docs(), 2);
+        Ok(())
+    }
+
+    /// Commits two identical segments in an index with the given size limit, and
+    /// returns the number of segments left once the merges end.
+    fn num_segments_after_merges(max_index_size_bytes: Option<u64>) -> crate::Result<usize> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let settings = IndexSettings {
+            max_index_size_bytes,
+            ..Default::default()
+        };
+        let index = Index::builder()
+            .schema(schema_builder.build())
+            .settings(settings)
+            .create_in_ram()?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(MergeWheneverPossible));
+        for _ in 0..2 {
+            index_writer.add_document(doc!(text_field => "hello happy tax payer"));
+            index_writer.commit()?;
+        }
+        index_writer.wait_merging_threads()?;
+        Ok(index.searchable_segment_ids()?.len())
+    }
+
+    #[test]
+    fn test_merge_skipped_by_index_size_limit() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for _ in 0..2 {
+            index_writer.add_document(doc!(text_field => "hello happy tax payer"));
+            index_writer.commit()?;
+        }
+        let segments_num_bytes: u64 = index
+            .searchable_segment_metas()?
+            .iter()
+            .map(|segment_meta| segment_meta.num_bytes().unwrap())
+            .sum();
+
+        assert_eq!(num_segments_after_merges(None)?, 1);
+        // The merge requires as many bytes as its segments, on top of the segments.
+        assert_eq!(num_segments_after_merges(Some(2 * segments_num_bytes))?, 1);
+        assert_eq!(
+            num_segments_after_merges(Some(2 * segments_num_bytes - 1))?,
+            2
+        );
+        Ok(())
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum RecordedEvent {
+        Flush(SegmentId, u32),
+        Commit(Opstamp, Vec<SegmentId>),
+        MergeStart(Vec<SegmentId>, Opstamp),
+        MergeEnd(Vec<SegmentId>, SegmentId),
+        SegmentDeleted(SegmentId),
+    }
+
+    // Only the segment ids are recorded: holding the segment metas would prevent
+    // the garbage collection of the merged segments.
+    #[derive(Default)]
+    struct RecordingListener {
+        events: std::sync::Mutex<Vec<RecordedEvent>>,
+    }
+
+    fn sorted_segment_ids(segment_metas: &[SegmentMeta]) -> Vec<SegmentId> {
+        let mut segment_ids: Vec<SegmentId> = segment_metas.iter().map(SegmentMeta::id).collect();
+        segment_ids.sort();
+        segment_ids
+    }
+
+    impl RecordingListener {
+        fn record(&self, event: RecordedEvent) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    impl IndexWriterEvents for RecordingListener {
+        fn on_flush(&self, segment_meta: &SegmentMeta) {
+            assert!(segment_meta.num_bytes().is_some());
+            self.record(RecordedEvent::Flush(
+                segment_meta.id(),
+                segment_meta.max_doc(),
+            ));
+        }
+
+        fn on_commit(&self, opstamp: Opstamp, segment_metas: &[SegmentMeta]) {
+            self.record(RecordedEvent::Commit(
+                opstamp,
+                sorted_segment_ids(segment_metas),
+            ));
+        }
+
+        fn on_merge_start(&self, segment_metas: &[SegmentMeta], target_opstamp: Opstamp) {
+            self.record(RecordedEvent::MergeStart(
+                sorted_segment_ids(segment_metas),
+                target_opstamp,
+            ));
+        }
+
+        fn on_merge_end(
+            &self,
+            segment_metas: &[SegmentMeta],
+            merged_segment_meta: &SegmentMeta,
+            _doc_mapping: &MergeDocMapping,
+        ) {
+            assert!(merged_segment_meta.num_bytes().is_some());
+            self.record(RecordedEvent::MergeEnd(
+                sorted_segment_ids(segment_metas),
+                merged_segment_meta.id(),
+            ));
+        }
+
+        fn on_segment_deleted(&self, segment_id: SegmentId, deleted_files: &[PathBuf]) {
+            assert!(!deleted_files.is_empty());
+            self.record(RecordedEvent::SegmentDeleted(segment_id));
+        }
+    }
+
+    #[test]
+    fn test_event_listener() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let listener = Arc::new(RecordingListener::default());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_event_listener(listener.clone());
+        index_writer.add_document(doc!(text_field => "a"));
+        let first_opstamp = index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.add_document(doc!(text_field => "c"));
+        let second_opstamp = index_writer.commit()?;
+        let mut segment_ids = index.searchable_segment_ids()?;
+        segment_ids.sort();
+        let merged_segment_meta = block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+
+        let events = std::mem::take(&mut *listener.events.lock().unwrap());
+        let first_segment_id = match events[0] {
+            RecordedEvent::Flush(segment_id, _) => segment_id,
+            _ => panic!("Expected a flush, got {:?}", events[0]),
+        };
+        let second_segment_id = segment_ids
+            .iter()
+            .cloned()
+            .find(|&segment_id| segment_id != first_segment_id)
+            .unwrap();
+        let mut expected_events = vec![
+            RecordedEvent::Flush(first_segment_id, 1),
+            RecordedEvent::Commit(first_opstamp, vec![first_segment_id]),
+            RecordedEvent::Flush(second_segment_id, 2),
+            RecordedEvent::Commit(second_opstamp, segment_ids.clone()),
+            RecordedEvent::MergeStart(segment_ids.clone(), second_opstamp),
+            RecordedEvent::MergeEnd(segment_ids.clone(), merged_segment_meta.id()),
+        ];
+        expected_events.extend(
+            segment_ids
+                .iter()
+                .cloned()
+                .map(RecordedEvent::SegmentDeleted),
+        );
+        assert_eq!(events, expected_events);
+        for segment_id in segment_ids {
+            let postings_path = PathBuf::from(format!("{}.idx", segment_id.uuid_string()));
+            assert!(!index.directory().exists(&postings_path)?);
+        }
+        Ok(())
+    }
+
+    // Records the new doc id of each document of the merged segments.
+    #[derive(Default)]
+    struct DocMappingListener {
+        doc_mappings: std::sync::Mutex<Vec<(SegmentId, DocId, Option<DocId>)>>,
+    }
+
+    impl IndexWriterEvents for DocMappingListener {
+        fn on_merge_end(
+            &self,
+            segment_metas: &[SegmentMeta],
+            _merged_segment_meta: &SegmentMeta,
+            doc_mapping: &MergeDocMapping,
+        ) {
+            let mut doc_mappings = self.doc_mappings.lock().unwrap();
+            for segment_meta in segment_metas {
+                for old_doc in 0..segment_meta.max_doc() {
+                    let new_doc_opt = doc_mapping.map_segment_doc(segment_meta.id(), old_doc);
+                    doc_mappings.push((segment_meta.id(), old_doc, new_doc_opt));
+                }
+            }
+        }
+    }
+
+    fn test_merge_doc_mapping_aux(sort_order: Option<Order>) -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED | STORED | FAST);
+        let schema = schema_builder.build();
+        let settings = IndexSettings {
+            sort_by_field: sort_order.clone().map(|order| IndexSortByField {
+                field: "id".to_string(),
+                order,
+            }),
+            ..Default::default()
+        };
+        let index = Index::builder()
+            .schema(schema)
+            .settings(settings)
+            .create_in_ram()?;
+        let listener = Arc::new(DocMappingListener::default());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_event_listener(listener.clone());
+        // the segments overlap, so the sorted merge interleaves their documents.
+        for ids in &[[3u64, 0, 8, 1, 2], [4, 5, 9, 6, 7]] {
+            for &id in ids {
+                index_writer.add_document(doc!(id_field => id));
+            }
+            index_writer.commit()?;
+        }
+        index_writer.delete_term(Term::from_field_u64(id_field, 4));
+        index_writer.delete_term(Term::from_field_u64(id_field, 6));
+        index_writer.commit()?;
+
+        let stored_id = |searcher: &Searcher, doc_address: DocAddress| -> crate::Result<u64> {
+            let doc = searcher.doc(doc_address)?;
+            Ok(doc.get_first(id_field).unwrap().u64_value().unwrap())
+        };
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let mut old_ids: HashMap<(SegmentId, DocId), u64> = HashMap::new();
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            for doc_id in 0..segment_reader.max_doc() {
+                let doc_address = DocAddress::new(segment_ord as u32, doc_id);
+                let id = stored_id(&searcher, doc_address)?;
+                old_ids.insert((segment_reader.segment_id(), doc_id), id);
+            }
+        }
+        let segment_ids = index.searchable_segment_ids()?;
+        block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let doc_mappings = std::mem::take(&mut *listener.doc_mappings.lock().unwrap());
+        assert_eq!(doc_mappings.len(), 10);
+        let mut new_ids = Vec::new();
+        for (segment_id, old_doc, new_doc_opt) in doc_mappings {
+            let old_id = old_ids[&(segment_id, old_doc)];
+            if old_id == 4 || old_id == 6 {
+                assert_eq!(new_doc_opt, None);
+            } else {
+                let new_doc = new_doc_opt.unwrap();
+                assert_eq!(stored_id(&searcher, DocAddress::new(0, new_doc))?, old_id);
+                new_ids.push((new_doc, old_id));
+            }
+        }
+        new_ids.sort();
+        let ids: Vec<u64> = new_ids.into_iter().map(|(_, id)| id).collect();
+        match sort_order {
+            Some(Order::Asc) => assert_eq!(ids, vec![0, 1, 2, 3, 5, 7, 8, 9]),
+            Some(Order::Desc) => assert_eq!(ids, vec![9, 8, 7, 5, 3, 2, 1, 0]),
+            None => assert_eq!(ids.len(), 8),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_doc_mapping() -> crate::Result<()> {
+        test_merge_doc_mapping_aux(None)
+    }
+
+    #[test]
+    fn test_merge_doc_mapping_sorted_index() -> crate::Result<()> {
+        test_merge_doc_mapping_aux(Some(Order::Asc))?;
+        test_merge_doc_mapping_aux(Some(Order::Desc))
+    }
+
+    fn num_committed_docs(index: &Index) -> crate::Result<u32> {
+        Ok(index
+            .searchable_segment_metas()?
+            .iter()
+            .map(SegmentMeta::num_docs)
+            .sum())
+    }
+
+    // Waits for the auto-commit of `num_docs` documents.
+    fn wait_for_committed_docs(index: &Index, num_docs: u32) -> crate::Result<()> {
+        let start = std::time::Instant::now();
+        while num_committed_docs(index)? < num_docs {
+            assert!(start.elapsed().as_secs() < 10, "No auto-commit happened");
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(num_committed_docs(index)?, num_docs);
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_policy_doc_count() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        assert_eq!(index_writer.commit_policy(), CommitPolicy::Manual);
+        index_writer.set_commit_policy(CommitPolicy::DocCount(3))?;
+        for _ in 0..3 {
+            index_writer.add_document(doc!(text_field => "a"));
+        }
+        wait_for_committed_docs(&index, 3)?;
+
+        // the explicit commits reset the document count.
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "a"));
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(num_committed_docs(&index)?, 4);
+        index_writer.add_document(doc!(text_field => "a"));
+        wait_for_committed_docs(&index, 7)?;
+
+        // the searcher sees the auto-committed documents.
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&query, &Count)?, 7);
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_policy_interval() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let listener = Arc::new(RecordingListener::default());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_event_listener(listener.clone());
+        index_writer.set_commit_policy(CommitPolicy::Interval(Duration::from_millis(10)))?;
+        let opstamp = index_writer.add_document(doc!(text_field => "a"));
+        wait_for_committed_docs(&index, 1)?;
+        assert!(index.load_metas()?.opstamp > opstamp);
+        let num_commits = || {
+            listener
+                .events
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|event| matches!(event, RecordedEvent::Commit(..)))
+                .count()
+        };
+        assert_eq!(num_commits(), 1);
+        // nothing is committed without pending operations.
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(num_commits(), 1);
+        index_writer.set_commit_policy(CommitPolicy::Manual)?;
+        index_writer.add_document(doc!(text_field => "a"));
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(num_committed_docs(&index)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_policy_prepared_commit_and_rollback() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let commit_policy = CommitPolicy::Interval(Duration::from_millis(10));
+        index_writer.set_commit_policy(commit_policy)?;
+        index_writer.add_document(doc!(text_field => "a"));
+        {
+            let prepared_commit = index_writer.prepare_commit()?;
+            // no auto-commit happens while the commit is prepared.
+            thread::sleep(Duration::from_millis(50));
+            assert_eq!(num_committed_docs(&index)?, 0);
+            prepared_commit.abort()?;
+        }
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(num_committed_docs(&index)?, 0);
+        // the commit policy survives the rollback.
+        assert_eq!(index_writer.commit_policy(), commit_policy);
+        index_writer.add_document(doc!(text_field => "a"));
+        wait_for_committed_docs(&index, 1)?;
+        index_writer.wait_merging_threads()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepare_commit_with_id_survives_restart() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let directory = RamDirectory::create();
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let prepared_opstamp;
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.commit()?;
+            index_writer.add_document(doc!(text_field => "b"));
+            index_writer.add_document(doc!(text_field => "c"));
+            index_writer.delete_term(Term::from_field_text(text_field, "a"));
+            let prepared_commit = index_writer.prepare_commit_with_id("tx-1")?;
+            assert_eq!(prepared_commit.external_id(), Some("tx-1"));
+            prepared_opstamp = prepared_commit.opstamp();
+            // the process crashes before the commit is finalized.
+        }
+        let index = Index::open(directory.clone())?;
+        assert_eq!(num_committed_docs(&index)?, 1);
+        let prepared_commits = index.list_prepared_commits()?;
+        assert_eq!(prepared_commits.len(), 1);
+        assert_eq!(prepared_commits[0].external_id, "tx-1");
+        assert_eq!(prepared_commits[0].opstamp(), prepared_opstamp);
+        let prepared_files: Vec<PathBuf> = prepared_commits[0]
+            .index_meta
+            .segments
+            .iter()
+            .flat_map(SegmentMeta::list_files)
+            .filter(|path| directory.exists(path).unwrap())
+            .collect();
+        assert!(prepared_files
+            .iter()
+            .any(|path| path.extension().unwrap() == "del"));
+        drop(prepared_commits);
+
+        let mut index_writer = index.writer_for_tests()?;
+        block_on(index_writer.garbage_collect_files())?;
+        for path in &prepared_files {
+            assert!(directory.exists(path)?, "{:?} was deleted", path);
+        }
+        assert_eq!(index_writer.finalize_prepared("tx-1")?, prepared_opstamp);
+        assert_eq!(index_writer.commit_opstamp(), prepared_opstamp);
+        assert!(index.list_prepared_commits()?.is_empty());
+        assert!(!directory.exists(Path::new(".prepared_commits.json"))?);
+        block_on(index_writer.garbage_collect_files())?;
+
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.num_docs(), 2);
+        let term_a = Term::from_field_text(text_field, "a");
+        let term_query = TermQuery::new(term_a, IndexRecordOption::Basic);
+        assert_eq!(searcher.search(&term_query, &Count)?, 0);
+        // the writer is usable after the commit is finalized.
+        index_writer.add_document(doc!(text_field => "d"));
+        index_writer.commit()?;
+        assert_eq!(num_committed_docs(&index)?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepare_commit_with_id() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "a"));
+        {
+            let mut prepared_commit = index_writer.prepare_commit_with_id("tx-1")?;
+            prepared_commit.set_payload("payload-1");
+            prepared_commit.commit()?;
+        }
+        assert_eq!(num_committed_docs(&index)?, 1);
+        assert_eq!(index.load_metas()?.payload(), Some("payload-1"));
+
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.prepare_commit_with_id("tx-2")?.abort()?;
+        assert_eq!(num_committed_docs(&index)?, 1);
+        assert!(index.list_prepared_commits()?.is_empty());
+
+        index_writer.add_document(doc!(text_field => "c"));
+        drop(index_writer.prepare_commit_with_id("tx-3")?);
+        assert!(index_writer.prepare_commit_with_id("tx-3").is_err());
+        assert!(index_writer.finalize_prepared("tx-4").is_err());
+        assert!(index_writer.abort_prepared("tx-4").is_err());
+        // the prepared commit is obsolete once the index is committed.
+        index_writer.commit()?;
+        assert!(matches!(
+            index_writer.finalize_prepared("tx-3"),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        assert_eq!(index.list_prepared_commits()?.len(), 1);
+        index_writer.abort_prepared("tx-3")?;
+        assert!(index.list_prepared_commits()?.is_empty());
+        // "c" was committed by the regular commit.
+        assert_eq!(num_committed_docs(&index)?, 2);
+        Ok(())
+    }
+
     #[test]
     fn test_lockfile_released_on_drop() {
         let schema_builder = schema::Schema::builder();
@@ -1100,6 +2285,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_commit_with_payload_round_trip() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let directory = RamDirectory::create();
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.commit_with_payload("checkpoint-1")?;
+            index_writer.add_document(doc!(text_field => "b"));
+            let mut prepared_commit = index_writer.prepare_commit()?;
+            prepared_commit.set_payload("checkpoint-2");
+            prepared_commit.abort()?;
+            // The rollback restores the payload of the last commit.
+            assert_eq!(index.load_metas()?.payload(), Some("checkpoint-1"));
+        }
+        let reopened_index = Index::open(directory)?;
+        assert_eq!(reopened_index.load_metas()?.payload(), Some("checkpoint-1"));
+        let searcher = reopened_index.reader()?.searcher();
+        assert_eq!(searcher.payload(), Some("checkpoint-1"));
+        assert_eq!(searcher.num_docs(), 1);
+        Ok(())
+    }
+
     #[test]
     fn test_prepare_but_rollback() {
         let mut schema_builder = schema::Schema::builder();
@@ -1306,6 +2520,281 @@ mod tests {
         assert!(commit_again.is_ok());
     }
 
+    #[test]
+    fn test_delete_query_date_range() -> crate::Result<()> {
+        use crate::query::{Query, RangeQuery};
+        use crate::schema::Type;
+        use chrono::{TimeZone, Utc};
+        use std::ops::Bound;
+
+        let mut schema_builder = schema::Schema::builder();
+        let date_field = schema_builder.add_date_field("date", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let index_reader = index.reader()?;
+        let mut index_writer = index.writer_for_tests()?;
+        let day = |day: u32| Utc.ymd(2021, 1, day).and_hms(0, 0, 0);
+        for day_of_month in 1..=20 {
+            index_writer.add_document(doc!(date_field => day(day_of_month)));
+        }
+        index_writer.commit()?;
+        for day_of_month in 21..=25 {
+            index_writer.add_document(doc!(date_field => day(day_of_month)));
+        }
+        // Purges everything older than the 10th.
+        let purge_query = RangeQuery::new_term_bounds(
+            date_field,
+            Type::Date,
+            &Bound::Unbounded,
+            &Bound::Excluded(Term::from_field_date(date_field, &day(10))),
+        );
+        index_writer.delete_query(Box::new(purge_query));
+        // Documents added after the delete query are not affected.
+        index_writer.add_document(doc!(date_field => day(1)));
+        index_writer.commit()?;
+        index_reader.reload()?;
+        let searcher = index_reader.searcher();
+        // 20 + 5 + 1 docs, minus the 9 docs from the 1st to the 9th.
+        assert_eq!(searcher.num_docs(), 17);
+        let count_day = |day_of_month: u32| {
+            let term = Term::from_field_date(date_field, &day(day_of_month));
+            let term_query = TermQuery::new(term, IndexRecordOption::Basic);
+            term_query.count(&searcher).unwrap()
+        };
+        assert_eq!(count_day(1), 1);
+        assert_eq!(count_day(9), 0);
+        assert_eq!(count_day(10), 1);
+        assert_eq!(count_day(25), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_query_concurrent_add_document() -> crate::Result<()> {
+        use crate::query::AllQuery;
+        use crate::Opstamp;
+
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let index_reader = index.reader()?;
+        let mut index_writer = index.writer_with_num_threads(4, 12_000_000)?;
+        let (delete_opstamp, add_opstamps) = crossbeam::scope(|scope| {
+            let handles: Vec<_> = (0u64..4u64)
+                .map(|thread_id| {
+                    let index_writer = &index_writer;
+                    scope.spawn(move |_| {
+                        (0u64..1_000u64)
+                            .map(|i| {
+                                let id = thread_id * 1_000 + i;
+                                (id, index_writer.add_document(doc!(id_field => id)))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            let delete_opstamp = index_writer.delete_query(Box::new(AllQuery));
+            let add_opstamps: Vec<(u64, Opstamp)> = handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect();
+            (delete_opstamp, add_opstamps)
+        })
+        .unwrap();
+        index_writer.commit()?;
+        index_reader.reload()?;
+        let searcher = index_reader.searcher();
+        let expected_alive_ids: HashSet<u64> = add_opstamps
+            .iter()
+            .filter(|(_, opstamp)| *opstamp > delete_opstamp)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut alive_ids = HashSet::new();
+        for segment_reader in searcher.segment_readers() {
+            let id_reader = segment_reader.fast_fields().u64(id_field)?;
+            for doc in segment_reader.doc_ids_alive() {
+                alive_ids.insert(id_reader.get(doc));
+            }
+        }
+        assert_eq!(alive_ids, expected_alive_ids);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_document() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED);
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let index_reader = index.reader()?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(id_field => 1u64, text_field => "first version"));
+        index_writer.add_document(doc!(id_field => 2u64, text_field => "other document"));
+        index_writer.commit()?;
+        let term = Term::from_field_u64(id_field, 1u64);
+        index_writer.update_document(
+            term.clone(),
+            doc!(id_field => 1u64, text_field => "second version"),
+        );
+        index_writer.commit()?;
+        index_reader.reload()?;
+        let searcher = index_reader.searcher();
+        assert_eq!(searcher.num_docs(), 2);
+        let term_query = TermQuery::new(term, IndexRecordOption::Basic);
+        let top_docs = searcher.search(&term_query, &TopDocs::with_limit(10))?;
+        assert_eq!(top_docs.len(), 1);
+        let doc = searcher.doc(top_docs[0].1)?;
+        assert_eq!(
+            doc.get_first(text_field).unwrap().text(),
+            Some("second version")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_document_concurrent() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED);
+        let version_field = schema_builder.add_u64_field("version", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let index_reader = index.reader()?;
+        let mut index_writer = index.writer_with_num_threads(4, 12_000_000)?;
+        let count_docs = |searcher: &crate::Searcher, term: Term| {
+            searcher
+                .search(
+                    &TermQuery::new(term, IndexRecordOption::Basic),
+                    &crate::collector::Count,
+                )
+                .unwrap()
+        };
+        for _ in 0..5 {
+            crossbeam::scope(|scope| {
+                for thread_id in 0u64..4u64 {
+                    let index_writer = &index_writer;
+                    scope.spawn(move |_| {
+                        for version in 0u64..200u64 {
+                            index_writer.update_document(
+                                Term::from_field_u64(id_field, 1u64),
+                                doc!(id_field => 1u64, version_field => thread_id * 1_000 + version),
+                            );
+                        }
+                    });
+                }
+            })
+            .unwrap();
+            index_writer.commit()?;
+            index_reader.reload()?;
+            let searcher = index_reader.searcher();
+            assert_eq!(
+                count_docs(&searcher, Term::from_field_u64(id_field, 1u64)),
+                1
+            );
+            assert_eq!(searcher.num_docs(), 1);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_document_concurrent_with_delete_term() -> crate::Result<()> {
+        use crate::Opstamp;
+
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let index_reader = index.reader()?;
+        let mut index_writer = index.writer_with_num_threads(4, 12_000_000)?;
+        for _ in 0..5 {
+            let term = Term::from_field_u64(id_field, 1u64);
+            let (last_update_opstamp, delete_opstamp) = crossbeam::scope(|scope| {
+                let index_writer = &index_writer;
+                let term = &term;
+                let update_handle = scope.spawn(move |_| {
+                    (0..500)
+                        .map(|_| index_writer.update_document(term.clone(), doc!(id_field => 1u64)))
+                        .max()
+                        .unwrap()
+                });
+                let delete_opstamp: Opstamp = index_writer.delete_term(term.clone());
+                (update_handle.join().unwrap(), delete_opstamp)
+            })
+            .unwrap();
+            index_writer.commit()?;
+            index_reader.reload()?;
+            let expected_num_docs = if last_update_opstamp > delete_opstamp {
+                1
+            } else {
+                0
+            };
+            assert_eq!(index_reader.searcher().num_docs(), expected_num_docs);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_documents_block() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let block_field = schema_builder.add_u64_field("block", FAST);
+        let pos_field = schema_builder.add_u64_field("pos", FAST);
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let index_reader = index.reader()?;
+        // Small heap, so that the blocks end up in several segments.
+        let mut index_writer = index.writer_with_num_threads(2, 8_000_000)?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        let num_blocks = 300u64;
+        crossbeam::scope(|scope| {
+            for thread_id in 0u64..2u64 {
+                let index_writer = &index_writer;
+                scope.spawn(move |_| {
+                    for block in (0..num_blocks).filter(|block| block % 2 == thread_id) {
+                        let documents: Vec<crate::Document> = (0..(block % 7) + 1)
+                            .map(|pos| doc!(block_field => block, pos_field => pos, text_field => LOREM))
+                            .collect();
+                        index_writer.add_documents(documents).unwrap();
+                    }
+                });
+            }
+        })
+        .unwrap();
+        index_writer.commit()?;
+        index_reader.reload()?;
+        let searcher = index_reader.searcher();
+        assert!(searcher.segment_readers().len() > 1);
+        let mut block_segments: HashMap<u64, usize> = HashMap::new();
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            let block_reader = segment_reader.fast_fields().u64(block_field)?;
+            let pos_reader = segment_reader.fast_fields().u64(pos_field)?;
+            let mut doc = 0;
+            while doc < segment_reader.max_doc() {
+                let block = block_reader.get(doc);
+                assert!(block_segments.insert(block, segment_ord).is_none());
+                for pos in 0..(block % 7) + 1 {
+                    assert_eq!(block_reader.get(doc), block);
+                    assert_eq!(pos_reader.get(doc), pos);
+                    doc += 1;
+                }
+            }
+        }
+        assert_eq!(block_segments.len(), num_blocks as usize);
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_documents_block_too_large() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 3_000_000)?;
+        let documents: Vec<crate::Document> =
+            (0..10_000).map(|_| doc!(text_field => LOREM)).collect();
+        assert!(matches!(
+            index_writer.add_documents(documents),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        index_writer.add_documents(vec![doc!(text_field => "small block")])?;
+        index_writer.commit()?;
+        assert_eq!(index.reader()?.searcher().num_docs(), 1);
+        Ok(())
+    }
+
     #[test]
     fn test_delete_with_sort_by_field() -> crate::Result<()> {
         let mut schema_builder = schema::Schema::builder();
@@ -0,0 +1,76 @@
+use crate::core::{SegmentId, SegmentMeta};
+use crate::indexer::MergeDocMapping;
+use crate::Opstamp;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// Listener of the lifecycle events of an `IndexWriter`, registered with
+/// [`IndexWriter::add_event_listener`](./struct.IndexWriter.html#method.add_event_listener).
+///
+/// All of the methods do nothing by default.
+///
+/// The methods are called synchronously, from the thread of the segment updater or from the
+/// thread starting a merge, and must not block: a slow listener slows down the indexing.
+/// Expensive work, like replicating files, should be sent to another thread.
+///
+/// The events of the segment updater are ordered. In particular:
+/// - a segment is flushed before the commit including it,
+/// - `on_commit` is called once the `meta.json` is saved,
+/// - `on_merge_start` is called before the `on_merge_end` of the same merge, which is
+///   called once the merged segment has replaced the merged segments,
+/// - the segments are deleted once they are no longer used by the index, after the commit
+///   or the merge which made them obsolete.
+pub trait IndexWriterEvents: Send + Sync + 'static {
+    /// Called when a new segment is added to the index writer, after it has been
+    /// flushed by an indexing thread.
+    ///
+    /// The segment is not searchable until the next commit.
+    fn on_flush(&self, _segment_meta: &SegmentMeta) {}
+
+    /// Called after a commit, once the `meta.json` listing the `segment_metas`
+    /// is saved.
+    fn on_commit(&self, _opstamp: Opstamp, _segment_metas: &[SegmentMeta]) {}
+
+    /// Called when the merge of the `segment_metas` starts.
+    ///
+    /// The deletes up to `target_opstamp` are applied to the merged segment.
+    fn on_merge_start(&self, _segment_metas: &[SegmentMeta], _target_opstamp: Opstamp) {}
+
+    /// Called when the merge of the `segment_metas` into the `merged_segment_meta` ends.
+    ///
+    /// The `doc_mapping` translates the doc ids of the merged segments into the doc ids
+    /// of the merged segment, e.g. to update the `DocAddress` of a cache. It is dropped
+    /// right after the call.
+    ///
+    /// If the merged segments were committed, the `meta.json` lists the merged segment
+    /// instead of the merged segments.
+    /// This is not called if the merge failed or was cancelled.
+    fn on_merge_end(
+        &self,
+        _segment_metas: &[SegmentMeta],
+        _merged_segment_meta: &SegmentMeta,
+        _doc_mapping: &MergeDocMapping,
+    ) {
+    }
+
+    /// Called when the files of a segment, which is no longer used by the index, have
+    /// been deleted by the garbage collector.
+    fn on_segment_deleted(&self, _segment_id: SegmentId, _deleted_files: &[PathBuf]) {}
+}
+
+/// The listeners of the events of an `IndexWriter`.
+#[derive(Default)]
+pub(crate) struct IndexWriterEventListeners(RwLock<Vec<Arc<dyn IndexWriterEvents>>>);
+
+impl IndexWriterEventListeners {
+    pub fn add(&self, listener: Arc<dyn IndexWriterEvents>) {
+        self.0.write().unwrap().push(listener);
+    }
+
+    /// Calls `notify` for each of the listeners, in the order they were added.
+    pub fn notify<F: Fn(&dyn IndexWriterEvents)>(&self, notify: F) {
+        for listener in self.0.read().unwrap().iter() {
+            notify(listener.as_ref());
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use crate::postings::MultiFieldPostingsWriter;
+use crate::schema::{
+    Field, Term, JSON_BOOL_CODE, JSON_F64_CODE, JSON_I64_CODE, JSON_TEXT_CODE, JSON_U64_CODE,
+};
+use crate::tokenizer::{BoxTokenStream, TextAnalyzer, Token, TokenStream};
+use crate::DocId;
+use serde_json::{Map, Value as JsonValue};
+
+/// Gap between the positions of two text leaves, so that
+/// phrase queries do not match across them.
+const POSITION_GAP: usize = 2;
+
+/// Token stream shifting the positions of the tokens of its tail.
+///
+/// The shifted tokens are copies: the tokenizers compute the position of a token
+/// from the position of the previous one, so the tokens of the tail are left untouched.
+struct ShiftedTokenStream<'a> {
+    tail: BoxTokenStream<'a>,
+    position_shift: usize,
+    token: Token,
+    /// Position of the last token emitted, if any.
+    last_position: Option<usize>,
+}
+
+impl<'a> TokenStream for ShiftedTokenStream<'a> {
+    fn advance(&mut self) -> bool {
+        if !self.tail.advance() {
+            return false;
+        }
+        let token = self.tail.token();
+        self.token.offset_from = token.offset_from;
+        self.token.offset_to = token.offset_to;
+        self.token.position = token.position.wrapping_add(self.position_shift);
+        self.token.position_length = token.position_length;
+        self.token.payload = token.payload;
+        self.token.text.clear();
+        self.token.text.push_str(&token.text);
+        self.last_position = Some(self.token.position);
+        true
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+/// Indexes the leaves of the json objects of a field of a document.
+struct JsonObjectsWriter<'a> {
+    doc: DocId,
+    field: Field,
+    text_analyzer: Option<&'a TextAnalyzer>,
+    term_buffer: &'a mut Term,
+    postings_writer: &'a mut MultiFieldPostingsWriter,
+    /// Path of the current leaf, i.e. its keys joined by dots.
+    json_path: String,
+    position_shift: usize,
+    num_tokens: u32,
+}
+
+impl<'a> JsonObjectsWriter<'a> {
+    fn index_object(&mut self, json_object: &Map<String, JsonValue>) {
+        for (key, json_value) in json_object {
+            let path_len = self.json_path.len();
+            if path_len > 0 {
+                self.json_path.push('.');
+            }
+            self.json_path.push_str(key);
+            self.index_value(json_value);
+            self.json_path.truncate(path_len);
+        }
+    }
+
+    fn index_value(&mut self, json_value: &JsonValue) {
+        match json_value {
+            JsonValue::Null => {}
+            JsonValue::Bool(val) => {
+                self.set_path(JSON_BOOL_CODE);
+                self.term_buffer.append_bytes(&[*val as u8]);
+                self.postings_writer.subscribe(self.doc, self.term_buffer);
+            }
+            JsonValue::Number(number) => {
+                let (type_code, val) = if let Some(val) = number.as_i64() {
+                    (JSON_I64_CODE, common::i64_to_u64(val))
+                } else if let Some(val) = number.as_u64() {
+                    (JSON_U64_CODE, val)
+                } else {
+                    let val = number.as_f64().unwrap_or(f64::NAN);
+                    (JSON_F64_CODE, common::f64_to_u64(val))
+                };
+                self.set_path(type_code);
+                self.term_buffer.append_bytes(val.to_be_bytes().as_ref());
+                self.postings_writer.subscribe(self.doc, self.term_buffer);
+            }
+            JsonValue::String(text) => self.index_text(text),
+            JsonValue::Array(json_values) => {
+                for json_value in json_values {
+                    self.index_value(json_value);
+                }
+            }
+            JsonValue::Object(json_object) => self.index_object(json_object),
+        }
+    }
+
+    fn index_text(&mut self, text: &str) {
+        let text_analyzer = match self.text_analyzer {
+            Some(text_analyzer) => text_analyzer,
+            None => return,
+        };
+        self.set_path(JSON_TEXT_CODE);
+        let mut token_stream = ShiftedTokenStream {
+            tail: text_analyzer.token_stream(text),
+            position_shift: self.position_shift,
+            token: Token::default(),
+            last_position: None,
+        };
+        self.num_tokens +=
+            self.postings_writer
+                .index_text(self.doc, &mut token_stream, self.term_buffer);
+        if let Some(last_position) = token_stream.last_position {
+            self.position_shift = last_position + 1 + POSITION_GAP;
+        }
+    }
+
+    fn set_path(&mut self, type_code: u8) {
+        self.term_buffer
+            .set_json_path(self.field, &self.json_path, type_code);
+    }
+}
+
+/// Indexes the leaves of the json objects of a field of a document,
+/// and returns the number of tokens of their text leaves.
+///
+/// The text leaves are skipped if `text_analyzer` is `None`.
+pub(crate) fn index_json_objects(
+    doc: DocId,
+    field: Field,
+    json_objects: &[&Map<String, JsonValue>],
+    text_analyzer: Option<&TextAnalyzer>,
+    term_buffer: &mut Term,
+    postings_writer: &mut MultiFieldPostingsWriter,
+) -> u32 {
+    let mut writer = JsonObjectsWriter {
+        doc,
+        field,
+        text_analyzer,
+        term_buffer,
+        postings_writer,
+        json_path: String::new(),
+        position_shift: 0,
+        num_tokens: 0,
+    };
+    for json_object in json_objects {
+        writer.index_object(json_object);
+    }
+    writer.num_tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::Count;
+    use crate::query::{PhraseQuery, QueryParser, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, Term, Value, STORED, STRING, TEXT};
+    use crate::Index;
+    use serde_json::json;
+
+    fn json_object(json: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        json.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_json_field_indexing() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let attributes = schema_builder.add_json_field("attributes", STORED | TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let first_attributes = json_object(json!({
+            "user": {"id": 12, "name": "Paul Smith", "admin": true},
+            "tags": ["red apple", "green pear"],
+            "price": 1.5,
+            "serial": 18446744073709551615u64,
+            "missing": null,
+        }));
+        index_writer.add_document(doc!(attributes => first_attributes.clone()));
+        index_writer.add_document(doc!(attributes => json_object(json!({
+            "user": [{"id": "12"}, {"name": "Jane"}],
+            "tags": [["nested", ["arrays"]]],
+        }))));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count =
+            |term: Term| searcher.search(&TermQuery::new(term, IndexRecordOption::Basic), &Count);
+        assert_eq!(
+            count(Term::from_field_json_i64(attributes, "user.id", 12))?,
+            1
+        );
+        assert_eq!(
+            count(Term::from_field_json_text(attributes, "user.id", "12"))?,
+            1
+        );
+        assert_eq!(
+            count(Term::from_field_json_text(attributes, "user.name", "paul"))?,
+            1
+        );
+        assert_eq!(
+            count(Term::from_field_json_text(attributes, "user.name", "jane"))?,
+            1
+        );
+        assert_eq!(
+            count(Term::from_field_json_text(attributes, "name", "paul"))?,
+            0
+        );
+        assert_eq!(
+            count(Term::from_field_json_bool(attributes, "user.admin", true))?,
+            1
+        );
+        assert_eq!(
+            count(Term::from_field_json_f64(attributes, "price", 1.5))?,
+            1
+        );
+        assert_eq!(
+            count(Term::from_field_json_u64(attributes, "serial", u64::MAX))?,
+            1
+        );
+        assert_eq!(
+            count(Term::from_field_json_text(attributes, "tags", "pear"))?,
+            1
+        );
+        assert_eq!(
+            count(Term::from_field_json_text(attributes, "tags", "arrays"))?,
+            1
+        );
+
+        // phrases do not match across the values of an array.
+        let phrase = |words: &[&str]| {
+            let terms = words
+                .iter()
+                .map(|word| Term::from_field_json_text(attributes, "tags", word))
+                .collect();
+            searcher.search(&PhraseQuery::new(terms), &Count)
+        };
+        assert_eq!(phrase(&["red", "apple"])?, 1);
+        assert_eq!(phrase(&["apple", "green"])?, 0);
+        assert_eq!(phrase(&["red", "pear"])?, 0);
+
+        let doc = searcher.doc(crate::DocAddress::new(0, 0))?;
+        assert_eq!(
+            doc.get_first(attributes),
+            Some(&Value::JsonObject(first_attributes))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_field_query_parser() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let attributes = schema_builder.add_json_field("attributes", TEXT);
+        let user_id = schema_builder.add_text_field("attributes.user.id", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(attributes => json_object(json!({
+            "user": {"id": 12, "name": "Paul Smith"},
+            "score": 3.0,
+            "active": true,
+        }))));
+        index_writer.add_document(doc!(attributes => json_object(json!({
+            "user": {"name": "Smith Paul"},
+            "score": "3",
+            "active": "true",
+        }))));
+        index_writer.add_document(doc!(user_id => "12"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![]);
+        let count = |query: &str| searcher.search(&query_parser.parse_query(query)?, &Count);
+        // a field of the schema has precedence over the paths of a json object field.
+        assert_eq!(count("attributes.user.id:12")?, 1);
+        assert_eq!(count("attributes.user.name:paul")?, 2);
+        assert_eq!(count("attributes.user.name:\"paul smith\"")?, 1);
+        // the number and the text `3` are searched, as well as the float `3.0`.
+        assert_eq!(count("attributes.score:3")?, 2);
+        assert_eq!(count("attributes.score:3.0")?, 1);
+        assert_eq!(count("attributes.active:true")?, 2);
+        assert_eq!(count("attributes.user:paul")?, 0);
+        assert!(query_parser
+            .parse_query("attributes.user.name:paul~1")
+            .is_err());
+        assert!(query_parser
+            .parse_query("attribute.user.name:paul")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_field_phrase_after_first_leaf() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let attributes = schema_builder.add_json_field("attributes", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(attributes => json_object(json!({
+            "a": "x y",
+            "user": {"bio": "a long story told", "name": "John Doe"},
+            "z": "last words",
+        }))));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![]);
+        let count = |query: &str| searcher.search(&query_parser.parse_query(query)?, &Count);
+        assert_eq!(count("attributes.user.name:\"john doe\"")?, 1);
+        assert_eq!(count("attributes.user.bio:\"long story told\"")?, 1);
+        assert_eq!(count("attributes.z:\"last words\"")?, 1);
+        assert_eq!(count("attributes.user.bio:\"story long\"")?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_field_range_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let attributes = schema_builder.add_json_field("attributes", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for (id, price, name) in [
+            (50, 1.5, "alice"),
+            (100, 10.0, "bob"),
+            (150, 99.9, "carol"),
+            (200, 100.0, "dave"),
+            (250, 1000.5, "eve"),
+        ] {
+            index_writer.add_document(doc!(attributes => json_object(json!({
+                "user": {"id": id, "name": name},
+                "price": price,
+                "other": {"id": 120},
+            }))));
+        }
+        index_writer.add_document(doc!(attributes => json_object(json!({
+            "user": {"id": 120.5},
+        }))));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![]);
+        let count = |query: &str| searcher.search(&query_parser.parse_query(query)?, &Count);
+        assert_eq!(count("attributes.user.id:[100 TO 200]")?, 4);
+        assert_eq!(count("attributes.user.id:{100 TO 200}")?, 2);
+        assert_eq!(count("attributes.user.id:[100.5 TO 199.5]")?, 2);
+        assert_eq!(count("attributes.user.id:>=150")?, 3);
+        assert_eq!(count("attributes.user.id:<100")?, 1);
+        assert_eq!(count("attributes.user.id:[* TO *]")?, 6);
+        assert_eq!(count("attributes.price:[10 TO 100]")?, 3);
+        assert_eq!(count("attributes.price:>100")?, 1);
+        assert_eq!(count("attributes.user.name:[bob TO dave]")?, 3);
+        assert_eq!(count("attributes.user.name:{bob TO *]")?, 3);
+        assert_eq!(count("attributes.user.name:<carol")?, 2);
+        assert_eq!(count("attributes.missing:[0 TO 1000]")?, 0);
+        Ok(())
+    }
+}
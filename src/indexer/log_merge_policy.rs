@@ -8,15 +8,20 @@ const DEFAULT_LEVEL_LOG_SIZE: f64 = 0.75;
 const DEFAULT_MIN_LAYER_SIZE: u32 = 10_000;
 const DEFAULT_MIN_NUM_SEGMENTS_IN_MERGE: usize = 8;
 const DEFAULT_MAX_DOCS_BEFORE_MERGE: usize = 10_000_000;
+const DEFAULT_DEL_DOCS_RATIO_BEFORE_MERGE: f32 = 1.0f32;
 
 /// `LogMergePolicy` tries to merge segments that have a similar number of
 /// documents.
+///
+/// The segments with too many deleted documents are also merged, whatever
+/// their number of documents, to reclaim the space of their deleted documents.
 #[derive(Debug, Clone)]
 pub struct LogMergePolicy {
     min_num_segments: usize,
     max_docs_before_merge: usize,
     min_layer_size: u32,
     level_log_size: f64,
+    del_docs_ratio_before_merge: f32,
 }
 
 impl LogMergePolicy {
@@ -52,17 +57,51 @@ impl LogMergePolicy {
     pub fn set_level_log_size(&mut self, level_log_size: f64) {
         self.level_log_size = level_log_size;
     }
+
+    /// Set the ratio of deleted documents in a segment above which the segment is
+    /// merged, whatever its level.
+    ///
+    /// The ratio must be in `(0, 1]`. The default value of `1.0` never triggers a
+    /// merge because of the deleted documents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ratio is not in `(0, 1]`.
+    pub fn set_del_docs_ratio_before_merge(&mut self, del_docs_ratio_before_merge: f32) {
+        assert!(
+            del_docs_ratio_before_merge > 0f32 && del_docs_ratio_before_merge <= 1f32,
+            "The ratio of deleted documents must be in (0, 1]."
+        );
+        self.del_docs_ratio_before_merge = del_docs_ratio_before_merge;
+    }
+
+    fn has_too_many_deletes(&self, segment: &SegmentMeta) -> bool {
+        segment.num_deleted_docs() as f32
+            > self.del_docs_ratio_before_merge * segment.max_doc() as f32
+    }
 }
 
 impl MergePolicy for LogMergePolicy {
     fn compute_merge_candidates(&self, segments: &[SegmentMeta]) -> Vec<MergeCandidate> {
-        let mut size_sorted_segments = segments
+        let (segments_with_deletes, mut size_sorted_segments): (
+            Vec<&SegmentMeta>,
+            Vec<&SegmentMeta>,
+        ) = segments
             .iter()
             .filter(|segment_meta| segment_meta.num_docs() <= (self.max_docs_before_merge as u32))
-            .collect::<Vec<&SegmentMeta>>();
+            .partition(|segment_meta| self.has_too_many_deletes(segment_meta));
+
+        // The segments with too many deletes are merged together, even if there is
+        // only one of them, as the merge drops the deleted documents.
+        let mut merge_candidates = Vec::new();
+        if !segments_with_deletes.is_empty() {
+            merge_candidates.push(MergeCandidate(
+                segments_with_deletes.iter().map(|seg| seg.id()).collect(),
+            ));
+        }
 
         if size_sorted_segments.len() <= 1 {
-            return vec![];
+            return merge_candidates;
         }
         size_sorted_segments.sort_by_key(|seg| std::cmp::Reverse(seg.num_docs()));
 
@@ -80,11 +119,13 @@ impl MergePolicy for LogMergePolicy {
             levels.push(merge_group.collect::<Vec<&SegmentMeta>>());
         }
 
-        levels
-            .iter()
-            .filter(|level| level.len() >= self.min_num_segments)
-            .map(|segments| MergeCandidate(segments.iter().map(|&seg| seg.id()).collect()))
-            .collect()
+        merge_candidates.extend(
+            levels
+                .iter()
+                .filter(|level| level.len() >= self.min_num_segments)
+                .map(|segments| MergeCandidate(segments.iter().map(|&seg| seg.id()).collect())),
+        );
+        merge_candidates
     }
 }
 
@@ -95,6 +136,7 @@ impl Default for LogMergePolicy {
             max_docs_before_merge: DEFAULT_MAX_DOCS_BEFORE_MERGE,
             min_layer_size: DEFAULT_MIN_LAYER_SIZE,
             level_log_size: DEFAULT_LEVEL_LOG_SIZE,
+            del_docs_ratio_before_merge: DEFAULT_DEL_DOCS_RATIO_BEFORE_MERGE,
         }
     }
 }
@@ -287,4 +329,51 @@ mod tests {
         assert_eq!(result_list[0].0[1], test_input[4].id());
         assert_eq!(result_list[0].0[2], test_input[5].id());
     }
+
+    fn create_segment_meta_with_deletes(max_doc: u32, num_deleted_docs: u32) -> SegmentMeta {
+        create_random_segment_meta(max_doc).with_delete_meta(num_deleted_docs, 0)
+    }
+
+    #[test]
+    fn test_log_merge_policy_del_docs_ratio() {
+        let test_input = vec![
+            create_segment_meta_with_deletes(100_000, 40_000),
+            create_segment_meta_with_deletes(10, 1),
+            create_random_segment_meta(10),
+            create_segment_meta_with_deletes(10, 5),
+        ];
+        // by default, the deletes do not trigger a merge.
+        assert!(test_merge_policy()
+            .compute_merge_candidates(&test_input)
+            .is_empty());
+
+        let mut merge_policy = test_merge_policy();
+        merge_policy.set_del_docs_ratio_before_merge(0.3);
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        assert_eq!(result_list.len(), 1);
+        assert_eq!(
+            result_list[0].0,
+            vec![test_input[0].id(), test_input[3].id()]
+        );
+    }
+
+    #[test]
+    fn test_log_merge_policy_del_docs_ratio_single_segment() {
+        let test_input = vec![create_segment_meta_with_deletes(1_000, 500)];
+        let mut merge_policy = test_merge_policy();
+        merge_policy.set_del_docs_ratio_before_merge(0.5);
+        assert!(merge_policy
+            .compute_merge_candidates(&test_input)
+            .is_empty());
+        merge_policy.set_del_docs_ratio_before_merge(0.4);
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        assert_eq!(result_list.len(), 1);
+        assert_eq!(result_list[0].0, vec![test_input[0].id()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The ratio of deleted documents must be in (0, 1].")]
+    fn test_log_merge_policy_invalid_del_docs_ratio() {
+        LogMergePolicy::default().set_del_docs_ratio_before_merge(0.0);
+    }
 }
@@ -1,8 +1,19 @@
 use crate::Opstamp;
 use crate::SegmentId;
+use crate::TantivyError;
 use census::{Inventory, TrackedObject};
+use common::{AntiCallToken, TerminatingWrite};
 use std::collections::HashSet;
+use std::io::{self, Write};
 use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// The throttled writes do not pause for less than this duration, the delay accumulating
+// until the next write instead.
+const MIN_PAUSE: Duration = Duration::from_millis(5);
 
 #[derive(Default)]
 pub(crate) struct MergeOperationInventory(Inventory<InnerMergeOperation>);
@@ -25,6 +36,173 @@ impl MergeOperationInventory {
         }
         segment_in_merge
     }
+
+    /// Returns the merge operations that have started.
+    pub fn running_merges(&self) -> Vec<MergeOperationInfo> {
+        self.list()
+            .into_iter()
+            .filter(|merge_op| merge_op.progress.is_running())
+            .map(|merge_op| MergeOperationInfo {
+                segment_ids: merge_op.segment_ids.clone(),
+                target_opstamp: merge_op.target_opstamp,
+                bytes_written: merge_op.progress.bytes_written(),
+            })
+            .collect()
+    }
+
+    /// Cancels all of the merge operations, running or not.
+    pub fn cancel_all(&self) {
+        for merge_op in self.list() {
+            merge_op.progress.cancel();
+        }
+    }
+
+    /// Returns the number of merge operations that have started.
+    pub fn num_running_merges(&self) -> usize {
+        self.list()
+            .into_iter()
+            .filter(|merge_op| merge_op.progress.is_running())
+            .count()
+    }
+}
+
+/// Describes a running merge, as returned by
+/// [`IndexWriter::running_merges`](struct.IndexWriter.html#method.running_merges).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeOperationInfo {
+    segment_ids: Vec<SegmentId>,
+    target_opstamp: Opstamp,
+    bytes_written: u64,
+}
+
+impl MergeOperationInfo {
+    /// Returns the ids of the segments being merged.
+    pub fn segment_ids(&self) -> &[SegmentId] {
+        &self.segment_ids
+    }
+
+    /// Returns the opstamp up to which the deletes are applied to the merged segment.
+    pub fn target_opstamp(&self) -> Opstamp {
+        self.target_opstamp
+    }
+
+    /// Returns the number of bytes written by the merge so far.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+}
+
+/// Tracks the bytes written by a merge, and throttles its writes.
+///
+/// It is also the cancellation token of the merge, checked periodically by the
+/// `IndexMerger`.
+#[derive(Default)]
+pub(crate) struct MergeProgress {
+    is_running: AtomicBool,
+    is_cancelled: AtomicBool,
+    bytes_written: AtomicU64,
+    // The maximum number of bytes written per second, 0 if the merge is not throttled.
+    max_bytes_per_sec: AtomicU64,
+    // The instant before which the next write has to wait.
+    next_write: Mutex<Option<Instant>>,
+}
+
+impl MergeProgress {
+    /// Marks the merge as running, with the given throttle.
+    pub fn start(&self, max_bytes_per_sec: Option<u64>) {
+        self.max_bytes_per_sec
+            .store(max_bytes_per_sec.unwrap_or(0), Ordering::Relaxed);
+        self.is_running.store(true, Ordering::Release);
+    }
+
+    /// Marks the merge as done, so that it does not count as running anymore.
+    pub fn finish(&self) {
+        self.is_running.store(false, Ordering::Release);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.is_running.load(Ordering::Acquire)
+    }
+
+    /// Requests the merge to stop.
+    pub fn cancel(&self) {
+        self.is_cancelled.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.is_cancelled.load(Ordering::Acquire)
+    }
+
+    /// Returns `TantivyError::MergeCancelled` if the merge was cancelled.
+    pub fn check_cancelled(&self) -> crate::Result<()> {
+        if self.is_cancelled() {
+            Err(TantivyError::MergeCancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(Ordering::Relaxed)
+    }
+
+    /// Records that `num_bytes` were written, pausing the calling thread
+    /// as long as required by the throttle.
+    fn record_write(&self, num_bytes: usize) {
+        self.bytes_written
+            .fetch_add(num_bytes as u64, Ordering::Relaxed);
+        let max_bytes_per_sec = self.max_bytes_per_sec.load(Ordering::Relaxed);
+        // A cancelled merge is not throttled anymore, to end as soon as possible.
+        if max_bytes_per_sec == 0 || self.is_cancelled() {
+            return;
+        }
+        let pause = {
+            let mut next_write = self.next_write.lock().unwrap();
+            let now = Instant::now();
+            let start = next_write.filter(|&instant| instant > now).unwrap_or(now);
+            let write_duration =
+                Duration::from_secs_f64(num_bytes as f64 / max_bytes_per_sec as f64);
+            let next = start + write_duration;
+            *next_write = Some(next);
+            next - now
+        };
+        if pause >= MIN_PAUSE {
+            thread::sleep(pause);
+        }
+    }
+}
+
+/// Wraps the writes of a merge, to track and throttle them.
+pub(crate) struct MergeWrite<W: TerminatingWrite> {
+    underlying: W,
+    progress: Arc<MergeProgress>,
+}
+
+impl<W: TerminatingWrite> MergeWrite<W> {
+    pub fn wrap(underlying: W, progress: Arc<MergeProgress>) -> MergeWrite<W> {
+        MergeWrite {
+            underlying,
+            progress,
+        }
+    }
+}
+
+impl<W: TerminatingWrite> Write for MergeWrite<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let num_bytes = self.underlying.write(buf)?;
+        self.progress.record_write(num_bytes);
+        Ok(num_bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.underlying.flush()
+    }
+}
+
+impl<W: TerminatingWrite> TerminatingWrite for MergeWrite<W> {
+    fn terminate_ref(&mut self, token: AntiCallToken) -> io::Result<()> {
+        self.underlying.terminate_ref(token)
+    }
 }
 
 /// A `MergeOperation` has two roles.
@@ -47,6 +225,7 @@ pub struct MergeOperation {
 pub(crate) struct InnerMergeOperation {
     target_opstamp: Opstamp,
     segment_ids: Vec<SegmentId>,
+    progress: Arc<MergeProgress>,
 }
 
 impl MergeOperation {
@@ -58,6 +237,7 @@ impl MergeOperation {
         let inner_merge_operation = InnerMergeOperation {
             target_opstamp,
             segment_ids,
+            progress: Arc::default(),
         };
         MergeOperation {
             inner: inventory.track(inner_merge_operation),
@@ -71,4 +251,48 @@ impl MergeOperation {
     pub fn segment_ids(&self) -> &[SegmentId] {
         &self.inner.segment_ids[..]
     }
+
+    pub(crate) fn progress(&self) -> &Arc<MergeProgress> {
+        &self.inner.progress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MergeProgress, MergeWrite};
+    use common::TerminatingWrite;
+    use std::io::Write;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_merge_write_progress() -> std::io::Result<()> {
+        let progress = Arc::new(MergeProgress::default());
+        progress.start(None);
+        assert!(progress.is_running());
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut merge_write = MergeWrite::wrap(&mut buffer, progress.clone());
+        merge_write.write_all(b"hello")?;
+        merge_write.terminate()?;
+        assert_eq!(progress.bytes_written(), 5);
+        assert_eq!(&buffer[..], b"hello");
+        progress.finish();
+        assert!(!progress.is_running());
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_write_throttle() -> std::io::Result<()> {
+        let progress = Arc::new(MergeProgress::default());
+        progress.start(Some(1_000));
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut merge_write = MergeWrite::wrap(&mut buffer, progress.clone());
+        let start = Instant::now();
+        for _ in 0..3 {
+            merge_write.write_all(&[0u8; 100])?;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(250));
+        assert_eq!(progress.bytes_written(), 300);
+        Ok(())
+    }
 }
@@ -1,3 +1,4 @@
+use crate::directory::Directory;
 use crate::error::DataCorruption;
 use crate::fastfield::CompositeFastFieldSerializer;
 use crate::fastfield::DeleteBitSet;
@@ -7,28 +8,33 @@ use crate::fastfield::FastFieldReader;
 use crate::fastfield::FastFieldStats;
 use crate::fastfield::MultiValueLength;
 use crate::fastfield::MultiValuedFastFieldReader;
+use crate::fastfield::OptionalFastFieldReader;
 use crate::fieldnorm::FieldNormsSerializer;
 use crate::fieldnorm::FieldNormsWriter;
 use crate::fieldnorm::{FieldNormReader, FieldNormReaders};
-use crate::indexer::doc_id_mapping::SegmentDocidMapping;
+use crate::indexer::doc_id_mapping::{MergeDocMapping, SegmentDocidMapping};
+use crate::indexer::merge_operation::MergeProgress;
 use crate::indexer::SegmentSerializer;
 use crate::postings::Postings;
 use crate::postings::{InvertedIndexSerializer, SegmentPostings};
 use crate::schema::Cardinality;
+use crate::schema::FastFieldCodecType;
 use crate::schema::FieldType;
 use crate::schema::{Field, Schema};
 use crate::store::StoreWriter;
 use crate::termdict::TermMerger;
 use crate::termdict::TermOrdinal;
+use crate::termvector::{fields_with_term_vectors, TermVectorReader, TermVectorsSerializer};
 use crate::IndexSettings;
 use crate::IndexSortByField;
+use crate::TantivyError;
 use crate::{core::Segment, indexer::doc_id_mapping::expect_field_id_for_sort_field};
 use crate::{core::SegmentReader, Order};
 use crate::{
     docset::{DocSet, TERMINATED},
     SegmentOrdinal,
 };
-use crate::{DocId, InvertedIndexReader, SegmentComponent};
+use crate::{DocId, InvertedIndexReader, SegmentComponent, SegmentId};
 use common::HasLen;
 use itertools::Itertools;
 use measure_time::debug_time;
@@ -42,6 +48,10 @@ use tantivy_bitpacker::minmax;
 /// We do not allow segments with more than
 pub const MAX_DOC_LIMIT: u32 = 1 << 31;
 
+/// Number of documents of the doc store written between two checks of the
+/// cancellation of the merge.
+const CANCELLATION_CHECK_NUM_DOCS: usize = 1_000;
+
 fn compute_total_num_tokens(readers: &[SegmentReader], field: Field) -> crate::Result<u64> {
     let mut total_tokens = 0u64;
     let mut count: [usize; 256] = [0; 256];
@@ -94,6 +104,7 @@ pub struct IndexMerger {
     schema: Schema,
     readers: Vec<SegmentReader>,
     max_doc: u32,
+    merge_progress: Option<Arc<MergeProgress>>,
 }
 
 fn compute_min_max_val(
@@ -176,6 +187,27 @@ impl DeltaComputer {
         }
         &self.buffer[..positions.len()]
     }
+
+    // Computes the position deltas, each followed by the payload of the position.
+    fn compute_delta_with_payloads(&mut self, positions: &[u32], payloads: &[u32]) -> &[u32] {
+        debug_assert_eq!(positions.len(), payloads.len());
+        let num_vals = positions.len() * 2;
+        if num_vals > self.buffer.len() {
+            self.buffer.resize(num_vals, 0u32);
+        }
+        let mut last_pos = 0u32;
+        for ((cur_pos, payload), dest) in positions
+            .iter()
+            .cloned()
+            .zip(payloads.iter().cloned())
+            .zip(self.buffer.chunks_mut(2))
+        {
+            dest[0] = cur_pos - last_pos;
+            dest[1] = payload;
+            last_pos = cur_pos;
+        }
+        &self.buffer[..num_vals]
+    }
 }
 
 impl IndexMerger {
@@ -191,6 +223,10 @@ impl IndexMerger {
                 let reader = SegmentReader::open(segment)?;
                 max_doc += reader.num_docs();
                 readers.push(reader);
+                let directory = segment.index().directory();
+                for path in segment.meta().list_files() {
+                    directory.advise_merge_read(&path);
+                }
             }
         }
         if let Some(sort_by_field) = index_settings.sort_by_field.as_ref() {
@@ -210,9 +246,29 @@ impl IndexMerger {
             schema,
             readers,
             max_doc,
+            merge_progress: None,
         })
     }
 
+    /// Makes the merge cancellable through its `MergeProgress`.
+    pub(crate) fn with_merge_progress(self, merge_progress: Arc<MergeProgress>) -> IndexMerger {
+        IndexMerger {
+            merge_progress: Some(merge_progress),
+            ..self
+        }
+    }
+
+    /// Returns `TantivyError::MergeCancelled` if the merge was cancelled.
+    ///
+    /// It is only called where the merge can be interrupted, leaving the
+    /// `SegmentSerializer` in a state where it can still be closed.
+    fn check_cancelled(&self) -> crate::Result<()> {
+        if let Some(merge_progress) = self.merge_progress.as_ref() {
+            merge_progress.check_cancelled()?;
+        }
+        Ok(())
+    }
+
     fn sort_readers_by_min_sort_field(
         readers: Vec<SegmentReader>,
         sort_by_field: &IndexSortByField,
@@ -264,6 +320,26 @@ impl IndexMerger {
         Ok(())
     }
 
+    fn write_term_vectors(
+        &self,
+        mut term_vectors_serializer: TermVectorsSerializer,
+        doc_id_mapping: &SegmentDocidMapping,
+    ) -> crate::Result<()> {
+        for field in fields_with_term_vectors(&self.schema) {
+            let term_vector_readers: Vec<TermVectorReader> = self
+                .readers
+                .iter()
+                .map(|reader| reader.term_vectors(field))
+                .collect::<Result<_, _>>()?;
+            let term_vectors = doc_id_mapping.iter().map(|(doc_id, reader_with_ordinal)| {
+                term_vector_readers[reader_with_ordinal.ordinal as usize].term_vector_bytes(*doc_id)
+            });
+            term_vectors_serializer.serialize_field(field, term_vectors)?;
+        }
+        term_vectors_serializer.close()?;
+        Ok(())
+    }
+
     fn write_fast_fields(
         &self,
         fast_field_serializer: &mut CompositeFastFieldSerializer,
@@ -273,6 +349,7 @@ impl IndexMerger {
         debug_time!("write_fast_fields");
 
         for (field, field_entry) in self.schema.fields() {
+            self.check_cancelled()?;
             let field_type = field_entry.field_type();
             match field_type {
                 FieldType::HierarchicalFacet(_) => {
@@ -290,16 +367,25 @@ impl IndexMerger {
                 FieldType::U64(ref options)
                 | FieldType::I64(ref options)
                 | FieldType::F64(ref options)
-                | FieldType::Date(ref options) => match options.get_fastfield_cardinality() {
-                    Some(Cardinality::SingleValue) => {
-                        self.write_single_fast_field(field, fast_field_serializer, doc_id_mapping)?;
-                    }
-                    Some(Cardinality::MultiValues) => {
-                        self.write_multi_fast_field(field, fast_field_serializer, doc_id_mapping)?;
-                    }
-                    None => {}
-                },
-                FieldType::Str(_) => {
+                | FieldType::Bool(ref options) => {
+                    self.write_int_fast_field(
+                        field,
+                        options.get_fastfield_cardinality(),
+                        options.get_fast_codec(),
+                        fast_field_serializer,
+                        doc_id_mapping,
+                    )?;
+                }
+                FieldType::Date(ref options) => {
+                    self.write_int_fast_field(
+                        field,
+                        options.get_fastfield_cardinality(),
+                        options.get_fast_codec(),
+                        fast_field_serializer,
+                        doc_id_mapping,
+                    )?;
+                }
+                FieldType::Str(_) | FieldType::JsonObject(_) => {
                     // We don't handle str fast field for the moment
                     // They can be implemented using what is done
                     // for facets in the future.
@@ -309,7 +395,37 @@ impl IndexMerger {
                         self.write_bytes_fast_field(field, fast_field_serializer, doc_id_mapping)?;
                     }
                 }
+                FieldType::IpAddr(ref options) => {
+                    if options.is_fast() {
+                        self.write_u128_fast_field(field, fast_field_serializer, doc_id_mapping)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_int_fast_field(
+        &self,
+        field: Field,
+        cardinality: Option<Cardinality>,
+        codec_type: Option<FastFieldCodecType>,
+        fast_field_serializer: &mut CompositeFastFieldSerializer,
+        doc_id_mapping: &SegmentDocidMapping,
+    ) -> crate::Result<()> {
+        match cardinality {
+            Some(Cardinality::SingleValue) => {
+                self.write_single_fast_field(
+                    field,
+                    codec_type,
+                    fast_field_serializer,
+                    doc_id_mapping,
+                )?;
+            }
+            Some(Cardinality::MultiValues) => {
+                self.write_multi_fast_field(field, fast_field_serializer, doc_id_mapping)?;
             }
+            None => {}
         }
         Ok(())
     }
@@ -318,12 +434,14 @@ impl IndexMerger {
     fn write_single_fast_field(
         &self,
         field: Field,
+        codec_type: Option<FastFieldCodecType>,
         fast_field_serializer: &mut CompositeFastFieldSerializer,
         doc_id_mapping: &SegmentDocidMapping,
     ) -> crate::Result<()> {
         let (min_value, max_value) = self.readers.iter().map(|reader|{
                 let u64_reader: DynamicFastFieldReader<u64> = reader
                 .fast_fields()
+.stored_values()
                 .typed_fast_field_reader(field)
                 .expect("Failed to find a reader for single fast field. This is a tantivy bug and it should never happen.");
                 compute_min_max_val(&u64_reader, reader.max_doc(), reader.delete_bitset())
@@ -337,9 +455,10 @@ impl IndexMerger {
             .readers
             .iter()
             .map(|reader| {
-               let u64_reader: DynamicFastFieldReader<u64> = reader
+               let u64_reader: OptionalFastFieldReader<u64> = reader
                     .fast_fields()
-                    .typed_fast_field_reader(field)
+.stored_values()
+                    .typed_optional_fast_field_reader(field)
                     .expect("Failed to find a reader for single fast field. This is a tantivy bug and it should never happen.");
                 u64_reader
             })
@@ -353,7 +472,7 @@ impl IndexMerger {
         #[derive(Clone)]
         struct SortedDocidFieldAccessProvider<'a> {
             doc_id_mapping: &'a SegmentDocidMapping<'a>,
-            fast_field_readers: &'a Vec<DynamicFastFieldReader<u64>>,
+            fast_field_readers: &'a Vec<OptionalFastFieldReader<u64>>,
         }
         impl<'a> FastFieldDataAccess for SortedDocidFieldAccessProvider<'a> {
             fn get_val(&self, doc: u64) -> u64 {
@@ -373,14 +492,26 @@ impl IndexMerger {
             let fast_field_reader = &fast_field_readers[reader_with_ordinal.ordinal as usize];
             fast_field_reader.get(*doc_id)
         });
-        fast_field_serializer.create_auto_detect_u64_fast_field(
+        fast_field_serializer.create_u64_fast_field_with_codec_and_idx(
             field,
+            codec_type,
             stats,
             fastfield_accessor,
             iter1,
             iter2,
+            0,
         )?;
 
+        if fast_field_readers
+            .iter()
+            .any(|fast_field_reader| fast_field_reader.has_missing_vals())
+        {
+            let has_vals = doc_id_mapping.iter().map(|(doc_id, reader_with_ordinal)| {
+                fast_field_readers[reader_with_ordinal.ordinal as usize].exists(*doc_id)
+            });
+            fast_field_serializer.create_null_index_with_idx(field, has_vals, 1)?;
+        }
+
         Ok(())
     }
 
@@ -674,6 +805,7 @@ impl IndexMerger {
         for reader in &self.readers {
             let ff_reader: MultiValuedFastFieldReader<u64> = reader
                 .fast_fields()
+                .stored_values()
                 .typed_fast_field_multi_reader(field)
                 .expect(
                     "Failed to find multivalued fast field reader. This is a bug in \
@@ -791,18 +923,65 @@ impl IndexMerger {
             doc_id_mapping,
             &reader_and_field_accessors,
         )?;
-        let mut serialize_vals = fast_field_serializer.new_bytes_fast_field_with_idx(field, 1);
 
-        for (doc_id, reader_with_ordinal) in doc_id_mapping.iter() {
-            let bytes_reader = &reader_and_field_accessors[reader_with_ordinal.ordinal as usize].1;
-            let val = bytes_reader.get_bytes(*doc_id);
-            serialize_vals.write_all(val)?;
+        let bytes_readers = || {
+            doc_id_mapping.iter().map(|(doc_id, reader_with_ordinal)| {
+                let bytes_reader =
+                    &reader_and_field_accessors[reader_with_ordinal.ordinal as usize].1;
+                (*doc_id, bytes_reader)
+            })
+        };
+
+        let total_num_bytes: u64 = bytes_readers()
+            .map(|(doc_id, bytes_reader)| bytes_reader.num_bytes(doc_id) as u64)
+            .sum();
+        let mut serialize_val_offsets =
+            fast_field_serializer.new_u64_fast_field_with_idx(field, 0, total_num_bytes, 2)?;
+        let mut offset = 0;
+        for (doc_id, bytes_reader) in bytes_readers() {
+            for val in bytes_reader.get_vals(doc_id) {
+                serialize_val_offsets.add_val(offset)?;
+                offset += val.len() as u64;
+            }
+        }
+        serialize_val_offsets.add_val(total_num_bytes)?;
+        serialize_val_offsets.close_field()?;
+
+        let mut serialize_vals = fast_field_serializer.new_bytes_fast_field_with_idx(field, 1);
+        for (doc_id, bytes_reader) in bytes_readers() {
+            for val in bytes_reader.get_vals(doc_id) {
+                serialize_vals.write_all(val)?;
+            }
         }
 
         serialize_vals.flush()?;
         Ok(())
     }
 
+    fn write_u128_fast_field(
+        &self,
+        field: Field,
+        fast_field_serializer: &mut CompositeFastFieldSerializer,
+        doc_id_mapping: &SegmentDocidMapping,
+    ) -> crate::Result<()> {
+        let u128_readers = self
+            .readers
+            .iter()
+            .map(|reader| {
+                reader.fast_fields().ip_addr(field)
+                    .expect("Failed to find a reader for u128 fast field. This is a bug in tantivy, please report.")
+            })
+            .collect::<Vec<_>>();
+        let vals: Vec<u128> = doc_id_mapping
+            .iter()
+            .map(|(doc_id, reader_with_ordinal)| {
+                u128_readers[reader_with_ordinal.ordinal as usize].get(*doc_id)
+            })
+            .collect();
+        fast_field_serializer.create_u128_fast_field_with_idx(field, vals.into_iter(), 0)?;
+        Ok(())
+    }
+
     fn write_postings_for_field(
         &self,
         indexed_field: Field,
@@ -813,6 +992,7 @@ impl IndexMerger {
     ) -> crate::Result<Option<TermOrdinalMapping>> {
         debug_time!("write_postings_for_field");
         let mut positions_buffer: Vec<u32> = Vec::with_capacity(1_000);
+        let mut payloads_buffer: Vec<u32> = Vec::new();
         let mut delta_computer = DeltaComputer::new();
 
         let mut max_term_ords: Vec<TermOrdinal> = Vec::new();
@@ -882,6 +1062,7 @@ impl IndexMerger {
         );
 
         let mut segment_postings_containing_the_term: Vec<(usize, SegmentPostings)> = vec![];
+        let has_payloads = segment_postings_option.has_payloads();
         let mut doc_id_and_positions = vec![];
 
         while merged_terms.advance() {
@@ -940,7 +1121,14 @@ impl IndexMerger {
                         // we make sure to only write the term if
                         // there is at least one document.
                         let term_freq = segment_postings.term_freq();
-                        segment_postings.positions(&mut positions_buffer);
+                        if has_payloads {
+                            segment_postings.positions_and_payloads(
+                                &mut positions_buffer,
+                                &mut payloads_buffer,
+                            );
+                        } else {
+                            segment_postings.positions(&mut positions_buffer);
+                        }
                         // if doc_id_mapping exists, the docids are reordered, they are
                         // not just stacked. The field serializer expects monotonically increasing
                         // docids, so we collect and sort them first, before writing.
@@ -953,9 +1141,17 @@ impl IndexMerger {
                                 remapped_doc_id,
                                 term_freq,
                                 positions_buffer.to_vec(),
+                                payloads_buffer.to_vec(),
                             ));
                         } else {
-                            let delta_positions = delta_computer.compute_delta(&positions_buffer);
+                            let delta_positions = if has_payloads {
+                                delta_computer.compute_delta_with_payloads(
+                                    &positions_buffer,
+                                    &payloads_buffer,
+                                )
+                            } else {
+                                delta_computer.compute_delta(&positions_buffer)
+                            };
                             field_serializer.write_doc(remapped_doc_id, term_freq, delta_positions);
                         }
                     }
@@ -964,10 +1160,14 @@ impl IndexMerger {
                 }
             }
             if !doc_id_mapping.is_trivial() {
-                doc_id_and_positions.sort_unstable_by_key(|&(doc_id, _, _)| doc_id);
-
-                for (doc_id, term_freq, positions) in &doc_id_and_positions {
-                    let delta_positions = delta_computer.compute_delta(positions);
+                doc_id_and_positions.sort_unstable_by_key(|&(doc_id, _, _, _)| doc_id);
+
+                for (doc_id, term_freq, positions, payloads) in &doc_id_and_positions {
+                    let delta_positions = if has_payloads {
+                        delta_computer.compute_delta_with_payloads(positions, payloads)
+                    } else {
+                        delta_computer.compute_delta(positions)
+                    };
                     field_serializer.write_doc(*doc_id, *term_freq, delta_positions);
                 }
                 doc_id_and_positions.clear();
@@ -987,6 +1187,7 @@ impl IndexMerger {
     ) -> crate::Result<HashMap<Field, TermOrdinalMapping>> {
         let mut term_ordinal_mappings = HashMap::new();
         for (field, field_entry) in self.schema.fields() {
+            self.check_cancelled()?;
             let fieldnorm_reader = fieldnorm_readers.get_field(field)?;
             if field_entry.is_indexed() {
                 if let Some(term_ordinal_mapping) = self.write_postings_for_field(
@@ -1021,7 +1222,10 @@ impl IndexMerger {
             .map(|(i, store)| store.iter_raw(self.readers[i].delete_bitset()))
             .collect();
         if !doc_id_mapping.is_trivial() {
-            for (old_doc_id, reader_with_ordinal) in doc_id_mapping.iter() {
+            for (doc_ord, (old_doc_id, reader_with_ordinal)) in doc_id_mapping.iter().enumerate() {
+                if doc_ord % CANCELLATION_CHECK_NUM_DOCS == 0 {
+                    self.check_cancelled()?;
+                }
                 let doc_bytes_it = &mut document_iterators[reader_with_ordinal.ordinal as usize];
                 if let Some(doc_bytes_res) = doc_bytes_it.next() {
                     let doc_bytes = doc_bytes_res?;
@@ -1036,6 +1240,7 @@ impl IndexMerger {
             }
         } else {
             for reader in &self.readers {
+                self.check_cancelled()?;
                 let store_reader = reader.get_store_reader()?;
                 if reader.num_deleted_docs() > 0
                     // If there is not enough data in the store, we avoid stacking in order to
@@ -1053,8 +1258,18 @@ impl IndexMerger {
                     // take 7 in order to not walk over all checkpoints.
                     || store_reader.block_checkpoints().take(7).count() < 6
                     || store_reader.compressor() != store_writer.compressor()
+                    // The blocks compressed with a dictionary are decompressed and compressed
+                    // again with the dictionary of the merged segment, trained on its first
+                    // documents.
+                    || store_reader.has_dictionary()
+                    || store_writer.has_dictionary()
                 {
-                    for doc_bytes_res in store_reader.iter_raw(reader.delete_bitset()) {
+                    for (doc_ord, doc_bytes_res) in
+                        store_reader.iter_raw(reader.delete_bitset()).enumerate()
+                    {
+                        if doc_ord % CANCELLATION_CHECK_NUM_DOCS == 0 {
+                            self.check_cancelled()?;
+                        }
                         let doc_bytes = doc_bytes_res?;
                         store_writer.store_bytes(&doc_bytes)?;
                     }
@@ -1069,9 +1284,53 @@ impl IndexMerger {
     /// Writes the merged segment by pushing information
     /// to the `SegmentSerializer`.
     ///
+    /// If the merge is cancelled, the serializer is closed and
+    /// `TantivyError::MergeCancelled` is returned. The files of the segment
+    /// are then incomplete.
+    ///
     /// # Returns
     /// The number of documents in the resulting segment.
-    pub fn write(&self, mut serializer: SegmentSerializer) -> crate::Result<u32> {
+    pub fn write(&self, serializer: SegmentSerializer) -> crate::Result<u32> {
+        self.write_and_close(serializer)?;
+        Ok(self.max_doc)
+    }
+
+    /// Writes the merged segment like [`write`](#method.write), and returns the
+    /// mapping from the doc_ids of the merged segments to the new doc_ids.
+    pub(crate) fn write_with_doc_mapping(
+        &self,
+        serializer: SegmentSerializer,
+    ) -> crate::Result<(u32, MergeDocMapping)> {
+        let doc_id_mapping = self.write_and_close(serializer)?;
+        let segment_ids: Vec<SegmentId> =
+            self.readers.iter().map(SegmentReader::segment_id).collect();
+        let max_docs: Vec<DocId> = self.readers.iter().map(SegmentReader::max_doc).collect();
+        let merge_doc_mapping =
+            MergeDocMapping::from_segment_doc_id_mapping(&doc_id_mapping, segment_ids, &max_docs);
+        Ok((self.max_doc, merge_doc_mapping))
+    }
+
+    fn write_and_close(
+        &self,
+        mut serializer: SegmentSerializer,
+    ) -> crate::Result<SegmentDocidMapping> {
+        match self.write_segment(&mut serializer) {
+            Ok(doc_id_mapping) => {
+                serializer.close()?;
+                Ok(doc_id_mapping)
+            }
+            Err(TantivyError::MergeCancelled) => {
+                serializer.close()?;
+                Err(TantivyError::MergeCancelled)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn write_segment(
+        &self,
+        serializer: &mut SegmentSerializer,
+    ) -> crate::Result<SegmentDocidMapping> {
         let doc_id_mapping = if let Some(sort_by_field) = self.index_settings.sort_by_field.as_ref()
         {
             // If the documents are already sorted and stackable, we ignore the mapping and execute
@@ -1085,9 +1344,14 @@ impl IndexMerger {
             self.get_doc_id_from_concatenated_data()?
         };
 
+        self.check_cancelled()?;
         if let Some(fieldnorms_serializer) = serializer.extract_fieldnorms_serializer() {
             self.write_fieldnorms(fieldnorms_serializer, &doc_id_mapping)?;
         }
+        self.check_cancelled()?;
+        if let Some(term_vectors_serializer) = serializer.extract_term_vectors_serializer() {
+            self.write_term_vectors(term_vectors_serializer, &doc_id_mapping)?;
+        }
         let fieldnorm_data = serializer
             .segment()
             .open_read(SegmentComponent::FieldNorms)?;
@@ -1097,14 +1361,14 @@ impl IndexMerger {
             fieldnorm_readers,
             &doc_id_mapping,
         )?;
+        self.check_cancelled()?;
         self.write_fast_fields(
             serializer.get_fast_field_serializer(),
             term_ord_mappings,
             &doc_id_mapping,
         )?;
         self.write_storable_fields(serializer.get_store_writer(), &doc_id_mapping)?;
-        serializer.close()?;
-        Ok(self.max_doc)
+        Ok(doc_id_mapping)
     }
 }
 
@@ -1,10 +1,13 @@
+mod commit_policy;
 pub mod delete_queue;
 
 pub mod doc_id_mapping;
 mod doc_opstamp_mapping;
 pub mod index_writer;
+mod index_writer_events;
+mod json_term_writer;
 mod log_merge_policy;
-mod merge_operation;
+pub(crate) mod merge_operation;
 pub mod merge_policy;
 pub mod merger;
 mod merger_sorted_index_test;
@@ -17,10 +20,14 @@ pub mod segment_serializer;
 pub mod segment_updater;
 mod segment_writer;
 mod stamper;
+mod tiered_merge_policy;
 
+pub use self::commit_policy::CommitPolicy;
+pub use self::doc_id_mapping::MergeDocMapping;
 pub use self::index_writer::IndexWriter;
+pub use self::index_writer_events::IndexWriterEvents;
 pub use self::log_merge_policy::LogMergePolicy;
-pub use self::merge_operation::MergeOperation;
+pub use self::merge_operation::{MergeOperation, MergeOperationInfo};
 pub use self::merge_policy::{MergeCandidate, MergePolicy, NoMergePolicy};
 pub use self::prepared_commit::PreparedCommit;
 pub use self::segment_entry::SegmentEntry;
@@ -28,6 +35,7 @@ pub use self::segment_manager::SegmentManager;
 pub use self::segment_serializer::SegmentSerializer;
 pub use self::segment_updater::merge_segments;
 pub use self::segment_writer::SegmentWriter;
+pub use self::tiered_merge_policy::TieredMergePolicy;
 
 /// Alias for the default merge policy, which is the `LogMergePolicy`.
 pub type DefaultMergePolicy = LogMergePolicy;
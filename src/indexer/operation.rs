@@ -1,21 +1,22 @@
+use crate::query::Query;
 use crate::schema::Document;
 use crate::schema::Term;
 use crate::Opstamp;
 
+/// Set of documents targetted by a delete operation.
+#[derive(Debug)]
+pub enum DeleteTarget {
+    /// All of the documents containing the term.
+    Term(Term),
+    /// All of the documents matching the query.
+    Query(Box<dyn Query>),
+}
+
 /// Timestamped Delete operation.
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Debug)]
 pub struct DeleteOperation {
     pub opstamp: Opstamp,
-    pub term: Term,
-}
-
-impl Default for DeleteOperation {
-    fn default() -> Self {
-        DeleteOperation {
-            opstamp: 0u64,
-            term: Term::new(),
-        }
-    }
+    pub target: DeleteTarget,
 }
 
 /// Timestamped Add operation.
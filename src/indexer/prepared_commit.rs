@@ -7,6 +7,7 @@ pub struct PreparedCommit<'a> {
     index_writer: &'a mut IndexWriter,
     payload: Option<String>,
     opstamp: Opstamp,
+    external_id: Option<String>,
 }
 
 impl<'a> PreparedCommit<'a> {
@@ -15,28 +16,65 @@ impl<'a> PreparedCommit<'a> {
             index_writer,
             payload: None,
             opstamp,
+            external_id: None,
         }
     }
 
+    pub(crate) fn with_external_id(
+        index_writer: &'a mut IndexWriter,
+        opstamp: Opstamp,
+        external_id: String,
+    ) -> PreparedCommit<'a> {
+        PreparedCommit {
+            index_writer,
+            payload: None,
+            opstamp,
+            external_id: Some(external_id),
+        }
+    }
+
+    /// Returns the opstamp of the commit.
     pub fn opstamp(&self) -> Opstamp {
         self.opstamp
     }
 
+    /// Returns the id of the commit, if it was prepared with
+    /// `IndexWriter::prepare_commit_with_id`.
+    pub fn external_id(&self) -> Option<&str> {
+        self.external_id.as_deref()
+    }
+
     pub fn set_payload(&mut self, payload: &str) {
         self.payload = Some(payload.to_string())
     }
 
-    pub fn abort(self) -> crate::Result<Opstamp> {
+    pub fn abort(mut self) -> crate::Result<Opstamp> {
+        if let Some(external_id) = self.external_id.take() {
+            return self.index_writer.abort_prepared(&external_id);
+        }
         self.index_writer.rollback()
     }
 
-    pub fn commit(self) -> crate::Result<Opstamp> {
+    pub fn commit(mut self) -> crate::Result<Opstamp> {
+        if let Some(external_id) = self.external_id.take() {
+            let payload = self.payload.take();
+            return self
+                .index_writer
+                .finalize_prepared_with_payload(&external_id, payload);
+        }
         info!("committing {}", self.opstamp);
         let _ = block_on(
             self.index_writer
                 .segment_updater()
-                .schedule_commit(self.opstamp, self.payload),
+                .schedule_commit(self.opstamp, self.payload.take()),
         );
         Ok(self.opstamp)
     }
 }
+
+impl<'a> Drop for PreparedCommit<'a> {
+    fn drop(&mut self) {
+        // resumes the auto-commits.
+        self.index_writer.end_prepared_commit();
+    }
+}
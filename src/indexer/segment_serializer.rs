@@ -4,6 +4,7 @@ use crate::fastfield::CompositeFastFieldSerializer;
 use crate::fieldnorm::FieldNormsSerializer;
 use crate::postings::InvertedIndexSerializer;
 use crate::store::StoreWriter;
+use crate::termvector::TermVectorsSerializer;
 
 /// Segment serializer is in charge of laying out on disk
 /// the data accumulated and sorted by the `SegmentWriter`.
@@ -12,6 +13,7 @@ pub struct SegmentSerializer {
     pub(crate) store_writer: StoreWriter,
     fast_field_serializer: CompositeFastFieldSerializer,
     fieldnorms_serializer: Option<FieldNormsSerializer>,
+    term_vectors_serializer: Option<TermVectorsSerializer>,
     postings_serializer: InvertedIndexSerializer,
 }
 
@@ -38,13 +40,17 @@ impl SegmentSerializer {
         let fieldnorms_write = segment.open_write(SegmentComponent::FieldNorms)?;
         let fieldnorms_serializer = FieldNormsSerializer::from_write(fieldnorms_write)?;
 
+        let term_vectors_write = segment.open_write(SegmentComponent::TermVectors)?;
+        let term_vectors_serializer = TermVectorsSerializer::from_write(term_vectors_write)?;
+
         let postings_serializer = InvertedIndexSerializer::open(&mut segment)?;
-        let compressor = segment.index().settings().docstore_compression;
+        let store_writer = StoreWriter::for_settings(store_write, segment.index().settings());
         Ok(SegmentSerializer {
             segment,
-            store_writer: StoreWriter::new(store_write, compressor),
+            store_writer,
             fast_field_serializer,
             fieldnorms_serializer: Some(fieldnorms_serializer),
+            term_vectors_serializer: Some(term_vectors_serializer),
             postings_serializer,
         })
     }
@@ -79,6 +85,13 @@ impl SegmentSerializer {
         self.fieldnorms_serializer.take()
     }
 
+    /// Extract the term vectors serializer.
+    ///
+    /// Note the term vectors serializer can only be extracted once.
+    pub fn extract_term_vectors_serializer(&mut self) -> Option<TermVectorsSerializer> {
+        self.term_vectors_serializer.take()
+    }
+
     /// Accessor to the `StoreWriter`.
     pub fn get_store_writer(&mut self) -> &mut StoreWriter {
         &mut self.store_writer
@@ -89,6 +102,9 @@ impl SegmentSerializer {
         if let Some(fieldnorms_serializer) = self.extract_fieldnorms_serializer() {
             fieldnorms_serializer.close()?;
         }
+        if let Some(term_vectors_serializer) = self.extract_term_vectors_serializer() {
+            term_vectors_serializer.close()?;
+        }
         self.fast_field_serializer.close()?;
         self.postings_serializer.close()?;
         self.store_writer.close()?;
@@ -6,33 +6,41 @@ use crate::core::Segment;
 use crate::core::SegmentId;
 use crate::core::SegmentMeta;
 use crate::core::META_FILEPATH;
+use crate::core::{PreparedCommitMeta, PREPARED_COMMITS_FILEPATH};
+use crate::directory::error::DeleteError;
 use crate::directory::{Directory, DirectoryClone, GarbageCollectionResult};
 use crate::indexer::delete_queue::DeleteCursor;
 use crate::indexer::index_writer::advance_deletes;
-use crate::indexer::merge_operation::MergeOperationInventory;
+use crate::indexer::index_writer_events::{IndexWriterEventListeners, IndexWriterEvents};
+use crate::indexer::merge_operation::{MergeOperationInfo, MergeOperationInventory, MergeProgress};
 use crate::indexer::merger::IndexMerger;
 use crate::indexer::segment_manager::SegmentsStatus;
 use crate::indexer::stamper::Stamper;
+use crate::indexer::MergeDocMapping;
 use crate::indexer::SegmentEntry;
 use crate::indexer::SegmentSerializer;
 use crate::indexer::{DefaultMergePolicy, MergePolicy};
 use crate::indexer::{MergeCandidate, MergeOperation};
 use crate::schema::Schema;
 use crate::Opstamp;
+use crate::TantivyError;
 use futures::channel::oneshot;
 use futures::executor::{ThreadPool, ThreadPoolBuilder};
 use futures::future::Future;
 use futures::future::TryFutureExt;
 use std::borrow::BorrowMut;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::Write;
 use std::ops::Deref;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::RwLock;
+use std::thread;
+use std::time::{Duration, Instant};
 
 const NUM_MERGE_THREADS: usize = 4;
+const MERGE_POLLING_INTERVAL: Duration = Duration::from_millis(10);
 
 /// Save the index meta file.
 /// This operation is atomic :
@@ -69,16 +77,46 @@ pub fn save_new_metas(
 /// and flushed.
 ///
 /// This method is not part of tantivy's public API
-fn save_metas(metas: &IndexMeta, directory: &dyn Directory) -> crate::Result<()> {
+pub(crate) fn save_metas(metas: &IndexMeta, directory: &dyn Directory) -> crate::Result<()> {
     info!("save metas");
     let mut buffer = serde_json::to_vec_pretty(metas)?;
     // Just adding a new line at the end of the buffer.
     writeln!(&mut buffer)?;
-    directory.atomic_write(&META_FILEPATH, &buffer[..])?;
+    directory.atomic_write_with_durability(
+        &META_FILEPATH,
+        &buffer[..],
+        metas.index_settings.durability,
+    )?;
     debug!("Saved metas {:?}", serde_json::to_string_pretty(&metas));
     Ok(())
 }
 
+/// Save the prepared commits file, or deletes it if there are no prepared commits.
+///
+/// Like `save_metas`, this operation is atomic.
+fn save_prepared_commits(
+    prepared_commits: &[PreparedCommitMeta],
+    directory: &dyn Directory,
+) -> crate::Result<()> {
+    if prepared_commits.is_empty() {
+        return match directory.delete(&PREPARED_COMMITS_FILEPATH) {
+            Ok(()) | Err(DeleteError::FileDoesNotExist(_)) => Ok(()),
+            Err(DeleteError::IoError { io_error, .. }) => Err(io_error.into()),
+        };
+    }
+    let mut buffer = serde_json::to_vec_pretty(prepared_commits)?;
+    writeln!(&mut buffer)?;
+    directory.atomic_write(&PREPARED_COMMITS_FILEPATH, &buffer[..])?;
+    Ok(())
+}
+
+fn unknown_prepared_commit(external_id: &str) -> TantivyError {
+    TantivyError::InvalidArgument(format!(
+        "There is no prepared commit with the id {:?}.",
+        external_id
+    ))
+}
+
 // The segment update runner is in charge of processing all
 //  of the `SegmentUpdate`s.
 //
@@ -104,20 +142,139 @@ async fn garbage_collect_files(
 ) -> crate::Result<GarbageCollectionResult> {
     info!("Running garbage collection");
     let mut index = segment_updater.index.clone();
-    index
+    // The prepared commits are only updated by the segment updater, so they cannot change
+    // during the garbage collection.
+    let prepared_commits = index.list_prepared_commits()?;
+    let living_files_segment_updater = segment_updater.clone();
+    let garbage_collection_result = index
         .directory_mut()
-        .garbage_collect(move || segment_updater.list_files())
+        .garbage_collect(move || living_files_segment_updater.list_files(&prepared_commits))?;
+    segment_updater.notify_deleted_segments(&garbage_collection_result.deleted_files);
+    Ok(garbage_collection_result)
+}
+
+/// Deletes the files written for a segment, which is not part of the index.
+fn delete_segment_files(index: &Index, segment: &Segment) {
+    for path in segment.meta().list_files() {
+        match index.directory().delete(&path) {
+            Ok(()) | Err(DeleteError::FileDoesNotExist(_)) => {}
+            Err(err) => {
+                warn!("Failed to delete {:?}: {:?}", path, err);
+            }
+        }
+    }
+}
+
+/// Returns the size of the files of a segment, deletes excluded.
+fn segment_num_bytes(index: &Index, segment_meta: &SegmentMeta) -> u64 {
+    segment_meta
+        .num_bytes()
+        .unwrap_or_else(|| index.segment(segment_meta.clone()).num_bytes())
+}
+
+/// Records a segment as written by a merge, as long as it lives.
+struct MergeTargetGuard<'a> {
+    index: &'a Index,
+    segment_id: SegmentId,
+}
+
+impl<'a> MergeTargetGuard<'a> {
+    fn register(index: &'a Index, segment_id: SegmentId) -> MergeTargetGuard<'a> {
+        index.set_merge_target(segment_id, true);
+        MergeTargetGuard { index, segment_id }
+    }
+}
+
+impl<'a> Drop for MergeTargetGuard<'a> {
+    fn drop(&mut self) {
+        self.index.set_merge_target(self.segment_id, false);
+    }
+}
+
+/// The space left for the merges by `IndexSettings::max_index_size_bytes`.
+///
+/// As the merged segments are only deleted once the merge ends, a merge requires as many
+/// bytes as the segments it merges, on top of the size of the index.
+struct MergeSpace {
+    max_index_size_bytes: Option<u64>,
+    segments_num_bytes: HashMap<SegmentId, u64>,
+    reserved_num_bytes: u64,
+}
+
+impl MergeSpace {
+    fn new(segment_updater: &SegmentUpdater) -> MergeSpace {
+        let max_index_size_bytes = segment_updater.index.settings().max_index_size_bytes;
+        if max_index_size_bytes.is_none() {
+            return MergeSpace {
+                max_index_size_bytes,
+                segments_num_bytes: HashMap::new(),
+                reserved_num_bytes: 0,
+            };
+        }
+        let segments_num_bytes = segment_updater.segments_num_bytes();
+        let mut reserved_num_bytes: u64 = segments_num_bytes.values().sum();
+        // The running merges hold the bytes they wrote so far, and will write about as
+        // many bytes as their segments.
+        for merge_info in segment_updater.running_merges() {
+            let merged_num_bytes: u64 = merge_info
+                .segment_ids()
+                .iter()
+                .filter_map(|segment_id| segments_num_bytes.get(segment_id))
+                .sum();
+            reserved_num_bytes += merged_num_bytes.max(merge_info.bytes_written());
+        }
+        MergeSpace {
+            max_index_size_bytes,
+            segments_num_bytes,
+            reserved_num_bytes,
+        }
+    }
+
+    /// Reserves the space of the merge, and returns false if it does not fit within
+    /// the limit.
+    fn reserve(&mut self, merge_operation: &MergeOperation) -> bool {
+        let max_index_size_bytes = match self.max_index_size_bytes {
+            Some(max_index_size_bytes) => max_index_size_bytes,
+            None => return true,
+        };
+        let merged_num_bytes: u64 = merge_operation
+            .segment_ids()
+            .iter()
+            .filter_map(|segment_id| self.segments_num_bytes.get(segment_id))
+            .sum();
+        if self.reserved_num_bytes + merged_num_bytes > max_index_size_bytes {
+            info!(
+                "Skipping the merge of {:?}: it requires {} bytes, and only {} bytes are left \
+                 by the index size limit.",
+                merge_operation.segment_ids(),
+                merged_num_bytes,
+                max_index_size_bytes.saturating_sub(self.reserved_num_bytes)
+            );
+            return false;
+        }
+        self.reserved_num_bytes += merged_num_bytes;
+        true
+    }
 }
 
 /// Merges a list of segments the list of segment givens in the `segment_entries`.
 /// This function happens in the calling thread and is computationally expensive.
+///
+/// Returns the entry of the merged segment, and the mapping of the doc ids of the
+/// merged segments to its doc ids.
 fn merge(
     index: &Index,
     mut segment_entries: Vec<SegmentEntry>,
     target_opstamp: Opstamp,
-) -> crate::Result<SegmentEntry> {
+    merge_progress: Arc<MergeProgress>,
+) -> crate::Result<(SegmentEntry, MergeDocMapping)> {
+    // the merge may have been cancelled while it was waiting for a merge thread.
+    merge_progress.check_cancelled()?;
     // first we need to apply deletes to our segment.
-    let merged_segment = index.new_segment();
+    let merged_segment = index
+        .new_segment()
+        .with_merge_progress(merge_progress.clone());
+    let _merge_target_guard = MergeTargetGuard::register(index, merged_segment.id());
 
     // First we apply all of the delete to the merged segment, up to the target opstamp.
     for segment_entry in &mut segment_entries {
@@ -134,17 +291,29 @@ fn merge(
 
     // An IndexMerger is like a "view" of our merged segments.
     let merger: IndexMerger =
-        IndexMerger::open(index.schema(), index.settings().clone(), &segments[..])?;
+        IndexMerger::open(index.schema(), index.settings().clone(), &segments[..])?
+            .with_merge_progress(merge_progress);
 
     // ... we just serialize this index merger in our new segment to merge the segments.
     let segment_serializer = SegmentSerializer::for_segment(merged_segment.clone(), true)?;
 
-    let num_docs = merger.write(segment_serializer)?;
+    let (num_docs, merge_doc_mapping) = match merger.write_with_doc_mapping(segment_serializer) {
+        Ok(num_docs_and_doc_mapping) => num_docs_and_doc_mapping,
+        Err(TantivyError::MergeCancelled) => {
+            // the index stays in its state before the merge.
+            delete_segment_files(index, &merged_segment);
+            return Err(TantivyError::MergeCancelled);
+        }
+        Err(err) => return Err(err),
+    };
 
     let merged_segment_id = merged_segment.id();
 
     let segment_meta = index.new_segment_meta(merged_segment_id, num_docs);
-    Ok(SegmentEntry::new(segment_meta, delete_cursor, None))
+    let num_bytes = index.segment(segment_meta.clone()).num_bytes();
+    let segment_meta = segment_meta.with_num_bytes(num_bytes);
+    let segment_entry = SegmentEntry::new(segment_meta, delete_cursor, None);
+    Ok((segment_entry, merge_doc_mapping))
 }
 
 /// Advanced: Merges a list of segments from different indices in a new index.
@@ -211,6 +380,8 @@ pub fn merge_segments<Dir: Directory>(
     let num_docs = merger.write(segment_serializer)?;
 
     let segment_meta = merged_index.new_segment_meta(merged_segment_id, num_docs);
+    let num_bytes = merged_index.segment(segment_meta.clone()).num_bytes();
+    let segment_meta = segment_meta.with_num_bytes(num_bytes);
 
     let stats = format!(
         "Segments Merge: [{}]",
@@ -255,6 +426,10 @@ pub(crate) struct InnerSegmentUpdater {
     killed: AtomicBool,
     stamper: Stamper,
     merge_operations: MergeOperationInventory,
+    max_concurrent_merges: AtomicUsize,
+    // The maximum number of bytes written per second by a merge, 0 if unlimited.
+    merge_throttle: AtomicU64,
+    event_listeners: IndexWriterEventListeners,
 }
 
 impl SegmentUpdater {
@@ -294,6 +469,9 @@ impl SegmentUpdater {
             killed: AtomicBool::new(false),
             stamper,
             merge_operations: Default::default(),
+            max_concurrent_merges: AtomicUsize::new(NUM_MERGE_THREADS),
+            merge_throttle: AtomicU64::new(0),
+            event_listeners: IndexWriterEventListeners::default(),
         })))
     }
 
@@ -306,6 +484,94 @@ impl SegmentUpdater {
         *self.merge_policy.write().unwrap() = arc_merge_policy;
     }
 
+    pub fn max_concurrent_merges(&self) -> usize {
+        self.max_concurrent_merges.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_concurrent_merges(&self, max_concurrent_merges: usize) {
+        self.max_concurrent_merges
+            .store(max_concurrent_merges, Ordering::Relaxed);
+    }
+
+    pub fn merge_throttle(&self) -> Option<u64> {
+        Some(self.merge_throttle.load(Ordering::Relaxed))
+            .filter(|&max_bytes_per_sec| max_bytes_per_sec > 0)
+    }
+
+    pub fn set_merge_throttle(&self, max_bytes_per_sec: Option<u64>) {
+        self.merge_throttle
+            .store(max_bytes_per_sec.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    pub fn running_merges(&self) -> Vec<MergeOperationInfo> {
+        self.merge_operations.running_merges()
+    }
+
+    /// Returns the size of each segment, committed or not.
+    fn segments_num_bytes(&self) -> HashMap<SegmentId, u64> {
+        self.segment_manager
+            .segment_entries()
+            .iter()
+            .map(|segment_entry| {
+                let num_bytes = segment_num_bytes(&self.index, segment_entry.meta());
+                (segment_entry.segment_id(), num_bytes)
+            })
+            .collect()
+    }
+
+    /// Returns the size counted against `IndexSettings::max_index_size_bytes`: the size
+    /// of the segments, committed or not, and of the files written by the running merges.
+    pub(crate) fn index_num_bytes(&self) -> u64 {
+        let segments_num_bytes: u64 = self.segments_num_bytes().values().sum();
+        let merges_num_bytes: u64 = self
+            .running_merges()
+            .iter()
+            .map(MergeOperationInfo::bytes_written)
+            .sum();
+        segments_num_bytes + merges_num_bytes
+    }
+
+    pub fn add_event_listener(&self, listener: Arc<dyn IndexWriterEvents>) {
+        self.event_listeners.add(listener);
+    }
+
+    // Notifies the deletion of the segments which are no longer used by the index,
+    // out of the files deleted by the garbage collector.
+    //
+    // The segments still in use, whose obsolete delete files were deleted, are ignored.
+    fn notify_deleted_segments(&self, deleted_files: &[PathBuf]) {
+        let living_segment_ids: HashSet<SegmentId> = self
+            .index
+            .list_all_segment_metas()
+            .iter()
+            .map(SegmentMeta::id)
+            .collect();
+        let mut deleted_segments: BTreeMap<SegmentId, Vec<PathBuf>> = BTreeMap::new();
+        for deleted_file in deleted_files {
+            let segment_id_opt = deleted_file
+                .to_str()
+                .and_then(|filename| filename.split('.').next())
+                .and_then(|uuid_string| SegmentId::from_uuid_string(uuid_string).ok());
+            if let Some(segment_id) = segment_id_opt {
+                if !living_segment_ids.contains(&segment_id) {
+                    deleted_segments
+                        .entry(segment_id)
+                        .or_default()
+                        .push(deleted_file.clone());
+                }
+            }
+        }
+        for (segment_id, segment_files) in deleted_segments {
+            self.event_listeners
+                .notify(|listener| listener.on_segment_deleted(segment_id, &segment_files));
+        }
+    }
+
+    /// Cancels all of the merges, running or waiting for a merge thread.
+    pub fn abort_merges(&self) {
+        self.merge_operations.cancel_all();
+    }
+
     fn schedule_future<T: 'static + Send, F: Future<Output = crate::Result<T>> + 'static + Send>(
         &self,
         f: F,
@@ -333,7 +599,11 @@ impl SegmentUpdater {
     ) -> impl Future<Output = crate::Result<()>> {
         let segment_updater = self.clone();
         self.schedule_future(async move {
+            let segment_meta = segment_entry.meta().clone();
             segment_updater.segment_manager.add_segment(segment_entry);
+            segment_updater
+                .event_listeners
+                .notify(|listener| listener.on_flush(&segment_meta));
             segment_updater.consider_merge_options().await;
             Ok(())
         })
@@ -356,9 +626,13 @@ impl SegmentUpdater {
     ///
     /// The method returns copies of the segment entries,
     /// updated with the delete information.
+    /// The deletes are not applied to the quarantined segments, which cannot be opened.
     fn purge_deletes(&self, target_opstamp: Opstamp) -> crate::Result<Vec<SegmentEntry>> {
         let mut segment_entries = self.segment_manager.segment_entries();
         for segment_entry in &mut segment_entries {
+            if self.index.is_quarantined(segment_entry.segment_id()) {
+                continue;
+            }
             let segment = self.index.segment(segment_entry.meta().clone());
             advance_deletes(segment, segment_entry, target_opstamp)?;
         }
@@ -390,7 +664,7 @@ impl SegmentUpdater {
             // Segment 1 from disk 1, Segment 1 from disk 2, etc.
             commited_segment_metas.sort_by_key(|segment_meta| -(segment_meta.max_doc() as i32));
             let index_meta = IndexMeta {
-                index_settings: index.settings().clone(),
+                index_settings: index.settings_to_save(),
                 segments: commited_segment_metas,
                 schema: index.schema(),
                 opstamp,
@@ -412,19 +686,126 @@ impl SegmentUpdater {
 
     /// List the files that are useful to the index.
     ///
+    /// This includes the files of the prepared commits.
     /// This does not include lock files, or files that are obsolete
     /// but have not yet been deleted by the garbage collector.
-    fn list_files(&self) -> HashSet<PathBuf> {
+    fn list_files(&self, prepared_commits: &[PreparedCommitMeta]) -> HashSet<PathBuf> {
         let mut files: HashSet<PathBuf> = self
             .index
             .list_all_segment_metas()
             .into_iter()
             .flat_map(|segment_meta| segment_meta.list_files())
             .collect();
+        for prepared_commit in prepared_commits {
+            for segment_meta in &prepared_commit.index_meta.segments {
+                files.extend(segment_meta.list_files());
+            }
+        }
         files.insert(META_FILEPATH.to_path_buf());
         files
     }
 
+    /// Applies the deletes up to `opstamp`, and saves the resulting segments in
+    /// the prepared commits file, without committing them.
+    pub fn schedule_prepare_commit(
+        &self,
+        opstamp: Opstamp,
+        external_id: String,
+    ) -> impl Future<Output = crate::Result<()>> {
+        let segment_updater: SegmentUpdater = self.clone();
+        self.schedule_future(async move {
+            let mut prepared_commits = segment_updater.index.list_prepared_commits()?;
+            if prepared_commits
+                .iter()
+                .any(|prepared_commit| prepared_commit.external_id == external_id)
+            {
+                return Err(TantivyError::InvalidArgument(format!(
+                    "A commit was already prepared with the id {:?}.",
+                    external_id
+                )));
+            }
+            let mut segment_metas: Vec<SegmentMeta> = segment_updater
+                .purge_deletes(opstamp)?
+                .iter()
+                .map(|segment_entry| segment_entry.meta().clone())
+                .collect();
+            // The segments are sorted as in `save_metas`.
+            segment_metas.sort_by_key(|segment_meta| -(segment_meta.max_doc() as i32));
+            let index = &segment_updater.index;
+            prepared_commits.push(PreparedCommitMeta {
+                external_id,
+                base_opstamp: segment_updater.load_meta().opstamp,
+                index_meta: IndexMeta {
+                    index_settings: index.settings_to_save(),
+                    segments: segment_metas,
+                    schema: index.schema(),
+                    opstamp,
+                    payload: None,
+                },
+            });
+            save_prepared_commits(&prepared_commits, index.directory())
+        })
+    }
+
+    /// Saves the `meta.json` of the prepared commit, and removes it from the prepared
+    /// commits file.
+    ///
+    /// The `payload`, if any, replaces the payload of the prepared commit.
+    /// The segment manager is not updated: the `IndexWriter` is expected to reload
+    /// the index.
+    pub fn schedule_finalize_prepared(
+        &self,
+        external_id: String,
+        payload: Option<String>,
+    ) -> impl Future<Output = crate::Result<Opstamp>> {
+        let segment_updater: SegmentUpdater = self.clone();
+        self.schedule_future(async move {
+            let mut prepared_commits = segment_updater.index.list_prepared_commits()?;
+            let position = prepared_commits
+                .iter()
+                .position(|prepared_commit| prepared_commit.external_id == external_id)
+                .ok_or_else(|| unknown_prepared_commit(&external_id))?;
+            let committed_opstamp = segment_updater.load_meta().opstamp;
+            if prepared_commits[position].base_opstamp != committed_opstamp {
+                return Err(TantivyError::InvalidArgument(format!(
+                    "The prepared commit {:?} is obsolete: the index was committed at opstamp \
+                     {} since it was prepared.",
+                    external_id, committed_opstamp
+                )));
+            }
+            let mut index_meta = prepared_commits.remove(position).index_meta;
+            if payload.is_some() {
+                index_meta.payload = payload;
+            }
+            let directory = segment_updater.index.directory();
+            save_metas(&index_meta, directory)?;
+            segment_updater.store_meta(&index_meta);
+            save_prepared_commits(&prepared_commits, directory)?;
+            let opstamp = index_meta.opstamp;
+            segment_updater.event_listeners.notify(|listener| {
+                listener.on_commit(opstamp, &index_meta.segments);
+            });
+            Ok(opstamp)
+        })
+    }
+
+    /// Removes the prepared commit from the prepared commits file.
+    pub fn schedule_abort_prepared(
+        &self,
+        external_id: String,
+    ) -> impl Future<Output = crate::Result<()>> {
+        let segment_updater: SegmentUpdater = self.clone();
+        self.schedule_future(async move {
+            let mut prepared_commits = segment_updater.index.list_prepared_commits()?;
+            let position = prepared_commits
+                .iter()
+                .position(|prepared_commit| prepared_commit.external_id == external_id)
+                .ok_or_else(|| unknown_prepared_commit(&external_id))?;
+            prepared_commits.remove(position);
+            save_prepared_commits(&prepared_commits, segment_updater.index.directory())
+        })
+    }
+
     pub fn schedule_commit(
         &self,
         opstamp: Opstamp,
@@ -435,6 +816,12 @@ impl SegmentUpdater {
             let segment_entries = segment_updater.purge_deletes(opstamp)?;
             segment_updater.segment_manager.commit(segment_entries);
             segment_updater.save_metas(opstamp, payload)?;
+            if segment_updater.is_alive() {
+                let index_meta = segment_updater.load_meta();
+                segment_updater.event_listeners.notify(|listener| {
+                    listener.on_commit(index_meta.opstamp, &index_meta.segments)
+                });
+            }
             let _ = garbage_collect_files(segment_updater.clone()).await;
             segment_updater.consider_merge_options().await;
             Ok(())
@@ -480,12 +867,30 @@ impl SegmentUpdater {
             "Segment_ids cannot be empty."
         );
 
+        if let Some(segment_id) = merge_operation
+            .segment_ids()
+            .iter()
+            .find(|segment_id| self.index.is_quarantined(**segment_id))
+        {
+            return Err(TantivyError::InvalidArgument(format!(
+                "The segment {} is quarantined, as it could not be opened.",
+                segment_id.short_uuid_string()
+            )));
+        }
         let segment_updater = self.clone();
         let segment_entries: Vec<SegmentEntry> = self
             .segment_manager
             .start_merge(merge_operation.segment_ids())?;
 
         info!("Starting merge  - {:?}", merge_operation.segment_ids());
+        merge_operation.progress().start(self.merge_throttle());
+        let segment_metas: Vec<SegmentMeta> = segment_entries
+            .iter()
+            .map(|segment_entry| segment_entry.meta().clone())
+            .collect();
+        self.event_listeners.notify(|listener| {
+            listener.on_merge_start(&segment_metas, merge_operation.target_opstamp())
+        });
 
         let (merging_future_send, merging_future_recv) =
             oneshot::channel::<crate::Result<SegmentMeta>>();
@@ -499,13 +904,29 @@ impl SegmentUpdater {
                 &segment_updater.index,
                 segment_entries,
                 merge_operation.target_opstamp(),
+                merge_operation.progress().clone(),
             ) {
-                Ok(after_merge_segment_entry) => {
+                Ok((after_merge_segment_entry, merge_doc_mapping)) => {
                     let segment_meta = segment_updater
-                        .end_merge(merge_operation, after_merge_segment_entry)
+                        .end_merge(
+                            merge_operation,
+                            segment_metas,
+                            after_merge_segment_entry,
+                            merge_doc_mapping,
+                        )
                         .await;
                     let _send_result = merging_future_send.send(segment_meta);
                 }
+                Err(crate::TantivyError::MergeCancelled) => {
+                    info!(
+                        "Merge of {:?} was cancelled.",
+                        merge_operation.segment_ids().to_vec()
+                    );
+                    // The segments are released before the merge is reported as cancelled.
+                    drop(merge_operation);
+                    let _send_result =
+                        merging_future_send.send(Err(crate::TantivyError::MergeCancelled));
+                }
                 Err(e) => {
                     warn!(
                         "Merge of {:?} was cancelled: {:?}",
@@ -528,7 +949,9 @@ impl SegmentUpdater {
     }
 
     pub(crate) fn get_mergeable_segments(&self) -> (Vec<SegmentMeta>, Vec<SegmentMeta>) {
-        let merge_segment_ids: HashSet<SegmentId> = self.merge_operations.segment_in_merge();
+        // The quarantined segments are excluded, as if they were in merge.
+        let mut merge_segment_ids: HashSet<SegmentId> = self.merge_operations.segment_in_merge();
+        merge_segment_ids.extend(self.index.quarantined_segment_ids());
         self.segment_manager
             .get_mergeable_segments(&merge_segment_ids)
     }
@@ -558,7 +981,17 @@ impl SegmentUpdater {
             });
         merge_candidates.extend(committed_merge_candidates);
 
-        for merge_operation in merge_candidates {
+        // The merges exceeding the limit are dropped, and will be suggested again
+        // by the merge policy once a merge ends.
+        let num_available_merges = self
+            .max_concurrent_merges()
+            .saturating_sub(self.merge_operations.num_running_merges());
+        let mut merge_space = MergeSpace::new(self);
+        for merge_operation in merge_candidates
+            .into_iter()
+            .filter(|merge_operation| merge_space.reserve(merge_operation))
+            .take(num_available_merges)
+        {
             if let Err(err) = self.start_merge(merge_operation) {
                 warn!(
                     "Starting the merge failed for the following reason. This is not fatal. {}",
@@ -571,13 +1004,17 @@ impl SegmentUpdater {
     fn end_merge(
         &self,
         merge_operation: MergeOperation,
+        segment_metas: Vec<SegmentMeta>,
         mut after_merge_segment_entry: SegmentEntry,
+        merge_doc_mapping: MergeDocMapping,
     ) -> impl Future<Output = crate::Result<SegmentMeta>> {
         let segment_updater = self.clone();
         let after_merge_segment_meta = after_merge_segment_entry.meta().clone();
         let end_merge_future = self.schedule_future(async move {
             info!("End merge {:?}", after_merge_segment_entry.meta());
             {
+                let segment_metas = segment_metas;
+                let merge_doc_mapping = merge_doc_mapping;
                 let mut delete_cursor = after_merge_segment_entry.delete_cursor().clone();
                 if let Some(delete_operation) = delete_cursor.get() {
                     let committed_opstamp = segment_updater.load_meta().opstamp;
@@ -605,14 +1042,19 @@ impl SegmentUpdater {
                     }
                 }
                 let previous_metas = segment_updater.load_meta();
+                let merged_segment_meta = after_merge_segment_entry.meta().clone();
                 let segments_status = segment_updater
                     .segment_manager
                     .end_merge(merge_operation.segment_ids(), after_merge_segment_entry)?;
+                merge_operation.progress().finish();
 
                 if segments_status == SegmentsStatus::Committed {
                     segment_updater
                         .save_metas(previous_metas.opstamp, previous_metas.payload.clone())?;
                 }
+                segment_updater.event_listeners.notify(|listener| {
+                    listener.on_merge_end(&segment_metas, &merged_segment_meta, &merge_doc_mapping)
+                });
 
                 segment_updater.consider_merge_options().await;
             } // we drop all possible handle to a now useless `SegmentMeta`.
@@ -642,15 +1084,428 @@ impl SegmentUpdater {
         self.merge_operations.wait_until_empty();
         Ok(())
     }
+
+    /// Waits for the current merging threads for at most `timeout`, and then
+    /// cancels the remaining merges.
+    ///
+    /// Returns true iff the merges were cancelled.
+    pub fn wait_merging_thread_with_timeout(&mut self, timeout: Duration) -> bool {
+        let start = Instant::now();
+        while self.merge_operations.len() > 0 {
+            if start.elapsed() >= timeout {
+                // no merge is started once the segment updater is killed.
+                self.kill();
+                self.abort_merges();
+                self.merge_operations.wait_until_empty();
+                return true;
+            }
+            thread::sleep(MERGE_POLLING_INTERVAL);
+        }
+        false
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::merge_segments;
-    use crate::directory::RamDirectory;
+    use crate::collector::Count;
+    use crate::directory::error::{DeleteError, OpenReadError, OpenWriteError};
+    use crate::directory::{
+        AntiCallToken, Directory, Durability, FileHandle, RamDirectory, TerminatingWrite,
+        WatchCallback, WatchHandle, WritePtr,
+    };
     use crate::indexer::merge_policy::tests::MergeWheneverPossible;
+    use crate::indexer::NoMergePolicy;
+    use crate::query::{AllQuery, TermQuery};
     use crate::schema::*;
-    use crate::Index;
+    use crate::{Index, IndexSettings, IndexWriter, TantivyError, Term};
+    use futures::executor::block_on;
+    use std::collections::{HashMap, HashSet};
+    use std::io::{self, BufWriter, Write};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// A `RamDirectory` with slow writes when `is_slow` is set, recording the
+    /// paths of the files it writes.
+    #[derive(Clone, Debug, Default)]
+    struct SlowDirectory {
+        directory: RamDirectory,
+        is_slow: Arc<AtomicBool>,
+        written_paths: Arc<Mutex<Vec<PathBuf>>>,
+    }
+
+    impl SlowDirectory {
+        fn set_slow(&self, is_slow: bool) {
+            self.is_slow.store(is_slow, Ordering::SeqCst);
+        }
+
+        fn written_paths(&self) -> Vec<PathBuf> {
+            self.written_paths.lock().unwrap().clone()
+        }
+    }
+
+    struct SlowWrite {
+        underlying: WritePtr,
+        is_slow: Arc<AtomicBool>,
+    }
+
+    impl Write for SlowWrite {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.is_slow.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(50));
+            }
+            self.underlying.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.underlying.flush()
+        }
+    }
+
+    impl TerminatingWrite for SlowWrite {
+        fn terminate_ref(&mut self, token: AntiCallToken) -> io::Result<()> {
+            self.underlying.terminate_ref(token)
+        }
+    }
+
+    impl Directory for SlowDirectory {
+        fn get_file_handle(&self, path: &Path) -> Result<Box<dyn FileHandle>, OpenReadError> {
+            self.directory.get_file_handle(path)
+        }
+
+        fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+            self.directory.delete(path)
+        }
+
+        fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+            self.directory.exists(path)
+        }
+
+        fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+            self.written_paths.lock().unwrap().push(path.to_path_buf());
+            let slow_write = SlowWrite {
+                underlying: self.directory.open_write(path)?,
+                is_slow: self.is_slow.clone(),
+            };
+            Ok(BufWriter::new(Box::new(slow_write)))
+        }
+
+        fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+            self.directory.atomic_read(path)
+        }
+
+        fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+            self.directory.atomic_write(path, data)
+        }
+
+        fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+            self.directory.watch(watch_callback)
+        }
+    }
+
+    /// A `RamDirectory` which loses the writes which were not synced when it crashes. The
+    /// deletes are applied right away, as if they were always synced.
+    #[derive(Clone, Debug, Default)]
+    struct CrashDirectory {
+        directory: RamDirectory,
+        synced_files: Arc<Mutex<HashMap<PathBuf, Vec<u8>>>>,
+    }
+
+    impl CrashDirectory {
+        /// Returns the files which survived a crash.
+        fn crash(&self) -> io::Result<RamDirectory> {
+            let directory = RamDirectory::create();
+            for (path, data) in self.synced_files.lock().unwrap().iter() {
+                directory.atomic_write(path, data)?;
+            }
+            Ok(directory)
+        }
+    }
+
+    struct CrashWrite {
+        underlying: WritePtr,
+        path: PathBuf,
+        data: Vec<u8>,
+        sync: bool,
+        synced_files: Arc<Mutex<HashMap<PathBuf, Vec<u8>>>>,
+    }
+
+    impl Write for CrashWrite {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.data.extend_from_slice(buf);
+            self.underlying.write_all(buf)?;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.underlying.flush()
+        }
+    }
+
+    impl TerminatingWrite for CrashWrite {
+        fn terminate_ref(&mut self, token: AntiCallToken) -> io::Result<()> {
+            self.underlying.terminate_ref(token)?;
+            if self.sync {
+                self.synced_files
+                    .lock()
+                    .unwrap()
+                    .insert(self.path.clone(), self.data.clone());
+            }
+            Ok(())
+        }
+    }
+
+    impl Directory for CrashDirectory {
+        fn get_file_handle(&self, path: &Path) -> Result<Box<dyn FileHandle>, OpenReadError> {
+            self.directory.get_file_handle(path)
+        }
+
+        fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+            self.synced_files.lock().unwrap().remove(path);
+            self.directory.delete(path)
+        }
+
+        fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+            self.directory.exists(path)
+        }
+
+        fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+            self.open_write_with_durability(path, Durability::Full)
+        }
+
+        fn open_write_with_durability(
+            &self,
+            path: &Path,
+            durability: Durability,
+        ) -> Result<WritePtr, OpenWriteError> {
+            let crash_write = CrashWrite {
+                underlying: self.directory.open_write(path)?,
+                path: path.to_path_buf(),
+                data: Vec::new(),
+                sync: durability == Durability::Full,
+                synced_files: self.synced_files.clone(),
+            };
+            Ok(BufWriter::new(Box::new(crash_write)))
+        }
+
+        fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+            self.directory.atomic_read(path)
+        }
+
+        fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+            self.atomic_write_with_durability(path, data, Durability::Full)
+        }
+
+        fn atomic_write_with_durability(
+            &self,
+            path: &Path,
+            data: &[u8],
+            durability: Durability,
+        ) -> io::Result<()> {
+            self.directory.atomic_write(path, data)?;
+            if durability != Durability::None {
+                self.synced_files
+                    .lock()
+                    .unwrap()
+                    .insert(path.to_path_buf(), data.to_vec());
+            }
+            Ok(())
+        }
+
+        fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+            self.directory.watch(watch_callback)
+        }
+    }
+
+    /// Commits a document with the `Durability::Full`, then another one with `durability`,
+    /// and returns the number of documents of the index after a crash.
+    fn num_docs_after_crash(durability: Durability) -> crate::Result<usize> {
+        let directory = CrashDirectory::default();
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let mut index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        for (text, durability) in [("a", Durability::Full), ("b", durability)] {
+            index.settings_mut().durability = durability;
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.set_merge_policy(Box::new(NoMergePolicy));
+            index_writer.add_document(doc!(text_field => text));
+            index_writer.commit()?;
+            index_writer.wait_merging_threads()?;
+        }
+        assert_eq!(index.reader()?.searcher().search(&AllQuery, &Count)?, 2);
+
+        let crashed_index = Index::open(directory.crash()?)?;
+        assert!(crashed_index.validate_checksum()?.is_empty());
+        let searcher = crashed_index.reader()?.searcher();
+        for segment_reader in searcher.segment_readers() {
+            let store_reader = segment_reader.get_store_reader()?;
+            assert!(store_reader.get(0).is_ok());
+        }
+        Ok(searcher.search(&AllQuery, &Count)?)
+    }
+
+    #[test]
+    fn test_durability_full_survives_crash() -> crate::Result<()> {
+        assert_eq!(num_docs_after_crash(Durability::Full)?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_durability_none_loses_last_commit_on_crash() -> crate::Result<()> {
+        // The last commit is lost, but the previous one is intact.
+        assert_eq!(num_docs_after_crash(Durability::None)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_durability_none_merge_keeps_synced_commit() -> crate::Result<()> {
+        let directory = CrashDirectory::default();
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let mut index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        index_writer.wait_merging_threads()?;
+        let first_segment_files = index.searchable_segment_metas()?[0].list_files();
+
+        index.settings_mut().durability = Durability::None;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit()?;
+        // The merge deletes the segments of the synced commit from the `meta.json`.
+        let segment_ids = index.searchable_segment_ids()?;
+        block_on(index_writer.merge(&segment_ids))?;
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit()?;
+        index_writer.wait_merging_threads()?;
+        assert_eq!(index.reader()?.searcher().search(&AllQuery, &Count)?, 3);
+
+        let crashed_index = Index::open(directory.crash()?)?;
+        assert!(crashed_index.validate_checksum()?.is_empty());
+        let searcher = crashed_index.reader()?.searcher();
+        assert_eq!(searcher.search(&AllQuery, &Count)?, 1);
+        assert!(searcher
+            .segment_reader(0)
+            .get_store_reader()?
+            .get(0)
+            .is_ok());
+
+        // The files of the merged segments are deleted once a commit is synced.
+        index.settings_mut().durability = Durability::Full;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.commit()?;
+        index_writer.wait_merging_threads()?;
+        for path in &first_segment_files {
+            assert!(!directory.exists(path)?);
+        }
+        Ok(())
+    }
+
+    fn create_slow_index(directory: &SlowDirectory) -> crate::Result<(Index, IndexWriter, Field)> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for _ in 0..2 {
+            index_writer.add_document(doc!(text_field => "a b"));
+            index_writer.add_document(doc!(text_field => "b c"));
+            index_writer.commit()?;
+        }
+        Ok((index, index_writer, text_field))
+    }
+
+    /// Checks that the only segment files are the files of the searchable segments,
+    /// and that the index can be searched.
+    fn check_index_after_aborted_merge(
+        index: &Index,
+        directory: &SlowDirectory,
+        text_field: Field,
+    ) -> crate::Result<()> {
+        let segment_metas = index.searchable_segment_metas()?;
+        assert_eq!(segment_metas.len(), 2);
+        let segment_files: HashSet<PathBuf> = segment_metas
+            .iter()
+            .flat_map(|segment_meta| segment_meta.list_files())
+            .collect();
+        // The lock files are not segment files.
+        let is_segment_file = |path: &Path| !path.to_string_lossy().starts_with('.');
+        for path in directory.written_paths() {
+            if is_segment_file(&path) && !segment_files.contains(&path) {
+                assert!(!directory.exists(&path)?, "stray file {:?}", path);
+            }
+        }
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(text_field, "b"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&query, &Count)?, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_abort_merges() -> crate::Result<()> {
+        let directory = SlowDirectory::default();
+        let (index, mut index_writer, text_field) = create_slow_index(&directory)?;
+        let segment_ids = index.searchable_segment_ids()?;
+        directory.set_slow(true);
+        let merge_future = index_writer.merge(&segment_ids);
+        let merge_thread = thread::spawn(move || block_on(merge_future));
+        let start = Instant::now();
+        while index_writer.running_merges().is_empty() && start.elapsed().as_secs() < 10 {
+            thread::yield_now();
+        }
+        index_writer.abort_merges();
+        let merge_result = merge_thread.join().unwrap();
+        assert!(matches!(merge_result, Err(TantivyError::MergeCancelled)));
+        assert!(index_writer.running_merges().is_empty());
+        directory.set_slow(false);
+        check_index_after_aborted_merge(&index, &directory, text_field)?;
+
+        // the segments can still be merged.
+        block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        assert_eq!(index.searchable_segment_ids()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_merging_threads_with_timeout() -> crate::Result<()> {
+        let directory = SlowDirectory::default();
+        let (index, mut index_writer, text_field) = create_slow_index(&directory)?;
+        let segment_ids = index.searchable_segment_ids()?;
+        directory.set_slow(true);
+        let merge_future = index_writer.merge(&segment_ids);
+        let merge_thread = thread::spawn(move || block_on(merge_future));
+        let start = Instant::now();
+        while index_writer.running_merges().is_empty() && start.elapsed().as_secs() < 10 {
+            thread::yield_now();
+        }
+        assert!(index_writer.wait_merging_threads_with_timeout(Duration::from_millis(0))?);
+        let merge_result = merge_thread.join().unwrap();
+        assert!(matches!(merge_result, Err(TantivyError::MergeCancelled)));
+        directory.set_slow(false);
+        check_index_after_aborted_merge(&index, &directory, text_field)
+    }
 
     #[test]
     fn test_delete_during_merge() -> crate::Result<()> {
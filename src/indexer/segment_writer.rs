@@ -1,5 +1,6 @@
 use super::{
     doc_id_mapping::{get_doc_id_mapping_from_field, DocIdMapping},
+    json_term_writer::index_json_objects,
     operation::AddOperation,
 };
 use crate::fastfield::FastFieldsWriter;
@@ -13,7 +14,8 @@ use crate::schema::Term;
 use crate::schema::Value;
 use crate::schema::{Field, FieldEntry};
 use crate::store::StoreReader;
-use crate::tokenizer::{BoxTokenStream, PreTokenizedStream};
+use crate::termvector::{RecordingTokenStream, TermVectorsWriter};
+use crate::tokenizer::{BoxTokenStream, PreTokenizedStream, Token};
 use crate::tokenizer::{FacetTokenizer, TextAnalyzer};
 use crate::tokenizer::{TokenStreamChain, Tokenizer};
 use crate::Opstamp;
@@ -61,9 +63,11 @@ pub struct SegmentWriter {
     pub(crate) segment_serializer: SegmentSerializer,
     pub(crate) fast_field_writers: FastFieldsWriter,
     pub(crate) fieldnorms_writer: FieldNormsWriter,
+    pub(crate) term_vectors_writer: TermVectorsWriter,
     pub(crate) doc_opstamps: Vec<Opstamp>,
     tokenizers: Vec<Option<TextAnalyzer>>,
     term_buffer: Term,
+    term_vector_tokens: Vec<Token>,
 }
 
 impl SegmentWriter {
@@ -95,6 +99,11 @@ impl SegmentWriter {
                             let tokenizer_name = &text_index_option.tokenizer();
                             tokenizer_manager.get(tokenizer_name)
                         }),
+                    FieldType::JsonObject(ref json_object_options) => json_object_options
+                        .get_text_indexing_options()
+                        .and_then(|text_index_option| {
+                            tokenizer_manager.get(text_index_option.tokenizer())
+                        }),
                     _ => None,
                 },
             )
@@ -103,11 +112,13 @@ impl SegmentWriter {
             max_doc: 0,
             multifield_postings,
             fieldnorms_writer: FieldNormsWriter::for_schema(schema),
+            term_vectors_writer: TermVectorsWriter::for_schema(schema),
             segment_serializer,
             fast_field_writers: FastFieldsWriter::from_schema(schema),
             doc_opstamps: Vec::with_capacity(1_000),
             tokenizers,
             term_buffer: Term::new(),
+            term_vector_tokens: Vec::new(),
         })
     }
 
@@ -117,6 +128,7 @@ impl SegmentWriter {
     /// be used afterwards.
     pub fn finalize(mut self) -> crate::Result<Vec<u64>> {
         self.fieldnorms_writer.fill_up_to_max_doc(self.max_doc);
+        self.term_vectors_writer.fill_up_to_max_doc(self.max_doc);
         let mapping: Option<DocIdMapping> = self
             .segment_serializer
             .segment()
@@ -130,6 +142,7 @@ impl SegmentWriter {
             &self.multifield_postings,
             &self.fast_field_writers,
             &self.fieldnorms_writer,
+            &self.term_vectors_writer,
             self.segment_serializer,
             mapping.as_ref(),
         )?;
@@ -140,6 +153,7 @@ impl SegmentWriter {
     pub fn mem_usage(&self) -> usize {
         self.multifield_postings.mem_usage()
             + self.fieldnorms_writer.mem_usage()
+            + self.term_vectors_writer.mem_usage()
             + self.fast_field_writers.mem_usage()
             + self.segment_serializer.mem_usage()
     }
@@ -234,12 +248,27 @@ impl SegmentWriter {
                         0
                     } else {
                         let mut token_stream = TokenStreamChain::new(offsets, token_streams);
-                        multifield_postings.index_text(
-                            doc_id,
-                            field,
-                            &mut token_stream,
-                            term_buffer,
-                        )
+                        term_buffer.set_field(field);
+                        if self.term_vectors_writer.has_term_vectors(field) {
+                            self.term_vector_tokens.clear();
+                            let mut recording_token_stream = RecordingTokenStream::new(
+                                &mut token_stream,
+                                &mut self.term_vector_tokens,
+                            );
+                            let num_tokens = multifield_postings.index_text(
+                                doc_id,
+                                &mut recording_token_stream,
+                                term_buffer,
+                            );
+                            self.term_vectors_writer.record(
+                                doc_id,
+                                field,
+                                &mut self.term_vector_tokens,
+                            )?;
+                            num_tokens
+                        } else {
+                            multifield_postings.index_text(doc_id, &mut token_stream, term_buffer)
+                        }
                     };
 
                     self.fieldnorms_writer.record(doc_id, field, num_tokens);
@@ -288,6 +317,28 @@ impl SegmentWriter {
                         multifield_postings.subscribe(doc_id, term_buffer);
                     }
                 }
+                FieldType::Bool(_) => {
+                    for field_value in field_values {
+                        term_buffer.set_field(field_value.field());
+                        let bool_val = field_value
+                            .value()
+                            .bool_value()
+                            .ok_or_else(make_schema_error)?;
+                        term_buffer.set_bool(bool_val);
+                        multifield_postings.subscribe(doc_id, term_buffer);
+                    }
+                }
+                FieldType::IpAddr(_) => {
+                    for field_value in field_values {
+                        term_buffer.set_field(field_value.field());
+                        let ip_addr_val = field_value
+                            .value()
+                            .ip_addr_value()
+                            .ok_or_else(make_schema_error)?;
+                        term_buffer.set_ip_addr(ip_addr_val);
+                        multifield_postings.subscribe(doc_id, term_buffer);
+                    }
+                }
                 FieldType::Bytes(_) => {
                     for field_value in field_values {
                         term_buffer.set_field(field_value.field());
@@ -299,6 +350,22 @@ impl SegmentWriter {
                         self.multifield_postings.subscribe(doc_id, term_buffer);
                     }
                 }
+                FieldType::JsonObject(_) => {
+                    let json_objects = field_values
+                        .iter()
+                        .map(|field_value| field_value.value().as_json())
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(make_schema_error)?;
+                    let num_tokens = index_json_objects(
+                        doc_id,
+                        field,
+                        &json_objects,
+                        self.tokenizers[field.field_id() as usize].as_ref(),
+                        term_buffer,
+                        multifield_postings,
+                    );
+                    self.fieldnorms_writer.record(doc_id, field, num_tokens);
+                }
             }
         }
         doc.filter_fields(|field| schema.get_field_entry(field).is_stored());
@@ -339,12 +406,16 @@ fn remap_and_write(
     multifield_postings: &MultiFieldPostingsWriter,
     fast_field_writers: &FastFieldsWriter,
     fieldnorms_writer: &FieldNormsWriter,
+    term_vectors_writer: &TermVectorsWriter,
     mut serializer: SegmentSerializer,
     doc_id_map: Option<&DocIdMapping>,
 ) -> crate::Result<()> {
     if let Some(fieldnorms_serializer) = serializer.extract_fieldnorms_serializer() {
         fieldnorms_writer.serialize(fieldnorms_serializer, doc_id_map)?;
     }
+    if let Some(term_vectors_serializer) = serializer.extract_term_vectors_serializer() {
+        term_vectors_writer.serialize(term_vectors_serializer, doc_id_map)?;
+    }
     let fieldnorm_data = serializer
         .segment()
         .open_read(SegmentComponent::FieldNorms)?;
@@ -365,11 +436,9 @@ fn remap_and_write(
         let store_write = serializer
             .segment_mut()
             .open_write(SegmentComponent::Store)?;
-        let compressor = serializer.segment().index().settings().docstore_compression;
-        let old_store_writer = std::mem::replace(
-            &mut serializer.store_writer,
-            StoreWriter::new(store_write, compressor),
-        );
+        let store_writer =
+            StoreWriter::for_settings(store_write, serializer.segment().index().settings());
+        let old_store_writer = std::mem::replace(&mut serializer.store_writer, store_writer);
         old_store_writer.close()?;
         let store_read = StoreReader::open(
             serializer
@@ -0,0 +1,499 @@
+use super::merge_policy::{MergeCandidate, MergePolicy};
+use crate::core::SegmentMeta;
+
+const DEFAULT_MAX_MERGED_SEGMENT_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+const DEFAULT_FLOOR_SEGMENT_BYTES: u64 = 2 * 1024 * 1024;
+const DEFAULT_SEGMENTS_PER_TIER: f64 = 10.0;
+const DEFAULT_MAX_MERGE_AT_ONCE: usize = 10;
+const DEFAULT_RECLAIM_DELETES_WEIGHT: f64 = 2.0;
+const DEFAULT_DELETES_RATIO_ALLOWED: f64 = 0.33;
+
+/// The size of a segment, as seen by the `TieredMergePolicy`.
+struct SegmentSize<'a> {
+    segment: &'a SegmentMeta,
+    // The size of the segment, deleted documents included.
+    num_bytes: f64,
+    // The size of the alive documents of the segment.
+    live_num_bytes: f64,
+}
+
+/// `TieredMergePolicy` merges segments of approximately equal size, in bytes,
+/// in the manner of Lucene's `TieredMergePolicy`.
+///
+/// The segments are grouped in tiers of exponentially growing sizes, and a budget of
+/// `segments_per_tier` segments is allowed per tier. When the index has more segments
+/// than its budget, the policy picks the merges of up to `max_merge_at_once` segments
+/// with the least skewed sizes, favoring the segments with deleted documents and
+/// never producing a segment larger than `max_merged_segment_bytes`.
+///
+/// The segments written by a version of tantivy that did not record their size
+/// are estimated from their number of documents.
+#[derive(Debug, Clone)]
+pub struct TieredMergePolicy {
+    max_merged_segment_bytes: u64,
+    floor_segment_bytes: u64,
+    segments_per_tier: f64,
+    max_merge_at_once: usize,
+    reclaim_deletes_weight: f64,
+    deletes_ratio_allowed: f64,
+}
+
+impl TieredMergePolicy {
+    /// Set the maximum size of a segment produced by a merge, in bytes.
+    ///
+    /// The segments larger than half of this size, deleted documents excluded,
+    /// are not merged anymore.
+    pub fn set_max_merged_segment_bytes(&mut self, max_merged_segment_bytes: u64) {
+        self.max_merged_segment_bytes = max_merged_segment_bytes;
+    }
+
+    /// Set the size under which the segments are considered of the same size, in
+    /// bytes.
+    ///
+    /// This prevents the index from having a long tail of tiny segments.
+    pub fn set_floor_segment_bytes(&mut self, floor_segment_bytes: u64) {
+        self.floor_segment_bytes = floor_segment_bytes;
+    }
+
+    /// Set the number of segments allowed per tier.
+    ///
+    /// A smaller value means more merging, and fewer segments.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `segments_per_tier` is smaller than 2.
+    pub fn set_segments_per_tier(&mut self, segments_per_tier: f64) {
+        assert!(
+            segments_per_tier >= 2.0,
+            "The number of segments per tier must be at least 2."
+        );
+        self.segments_per_tier = segments_per_tier;
+    }
+
+    /// Set the maximum number of segments merged at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_merge_at_once` is smaller than 2.
+    pub fn set_max_merge_at_once(&mut self, max_merge_at_once: usize) {
+        assert!(
+            max_merge_at_once >= 2,
+            "The number of segments merged at once must be at least 2."
+        );
+        self.max_merge_at_once = max_merge_at_once;
+    }
+
+    /// Set how strongly the merges reclaiming deleted documents are favored.
+    ///
+    /// The score of a merge is multiplied by the ratio of its alive bytes raised to
+    /// this weight, `0.0` ignoring the deleted documents.
+    pub fn set_reclaim_deletes_weight(&mut self, reclaim_deletes_weight: f64) {
+        assert!(
+            reclaim_deletes_weight >= 0.0,
+            "The weight of the deletes must be positive."
+        );
+        self.reclaim_deletes_weight = reclaim_deletes_weight;
+    }
+
+    /// Set the ratio of deleted documents of the mergeable segments above which
+    /// segments are merged, even within the budget of segments.
+    ///
+    /// A single segment may then be merged, to drop its deleted documents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ratio is not in `(0, 1]`.
+    pub fn set_deletes_ratio_allowed(&mut self, deletes_ratio_allowed: f64) {
+        assert!(
+            deletes_ratio_allowed > 0.0 && deletes_ratio_allowed <= 1.0,
+            "The ratio of deleted documents must be in (0, 1]."
+        );
+        self.deletes_ratio_allowed = deletes_ratio_allowed;
+    }
+
+    fn floor_size(&self, num_bytes: f64) -> f64 {
+        num_bytes.max(self.floor_segment_bytes as f64)
+    }
+
+    /// Returns the number of segments allowed for `total_bytes` alive bytes.
+    fn allowed_num_segments(&self, total_bytes: f64, min_segment_bytes: f64) -> f64 {
+        let mut level_size = self.floor_size(min_segment_bytes);
+        let mut bytes_left = total_bytes;
+        let mut allowed_num_segments = 0.0;
+        loop {
+            let level_num_segments = bytes_left / level_size;
+            if level_num_segments < self.segments_per_tier {
+                allowed_num_segments += level_num_segments.ceil();
+                break;
+            }
+            allowed_num_segments += self.segments_per_tier;
+            bytes_left -= self.segments_per_tier * level_size;
+            level_size *= self.max_merge_at_once as f64;
+        }
+        allowed_num_segments.max(self.segments_per_tier)
+    }
+
+    /// Scores a merge; lower is better.
+    ///
+    /// Merges of segments of similar sizes are favored, as well as small merges
+    /// and the merges reclaiming deleted documents.
+    fn merge_score(&self, merge: &[&SegmentSize], hit_max_merge_at_once: bool) -> f64 {
+        let total_bytes: f64 = merge.iter().map(|segment| segment.num_bytes).sum();
+        let total_live_bytes: f64 = merge.iter().map(|segment| segment.live_num_bytes).sum();
+        let skew = if hit_max_merge_at_once {
+            1.0 / self.max_merge_at_once as f64
+        } else {
+            let total_floored_bytes: f64 = merge
+                .iter()
+                .map(|segment| self.floor_size(segment.live_num_bytes))
+                .sum();
+            self.floor_size(merge[0].live_num_bytes) / total_floored_bytes
+        };
+        let live_ratio = if total_bytes > 0.0 {
+            total_live_bytes / total_bytes
+        } else {
+            1.0
+        };
+        skew * total_live_bytes.max(1.0).powf(0.05) * live_ratio.powf(self.reclaim_deletes_weight)
+    }
+
+    /// Returns the best merge among the `segments`, sorted by decreasing size, if any.
+    ///
+    /// A merge of a single segment is only considered if `reclaim_deletes` is true
+    /// and the segment has deleted documents.
+    fn find_best_merge(
+        &self,
+        segments: &[SegmentSize],
+        reclaim_deletes: bool,
+    ) -> Option<Vec<usize>> {
+        let max_merged_segment_bytes = self.max_merged_segment_bytes as f64;
+        let mut best_merge: Option<(f64, Vec<usize>)> = None;
+        for start in 0..segments.len() {
+            let mut merge: Vec<usize> = Vec::new();
+            let mut merge_bytes = 0.0;
+            let mut hit_too_large = false;
+            for (ord, segment) in segments.iter().enumerate().skip(start) {
+                if merge.len() >= self.max_merge_at_once {
+                    break;
+                }
+                if merge_bytes + segment.live_num_bytes > max_merged_segment_bytes {
+                    // a smaller segment may still fit in the merge.
+                    hit_too_large = true;
+                    continue;
+                }
+                merge.push(ord);
+                merge_bytes += segment.live_num_bytes;
+            }
+            let has_deletes = merge.iter().any(|&ord| segments[ord].segment.has_deletes());
+            if merge.is_empty() || (merge.len() == 1 && !(reclaim_deletes && has_deletes)) {
+                continue;
+            }
+            let hit_max_merge_at_once = merge.len() >= self.max_merge_at_once || hit_too_large;
+            let merge_segments: Vec<&SegmentSize> =
+                merge.iter().map(|&ord| &segments[ord]).collect();
+            let score = self.merge_score(&merge_segments, hit_max_merge_at_once);
+            if best_merge
+                .as_ref()
+                .map(|(best_score, _)| score < *best_score)
+                .unwrap_or(true)
+            {
+                best_merge = Some((score, merge));
+            }
+        }
+        best_merge.map(|(_, merge)| merge)
+    }
+}
+
+impl MergePolicy for TieredMergePolicy {
+    fn compute_merge_candidates(&self, segments: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        // The size of the segments without a recorded size is estimated from the
+        // average size of a document.
+        let (known_bytes, known_docs) = segments
+            .iter()
+            .filter_map(|segment| {
+                segment
+                    .num_bytes()
+                    .map(|num_bytes| (num_bytes, segment.max_doc()))
+            })
+            .fold(
+                (0u64, 0u64),
+                |(total_bytes, total_docs), (num_bytes, max_doc)| {
+                    (total_bytes + num_bytes, total_docs + u64::from(max_doc))
+                },
+            );
+        let bytes_per_doc = if known_docs > 0 {
+            known_bytes as f64 / known_docs as f64
+        } else {
+            1.0
+        };
+        let max_segment_bytes = self.max_merged_segment_bytes as f64 / 2.0;
+        let mut eligible_segments: Vec<SegmentSize> = segments
+            .iter()
+            .map(|segment| {
+                let num_bytes = segment
+                    .num_bytes()
+                    .map(|num_bytes| num_bytes as f64)
+                    .unwrap_or_else(|| f64::from(segment.max_doc()) * bytes_per_doc);
+                let live_ratio = if segment.max_doc() > 0 {
+                    f64::from(segment.num_docs()) / f64::from(segment.max_doc())
+                } else {
+                    1.0
+                };
+                SegmentSize {
+                    segment,
+                    num_bytes,
+                    live_num_bytes: num_bytes * live_ratio,
+                }
+            })
+            .filter(|segment_size| segment_size.live_num_bytes < max_segment_bytes)
+            .collect();
+        if eligible_segments.is_empty() {
+            return Vec::new();
+        }
+        eligible_segments.sort_by(|left, right| {
+            right
+                .live_num_bytes
+                .partial_cmp(&left.live_num_bytes)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let total_live_bytes: f64 = eligible_segments
+            .iter()
+            .map(|segment| self.floor_size(segment.live_num_bytes))
+            .sum();
+        let min_segment_bytes = eligible_segments
+            .last()
+            .map(|segment| segment.live_num_bytes)
+            .unwrap_or(0.0);
+        let allowed_num_segments = self.allowed_num_segments(total_live_bytes, min_segment_bytes);
+
+        let mut merge_candidates = Vec::new();
+        loop {
+            let (num_deleted_docs, max_doc) = eligible_segments.iter().fold(
+                (0u64, 0u64),
+                |(num_deleted_docs, max_doc), segment| {
+                    (
+                        num_deleted_docs + u64::from(segment.segment.num_deleted_docs()),
+                        max_doc + u64::from(segment.segment.max_doc()),
+                    )
+                },
+            );
+            let reclaim_deletes =
+                num_deleted_docs as f64 > self.deletes_ratio_allowed * max_doc as f64;
+            if eligible_segments.len() as f64 <= allowed_num_segments && !reclaim_deletes {
+                break;
+            }
+            let best_merge = if let Some(best_merge) =
+                self.find_best_merge(&eligible_segments, reclaim_deletes)
+            {
+                best_merge
+            } else {
+                break;
+            };
+            merge_candidates.push(MergeCandidate(
+                best_merge
+                    .iter()
+                    .map(|&ord| eligible_segments[ord].segment.id())
+                    .collect(),
+            ));
+            for &ord in best_merge.iter().rev() {
+                eligible_segments.remove(ord);
+            }
+        }
+        merge_candidates
+    }
+}
+
+impl Default for TieredMergePolicy {
+    fn default() -> TieredMergePolicy {
+        TieredMergePolicy {
+            max_merged_segment_bytes: DEFAULT_MAX_MERGED_SEGMENT_BYTES,
+            floor_segment_bytes: DEFAULT_FLOOR_SEGMENT_BYTES,
+            segments_per_tier: DEFAULT_SEGMENTS_PER_TIER,
+            max_merge_at_once: DEFAULT_MAX_MERGE_AT_ONCE,
+            reclaim_deletes_weight: DEFAULT_RECLAIM_DELETES_WEIGHT,
+            deletes_ratio_allowed: DEFAULT_DELETES_RATIO_ALLOWED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{SegmentId, SegmentMeta, SegmentMetaInventory};
+    use crate::indexer::merge_policy::MergePolicy;
+    use crate::schema::{Schema, TEXT};
+    use crate::{doc, Index};
+    use once_cell::sync::Lazy;
+
+    static INVENTORY: Lazy<SegmentMetaInventory> = Lazy::new(SegmentMetaInventory::default);
+
+    fn create_segment_meta(num_bytes: u64, max_doc: u32, num_deleted_docs: u32) -> SegmentMeta {
+        let segment_meta = INVENTORY
+            .new_segment_meta(SegmentId::generate_random(), max_doc)
+            .with_num_bytes(num_bytes);
+        if num_deleted_docs > 0 {
+            segment_meta.with_delete_meta(num_deleted_docs, 0)
+        } else {
+            segment_meta
+        }
+    }
+
+    fn test_merge_policy() -> TieredMergePolicy {
+        let mut merge_policy = TieredMergePolicy::default();
+        merge_policy.set_floor_segment_bytes(100);
+        merge_policy.set_max_merged_segment_bytes(100_000);
+        merge_policy
+    }
+
+    #[test]
+    fn test_tiered_merge_policy_empty() {
+        assert!(test_merge_policy().compute_merge_candidates(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_tiered_merge_policy_within_budget() {
+        let test_input: Vec<SegmentMeta> = (0..10)
+            .map(|_| create_segment_meta(1_000, 100, 0))
+            .collect();
+        assert!(test_merge_policy()
+            .compute_merge_candidates(&test_input)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_tiered_merge_policy_too_many_segments() {
+        // 20 segments of 1000 bytes: 10 are allowed in the first tier, and 1 in the next one.
+        let test_input: Vec<SegmentMeta> = (0..20)
+            .map(|_| create_segment_meta(1_000, 100, 0))
+            .collect();
+        let result_list = test_merge_policy().compute_merge_candidates(&test_input);
+        assert_eq!(result_list.len(), 1);
+        let expected_ids: Vec<SegmentId> = test_input[..10].iter().map(SegmentMeta::id).collect();
+        assert_eq!(result_list[0].0, expected_ids);
+    }
+
+    #[test]
+    fn test_tiered_merge_policy_similar_sizes() {
+        // the small segments are merged together, rather than with a large one.
+        let mut test_input = vec![create_segment_meta(20_000, 1_000, 0)];
+        test_input.extend((0..6).map(|_| create_segment_meta(1_000, 100, 0)));
+        let mut merge_policy = test_merge_policy();
+        merge_policy.set_segments_per_tier(2.0);
+        merge_policy.set_max_merge_at_once(4);
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        assert_eq!(result_list.len(), 1);
+        let expected_ids: Vec<SegmentId> = test_input[1..5].iter().map(SegmentMeta::id).collect();
+        assert_eq!(result_list[0].0, expected_ids);
+    }
+
+    #[test]
+    fn test_tiered_merge_policy_max_merged_segment_bytes() {
+        // the segments larger than half of the maximum size are not merged anymore,
+        // and the merges do not exceed the maximum size.
+        let mut test_input: Vec<SegmentMeta> = (0..4)
+            .map(|_| create_segment_meta(60_000, 1_000, 0))
+            .collect();
+        test_input.extend((0..6).map(|_| create_segment_meta(40_000, 1_000, 0)));
+        let mut merge_policy = test_merge_policy();
+        merge_policy.set_segments_per_tier(2.0);
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        assert_eq!(result_list.len(), 2);
+        for merge_candidate in &result_list {
+            assert_eq!(merge_candidate.0.len(), 2);
+            for segment_id in &merge_candidate.0 {
+                assert!(test_input[4..]
+                    .iter()
+                    .any(|segment_meta| segment_meta.id() == *segment_id));
+            }
+        }
+    }
+
+    #[test]
+    fn test_tiered_merge_policy_reclaim_deletes_weight() {
+        // the two last segments have as many alive bytes as the other ones,
+        // but half of their documents are deleted.
+        let mut test_input: Vec<SegmentMeta> = (0..10)
+            .map(|_| create_segment_meta(1_000, 100, 0))
+            .collect();
+        test_input.push(create_segment_meta(2_000, 100, 50));
+        test_input.push(create_segment_meta(2_000, 100, 50));
+        let mut merge_policy = test_merge_policy();
+        merge_policy.set_max_merge_at_once(2);
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        assert_eq!(result_list.len(), 1);
+        assert_eq!(
+            result_list[0].0,
+            vec![test_input[10].id(), test_input[11].id()]
+        );
+
+        merge_policy.set_reclaim_deletes_weight(0.0);
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        assert_eq!(result_list.len(), 1);
+        assert_eq!(
+            result_list[0].0,
+            vec![test_input[0].id(), test_input[1].id()]
+        );
+    }
+
+    #[test]
+    fn test_tiered_merge_policy_deletes_ratio_allowed() {
+        let test_input = vec![
+            create_segment_meta(1_000, 1_000, 600),
+            create_segment_meta(1_000, 100, 0),
+            create_segment_meta(1_000, 100, 0),
+        ];
+        let mut merge_policy = test_merge_policy();
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        assert_eq!(result_list.len(), 1);
+        assert_eq!(result_list[0].0, vec![test_input[0].id()]);
+
+        merge_policy.set_deletes_ratio_allowed(0.6);
+        assert!(merge_policy
+            .compute_merge_candidates(&test_input)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_tiered_merge_policy_unknown_sizes() {
+        // the size of the segments without a recorded size is estimated from their
+        // number of documents.
+        let mut test_input = vec![create_segment_meta(1_000, 100, 0)];
+        test_input
+            .extend((0..3).map(|_| INVENTORY.new_segment_meta(SegmentId::generate_random(), 100)));
+        test_input.push(INVENTORY.new_segment_meta(SegmentId::generate_random(), 100_000));
+        let mut merge_policy = test_merge_policy();
+        merge_policy.set_segments_per_tier(2.0);
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        // the last segment is estimated at 1_000_000 bytes, and is not merged.
+        assert_eq!(result_list.len(), 1);
+        let expected_ids: Vec<SegmentId> = test_input[..4].iter().map(SegmentMeta::id).collect();
+        assert_eq!(result_list[0].0, expected_ids);
+    }
+
+    #[test]
+    fn test_tiered_merge_policy_index_writer() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let mut merge_policy = TieredMergePolicy::default();
+        merge_policy.set_floor_segment_bytes(1);
+        merge_policy.set_segments_per_tier(2.0);
+        merge_policy.set_max_merge_at_once(2);
+        index_writer.set_merge_policy(Box::new(merge_policy));
+        for _ in 0..4 {
+            index_writer.add_document(doc!(text_field => "hello"));
+            index_writer.commit()?;
+        }
+        index_writer.wait_merging_threads()?;
+        let segment_metas = index.searchable_segment_metas()?;
+        assert!(segment_metas.len() < 4);
+        for segment_meta in &segment_metas {
+            assert!(segment_meta.num_bytes().unwrap() > 0);
+        }
+        let num_docs: u32 = segment_metas.iter().map(SegmentMeta::num_docs).sum();
+        assert_eq!(num_docs, 4);
+        Ok(())
+    }
+}
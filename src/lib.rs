@@ -153,25 +153,36 @@ pub mod schema;
 pub mod space_usage;
 pub mod store;
 pub mod termdict;
+pub mod termvector;
 
 mod reader;
 
-pub use self::reader::{IndexReader, IndexReaderBuilder, ReloadPolicy};
+pub use self::reader::{
+    FastFieldWarmer, IndexReader, IndexReaderBuilder, ReloadPolicy, SegmentLoadPolicy, Warmer,
+};
 mod snippet;
-pub use self::snippet::{Snippet, SnippetGenerator};
+pub use self::snippet::{FragmentCandidate, PassageScorer, Snippet, SnippetGenerator};
 
 mod docset;
 pub use self::docset::{DocSet, TERMINATED};
-pub use crate::core::{Executor, SegmentComponent};
 pub use crate::core::{
-    Index, IndexBuilder, IndexMeta, IndexSettings, IndexSortByField, Order, Searcher, Segment,
-    SegmentId, SegmentMeta,
+    CollectionStatistics, Index, IndexBuilder, IndexMeta, IndexSettings, IndexSortByField, Order,
+    PreparedCommitMeta, Searcher, Segment, SegmentId, SegmentMeta, TermStatistics,
 };
+pub use crate::core::{Executor, IndexDiskUsage, IndexSnapshot, SegmentComponent, SnapshotFile};
 pub use crate::core::{InvertedIndexReader, SegmentReader};
+pub use crate::core::{SearchableSegmentFiles, SegmentFile, SegmentFiles};
+pub use crate::core::{
+    ValidationOptions, ValidationProblem, ValidationProblemKind, ValidationReport,
+};
 pub use crate::directory::Directory;
 pub use crate::indexer::merge_segments;
 pub use crate::indexer::operation::UserOperation;
+pub use crate::indexer::CommitPolicy;
 pub use crate::indexer::IndexWriter;
+pub use crate::indexer::IndexWriterEvents;
+pub use crate::indexer::MergeDocMapping;
+pub use crate::indexer::MergeOperationInfo;
 pub use crate::postings::Postings;
 pub use crate::reader::LeasedItem;
 pub use crate::schema::{Document, Term};
@@ -183,7 +194,7 @@ use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
 /// Index format version.
-const INDEX_FORMAT_VERSION: u32 = 4;
+const INDEX_FORMAT_VERSION: u32 = 6;
 
 /// Structure version for the index.
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -236,6 +247,7 @@ pub mod merge_policy {
     pub use crate::indexer::LogMergePolicy;
     pub use crate::indexer::MergePolicy;
     pub use crate::indexer::NoMergePolicy;
+    pub use crate::indexer::TieredMergePolicy;
 }
 
 /// A `u32` identifying a document within a segment.
@@ -303,6 +315,7 @@ pub mod tests {
     use crate::Index;
     use crate::Postings;
     use crate::ReloadPolicy;
+    use crate::{CollectionStatistics, TermStatistics};
     use common::{BinarySerializable, FixedSize};
     use rand::distributions::Bernoulli;
     use rand::distributions::Uniform;
@@ -423,6 +436,103 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_term_statistics() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let string_field = schema_builder.add_text_field("string", STRING);
+        let num_field = schema_builder.add_u64_field("num", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field=>"a b c", string_field=>"x", num_field=>1u64));
+        index_writer.add_document(doc!(text_field=>"a a", string_field=>"x"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field=>"a a a b", string_field=>"y"));
+        index_writer.add_document(doc!(num_field=>1u64));
+        index_writer.commit()?;
+        let term_a = Term::from_field_text(text_field, "a");
+        let term_b = Term::from_field_text(text_field, "b");
+        let term_x = Term::from_field_text(string_field, "x");
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        assert_eq!(
+            searcher.term_statistics(&term_a)?,
+            TermStatistics {
+                doc_freq: 3,
+                total_term_freq: Some(6),
+            }
+        );
+        assert_eq!(
+            searcher.term_statistics(&Term::from_field_text(text_field, "d"))?,
+            TermStatistics {
+                doc_freq: 0,
+                total_term_freq: Some(0),
+            }
+        );
+        assert_eq!(
+            searcher.term_statistics(&term_x)?,
+            TermStatistics {
+                doc_freq: 2,
+                total_term_freq: None,
+            }
+        );
+        assert_eq!(
+            searcher.collection_statistics(text_field)?,
+            CollectionStatistics {
+                doc_count: 3,
+                sum_total_term_freq: 9,
+                sum_doc_freq: 6,
+            }
+        );
+        assert_eq!(
+            searcher.collection_statistics(num_field)?,
+            CollectionStatistics {
+                doc_count: 4,
+                sum_total_term_freq: 2,
+                sum_doc_freq: 2,
+            }
+        );
+
+        index_writer.delete_term(Term::from_field_text(string_field, "y"));
+        index_writer.commit()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.term_statistics(&term_a)?.doc_freq, 3);
+        let expected_term_a = TermStatistics {
+            doc_freq: 2,
+            total_term_freq: Some(3),
+        };
+        assert_eq!(
+            searcher.term_statistics_given_deletes(&term_a)?,
+            expected_term_a
+        );
+        assert_eq!(
+            searcher.term_statistics_given_deletes(&term_x)?,
+            TermStatistics {
+                doc_freq: 2,
+                total_term_freq: None,
+            }
+        );
+
+        // the total term frequencies are summed up when the segments are merged.
+        let segment_ids = index.searchable_segment_ids()?;
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.term_statistics(&term_a)?, expected_term_a);
+        assert_eq!(
+            searcher.term_statistics(&term_b)?,
+            TermStatistics {
+                doc_freq: 1,
+                total_term_freq: Some(1),
+            }
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_fieldnorm_no_docs_with_field() -> crate::Result<()> {
         let mut schema_builder = Schema::builder();
@@ -5,7 +5,7 @@ use crate::directory::OwnedBytes;
 use crate::fieldnorm::FieldNormReader;
 use crate::postings::compression::{BlockDecoder, VIntDecoder, COMPRESSION_BLOCK_SIZE};
 use crate::postings::{BlockInfo, FreqReadingOption, SkipReader};
-use crate::query::Bm25Weight;
+use crate::query::SimilarityWeight;
 use crate::schema::IndexRecordOption;
 use crate::{DocId, Score, TERMINATED};
 use common::{BinarySerializable, VInt};
@@ -121,12 +121,12 @@ impl BlockSegmentPostings {
     pub fn block_max_score(
         &mut self,
         fieldnorm_reader: &FieldNormReader,
-        bm25_weight: &Bm25Weight,
+        similarity_weight: &SimilarityWeight,
     ) -> Score {
         if let Some(score) = self.block_max_score_cache {
             return score;
         }
-        if let Some(skip_reader_max_score) = self.skip_reader.block_max_score(bm25_weight) {
+        if let Some(skip_reader_max_score) = self.skip_reader.block_max_score(similarity_weight) {
             // if we are on a full block, the skip reader should have the block max information
             // for us
             self.block_max_score_cache = Some(skip_reader_max_score);
@@ -137,19 +137,19 @@ impl BlockSegmentPostings {
         if self.block_is_loaded() {
             let docs = self.doc_decoder.output_array().iter().cloned();
             let freqs = self.freq_decoder.output_array().iter().cloned();
-            let bm25_scores = docs.zip(freqs).map(|(doc, term_freq)| {
+            let scores = docs.zip(freqs).map(|(doc, term_freq)| {
                 let fieldnorm_id = fieldnorm_reader.fieldnorm_id(doc);
-                bm25_weight.score(fieldnorm_id, term_freq)
+                similarity_weight.score(fieldnorm_id, term_freq)
             });
-            let block_max_score = max_score(bm25_scores).unwrap_or(0.0);
+            let block_max_score = max_score(scores).unwrap_or(0.0);
             self.block_max_score_cache = Some(block_max_score);
             return block_max_score;
         }
-        // We do not have access to any good block max value. We return bm25_weight.max_score()
+        // We do not have access to any good block max value. We return similarity_weight.max_score()
         // as it is a valid upperbound.
         //
         // We do not cache it however, so that it gets computed when once block is loaded.
-        bm25_weight.max_score()
+        similarity_weight.max_score()
     }
 
     pub(crate) fn freq_reading_option(&self) -> FreqReadingOption {
@@ -40,6 +40,8 @@ pub(crate) enum FreqReadingOption {
 pub mod tests {
     use super::InvertedIndexSerializer;
     use super::Postings;
+    use super::SegmentPostings;
+    use crate::collector::TopDocs;
     use crate::core::Index;
     use crate::core::SegmentComponent;
     use crate::core::SegmentReader;
@@ -48,14 +50,18 @@ pub mod tests {
     use crate::indexer::operation::AddOperation;
     use crate::indexer::SegmentWriter;
     use crate::merge_policy::NoMergePolicy;
-    use crate::query::Scorer;
+    use crate::query::{Explanation, Query, Scorer, Weight};
     use crate::schema::{Field, TextOptions};
     use crate::schema::{IndexRecordOption, TextFieldIndexing};
     use crate::schema::{Schema, Term, INDEXED, TEXT};
-    use crate::tokenizer::{SimpleTokenizer, MAX_TOKEN_LEN};
+    use crate::tokenizer::{
+        BoxTokenStream, SimpleTokenizer, TextAnalyzer, Token, TokenFilter, TokenStream,
+        MAX_TOKEN_LEN,
+    };
     use crate::DocId;
     use crate::HasLen;
     use crate::Score;
+    use crate::Searcher;
     use std::mem;
 
     #[test]
@@ -200,6 +206,217 @@ pub mod tests {
         Ok(())
     }
 
+    // Sets the length of the token as its payload.
+    #[derive(Clone)]
+    struct LengthPayloadFilter;
+
+    impl TokenFilter for LengthPayloadFilter {
+        fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+            BoxTokenStream::from(LengthPayloadTokenStream { tail: token_stream })
+        }
+    }
+
+    struct LengthPayloadTokenStream<'a> {
+        tail: BoxTokenStream<'a>,
+    }
+
+    impl<'a> TokenStream for LengthPayloadTokenStream<'a> {
+        fn advance(&mut self) -> bool {
+            if !self.tail.advance() {
+                return false;
+            }
+            let token = self.tail.token_mut();
+            token.payload = token.text.len() as u32;
+            true
+        }
+
+        fn token(&self) -> &Token {
+            self.tail.token()
+        }
+
+        fn token_mut(&mut self) -> &mut Token {
+            self.tail.token_mut()
+        }
+    }
+
+    // Scores the documents containing a term by the sum of the payloads of its positions.
+    #[derive(Clone, Debug)]
+    struct PayloadSumQuery {
+        term: Term,
+    }
+
+    impl Query for PayloadSumQuery {
+        fn weight(&self, _: &Searcher, _: bool) -> crate::Result<Box<dyn Weight>> {
+            Ok(Box::new(self.clone()))
+        }
+    }
+
+    impl Weight for PayloadSumQuery {
+        fn scorer(&self, reader: &SegmentReader, _: Score) -> crate::Result<Box<dyn Scorer>> {
+            let postings = reader
+                .inverted_index(self.term.field())?
+                .read_postings(
+                    &self.term,
+                    IndexRecordOption::WithFreqsAndPositionsAndPayloads,
+                )?
+                .unwrap_or_else(SegmentPostings::empty);
+            Ok(Box::new(PayloadSumScorer {
+                postings,
+                positions: Vec::new(),
+                payloads: Vec::new(),
+            }))
+        }
+
+        fn explain(&self, _: &SegmentReader, _: DocId) -> crate::Result<Explanation> {
+            Ok(Explanation::new("PayloadSumQuery", 0.0))
+        }
+    }
+
+    struct PayloadSumScorer {
+        postings: SegmentPostings,
+        positions: Vec<u32>,
+        payloads: Vec<u32>,
+    }
+
+    impl DocSet for PayloadSumScorer {
+        fn advance(&mut self) -> DocId {
+            self.postings.advance()
+        }
+
+        fn doc(&self) -> DocId {
+            self.postings.doc()
+        }
+
+        fn size_hint(&self) -> u32 {
+            self.postings.size_hint()
+        }
+    }
+
+    impl Scorer for PayloadSumScorer {
+        fn score(&mut self) -> Score {
+            self.postings
+                .positions_and_payloads(&mut self.positions, &mut self.payloads);
+            self.payloads.iter().sum::<u32>() as Score
+        }
+    }
+
+    fn payload_index(index_record_option: IndexRecordOption) -> crate::Result<(Index, Field)> {
+        let mut schema_builder = Schema::builder();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(index_record_option)
+                .set_tokenizer("payload"),
+        );
+        let text_field = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        index.tokenizers().register(
+            "payload",
+            TextAnalyzer::from(SimpleTokenizer).filter(LengthPayloadFilter),
+        );
+        Ok((index, text_field))
+    }
+
+    #[test]
+    pub fn test_payloads() -> crate::Result<()> {
+        let (index, text_field) =
+            payload_index(IndexRecordOption::WithFreqsAndPositionsAndPayloads)?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_document(doc!(text_field => "a bbb a"));
+        for _ in 0..300 {
+            index_writer.add_document(doc!(text_field => "cc a cc"));
+        }
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "a cc cc cc"));
+        index_writer.commit()?;
+
+        let searcher = index.reader()?.searcher();
+        let term_cc = Term::from_field_text(text_field, "cc");
+        let top_docs = searcher.search(
+            &PayloadSumQuery {
+                term: term_cc.clone(),
+            },
+            &TopDocs::with_limit(2),
+        )?;
+        assert_eq!(top_docs[0].0, 6.0);
+        assert_eq!(top_docs[1].0, 4.0);
+
+        let inverted_index = searcher.segment_reader(0u32).inverted_index(text_field)?;
+        let mut positions = Vec::new();
+        let mut payloads = Vec::new();
+        let mut postings = inverted_index
+            .read_postings(
+                &Term::from_field_text(text_field, "a"),
+                IndexRecordOption::WithFreqsAndPositionsAndPayloads,
+            )?
+            .unwrap();
+        postings.positions_and_payloads(&mut positions, &mut payloads);
+        assert_eq!(&positions[..], &[0, 2]);
+        assert_eq!(&payloads[..], &[1, 1]);
+        // the positions skip the payloads, including within the blocks after a seek.
+        assert_eq!(postings.seek(250), 250);
+        postings.positions(&mut positions);
+        assert_eq!(&positions[..], &[1]);
+        let mut postings = inverted_index
+            .read_postings(
+                &term_cc,
+                IndexRecordOption::WithFreqsAndPositionsAndPayloads,
+            )?
+            .unwrap();
+        assert_eq!(postings.seek(300), 300);
+        postings.positions_and_payloads_with_offset(10, &mut positions, &mut payloads);
+        assert_eq!(&positions[..], &[10, 12]);
+        assert_eq!(&payloads[..], &[2, 2]);
+
+        // the payloads are kept through merges.
+        let segment_ids = index.searchable_segment_ids()?;
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let top_docs =
+            searcher.search(&PayloadSumQuery { term: term_cc }, &TopDocs::with_limit(1))?;
+        assert_eq!(top_docs[0].0, 6.0);
+        let inverted_index = searcher.segment_reader(0u32).inverted_index(text_field)?;
+        let mut postings = inverted_index
+            .read_postings(
+                &Term::from_field_text(text_field, "bbb"),
+                IndexRecordOption::WithFreqsAndPositionsAndPayloads,
+            )?
+            .unwrap();
+        postings.positions_and_payloads(&mut positions, &mut payloads);
+        assert_eq!(&positions[..], &[1]);
+        assert_eq!(&payloads[..], &[3]);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_payloads_absent() -> crate::Result<()> {
+        let (index, text_field) = payload_index(IndexRecordOption::WithFreqsAndPositions)?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "a bbb a"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term = Term::from_field_text(text_field, "a");
+        let top_docs = searcher.search(
+            &PayloadSumQuery { term: term.clone() },
+            &TopDocs::with_limit(1),
+        )?;
+        assert_eq!(top_docs.len(), 1);
+        assert_eq!(top_docs[0].0, 0.0);
+        let mut postings = searcher
+            .segment_reader(0u32)
+            .inverted_index(text_field)?
+            .read_postings(&term, IndexRecordOption::WithFreqsAndPositionsAndPayloads)?
+            .unwrap();
+        let mut positions = Vec::new();
+        let mut payloads = Vec::new();
+        postings.positions_and_payloads(&mut positions, &mut payloads);
+        assert_eq!(&positions[..], &[0, 2]);
+        assert_eq!(&payloads[..], &[0, 0]);
+        Ok(())
+    }
+
     #[test]
     pub fn test_position_and_fieldnorm1() -> crate::Result<()> {
         let mut positions = Vec::new();
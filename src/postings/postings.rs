@@ -23,4 +23,26 @@ pub trait Postings: DocSet + 'static {
     fn positions(&mut self, output: &mut Vec<u32>) {
         self.positions_with_offset(0u32, output);
     }
+
+    /// Returns the positions offseted with a given value, and their payloads.
+    /// Both output vectors will be resized to the `term_freq`.
+    ///
+    /// The payloads are `0` if the field was not indexed with
+    /// `IndexRecordOption::WithFreqsAndPositionsAndPayloads`.
+    fn positions_and_payloads_with_offset(
+        &mut self,
+        offset: u32,
+        positions: &mut Vec<u32>,
+        payloads: &mut Vec<u32>,
+    ) {
+        self.positions_with_offset(offset, positions);
+        payloads.clear();
+        payloads.resize(positions.len(), 0u32);
+    }
+
+    /// Returns the positions of the term in the given document, and their payloads.
+    /// Both output vectors will be resized to the `term_freq`.
+    fn positions_and_payloads(&mut self, positions: &mut Vec<u32>, payloads: &mut Vec<u32>) {
+        self.positions_and_payloads_with_offset(0u32, positions, payloads);
+    }
 }
@@ -2,11 +2,12 @@ use super::stacker::{Addr, MemoryArena, TermHashMap};
 
 use crate::postings::recorder::{
     BufferLender, NothingRecorder, Recorder, TermFrequencyRecorder, TfAndPositionRecorder,
+    TfPositionAndPayloadRecorder,
 };
 use crate::postings::UnorderedTermId;
 use crate::postings::{FieldSerializer, InvertedIndexSerializer};
-use crate::schema::IndexRecordOption;
 use crate::schema::{Field, FieldEntry, FieldType, Schema, Term};
+use crate::schema::{IndexRecordOption, TextFieldIndexing};
 use crate::termdict::TermOrdinal;
 use crate::tokenizer::TokenStream;
 use crate::tokenizer::{Token, MAX_TOKEN_LEN};
@@ -18,25 +19,38 @@ use std::io;
 use std::marker::PhantomData;
 use std::ops::{DerefMut, Range};
 
+fn posting_from_text_indexing(
+    text_indexing_options: Option<&TextFieldIndexing>,
+) -> Box<dyn PostingsWriter> {
+    text_indexing_options
+        .map(|indexing_options| match indexing_options.index_option() {
+            IndexRecordOption::Basic => SpecializedPostingsWriter::<NothingRecorder>::new_boxed(),
+            IndexRecordOption::WithFreqs => {
+                SpecializedPostingsWriter::<TermFrequencyRecorder>::new_boxed()
+            }
+            IndexRecordOption::WithFreqsAndPositions => {
+                SpecializedPostingsWriter::<TfAndPositionRecorder>::new_boxed()
+            }
+            IndexRecordOption::WithFreqsAndPositionsAndPayloads => {
+                SpecializedPostingsWriter::<TfPositionAndPayloadRecorder>::new_boxed()
+            }
+        })
+        .unwrap_or_else(|| SpecializedPostingsWriter::<NothingRecorder>::new_boxed())
+}
+
 fn posting_from_field_entry(field_entry: &FieldEntry) -> Box<dyn PostingsWriter> {
     match *field_entry.field_type() {
-        FieldType::Str(ref text_options) => text_options
-            .get_indexing_options()
-            .map(|indexing_options| match indexing_options.index_option() {
-                IndexRecordOption::Basic => {
-                    SpecializedPostingsWriter::<NothingRecorder>::new_boxed()
-                }
-                IndexRecordOption::WithFreqs => {
-                    SpecializedPostingsWriter::<TermFrequencyRecorder>::new_boxed()
-                }
-                IndexRecordOption::WithFreqsAndPositions => {
-                    SpecializedPostingsWriter::<TfAndPositionRecorder>::new_boxed()
-                }
-            })
-            .unwrap_or_else(|| SpecializedPostingsWriter::<NothingRecorder>::new_boxed()),
+        FieldType::Str(ref text_options) => {
+            posting_from_text_indexing(text_options.get_indexing_options())
+        }
+        FieldType::JsonObject(ref json_object_options) => {
+            posting_from_text_indexing(json_object_options.get_text_indexing_options())
+        }
         FieldType::U64(_)
         | FieldType::I64(_)
         | FieldType::F64(_)
+        | FieldType::Bool(_)
+        | FieldType::IpAddr(_)
         | FieldType::Date(_)
         | FieldType::Bytes(_)
         | FieldType::HierarchicalFacet(_) => {
@@ -98,19 +112,21 @@ impl MultiFieldPostingsWriter {
         self.term_index.mem_usage() + self.heap.mem_usage()
     }
 
+    /// Indexes the tokens of `token_stream`.
+    ///
+    /// The terms are made of the current content of `term_buffer`,
+    /// (e.g. the field), followed by the text of the tokens.
     pub fn index_text(
         &mut self,
         doc: DocId,
-        field: Field,
         token_stream: &mut dyn TokenStream,
         term_buffer: &mut Term,
     ) -> u32 {
         let postings_writer =
-            self.per_field_postings_writers[field.field_id() as usize].deref_mut();
+            self.per_field_postings_writers[term_buffer.field().field_id() as usize].deref_mut();
         postings_writer.index_text(
             &mut self.term_index,
             doc,
-            field,
             token_stream,
             &mut self.heap,
             term_buffer,
@@ -120,7 +136,7 @@ impl MultiFieldPostingsWriter {
     pub fn subscribe(&mut self, doc: DocId, term: &Term) -> UnorderedTermId {
         let postings_writer =
             self.per_field_postings_writers[term.field().field_id() as usize].deref_mut();
-        postings_writer.subscribe(&mut self.term_index, doc, 0u32, term, &mut self.heap)
+        postings_writer.subscribe(&mut self.term_index, doc, 0u32, 0u32, term, &mut self.heap)
     }
 
     /// Serialize the inverted index.
@@ -159,8 +175,13 @@ impl MultiFieldPostingsWriter {
                         .collect();
                     unordered_term_mappings.insert(field, mapping);
                 }
-                FieldType::U64(_) | FieldType::I64(_) | FieldType::F64(_) | FieldType::Date(_) => {}
-                FieldType::Bytes(_) => {}
+                FieldType::U64(_)
+                | FieldType::I64(_)
+                | FieldType::F64(_)
+                | FieldType::Bool(_)
+                | FieldType::IpAddr(_)
+                | FieldType::Date(_) => {}
+                FieldType::Bytes(_) | FieldType::JsonObject(_) => {}
             }
 
             let postings_writer =
@@ -193,6 +214,7 @@ pub trait PostingsWriter {
     ///
     /// * doc  - the document id
     /// * pos  - the term position (expressed in tokens)
+    /// * payload - the payload of the position, `0` if none
     /// * term - the term
     /// * heap - heap used to store the postings informations as well as the terms
     /// in the hashmap.
@@ -201,6 +223,7 @@ pub trait PostingsWriter {
         term_index: &mut TermHashMap,
         doc: DocId,
         pos: u32,
+        payload: u32,
         term: &Term,
         heap: &mut MemoryArena,
     ) -> UnorderedTermId;
@@ -217,21 +240,30 @@ pub trait PostingsWriter {
     ) -> io::Result<()>;
 
     /// Tokenize a text and subscribe all of its token.
+    ///
+    /// The current content of `term_buffer` is the prefix of the terms.
     fn index_text(
         &mut self,
         term_index: &mut TermHashMap,
         doc_id: DocId,
-        field: Field,
         token_stream: &mut dyn TokenStream,
         heap: &mut MemoryArena,
         term_buffer: &mut Term,
     ) -> u32 {
-        term_buffer.set_field(field);
+        let prefix_len = term_buffer.as_slice().len();
         let mut sink = |token: &Token| {
             // We skip all tokens with a len greater than u16.
             if token.text.len() <= MAX_TOKEN_LEN {
-                term_buffer.set_text(token.text.as_str());
-                self.subscribe(term_index, doc_id, token.position as u32, term_buffer, heap);
+                term_buffer.truncate(prefix_len);
+                term_buffer.append_bytes(token.text.as_bytes());
+                self.subscribe(
+                    term_index,
+                    doc_id,
+                    token.position as u32,
+                    token.payload,
+                    term_buffer,
+                    heap,
+                );
             } else {
                 warn!(
                     "A token exceeding MAX_TOKEN_LEN ({}>{}) was dropped. Search for \
@@ -275,6 +307,7 @@ impl<Rec: Recorder + 'static> PostingsWriter for SpecializedPostingsWriter<Rec>
         term_index: &mut TermHashMap,
         doc: DocId,
         position: u32,
+        payload: u32,
         term: &Term,
         heap: &mut MemoryArena,
     ) -> UnorderedTermId {
@@ -287,12 +320,12 @@ impl<Rec: Recorder + 'static> PostingsWriter for SpecializedPostingsWriter<Rec>
                     recorder.close_doc(heap);
                     recorder.new_doc(doc, heap);
                 }
-                recorder.record_position(position, heap);
+                recorder.record_position(position, payload, heap);
                 recorder
             } else {
                 let mut recorder = Rec::new();
                 recorder.new_doc(doc, heap);
-                recorder.record_position(position, heap);
+                recorder.record_position(position, payload, heap);
                 recorder
             }
         }) as UnorderedTermId
@@ -18,6 +18,8 @@ pub struct SegmentPostings {
     pub(crate) block_cursor: BlockSegmentPostings,
     cur: usize,
     position_reader: Option<PositionReader>,
+    // If true, each position is followed by its payload in the positions.
+    has_payloads: bool,
 }
 
 impl SegmentPostings {
@@ -27,6 +29,7 @@ impl SegmentPostings {
             block_cursor: BlockSegmentPostings::empty(),
             cur: 0,
             position_reader: None,
+            has_payloads: false,
         }
     }
 
@@ -152,6 +155,35 @@ impl SegmentPostings {
             block_cursor: segment_block_postings,
             cur: 0, // cursor within the block
             position_reader,
+            has_payloads: false,
+        }
+    }
+
+    /// Sets whether the positions of the postings are followed by their payloads.
+    pub(crate) fn with_payloads(mut self, has_payloads: bool) -> SegmentPostings {
+        self.has_payloads = has_payloads;
+        self
+    }
+
+    // Reads the positions of the current document, interleaved with their payloads
+    // if the postings have payloads. The positions are returned as deltas.
+    //
+    // Returns false if there are no positions.
+    fn read_positions_data(&mut self, output: &mut Vec<u32>) -> bool {
+        let stride = if self.has_payloads { 2 } else { 1 };
+        let term_freq = self.term_freq() as usize;
+        if let Some(position_reader) = self.position_reader.as_mut() {
+            let read_offset = self.block_cursor.position_offset()
+                + (self.block_cursor.freqs()[..self.cur]
+                    .iter()
+                    .cloned()
+                    .sum::<u32>() as u64)
+                    * stride as u64;
+            output.resize(term_freq * stride, 0u32);
+            position_reader.read(read_offset, &mut output[..]);
+            true
+        } else {
+            false
         }
     }
 }
@@ -241,23 +273,49 @@ impl Postings for SegmentPostings {
     }
 
     fn positions_with_offset(&mut self, offset: u32, output: &mut Vec<u32>) {
-        let term_freq = self.term_freq() as usize;
-        if let Some(position_reader) = self.position_reader.as_mut() {
-            let read_offset = self.block_cursor.position_offset()
-                + (self.block_cursor.freqs()[..self.cur]
-                    .iter()
-                    .cloned()
-                    .sum::<u32>() as u64);
-            output.resize(term_freq, 0u32);
-            position_reader.read(read_offset, &mut output[..]);
-            let mut cum = offset;
-            for output_mut in output.iter_mut() {
-                cum += *output_mut;
-                *output_mut = cum;
-            }
-        } else {
+        if !self.read_positions_data(output) {
             output.clear();
+            return;
+        }
+        if self.has_payloads {
+            // drops the payloads.
+            let term_freq = output.len() / 2;
+            for i in 0..term_freq {
+                output[i] = output[2 * i];
+            }
+            output.truncate(term_freq);
+        }
+        let mut cum = offset;
+        for output_mut in output.iter_mut() {
+            cum += *output_mut;
+            *output_mut = cum;
+        }
+    }
+
+    fn positions_and_payloads_with_offset(
+        &mut self,
+        offset: u32,
+        positions: &mut Vec<u32>,
+        payloads: &mut Vec<u32>,
+    ) {
+        payloads.clear();
+        if !self.has_payloads {
+            self.positions_with_offset(offset, positions);
+            payloads.resize(positions.len(), 0u32);
+            return;
+        }
+        if !self.read_positions_data(positions) {
+            positions.clear();
+            return;
+        }
+        let term_freq = positions.len() / 2;
+        let mut cum = offset;
+        for i in 0..term_freq {
+            cum += positions[2 * i];
+            payloads.push(positions[2 * i + 1]);
+            positions[i] = cum;
         }
+        positions.truncate(term_freq);
     }
 }
 
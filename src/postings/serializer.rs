@@ -6,7 +6,7 @@ use crate::fieldnorm::FieldNormReader;
 use crate::positions::PositionSerializer;
 use crate::postings::compression::{BlockEncoder, VIntEncoder, COMPRESSION_BLOCK_SIZE};
 use crate::postings::skip::SkipSerializer;
-use crate::query::Bm25Weight;
+use crate::query::SimilarityWeight;
 use crate::schema::{Field, FieldEntry, FieldType};
 use crate::schema::{IndexRecordOption, Schema};
 use crate::termdict::{TermDictionaryBuilder, TermOrdinal};
@@ -108,6 +108,7 @@ pub struct FieldSerializer<'a> {
     postings_serializer: PostingsSerializer<&'a mut CountingWriter<WritePtr>>,
     positions_serializer_opt: Option<PositionSerializer<&'a mut CountingWriter<WritePtr>>>,
     current_term_info: TermInfo,
+    has_freqs: bool,
     term_open: bool,
     num_terms: TermOrdinal,
 }
@@ -122,16 +123,9 @@ impl<'a> FieldSerializer<'a> {
         fieldnorm_reader: Option<FieldNormReader>,
     ) -> io::Result<FieldSerializer<'a>> {
         total_num_tokens.serialize(postings_write)?;
-        let mode = match field_type {
-            FieldType::Str(ref text_options) => {
-                if let Some(text_indexing_options) = text_options.get_indexing_options() {
-                    text_indexing_options.index_option()
-                } else {
-                    IndexRecordOption::Basic
-                }
-            }
-            _ => IndexRecordOption::Basic,
-        };
+        let mode = field_type
+            .get_index_record_option()
+            .unwrap_or(IndexRecordOption::Basic);
         let term_dictionary_builder = TermDictionaryBuilder::create(term_dictionary_write)?;
         let average_fieldnorm = fieldnorm_reader
             .as_ref()
@@ -150,6 +144,7 @@ impl<'a> FieldSerializer<'a> {
             postings_serializer,
             positions_serializer_opt,
             current_term_info: TermInfo::default(),
+            has_freqs: mode.has_freq(),
             term_open: false,
             num_terms: TermOrdinal::default(),
         })
@@ -167,6 +162,7 @@ impl<'a> FieldSerializer<'a> {
             doc_freq: 0,
             postings_range: addr..addr,
             positions_range: positions_start..positions_start,
+            total_term_freq: if self.has_freqs { Some(0) } else { None },
         }
     }
 
@@ -197,10 +193,16 @@ impl<'a> FieldSerializer<'a> {
     /// For instance, if the positions are `2, 3, 17`,
     /// `position_deltas` is `2, 1, 14`
     ///
+    /// If the field is indexed with payloads, each position delta is followed by the
+    /// payload of the position, e.g. `2, p0, 1, p1, 14, p2`.
+    ///
     /// Term frequencies and positions may be ignored by the serializer depending
     /// on the configuration of the field in the `Schema`.
     pub fn write_doc(&mut self, doc_id: DocId, term_freq: u32, position_deltas: &[u32]) {
         self.current_term_info.doc_freq += 1;
+        if let Some(total_term_freq) = self.current_term_info.total_term_freq.as_mut() {
+            *total_term_freq += u64::from(term_freq);
+        }
         self.postings_serializer.write_doc(doc_id, term_freq);
         if let Some(ref mut positions_serializer) = self.positions_serializer_opt.as_mut() {
             positions_serializer.write_positions_delta(position_deltas);
@@ -303,7 +305,7 @@ pub struct PostingsSerializer<W: Write> {
     mode: IndexRecordOption,
     fieldnorm_reader: Option<FieldNormReader>,
 
-    bm25_weight: Option<Bm25Weight>,
+    bm25_weight: Option<SimilarityWeight>,
 
     num_docs: u32, // Number of docs in the segment
     avg_fieldnorm: Score, // Average number of term in the field for that segment.
@@ -343,7 +345,7 @@ impl<W: Write> PostingsSerializer<W> {
 
     pub fn new_term(&mut self, term_doc_freq: u32) {
         if self.mode.has_freq() && self.num_docs > 0 {
-            let bm25_weight = Bm25Weight::for_one_term(
+            let bm25_weight = SimilarityWeight::for_one_term(
                 term_doc_freq as u64,
                 self.num_docs as u64,
                 self.avg_fieldnorm,
@@ -373,8 +375,13 @@ impl<W: Write> PostingsSerializer<W> {
             if self.mode.has_positions() {
                 // We serialize the sum of term freqs within the skip information
                 // in order to navigate through positions.
-                let sum_freq = self.block.term_freqs().iter().cloned().sum();
-                self.skip_write.write_total_term_freq(sum_freq);
+                // With payloads, each position is followed by its payload.
+                let sum_freq: u32 = self.block.term_freqs().iter().cloned().sum();
+                if self.mode.has_payloads() {
+                    self.skip_write.write_total_term_freq(sum_freq * 2);
+                } else {
+                    self.skip_write.write_total_term_freq(sum_freq);
+                }
             }
             let mut blockwand_params = (0u8, 0u32);
             if let Some(bm25_weight) = self.bm25_weight.as_ref() {
@@ -388,9 +395,9 @@ impl<W: Write> PostingsSerializer<W> {
                             |(left_fieldnorm_id, left_term_freq),
                              (right_fieldnorm_id, right_term_freq)| {
                                 let left_score =
-                                    bm25_weight.tf_factor(*left_fieldnorm_id, *left_term_freq);
+                                    bm25_weight.score(*left_fieldnorm_id, *left_term_freq);
                                 let right_score =
-                                    bm25_weight.tf_factor(*right_fieldnorm_id, *right_term_freq);
+                                    bm25_weight.score(*right_fieldnorm_id, *right_term_freq);
                                 left_score
                                     .partial_cmp(&right_score)
                                     .unwrap_or(Ordering::Equal)
@@ -13,6 +13,13 @@ pub struct TermInfo {
     pub postings_range: Range<usize>,
     /// Byte range of the positions of this terms in the positions (`.pos`) file.
     pub positions_range: Range<usize>,
+    /// Number of occurrences of the term in the documents of the segment.
+    ///
+    /// It is `None` if the field does not record term frequencies, or if the segment
+    /// was written before total term frequencies were recorded. It is not part of the
+    /// fixed size serialization of the `TermInfo`: the term dictionary stores it
+    /// separately.
+    pub total_term_freq: Option<u64>,
 }
 
 impl TermInfo {
@@ -59,6 +66,7 @@ impl BinarySerializable for TermInfo {
             doc_freq,
             postings_range: postings_start_offset..postings_end_offset,
             positions_range: positions_start_offset..positions_end_offset,
+            total_term_freq: None,
         })
     }
 }
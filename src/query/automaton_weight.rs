@@ -3,7 +3,7 @@ use crate::query::ConstScorer;
 use crate::query::{BitSetDocSet, Explanation};
 use crate::query::{Scorer, Weight};
 use crate::schema::{Field, IndexRecordOption};
-use crate::termdict::{TermDictionary, TermStreamer};
+use crate::termdict::{prefix_successor, TermDictionary, TermStreamer};
 use crate::TantivyError;
 use crate::{DocId, Score};
 use common::BitSet;
@@ -11,10 +11,11 @@ use std::io;
 use std::sync::Arc;
 use tantivy_fst::Automaton;
 
-/// A weight struct for Fuzzy Term and Regex Queries
+/// A weight struct for automaton based queries, such as Regex and Wildcard Queries
 pub struct AutomatonWeight<A> {
     field: Field,
     automaton: Arc<A>,
+    prefix: Option<Vec<u8>>,
 }
 
 impl<A> AutomatonWeight<A>
@@ -27,15 +28,30 @@ where
         AutomatonWeight {
             field,
             automaton: automaton.into(),
+            prefix: None,
         }
     }
 
+    /// Restricts the terms visited in the term dictionary to the ones starting with `prefix`.
+    ///
+    /// The automaton is expected to only match terms starting with `prefix`.
+    pub fn with_prefix(mut self, prefix: Vec<u8>) -> AutomatonWeight<A> {
+        self.prefix = Some(prefix);
+        self
+    }
+
     fn automaton_stream<'a>(
         &'a self,
         term_dict: &'a TermDictionary,
     ) -> io::Result<TermStreamer<'a, &'a A>> {
         let automaton: &A = &*self.automaton;
-        let term_stream_builder = term_dict.search(automaton);
+        let mut term_stream_builder = term_dict.search(automaton);
+        if let Some(prefix) = self.prefix.as_ref() {
+            term_stream_builder = term_stream_builder.ge(prefix);
+            if let Some(prefix_successor) = prefix_successor(prefix) {
+                term_stream_builder = term_stream_builder.lt(prefix_successor);
+            }
+        }
         term_stream_builder.into_stream()
     }
 }
@@ -170,4 +186,19 @@ mod tests {
         assert_eq!(scorer.doc(), 0u32);
         assert_eq!(scorer.score(), 1.32);
     }
+
+    #[test]
+    fn test_automaton_weight_prefix() {
+        let index = create_index();
+        let field = index.schema().get_field("title").unwrap();
+        let automaton_weight =
+            AutomatonWeight::new(field, PrefixedByA).with_prefix(b"abcd".to_vec());
+        let reader = index.reader().unwrap();
+        let searcher = reader.searcher();
+        let mut scorer = automaton_weight
+            .scorer(searcher.segment_reader(0u32), 1.0)
+            .unwrap();
+        assert_eq!(scorer.doc(), 2u32);
+        assert_eq!(scorer.advance(), TERMINATED);
+    }
 }
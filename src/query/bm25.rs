@@ -1,8 +1,7 @@
-use crate::fieldnorm::FieldNormReader;
+use crate::query::similarity::MAX_TERM_FREQ;
 use crate::query::Explanation;
+use crate::query::{BuiltinSimilarity, Similarity};
 use crate::Score;
-use crate::Searcher;
-use crate::Term;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -15,129 +14,81 @@ pub(crate) fn idf(doc_freq: u64, doc_count: u64) -> Score {
     (1.0 + x).ln()
 }
 
-fn cached_tf_component(fieldnorm: u32, average_fieldnorm: Score) -> Score {
-    K1 * (1.0 - B + B * fieldnorm as Score / average_fieldnorm)
-}
-
-fn compute_tf_cache(average_fieldnorm: Score) -> [Score; 256] {
-    let mut cache: [Score; 256] = [0.0; 256];
-    for (fieldnorm_id, cache_mut) in cache.iter_mut().enumerate() {
-        let fieldnorm = FieldNormReader::id_to_fieldnorm(fieldnorm_id as u8);
-        *cache_mut = cached_tf_component(fieldnorm, average_fieldnorm);
-    }
-    cache
-}
-
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Bm25Params {
     pub idf: Score,
     pub avg_fieldnorm: Score,
 }
 
-#[derive(Clone)]
-pub struct Bm25Weight {
-    idf_explain: Explanation,
-    weight: Score,
-    cache: [Score; 256],
-    average_fieldnorm: Score,
+/// The BM25 similarity, used by default to score all of the fields.
+///
+/// Its free parameters only affect scoring: they can be changed on an existing index
+/// without reindexing, using [`Index::set_similarity()`](../struct.Index.html#method.set_similarity).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bm25Similarity {
+    /// Term frequency saturation parameter. Defaults to `1.2`.
+    pub k1: Score,
+    /// Length normalization parameter, between `0` and `1`. Defaults to `0.75`.
+    ///
+    /// With `b = 0`, the length of the field is ignored.
+    pub b: Score,
 }
 
-impl Bm25Weight {
-    pub fn boost_by(&self, boost: Score) -> Bm25Weight {
-        Bm25Weight {
-            idf_explain: self.idf_explain.clone(),
-            weight: self.weight * boost,
-            cache: self.cache,
-            average_fieldnorm: self.average_fieldnorm,
-        }
-    }
-
-    pub fn for_terms(searcher: &Searcher, terms: &[Term]) -> crate::Result<Bm25Weight> {
-        assert!(!terms.is_empty(), "Bm25 requires at least one term");
-        let field = terms[0].field();
-        for term in &terms[1..] {
-            assert_eq!(
-                term.field(),
-                field,
-                "All terms must belong to the same field."
-            );
-        }
-
-        let mut total_num_tokens = 0u64;
-        let mut total_num_docs = 0u64;
-        for segment_reader in searcher.segment_readers() {
-            let inverted_index = segment_reader.inverted_index(field)?;
-            total_num_tokens += inverted_index.total_num_tokens();
-            total_num_docs += u64::from(segment_reader.max_doc());
-        }
-        let average_fieldnorm = total_num_tokens as Score / total_num_docs as Score;
-
-        if terms.len() == 1 {
-            let term_doc_freq = searcher.doc_freq(&terms[0])?;
-            Ok(Bm25Weight::for_one_term(
-                term_doc_freq,
-                total_num_docs,
-                average_fieldnorm,
-            ))
-        } else {
-            let mut idf_sum: Score = 0.0;
-            for term in terms {
-                let term_doc_freq = searcher.doc_freq(term)?;
-                idf_sum += idf(term_doc_freq, total_num_docs);
-            }
-            let idf_explain = Explanation::new("idf", idf_sum);
-            Ok(Bm25Weight::new(idf_explain, average_fieldnorm))
-        }
+impl Bm25Similarity {
+    /// Creates a new `Bm25Similarity`.
+    pub fn new(k1: Score, b: Score) -> Bm25Similarity {
+        Bm25Similarity { k1, b }
     }
+}
 
-    pub fn for_one_term(
-        term_doc_freq: u64,
-        total_num_docs: u64,
-        avg_fieldnorm: Score,
-    ) -> Bm25Weight {
-        let idf = idf(term_doc_freq, total_num_docs);
-        let mut idf_explain =
-            Explanation::new("idf, computed as log(1 + (N - n + 0.5) / (n + 0.5))", idf);
-        idf_explain.add_const(
-            "n, number of docs containing this term",
-            term_doc_freq as Score,
-        );
-        idf_explain.add_const("N, total number of docs", total_num_docs as Score);
-        Bm25Weight::new(idf_explain, avg_fieldnorm)
+impl Default for Bm25Similarity {
+    fn default() -> Bm25Similarity {
+        Bm25Similarity { k1: K1, b: B }
     }
+}
 
-    pub(crate) fn new(idf_explain: Explanation, average_fieldnorm: Score) -> Bm25Weight {
-        let weight = idf_explain.value() * (1.0 + K1);
-        Bm25Weight {
-            idf_explain,
-            weight,
-            cache: compute_tf_cache(average_fieldnorm),
-            average_fieldnorm,
+impl Similarity for Bm25Similarity {
+    fn term_weight(&self, term_doc_freqs: &[u64], total_num_docs: u64) -> Explanation {
+        if let [term_doc_freq] = *term_doc_freqs {
+            let idf = idf(term_doc_freq, total_num_docs);
+            let mut idf_explain =
+                Explanation::new("idf, computed as log(1 + (N - n + 0.5) / (n + 0.5))", idf);
+            idf_explain.add_const(
+                "n, number of docs containing this term",
+                term_doc_freq as Score,
+            );
+            idf_explain.add_const("N, total number of docs", total_num_docs as Score);
+            idf_explain
+        } else {
+            let idf_sum: Score = term_doc_freqs
+                .iter()
+                .map(|&term_doc_freq| idf(term_doc_freq, total_num_docs))
+                .sum();
+            Explanation::new("idf", idf_sum)
         }
     }
 
-    #[inline]
-    pub fn score(&self, fieldnorm_id: u8, term_freq: u32) -> Score {
-        self.weight * self.tf_factor(fieldnorm_id, term_freq)
-    }
-
-    pub fn max_score(&self) -> Score {
-        self.score(255u8, 2_013_265_944)
+    fn doc_normalization(&self, fieldnorm: u32, average_fieldnorm: Score) -> Score {
+        self.k1 * (1.0 - self.b + self.b * fieldnorm as Score / average_fieldnorm)
     }
 
     #[inline]
-    pub(crate) fn tf_factor(&self, fieldnorm_id: u8, term_freq: u32) -> Score {
+    fn score(&self, term_weight: Score, term_freq: u32, doc_normalization: Score) -> Score {
         let term_freq = term_freq as Score;
-        let norm = self.cache[fieldnorm_id as usize];
-        term_freq / (term_freq + norm)
+        term_weight * (self.k1 + 1.0) * (term_freq / (term_freq + doc_normalization))
     }
 
-    pub fn explain(&self, fieldnorm_id: u8, term_freq: u32) -> Explanation {
+    fn explain(
+        &self,
+        term_weight: &Explanation,
+        term_freq: u32,
+        fieldnorm: u32,
+        average_fieldnorm: Score,
+    ) -> Explanation {
         // The explain format is directly copied from Lucene's.
         // (So, Kudos to Lucene)
-        let score = self.score(fieldnorm_id, term_freq);
-
-        let norm = self.cache[fieldnorm_id as usize];
+        let norm = self.doc_normalization(fieldnorm, average_fieldnorm);
+        let score = self.score(term_weight.value(), term_freq, norm);
         let term_freq = term_freq as Score;
         let right_factor = term_freq / (term_freq + norm);
 
@@ -147,20 +98,25 @@ impl Bm25Weight {
         );
 
         tf_explanation.add_const("freq, occurrences of term within document", term_freq);
-        tf_explanation.add_const("k1, term saturation parameter", K1);
-        tf_explanation.add_const("b, length normalization parameter", B);
-        tf_explanation.add_const(
-            "dl, length of field",
-            FieldNormReader::id_to_fieldnorm(fieldnorm_id) as Score,
-        );
-        tf_explanation.add_const("avgdl, average length of field", self.average_fieldnorm);
+        tf_explanation.add_const("k1, term saturation parameter", self.k1);
+        tf_explanation.add_const("b, length normalization parameter", self.b);
+        tf_explanation.add_const("dl, length of field", fieldnorm as Score);
+        tf_explanation.add_const("avgdl, average length of field", average_fieldnorm);
 
         let mut explanation = Explanation::new("TermQuery, product of...", score);
-        explanation.add_detail(Explanation::new("(K1+1)", K1 + 1.0));
-        explanation.add_detail(self.idf_explain.clone());
+        explanation.add_detail(Explanation::new("(K1+1)", self.k1 + 1.0));
+        explanation.add_detail(term_weight.clone());
         explanation.add_detail(tf_explanation);
         explanation
     }
+
+    fn max_score(&self, term_weight: Score, doc_normalizations: &[Score; 256]) -> Score {
+        self.score(term_weight, MAX_TERM_FREQ, doc_normalizations[255])
+    }
+
+    fn as_builtin(&self) -> Option<BuiltinSimilarity> {
+        Some((*self).into())
+    }
 }
 
 #[cfg(test)]
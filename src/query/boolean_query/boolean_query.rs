@@ -1,12 +1,14 @@
 use super::boolean_weight::BooleanWeight;
+use crate::query::filter_cache::{query_cache_key, CachedFilterWeight};
+use crate::query::query_visitor::rewrite_queries;
 use crate::query::Occur;
 use crate::query::Query;
 use crate::query::TermQuery;
 use crate::query::Weight;
+use crate::query::{QueryRewriter, QueryVisitor};
 use crate::schema::IndexRecordOption;
 use crate::schema::Term;
 use crate::Searcher;
-use std::collections::BTreeMap;
 
 /// The boolean query returns a set of documents
 /// that matches the Boolean combination of constituent subqueries.
@@ -14,12 +16,15 @@ use std::collections::BTreeMap;
 /// The documents matched by the boolean query are
 /// those which
 /// * match all of the sub queries associated with the
-/// `Must` occurence
+/// `Must` or `Filter` occurences
 /// * match none of the sub queries associated with the
 /// `MustNot` occurence.
 /// * match at least one of the subqueries that is not
 /// a `MustNot` occurence.
 ///
+/// The sub queries associated with the `Filter` occurence are not scored, and do not
+/// contribute to the score of the documents.
+///
 ///
 /// You can combine other query types and their `Occur`ances into one `BooleanQuery`
 ///
@@ -129,15 +134,20 @@ use std::collections::BTreeMap;
 #[derive(Debug)]
 pub struct BooleanQuery {
     subqueries: Vec<(Occur, Box<dyn Query>)>,
+    minimum_number_should_match: usize,
 }
 
 impl Clone for BooleanQuery {
     fn clone(&self) -> Self {
-        self.subqueries
+        let subqueries = self
+            .subqueries
             .iter()
             .map(|(occur, subquery)| (*occur, subquery.box_clone()))
-            .collect::<Vec<_>>()
-            .into()
+            .collect::<Vec<_>>();
+        BooleanQuery {
+            subqueries,
+            minimum_number_should_match: self.minimum_number_should_match,
+        }
     }
 }
 
@@ -153,23 +163,102 @@ impl Query for BooleanQuery {
             .subqueries
             .iter()
             .map(|&(ref occur, ref subquery)| {
-                Ok((*occur, subquery.weight(searcher, scoring_enabled)?))
+                // Filter clauses never contribute to the score.
+                let subquery_scoring_enabled = scoring_enabled && *occur != Occur::Filter;
+                let weight = subquery.weight(searcher, subquery_scoring_enabled)?;
+                // The documents of the clauses that do not contribute to the score may be
+                // read from the filter cache.
+                if let (Occur::Filter | Occur::MustNot, Some(filter_cache), Some(query_key)) =
+                    (*occur, searcher.filter_cache(), subquery.cache_key())
+                {
+                    let weight = CachedFilterWeight::new(weight, query_key, filter_cache.clone());
+                    return Ok((*occur, Box::new(weight) as Box<dyn Weight>));
+                }
+                Ok((*occur, weight))
             })
             .collect::<crate::Result<_>>()?;
-        Ok(Box::new(BooleanWeight::new(sub_weights, scoring_enabled)))
+        Ok(Box::new(BooleanWeight::new(
+            sub_weights,
+            self.minimum_number_should_match,
+            scoring_enabled,
+        )))
     }
 
-    fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
-        for (_occur, subquery) in &self.subqueries {
-            subquery.query_terms(terms);
+    fn visit(&self, visitor: &mut dyn QueryVisitor) {
+        if visitor.visit_boolean_query(self) {
+            for (_occur, subquery) in &self.subqueries {
+                subquery.visit(visitor);
+            }
         }
     }
+
+    fn rewrite_subqueries(&self, rewriter: &mut dyn QueryRewriter) -> Option<Box<dyn Query>> {
+        let subqueries = rewrite_queries(
+            self.subqueries
+                .iter()
+                .map(|(_occur, subquery)| subquery.as_ref()),
+            rewriter,
+        )?;
+        let subqueries = self
+            .subqueries
+            .iter()
+            .map(|(occur, _)| *occur)
+            .zip(subqueries)
+            .collect();
+        let boolean_query = BooleanQuery::new(subqueries)
+            .with_minimum_required_clauses(self.minimum_number_should_match);
+        Some(Box::new(boolean_query))
+    }
+
+    fn cache_key(&self) -> Option<Vec<u8>> {
+        let clause_keys = self
+            .subqueries
+            .iter()
+            .map(|(occur, subquery)| {
+                let occur_code: u8 = match occur {
+                    Occur::Should => 0,
+                    Occur::Must => 1,
+                    Occur::MustNot => 2,
+                    Occur::Filter => 3,
+                };
+                Some((occur_code, subquery.cache_key()?))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(query_cache_key(
+            "boolean",
+            &(clause_keys, self.minimum_number_should_match as u64),
+        ))
+    }
 }
 
 impl BooleanQuery {
     /// Creates a new boolean query.
     pub fn new(subqueries: Vec<(Occur, Box<dyn Query>)>) -> BooleanQuery {
-        BooleanQuery { subqueries }
+        BooleanQuery {
+            subqueries,
+            minimum_number_should_match: 0,
+        }
+    }
+
+    /// Requires documents to match at least `minimum_number_should_match`
+    /// of the `Should` clauses.
+    ///
+    /// The score of a document is still the sum of the scores of the clauses it matches.
+    ///
+    /// - `0`, the default, means that `Should` clauses are only required
+    ///   if there are no `Must` clauses.
+    /// - If it exceeds the number of `Should` clauses, the query matches no document.
+    pub fn with_minimum_required_clauses(
+        mut self,
+        minimum_number_should_match: usize,
+    ) -> BooleanQuery {
+        self.minimum_number_should_match = minimum_number_should_match;
+        self
+    }
+
+    /// Returns the minimum number of `Should` clauses a document must match.
+    pub fn minimum_number_should_match(&self) -> usize {
+        self.minimum_number_should_match
     }
 
     /// Returns the intersection of the queries.
@@ -4,6 +4,8 @@ use crate::query::explanation::does_not_match;
 use crate::query::score_combiner::{DoNothingCombiner, ScoreCombiner, SumWithCoordsCombiner};
 use crate::query::term_query::TermScorer;
 use crate::query::weight::{for_each_pruning_scorer, for_each_scorer};
+use crate::query::ConstScorer;
+use crate::query::Disjunction;
 use crate::query::EmptyScorer;
 use crate::query::Exclude;
 use crate::query::Occur;
@@ -64,17 +66,30 @@ fn into_box_scorer<TScoreCombiner: ScoreCombiner>(scorer: SpecializedScorer) ->
 
 pub struct BooleanWeight {
     weights: Vec<(Occur, Box<dyn Weight>)>,
+    minimum_number_should_match: usize,
     scoring_enabled: bool,
 }
 
 impl BooleanWeight {
-    pub fn new(weights: Vec<(Occur, Box<dyn Weight>)>, scoring_enabled: bool) -> BooleanWeight {
+    pub fn new(
+        weights: Vec<(Occur, Box<dyn Weight>)>,
+        minimum_number_should_match: usize,
+        scoring_enabled: bool,
+    ) -> BooleanWeight {
         BooleanWeight {
             weights,
+            minimum_number_should_match,
             scoring_enabled,
         }
     }
 
+    fn num_should_clauses(&self) -> usize {
+        self.weights
+            .iter()
+            .filter(|(occur, _)| *occur == Occur::Should)
+            .count()
+    }
+
     fn per_occur_scorers(
         &self,
         reader: &SegmentReader,
@@ -96,24 +111,50 @@ impl BooleanWeight {
         reader: &SegmentReader,
         boost: Score,
     ) -> crate::Result<SpecializedScorer> {
+        if self.minimum_number_should_match > self.num_should_clauses() {
+            return Ok(SpecializedScorer::Other(Box::new(EmptyScorer)));
+        }
         let mut per_occur_scorers = self.per_occur_scorers(reader, boost)?;
 
+        let minimum_number_should_match = self.minimum_number_should_match;
         let should_scorer_opt: Option<SpecializedScorer> = per_occur_scorers
             .remove(&Occur::Should)
-            .map(scorer_union::<TScoreCombiner>);
+            .map(|should_scorers| {
+                if minimum_number_should_match > 1 {
+                    SpecializedScorer::Other(Box::new(Disjunction::<_, TScoreCombiner>::new(
+                        should_scorers,
+                        minimum_number_should_match,
+                    )))
+                } else {
+                    scorer_union::<TScoreCombiner>(should_scorers)
+                }
+            });
 
         let exclude_scorer_opt: Option<Box<dyn Scorer>> = per_occur_scorers
             .remove(&Occur::MustNot)
             .map(scorer_union::<DoNothingCombiner>)
             .map(into_box_scorer::<DoNothingCombiner>);
 
-        let must_scorer_opt: Option<Box<dyn Scorer>> = per_occur_scorers
-            .remove(&Occur::Must)
-            .map(intersect_scorers);
+        // Filter clauses are intersected together with the must clauses, so that
+        // the cheapest of all of them drives the iteration.
+        let mut must_scorers = per_occur_scorers.remove(&Occur::Must).unwrap_or_default();
+        if let Some(filter_scorers) = per_occur_scorers.remove(&Occur::Filter) {
+            must_scorers.extend(filter_scorers.into_iter().map(non_scoring));
+        }
+        let must_scorer_opt: Option<Box<dyn Scorer>> = if must_scorers.is_empty() {
+            None
+        } else {
+            Some(intersect_scorers(must_scorers))
+        };
 
         let positive_scorer: SpecializedScorer = match (should_scorer_opt, must_scorer_opt) {
             (Some(should_scorer), Some(must_scorer)) => {
-                if self.scoring_enabled {
+                if minimum_number_should_match > 0 {
+                    SpecializedScorer::Other(intersect_scorers(vec![
+                        must_scorer,
+                        into_box_scorer::<TScoreCombiner>(should_scorer),
+                    ]))
+                } else if self.scoring_enabled {
                     SpecializedScorer::Other(Box::new(RequiredOptionalScorer::<
                         Box<dyn Scorer>,
                         Box<dyn Scorer>,
@@ -148,12 +189,14 @@ impl BooleanWeight {
 
 impl Weight for BooleanWeight {
     fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
-        if self.weights.is_empty() {
+        if self.weights.is_empty() || self.minimum_number_should_match > self.num_should_clauses() {
             Ok(Box::new(EmptyScorer))
         } else if self.weights.len() == 1 {
             let &(occur, ref weight) = &self.weights[0];
             if occur == Occur::MustNot {
                 Ok(Box::new(EmptyScorer))
+            } else if occur == Occur::Filter {
+                Ok(non_scoring(weight.scorer(reader, boost)?))
             } else {
                 weight.scorer(reader, boost)
             }
@@ -179,10 +222,18 @@ impl Weight for BooleanWeight {
 
         let mut explanation = Explanation::new("BooleanClause. Sum of ...", scorer.score());
         for &(ref occur, ref subweight) in &self.weights {
-            if is_positive_occur(*occur) {
-                if let Ok(child_explanation) = subweight.explain(reader, doc) {
-                    explanation.add_detail(child_explanation);
+            match *occur {
+                Occur::Must | Occur::Should => {
+                    if let Ok(child_explanation) = subweight.explain(reader, doc) {
+                        explanation.add_detail(child_explanation);
+                    }
+                }
+                Occur::Filter => {
+                    if subweight.scorer(reader, 1.0)?.seek(doc) == doc {
+                        explanation.add_detail(Explanation::new("Filter (non-scoring)", 0.0));
+                    }
                 }
+                Occur::MustNot => {}
             }
         }
         Ok(explanation)
@@ -236,9 +287,7 @@ impl Weight for BooleanWeight {
     }
 }
 
-fn is_positive_occur(occur: Occur) -> bool {
-    match occur {
-        Occur::Must | Occur::Should => true,
-        Occur::MustNot => false,
-    }
+/// Wraps the scorer of a filter clause, so that it does not contribute to the score.
+fn non_scoring(scorer: Box<dyn Scorer>) -> Box<dyn Scorer> {
+    Box::new(ConstScorer::new(scorer, 0.0))
 }
@@ -313,4 +313,248 @@ mod tests {
         assert_nearly_equals!(explanation.value(), std::f32::consts::LN_2);
         Ok(())
     }
+
+    #[test]
+    pub fn test_boolean_minimum_required_clauses() -> crate::Result<()> {
+        let (index, text_field) = aux_test_helper();
+        let searcher = index.reader()?.searcher();
+        let term_query = |text: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::WithFreqs,
+            ))
+        };
+        let should_query = |minimum_number_should_match: usize| {
+            BooleanQuery::union(vec![
+                term_query("a"),
+                term_query("b"),
+                term_query("c"),
+                term_query("d"),
+            ])
+            .with_minimum_required_clauses(minimum_number_should_match)
+        };
+        let top_docs = |query: &dyn Query| -> crate::Result<Vec<(DocId, Score)>> {
+            let mut top_docs: Vec<(DocId, Score)> = searcher
+                .search(query, &TopDocs::with_limit(10))?
+                .into_iter()
+                .map(|(score, doc_address)| (doc_address.doc_id, score))
+                .collect();
+            top_docs.sort_by_key(|(doc, _)| *doc);
+            Ok(top_docs)
+        };
+        let union_top_docs = top_docs(&should_query(0))?;
+        assert_eq!(union_top_docs.len(), 5);
+        assert_eq!(top_docs(&should_query(1))?, union_top_docs);
+        for &(minimum_number_should_match, ref expected_docs) in &[
+            (2, vec![0, 1, 2, 3]),
+            (3, vec![0, 3]),
+            (4, vec![3]),
+            (5, vec![]),
+        ] {
+            let query = should_query(minimum_number_should_match);
+            let docs_and_scores = top_docs(&query)?;
+            let docs: Vec<DocId> = docs_and_scores.iter().map(|(doc, _)| *doc).collect();
+            assert_eq!(&docs, expected_docs);
+            assert_eq!(query.count(&searcher)?, expected_docs.len());
+            // Only the matching clauses are summed.
+            for (doc, score) in docs_and_scores {
+                assert_nearly_equals!(score, union_top_docs[doc as usize].1);
+            }
+        }
+        {
+            let query = BooleanQuery::new(vec![
+                (Occur::Must, term_query("d")),
+                (Occur::Should, term_query("a")),
+                (Occur::Should, term_query("b")),
+            ]);
+            assert_eq!(query.count(&searcher)?, 2);
+            assert_eq!(query.with_minimum_required_clauses(1).count(&searcher)?, 1);
+        }
+        {
+            let single_should = BooleanQuery::union(vec![term_query("a")]);
+            assert_eq!(
+                single_should
+                    .clone()
+                    .with_minimum_required_clauses(1)
+                    .count(&searcher)?,
+                3
+            );
+            assert_eq!(
+                single_should
+                    .with_minimum_required_clauses(2)
+                    .count(&searcher)?,
+                0
+            );
+            let single_must = BooleanQuery::intersection(vec![term_query("a")]);
+            assert_eq!(
+                single_must
+                    .with_minimum_required_clauses(1)
+                    .count(&searcher)?,
+                0
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_boolean_filter_clause() -> crate::Result<()> {
+        let (index, text_field) = aux_test_helper();
+        let searcher = index.reader()?.searcher();
+        let term_query = |text: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::WithFreqs,
+            ))
+        };
+        let top_docs = |query: &dyn Query| -> crate::Result<Vec<(DocId, Score)>> {
+            let mut top_docs: Vec<(DocId, Score)> = searcher
+                .search(query, &TopDocs::with_limit(10))?
+                .into_iter()
+                .map(|(score, doc_address)| (doc_address.doc_id, score))
+                .collect();
+            top_docs.sort_by_key(|(doc, _)| *doc);
+            Ok(top_docs)
+        };
+        let must_query = BooleanQuery::new(vec![
+            (Occur::Must, term_query("c")),
+            (Occur::Must, term_query("a")),
+        ]);
+        let filter_query = BooleanQuery::new(vec![
+            (Occur::Must, term_query("c")),
+            (Occur::Filter, term_query("a")),
+        ]);
+        let must_top_docs = top_docs(&must_query)?;
+        let filter_top_docs = top_docs(&filter_query)?;
+        let c_top_docs = top_docs(term_query("c").as_ref())?;
+        let docs = |top_docs: &[(DocId, Score)]| -> Vec<DocId> {
+            top_docs.iter().map(|(doc, _)| *doc).collect()
+        };
+        assert_eq!(docs(&filter_top_docs), vec![0, 1, 3]);
+        assert_eq!(docs(&filter_top_docs), docs(&must_top_docs));
+        assert_eq!(filter_query.count(&searcher)?, 3);
+        for (&(doc, filter_score), &(_, must_score)) in
+            filter_top_docs.iter().zip(must_top_docs.iter())
+        {
+            // Only the `Must` clause is scored.
+            assert!(filter_score < must_score);
+            let c_score = c_top_docs
+                .iter()
+                .find(|(c_doc, _)| *c_doc == doc)
+                .unwrap()
+                .1;
+            assert_nearly_equals!(filter_score, c_score);
+        }
+        {
+            let explanation = filter_query.explain(&searcher, DocAddress::new(0, 1))?;
+            assert_nearly_equals!(explanation.value(), filter_top_docs[1].1);
+            let explanation_json = explanation.to_pretty_json();
+            assert!(explanation_json.contains("Filter (non-scoring)"));
+        }
+        {
+            let filter_only_query = BooleanQuery::new(vec![(Occur::Filter, term_query("d"))]);
+            assert_eq!(top_docs(&filter_only_query)?, vec![(3, 0.0), (4, 0.0)]);
+            let filter_should_query = BooleanQuery::new(vec![
+                (Occur::Filter, term_query("d")),
+                (Occur::Should, term_query("a")),
+            ]);
+            let filter_should_top_docs = top_docs(&filter_should_query)?;
+            assert_eq!(docs(&filter_should_top_docs), vec![3, 4]);
+            assert!(filter_should_top_docs[0].1 > 0.0);
+            assert_nearly_equals!(filter_should_top_docs[1].1, 0.0);
+        }
+        Ok(())
+    }
+
+    /// Builds an index of two segments, whose documents are made of words with
+    /// skewed frequencies, with some deleted documents.
+    pub(crate) fn skewed_text_index(num_docs_per_segment: usize) -> crate::Result<(Index, Field)> {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut index_writer = index.writer_for_tests()?;
+        for _ in 0..2 {
+            for _ in 0..num_docs_per_segment {
+                let num_words = rng.gen_range(1..30);
+                let words: Vec<String> = (0..num_words)
+                    .map(|_| {
+                        // the frequency of the word `wN` is ~ 1 / (N + 1).
+                        let word_id = (1.0f64 / rng.gen_range(0.01..1.0)) as usize - 1;
+                        format!("w{}", word_id)
+                    })
+                    .collect();
+                index_writer.add_document(doc!(text_field => words.join(" ")));
+            }
+            index_writer.commit()?;
+        }
+        index_writer.delete_term(Term::from_field_text(text_field, "w40"));
+        index_writer.commit()?;
+        Ok((index, text_field))
+    }
+
+    #[test]
+    pub fn test_boolean_disjunction_top_k_matches_exhaustive() -> crate::Result<()> {
+        let (index, text_field) = skewed_text_index(3_000)?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let query_parser = QueryParser::for_index(&index, vec![text_field]);
+        for query_str in ["w0 w1", "w1 w5 w30", "w0 w2 w8 w60", "w3 w12 w99"] {
+            let query = query_parser.parse_query(query_str)?;
+            let exhaustive = searcher.search(&query, &TEST_COLLECTOR_WITH_SCORE)?;
+            let mut exhaustive_scores: Vec<(Score, DocAddress)> = exhaustive
+                .scores()
+                .iter()
+                .cloned()
+                .zip(exhaustive.docs().iter().cloned())
+                .collect();
+            exhaustive_scores.sort_by(|left, right| right.0.partial_cmp(&left.0).unwrap());
+            for limit in [1, 10, 100] {
+                // `TopDocs` prunes the non-competitive documents with block-max WAND.
+                let top_docs = searcher.search(&query, &TopDocs::with_limit(limit))?;
+                assert_eq!(top_docs.len(), limit.min(exhaustive_scores.len()));
+                for ((score, doc), (exhaustive_score, _)) in top_docs.iter().zip(&exhaustive_scores)
+                {
+                    assert_nearly_equals!(*score, *exhaustive_score);
+                    let doc_pos = exhaustive.docs().iter().position(|d| d == doc).unwrap();
+                    assert_nearly_equals!(*score, exhaustive.scores()[doc_pos]);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "unstable"))]
+mod bench {
+    use super::tests::skewed_text_index;
+    use crate::collector::{Count, TopDocs};
+    use crate::query::QueryParser;
+    use test::Bencher;
+
+    #[bench]
+    fn bench_boolean_disjunction_top_10_pruning(b: &mut Bencher) {
+        let (index, text_field) = skewed_text_index(100_000).unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let query = QueryParser::for_index(&index, vec![text_field])
+            .parse_query("w0 w2 w8 w60")
+            .unwrap();
+        b.iter(|| searcher.search(&query, &TopDocs::with_limit(10)).unwrap());
+    }
+
+    #[bench]
+    fn bench_boolean_disjunction_top_10_exhaustive(b: &mut Bencher) {
+        let (index, text_field) = skewed_text_index(100_000).unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let query = QueryParser::for_index(&index, vec![text_field])
+            .parse_query("w0 w2 w8 w60")
+            .unwrap();
+        // counting the documents requires visiting all of them, which prevents the pruning.
+        b.iter(|| {
+            searcher
+                .search(&query, &(TopDocs::with_limit(10), Count))
+                .unwrap()
+        });
+    }
 }
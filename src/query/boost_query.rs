@@ -1,8 +1,9 @@
 use crate::fastfield::DeleteBitSet;
 use crate::query::explanation::does_not_match;
-use crate::query::{Explanation, Query, Scorer, Weight};
-use crate::{DocId, DocSet, Score, Searcher, SegmentReader, Term};
-use std::collections::BTreeMap;
+use crate::query::{
+    rewrite_query, Explanation, Query, QueryRewriter, QueryVisitor, Scorer, Weight,
+};
+use crate::{DocId, DocSet, Score, Searcher, SegmentReader};
 use std::fmt;
 
 /// `BoostQuery` is a wrapper over a query used to boost its score.
@@ -20,6 +21,16 @@ impl BoostQuery {
     pub fn new(query: Box<dyn Query>, boost: Score) -> BoostQuery {
         BoostQuery { query, boost }
     }
+
+    /// The underlying query.
+    pub fn query(&self) -> &dyn Query {
+        self.query.as_ref()
+    }
+
+    /// The boost factor.
+    pub fn boost(&self) -> Score {
+        self.boost
+    }
 }
 
 impl Clone for BoostQuery {
@@ -48,8 +59,20 @@ impl Query for BoostQuery {
         Ok(boosted_weight)
     }
 
-    fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
-        self.query.query_terms(terms)
+    fn visit(&self, visitor: &mut dyn QueryVisitor) {
+        if visitor.visit_boost_query(self) {
+            self.query.visit(visitor);
+        }
+    }
+
+    fn rewrite_subqueries(&self, rewriter: &mut dyn QueryRewriter) -> Option<Box<dyn Query>> {
+        let query = rewrite_query(self.query.as_ref(), rewriter)?;
+        Some(Box::new(BoostQuery::new(query, self.boost)))
+    }
+
+    fn cache_key(&self) -> Option<Vec<u8>> {
+        // the score does not change the matched documents.
+        self.query.cache_key()
     }
 }
 
@@ -76,6 +99,7 @@ impl Weight for BoostWeight {
         }
         let mut explanation =
             Explanation::new(format!("Boost x{} of ...", self.boost), scorer.score());
+        explanation.add_const("boost", self.boost);
         let underlying_explanation = self.weight.explain(reader, doc)?;
         explanation.add_detail(underlying_explanation);
         Ok(explanation)
@@ -136,9 +160,10 @@ impl<S: Scorer> Scorer for BoostScorer<S> {
 #[cfg(test)]
 mod tests {
     use super::BoostQuery;
-    use crate::query::{AllQuery, Query};
-    use crate::schema::Schema;
-    use crate::{DocAddress, Document, Index};
+    use crate::collector::TopDocs;
+    use crate::query::{AllQuery, BooleanQuery, Occur, Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{assert_nearly_equals, DocAddress, Document, Index, Term};
 
     #[test]
     fn test_boost_query_explain() {
@@ -153,7 +178,37 @@ mod tests {
         let explanation = query.explain(&searcher, DocAddress::new(0, 0u32)).unwrap();
         assert_eq!(
             explanation.to_pretty_json(),
-            "{\n  \"value\": 0.2,\n  \"description\": \"Boost x0.2 of ...\",\n  \"details\": [\n    {\n      \"value\": 1.0,\n      \"description\": \"AllQuery\",\n      \"context\": []\n    }\n  ],\n  \"context\": []\n}"
+            "{\n  \"value\": 0.2,\n  \"description\": \"Boost x0.2 of ...\",\n  \"details\": [\n    {\n      \"value\": 0.2,\n      \"description\": \"boost\",\n      \"context\": []\n    },\n    {\n      \"value\": 1.0,\n      \"description\": \"AllQuery\",\n      \"context\": []\n    }\n  ],\n  \"context\": []\n}"
         )
     }
+
+    #[test]
+    fn test_boost_query_composition() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field=>"a b"));
+        index_writer.add_document(doc!(text_field=>"a c"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term_query: Box<dyn Query> = Box::new(TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::WithFreqs,
+        ));
+        let top_score = |query: &dyn Query| -> crate::Result<f32> {
+            let top_docs = searcher.search(query, &TopDocs::with_limit(1))?;
+            Ok(top_docs[0].0)
+        };
+        let score = top_score(term_query.as_ref())?;
+        let boost_of_boost =
+            BoostQuery::new(Box::new(BoostQuery::new(term_query.box_clone(), 2.0)), 3.0);
+        assert_nearly_equals!(top_score(&boost_of_boost)?, score * 6.0);
+        let boolean_query = BooleanQuery::new(vec![(
+            Occur::Must,
+            Box::new(boost_of_boost) as Box<dyn Query>,
+        )]);
+        assert_nearly_equals!(top_score(&boolean_query)?, score * 6.0);
+        Ok(())
+    }
 }
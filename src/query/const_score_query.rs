@@ -0,0 +1,167 @@
+use crate::query::explanation::does_not_match;
+use crate::query::{rewrite_query, ConstScorer, Explanation, Query, QueryRewriter, QueryVisitor};
+use crate::query::{Scorer, Weight};
+use crate::{DocId, DocSet, Score, Searcher, SegmentReader};
+use std::fmt;
+
+/// `ConstScoreQuery` is a wrapper over a query that gives the same score to all of
+/// the documents it matches.
+///
+/// The document set matched by the `ConstScoreQuery` is strictly the same as the underlying
+/// query. The score of the underlying query is ignored, and is not even computed: every
+/// document gets the score `score`.
+pub struct ConstScoreQuery {
+    query: Box<dyn Query>,
+    score: Score,
+}
+
+impl ConstScoreQuery {
+    /// Builds a const score query.
+    pub fn new(query: Box<dyn Query>, score: Score) -> ConstScoreQuery {
+        ConstScoreQuery { query, score }
+    }
+
+    /// Returns the underlying query.
+    pub fn query(&self) -> &dyn Query {
+        self.query.as_ref()
+    }
+
+    /// Returns the score given to the matched documents.
+    pub fn score(&self) -> Score {
+        self.score
+    }
+}
+
+impl Clone for ConstScoreQuery {
+    fn clone(&self) -> Self {
+        ConstScoreQuery {
+            query: self.query.box_clone(),
+            score: self.score,
+        }
+    }
+}
+
+impl fmt::Debug for ConstScoreQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ConstScore(query={:?}, score={})",
+            self.query, self.score
+        )
+    }
+}
+
+impl Query for ConstScoreQuery {
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> crate::Result<Box<dyn Weight>> {
+        let inner_weight = self.query.weight(searcher, false)?;
+        if scoring_enabled {
+            Ok(Box::new(ConstScoreWeight::new(inner_weight, self.score)))
+        } else {
+            Ok(inner_weight)
+        }
+    }
+
+    fn visit(&self, visitor: &mut dyn QueryVisitor) {
+        if visitor.visit_const_score_query(self) {
+            self.query.visit(visitor);
+        }
+    }
+
+    fn rewrite_subqueries(&self, rewriter: &mut dyn QueryRewriter) -> Option<Box<dyn Query>> {
+        let query = rewrite_query(self.query.as_ref(), rewriter)?;
+        Some(Box::new(ConstScoreQuery::new(query, self.score)))
+    }
+
+    fn cache_key(&self) -> Option<Vec<u8>> {
+        // the score does not change the matched documents.
+        self.query.cache_key()
+    }
+}
+
+struct ConstScoreWeight {
+    weight: Box<dyn Weight>,
+    score: Score,
+}
+
+impl ConstScoreWeight {
+    fn new(weight: Box<dyn Weight>, score: Score) -> Self {
+        ConstScoreWeight { weight, score }
+    }
+}
+
+impl Weight for ConstScoreWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let inner_scorer = self.weight.scorer(reader, 1.0)?;
+        Ok(Box::new(ConstScorer::new(inner_scorer, boost * self.score)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        let mut explanation = Explanation::new("ConstScore", scorer.score());
+        let underlying_explanation = self.weight.explain(reader, doc)?;
+        explanation.add_detail(underlying_explanation);
+        Ok(explanation)
+    }
+
+    fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
+        self.weight.count(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConstScoreQuery;
+    use crate::collector::TopDocs;
+    use crate::query::{BooleanQuery, BoostQuery, Occur, Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{assert_nearly_equals, DocAddress, Index, Term};
+
+    #[test]
+    fn test_const_score_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field=>"a b"));
+        index_writer.add_document(doc!(text_field=>"a a a c"));
+        index_writer.add_document(doc!(text_field=>"c"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term_query = |text: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::WithFreqs,
+            ))
+        };
+        let query = ConstScoreQuery::new(term_query("a"), 0.3);
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(3))?;
+        assert_eq!(top_docs.len(), 2);
+        for (score, _) in top_docs {
+            assert_nearly_equals!(score, 0.3);
+        }
+        assert_eq!(query.count(&searcher)?, 2);
+        let explanation = query.explain(&searcher, DocAddress::new(0, 1))?;
+        assert_nearly_equals!(explanation.value(), 0.3);
+        assert!(query.explain(&searcher, DocAddress::new(0, 2)).is_err());
+
+        // Boost of a const score, as a clause of a boolean query.
+        let boolean_query = BooleanQuery::new(vec![
+            (
+                Occur::Must,
+                Box::new(BoostQuery::new(Box::new(query), 2.0)) as Box<dyn Query>,
+            ),
+            (
+                Occur::Must,
+                Box::new(ConstScoreQuery::new(term_query("c"), 0.1)),
+            ),
+        ]);
+        let top_docs = searcher.search(&boolean_query, &TopDocs::with_limit(3))?;
+        assert_eq!(top_docs.len(), 1);
+        assert_eq!(top_docs[0].1, DocAddress::new(0, 1));
+        assert_nearly_equals!(top_docs[0].0, 0.7);
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use crate::docset::{DocSet, TERMINATED};
+use crate::query::score_combiner::ScoreCombiner;
+use crate::query::Scorer;
+use crate::{DocId, Score};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Creates a `DocSet` that iterates through the documents matched by
+/// at least `minimum_matches_required` of its `DocSet`s.
+///
+/// The score of a document is the combination of the scores of the
+/// matching `DocSet`s only.
+///
+/// Unlike `Union`, documents matching too few `DocSet`s are skipped
+/// without ever being scored.
+pub struct Disjunction<TScorer, TScoreCombiner> {
+    scorers: Vec<TScorer>,
+    /// `(doc, scorer_ord)` for the scorers that are ahead of the current doc.
+    heap: BinaryHeap<Reverse<(DocId, usize)>>,
+    /// Ordinals of the scorers positioned on the current doc.
+    current: Vec<usize>,
+    minimum_matches_required: usize,
+    score_combiner: TScoreCombiner,
+    doc: DocId,
+}
+
+impl<TScorer: Scorer, TScoreCombiner: ScoreCombiner> Disjunction<TScorer, TScoreCombiner> {
+    /// Creates a new `Disjunction`.
+    ///
+    /// `minimum_matches_required` is expected to be at least `1`.
+    pub fn new(
+        scorers: Vec<TScorer>,
+        minimum_matches_required: usize,
+    ) -> Disjunction<TScorer, TScoreCombiner> {
+        Disjunction::with_score_combiner(
+            scorers,
+            minimum_matches_required,
+            TScoreCombiner::default(),
+        )
+    }
+
+    /// Creates a new `Disjunction`, combining the scores with `score_combiner`.
+    pub fn with_score_combiner(
+        scorers: Vec<TScorer>,
+        minimum_matches_required: usize,
+        score_combiner: TScoreCombiner,
+    ) -> Disjunction<TScorer, TScoreCombiner> {
+        let heap = scorers
+            .iter()
+            .enumerate()
+            .filter(|(_, scorer)| scorer.doc() != TERMINATED)
+            .map(|(scorer_ord, scorer)| Reverse((scorer.doc(), scorer_ord)))
+            .collect();
+        let mut disjunction = Disjunction {
+            scorers,
+            heap,
+            current: Vec::new(),
+            minimum_matches_required: minimum_matches_required.max(1),
+            score_combiner,
+            doc: TERMINATED,
+        };
+        disjunction.pop_current();
+        disjunction.skip_to_match();
+        disjunction
+    }
+
+    /// Moves all of the scorers positioned on the smallest doc of the heap
+    /// to `current`.
+    fn pop_current(&mut self) {
+        debug_assert!(self.current.is_empty());
+        self.doc = match self.heap.peek() {
+            Some(&Reverse((doc, _))) => doc,
+            None => TERMINATED,
+        };
+        while let Some(&Reverse((doc, scorer_ord))) = self.heap.peek() {
+            if doc != self.doc {
+                break;
+            }
+            self.heap.pop();
+            self.current.push(scorer_ord);
+        }
+    }
+
+    fn advance_current(&mut self) {
+        for scorer_ord in std::mem::take(&mut self.current) {
+            let doc = self.scorers[scorer_ord].advance();
+            if doc != TERMINATED {
+                self.heap.push(Reverse((doc, scorer_ord)));
+            }
+        }
+    }
+
+    /// Advances until the current doc is matched by enough scorers.
+    fn skip_to_match(&mut self) {
+        while self.doc != TERMINATED && self.current.len() < self.minimum_matches_required {
+            if self.current.len() + self.heap.len() < self.minimum_matches_required {
+                // Not enough scorers left to match any document.
+                self.current.clear();
+                self.heap.clear();
+                self.doc = TERMINATED;
+                return;
+            }
+            self.advance_current();
+            self.pop_current();
+        }
+    }
+}
+
+impl<TScorer: Scorer, TScoreCombiner: ScoreCombiner> DocSet
+    for Disjunction<TScorer, TScoreCombiner>
+{
+    fn advance(&mut self) -> DocId {
+        self.advance_current();
+        self.pop_current();
+        self.skip_to_match();
+        self.doc
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.doc >= target {
+            return self.doc;
+        }
+        for scorer_ord in std::mem::take(&mut self.current) {
+            let doc = self.scorers[scorer_ord].seek(target);
+            if doc != TERMINATED {
+                self.heap.push(Reverse((doc, scorer_ord)));
+            }
+        }
+        while let Some(&Reverse((doc, scorer_ord))) = self.heap.peek() {
+            if doc >= target {
+                break;
+            }
+            self.heap.pop();
+            let doc = self.scorers[scorer_ord].seek(target);
+            if doc != TERMINATED {
+                self.heap.push(Reverse((doc, scorer_ord)));
+            }
+        }
+        self.pop_current();
+        self.skip_to_match();
+        self.doc
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorers
+            .iter()
+            .map(|scorer| scorer.size_hint())
+            .max()
+            .unwrap_or(0u32)
+    }
+}
+
+impl<TScorer: Scorer, TScoreCombiner: ScoreCombiner> Scorer
+    for Disjunction<TScorer, TScoreCombiner>
+{
+    fn score(&mut self) -> Score {
+        self.score_combiner.clear();
+        for &scorer_ord in &self.current {
+            self.score_combiner.update(&mut self.scorers[scorer_ord]);
+        }
+        self.score_combiner.score()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Disjunction;
+    use crate::docset::{DocSet, TERMINATED};
+    use crate::query::score_combiner::SumCombiner;
+    use crate::query::{ConstScorer, Scorer, VecDocSet};
+    use crate::{DocId, Score};
+
+    fn disjunction(
+        docs: &[&[DocId]],
+        minimum_matches_required: usize,
+    ) -> Disjunction<ConstScorer<VecDocSet>, SumCombiner> {
+        let scorers = docs
+            .iter()
+            .map(|docs| ConstScorer::new(VecDocSet::from(docs.to_vec()), 1.0))
+            .collect();
+        Disjunction::new(scorers, minimum_matches_required)
+    }
+
+    fn collect(mut scorer: impl Scorer) -> Vec<(DocId, Score)> {
+        let mut doc_scores = Vec::new();
+        while scorer.doc() != TERMINATED {
+            doc_scores.push((scorer.doc(), scorer.score()));
+            scorer.advance();
+        }
+        doc_scores
+    }
+
+    #[test]
+    fn test_disjunction_minimum_matches() {
+        let docs: &[&[DocId]] = &[&[1, 2, 3, 8], &[2, 3, 5, 8], &[3, 5, 9]];
+        assert_eq!(
+            collect(disjunction(docs, 1)),
+            vec![(1, 1.0), (2, 2.0), (3, 3.0), (5, 2.0), (8, 2.0), (9, 1.0)]
+        );
+        assert_eq!(
+            collect(disjunction(docs, 2)),
+            vec![(2, 2.0), (3, 3.0), (5, 2.0), (8, 2.0)]
+        );
+        assert_eq!(collect(disjunction(docs, 3)), vec![(3, 3.0)]);
+        assert!(collect(disjunction(docs, 4)).is_empty());
+    }
+
+    #[test]
+    fn test_disjunction_seek() {
+        let docs: &[&[DocId]] = &[&[1, 2, 3, 8], &[2, 3, 5, 8], &[3, 5, 9]];
+        let mut scorer = disjunction(docs, 2);
+        assert_eq!(scorer.doc(), 2);
+        assert_eq!(scorer.seek(4), 5);
+        assert_eq!(scorer.score(), 2.0);
+        assert_eq!(scorer.seek(5), 5);
+        assert_eq!(scorer.seek(6), 8);
+        assert_eq!(scorer.advance(), TERMINATED);
+        assert_eq!(scorer.seek(10), TERMINATED);
+    }
+}
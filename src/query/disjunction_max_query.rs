@@ -0,0 +1,281 @@
+use crate::docset::{DocSet, TERMINATED};
+use crate::query::explanation::does_not_match;
+use crate::query::query_visitor::rewrite_queries;
+use crate::query::score_combiner::DisjunctionMaxCombiner;
+use crate::query::{
+    Disjunction, EmptyScorer, Explanation, Query, QueryRewriter, QueryVisitor, Scorer, Weight,
+};
+use crate::{DocId, Score, Searcher, SegmentReader};
+use std::fmt;
+
+/// `DisjunctionMaxQuery` matches the union of the documents matched by its sub-queries.
+///
+/// Unlike a `BooleanQuery` made of `Should` clauses, the score of a document is not
+/// the sum of the score of the matching sub-queries, but the maximum of these scores,
+/// plus `tie_breaker` times the sum of the other scores.
+///
+/// This is typically useful when searching the same terms in several fields:
+/// a document matching strongly in one field will outrank a document matching
+/// weakly in all of the fields.
+///
+/// With a `tie_breaker` of `0.0`, only the best sub-query score is kept.
+/// With a `tie_breaker` of `1.0`, the scores are summed.
+pub struct DisjunctionMaxQuery {
+    disjuncts: Vec<Box<dyn Query>>,
+    tie_breaker: Score,
+}
+
+impl DisjunctionMaxQuery {
+    /// Creates a new `DisjunctionMaxQuery`.
+    pub fn new(disjuncts: Vec<Box<dyn Query>>, tie_breaker: Score) -> DisjunctionMaxQuery {
+        DisjunctionMaxQuery {
+            disjuncts,
+            tie_breaker,
+        }
+    }
+
+    /// Returns the sub-queries of this query.
+    pub fn disjuncts(&self) -> &[Box<dyn Query>] {
+        &self.disjuncts[..]
+    }
+
+    /// Returns the tie breaker of this query.
+    pub fn tie_breaker(&self) -> Score {
+        self.tie_breaker
+    }
+}
+
+impl Clone for DisjunctionMaxQuery {
+    fn clone(&self) -> Self {
+        DisjunctionMaxQuery {
+            disjuncts: self
+                .disjuncts
+                .iter()
+                .map(|disjunct| disjunct.box_clone())
+                .collect(),
+            tie_breaker: self.tie_breaker,
+        }
+    }
+}
+
+impl fmt::Debug for DisjunctionMaxQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DisjunctionMax(disjuncts={:?}, tie_breaker={})",
+            self.disjuncts, self.tie_breaker
+        )
+    }
+}
+
+impl Query for DisjunctionMaxQuery {
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> crate::Result<Box<dyn Weight>> {
+        let weights = self
+            .disjuncts
+            .iter()
+            .map(|disjunct| disjunct.weight(searcher, scoring_enabled))
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(Box::new(DisjunctionMaxWeight::new(
+            weights,
+            self.tie_breaker,
+        )))
+    }
+
+    fn visit(&self, visitor: &mut dyn QueryVisitor) {
+        if visitor.visit_disjunction_max_query(self) {
+            for disjunct in &self.disjuncts {
+                disjunct.visit(visitor);
+            }
+        }
+    }
+
+    fn rewrite_subqueries(&self, rewriter: &mut dyn QueryRewriter) -> Option<Box<dyn Query>> {
+        let disjuncts = rewrite_queries(
+            self.disjuncts.iter().map(|disjunct| disjunct.as_ref()),
+            rewriter,
+        )?;
+        Some(Box::new(DisjunctionMaxQuery::new(
+            disjuncts,
+            self.tie_breaker,
+        )))
+    }
+}
+
+/// Returns a scorer iterating through the union of `scorers`, and combining the scores
+/// of the matching scorers as described in `DisjunctionMaxQuery`.
+fn disjunction_max_scorer<TScorer: Scorer>(
+    scorers: Vec<TScorer>,
+    tie_breaker: Score,
+) -> Disjunction<TScorer, DisjunctionMaxCombiner> {
+    Disjunction::with_score_combiner(
+        scorers,
+        1,
+        DisjunctionMaxCombiner::with_tie_breaker(tie_breaker),
+    )
+}
+
+struct DisjunctionMaxWeight {
+    weights: Vec<Box<dyn Weight>>,
+    tie_breaker: Score,
+}
+
+impl DisjunctionMaxWeight {
+    fn new(weights: Vec<Box<dyn Weight>>, tie_breaker: Score) -> DisjunctionMaxWeight {
+        DisjunctionMaxWeight {
+            weights,
+            tie_breaker,
+        }
+    }
+}
+
+impl Weight for DisjunctionMaxWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let mut scorers = self
+            .weights
+            .iter()
+            .map(|weight| weight.scorer(reader, boost))
+            .collect::<crate::Result<Vec<_>>>()?;
+        scorers.retain(|scorer| scorer.doc() != TERMINATED);
+        match scorers.len() {
+            0 => Ok(Box::new(EmptyScorer)),
+            1 => Ok(scorers.pop().unwrap()),
+            _ => Ok(Box::new(disjunction_max_scorer(scorers, self.tie_breaker))),
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        let mut explanation = Explanation::new(
+            format!(
+                "DisjunctionMax. Max plus {} times the sum of the others of ...",
+                self.tie_breaker
+            ),
+            scorer.score(),
+        );
+        for weight in &self.weights {
+            if let Ok(child_explanation) = weight.explain(reader, doc) {
+                explanation.add_detail(child_explanation);
+            }
+        }
+        Ok(explanation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{disjunction_max_scorer, DisjunctionMaxQuery};
+    use crate::collector::TopDocs;
+    use crate::docset::{DocSet, TERMINATED};
+    use crate::query::{
+        BooleanQuery, ConstScorer, Occur, Query, QueryClone, Scorer, TermQuery, VecDocSet,
+    };
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{assert_nearly_equals, DocAddress, DocId, Index, Score, Term};
+
+    fn const_scorer(docs: &[DocId], score: Score) -> ConstScorer<VecDocSet> {
+        ConstScorer::new(VecDocSet::from(docs.to_vec()), score)
+    }
+
+    #[test]
+    fn test_disjunction_max_scorer() {
+        let scorers = vec![
+            const_scorer(&[1, 3, 5, 8], 1.0),
+            const_scorer(&[2, 3, 8], 2.0),
+            const_scorer(&[3, 9], 4.0),
+        ];
+        let mut scorer = disjunction_max_scorer(scorers, 0.5);
+        let mut doc_scores = Vec::new();
+        while scorer.doc() != TERMINATED {
+            doc_scores.push((scorer.doc(), scorer.score()));
+            scorer.advance();
+        }
+        assert_eq!(
+            doc_scores,
+            vec![
+                (1, 1.0),
+                (2, 2.0),
+                (3, 4.0 + 0.5 * 3.0),
+                (5, 1.0),
+                (8, 2.0 + 0.5 * 1.0),
+                (9, 4.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disjunction_max_scorer_seek() {
+        let scorers = vec![
+            const_scorer(&[1, 3, 5, 8], 1.0),
+            const_scorer(&[2, 3, 8], 2.0),
+            const_scorer(&[3, 9], 4.0),
+        ];
+        let mut scorer = disjunction_max_scorer(scorers, 0.0);
+        assert_eq!(scorer.seek(3), 3);
+        assert_eq!(scorer.score(), 4.0);
+        assert_eq!(scorer.seek(3), 3);
+        assert_eq!(scorer.seek(6), 8);
+        assert_eq!(scorer.score(), 2.0);
+        assert_eq!(scorer.advance(), 9);
+        assert_eq!(scorer.seek(10), TERMINATED);
+    }
+
+    #[test]
+    fn test_disjunction_max_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title=>"rust", body=>"a language"));
+        index_writer.add_document(doc!(title=>"a language", body=>"rust rust"));
+        index_writer.add_document(doc!(title=>"rust", body=>"rust"));
+        index_writer.add_document(doc!(title=>"python", body=>"python"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term_query = |field| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(field, "rust"),
+                IndexRecordOption::WithFreqs,
+            ))
+        };
+        let scores = |query: &dyn Query| -> crate::Result<Vec<Score>> {
+            let mut scores = vec![0.0; 4];
+            for (score, doc_address) in searcher.search(query, &TopDocs::with_limit(4))? {
+                scores[doc_address.doc_id as usize] = score;
+            }
+            Ok(scores)
+        };
+        let title_scores = scores(term_query(title).as_ref())?;
+        let body_scores = scores(term_query(body).as_ref())?;
+        let tie_breaker = 0.3;
+        let dismax_query =
+            DisjunctionMaxQuery::new(vec![term_query(title), term_query(body)], tie_breaker);
+        let dismax_scores = scores(&dismax_query)?;
+        for doc in 0..4 {
+            let max_score = title_scores[doc].max(body_scores[doc]);
+            let min_score = title_scores[doc].min(body_scores[doc]);
+            assert_nearly_equals!(dismax_scores[doc], max_score + tie_breaker * min_score);
+        }
+        assert_eq!(dismax_scores[3], 0.0);
+        assert_eq!(dismax_query.count(&searcher)?, 3);
+
+        let explanation = dismax_query.explain(&searcher, DocAddress::new(0, 2))?;
+        assert_nearly_equals!(explanation.value(), dismax_scores[2]);
+        assert!(dismax_query
+            .explain(&searcher, DocAddress::new(0, 3))
+            .is_err());
+
+        // As a clause of a boolean query.
+        let boolean_query = BooleanQuery::new(vec![
+            (Occur::Must, dismax_query.box_clone()),
+            (Occur::MustNot, term_query(body)),
+        ]);
+        let boolean_scores = scores(&boolean_query)?;
+        assert_nearly_equals!(boolean_scores[0], dismax_scores[0]);
+        assert_eq!(&boolean_scores[1..], &[0.0, 0.0, 0.0]);
+        Ok(())
+    }
+}
@@ -0,0 +1,297 @@
+use crate::core::Searcher;
+use crate::core::SegmentReader;
+use crate::error::TantivyError;
+use crate::query::explanation::does_not_match;
+use crate::query::filter_cache::query_cache_key;
+use crate::query::{BitSetDocSet, ConstScorer, Explanation, Query, Scorer, Weight};
+use crate::schema::{Cardinality, Field, FieldType, IndexRecordOption};
+use crate::{DocId, Score};
+use common::BitSet;
+
+/// `ExistsQuery` matches all of the documents that have a value for a given field.
+///
+/// A document has a value for a field if it has at least one indexed token,
+/// or at least one fast field value, for that field.
+///
+/// Matched document will all get a constant `Score` of one.
+///
+/// Documents missing the field are matched by combining an `ExistsQuery` with
+/// the `MustNot` occurence, within a `BooleanQuery`.
+///
+/// # Implementation
+///
+/// Depending on the field type, the matching documents are found
+/// - for indexed text fields, as the documents with a non-zero field norm.
+/// - for multivalued fast fields and bytes fast fields, as the documents with at least one value.
+/// - for other indexed fields, by iterating over all of the posting lists of the field.
+///
+/// Single-valued numeric fast fields that are not indexed cannot be searched, as every
+/// document has a value, possibly a default one.
+///
+/// # Example
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::{AllQuery, BooleanQuery, ExistsQuery, Occur, Query};
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index};
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let subtitle = schema_builder.add_text_field("subtitle", TEXT);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::create_in_ram(schema);
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "The Name of the Wind"));
+/// index_writer.add_document(doc!(
+///     title => "The Wise Man's Fear",
+///     subtitle => "The Kingkiller Chronicle: Day Two",
+/// ));
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let with_subtitle = ExistsQuery::new(subtitle);
+/// assert_eq!(searcher.search(&with_subtitle, &Count)?, 1);
+///
+/// let without_subtitle = BooleanQuery::new(vec![
+///     (Occur::Must, Box::new(AllQuery) as Box<dyn Query>),
+///     (Occur::MustNot, Box::new(ExistsQuery::new(subtitle))),
+/// ]);
+/// assert_eq!(searcher.search(&without_subtitle, &Count)?, 1);
+/// Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct ExistsQuery {
+    field: Field,
+}
+
+impl ExistsQuery {
+    /// Creates a new `ExistsQuery` over the given field.
+    pub fn new(field: Field) -> ExistsQuery {
+        ExistsQuery { field }
+    }
+
+    /// Field to search over
+    pub fn field(&self) -> Field {
+        self.field
+    }
+}
+
+impl Query for ExistsQuery {
+    fn weight(
+        &self,
+        searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> crate::Result<Box<dyn Weight>> {
+        let field_entry = searcher.schema().get_field_entry(self.field);
+        let field_type = field_entry.field_type();
+        let multivalued_fast_field = match field_type {
+            FieldType::U64(options)
+            | FieldType::I64(options)
+            | FieldType::F64(options)
+            | FieldType::Bool(options) => {
+                options.get_fastfield_cardinality() == Some(Cardinality::MultiValues)
+            }
+            FieldType::Date(options) => {
+                options.get_fastfield_cardinality() == Some(Cardinality::MultiValues)
+            }
+            _ => false,
+        };
+        let source = match field_type {
+            FieldType::Str(_) if field_type.is_indexed() => ValueSource::FieldNorms,
+            _ if multivalued_fast_field => ValueSource::MultiValuedFastField,
+            FieldType::Bytes(options) if options.is_fast() => ValueSource::BytesFastField,
+            _ if field_type.is_indexed() => ValueSource::Postings,
+            _ => {
+                return Err(TantivyError::SchemaError(format!(
+                    "Field {:?} cannot be searched with an ExistsQuery. It should be indexed, \
+                     or be a multivalued or bytes fast field.",
+                    field_entry.name()
+                )));
+            }
+        };
+        Ok(Box::new(ExistsWeight {
+            field: self.field,
+            source,
+        }))
+    }
+
+    fn cache_key(&self) -> Option<Vec<u8>> {
+        Some(query_cache_key("exists", &self.field))
+    }
+}
+
+/// Where the weight looks for the values of the field.
+#[derive(Clone, Copy)]
+enum ValueSource {
+    FieldNorms,
+    MultiValuedFastField,
+    BytesFastField,
+    Postings,
+}
+
+struct ExistsWeight {
+    field: Field,
+    source: ValueSource,
+}
+
+impl ExistsWeight {
+    fn docs_with_value(&self, reader: &SegmentReader) -> crate::Result<BitSet> {
+        let max_doc = reader.max_doc();
+        let mut doc_bitset = BitSet::with_max_value(max_doc);
+        match self.source {
+            ValueSource::FieldNorms => {
+                let fieldnorm_reader = reader.get_fieldnorms_reader(self.field)?;
+                for doc in 0..max_doc {
+                    if fieldnorm_reader.fieldnorm_id(doc) > 0 {
+                        doc_bitset.insert(doc);
+                    }
+                }
+            }
+            ValueSource::MultiValuedFastField => {
+                let fast_field_reader = reader.fast_fields().u64s_lenient(self.field)?;
+                for doc in 0..max_doc {
+                    if fast_field_reader.num_vals(doc) > 0 {
+                        doc_bitset.insert(doc);
+                    }
+                }
+            }
+            ValueSource::BytesFastField => {
+                let fast_field_reader = reader.fast_fields().bytes(self.field)?;
+                for doc in 0..max_doc {
+                    if fast_field_reader.num_bytes(doc) > 0 {
+                        doc_bitset.insert(doc);
+                    }
+                }
+            }
+            ValueSource::Postings => {
+                let inverted_index = reader.inverted_index(self.field)?;
+                let mut term_stream = inverted_index.terms().stream()?;
+                while term_stream.advance() {
+                    let term_info = term_stream.value();
+                    let mut block_segment_postings = inverted_index
+                        .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
+                    loop {
+                        let docs = block_segment_postings.docs();
+                        if docs.is_empty() {
+                            break;
+                        }
+                        for &doc in docs {
+                            doc_bitset.insert(doc);
+                        }
+                        block_segment_postings.advance();
+                    }
+                }
+            }
+        }
+        Ok(doc_bitset)
+    }
+}
+
+impl Weight for ExistsWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let doc_bitset = BitSetDocSet::from(self.docs_with_value(reader)?);
+        Ok(Box::new(ConstScorer::new(doc_bitset, boost)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        Ok(Explanation::new("ExistsQuery", 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::ExistsQuery;
+    use crate::collector::DocSetCollector;
+    use crate::query::{AllQuery, BooleanQuery, Occur, Query};
+    use crate::schema::{
+        BytesOptions, Cardinality, Facet, IntOptions, Schema, FAST, INDEXED, TEXT,
+    };
+    use crate::{DocAddress, Index, Term};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_exists_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let int_field = schema_builder.add_i64_field("int", INDEXED);
+        let multi_field = schema_builder.add_u64_field(
+            "multi",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let fast_field = schema_builder.add_u64_field("fast", FAST);
+        let bytes_field =
+            schema_builder.add_bytes_field("bytes", BytesOptions::default().set_fast());
+        let facet_field = schema_builder.add_facet_field("facet", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        // 0
+        index_writer.add_document(doc!(
+            text_field => "hello",
+            int_field => 1i64,
+            multi_field => 1u64,
+            multi_field => 2u64,
+            fast_field => 1u64,
+        ));
+        // 1
+        index_writer.add_document(doc!(
+            bytes_field => vec![1u8, 2u8],
+            facet_field => Facet::from("/a/b"),
+            fast_field => 2u64,
+        ));
+        // 2
+        index_writer.add_document(doc!(
+            text_field => "hello world",
+            text_field => "again",
+            int_field => 2i64,
+            int_field => 3i64,
+            multi_field => 3u64,
+        ));
+        // 3
+        index_writer.add_document(doc!(text_field => ""));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let docs = |query: &dyn Query| -> crate::Result<HashSet<u32>> {
+            Ok(searcher
+                .search(query, &DocSetCollector)?
+                .into_iter()
+                .map(|doc_address| doc_address.doc_id)
+                .collect())
+        };
+        let set = |docs: &[u32]| -> HashSet<u32> { docs.iter().cloned().collect() };
+        assert_eq!(docs(&ExistsQuery::new(text_field))?, set(&[0, 2]));
+        assert_eq!(docs(&ExistsQuery::new(int_field))?, set(&[0, 2]));
+        assert_eq!(docs(&ExistsQuery::new(multi_field))?, set(&[0, 2]));
+        assert_eq!(docs(&ExistsQuery::new(bytes_field))?, set(&[1]));
+        assert_eq!(docs(&ExistsQuery::new(facet_field))?, set(&[1]));
+        assert!(ExistsQuery::new(fast_field).count(&searcher).is_err());
+
+        let missing_text = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(AllQuery) as Box<dyn Query>),
+            (Occur::MustNot, Box::new(ExistsQuery::new(text_field))),
+        ]);
+        assert_eq!(docs(&missing_text)?, set(&[1, 3]));
+        assert_eq!(missing_text.count(&searcher)?, 2);
+
+        let explanation = ExistsQuery::new(int_field).explain(&searcher, DocAddress::new(0, 2))?;
+        assert_eq!(explanation.value(), 1.0);
+        assert!(ExistsQuery::new(int_field)
+            .explain(&searcher, DocAddress::new(0, 1))
+            .is_err());
+
+        // Deleted documents are not matched.
+        index_writer.delete_term(Term::from_field_i64(int_field, 1i64));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(ExistsQuery::new(text_field).count(&searcher)?, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,784 @@
+use crate::core::Searcher;
+use crate::core::SegmentReader;
+use crate::error::TantivyError;
+use crate::fastfield::{
+    DynamicFastFieldReader, FastFieldReader, FastValue, MultiValuedFastFieldReader,
+};
+use crate::query::explanation::does_not_match;
+use crate::query::{BitSetDocSet, ConstScorer, EmptyScorer, Explanation};
+use crate::query::{Query, Scorer, Weight};
+use crate::schema::{Cardinality, Field, FieldType, Type};
+use crate::{DocId, Score};
+use common::BitSet;
+use std::ops::{Bound, RangeInclusive};
+
+/// Number of fast field values decoded at once when scanning a single-valued column.
+const SCAN_BLOCK_LEN: usize = 1_024;
+
+/// `FastFieldRangeQuery` matches all of the documents having at least one value within a
+/// defined range, in a numeric (`u64`, `i64`, `f64` or date) fast field.
+///
+/// Matched document will all get a constant `Score` of one.
+///
+/// # Implementation
+///
+/// Unlike `RangeQuery`, the inverted index is not used at all: the fast field column
+/// of each segment is scanned, and the matching documents are appended into a `BitSet`.
+/// It does not depend on the number of distinct values within the range, which makes it
+/// a much better fit for wide ranges over high cardinality fields, like timestamps.
+///
+/// `RangeQuery` automatically falls back to this execution path when it is cheaper.
+///
+/// # Example
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::FastFieldRangeQuery;
+/// use tantivy::schema::{Schema, FAST};
+/// use tantivy::{doc, Index};
+/// use std::ops::Bound;
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let year_field = schema_builder.add_u64_field("year", FAST);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::create_in_ram(schema);
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// for year in 1950u64..2017u64 {
+///     let num_docs_within_year = 10 + (year - 1950) * (year - 1950);
+///     for _ in 0..num_docs_within_year {
+///       index_writer.add_document(doc!(year_field => year));
+///     }
+/// }
+/// index_writer.commit()?;
+///
+/// let reader = index.reader()?;
+/// let searcher = reader.searcher();
+/// let docs_in_the_sixties = FastFieldRangeQuery::new_u64_bounds(
+///     year_field,
+///     Bound::Included(1960),
+///     Bound::Excluded(1970),
+/// );
+/// let num_60s_books = searcher.search(&docs_in_the_sixties, &Count)?;
+/// assert_eq!(num_60s_books, 2285);
+/// Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct FastFieldRangeQuery {
+    field: Field,
+    value_type: Type,
+    left_bound: Bound<u64>,
+    right_bound: Bound<u64>,
+}
+
+impl FastFieldRangeQuery {
+    fn new<TFastValue: FastValue>(
+        field: Field,
+        value_type: Type,
+        left_bound: Bound<TFastValue>,
+        right_bound: Bound<TFastValue>,
+    ) -> FastFieldRangeQuery {
+        FastFieldRangeQuery {
+            field,
+            value_type,
+            left_bound: map_bound(left_bound, |val| val.to_u64()),
+            right_bound: map_bound(right_bound, |val| val.to_u64()),
+        }
+    }
+
+    /// Creates a new `FastFieldRangeQuery` over a `u64` fast field.
+    ///
+    /// If the field is not a `u64` fast field, an error will be returned
+    /// when the `Weight` object is created.
+    pub fn new_u64_bounds(
+        field: Field,
+        left_bound: Bound<u64>,
+        right_bound: Bound<u64>,
+    ) -> FastFieldRangeQuery {
+        FastFieldRangeQuery::new(field, Type::U64, left_bound, right_bound)
+    }
+
+    /// Creates a new `FastFieldRangeQuery` over a `i64` fast field.
+    ///
+    /// If the field is not a `i64` fast field, an error will be returned
+    /// when the `Weight` object is created.
+    pub fn new_i64_bounds(
+        field: Field,
+        left_bound: Bound<i64>,
+        right_bound: Bound<i64>,
+    ) -> FastFieldRangeQuery {
+        FastFieldRangeQuery::new(field, Type::I64, left_bound, right_bound)
+    }
+
+    /// Creates a new `FastFieldRangeQuery` over a `f64` fast field.
+    ///
+    /// If the field is not a `f64` fast field, an error will be returned
+    /// when the `Weight` object is created.
+    pub fn new_f64_bounds(
+        field: Field,
+        left_bound: Bound<f64>,
+        right_bound: Bound<f64>,
+    ) -> FastFieldRangeQuery {
+        FastFieldRangeQuery::new(field, Type::F64, left_bound, right_bound)
+    }
+
+    /// Creates a new `FastFieldRangeQuery` over a date fast field.
+    ///
+    /// If the field is not a date fast field, an error will be returned
+    /// when the `Weight` object is created.
+    pub fn new_date_bounds(
+        field: Field,
+        left_bound: Bound<crate::DateTime>,
+        right_bound: Bound<crate::DateTime>,
+    ) -> FastFieldRangeQuery {
+        FastFieldRangeQuery::new(field, Type::Date, left_bound, right_bound)
+    }
+
+    /// Field to search over
+    pub fn field(&self) -> Field {
+        self.field
+    }
+}
+
+impl Query for FastFieldRangeQuery {
+    fn weight(
+        &self,
+        searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> crate::Result<Box<dyn Weight>> {
+        let field_entry = searcher.schema().get_field_entry(self.field);
+        let value_type = field_entry.field_type().value_type();
+        if value_type != self.value_type {
+            let err_msg = format!(
+                "Create a range query of the type {:?}, when the field given was of type {:?}",
+                self.value_type, value_type
+            );
+            return Err(TantivyError::SchemaError(err_msg));
+        }
+        let cardinality = fast_field_cardinality(field_entry.field_type()).ok_or_else(|| {
+            TantivyError::SchemaError(format!(
+                "Field {:?} is not a fast field.",
+                field_entry.name()
+            ))
+        })?;
+        Ok(Box::new(FastFieldRangeWeight::new(
+            self.field,
+            cardinality,
+            stored_value_scale(field_entry.field_type()),
+            &self.left_bound,
+            &self.right_bound,
+        )))
+    }
+}
+
+fn map_bound<TFrom, TTo>(bound: Bound<TFrom>, transform: impl Fn(TFrom) -> TTo) -> Bound<TTo> {
+    match bound {
+        Bound::Included(val) => Bound::Included(transform(val)),
+        Bound::Excluded(val) => Bound::Excluded(transform(val)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// Returns the cardinality of a numeric fast field, or `None` if the field
+/// is not a numeric fast field.
+pub(crate) fn fast_field_cardinality(field_type: &FieldType) -> Option<Cardinality> {
+    match field_type {
+        FieldType::U64(options) | FieldType::I64(options) | FieldType::F64(options) => {
+            options.get_fastfield_cardinality()
+        }
+        FieldType::Date(options) => options.get_fastfield_cardinality(),
+        _ => None,
+    }
+}
+
+/// Returns the factor mapping the values stored in the column of a numeric fast field
+/// to their `u64` representation, i.e. the number of microseconds in the unit of the
+/// precision of a date field, and `1` for the other fields.
+pub(crate) fn stored_value_scale(field_type: &FieldType) -> i64 {
+    match field_type {
+        FieldType::Date(options) => options.get_precision().micros_per_unit(),
+        _ => 1,
+    }
+}
+
+/// Converts an inclusive range of `u64` representations into the range of the values
+/// stored in a column with the given `scale`, or `None` if the range is empty.
+///
+/// A stored value `val` matches iff `val * scale` is within `range`, so that dates
+/// match if their value truncated to the precision of the field is within the range.
+fn stored_range(range: RangeInclusive<u64>, scale: i64) -> Option<RangeInclusive<u64>> {
+    if scale == 1 {
+        return Some(range);
+    }
+    let start = common::u64_to_i64(*range.start());
+    let end = common::u64_to_i64(*range.end());
+    // ceil(start / scale) and floor(end / scale), as `scale` is positive.
+    let start = start.div_euclid(scale) + (start.rem_euclid(scale) != 0) as i64;
+    let end = end.div_euclid(scale);
+    if start > end {
+        return None;
+    }
+    Some(common::i64_to_u64(start)..=common::i64_to_u64(end))
+}
+
+/// Converts a pair of bounds into an inclusive range, or `None` if the range is empty.
+fn inclusive_range(
+    left_bound: &Bound<u64>,
+    right_bound: &Bound<u64>,
+) -> Option<RangeInclusive<u64>> {
+    let start = match *left_bound {
+        Bound::Included(val) => val,
+        Bound::Excluded(val) => val.checked_add(1)?,
+        Bound::Unbounded => 0u64,
+    };
+    let end = match *right_bound {
+        Bound::Included(val) => val,
+        Bound::Excluded(val) => val.checked_sub(1)?,
+        Bound::Unbounded => u64::MAX,
+    };
+    if start > end {
+        return None;
+    }
+    Some(start..=end)
+}
+
+/// The fast field column of a segment, read as its stored `u64` values.
+pub(crate) enum FastFieldColumn {
+    SingleValue(DynamicFastFieldReader<u64>),
+    MultiValues(MultiValuedFastFieldReader<u64>),
+}
+
+impl FastFieldColumn {
+    fn min_value(&self) -> u64 {
+        match self {
+            FastFieldColumn::SingleValue(reader) => reader.min_value(),
+            FastFieldColumn::MultiValues(reader) => reader.min_value(),
+        }
+    }
+
+    fn max_value(&self) -> u64 {
+        match self {
+            FastFieldColumn::SingleValue(reader) => reader.max_value(),
+            FastFieldColumn::MultiValues(reader) => reader.max_value(),
+        }
+    }
+
+    /// Number of values a scan of the column has to go through.
+    pub(crate) fn num_vals(&self, max_doc: DocId) -> u64 {
+        match self {
+            FastFieldColumn::SingleValue(_) => max_doc as u64,
+            FastFieldColumn::MultiValues(reader) => reader.total_num_vals(),
+        }
+    }
+
+    /// Returns the fraction of the `[min_value, max_value]` interval of the column
+    /// covered by `range`.
+    pub(crate) fn coverage(&self, range: &RangeInclusive<u64>) -> f64 {
+        let (min_value, max_value) = (self.min_value(), self.max_value());
+        let start = (*range.start()).max(min_value);
+        let end = (*range.end()).min(max_value);
+        if start > end {
+            return 0.0;
+        }
+        ((end - start) as f64 + 1.0) / ((max_value - min_value) as f64 + 1.0)
+    }
+}
+
+/// Weight scanning the fast field column of each segment.
+///
+/// Deleted documents are skipped during the scan.
+pub(crate) struct FastFieldRangeWeight {
+    field: Field,
+    cardinality: Cardinality,
+    range: Option<RangeInclusive<u64>>,
+}
+
+impl FastFieldRangeWeight {
+    /// Creates a weight matching the values within the bounds, given as `u64`
+    /// representations of the values of the field.
+    ///
+    /// `scale` is the factor mapping the stored values of the column to these
+    /// representations, as returned by `stored_value_scale`.
+    pub(crate) fn new(
+        field: Field,
+        cardinality: Cardinality,
+        scale: i64,
+        left_bound: &Bound<u64>,
+        right_bound: &Bound<u64>,
+    ) -> FastFieldRangeWeight {
+        FastFieldRangeWeight {
+            field,
+            cardinality,
+            range: inclusive_range(left_bound, right_bound)
+                .and_then(|range| stored_range(range, scale)),
+        }
+    }
+
+    /// Range of stored values matched by this weight, or `None` if it cannot match anything.
+    pub(crate) fn range(&self) -> Option<&RangeInclusive<u64>> {
+        self.range.as_ref()
+    }
+
+    pub(crate) fn column(&self, reader: &SegmentReader) -> crate::Result<FastFieldColumn> {
+        let fast_field_readers = reader.fast_fields().stored_values();
+        match self.cardinality {
+            Cardinality::SingleValue => Ok(FastFieldColumn::SingleValue(
+                fast_field_readers.u64_lenient(self.field)?,
+            )),
+            Cardinality::MultiValues => Ok(FastFieldColumn::MultiValues(
+                fast_field_readers.u64s_lenient(self.field)?,
+            )),
+        }
+    }
+
+    pub(crate) fn scorer_for_column(
+        &self,
+        column: &FastFieldColumn,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> Box<dyn Scorer> {
+        let range = match self.range.as_ref() {
+            Some(range) if column.coverage(range) > 0.0 => range,
+            _ => return Box::new(EmptyScorer),
+        };
+        let max_doc = reader.max_doc();
+        let mut doc_bitset = BitSet::with_max_value(max_doc);
+        let delete_bitset_opt = reader.delete_bitset();
+        let mut insert_if_alive = |doc: DocId| {
+            if let Some(delete_bitset) = delete_bitset_opt {
+                if delete_bitset.is_deleted(doc) {
+                    return;
+                }
+            }
+            doc_bitset.insert(doc);
+        };
+        match column {
+            FastFieldColumn::SingleValue(fast_field_reader) => {
+                let mut vals = vec![0u64; SCAN_BLOCK_LEN];
+                for block_start in (0..max_doc).step_by(SCAN_BLOCK_LEN) {
+                    let block_len = (max_doc - block_start).min(SCAN_BLOCK_LEN as u32) as usize;
+                    let block_vals = &mut vals[..block_len];
+                    fast_field_reader.get_range(block_start as u64, block_vals);
+                    for (doc, val) in (block_start..).zip(block_vals.iter()) {
+                        if range.contains(val) {
+                            insert_if_alive(doc);
+                        }
+                    }
+                }
+            }
+            FastFieldColumn::MultiValues(fast_field_reader) => {
+                let mut vals = Vec::new();
+                for doc in 0..max_doc {
+                    fast_field_reader.get_vals(doc, &mut vals);
+                    if vals.iter().any(|val| range.contains(val)) {
+                        insert_if_alive(doc);
+                    }
+                }
+            }
+        }
+        let doc_bitset = BitSetDocSet::from(doc_bitset);
+        Box::new(ConstScorer::new(doc_bitset, boost))
+    }
+}
+
+impl Weight for FastFieldRangeWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        if self.range.is_none() {
+            return Ok(Box::new(EmptyScorer));
+        }
+        let column = self.column(reader)?;
+        Ok(self.scorer_for_column(&column, reader, boost))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        Ok(Explanation::new("FastFieldRangeQuery", 1.0))
+    }
+}
+
+/// Weight scanning the `u128` fast field column of each segment, e.g. an ip address column.
+///
+/// Deleted documents are skipped during the scan.
+pub(crate) struct U128FastFieldRangeWeight {
+    field: Field,
+    range: Option<RangeInclusive<u128>>,
+}
+
+impl U128FastFieldRangeWeight {
+    pub(crate) fn new(
+        field: Field,
+        left_bound: &Bound<u128>,
+        right_bound: &Bound<u128>,
+    ) -> U128FastFieldRangeWeight {
+        let start = match *left_bound {
+            Bound::Included(val) => Some(val),
+            Bound::Excluded(val) => val.checked_add(1),
+            Bound::Unbounded => Some(0u128),
+        };
+        let end = match *right_bound {
+            Bound::Included(val) => Some(val),
+            Bound::Excluded(val) => val.checked_sub(1),
+            Bound::Unbounded => Some(u128::MAX),
+        };
+        let range = match (start, end) {
+            (Some(start), Some(end)) if start <= end => Some(start..=end),
+            _ => None,
+        };
+        U128FastFieldRangeWeight { field, range }
+    }
+}
+
+impl Weight for U128FastFieldRangeWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let fast_field_reader = reader.fast_fields().ip_addr(self.field)?;
+        let range = match self.range.as_ref() {
+            Some(range)
+                if *range.start() <= fast_field_reader.max_value()
+                    && *range.end() >= fast_field_reader.min_value() =>
+            {
+                range
+            }
+            _ => return Ok(Box::new(EmptyScorer)),
+        };
+        let max_doc = reader.max_doc();
+        let mut doc_bitset = BitSet::with_max_value(max_doc);
+        let delete_bitset_opt = reader.delete_bitset();
+        for doc in 0..max_doc {
+            if let Some(delete_bitset) = delete_bitset_opt {
+                if delete_bitset.is_deleted(doc) {
+                    continue;
+                }
+            }
+            if range.contains(&fast_field_reader.get(doc)) {
+                doc_bitset.insert(doc);
+            }
+        }
+        let doc_bitset = BitSetDocSet::from(doc_bitset);
+        Ok(Box::new(ConstScorer::new(doc_bitset, boost)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        Ok(Explanation::new("FastFieldRangeQuery", 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::FastFieldRangeQuery;
+    use crate::collector::{Count, DocSetCollector};
+    use crate::query::{Query, QueryParser, RangeQuery};
+    use crate::schema::{
+        Cardinality, DateOptions, DatePrecision, IntOptions, Schema, Type, FAST, INDEXED, TEXT,
+    };
+    use crate::{DocAddress, Index, Term};
+    use chrono::{TimeZone, Utc};
+    use std::ops::Bound;
+
+    #[test]
+    fn test_fast_field_range_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let int_field = schema_builder.add_i64_field("int", FAST);
+        let float_field = schema_builder.add_f64_field("float", FAST | INDEXED);
+        let multi_field = schema_builder.add_u64_field(
+            "multi",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in -50i64..50i64 {
+            index_writer.add_document(doc!(
+                int_field => i,
+                float_field => i as f64 / 10.0,
+                multi_field => (i + 50) as u64,
+                multi_field => (i + 50) as u64 * 100,
+                text_field => if i % 2 == 0 { "even" } else { "odd" },
+            ));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count = |query: &dyn Query| query.count(&searcher).unwrap();
+
+        assert_eq!(
+            count(&FastFieldRangeQuery::new_i64_bounds(
+                int_field,
+                Bound::Included(-10),
+                Bound::Excluded(10)
+            )),
+            20
+        );
+        assert_eq!(
+            count(&FastFieldRangeQuery::new_i64_bounds(
+                int_field,
+                Bound::Excluded(-10),
+                Bound::Unbounded
+            )),
+            59
+        );
+        assert_eq!(
+            count(&FastFieldRangeQuery::new_i64_bounds(
+                int_field,
+                Bound::Excluded(10),
+                Bound::Excluded(11)
+            )),
+            0
+        );
+        assert_eq!(
+            count(&FastFieldRangeQuery::new_i64_bounds(
+                int_field,
+                Bound::Included(100),
+                Bound::Unbounded
+            )),
+            0
+        );
+        assert_eq!(
+            count(&FastFieldRangeQuery::new_f64_bounds(
+                float_field,
+                Bound::Included(-0.5),
+                Bound::Included(0.5)
+            )),
+            11
+        );
+        // A document matches if any of its values is within the range.
+        assert_eq!(
+            count(&FastFieldRangeQuery::new_u64_bounds(
+                multi_field,
+                Bound::Included(95),
+                Bound::Included(200)
+            )),
+            7
+        );
+
+        // The fast field path and the term path agree.
+        let range_query = RangeQuery::new_f64(float_field, -1.0..2.5);
+        let fast_field_range_query = FastFieldRangeQuery::new_f64_bounds(
+            float_field,
+            Bound::Included(-1.0),
+            Bound::Excluded(2.5),
+        );
+        let term_range_query = range_query.clone().with_fast_field_fallback(false);
+        let docs = searcher.search(&term_range_query, &DocSetCollector)?;
+        assert_eq!(docs.len(), 35);
+        assert_eq!(
+            searcher.search(&fast_field_range_query, &DocSetCollector)?,
+            docs
+        );
+        // The range contains too many terms, the fast field is used.
+        assert_eq!(searcher.search(&range_query, &DocSetCollector)?, docs);
+
+        // The field is not indexed, RangeQuery uses the fast field.
+        assert_eq!(count(&RangeQuery::new_i64(int_field, -10..10)), 20);
+
+        assert!(searcher
+            .search(
+                &FastFieldRangeQuery::new_u64_bounds(int_field, Bound::Unbounded, Bound::Unbounded),
+                &Count
+            )
+            .is_err());
+        assert!(RangeQuery::new_str(text_field, "a".."z")
+            .count(&searcher)
+            .is_ok());
+        let explanation = fast_field_range_query.explain(&searcher, DocAddress::new(0, 40))?;
+        assert_eq!(explanation.value(), 1.0);
+        assert!(fast_field_range_query
+            .explain(&searcher, DocAddress::new(0, 0))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fast_field_range_query_with_deletes() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let int_field = schema_builder.add_u64_field("int", FAST | INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0u64..100u64 {
+            index_writer.add_document(doc!(int_field => i));
+        }
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_u64(int_field, 5));
+        index_writer.delete_term(Term::from_field_u64(int_field, 50));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query =
+            FastFieldRangeQuery::new_u64_bounds(int_field, Bound::Included(0), Bound::Excluded(10));
+        assert_eq!(query.count(&searcher)?, 9);
+        let docs = searcher.search(&query, &DocSetCollector)?;
+        assert_eq!(docs.len(), 9);
+        assert!(!docs.contains(&DocAddress::new(0, 5)));
+        let query =
+            FastFieldRangeQuery::new_u64_bounds(int_field, Bound::Unbounded, Bound::Unbounded);
+        assert_eq!(searcher.search(&query, &Count)?, 98);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fast_field_range_query_date_precision() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let seconds_field = schema_builder.add_date_field("seconds", FAST);
+        let millis_field = schema_builder.add_date_field(
+            "millis",
+            DateOptions::from(FAST).set_precision(DatePrecision::Milliseconds),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for nanos in [100_000_000, 900_000_000] {
+            let date = Utc.timestamp(1_000, nanos);
+            index_writer.add_document(doc!(seconds_field => date, millis_field => date));
+        }
+        let date = Utc.timestamp(1_001, 0);
+        index_writer.add_document(doc!(seconds_field => date, millis_field => date));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count = |field, left_bound, right_bound| {
+            FastFieldRangeQuery::new_date_bounds(field, left_bound, right_bound).count(&searcher)
+        };
+        let left_bound = Bound::Included(Utc.timestamp(1_000, 500_000_000));
+        let right_bound = Bound::Excluded(Utc.timestamp(1_001, 0));
+        // the dates are truncated to the second, before the lower bound.
+        assert_eq!(count(seconds_field, left_bound, right_bound)?, 0);
+        assert_eq!(count(millis_field, left_bound, right_bound)?, 1);
+        let right_bound = Bound::Included(Utc.timestamp(1_001, 0));
+        assert_eq!(count(millis_field, left_bound, right_bound)?, 2);
+        let left_bound = Bound::Excluded(Utc.timestamp(1_000, 100_000_000));
+        assert_eq!(count(millis_field, left_bound, Bound::Unbounded)?, 2);
+        // open lower bounds.
+        let right_bound = Bound::Excluded(Utc.timestamp(1_000, 900_000_000));
+        assert_eq!(count(millis_field, Bound::Unbounded, right_bound)?, 1);
+        assert_eq!(count(seconds_field, Bound::Unbounded, right_bound)?, 2);
+        assert_eq!(count(millis_field, Bound::Unbounded, Bound::Unbounded)?, 3);
+        let right_bound = Bound::Excluded(Utc.timestamp(i64::MIN / 1_000_000 / 2, 0));
+        assert_eq!(count(millis_field, Bound::Unbounded, right_bound)?, 0);
+        // the range query matches indexed dates at the second.
+        let range_query = |field| {
+            let term = Term::from_field_date(field, &Utc.timestamp(1_000, 0));
+            RangeQuery::new_term_bounds(
+                field,
+                Type::Date,
+                &Bound::Included(term.clone()),
+                &Bound::Included(term),
+            )
+            .count(&searcher)
+        };
+        assert_eq!(range_query(seconds_field)?, 2);
+        assert_eq!(range_query(millis_field)?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fast_field_range_query_date_open_lower_bound_parser() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let date_field = schema_builder.add_date_field(
+            "d",
+            DateOptions::from(INDEXED | FAST).set_precision(DatePrecision::Milliseconds),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        // one distinct date per document, so that the fast field is searched.
+        for day in 0..100 {
+            let date = Utc.timestamp(1_500_000_000 + day * 86_400 * 10, 500_000_000);
+            index_writer.add_document(doc!(date_field => date));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![]);
+        let count = |query: &str| query_parser.parse_query(query)?.count(&searcher);
+        let num_before_2020 = (0..100)
+            .filter(|day| 1_500_000_000 + day * 86_400 * 10 < 1_577_836_800)
+            .count();
+        assert_eq!(count("d:[* TO 2020-01-01T00:00:00Z]")?, num_before_2020);
+        assert_eq!(count("d:<2020-01-01T00:00:00Z")?, num_before_2020);
+        assert_eq!(count("d:[* TO *]")?, 100);
+        assert_eq!(count("d:>=2020-01-01T00:00:00Z")?, 100 - num_before_2020);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "unstable"))]
+mod bench {
+
+    use super::FastFieldRangeQuery;
+    use crate::collector::Count;
+    use crate::query::RangeQuery;
+    use crate::schema::{Field, Schema, FAST, INDEXED};
+    use crate::{Index, IndexReader};
+    use std::ops::Bound;
+    use test::Bencher;
+
+    const NUM_DOCS: u64 = 500_000;
+    const FIRST_TIMESTAMP: i64 = 1_600_000_000;
+
+    /// Builds an index with a dense timestamp field, with one distinct second per document.
+    fn dense_timestamp_reader() -> (IndexReader, Field) {
+        let mut schema_builder = Schema::builder();
+        let timestamp_field = schema_builder.add_i64_field("timestamp", FAST | INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 100_000_000).unwrap();
+        for i in 0..NUM_DOCS {
+            index_writer.add_document(doc!(timestamp_field => FIRST_TIMESTAMP + i as i64));
+        }
+        index_writer.commit().unwrap();
+        (index.reader().unwrap(), timestamp_field)
+    }
+
+    fn last_timestamps(num_secs: i64) -> std::ops::Range<i64> {
+        let end = FIRST_TIMESTAMP + NUM_DOCS as i64;
+        (end - num_secs)..end
+    }
+
+    #[bench]
+    fn bench_wide_range_query_terms(bench: &mut Bencher) {
+        let (reader, timestamp_field) = dense_timestamp_reader();
+        let searcher = reader.searcher();
+        let range = last_timestamps(NUM_DOCS as i64 / 2);
+        let query = RangeQuery::new_i64(timestamp_field, range);
+        // Run the term based plan regardless of the heuristic.
+        let query = query.with_fast_field_fallback(false);
+        bench.iter(|| searcher.search(&query, &Count).unwrap());
+    }
+
+    #[bench]
+    fn bench_wide_range_query_fast_field(bench: &mut Bencher) {
+        let (reader, timestamp_field) = dense_timestamp_reader();
+        let searcher = reader.searcher();
+        let range = last_timestamps(NUM_DOCS as i64 / 2);
+        let query = FastFieldRangeQuery::new_i64_bounds(
+            timestamp_field,
+            Bound::Included(range.start),
+            Bound::Excluded(range.end),
+        );
+        bench.iter(|| searcher.search(&query, &Count).unwrap());
+    }
+
+    #[bench]
+    fn bench_narrow_range_query_terms(bench: &mut Bencher) {
+        let (reader, timestamp_field) = dense_timestamp_reader();
+        let searcher = reader.searcher();
+        let query = RangeQuery::new_i64(timestamp_field, last_timestamps(100))
+            .with_fast_field_fallback(false);
+        bench.iter(|| searcher.search(&query, &Count).unwrap());
+    }
+
+    #[bench]
+    fn bench_narrow_range_query_fast_field(bench: &mut Bencher) {
+        let (reader, timestamp_field) = dense_timestamp_reader();
+        let searcher = reader.searcher();
+        let range = last_timestamps(100);
+        let query = FastFieldRangeQuery::new_i64_bounds(
+            timestamp_field,
+            Bound::Included(range.start),
+            Bound::Excluded(range.end),
+        );
+        bench.iter(|| searcher.search(&query, &Count).unwrap());
+    }
+}
@@ -0,0 +1,311 @@
+use crate::core::{SegmentId, SegmentReader};
+use crate::query::{BitSetDocSet, ConstScorer, Explanation, Scorer, Weight};
+use crate::{DocId, Opstamp, Score};
+use common::{BinarySerializable, BitSet};
+use lru::LruCache;
+use std::ops::Bound;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Returns the cache key of a query of type `query_type`, defined by `content`.
+///
+/// The key is the serialization of the type and of the content, so that two queries
+/// only share a key if they have the same type and content.
+pub(crate) fn query_cache_key<T: BinarySerializable>(query_type: &str, content: &T) -> Vec<u8> {
+    let mut key = Vec::new();
+    query_type
+        .to_string()
+        .serialize(&mut key)
+        .and_then(|()| content.serialize(&mut key))
+        .expect("Writing to a Vec does not fail.");
+    key
+}
+
+/// Returns the cache key of the bound of a range.
+pub(crate) fn bound_cache_key(bound: &Bound<Vec<u8>>) -> (u8, Vec<u8>) {
+    match bound {
+        Bound::Included(bytes) => (0, bytes.clone()),
+        Bound::Excluded(bytes) => (1, bytes.clone()),
+        Bound::Unbounded => (2, Vec::new()),
+    }
+}
+
+/// Statistics of the filter cache of an [`IndexReader`](../struct.IndexReader.html).
+///
+/// See [`IndexReaderBuilder::filter_cache`](../struct.IndexReaderBuilder.html#method.filter_cache).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FilterCacheStats {
+    /// Number of filters of a segment read from the cache.
+    pub hits: u64,
+    /// Number of filters of a segment evaluated because they were not in the cache.
+    pub misses: u64,
+    /// Number of filters of a segment in the cache.
+    pub num_entries: usize,
+    /// Number of bytes of the bitsets in the cache.
+    pub num_bytes: usize,
+}
+
+// The delete opstamp tells apart the versions of a segment with different deletes.
+type FilterCacheKey = (SegmentId, Option<Opstamp>, Vec<u8>);
+
+struct FilterCacheEntries {
+    bitsets: LruCache<FilterCacheKey, Arc<BitSet>>,
+    num_bytes: usize,
+}
+
+/// Cache of the alive documents matching a filter, for each segment.
+///
+/// Segments are immutable, so an entry is never invalidated: the entries of the
+/// segments that are not searched anymore are evicted once the cache is full.
+pub(crate) struct FilterCache {
+    max_num_bytes: usize,
+    entries: Mutex<FilterCacheEntries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+fn num_bytes(bitset: &BitSet) -> usize {
+    (bitset.max_value() as usize + 63) / 64 * 8
+}
+
+impl FilterCache {
+    /// Creates a cache keeping at most `max_num_bytes` bytes of bitsets.
+    pub(crate) fn new(max_num_bytes: usize) -> FilterCache {
+        FilterCache {
+            max_num_bytes,
+            entries: Mutex::new(FilterCacheEntries {
+                bitsets: LruCache::unbounded(),
+                num_bytes: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> FilterCacheStats {
+        let entries = self.entries.lock().unwrap();
+        FilterCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            num_entries: entries.bitsets.len(),
+            num_bytes: entries.num_bytes,
+        }
+    }
+
+    fn get(&self, key: &FilterCacheKey) -> Option<Arc<BitSet>> {
+        let bitset = self.entries.lock().unwrap().bitsets.get(key).cloned();
+        let counter = if bitset.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        bitset
+    }
+
+    fn insert(&self, key: FilterCacheKey, bitset: Arc<BitSet>) {
+        let bitset_num_bytes = num_bytes(&bitset);
+        if bitset_num_bytes > self.max_num_bytes {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(previous_bitset) = entries.bitsets.put(key, bitset) {
+            entries.num_bytes -= num_bytes(&previous_bitset);
+        }
+        entries.num_bytes += bitset_num_bytes;
+        while entries.num_bytes > self.max_num_bytes {
+            if let Some((_, evicted_bitset)) = entries.bitsets.pop_lru() {
+                entries.num_bytes -= num_bytes(&evicted_bitset);
+            }
+        }
+    }
+}
+
+/// Weight of a filter, reading the documents matching it from the filter cache.
+pub(crate) struct CachedFilterWeight {
+    weight: Box<dyn Weight>,
+    query_key: Vec<u8>,
+    filter_cache: Arc<FilterCache>,
+}
+
+impl CachedFilterWeight {
+    pub(crate) fn new(
+        weight: Box<dyn Weight>,
+        query_key: Vec<u8>,
+        filter_cache: Arc<FilterCache>,
+    ) -> CachedFilterWeight {
+        CachedFilterWeight {
+            weight,
+            query_key,
+            filter_cache,
+        }
+    }
+
+    fn alive_docs(&self, reader: &SegmentReader) -> crate::Result<Arc<BitSet>> {
+        let key = (
+            reader.segment_id(),
+            reader.delete_opstamp(),
+            self.query_key.clone(),
+        );
+        if let Some(bitset) = self.filter_cache.get(&key) {
+            return Ok(bitset);
+        }
+        let mut bitset = BitSet::with_max_value(reader.max_doc());
+        self.weight.for_each(reader, &mut |doc, _| {
+            if !reader.is_deleted(doc) {
+                bitset.insert(doc);
+            }
+        })?;
+        let bitset = Arc::new(bitset);
+        self.filter_cache.insert(key, bitset.clone());
+        Ok(bitset)
+    }
+}
+
+impl Weight for CachedFilterWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let docs = BitSetDocSet::from(self.alive_docs(reader)?.as_ref().clone());
+        Ok(Box::new(ConstScorer::new(docs, boost)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        self.weight.explain(reader, doc)
+    }
+
+    fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
+        Ok(self.alive_docs(reader)?.len() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FilterCache;
+    use crate::core::SegmentId;
+    use crate::query::{BooleanQuery, Occur, Query, RangeQuery, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, INDEXED, STRING};
+    use crate::{doc, Index, ReloadPolicy, Term};
+    use common::BitSet;
+    use std::ops::Bound;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_filter_cache_compares_query_keys() {
+        let mut schema_builder = Schema::builder();
+        let tenant = schema_builder.add_text_field("tenant", STRING);
+        let term_query = |text| {
+            TermQuery::new(
+                Term::from_field_text(tenant, text),
+                IndexRecordOption::Basic,
+            )
+        };
+        assert_eq!(term_query("a").cache_key(), term_query("a").cache_key());
+        assert_ne!(term_query("a").cache_key(), term_query("ab").cache_key());
+        assert_ne!(
+            RangeQuery::new_str_bounds(tenant, Bound::Included("a"), Bound::Unbounded).cache_key(),
+            RangeQuery::new_str_bounds(tenant, Bound::Excluded("a"), Bound::Unbounded).cache_key()
+        );
+
+        let filter_cache = FilterCache::new(1_000);
+        let segment_id = SegmentId::generate_random();
+        let key = |query: &dyn Query| (segment_id, None, query.cache_key().unwrap());
+        filter_cache.insert(key(&term_query("a")), Arc::new(BitSet::with_max_value(8)));
+        assert!(filter_cache.get(&key(&term_query("a"))).is_some());
+        assert!(filter_cache.get(&key(&term_query("ab"))).is_none());
+        assert!(filter_cache.get(&key(&term_query("b"))).is_none());
+    }
+
+    #[test]
+    fn test_filter_cache_with_deletes() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let tenant = schema_builder.add_text_field("tenant", STRING);
+        let status = schema_builder.add_text_field("status", STRING);
+        let id = schema_builder.add_u64_field("id", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(id => 0u64, tenant => "a", status => "published"));
+        index_writer.add_document(doc!(id => 1u64, tenant => "a", status => "draft"));
+        index_writer.add_document(doc!(id => 2u64, tenant => "b", status => "published"));
+        index_writer.commit()?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .filter_cache(1_000_000)
+            .try_into()?;
+        let term_query = |field, text| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(field, text),
+                IndexRecordOption::Basic,
+            ))
+        };
+        let query = BooleanQuery::from(vec![
+            (Occur::Filter, term_query(tenant, "a")),
+            (Occur::MustNot, term_query(status, "draft")),
+        ]);
+
+        let searcher = reader.searcher();
+        assert_eq!(query.count(&searcher)?, 1);
+        let stats = searcher.filter_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.num_entries), (0, 2, 2));
+        assert_eq!(query.count(&searcher)?, 1);
+        let stats = searcher.filter_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses), (2, 2));
+
+        // deleting a document of the segment changes the filtered documents.
+        index_writer.add_document(doc!(id => 3u64, tenant => "a", status => "published"));
+        index_writer.delete_term(Term::from_field_text(status, "draft"));
+        index_writer.commit()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(query.count(&searcher)?, 2);
+        let stats = searcher.filter_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses), (2, 6));
+
+        // the deletes of a later commit change the filtered documents of the first
+        // segment, while the second segment is left untouched.
+        index_writer.delete_term(Term::from_field_u64(id, 0));
+        index_writer.commit()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(query.count(&searcher)?, 1);
+        let stats = searcher.filter_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses), (4, 8));
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_cache_eviction() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let tenant = schema_builder.add_text_field("tenant", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for _ in 0..1_000 {
+            index_writer.add_document(doc!(tenant => "a"));
+            index_writer.add_document(doc!(tenant => "b"));
+        }
+        index_writer.commit()?;
+        // 2_000 documents take 256 bytes per bitset.
+        let reader = index.reader_builder().filter_cache(300).try_into()?;
+        let searcher = reader.searcher();
+        let filter = |text| {
+            BooleanQuery::from(vec![(
+                Occur::Filter,
+                Box::new(TermQuery::new(
+                    Term::from_field_text(tenant, text),
+                    IndexRecordOption::Basic,
+                )) as Box<dyn Query>,
+            )])
+        };
+        assert_eq!(filter("a").count(&searcher)?, 1_000);
+        assert_eq!(filter("b").count(&searcher)?, 1_000);
+        let stats = searcher.filter_cache_stats().unwrap();
+        assert_eq!((stats.num_entries, stats.num_bytes), (1, 256));
+        assert_eq!(filter("b").count(&searcher)?, 1_000);
+        assert_eq!(filter("a").count(&searcher)?, 1_000);
+        let stats = searcher.filter_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses), (1, 3));
+
+        let reader = index.reader()?;
+        assert!(reader.searcher().filter_cache_stats().is_none());
+        Ok(())
+    }
+}
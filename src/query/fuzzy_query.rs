@@ -1,15 +1,27 @@
-use crate::query::{AutomatonWeight, Query, Weight};
-use crate::schema::Term;
+use crate::core::SegmentReader;
+use crate::postings::TermInfo;
+use crate::query::explanation::does_not_match;
+use crate::query::score_combiner::SumCombiner;
+use crate::query::{BitSetDocSet, ConstScorer, EmptyScorer, Explanation};
+use crate::query::{Query, QueryVisitor, Scorer, Union, Weight};
+use crate::schema::{Field, IndexRecordOption, Term};
+use crate::termdict::{prefix_successor, TermDictionary, TermStreamer};
 use crate::Searcher;
 use crate::TantivyError::InvalidArgument;
+use crate::{DocId, Score};
+use common::BitSet;
 use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA};
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
+use std::io;
 use std::ops::Range;
+use std::sync::Arc;
 use tantivy_fst::Automaton;
 
+#[cfg(test)]
 pub(crate) struct DfaWrapper(pub DFA);
 
+#[cfg(test)]
 impl Automaton for DfaWrapper {
     type State = u32;
 
@@ -33,6 +45,87 @@ impl Automaton for DfaWrapper {
     }
 }
 
+/// State of a [`FuzzyAutomaton`](./struct.FuzzyAutomaton.html).
+#[derive(Clone, Copy)]
+pub enum FuzzyState {
+    /// Number of bytes of the prefix accepted so far.
+    Prefix(usize),
+    /// State of the Levenshtein DFA of the suffix.
+    Dfa(u32),
+    /// No term with the bytes accepted so far matches.
+    Sink,
+}
+
+/// Automaton matching the terms starting with an exact prefix,
+/// followed by a suffix accepted by a Levenshtein DFA.
+///
+/// It is built with [`FuzzyTermQuery::automaton`](./struct.FuzzyTermQuery.html#method.automaton),
+/// and can search a [`TermDictionary`](../termdict/type.TermDictionary.html).
+pub struct FuzzyAutomaton {
+    prefix: Vec<u8>,
+    dfa: DFA,
+}
+
+impl FuzzyAutomaton {
+    /// Returns the Levenshtein distance of a matching term.
+    fn distance(&self, term_bytes: &[u8]) -> Option<u8> {
+        let suffix = term_bytes.strip_prefix(&self.prefix[..])?;
+        match self.dfa.eval(suffix) {
+            Distance::Exact(distance) => Some(distance),
+            Distance::AtLeast(_) => None,
+        }
+    }
+
+    fn dfa_state(&self, state: u32) -> FuzzyState {
+        if state == levenshtein_automata::SINK_STATE {
+            FuzzyState::Sink
+        } else {
+            FuzzyState::Dfa(state)
+        }
+    }
+}
+
+impl Automaton for FuzzyAutomaton {
+    type State = FuzzyState;
+
+    fn start(&self) -> Self::State {
+        if self.prefix.is_empty() {
+            self.dfa_state(self.dfa.initial_state())
+        } else {
+            FuzzyState::Prefix(0)
+        }
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        match *state {
+            FuzzyState::Dfa(dfa_state) => {
+                matches!(self.dfa.distance(dfa_state), Distance::Exact(_))
+            }
+            FuzzyState::Prefix(_) | FuzzyState::Sink => false,
+        }
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        !matches!(*state, FuzzyState::Sink)
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        match *state {
+            FuzzyState::Prefix(num_accepted) => {
+                if self.prefix[num_accepted] != byte {
+                    FuzzyState::Sink
+                } else if num_accepted + 1 == self.prefix.len() {
+                    self.dfa_state(self.dfa.initial_state())
+                } else {
+                    FuzzyState::Prefix(num_accepted + 1)
+                }
+            }
+            FuzzyState::Dfa(dfa_state) => self.dfa_state(self.dfa.transition(dfa_state, byte)),
+            FuzzyState::Sink => FuzzyState::Sink,
+        }
+    }
+}
+
 /// A range of Levenshtein distances that we will build DFAs for our terms
 /// The computation is exponential, so best keep it to low single digits
 const VALID_LEVENSHTEIN_DISTANCE_RANGE: Range<u8> = 0..3;
@@ -52,6 +145,14 @@ static LEV_BUILDER: Lazy<HashMap<(u8, bool), LevenshteinAutomatonBuilder>> = Laz
 /// A Fuzzy Query matches all of the documents
 /// containing a specific term that is within
 /// Levenshtein distance
+///
+/// Documents are scored `1 / (1 + distance)`, `distance` being the smallest
+/// distance between the term and the terms of the document. Exact matches are
+/// hence scored `1`, above the matches at a distance of `1`, scored `0.5`.
+///
+/// Requiring the first characters of the term to match exactly, with a non zero
+/// prefix length, restricts the part of the term dictionary being visited, and
+/// makes the query much cheaper on large dictionaries.
 /// ```rust
 /// use tantivy::collector::{Count, TopDocs};
 /// use tantivy::query::FuzzyTermQuery;
@@ -104,16 +205,30 @@ pub struct FuzzyTermQuery {
     transposition_cost_one: bool,
     ///
     prefix: bool,
+    /// How many characters at the beginning of the term must match exactly
+    prefix_length: usize,
 }
 
 impl FuzzyTermQuery {
     /// Creates a new Fuzzy Query
     pub fn new(term: Term, distance: u8, transposition_cost_one: bool) -> FuzzyTermQuery {
+        FuzzyTermQuery::new_with_params(term, distance, transposition_cost_one, 0)
+    }
+
+    /// Creates a new Fuzzy Query, requiring the first `prefix_length` characters
+    /// of the term to match exactly.
+    pub fn new_with_params(
+        term: Term,
+        distance: u8,
+        transposition_cost_one: bool,
+        prefix_length: usize,
+    ) -> FuzzyTermQuery {
         FuzzyTermQuery {
             term,
             distance,
             transposition_cost_one,
             prefix: false,
+            prefix_length,
         }
     }
 
@@ -124,23 +239,65 @@ impl FuzzyTermQuery {
             distance,
             transposition_cost_one,
             prefix: true,
+            prefix_length: 0,
         }
     }
 
-    fn specialized_weight(&self) -> crate::Result<AutomatonWeight<DfaWrapper>> {
+    /// The term searched
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
+    /// The maximum Levenshtein distance to the term
+    pub fn distance(&self) -> u8 {
+        self.distance
+    }
+
+    /// Returns true if a transposition costs one, rather than two.
+    pub fn transposition_cost_one(&self) -> bool {
+        self.transposition_cost_one
+    }
+
+    /// Returns true if the query matches the terms having a prefix close to the term.
+    pub fn is_prefix(&self) -> bool {
+        self.prefix
+    }
+
+    /// The number of characters at the beginning of the term that must match exactly
+    pub fn prefix_length(&self) -> usize {
+        self.prefix_length
+    }
+
+    /// Splits the text of the term into its exact prefix and the rest of the term.
+    fn split_prefix(&self) -> (&str, &str) {
+        let text = self.term.text();
+        let prefix_len = text
+            .char_indices()
+            .nth(self.prefix_length)
+            .map(|(byte_offset, _)| byte_offset)
+            .unwrap_or(text.len());
+        text.split_at(prefix_len)
+    }
+
+    /// Returns the automaton matching the terms of the query, to search the terms of a
+    /// [`TermDictionary`](../termdict/type.TermDictionary.html).
+    ///
+    /// Returns an error if the distance of the query is not supported.
+    pub fn automaton(&self) -> crate::Result<FuzzyAutomaton> {
         // LEV_BUILDER is a HashMap, whose `get` method returns an Option
-        match LEV_BUILDER.get(&(self.distance, false)) {
-            // Unwrap the option and build the Ok(AutomatonWeight)
+        match LEV_BUILDER.get(&(self.distance, self.transposition_cost_one)) {
+            // Unwrap the option and build the FuzzyAutomaton
             Some(automaton_builder) => {
-                let automaton = if self.prefix {
-                    automaton_builder.build_prefix_dfa(self.term.text())
+                let (prefix, suffix) = self.split_prefix();
+                let dfa = if self.prefix {
+                    automaton_builder.build_prefix_dfa(suffix)
                 } else {
-                    automaton_builder.build_dfa(self.term.text())
+                    automaton_builder.build_dfa(suffix)
                 };
-                Ok(AutomatonWeight::new(
-                    self.term.field(),
-                    DfaWrapper(automaton),
-                ))
+                Ok(FuzzyAutomaton {
+                    prefix: prefix.as_bytes().to_vec(),
+                    dfa,
+                })
             }
             None => Err(InvalidArgument(format!(
                 "Levenshtein distance of {} is not allowed. Choose a value in the {:?} range",
@@ -156,19 +313,138 @@ impl Query for FuzzyTermQuery {
         _searcher: &Searcher,
         _scoring_enabled: bool,
     ) -> crate::Result<Box<dyn Weight>> {
-        Ok(Box::new(self.specialized_weight()?))
+        Ok(Box::new(FuzzyWeight {
+            field: self.term.field(),
+            automaton: Arc::new(self.automaton()?),
+            max_distance: self.distance,
+        }))
+    }
+
+    fn visit(&self, visitor: &mut dyn QueryVisitor) {
+        visitor.visit_fuzzy_query(self);
+    }
+}
+
+/// Score of the documents matching a term at the given distance.
+fn distance_score(distance: u8) -> Score {
+    1.0 / (1.0 + distance as Score)
+}
+
+struct FuzzyWeight {
+    field: Field,
+    automaton: Arc<FuzzyAutomaton>,
+    max_distance: u8,
+}
+
+impl FuzzyWeight {
+    fn automaton_stream<'a>(
+        &'a self,
+        term_dict: &'a TermDictionary,
+    ) -> io::Result<TermStreamer<'a, &'a FuzzyAutomaton>> {
+        let automaton: &FuzzyAutomaton = &self.automaton;
+        let mut term_stream_builder = term_dict.search(automaton);
+        let prefix = &self.automaton.prefix;
+        if !prefix.is_empty() {
+            term_stream_builder = term_stream_builder.ge(prefix);
+            if let Some(prefix_successor) = prefix_successor(prefix) {
+                term_stream_builder = term_stream_builder.lt(prefix_successor);
+            }
+        }
+        term_stream_builder.into_stream()
+    }
+
+    /// Returns the documents matching the term, grouped by distance.
+    ///
+    /// Documents only appear with the smallest distance of their terms.
+    fn docs_per_distance(&self, reader: &SegmentReader) -> crate::Result<Vec<BitSet>> {
+        let max_doc = reader.max_doc();
+        let inverted_index = reader.inverted_index(self.field)?;
+        let mut term_infos_per_distance: Vec<Vec<TermInfo>> =
+            vec![Vec::new(); self.max_distance as usize + 1];
+        let mut term_stream = self.automaton_stream(inverted_index.terms())?;
+        while term_stream.advance() {
+            if let Some(distance) = self.automaton.distance(term_stream.key()) {
+                term_infos_per_distance[distance as usize].push(term_stream.value().clone());
+            }
+        }
+        let mut matched_docs = BitSet::with_max_value(max_doc);
+        let mut docs_per_distance = Vec::with_capacity(term_infos_per_distance.len());
+        for term_infos in term_infos_per_distance {
+            let mut doc_bitset = BitSet::with_max_value(max_doc);
+            for term_info in &term_infos {
+                let mut block_segment_postings = inverted_index
+                    .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
+                loop {
+                    let docs = block_segment_postings.docs();
+                    if docs.is_empty() {
+                        break;
+                    }
+                    for &doc in docs {
+                        if !matched_docs.contains(doc) {
+                            matched_docs.insert(doc);
+                            doc_bitset.insert(doc);
+                        }
+                    }
+                    block_segment_postings.advance();
+                }
+            }
+            docs_per_distance.push(doc_bitset);
+        }
+        Ok(docs_per_distance)
+    }
+}
+
+impl Weight for FuzzyWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let scorers: Vec<ConstScorer<BitSetDocSet>> = self
+            .docs_per_distance(reader)?
+            .into_iter()
+            .enumerate()
+            .filter(|(_, doc_bitset)| doc_bitset.len() > 0)
+            .map(|(distance, doc_bitset)| {
+                let score = boost * distance_score(distance as u8);
+                ConstScorer::new(BitSetDocSet::from(doc_bitset), score)
+            })
+            .collect();
+        match scorers.len() {
+            0 => Ok(Box::new(EmptyScorer)),
+            1 => Ok(Box::new(scorers.into_iter().next().unwrap())),
+            // The sets of documents are disjoint, so the sum is the score of the only match.
+            _ => Ok(Box::new(Union::<_, SumCombiner>::from(scorers))),
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        for (distance, doc_bitset) in self.docs_per_distance(reader)?.iter().enumerate() {
+            if doc_bitset.contains(doc) {
+                let mut explanation = Explanation::new(
+                    "FuzzyTermQuery, 1 / (1 + distance)",
+                    distance_score(distance as u8),
+                );
+                explanation.add_const("distance", distance as Score);
+                return Ok(explanation);
+            }
+        }
+        Err(does_not_match(doc))
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::FuzzyTermQuery;
+    use super::{FuzzyAutomaton, FuzzyTermQuery};
     use crate::assert_nearly_equals;
-    use crate::collector::TopDocs;
+    use crate::collector::{Count, TopDocs};
+    use crate::directory::{Directory, RamDirectory, TerminatingWrite};
+    use crate::postings::TermInfo;
+    use crate::query::Query;
     use crate::schema::Schema;
     use crate::schema::TEXT;
-    use crate::Index;
+    use crate::termdict::{TermDictionary, TermDictionaryBuilder};
     use crate::Term;
+    use crate::{DocAddress, Index};
+    use std::cell::Cell;
+    use std::path::PathBuf;
+    use tantivy_fst::Automaton;
 
     #[test]
     pub fn test_fuzzy_term() {
@@ -199,7 +475,7 @@ mod test {
                 .unwrap();
             assert_eq!(top_docs.len(), 1, "Expected only 1 document");
             let (score, _) = top_docs[0];
-            assert_nearly_equals!(1.0, score);
+            assert_nearly_equals!(0.5, score);
         }
 
         // fails because non-prefix Levenshtein distance is more than 1 (add 'a' and 'n')
@@ -226,4 +502,126 @@ mod test {
             assert_nearly_equals!(1.0, score);
         }
     }
+
+    #[test]
+    pub fn test_fuzzy_term_params() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let country_field = schema_builder.add_text_field("country", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(country_field => "japan"));
+        index_writer.add_document(doc!(country_field => "japon"));
+        index_writer.add_document(doc!(country_field => "jopon"));
+        index_writer.add_document(doc!(country_field => "jpaan"));
+        index_writer.add_document(doc!(country_field => "japon jopon"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term = |text: &str| Term::from_field_text(country_field, text);
+
+        // Closer terms are scored higher.
+        let fuzzy_query = FuzzyTermQuery::new(term("japan"), 2, false);
+        let top_docs = searcher.search(&fuzzy_query, &TopDocs::with_limit(5))?;
+        let scores: Vec<f32> = top_docs.iter().map(|(score, _)| *score).collect();
+        assert_eq!(top_docs[0].1, DocAddress::new(0, 0));
+        assert_nearly_equals!(scores[0], 1.0);
+        assert_nearly_equals!(scores[1], 0.5);
+        assert_nearly_equals!(scores[2], 0.5);
+        assert_nearly_equals!(scores[3], 1.0 / 3.0);
+        assert_nearly_equals!(scores[4], 1.0 / 3.0);
+        // A document is scored with its closest term.
+        let explanation = fuzzy_query.explain(&searcher, DocAddress::new(0, 4))?;
+        assert_nearly_equals!(explanation.value(), 0.5);
+
+        // Transpositions cost one only if requested.
+        let count = |query: &FuzzyTermQuery| searcher.search(query, &Count);
+        assert_eq!(count(&FuzzyTermQuery::new(term("japan"), 1, true))?, 4);
+        assert_eq!(count(&FuzzyTermQuery::new(term("japan"), 1, false))?, 3);
+
+        // The first characters have to match exactly.
+        assert_eq!(count(&FuzzyTermQuery::new(term("kapan"), 1, true))?, 1);
+        assert_eq!(
+            count(&FuzzyTermQuery::new_with_params(term("kapan"), 1, true, 1))?,
+            0
+        );
+        assert_eq!(
+            count(&FuzzyTermQuery::new_with_params(term("japan"), 1, true, 2))?,
+            3
+        );
+        assert_eq!(
+            count(&FuzzyTermQuery::new_with_params(term("japan"), 2, true, 10))?,
+            1
+        );
+        Ok(())
+    }
+
+    /// Counts the transitions visited in the term dictionary.
+    struct CountingAutomaton<'a> {
+        automaton: FuzzyAutomaton,
+        num_transitions: &'a Cell<usize>,
+    }
+
+    impl<'a> Automaton for CountingAutomaton<'a> {
+        type State = <FuzzyAutomaton as Automaton>::State;
+
+        fn start(&self) -> Self::State {
+            self.automaton.start()
+        }
+
+        fn is_match(&self, state: &Self::State) -> bool {
+            self.automaton.is_match(state)
+        }
+
+        fn can_match(&self, state: &Self::State) -> bool {
+            self.automaton.can_match(state)
+        }
+
+        fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+            self.num_transitions.set(self.num_transitions.get() + 1);
+            self.automaton.accept(state, byte)
+        }
+    }
+
+    #[test]
+    pub fn test_fuzzy_term_prefix_length_visits_less_terms() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let path = PathBuf::from("TermDictionary");
+        {
+            let write = directory.open_write(&path)?;
+            let mut term_dictionary_builder = TermDictionaryBuilder::create(write)?;
+            for i in 0u32..100_000u32 {
+                let term = format!("{:06}", i);
+                term_dictionary_builder.insert(term.as_bytes(), &TermInfo::default())?;
+            }
+            term_dictionary_builder.finish()?.terminate()?;
+        }
+        let term_dict = TermDictionary::open(directory.open_read(&path)?)?;
+        let field = Schema::builder().add_text_field("text", TEXT);
+
+        // Returns the number of terms matched, and the number of transitions visited.
+        let search = |prefix_length: usize| -> crate::Result<(usize, usize)> {
+            let query = FuzzyTermQuery::new_with_params(
+                Term::from_field_text(field, "012345"),
+                2,
+                true,
+                prefix_length,
+            );
+            let num_transitions = Cell::new(0);
+            let automaton = CountingAutomaton {
+                automaton: query.automaton()?,
+                num_transitions: &num_transitions,
+            };
+            let mut term_stream = term_dict.search(automaton).into_stream()?;
+            let mut num_terms = 0;
+            while term_stream.advance() {
+                num_terms += 1;
+            }
+            Ok((num_terms, num_transitions.get()))
+        };
+        let (num_terms, num_transitions) = search(0)?;
+        let (num_terms_with_prefix, num_transitions_with_prefix) = search(3)?;
+        assert!(num_terms_with_prefix > 0);
+        assert!(num_terms_with_prefix < num_terms);
+        assert!(num_transitions_with_prefix * 10 < num_transitions);
+        Ok(())
+    }
 }
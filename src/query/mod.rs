@@ -6,58 +6,81 @@ mod bitset;
 mod bm25;
 mod boolean_query;
 mod boost_query;
+mod const_score_query;
+mod disjunction;
+mod disjunction_max_query;
 mod empty_query;
 mod exclude;
+mod exists_query;
 mod explanation;
+mod fast_field_range_query;
+mod filter_cache;
 mod fuzzy_query;
 mod intersection;
 mod more_like_this;
 mod phrase_query;
 mod query;
 mod query_parser;
+#[cfg(feature = "query-serde")]
+mod query_serde;
+mod query_visitor;
 mod range_query;
 mod regex_query;
 mod reqopt_scorer;
 mod scorer;
+mod similarity;
 mod term_query;
+mod term_set_query;
 mod union;
-mod weight;
-
-#[cfg(test)]
 mod vec_docset;
+mod weight;
+mod wildcard_query;
 
 pub(crate) mod score_combiner;
-pub(crate) use self::bm25::Bm25Weight;
+pub use self::bm25::Bm25Similarity;
 pub use self::intersection::Intersection;
 pub use self::union::Union;
 
-#[cfg(test)]
-pub use self::vec_docset::VecDocSet;
+pub(crate) use self::vec_docset::VecDocSet;
 
 pub use self::all_query::{AllQuery, AllScorer, AllWeight};
 pub use self::automaton_weight::AutomatonWeight;
 pub use self::bitset::BitSetDocSet;
 pub use self::boolean_query::BooleanQuery;
 pub use self::boost_query::BoostQuery;
+pub use self::const_score_query::ConstScoreQuery;
+pub(crate) use self::disjunction::Disjunction;
+pub use self::disjunction_max_query::DisjunctionMaxQuery;
 pub use self::empty_query::{EmptyQuery, EmptyScorer, EmptyWeight};
 pub use self::exclude::Exclude;
+pub use self::exists_query::ExistsQuery;
 pub use self::explanation::Explanation;
+pub use self::fast_field_range_query::FastFieldRangeQuery;
+pub(crate) use self::filter_cache::FilterCache;
+pub use self::filter_cache::FilterCacheStats;
 #[cfg(test)]
 pub(crate) use self::fuzzy_query::DfaWrapper;
-pub use self::fuzzy_query::FuzzyTermQuery;
+pub use self::fuzzy_query::{FuzzyAutomaton, FuzzyState, FuzzyTermQuery};
 pub use self::intersection::intersect_scorers;
 pub use self::more_like_this::{MoreLikeThisQuery, MoreLikeThisQueryBuilder};
 pub use self::phrase_query::PhraseQuery;
 pub use self::query::{Query, QueryClone};
 pub use self::query_parser::QueryParser;
 pub use self::query_parser::QueryParserError;
+#[cfg(feature = "query-serde")]
+pub use self::query_serde::{BooleanClause, FieldTerm, PhraseTerm, QueryDescription, TermValue};
+pub use self::query_visitor::{rewrite_query, QueryRewriter, QueryVisitor};
 pub use self::range_query::RangeQuery;
 pub use self::regex_query::RegexQuery;
 pub use self::reqopt_scorer::RequiredOptionalScorer;
 pub use self::scorer::ConstScorer;
 pub use self::scorer::Scorer;
+pub(crate) use self::similarity::SimilarityWeight;
+pub use self::similarity::{BooleanSimilarity, BuiltinSimilarity, ClassicTfIdf, Similarity};
 pub use self::term_query::TermQuery;
+pub use self::term_set_query::TermSetQuery;
 pub use self::weight::Weight;
+pub use self::wildcard_query::WildcardQuery;
 pub use tantivy_query_grammar::Occur;
 
 #[cfg(test)]
@@ -1,9 +1,8 @@
-use std::cmp::Reverse;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::HashMap;
 
 use crate::{
     query::{bm25::idf, BooleanQuery, BoostQuery, Occur, Query, TermQuery},
-    schema::{Field, FieldType, FieldValue, IndexRecordOption, Term, Value},
+    schema::{Document, Field, FieldType, FieldValue, IndexRecordOption, Term, Value},
     tokenizer::{BoxTokenStream, FacetTokenizer, PreTokenizedStream, Tokenizer},
     DocAddress, Result, Searcher, TantivyError,
 };
@@ -50,6 +49,8 @@ pub struct MoreLikeThis {
     pub min_term_frequency: Option<usize>,
     /// Don't return a query longer than this.
     pub max_query_terms: Option<usize>,
+    /// Don't return a query with more terms than this for any field.
+    pub max_query_terms_per_field: Option<usize>,
     /// Ignore words if less than this length.
     pub min_word_length: Option<usize>,
     /// Ignore words if greater than this length.
@@ -67,6 +68,7 @@ impl Default for MoreLikeThis {
             max_doc_frequency: None,
             min_term_frequency: Some(2),
             max_query_terms: Some(25),
+            max_query_terms_per_field: None,
             min_word_length: None,
             max_word_length: None,
             boost_factor: Some(1.0),
@@ -126,19 +128,7 @@ impl MoreLikeThis {
         doc_address: DocAddress,
     ) -> Result<Vec<ScoreTerm>> {
         let doc = searcher.doc(doc_address)?;
-        let field_to_field_values = doc
-            .get_sorted_field_values()
-            .iter()
-            .map(|(field, values)| {
-                (
-                    *field,
-                    values
-                        .iter()
-                        .map(|v| (**v).clone())
-                        .collect::<Vec<FieldValue>>(),
-                )
-            })
-            .collect::<Vec<_>>();
+        let field_to_field_values = document_fields(&doc);
         self.retrieve_terms_from_doc_fields(searcher, &field_to_field_values)
     }
 
@@ -211,7 +201,7 @@ impl MoreLikeThis {
                             if let Some(tokenizer) = text_options
                                 .get_indexing_options()
                                 .map(|text_indexing_options| {
-                                    text_indexing_options.tokenizer().to_string()
+                                    text_indexing_options.search_tokenizer().to_string()
                                 })
                                 .and_then(|tokenizer_name| tokenizer_manager.get(&tokenizer_name))
                             {
@@ -312,7 +302,7 @@ impl MoreLikeThis {
         searcher: &Searcher,
         per_field_term_frequencies: HashMap<Term, usize>,
     ) -> Result<Vec<ScoreTerm>> {
-        let mut score_terms: BinaryHeap<Reverse<ScoreTerm>> = BinaryHeap::new();
+        let mut score_terms: Vec<ScoreTerm> = Vec::new();
         let num_docs = searcher
             .segment_readers()
             .iter()
@@ -357,26 +347,39 @@ impl MoreLikeThis {
             // compute similarity & score
             let idf = idf(doc_freq, num_docs);
             let score = (*term_frequency as f32) * idf;
-            if let Some(limit) = self.max_query_terms {
-                if score_terms.len() > limit {
-                    // update the least significant term
-                    let least_significant_term_score = score_terms.peek().unwrap().0.score;
-                    if least_significant_term_score < score {
-                        score_terms.peek_mut().unwrap().0 = ScoreTerm::new(term.clone(), score);
-                    }
-                } else {
-                    score_terms.push(Reverse(ScoreTerm::new(term.clone(), score)));
-                }
-            } else {
-                score_terms.push(Reverse(ScoreTerm::new(term.clone(), score)));
-            }
+            score_terms.push(ScoreTerm::new(term.clone(), score));
         }
 
-        let score_terms_vec: Vec<ScoreTerm> = score_terms
-            .into_iter()
-            .map(|reverse_score_term| reverse_score_term.0)
-            .collect();
+        // keep the most significant terms
+        score_terms.sort_by(|left_ts, right_ts| right_ts.cmp(left_ts));
+        if let Some(limit) = self.max_query_terms_per_field {
+            let mut field_num_terms: HashMap<Field, usize> = HashMap::new();
+            score_terms.retain(|score_term| {
+                let num_terms = field_num_terms.entry(score_term.term.field()).or_insert(0);
+                *num_terms += 1;
+                *num_terms <= limit
+            });
+        }
+        if let Some(limit) = self.max_query_terms {
+            score_terms.truncate(limit);
+        }
 
-        Ok(score_terms_vec)
+        Ok(score_terms)
     }
 }
+
+/// Groups the field values of a document by field.
+pub(crate) fn document_fields(doc: &Document) -> Vec<(Field, Vec<FieldValue>)> {
+    doc.get_sorted_field_values()
+        .iter()
+        .map(|(field, values)| {
+            (
+                *field,
+                values
+                    .iter()
+                    .map(|v| (**v).clone())
+                    .collect::<Vec<FieldValue>>(),
+            )
+        })
+        .collect()
+}
@@ -1,8 +1,9 @@
+use super::more_like_this::document_fields;
 use super::MoreLikeThis;
 
 use crate::{
     query::{Query, Weight},
-    schema::{Field, FieldValue},
+    schema::{Document, Field, FieldValue},
     DocAddress, Result, Searcher,
 };
 
@@ -79,6 +80,9 @@ impl MoreLikeThisQueryBuilder {
     ///
     /// The resulting query will ignore words which do not occur
     /// in at least this many docs.
+    ///
+    /// The document frequencies are computed against the searched index,
+    /// also for documents given by their field values.
     pub fn with_min_doc_frequency(mut self, value: u64) -> Self {
         self.mlt.min_doc_frequency = Some(value);
         self
@@ -110,6 +114,15 @@ impl MoreLikeThisQueryBuilder {
         self
     }
 
+    /// Sets the maximum query terms per field.
+    ///
+    /// The resulting query will not have more clauses than this for any field,
+    /// so that a long field cannot crowd out the terms of the other fields.
+    pub fn with_max_query_terms_per_field(mut self, value: usize) -> Self {
+        self.mlt.max_query_terms_per_field = Some(value);
+        self
+    }
+
     /// Sets the minimum word length.
     ///
     /// The resulting query will ignore words shorter than this length.
@@ -173,6 +186,17 @@ impl MoreLikeThisQueryBuilder {
             target: TargetDocument::DocumentFields(doc_fields),
         }
     }
+
+    /// Sets a document, that does not need to be in the index
+    /// Returns the constructed [`MoreLikeThisQuery`]
+    ///
+    /// The values of the document are tokenized at query time, with the tokenizer
+    /// of their field, to extract the terms needed for composing the query.
+    /// Unlike with [`with_document`](#method.with_document), all of the
+    /// indexed fields are used, whether they are stored or not.
+    pub fn with_raw_document(self, document: &Document) -> MoreLikeThisQuery {
+        self.with_document_fields(document_fields(document))
+    }
 }
 
 #[cfg(test)]
@@ -183,6 +207,7 @@ mod tests {
     use crate::schema::{Schema, STORED, TEXT};
     use crate::DocAddress;
     use crate::Index;
+    use crate::Term;
 
     fn create_test_index() -> Index {
         let mut schema_builder = Schema::builder();
@@ -209,6 +234,7 @@ mod tests {
         assert_eq!(query.mlt.max_doc_frequency, None);
         assert_eq!(query.mlt.min_term_frequency, Some(2));
         assert_eq!(query.mlt.max_query_terms, Some(25));
+        assert_eq!(query.mlt.max_query_terms_per_field, None);
         assert_eq!(query.mlt.min_word_length, None);
         assert_eq!(query.mlt.max_word_length, None);
         assert_eq!(query.mlt.boost_factor, Some(1.0));
@@ -220,6 +246,7 @@ mod tests {
             .with_min_doc_frequency(2)
             .with_max_doc_frequency(5)
             .with_min_term_frequency(2)
+            .with_max_query_terms_per_field(3)
             .with_min_word_length(2)
             .with_max_word_length(4)
             .with_boost_factor(0.5)
@@ -229,6 +256,7 @@ mod tests {
         assert_eq!(query.mlt.min_doc_frequency, Some(2));
         assert_eq!(query.mlt.max_doc_frequency, Some(5));
         assert_eq!(query.mlt.min_term_frequency, Some(2));
+        assert_eq!(query.mlt.max_query_terms_per_field, Some(3));
         assert_eq!(query.mlt.min_word_length, Some(2));
         assert_eq!(query.mlt.max_word_length, Some(4));
         assert_eq!(query.mlt.boost_factor, Some(0.5));
@@ -281,4 +309,90 @@ mod tests {
         assert_eq!(doc_ids.len(), 2);
         assert_eq!(doc_ids, vec![3, 4]);
     }
+
+    #[test]
+    fn test_more_like_this_query_with_raw_document() {
+        let index = create_test_index();
+        let body = index.schema().get_field("body").unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let builder = || {
+            MoreLikeThisQuery::builder()
+                .with_min_doc_frequency(1)
+                .with_min_term_frequency(1)
+                .with_stop_words(vec!["the".to_string()])
+        };
+
+        // The 1st doc, given by its address or by its text.
+        let top_docs =
+            |query: &MoreLikeThisQuery| searcher.search(query, &TopDocs::with_limit(5)).unwrap();
+        let raw_document = doc!(body => "the old man and the sea");
+        assert_eq!(
+            top_docs(&builder().with_raw_document(&raw_document)),
+            top_docs(&builder().with_document(DocAddress::new(0, 0)))
+        );
+
+        // A document that is not in the index.
+        let raw_document = doc!(body => "alice rides a bike to the sea");
+        let query = builder()
+            .with_min_doc_frequency(2)
+            .with_raw_document(&raw_document);
+        let mut doc_ids: Vec<_> = top_docs(&query)
+            .iter()
+            .map(|(_, doc_address)| doc_address.doc_id)
+            .collect();
+        doc_ids.sort_unstable();
+        // The doc frequencies are computed against the index:
+        // [alice, rides, a, bike] occur in less than 2 docs, only [sea] is kept.
+        assert_eq!(doc_ids, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_more_like_this_query_max_query_terms_per_field() {
+        let index = create_test_index();
+        let title = index.schema().get_field("title").unwrap();
+        let body = index.schema().get_field("body").unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let raw_document = doc!(title => "aaa bbb", body => "old man sailing on the sea");
+        let query_fields = |query: MoreLikeThisQuery| -> Vec<usize> {
+            let document_fields = match query.target {
+                TargetDocument::DocumentFields(document_fields) => document_fields,
+                TargetDocument::DocumentAdress(_) => unreachable!(),
+            };
+            let query = query
+                .mlt
+                .query_with_document_fields(&searcher, &document_fields)
+                .unwrap();
+            let mut terms = std::collections::BTreeMap::<Term, bool>::new();
+            crate::query::Query::query_terms(&query, &mut terms);
+            [title, body]
+                .iter()
+                .map(|field| terms.keys().filter(|term| term.field() == *field).count())
+                .collect()
+        };
+        let builder = || {
+            MoreLikeThisQuery::builder()
+                .with_min_doc_frequency(1)
+                .with_min_term_frequency(1)
+        };
+        assert_eq!(
+            query_fields(builder().with_raw_document(&raw_document)),
+            vec![2, 6]
+        );
+        assert_eq!(
+            query_fields(
+                builder()
+                    .with_max_query_terms_per_field(3)
+                    .with_raw_document(&raw_document)
+            ),
+            vec![2, 3]
+        );
+        let num_terms: usize = query_fields(
+            builder()
+                .with_max_query_terms(4)
+                .with_raw_document(&raw_document),
+        )
+        .iter()
+        .sum();
+        assert_eq!(num_terms, 4);
+    }
 }
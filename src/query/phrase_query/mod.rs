@@ -13,10 +13,10 @@ pub mod tests {
     use crate::assert_nearly_equals;
     use crate::collector::tests::{TEST_COLLECTOR_WITHOUT_SCORE, TEST_COLLECTOR_WITH_SCORE};
     use crate::core::Index;
-    use crate::query::Weight;
+    use crate::query::{Query, Weight};
     use crate::schema::{Schema, Term, TEXT};
-    use crate::DocId;
     use crate::{DocAddress, TERMINATED};
+    use crate::{DocId, Score};
 
     pub fn create_index(texts: &[&'static str]) -> Index {
         let mut schema_builder = Schema::builder();
@@ -246,4 +246,149 @@ pub mod tests {
         assert!(test_query(vec![(0, "a"), (2, "d")]).is_empty());
         assert_eq!(test_query(vec![(1, "a"), (3, "c")]), vec![0]);
     }
+
+    #[test]
+    pub fn test_phrase_query_slop() {
+        let index = create_index(&[
+            "quick fox jumps over",
+            "quick brown fox over",
+            "quick brown fast fox",
+            "fox jumps over quick",
+            "quick brown fast red fox",
+        ]);
+        let schema = index.schema();
+        let text_field = schema.get_field("text").unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let test_query = |texts: Vec<&str>, slop: u32| {
+            let terms: Vec<Term> = texts
+                .iter()
+                .map(|text| Term::from_field_text(text_field, text))
+                .collect();
+            let mut phrase_query = PhraseQuery::new(terms);
+            phrase_query.set_slop(slop);
+            let test_fruits = searcher
+                .search(&phrase_query, &TEST_COLLECTOR_WITH_SCORE)
+                .expect("search should succeed");
+            test_fruits
+                .docs()
+                .iter()
+                .map(|doc_address| doc_address.doc_id)
+                .zip(test_fruits.scores().iter().cloned())
+                .collect::<Vec<_>>()
+        };
+        let docs = |doc_scores: Vec<(DocId, Score)>| -> Vec<DocId> {
+            doc_scores.into_iter().map(|(doc, _)| doc).collect()
+        };
+        assert_eq!(docs(test_query(vec!["quick", "fox"], 0)), vec![0]);
+        assert_eq!(docs(test_query(vec!["quick", "fox"], 1)), vec![0, 1]);
+        assert_eq!(docs(test_query(vec!["quick", "fox"], 2)), vec![0, 1, 2]);
+        // The terms must appear in the order of the phrase.
+        assert_eq!(docs(test_query(vec!["fox", "quick"], 10)), vec![3]);
+        assert_eq!(docs(test_query(vec!["quick", "fast", "fox"], 1)), vec![2]);
+        assert_eq!(
+            docs(test_query(vec!["quick", "fast", "fox"], 2)),
+            vec![2, 4]
+        );
+        assert!(test_query(vec!["quick", "fast", "fox"], 0).is_empty());
+
+        // Closer matches get a higher score.
+        let doc_scores = test_query(vec!["quick", "fox"], 2);
+        assert!(doc_scores[0].1 > doc_scores[1].1);
+        assert!(doc_scores[1].1 > doc_scores[2].1);
+        let mut phrase_query = PhraseQuery::new(vec![
+            Term::from_field_text(text_field, "quick"),
+            Term::from_field_text(text_field, "fox"),
+        ]);
+        phrase_query.set_slop(2);
+        let explanation = phrase_query
+            .explain(&searcher, DocAddress::new(0, 2))
+            .unwrap();
+        assert_nearly_equals!(explanation.value(), doc_scores[2].1);
+    }
+
+    #[test]
+    pub fn test_phrase_query_slop_repeated_terms() {
+        let index = create_index(&["a b a", "a", "b a a", "a b b b a"]);
+        let schema = index.schema();
+        let text_field = schema.get_field("text").unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let test_query = |texts: Vec<&str>, slop: u32| {
+            let terms: Vec<Term> = texts
+                .iter()
+                .map(|text| Term::from_field_text(text_field, text))
+                .collect();
+            let phrase_query = PhraseQuery::new_with_offset_and_slop(
+                terms.into_iter().enumerate().collect(),
+                slop,
+            );
+            searcher
+                .search(&phrase_query, &TEST_COLLECTOR_WITHOUT_SCORE)
+                .expect("search should succeed")
+                .docs()
+                .iter()
+                .map(|doc_address| doc_address.doc_id)
+                .collect::<Vec<DocId>>()
+        };
+        assert_eq!(test_query(vec!["a", "a"], 0), vec![2]);
+        // A single occurence cannot match both terms.
+        assert_eq!(test_query(vec!["a", "a"], 1), vec![0, 2]);
+        assert_eq!(test_query(vec!["a", "a"], 3), vec![0, 2, 3]);
+        assert_eq!(test_query(vec!["a", "b", "a"], 0), vec![0]);
+        assert_eq!(test_query(vec!["a", "b", "a"], 2), vec![0, 3]);
+    }
+
+    #[test]
+    pub fn test_phrase_query_slop_with_offsets() {
+        // Offsets with a gap are what the query parser produces
+        // when a stop word was removed from the phrase.
+        let index = create_index(&["a b c d e f g h"]);
+        let schema = index.schema();
+        let text_field = schema.get_field("text").unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let test_query = |texts: Vec<(usize, &str)>, slop: u32| {
+            let terms: Vec<(usize, Term)> = texts
+                .iter()
+                .map(|(offset, text)| (*offset, Term::from_field_text(text_field, text)))
+                .collect();
+            let phrase_query = PhraseQuery::new_with_offset_and_slop(terms, slop);
+            searcher
+                .search(&phrase_query, &TEST_COLLECTOR_WITH_SCORE)
+                .expect("search should succeed")
+                .docs()
+                .len()
+        };
+        assert_eq!(test_query(vec![(0, "a"), (2, "c")], 0), 1);
+        assert_eq!(test_query(vec![(0, "a"), (2, "d")], 0), 0);
+        assert_eq!(test_query(vec![(0, "a"), (2, "d")], 1), 1);
+        // The removed word may be missing from the document.
+        assert_eq!(test_query(vec![(0, "a"), (2, "b")], 0), 0);
+        assert_eq!(test_query(vec![(0, "a"), (2, "b")], 1), 1);
+        assert_eq!(test_query(vec![(2, "c"), (0, "a"), (3, "f")], 2), 1);
+        assert_eq!(test_query(vec![(2, "c"), (0, "a"), (3, "f")], 1), 0);
+    }
+
+    #[test]
+    pub fn test_phrase_query_zero_slop_is_exact() {
+        let index = create_index(&["a b c", "a b c a b", "a c b"]);
+        let schema = index.schema();
+        let text_field = schema.get_field("text").unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let terms = vec![
+            Term::from_field_text(text_field, "a"),
+            Term::from_field_text(text_field, "b"),
+        ];
+        let phrase_query = PhraseQuery::new(terms.clone());
+        let zero_slop_query =
+            PhraseQuery::new_with_offset_and_slop(terms.into_iter().enumerate().collect(), 0);
+        assert_eq!(zero_slop_query.slop(), 0);
+        let exact_fruits = searcher
+            .search(&phrase_query, &TEST_COLLECTOR_WITH_SCORE)
+            .unwrap();
+        let zero_slop_fruits = searcher
+            .search(&zero_slop_query, &TEST_COLLECTOR_WITH_SCORE)
+            .unwrap();
+        assert_eq!(exact_fruits.docs(), zero_slop_fruits.docs());
+        assert_eq!(exact_fruits.scores(), zero_slop_fruits.scores());
+        assert_eq!(zero_slop_fruits.docs().len(), 2);
+    }
 }
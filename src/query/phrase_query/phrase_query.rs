@@ -1,9 +1,8 @@
-use std::collections::BTreeMap;
-
 use super::PhraseWeight;
 use crate::core::searcher::Searcher;
-use crate::query::bm25::Bm25Weight;
 use crate::query::Query;
+use crate::query::QueryVisitor;
+use crate::query::SimilarityWeight;
 use crate::query::Weight;
 use crate::schema::IndexRecordOption;
 use crate::schema::{Field, Term};
@@ -22,10 +21,17 @@ use crate::schema::{Field, Term};
 /// Using a `PhraseQuery` on a field requires positions
 /// to be indexed for this field.
 ///
+/// A slop can be set, to also match the documents where the terms of
+/// the phrase are not exactly adjacent. For instance, the phrase query
+/// for `"part job"` matches the first sentence above with a slop of `1`,
+/// and both sentences with a slop of `2`.
+/// Matches requiring fewer moves are scored higher.
+///
 #[derive(Clone, Debug)]
 pub struct PhraseQuery {
     field: Field,
     phrase_terms: Vec<(usize, Term)>,
+    slop: u32,
 }
 
 impl PhraseQuery {
@@ -42,7 +48,14 @@ impl PhraseQuery {
     /// Creates a new `PhraseQuery` given a list of terms and their offsets.
     ///
     /// Can be used to provide custom offset for each term.
-    pub fn new_with_offset(mut terms: Vec<(usize, Term)>) -> PhraseQuery {
+    pub fn new_with_offset(terms: Vec<(usize, Term)>) -> PhraseQuery {
+        PhraseQuery::new_with_offset_and_slop(terms, 0)
+    }
+
+    /// Creates a new `PhraseQuery` given a list of terms, their offsets and a slop.
+    ///
+    /// See [`set_slop()`](#method.set_slop).
+    pub fn new_with_offset_and_slop(mut terms: Vec<(usize, Term)>, slop: u32) -> PhraseQuery {
         assert!(
             terms.len() > 1,
             "A phrase query is required to have strictly more than one term."
@@ -56,9 +69,26 @@ impl PhraseQuery {
         PhraseQuery {
             field,
             phrase_terms: terms,
+            slop,
         }
     }
 
+    /// Sets the slop of the phrase query.
+    ///
+    /// The slop is the maximum number of positions the terms of a document may be
+    /// moved by, in total, to match the phrase. The terms must still appear in the
+    /// same order as in the phrase.
+    ///
+    /// With the default slop of `0`, the terms have to be exactly adjacent.
+    pub fn set_slop(&mut self, slop: u32) {
+        self.slop = slop;
+    }
+
+    /// Returns the slop of the phrase query.
+    pub fn slop(&self) -> u32 {
+        self.slop
+    }
+
     /// The `Field` this `PhraseQuery` is targeting.
     pub fn field(&self) -> Field {
         self.field
@@ -72,6 +102,11 @@ impl PhraseQuery {
             .collect::<Vec<Term>>()
     }
 
+    /// `Term`s in the phrase, with their offsets.
+    pub fn phrase_terms_with_offsets(&self) -> &[(usize, Term)] {
+        &self.phrase_terms[..]
+    }
+
     /// Returns the `PhraseWeight` for the given phrase query given a specific `searcher`.
     ///
     /// This function is the same as `.weight(...)` except it returns
@@ -96,10 +131,11 @@ impl PhraseQuery {
             )));
         }
         let terms = self.phrase_terms();
-        let bm25_weight = Bm25Weight::for_terms(searcher, &terms)?;
+        let similarity_weight = SimilarityWeight::for_terms(searcher, &terms)?;
         Ok(PhraseWeight::new(
             self.phrase_terms.clone(),
-            bm25_weight,
+            self.slop,
+            similarity_weight,
             scoring_enabled,
         ))
     }
@@ -114,9 +150,7 @@ impl Query for PhraseQuery {
         Ok(Box::new(phrase_weight))
     }
 
-    fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
-        for (_, term) in &self.phrase_terms {
-            terms.insert(term.clone(), true);
-        }
+    fn visit(&self, visitor: &mut dyn QueryVisitor) {
+        visitor.visit_phrase_query(self);
     }
 }
@@ -1,7 +1,7 @@
 use crate::docset::{DocSet, TERMINATED};
 use crate::fieldnorm::FieldNormReader;
 use crate::postings::Postings;
-use crate::query::bm25::Bm25Weight;
+use crate::query::SimilarityWeight;
 use crate::query::{Intersection, Scorer};
 use crate::{DocId, Score};
 use std::cmp::Ordering;
@@ -49,8 +49,18 @@ pub struct PhraseScorer<TPostings: Postings> {
     right: Vec<u32>,
     phrase_count: u32,
     fieldnorm_reader: FieldNormReader,
-    similarity_weight: Bm25Weight,
+    similarity_weight: SimilarityWeight,
     scoring_enabled: bool,
+    slop: u32,
+    /// For sloppy phrases, `(ord, offset_gap)` for each of the terms in the order of
+    /// the phrase, `ord` being the ordinal of the term docset in the intersection, and
+    /// `offset_gap` the difference between the offset of the term and the offset of the
+    /// previous term.
+    sloppy_terms: Vec<(usize, u32)>,
+    left_costs: Vec<u32>,
+    next: Vec<u32>,
+    next_costs: Vec<u32>,
+    sloppy_weight: Score,
 }
 
 /// Returns true iff the two sorted array contain a common element
@@ -99,6 +109,60 @@ fn intersection_count(left: &[u32], right: &[u32]) -> usize {
     count
 }
 
+/// Extends the partial matches of a sloppy phrase with the positions `right` of its next term.
+///
+/// Positions are shifted by the offset of their term, so that the terms of an exact
+/// match are all at the same position. A partial match is given by the shifted position of
+/// its last term in the sorted array `left`, and the number of moves it required in `left_costs`.
+///
+/// The number of moves between two consecutive terms is the difference between their
+/// shifted positions, and their actual positions must be increasing. Given the difference
+/// `offset_gap` between the offsets of the terms, this means that the shifted position of the
+/// term may go back by up to `offset_gap - 1`.
+///
+/// The partial matches including the next term and requiring at most `slop` moves
+/// are written to `output` and `output_costs`, keeping the cheapest match for each position.
+fn intersection_with_slop(
+    (left, left_costs): (&[u32], &[u32]),
+    right: &[u32],
+    offset_gap: u32,
+    slop: u32,
+    (output, output_costs): (&mut Vec<u32>, &mut Vec<u32>),
+) {
+    output.clear();
+    output_costs.clear();
+    // Left positions up to `right_val` (excluded if the offsets are the same) are the
+    // ones preceding `right_val`.
+    let preceding_end = |right_val: u32| right_val as u64 + offset_gap.min(1) as u64;
+    let mut left_i = 0;
+    // Minimum of `cost - left_val` over the left positions preceding the current right value.
+    let mut min_preceding_cost: Option<i64> = None;
+    for &right_val in right {
+        while left_i < left.len() && (left[left_i] as u64) < preceding_end(right_val) {
+            let cost = left_costs[left_i] as i64 - left[left_i] as i64;
+            min_preceding_cost =
+                Some(min_preceding_cost.map_or(cost, |min_cost| min_cost.min(cost)));
+            left_i += 1;
+        }
+        let mut best_cost: Option<i64> =
+            min_preceding_cost.map(|min_cost| min_cost + right_val as i64);
+        // Left positions that are after `right_val`, but still before the actual position.
+        for (&left_val, &left_cost) in left[left_i..].iter().zip(&left_costs[left_i..]) {
+            if left_val as u64 >= right_val as u64 + offset_gap as u64 {
+                break;
+            }
+            let cost = left_cost as i64 + (left_val - right_val) as i64;
+            best_cost = Some(best_cost.map_or(cost, |best_cost| best_cost.min(cost)));
+        }
+        if let Some(cost) = best_cost {
+            if cost <= slop as i64 {
+                output.push(right_val);
+                output_costs.push(cost as u32);
+            }
+        }
+    }
+}
+
 /// Intersect twos sorted arrays `left` and `right` and outputs the
 /// resulting array in left.
 ///
@@ -133,7 +197,8 @@ fn intersection(left: &mut [u32], right: &[u32]) -> usize {
 impl<TPostings: Postings> PhraseScorer<TPostings> {
     pub fn new(
         term_postings: Vec<(usize, TPostings)>,
-        similarity_weight: Bm25Weight,
+        slop: u32,
+        similarity_weight: SimilarityWeight,
         fieldnorm_reader: FieldNormReader,
         scoring_enabled: bool,
     ) -> PhraseScorer<TPostings> {
@@ -149,8 +214,22 @@ impl<TPostings: Postings> PhraseScorer<TPostings> {
                 PostingsWithOffset::new(postings, (max_offset - offset) as u32)
             })
             .collect::<Vec<_>>();
+        let mut intersection_docset = Intersection::new(postings_with_offsets);
+        let mut sloppy_terms = Vec::new();
+        if slop > 0 {
+            // The intersection orders the docsets by size, restore the order of the phrase.
+            let mut ord_offsets: Vec<(usize, u32)> = (0..num_docsets)
+                .map(|ord| (ord, intersection_docset.docset_mut_specialized(ord).offset))
+                .collect();
+            ord_offsets.sort_by_key(|&(ord, offset)| (std::cmp::Reverse(offset), ord));
+            let mut previous_offset = ord_offsets[0].1;
+            for (ord, offset) in ord_offsets {
+                sloppy_terms.push((ord, previous_offset - offset));
+                previous_offset = offset;
+            }
+        }
         let mut scorer = PhraseScorer {
-            intersection_docset: Intersection::new(postings_with_offsets),
+            intersection_docset,
             num_terms: num_docsets,
             left: Vec::with_capacity(100),
             right: Vec::with_capacity(100),
@@ -158,6 +237,12 @@ impl<TPostings: Postings> PhraseScorer<TPostings> {
             similarity_weight,
             fieldnorm_reader,
             scoring_enabled,
+            slop,
+            sloppy_terms,
+            left_costs: Vec::new(),
+            next: Vec::new(),
+            next_costs: Vec::new(),
+            sloppy_weight: 1.0,
         };
         if scorer.doc() != TERMINATED && !scorer.phrase_match() {
             scorer.advance();
@@ -169,8 +254,20 @@ impl<TPostings: Postings> PhraseScorer<TPostings> {
         self.phrase_count
     }
 
+    /// Average of `1 / (moves + 1)` over the matches of the phrase in the current document,
+    /// `moves` being the number of position moves required by a match.
+    ///
+    /// It is always `1` for an exact phrase.
+    pub fn sloppy_weight(&self) -> Score {
+        self.sloppy_weight
+    }
+
     fn phrase_match(&mut self) -> bool {
-        if self.scoring_enabled {
+        if self.slop > 0 {
+            let count = self.compute_sloppy_phrase_count();
+            self.phrase_count = count;
+            count > 0u32
+        } else if self.scoring_enabled {
             let count = self.compute_phrase_count();
             self.phrase_count = count;
             count > 0u32
@@ -228,6 +325,42 @@ impl<TPostings: Postings> PhraseScorer<TPostings> {
     }
 }
 
+impl<TPostings: Postings> PhraseScorer<TPostings> {
+    fn compute_sloppy_phrase_count(&mut self) -> u32 {
+        let (first_ord, _) = self.sloppy_terms[0];
+        self.intersection_docset
+            .docset_mut_specialized(first_ord)
+            .positions(&mut self.left);
+        self.left_costs.clear();
+        self.left_costs.resize(self.left.len(), 0u32);
+        for i in 1..self.num_terms {
+            let (ord, offset_gap) = self.sloppy_terms[i];
+            self.intersection_docset
+                .docset_mut_specialized(ord)
+                .positions(&mut self.right);
+            intersection_with_slop(
+                (&self.left, &self.left_costs),
+                &self.right,
+                offset_gap,
+                self.slop,
+                (&mut self.next, &mut self.next_costs),
+            );
+            std::mem::swap(&mut self.left, &mut self.next);
+            std::mem::swap(&mut self.left_costs, &mut self.next_costs);
+            if self.left.is_empty() {
+                return 0u32;
+            }
+        }
+        let weight_sum: Score = self
+            .left_costs
+            .iter()
+            .map(|&cost| 1.0 / (cost as Score + 1.0))
+            .sum();
+        self.sloppy_weight = weight_sum / self.left.len() as Score;
+        self.left.len() as u32
+    }
+}
+
 impl<TPostings: Postings> DocSet for PhraseScorer<TPostings> {
     fn advance(&mut self) -> DocId {
         loop {
@@ -262,12 +395,13 @@ impl<TPostings: Postings> Scorer for PhraseScorer<TPostings> {
         let fieldnorm_id = self.fieldnorm_reader.fieldnorm_id(doc);
         self.similarity_weight
             .score(fieldnorm_id, self.phrase_count)
+            * self.sloppy_weight
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{intersection, intersection_count};
+    use super::{intersection, intersection_count, intersection_with_slop};
 
     fn test_intersection_sym(left: &[u32], right: &[u32], expected: &[u32]) {
         test_intersection_aux(left, right, expected);
@@ -290,6 +424,42 @@ mod tests {
         test_intersection_sym(&[5, 7], &[1, 5, 10, 12], &[5]);
         test_intersection_sym(&[1, 5, 6, 9, 10, 12], &[6, 8, 9, 12], &[6, 9, 12]);
     }
+
+    fn test_intersection_with_slop_aux(
+        left: &[(u32, u32)],
+        right: &[u32],
+        offset_gap: u32,
+        slop: u32,
+        expected: &[(u32, u32)],
+    ) {
+        let (left, left_costs): (Vec<u32>, Vec<u32>) = left.iter().cloned().unzip();
+        let mut output = Vec::new();
+        let mut output_costs = Vec::new();
+        intersection_with_slop(
+            (&left, &left_costs),
+            right,
+            offset_gap,
+            slop,
+            (&mut output, &mut output_costs),
+        );
+        let output: Vec<(u32, u32)> = output.into_iter().zip(output_costs).collect();
+        assert_eq!(&output[..], expected);
+    }
+
+    #[test]
+    fn test_intersection_with_slop() {
+        test_intersection_with_slop_aux(&[(5, 0)], &[5], 1, 0, &[(5, 0)]);
+        test_intersection_with_slop_aux(&[(5, 0)], &[4, 6, 7, 8], 1, 2, &[(6, 1), (7, 2)]);
+        // The cheapest partial match is kept.
+        test_intersection_with_slop_aux(&[(1, 0), (3, 1)], &[4], 1, 2, &[(4, 2)]);
+        test_intersection_with_slop_aux(&[(1, 0), (3, 0)], &[4], 1, 2, &[(4, 1)]);
+        test_intersection_with_slop_aux(&[(5, 1)], &[6], 1, 1, &[]);
+        // With a gap in the offsets, the term can be closer than in the phrase.
+        test_intersection_with_slop_aux(&[(5, 0)], &[3, 4, 5], 2, 1, &[(4, 1), (5, 0)]);
+        test_intersection_with_slop_aux(&[(5, 0)], &[4], 1, 3, &[]);
+        // With the same offset, the terms cannot be at the same position.
+        test_intersection_with_slop_aux(&[(5, 0)], &[5, 6], 0, 1, &[(6, 1)]);
+    }
 }
 
 #[cfg(all(test, feature = "unstable"))]
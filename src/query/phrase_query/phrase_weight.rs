@@ -2,9 +2,9 @@ use super::PhraseScorer;
 use crate::core::SegmentReader;
 use crate::fieldnorm::FieldNormReader;
 use crate::postings::SegmentPostings;
-use crate::query::bm25::Bm25Weight;
 use crate::query::explanation::does_not_match;
 use crate::query::Scorer;
+use crate::query::SimilarityWeight;
 use crate::query::Weight;
 use crate::query::{EmptyScorer, Explanation};
 use crate::schema::IndexRecordOption;
@@ -14,7 +14,8 @@ use crate::{DocId, DocSet};
 
 pub struct PhraseWeight {
     phrase_terms: Vec<(usize, Term)>,
-    similarity_weight: Bm25Weight,
+    slop: u32,
+    similarity_weight: SimilarityWeight,
     scoring_enabled: bool,
 }
 
@@ -22,11 +23,13 @@ impl PhraseWeight {
     /// Creates a new phrase weight.
     pub fn new(
         phrase_terms: Vec<(usize, Term)>,
-        similarity_weight: Bm25Weight,
+        slop: u32,
+        similarity_weight: SimilarityWeight,
         scoring_enabled: bool,
     ) -> PhraseWeight {
         PhraseWeight {
             phrase_terms,
+            slop,
             similarity_weight,
             scoring_enabled,
         }
@@ -74,6 +77,7 @@ impl PhraseWeight {
         }
         Ok(Some(PhraseScorer::new(
             term_postings_list,
+            self.slop,
             similarity_weight,
             fieldnorm_reader,
             self.scoring_enabled,
@@ -104,6 +108,12 @@ impl Weight for PhraseWeight {
         let phrase_count = scorer.phrase_count();
         let mut explanation = Explanation::new("Phrase Scorer", scorer.score());
         explanation.add_detail(self.similarity_weight.explain(fieldnorm_id, phrase_count));
+        if self.slop > 0 {
+            explanation.add_const(
+                "sloppy weight, average of 1 / (moves + 1) over the matches",
+                scorer.sloppy_weight(),
+            );
+        }
         Ok(explanation)
     }
 }
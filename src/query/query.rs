@@ -1,6 +1,7 @@
 use super::Weight;
 use crate::core::searcher::Searcher;
-use crate::query::Explanation;
+use crate::query::query_visitor::QueryTermsCollector;
+use crate::query::{Explanation, QueryRewriter, QueryVisitor};
 use crate::DocAddress;
 use crate::Term;
 use downcast_rs::impl_downcast;
@@ -71,13 +72,59 @@ pub trait Query: QueryClone + Send + Sync + downcast_rs::Downcast + fmt::Debug {
     ///
     /// Each term is associated with a boolean indicating whether
     /// Positions are required or not.
-    fn query_terms(&self, _term_set: &mut BTreeMap<Term, bool>) {}
+    ///
+    /// The terms are collected by [visiting](#method.visit) the query. A custom query
+    /// reported to [`visit_other`](./trait.QueryVisitor.html#method.visit_other) may
+    /// override this method instead, to report its terms.
+    fn query_terms(&self, term_set: &mut BTreeMap<Term, bool>) {
+        QueryTermsCollector::collect(self, term_set);
+    }
+
+    /// Calls the method of the visitor matching this query and,
+    /// for composite queries, visits their subqueries.
+    ///
+    /// See [`QueryVisitor`](./trait.QueryVisitor.html).
+    ///
+    /// By default, the query is reported to
+    /// [`visitor.visit_other(self)`](./trait.QueryVisitor.html#method.visit_other).
+    /// A custom query can instead call
+    /// [`visitor.visit_term(...)`](./trait.QueryVisitor.html#method.visit_term)
+    /// for each of its terms, and visit its subqueries if it has any.
+    fn visit(&self, visitor: &mut dyn QueryVisitor) {
+        visitor.visit_other(self.as_query());
+    }
+
+    /// Returns a copy of this query with its subqueries rewritten by `rewriter`,
+    /// or `None` if none of them was rewritten.
+    ///
+    /// Queries without subqueries do not need to implement this method.
+    /// See [`rewrite_query`](./fn.rewrite_query.html).
+    fn rewrite_subqueries(&self, _rewriter: &mut dyn QueryRewriter) -> Option<Box<dyn Query>> {
+        None
+    }
+
+    /// Returns a key identifying the set of documents matched by the query, or `None`
+    /// if the documents matched by the query cannot be cached.
+    ///
+    /// The key is a serialization of everything defining the matched documents, as the
+    /// cache compares the whole keys: queries matching the same documents of any segment,
+    /// whatever their score, may share a key, but the queries matching different
+    /// documents must not. The key is used by the `Filter` and `MustNot` clauses of a
+    /// [`BooleanQuery`](./struct.BooleanQuery.html) to read their documents from the
+    /// filter cache of the searcher, if any.
+    /// See [`IndexReaderBuilder::filter_cache`](../struct.IndexReaderBuilder.html#method.filter_cache).
+    fn cache_key(&self) -> Option<Vec<u8>> {
+        None
+    }
 }
 
-/// Implements `box_clone`.
+/// Implements `box_clone` and `as_query`.
 pub trait QueryClone {
     /// Returns a boxed clone of `self`.
     fn box_clone(&self) -> Box<dyn Query>;
+
+    /// Returns `self` as a `&dyn Query`.
+    fn as_query(&self) -> &dyn Query;
 }
 
 impl<T> QueryClone for T
@@ -87,6 +134,10 @@ where
     fn box_clone(&self) -> Box<dyn Query> {
         Box::new(self.clone())
     }
+
+    fn as_query(&self) -> &dyn Query {
+        self
+    }
 }
 
 impl Query for Box<dyn Query> {
@@ -101,12 +152,28 @@ impl Query for Box<dyn Query> {
     fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
         self.as_ref().query_terms(terms);
     }
+
+    fn visit(&self, visitor: &mut dyn QueryVisitor) {
+        self.as_ref().visit(visitor);
+    }
+
+    fn rewrite_subqueries(&self, rewriter: &mut dyn QueryRewriter) -> Option<Box<dyn Query>> {
+        self.as_ref().rewrite_subqueries(rewriter)
+    }
+
+    fn cache_key(&self) -> Option<Vec<u8>> {
+        self.as_ref().cache_key()
+    }
 }
 
 impl QueryClone for Box<dyn Query> {
     fn box_clone(&self) -> Box<dyn Query> {
         self.as_ref().box_clone()
     }
+
+    fn as_query(&self) -> &dyn Query {
+        self.as_ref()
+    }
 }
 
 impl_downcast!(Query);
@@ -0,0 +1,135 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use std::convert::TryFrom;
+
+/// Parses a date value of a query, either as a RFC 3339 date, e.g.
+/// `2002-10-02T15:00:00Z`, or as a date math expression relative to `now`.
+///
+/// A date math expression starts with `now`, followed by any number of
+/// - additions or subtractions of a number of units, e.g. `+1h` or `-7d`,
+/// - roundings down to the beginning of a unit, e.g. `/d`.
+///
+/// The units are `y` (year), `M` (month), `w` (week), `d` (day), `h` (hour),
+/// `m` (minute) and `s` (second). For instance `now-1d/d` is the beginning of yesterday.
+///
+/// Returns `None` if the value is not a valid date.
+pub(crate) fn parse_date(date_str: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Some(operations) = date_str.strip_prefix("now") {
+        return eval_date_math(operations, now);
+    }
+    DateTime::parse_from_rfc3339(date_str)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+fn eval_date_math(mut operations: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let mut date = now.naive_utc();
+    while let Some(operator) = operations.chars().next() {
+        operations = &operations[operator.len_utf8()..];
+        match operator {
+            '+' | '-' => {
+                let num_digits = operations
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(operations.len());
+                let amount = operations[..num_digits].parse::<u32>().ok()? as i64;
+                let amount = if operator == '-' { -amount } else { amount };
+                let unit = operations[num_digits..].chars().next()?;
+                operations = &operations[num_digits + unit.len_utf8()..];
+                date = add_units(date, amount, unit)?;
+            }
+            '/' => {
+                let unit = operations.chars().next()?;
+                operations = &operations[unit.len_utf8()..];
+                date = round_down(date, unit)?;
+            }
+            _ => return None,
+        }
+    }
+    Some(Utc.from_utc_datetime(&date))
+}
+
+fn add_units(date: NaiveDateTime, amount: i64, unit: char) -> Option<NaiveDateTime> {
+    let duration = match unit {
+        'y' => return add_months(date, amount * 12),
+        'M' => return add_months(date, amount),
+        'w' => Duration::weeks(amount),
+        'd' => Duration::days(amount),
+        'h' => Duration::hours(amount),
+        'm' => Duration::minutes(amount),
+        's' => Duration::seconds(amount),
+        _ => return None,
+    };
+    date.checked_add_signed(duration)
+}
+
+/// Adds a number of months to a date, clamping its day to the length of the resulting month.
+fn add_months(date: NaiveDateTime, months: i64) -> Option<NaiveDateTime> {
+    let total_months = date.year() as i64 * 12 + date.month0() as i64 + months;
+    let year = i32::try_from(total_months.div_euclid(12)).ok()?;
+    let month = total_months.rem_euclid(12) as u32 + 1;
+    let day = (1..=date.day())
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))?;
+    Some(day.and_time(date.time()))
+}
+
+fn round_down(date: NaiveDateTime, unit: char) -> Option<NaiveDateTime> {
+    let day = date.date();
+    match unit {
+        'y' => NaiveDate::from_ymd_opt(day.year(), 1, 1)?.and_hms_opt(0, 0, 0),
+        'M' => NaiveDate::from_ymd_opt(day.year(), day.month(), 1)?.and_hms_opt(0, 0, 0),
+        'w' => {
+            let days_from_monday = day.weekday().num_days_from_monday() as i64;
+            (day - Duration::days(days_from_monday)).and_hms_opt(0, 0, 0)
+        }
+        'd' => day.and_hms_opt(0, 0, 0),
+        'h' => day.and_hms_opt(date.hour(), 0, 0),
+        'm' => day.and_hms_opt(date.hour(), date.minute(), 0),
+        's' => day.and_hms_opt(date.hour(), date.minute(), date.second()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_date;
+    use chrono::{DateTime, Utc};
+
+    fn date(date_str: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(date_str)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_date() {
+        // A wednesday.
+        let now = date("2021-03-31T14:25:36.5Z");
+        let parse = |date_str: &str| parse_date(date_str, now);
+        assert_eq!(parse("now"), Some(now));
+        assert_eq!(
+            parse("2002-10-02T15:00:00+02:00"),
+            Some(date("2002-10-02T13:00:00Z"))
+        );
+        assert_eq!(parse("now-7d"), Some(date("2021-03-24T14:25:36.5Z")));
+        assert_eq!(parse("now+1h-30m"), Some(date("2021-03-31T14:55:36.5Z")));
+        assert_eq!(parse("now/d"), Some(date("2021-03-31T00:00:00Z")));
+        assert_eq!(parse("now-1d/d"), Some(date("2021-03-30T00:00:00Z")));
+        assert_eq!(parse("now/w"), Some(date("2021-03-29T00:00:00Z")));
+        assert_eq!(parse("now/M"), Some(date("2021-03-01T00:00:00Z")));
+        assert_eq!(parse("now/y"), Some(date("2021-01-01T00:00:00Z")));
+        assert_eq!(parse("now/h"), Some(date("2021-03-31T14:00:00Z")));
+        assert_eq!(parse("now/m"), Some(date("2021-03-31T14:25:00Z")));
+        assert_eq!(parse("now/s"), Some(date("2021-03-31T14:25:36Z")));
+        assert_eq!(parse("now-1M"), Some(date("2021-02-28T14:25:36.5Z")));
+        assert_eq!(parse("now+11M/M"), Some(date("2022-02-01T00:00:00Z")));
+        assert_eq!(parse("now-1y/y"), Some(date("2020-01-01T00:00:00Z")));
+        assert_eq!(parse("2021-03-31"), None);
+        assert_eq!(parse("now-7"), None);
+        assert_eq!(parse("now-d"), None);
+        assert_eq!(parse("now-7x"), None);
+        assert_eq!(parse("now/"), None);
+        assert_eq!(parse("now*2"), None);
+        assert_eq!(parse("nowhere"), None);
+        assert_eq!(parse("now-1é"), None);
+    }
+}
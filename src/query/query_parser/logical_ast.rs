@@ -9,7 +9,15 @@ use std::ops::Bound;
 #[derive(Clone)]
 pub enum LogicalLiteral {
     Term(Term),
-    Phrase(Vec<(usize, Term)>),
+    Phrase(Vec<(usize, Term)>, u32),
+    Fuzzy {
+        term: Term,
+        distance: u8,
+    },
+    Wildcard {
+        field: Field,
+        pattern: String,
+    },
     Range {
         field: Field,
         value_type: Type,
@@ -40,6 +48,7 @@ fn occur_letter(occur: Occur) -> &'static str {
         Occur::Must => "+",
         Occur::MustNot => "-",
         Occur::Should => "",
+        Occur::Filter => "#",
     }
 }
 
@@ -75,7 +84,23 @@ impl fmt::Debug for LogicalLiteral {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match *self {
             LogicalLiteral::Term(ref term) => write!(formatter, "{:?}", term),
-            LogicalLiteral::Phrase(ref terms) => write!(formatter, "\"{:?}\"", terms),
+            LogicalLiteral::Phrase(ref terms, slop) => {
+                write!(formatter, "\"{:?}\"", terms)?;
+                if slop > 0 {
+                    write!(formatter, "~{:?}", slop)
+                } else {
+                    Ok(())
+                }
+            }
+            LogicalLiteral::Fuzzy { ref term, distance } => {
+                write!(formatter, "{:?}~{}", term, distance)
+            }
+            LogicalLiteral::Wildcard { field, ref pattern } => write!(
+                formatter,
+                "Wildcard(field={},pattern={:?})",
+                field.field_id(),
+                pattern
+            ),
             LogicalLiteral::Range {
                 ref lower,
                 ref upper,
@@ -1,3 +1,4 @@
+mod date_math;
 mod query_parser;
 
 pub mod logical_ast;
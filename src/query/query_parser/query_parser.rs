@@ -1,23 +1,28 @@
+use super::date_math::parse_date;
 use super::logical_ast::*;
 use crate::core::Index;
 use crate::query::BooleanQuery;
 use crate::query::EmptyQuery;
+use crate::query::FuzzyTermQuery;
 use crate::query::Occur;
 use crate::query::PhraseQuery;
 use crate::query::Query;
 use crate::query::RangeQuery;
 use crate::query::TermQuery;
+use crate::query::WildcardQuery;
 use crate::query::{AllQuery, BoostQuery};
+use crate::schema::{ip_addr_to_ipv6, parse_ip_addr};
 use crate::schema::{Facet, FacetParseError, IndexRecordOption};
 use crate::schema::{Field, Schema};
-use crate::schema::{FieldType, Term};
-use crate::tokenizer::TokenizerManager;
+use crate::schema::{FieldType, Term, Type, JSON_TEXT_CODE};
+use crate::tokenizer::{TextAnalyzer, TokenizerManager};
 use crate::Score;
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr};
 use std::num::{ParseFloatError, ParseIntError};
 use std::ops::Bound;
-use std::str::FromStr;
+use std::str::{FromStr, ParseBoolError};
 use tantivy_query_grammar::{UserInputAst, UserInputBound, UserInputLeaf};
 
 /// Possible error that may happen when parsing a query.
@@ -42,6 +47,14 @@ pub enum QueryParserError {
     /// is not a f64.
     #[error("Invalid query: Only excluding terms given")]
     ExpectedFloat(ParseFloatError),
+    /// The query contains a term for a `bool`-field, but the value
+    /// is neither `true` nor `false`.
+    #[error("Expected a bool value: '{0:?}'")]
+    ExpectedBool(ParseBoolError),
+    /// The query contains a term for an ip address field, but the value is
+    /// neither an ip address nor a CIDR block, e.g. `10.0.0.0/16`.
+    #[error("Expected an ip address: '{0}'")]
+    ExpectedIpAddr(String),
     /// It is forbidden queries that are only "excluding". (e.g. -title:pop)
     #[error("Invalid query: Only excluding terms given")]
     AllButQueryForbidden,
@@ -65,12 +78,53 @@ pub enum QueryParserError {
     /// Only terms can be used as bounds.
     #[error("A range query cannot have a phrase as one of the bounds")]
     RangeMustNotHavePhrase,
-    /// The format for the date field is not RFC 3339 compliant.
-    #[error("The date field has an invalid format")]
-    DateFormatError(chrono::ParseError),
+    /// The date is neither RFC 3339 compliant nor a valid date math expression.
+    /// The two argument strings are the name of the field, the invalid date.
+    #[error("The date '{1}' of the field '{0}' has an invalid format")]
+    DateFormatError(String, String),
     /// The format for the facet field is invalid.
     #[error("The facet field is malformed: {0}")]
     FacetFormatError(FacetParseError),
+    /// The boost of a part of the query is not a positive number, e.g. `^abc`.
+    #[error("Invalid boost: '{0}'")]
+    InvalidBoost(String),
+    /// The distance of a fuzzy term is not `0`, `1` or `2`, e.g. `~3`.
+    #[error("Invalid fuzzy distance: '{0}'")]
+    InvalidFuzzyDistance(String),
+    /// A fuzzy term was used on a field that is not a text field, or
+    /// its text was tokenized into several tokens.
+    #[error("'{0}' cannot be searched as a fuzzy term")]
+    UnsupportedFuzzyTerm(String),
+    /// A double quote of the query is not matched.
+    #[error("Unbalanced quotes")]
+    UnbalancedQuotes,
+    /// A parenthesis of the query is not matched.
+    #[error("Unbalanced parentheses")]
+    UnbalancedParentheses,
+    /// A part of the query could not be parsed.
+    #[error("Invalid query fragment: '{0}'")]
+    InvalidFragment(String),
+}
+
+impl From<tantivy_query_grammar::Error> for QueryParserError {
+    fn from(err: tantivy_query_grammar::Error) -> QueryParserError {
+        match err {
+            tantivy_query_grammar::Error::Syntax => QueryParserError::SyntaxError,
+            tantivy_query_grammar::Error::InvalidBoost(token) => {
+                QueryParserError::InvalidBoost(token)
+            }
+            tantivy_query_grammar::Error::InvalidFuzzyDistance(token) => {
+                QueryParserError::InvalidFuzzyDistance(token)
+            }
+            tantivy_query_grammar::Error::UnbalancedQuotes => QueryParserError::UnbalancedQuotes,
+            tantivy_query_grammar::Error::UnbalancedParentheses => {
+                QueryParserError::UnbalancedParentheses
+            }
+            tantivy_query_grammar::Error::InvalidFragment(fragment) => {
+                QueryParserError::InvalidFragment(fragment)
+            }
+        }
+    }
 }
 
 impl From<ParseIntError> for QueryParserError {
@@ -85,9 +139,9 @@ impl From<ParseFloatError> for QueryParserError {
     }
 }
 
-impl From<chrono::ParseError> for QueryParserError {
-    fn from(err: chrono::ParseError) -> QueryParserError {
-        QueryParserError::DateFormatError(err)
+impl From<ParseBoolError> for QueryParserError {
+    fn from(err: ParseBoolError) -> QueryParserError {
+        QueryParserError::ExpectedBool(err)
     }
 }
 
@@ -141,6 +195,8 @@ fn trim_ast(logical_ast: LogicalAst) -> Option<LogicalAst> {
 ///   are not relevant anymore.
 ///
 ///   Switching to a default of `AND` can be done by calling `.set_conjunction_by_default()`.
+///   The default can also be set per field, see
+///   [`set_field_default_occur(...)`](#method.set_field_default_occur).
 ///
 ///
 /// * boolean operators `AND`, `OR`. `AND` takes precedence over `OR`, so that `a AND b OR c` is interpreted
@@ -164,13 +220,40 @@ fn trim_ast(logical_ast: LogicalAst) -> Option<LogicalAst> {
 /// * range terms: Range searches can be done by specifying the start and end bound. These can be
 ///   inclusive or exclusive. e.g., `title:[a TO c}` will find all documents whose title contains
 ///   a word lexicographically between `a` and `c` (inclusive lower bound, exclusive upper bound).
-///   Inclusive bounds are `[]`, exclusive are `{}`.
+///   Inclusive bounds are `[]`, exclusive are `{}`, and `*` leaves a bound open.
+///   Ranges with a single bound can also be written with the `>`, `>=`, `<`, `<=` operators,
+///   e.g. `price:>=10`.
 ///
 /// * date values: The query parser supports rfc3339 formatted dates. For example `"2002-10-02T15:00:00.05Z"`
-///   or `some_date_field:[2002-10-02T15:00:00Z TO 2002-10-02T18:00:00Z}`
+///   or `some_date_field:[2002-10-02T15:00:00Z TO 2002-10-02T18:00:00Z}`.
+///   Dates can also be expressed relatively to the current time, as `now` followed by
+///   additions or subtractions of units, and roundings down to the beginning of a unit.
+///   e.g. `some_date_field:>=now-7d/d` matches the dates since the beginning of the day, a week ago.
+///   The units are `y`, `M` (month), `w`, `d`, `h`, `m` (minute) and `s`.
+///
+/// * bool values: The terms of a bool field are `true` or `false`, e.g. `is_published:true`.
+///
+/// * field groups: The terms of a parenthesized group prefixed by a field name search this
+///   field, unless they are given another field explicitly.
+///   e.g. `title:(rust OR lucene)` is equivalent to `title:rust OR title:lucene`.
+///
+/// * json object fields: The leaves of a json object field are searched by appending their
+///   path to the name of the field, e.g. `attributes.user.id:12`. A term is searched both as
+///   text and, if it represents one, as a number or a bool: `attributes.user.id:12` matches
+///   `{"user": {"id": 12}}` as well as `{"user": {"id": "12"}}`.
 ///
 /// *  all docs query: A plain `*` will match all documents in the index.
 ///
+/// * wildcard terms: Once enabled with
+///   [`set_wildcards_enabled(...)`](#method.set_wildcards_enabled), unquoted terms
+///   containing `*` (any sequence of characters) or `?` (any single character) match
+///   the terms of text fields following this pattern. e.g. `user:jo*n`.
+///   The pattern is lowercased if the tokenizer of the field lowercases its tokens.
+///
+/// * fuzzy terms: Appending `~1` or `~2` to a term of a text field matches the terms within
+///   this Levenshtein distance, with a transposition costing one. e.g. `title:rust~1`.
+///   Closer terms get a higher score.
+///
 /// Parts of the queries can be boosted by appending `^boostfactor`.
 /// For instance, `"SRE"^2.0 OR devops^0.4` will boost documents containing `SRE` instead of
 /// devops. Negative boosts are not allowed.
@@ -182,9 +265,35 @@ fn trim_ast(logical_ast: LogicalAst) -> Option<LogicalAst> {
 pub struct QueryParser {
     schema: Schema,
     default_fields: Vec<Field>,
-    conjunction_by_default: bool,
+    default_occur: Occur,
+    field_default_occur: HashMap<Field, Occur>,
+    minimum_should_match: usize,
     tokenizer_manager: TokenizerManager,
     boost: HashMap<Field, Score>,
+    wildcards_enabled: bool,
+}
+
+/// Returns true if the analyzer lowercases the tokens it produces.
+fn analyzer_lowercases(text_analyzer: &TextAnalyzer) -> bool {
+    let mut lowercases = false;
+    text_analyzer.token_stream("A").process(&mut |token| {
+        lowercases = token.text == "a";
+    });
+    lowercases
+}
+
+/// Returns the name of the field `user_input_ast` is explicitly searching, if any.
+fn explicit_field_name(user_input_ast: &UserInputAst) -> Option<&str> {
+    match user_input_ast {
+        UserInputAst::Leaf(leaf) => match leaf.as_ref() {
+            UserInputLeaf::Literal(literal) => literal.field_name.as_deref(),
+            UserInputLeaf::Range { field, .. } => field.as_deref(),
+            UserInputLeaf::All => None,
+        },
+        UserInputAst::Boost(ast, _) => explicit_field_name(ast),
+        UserInputAst::FieldGroup(field_name, _) => Some(field_name),
+        UserInputAst::Clause(_) => None,
+    }
 }
 
 fn all_negative(ast: &LogicalAst) -> bool {
@@ -211,8 +320,11 @@ impl QueryParser {
             schema,
             default_fields,
             tokenizer_manager,
-            conjunction_by_default: false,
+            default_occur: Occur::Should,
+            field_default_occur: Default::default(),
+            minimum_should_match: 0,
             boost: Default::default(),
+            wildcards_enabled: false,
         }
     }
 
@@ -229,8 +341,54 @@ impl QueryParser {
     /// By default, the query `happy tax payer` is equivalent to the query
     /// `happy OR tax OR payer`. After calling `.set_conjunction_by_default()`
     /// `happy tax payer` will be interpreted by the parser as `happy AND tax AND payer`.
+    ///
+    /// This is equivalent to `.set_default_occur(Occur::Must)`.
     pub fn set_conjunction_by_default(&mut self) {
-        self.conjunction_by_default = true;
+        self.set_default_occur(Occur::Must);
+    }
+
+    /// Sets the occur of the parts of a query written without an operator.
+    ///
+    /// It defaults to `Occur::Should`, so that the query `happy tax payer` is equivalent
+    /// to `happy OR tax OR payer`. The operators written in the query, `AND`, `OR`, `+`
+    /// and `-`, always take precedence over the default.
+    pub fn set_default_occur(&mut self, occur: Occur) {
+        self.default_occur = occur;
+    }
+
+    /// Sets the occur of the parts of a query written without an operator,
+    /// for the parts that only search `field`.
+    ///
+    /// e.g. after `.set_field_default_occur(tags, Occur::Must)`, the query
+    /// `rust tags:search tags:engine` is interpreted as `rust +tags:search +tags:engine`,
+    /// and `tags:(search engine)` as `tags:(+search +engine)`.
+    ///
+    /// It takes precedence over the occur set with
+    /// [`set_default_occur(...)`](#method.set_default_occur).
+    pub fn set_field_default_occur(&mut self, field: Field, occur: Occur) {
+        self.field_default_occur.insert(field, occur);
+    }
+
+    /// Sets the minimum number of `Should` clauses of the query a document must match,
+    /// as with [`BooleanQuery::with_minimum_required_clauses`](../query/struct.BooleanQuery.html#method.with_minimum_required_clauses).
+    ///
+    /// It only applies to the top-level clauses of the query, and counts the parts of the
+    /// query which are `Should` clauses, by default or because of an explicit `OR`.
+    /// e.g. with a minimum of 2, `happy tax payer` matches the documents containing at least
+    /// two of the three terms, and `+happy tax` matches no document.
+    /// A query without `Should` clauses, e.g. after `.set_conjunction_by_default()`, is not
+    /// affected.
+    pub fn set_minimum_should_match(&mut self, minimum_should_match: usize) {
+        self.minimum_should_match = minimum_should_match;
+    }
+
+    /// Enables or disables wildcard terms, e.g. `jo*n`.
+    ///
+    /// When enabled, unquoted terms containing `*` or `?` are turned into a
+    /// [`WildcardQuery`](../query/struct.WildcardQuery.html) on text fields.
+    /// Wildcards are disabled by default.
+    pub fn set_wildcards_enabled(&mut self, wildcards_enabled: bool) {
+        self.wildcards_enabled = wildcards_enabled;
     }
 
     /// Sets a boost for a specific field.
@@ -249,20 +407,50 @@ impl QueryParser {
     /// Note that `parse_query` returns an error if the input
     /// is not a valid query.
     ///
-    /// There is currently no lenient mode for the query parser
-    /// which makes it a bad choice for a public/broad user search engine.
-    ///
-    /// Implementing a lenient mode for this query parser is tracked
-    /// in [Issue 5](https://github.com/fulmicoton/tantivy/issues/5)
+    /// For queries typed by the users of a public/broad search engine,
+    /// [`parse_query_lenient`](#method.parse_query_lenient) is a better choice.
     pub fn parse_query(&self, query: &str) -> Result<Box<dyn Query>, QueryParserError> {
         let logical_ast = self.parse_query_to_logical_ast(query)?;
-        Ok(convert_to_query(logical_ast))
+        Ok(convert_to_query(logical_ast, self.minimum_should_match))
+    }
+
+    /// Parse a query, recovering from its errors rather than failing.
+    ///
+    /// * syntax errors are repaired: unmatched quotes and parentheses are balanced,
+    ///   invalid boosts and fuzzy distances are removed, and the parts of the query
+    ///   that still cannot be parsed are degraded to their words, searched in the
+    ///   default fields.
+    /// * terms of a field that does not exist are searched in the default fields.
+    /// * the other invalid parts of the query, e.g. a range bound that does not match
+    ///   the type of its field, are dropped.
+    ///
+    /// Returns the query along with all of the errors that were recovered from.
+    /// The errors are empty iff `parse_query` would have succeeded.
+    pub fn parse_query_lenient(&self, query: &str) -> (Box<dyn Query>, Vec<QueryParserError>) {
+        let (user_input_ast, grammar_errors) = tantivy_query_grammar::parse_query_lenient(query);
+        let mut errors: Vec<QueryParserError> = grammar_errors
+            .into_iter()
+            .map(QueryParserError::from)
+            .collect();
+        let logical_ast = self
+            .compute_logical_ast_lenient(user_input_ast, &self.default_fields, &mut errors)
+            .and_then(trim_ast);
+        match logical_ast {
+            Some(logical_ast) if all_negative(&logical_ast) => {
+                errors.push(QueryParserError::AllButQueryForbidden);
+                (Box::new(EmptyQuery), errors)
+            }
+            Some(logical_ast) => (
+                convert_to_query(logical_ast, self.minimum_should_match),
+                errors,
+            ),
+            None => (Box::new(EmptyQuery), errors),
+        }
     }
 
     /// Parse the user query into an AST.
     fn parse_query_to_logical_ast(&self, query: &str) -> Result<LogicalAst, QueryParserError> {
-        let user_input_ast =
-            tantivy_query_grammar::parse_query(query).map_err(|_| QueryParserError::SyntaxError)?;
+        let user_input_ast = tantivy_query_grammar::parse_query(query)?;
         self.compute_logical_ast(user_input_ast)
     }
 
@@ -272,11 +460,35 @@ impl QueryParser {
             .ok_or_else(|| QueryParserError::FieldDoesNotExist(String::from(field_name)))
     }
 
+    /// Resolves the field of a term, as well as its json path if the field
+    /// is a json object field.
+    ///
+    /// e.g. `attributes.user.id` gives the json object field `attributes` and the path
+    /// `user.id`, unless the schema has a field named `attributes.user.id`.
+    fn resolve_field_and_json_path<'a>(
+        &self,
+        field_name: &'a str,
+    ) -> Result<(Field, &'a str), QueryParserError> {
+        if let Some(field) = self.schema.get_field(field_name) {
+            return Ok((field, ""));
+        }
+        field_name
+            .rmatch_indices('.')
+            .find_map(|(dot_offset, _)| {
+                let field = self.schema.get_field(&field_name[..dot_offset])?;
+                match self.schema.get_field_entry(field).field_type() {
+                    FieldType::JsonObject(_) => Some((field, &field_name[dot_offset + 1..])),
+                    _ => None,
+                }
+            })
+            .ok_or_else(|| QueryParserError::FieldDoesNotExist(String::from(field_name)))
+    }
+
     fn compute_logical_ast(
         &self,
         user_input_ast: UserInputAst,
     ) -> Result<LogicalAst, QueryParserError> {
-        let ast = self.compute_logical_ast_with_occur(user_input_ast)?;
+        let ast = self.compute_logical_ast_with_occur(user_input_ast, &self.default_fields)?;
         if let LogicalAst::Clause(children) = &ast {
             if children.is_empty() {
                 return Ok(ast);
@@ -291,6 +503,7 @@ impl QueryParser {
     fn compute_terms_for_string(
         &self,
         field: Field,
+        json_path: &str,
         phrase: &str,
     ) -> Result<Vec<(usize, Term)>, QueryParserError> {
         let field_entry = self.schema.get_field_entry(field);
@@ -310,33 +523,57 @@ impl QueryParser {
                 let term = Term::from_field_f64(field, val);
                 Ok(vec![(0, term)])
             }
-            FieldType::Date(_) => match chrono::DateTime::parse_from_rfc3339(phrase) {
-                Ok(x) => Ok(vec![(
-                    0,
-                    Term::from_field_date(field, &x.with_timezone(&chrono::Utc)),
-                )]),
-                Err(e) => Err(QueryParserError::DateFormatError(e)),
-            },
+            FieldType::Bool(_) => {
+                let val: bool = bool::from_str(phrase)?;
+                let term = Term::from_field_bool(field, val);
+                Ok(vec![(0, term)])
+            }
+            FieldType::IpAddr(_) => {
+                let val = parse_ip_addr(phrase)
+                    .map_err(|_| QueryParserError::ExpectedIpAddr(phrase.to_string()))?;
+                Ok(vec![(0, Term::from_field_ip_addr(field, val))])
+            }
+            FieldType::Date(_) => {
+                let date = parse_date(phrase, chrono::Utc::now()).ok_or_else(|| {
+                    QueryParserError::DateFormatError(
+                        field_entry.name().to_string(),
+                        phrase.to_string(),
+                    )
+                })?;
+                Ok(vec![(0, Term::from_field_date(field, &date))])
+            }
             FieldType::U64(_) => {
                 let val: u64 = u64::from_str(phrase)?;
                 let term = Term::from_field_u64(field, val);
                 Ok(vec![(0, term)])
             }
-            FieldType::Str(ref str_options) => {
-                if let Some(option) = str_options.get_indexing_options() {
-                    let tokenizer =
-                        self.tokenizer_manager
-                            .get(option.tokenizer())
-                            .ok_or_else(|| {
-                                QueryParserError::UnknownTokenizer(
-                                    field_entry.name().to_string(),
-                                    option.tokenizer().to_string(),
-                                )
-                            })?;
+            FieldType::Str(_) | FieldType::JsonObject(_) => {
+                let indexing_options = match *field_type {
+                    FieldType::Str(ref str_options) => str_options.get_indexing_options(),
+                    FieldType::JsonObject(ref json_object_options) => {
+                        json_object_options.get_text_indexing_options()
+                    }
+                    _ => None,
+                };
+                if let Some(option) = indexing_options {
+                    let tokenizer = self
+                        .tokenizer_manager
+                        .get(option.search_tokenizer())
+                        .ok_or_else(|| {
+                            QueryParserError::UnknownTokenizer(
+                                field_entry.name().to_string(),
+                                option.search_tokenizer().to_string(),
+                            )
+                        })?;
                     let mut terms: Vec<(usize, Term)> = Vec::new();
                     let mut token_stream = tokenizer.token_stream(phrase);
                     token_stream.process(&mut |token| {
-                        let term = Term::from_field_text(field, &token.text);
+                        let term = match *field_type {
+                            FieldType::JsonObject(_) => {
+                                Term::from_field_json_text(field, json_path, &token.text)
+                            }
+                            _ => Term::from_field_text(field, &token.text),
+                        };
                         terms.push((token.position, term));
                     });
                     if terms.is_empty() {
@@ -379,25 +616,147 @@ impl QueryParser {
         }
     }
 
+    fn get_text_analyzer(&self, field: Field) -> Result<Option<TextAnalyzer>, QueryParserError> {
+        let field_entry = self.schema.get_field_entry(field);
+        let indexing_options = match field_entry.field_type() {
+            FieldType::Str(ref str_options) => str_options.get_indexing_options(),
+            _ => return Ok(None),
+        };
+        let indexing_options = indexing_options
+            .ok_or_else(|| QueryParserError::FieldNotIndexed(field_entry.name().to_string()))?;
+        self.tokenizer_manager
+            .get(indexing_options.search_tokenizer())
+            .map(Some)
+            .ok_or_else(|| {
+                QueryParserError::UnknownTokenizer(
+                    field_entry.name().to_string(),
+                    indexing_options.search_tokenizer().to_string(),
+                )
+            })
+    }
+
+    /// Returns a wildcard literal if `phrase` is a wildcard pattern for a text field.
+    fn compute_wildcard_literal(
+        &self,
+        field: Field,
+        phrase: &str,
+    ) -> Result<Option<LogicalLiteral>, QueryParserError> {
+        if !phrase.contains(['*', '?']) {
+            return Ok(None);
+        }
+        let text_analyzer = match self.get_text_analyzer(field)? {
+            Some(text_analyzer) => text_analyzer,
+            None => return Ok(None),
+        };
+        let pattern = if analyzer_lowercases(&text_analyzer) {
+            phrase.to_lowercase()
+        } else {
+            phrase.to_string()
+        };
+        Ok(Some(LogicalLiteral::Wildcard { field, pattern }))
+    }
+
+    fn compute_fuzzy_literal(
+        &self,
+        field: Field,
+        phrase: &str,
+        distance: u8,
+    ) -> Result<Option<LogicalLiteral>, QueryParserError> {
+        if self.get_text_analyzer(field)?.is_none() {
+            return Err(QueryParserError::UnsupportedFuzzyTerm(phrase.to_string()));
+        }
+        let terms = self.compute_terms_for_string(field, "", phrase)?;
+        match &terms[..] {
+            [] => Ok(None),
+            [(_, term)] => Ok(Some(LogicalLiteral::Fuzzy {
+                term: term.clone(),
+                distance,
+            })),
+            _ => Err(QueryParserError::UnsupportedFuzzyTerm(phrase.to_string())),
+        }
+    }
+
     fn compute_logical_ast_for_leaf(
         &self,
         field: Field,
         phrase: &str,
+        slop: u32,
     ) -> Result<Option<LogicalLiteral>, QueryParserError> {
-        let terms = self.compute_terms_for_string(field, phrase)?;
+        if let FieldType::IpAddr(_) = self.schema.get_field_entry(field).field_type() {
+            if phrase.contains('/') {
+                let (lower, upper) = parse_cidr(phrase)
+                    .ok_or_else(|| QueryParserError::ExpectedIpAddr(phrase.to_string()))?;
+                return Ok(Some(LogicalLiteral::Range {
+                    field,
+                    value_type: Type::IpAddr,
+                    lower: Bound::Included(Term::from_field_ip_addr(field, lower)),
+                    upper: Bound::Included(Term::from_field_ip_addr(field, upper)),
+                }));
+            }
+        }
+        let terms = self.compute_terms_for_string(field, "", phrase)?;
         match &terms[..] {
             [] => Ok(None),
             [(_, term)] => Ok(Some(LogicalLiteral::Term(term.clone()))),
-            _ => Ok(Some(LogicalLiteral::Phrase(terms.clone()))),
+            _ => Ok(Some(LogicalLiteral::Phrase(terms.clone(), slop))),
         }
     }
 
-    fn default_occur(&self) -> Occur {
-        if self.conjunction_by_default {
-            Occur::Must
-        } else {
-            Occur::Should
+    /// Computes the logical AST of a term searched in a json object field.
+    ///
+    /// Since the leaves of the json objects are indexed with their json type, the text
+    /// of the term is searched as well as the number or the bool it represents, if any.
+    fn compute_logical_ast_for_json_leaf(
+        &self,
+        field: Field,
+        json_path: &str,
+        phrase: &str,
+        slop: u32,
+    ) -> Result<Option<LogicalAst>, QueryParserError> {
+        let mut literals = Vec::new();
+        let terms = self.compute_terms_for_string(field, json_path, phrase)?;
+        match &terms[..] {
+            [] => {}
+            [(_, term)] => literals.push(LogicalLiteral::Term(term.clone())),
+            _ => literals.push(LogicalLiteral::Phrase(terms.clone(), slop)),
         }
+        literals.extend(
+            json_typed_terms(field, json_path, phrase)
+                .into_iter()
+                .map(LogicalLiteral::Term),
+        );
+        let mut asts: Vec<LogicalAst> = literals
+            .into_iter()
+            .map(|literal| LogicalAst::Leaf(Box::new(literal)))
+            .collect();
+        Ok(match asts.len() {
+            0 => None,
+            1 => asts.pop(),
+            _ => Some(LogicalAst::Clause(
+                asts.into_iter().map(|ast| (Occur::Should, ast)).collect(),
+            )),
+        })
+    }
+
+    /// Returns the occur of `user_input_ast` within a clause, if it is written
+    /// without an explicit operator.
+    ///
+    /// The default occur of a field applies to the parts of the query which only
+    /// search this field.
+    fn default_occur(&self, user_input_ast: &UserInputAst, default_fields: &[Field]) -> Occur {
+        let field_opt = match explicit_field_name(user_input_ast) {
+            Some(field_name) => self
+                .resolve_field_and_json_path(field_name)
+                .ok()
+                .map(|(field, _)| field),
+            None => match default_fields {
+                [field] => Some(*field),
+                _ => None,
+            },
+        };
+        field_opt
+            .and_then(|field| self.field_default_occur.get(&field).cloned())
+            .unwrap_or(self.default_occur)
     }
 
     fn resolve_bound(
@@ -408,7 +767,7 @@ impl QueryParser {
         if bound.term_str() == "*" {
             return Ok(Bound::Unbounded);
         }
-        let terms = self.compute_terms_for_string(field, bound.term_str())?;
+        let terms = self.compute_terms_for_string(field, "", bound.term_str())?;
         if terms.len() != 1 {
             return Err(QueryParserError::RangeMustNotHavePhrase);
         }
@@ -420,42 +779,172 @@ impl QueryParser {
         }
     }
 
-    fn resolved_fields(
+    /// Computes the logical AST of a range searched in a json object field.
+    ///
+    /// If its bounds are numbers, the range matches the leaves indexed as `i64` and `f64`
+    /// values, otherwise it matches the text leaves. An unbounded side of the range is
+    /// limited to the leaves of `json_path`, of the type of the range.
+    fn compute_logical_ast_for_json_range(
+        &self,
+        field: Field,
+        json_path: &str,
+        lower: &UserInputBound,
+        upper: &UserInputBound,
+    ) -> Result<LogicalAst, QueryParserError> {
+        let is_number = |bound: &UserInputBound| {
+            bound.term_str() == "*"
+                || f64::from_str(bound.term_str())
+                    .map(f64::is_finite)
+                    .unwrap_or(false)
+        };
+        let range = |lower: Bound<Term>, upper: Bound<Term>| {
+            LogicalAst::Leaf(Box::new(LogicalLiteral::Range {
+                field,
+                value_type: Type::Json,
+                lower,
+                upper,
+            }))
+        };
+        if is_number(lower) && is_number(upper) {
+            let i64_term = |val: i64| Term::from_field_json_i64(field, json_path, val);
+            let f64_term = |val: f64| Term::from_field_json_f64(field, json_path, val);
+            let i64_range = range(
+                json_i64_bound(lower, true).map(i64_term),
+                json_i64_bound(upper, false).map(i64_term),
+            );
+            let f64_range = range(
+                json_f64_bound(lower, f64::NEG_INFINITY).map(f64_term),
+                json_f64_bound(upper, f64::INFINITY).map(f64_term),
+            );
+            return Ok(LogicalAst::Clause(vec![
+                (Occur::Should, i64_range),
+                (Occur::Should, f64_range),
+            ]));
+        }
+        let text_bound = |bound: &UserInputBound| -> Result<Option<Term>, QueryParserError> {
+            if bound.term_str() == "*" {
+                return Ok(None);
+            }
+            let terms = self.compute_terms_for_string(field, json_path, bound.term_str())?;
+            if terms.len() != 1 {
+                return Err(QueryParserError::RangeMustNotHavePhrase);
+            }
+            Ok(terms.into_iter().next().map(|(_, term)| term))
+        };
+        let lower_term = match text_bound(lower)? {
+            Some(term) => json_bound_with_value(lower, term),
+            None => Bound::Included(Term::from_field_json_text(field, json_path, "")),
+        };
+        let upper_term = match text_bound(upper)? {
+            Some(term) => json_bound_with_value(upper, term),
+            None => {
+                // The prefix of the leaves of `json_path` following the text leaves.
+                let mut term = Term::new();
+                term.set_json_path(field, json_path, JSON_TEXT_CODE + 1);
+                Bound::Excluded(term)
+            }
+        };
+        Ok(range(lower_term, upper_term))
+    }
+
+    fn resolved_fields<'a>(
         &self,
         given_field: &Option<String>,
-    ) -> Result<Cow<'_, [Field]>, QueryParserError> {
+        default_fields: &'a [Field],
+    ) -> Result<Cow<'a, [Field]>, QueryParserError> {
         match *given_field {
             None => {
-                if self.default_fields.is_empty() {
+                if default_fields.is_empty() {
                     Err(QueryParserError::NoDefaultFieldDeclared)
                 } else {
-                    Ok(Cow::from(&self.default_fields[..]))
+                    Ok(Cow::from(default_fields))
                 }
             }
             Some(ref field) => Ok(Cow::from(vec![self.resolve_field_name(&*field)?])),
         }
     }
 
+    /// Computes the logical AST of `user_input_ast`, searching `default_fields`
+    /// for the terms that are not given a field explicitly.
     fn compute_logical_ast_with_occur(
         &self,
         user_input_ast: UserInputAst,
+        default_fields: &[Field],
     ) -> Result<LogicalAst, QueryParserError> {
         match user_input_ast {
             UserInputAst::Clause(sub_queries) => {
-                let default_occur = self.default_occur();
                 let mut logical_sub_queries: Vec<(Occur, LogicalAst)> = Vec::new();
                 for (occur_opt, sub_ast) in sub_queries {
-                    let sub_ast = self.compute_logical_ast_with_occur(sub_ast)?;
-                    let occur = occur_opt.unwrap_or(default_occur);
+                    let occur =
+                        occur_opt.unwrap_or_else(|| self.default_occur(&sub_ast, default_fields));
+                    let sub_ast = self.compute_logical_ast_with_occur(sub_ast, default_fields)?;
                     logical_sub_queries.push((occur, sub_ast));
                 }
                 Ok(LogicalAst::Clause(logical_sub_queries))
             }
             UserInputAst::Boost(ast, boost) => {
-                let ast = self.compute_logical_ast_with_occur(*ast)?;
+                let ast = self.compute_logical_ast_with_occur(*ast, default_fields)?;
                 Ok(ast.boost(boost as Score))
             }
-            UserInputAst::Leaf(leaf) => self.compute_logical_ast_from_leaf(*leaf),
+            UserInputAst::FieldGroup(field_name, ast) => {
+                let field = self.resolve_field_name(&field_name)?;
+                self.compute_logical_ast_with_occur(*ast, &[field])
+            }
+            UserInputAst::Leaf(leaf) => self.compute_logical_ast_from_leaf(*leaf, default_fields),
+        }
+    }
+
+    /// Lenient counterpart of `compute_logical_ast_with_occur`.
+    ///
+    /// The parts of the AST that cannot be computed are dropped, and their errors
+    /// are appended to `errors`. Terms of a field that does not exist are searched
+    /// in `default_fields` instead.
+    fn compute_logical_ast_lenient(
+        &self,
+        user_input_ast: UserInputAst,
+        default_fields: &[Field],
+        errors: &mut Vec<QueryParserError>,
+    ) -> Option<LogicalAst> {
+        match user_input_ast {
+            UserInputAst::Clause(sub_queries) => {
+                let logical_sub_queries: Vec<(Occur, LogicalAst)> = sub_queries
+                    .into_iter()
+                    .filter_map(|(occur_opt, sub_ast)| {
+                        let occur = occur_opt
+                            .unwrap_or_else(|| self.default_occur(&sub_ast, default_fields));
+                        let sub_ast =
+                            self.compute_logical_ast_lenient(sub_ast, default_fields, errors)?;
+                        Some((occur, sub_ast))
+                    })
+                    .collect();
+                Some(LogicalAst::Clause(logical_sub_queries))
+            }
+            UserInputAst::Boost(ast, boost) => self
+                .compute_logical_ast_lenient(*ast, default_fields, errors)
+                .map(|ast| ast.boost(boost as Score)),
+            UserInputAst::FieldGroup(field_name, ast) => {
+                match self.resolve_field_name(&field_name) {
+                    Ok(field) => self.compute_logical_ast_lenient(*ast, &[field], errors),
+                    Err(err) => {
+                        errors.push(err);
+                        self.compute_logical_ast_lenient(*ast, default_fields, errors)
+                    }
+                }
+            }
+            UserInputAst::Leaf(leaf) => {
+                let mut leaf = *leaf;
+                if let UserInputLeaf::Literal(literal) = &mut leaf {
+                    if let Some(field_name) = &literal.field_name {
+                        if let Err(err) = self.resolve_field_and_json_path(field_name) {
+                            errors.push(err);
+                            literal.field_name = None;
+                        }
+                    }
+                }
+                self.compute_logical_ast_from_leaf(leaf, default_fields)
+                    .map_err(|err| errors.push(err))
+                    .ok()
+            }
         }
     }
 
@@ -466,28 +955,59 @@ impl QueryParser {
     fn compute_logical_ast_from_leaf(
         &self,
         leaf: UserInputLeaf,
+        default_fields: &[Field],
     ) -> Result<LogicalAst, QueryParserError> {
         match leaf {
             UserInputLeaf::Literal(literal) => {
-                let term_phrases: Vec<(Field, String)> = match literal.field_name {
+                let term_phrases: Vec<(Field, &str, String)> = match literal.field_name {
                     Some(ref field_name) => {
-                        let field = self.resolve_field_name(field_name)?;
-                        vec![(field, literal.phrase.clone())]
+                        let (field, json_path) = self.resolve_field_and_json_path(field_name)?;
+                        vec![(field, json_path, literal.phrase.clone())]
                     }
                     None => {
-                        if self.default_fields.is_empty() {
+                        if default_fields.is_empty() {
                             return Err(QueryParserError::NoDefaultFieldDeclared);
                         } else {
-                            self.default_fields
+                            default_fields
                                 .iter()
-                                .map(|default_field| (*default_field, literal.phrase.clone()))
-                                .collect::<Vec<(Field, String)>>()
+                                .map(|default_field| (*default_field, "", literal.phrase.clone()))
+                                .collect::<Vec<(Field, &str, String)>>()
                         }
                     }
                 };
                 let mut asts: Vec<LogicalAst> = Vec::new();
-                for (field, phrase) in term_phrases {
-                    if let Some(ast) = self.compute_logical_ast_for_leaf(field, &phrase)? {
+                for (field, json_path, phrase) in term_phrases {
+                    if let FieldType::JsonObject(_) =
+                        self.schema.get_field_entry(field).field_type()
+                    {
+                        if literal.fuzzy_distance.is_some() {
+                            return Err(QueryParserError::UnsupportedFuzzyTerm(phrase));
+                        }
+                        if let Some(ast) = self.compute_logical_ast_for_json_leaf(
+                            field,
+                            json_path,
+                            &phrase,
+                            literal.slop,
+                        )? {
+                            asts.push(ast.boost(self.field_boost(field)));
+                        }
+                        continue;
+                    }
+                    let wildcard_literal = if self.wildcards_enabled && !literal.quoted {
+                        self.compute_wildcard_literal(field, &phrase)?
+                    } else {
+                        None
+                    };
+                    let literal_opt = match (wildcard_literal, literal.fuzzy_distance) {
+                        (Some(wildcard_literal), _) => Some(wildcard_literal),
+                        (None, Some(distance)) => {
+                            self.compute_fuzzy_literal(field, &phrase, distance)?
+                        }
+                        (None, None) => {
+                            self.compute_logical_ast_for_leaf(field, &phrase, literal.slop)?
+                        }
+                    };
+                    if let Some(ast) = literal_opt {
                         // Apply some field specific boost defined at the query parser level.
                         let boost = self.field_boost(field);
                         asts.push(LogicalAst::Leaf(Box::new(ast)).boost(boost));
@@ -506,7 +1026,15 @@ impl QueryParser {
                 lower,
                 upper,
             } => {
-                let fields = self.resolved_fields(&field)?;
+                if let Some(field_name) = &field {
+                    let (field, json_path) = self.resolve_field_and_json_path(field_name)?;
+                    if !json_path.is_empty() {
+                        let logical_ast = self
+                            .compute_logical_ast_for_json_range(field, json_path, &lower, &upper)?;
+                        return Ok(logical_ast.boost(self.field_boost(field)));
+                    }
+                }
+                let fields = self.resolved_fields(&field, default_fields)?;
                 let mut clauses = fields
                     .iter()
                     .map(|&field| {
@@ -538,11 +1066,111 @@ impl QueryParser {
     }
 }
 
+/// Returns the bound of the range of the `i64` leaves of a json object field,
+/// given a numeric bound.
+///
+/// A float bound is rounded towards the inside of the range.
+fn json_i64_bound(bound: &UserInputBound, is_lower: bool) -> Bound<i64> {
+    let bound_str = bound.term_str();
+    if bound_str == "*" {
+        return Bound::Included(if is_lower { i64::MIN } else { i64::MAX });
+    }
+    if let Ok(val) = i64::from_str(bound_str) {
+        return json_bound_with_value(bound, val);
+    }
+    let val = f64::from_str(bound_str).unwrap_or(0.0);
+    let rounded_val = if is_lower { val.ceil() } else { val.floor() };
+    if rounded_val == val {
+        json_bound_with_value(bound, rounded_val as i64)
+    } else {
+        Bound::Included(rounded_val as i64)
+    }
+}
+
+/// Returns the bound of the range of the `f64` leaves of a json object field,
+/// given a numeric bound.
+fn json_f64_bound(bound: &UserInputBound, unbounded_val: f64) -> Bound<f64> {
+    match f64::from_str(bound.term_str()) {
+        Ok(val) => json_bound_with_value(bound, val),
+        Err(_) => Bound::Included(unbounded_val),
+    }
+}
+
+/// Returns the bound of a range at `val`, included or excluded like `bound`.
+fn json_bound_with_value<T>(bound: &UserInputBound, val: T) -> Bound<T> {
+    match bound {
+        UserInputBound::Exclusive(_) => Bound::Excluded(val),
+        _ => Bound::Included(val),
+    }
+}
+
+/// Returns the terms of the number or the bool represented by `phrase`, searched
+/// in a json object field.
+///
+/// A number matches the numbers of the same value, whether they are indexed
+/// as integers or as floats.
+fn json_typed_terms(field: Field, json_path: &str, phrase: &str) -> Vec<Term> {
+    if let Ok(val) = bool::from_str(phrase) {
+        return vec![Term::from_field_json_bool(field, json_path, val)];
+    }
+    if let Ok(val) = i64::from_str(phrase) {
+        return vec![
+            Term::from_field_json_i64(field, json_path, val),
+            Term::from_field_json_f64(field, json_path, val as f64),
+        ];
+    }
+    if let Ok(val) = u64::from_str(phrase) {
+        return vec![
+            Term::from_field_json_u64(field, json_path, val),
+            Term::from_field_json_f64(field, json_path, val as f64),
+        ];
+    }
+    match f64::from_str(phrase) {
+        Ok(val) if val.is_finite() => {
+            let mut terms = vec![Term::from_field_json_f64(field, json_path, val)];
+            if val.fract() == 0.0 {
+                if val >= i64::MIN as f64 && val < i64::MAX as f64 {
+                    terms.push(Term::from_field_json_i64(field, json_path, val as i64));
+                } else if val >= 0.0 && val < u64::MAX as f64 {
+                    terms.push(Term::from_field_json_u64(field, json_path, val as u64));
+                }
+            }
+            terms
+        }
+        _ => vec![],
+    }
+}
+
+/// Parses a CIDR block, e.g. `10.0.0.0/16` or `2001:db8::/32`, and returns its
+/// first and last addresses.
+///
+/// The prefix length of an IPv4 block applies to the IPv4 address, before
+/// it is mapped into the IPv6 space.
+fn parse_cidr(cidr: &str) -> Option<(Ipv6Addr, Ipv6Addr)> {
+    let (ip_addr, prefix_len) = cidr.split_once('/')?;
+    let ip_addr = IpAddr::from_str(ip_addr).ok()?;
+    let prefix_len = u32::from_str(prefix_len).ok()?;
+    let max_prefix_len = if ip_addr.is_ipv4() { 32 } else { 128 };
+    if prefix_len > max_prefix_len {
+        return None;
+    }
+    let prefix_len = prefix_len + 128 - max_prefix_len;
+    let host_mask = u128::MAX.checked_shr(prefix_len).unwrap_or(0);
+    let network = u128::from(ip_addr_to_ipv6(ip_addr)) & !host_mask;
+    Some((Ipv6Addr::from(network), Ipv6Addr::from(network | host_mask)))
+}
+
 fn convert_literal_to_query(logical_literal: LogicalLiteral) -> Box<dyn Query> {
     match logical_literal {
         LogicalLiteral::Term(term) => Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs)),
-        LogicalLiteral::Phrase(term_with_offsets) => {
-            Box::new(PhraseQuery::new_with_offset(term_with_offsets))
+        LogicalLiteral::Phrase(term_with_offsets, slop) => Box::new(
+            PhraseQuery::new_with_offset_and_slop(term_with_offsets, slop),
+        ),
+        LogicalLiteral::Fuzzy { term, distance } => {
+            Box::new(FuzzyTermQuery::new(term, distance, true))
+        }
+        LogicalLiteral::Wildcard { field, pattern } => {
+            Box::new(WildcardQuery::new(field, &pattern))
         }
         LogicalLiteral::Range {
             field,
@@ -556,24 +1184,34 @@ fn convert_literal_to_query(logical_literal: LogicalLiteral) -> Box<dyn Query> {
     }
 }
 
-fn convert_to_query(logical_ast: LogicalAst) -> Box<dyn Query> {
+/// Converts the logical ast to a query, requiring the documents to match at least
+/// `minimum_should_match` of its top-level `Should` clauses, if it has any.
+fn convert_to_query(logical_ast: LogicalAst, minimum_should_match: usize) -> Box<dyn Query> {
     match trim_ast(logical_ast) {
         Some(LogicalAst::Clause(trimmed_clause)) => {
             let occur_subqueries = trimmed_clause
                 .into_iter()
-                .map(|(occur, subquery)| (occur, convert_to_query(subquery)))
+                .map(|(occur, subquery)| (occur, convert_to_query(subquery, 0)))
                 .collect::<Vec<_>>();
             assert!(
                 !occur_subqueries.is_empty(),
                 "Should not be empty after trimming"
             );
-            Box::new(BooleanQuery::new(occur_subqueries))
+            let has_should_clause = occur_subqueries
+                .iter()
+                .any(|(occur, _)| *occur == Occur::Should);
+            let boolean_query = BooleanQuery::new(occur_subqueries);
+            if has_should_clause {
+                Box::new(boolean_query.with_minimum_required_clauses(minimum_should_match))
+            } else {
+                Box::new(boolean_query)
+            }
         }
         Some(LogicalAst::Leaf(trimmed_logical_literal)) => {
             convert_literal_to_query(*trimmed_logical_literal)
         }
         Some(LogicalAst::Boost(ast, boost)) => {
-            let query = convert_to_query(*ast);
+            let query = convert_to_query(*ast, minimum_should_match);
             let boosted_query = BoostQuery::new(query, boost);
             Box::new(boosted_query)
         }
@@ -584,10 +1222,12 @@ fn convert_to_query(logical_ast: LogicalAst) -> Box<dyn Query> {
 #[cfg(test)]
 mod test {
     use super::super::logical_ast::*;
+    use super::parse_cidr;
     use super::QueryParser;
     use super::QueryParserError;
-    use crate::query::Query;
-    use crate::schema::Field;
+    use crate::collector::Count;
+    use crate::query::{BooleanQuery, EmptyQuery, Occur, Query};
+    use crate::schema::{ip_addr_to_ipv6, Document, Field};
     use crate::schema::{IndexRecordOption, TextFieldIndexing, TextOptions};
     use crate::schema::{Schema, Term, INDEXED, STORED, STRING, TEXT};
     use crate::tokenizer::{
@@ -595,6 +1235,7 @@ mod test {
     };
     use crate::Index;
     use matches::assert_matches;
+    use std::net::{IpAddr, Ipv6Addr};
 
     fn make_schema() -> Schema {
         let mut schema_builder = Schema::builder();
@@ -619,6 +1260,8 @@ mod test {
         schema_builder.add_facet_field("facet_not_indexed", STORED);
         schema_builder.add_bytes_field("bytes", INDEXED);
         schema_builder.add_bytes_field("bytes_not_indexed", STORED);
+        schema_builder.add_bool_field("bool", INDEXED);
+        schema_builder.add_ip_addr_field("ip", INDEXED);
         schema_builder.build()
     }
 
@@ -698,7 +1341,7 @@ mod test {
         let query = query_parser.parse_query("title:[A TO B]").unwrap();
         assert_eq!(
             format!("{:?}", query),
-            "Boost(query=RangeQuery { field: Field(0), value_type: Str, left_bound: Included([97]), right_bound: Included([98]) }, boost=2)"
+            "Boost(query=RangeQuery { field: Field(0), value_type: Str, left_bound: Included([97]), right_bound: Included([98]), fast_field_fallback: true }, boost=2)"
         );
     }
 
@@ -902,6 +1545,243 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn test_parse_query_wildcards() {
+        let mut query_parser = make_query_parser();
+        let logical_ast = |query_parser: &QueryParser, query: &str| -> String {
+            format!(
+                "{:?}",
+                query_parser.parse_query_to_logical_ast(query).unwrap()
+            )
+        };
+        // Wildcards are disabled by default.
+        assert_eq!(
+            logical_ast(&query_parser, "title:jo*n"),
+            "\"[(0, Term(field=0,bytes=[106, 111])), \
+             (1, Term(field=0,bytes=[110]))]\""
+        );
+        query_parser.set_wildcards_enabled(true);
+        assert_eq!(
+            logical_ast(&query_parser, "title:Jo*N"),
+            "Wildcard(field=0,pattern=\"jo*n\")"
+        );
+        assert_eq!(
+            logical_ast(&query_parser, "nottokenized:Jo?N"),
+            "Wildcard(field=7,pattern=\"Jo?N\")"
+        );
+        assert_eq!(
+            logical_ast(&query_parser, "*on"),
+            "(Wildcard(field=0,pattern=\"*on\") Wildcard(field=1,pattern=\"*on\"))"
+        );
+        // Quoted terms are not wildcard patterns.
+        assert_eq!(
+            logical_ast(&query_parser, "nottokenized:\"jo*n\""),
+            "Term(field=7,bytes=[106, 111, 42, 110])"
+        );
+        assert_eq!(
+            logical_ast(&query_parser, "title:john"),
+            "Term(field=0,bytes=[106, 111, 104, 110])"
+        );
+        let query = query_parser.parse_query("title:jo*n").unwrap();
+        assert!(format!("{:?}", query).starts_with("WildcardQuery"));
+        assert_matches!(
+            query_parser.parse_query("notindexed_text:jo*n"),
+            Err(QueryParserError::FieldNotIndexed(_))
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_field_group() {
+        test_parse_query_to_logical_ast_helper(
+            "title:(a OR b)",
+            "(Term(field=0,bytes=[97]) Term(field=0,bytes=[98]))",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "title:(+a -b \"c d\")",
+            "(+Term(field=0,bytes=[97]) -Term(field=0,bytes=[98]) \
+             \"[(0, Term(field=0,bytes=[99])), (1, Term(field=0,bytes=[100]))]\")",
+            false,
+        );
+        // The default fields are restored after the group.
+        test_parse_query_to_logical_ast_helper(
+            "title:(a text:b) c",
+            "((Term(field=0,bytes=[97]) Term(field=1,bytes=[98])) \
+             (Term(field=0,bytes=[99]) Term(field=1,bytes=[99])))",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "text:(a title:(b c))^2",
+            "(Term(field=1,bytes=[97]) (Term(field=0,bytes=[98]) Term(field=0,bytes=[99])))^2",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "title:([a TO b] c)",
+            "((Included(Term(field=0,bytes=[97])) TO Included(Term(field=0,bytes=[98]))) \
+             Term(field=0,bytes=[99]))",
+            false,
+        );
+        let query_parser = make_query_parser();
+        assert_eq!(
+            query_parser.parse_query("unknown:(a OR b)").err(),
+            Some(QueryParserError::FieldDoesNotExist("unknown".to_string()))
+        );
+        assert_eq!(
+            query_parser.parse_query("title:(a OR unknown:b)").err(),
+            Some(QueryParserError::FieldDoesNotExist("unknown".to_string()))
+        );
+        assert_eq!(
+            query_parser.parse_query("title:(-a)").err(),
+            Some(QueryParserError::AllButQueryForbidden)
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_lenient() {
+        let query_parser = make_query_parser();
+        let lenient = |query: &str| -> (String, Vec<QueryParserError>) {
+            let (query, errors) = query_parser.parse_query_lenient(query);
+            (format!("{:?}", query), errors)
+        };
+        let strict =
+            |query: &str| -> String { format!("{:?}", query_parser.parse_query(query).unwrap()) };
+        assert_eq!(lenient("title:a AND b"), (strict("title:a AND b"), vec![]));
+        assert_eq!(
+            lenient("title:(a OR b"),
+            (
+                strict("title:(a OR b)"),
+                vec![QueryParserError::UnbalancedParentheses]
+            )
+        );
+        assert_eq!(
+            lenient("a title:b\"c"),
+            (
+                strict("a title:bc"),
+                vec![QueryParserError::UnbalancedQuotes]
+            )
+        );
+        assert_eq!(
+            lenient("a~3 b"),
+            (
+                strict("a b"),
+                vec![QueryParserError::InvalidFuzzyDistance("~3".to_string())]
+            )
+        );
+        assert_eq!(
+            lenient("a title: b"),
+            (
+                strict("a title b"),
+                vec![QueryParserError::InvalidFragment("title:".to_string())]
+            )
+        );
+        // Unknown fields are searched in the default fields.
+        assert_eq!(
+            lenient("unknown:a +title:b"),
+            (
+                strict("a +title:b"),
+                vec![QueryParserError::FieldDoesNotExist("unknown".to_string())]
+            )
+        );
+        assert_eq!(
+            lenient("unknown:(a b)"),
+            (
+                strict("(a b)"),
+                vec![QueryParserError::FieldDoesNotExist("unknown".to_string())]
+            )
+        );
+        // Invalid range bounds and terms are dropped.
+        let (query, errors) = lenient("a signed:[1 TO abc] date:[* TO 2020] b");
+        assert_eq!(query, strict("a b"));
+        assert_eq!(errors.len(), 2);
+        assert_matches!(errors[0], QueryParserError::ExpectedInt(_));
+        assert_matches!(errors[1], QueryParserError::DateFormatError(_, _));
+        assert_eq!(
+            lenient("-a"),
+            (
+                format!("{:?}", EmptyQuery),
+                vec![QueryParserError::AllButQueryForbidden]
+            )
+        );
+        let (query, errors) = lenient("signed:abc");
+        assert_eq!(query, format!("{:?}", EmptyQuery));
+        assert_matches!(errors[..], [QueryParserError::ExpectedInt(_)]);
+    }
+
+    #[test]
+    pub fn test_parse_query_fuzzy_and_boost() {
+        test_parse_query_to_logical_ast_helper(
+            "title:rust~1",
+            "Term(field=0,bytes=[114, 117, 115, 116])~1",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "title:Rust~2^2",
+            "Term(field=0,bytes=[114, 117, 115, 116])~2^2",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "title:a~1 AND title:b^2",
+            "(+Term(field=0,bytes=[97])~1 +Term(field=0,bytes=[98])^2)",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "(title:a~1 OR title:b)^0.5 title:c",
+            "((Term(field=0,bytes=[97])~1 Term(field=0,bytes=[98]))^0.5 \
+             Term(field=0,bytes=[99]))",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "title:\"a b\"^0.3",
+            "\"[(0, Term(field=0,bytes=[97])), \
+             (1, Term(field=0,bytes=[98]))]\"^0.3",
+            false,
+        );
+        let query_parser = make_query_parser();
+        let query = query_parser.parse_query("title:rust~1^2").unwrap();
+        assert!(format!("{:?}", query).starts_with("Boost(query=FuzzyTermQuery {"));
+        assert_eq!(
+            query_parser.parse_query("title:rust~3").err(),
+            Some(QueryParserError::InvalidFuzzyDistance("~3".to_string()))
+        );
+        assert_eq!(
+            query_parser.parse_query("(title:rust^abc OR b)").err(),
+            Some(QueryParserError::InvalidBoost("^abc".to_string()))
+        );
+        assert_eq!(
+            query_parser.parse_query("signed:1~1").err(),
+            Some(QueryParserError::UnsupportedFuzzyTerm("1".to_string()))
+        );
+        assert_eq!(
+            query_parser.parse_query("title:a-b~1").err(),
+            Some(QueryParserError::UnsupportedFuzzyTerm("a-b".to_string()))
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_phrase_slop() {
+        test_parse_query_to_logical_ast_helper(
+            "title:\"a b\"~2",
+            "\"[(0, Term(field=0,bytes=[97])), \
+             (1, Term(field=0,bytes=[98]))]\"~2",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "title:\"a b\"~0",
+            "\"[(0, Term(field=0,bytes=[97])), \
+             (1, Term(field=0,bytes=[98]))]\"",
+            false,
+        );
+        let query_parser = make_query_parser();
+        assert_matches!(
+            query_parser.parse_query("title:\"a b\"~-1"),
+            Err(QueryParserError::SyntaxError)
+        );
+        assert_matches!(
+            query_parser.parse_query("title:\"a b\"~x"),
+            Err(QueryParserError::SyntaxError)
+        );
+    }
+
     #[test]
     pub fn test_parse_query_to_ast_ranges() {
         test_parse_query_to_logical_ast_helper(
@@ -945,10 +1825,70 @@ mod test {
              Excluded(Term(field=10,bytes=[191, 248, 0, 0, 0, 0, 0, 0])))",
             false,
         );
+        test_parse_query_to_logical_ast_helper(
+            "signed:>=-5",
+            "(Included(Term(field=2,bytes=[127, 255, 255, 255, 255, 255, 255, 251])) TO Unbounded)",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "unsigned:<3",
+            "(Unbounded TO Excluded(Term(field=3,bytes=[0, 0, 0, 0, 0, 0, 0, 3])))",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "date:[2023-01-01T00:00:00Z TO *]",
+            "(Included(Term(field=9,bytes=[128, 0, 0, 0, 99, 176, 205, 0])) TO Unbounded)",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "date:>2023-01-01T01:00:00+01:00",
+            "(Excluded(Term(field=9,bytes=[128, 0, 0, 0, 99, 176, 205, 0])) TO Unbounded)",
+            false,
+        );
 
         test_parse_query_to_logical_ast_helper("*", "*", false);
     }
 
+    #[test]
+    pub fn test_query_parser_date_math() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let date_field = schema_builder.add_date_field("date", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let now = chrono::Utc::now();
+        for days_ago in &[10, 3, 0] {
+            index_writer.add_document(doc!(date_field => now - chrono::Duration::days(*days_ago)));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![]);
+        let count = |query: &str| -> crate::Result<usize> {
+            let query = query_parser.parse_query(query).unwrap();
+            searcher.search(&query, &Count)
+        };
+        assert_eq!(count("date:>=now-7d")?, 2);
+        assert_eq!(count("date:<now-7d")?, 1);
+        assert_eq!(count("date:[now-7d TO now+1m]")?, 2);
+        assert_eq!(count("date:[* TO now-2d/d]")?, 2);
+        assert_eq!(count("date:{now-1y TO now-1d}")?, 2);
+        assert_eq!(count("date:>now+1d")?, 0);
+        assert_eq!(
+            query_parser.parse_query("date:>=now-7x").err(),
+            Some(QueryParserError::DateFormatError(
+                "date".to_string(),
+                "now-7x".to_string()
+            ))
+        );
+        assert_eq!(
+            query_parser.parse_query("date:[2023-01-01 TO *]").err(),
+            Some(QueryParserError::DateFormatError(
+                "date".to_string(),
+                "2023-01-01".to_string()
+            ))
+        );
+        Ok(())
+    }
+
     #[test]
     pub fn test_query_parser_field_does_not_exist() {
         let query_parser = make_query_parser();
@@ -1024,12 +1964,139 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn test_query_parser_bool() {
+        let query_parser = make_query_parser();
+        let query = query_parser.parse_query("bool:true").unwrap();
+        assert_eq!(
+            format!("{:?}", query),
+            "TermQuery(Term(field=15,bytes=[0, 0, 0, 0, 0, 0, 0, 1]))"
+        );
+        assert!(query_parser.parse_query("bool:false").is_ok());
+        assert_matches!(
+            query_parser.parse_query("bool:1"),
+            Err(QueryParserError::ExpectedBool(_))
+        );
+    }
+
+    #[test]
+    pub fn test_query_parser_bool_search() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let is_published = schema_builder.add_bool_field("is_published", INDEXED | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(is_published => true));
+        index_writer.add_document(doc!(is_published => false));
+        index_writer.add_document(doc!(is_published => true));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![]);
+        let count = |query: &str| searcher.search(&query_parser.parse_query(query)?, &Count);
+        assert_eq!(count("is_published:true")?, 2);
+        assert_eq!(count("is_published:false")?, 1);
+        let doc = searcher.doc(crate::DocAddress::new(0, 1))?;
+        assert_eq!(
+            doc.get_first(is_published).unwrap().bool_value(),
+            Some(false)
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_query_parser_ip_addr() {
+        let query_parser = make_query_parser();
+        assert!(query_parser.parse_query("ip:10.0.0.1").is_ok());
+        assert!(query_parser.parse_query("ip:2001:db8::1").is_ok());
+        assert!(query_parser.parse_query("ip:\"2001:db8::1\"").is_ok());
+        assert_matches!(
+            query_parser.parse_query("ip:10.0.0.256"),
+            Err(QueryParserError::ExpectedIpAddr(_))
+        );
+        assert_matches!(
+            query_parser.parse_query("ip:10.0.0.0/33"),
+            Err(QueryParserError::ExpectedIpAddr(_))
+        );
+    }
+
+    #[test]
+    fn test_parse_cidr() {
+        let v4 = |ip: &str| ip_addr_to_ipv6(IpAddr::V4(ip.parse().unwrap()));
+        assert_eq!(
+            parse_cidr("10.0.0.0/16"),
+            Some((v4("10.0.0.0"), v4("10.0.255.255")))
+        );
+        assert_eq!(
+            parse_cidr("10.1.2.3/8"),
+            Some((v4("10.0.0.0"), v4("10.255.255.255")))
+        );
+        assert_eq!(
+            parse_cidr("10.1.2.3/32"),
+            Some((v4("10.1.2.3"), v4("10.1.2.3")))
+        );
+        assert_eq!(
+            parse_cidr("2001:db8::/32"),
+            Some((
+                "2001:db8::".parse().unwrap(),
+                "2001:db8:ffff:ffff:ffff:ffff:ffff:ffff".parse().unwrap()
+            ))
+        );
+        assert_eq!(
+            parse_cidr("::/0"),
+            Some((Ipv6Addr::from(0u128), Ipv6Addr::from(u128::MAX)))
+        );
+        assert_eq!(parse_cidr("10.0.0.0/33"), None);
+        assert_eq!(parse_cidr("2001:db8::/129"), None);
+        assert_eq!(parse_cidr("10.0.0.0/a"), None);
+    }
+
+    #[test]
+    pub fn test_query_parser_ip_addr_search() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let ip = schema_builder.add_ip_addr_field("ip", INDEXED | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let v4 = |ip: &str| IpAddr::V4(ip.parse().unwrap());
+        let v6 = |ip: &str| IpAddr::V6(ip.parse().unwrap());
+        let mut doc = Document::new();
+        doc.add_ip_addr(ip, v4("10.0.0.1"));
+        index_writer.add_document(doc);
+        let mut doc = Document::new();
+        doc.add_ip_addr(ip, v4("10.0.3.4"));
+        index_writer.add_document(doc);
+        let mut doc = Document::new();
+        doc.add_ip_addr(ip, v4("10.1.0.1"));
+        index_writer.add_document(doc);
+        let mut doc = Document::new();
+        doc.add_ip_addr(ip, v6("2001:db8::1"));
+        index_writer.add_document(doc);
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![]);
+        let count = |query: &str| searcher.search(&query_parser.parse_query(query)?, &Count);
+        assert_eq!(count("ip:10.0.0.1")?, 1);
+        // ipv4 addresses are mapped to ipv6 addresses.
+        assert_eq!(count("ip:::ffff:10.0.0.1")?, 1);
+        assert_eq!(count("ip:2001:db8::1")?, 1);
+        assert_eq!(count("ip:2001:db8::2")?, 0);
+        assert_eq!(count("ip:[10.0.0.0 TO 10.0.255.255]")?, 2);
+        assert_eq!(count("ip:>=10.0.3.4")?, 3);
+        assert_eq!(count("ip:10.0.0.0/16")?, 2);
+        assert_eq!(count("ip:10.0.0.0/8")?, 3);
+        assert_eq!(count("ip:2001:db8::/32")?, 1);
+        let doc = searcher.doc(crate::DocAddress::new(0, 3))?;
+        assert_eq!(
+            doc.get_first(ip).unwrap().ip_addr_value(),
+            Some("2001:db8::1".parse().unwrap())
+        );
+        Ok(())
+    }
+
     #[test]
     pub fn test_query_parser_expected_date() {
         let query_parser = make_query_parser();
         assert_matches!(
             query_parser.parse_query("date:18a"),
-            Err(QueryParserError::DateFormatError(_))
+            Err(QueryParserError::DateFormatError(_, _))
         );
         assert!(query_parser
             .parse_query("date:\"1985-04-12T23:20:50.52Z\"")
@@ -1068,6 +2135,107 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn test_parse_query_default_occur() {
+        let title = Field::from_field_id(0);
+        let text = Field::from_field_id(1);
+        let logical_ast = |query_parser: &QueryParser, query: &str| -> String {
+            format!(
+                "{:?}",
+                query_parser.parse_query_to_logical_ast(query).unwrap()
+            )
+        };
+        let mut query_parser = make_query_parser();
+        query_parser.set_default_occur(Occur::Must);
+        assert_eq!(
+            logical_ast(&query_parser, "title:a title:b"),
+            "(+Term(field=0,bytes=[97]) +Term(field=0,bytes=[98]))"
+        );
+        // Explicit operators take precedence over the default.
+        assert_eq!(
+            logical_ast(&query_parser, "title:a OR title:b"),
+            "(Term(field=0,bytes=[97]) Term(field=0,bytes=[98]))"
+        );
+        assert_eq!(
+            logical_ast(&query_parser, "title:a -title:b"),
+            "(+Term(field=0,bytes=[97]) -Term(field=0,bytes=[98]))"
+        );
+
+        let mut query_parser = make_query_parser();
+        query_parser.set_field_default_occur(title, Occur::Must);
+        assert_eq!(
+            logical_ast(&query_parser, "title:a text:b text:c^2"),
+            "(+Term(field=0,bytes=[97]) Term(field=1,bytes=[98]) Term(field=1,bytes=[99])^2)"
+        );
+        assert_eq!(
+            logical_ast(&query_parser, "title:(a b) text:(c d)"),
+            "(+(+Term(field=0,bytes=[97]) +Term(field=0,bytes=[98])) \
+             (Term(field=1,bytes=[99]) Term(field=1,bytes=[100])))"
+        );
+        // Terms searching several default fields get the global default.
+        assert_eq!(
+            logical_ast(&query_parser, "a title:b"),
+            "((Term(field=0,bytes=[97]) Term(field=1,bytes=[97])) +Term(field=0,bytes=[98]))"
+        );
+        assert_eq!(
+            logical_ast(&query_parser, "title:a OR title:b"),
+            "(Term(field=0,bytes=[97]) Term(field=0,bytes=[98]))"
+        );
+
+        // The default of a field takes precedence over the global default.
+        let mut query_parser = make_query_parser();
+        query_parser.set_conjunction_by_default();
+        query_parser.set_field_default_occur(text, Occur::Should);
+        assert_eq!(
+            logical_ast(&query_parser, "title:a text:b text:c"),
+            "(+Term(field=0,bytes=[97]) Term(field=1,bytes=[98]) Term(field=1,bytes=[99]))"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_minimum_should_match() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let tags = schema_builder.add_text_field("tags", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "happy tax payer", tags => "a"));
+        index_writer.add_document(doc!(title => "happy payer", tags => "b"));
+        index_writer.add_document(doc!(title => "happy", tags => "a"));
+        index_writer.add_document(doc!(title => "tax", tags => "b"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count = |query_parser: &QueryParser, query: &str| -> crate::Result<usize> {
+            searcher.search(&query_parser.parse_query(query)?, &Count)
+        };
+        let mut query_parser = QueryParser::for_index(&index, vec![title]);
+        query_parser.set_minimum_should_match(2);
+        let query = query_parser.parse_query("happy tax payer")?;
+        let boolean_query = query.downcast_ref::<BooleanQuery>().unwrap();
+        assert_eq!(boolean_query.minimum_number_should_match(), 2);
+        assert_eq!(count(&query_parser, "happy tax payer")?, 2);
+        assert_eq!(count(&query_parser, "happy OR tax OR payer")?, 2);
+        // Only the top-level clauses get the minimum.
+        let query = query_parser.parse_query("happy AND (tax payer)")?;
+        let boolean_query = query.downcast_ref::<BooleanQuery>().unwrap();
+        assert_eq!(boolean_query.minimum_number_should_match(), 0);
+        let subquery = &boolean_query.clauses()[1].1;
+        let sub_boolean_query = subquery.downcast_ref::<BooleanQuery>().unwrap();
+        assert_eq!(sub_boolean_query.minimum_number_should_match(), 0);
+        assert_eq!(count(&query_parser, "happy AND (tax payer)")?, 2);
+        // Explicitly required clauses do not count as `Should` clauses.
+        assert_eq!(count(&query_parser, "+happy tax payer")?, 1);
+        assert_eq!(count(&query_parser, "+happy tax")?, 0);
+
+        // The clauses required by default do not count either.
+        query_parser.set_field_default_occur(tags, Occur::Must);
+        assert_eq!(count(&query_parser, "tags:a happy tax payer")?, 1);
+        query_parser.set_conjunction_by_default();
+        assert_eq!(count(&query_parser, "happy tax payer")?, 1);
+        assert_eq!(count(&query_parser, "happy OR tax OR payer")?, 2);
+        Ok(())
+    }
+
     #[test]
     pub fn test_parse_query_to_ast_conjunction() {
         test_parse_query_to_logical_ast_helper(
@@ -1132,4 +2300,43 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_query_parser_search_tokenizer() -> crate::Result<()> {
+        use crate::tokenizer::EdgeNgramFilter;
+        let mut schema_builder = Schema::builder();
+        let text_field_indexing = TextFieldIndexing::default()
+            .set_tokenizer("edge_ngram")
+            .set_search_tokenizer("default")
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+        let title = schema_builder.add_text_field(
+            "title",
+            TextOptions::default().set_indexing_options(text_field_indexing),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        index.tokenizers().register(
+            "edge_ngram",
+            TextAnalyzer::from(SimpleTokenizer)
+                .filter(LowerCaser)
+                .filter(EdgeNgramFilter::new(1, 10)),
+        );
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "Searching the Web"));
+        index_writer.add_document(doc!(title => "Sea shells"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![title]);
+        let count = |query: &str| searcher.search(&query_parser.parse_query(query)?, &Count);
+        // the query is not split into edge n-grams: "sea" is a single term.
+        assert_eq!(
+            format!("{:?}", query_parser.parse_query("SEA")?),
+            format!("{:?}", query_parser.parse_query("sea")?)
+        );
+        assert_eq!(count("SEA")?, 2);
+        assert_eq!(count("searc")?, 1);
+        assert_eq!(count("seas")?, 0);
+        assert_eq!(count("\"search the w\"")?, 1);
+        assert_eq!(count("\"sea web\"")?, 0);
+        Ok(())
+    }
 }
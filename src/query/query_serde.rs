@@ -0,0 +1,849 @@
+use crate::query::{
+    AllQuery, BooleanQuery, BoostQuery, ConstScoreQuery, DisjunctionMaxQuery, ExistsQuery,
+    FuzzyTermQuery, Occur, PhraseQuery, Query, RangeQuery, RegexQuery, TermQuery, TermSetQuery,
+    WildcardQuery,
+};
+use crate::schema::{parse_ip_addr, Facet, Field, FieldType, IndexRecordOption, Schema, Term};
+use crate::{Score, TantivyError};
+use chrono::{SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::ops::Bound;
+
+/// A serializable description of a query tree, e.g. to send a query
+/// to the shards of a distributed index as JSON.
+///
+/// Fields are referred to by their name rather than by their `Field` id,
+/// so that a description can be used on any index with a compatible schema.
+///
+/// The supported queries are `AllQuery`, `TermQuery`, `BooleanQuery`, `PhraseQuery`,
+/// `RangeQuery`, `FuzzyTermQuery`, `RegexQuery`, `BoostQuery`, `ConstScoreQuery`,
+/// `DisjunctionMaxQuery`, `ExistsQuery`, `TermSetQuery` and `WildcardQuery`.
+///
+/// ```rust
+/// use tantivy::query::{QueryDescription, QueryParser};
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::Index;
+///
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let schema = schema_builder.build();
+/// let index = Index::create_in_ram(schema.clone());
+///
+/// let query = QueryParser::for_index(&index, vec![title]).parse_query("hello^2").unwrap();
+/// let description = QueryDescription::from_query(query.as_ref(), &schema)?;
+/// let json = serde_json::to_string(&description).unwrap();
+/// assert_eq!(
+///     json,
+///     r#"{"type":"boost","query":{"type":"term","field":"title","value":"hello","index_record_option":"freq"},"boost":2.0}"#
+/// );
+///
+/// // ... on another node, having the same schema.
+/// let description: QueryDescription = serde_json::from_str(&json).unwrap();
+/// let query = description.to_query(&schema)?;
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QueryDescription {
+    /// Describes an `AllQuery`.
+    All,
+    /// Describes a `TermQuery`.
+    Term {
+        /// Name of the field of the term.
+        field: String,
+        /// Value of the term.
+        value: TermValue,
+        /// Postings to read for the term.
+        #[serde(default = "default_index_record_option")]
+        index_record_option: IndexRecordOption,
+    },
+    /// Describes a `BooleanQuery`.
+    Boolean {
+        /// Subqueries, with their occur.
+        clauses: Vec<BooleanClause>,
+        /// Minimum number of `Should` clauses that documents have to match.
+        #[serde(default)]
+        minimum_should_match: usize,
+    },
+    /// Describes a `PhraseQuery`.
+    Phrase {
+        /// Name of the field of the phrase.
+        field: String,
+        /// Terms of the phrase, with their offsets.
+        terms: Vec<PhraseTerm>,
+        /// Slop of the phrase.
+        #[serde(default)]
+        slop: u32,
+    },
+    /// Describes a `RangeQuery`.
+    Range {
+        /// Name of the field of the range.
+        field: String,
+        /// Lower bound of the range.
+        lower: Bound<TermValue>,
+        /// Upper bound of the range.
+        upper: Bound<TermValue>,
+        /// Whether the fast field may be searched instead of the inverted index.
+        #[serde(default = "default_fast_field_fallback")]
+        fast_field_fallback: bool,
+    },
+    /// Describes a `FuzzyTermQuery`.
+    Fuzzy {
+        /// Name of the field of the term.
+        field: String,
+        /// Text of the term.
+        value: String,
+        /// Maximum Levenshtein distance to the term.
+        distance: u8,
+        /// Whether a transposition costs one, rather than two.
+        #[serde(default)]
+        transposition_cost_one: bool,
+        /// Whether the terms having a prefix close to the term are matched.
+        #[serde(default)]
+        prefix: bool,
+        /// Number of characters at the beginning of the term that must match exactly.
+        #[serde(default)]
+        prefix_length: usize,
+    },
+    /// Describes a `RegexQuery`.
+    Regex {
+        /// Name of the field searched.
+        field: String,
+        /// Pattern of the regex.
+        pattern: String,
+    },
+    /// Describes a `BoostQuery`.
+    Boost {
+        /// The boosted query.
+        query: Box<QueryDescription>,
+        /// Boost factor.
+        boost: Score,
+    },
+    /// Describes a `ConstScoreQuery`.
+    ConstScore {
+        /// The query whose matching documents get the constant score.
+        query: Box<QueryDescription>,
+        /// Score of the matching documents.
+        score: Score,
+    },
+    /// Describes a `DisjunctionMaxQuery`.
+    DisjunctionMax {
+        /// Subqueries of the disjunction.
+        disjuncts: Vec<QueryDescription>,
+        /// Weight of the scores of the disjuncts other than the best one.
+        #[serde(default)]
+        tie_breaker: Score,
+    },
+    /// Describes an `ExistsQuery`.
+    Exists {
+        /// Name of the field searched.
+        field: String,
+    },
+    /// Describes a `TermSetQuery`.
+    TermSet {
+        /// Terms of the set, possibly of different fields.
+        terms: Vec<FieldTerm>,
+    },
+    /// Describes a `WildcardQuery`.
+    Wildcard {
+        /// Name of the field searched.
+        field: String,
+        /// Pattern of the wildcard query.
+        pattern: String,
+    },
+}
+
+/// A clause of a `BooleanQuery` description.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BooleanClause {
+    /// Occur of the clause.
+    #[serde(with = "OccurDef")]
+    pub occur: Occur,
+    /// Query of the clause.
+    pub query: QueryDescription,
+}
+
+/// A term of a `PhraseQuery` description.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PhraseTerm {
+    /// Offset of the term in the phrase.
+    pub offset: usize,
+    /// Value of the term.
+    pub value: TermValue,
+}
+
+/// A term of a `TermSetQuery` description.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldTerm {
+    /// Name of the field of the term.
+    pub field: String,
+    /// Value of the term.
+    pub value: TermValue,
+}
+
+/// Value of a term, interpreted according to the type of its field.
+///
+/// Text, date, facet and bytes values are all strings, respectively the text
+/// of the term, a RFC 3339 date, the path of the facet and the base64 encoded bytes.
+///
+/// Positive integers are always described as `U64`, whatever the type of their field,
+/// as they are deserialized as such.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TermValue {
+    /// Value of an `u64` term, or of an `i64` or `f64` term.
+    U64(u64),
+    /// Value of an `i64` term, or of a `f64` term.
+    I64(i64),
+    /// Value of a `f64` term.
+    F64(f64),
+    /// Value of a `bool` term.
+    Bool(bool),
+    /// Value of a text, date, facet or bytes term.
+    Str(String),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Occur", rename_all = "snake_case")]
+enum OccurDef {
+    Should,
+    Must,
+    MustNot,
+    Filter,
+}
+
+fn default_index_record_option() -> IndexRecordOption {
+    IndexRecordOption::WithFreqs
+}
+
+fn default_fast_field_fallback() -> bool {
+    true
+}
+
+fn map_bound<TFrom, TTo>(
+    bound: Bound<TFrom>,
+    transform: impl Fn(TFrom) -> crate::Result<TTo>,
+) -> crate::Result<Bound<TTo>> {
+    Ok(match bound {
+        Bound::Included(value) => Bound::Included(transform(value)?),
+        Bound::Excluded(value) => Bound::Excluded(transform(value)?),
+        Bound::Unbounded => Bound::Unbounded,
+    })
+}
+
+fn field_name(schema: &Schema, field: Field) -> String {
+    schema.get_field_name(field).to_string()
+}
+
+fn term_value(schema: &Schema, term: &Term) -> crate::Result<TermValue> {
+    let field_entry = schema.get_field_entry(term.field());
+    let invalid_term = || {
+        TantivyError::InvalidArgument(format!(
+            "Invalid term for the field {:?}: {:?}",
+            field_entry.name(),
+            term
+        ))
+    };
+    let value = match field_entry.field_type() {
+        FieldType::Str(_) => {
+            let text = std::str::from_utf8(term.value_bytes()).map_err(|_| invalid_term())?;
+            TermValue::Str(text.to_string())
+        }
+        FieldType::U64(_) => TermValue::U64(term.get_u64()),
+        FieldType::I64(_) => {
+            let val = term.get_i64();
+            u64::try_from(val)
+                .map(TermValue::U64)
+                .unwrap_or(TermValue::I64(val))
+        }
+        FieldType::F64(_) => TermValue::F64(term.get_f64()),
+        FieldType::Bool(_) => TermValue::Bool(term.get_bool()),
+        FieldType::Date(_) => {
+            let date = Utc
+                .timestamp_opt(term.get_i64(), 0)
+                .single()
+                .ok_or_else(invalid_term)?;
+            TermValue::Str(date.to_rfc3339_opts(SecondsFormat::Secs, true))
+        }
+        FieldType::HierarchicalFacet(_) => {
+            let facet =
+                Facet::from_encoded(term.value_bytes().to_vec()).map_err(|_| invalid_term())?;
+            TermValue::Str(facet.to_path_string())
+        }
+        FieldType::Bytes(_) => TermValue::Str(base64::encode(term.value_bytes())),
+        FieldType::IpAddr(_) => TermValue::Str(term.get_ip_addr().to_string()),
+        FieldType::JsonObject(_) => {
+            return Err(TantivyError::InvalidArgument(format!(
+                "The terms of the json object field {:?} cannot be described",
+                field_entry.name()
+            )))
+        }
+    };
+    Ok(value)
+}
+
+fn resolve_field(schema: &Schema, field_name: &str) -> crate::Result<Field> {
+    schema.get_field(field_name).ok_or_else(|| {
+        TantivyError::SchemaError(format!(
+            "Field {:?} does not exist in the schema",
+            field_name
+        ))
+    })
+}
+
+fn term_from_value(schema: &Schema, field: Field, value: &TermValue) -> crate::Result<Term> {
+    let field_entry = schema.get_field_entry(field);
+    let field_type = field_entry.field_type();
+    let type_mismatch = || {
+        TantivyError::SchemaError(format!(
+            "Value {:?} does not match the type {:?} of the field {:?}",
+            value,
+            field_type.value_type(),
+            field_entry.name()
+        ))
+    };
+    let term = match (field_type, value) {
+        (FieldType::Str(_), TermValue::Str(text)) => Term::from_field_text(field, text),
+        (FieldType::U64(_), TermValue::U64(val)) => Term::from_field_u64(field, *val),
+        (FieldType::I64(_), TermValue::I64(val)) => Term::from_field_i64(field, *val),
+        (FieldType::I64(_), TermValue::U64(val)) => {
+            let val = i64::try_from(*val).map_err(|_| type_mismatch())?;
+            Term::from_field_i64(field, val)
+        }
+        (FieldType::F64(_), TermValue::F64(val)) => Term::from_field_f64(field, *val),
+        (FieldType::F64(_), TermValue::I64(val)) => Term::from_field_f64(field, *val as f64),
+        (FieldType::F64(_), TermValue::U64(val)) => Term::from_field_f64(field, *val as f64),
+        (FieldType::Bool(_), TermValue::Bool(val)) => Term::from_field_bool(field, *val),
+        (FieldType::Date(_), TermValue::Str(date_str)) => {
+            let date = chrono::DateTime::parse_from_rfc3339(date_str)
+                .map_err(|_| type_mismatch())?
+                .with_timezone(&Utc);
+            Term::from_field_date(field, &date)
+        }
+        (FieldType::HierarchicalFacet(_), TermValue::Str(path)) => {
+            let facet = Facet::from_text(path).map_err(|_| type_mismatch())?;
+            Term::from_facet(field, &facet)
+        }
+        (FieldType::Bytes(_), TermValue::Str(encoded)) => {
+            let bytes = base64::decode(encoded).map_err(|_| type_mismatch())?;
+            Term::from_field_bytes(field, &bytes)
+        }
+        (FieldType::IpAddr(_), TermValue::Str(ip_addr)) => {
+            let ip_addr = parse_ip_addr(ip_addr).map_err(|_| type_mismatch())?;
+            Term::from_field_ip_addr(field, ip_addr)
+        }
+        _ => return Err(type_mismatch()),
+    };
+    Ok(term)
+}
+
+impl QueryDescription {
+    /// Describes the given query.
+    ///
+    /// Returns an error if the query, or one of its subqueries,
+    /// is not supported.
+    pub fn from_query(query: &dyn Query, schema: &Schema) -> crate::Result<QueryDescription> {
+        if query.is::<AllQuery>() {
+            return Ok(QueryDescription::All);
+        }
+        if let Some(term_query) = query.downcast_ref::<TermQuery>() {
+            let term = term_query.term();
+            return Ok(QueryDescription::Term {
+                field: field_name(schema, term.field()),
+                value: term_value(schema, term)?,
+                index_record_option: term_query.index_record_option(),
+            });
+        }
+        if let Some(boolean_query) = query.downcast_ref::<BooleanQuery>() {
+            let clauses = boolean_query
+                .clauses()
+                .iter()
+                .map(|(occur, subquery)| {
+                    Ok(BooleanClause {
+                        occur: *occur,
+                        query: QueryDescription::from_query(subquery.as_ref(), schema)?,
+                    })
+                })
+                .collect::<crate::Result<Vec<_>>>()?;
+            return Ok(QueryDescription::Boolean {
+                clauses,
+                minimum_should_match: boolean_query.minimum_number_should_match(),
+            });
+        }
+        if let Some(phrase_query) = query.downcast_ref::<PhraseQuery>() {
+            let terms = phrase_query
+                .phrase_terms_with_offsets()
+                .iter()
+                .map(|(offset, term)| {
+                    Ok(PhraseTerm {
+                        offset: *offset,
+                        value: term_value(schema, term)?,
+                    })
+                })
+                .collect::<crate::Result<Vec<_>>>()?;
+            return Ok(QueryDescription::Phrase {
+                field: field_name(schema, phrase_query.field()),
+                terms,
+                slop: phrase_query.slop(),
+            });
+        }
+        if let Some(range_query) = query.downcast_ref::<RangeQuery>() {
+            let to_value = |term: Term| term_value(schema, &term);
+            return Ok(QueryDescription::Range {
+                field: field_name(schema, range_query.field()),
+                lower: map_bound(range_query.left_bound(), to_value)?,
+                upper: map_bound(range_query.right_bound(), to_value)?,
+                fast_field_fallback: range_query.fast_field_fallback(),
+            });
+        }
+        if let Some(fuzzy_query) = query.downcast_ref::<FuzzyTermQuery>() {
+            let term = fuzzy_query.term();
+            let value = match term_value(schema, term)? {
+                TermValue::Str(text) => text,
+                _ => {
+                    return Err(TantivyError::InvalidArgument(format!(
+                        "Fuzzy term is not a text term: {:?}",
+                        term
+                    )))
+                }
+            };
+            return Ok(QueryDescription::Fuzzy {
+                field: field_name(schema, term.field()),
+                value,
+                distance: fuzzy_query.distance(),
+                transposition_cost_one: fuzzy_query.transposition_cost_one(),
+                prefix: fuzzy_query.is_prefix(),
+                prefix_length: fuzzy_query.prefix_length(),
+            });
+        }
+        if let Some(regex_query) = query.downcast_ref::<RegexQuery>() {
+            let pattern = regex_query.pattern().ok_or_else(|| {
+                TantivyError::InvalidArgument(
+                    "A RegexQuery built from a Regex, rather than from a pattern, \
+                     cannot be described"
+                        .to_string(),
+                )
+            })?;
+            return Ok(QueryDescription::Regex {
+                field: field_name(schema, regex_query.field()),
+                pattern: pattern.to_string(),
+            });
+        }
+        if let Some(boost_query) = query.downcast_ref::<BoostQuery>() {
+            return Ok(QueryDescription::Boost {
+                query: Box::new(QueryDescription::from_query(boost_query.query(), schema)?),
+                boost: boost_query.boost(),
+            });
+        }
+        if let Some(const_score_query) = query.downcast_ref::<ConstScoreQuery>() {
+            return Ok(QueryDescription::ConstScore {
+                query: Box::new(QueryDescription::from_query(
+                    const_score_query.query(),
+                    schema,
+                )?),
+                score: const_score_query.score(),
+            });
+        }
+        if let Some(disjunction_max_query) = query.downcast_ref::<DisjunctionMaxQuery>() {
+            let disjuncts = disjunction_max_query
+                .disjuncts()
+                .iter()
+                .map(|disjunct| QueryDescription::from_query(disjunct.as_ref(), schema))
+                .collect::<crate::Result<Vec<_>>>()?;
+            return Ok(QueryDescription::DisjunctionMax {
+                disjuncts,
+                tie_breaker: disjunction_max_query.tie_breaker(),
+            });
+        }
+        if let Some(exists_query) = query.downcast_ref::<ExistsQuery>() {
+            return Ok(QueryDescription::Exists {
+                field: field_name(schema, exists_query.field()),
+            });
+        }
+        if let Some(term_set_query) = query.downcast_ref::<TermSetQuery>() {
+            let terms = term_set_query
+                .terms()
+                .iter()
+                .map(|term| {
+                    Ok(FieldTerm {
+                        field: field_name(schema, term.field()),
+                        value: term_value(schema, term)?,
+                    })
+                })
+                .collect::<crate::Result<Vec<_>>>()?;
+            return Ok(QueryDescription::TermSet { terms });
+        }
+        if let Some(wildcard_query) = query.downcast_ref::<WildcardQuery>() {
+            return Ok(QueryDescription::Wildcard {
+                field: field_name(schema, wildcard_query.field()),
+                pattern: wildcard_query.pattern().to_string(),
+            });
+        }
+        Err(TantivyError::InvalidArgument(format!(
+            "Query cannot be described: {:?}",
+            query
+        )))
+    }
+
+    /// Builds the described query.
+    ///
+    /// Returns an error if a field does not exist in the schema,
+    /// or if a value does not match the type of its field.
+    pub fn to_query(&self, schema: &Schema) -> crate::Result<Box<dyn Query>> {
+        let query: Box<dyn Query> = match self {
+            QueryDescription::All => Box::new(AllQuery),
+            QueryDescription::Term {
+                field,
+                value,
+                index_record_option,
+            } => {
+                let field = resolve_field(schema, field)?;
+                let term = term_from_value(schema, field, value)?;
+                Box::new(TermQuery::new(term, *index_record_option))
+            }
+            QueryDescription::Boolean {
+                clauses,
+                minimum_should_match,
+            } => {
+                let subqueries = clauses
+                    .iter()
+                    .map(|clause| Ok((clause.occur, clause.query.to_query(schema)?)))
+                    .collect::<crate::Result<Vec<_>>>()?;
+                Box::new(
+                    BooleanQuery::new(subqueries)
+                        .with_minimum_required_clauses(*minimum_should_match),
+                )
+            }
+            QueryDescription::Phrase { field, terms, slop } => {
+                if terms.len() < 2 {
+                    return Err(TantivyError::InvalidArgument(
+                        "A phrase query must have at least two terms".to_string(),
+                    ));
+                }
+                let field = resolve_field(schema, field)?;
+                let terms = terms
+                    .iter()
+                    .map(|phrase_term| {
+                        let term = term_from_value(schema, field, &phrase_term.value)?;
+                        Ok((phrase_term.offset, term))
+                    })
+                    .collect::<crate::Result<Vec<_>>>()?;
+                Box::new(PhraseQuery::new_with_offset_and_slop(terms, *slop))
+            }
+            QueryDescription::Range {
+                field,
+                lower,
+                upper,
+                fast_field_fallback,
+            } => {
+                let field = resolve_field(schema, field)?;
+                let to_term = |value: &TermValue| term_from_value(schema, field, value);
+                let value_type = schema.get_field_entry(field).field_type().value_type();
+                let range_query = RangeQuery::new_term_bounds(
+                    field,
+                    value_type,
+                    &map_bound(lower.as_ref(), to_term)?,
+                    &map_bound(upper.as_ref(), to_term)?,
+                );
+                Box::new(range_query.with_fast_field_fallback(*fast_field_fallback))
+            }
+            QueryDescription::Fuzzy {
+                field,
+                value,
+                distance,
+                transposition_cost_one,
+                prefix,
+                prefix_length,
+            } => {
+                let field = resolve_field(schema, field)?;
+                let term = term_from_value(schema, field, &TermValue::Str(value.clone()))?;
+                if *prefix {
+                    Box::new(FuzzyTermQuery::new_prefix(
+                        term,
+                        *distance,
+                        *transposition_cost_one,
+                    ))
+                } else {
+                    Box::new(FuzzyTermQuery::new_with_params(
+                        term,
+                        *distance,
+                        *transposition_cost_one,
+                        *prefix_length,
+                    ))
+                }
+            }
+            QueryDescription::Regex { field, pattern } => {
+                let field = resolve_field(schema, field)?;
+                Box::new(RegexQuery::from_pattern(pattern, field)?)
+            }
+            QueryDescription::Boost { query, boost } => {
+                Box::new(BoostQuery::new(query.to_query(schema)?, *boost))
+            }
+            QueryDescription::ConstScore { query, score } => {
+                Box::new(ConstScoreQuery::new(query.to_query(schema)?, *score))
+            }
+            QueryDescription::DisjunctionMax {
+                disjuncts,
+                tie_breaker,
+            } => {
+                let disjuncts = disjuncts
+                    .iter()
+                    .map(|disjunct| disjunct.to_query(schema))
+                    .collect::<crate::Result<Vec<_>>>()?;
+                Box::new(DisjunctionMaxQuery::new(disjuncts, *tie_breaker))
+            }
+            QueryDescription::Exists { field } => {
+                Box::new(ExistsQuery::new(resolve_field(schema, field)?))
+            }
+            QueryDescription::TermSet { terms } => {
+                let terms = terms
+                    .iter()
+                    .map(|field_term| {
+                        let field = resolve_field(schema, &field_term.field)?;
+                        term_from_value(schema, field, &field_term.value)
+                    })
+                    .collect::<crate::Result<Vec<_>>>()?;
+                Box::new(TermSetQuery::new(terms))
+            }
+            QueryDescription::Wildcard { field, pattern } => {
+                Box::new(WildcardQuery::new(resolve_field(schema, field)?, pattern))
+            }
+        };
+        Ok(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QueryDescription, TermValue};
+    use crate::collector::TopDocs;
+    use crate::query::{
+        BooleanQuery, ConstScoreQuery, DisjunctionMaxQuery, EmptyQuery, ExistsQuery, Occur, Query,
+        QueryParser, RangeQuery, RegexQuery, TermQuery, TermSetQuery, WildcardQuery,
+    };
+    use crate::schema::{Facet, IndexRecordOption, Schema, FAST, INDEXED, STORED, STRING, TEXT};
+    use crate::{DocAddress, Index, Score, Term};
+    use chrono::{TimeZone, Utc};
+    use std::ops::Bound;
+
+    #[test]
+    fn test_query_description_round_trip() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let tag = schema_builder.add_text_field("tag", STRING);
+        let count = schema_builder.add_u64_field("count", INDEXED | FAST);
+        let delta = schema_builder.add_i64_field("delta", INDEXED);
+        let ratio = schema_builder.add_f64_field("ratio", INDEXED);
+        let date = schema_builder.add_date_field("date", INDEXED);
+        let facet = schema_builder.add_facet_field("facet", INDEXED);
+        let bytes = schema_builder.add_bytes_field("bytes", INDEXED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_for_tests()?;
+        let texts = [
+            ("hello world", "the quick brown fox"),
+            ("hello tantivy", "jumps over the lazy dog"),
+            ("world of search", "hello quick dog"),
+            ("helo wrld", "brown search engine"),
+        ];
+        for (i, (title_text, body_text)) in texts.iter().enumerate() {
+            let i = i as u64;
+            index_writer.add_document(doc!(
+                title => *title_text,
+                body => *body_text,
+                tag => format!("tag{}", i % 2),
+                count => i,
+                delta => i as i64 - 2,
+                ratio => i as f64 / 2.0,
+                date => Utc.timestamp(1_600_000_000 + i as i64 * 86_400, 0),
+                facet => Facet::from(&format!("/cat/{}", i % 2)),
+                bytes => vec![i as u8, 1u8],
+            ));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let search = |query: &dyn Query| -> crate::Result<Vec<(Score, DocAddress)>> {
+            searcher.search(query, &TopDocs::with_limit(10))
+        };
+
+        let query_parser = QueryParser::for_index(&index, vec![title, body]);
+        let mut queries: Vec<Box<dyn Query>> = [
+            "hello",
+            "+title:hello -body:dog",
+            "\"quick dog\" OR \"brown fox\"~1",
+            "title:hello^2 body:search",
+            "hello~1 tag:tag1",
+            "count:[1 TO 3} delta:<0",
+            "ratio:>=0.5 AND date:[2020-09-14T00:00:00Z TO *]",
+            "facet:/cat/1",
+            "*",
+        ]
+        .iter()
+        .map(|query| query_parser.parse_query(query).unwrap())
+        .collect();
+        queries.push(Box::new(RegexQuery::from_pattern("wor.*", title)?));
+        queries.push(Box::new(
+            BooleanQuery::new(vec![
+                (
+                    Occur::Should,
+                    Box::new(TermQuery::new(
+                        Term::from_field_text(body, "brown"),
+                        IndexRecordOption::WithFreqs,
+                    )) as Box<dyn Query>,
+                ),
+                (
+                    Occur::Should,
+                    Box::new(TermQuery::new(
+                        Term::from_field_bytes(bytes, &[3u8, 1u8]),
+                        IndexRecordOption::Basic,
+                    )),
+                ),
+                (
+                    Occur::Filter,
+                    Box::new(
+                        RangeQuery::new_u64_bounds(count, Bound::Included(1), Bound::Unbounded)
+                            .with_fast_field_fallback(false),
+                    ),
+                ),
+            ])
+            .with_minimum_required_clauses(2),
+        ));
+        let term_query = |field, text| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(field, text),
+                IndexRecordOption::WithFreqs,
+            ))
+        };
+        queries.push(Box::new(ConstScoreQuery::new(
+            term_query(title, "hello"),
+            2.5,
+        )));
+        queries.push(Box::new(DisjunctionMaxQuery::new(
+            vec![term_query(title, "hello"), term_query(body, "dog")],
+            0.3,
+        )));
+        queries.push(Box::new(ExistsQuery::new(count)));
+        queries.push(Box::new(TermSetQuery::new(vec![
+            Term::from_field_text(tag, "tag1"),
+            Term::from_field_u64(count, 0),
+            Term::from_field_i64(delta, 0),
+        ])));
+        queries.push(Box::new(WildcardQuery::new(title, "hel*o")));
+
+        for query in &queries {
+            let description = QueryDescription::from_query(query.as_ref(), &schema)?;
+            let json = serde_json::to_string(&description).unwrap();
+            let deserialized: QueryDescription = serde_json::from_str(&json).unwrap();
+            assert_eq!(deserialized, description, "{}", json);
+            let deserialized_query = deserialized.to_query(&schema)?;
+            let expected_hits = search(query.as_ref())?;
+            assert!(!expected_hits.is_empty(), "{}", json);
+            assert_eq!(
+                search(deserialized_query.as_ref())?,
+                expected_hits,
+                "{}",
+                json
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_description_json() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let count = schema_builder.add_u64_field("count", INDEXED);
+        let schema = schema_builder.build();
+        let query = BooleanQuery::new(vec![
+            (
+                Occur::Must,
+                Box::new(TermQuery::new(
+                    Term::from_field_text(title, "hello"),
+                    IndexRecordOption::Basic,
+                )) as Box<dyn Query>,
+            ),
+            (Occur::MustNot, Box::new(RangeQuery::new_u64(count, 3..5))),
+        ]);
+        let description = QueryDescription::from_query(&query, &schema)?;
+        assert_eq!(
+            serde_json::to_value(&description).unwrap(),
+            serde_json::json!({
+                "type": "boolean",
+                "clauses": [
+                    {
+                        "occur": "must",
+                        "query": {
+                            "type": "term",
+                            "field": "title",
+                            "value": "hello",
+                            "index_record_option": "basic"
+                        }
+                    },
+                    {
+                        "occur": "must_not",
+                        "query": {
+                            "type": "range",
+                            "field": "count",
+                            "lower": {"Included": 3},
+                            "upper": {"Excluded": 5},
+                            "fast_field_fallback": true
+                        }
+                    }
+                ],
+                "minimum_should_match": 0
+            })
+        );
+        // Optional attributes can be omitted.
+        let description: QueryDescription =
+            serde_json::from_str(r#"{"type": "term", "field": "count", "value": 3}"#).unwrap();
+        assert_eq!(
+            description,
+            QueryDescription::Term {
+                field: "count".to_string(),
+                value: TermValue::U64(3),
+                index_record_option: IndexRecordOption::WithFreqs,
+            }
+        );
+        assert!(description.to_query(&schema).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_description_errors() {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        schema_builder.add_u64_field("count", INDEXED);
+        let schema = schema_builder.build();
+        let to_query = |json: &str| -> String {
+            let description: QueryDescription = serde_json::from_str(json).unwrap();
+            format!("{:?}", description.to_query(&schema).err().unwrap())
+        };
+        assert_eq!(
+            to_query(r#"{"type": "term", "field": "count", "value": "abc"}"#),
+            "SchemaError(\"Value Str(\\\"abc\\\") does not match the type U64 of the field \\\"count\\\"\")"
+        );
+        assert_eq!(
+            to_query(r#"{"type": "term", "field": "title", "value": -1}"#),
+            "SchemaError(\"Value I64(-1) does not match the type Str of the field \\\"title\\\"\")"
+        );
+        assert_eq!(
+            to_query(r#"{"type": "term", "field": "missing", "value": "a"}"#),
+            "SchemaError(\"Field \\\"missing\\\" does not exist in the schema\")"
+        );
+        assert!(to_query(
+            r#"{"type": "phrase", "field": "title", "terms": [{"offset": 0, "value": "a"}]}"#
+        )
+        .starts_with("InvalidArgument"));
+        assert!(serde_json::from_str::<QueryDescription>(r#"{"type": "unknown"}"#).is_err());
+
+        assert!(QueryDescription::from_query(&EmptyQuery, &schema).is_err());
+        let regex = tantivy_fst::Regex::new("a.*").unwrap();
+        let regex_query = RegexQuery::from_regex(regex, title);
+        assert!(QueryDescription::from_query(&regex_query, &schema).is_err());
+    }
+}
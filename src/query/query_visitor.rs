@@ -0,0 +1,434 @@
+use crate::query::{
+    BooleanQuery, BoostQuery, ConstScoreQuery, DisjunctionMaxQuery, FuzzyTermQuery, PhraseQuery,
+    Query, RangeQuery, RegexQuery, TermQuery, TermSetQuery, WildcardQuery,
+};
+use crate::Term;
+use std::any::{Any, TypeId};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+
+/// Visitor over a query tree, see [`Query::visit`](./trait.Query.html#method.visit).
+///
+/// Every method has a default implementation, so that a visitor only needs to
+/// implement the methods of the queries it is interested in.
+///
+/// - The methods of the leaf queries report the terms of the query to
+///   [`visit_term`](#method.visit_term) by default.
+/// - The methods of the composite queries return whether their subqueries
+///   should be visited, `true` by default.
+/// - The queries without a dedicated method, including custom queries, are
+///   reported to [`visit_other`](#method.visit_other).
+///
+/// ```rust
+/// use tantivy::query::{Query, QueryParser, QueryVisitor, TermQuery};
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::Index;
+///
+/// #[derive(Default)]
+/// struct TermQueryCounter(usize);
+///
+/// impl QueryVisitor for TermQueryCounter {
+///     fn visit_term_query(&mut self, _query: &TermQuery) {
+///         self.0 += 1;
+///     }
+/// }
+///
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let query_parser = QueryParser::for_index(&index, vec![title]);
+/// let query = query_parser.parse_query("+(diary cow) -\"dairy cow\"").unwrap();
+///
+/// let mut counter = TermQueryCounter::default();
+/// query.visit(&mut counter);
+/// assert_eq!(counter.0, 2);
+/// ```
+pub trait QueryVisitor {
+    /// Called for each term of the visited leaf queries.
+    ///
+    /// `positions_required` is true if the query needs the positions of the term,
+    /// as a phrase query does.
+    fn visit_term(&mut self, _term: &Term, _positions_required: bool) {}
+
+    /// Visits a `TermQuery`.
+    fn visit_term_query(&mut self, query: &TermQuery) {
+        self.visit_term(query.term(), false);
+    }
+
+    /// Visits a `PhraseQuery`.
+    fn visit_phrase_query(&mut self, query: &PhraseQuery) {
+        for (_, term) in query.phrase_terms_with_offsets() {
+            self.visit_term(term, true);
+        }
+    }
+
+    /// Visits a `TermSetQuery`.
+    fn visit_term_set_query(&mut self, query: &TermSetQuery) {
+        for term in query.terms() {
+            self.visit_term(term, false);
+        }
+    }
+
+    /// Visits a `RangeQuery`.
+    fn visit_range_query(&mut self, _query: &RangeQuery) {}
+
+    /// Visits a `FuzzyTermQuery`.
+    fn visit_fuzzy_query(&mut self, _query: &FuzzyTermQuery) {}
+
+    /// Visits a `RegexQuery`.
+    fn visit_regex_query(&mut self, _query: &RegexQuery) {}
+
+    /// Visits a `WildcardQuery`.
+    fn visit_wildcard_query(&mut self, _query: &WildcardQuery) {}
+
+    /// Visits a `BooleanQuery`, before its clauses.
+    fn visit_boolean_query(&mut self, _query: &BooleanQuery) -> bool {
+        true
+    }
+
+    /// Visits a `BoostQuery`, before its underlying query.
+    fn visit_boost_query(&mut self, _query: &BoostQuery) -> bool {
+        true
+    }
+
+    /// Visits a `ConstScoreQuery`, before its underlying query.
+    fn visit_const_score_query(&mut self, _query: &ConstScoreQuery) -> bool {
+        true
+    }
+
+    /// Visits a `DisjunctionMaxQuery`, before its disjuncts.
+    fn visit_disjunction_max_query(&mut self, _query: &DisjunctionMaxQuery) -> bool {
+        true
+    }
+
+    /// Visits any other query, including the custom queries which do not
+    /// implement [`Query::visit`](./trait.Query.html#method.visit).
+    fn visit_other(&mut self, _query: &dyn Query) {}
+}
+
+/// Rewrites the queries of a query tree, see [`rewrite_query`](./fn.rewrite_query.html).
+///
+/// It is implemented for closures taking a `&dyn Query`
+/// and returning an `Option<Box<dyn Query>>`.
+pub trait QueryRewriter {
+    /// Returns the query replacing `query`, or `None` to keep `query`
+    /// and rewrite its subqueries instead.
+    fn rewrite(&mut self, query: &dyn Query) -> Option<Box<dyn Query>>;
+}
+
+impl<F> QueryRewriter for F
+where
+    F: FnMut(&dyn Query) -> Option<Box<dyn Query>>,
+{
+    fn rewrite(&mut self, query: &dyn Query) -> Option<Box<dyn Query>> {
+        self(query)
+    }
+}
+
+/// Rewrites a query tree, from its root down.
+///
+/// A query replaced by the rewriter is not rewritten any further.
+/// The other queries are kept, but their subqueries are rewritten, see
+/// [`Query::rewrite_subqueries`](./trait.Query.html#method.rewrite_subqueries).
+///
+/// Returns `None` if the query tree was not modified.
+///
+/// ```rust
+/// use tantivy::query::{rewrite_query, Query, QueryParser, TermQuery};
+/// use tantivy::schema::{IndexRecordOption, Schema, TEXT};
+/// use tantivy::{Index, Term};
+///
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let old_title = schema_builder.add_text_field("old_title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let query_parser = QueryParser::for_index(&index, vec![title]);
+/// let query = query_parser.parse_query("old_title:diary AND cow").unwrap();
+///
+/// // Searches the deprecated `old_title` field in `title` instead.
+/// let rewritten_query = rewrite_query(query.as_ref(), &mut |query: &dyn Query| {
+///     let term_query = query.downcast_ref::<TermQuery>()?;
+///     if term_query.term().field() != old_title {
+///         return None;
+///     }
+///     let term = Term::from_field_bytes(title, term_query.term().value_bytes());
+///     let new_query = TermQuery::new(term, IndexRecordOption::WithFreqs);
+///     Some(Box::new(new_query) as Box<dyn Query>)
+/// })
+/// .unwrap();
+/// let expected_query = query_parser.parse_query("title:diary AND cow").unwrap();
+/// assert_eq!(format!("{:?}", rewritten_query), format!("{:?}", expected_query));
+/// ```
+pub fn rewrite_query(
+    query: &dyn Query,
+    rewriter: &mut dyn QueryRewriter,
+) -> Option<Box<dyn Query>> {
+    if let Some(rewritten_query) = rewriter.rewrite(query) {
+        return Some(rewritten_query);
+    }
+    query.rewrite_subqueries(rewriter)
+}
+
+/// Rewrites a list of queries, returning `None` if none of them was rewritten.
+pub(crate) fn rewrite_queries<'a>(
+    queries: impl Iterator<Item = &'a dyn Query>,
+    rewriter: &mut dyn QueryRewriter,
+) -> Option<Vec<Box<dyn Query>>> {
+    let queries: Vec<&dyn Query> = queries.collect();
+    let rewritten_queries: Vec<Option<Box<dyn Query>>> = queries
+        .iter()
+        .map(|query| rewrite_query(*query, rewriter))
+        .collect();
+    if rewritten_queries.iter().all(Option::is_none) {
+        return None;
+    }
+    Some(
+        queries
+            .into_iter()
+            .zip(rewritten_queries)
+            .map(|(query, rewritten_query)| rewritten_query.unwrap_or_else(|| query.box_clone()))
+            .collect(),
+    )
+}
+
+/// Identifies a query by its address and its type.
+type QueryId = (*const (), TypeId);
+
+thread_local! {
+    /// The query whose `query_terms` is being called by `QueryTermsCollector::visit_other`.
+    static OTHER_QUERY: Cell<Option<QueryId>> = const { Cell::new(None) };
+}
+
+/// Collects the terms of a query, see [`Query::query_terms`](./trait.Query.html#method.query_terms).
+///
+/// The queries reported to `visit_other` are asked for their terms through their own
+/// `query_terms`. If they do not override it, the default `query_terms` would visit
+/// them again: the query being asked is remembered to report no terms instead.
+pub(crate) struct QueryTermsCollector<'a> {
+    terms: &'a mut BTreeMap<Term, bool>,
+}
+
+impl<'a> QueryTermsCollector<'a> {
+    /// Collects the terms of `query` by visiting it, which is the default `query_terms`.
+    pub fn collect<Q: Query + ?Sized>(query: &Q, terms: &'a mut BTreeMap<Term, bool>) {
+        let query_id: QueryId = (query as *const Q as *const (), TypeId::of::<Q>());
+        if OTHER_QUERY.with(|other_query| other_query.get()) == Some(query_id) {
+            return;
+        }
+        query.visit(&mut QueryTermsCollector { terms });
+    }
+}
+
+impl<'a> QueryVisitor for QueryTermsCollector<'a> {
+    fn visit_term(&mut self, term: &Term, positions_required: bool) {
+        self.terms.insert(term.clone(), positions_required);
+    }
+
+    fn visit_other(&mut self, query: &dyn Query) {
+        let query_id: QueryId = (
+            query as *const dyn Query as *const (),
+            Any::type_id(query.as_any()),
+        );
+        let previous_query = OTHER_QUERY.with(|other_query| other_query.replace(Some(query_id)));
+        query.query_terms(self.terms);
+        OTHER_QUERY.with(|other_query| other_query.set(previous_query));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rewrite_query, QueryVisitor};
+    use crate::query::{
+        AllQuery, BooleanQuery, BoostQuery, EmptyQuery, Occur, PhraseQuery, Query, QueryParser,
+        TermQuery, Weight,
+    };
+    use crate::schema::{Field, IndexRecordOption, Schema, STRING, TEXT};
+    use crate::{Index, Searcher, SnippetGenerator, Term};
+    use std::collections::BTreeMap;
+
+    fn index() -> (Index, Field, Field, Field) {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let old_title = schema_builder.add_text_field("old_title", TEXT);
+        let tenant = schema_builder.add_text_field("tenant", STRING);
+        (
+            Index::create_in_ram(schema_builder.build()),
+            title,
+            old_title,
+            tenant,
+        )
+    }
+
+    #[test]
+    fn test_query_terms() {
+        let (index, title, _, _) = index();
+        let query_parser = QueryParser::for_index(&index, vec![title]);
+        let query = query_parser
+            .parse_query("+(diary^2 cow) -\"dairy cow\" +title:[a TO c]")
+            .unwrap();
+        let mut terms = BTreeMap::new();
+        query.query_terms(&mut terms);
+        let term = |text: &str| Term::from_field_text(title, text);
+        let expected_terms: BTreeMap<Term, bool> = vec![
+            (term("diary"), false),
+            (term("cow"), true),
+            (term("dairy"), true),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(terms, expected_terms);
+    }
+
+    #[test]
+    fn test_query_terms_of_custom_query() -> crate::Result<()> {
+        /// Matches the documents of a term, and only reports it through `query_terms`.
+        #[derive(Clone, Debug)]
+        struct CustomTermQuery(TermQuery);
+        impl Query for CustomTermQuery {
+            fn weight(
+                &self,
+                searcher: &Searcher,
+                scoring_enabled: bool,
+            ) -> crate::Result<Box<dyn Weight>> {
+                self.0.weight(searcher, scoring_enabled)
+            }
+            fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
+                terms.insert(self.0.term().clone(), false);
+            }
+        }
+        /// Overrides neither `query_terms` nor `visit`.
+        #[derive(Clone, Debug)]
+        struct OpaqueQuery;
+        impl Query for OpaqueQuery {
+            fn weight(
+                &self,
+                searcher: &Searcher,
+                scoring_enabled: bool,
+            ) -> crate::Result<Box<dyn Weight>> {
+                AllQuery.weight(searcher, scoring_enabled)
+            }
+        }
+        let (index, title, _, _) = index();
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "the diary of a dairy cow"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term_query = |text: &str| {
+            TermQuery::new(Term::from_field_text(title, text), IndexRecordOption::Basic)
+        };
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(term_query("diary"))),
+            (Occur::Should, Box::new(CustomTermQuery(term_query("cow")))),
+            (Occur::Should, Box::new(OpaqueQuery)),
+            (Occur::Should, Box::new(AllQuery)),
+        ]);
+        let mut terms = BTreeMap::new();
+        query.query_terms(&mut terms);
+        let expected_terms: BTreeMap<Term, bool> = vec![
+            (Term::from_field_text(title, "diary"), false),
+            (Term::from_field_text(title, "cow"), false),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(terms, expected_terms);
+        let snippet_generator = SnippetGenerator::create(&searcher, &query, title)?;
+        let snippet = snippet_generator.snippet("the diary of a dairy cow");
+        assert_eq!(snippet.to_html(), "the <b>diary</b> of a dairy <b>cow</b>");
+        Ok(())
+    }
+
+    #[test]
+    fn test_visit_other_and_skip_subqueries() {
+        #[derive(Default)]
+        struct Visitor {
+            num_other: usize,
+            num_terms: usize,
+        }
+        impl QueryVisitor for Visitor {
+            fn visit_term(&mut self, _term: &Term, _positions_required: bool) {
+                self.num_terms += 1;
+            }
+            fn visit_boost_query(&mut self, _query: &BoostQuery) -> bool {
+                false
+            }
+            fn visit_other(&mut self, _query: &dyn Query) {
+                self.num_other += 1;
+            }
+        }
+        let (_, title, _, _) = index();
+        let term_query = || -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(title, "diary"),
+                IndexRecordOption::Basic,
+            ))
+        };
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(AllQuery)),
+            (Occur::Should, Box::new(EmptyQuery)),
+            (Occur::Should, term_query()),
+            (Occur::Should, Box::new(BoostQuery::new(term_query(), 2.0))),
+        ]);
+        let mut visitor = Visitor::default();
+        query.visit(&mut visitor);
+        assert_eq!(visitor.num_other, 2);
+        assert_eq!(visitor.num_terms, 1);
+    }
+
+    #[test]
+    fn test_rewrite_deprecated_field() {
+        let (index, title, old_title, _) = index();
+        let query_parser = QueryParser::for_index(&index, vec![title]);
+        let mut rewriter = |query: &dyn Query| -> Option<Box<dyn Query>> {
+            let term_query = query.downcast_ref::<TermQuery>()?;
+            if term_query.term().field() != old_title {
+                return None;
+            }
+            let term = Term::from_field_bytes(title, term_query.term().value_bytes());
+            Some(Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs)))
+        };
+        let query = query_parser
+            .parse_query("(old_title:diary^2 OR cow) AND -\"dairy cow\"")
+            .unwrap();
+        let expected_query = query_parser
+            .parse_query("(title:diary^2 OR cow) AND -\"dairy cow\"")
+            .unwrap();
+        let rewritten_query = rewrite_query(query.as_ref(), &mut rewriter).unwrap();
+        assert_eq!(
+            format!("{:?}", rewritten_query),
+            format!("{:?}", expected_query)
+        );
+        // Nothing to rewrite.
+        assert!(rewrite_query(expected_query.as_ref(), &mut rewriter).is_none());
+    }
+
+    #[test]
+    fn test_rewrite_inject_tenant_filter() {
+        let (index, title, _, tenant) = index();
+        let query_parser = QueryParser::for_index(&index, vec![title]);
+        let tenant_query = TermQuery::new(
+            Term::from_field_text(tenant, "acme"),
+            IndexRecordOption::Basic,
+        );
+        // Restricts the phrase queries to the documents of the tenant.
+        let mut rewriter = |query: &dyn Query| -> Option<Box<dyn Query>> {
+            if !query.is::<PhraseQuery>() {
+                return None;
+            }
+            Some(Box::new(BooleanQuery::new(vec![
+                (Occur::Must, query.box_clone()),
+                (Occur::Filter, Box::new(tenant_query.clone())),
+            ])))
+        };
+        let query = query_parser.parse_query("diary OR \"dairy cow\"").unwrap();
+        let rewritten_query = rewrite_query(query.as_ref(), &mut rewriter).unwrap();
+        let boolean_query = rewritten_query.downcast_ref::<BooleanQuery>().unwrap();
+        let clauses = boolean_query.clauses();
+        assert_eq!(clauses.len(), 2);
+        assert!(clauses[0].1.is::<TermQuery>());
+        let filtered_phrase = clauses[1].1.downcast_ref::<BooleanQuery>().unwrap();
+        assert_eq!(filtered_phrase.clauses()[1].0, Occur::Filter);
+        let mut terms = BTreeMap::new();
+        rewritten_query.query_terms(&mut terms);
+        assert!(terms.contains_key(tenant_query.term()));
+    }
+}
@@ -2,15 +2,21 @@ use crate::core::Searcher;
 use crate::core::SegmentReader;
 use crate::error::TantivyError;
 use crate::query::explanation::does_not_match;
+use crate::query::fast_field_range_query::{
+    fast_field_cardinality, stored_value_scale, FastFieldRangeWeight, U128FastFieldRangeWeight,
+};
+use crate::query::filter_cache::{bound_cache_key, query_cache_key};
 use crate::query::ConstScorer;
-use crate::query::{BitSetDocSet, Explanation};
-use crate::query::{Query, Scorer, Weight};
-use crate::schema::Type;
+use crate::query::{BitSetDocSet, EmptyScorer, Explanation};
+use crate::query::{Query, QueryVisitor, Scorer, Weight};
 use crate::schema::{Field, IndexRecordOption, Term};
+use crate::schema::{FieldType, Type};
 use crate::termdict::{TermDictionary, TermStreamer};
 use crate::{DocId, Score};
 use common::BitSet;
+use std::convert::TryInto;
 use std::io;
+use std::net::Ipv6Addr;
 use std::ops::{Bound, Range};
 
 fn map_bound<TFrom, TTo, Transform: Fn(&TFrom) -> TTo>(
@@ -34,6 +40,13 @@ fn map_bound<TFrom, TTo, Transform: Fn(&TFrom) -> TTo>(
 /// The current implement will iterate over the terms within the range
 /// and append all of the document cross into a `BitSet`.
 ///
+/// For numeric fields that are also fast fields, a segment may instead be
+/// searched by scanning its fast field column, like `FastFieldRangeQuery` does.
+/// This happens when the field is not indexed, or when the range is estimated to
+/// contain too many terms for the term based plan to be efficient.
+/// Ip address fields that are fast but not indexed are searched by scanning their
+/// fast field column.
+///
 /// # Example
 ///
 /// ```rust
@@ -71,6 +84,7 @@ pub struct RangeQuery {
     value_type: Type,
     left_bound: Bound<Vec<u8>>,
     right_bound: Bound<Vec<u8>>,
+    fast_field_fallback: bool,
 }
 
 impl RangeQuery {
@@ -93,6 +107,7 @@ impl RangeQuery {
             value_type,
             left_bound: map_bound(left_bound, &verify_and_unwrap_term),
             right_bound: map_bound(right_bound, &verify_and_unwrap_term),
+            fast_field_fallback: true,
         }
     }
 
@@ -126,6 +141,7 @@ impl RangeQuery {
             value_type: Type::I64,
             left_bound: map_bound(&left_bound, &make_term_val),
             right_bound: map_bound(&right_bound, &make_term_val),
+            fast_field_fallback: true,
         }
     }
 
@@ -159,6 +175,7 @@ impl RangeQuery {
             value_type: Type::F64,
             left_bound: map_bound(&left_bound, &make_term_val),
             right_bound: map_bound(&right_bound, &make_term_val),
+            fast_field_fallback: true,
         }
     }
 
@@ -180,6 +197,7 @@ impl RangeQuery {
             value_type: Type::U64,
             left_bound: map_bound(&left_bound, &make_term_val),
             right_bound: map_bound(&right_bound, &make_term_val),
+            fast_field_fallback: true,
         }
     }
 
@@ -195,6 +213,44 @@ impl RangeQuery {
         )
     }
 
+    /// Create a new `RangeQuery` over an ip address field.
+    ///
+    /// The two `Bound` arguments make it possible to create more complex
+    /// ranges than semi-inclusive range.
+    ///
+    /// If the field is not of the type `IpAddr`, tantivy
+    /// will panic when the `Weight` object is created.
+    pub fn new_ip_addr_bounds(
+        field: Field,
+        left_bound: Bound<Ipv6Addr>,
+        right_bound: Bound<Ipv6Addr>,
+    ) -> RangeQuery {
+        let make_term_val = |val: &Ipv6Addr| {
+            Term::from_field_ip_addr(field, *val)
+                .value_bytes()
+                .to_owned()
+        };
+        RangeQuery {
+            field,
+            value_type: Type::IpAddr,
+            left_bound: map_bound(&left_bound, &make_term_val),
+            right_bound: map_bound(&right_bound, &make_term_val),
+            fast_field_fallback: true,
+        }
+    }
+
+    /// Create a new `RangeQuery` over an ip address field.
+    ///
+    /// If the field is not of the type `IpAddr`, tantivy
+    /// will panic when the `Weight` object is created.
+    pub fn new_ip_addr(field: Field, range: Range<Ipv6Addr>) -> RangeQuery {
+        RangeQuery::new_ip_addr_bounds(
+            field,
+            Bound::Included(range.start),
+            Bound::Excluded(range.end),
+        )
+    }
+
     /// Create a new `RangeQuery` over a `Str` field.
     ///
     /// The two `Bound` arguments make it possible to create more complex
@@ -209,6 +265,7 @@ impl RangeQuery {
             value_type: Type::Str,
             left_bound: map_bound(&left, &make_term_val),
             right_bound: map_bound(&right, &make_term_val),
+            fast_field_fallback: true,
         }
     }
 
@@ -224,11 +281,25 @@ impl RangeQuery {
         )
     }
 
+    /// Enables or disables searching the fast field instead of the inverted index,
+    /// when the field is both indexed and fast.
+    ///
+    /// It is enabled by default.
+    pub fn with_fast_field_fallback(mut self, fast_field_fallback: bool) -> RangeQuery {
+        self.fast_field_fallback = fast_field_fallback;
+        self
+    }
+
     /// Field to search over
     pub fn field(&self) -> Field {
         self.field
     }
 
+    /// Returns true if the fast field may be searched instead of the inverted index.
+    pub fn fast_field_fallback(&self) -> bool {
+        self.fast_field_fallback
+    }
+
     /// Lower bound of range
     pub fn left_bound(&self) -> Bound<Term> {
         map_bound(&self.left_bound, &|bytes| {
@@ -251,7 +322,8 @@ impl Query for RangeQuery {
         _scoring_enabled: bool,
     ) -> crate::Result<Box<dyn Weight>> {
         let schema = searcher.schema();
-        let value_type = schema.get_field_entry(self.field).field_type().value_type();
+        let field_entry = schema.get_field_entry(self.field);
+        let value_type = field_entry.field_type().value_type();
         if value_type != self.value_type {
             let err_msg = format!(
                 "Create a range query of the type {:?}, when the field given was of type {:?}",
@@ -259,18 +331,136 @@ impl Query for RangeQuery {
             );
             return Err(TantivyError::SchemaError(err_msg));
         }
+        if let FieldType::IpAddr(options) = field_entry.field_type() {
+            // ip addresses are only looked up in their fast field column if they are not indexed.
+            if options.is_fast() && !options.is_indexed() {
+                if let (Some(left_bound), Some(right_bound)) =
+                    (u128_bound(&self.left_bound), u128_bound(&self.right_bound))
+                {
+                    return Ok(Box::new(U128FastFieldRangeWeight::new(
+                        self.field,
+                        &left_bound,
+                        &right_bound,
+                    )));
+                }
+            }
+        }
+        let fast_field_weight_opt =
+            fast_field_cardinality(field_entry.field_type()).and_then(|cardinality| {
+                let mut left_bound = u64_bound(&self.left_bound)?;
+                let mut right_bound = u64_bound(&self.right_bound)?;
+                if self.value_type == Type::Date {
+                    left_bound = date_lower_bound_in_micros(left_bound);
+                    right_bound = date_upper_bound_in_micros(right_bound);
+                }
+                Some(FastFieldRangeWeight::new(
+                    self.field,
+                    cardinality,
+                    stored_value_scale(field_entry.field_type()),
+                    &left_bound,
+                    &right_bound,
+                ))
+            });
+        if let Some(fast_field_weight) = fast_field_weight_opt {
+            if !field_entry.is_indexed() {
+                return Ok(Box::new(fast_field_weight));
+            }
+            if self.fast_field_fallback {
+                return Ok(Box::new(RangeWeight {
+                    field: self.field,
+                    left_bound: self.left_bound.clone(),
+                    right_bound: self.right_bound.clone(),
+                    fast_field_weight_opt: Some(fast_field_weight),
+                }));
+            }
+        }
         Ok(Box::new(RangeWeight {
             field: self.field,
             left_bound: self.left_bound.clone(),
             right_bound: self.right_bound.clone(),
+            fast_field_weight_opt: None,
         }))
     }
+
+    fn visit(&self, visitor: &mut dyn QueryVisitor) {
+        visitor.visit_range_query(self);
+    }
+
+    fn cache_key(&self) -> Option<Vec<u8>> {
+        Some(query_cache_key(
+            "range",
+            &(
+                self.field,
+                (
+                    bound_cache_key(&self.left_bound),
+                    bound_cache_key(&self.right_bound),
+                ),
+            ),
+        ))
+    }
+}
+
+/// Decodes the bound of a numeric range, expressed as the bytes of a term.
+fn u64_bound(bound: &Bound<Vec<u8>>) -> Option<Bound<u64>> {
+    let decode = |bytes: &Vec<u8>| -> Option<u64> {
+        let bytes: [u8; 8] = bytes.as_slice().try_into().ok()?;
+        Some(u64::from_be_bytes(bytes))
+    };
+    Some(match bound {
+        Bound::Included(bytes) => Bound::Included(decode(bytes)?),
+        Bound::Excluded(bytes) => Bound::Excluded(decode(bytes)?),
+        Bound::Unbounded => Bound::Unbounded,
+    })
+}
+
+/// Indexed dates are recorded at the second: the lower bound of a date range on a
+/// timestamp in seconds is converted to the equivalent bound in microseconds, so that
+/// a fast field column with a finer precision matches the same documents.
+fn date_lower_bound_in_micros(bound: Bound<u64>) -> Bound<u64> {
+    let micros = |secs: i64| common::i64_to_u64(secs.saturating_mul(1_000_000));
+    match bound {
+        Bound::Included(secs) => Bound::Included(micros(common::u64_to_i64(secs))),
+        Bound::Excluded(secs) => {
+            Bound::Included(micros(common::u64_to_i64(secs).saturating_add(1)))
+        }
+        Bound::Unbounded => Bound::Unbounded,
+    }
 }
 
+/// Same as `date_lower_bound_in_micros`, for the upper bound of a date range.
+fn date_upper_bound_in_micros(bound: Bound<u64>) -> Bound<u64> {
+    let micros = |secs: i64| common::i64_to_u64(secs.saturating_mul(1_000_000));
+    match bound {
+        Bound::Included(secs) => {
+            Bound::Excluded(micros(common::u64_to_i64(secs).saturating_add(1)))
+        }
+        Bound::Excluded(secs) => Bound::Excluded(micros(common::u64_to_i64(secs))),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// Decodes the bound of an ip address range, expressed as the bytes of a term.
+fn u128_bound(bound: &Bound<Vec<u8>>) -> Option<Bound<u128>> {
+    let decode = |bytes: &Vec<u8>| -> Option<u128> {
+        let bytes: [u8; 16] = bytes.as_slice().try_into().ok()?;
+        Some(u128::from_be_bytes(bytes))
+    };
+    Some(match bound {
+        Bound::Included(bytes) => Bound::Included(decode(bytes)?),
+        Bound::Excluded(bytes) => Bound::Excluded(decode(bytes)?),
+        Bound::Unbounded => Bound::Unbounded,
+    })
+}
+
+/// Visiting one term of the range is roughly as expensive as scanning that many values
+/// of a fast field column.
+const TERM_VISIT_COST_IN_SCANNED_VALUES: f64 = 64.0;
+
 pub struct RangeWeight {
     field: Field,
     left_bound: Bound<Vec<u8>>,
     right_bound: Bound<Vec<u8>>,
+    fast_field_weight_opt: Option<FastFieldRangeWeight>,
 }
 
 impl RangeWeight {
@@ -294,10 +484,26 @@ impl RangeWeight {
 impl Weight for RangeWeight {
     fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
         let max_doc = reader.max_doc();
-        let mut doc_bitset = BitSet::with_max_value(max_doc);
-
         let inverted_index = reader.inverted_index(self.field)?;
         let term_dict = inverted_index.terms();
+
+        if let Some(fast_field_weight) = self.fast_field_weight_opt.as_ref() {
+            let range = match fast_field_weight.range() {
+                Some(range) => range,
+                None => return Ok(Box::new(EmptyScorer)),
+            };
+            // The values are assumed to be evenly spread between the min and the max value
+            // of the fast field column, to estimate the number of terms within the range.
+            let column = fast_field_weight.column(reader)?;
+            let estimated_num_terms = term_dict.num_terms() as f64 * column.coverage(range);
+            if estimated_num_terms * TERM_VISIT_COST_IN_SCANNED_VALUES
+                > column.num_vals(max_doc) as f64
+            {
+                return Ok(fast_field_weight.scorer_for_column(&column, reader, boost));
+            }
+        }
+
+        let mut doc_bitset = BitSet::with_max_value(max_doc);
         let mut term_range = self.term_range(term_dict)?;
         while term_range.advance() {
             let term_info = term_range.value();
@@ -333,8 +539,9 @@ mod tests {
     use super::RangeQuery;
     use crate::collector::{Count, TopDocs};
     use crate::query::QueryParser;
-    use crate::schema::{Document, Field, Schema, INDEXED, TEXT};
+    use crate::schema::{Document, Field, Schema, Term, FAST, INDEXED, TEXT};
     use crate::Index;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
     use std::ops::Bound;
 
     #[test]
@@ -481,6 +688,68 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_range_ip_addr() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let ip_indexed = schema_builder.add_ip_addr_field("ip_indexed", INDEXED);
+        let ip_fast = schema_builder.add_ip_addr_field("ip_fast", FAST);
+        let id = schema_builder.add_u64_field("id", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0u8..100 {
+            let ip_addr = IpAddr::V4(Ipv4Addr::new(10, 0, i / 10, i % 10));
+            let mut doc = Document::new();
+            doc.add_ip_addr(ip_indexed, ip_addr);
+            doc.add_ip_addr(ip_fast, ip_addr);
+            doc.add_u64(id, u64::from(i));
+            index_writer.add_document(doc);
+            if i % 30 == 0 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_u64(id, 3));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let v4 = |a: u8, b: u8| Ipv4Addr::new(10, 0, a, b).to_ipv6_mapped();
+        for &field in &[ip_indexed, ip_fast] {
+            let count = |range_query: RangeQuery| searcher.search(&range_query, &Count).unwrap();
+            assert_eq!(count(RangeQuery::new_ip_addr(field, v4(0, 0)..v4(1, 0))), 9);
+            assert_eq!(
+                count(RangeQuery::new_ip_addr_bounds(
+                    field,
+                    Bound::Excluded(v4(0, 5)),
+                    Bound::Included(v4(1, 5))
+                )),
+                10
+            );
+            assert_eq!(
+                count(RangeQuery::new_ip_addr_bounds(
+                    field,
+                    Bound::Included(v4(9, 0)),
+                    Bound::Unbounded
+                )),
+                10
+            );
+            assert_eq!(
+                count(RangeQuery::new_ip_addr_bounds(
+                    field,
+                    Bound::Included(Ipv6Addr::UNSPECIFIED),
+                    Bound::Unbounded
+                )),
+                99
+            );
+            assert_eq!(
+                count(RangeQuery::new_ip_addr(
+                    field,
+                    "2001:db8::".parse().unwrap().."2001:db9::".parse().unwrap()
+                )),
+                0
+            );
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_bug_reproduce_range_query() -> crate::Result<()> {
         let mut schema_builder = Schema::builder();
@@ -1,5 +1,5 @@
 use crate::error::TantivyError;
-use crate::query::{AutomatonWeight, Query, Weight};
+use crate::query::{AutomatonWeight, Query, QueryVisitor, Weight};
 use crate::schema::Field;
 use crate::Searcher;
 use std::clone::Clone;
@@ -10,8 +10,8 @@ use tantivy_fst::Regex;
 /// containing a specific term that matches
 /// a regex pattern.
 ///
-/// Wildcard queries (e.g. ho*se) can be achieved
-/// by converting them to their regex counterparts.
+/// Wildcard queries (e.g. ho*se) are better expressed
+/// with a [`WildcardQuery`](./struct.WildcardQuery.html).
 ///
 /// ```rust
 /// use tantivy::collector::Count;
@@ -55,6 +55,7 @@ use tantivy_fst::Regex;
 #[derive(Debug, Clone)]
 pub struct RegexQuery {
     regex: Arc<Regex>,
+    pattern: Option<String>,
     field: Field,
 }
 
@@ -63,17 +64,32 @@ impl RegexQuery {
     pub fn from_pattern(regex_pattern: &str, field: Field) -> crate::Result<Self> {
         let regex = Regex::new(regex_pattern)
             .map_err(|_| TantivyError::InvalidArgument(regex_pattern.to_string()))?;
-        Ok(RegexQuery::from_regex(regex, field))
+        Ok(RegexQuery {
+            regex: Arc::new(regex),
+            pattern: Some(regex_pattern.to_string()),
+            field,
+        })
     }
 
     /// Creates a new RegexQuery from a fully built Regex
     pub fn from_regex<T: Into<Arc<Regex>>>(regex: T, field: Field) -> Self {
         RegexQuery {
             regex: regex.into(),
+            pattern: None,
             field,
         }
     }
 
+    /// Field to search over
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    /// The pattern of the regex, if the query was created from a pattern.
+    pub fn pattern(&self) -> Option<&str> {
+        self.pattern.as_deref()
+    }
+
     fn specialized_weight(&self) -> AutomatonWeight<Regex> {
         AutomatonWeight::new(self.field, self.regex.clone())
     }
@@ -87,6 +103,10 @@ impl Query for RegexQuery {
     ) -> crate::Result<Box<dyn Weight>> {
         Ok(Box::new(self.specialized_weight()))
     }
+
+    fn visit(&self, visitor: &mut dyn QueryVisitor) {
+        visitor.visit_regex_query(self);
+    }
 }
 
 #[cfg(test)]
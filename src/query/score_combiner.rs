@@ -55,6 +55,43 @@ impl ScoreCombiner for SumCombiner {
     }
 }
 
+/// Keeps the maximum score of different scorers, plus `tie_breaker`
+/// times the sum of the other scores, see `DisjunctionMaxQuery`.
+#[derive(Default, Clone, Copy)]
+pub struct DisjunctionMaxCombiner {
+    max_score: Score,
+    sum_score: Score,
+    tie_breaker: Score,
+}
+
+impl DisjunctionMaxCombiner {
+    /// Creates a `DisjunctionMaxCombiner` with the given tie breaker.
+    pub fn with_tie_breaker(tie_breaker: Score) -> DisjunctionMaxCombiner {
+        DisjunctionMaxCombiner {
+            max_score: 0.0,
+            sum_score: 0.0,
+            tie_breaker,
+        }
+    }
+}
+
+impl ScoreCombiner for DisjunctionMaxCombiner {
+    fn update<TScorer: Scorer>(&mut self, scorer: &mut TScorer) {
+        let score = scorer.score();
+        self.max_score = self.max_score.max(score);
+        self.sum_score += score;
+    }
+
+    fn clear(&mut self) {
+        self.max_score = 0.0;
+        self.sum_score = 0.0;
+    }
+
+    fn score(&self) -> Score {
+        self.max_score + self.tie_breaker * (self.sum_score - self.max_score)
+    }
+}
+
 /// Sums the score of different scorers and keeps the count
 /// of scorers which matched.
 #[derive(Default, Clone, Copy)]
@@ -0,0 +1,750 @@
+use crate::fieldnorm::FieldNormReader;
+use crate::query::{Bm25Similarity, Explanation};
+use crate::Score;
+use crate::Searcher;
+use crate::Term;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// Maximum term frequency used to compute the upper bound of the score
+/// returned by a `Similarity`.
+pub(crate) const MAX_TERM_FREQ: u32 = 2_013_265_944;
+
+/// A `Similarity` defines how the documents matching a term,
+/// or a phrase, are scored.
+///
+/// The score of a document is computed from
+/// - the weight of the query term, computed once per query (e.g. its idf).
+/// - the normalization factor of the document, computed from the length of its field.
+/// - the number of occurrences of the term in the document.
+///
+/// By default, all fields are scored using [`Bm25Similarity`](./struct.Bm25Similarity.html).
+/// The similarity of a field can be changed at any time, without reindexing,
+/// using [`Index::set_similarity()`](../struct.Index.html#method.set_similarity).
+pub trait Similarity: Send + Sync + 'static {
+    /// Computes the weight of a query term, given the number of documents
+    /// containing each of its terms and the total number of documents.
+    ///
+    /// Phrase queries pass the document frequencies of all of their terms.
+    fn term_weight(&self, term_doc_freqs: &[u64], total_num_docs: u64) -> Explanation;
+
+    /// Computes the normalization factor of a document whose field
+    /// contains `fieldnorm` tokens.
+    ///
+    /// `average_fieldnorm` is the average number of tokens of the field
+    /// over the documents of the index.
+    fn doc_normalization(&self, fieldnorm: u32, average_fieldnorm: Score) -> Score;
+
+    /// Computes the score of a document.
+    ///
+    /// `term_weight` is the value of the weight returned by `.term_weight()`,
+    /// multiplied by the boost of the query, and `doc_normalization` is the value
+    /// returned by `.doc_normalization()` for this document.
+    ///
+    /// The score should be non-decreasing with the `term_freq`.
+    fn score(&self, term_weight: Score, term_freq: u32, doc_normalization: Score) -> Score;
+
+    /// Explains the score of a document.
+    fn explain(
+        &self,
+        term_weight: &Explanation,
+        term_freq: u32,
+        fieldnorm: u32,
+        average_fieldnorm: Score,
+    ) -> Explanation;
+
+    /// Returns an upper bound of the score of the documents, given the term weight
+    /// and the normalization factors of all of the fieldnorm ids.
+    ///
+    /// It is used to skip documents that cannot make it to the top hits.
+    fn max_score(&self, term_weight: Score, doc_normalizations: &[Score; 256]) -> Score {
+        doc_normalizations
+            .iter()
+            .map(|&doc_normalization| self.score(term_weight, MAX_TERM_FREQ, doc_normalization))
+            .fold(0.0, Score::max)
+    }
+
+    /// Returns the built-in similarity this similarity is, if any.
+    ///
+    /// The built-in similarities are scored without dynamic dispatch, and are saved
+    /// in the index settings.
+    #[doc(hidden)]
+    fn as_builtin(&self) -> Option<BuiltinSimilarity> {
+        None
+    }
+}
+
+/// The similarities provided by tantivy, as they are saved in the
+/// [`IndexSettings`](../struct.IndexSettings.html).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BuiltinSimilarity {
+    /// See [`Bm25Similarity`](./struct.Bm25Similarity.html).
+    Bm25 {
+        /// Term frequency saturation parameter.
+        k1: Score,
+        /// Length normalization parameter.
+        b: Score,
+    },
+    /// See [`ClassicTfIdf`](./struct.ClassicTfIdf.html).
+    ClassicTfIdf,
+    /// See [`BooleanSimilarity`](./struct.BooleanSimilarity.html).
+    Boolean,
+}
+
+// The BM25 parameters are checked to be finite by `IndexSettings::validate`.
+impl Eq for BuiltinSimilarity {}
+
+impl Default for BuiltinSimilarity {
+    fn default() -> BuiltinSimilarity {
+        Bm25Similarity::default().into()
+    }
+}
+
+impl From<Bm25Similarity> for BuiltinSimilarity {
+    fn from(bm25: Bm25Similarity) -> BuiltinSimilarity {
+        BuiltinSimilarity::Bm25 {
+            k1: bm25.k1,
+            b: bm25.b,
+        }
+    }
+}
+
+impl BuiltinSimilarity {
+    /// Returns an error if the parameters of the similarity are not valid.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if let BuiltinSimilarity::Bm25 { k1, b } = *self {
+            if !k1.is_finite() || k1 < 0.0 || !(0.0..=1.0).contains(&b) {
+                return Err(format!(
+                    "Invalid BM25 parameters k1={} and b={}: k1 must be positive, and b \
+                     between 0 and 1.",
+                    k1, b
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true iff the similarity is BM25 with its default parameters.
+    ///
+    /// The block-max information stored in the index is computed for this similarity.
+    pub(crate) fn is_default_bm25(&self) -> bool {
+        *self == BuiltinSimilarity::default()
+    }
+}
+
+macro_rules! dispatch_builtin {
+    ($builtin:expr, $similarity:ident => $call:expr) => {
+        match *$builtin {
+            BuiltinSimilarity::Bm25 { k1, b } => {
+                let $similarity = Bm25Similarity { k1, b };
+                $call
+            }
+            BuiltinSimilarity::ClassicTfIdf => {
+                let $similarity = ClassicTfIdf;
+                $call
+            }
+            BuiltinSimilarity::Boolean => {
+                let $similarity = BooleanSimilarity;
+                $call
+            }
+        }
+    };
+}
+
+impl Similarity for BuiltinSimilarity {
+    fn term_weight(&self, term_doc_freqs: &[u64], total_num_docs: u64) -> Explanation {
+        dispatch_builtin!(self, similarity => similarity.term_weight(term_doc_freqs, total_num_docs))
+    }
+
+    fn doc_normalization(&self, fieldnorm: u32, average_fieldnorm: Score) -> Score {
+        dispatch_builtin!(self, similarity => similarity.doc_normalization(fieldnorm, average_fieldnorm))
+    }
+
+    #[inline]
+    fn score(&self, term_weight: Score, term_freq: u32, doc_normalization: Score) -> Score {
+        dispatch_builtin!(self, similarity => similarity.score(term_weight, term_freq, doc_normalization))
+    }
+
+    fn explain(
+        &self,
+        term_weight: &Explanation,
+        term_freq: u32,
+        fieldnorm: u32,
+        average_fieldnorm: Score,
+    ) -> Explanation {
+        dispatch_builtin!(self, similarity => similarity.explain(term_weight, term_freq, fieldnorm, average_fieldnorm))
+    }
+
+    fn max_score(&self, term_weight: Score, doc_normalizations: &[Score; 256]) -> Score {
+        dispatch_builtin!(self, similarity => similarity.max_score(term_weight, doc_normalizations))
+    }
+
+    fn as_builtin(&self) -> Option<BuiltinSimilarity> {
+        Some(*self)
+    }
+}
+
+/// The classic TF-IDF similarity, as defined by Lucene's `ClassicSimilarity`.
+///
+/// The score of a document is `sqrt(freq) * idf * 1 / sqrt(dl)`, with
+/// `idf = 1 + log((N + 1) / (n + 1))`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ClassicTfIdf;
+
+impl ClassicTfIdf {
+    fn idf(term_doc_freq: u64, total_num_docs: u64) -> Score {
+        1.0 + ((total_num_docs as Score + 1.0) / (term_doc_freq as Score + 1.0)).ln()
+    }
+}
+
+impl Similarity for ClassicTfIdf {
+    fn term_weight(&self, term_doc_freqs: &[u64], total_num_docs: u64) -> Explanation {
+        if let [term_doc_freq] = *term_doc_freqs {
+            let idf = ClassicTfIdf::idf(term_doc_freq, total_num_docs);
+            let mut idf_explain =
+                Explanation::new("idf, computed as 1 + log((N + 1) / (n + 1))", idf);
+            idf_explain.add_const(
+                "n, number of docs containing this term",
+                term_doc_freq as Score,
+            );
+            idf_explain.add_const("N, total number of docs", total_num_docs as Score);
+            idf_explain
+        } else {
+            let idf_sum: Score = term_doc_freqs
+                .iter()
+                .map(|&term_doc_freq| ClassicTfIdf::idf(term_doc_freq, total_num_docs))
+                .sum();
+            Explanation::new("idf", idf_sum)
+        }
+    }
+
+    fn doc_normalization(&self, fieldnorm: u32, _average_fieldnorm: Score) -> Score {
+        1.0 / (fieldnorm.max(1) as Score).sqrt()
+    }
+
+    #[inline]
+    fn score(&self, term_weight: Score, term_freq: u32, doc_normalization: Score) -> Score {
+        (term_freq as Score).sqrt() * term_weight * doc_normalization
+    }
+
+    fn explain(
+        &self,
+        term_weight: &Explanation,
+        term_freq: u32,
+        fieldnorm: u32,
+        average_fieldnorm: Score,
+    ) -> Explanation {
+        let norm = self.doc_normalization(fieldnorm, average_fieldnorm);
+        let score = self.score(term_weight.value(), term_freq, norm);
+        let mut tf_explanation =
+            Explanation::new("tf, computed as sqrt(freq)", (term_freq as Score).sqrt());
+        tf_explanation.add_const(
+            "freq, occurrences of term within document",
+            term_freq as Score,
+        );
+        let mut norm_explanation = Explanation::new("fieldNorm, computed as 1 / sqrt(dl)", norm);
+        norm_explanation.add_const("dl, length of field", fieldnorm as Score);
+
+        let mut explanation = Explanation::new("TermQuery, product of...", score);
+        explanation.add_detail(tf_explanation);
+        explanation.add_detail(term_weight.clone());
+        explanation.add_detail(norm_explanation);
+        explanation
+    }
+
+    fn as_builtin(&self) -> Option<BuiltinSimilarity> {
+        Some(BuiltinSimilarity::ClassicTfIdf)
+    }
+}
+
+/// A similarity that ignores term frequencies and field lengths:
+/// the score of a matching document is always `1.0`, multiplied by the boost of the query.
+///
+/// This is useful when terms are used as keywords or tags.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BooleanSimilarity;
+
+impl Similarity for BooleanSimilarity {
+    fn term_weight(&self, _term_doc_freqs: &[u64], _total_num_docs: u64) -> Explanation {
+        Explanation::new("boolean similarity, constant weight", 1.0)
+    }
+
+    fn doc_normalization(&self, _fieldnorm: u32, _average_fieldnorm: Score) -> Score {
+        1.0
+    }
+
+    #[inline]
+    fn score(&self, term_weight: Score, _term_freq: u32, _doc_normalization: Score) -> Score {
+        term_weight
+    }
+
+    fn explain(
+        &self,
+        term_weight: &Explanation,
+        _term_freq: u32,
+        _fieldnorm: u32,
+        _average_fieldnorm: Score,
+    ) -> Explanation {
+        let mut explanation =
+            Explanation::new("TermQuery, boolean similarity", term_weight.value());
+        explanation.add_detail(term_weight.clone());
+        explanation
+    }
+
+    fn as_builtin(&self) -> Option<BuiltinSimilarity> {
+        Some(BuiltinSimilarity::Boolean)
+    }
+}
+
+/// The similarity of a `SimilarityWeight`: the built-in similarities are called
+/// without dynamic dispatch on the scoring hot path.
+#[derive(Clone)]
+enum WeightSimilarity {
+    Builtin(BuiltinSimilarity),
+    Custom(Arc<dyn Similarity>),
+}
+
+impl WeightSimilarity {
+    fn as_similarity(&self) -> &dyn Similarity {
+        match self {
+            WeightSimilarity::Builtin(builtin) => builtin,
+            WeightSimilarity::Custom(similarity) => similarity.as_ref(),
+        }
+    }
+}
+
+/// Number of possible bit widths of the term frequencies of a block.
+const NUM_TF_NUM_BITS: usize = 33;
+
+/// Bits of a block-max score bound which is not computed yet.
+const UNCOMPUTED_BOUND: u32 = u32::MAX;
+
+/// The weight of a query term, or of a phrase, for a given `Similarity`.
+///
+/// The normalization factors of all of the possible fieldnorm ids are
+/// precomputed.
+#[derive(Clone)]
+pub struct SimilarityWeight {
+    similarity: WeightSimilarity,
+    term_weight_explain: Explanation,
+    weight: Score,
+    cache: [Score; 256],
+    average_fieldnorm: Score,
+    max_score: Score,
+    // Upper bound of the score of a block, given the bit width of its term frequencies,
+    // for the similarities other than the default BM25. The bounds are computed on their
+    // first use, and shared with the clones of the weight.
+    block_max_score_bounds: Option<Arc<[AtomicU32]>>,
+}
+
+impl SimilarityWeight {
+    pub fn boost_by(&self, boost: Score) -> SimilarityWeight {
+        SimilarityWeight::with_weight(
+            self.similarity.clone(),
+            self.term_weight_explain.clone(),
+            self.weight * boost,
+            self.cache,
+            self.average_fieldnorm,
+        )
+    }
+
+    pub fn for_terms(searcher: &Searcher, terms: &[Term]) -> crate::Result<SimilarityWeight> {
+        assert!(!terms.is_empty(), "Similarity requires at least one term");
+        let field = terms[0].field();
+        for term in &terms[1..] {
+            assert_eq!(
+                term.field(),
+                field,
+                "All terms must belong to the same field."
+            );
+        }
+
+        let mut total_num_tokens = 0u64;
+        let mut total_num_docs = 0u64;
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            total_num_tokens += inverted_index.total_num_tokens();
+            total_num_docs += u64::from(segment_reader.max_doc());
+        }
+        let average_fieldnorm = total_num_tokens as Score / total_num_docs as Score;
+
+        let term_doc_freqs = terms
+            .iter()
+            .map(|term| searcher.doc_freq(term))
+            .collect::<crate::Result<Vec<u64>>>()?;
+        let similarity = searcher.index().similarity(field);
+        let term_weight_explain = similarity.term_weight(&term_doc_freqs, total_num_docs);
+        Ok(SimilarityWeight::new(
+            similarity,
+            term_weight_explain,
+            average_fieldnorm,
+        ))
+    }
+
+    /// Creates the BM25 weight of a term, using the default parameters.
+    pub fn for_one_term(
+        term_doc_freq: u64,
+        total_num_docs: u64,
+        avg_fieldnorm: Score,
+    ) -> SimilarityWeight {
+        let similarity = Bm25Similarity::default();
+        let term_weight_explain = similarity.term_weight(&[term_doc_freq], total_num_docs);
+        SimilarityWeight::new(Arc::new(similarity), term_weight_explain, avg_fieldnorm)
+    }
+
+    pub(crate) fn new(
+        similarity: Arc<dyn Similarity>,
+        term_weight_explain: Explanation,
+        average_fieldnorm: Score,
+    ) -> SimilarityWeight {
+        let similarity = match similarity.as_builtin() {
+            Some(builtin) => WeightSimilarity::Builtin(builtin),
+            None => WeightSimilarity::Custom(similarity),
+        };
+        let weight = term_weight_explain.value();
+        let cache = compute_doc_normalization_cache(similarity.as_similarity(), average_fieldnorm);
+        SimilarityWeight::with_weight(
+            similarity,
+            term_weight_explain,
+            weight,
+            cache,
+            average_fieldnorm,
+        )
+    }
+
+    fn with_weight(
+        similarity: WeightSimilarity,
+        term_weight_explain: Explanation,
+        weight: Score,
+        cache: [Score; 256],
+        average_fieldnorm: Score,
+    ) -> SimilarityWeight {
+        let max_score = similarity.as_similarity().max_score(weight, &cache);
+        let block_max_score_bounds = match &similarity {
+            WeightSimilarity::Builtin(builtin) if builtin.is_default_bm25() => None,
+            _ => Some(
+                (0..NUM_TF_NUM_BITS)
+                    .map(|_| AtomicU32::new(UNCOMPUTED_BOUND))
+                    .collect(),
+            ),
+        };
+        SimilarityWeight {
+            similarity,
+            term_weight_explain,
+            weight,
+            cache,
+            average_fieldnorm,
+            max_score,
+            block_max_score_bounds,
+        }
+    }
+
+    /// Returns true iff the block-max information stored in the skip lists
+    /// is valid for this weight.
+    pub(crate) fn uses_default_bm25(&self) -> bool {
+        self.block_max_score_bounds.is_none()
+    }
+
+    /// Returns an upper bound of the score of the documents of a block whose term
+    /// frequencies are encoded on `tf_num_bits` bits, for the similarities for which the
+    /// block-max information stored in the skip lists is not valid.
+    pub(crate) fn block_max_score_bound(&self, tf_num_bits: u8) -> Score {
+        let bounds = match &self.block_max_score_bounds {
+            Some(bounds) => bounds,
+            None => return self.max_score,
+        };
+        let tf_num_bits = (tf_num_bits as usize).min(NUM_TF_NUM_BITS - 1);
+        let bound_bits = bounds[tf_num_bits].load(Ordering::Relaxed);
+        if bound_bits != UNCOMPUTED_BOUND {
+            return Score::from_bits(bound_bits);
+        }
+        // A block without term frequencies is scored with a term frequency of 1.
+        let max_term_freq = ((1u64 << tf_num_bits) - 1).clamp(1, u64::from(u32::MAX)) as u32;
+        // The score is non-decreasing with the term frequency.
+        let similarity = self.similarity.as_similarity();
+        let bound = self
+            .cache
+            .iter()
+            .map(|&doc_normalization| {
+                similarity.score(self.weight, max_term_freq, doc_normalization)
+            })
+            .fold(0.0, Score::max);
+        bounds[tf_num_bits].store(bound.to_bits(), Ordering::Relaxed);
+        bound
+    }
+
+    #[inline]
+    pub fn score(&self, fieldnorm_id: u8, term_freq: u32) -> Score {
+        let doc_normalization = self.cache[fieldnorm_id as usize];
+        match &self.similarity {
+            WeightSimilarity::Builtin(BuiltinSimilarity::Bm25 { k1, b }) => {
+                Bm25Similarity { k1: *k1, b: *b }.score(self.weight, term_freq, doc_normalization)
+            }
+            WeightSimilarity::Builtin(builtin) => {
+                builtin.score(self.weight, term_freq, doc_normalization)
+            }
+            WeightSimilarity::Custom(similarity) => {
+                similarity.score(self.weight, term_freq, doc_normalization)
+            }
+        }
+    }
+
+    pub fn max_score(&self) -> Score {
+        self.max_score
+    }
+
+    pub fn explain(&self, fieldnorm_id: u8, term_freq: u32) -> Explanation {
+        self.similarity.as_similarity().explain(
+            &self.term_weight_explain,
+            term_freq,
+            FieldNormReader::id_to_fieldnorm(fieldnorm_id),
+            self.average_fieldnorm,
+        )
+    }
+}
+
+fn compute_doc_normalization_cache(
+    similarity: &dyn Similarity,
+    average_fieldnorm: Score,
+) -> [Score; 256] {
+    let mut cache: [Score; 256] = [0.0; 256];
+    for (fieldnorm_id, cache_mut) in cache.iter_mut().enumerate() {
+        let fieldnorm = FieldNormReader::id_to_fieldnorm(fieldnorm_id as u8);
+        *cache_mut = similarity.doc_normalization(fieldnorm, average_fieldnorm);
+    }
+    cache
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BuiltinSimilarity, Similarity};
+    use crate::collector::TopDocs;
+    use crate::directory::RamDirectory;
+    use crate::query::{
+        Bm25Similarity, BooleanQuery, BooleanSimilarity, BoostQuery, ClassicTfIdf, Explanation,
+        PhraseQuery, Query, TermQuery,
+    };
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{assert_nearly_equals, DocAddress, Index, IndexSettings, Score, Term};
+
+    fn create_index() -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field=>"a b"));
+        index_writer.add_document(doc!(text_field=>"a a a b"));
+        index_writer.add_document(doc!(text_field=>"c"));
+        index_writer.add_document(doc!(text_field=>"d"));
+        index_writer.commit()?;
+        Ok(index)
+    }
+
+    fn scores(index: &Index, query: &dyn Query) -> crate::Result<Vec<(DocAddress, Score)>> {
+        let searcher = index.reader()?.searcher();
+        let top_docs = searcher.search(query, &TopDocs::with_limit(10))?;
+        let mut doc_scores: Vec<(DocAddress, Score)> = top_docs
+            .into_iter()
+            .map(|(score, doc_address)| (doc_address, score))
+            .collect();
+        doc_scores.sort_by_key(|(doc_address, _)| *doc_address);
+        Ok(doc_scores)
+    }
+
+    #[test]
+    fn test_classic_tf_idf_term_query() -> crate::Result<()> {
+        let index = create_index()?;
+        let text_field = index.schema().get_field("text").unwrap();
+        index.set_similarity(text_field, ClassicTfIdf);
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+        // idf = 1 + ln((N + 1) / (n + 1)) with N = 4, n = 2
+        let idf: Score = 1.0 + (5.0 as Score / 3.0).ln();
+        let doc_scores = scores(&index, &term_query)?;
+        assert_eq!(doc_scores.len(), 2);
+        // sqrt(freq) * idf / sqrt(dl)
+        assert_nearly_equals!(doc_scores[0].1, idf / (2.0 as Score).sqrt());
+        assert_nearly_equals!(doc_scores[1].1, (3.0 as Score).sqrt() * idf / 2.0);
+        let searcher = index.reader()?.searcher();
+        let explanation = term_query.explain(&searcher, DocAddress::new(0, 1))?;
+        assert_nearly_equals!(explanation.value(), doc_scores[1].1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_classic_tf_idf_phrase_query() -> crate::Result<()> {
+        let index = create_index()?;
+        let text_field = index.schema().get_field("text").unwrap();
+        index.set_similarity(text_field, ClassicTfIdf);
+        let phrase_query = PhraseQuery::new(vec![
+            Term::from_field_text(text_field, "a"),
+            Term::from_field_text(text_field, "b"),
+        ]);
+        // The idf of a phrase is the sum of the idf of its terms.
+        let idf: Score = 2.0 * (1.0 + (5.0 as Score / 3.0).ln());
+        let doc_scores = scores(&index, &phrase_query)?;
+        assert_eq!(doc_scores.len(), 2);
+        assert_nearly_equals!(doc_scores[0].1, idf / (2.0 as Score).sqrt());
+        assert_nearly_equals!(doc_scores[1].1, idf / 2.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_boolean_similarity() -> crate::Result<()> {
+        let index = create_index()?;
+        let text_field = index.schema().get_field("text").unwrap();
+        index.set_similarity(text_field, BooleanSimilarity);
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+        let doc_scores = scores(&index, &term_query)?;
+        assert_eq!(doc_scores.len(), 2);
+        assert_nearly_equals!(doc_scores[0].1, 1.0);
+        assert_nearly_equals!(doc_scores[1].1, 1.0);
+        let boost_query = BoostQuery::new(Box::new(term_query), 2.0);
+        let doc_scores = scores(&index, &boost_query)?;
+        assert_nearly_equals!(doc_scores[0].1, 2.0);
+        assert_nearly_equals!(doc_scores[1].1, 2.0);
+        Ok(())
+    }
+
+    #[derive(Clone, Copy)]
+    struct ConstantSimilarity;
+
+    impl Similarity for ConstantSimilarity {
+        fn term_weight(&self, _term_doc_freqs: &[u64], _total_num_docs: u64) -> Explanation {
+            Explanation::new("constant", 3.0)
+        }
+
+        fn doc_normalization(&self, _fieldnorm: u32, _average_fieldnorm: Score) -> Score {
+            1.0
+        }
+
+        fn score(&self, term_weight: Score, _term_freq: u32, _doc_normalization: Score) -> Score {
+            term_weight
+        }
+
+        fn explain(
+            &self,
+            term_weight: &Explanation,
+            _term_freq: u32,
+            _fieldnorm: u32,
+            _average_fieldnorm: Score,
+        ) -> Explanation {
+            term_weight.clone()
+        }
+    }
+
+    #[test]
+    fn test_similarities_saved_in_settings() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let tags = schema_builder.add_text_field("tags", TEXT);
+        let directory = RamDirectory::create();
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            Default::default(),
+        )?;
+        index.set_similarity(title, Bm25Similarity::new(2.0, 0.0));
+        index.set_similarity(body, ClassicTfIdf);
+        index.set_similarity(tags, ConstantSimilarity);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "a", body => "a b", tags => "a"));
+        index_writer.commit()?;
+
+        let reopened_index = Index::open(directory)?;
+        let similarities = &reopened_index.load_metas()?.index_settings.similarities;
+        assert_eq!(similarities.len(), 2);
+        assert_eq!(
+            similarities["title"],
+            BuiltinSimilarity::Bm25 { k1: 2.0, b: 0.0 }
+        );
+        assert_eq!(
+            reopened_index.similarity(body).as_builtin(),
+            Some(BuiltinSimilarity::ClassicTfIdf)
+        );
+        // the custom similarity is not saved.
+        assert_eq!(
+            reopened_index.similarity(tags).as_builtin(),
+            Some(BuiltinSimilarity::default())
+        );
+        let term_query = TermQuery::new(
+            Term::from_field_text(body, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+        assert_eq!(
+            scores(&index, &term_query)?,
+            scores(&reopened_index, &term_query)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builtin_similarity_serialization() -> crate::Result<()> {
+        let similarity: BuiltinSimilarity = Bm25Similarity::new(1.5, 0.5).into();
+        let json = serde_json::to_string(&similarity)?;
+        assert_eq!(json, r#"{"type":"bm25","k1":1.5,"b":0.5}"#);
+        assert_eq!(
+            serde_json::from_str::<BuiltinSimilarity>(&json)?,
+            similarity
+        );
+        assert_eq!(
+            serde_json::to_string(&BuiltinSimilarity::ClassicTfIdf)?,
+            r#"{"type":"classic_tf_idf"}"#
+        );
+        let mut settings = IndexSettings::default();
+        settings
+            .similarities
+            .insert("title".to_string(), Bm25Similarity::new(1.2, 2.0).into());
+        assert!(settings.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_max_pruning_with_other_similarities() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for doc_id in 0..2_000usize {
+            let mut text = String::new();
+            for _ in 0..doc_id % 7 {
+                text.push_str("a ");
+            }
+            for _ in 0..doc_id % 5 {
+                text.push_str("b ");
+            }
+            for _ in 0..doc_id % 13 {
+                text.push_str("c ");
+            }
+            index_writer.add_document(doc!(text_field => text));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = BooleanQuery::new_multiterms_query(vec![
+            Term::from_field_text(text_field, "a"),
+            Term::from_field_text(text_field, "b"),
+        ]);
+        for similarity in [
+            BuiltinSimilarity::default(),
+            Bm25Similarity::new(2.0, 0.3).into(),
+            BuiltinSimilarity::ClassicTfIdf,
+        ] {
+            index.set_similarity(text_field, similarity);
+            // the top docs of the pruned search are the ones of the exhaustive search.
+            let top_docs = searcher.search(&query, &TopDocs::with_limit(10))?;
+            let all_docs = searcher.search(&query, &TopDocs::with_limit(2_000))?;
+            for ((score, _), (expected_score, _)) in top_docs.iter().zip(&all_docs[..10]) {
+                assert_nearly_equals!(*score, *expected_score);
+            }
+        }
+        Ok(())
+    }
+}
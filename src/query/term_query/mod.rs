@@ -12,9 +12,9 @@ mod tests {
     use crate::collector::TopDocs;
     use crate::docset::DocSet;
     use crate::postings::compression::COMPRESSION_BLOCK_SIZE;
-    use crate::query::{Query, QueryParser, Scorer, TermQuery};
+    use crate::query::{Bm25Similarity, PhraseQuery, Query, QueryParser, Scorer, TermQuery};
     use crate::schema::{Field, IndexRecordOption, Schema, STRING, TEXT};
-    use crate::{assert_nearly_equals, DocAddress};
+    use crate::{assert_nearly_equals, DocAddress, Score};
     use crate::{Index, Term, TERMINATED};
 
     #[test]
@@ -215,4 +215,50 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_term_query_bm25_parameters() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field=>"a b"));
+        index_writer.add_document(doc!(text_field=>"a b c d e f g h"));
+        index_writer.add_document(doc!(text_field=>"c"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let scores = |query: &dyn Query| -> crate::Result<Vec<Score>> {
+            let top_docs = searcher.search(query, &TopDocs::with_limit(3))?;
+            Ok(top_docs.into_iter().map(|(score, _)| score).collect())
+        };
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+        let phrase_query = PhraseQuery::new(vec![
+            Term::from_field_text(text_field, "a"),
+            Term::from_field_text(text_field, "b"),
+        ]);
+        {
+            let term_scores = scores(&term_query)?;
+            assert!(term_scores[0] > term_scores[1]);
+            let phrase_scores = scores(&phrase_query)?;
+            assert!(phrase_scores[0] > phrase_scores[1]);
+        }
+        // With b = 0, the length of the field is ignored.
+        index.set_similarity(text_field, Bm25Similarity::new(2.0, 0.0));
+        {
+            let term_scores = scores(&term_query)?;
+            assert_eq!(term_scores.len(), 2);
+            assert_nearly_equals!(term_scores[0], term_scores[1]);
+            let phrase_scores = scores(&phrase_query)?;
+            assert_nearly_equals!(phrase_scores[0], phrase_scores[1]);
+            let explanation = term_query.explain(&searcher, DocAddress::new(0u32, 0u32))?;
+            let explanation_json = explanation.to_pretty_json();
+            assert!(explanation_json.contains(r#""value": 2.0"#));
+            assert!(explanation_json.contains(r#""value": 0.0"#));
+        }
+        Ok(())
+    }
 }
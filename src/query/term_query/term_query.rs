@@ -1,12 +1,13 @@
 use super::term_weight::TermWeight;
-use crate::query::bm25::Bm25Weight;
+use crate::query::filter_cache::query_cache_key;
 use crate::query::Weight;
-use crate::query::{Explanation, Query};
+use crate::query::{Bm25Similarity, SimilarityWeight};
+use crate::query::{Explanation, Query, QueryVisitor};
 use crate::schema::IndexRecordOption;
 use crate::Searcher;
 use crate::Term;
-use std::collections::BTreeMap;
 use std::fmt;
+use std::sync::Arc;
 
 /// A Term query matches all of the documents
 /// containing a specific term.
@@ -82,6 +83,11 @@ impl TermQuery {
         &self.term
     }
 
+    /// The `IndexRecordOption` of the postings read by this query.
+    pub fn index_record_option(&self) -> IndexRecordOption {
+        self.index_record_option
+    }
+
     /// Returns a weight object.
     ///
     /// While `.weight(...)` returns a boxed trait object,
@@ -100,13 +106,15 @@ impl TermQuery {
                 field_entry.name()
             )));
         }
-        let bm25_weight;
-        if scoring_enabled {
-            bm25_weight = Bm25Weight::for_terms(searcher, &[term])?;
+        let similarity_weight = if scoring_enabled {
+            SimilarityWeight::for_terms(searcher, &[term])?
         } else {
-            bm25_weight =
-                Bm25Weight::new(Explanation::new("<no score>".to_string(), 1.0f32), 1.0f32);
-        }
+            SimilarityWeight::new(
+                Arc::new(Bm25Similarity::default()),
+                Explanation::new("<no score>".to_string(), 1.0f32),
+                1.0f32,
+            )
+        };
         let index_record_option = if scoring_enabled {
             self.index_record_option
         } else {
@@ -115,7 +123,7 @@ impl TermQuery {
         Ok(TermWeight::new(
             self.term.clone(),
             index_record_option,
-            bm25_weight,
+            similarity_weight,
             scoring_enabled,
         ))
     }
@@ -127,7 +135,12 @@ impl Query for TermQuery {
             self.specialized_weight(searcher, scoring_enabled)?,
         ))
     }
-    fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
-        terms.insert(self.term.clone(), false);
+
+    fn visit(&self, visitor: &mut dyn QueryVisitor) {
+        visitor.visit_term_query(self);
+    }
+
+    fn cache_key(&self) -> Option<Vec<u8>> {
+        Some(query_cache_key("term", &self.term.as_slice().to_vec()))
     }
 }
@@ -6,20 +6,20 @@ use crate::Score;
 use crate::fieldnorm::FieldNormReader;
 use crate::postings::SegmentPostings;
 use crate::postings::{FreqReadingOption, Postings};
-use crate::query::bm25::Bm25Weight;
+use crate::query::SimilarityWeight;
 
 #[derive(Clone)]
 pub struct TermScorer {
     postings: SegmentPostings,
     fieldnorm_reader: FieldNormReader,
-    similarity_weight: Bm25Weight,
+    similarity_weight: SimilarityWeight,
 }
 
 impl TermScorer {
     pub fn new(
         postings: SegmentPostings,
         fieldnorm_reader: FieldNormReader,
-        similarity_weight: Bm25Weight,
+        similarity_weight: SimilarityWeight,
     ) -> TermScorer {
         TermScorer {
             postings,
@@ -36,7 +36,7 @@ impl TermScorer {
     pub fn create_for_test(
         doc_and_tfs: &[(DocId, u32)],
         fieldnorms: &[u32],
-        similarity_weight: Bm25Weight,
+        similarity_weight: SimilarityWeight,
     ) -> TermScorer {
         assert!(!doc_and_tfs.is_empty());
         assert!(
@@ -131,17 +131,19 @@ mod tests {
     use crate::merge_policy::NoMergePolicy;
     use crate::postings::compression::COMPRESSION_BLOCK_SIZE;
     use crate::query::term_query::TermScorer;
-    use crate::query::{Bm25Weight, Scorer, TermQuery};
+    use crate::query::{Bm25Similarity, ClassicTfIdf, Similarity};
+    use crate::query::{Scorer, SimilarityWeight, TermQuery};
     use crate::schema::{IndexRecordOption, Schema, TEXT};
     use crate::Score;
     use crate::{assert_nearly_equals, Index, Searcher, SegmentId, Term};
     use crate::{DocId, DocSet, TERMINATED};
     use futures::executor::block_on;
     use proptest::prelude::*;
+    use std::sync::Arc;
 
     #[test]
     fn test_term_scorer_max_score() -> crate::Result<()> {
-        let bm25_weight = Bm25Weight::for_one_term(3, 6, 10.0);
+        let bm25_weight = SimilarityWeight::for_one_term(3, 6, 10.0);
         let mut term_scorer = TermScorer::create_for_test(
             &[(2, 3), (3, 12), (7, 8)],
             &[0, 0, 10, 12, 0, 0, 0, 100],
@@ -167,7 +169,7 @@ mod tests {
 
     #[test]
     fn test_term_scorer_shallow_advance() -> crate::Result<()> {
-        let bm25_weight = Bm25Weight::for_one_term(300, 1024, 10.0);
+        let bm25_weight = SimilarityWeight::for_one_term(300, 1024, 10.0);
         let mut doc_and_tfs = vec![];
         for i in 0u32..300u32 {
             let doc = i * 10;
@@ -205,7 +207,7 @@ mod tests {
              // Average fieldnorm is over the entire index,
              // not necessarily the docs that are in the posting list.
              // For this reason we multiply by 1.1 to make a realistic value.
-         let bm25_weight = Bm25Weight::for_one_term(term_doc_freq as u64,
+         let bm25_weight = SimilarityWeight::for_one_term(term_doc_freq as u64,
             term_doc_freq as u64 * 10u64,
             average_fieldnorm);
 
@@ -224,6 +226,38 @@ mod tests {
              assert_nearly_equals!(block_max_score_computed, block_max_score);
          }
         }
+
+        #[test]
+        fn test_term_scorer_block_max_score_bound(term_freqs_fieldnorms in proptest::collection::vec((1u32..300u32, 0u32..100u32), 80..300)) {
+            let doc_tfs: Vec<(u32, u32)> = term_freqs_fieldnorms.iter()
+                .enumerate()
+                .map(|(doc, &(tf, _))| (doc as u32, tf))
+                .collect();
+            let fieldnorms: Vec<u32> = term_freqs_fieldnorms.iter()
+                .map(|&(tf, num_extra_terms)| tf + num_extra_terms)
+                .collect();
+            let similarities: Vec<Arc<dyn Similarity>> = vec![
+                Arc::new(Bm25Similarity::new(2.0, 0.3)),
+                Arc::new(ClassicTfIdf),
+            ];
+            for similarity in similarities {
+                let term_weight = similarity.term_weight(&[doc_tfs.len() as u64], 1_000);
+                let similarity_weight = SimilarityWeight::new(similarity, term_weight, 50.0);
+                let mut term_scorer =
+                    TermScorer::create_for_test(&doc_tfs[..], &fieldnorms[..], similarity_weight);
+                // the block-max score of the other similarities is an upper bound.
+                while term_scorer.doc() != TERMINATED {
+                    let block_max_score = term_scorer.block_max_score();
+                    let last_doc_in_block = term_scorer.last_doc_in_block();
+                    while term_scorer.doc() <= last_doc_in_block {
+                        prop_assert!(term_scorer.score() <= block_max_score);
+                        if term_scorer.advance() == TERMINATED {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
     }
 
     #[test]
@@ -240,7 +274,7 @@ mod tests {
         doc_tfs.push((258, 1u32));
 
         let fieldnorms: Vec<u32> = std::iter::repeat(20u32).take(300).collect();
-        let bm25_weight = Bm25Weight::for_one_term(10, 129, 20.0);
+        let bm25_weight = SimilarityWeight::for_one_term(10, 129, 20.0);
         let mut docs = TermScorer::create_for_test(&doc_tfs[..], &fieldnorms[..], bm25_weight);
         assert_nearly_equals!(docs.block_max_score(), 2.5161593);
         docs.shallow_seek(135);
@@ -3,9 +3,9 @@ use crate::core::SegmentReader;
 use crate::docset::DocSet;
 use crate::fieldnorm::FieldNormReader;
 use crate::postings::SegmentPostings;
-use crate::query::bm25::Bm25Weight;
 use crate::query::explanation::does_not_match;
 use crate::query::weight::for_each_scorer;
+use crate::query::SimilarityWeight;
 use crate::query::Weight;
 use crate::query::{Explanation, Scorer};
 use crate::schema::IndexRecordOption;
@@ -15,7 +15,7 @@ use crate::{DocId, Score};
 pub struct TermWeight {
     term: Term,
     index_record_option: IndexRecordOption,
-    similarity_weight: Bm25Weight,
+    similarity_weight: SimilarityWeight,
     scoring_enabled: bool,
 }
 
@@ -88,7 +88,7 @@ impl TermWeight {
     pub fn new(
         term: Term,
         index_record_option: IndexRecordOption,
-        similarity_weight: Bm25Weight,
+        similarity_weight: SimilarityWeight,
         scoring_enabled: bool,
     ) -> TermWeight {
         TermWeight {
@@ -0,0 +1,342 @@
+use crate::core::{InvertedIndexReader, Searcher, SegmentReader};
+use crate::postings::TermInfo;
+use crate::query::explanation::does_not_match;
+use crate::query::filter_cache::query_cache_key;
+use crate::query::{BitSetDocSet, ConstScorer, EmptyScorer, Explanation, VecDocSet};
+use crate::query::{Query, QueryVisitor, Scorer, Weight};
+use crate::schema::{Field, IndexRecordOption, Term};
+use crate::termdict::TermDictionary;
+use crate::{DocId, Score};
+use common::BitSet;
+use std::io;
+use std::sync::Arc;
+
+/// If the set has at least one term for this many terms in the dictionary,
+/// streaming through the dictionary is cheaper than looking up every term.
+const STREAMING_TERM_RATIO: usize = 16;
+
+/// Documents are collected into a `BitSet` rather than a sorted `Vec`
+/// when the number of postings is above `max_doc / DENSE_POSTINGS_RATIO`.
+const DENSE_POSTINGS_RATIO: u32 = 32;
+
+/// `TermSetQuery` matches all of the documents containing any of the terms of a set.
+///
+/// It is a much cheaper alternative to a `BooleanQuery` made of a `Should` `TermQuery`
+/// per term, when the set is large (e.g. filtering over a list of ids).
+///
+/// Matched document will all get a constant `Score` of one.
+///
+/// The terms may belong to different fields. Terms that are not present in the index
+/// are simply ignored.
+///
+/// # Example
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::TermSetQuery;
+/// use tantivy::schema::{Schema, INDEXED};
+/// use tantivy::{doc, Index, Term};
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let id_field = schema_builder.add_u64_field("id", INDEXED);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::create_in_ram(schema);
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// for id in 0u64..1_000u64 {
+///     index_writer.add_document(doc!(id_field => id));
+/// }
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let ids = (0u64..2_000u64).step_by(3);
+/// let query = TermSetQuery::new(ids.map(|id| Term::from_field_u64(id_field, id)).collect());
+/// assert_eq!(searcher.search(&query, &Count)?, 334);
+/// Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct TermSetQuery {
+    terms: Arc<Vec<Term>>,
+}
+
+impl TermSetQuery {
+    /// Creates a new `TermSetQuery` matching the documents containing any of `terms`.
+    pub fn new(mut terms: Vec<Term>) -> TermSetQuery {
+        terms.sort();
+        terms.dedup();
+        TermSetQuery {
+            terms: Arc::new(terms),
+        }
+    }
+
+    /// The set of terms, sorted and deduplicated.
+    pub fn terms(&self) -> &[Term] {
+        &self.terms[..]
+    }
+}
+
+impl Query for TermSetQuery {
+    fn weight(
+        &self,
+        _searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> crate::Result<Box<dyn Weight>> {
+        Ok(Box::new(TermSetWeight {
+            terms: Arc::clone(&self.terms),
+        }))
+    }
+
+    fn visit(&self, visitor: &mut dyn QueryVisitor) {
+        visitor.visit_term_set_query(self);
+    }
+
+    fn cache_key(&self) -> Option<Vec<u8>> {
+        let terms: Vec<Vec<u8>> = self
+            .terms
+            .iter()
+            .map(|term| term.as_slice().to_vec())
+            .collect();
+        Some(query_cache_key("term_set", &terms))
+    }
+}
+
+struct TermSetWeight {
+    /// Sorted, hence grouped by field.
+    terms: Arc<Vec<Term>>,
+}
+
+/// Returns the `TermInfo` of the terms of `sorted_terms` that are present in `term_dict`.
+fn find_term_infos(
+    term_dict: &TermDictionary,
+    sorted_terms: &[Term],
+    term_infos: &mut Vec<TermInfo>,
+) -> io::Result<()> {
+    let (first_term, last_term) = match (sorted_terms.first(), sorted_terms.last()) {
+        (Some(first_term), Some(last_term)) => (first_term, last_term),
+        _ => return Ok(()),
+    };
+    if sorted_terms.len() * STREAMING_TERM_RATIO < term_dict.num_terms() {
+        for term in sorted_terms {
+            if let Some(term_info) = term_dict.get(term.value_bytes())? {
+                term_infos.push(term_info);
+            }
+        }
+        return Ok(());
+    }
+    let mut term_stream = term_dict
+        .range()
+        .ge(first_term.value_bytes())
+        .le(last_term.value_bytes())
+        .into_stream()?;
+    let mut terms_it = sorted_terms.iter().map(Term::value_bytes).peekable();
+    while term_stream.advance() {
+        let key = term_stream.key();
+        while terms_it.next_if(|term_bytes| *term_bytes < key).is_some() {}
+        match terms_it.peek() {
+            Some(term_bytes) if *term_bytes == key => {
+                term_infos.push(term_stream.value().clone());
+                terms_it.next();
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+impl TermSetWeight {
+    /// Returns the postings to read, grouped by field, as `(inverted index, term infos)`.
+    fn postings(
+        &self,
+        reader: &SegmentReader,
+    ) -> crate::Result<Vec<(Arc<InvertedIndexReader>, Vec<TermInfo>)>> {
+        let mut postings = Vec::new();
+        let mut remaining_terms = &self.terms[..];
+        while let Some(first_term) = remaining_terms.first() {
+            let field: Field = first_term.field();
+            let num_field_terms = remaining_terms
+                .iter()
+                .take_while(|term| term.field() == field)
+                .count();
+            let (field_terms, other_terms) = remaining_terms.split_at(num_field_terms);
+            remaining_terms = other_terms;
+            let inverted_index = reader.inverted_index(field)?;
+            let mut term_infos = Vec::new();
+            find_term_infos(inverted_index.terms(), field_terms, &mut term_infos)?;
+            if !term_infos.is_empty() {
+                postings.push((inverted_index, term_infos));
+            }
+        }
+        Ok(postings)
+    }
+}
+
+impl Weight for TermSetWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let postings = self.postings(reader)?;
+        let num_postings: u32 = postings
+            .iter()
+            .flat_map(|(_, term_infos)| term_infos.iter())
+            .map(|term_info| term_info.doc_freq)
+            .sum();
+        if num_postings == 0 {
+            return Ok(Box::new(EmptyScorer));
+        }
+        let max_doc = reader.max_doc();
+        let is_dense = num_postings > max_doc / DENSE_POSTINGS_RATIO;
+        let mut doc_bitset = if is_dense {
+            Some(BitSet::with_max_value(max_doc))
+        } else {
+            None
+        };
+        let mut doc_ids = Vec::new();
+        for (inverted_index, term_infos) in &postings {
+            for term_info in term_infos {
+                let mut block_segment_postings = inverted_index
+                    .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
+                loop {
+                    let docs = block_segment_postings.docs();
+                    if docs.is_empty() {
+                        break;
+                    }
+                    if let Some(doc_bitset) = doc_bitset.as_mut() {
+                        for &doc in docs {
+                            doc_bitset.insert(doc);
+                        }
+                    } else {
+                        doc_ids.extend_from_slice(docs);
+                    }
+                    block_segment_postings.advance();
+                }
+            }
+        }
+        if let Some(doc_bitset) = doc_bitset {
+            let docset = BitSetDocSet::from(doc_bitset);
+            Ok(Box::new(ConstScorer::new(docset, boost)))
+        } else {
+            doc_ids.sort_unstable();
+            doc_ids.dedup();
+            let docset = VecDocSet::from(doc_ids);
+            Ok(Box::new(ConstScorer::new(docset, boost)))
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        Ok(Explanation::new("TermSetQuery", 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::TermSetQuery;
+    use crate::collector::{Count, DocSetCollector};
+    use crate::query::{BooleanQuery, Query};
+    use crate::schema::{Schema, INDEXED, TEXT};
+    use crate::{DocAddress, Index, Term};
+
+    #[test]
+    fn test_term_set_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED);
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for id in 0u64..1_000u64 {
+            let text = if id % 2 == 0 { "even" } else { "odd" };
+            index_writer.add_document(doc!(id_field => id, text_field => text));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let id_terms = |ids: &[u64]| -> Vec<Term> {
+            ids.iter()
+                .map(|&id| Term::from_field_u64(id_field, id))
+                .collect()
+        };
+
+        // Sparse results, with duplicates and missing terms.
+        let query = TermSetQuery::new(id_terms(&[5, 3, 3, 999, 1_000, 2_000]));
+        assert_eq!(query.terms().len(), 5);
+        let docs = searcher.search(&query, &DocSetCollector)?;
+        let expected_docs = [3, 5, 999]
+            .iter()
+            .map(|&doc| DocAddress::new(0, doc))
+            .collect();
+        assert_eq!(docs, expected_docs);
+        assert_eq!(query.count(&searcher)?, 3);
+        let explanation = query.explain(&searcher, DocAddress::new(0, 5))?;
+        assert_eq!(explanation.value(), 1.0);
+        assert!(query.explain(&searcher, DocAddress::new(0, 4)).is_err());
+
+        // Dense results, and terms from several fields.
+        let ids: Vec<u64> = (0u64..1_000u64).step_by(3).collect();
+        let mut terms = id_terms(&ids);
+        terms.push(Term::from_field_text(text_field, "odd"));
+        terms.push(Term::from_field_text(text_field, "missing"));
+        let query = TermSetQuery::new(terms.clone());
+        let boolean_query = BooleanQuery::new_multiterms_query(terms);
+        assert_eq!(
+            searcher.search(&query, &DocSetCollector)?,
+            searcher.search(&boolean_query, &DocSetCollector)?
+        );
+        assert_eq!(searcher.search(&query, &Count)?, 667);
+
+        assert_eq!(TermSetQuery::new(Vec::new()).count(&searcher)?, 0);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "unstable"))]
+mod bench {
+
+    use super::TermSetQuery;
+    use crate::collector::Count;
+    use crate::query::BooleanQuery;
+    use crate::schema::{Field, Schema, INDEXED};
+    use crate::{Index, IndexReader, Term};
+    use test::Bencher;
+
+    const NUM_DOCS: u64 = 200_000;
+
+    fn id_reader() -> (IndexReader, Field) {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 100_000_000).unwrap();
+        for id in 0..NUM_DOCS {
+            index_writer.add_document(doc!(id_field => id));
+        }
+        index_writer.commit().unwrap();
+        (index.reader().unwrap(), id_field)
+    }
+
+    /// 10,000 ids spread over the whole id space.
+    fn id_terms(id_field: Field) -> Vec<Term> {
+        (0..NUM_DOCS)
+            .step_by((NUM_DOCS / 10_000) as usize)
+            .map(|id| Term::from_field_u64(id_field, id))
+            .collect()
+    }
+
+    #[bench]
+    fn bench_term_set_query(bench: &mut Bencher) {
+        let (reader, id_field) = id_reader();
+        let searcher = reader.searcher();
+        let query = TermSetQuery::new(id_terms(id_field));
+        bench.iter(|| searcher.search(&query, &Count).unwrap());
+    }
+
+    #[bench]
+    fn bench_boolean_of_term_queries(bench: &mut Bencher) {
+        let (reader, id_field) = id_reader();
+        let searcher = reader.searcher();
+        let query = BooleanQuery::new_multiterms_query(id_terms(id_field));
+        bench.iter(|| searcher.search(&query, &Count).unwrap());
+    }
+}
@@ -4,11 +4,9 @@ use crate::docset::{DocSet, TERMINATED};
 use crate::DocId;
 use common::HasLen;
 
-/// Simulate a `Postings` objects from a `VecPostings`.
-/// `VecPostings` only exist for testing purposes.
+/// `DocSet` over a sorted `Vec` of `DocId`s, without duplicates.
 ///
-/// Term frequencies always return 1.
-/// No positions are returned.
+/// It is also used to simulate a `Postings` objects in tests.
 pub struct VecDocSet {
     doc_ids: Vec<DocId>,
     cursor: usize,
@@ -30,6 +28,14 @@ impl DocSet for VecDocSet {
         self.doc()
     }
 
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.doc() >= target {
+            return self.doc();
+        }
+        self.cursor += self.doc_ids[self.cursor..].partition_point(|&doc| doc < target);
+        self.doc()
+    }
+
     fn doc(&self) -> DocId {
         if self.cursor == self.doc_ids.len() {
             return TERMINATED;
@@ -0,0 +1,173 @@
+use crate::error::TantivyError;
+use crate::query::{AutomatonWeight, Query, QueryVisitor, Weight};
+use crate::schema::Field;
+use crate::Searcher;
+use tantivy_fst::Regex;
+
+/// Characters having a special meaning in a regex pattern.
+const REGEX_META_CHARS: &[char] = &[
+    '\\', '.', '+', '*', '?', '(', ')', '|', '[', ']', '{', '}', '^', '$', '#', '&', '-', '~',
+];
+
+/// A Wildcard Query matches all of the documents
+/// containing a term that matches a wildcard pattern.
+///
+/// In the pattern, `*` matches any sequence of characters, possibly empty,
+/// and `?` matches exactly one character. All of the other characters
+/// are matched as is: the pattern is not processed by the tokenizer of the field.
+///
+/// The literal prefix of the pattern, before its first wildcard, is used
+/// to restrict the part of the term dictionary that is visited.
+/// Patterns starting with a wildcard have to visit all of the terms of the field.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::WildcardQuery;
+/// use tantivy::schema::{Schema, STRING};
+/// use tantivy::{doc, Index};
+///
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let user = schema_builder.add_text_field("user", STRING);
+/// let schema = schema_builder.build();
+/// let index = Index::create_in_ram(schema);
+/// {
+///     let mut index_writer = index.writer(3_000_000)?;
+///     index_writer.add_document(doc!(user => "john"));
+///     index_writer.add_document(doc!(user => "jon"));
+///     index_writer.add_document(doc!(user => "joan"));
+///     index_writer.add_document(doc!(user => "jonathan"));
+///     index_writer.commit()?;
+/// }
+///
+/// let reader = index.reader()?;
+/// let searcher = reader.searcher();
+///
+/// assert_eq!(searcher.search(&WildcardQuery::new(user, "jo*n"), &Count)?, 4);
+/// assert_eq!(searcher.search(&WildcardQuery::new(user, "jo?n"), &Count)?, 2);
+/// Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct WildcardQuery {
+    field: Field,
+    pattern: String,
+}
+
+impl WildcardQuery {
+    /// Creates a new WildcardQuery from a given pattern
+    pub fn new(field: Field, pattern: &str) -> WildcardQuery {
+        WildcardQuery {
+            field,
+            pattern: pattern.to_string(),
+        }
+    }
+
+    /// Field to search over
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    /// The wildcard pattern
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns the part of the pattern preceding its first wildcard.
+    fn literal_prefix(&self) -> &str {
+        let prefix_len = self.pattern.find(['*', '?']).unwrap_or(self.pattern.len());
+        &self.pattern[..prefix_len]
+    }
+
+    fn regex_pattern(&self) -> String {
+        let mut regex_pattern = String::from("(?s)");
+        for c in self.pattern.chars() {
+            match c {
+                '*' => regex_pattern.push_str(".*"),
+                '?' => regex_pattern.push('.'),
+                _ => {
+                    if REGEX_META_CHARS.contains(&c) {
+                        regex_pattern.push('\\');
+                    }
+                    regex_pattern.push(c);
+                }
+            }
+        }
+        regex_pattern
+    }
+
+    fn specialized_weight(&self) -> crate::Result<AutomatonWeight<Regex>> {
+        let regex = Regex::new(&self.regex_pattern())
+            .map_err(|_| TantivyError::InvalidArgument(self.pattern.clone()))?;
+        let automaton_weight = AutomatonWeight::new(self.field, regex);
+        let prefix = self.literal_prefix();
+        if prefix.is_empty() {
+            Ok(automaton_weight)
+        } else {
+            Ok(automaton_weight.with_prefix(prefix.as_bytes().to_vec()))
+        }
+    }
+}
+
+impl Query for WildcardQuery {
+    fn weight(
+        &self,
+        _searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> crate::Result<Box<dyn Weight>> {
+        Ok(Box::new(self.specialized_weight()?))
+    }
+
+    fn visit(&self, visitor: &mut dyn QueryVisitor) {
+        visitor.visit_wildcard_query(self);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::WildcardQuery;
+    use crate::collector::Count;
+    use crate::schema::{Schema, STRING};
+    use crate::Index;
+
+    #[test]
+    pub fn test_wildcard_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let user_field = schema_builder.add_text_field("user", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for user in &[
+            "john", "jon", "joan", "Jon", "jo.n", "jo(n)", "j", "jo\nn", "ajon",
+        ] {
+            index_writer.add_document(doc!(user_field => *user));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count = |pattern: &str| -> crate::Result<usize> {
+            searcher.search(&WildcardQuery::new(user_field, pattern), &Count)
+        };
+        assert_eq!(count("jon")?, 1);
+        assert_eq!(count("jo*n")?, 5);
+        assert_eq!(count("jo?n")?, 4);
+        assert_eq!(count("*on")?, 3);
+        assert_eq!(count("j*")?, 7);
+        assert_eq!(count("*")?, 9);
+        assert_eq!(count("?")?, 1);
+        assert_eq!(count("J*")?, 1);
+        // Regex special characters are matched as is.
+        assert_eq!(count("jo.n")?, 1);
+        assert_eq!(count("jo(*")?, 1);
+        assert_eq!(count("jo[n]")?, 0);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_wildcard_literal_prefix() {
+        let field = crate::schema::Field::from_field_id(0);
+        assert_eq!(WildcardQuery::new(field, "jo*n").literal_prefix(), "jo");
+        assert_eq!(WildcardQuery::new(field, "jo?n*").literal_prefix(), "jo");
+        assert_eq!(WildcardQuery::new(field, "*jon").literal_prefix(), "");
+        assert_eq!(WildcardQuery::new(field, "jon").literal_prefix(), "jon");
+    }
+}
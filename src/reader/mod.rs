@@ -1,15 +1,24 @@
 mod pool;
+mod warming;
 
 pub use self::pool::LeasedItem;
 use self::pool::Pool;
-use crate::core::Segment;
+pub use self::warming::{FastFieldWarmer, Warmer};
 use crate::directory::WatchHandle;
 use crate::directory::META_LOCK;
 use crate::directory::{Directory, WatchCallback};
+use crate::error::DataCorruption;
+use crate::query::FilterCache;
+use crate::store::{StoreReader, DOCSTORE_CACHE_CAPACITY};
 use crate::Index;
 use crate::Searcher;
+use crate::SegmentId;
+use crate::SegmentMeta;
 use crate::SegmentReader;
-use std::sync::Arc;
+use crate::TantivyError;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::Duration;
 use std::{convert::TryInto, io};
 
 /// Defines when a new version of the index should be reloaded.
@@ -29,6 +38,25 @@ pub enum ReloadPolicy {
     OnCommit, // TODO add NEAR_REAL_TIME(target_ms)
 }
 
+/// Defines how the reader handles the segments which cannot be opened.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SegmentLoadPolicy {
+    /// Loading the index fails if one of its segments cannot be opened. This is the default.
+    #[default]
+    Strict,
+    /// The segments whose files are corrupted, or which cannot be opened, are excluded
+    /// from the searchers, and quarantined.
+    ///
+    /// The checksums of the files of a segment are verified when the segment is first
+    /// loaded, which requires to read all of them.
+    /// The quarantined segments are listed by
+    /// [`IndexReader::quarantined_segments`](./struct.IndexReader.html#method.quarantined_segments).
+    /// They are kept in the index, so that they can be repaired: an `IndexWriter` created
+    /// from the same `Index` does not merge them, does not apply the deletes to them,
+    /// and does not garbage collect their files.
+    SkipCorrupted,
+}
+
 /// `IndexReader` builder
 ///
 /// It makes it possible to set the following values.
@@ -40,10 +68,31 @@ pub enum ReloadPolicy {
 /// - `reload_policy` (by default `ReloadPolicy::OnCommit`):
 ///
 ///   See [`ReloadPolicy`](./enum.ReloadPolicy.html) for more details.
+/// - `warmers` (by default, none):
+///
+///   See [`Warmer`](./trait.Warmer.html) for more details.
+/// - `filter_cache` (by default, none):
+///
+///   See [`IndexReaderBuilder::filter_cache`](#method.filter_cache) for more details.
+/// - `doc_store_cache_capacity` (by default, 100 blocks):
+///
+///   See [`IndexReaderBuilder::doc_store_cache_capacity`](#method.doc_store_cache_capacity)
+///   for more details.
+/// - `segment_load_policy` (by default `SegmentLoadPolicy::Strict`):
+///
+///   See [`SegmentLoadPolicy`](./enum.SegmentLoadPolicy.html) for more details.
+/// - `reload_debounce` (by default, none):
+///
+///   See [`IndexReaderBuilder::reload_debounce`](#method.reload_debounce) for more details.
 #[derive(Clone)]
 pub struct IndexReaderBuilder {
     num_searchers: usize,
     reload_policy: ReloadPolicy,
+    warmers: Vec<Weak<dyn Warmer>>,
+    filter_cache_num_bytes: Option<usize>,
+    doc_store_cache_capacity: usize,
+    segment_load_policy: SegmentLoadPolicy,
+    reload_debounce: Option<Duration>,
     index: Index,
 }
 
@@ -52,6 +101,11 @@ impl IndexReaderBuilder {
         IndexReaderBuilder {
             num_searchers: num_cpus::get(),
             reload_policy: ReloadPolicy::OnCommit,
+            warmers: Vec::new(),
+            filter_cache_num_bytes: None,
+            doc_store_cache_capacity: DOCSTORE_CACHE_CAPACITY,
+            segment_load_policy: SegmentLoadPolicy::Strict,
+            reload_debounce: None,
             index,
         }
     }
@@ -66,6 +120,15 @@ impl IndexReaderBuilder {
             index: self.index,
             num_searchers: self.num_searchers,
             searcher_pool: Pool::new(),
+            warmers: self.warmers,
+            warmed_segment_ids: Mutex::new(HashSet::new()),
+            filter_cache: self
+                .filter_cache_num_bytes
+                .map(|num_bytes| Arc::new(FilterCache::new(num_bytes))),
+            doc_store_cache_capacity: self.doc_store_cache_capacity,
+            store_readers: Mutex::new(HashMap::new()),
+            segment_load_policy: self.segment_load_policy,
+            quarantined_segments: Mutex::default(),
         };
         inner_reader.reload()?;
         let inner_reader_arc = Arc::new(inner_reader);
@@ -85,10 +148,11 @@ impl IndexReaderBuilder {
                         );
                     }
                 };
-                let watch_handle = inner_reader_arc
-                    .index
-                    .directory()
-                    .watch(WatchCallback::new(callback))?;
+                let mut watch_callback = WatchCallback::new(callback);
+                if let Some(interval) = self.reload_debounce {
+                    watch_callback = watch_callback.debounced(interval);
+                }
+                let watch_handle = inner_reader_arc.index.directory().watch(watch_callback)?;
                 watch_handle_opt = Some(watch_handle);
             }
         }
@@ -111,6 +175,63 @@ impl IndexReaderBuilder {
         self.num_searchers = num_searchers;
         self
     }
+
+    /// Sets the warmers run on the new segments before each generation of searchers is
+    /// published.
+    ///
+    /// The reader only keeps weak references to the warmers: the warmers that were dropped
+    /// are not run anymore.
+    pub fn warmers(mut self, warmers: Vec<Weak<dyn Warmer>>) -> IndexReaderBuilder {
+        self.warmers = warmers;
+        self
+    }
+
+    /// Enables the cache of the filters, keeping at most `num_bytes` bytes of results.
+    ///
+    /// The documents matched by the `Filter` and `MustNot` clauses of a
+    /// [`BooleanQuery`](../query/struct.BooleanQuery.html) are then kept as a bitset for
+    /// each segment, and reused by the next queries having the same clause, as identified
+    /// by its [cache key](../query/trait.Query.html#method.cache_key). The least recently
+    /// used bitsets are evicted first.
+    ///
+    /// The cache is shared by all the generations of searchers of the reader. A segment
+    /// with new deletes does not reuse the bitsets computed before these deletes.
+    pub fn filter_cache(mut self, num_bytes: usize) -> IndexReaderBuilder {
+        self.filter_cache_num_bytes = Some(num_bytes);
+        self
+    }
+
+    /// Sets the number of decompressed blocks of the doc store cached for each segment.
+    ///
+    /// The store reader of a segment, and its cache, is shared by all the searchers of
+    /// the reader, including the ones of the next generations. A capacity of `0`
+    /// disables the cache.
+    /// See [`Searcher::doc_store_cache_stats`](./struct.Searcher.html#method.doc_store_cache_stats).
+    pub fn doc_store_cache_capacity(mut self, num_blocks: usize) -> IndexReaderBuilder {
+        self.doc_store_cache_capacity = num_blocks;
+        self
+    }
+
+    /// Sets the segment load policy.
+    ///
+    /// See [`SegmentLoadPolicy`](./enum.SegmentLoadPolicy.html) for more details.
+    pub fn segment_load_policy(
+        mut self,
+        segment_load_policy: SegmentLoadPolicy,
+    ) -> IndexReaderBuilder {
+        self.segment_load_policy = segment_load_policy;
+        self
+    }
+
+    /// Reloads the index at most once per `interval` with the `OnCommit` reload policy.
+    ///
+    /// The commits detected less than `interval` after a reload are coalesced into a
+    /// single reload, made once the `interval` has elapsed: the last commit is always
+    /// loaded. See [`WatchCallback::debounced`](../directory/struct.WatchCallback.html#method.debounced).
+    pub fn reload_debounce(mut self, interval: Duration) -> IndexReaderBuilder {
+        self.reload_debounce = Some(interval);
+        self
+    }
 }
 
 impl TryInto<IndexReader> for IndexReaderBuilder {
@@ -124,34 +245,188 @@ impl TryInto<IndexReader> for IndexReaderBuilder {
 struct InnerIndexReader {
     num_searchers: usize,
     searcher_pool: Pool<Searcher>,
+    warmers: Vec<Weak<dyn Warmer>>,
+    // Segments of the last published generation. The lock also serializes the reloads.
+    warmed_segment_ids: Mutex<HashSet<SegmentId>>,
+    filter_cache: Option<Arc<FilterCache>>,
+    doc_store_cache_capacity: usize,
+    // Store readers of the last published generation.
+    store_readers: Mutex<HashMap<SegmentId, StoreReader>>,
+    segment_load_policy: SegmentLoadPolicy,
+    // Segments of the last published generation which could not be opened.
+    quarantined_segments: Mutex<Arc<Vec<(SegmentId, TantivyError)>>>,
     index: Index,
 }
 
 impl InnerIndexReader {
+    fn lock_warmed_segment_ids(&self) -> MutexGuard<HashSet<SegmentId>> {
+        // The set is only updated once all the warmers succeeded, so it is consistent even
+        // if a warmer panicked.
+        self.warmed_segment_ids
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the store readers of `segment_readers`, reusing the ones of the last
+    /// published generation so that their cache is kept.
+    fn open_store_readers(
+        &self,
+        segment_readers: &[SegmentReader],
+    ) -> io::Result<Vec<StoreReader>> {
+        let store_readers = self
+            .store_readers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        segment_readers
+            .iter()
+            .map(|segment_reader| {
+                if let Some(store_reader) = store_readers.get(&segment_reader.segment_id()) {
+                    Ok(store_reader.clone())
+                } else {
+                    segment_reader
+                        .get_store_reader_with_cache_capacity(self.doc_store_cache_capacity)
+                }
+            })
+            .collect()
+    }
+
+    /// Opens the segment, after verifying the checksums of its files if it was not
+    /// loaded by the last published generation.
+    fn open_segment_checked(
+        &self,
+        segment_meta: SegmentMeta,
+        warmed_segment_ids: &HashSet<SegmentId>,
+    ) -> crate::Result<SegmentReader> {
+        if !warmed_segment_ids.contains(&segment_meta.id()) {
+            let directory = self.index.directory();
+            for path in segment_meta.list_files() {
+                if directory.exists(&path)? && !directory.validate_checksum(&path)? {
+                    return Err(DataCorruption::new(path, "Checksum mismatch".to_string()).into());
+                }
+            }
+        }
+        SegmentReader::open(&self.index.segment(segment_meta))
+    }
+
+    /// Runs the warmers on the segments that are not in `warmed_segment_ids`.
+    fn warm(
+        &self,
+        segment_readers: &[SegmentReader],
+        store_readers: &[StoreReader],
+        warmed_segment_ids: &HashSet<SegmentId>,
+        payload: &Option<String>,
+    ) -> crate::Result<()> {
+        let warmers: Vec<Arc<dyn Warmer>> = self.warmers.iter().flat_map(Weak::upgrade).collect();
+        if warmers.is_empty() {
+            return Ok(());
+        }
+        let (new_segment_readers, new_store_readers): (Vec<SegmentReader>, Vec<StoreReader>) =
+            segment_readers
+                .iter()
+                .zip(store_readers)
+                .filter(|(segment_reader, _)| {
+                    !warmed_segment_ids.contains(&segment_reader.segment_id())
+                })
+                .map(|(segment_reader, store_reader)| {
+                    (segment_reader.clone(), store_reader.clone())
+                })
+                .unzip();
+        if new_segment_readers.is_empty() {
+            return Ok(());
+        }
+        let searcher = Searcher::with_store_readers(
+            self.index.schema(),
+            self.index.clone(),
+            new_segment_readers,
+            new_store_readers,
+            payload.clone(),
+        )
+        .with_filter_cache(self.filter_cache.clone());
+        for warmer in warmers {
+            warmer.warm(&searcher)?;
+        }
+        Ok(())
+    }
+
     fn reload(&self) -> crate::Result<()> {
-        let segment_readers: Vec<SegmentReader> = {
+        let mut warmed_segment_ids = self.lock_warmed_segment_ids();
+        let mut quarantined_segments: Vec<(SegmentMeta, TantivyError)> = Vec::new();
+        let (segment_readers, payload): (Vec<SegmentReader>, Option<String>) = {
             let _meta_lock = self.index.directory().acquire_lock(&META_LOCK)?;
-            let searchable_segments = self.searchable_segments()?;
-            searchable_segments
-                .iter()
-                .map(SegmentReader::open)
-                .collect::<crate::Result<_>>()?
+            let index_meta = self.index.load_metas()?;
+            let segment_readers = match self.segment_load_policy {
+                SegmentLoadPolicy::Strict => index_meta
+                    .segments
+                    .into_iter()
+                    .map(|segment_meta| SegmentReader::open(&self.index.segment(segment_meta)))
+                    .collect::<crate::Result<_>>()?,
+                SegmentLoadPolicy::SkipCorrupted => {
+                    let mut segment_readers = Vec::new();
+                    for segment_meta in index_meta.segments {
+                        match self.open_segment_checked(segment_meta.clone(), &warmed_segment_ids) {
+                            Ok(segment_reader) => segment_readers.push(segment_reader),
+                            Err(err) => {
+                                warn!(
+                                    "Segment {:?} is quarantined, as it could not be opened: {:?}",
+                                    segment_meta.id(),
+                                    err
+                                );
+                                quarantined_segments.push((segment_meta, err));
+                            }
+                        }
+                    }
+                    segment_readers
+                }
+            };
+            (segment_readers, index_meta.payload)
         };
+        let store_readers = self.open_store_readers(&segment_readers)?;
+        self.warm(
+            &segment_readers,
+            &store_readers,
+            &warmed_segment_ids,
+            &payload,
+        )?;
         let schema = self.index.schema();
         let searchers: Vec<Searcher> = std::iter::repeat_with(|| {
-            Searcher::new(schema.clone(), self.index.clone(), segment_readers.clone())
+            Searcher::with_store_readers(
+                schema.clone(),
+                self.index.clone(),
+                segment_readers.clone(),
+                store_readers.clone(),
+                payload.clone(),
+            )
+            .with_filter_cache(self.filter_cache.clone())
         })
         .take(self.num_searchers)
-        .collect::<io::Result<_>>()?;
+        .collect();
+        *warmed_segment_ids = segment_readers
+            .iter()
+            .map(|segment_reader| segment_reader.segment_id())
+            .collect();
+        *self
+            .store_readers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = segment_readers
+            .iter()
+            .map(SegmentReader::segment_id)
+            .zip(store_readers)
+            .collect();
+        if self.segment_load_policy == SegmentLoadPolicy::SkipCorrupted {
+            let (segment_metas, errors): (Vec<SegmentMeta>, Vec<TantivyError>) =
+                quarantined_segments.into_iter().unzip();
+            let segment_ids = segment_metas.iter().map(SegmentMeta::id);
+            *self
+                .quarantined_segments
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) =
+                Arc::new(segment_ids.zip(errors).collect());
+            self.index.set_quarantined_segments(segment_metas);
+        }
         self.searcher_pool.publish_new_generation(searchers);
         Ok(())
     }
 
-    /// Returns the list of segments that are searchable
-    fn searchable_segments(&self) -> crate::Result<Vec<Segment>> {
-        self.index.searchable_segments()
-    }
-
     fn searcher(&self) -> LeasedItem<Searcher> {
         self.searcher_pool.acquire()
     }
@@ -185,6 +460,9 @@ impl IndexReader {
     ///
     /// This automatic reload can take 10s of milliseconds to kick in however, and in unit tests
     /// it can be nice to deterministically force the reload of searchers.
+    ///
+    /// If one of the [warmers](./trait.Warmer.html) fails, its error is returned and the
+    /// searchers of the previous generation are kept.
     pub fn reload(&self) -> crate::Result<()> {
         self.inner.reload()
     }
@@ -202,4 +480,17 @@ impl IndexReader {
     pub fn searcher(&self) -> LeasedItem<Searcher> {
         self.inner.searcher()
     }
+
+    /// Returns the segments excluded from the searchers of the last loaded version, with
+    /// the error which occurred when opening them.
+    ///
+    /// The list is always empty with the `SegmentLoadPolicy::Strict` policy.
+    /// See [`SegmentLoadPolicy`](./enum.SegmentLoadPolicy.html) for more details.
+    pub fn quarantined_segments(&self) -> Arc<Vec<(SegmentId, TantivyError)>> {
+        self.inner
+            .quarantined_segments
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
 }
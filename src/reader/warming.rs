@@ -0,0 +1,167 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::schema::Field;
+use crate::Searcher;
+
+const PAGE_SIZE: usize = 4_096;
+
+/// Warms the segments of a new searcher generation before it is published.
+///
+/// The warmers of an [`IndexReader`](./struct.IndexReader.html) are registered with
+/// [`IndexReaderBuilder::warmers`](./struct.IndexReaderBuilder.html#method.warmers).
+/// On each reload, they are given a searcher over the segments that were not in the
+/// previous generation, so that the first queries over the new segments do not pay for
+/// loading their data.
+pub trait Warmer: Send + Sync {
+    /// Warms the segments of `searcher`.
+    ///
+    /// An error aborts the reload: the searchers of the previous generation remain
+    /// published, and the new segments are warmed again by the next reload.
+    fn warm(&self, searcher: &Searcher) -> crate::Result<()>;
+}
+
+/// Warmer reading the data of some fast fields, so that it is loaded in memory, or in
+/// the page cache for memory mapped indexes.
+#[derive(Debug)]
+pub struct FastFieldWarmer {
+    fields: Vec<Field>,
+    num_warmed_bytes: AtomicU64,
+}
+
+impl FastFieldWarmer {
+    /// Creates a warmer of the fast `fields`.
+    pub fn new(fields: Vec<Field>) -> FastFieldWarmer {
+        FastFieldWarmer {
+            fields,
+            num_warmed_bytes: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the number of bytes of fast field data read by the warmer.
+    pub fn num_warmed_bytes(&self) -> u64 {
+        self.num_warmed_bytes.load(Ordering::Relaxed)
+    }
+}
+
+/// Reads a byte of each page of `bytes`.
+fn touch_pages(bytes: &[u8]) {
+    for page in bytes.chunks(PAGE_SIZE) {
+        // Safety: the pointer comes from a reference, so it is aligned and valid.
+        // The volatile read prevents the compiler from optimizing it away.
+        unsafe {
+            std::ptr::read_volatile(&page[0]);
+        }
+    }
+}
+
+impl Warmer for FastFieldWarmer {
+    fn warm(&self, searcher: &Searcher) -> crate::Result<()> {
+        for segment_reader in searcher.segment_readers() {
+            let fast_fields = segment_reader.fast_fields();
+            for &field in &self.fields {
+                for file_slice in fast_fields.all_fast_field_data(field) {
+                    let bytes = file_slice.read_bytes()?;
+                    touch_pages(bytes.as_slice());
+                    self.num_warmed_bytes
+                        .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex, Weak};
+
+    use super::{FastFieldWarmer, Warmer};
+    use crate::schema::{Schema, FAST};
+    use crate::{doc, Index, ReloadPolicy, Searcher, SegmentId, TantivyError};
+
+    #[derive(Default)]
+    struct RecordingWarmer {
+        warmed_segment_ids: Mutex<Vec<Vec<SegmentId>>>,
+        num_failures_left: AtomicUsize,
+    }
+
+    impl Warmer for RecordingWarmer {
+        fn warm(&self, searcher: &Searcher) -> crate::Result<()> {
+            if self.num_failures_left.load(Ordering::SeqCst) > 0 {
+                self.num_failures_left.fetch_sub(1, Ordering::SeqCst);
+                return Err(TantivyError::SystemError("warming failed".to_string()));
+            }
+            let mut segment_ids: Vec<SegmentId> = searcher
+                .segment_readers()
+                .iter()
+                .map(|segment_reader| segment_reader.segment_id())
+                .collect();
+            segment_ids.sort();
+            self.warmed_segment_ids.lock().unwrap().push(segment_ids);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_warmers_only_warm_new_segments() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let val = schema_builder.add_u64_field("val", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(val => 1u64));
+        index_writer.commit()?;
+
+        let warmer = Arc::new(RecordingWarmer::default());
+        let fast_field_warmer = Arc::new(FastFieldWarmer::new(vec![val]));
+        let warmers: Vec<Weak<dyn Warmer>> = vec![
+            Arc::downgrade(&warmer) as Weak<dyn Warmer>,
+            Arc::downgrade(&fast_field_warmer) as Weak<dyn Warmer>,
+        ];
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .warmers(warmers)
+            .try_into()?;
+        let first_segment_ids = index.searchable_segment_ids()?;
+        assert_eq!(
+            *warmer.warmed_segment_ids.lock().unwrap(),
+            vec![first_segment_ids.clone()]
+        );
+        let num_warmed_bytes = fast_field_warmer.num_warmed_bytes();
+        assert!(num_warmed_bytes > 0);
+
+        // nothing new to warm.
+        reader.reload()?;
+        assert_eq!(warmer.warmed_segment_ids.lock().unwrap().len(), 1);
+
+        index_writer.add_document(doc!(val => 2u64));
+        index_writer.commit()?;
+        warmer.num_failures_left.store(1, Ordering::SeqCst);
+        assert!(reader.reload().is_err());
+        // the previous generation is still searchable.
+        assert_eq!(reader.searcher().num_docs(), 1);
+
+        reader.reload()?;
+        assert_eq!(reader.searcher().num_docs(), 2);
+        let new_segment_ids: Vec<SegmentId> = index
+            .searchable_segment_ids()?
+            .into_iter()
+            .filter(|segment_id| !first_segment_ids.contains(segment_id))
+            .collect();
+        assert_eq!(new_segment_ids.len(), 1);
+        assert_eq!(
+            warmer.warmed_segment_ids.lock().unwrap()[1],
+            new_segment_ids
+        );
+        assert!(fast_field_warmer.num_warmed_bytes() > num_warmed_bytes);
+
+        // dropped warmers are no longer called.
+        drop(warmer);
+        index_writer.add_document(doc!(val => 3u64));
+        index_writer.commit()?;
+        reader.reload()?;
+        assert_eq!(reader.searcher().num_docs(), 3);
+        Ok(())
+    }
+}
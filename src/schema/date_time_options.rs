@@ -0,0 +1,264 @@
+use crate::schema::flags::{FastFlag, IndexedFlag, SchemaFlagList, StoredFlag};
+use crate::schema::{Cardinality, FastFieldCodecType, IntOptions};
+use crate::DateTime;
+use serde::{Deserialize, Serialize};
+use std::ops::BitOr;
+
+/// Precision at which the values of a date field are recorded in its fast field column.
+///
+/// The values are truncated to the precision before being written to the fast field,
+/// which makes the column more compact. The doc store keeps the original value, and the
+/// inverted index records dates at the second.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DatePrecision {
+    /// Seconds precision.
+    #[default]
+    #[serde(rename = "seconds")]
+    Seconds,
+    /// Milliseconds precision.
+    #[serde(rename = "milliseconds")]
+    Milliseconds,
+    /// Microseconds precision.
+    #[serde(rename = "microseconds")]
+    Microseconds,
+}
+
+impl DatePrecision {
+    fn is_seconds(&self) -> bool {
+        *self == DatePrecision::Seconds
+    }
+
+    /// Number of microseconds in one unit of the precision.
+    pub(crate) fn micros_per_unit(self) -> i64 {
+        match self {
+            DatePrecision::Seconds => 1_000_000,
+            DatePrecision::Milliseconds => 1_000,
+            DatePrecision::Microseconds => 1,
+        }
+    }
+
+    /// Returns the timestamp of `date_time`, truncated to and expressed in the unit of
+    /// the precision.
+    pub(crate) fn truncated_timestamp(self, date_time: &DateTime) -> i64 {
+        match self {
+            DatePrecision::Seconds => date_time.timestamp(),
+            DatePrecision::Milliseconds => date_time.timestamp_millis(),
+            DatePrecision::Microseconds => {
+                date_time.timestamp() * 1_000_000 + i64::from(date_time.timestamp_subsec_micros())
+            }
+        }
+    }
+}
+
+/// Define how a date field should be handled by tantivy.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateOptions {
+    indexed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fast: Option<Cardinality>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fast_codec: Option<FastFieldCodecType>,
+    stored: bool,
+    #[serde(default, skip_serializing_if = "DatePrecision::is_seconds")]
+    precision: DatePrecision,
+}
+
+impl DateOptions {
+    /// Returns true iff the value is stored.
+    pub fn is_stored(&self) -> bool {
+        self.stored
+    }
+
+    /// Returns true iff the value is indexed and therefore searchable.
+    pub fn is_indexed(&self) -> bool {
+        self.indexed
+    }
+
+    /// Returns true iff the value is a fast field.
+    pub fn is_fast(&self) -> bool {
+        self.fast.is_some()
+    }
+
+    /// Set the field as stored.
+    ///
+    /// Only the fields that are set as *stored* are
+    /// persisted into the Tantivy's store.
+    pub fn set_stored(mut self) -> DateOptions {
+        self.stored = true;
+        self
+    }
+
+    /// Set the field as indexed.
+    ///
+    /// Setting a date as indexed will generate a posting list for each
+    /// value taken by the date, at the second.
+    ///
+    /// This is required for the field to be searchable.
+    pub fn set_indexed(mut self) -> DateOptions {
+        self.indexed = true;
+        self
+    }
+
+    /// Set the field as a fast field.
+    ///
+    /// Fast fields are designed for random access.
+    /// Access time are similar to a random lookup in an array.
+    /// If more than one value is associated to a single-valued fast field,
+    /// only the last one is kept.
+    pub fn set_fast(mut self, cardinality: Cardinality) -> DateOptions {
+        self.fast = Some(cardinality);
+        self
+    }
+
+    /// Returns the cardinality of the fastfield.
+    ///
+    /// If the field has not been declared as a fastfield, then
+    /// the method returns None.
+    pub fn get_fastfield_cardinality(&self) -> Option<Cardinality> {
+        self.fast
+    }
+
+    /// Sets the codec of the single-valued fast field.
+    ///
+    /// See [`IntOptions::set_fast_codec`](./struct.IntOptions.html#method.set_fast_codec).
+    pub fn set_fast_codec(mut self, codec_type: FastFieldCodecType) -> DateOptions {
+        self.fast_codec = Some(codec_type);
+        self
+    }
+
+    /// Returns the codec set for the fast field, if any.
+    pub fn get_fast_codec(&self) -> Option<FastFieldCodecType> {
+        self.fast_codec
+    }
+
+    /// Sets the precision of the values of the fast field.
+    ///
+    /// Defaults to `DatePrecision::Seconds`.
+    pub fn set_precision(mut self, precision: DatePrecision) -> DateOptions {
+        self.precision = precision;
+        self
+    }
+
+    /// Returns the precision of the values of the fast field.
+    pub fn get_precision(&self) -> DatePrecision {
+        self.precision
+    }
+}
+
+impl From<IntOptions> for DateOptions {
+    fn from(int_options: IntOptions) -> DateOptions {
+        DateOptions {
+            indexed: int_options.is_indexed(),
+            fast: int_options.get_fastfield_cardinality(),
+            fast_codec: int_options.get_fast_codec(),
+            stored: int_options.is_stored(),
+            precision: DatePrecision::default(),
+        }
+    }
+}
+
+impl From<()> for DateOptions {
+    fn from(_: ()) -> DateOptions {
+        DateOptions::default()
+    }
+}
+
+impl From<FastFlag> for DateOptions {
+    fn from(flag: FastFlag) -> DateOptions {
+        DateOptions::from(IntOptions::from(flag))
+    }
+}
+
+impl From<StoredFlag> for DateOptions {
+    fn from(flag: StoredFlag) -> DateOptions {
+        DateOptions::from(IntOptions::from(flag))
+    }
+}
+
+impl From<IndexedFlag> for DateOptions {
+    fn from(flag: IndexedFlag) -> DateOptions {
+        DateOptions::from(IntOptions::from(flag))
+    }
+}
+
+impl<T: Into<DateOptions>> BitOr<T> for DateOptions {
+    type Output = DateOptions;
+
+    fn bitor(self, other: T) -> DateOptions {
+        let other = other.into();
+        DateOptions {
+            indexed: self.indexed | other.indexed,
+            stored: self.stored | other.stored,
+            fast: self.fast.or(other.fast),
+            fast_codec: self.fast_codec.or(other.fast_codec),
+            precision: if self.precision.is_seconds() {
+                other.precision
+            } else {
+                self.precision
+            },
+        }
+    }
+}
+
+impl<Head, Tail> From<SchemaFlagList<Head, Tail>> for DateOptions
+where
+    Head: Clone,
+    Tail: Clone,
+    IntOptions: From<SchemaFlagList<Head, Tail>>,
+{
+    fn from(head_tail: SchemaFlagList<Head, Tail>) -> Self {
+        DateOptions::from(IntOptions::from(head_tail))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_date_options() {
+        let options = DateOptions::from(FAST | STORED);
+        assert!(options.is_stored());
+        assert!(!options.is_indexed());
+        assert_eq!(
+            options.get_fastfield_cardinality(),
+            Some(Cardinality::SingleValue)
+        );
+        assert_eq!(options.get_precision(), DatePrecision::Seconds);
+        let options = DateOptions::default().set_precision(DatePrecision::Milliseconds) | INDEXED;
+        assert!(options.is_indexed());
+        assert_eq!(options.get_precision(), DatePrecision::Milliseconds);
+    }
+
+    #[test]
+    fn test_date_options_serialization() {
+        let options: DateOptions =
+            serde_json::from_str(r#"{"indexed":true,"fast":"single","stored":false}"#).unwrap();
+        assert_eq!(options.get_precision(), DatePrecision::Seconds);
+        let options = options.set_precision(DatePrecision::Microseconds);
+        let options_json = serde_json::to_string(&options).unwrap();
+        assert_eq!(
+            options_json,
+            r#"{"indexed":true,"fast":"single","stored":false,"precision":"microseconds"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<DateOptions>(&options_json).unwrap(),
+            options
+        );
+    }
+
+    #[test]
+    fn test_truncated_timestamp() {
+        let date_time = Utc.timestamp(-2, 123_456_789);
+        assert_eq!(DatePrecision::Seconds.truncated_timestamp(&date_time), -2);
+        assert_eq!(
+            DatePrecision::Milliseconds.truncated_timestamp(&date_time),
+            -1_877
+        );
+        assert_eq!(
+            DatePrecision::Microseconds.truncated_timestamp(&date_time),
+            -1_876_544
+        );
+    }
+}
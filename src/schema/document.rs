@@ -1,10 +1,12 @@
 use super::*;
+use crate::schema::value::{ip_addr_to_ipv6, skip_value};
 use crate::tokenizer::PreTokenizedString;
 use crate::DateTime;
 use common::BinarySerializable;
 use common::VInt;
 use std::io::{self, Read, Write};
 use std::mem;
+use std::net::IpAddr;
 
 /// Tantivy's Document is the object that can
 /// be indexed and then searched for.
@@ -103,6 +105,21 @@ impl Document {
         self.add(FieldValue::new(field, Value::F64(value)));
     }
 
+    /// Add a bool field
+    pub fn add_bool(&mut self, field: Field, value: bool) {
+        self.add(FieldValue::new(field, Value::Bool(value)));
+    }
+
+    /// Add an ip address field
+    ///
+    /// IPv4 addresses are mapped into the IPv6 space.
+    pub fn add_ip_addr<T: Into<IpAddr>>(&mut self, field: Field, value: T) {
+        self.add(FieldValue::new(
+            field,
+            Value::IpAddr(ip_addr_to_ipv6(value.into())),
+        ));
+    }
+
     /// Add a date field
     pub fn add_date(&mut self, field: Field, value: &DateTime) {
         self.add(FieldValue::new(field, Value::Date(*value)));
@@ -113,6 +130,15 @@ impl Document {
         self.add(FieldValue::new(field, Value::Bytes(value.into())))
     }
 
+    /// Add a json object field
+    pub fn add_json_object(
+        &mut self,
+        field: Field,
+        json_object: serde_json::Map<String, serde_json::Value>,
+    ) {
+        self.add(FieldValue::new(field, Value::JsonObject(json_object)))
+    }
+
     /// Add a field value
     pub fn add(&mut self, field_value: FieldValue) {
         self.field_values.push(field_value);
@@ -208,11 +234,30 @@ impl BinarySerializable for Document {
     }
 }
 
+impl Document {
+    /// Deserializes the values of `fields` of a document serialized in `bytes`, skipping
+    /// over the values of the other fields.
+    pub(crate) fn deserialize_fields(bytes: &mut &[u8], fields: &[Field]) -> io::Result<Document> {
+        let num_field_values = VInt::deserialize(bytes)?.val() as usize;
+        let mut field_values = Vec::new();
+        for _ in 0..num_field_values {
+            let field = Field::deserialize(bytes)?;
+            if fields.contains(&field) {
+                field_values.push(FieldValue::new(field, Value::deserialize(bytes)?));
+            } else {
+                skip_value(bytes)?;
+            }
+        }
+        Ok(Document::from(field_values))
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use crate::schema::*;
     use crate::tokenizer::{PreTokenizedString, Token};
+    use common::BinarySerializable;
 
     #[test]
     fn test_doc() {
@@ -237,6 +282,7 @@ mod tests {
                 position: 0,
                 text: String::from("A"),
                 position_length: 1,
+                payload: 0,
             }],
         };
 
@@ -256,4 +302,65 @@ mod tests {
             _ => panic!("Incorrect variant of Value"),
         }
     }
+    #[test]
+    fn test_deserialize_fields() {
+        let pre_tokenized_text = PreTokenizedString {
+            text: String::from("A"),
+            tokens: Vec::new(),
+        };
+        let mut json_object = serde_json::Map::new();
+        json_object.insert("key".to_string(), serde_json::Value::from(3));
+        let values = vec![
+            Value::from("text"),
+            Value::PreTokStr(pre_tokenized_text),
+            Value::U64(1),
+            Value::I64(-1),
+            Value::F64(0.5),
+            Value::Bool(true),
+            Value::IpAddr("::1".parse().unwrap()),
+            Value::Date("2021-10-14T12:00:00Z".parse().unwrap()),
+            Value::Date("2021-10-14T12:00:00.5Z".parse().unwrap()),
+            Value::Facet(Facet::from("/a/b")),
+            Value::Bytes(vec![1, 2, 3]),
+            Value::JsonObject(json_object),
+        ];
+        // each value is followed by a marker, read to check that the value was skipped
+        // entirely.
+        let mut doc = Document::default();
+        for (i, value) in values.iter().enumerate() {
+            doc.add(FieldValue::new(Field::from_field_id(0), value.clone()));
+            doc.add_u64(Field::from_field_id(i as u32 + 1), i as u64);
+        }
+        let mut bytes = Vec::new();
+        doc.serialize(&mut bytes).unwrap();
+
+        let marker_fields: Vec<Field> = (1..=values.len() as u32)
+            .map(Field::from_field_id)
+            .collect();
+        let markers = Document::deserialize_fields(&mut &bytes[..], &marker_fields).unwrap();
+        let expected_markers: Vec<Value> = (0..values.len() as u64).map(Value::U64).collect();
+        let marker_values: Vec<Value> = markers
+            .field_values()
+            .iter()
+            .map(|field_value| field_value.value().clone())
+            .collect();
+        assert_eq!(marker_values, expected_markers);
+
+        let skipped_markers =
+            Document::deserialize_fields(&mut &bytes[..], &[Field::from_field_id(0)]).unwrap();
+        assert_eq!(
+            skipped_markers.get_all(Field::from_field_id(0)).count(),
+            values.len()
+        );
+        let skipped_markers_values: Vec<Value> = skipped_markers
+            .field_values()
+            .iter()
+            .map(|field_value| field_value.value().clone())
+            .collect();
+        assert_eq!(skipped_markers_values, values);
+
+        assert!(
+            Document::deserialize_fields(&mut &bytes[..bytes.len() - 1], &marker_fields).is_err()
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use crate::schema::FacetOptions;
+use crate::schema::JsonObjectOptions;
 use crate::schema::TextOptions;
-use crate::schema::{is_valid_field_name, IntOptions};
+use crate::schema::{is_valid_field_name, Cardinality, DateOptions, IntOptions};
 
 use crate::schema::bytes_options::BytesOptions;
 use crate::schema::FieldType;
@@ -72,9 +73,39 @@ impl FieldEntry {
         }
     }
 
+    /// Creates a new bool field entry in the schema, given
+    /// a name, and some options.
+    pub fn new_bool(field_name: String, field_type: IntOptions) -> FieldEntry {
+        assert!(is_valid_field_name(&field_name));
+        FieldEntry {
+            name: field_name,
+            field_type: FieldType::Bool(field_type),
+        }
+    }
+
+    /// Creates a new ip address field entry in the schema, given
+    /// a name, and some options.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the options define a multivalued fast field,
+    /// as ip address fast fields are single valued.
+    pub fn new_ip_addr(field_name: String, field_type: IntOptions) -> FieldEntry {
+        assert!(is_valid_field_name(&field_name));
+        assert_ne!(
+            field_type.get_fastfield_cardinality(),
+            Some(Cardinality::MultiValues),
+            "ip address fast fields are single valued"
+        );
+        FieldEntry {
+            name: field_name,
+            field_type: FieldType::IpAddr(field_type),
+        }
+    }
+
     /// Creates a new date field entry in the schema, given
     /// a name, and some options.
-    pub fn new_date(field_name: String, field_type: IntOptions) -> FieldEntry {
+    pub fn new_date(field_name: String, field_type: DateOptions) -> FieldEntry {
         assert!(is_valid_field_name(&field_name));
         FieldEntry {
             name: field_name,
@@ -99,6 +130,15 @@ impl FieldEntry {
         }
     }
 
+    /// Creates a field entry for a json object field
+    pub fn new_json(field_name: String, json_object_options: JsonObjectOptions) -> FieldEntry {
+        assert!(is_valid_field_name(&field_name));
+        FieldEntry {
+            name: field_name,
+            field_type: FieldType::JsonObject(json_object_options),
+        }
+    }
+
     /// Returns the name of the field
     pub fn name(&self) -> &str {
         &self.name
@@ -116,13 +156,15 @@ impl FieldEntry {
         self.field_type.is_indexed()
     }
 
-    /// Returns true iff the field is a int (signed or unsigned) fast field
+    /// Returns true iff the field is a int (signed or unsigned), float, date or bool fast field
     pub fn is_fast(&self) -> bool {
         match self.field_type {
             FieldType::U64(ref options)
             | FieldType::I64(ref options)
-            | FieldType::Date(ref options)
-            | FieldType::F64(ref options) => options.is_fast(),
+            | FieldType::F64(ref options)
+            | FieldType::Bool(ref options)
+            | FieldType::IpAddr(ref options) => options.is_fast(),
+            FieldType::Date(ref options) => options.is_fast(),
             _ => false,
         }
     }
@@ -133,10 +175,13 @@ impl FieldEntry {
             FieldType::U64(ref options)
             | FieldType::I64(ref options)
             | FieldType::F64(ref options)
-            | FieldType::Date(ref options) => options.is_stored(),
+            | FieldType::Bool(ref options)
+            | FieldType::IpAddr(ref options) => options.is_stored(),
+            FieldType::Date(ref options) => options.is_stored(),
             FieldType::Str(ref options) => options.is_stored(),
             FieldType::HierarchicalFacet(ref options) => options.is_stored(),
             FieldType::Bytes(ref options) => options.is_stored(),
+            FieldType::JsonObject(ref options) => options.is_stored(),
         }
     }
 }
@@ -166,6 +211,14 @@ impl Serialize for FieldEntry {
                 s.serialize_field("type", "f64")?;
                 s.serialize_field("options", options)?;
             }
+            FieldType::Bool(ref options) => {
+                s.serialize_field("type", "bool")?;
+                s.serialize_field("options", options)?;
+            }
+            FieldType::IpAddr(ref options) => {
+                s.serialize_field("type", "ip_addr")?;
+                s.serialize_field("options", options)?;
+            }
             FieldType::Date(ref options) => {
                 s.serialize_field("type", "date")?;
                 s.serialize_field("options", options)?;
@@ -178,6 +231,10 @@ impl Serialize for FieldEntry {
                 s.serialize_field("type", "bytes")?;
                 s.serialize_field("options", options)?;
             }
+            FieldType::JsonObject(ref options) => {
+                s.serialize_field("type", "json_object")?;
+                s.serialize_field("options", options)?;
+            }
         }
 
         s.end()
@@ -229,8 +286,8 @@ impl<'de> Deserialize<'de> for FieldEntry {
                             }
                             let type_string = map.next_value::<String>()?;
                             match type_string.as_str() {
-                                "text" | "u64" | "i64" | "f64" | "date" | "bytes"
-                                | "hierarchical_facet" => {
+                                "text" | "u64" | "i64" | "f64" | "bool" | "ip_addr" | "date"
+                                | "bytes" | "hierarchical_facet" | "json_object" => {
                                     // These types require additional options to create a field_type
                                 }
                                 _ => panic!("unhandled type"),
@@ -248,8 +305,15 @@ impl<'de> Deserialize<'de> for FieldEntry {
                                 "u64" => field_type = Some(FieldType::U64(map.next_value()?)),
                                 "i64" => field_type = Some(FieldType::I64(map.next_value()?)),
                                 "f64" => field_type = Some(FieldType::F64(map.next_value()?)),
+                                "bool" => field_type = Some(FieldType::Bool(map.next_value()?)),
+                                "ip_addr" => {
+                                    field_type = Some(FieldType::IpAddr(map.next_value()?))
+                                }
                                 "date" => field_type = Some(FieldType::Date(map.next_value()?)),
                                 "bytes" => field_type = Some(FieldType::Bytes(map.next_value()?)),
+                                "json_object" => {
+                                    field_type = Some(FieldType::JsonObject(map.next_value()?))
+                                }
                                 "hierarchical_facet" => {
                                     field_type =
                                         Some(FieldType::HierarchicalFacet(map.next_value()?))
@@ -278,7 +342,7 @@ impl<'de> Deserialize<'de> for FieldEntry {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::schema::TEXT;
+    use crate::schema::{STORED, TEXT};
     use serde_json;
 
     #[test]
@@ -315,4 +379,24 @@ mod tests {
             _ => panic!("expected FieldType::Str"),
         }
     }
+
+    #[test]
+    fn test_json_object_serialization() {
+        let field_entry = FieldEntry::new_json(String::from("attributes"), (STORED | TEXT).into());
+        let expected = r#"{
+  "name": "attributes",
+  "type": "json_object",
+  "options": {
+    "stored": true,
+    "indexing": {
+      "record": "position",
+      "tokenizer": "default"
+    }
+  }
+}"#;
+        let field_entry_json = serde_json::to_string_pretty(&field_entry).unwrap();
+        assert_eq!(expected, &field_entry_json);
+        let deserialized_field_entry: FieldEntry = serde_json::from_str(expected).unwrap();
+        assert_eq!(deserialized_field_entry, field_entry);
+    }
 }
@@ -1,10 +1,12 @@
 use crate::schema::bytes_options::BytesOptions;
 use crate::schema::facet_options::FacetOptions;
+use crate::schema::value::parse_ip_addr;
 use crate::schema::Facet;
 use crate::schema::IndexRecordOption;
+use crate::schema::JsonObjectOptions;
 use crate::schema::TextFieldIndexing;
 use crate::schema::Value;
-use crate::schema::{IntOptions, TextOptions};
+use crate::schema::{DateOptions, IntOptions, TextOptions};
 use crate::tokenizer::PreTokenizedString;
 use chrono::{FixedOffset, Utc};
 use serde_json::Value as JsonValue;
@@ -38,12 +40,18 @@ pub enum Type {
     I64,
     /// `f64`
     F64,
+    /// `bool`
+    Bool,
+    /// `std::net::Ipv6Addr`, IPv4 addresses being mapped into the IPv6 space.
+    IpAddr,
     /// `date(i64) timestamp`
     Date,
     /// `tantivy::schema::Facet`. Passed as a string in JSON.
     HierarchicalFacet,
     /// `Vec<u8>`
     Bytes,
+    /// `serde_json::Map<String, serde_json::Value>`
+    Json,
 }
 
 /// A `FieldType` describes the type (text, u64) of a field as well as
@@ -58,12 +66,18 @@ pub enum FieldType {
     I64(IntOptions),
     /// 64-bits float 64 field type configuration
     F64(IntOptions),
-    /// Signed 64-bits Date 64 field type configuration,
-    Date(IntOptions),
+    /// Bool field type configuration
+    Bool(IntOptions),
+    /// Ip address field type configuration
+    IpAddr(IntOptions),
+    /// Date field type configuration,
+    Date(DateOptions),
     /// Hierachical Facet
     HierarchicalFacet(FacetOptions),
     /// Bytes (one per document)
     Bytes(BytesOptions),
+    /// Json object
+    JsonObject(JsonObjectOptions),
 }
 
 impl FieldType {
@@ -74,9 +88,12 @@ impl FieldType {
             FieldType::U64(_) => Type::U64,
             FieldType::I64(_) => Type::I64,
             FieldType::F64(_) => Type::F64,
+            FieldType::Bool(_) => Type::Bool,
+            FieldType::IpAddr(_) => Type::IpAddr,
             FieldType::Date(_) => Type::Date,
             FieldType::HierarchicalFacet(_) => Type::HierarchicalFacet,
             FieldType::Bytes(_) => Type::Bytes,
+            FieldType::JsonObject(_) => Type::Json,
         }
     }
 
@@ -86,10 +103,15 @@ impl FieldType {
             FieldType::Str(ref text_options) => text_options.get_indexing_options().is_some(),
             FieldType::U64(ref int_options)
             | FieldType::I64(ref int_options)
-            | FieldType::F64(ref int_options) => int_options.is_indexed(),
+            | FieldType::F64(ref int_options)
+            | FieldType::Bool(ref int_options)
+            | FieldType::IpAddr(ref int_options) => int_options.is_indexed(),
             FieldType::Date(ref date_options) => date_options.is_indexed(),
             FieldType::HierarchicalFacet(ref facet_options) => facet_options.is_indexed(),
             FieldType::Bytes(ref bytes_options) => bytes_options.is_indexed(),
+            FieldType::JsonObject(ref json_object_options) => {
+                json_object_options.get_text_indexing_options().is_some()
+            }
         }
     }
 
@@ -105,13 +127,21 @@ impl FieldType {
             FieldType::U64(ref int_options)
             | FieldType::I64(ref int_options)
             | FieldType::F64(ref int_options)
-            | FieldType::Date(ref int_options) => {
+            | FieldType::Bool(ref int_options)
+            | FieldType::IpAddr(ref int_options) => {
                 if int_options.is_indexed() {
                     Some(IndexRecordOption::Basic)
                 } else {
                     None
                 }
             }
+            FieldType::Date(ref date_options) => {
+                if date_options.is_indexed() {
+                    Some(IndexRecordOption::Basic)
+                } else {
+                    None
+                }
+            }
             FieldType::HierarchicalFacet(ref facet_options) => {
                 if facet_options.is_indexed() {
                     Some(IndexRecordOption::Basic)
@@ -126,6 +156,9 @@ impl FieldType {
                     None
                 }
             }
+            FieldType::JsonObject(ref json_object_options) => json_object_options
+                .get_text_indexing_options()
+                .map(TextFieldIndexing::index_option),
         }
     }
 
@@ -151,6 +184,22 @@ impl FieldType {
                 FieldType::U64(_) | FieldType::I64(_) | FieldType::F64(_) => Err(
                     ValueParsingError::TypeError(format!("Expected an integer, got {:?}", json)),
                 ),
+                FieldType::Bool(_) => Err(ValueParsingError::TypeError(format!(
+                    "Expected a bool, got {:?}",
+                    json
+                ))),
+                FieldType::IpAddr(_) => {
+                    parse_ip_addr(field_text).map(Value::IpAddr).map_err(|_| {
+                        ValueParsingError::TypeError(format!(
+                            "Expected an ip address, got {:?}",
+                            field_text
+                        ))
+                    })
+                }
+                FieldType::JsonObject(_) => Err(ValueParsingError::TypeError(format!(
+                    "Expected a json object, got {:?}",
+                    json
+                ))),
                 FieldType::HierarchicalFacet(_) => Ok(Value::Facet(Facet::from(field_text))),
                 FieldType::Bytes(_) => base64::decode(field_text).map(Value::Bytes).map_err(|_| {
                     ValueParsingError::InvalidBase64(format!(
@@ -184,12 +233,34 @@ impl FieldType {
                         Err(ValueParsingError::OverflowError(msg))
                     }
                 }
-                FieldType::Str(_) | FieldType::HierarchicalFacet(_) | FieldType::Bytes(_) => {
+                FieldType::Str(_)
+                | FieldType::HierarchicalFacet(_)
+                | FieldType::Bytes(_)
+                | FieldType::IpAddr(_) => {
                     let msg = format!("Expected a string, got {:?}", json);
                     Err(ValueParsingError::TypeError(msg))
                 }
+                FieldType::Bool(_) => {
+                    let msg = format!("Expected a bool, got {:?}", json);
+                    Err(ValueParsingError::TypeError(msg))
+                }
+                FieldType::JsonObject(_) => {
+                    let msg = format!("Expected a json object, got {:?}", json);
+                    Err(ValueParsingError::TypeError(msg))
+                }
+            },
+            JsonValue::Bool(field_val_bool) => match *self {
+                FieldType::Bool(_) => Ok(Value::Bool(field_val_bool)),
+                _ => {
+                    let msg = format!(
+                        "Json value not supported error {:?}. Expected {:?}",
+                        json, self
+                    );
+                    Err(ValueParsingError::TypeError(msg))
+                }
             },
-            JsonValue::Object(_) => match *self {
+            JsonValue::Object(ref json_object) => match *self {
+                FieldType::JsonObject(_) => Ok(Value::JsonObject(json_object.clone())),
                 FieldType::Str(_) => {
                     if let Ok(tok_str_val) =
                         serde_json::from_value::<PreTokenizedString>(json.clone())
@@ -228,10 +299,11 @@ mod tests {
     use crate::schema::field_type::ValueParsingError;
     use crate::schema::TextOptions;
     use crate::schema::Value;
-    use crate::schema::{Schema, INDEXED};
+    use crate::schema::{DocParsingError, Schema, INDEXED, STORED};
     use crate::tokenizer::{PreTokenizedString, Token};
     use crate::{DateTime, Document};
     use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Utc};
+    use std::net::{Ipv4Addr, Ipv6Addr};
 
     #[test]
     fn test_deserialize_json_date() {
@@ -278,6 +350,88 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_bool_value_from_json() {
+        let mut schema_builder = Schema::builder();
+        let is_published = schema_builder.add_bool_field("is_published", STORED);
+        let schema = schema_builder.build();
+        let doc = schema
+            .parse_document(r#"{"is_published": [true, false]}"#)
+            .unwrap();
+        let values: Vec<&Value> = doc.get_all(is_published).collect();
+        assert_eq!(values, vec![&Value::Bool(true), &Value::Bool(false)]);
+        assert_eq!(schema.to_json(&doc), r#"{"is_published":[true,false]}"#);
+        for invalid_json in [r#"{"is_published": 1}"#, r#"{"is_published": "true"}"#] {
+            assert!(matches!(
+                schema.parse_document(invalid_json),
+                Err(DocParsingError::ValueError(
+                    _,
+                    ValueParsingError::TypeError(_)
+                ))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_ip_addr_value_from_json() {
+        let mut schema_builder = Schema::builder();
+        let ip = schema_builder.add_ip_addr_field("ip", STORED);
+        let schema = schema_builder.build();
+        let doc = schema
+            .parse_document(r#"{"ip": ["10.0.0.1", "2001:db8::1"]}"#)
+            .unwrap();
+        let values: Vec<Ipv6Addr> = doc
+            .get_all(ip)
+            .map(|value| value.ip_addr_value().unwrap())
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                Ipv4Addr::new(10, 0, 0, 1).to_ipv6_mapped(),
+                Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)
+            ]
+        );
+        assert_eq!(
+            schema.to_json(&doc),
+            r#"{"ip":["::ffff:10.0.0.1","2001:db8::1"]}"#
+        );
+        assert_eq!(schema.parse_document(&schema.to_json(&doc)).unwrap(), doc);
+        for invalid_json in [r#"{"ip": 1}"#, r#"{"ip": "10.0.0.256"}"#] {
+            assert!(matches!(
+                schema.parse_document(invalid_json),
+                Err(DocParsingError::ValueError(
+                    _,
+                    ValueParsingError::TypeError(_)
+                ))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_json_object_value_from_json() {
+        let mut schema_builder = Schema::builder();
+        let attributes = schema_builder.add_json_field("attributes", STORED);
+        let schema = schema_builder.build();
+        let doc = schema
+            .parse_document(r#"{"attributes": [{"color": "red"}, {"size": [1, 2]}]}"#)
+            .unwrap();
+        let json_objects: Vec<String> = doc
+            .get_all(attributes)
+            .map(|value| serde_json::to_string(value.as_json().unwrap()).unwrap())
+            .collect();
+        assert_eq!(
+            json_objects,
+            vec![r#"{"color":"red"}"#, r#"{"size":[1,2]}"#]
+        );
+        assert!(matches!(
+            schema.parse_document(r#"{"attributes": "red"}"#),
+            Err(DocParsingError::ValueError(
+                _,
+                ValueParsingError::TypeError(_)
+            ))
+        ));
+    }
+
     #[test]
     fn test_pre_tok_str_value_from_json() {
         let pre_tokenized_string_json = r#"{
@@ -316,6 +470,7 @@ mod tests {
                     position: 0,
                     text: String::from("The"),
                     position_length: 1,
+                    payload: 0,
                 },
                 Token {
                     offset_from: 4,
@@ -323,6 +478,7 @@ mod tests {
                     position: 1,
                     text: String::from("Old"),
                     position_length: 1,
+                    payload: 0,
                 },
                 Token {
                     offset_from: 8,
@@ -330,6 +486,7 @@ mod tests {
                     position: 2,
                     text: String::from("Man"),
                     position_length: 1,
+                    payload: 0,
                 },
             ],
         });
@@ -28,6 +28,13 @@ pub enum IndexRecordOption {
     /// Positions are required to run [PhraseQueries](../query/struct.PhraseQuery.html).
     #[serde(rename = "position")]
     WithFreqsAndPositions,
+    /// records the document id, the term frequency, the positions of
+    /// the occurences in the document, and the payload attached to each
+    /// of these occurences by the token filters.
+    /// Payloads can be read through
+    /// [`Postings::positions_and_payloads`](../postings/trait.Postings.html#method.positions_and_payloads).
+    #[serde(rename = "payload")]
+    WithFreqsAndPositionsAndPayloads,
 }
 
 impl IndexRecordOption {
@@ -36,7 +43,9 @@ impl IndexRecordOption {
     pub fn has_freq(self) -> bool {
         match self {
             IndexRecordOption::Basic => false,
-            IndexRecordOption::WithFreqs | IndexRecordOption::WithFreqsAndPositions => true,
+            IndexRecordOption::WithFreqs
+            | IndexRecordOption::WithFreqsAndPositions
+            | IndexRecordOption::WithFreqsAndPositionsAndPayloads => true,
         }
     }
 
@@ -45,7 +54,19 @@ impl IndexRecordOption {
     pub fn has_positions(self) -> bool {
         match self {
             IndexRecordOption::Basic | IndexRecordOption::WithFreqs => false,
-            IndexRecordOption::WithFreqsAndPositions => true,
+            IndexRecordOption::WithFreqsAndPositions
+            | IndexRecordOption::WithFreqsAndPositionsAndPayloads => true,
+        }
+    }
+
+    /// Returns true iff this option include encoding
+    /// the payloads of the term positions.
+    pub fn has_payloads(self) -> bool {
+        match self {
+            IndexRecordOption::Basic
+            | IndexRecordOption::WithFreqs
+            | IndexRecordOption::WithFreqsAndPositions => false,
+            IndexRecordOption::WithFreqsAndPositionsAndPayloads => true,
         }
     }
 }
@@ -14,12 +14,29 @@ pub enum Cardinality {
     MultiValues,
 }
 
+/// Codec used to compress the values of a fast field.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum FastFieldCodecType {
+    /// The values are bitpacked.
+    #[serde(rename = "bitpacked")]
+    Bitpacked,
+    /// The values are estimated by a linear interpolation, and their
+    /// differences to the estimation are bitpacked.
+    #[serde(rename = "linear")]
+    LinearInterpol,
+    /// Same as `LinearInterpol`, but with an interpolation per block of 512 values.
+    #[serde(rename = "blockwise_linear")]
+    MultiLinearInterpol,
+}
+
 /// Define how an u64, i64, of f64 field should be handled by tantivy.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct IntOptions {
     indexed: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     fast: Option<Cardinality>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fast_codec: Option<FastFieldCodecType>,
     stored: bool,
 }
 
@@ -77,6 +94,22 @@ impl IntOptions {
     pub fn get_fastfield_cardinality(&self) -> Option<Cardinality> {
         self.fast
     }
+
+    /// Sets the codec of the single-valued fast field.
+    ///
+    /// By default, the codec compressing the values the most is chosen.
+    /// If the given codec cannot represent the values of a segment, e.g. if they
+    /// are too few for a linear interpolation, the codec is chosen automatically
+    /// and a warning is logged.
+    pub fn set_fast_codec(mut self, codec_type: FastFieldCodecType) -> IntOptions {
+        self.fast_codec = Some(codec_type);
+        self
+    }
+
+    /// Returns the codec set for the fast field, if any.
+    pub fn get_fast_codec(&self) -> Option<FastFieldCodecType> {
+        self.fast_codec
+    }
 }
 
 impl Default for IntOptions {
@@ -85,6 +118,7 @@ impl Default for IntOptions {
             indexed: false,
             stored: false,
             fast: None,
+            fast_codec: None,
         }
     }
 }
@@ -101,6 +135,7 @@ impl From<FastFlag> for IntOptions {
             indexed: false,
             stored: false,
             fast: Some(Cardinality::SingleValue),
+            fast_codec: None,
         }
     }
 }
@@ -111,6 +146,7 @@ impl From<StoredFlag> for IntOptions {
             indexed: false,
             stored: true,
             fast: None,
+            fast_codec: None,
         }
     }
 }
@@ -121,6 +157,7 @@ impl From<IndexedFlag> for IntOptions {
             indexed: true,
             stored: false,
             fast: None,
+            fast_codec: None,
         }
     }
 }
@@ -134,6 +171,7 @@ impl<T: Into<IntOptions>> BitOr<T> for IntOptions {
             indexed: self.indexed | other.indexed,
             stored: self.stored | other.stored,
             fast: self.fast.or(other.fast),
+            fast_codec: self.fast_codec.or(other.fast_codec),
         }
     }
 }
@@ -0,0 +1,118 @@
+use crate::schema::flags::SchemaFlagList;
+use crate::schema::flags::StoredFlag;
+use crate::schema::{TextFieldIndexing, TextOptions};
+use serde::{Deserialize, Serialize};
+use std::ops::BitOr;
+
+/// Define how a json object field should be handled by tantivy.
+///
+/// Every leaf of the objects is indexed under its path, i.e. the keys leading to it
+/// joined by dots, e.g. `user.id` for `{"user": {"id": 12}}`.
+///
+/// - String leaves are tokenized with the tokenizer of the indexing options.
+/// - Number leaves are indexed as `i64` if they are integers fitting in an `i64`,
+///   as `u64` if they are greater integers, and as `f64` otherwise.
+/// - Bool leaves are indexed as bools.
+/// - The values of an array are all indexed under the path of the array,
+///   and `null` values are ignored.
+///
+/// Values are only indexed with their JSON type: the string `"123"` is not indexed
+/// as a number. The [`QueryParser`](../query/struct.QueryParser.html) matches both
+/// the text and, when it can be parsed as such, the number or the bool given in a query.
+///
+/// Since the keys are joined by dots, `{"user.id": 12}` is indexed as `{"user": {"id": 12}}`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct JsonObjectOptions {
+    stored: bool,
+    indexing: Option<TextFieldIndexing>,
+}
+
+impl JsonObjectOptions {
+    /// Returns true iff the json objects are to be stored.
+    pub fn is_stored(&self) -> bool {
+        self.stored
+    }
+
+    /// Returns the indexing options of the text leaves, or `None` if the
+    /// field is not indexed.
+    pub fn get_text_indexing_options(&self) -> Option<&TextFieldIndexing> {
+        self.indexing.as_ref()
+    }
+
+    /// Sets the field as stored
+    pub fn set_stored(mut self) -> JsonObjectOptions {
+        self.stored = true;
+        self
+    }
+
+    /// Sets the field as indexed, with the indexing options of its text leaves.
+    pub fn set_indexing_options(mut self, indexing: TextFieldIndexing) -> JsonObjectOptions {
+        self.indexing = Some(indexing);
+        self
+    }
+}
+
+impl From<TextOptions> for JsonObjectOptions {
+    fn from(text_options: TextOptions) -> JsonObjectOptions {
+        JsonObjectOptions {
+            stored: text_options.is_stored(),
+            indexing: text_options.get_indexing_options().cloned(),
+        }
+    }
+}
+
+impl From<StoredFlag> for JsonObjectOptions {
+    fn from(_: StoredFlag) -> JsonObjectOptions {
+        JsonObjectOptions {
+            stored: true,
+            indexing: None,
+        }
+    }
+}
+
+impl<Head, Tail> From<SchemaFlagList<Head, Tail>> for JsonObjectOptions
+where
+    Head: Clone,
+    Tail: Clone,
+    TextOptions: From<SchemaFlagList<Head, Tail>>,
+{
+    fn from(head_tail: SchemaFlagList<Head, Tail>) -> Self {
+        JsonObjectOptions::from(TextOptions::from(head_tail))
+    }
+}
+
+impl<T: Into<JsonObjectOptions>> BitOr<T> for JsonObjectOptions {
+    type Output = JsonObjectOptions;
+
+    fn bitor(self, other: T) -> JsonObjectOptions {
+        let other = other.into();
+        JsonObjectOptions {
+            indexing: self.indexing.or(other.indexing),
+            stored: self.stored | other.stored,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::*;
+
+    #[test]
+    fn test_json_object_options() {
+        let options = JsonObjectOptions::from(STORED | TEXT);
+        assert!(options.is_stored());
+        assert_eq!(
+            options.get_text_indexing_options().unwrap().tokenizer(),
+            "default"
+        );
+        let options = JsonObjectOptions::from(STORED);
+        assert!(options.is_stored());
+        assert!(options.get_text_indexing_options().is_none());
+        let options = JsonObjectOptions::default() | STRING;
+        assert!(!options.is_stored());
+        assert_eq!(
+            options.get_text_indexing_options().unwrap().tokenizer(),
+            "raw"
+        );
+    }
+}
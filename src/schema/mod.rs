@@ -84,6 +84,38 @@ make it possible to access the u64 value given the doc id rapidly. This is usefu
 the field is required during scoring or collection for instance.
 
 
+## Setting a bool field
+
+### Example
+
+```
+use tantivy::schema::*;
+let mut schema_builder = Schema::builder();
+schema_builder.add_bool_field("is_published", INDEXED | FAST);
+let schema = schema_builder.build();
+```
+
+Bool fields take the same options as u64 fields. Their fast field values
+are bitpacked with a single bit per document, and are read with
+[`FastFieldReaders::bool`](../fastfield/struct.FastFieldReaders.html#method.bool).
+
+
+## Setting a json object field
+
+### Example
+
+```
+use tantivy::schema::*;
+let mut schema_builder = Schema::builder();
+schema_builder.add_json_field("attributes", STORED | TEXT);
+let schema = schema_builder.build();
+```
+
+The leaves of the json objects are indexed under their path, e.g. `user.id` for
+`{"user": {"id": 12}}`. The text leaves are tokenized like a text field, while numbers
+and bools are indexed with their type. See [`JsonObjectOptions`](struct.JsonObjectOptions.html).
+
+
 ### Shortcuts
 
 
@@ -113,9 +145,11 @@ mod field_type;
 mod field_value;
 
 mod bytes_options;
+mod date_time_options;
 mod field;
 mod index_record_option;
 mod int_options;
+mod json_object_options;
 mod named_field_document;
 mod text_options;
 mod value;
@@ -126,6 +160,7 @@ pub use self::named_field_document::NamedFieldDocument;
 pub use self::schema::DocParsingError;
 pub use self::schema::{Schema, SchemaBuilder};
 pub use self::value::Value;
+pub(crate) use self::value::{ip_addr_to_ipv6, parse_ip_addr};
 
 pub use self::facet::Facet;
 pub use self::facet::FacetParseError;
@@ -135,6 +170,9 @@ pub use self::facet_options::FacetOptions;
 pub use self::document::Document;
 pub use self::field::Field;
 pub use self::term::Term;
+pub(crate) use self::term::{
+    JSON_BOOL_CODE, JSON_F64_CODE, JSON_I64_CODE, JSON_TEXT_CODE, JSON_U64_CODE,
+};
 
 pub use self::field_entry::FieldEntry;
 pub use self::field_type::{FieldType, Type};
@@ -147,9 +185,12 @@ pub use self::text_options::STRING;
 pub use self::text_options::TEXT;
 
 pub use self::bytes_options::BytesOptions;
+pub use self::date_time_options::{DateOptions, DatePrecision};
 pub use self::flags::{FAST, INDEXED, STORED};
 pub use self::int_options::Cardinality;
+pub use self::int_options::FastFieldCodecType;
 pub use self::int_options::IntOptions;
+pub use self::json_object_options::JsonObjectOptions;
 
 /// Validator for a potential `field_name`.
 /// Returns true if the name can be use for a field name.
@@ -103,10 +103,59 @@ impl SchemaBuilder {
         self.add_field(field_entry)
     }
 
+    /// Adds a new bool field.
+    /// Returns the associated field handle
+    ///
+    /// # Caution
+    ///
+    /// Appending two fields with the same name
+    /// will result in the shadowing of the first
+    /// by the second one.
+    /// The first field will get a field id
+    /// but only the second one will be indexed
+    pub fn add_bool_field<T: Into<IntOptions>>(
+        &mut self,
+        field_name_str: &str,
+        field_options: T,
+    ) -> Field {
+        let field_name = String::from(field_name_str);
+        let field_entry = FieldEntry::new_bool(field_name, field_options.into());
+        self.add_field(field_entry)
+    }
+
+    /// Adds a new ip address field.
+    /// Returns the associated field handle
+    ///
+    /// IPv4 addresses are mapped into the IPv6 space, so that both can be
+    /// stored in the same field and searched with range queries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field is set as a multivalued fast field.
+    ///
+    /// # Caution
+    ///
+    /// Appending two fields with the same name
+    /// will result in the shadowing of the first
+    /// by the second one.
+    /// The first field will get a field id
+    /// but only the second one will be indexed
+    pub fn add_ip_addr_field<T: Into<IntOptions>>(
+        &mut self,
+        field_name_str: &str,
+        field_options: T,
+    ) -> Field {
+        let field_name = String::from(field_name_str);
+        let field_entry = FieldEntry::new_ip_addr(field_name, field_options.into());
+        self.add_field(field_entry)
+    }
+
     /// Adds a new date field.
     /// Returns the associated field handle
     /// Internally, Tantivy simply stores dates as i64 UTC timestamps,
     /// while the user supplies DateTime values for convenience.
+    /// The precision of the timestamps of the fast field is set by
+    /// `DateOptions::set_precision`.
     ///
     /// # Caution
     ///
@@ -115,7 +164,7 @@ impl SchemaBuilder {
     /// by the second one.
     /// The first field will get a field id
     /// but only the second one will be indexed
-    pub fn add_date_field<T: Into<IntOptions>>(
+    pub fn add_date_field<T: Into<DateOptions>>(
         &mut self,
         field_name_str: &str,
         field_options: T,
@@ -174,6 +223,21 @@ impl SchemaBuilder {
         self.add_field(field_entry)
     }
 
+    /// Adds a json object field to the schema.
+    ///
+    /// The leaves of the json objects are indexed under their path, and can be searched
+    /// with the `QueryParser` by prefixing the path with the name of the field,
+    /// e.g. `attributes.user.id:12`.
+    /// See [`JsonObjectOptions`](./struct.JsonObjectOptions.html) for how the leaves are indexed.
+    pub fn add_json_field<T: Into<JsonObjectOptions>>(
+        &mut self,
+        field_name: &str,
+        field_options: T,
+    ) -> Field {
+        let field_entry = FieldEntry::new_json(field_name.to_string(), field_options.into());
+        self.add_field(field_entry)
+    }
+
     /// Adds a field entry to the schema in build.
     pub fn add_field(&mut self, field_entry: FieldEntry) -> Field {
         let field = Field::from_field_id(self.fields.len() as u32);
@@ -741,6 +805,24 @@ mod tests {
         }
     }
 
+    #[test]
+    pub fn test_schema_fast_codec_serialization() {
+        let mut schema_builder = Schema::builder();
+        let timestamp_options = IntOptions::default()
+            .set_fast(SingleValue)
+            .set_fast_codec(crate::schema::FastFieldCodecType::LinearInterpol);
+        schema_builder.add_u64_field("timestamp", timestamp_options.clone());
+        let schema = schema_builder.build();
+        let schema_json = serde_json::to_string(&schema).unwrap();
+        assert_eq!(
+            schema_json,
+            r#"[{"name":"timestamp","type":"u64","options":{"indexed":false,"fast":"single","fast_codec":"linear","stored":false}}]"#
+        );
+        let schema: Schema = serde_json::from_str(&schema_json).unwrap();
+        let field_entry = schema.get_field_entry(schema.get_field("timestamp").unwrap());
+        assert_eq!(field_entry.field_type(), &FieldType::U64(timestamp_options));
+    }
+
     #[test]
     pub fn test_schema_add_field() {
         let mut schema_builder = SchemaBuilder::default();
@@ -4,11 +4,23 @@ use super::Field;
 use crate::schema::Facet;
 use crate::DateTime;
 use common;
+use std::net::Ipv6Addr;
 use std::str;
 
 /// Size (in bytes) of the buffer of a int field.
 const INT_TERM_LEN: usize = 4 + 8;
 
+/// Byte ending the path of the terms of a json object field.
+pub(crate) const JSON_END_OF_PATH: u8 = 0u8;
+
+/// Byte following the path of the terms of a json object field,
+/// encoding the type of their value.
+pub(crate) const JSON_TEXT_CODE: u8 = b's';
+pub(crate) const JSON_U64_CODE: u8 = b'u';
+pub(crate) const JSON_I64_CODE: u8 = b'i';
+pub(crate) const JSON_F64_CODE: u8 = b'f';
+pub(crate) const JSON_BOOL_CODE: u8 = b'o';
+
 /// Term represents the value that the token can take.
 ///
 /// It actually wraps a `Vec<u8>`.
@@ -46,6 +58,23 @@ impl Term {
         Term::from_field_u64(field, val_u64)
     }
 
+    /// Builds a term given a field, and a bool value
+    ///
+    /// The bool is encoded as the u64 value `0` or `1`.
+    pub fn from_field_bool(field: Field, val: bool) -> Term {
+        Term::from_field_u64(field, u64::from(val))
+    }
+
+    /// Builds a term given a field, and an ip address
+    ///
+    /// The address is encoded as a big-endian `u128` over 16 bytes, so that the
+    /// order of the terms is the order of the addresses.
+    pub fn from_field_ip_addr(field: Field, val: Ipv6Addr) -> Term {
+        let mut term = Term::for_field(field);
+        term.set_ip_addr(val);
+        term
+    }
+
     /// Builds a term given a field, and a DateTime value
     ///
     /// Assuming the term has a field id of 1, and a timestamp i64 value of 3234,
@@ -103,6 +132,58 @@ impl Term {
         term
     }
 
+    /// Builds a term given a json object field, the path of a leaf
+    /// (i.e. its keys joined by dots, e.g. `user.name`) and a text token.
+    ///
+    /// The term is made of the field id, the path, a `0u8` byte, a byte
+    /// encoding the type of the value and the value itself.
+    pub fn from_field_json_text(field: Field, json_path: &str, text: &str) -> Term {
+        let mut term = Term::new();
+        term.set_json_path(field, json_path, JSON_TEXT_CODE);
+        term.append_bytes(text.as_bytes());
+        term
+    }
+
+    /// Builds a term given a json object field, the path of a leaf and a u64-value.
+    ///
+    /// See [`from_field_json_text`](#method.from_field_json_text) for the layout of the term.
+    pub fn from_field_json_u64(field: Field, json_path: &str, val: u64) -> Term {
+        let mut term = Term::new();
+        term.set_json_path(field, json_path, JSON_U64_CODE);
+        term.append_bytes(val.to_be_bytes().as_ref());
+        term
+    }
+
+    /// Builds a term given a json object field, the path of a leaf and a i64-value.
+    ///
+    /// See [`from_field_json_text`](#method.from_field_json_text) for the layout of the term.
+    pub fn from_field_json_i64(field: Field, json_path: &str, val: i64) -> Term {
+        let mut term = Term::new();
+        term.set_json_path(field, json_path, JSON_I64_CODE);
+        term.append_bytes(common::i64_to_u64(val).to_be_bytes().as_ref());
+        term
+    }
+
+    /// Builds a term given a json object field, the path of a leaf and a f64-value.
+    ///
+    /// See [`from_field_json_text`](#method.from_field_json_text) for the layout of the term.
+    pub fn from_field_json_f64(field: Field, json_path: &str, val: f64) -> Term {
+        let mut term = Term::new();
+        term.set_json_path(field, json_path, JSON_F64_CODE);
+        term.append_bytes(common::f64_to_u64(val).to_be_bytes().as_ref());
+        term
+    }
+
+    /// Builds a term given a json object field, the path of a leaf and a bool.
+    ///
+    /// See [`from_field_json_text`](#method.from_field_json_text) for the layout of the term.
+    pub fn from_field_json_bool(field: Field, json_path: &str, val: bool) -> Term {
+        let mut term = Term::new();
+        term.set_json_path(field, json_path, JSON_BOOL_CODE);
+        term.append_bytes(&[val as u8]);
+        term
+    }
+
     /// Creates a new Term for a given field.
     pub(crate) fn for_field(field: Field) -> Term {
         let mut term = Term(Vec::with_capacity(100));
@@ -137,6 +218,16 @@ impl Term {
         self.set_u64(common::f64_to_u64(val));
     }
 
+    /// Sets a `bool` value in the term.
+    pub fn set_bool(&mut self, val: bool) {
+        self.set_u64(u64::from(val));
+    }
+
+    /// Sets an ip address value in the term.
+    pub fn set_ip_addr(&mut self, val: Ipv6Addr) {
+        self.set_bytes(u128::from(val).to_be_bytes().as_ref());
+    }
+
     /// Sets the value of a `Bytes` field.
     pub fn set_bytes(&mut self, bytes: &[u8]) {
         self.0.resize(4, 0u8);
@@ -147,6 +238,24 @@ impl Term {
     pub fn set_text(&mut self, text: &str) {
         self.set_bytes(text.as_bytes());
     }
+
+    /// Sets the field and the json path of the term, followed by the type code of its value.
+    pub(crate) fn set_json_path(&mut self, field: Field, json_path: &str, type_code: u8) {
+        self.set_field(field);
+        self.0.extend_from_slice(json_path.as_bytes());
+        self.0.push(JSON_END_OF_PATH);
+        self.0.push(type_code);
+    }
+
+    /// Appends some bytes to the term.
+    pub(crate) fn append_bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    /// Truncates the term to its first `len` bytes.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.0.truncate(len);
+    }
 }
 
 impl<B> Term<B>
@@ -194,6 +303,26 @@ where
         common::u64_to_f64(self.get_u64())
     }
 
+    /// Returns the `bool` value stored in a term.
+    ///
+    /// # Panics
+    /// ... or returns an invalid value
+    /// if the term is not a `bool` field.
+    pub fn get_bool(&self) -> bool {
+        self.get_u64() != 0
+    }
+
+    /// Returns the ip address stored in a term.
+    ///
+    /// # Panics
+    /// ... or returns an invalid value
+    /// if the term is not an ip address field.
+    pub fn get_ip_addr(&self) -> Ipv6Addr {
+        let mut ip_addr_bytes = [0u8; 16];
+        ip_addr_bytes.copy_from_slice(self.value_bytes());
+        Ipv6Addr::from(u128::from_be_bytes(ip_addr_bytes))
+    }
+
     /// Returns the text associated with the term.
     ///
     /// # Panics
@@ -271,5 +400,41 @@ mod tests {
             assert_eq!(term.as_slice()[10], (933u64 / 256u64) as u8);
             assert_eq!(term.as_slice()[11], (983u64 % 256u64) as u8);
         }
+        {
+            let term = Term::from_field_bool(count_field, true);
+            assert_eq!(term.as_slice().len(), 4 + 8);
+            assert_eq!(&term.as_slice()[4..], &[0u8, 0, 0, 0, 0, 0, 0, 1]);
+            assert!(term.get_bool());
+            assert!(!Term::from_field_bool(count_field, false).get_bool());
+        }
+        {
+            let ip_addr = "2001:db8::ff00:42:8329".parse().unwrap();
+            let term = Term::from_field_ip_addr(count_field, ip_addr);
+            assert_eq!(term.as_slice().len(), 4 + 16);
+            assert_eq!(&term.as_slice()[4..6], &[0x20u8, 0x01]);
+            assert_eq!(term.get_ip_addr(), ip_addr);
+            let lower_ip_addr = "2001:db8::ff00:42:8328".parse().unwrap();
+            assert!(Term::from_field_ip_addr(count_field, lower_ip_addr) < term);
+        }
+    }
+
+    #[test]
+    pub fn test_json_term() {
+        let mut schema_builder = Schema::builder();
+        let json_field = schema_builder.add_json_field("attributes", TEXT);
+        let term = Term::from_field_json_text(json_field, "user.name", "paul");
+        assert_eq!(term.field(), json_field);
+        assert_eq!(term.value_bytes(), b"user.name\x00spaul");
+        let term = Term::from_field_json_u64(json_field, "id", 983u64);
+        assert_eq!(
+            term.value_bytes(),
+            b"id\x00u\x00\x00\x00\x00\x00\x00\x03\xd7"
+        );
+        let term = Term::from_field_json_bool(json_field, "active", true);
+        assert_eq!(term.value_bytes(), b"active\x00o\x01");
+        assert!(
+            Term::from_field_json_i64(json_field, "a", -1)
+                < Term::from_field_json_i64(json_field, "a", 1)
+        );
     }
 }
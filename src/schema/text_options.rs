@@ -51,10 +51,20 @@ impl Default for TextOptions {
 /// - the amount of information that should be stored about the presence of a term in a document.
 /// Essentially, should we store the term frequency and/or the positions (See [`IndexRecordOption`](./enum.IndexRecordOption.html)).
 /// - the name of the `Tokenizer` that should be used to process the field.
+/// - optionally, the name of a different `Tokenizer` used to process the queries on the field.
+/// - whether the term vectors of the documents should be stored.
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct TextFieldIndexing {
     record: IndexRecordOption,
     tokenizer: Cow<'static, str>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    search_tokenizer: Option<Cow<'static, str>>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    store_term_vectors: bool,
+}
+
+fn is_false(val: &bool) -> bool {
+    !*val
 }
 
 impl Default for TextFieldIndexing {
@@ -62,6 +72,8 @@ impl Default for TextFieldIndexing {
         TextFieldIndexing {
             tokenizer: Cow::Borrowed("default"),
             record: IndexRecordOption::Basic,
+            search_tokenizer: None,
+            store_term_vectors: false,
         }
     }
 }
@@ -78,6 +90,23 @@ impl TextFieldIndexing {
         &self.tokenizer
     }
 
+    /// Sets the tokenizer to be used for the queries on a given field,
+    /// e.g. by the `QueryParser`.
+    ///
+    /// By default, the queries are processed with the same tokenizer as the field.
+    /// A different tokenizer is useful when the indexed tokens are derived from
+    /// the text in a way that should not apply to the queries, like with an `EdgeNgramFilter`.
+    pub fn set_search_tokenizer(mut self, tokenizer_name: &str) -> TextFieldIndexing {
+        self.search_tokenizer = Some(Cow::Owned(tokenizer_name.to_string()));
+        self
+    }
+
+    /// Returns the tokenizer that will be used for the queries on this field:
+    /// the search tokenizer if it is set, the tokenizer of the field otherwise.
+    pub fn search_tokenizer(&self) -> &str {
+        self.search_tokenizer.as_deref().unwrap_or(&self.tokenizer)
+    }
+
     /// Sets which information should be indexed with the tokens.
     ///
     /// See [IndexRecordOption](./enum.IndexRecordOption.html) for more detail.
@@ -92,6 +121,22 @@ impl TextFieldIndexing {
     pub fn index_option(&self) -> IndexRecordOption {
         self.record
     }
+
+    /// Sets the field to store the term vector of each document: its terms, with their
+    /// positions and their offsets in the text.
+    ///
+    /// The term vectors are read through a
+    /// [`TermVectorReader`](../termvector/struct.TermVectorReader.html), regardless of the
+    /// `IndexRecordOption` of the field. They are only stored for the text fields.
+    pub fn set_store_term_vectors(mut self) -> TextFieldIndexing {
+        self.store_term_vectors = true;
+        self
+    }
+
+    /// Returns true iff the term vectors of the documents are stored.
+    pub fn store_term_vectors(&self) -> bool {
+        self.store_term_vectors
+    }
 }
 
 /// The field will be untokenized and indexed.
@@ -99,6 +144,8 @@ pub const STRING: TextOptions = TextOptions {
     indexing: Some(TextFieldIndexing {
         tokenizer: Cow::Borrowed("raw"),
         record: IndexRecordOption::Basic,
+        search_tokenizer: None,
+        store_term_vectors: false,
     }),
     stored: false,
 };
@@ -108,6 +155,8 @@ pub const TEXT: TextOptions = TextOptions {
     indexing: Some(TextFieldIndexing {
         tokenizer: Cow::Borrowed("default"),
         record: IndexRecordOption::WithFreqsAndPositions,
+        search_tokenizer: None,
+        store_term_vectors: false,
     }),
     stored: false,
 };
@@ -169,6 +218,50 @@ mod tests {
                 if text_options.get_indexing_options().unwrap().tokenizer() == "default"));
     }
 
+    #[test]
+    fn test_text_field_indexing_search_tokenizer_serde() {
+        let indexing = TextFieldIndexing::default().set_tokenizer("edge_ngram");
+        assert_eq!(indexing.search_tokenizer(), "edge_ngram");
+        assert_eq!(
+            serde_json::to_string(&indexing).unwrap(),
+            r#"{"record":"basic","tokenizer":"edge_ngram"}"#
+        );
+        let indexing = indexing.set_search_tokenizer("default");
+        assert_eq!(indexing.tokenizer(), "edge_ngram");
+        assert_eq!(indexing.search_tokenizer(), "default");
+        let json = serde_json::to_string(&indexing).unwrap();
+        assert_eq!(
+            json,
+            r#"{"record":"basic","tokenizer":"edge_ngram","search_tokenizer":"default"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<TextFieldIndexing>(&json).unwrap(),
+            indexing
+        );
+        // schemas written without a search tokenizer are still readable.
+        let indexing: TextFieldIndexing =
+            serde_json::from_str(r#"{"record":"basic","tokenizer":"raw"}"#).unwrap();
+        assert_eq!(indexing.search_tokenizer(), "raw");
+    }
+
+    #[test]
+    fn test_text_field_indexing_store_term_vectors_serde() {
+        let indexing = TextFieldIndexing::default().set_store_term_vectors();
+        assert!(indexing.store_term_vectors());
+        let json = serde_json::to_string(&indexing).unwrap();
+        assert_eq!(
+            json,
+            r#"{"record":"basic","tokenizer":"default","store_term_vectors":true}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<TextFieldIndexing>(&json).unwrap(),
+            indexing
+        );
+        let indexing: TextFieldIndexing =
+            serde_json::from_str(r#"{"record":"basic","tokenizer":"raw"}"#).unwrap();
+        assert!(!indexing.store_term_vectors());
+    }
+
     #[test]
     fn test_cmp_index_record_option() {
         assert!(IndexRecordOption::WithFreqsAndPositions > IndexRecordOption::WithFreqs);
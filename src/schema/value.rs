@@ -3,6 +3,9 @@ use crate::tokenizer::PreTokenizedString;
 use crate::DateTime;
 use serde::de::Visitor;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Map;
+use std::net::{AddrParseError, IpAddr, Ipv6Addr};
+use std::str::FromStr;
 use std::{cmp::Ordering, fmt};
 
 /// Value represents the value of a any field.
@@ -19,12 +22,18 @@ pub enum Value {
     I64(i64),
     /// 64-bits Float `f64`
     F64(f64),
+    /// Bool value
+    Bool(bool),
+    /// Ip address, IPv4 addresses being mapped into the IPv6 space.
+    IpAddr(Ipv6Addr),
     /// Signed 64-bits Date time stamp `date`
     Date(DateTime),
     /// Hierarchical Facet
     Facet(Facet),
     /// Arbitrarily sized byte array
     Bytes(Vec<u8>),
+    /// Json object value.
+    JsonObject(Map<String, serde_json::Value>),
 }
 
 impl Eq for Value {}
@@ -40,9 +49,16 @@ impl Ord for Value {
             (Value::PreTokStr(l), Value::PreTokStr(r)) => l.cmp(r),
             (Value::U64(l), Value::U64(r)) => l.cmp(r),
             (Value::I64(l), Value::I64(r)) => l.cmp(r),
+            (Value::Bool(l), Value::Bool(r)) => l.cmp(r),
+            (Value::IpAddr(l), Value::IpAddr(r)) => l.cmp(r),
             (Value::Date(l), Value::Date(r)) => l.cmp(r),
             (Value::Facet(l), Value::Facet(r)) => l.cmp(r),
             (Value::Bytes(l), Value::Bytes(r)) => l.cmp(r),
+            (Value::JsonObject(l), Value::JsonObject(r)) => {
+                // json values are not ordered, their serialization is compared instead.
+                let to_string = |json_object| serde_json::to_string(json_object).ok();
+                to_string(l).cmp(&to_string(r))
+            }
             (Value::F64(l), Value::F64(r)) => {
                 match (l.is_nan(), r.is_nan()) {
                     (false, false) => l.partial_cmp(r).unwrap(), // only fail on NaN
@@ -61,10 +77,16 @@ impl Ord for Value {
             (_, Value::I64(_)) => Ordering::Greater,
             (Value::F64(_), _) => Ordering::Less,
             (_, Value::F64(_)) => Ordering::Greater,
+            (Value::Bool(_), _) => Ordering::Less,
+            (_, Value::Bool(_)) => Ordering::Greater,
+            (Value::IpAddr(_), _) => Ordering::Less,
+            (_, Value::IpAddr(_)) => Ordering::Greater,
             (Value::Date(_), _) => Ordering::Less,
             (_, Value::Date(_)) => Ordering::Greater,
             (Value::Facet(_), _) => Ordering::Less,
             (_, Value::Facet(_)) => Ordering::Greater,
+            (Value::Bytes(_), _) => Ordering::Less,
+            (_, Value::Bytes(_)) => Ordering::Greater,
         }
     }
 }
@@ -80,9 +102,12 @@ impl Serialize for Value {
             Value::U64(u) => serializer.serialize_u64(u),
             Value::I64(u) => serializer.serialize_i64(u),
             Value::F64(u) => serializer.serialize_f64(u),
+            Value::Bool(b) => serializer.serialize_bool(b),
+            Value::IpAddr(ref ip_addr) => serializer.serialize_str(&ip_addr.to_string()),
             Value::Date(ref date) => serializer.serialize_str(&date.to_rfc3339()),
             Value::Facet(ref facet) => facet.serialize(serializer),
             Value::Bytes(ref bytes) => serializer.serialize_bytes(bytes),
+            Value::JsonObject(ref json_object) => json_object.serialize(serializer),
         }
     }
 }
@@ -113,6 +138,10 @@ impl<'de> Deserialize<'de> for Value {
                 Ok(Value::F64(v))
             }
 
+            fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+                Ok(Value::Bool(v))
+            }
+
             fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
                 Ok(Value::Str(v.to_owned()))
             }
@@ -120,6 +149,15 @@ impl<'de> Deserialize<'de> for Value {
             fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
                 Ok(Value::Str(v))
             }
+
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let json_object =
+                    Map::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+                Ok(Value::JsonObject(json_object))
+            }
         }
 
         deserializer.deserialize_any(ValueVisitor)
@@ -191,6 +229,28 @@ impl Value {
         }
     }
 
+    /// Returns the bool value, provided the value is of the `Bool` type.
+    ///
+    /// Return None if the value is not of type `Bool`.
+    pub fn bool_value(&self) -> Option<bool> {
+        if let Value::Bool(value) = self {
+            Some(*value)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the ip address, provided the value is of the `IpAddr` type.
+    ///
+    /// Return None if the value is not of type `IpAddr`.
+    pub fn ip_addr_value(&self) -> Option<Ipv6Addr> {
+        if let Value::IpAddr(value) = self {
+            Some(*value)
+        } else {
+            None
+        }
+    }
+
     /// Returns the Date-value, provided the value is of the `Date` type.
     ///
     /// Returns None if the value is not of type `Date`.
@@ -212,6 +272,17 @@ impl Value {
             None
         }
     }
+
+    /// Returns the json object, provided the value is of the `JsonObject` type.
+    ///
+    /// Returns None if the value is not of type `JsonObject`.
+    pub fn as_json(&self) -> Option<&Map<String, serde_json::Value>> {
+        if let Value::JsonObject(json_object) = self {
+            Some(json_object)
+        } else {
+            None
+        }
+    }
 }
 
 impl From<String> for Value {
@@ -238,6 +309,40 @@ impl From<f64> for Value {
     }
 }
 
+impl From<bool> for Value {
+    fn from(v: bool) -> Value {
+        Value::Bool(v)
+    }
+}
+
+impl From<Ipv6Addr> for Value {
+    fn from(v: Ipv6Addr) -> Value {
+        Value::IpAddr(v)
+    }
+}
+
+/// IPv4 addresses are mapped into the IPv6 space.
+impl From<IpAddr> for Value {
+    fn from(v: IpAddr) -> Value {
+        Value::IpAddr(ip_addr_to_ipv6(v))
+    }
+}
+
+/// Maps IPv4 addresses into the IPv6 space, e.g. `10.0.0.1` to `::ffff:10.0.0.1`.
+pub(crate) fn ip_addr_to_ipv6(ip_addr: IpAddr) -> Ipv6Addr {
+    match ip_addr {
+        IpAddr::V4(ipv4_addr) => ipv4_addr.to_ipv6_mapped(),
+        IpAddr::V6(ipv6_addr) => ipv6_addr,
+    }
+}
+
+/// Parses an IPv4 address in dotted-quad notation, or an IPv6 address.
+///
+/// IPv4 addresses are mapped into the IPv6 space.
+pub(crate) fn parse_ip_addr(text: &str) -> Result<Ipv6Addr, AddrParseError> {
+    IpAddr::from_str(text).map(ip_addr_to_ipv6)
+}
+
 impl From<crate::DateTime> for Value {
     fn from(date_time: crate::DateTime) -> Value {
         Value::Date(date_time)
@@ -274,13 +379,22 @@ impl From<PreTokenizedString> for Value {
     }
 }
 
+impl From<Map<String, serde_json::Value>> for Value {
+    fn from(json_object: Map<String, serde_json::Value>) -> Value {
+        Value::JsonObject(json_object)
+    }
+}
+
+pub(crate) use self::binary_serialize::skip_value;
+
 mod binary_serialize {
     use super::Value;
     use crate::schema::Facet;
     use crate::tokenizer::PreTokenizedString;
     use chrono::{TimeZone, Utc};
-    use common::{f64_to_u64, u64_to_f64, BinarySerializable};
+    use common::{f64_to_u64, u64_to_f64, BinarySerializable, FixedSize, VInt};
     use std::io::{self, Read, Write};
+    use std::net::Ipv6Addr;
 
     const TEXT_CODE: u8 = 0;
     const U64_CODE: u8 = 1;
@@ -290,10 +404,16 @@ mod binary_serialize {
     const DATE_CODE: u8 = 5;
     const F64_CODE: u8 = 6;
     const EXT_CODE: u8 = 7;
+    const BOOL_CODE: u8 = 8;
+    const IP_ADDR_CODE: u8 = 9;
 
     // extended types
 
     const TOK_STR_CODE: u8 = 0;
+    const JSON_OBJ_CODE: u8 = 1;
+    /// Dates with a fractional second. The others are serialized with `DATE_CODE`,
+    /// like before dates kept their fractional second.
+    const PRECISE_DATE_CODE: u8 = 2;
 
     impl BinarySerializable for Value {
         fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
@@ -326,9 +446,25 @@ mod binary_serialize {
                     F64_CODE.serialize(writer)?;
                     f64_to_u64(*val).serialize(writer)
                 }
+                Value::Bool(ref val) => {
+                    BOOL_CODE.serialize(writer)?;
+                    val.serialize(writer)
+                }
+                Value::IpAddr(ref val) => {
+                    IP_ADDR_CODE.serialize(writer)?;
+                    u128::from(*val).serialize(writer)
+                }
                 Value::Date(ref val) => {
-                    DATE_CODE.serialize(writer)?;
-                    val.timestamp().serialize(writer)
+                    let nanos = val.timestamp_subsec_nanos();
+                    if nanos == 0 {
+                        DATE_CODE.serialize(writer)?;
+                        val.timestamp().serialize(writer)
+                    } else {
+                        EXT_CODE.serialize(writer)?;
+                        PRECISE_DATE_CODE.serialize(writer)?;
+                        val.timestamp().serialize(writer)?;
+                        nanos.serialize(writer)
+                    }
                 }
                 Value::Facet(ref facet) => {
                     HIERARCHICAL_FACET_CODE.serialize(writer)?;
@@ -338,6 +474,18 @@ mod binary_serialize {
                     BYTES_CODE.serialize(writer)?;
                     bytes.serialize(writer)
                 }
+                Value::JsonObject(ref json_object) => {
+                    EXT_CODE.serialize(writer)?;
+                    JSON_OBJ_CODE.serialize(writer)?;
+                    if let Ok(text) = serde_json::to_string(json_object) {
+                        text.serialize(writer)
+                    } else {
+                        Err(io::Error::new(
+                            io::ErrorKind::Other,
+                            "Failed to dump Value::JsonObject(_) to json.",
+                        ))
+                    }
+                }
             }
         }
         fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
@@ -359,6 +507,8 @@ mod binary_serialize {
                     let value = u64_to_f64(u64::deserialize(reader)?);
                     Ok(Value::F64(value))
                 }
+                BOOL_CODE => Ok(Value::Bool(bool::deserialize(reader)?)),
+                IP_ADDR_CODE => Ok(Value::IpAddr(Ipv6Addr::from(u128::deserialize(reader)?))),
                 DATE_CODE => {
                     let timestamp = i64::deserialize(reader)?;
                     Ok(Value::Date(Utc.timestamp(timestamp, 0)))
@@ -380,6 +530,22 @@ mod binary_serialize {
                                 ))
                             }
                         }
+                        PRECISE_DATE_CODE => {
+                            let timestamp = i64::deserialize(reader)?;
+                            let nanos = u32::deserialize(reader)?;
+                            Ok(Value::Date(Utc.timestamp(timestamp, nanos)))
+                        }
+                        JSON_OBJ_CODE => {
+                            let str_val = String::deserialize(reader)?;
+                            if let Ok(json_object) = serde_json::from_str(&str_val) {
+                                Ok(Value::JsonObject(json_object))
+                            } else {
+                                Err(io::Error::new(
+                                    io::ErrorKind::Other,
+                                    "Failed to parse string data as Value::JsonObject(_).",
+                                ))
+                            }
+                        }
                         _ => Err(io::Error::new(
                             io::ErrorKind::InvalidData,
                             format!(
@@ -396,12 +562,61 @@ mod binary_serialize {
             }
         }
     }
+
+    /// Moves `bytes` past a serialized value, without materializing it.
+    ///
+    /// The values are either of a fixed size, or prefixed by their length.
+    pub(crate) fn skip_value(bytes: &mut &[u8]) -> io::Result<()> {
+        let type_code = u8::deserialize(bytes)?;
+        let num_bytes = match type_code {
+            TEXT_CODE | HIERARCHICAL_FACET_CODE | BYTES_CODE => {
+                VInt::deserialize(bytes)?.val() as usize
+            }
+            U64_CODE => u64::SIZE_IN_BYTES,
+            I64_CODE | DATE_CODE => i64::SIZE_IN_BYTES,
+            F64_CODE => u64::SIZE_IN_BYTES,
+            BOOL_CODE => bool::SIZE_IN_BYTES,
+            IP_ADDR_CODE => u128::SIZE_IN_BYTES,
+            EXT_CODE => {
+                let ext_type_code = u8::deserialize(bytes)?;
+                match ext_type_code {
+                    TOK_STR_CODE | JSON_OBJ_CODE => VInt::deserialize(bytes)?.val() as usize,
+                    PRECISE_DATE_CODE => i64::SIZE_IN_BYTES + u32::SIZE_IN_BYTES,
+                    _ => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "No extened field type is associated with code {:?}",
+                                ext_type_code
+                            ),
+                        ));
+                    }
+                }
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("No field type is associated with code {:?}", type_code),
+                ));
+            }
+        };
+        if bytes.len() < num_bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "The serialized value is truncated.",
+            ));
+        }
+        *bytes = &bytes[num_bytes..];
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::Value;
     use crate::DateTime;
+    use common::BinarySerializable;
+    use std::net::{IpAddr, Ipv4Addr};
     use std::str::FromStr;
 
     #[test]
@@ -410,4 +625,59 @@ mod tests {
         let serialized_value_json = serde_json::to_string_pretty(&value).unwrap();
         assert_eq!(serialized_value_json, r#""1996-12-20T00:39:57+00:00""#);
     }
+
+    #[test]
+    fn test_date_value_binary_serialization() {
+        for date_str in [
+            "1996-12-20T00:39:57+00:00",
+            "1996-12-20T00:39:57.123456789+00:00",
+        ] {
+            let value = Value::Date(DateTime::from_str(date_str).unwrap());
+            let mut buffer = Vec::new();
+            value.serialize(&mut buffer).unwrap();
+            assert_eq!(Value::deserialize(&mut &buffer[..]).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_json_object_value_serialization() {
+        let json_object =
+            serde_json::from_str(r#"{"user": {"id": 12, "tags": ["a", "b"]}}"#).unwrap();
+        let value = Value::JsonObject(json_object);
+        let mut buffer = Vec::new();
+        value.serialize(&mut buffer).unwrap();
+        assert_eq!(Value::deserialize(&mut &buffer[..]).unwrap(), value);
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, r#"{"user":{"id":12,"tags":["a","b"]}}"#);
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+    }
+
+    #[test]
+    fn test_bool_value_serialization() {
+        for value in [Value::Bool(true), Value::Bool(false)] {
+            let mut buffer = Vec::new();
+            value.serialize(&mut buffer).unwrap();
+            assert_eq!(Value::deserialize(&mut &buffer[..]).unwrap(), value);
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+        }
+        assert_eq!(serde_json::to_string(&Value::Bool(true)).unwrap(), "true");
+    }
+
+    #[test]
+    fn test_ip_addr_value_serialization() {
+        let value = Value::from(IpAddr::from_str("192.168.0.1").unwrap());
+        assert_eq!(
+            value,
+            Value::IpAddr(Ipv4Addr::new(192, 168, 0, 1).to_ipv6_mapped())
+        );
+        let mut buffer = Vec::new();
+        value.serialize(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), 1 + 16);
+        assert_eq!(Value::deserialize(&mut &buffer[..]).unwrap(), value);
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#""::ffff:192.168.0.1""#
+        );
+    }
 }
@@ -1,16 +1,41 @@
+mod phrase;
+
+use self::phrase::{SnippetPhrase, SnippetTermsCollector};
 use crate::query::Query;
-use crate::schema::Field;
 use crate::schema::Value;
+use crate::schema::{Field, Term};
 use crate::tokenizer::{TextAnalyzer, Token};
 use crate::Searcher;
 use crate::{Document, Score};
 use htmlescape::encode_minimal;
 use std::cmp::Ordering;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::Range;
 
 const DEFAULT_MAX_NUM_CHARS: usize = 150;
 
+const DEFAULT_FRAGMENT_SEPARATOR: &str = " ... ";
+
+/// Scores the candidate passages of a `SnippetGenerator`, to select its snippets.
+///
+/// It is implemented for the closures taking a `FragmentCandidate` and the text.
+pub trait PassageScorer: Send + Sync + 'static {
+    /// Returns the score of a passage of `text`.
+    fn score(&self, passage: &FragmentCandidate, text: &str) -> Score;
+}
+
+impl<F> PassageScorer for F
+where
+    F: Fn(&FragmentCandidate, &str) -> Score + Send + Sync + 'static,
+{
+    fn score(&self, passage: &FragmentCandidate, text: &str) -> Score {
+        self(passage, text)
+    }
+}
+
+/// A passage of a text, candidate to become a `Snippet`.
+///
+/// The offsets are byte offsets in the text.
 #[derive(Debug)]
 pub struct FragmentCandidate {
     score: Score,
@@ -36,56 +61,109 @@ impl FragmentCandidate {
         }
     }
 
+    /// Returns the score of the passage.
+    ///
+    /// Unless a `PassageScorer` is set, it is the sum of the weights of
+    /// the highlighted terms.
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    /// Returns the byte range of the passage in the text.
+    pub fn range(&self) -> Range<usize> {
+        self.start_offset..self.stop_offset
+    }
+
+    /// Returns the byte ranges of the highlighted terms in the text.
+    pub fn highlighted(&self) -> &[Range<usize>] {
+        &self.highlighted
+    }
+
     /// Updates `score` and `highlighted` fields of the objects.
     ///
-    /// taking the token and terms, the token is added to the fragment.
-    /// if the token is one of the terms, the score
+    /// taking the token and its score, the token is added to the fragment.
+    /// if the token is to be highlighted, the score
     /// and highlighted fields are updated in the fragment.
-    fn try_add_token(&mut self, token: &Token, terms: &BTreeMap<String, Score>) {
+    fn try_add_token(&mut self, token: &Token, score_opt: Option<Score>) {
         self.stop_offset = token.offset_to;
 
-        if let Some(&score) = terms.get(&token.text.to_lowercase()) {
+        if let Some(score) = score_opt {
             self.score += score;
             self.highlighted.push(token.offset_from..token.offset_to);
         }
     }
 }
 
+const HIGHLIGHTEN_PREFIX: &str = "<b>";
+const HIGHLIGHTEN_POSTFIX: &str = "</b>";
+
+/// How the highlighted parts of a `Snippet` are rendered by `Snippet::to_html`.
+#[derive(Clone, Debug)]
+struct SnippetMarkup {
+    pre_tag: String,
+    post_tag: String,
+    html_escape: bool,
+}
+
+impl Default for SnippetMarkup {
+    fn default() -> SnippetMarkup {
+        SnippetMarkup {
+            pre_tag: HIGHLIGHTEN_PREFIX.to_string(),
+            post_tag: HIGHLIGHTEN_POSTFIX.to_string(),
+            html_escape: true,
+        }
+    }
+}
+
+impl SnippetMarkup {
+    fn push_text(&self, text: &str, output: &mut String) {
+        if self.html_escape {
+            output.push_str(&encode_minimal(text));
+        } else {
+            output.push_str(text);
+        }
+    }
+}
+
 /// `Snippet`
 /// Contains a fragment of a document, and some highlighed parts inside it.
 #[derive(Debug)]
 pub struct Snippet {
     fragments: String,
     highlighted: Vec<Range<usize>>,
+    markup: SnippetMarkup,
 }
 
-const HIGHLIGHTEN_PREFIX: &str = "<b>";
-const HIGHLIGHTEN_POSTFIX: &str = "</b>";
-
 impl Snippet {
     /// Create a new, empty, `Snippet`
     pub fn empty() -> Snippet {
         Snippet {
             fragments: String::new(),
             highlighted: Vec::new(),
+            markup: SnippetMarkup::default(),
         }
     }
 
     /// Returns a hignlightned html from the `Snippet`.
+    ///
+    /// The highlighted parts are surrounded by the tags of the `SnippetGenerator`,
+    /// `<b>` and `</b>` by default, and the text is html-escaped unless
+    /// disabled with `SnippetGenerator::set_html_escape`.
     pub fn to_html(&self) -> String {
         let mut html = String::new();
         let mut start_from: usize = 0;
 
         for item in self.highlighted.iter() {
-            html.push_str(&encode_minimal(&self.fragments[start_from..item.start]));
-            html.push_str(HIGHLIGHTEN_PREFIX);
-            html.push_str(&encode_minimal(&self.fragments[item.clone()]));
-            html.push_str(HIGHLIGHTEN_POSTFIX);
+            self.markup
+                .push_text(&self.fragments[start_from..item.start], &mut html);
+            html.push_str(&self.markup.pre_tag);
+            self.markup
+                .push_text(&self.fragments[item.clone()], &mut html);
+            html.push_str(&self.markup.post_tag);
             start_from = item.end;
         }
-        html.push_str(&encode_minimal(
-            &self.fragments[start_from..self.fragments.len()],
-        ));
+        self.markup
+            .push_text(&self.fragments[start_from..self.fragments.len()], &mut html);
         html
     }
 
@@ -95,6 +173,8 @@ impl Snippet {
     }
 
     /// Returns a list of higlighted positions from the `Snippet`.
+    ///
+    /// The positions are byte ranges in `fragments()`.
     pub fn highlighted(&self) -> &[Range<usize>] {
         &self.highlighted
     }
@@ -121,23 +201,48 @@ impl Snippet {
 ///
 /// Fragments must be valid in the sense that `&text[fragment.start..fragment.stop]`\
 /// has to be a valid string.
+///
+/// The terms are highlighted wherever they appear, while the terms
+/// of the `phrases` are only highlighted where the phrase matches.
+///
+/// A fragment also ends before the first token following one of the `boundary_chars`.
 fn search_fragments<'a>(
     tokenizer: &TextAnalyzer,
     text: &'a str,
     terms: &BTreeMap<String, Score>,
+    phrases: &[SnippetPhrase],
+    boundary_chars: &[char],
     max_num_chars: usize,
 ) -> Vec<FragmentCandidate> {
     let mut token_stream = tokenizer.token_stream(text);
+    let mut tokens: Vec<Token> = Vec::new();
+    while let Some(next) = token_stream.next() {
+        tokens.push(next.clone());
+    }
+    let token_texts: Vec<(String, usize)> = tokens
+        .iter()
+        .map(|token| (token.text.to_lowercase(), token.position))
+        .collect();
+    let mut phrase_tokens: BTreeMap<usize, Score> = BTreeMap::new();
+    for phrase in phrases {
+        phrase_tokens.extend(phrase.matching_tokens(&token_texts));
+    }
     let mut fragment = FragmentCandidate::new(0);
     let mut fragments: Vec<FragmentCandidate> = vec![];
-    while let Some(next) = token_stream.next() {
-        if (next.offset_to - fragment.start_offset) > max_num_chars {
+    for (token_ord, (next, (token_text, _))) in tokens.iter().zip(&token_texts).enumerate() {
+        let is_after_boundary = next.offset_from > fragment.stop_offset
+            && text[fragment.stop_offset..next.offset_from].contains(boundary_chars);
+        if (next.offset_to - fragment.start_offset) > max_num_chars || is_after_boundary {
             if fragment.score > 0.0 {
                 fragments.push(fragment)
             };
             fragment = FragmentCandidate::new(next.offset_from);
         }
-        fragment.try_add_token(next, terms);
+        let score_opt = terms
+            .get(token_text)
+            .or_else(|| phrase_tokens.get(&token_ord))
+            .cloned();
+        fragment.try_add_token(next, score_opt);
     }
     if fragment.score > 0.0 {
         fragments.push(fragment)
@@ -146,41 +251,80 @@ fn search_fragments<'a>(
     fragments
 }
 
+/// Orders the fragments by score, and then by reverse offsets: the best fragment
+/// is the greatest one.
+fn cmp_fragments(left: &FragmentCandidate, right: &FragmentCandidate) -> Ordering {
+    let cmp_score = left
+        .score
+        .partial_cmp(&right.score)
+        .unwrap_or(Ordering::Equal);
+    if cmp_score == Ordering::Equal {
+        (right.start_offset, right.stop_offset).cmp(&(left.start_offset, left.stop_offset))
+    } else {
+        cmp_score
+    }
+}
+
+fn fragment_to_snippet(fragment: &FragmentCandidate, text: &str) -> Snippet {
+    let fragment_text = &text[fragment.start_offset..fragment.stop_offset];
+    let highlighted = fragment
+        .highlighted
+        .iter()
+        .map(|item| item.start - fragment.start_offset..item.end - fragment.start_offset)
+        .collect();
+    Snippet {
+        fragments: fragment_text.to_string(),
+        highlighted,
+        markup: SnippetMarkup::default(),
+    }
+}
+
 /// Returns a Snippet
 ///
 /// Takes a vector of `FragmentCandidate`s and the text.
 /// Figures out the best fragment from it and creates a snippet.
 fn select_best_fragment_combination(fragments: &[FragmentCandidate], text: &str) -> Snippet {
-    let best_fragment_opt = fragments.iter().max_by(|left, right| {
-        let cmp_score = left
-            .score
-            .partial_cmp(&right.score)
-            .unwrap_or(Ordering::Equal);
-        if cmp_score == Ordering::Equal {
-            (right.start_offset, right.stop_offset).cmp(&(left.start_offset, left.stop_offset))
-        } else {
-            cmp_score
-        }
-    });
+    let best_fragment_opt = fragments
+        .iter()
+        .max_by(|left, right| cmp_fragments(left, right));
     if let Some(fragment) = best_fragment_opt {
-        let fragment_text = &text[fragment.start_offset..fragment.stop_offset];
-        let highlighted = fragment
-            .highlighted
-            .iter()
-            .map(|item| item.start - fragment.start_offset..item.end - fragment.start_offset)
-            .collect();
-        Snippet {
-            fragments: fragment_text.to_string(),
-            highlighted,
-        }
+        fragment_to_snippet(fragment, text)
     } else {
         // when there no fragments to chose from,
         // for now create a empty snippet
-        Snippet {
-            fragments: String::new(),
-            highlighted: vec![],
+        Snippet::empty()
+    }
+}
+
+/// Returns at most `num_fragments` Snippets.
+///
+/// The best non-overlapping fragments are selected, and returned in the order
+/// of the text.
+fn select_best_fragments(
+    fragments: &[FragmentCandidate],
+    text: &str,
+    num_fragments: usize,
+) -> Vec<Snippet> {
+    let mut sorted_fragments: Vec<&FragmentCandidate> = fragments.iter().collect();
+    sorted_fragments.sort_by(|left, right| cmp_fragments(right, left));
+    let mut selected_fragments: Vec<&FragmentCandidate> = Vec::with_capacity(num_fragments);
+    for fragment in sorted_fragments {
+        if selected_fragments.len() >= num_fragments {
+            break;
+        }
+        let overlaps = selected_fragments.iter().any(|selected| {
+            fragment.start_offset < selected.stop_offset
+                && selected.start_offset < fragment.stop_offset
+        });
+        if !overlaps {
+            selected_fragments.push(fragment);
         }
     }
+    selected_fragments.sort_by_key(|fragment| fragment.start_offset);
+    selected_fragments
+        .into_iter()
+        .map(|fragment| fragment_to_snippet(fragment, text))
+        .collect()
 }
 
 /// `SnippetGenerator`
@@ -221,14 +365,98 @@ fn select_best_fragment_combination(fragments: &[FragmentCandidate], text: &str)
 /// let snippet = snippet_generator.snippet_from_doc(&doc);
 /// let snippet_html: String = snippet.to_html();
 /// assert_eq!(snippet_html, "Comme je descendais des Fleuves impassibles,\n  Je ne me sentis plus guidé par les <b>haleurs</b> :\n Des");
+///
+/// // several passages, with a custom markup.
+/// snippet_generator.set_max_num_chars(30);
+/// snippet_generator.set_num_fragments(2);
+/// snippet_generator.set_pre_tag("<em>");
+/// snippet_generator.set_post_tag("</em>");
+/// let snippets = snippet_generator.snippets_from_doc(&doc);
+/// assert_eq!(snippets.len(), 2);
+/// assert_eq!(
+///     snippet_generator.snippets_to_html(&snippets),
+///     "<em>haleurs</em> :\n Des Peaux-Rouges ... <em>flamands</em> ou de cotons anglais"
+/// );
 /// #    Ok(())
 /// # }
 /// ```
 pub struct SnippetGenerator {
     terms_text: BTreeMap<String, Score>,
+    phrases: Vec<SnippetPhrase>,
+    is_position_aware: bool,
     tokenizer: TextAnalyzer,
     field: Field,
     max_num_chars: usize,
+    num_fragments: usize,
+    fragment_separator: String,
+    markup: SnippetMarkup,
+    boundary_chars: Vec<char>,
+    passage_scorer: Option<Box<dyn PassageScorer>>,
+}
+
+/// Returns true iff the positions of the field are indexed.
+fn has_positions(searcher: &Searcher, field: Field) -> bool {
+    searcher
+        .schema()
+        .get_field_entry(field)
+        .field_type()
+        .get_index_record_option()
+        .map(|record_option| record_option.has_positions())
+        .unwrap_or(false)
+}
+
+/// Returns the weight of a term, or `None` if no document contains it.
+fn term_weight(searcher: &Searcher, term: &Term) -> crate::Result<Option<Score>> {
+    let doc_freq = searcher.doc_freq(term)?;
+    if doc_freq > 0 {
+        Ok(Some(1.0 / (1.0 + doc_freq as Score)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Returns the weights of the terms of the query for the field, and its phrases if
+/// `with_phrases` is true.
+///
+/// The terms of the phrases are only included in the weights if `with_phrases` is false.
+fn collect_query_terms(
+    searcher: &Searcher,
+    query: &dyn Query,
+    field: Field,
+    with_phrases: bool,
+) -> crate::Result<(BTreeMap<String, Score>, Vec<SnippetPhrase>)> {
+    let mut terms = BTreeSet::new();
+    let mut phrase_queries = Vec::new();
+    query.visit(&mut SnippetTermsCollector {
+        field,
+        terms: &mut terms,
+        phrases: if with_phrases {
+            Some(&mut phrase_queries)
+        } else {
+            None
+        },
+    });
+    let mut terms_text: BTreeMap<String, Score> = Default::default();
+    for term in terms {
+        if let Some(score) = term_weight(searcher, &term)? {
+            terms_text.insert(term.text().to_string(), score);
+        }
+    }
+    let mut phrases = Vec::new();
+    'phrases: for phrase_query in phrase_queries {
+        let phrase_terms = phrase_query.phrase_terms_with_offsets();
+        let mut phrase_terms_text = Vec::with_capacity(phrase_terms.len());
+        for (offset, term) in phrase_terms {
+            if let Some(score) = term_weight(searcher, term)? {
+                phrase_terms_text.push((*offset, term.text().to_string(), score));
+            } else {
+                // the phrase cannot match.
+                continue 'phrases;
+            }
+        }
+        phrases.push(SnippetPhrase::new(phrase_terms_text, phrase_query.slop()));
+    }
+    Ok((terms_text, phrases))
 }
 
 impl SnippetGenerator {
@@ -238,33 +466,119 @@ impl SnippetGenerator {
         query: &dyn Query,
         field: Field,
     ) -> crate::Result<SnippetGenerator> {
-        let mut terms = BTreeMap::new();
-        query.query_terms(&mut terms);
-        let mut terms_text: BTreeMap<String, Score> = Default::default();
-        for (term, _) in terms {
-            if term.field() != field {
-                continue;
-            }
-            let doc_freq = searcher.doc_freq(&term)?;
-            if doc_freq > 0 {
-                let score = 1.0 / (1.0 + doc_freq as Score);
-                terms_text.insert(term.text().to_string(), score);
-            }
-        }
+        let is_position_aware = has_positions(searcher, field);
+        let (terms_text, phrases) = collect_query_terms(searcher, query, field, is_position_aware)?;
         let tokenizer = searcher.index().tokenizer_for_field(field)?;
         Ok(SnippetGenerator {
             terms_text,
+            phrases,
+            is_position_aware,
             tokenizer,
             field,
             max_num_chars: DEFAULT_MAX_NUM_CHARS,
+            num_fragments: 1,
+            fragment_separator: DEFAULT_FRAGMENT_SEPARATOR.to_string(),
+            markup: SnippetMarkup::default(),
+            boundary_chars: Vec::new(),
+            passage_scorer: None,
         })
     }
 
+    /// Returns the weights of the terms of a query for a field, as computed by
+    /// `SnippetGenerator::create`.
+    ///
+    /// The weight of a term decreases with its document frequency. The terms of
+    /// the phrase queries are excluded if the positions of the field are indexed,
+    /// as they are highlighted with their phrase.
+    pub fn query_term_weights(
+        searcher: &Searcher,
+        query: &dyn Query,
+        field: Field,
+    ) -> crate::Result<BTreeMap<String, Score>> {
+        let (terms_text, _) =
+            collect_query_terms(searcher, query, field, has_positions(searcher, field))?;
+        Ok(terms_text)
+    }
+
     /// Sets a maximum number of chars.
     pub fn set_max_num_chars(&mut self, max_num_chars: usize) {
         self.max_num_chars = max_num_chars;
     }
 
+    /// Sets the maximum number of fragments returned by `snippets`.
+    ///
+    /// By default, a single fragment is returned.
+    pub fn set_num_fragments(&mut self, num_fragments: usize) {
+        self.num_fragments = num_fragments;
+    }
+
+    /// Sets the tag inserted before the highlighted parts by `Snippet::to_html`.
+    ///
+    /// By default, it is `<b>`.
+    pub fn set_pre_tag(&mut self, pre_tag: &str) {
+        self.markup.pre_tag = pre_tag.to_string();
+    }
+
+    /// Sets the tag inserted after the highlighted parts by `Snippet::to_html`.
+    ///
+    /// By default, it is `</b>`.
+    pub fn set_post_tag(&mut self, post_tag: &str) {
+        self.markup.post_tag = post_tag.to_string();
+    }
+
+    /// Sets the separator inserted between the fragments by `snippets_to_html`.
+    ///
+    /// By default, it is ` ... `.
+    pub fn set_fragment_separator(&mut self, fragment_separator: &str) {
+        self.fragment_separator = fragment_separator.to_string();
+    }
+
+    /// Sets whether `Snippet::to_html` html-escapes the text of the fragments.
+    ///
+    /// The text is escaped by default. It should only be disabled if the stored text
+    /// is trusted, or escaped by the caller.
+    pub fn set_html_escape(&mut self, html_escape: bool) {
+        self.markup.html_escape = html_escape;
+    }
+
+    /// Returns true iff the terms of the phrase queries are only highlighted where
+    /// the phrase matches.
+    ///
+    /// This requires the positions of the field to be indexed. Otherwise, the terms of
+    /// the phrases are highlighted wherever they appear, as the terms of the other queries.
+    pub fn is_position_aware(&self) -> bool {
+        self.is_position_aware
+    }
+
+    /// Sets the characters ending a passage, e.g. `&['.', '?', '!']` for the passages
+    /// not to span several sentences.
+    ///
+    /// By default, the passages are only cut when they exceed the maximum number of chars.
+    pub fn set_boundary_chars(&mut self, boundary_chars: &[char]) {
+        self.boundary_chars = boundary_chars.to_vec();
+    }
+
+    /// Sets the scorer of the passages, used to select the best fragments.
+    ///
+    /// By default, the score of a passage is the sum of the weights of its
+    /// highlighted terms.
+    pub fn set_passage_scorer<S: PassageScorer>(&mut self, passage_scorer: S) {
+        self.passage_scorer = Some(Box::new(passage_scorer));
+    }
+
+    /// Returns the weights of the terms highlighted wherever they appear.
+    pub fn term_weights(&self) -> &BTreeMap<String, Score> {
+        &self.terms_text
+    }
+
+    /// Sets the weights of the terms highlighted wherever they appear,
+    /// replacing the weights derived from the query.
+    ///
+    /// The terms are expected to be lowercased.
+    pub fn set_term_weights(&mut self, term_weights: BTreeMap<String, Score>) {
+        self.terms_text = term_weights;
+    }
+
     #[cfg(test)]
     pub fn terms_text(&self) -> &BTreeMap<String, Score> {
         &self.terms_text
@@ -283,24 +597,87 @@ impl SnippetGenerator {
         self.snippet(&text)
     }
 
+    /// Returns the candidate passages of the text containing at least one of the terms
+    /// of the query, scored by the passage scorer, in the order of the text.
+    ///
+    /// The text is tokenized with the tokenizer of the field, and does not need to be stored.
+    pub fn fragment_candidates(&self, text: &str) -> Vec<FragmentCandidate> {
+        let mut fragment_candidates = search_fragments(
+            &self.tokenizer,
+            text,
+            &self.terms_text,
+            &self.phrases,
+            &self.boundary_chars,
+            self.max_num_chars,
+        );
+        if let Some(passage_scorer) = self.passage_scorer.as_ref() {
+            for fragment_candidate in &mut fragment_candidates {
+                fragment_candidate.score = passage_scorer.score(fragment_candidate, text);
+            }
+        }
+        fragment_candidates
+    }
+
     /// Generates a snippet for the given text.
     pub fn snippet(&self, text: &str) -> Snippet {
-        let fragment_candidates =
-            search_fragments(&self.tokenizer, text, &self.terms_text, self.max_num_chars);
-        select_best_fragment_combination(&fragment_candidates[..], text)
+        let fragment_candidates = self.fragment_candidates(text);
+        let mut snippet = select_best_fragment_combination(&fragment_candidates[..], text);
+        snippet.markup = self.markup.clone();
+        snippet
+    }
+
+    /// Generates the snippets for the given `Document`.
+    ///
+    /// This method extract the text associated to the `SnippetGenerator`'s field
+    /// and computes its snippets.
+    pub fn snippets_from_doc(&self, doc: &Document) -> Vec<Snippet> {
+        let text: String = doc
+            .get_all(self.field)
+            .flat_map(Value::text)
+            .collect::<Vec<&str>>()
+            .join(" ");
+        self.snippets(&text)
+    }
+
+    /// Generates at most `num_fragments` snippets for the given text.
+    ///
+    /// The fragments with the highest scores that do not overlap are selected,
+    /// and returned in the order of the text. No snippet is returned if the text
+    /// does not contain any of the terms of the query.
+    pub fn snippets(&self, text: &str) -> Vec<Snippet> {
+        let fragment_candidates = self.fragment_candidates(text);
+        let mut snippets =
+            select_best_fragments(&fragment_candidates[..], text, self.num_fragments);
+        for snippet in &mut snippets {
+            snippet.markup = self.markup.clone();
+        }
+        snippets
+    }
+
+    /// Returns the html of the snippets, joined by the fragment separator.
+    pub fn snippets_to_html(&self, snippets: &[Snippet]) -> String {
+        snippets
+            .iter()
+            .map(Snippet::to_html)
+            .collect::<Vec<String>>()
+            .join(&self.fragment_separator)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{search_fragments, select_best_fragment_combination};
-    use crate::query::QueryParser;
-    use crate::schema::{IndexRecordOption, Schema, TextFieldIndexing, TextOptions, TEXT};
+    use super::{
+        search_fragments, select_best_fragment_combination, select_best_fragments,
+        FragmentCandidate,
+    };
+    use crate::query::{PhraseQuery, QueryParser};
+    use crate::schema::{IndexRecordOption, Schema, Term, TextFieldIndexing, TextOptions, TEXT};
     use crate::tokenizer::SimpleTokenizer;
     use crate::Index;
-    use crate::SnippetGenerator;
+    use crate::{Score, SnippetGenerator};
     use maplit::btreemap;
     use std::collections::BTreeMap;
+    use std::ops::Range;
 
     const TEST_TEXT: &str = r#"Rust is a systems programming language sponsored by
 Mozilla which describes it as a "safe, concurrent, practical language", supporting functional and
@@ -322,7 +699,14 @@ Survey in 2016, 2017, and 2018."#;
             String::from("rust") => 1.0,
             String::from("language") => 0.9
         };
-        let fragments = search_fragments(&From::from(SimpleTokenizer), TEST_TEXT, &terms, 100);
+        let fragments = search_fragments(
+            &From::from(SimpleTokenizer),
+            TEST_TEXT,
+            &terms,
+            &[],
+            &[],
+            100,
+        );
         assert_eq!(fragments.len(), 7);
         {
             let first = &fragments[0];
@@ -349,7 +733,14 @@ Survey in 2016, 2017, and 2018."#;
                 String::from("rust") =>1.0,
                 String::from("language") => 0.9
             };
-            let fragments = search_fragments(&From::from(SimpleTokenizer), TEST_TEXT, &terms, 20);
+            let fragments = search_fragments(
+                &From::from(SimpleTokenizer),
+                TEST_TEXT,
+                &terms,
+                &[],
+                &[],
+                20,
+            );
             {
                 let first = &fragments[0];
                 assert_eq!(first.score, 1.0);
@@ -363,7 +754,14 @@ Survey in 2016, 2017, and 2018."#;
                 String::from("rust") =>0.9,
                 String::from("language") => 1.0
             };
-            let fragments = search_fragments(&From::from(SimpleTokenizer), TEST_TEXT, &terms, 20);
+            let fragments = search_fragments(
+                &From::from(SimpleTokenizer),
+                TEST_TEXT,
+                &terms,
+                &[],
+                &[],
+                20,
+            );
             //assert_eq!(fragments.len(), 7);
             {
                 let first = &fragments[0];
@@ -382,7 +780,7 @@ Survey in 2016, 2017, and 2018."#;
         let mut terms = BTreeMap::new();
         terms.insert(String::from("c"), 1.0);
 
-        let fragments = search_fragments(&From::from(SimpleTokenizer), text, &terms, 3);
+        let fragments = search_fragments(&From::from(SimpleTokenizer), text, &terms, &[], &[], 3);
 
         assert_eq!(fragments.len(), 1);
         {
@@ -404,7 +802,7 @@ Survey in 2016, 2017, and 2018."#;
         let mut terms = BTreeMap::new();
         terms.insert(String::from("f"), 1.0);
 
-        let fragments = search_fragments(&From::from(SimpleTokenizer), text, &terms, 3);
+        let fragments = search_fragments(&From::from(SimpleTokenizer), text, &terms, &[], &[], 3);
 
         assert_eq!(fragments.len(), 2);
         {
@@ -427,7 +825,7 @@ Survey in 2016, 2017, and 2018."#;
         terms.insert(String::from("f"), 1.0);
         terms.insert(String::from("a"), 0.9);
 
-        let fragments = search_fragments(&From::from(SimpleTokenizer), text, &terms, 7);
+        let fragments = search_fragments(&From::from(SimpleTokenizer), text, &terms, &[], &[], 7);
 
         assert_eq!(fragments.len(), 2);
         {
@@ -442,6 +840,32 @@ Survey in 2016, 2017, and 2018."#;
         assert_eq!(snippet.to_html(), "e <b>f</b> g");
     }
 
+    #[test]
+    fn test_snippet_multiple_fragments() {
+        let text = "a b c d e f a g";
+
+        let mut terms = BTreeMap::new();
+        terms.insert(String::from("a"), 1.0);
+        terms.insert(String::from("f"), 0.5);
+
+        let fragments = search_fragments(&From::from(SimpleTokenizer), text, &terms, &[], &[], 3);
+        assert_eq!(fragments.len(), 3);
+
+        // the best fragments are returned in the order of the text.
+        let snippets = select_best_fragments(&fragments[..], text, 2);
+        assert_eq!(snippets.len(), 2);
+        assert_eq!(snippets[0].fragments(), "a b");
+        assert_eq!(snippets[0].highlighted(), &[0..1]);
+        assert_eq!(snippets[1].fragments(), "a g");
+        assert_eq!(snippets[1].highlighted(), &[0..1]);
+
+        let snippets = select_best_fragments(&fragments[..], text, 10);
+        let snippet_texts: Vec<&str> = snippets.iter().map(|snippet| snippet.fragments()).collect();
+        assert_eq!(snippet_texts, vec!["a b", "e f", "a g"]);
+
+        assert!(select_best_fragments(&fragments[..], text, 0).is_empty());
+    }
+
     #[test]
     fn test_snippet_with_term_not_in_text() {
         let text = "a b c d";
@@ -449,7 +873,7 @@ Survey in 2016, 2017, and 2018."#;
         let mut terms = BTreeMap::new();
         terms.insert(String::from("z"), 1.0);
 
-        let fragments = search_fragments(&From::from(SimpleTokenizer), text, &terms, 3);
+        let fragments = search_fragments(&From::from(SimpleTokenizer), text, &terms, &[], &[], 3);
 
         assert_eq!(fragments.len(), 0);
 
@@ -463,7 +887,7 @@ Survey in 2016, 2017, and 2018."#;
         let text = "a b c d";
 
         let terms = BTreeMap::new();
-        let fragments = search_fragments(&From::from(SimpleTokenizer), text, &terms, 3);
+        let fragments = search_fragments(&From::from(SimpleTokenizer), text, &terms, &[], &[], 3);
         assert_eq!(fragments.len(), 0);
 
         let snippet = select_best_fragment_combination(&fragments[..], text);
@@ -522,6 +946,195 @@ Survey in 2016, 2017, and 2018."#;
         }
     }
 
+    #[test]
+    fn test_snippet_generator_markup() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![text_field]);
+        let query = query_parser.parse_query("a")?;
+        let mut snippet_generator = SnippetGenerator::create(&searcher, &*query, text_field)?;
+        snippet_generator.set_max_num_chars(8);
+        let text = "<a> b c d e f <a>";
+        assert_eq!(
+            snippet_generator.snippet(text).to_html(),
+            "&lt;<b>a</b>&gt; b c"
+        );
+        snippet_generator.set_num_fragments(3);
+        snippet_generator.set_pre_tag("[");
+        snippet_generator.set_post_tag("]");
+        snippet_generator.set_fragment_separator(" | ");
+        let snippets = snippet_generator.snippets(text);
+        assert_eq!(snippets.len(), 2);
+        assert_eq!(
+            snippet_generator.snippets_to_html(&snippets),
+            "&lt;[a]&gt; b c | d e f &lt;[a]"
+        );
+        snippet_generator.set_html_escape(false);
+        let snippets = snippet_generator.snippets(text);
+        assert_eq!(snippets[1].fragments(), "d e f <a");
+        assert_eq!(snippets[1].highlighted(), &[7..8]);
+        assert_eq!(
+            snippet_generator.snippets_to_html(&snippets),
+            "<[a]> b c | d e f <[a]"
+        );
+        assert!(snippet_generator.snippets("b c").is_empty());
+        assert_eq!(snippet_generator.snippets_to_html(&[]), "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_snippet_generator_phrase() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "new jersey is not new york"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![text_field]);
+        let text = "New Jersey is not New York, York is new";
+
+        let query = query_parser.parse_query("\"new york\"")?;
+        let snippet_generator = SnippetGenerator::create(&searcher, &*query, text_field)?;
+        assert!(snippet_generator.is_position_aware());
+        assert_eq!(
+            snippet_generator.snippet(text).to_html(),
+            "New Jersey is not <b>New</b> <b>York</b>, York is new"
+        );
+
+        // the clauses of a boolean query are highlighted independently.
+        let query = query_parser.parse_query("\"new york\" jersey")?;
+        let snippet_generator = SnippetGenerator::create(&searcher, &*query, text_field)?;
+        assert_eq!(
+            snippet_generator.snippet(text).to_html(),
+            "New <b>Jersey</b> is not <b>New</b> <b>York</b>, York is new"
+        );
+
+        let new_york = vec![
+            (0, Term::from_field_text(text_field, "new")),
+            (1, Term::from_field_text(text_field, "york")),
+        ];
+        let query = PhraseQuery::new_with_offset_and_slop(new_york.clone(), 1);
+        let snippet_generator = SnippetGenerator::create(&searcher, &query, text_field)?;
+        assert_eq!(
+            snippet_generator
+                .snippet("new jersey york, new york")
+                .to_html(),
+            "<b>new</b> jersey <b>york</b>, <b>new</b> <b>york</b>"
+        );
+        // the terms of the phrase have to be in order.
+        let query = PhraseQuery::new_with_offset_and_slop(new_york, 2);
+        let snippet_generator = SnippetGenerator::create(&searcher, &query, text_field)?;
+        assert_eq!(
+            snippet_generator
+                .snippet("york new, new a b york")
+                .to_html(),
+            "york new, <b>new</b> a b <b>york</b>"
+        );
+
+        // a phrase with a term absent from the index cannot match.
+        let query = query_parser.parse_query("\"new jersey city\"")?;
+        let snippet_generator = SnippetGenerator::create(&searcher, &*query, text_field)?;
+        assert_eq!(snippet_generator.snippet(text).to_html(), "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_snippet_generator_phrase_without_positions() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("default")
+                .set_index_option(IndexRecordOption::WithFreqs),
+        );
+        let text_field = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "new jersey is not new york"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = PhraseQuery::new(vec![
+            Term::from_field_text(text_field, "new"),
+            Term::from_field_text(text_field, "york"),
+        ]);
+        let snippet_generator = SnippetGenerator::create(&searcher, &query, text_field)?;
+        assert!(!snippet_generator.is_position_aware());
+        assert_eq!(
+            snippet_generator
+                .snippet("new jersey is not new york")
+                .to_html(),
+            "<b>new</b> jersey is not <b>new</b> <b>york</b>"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_snippet_generator_passages() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "rust is fast. rust is safe"));
+        index_writer.add_document(doc!(text_field => "safe and sound"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![text_field]);
+        let query = query_parser.parse_query("rust safe")?;
+        let term_weights = SnippetGenerator::query_term_weights(&searcher, &*query, text_field)?;
+        assert_eq!(
+            term_weights,
+            btreemap! {
+                "rust".to_string() => 0.5,
+                "safe".to_string() => 1.0 / 3.0,
+            }
+        );
+        let text = "Rust is fast. Is it safe? Rust is.";
+        let mut snippet_generator = SnippetGenerator::create(&searcher, &*query, text_field)?;
+        assert_eq!(snippet_generator.term_weights(), &term_weights);
+        assert_eq!(snippet_generator.fragment_candidates(text).len(), 1);
+
+        snippet_generator.set_boundary_chars(&['.', '?', '!']);
+        let fragment_candidates = snippet_generator.fragment_candidates(text);
+        let ranges: Vec<Range<usize>> = fragment_candidates
+            .iter()
+            .map(FragmentCandidate::range)
+            .collect();
+        assert_eq!(ranges, vec![0..12, 14..24, 26..33]);
+        assert_eq!(fragment_candidates[1].highlighted(), &[20..24]);
+        assert_eq!(
+            snippet_generator.snippet(text).to_html(),
+            "<b>Rust</b> is fast"
+        );
+
+        snippet_generator.set_term_weights(btreemap! { "safe".to_string() => 1.0 });
+        assert_eq!(
+            snippet_generator.snippet(text).to_html(),
+            "Is it <b>safe</b>"
+        );
+
+        // rank the passages by their length.
+        snippet_generator.set_term_weights(term_weights);
+        snippet_generator.set_passage_scorer(|passage: &FragmentCandidate, text: &str| {
+            text[passage.range()].len() as Score
+        });
+        assert_eq!(
+            snippet_generator.snippet(text).to_html(),
+            "<b>Rust</b> is fast"
+        );
+        snippet_generator.set_num_fragments(2);
+        let snippets = snippet_generator.snippets(text);
+        assert_eq!(
+            snippet_generator.snippets_to_html(&snippets),
+            "<b>Rust</b> is fast ... Is it <b>safe</b>"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_snippet_generator() {
         let mut schema_builder = Schema::builder();
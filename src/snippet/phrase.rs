@@ -0,0 +1,133 @@
+use crate::query::{PhraseQuery, Query, QueryVisitor};
+use crate::schema::{Field, Term};
+use crate::Score;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A phrase of the query, highlighted only where the text matches the phrase.
+#[derive(Debug)]
+pub(crate) struct SnippetPhrase {
+    // The offset, the text and the score of each term, sorted by offset.
+    terms: Vec<(usize, String, Score)>,
+    slop: u32,
+}
+
+impl SnippetPhrase {
+    pub fn new(mut terms: Vec<(usize, String, Score)>, slop: u32) -> SnippetPhrase {
+        terms.sort_by_key(|&(offset, _, _)| offset);
+        SnippetPhrase { terms, slop }
+    }
+
+    /// Returns the score of the tokens that are part of a match of the phrase,
+    /// given the lowercased text and the position of the tokens.
+    pub fn matching_tokens(&self, tokens: &[(String, usize)]) -> BTreeMap<usize, Score> {
+        let mut matching_tokens = BTreeMap::new();
+        // the (position, token ordinal) of the occurrences of each term.
+        let occurrences: Vec<Vec<(usize, usize)>> = self
+            .terms
+            .iter()
+            .map(|(_, term_text, _)| {
+                tokens
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (token_text, _))| token_text == term_text)
+                    .map(|(token_ord, &(_, position))| (position, token_ord))
+                    .collect()
+            })
+            .collect();
+        if occurrences.iter().any(Vec::is_empty) {
+            return matching_tokens;
+        }
+        let mut path = Vec::with_capacity(self.terms.len());
+        for &(position, token_ord) in &occurrences[0] {
+            path.clear();
+            path.push(token_ord);
+            if self.extend_match(&occurrences, position, 0, &mut path) {
+                for (&token_ord, (_, _, score)) in path.iter().zip(&self.terms) {
+                    matching_tokens.insert(token_ord, *score);
+                }
+            }
+        }
+        matching_tokens
+    }
+
+    // Extends a partial match of the first `path.len()` terms, the last one being at
+    // `position`, with the following terms.
+    //
+    // As in the `PhraseScorer`, the cost of a match is the sum of the differences between
+    // the positions of consecutive terms, shifted by their offset, and the positions of the
+    // terms must be increasing.
+    fn extend_match(
+        &self,
+        occurrences: &[Vec<(usize, usize)>],
+        position: usize,
+        cost: u32,
+        path: &mut Vec<usize>,
+    ) -> bool {
+        let term_ord = path.len();
+        if term_ord == self.terms.len() {
+            return true;
+        }
+        let previous_offset = self.terms[term_ord - 1].0;
+        let offset = self.terms[term_ord].0;
+        let expected_position = position + offset - previous_offset;
+        for &(next_position, token_ord) in &occurrences[term_ord] {
+            let is_increasing = if offset == previous_offset {
+                next_position >= position
+            } else {
+                next_position > position
+            };
+            if !is_increasing {
+                continue;
+            }
+            let move_cost = (next_position as i64 - expected_position as i64).abs();
+            let next_cost = cost as i64 + move_cost;
+            if next_cost > self.slop as i64 {
+                continue;
+            }
+            path.push(token_ord);
+            if self.extend_match(occurrences, next_position, next_cost as u32, path) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+}
+
+/// Collects the terms and the phrases of a query for a field.
+///
+/// If `phrases` is `None`, the terms of the phrases are collected as individual terms.
+pub(crate) struct SnippetTermsCollector<'a> {
+    pub field: Field,
+    pub terms: &'a mut BTreeSet<Term>,
+    pub phrases: Option<&'a mut Vec<PhraseQuery>>,
+}
+
+impl<'a> QueryVisitor for SnippetTermsCollector<'a> {
+    fn visit_term(&mut self, term: &Term, _positions_required: bool) {
+        if term.field() == self.field {
+            self.terms.insert(term.clone());
+        }
+    }
+
+    fn visit_phrase_query(&mut self, query: &PhraseQuery) {
+        if query.field() != self.field {
+            return;
+        }
+        if let Some(phrases) = self.phrases.as_mut() {
+            phrases.push(query.clone());
+        } else {
+            for (_, term) in query.phrase_terms_with_offsets() {
+                self.visit_term(term, true);
+            }
+        }
+    }
+
+    fn visit_other(&mut self, query: &dyn Query) {
+        let mut terms = BTreeMap::new();
+        query.query_terms(&mut terms);
+        for term in terms.keys() {
+            self.visit_term(term, false);
+        }
+    }
+}
@@ -71,6 +71,7 @@ pub struct SegmentSpaceUsage {
     positions: PerFieldSpaceUsage,
     fast_fields: PerFieldSpaceUsage,
     fieldnorms: PerFieldSpaceUsage,
+    term_vectors: PerFieldSpaceUsage,
 
     store: StoreSpaceUsage,
 
@@ -88,6 +89,7 @@ impl SegmentSpaceUsage {
         positions: PerFieldSpaceUsage,
         fast_fields: PerFieldSpaceUsage,
         fieldnorms: PerFieldSpaceUsage,
+        term_vectors: PerFieldSpaceUsage,
         store: StoreSpaceUsage,
         deletes: ByteCount,
     ) -> SegmentSpaceUsage {
@@ -96,6 +98,7 @@ impl SegmentSpaceUsage {
             + positions.total()
             + fast_fields.total()
             + fieldnorms.total()
+            + term_vectors.total()
             + store.total()
             + deletes;
         SegmentSpaceUsage {
@@ -105,6 +108,7 @@ impl SegmentSpaceUsage {
             positions,
             fast_fields,
             fieldnorms,
+            term_vectors,
             store,
             deletes,
             total,
@@ -127,6 +131,7 @@ impl SegmentSpaceUsage {
             SegmentComponent::Store => ComponentSpaceUsage::Store(self.store().clone()),
             SegmentComponent::TempStore => ComponentSpaceUsage::Store(self.store().clone()),
             Delete => Basic(self.deletes()),
+            TermVectors => PerField(self.term_vectors().clone()),
         }
     }
 
@@ -160,6 +165,11 @@ impl SegmentSpaceUsage {
         &self.fieldnorms
     }
 
+    /// Space usage for term vectors
+    pub fn term_vectors(&self) -> &PerFieldSpaceUsage {
+        &self.term_vectors
+    }
+
     /// Space usage for stored documents
     pub fn store(&self) -> &StoreSpaceUsage {
         &self.store
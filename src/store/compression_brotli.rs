@@ -1,9 +1,13 @@
 use std::io;
 
 #[inline]
-pub fn compress(mut uncompressed: &[u8], compressed: &mut Vec<u8>) -> io::Result<()> {
+pub fn compress(
+    mut uncompressed: &[u8],
+    compressed: &mut Vec<u8>,
+    compress_level: Option<i32>,
+) -> io::Result<()> {
     let mut params = brotli::enc::BrotliEncoderParams::default();
-    params.quality = 5;
+    params.quality = compress_level.unwrap_or(5);
     compressed.clear();
     brotli::BrotliCompress(&mut uncompressed, compressed, &params)?;
     Ok(())
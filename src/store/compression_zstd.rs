@@ -0,0 +1,59 @@
+use std::io;
+
+use core::convert::TryInto;
+use zstd::bulk::{Compressor, Decompressor};
+
+/// Compresses `uncompressed`, prefixed by its length, with `dictionary` if it is not empty.
+#[inline]
+pub fn compress(
+    uncompressed: &[u8],
+    compressed: &mut Vec<u8>,
+    compress_level: Option<i32>,
+    dictionary: &[u8],
+) -> io::Result<()> {
+    compressed.clear();
+    let maximum_ouput_size = zstd::zstd_safe::compress_bound(uncompressed.len());
+    compressed.resize(maximum_ouput_size + 4, 0);
+    let mut compressor = Compressor::with_dictionary(
+        compress_level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL),
+        dictionary,
+    )?;
+    let bytes_written = compressor.compress_to_buffer(uncompressed, &mut compressed[4..])?;
+    let num_bytes = uncompressed.len() as u32;
+    compressed[0..4].copy_from_slice(&num_bytes.to_le_bytes());
+    compressed.truncate(bytes_written + 4);
+    Ok(())
+}
+
+#[inline]
+pub fn decompress(
+    compressed: &[u8],
+    decompressed: &mut Vec<u8>,
+    dictionary: &[u8],
+) -> io::Result<()> {
+    decompressed.clear();
+    let uncompressed_size_bytes: &[u8; 4] = compressed
+        .get(..4)
+        .ok_or(io::ErrorKind::InvalidData)?
+        .try_into()
+        .unwrap();
+    let uncompressed_size = u32::from_le_bytes(*uncompressed_size_bytes) as usize;
+    decompressed.resize(uncompressed_size, 0);
+    let mut decompressor = Decompressor::with_dictionary(dictionary)?;
+    let bytes_written =
+        decompressor.decompress_to_buffer(&compressed[4..], &mut decompressed[..])?;
+    if bytes_written != uncompressed_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "doc store block not completely decompressed, data corruption".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Trains a dictionary of at most `dictionary_size` bytes on the documents.
+///
+/// Fails if there are too few documents to train a dictionary on.
+pub fn train_dictionary(documents: &[&[u8]], dictionary_size: usize) -> io::Result<Vec<u8>> {
+    zstd::dict::from_samples(documents, dictionary_size)
+}
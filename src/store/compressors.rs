@@ -22,6 +22,9 @@ pub enum Compressor {
     #[serde(rename = "snappy")]
     /// Use the snap compressor
     Snappy,
+    #[serde(rename = "zstd")]
+    /// Use the zstd compressor
+    Zstd,
 }
 
 impl Default for Compressor {
@@ -32,6 +35,8 @@ impl Default for Compressor {
             Compressor::Brotli
         } else if cfg!(feature = "snappy-compression") {
             Compressor::Snappy
+        } else if cfg!(feature = "zstd-compression") {
+            Compressor::Zstd
         } else {
             panic!(
                 "all compressor feature flags like are disabled (e.g. lz4-compression), can't choose default compressor"
@@ -41,12 +46,20 @@ impl Default for Compressor {
 }
 
 impl Compressor {
-    pub(crate) fn from_id(id: u8) -> Compressor {
+    pub(crate) fn from_id(id: u8) -> io::Result<Compressor> {
         match id {
-            1 => Compressor::Lz4,
-            2 => Compressor::Brotli,
-            3 => Compressor::Snappy,
-            _ => panic!("unknown compressor id {:?}", id),
+            1 => Ok(Compressor::Lz4),
+            2 => Ok(Compressor::Brotli),
+            3 => Ok(Compressor::Snappy),
+            4 => Ok(Compressor::Zstd),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Unknown doc store compressor id {}. The doc store may have been written \
+                     by a more recent version of tantivy.",
+                    id
+                ),
+            )),
         }
     }
     pub(crate) fn get_id(&self) -> u8 {
@@ -54,15 +67,64 @@ impl Compressor {
             Self::Lz4 => 1,
             Self::Brotli => 2,
             Self::Snappy => 3,
+            Self::Zstd => 4,
         }
     }
+    /// Returns an error message if the compressor has no compression level `compress_level`.
+    pub(crate) fn check_compress_level(&self, compress_level: i32) -> Result<(), String> {
+        match self {
+            Self::Brotli if (0..=11).contains(&compress_level) => Ok(()),
+            Self::Brotli => Err(format!(
+                "The brotli compression level must be between 0 and 11, got {}.",
+                compress_level
+            )),
+            Self::Zstd if (1..=22).contains(&compress_level) => Ok(()),
+            Self::Zstd => Err(format!(
+                "The zstd compression level must be between 1 and 22, got {}.",
+                compress_level
+            )),
+            Self::Lz4 | Self::Snappy => Err(format!(
+                "The {:?} compressor does not support compression levels.",
+                self
+            )),
+        }
+    }
+
+    /// Returns the maximum size of the dictionaries of the compressor, or `None` if it
+    /// cannot compress the blocks with a dictionary.
+    pub(crate) fn max_dictionary_size(&self) -> Option<usize> {
+        match self {
+            // lz4 only looks 64KB back.
+            Self::Lz4 => Some(65_536),
+            Self::Zstd => Some(1_048_576),
+            Self::Brotli | Self::Snappy => None,
+        }
+    }
+
+    /// Returns true if the compressor can compress the blocks with a dictionary.
+    pub(crate) fn supports_dictionary(&self) -> bool {
+        self.max_dictionary_size().is_some()
+    }
+
+    /// Compresses `uncompressed` with the compression level `compress_level`, or the
+    /// default level of the compressor if `None`, and `dictionary` if it is not empty.
+    ///
+    /// The compression level must have been checked with `check_compress_level`, and
+    /// the dictionary must be empty if the compressor does not support dictionaries.
     #[inline]
-    pub(crate) fn compress(&self, uncompressed: &[u8], compressed: &mut Vec<u8>) -> io::Result<()> {
+    pub(crate) fn compress(
+        &self,
+        uncompressed: &[u8],
+        compressed: &mut Vec<u8>,
+        compress_level: Option<i32>,
+        dictionary: &[u8],
+    ) -> io::Result<()> {
+        debug_assert!(dictionary.is_empty() || self.supports_dictionary());
         match self {
             Self::Lz4 => {
                 #[cfg(feature = "lz4-compression")]
                 {
-                    super::compression_lz4_block::compress(uncompressed, compressed)
+                    super::compression_lz4_block::compress(uncompressed, compressed, dictionary)
                 }
                 #[cfg(not(feature = "lz4-compression"))]
                 {
@@ -72,10 +134,11 @@ impl Compressor {
             Self::Brotli => {
                 #[cfg(feature = "brotli-compression")]
                 {
-                    super::compression_brotli::compress(uncompressed, compressed)
+                    super::compression_brotli::compress(uncompressed, compressed, compress_level)
                 }
                 #[cfg(not(feature = "brotli-compression"))]
                 {
+                    let _ = compress_level;
                     panic!("brotli-compression-compression feature flag not activated");
                 }
             }
@@ -89,20 +152,38 @@ impl Compressor {
                     panic!("snappy-compression feature flag not activated");
                 }
             }
+            Self::Zstd => {
+                #[cfg(feature = "zstd-compression")]
+                {
+                    super::compression_zstd::compress(
+                        uncompressed,
+                        compressed,
+                        compress_level,
+                        dictionary,
+                    )
+                }
+                #[cfg(not(feature = "zstd-compression"))]
+                {
+                    panic!("zstd-compression feature flag not activated");
+                }
+            }
         }
     }
 
+    /// Decompresses `compressed`, with the `dictionary` it was compressed with.
     #[inline]
     pub(crate) fn decompress(
         &self,
         compressed: &[u8],
         decompressed: &mut Vec<u8>,
+        dictionary: &[u8],
     ) -> io::Result<()> {
+        debug_assert!(dictionary.is_empty() || self.supports_dictionary());
         match self {
             Self::Lz4 => {
                 #[cfg(feature = "lz4-compression")]
                 {
-                    super::compression_lz4_block::decompress(compressed, decompressed)
+                    super::compression_lz4_block::decompress(compressed, decompressed, dictionary)
                 }
                 #[cfg(not(feature = "lz4-compression"))]
                 {
@@ -129,6 +210,16 @@ impl Compressor {
                     panic!("snappy-compression feature flag not activated");
                 }
             }
+            Self::Zstd => {
+                #[cfg(feature = "zstd-compression")]
+                {
+                    super::compression_zstd::decompress(compressed, decompressed, dictionary)
+                }
+                #[cfg(not(feature = "zstd-compression"))]
+                {
+                    panic!("zstd-compression feature flag not activated");
+                }
+            }
         }
     }
 }
@@ -2,32 +2,58 @@ use crate::{directory::FileSlice, store::Compressor};
 use common::{BinarySerializable, FixedSize, HasLen};
 use std::io;
 
+/// Added to the compressor id of the doc stores compressed with a dictionary, written
+/// between the skip index and the footer.
+///
+/// The releases without dictionaries do not know these compressor ids, and refuse to
+/// open the doc stores compressed with a dictionary.
+const DICTIONARY_COMPRESSOR_ID_OFFSET: u8 = 128;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct DocStoreFooter {
     pub offset: u64,
     pub compressor: Compressor,
+    pub dictionary_len: u32,
 }
 
 /// Serialises the footer to a byte-array
 /// - offset : 8 bytes
-///-  compressor id: 1 byte
-/// - reserved for future use: 15 bytes
+///-  compressor id: 1 byte, offset by `DICTIONARY_COMPRESSOR_ID_OFFSET` with a dictionary
+/// - dictionary length: 4 bytes
+/// - reserved for future use: 11 bytes
 impl BinarySerializable for DocStoreFooter {
     fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut compressor_id = self.compressor.get_id();
+        if self.dictionary_len > 0 {
+            compressor_id += DICTIONARY_COMPRESSOR_ID_OFFSET;
+        }
         BinarySerializable::serialize(&self.offset, writer)?;
-        BinarySerializable::serialize(&self.compressor.get_id(), writer)?;
-        writer.write_all(&[0; 15])?;
+        BinarySerializable::serialize(&compressor_id, writer)?;
+        BinarySerializable::serialize(&self.dictionary_len, writer)?;
+        writer.write_all(&[0; 11])?;
         Ok(())
     }
 
     fn deserialize<R: io::Read>(reader: &mut R) -> io::Result<Self> {
         let offset = u64::deserialize(reader)?;
-        let compressor_id = u8::deserialize(reader)?;
-        let mut skip_buf = [0; 15];
+        let mut compressor_id = u8::deserialize(reader)?;
+        let dictionary_len = u32::deserialize(reader)?;
+        if compressor_id >= DICTIONARY_COMPRESSOR_ID_OFFSET {
+            compressor_id -= DICTIONARY_COMPRESSOR_ID_OFFSET;
+        } else if dictionary_len > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The doc store footer has a dictionary length, but its compressor does not \
+                 use a dictionary."
+                    .to_string(),
+            ));
+        }
+        let mut skip_buf = [0; 11];
         reader.read_exact(&mut skip_buf)?;
         Ok(DocStoreFooter {
             offset,
-            compressor: Compressor::from_id(compressor_id),
+            compressor: Compressor::from_id(compressor_id)?,
+            dictionary_len,
         })
     }
 }
@@ -37,8 +63,12 @@ impl FixedSize for DocStoreFooter {
 }
 
 impl DocStoreFooter {
-    pub fn new(offset: u64, compressor: Compressor) -> Self {
-        DocStoreFooter { offset, compressor }
+    pub fn new(offset: u64, compressor: Compressor, dictionary_len: u32) -> Self {
+        DocStoreFooter {
+            offset,
+            compressor,
+            dictionary_len,
+        }
     }
 
     pub fn extract_footer(file: FileSlice) -> io::Result<(DocStoreFooter, FileSlice)> {
@@ -64,3 +94,35 @@ fn doc_store_footer_test() {
     // When the doc store footer is updated, make sure to update also the serialize/deserialize methods
     assert_eq!(core::mem::size_of::<DocStoreFooter>(), 16);
 }
+
+#[test]
+fn doc_store_footer_dictionary_test() {
+    let serialize = |footer: &DocStoreFooter| {
+        let mut buffer = Vec::new();
+        footer.serialize(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), DocStoreFooter::SIZE_IN_BYTES);
+        assert_eq!(
+            &DocStoreFooter::deserialize(&mut &buffer[..]).unwrap(),
+            footer
+        );
+        buffer
+    };
+    let mut buffer = serialize(&DocStoreFooter::new(10, Compressor::Lz4, 0));
+    assert_eq!(buffer[8], Compressor::Lz4.get_id());
+    buffer[8] = 100;
+    let err = DocStoreFooter::deserialize(&mut &buffer[..]).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("Unknown doc store compressor id 100."));
+
+    let mut buffer = serialize(&DocStoreFooter::new(10, Compressor::Lz4, 100));
+    // The readers without dictionaries reject the compressor id.
+    assert_eq!(
+        buffer[8],
+        Compressor::Lz4.get_id() + DICTIONARY_COMPRESSOR_ID_OFFSET
+    );
+    assert!(Compressor::from_id(buffer[8]).is_err());
+    buffer[8] = Compressor::Lz4.get_id();
+    let err = DocStoreFooter::deserialize(&mut &buffer[..]).unwrap_err();
+    assert!(err.to_string().contains("does not use a dictionary"));
+}
@@ -5,17 +5,20 @@ A field needs to be marked as stored in the schema in
 order to be handled in the `Store`.
 
 Internally, documents (or rather their stored fields) are serialized to a buffer.
-When the buffer exceeds 16K, the buffer is compressed using `brotli`, `LZ4` or `snappy`
-and the resulting block is written to disk.
+When the buffer exceeds the block size of the index settings (16K by default), the
+buffer is compressed using `brotli`, `LZ4`, `snappy` or `zstd` and the resulting block is
+written to disk. With `LZ4` and `zstd`, the blocks can be compressed with a dictionary trained
+on the first documents of the segment, see `IndexSettings::docstore_dictionary_size`.
 
 One can then request for a specific `DocId`.
 A skip list helps navigating to the right block,
 decompresses it entirely and returns the document within it.
 
-If the last document requested was in the same block,
-the reader is smart enough to avoid decompressing
-the block a second time, but their is no real
-*uncompressed block* cache.
+The reader keeps the last decompressed blocks in a LRU cache, shared by
+its clones, so that requesting documents of the same block does not
+decompress the block a second time. The capacity of the cache of the
+searchers is set with
+[`IndexReaderBuilder::doc_store_cache_capacity`](../struct.IndexReaderBuilder.html#method.doc_store_cache_capacity).
 
 A typical use case for the store is, once
 the search result page has been computed, returning
@@ -39,7 +42,7 @@ mod index;
 mod reader;
 mod writer;
 pub use self::compressors::Compressor;
-pub use self::reader::StoreReader;
+pub use self::reader::{CacheStats, StoreReader, DOCSTORE_CACHE_CAPACITY};
 pub use self::writer::StoreWriter;
 
 #[cfg(feature = "lz4-compression")]
@@ -51,6 +54,9 @@ mod compression_brotli;
 #[cfg(feature = "snappy-compression")]
 mod compression_snap;
 
+#[cfg(feature = "zstd-compression")]
+mod compression_zstd;
+
 #[cfg(test)]
 pub mod tests {
 
@@ -64,7 +70,7 @@ pub mod tests {
         directory::{Directory, RamDirectory, WritePtr},
         Term,
     };
-    use crate::{schema::Schema, Index};
+    use crate::{doc, schema::Schema, DocAddress, Index, IndexSettings, TantivyError};
     use std::path::Path;
 
     const LOREM: &str = "Doc Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed \
@@ -87,7 +93,7 @@ pub mod tests {
             schema_builder.add_text_field("title", TextOptions::default().set_stored());
         let schema = schema_builder.build();
         {
-            let mut store_writer = StoreWriter::new(writer, compressor);
+            let mut store_writer = StoreWriter::new(writer, compressor, None, 16_384);
             for i in 0..num_docs {
                 let mut fields: Vec<FieldValue> = Vec::new();
                 {
@@ -197,6 +203,11 @@ pub mod tests {
     fn test_store_brotli() -> crate::Result<()> {
         test_store(Compressor::Brotli)
     }
+    #[cfg(feature = "zstd-compression")]
+    #[test]
+    fn test_store_zstd() -> crate::Result<()> {
+        test_store(Compressor::Zstd)
+    }
 
     #[test]
     fn test_store_with_delete() -> crate::Result<()> {
@@ -340,6 +351,250 @@ pub mod tests {
         assert_eq!(store.block_checkpoints().count(), 1);
         Ok(())
     }
+
+    #[test]
+    fn test_docstore_settings_through_merge() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text_field", TEXT | STORED);
+        let settings = IndexSettings {
+            docstore_blocksize: 64,
+            ..Default::default()
+        };
+        let directory = RamDirectory::create();
+        let index = Index::create(directory.clone(), schema_builder.build(), settings)?;
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            for i in 0..20 {
+                index_writer.add_document(doc!(text_field => format!("document number {}", i)));
+                if i == 9 {
+                    index_writer.commit()?;
+                }
+            }
+            index_writer.commit()?;
+            let segment_ids = index.searchable_segment_ids()?;
+            block_on(index_writer.merge(&segment_ids))?;
+            index_writer.wait_merging_threads()?;
+        }
+
+        let index = Index::open(directory)?;
+        assert_eq!(index.settings().docstore_blocksize, 64);
+        assert_eq!(index.settings().docstore_compress_level, None);
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let store = searcher.segment_reader(0).get_store_reader()?;
+        // the merged store is rewritten with small blocks of a few documents.
+        assert!(store.block_checkpoints().count() >= 5);
+        for (i, doc) in store.iter(None).enumerate() {
+            assert_eq!(
+                doc?.get_first(text_field).unwrap().text(),
+                Some(format!("document number {}", i).as_str())
+            );
+        }
+        Ok(())
+    }
+
+    fn small_json_doc(i: usize) -> String {
+        let countries = ["fr", "de", "us", "jp", "br"];
+        let events = ["login", "logout", "purchase", "search"];
+        format!(
+            r#"{{"id":{},"user":{{"name":"user{}","email":"user{}@example.com","country":"{}"}},"event":"{}","session":"{:x}","tags":["web","mobile","beta"],"message":"{}"}}"#,
+            i,
+            i % 97,
+            i % 97,
+            countries[i % countries.len()],
+            events[i % events.len()],
+            i * 7_919,
+            &LOREM[..(i * 31) % 400]
+        )
+    }
+
+    fn store_num_bytes(index: &Index) -> crate::Result<usize> {
+        let searcher = index.reader()?.searcher();
+        Ok(searcher
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| segment_reader.get_store_reader().unwrap())
+            .map(|store_reader| store_reader.space_usage().total())
+            .sum())
+    }
+
+    fn test_docstore_dictionary(compressor: Compressor) -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text_field", STORED);
+        let schema = schema_builder.build();
+        let create_index = |docstore_dictionary_size| -> crate::Result<Index> {
+            let settings = IndexSettings {
+                docstore_compression: compressor,
+                docstore_blocksize: 4_096,
+                docstore_dictionary_size,
+                ..Default::default()
+            };
+            let index = Index::create(RamDirectory::create(), schema.clone(), settings)?;
+            let mut index_writer = index.writer_for_tests()?;
+            for i in 0..2_000 {
+                index_writer.add_document(doc!(text_field => small_json_doc(i)));
+                if i == 999 {
+                    index_writer.commit()?;
+                }
+            }
+            index_writer.commit()?;
+            Ok(index)
+        };
+        let index = create_index(None)?;
+        let index_with_dictionary = create_index(Some(4_096))?;
+        assert!(store_num_bytes(&index_with_dictionary)? < store_num_bytes(&index)?);
+
+        // the merged segment is compressed with a new dictionary.
+        let segment_ids = index_with_dictionary.searchable_segment_ids()?;
+        let mut index_writer = index_with_dictionary.writer_for_tests()?;
+        block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        let searcher = index_with_dictionary.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let store_reader = searcher.segment_reader(0).get_store_reader()?;
+        assert!(store_reader.has_dictionary());
+        for (i, doc) in store_reader.iter(None).enumerate() {
+            assert_eq!(
+                doc?.get_first(text_field).unwrap().text(),
+                Some(small_json_doc(i).as_str())
+            );
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "lz4-compression")]
+    #[test]
+    fn test_docstore_dictionary_lz4() -> crate::Result<()> {
+        test_docstore_dictionary(Compressor::Lz4)
+    }
+
+    #[cfg(feature = "zstd-compression")]
+    #[test]
+    fn test_docstore_dictionary_zstd() -> crate::Result<()> {
+        test_docstore_dictionary(Compressor::Zstd)
+    }
+
+    #[test]
+    fn test_docstore_settings_validation() {
+        let schema = Schema::builder().build();
+        let create_index = |settings: IndexSettings| {
+            Index::builder()
+                .schema(schema.clone())
+                .settings(settings)
+                .create_in_ram()
+        };
+        let blocksize_err = create_index(IndexSettings {
+            docstore_blocksize: 0,
+            ..Default::default()
+        });
+        assert!(matches!(
+            blocksize_err,
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        for compressor in [Compressor::Lz4, Compressor::Snappy] {
+            let level_err = create_index(IndexSettings {
+                docstore_compression: compressor,
+                docstore_compress_level: Some(3),
+                ..Default::default()
+            });
+            assert!(matches!(level_err, Err(TantivyError::InvalidArgument(_))));
+        }
+        for (compressor, compress_level) in [(Compressor::Brotli, 12), (Compressor::Zstd, 23)] {
+            let level_err = create_index(IndexSettings {
+                docstore_compression: compressor,
+                docstore_compress_level: Some(compress_level),
+                ..Default::default()
+            });
+            assert!(matches!(level_err, Err(TantivyError::InvalidArgument(_))));
+        }
+        for (compressor, dictionary_size) in [
+            (Compressor::Lz4, 0),
+            (Compressor::Lz4, 100_000),
+            (Compressor::Zstd, 2_000_000),
+            (Compressor::Brotli, 1_000),
+            (Compressor::Snappy, 1_000),
+        ] {
+            let dictionary_err = create_index(IndexSettings {
+                docstore_compression: compressor,
+                docstore_dictionary_size: Some(dictionary_size),
+                ..Default::default()
+            });
+            assert!(matches!(
+                dictionary_err,
+                Err(TantivyError::InvalidArgument(_))
+            ));
+        }
+    }
+
+    #[cfg(feature = "brotli-compression")]
+    #[test]
+    fn test_brotli_compress_level() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text_field", TEXT | STORED);
+        let index = Index::builder()
+            .schema(schema_builder.build())
+            .settings(IndexSettings {
+                docstore_compression: Compressor::Brotli,
+                docstore_compress_level: Some(11),
+                ..Default::default()
+            })
+            .create_in_ram()?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => LOREM));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let doc = searcher.doc(DocAddress::new(0, 0))?;
+        assert_eq!(doc.get_first(text_field).unwrap().text(), Some(LOREM));
+        Ok(())
+    }
+
+    #[test]
+    fn test_searcher_docs_share_store_cache() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id = schema_builder.add_u64_field("id", STORED);
+        let text_field = schema_builder.add_text_field("text_field", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..200u64 {
+            index_writer.add_document(doc!(id => i, text_field => LOREM));
+        }
+        index_writer.commit()?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(crate::ReloadPolicy::Manual)
+            .num_searchers(2)
+            .doc_store_cache_capacity(2)
+            .try_into()?;
+        let searcher = reader.searcher();
+        let other_searcher = reader.searcher();
+        let doc_addresses: Vec<DocAddress> = [150, 0, 151, 1]
+            .iter()
+            .map(|&doc_id| DocAddress::new(0, doc_id))
+            .collect();
+        let ids: Vec<u64> = searcher
+            .docs(&doc_addresses)?
+            .iter()
+            .map(|doc| doc.get_first(id).unwrap().u64_value().unwrap())
+            .collect();
+        assert_eq!(ids, vec![150, 0, 151, 1]);
+        let cache_stats = searcher.doc_store_cache_stats();
+        assert_eq!((cache_stats.hits, cache_stats.misses), (2, 2));
+        assert_eq!(cache_stats.num_blocks, 2);
+        other_searcher.doc(DocAddress::new(0, 2))?;
+        assert_eq!(searcher.doc_store_cache_stats().hits, 3);
+        drop(searcher);
+        drop(other_searcher);
+
+        // the cache of the segment is kept by the next generation.
+        index_writer.add_document(doc!(id => 200u64, text_field => LOREM));
+        index_writer.commit()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let cache_stats = searcher.doc_store_cache_stats();
+        assert_eq!((cache_stats.hits, cache_stats.misses), (3, 2));
+        Ok(())
+    }
 }
 
 #[cfg(all(test, feature = "unstable"))]
@@ -348,6 +603,7 @@ mod bench {
     use super::tests::write_lorem_ipsum_store;
     use crate::directory::Directory;
     use crate::directory::RamDirectory;
+    use crate::schema::Schema;
     use crate::store::Compressor;
     use crate::store::StoreReader;
     use std::path::Path;
@@ -381,4 +637,33 @@ mod bench {
         let store = StoreReader::open(store_file).unwrap();
         b.iter(|| store.iter(None).collect::<Vec<_>>());
     }
+
+    fn open_lorem_ipsum_store() -> (Schema, StoreReader) {
+        let directory = RamDirectory::create();
+        let path = Path::new("store");
+        let schema = write_lorem_ipsum_store(
+            directory.open_write(path).unwrap(),
+            1_000,
+            Compressor::default(),
+        );
+        let store_file = directory.open_read(path).unwrap();
+        (schema, StoreReader::open(store_file).unwrap())
+    }
+
+    #[bench]
+    fn bench_store_get_all_fields(b: &mut Bencher) {
+        let (_schema, store) = open_lorem_ipsum_store();
+        b.iter(|| (0..1_000).map(|doc| store.get(doc)).collect::<Vec<_>>());
+    }
+
+    #[bench]
+    fn bench_store_get_title_field(b: &mut Bencher) {
+        let (schema, store) = open_lorem_ipsum_store();
+        let title = schema.get_field("title").unwrap();
+        b.iter(|| {
+            (0..1_000)
+                .map(|doc| store.get_fields(doc, &[title]))
+                .collect::<Vec<_>>()
+        });
+    }
 }
@@ -1,7 +1,7 @@
 use super::Compressor;
 use super::{footer::DocStoreFooter, index::SkipIndex};
 use crate::directory::{FileSlice, OwnedBytes};
-use crate::schema::Document;
+use crate::schema::{Document, Field};
 use crate::space_usage::StoreSpaceUsage;
 use crate::store::index::Checkpoint;
 use crate::DocId;
@@ -12,51 +12,160 @@ use std::io;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
-const LRU_CACHE_CAPACITY: usize = 100;
+/// Default number of decompressed blocks kept by the cache of a `StoreReader`.
+pub const DOCSTORE_CACHE_CAPACITY: usize = 100;
 
 type Block = OwnedBytes;
 
 type BlockCache = Arc<Mutex<LruCache<usize, Block>>>;
 
+/// Statistics of the block cache of a [`StoreReader`](./struct.StoreReader.html).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    /// Number of blocks read from the cache.
+    pub hits: usize,
+    /// Number of blocks decompressed because they were not in the cache.
+    pub misses: usize,
+    /// Number of blocks evicted from the cache to make room for another block.
+    pub evictions: usize,
+    /// Number of blocks in the cache.
+    pub num_blocks: usize,
+}
+
+impl std::ops::AddAssign for CacheStats {
+    fn add_assign(&mut self, other: CacheStats) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.evictions += other.evictions;
+        self.num_blocks += other.num_blocks;
+    }
+}
+
+/// Returns the bytes of the document `doc_id` in the decompressed `block` of `checkpoint`.
+fn document_bytes(
+    block: &Block,
+    checkpoint: &Checkpoint,
+    doc_id: DocId,
+) -> crate::Result<OwnedBytes> {
+    let mut cursor = &block[..];
+    let cursor_len_before = cursor.len();
+    for _ in checkpoint.doc_range.start..doc_id {
+        let doc_length = VInt::deserialize(&mut cursor)?.val() as usize;
+        cursor = &cursor[doc_length..];
+    }
+
+    let doc_length = VInt::deserialize(&mut cursor)?.val() as usize;
+    let start_pos = cursor_len_before - cursor.len();
+    let end_pos = cursor_len_before - cursor.len() + doc_length;
+    Ok(block.slice(start_pos..end_pos))
+}
+
 /// Reads document off tantivy's [`Store`](./index.html)
+///
+/// The clones of a `StoreReader` share its cache of decompressed blocks.
+#[derive(Clone)]
 pub struct StoreReader {
     compressor: Compressor,
+    dictionary: OwnedBytes,
     data: FileSlice,
     cache: BlockCache,
     cache_hits: Arc<AtomicUsize>,
     cache_misses: Arc<AtomicUsize>,
+    cache_evictions: Arc<AtomicUsize>,
     skip_index: Arc<SkipIndex>,
     space_usage: StoreSpaceUsage,
 }
 
 impl StoreReader {
-    /// Opens a store reader
+    /// Opens a store reader, caching up to
+    /// [`DOCSTORE_CACHE_CAPACITY`](./constant.DOCSTORE_CACHE_CAPACITY.html) blocks.
     pub fn open(store_file: FileSlice) -> io::Result<StoreReader> {
+        StoreReader::open_with_cache_capacity(store_file, DOCSTORE_CACHE_CAPACITY)
+    }
+
+    /// Opens a store reader, caching up to `cache_capacity` decompressed blocks.
+    ///
+    /// A capacity of `0` disables the cache.
+    pub fn open_with_cache_capacity(
+        store_file: FileSlice,
+        cache_capacity: usize,
+    ) -> io::Result<StoreReader> {
         let (footer, data_and_offset) = DocStoreFooter::extract_footer(store_file)?;
 
-        let (data_file, offset_index_file) = data_and_offset.split(footer.offset as usize);
+        let (data_file, offset_index_and_dictionary) =
+            data_and_offset.split(footer.offset as usize);
+        let space_usage = StoreSpaceUsage::new(data_file.len(), offset_index_and_dictionary.len());
+        let (offset_index_file, dictionary_file) =
+            offset_index_and_dictionary.split_from_end(footer.dictionary_len as usize);
         let index_data = offset_index_file.read_bytes()?;
-        let space_usage = StoreSpaceUsage::new(data_file.len(), offset_index_file.len());
         let skip_index = SkipIndex::open(index_data);
         Ok(StoreReader {
             compressor: footer.compressor,
+            dictionary: dictionary_file.read_bytes()?,
             data: data_file,
-            cache: Arc::new(Mutex::new(LruCache::new(LRU_CACHE_CAPACITY))),
+            cache: Arc::new(Mutex::new(LruCache::new(cache_capacity))),
             cache_hits: Default::default(),
             cache_misses: Default::default(),
+            cache_evictions: Default::default(),
             skip_index: Arc::new(skip_index),
             space_usage,
         })
     }
 
+    /// Returns the statistics of the block cache, shared by the clones of the reader.
+    pub fn cache_stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.cache_hits.load(Ordering::SeqCst),
+            misses: self.cache_misses.load(Ordering::SeqCst),
+            evictions: self.cache_evictions.load(Ordering::SeqCst),
+            num_blocks: self.cache.lock().unwrap().len(),
+        }
+    }
+
     pub(crate) fn block_checkpoints(&self) -> impl Iterator<Item = Checkpoint> + '_ {
         self.skip_index.checkpoints()
     }
 
+    /// Checks that the blocks of the store are contiguous and span the `max_doc`
+    /// documents of the segment, and the whole data of the store.
+    ///
+    /// Returns the description of the first problem found, if any.
+    pub(crate) fn check_block_index(&self, max_doc: DocId) -> Option<String> {
+        let mut previous_checkpoint = Checkpoint::default();
+        for checkpoint in self.block_checkpoints() {
+            if !checkpoint.follows(&previous_checkpoint) {
+                return Some(format!(
+                    "The block {:?} does not follow the block {:?}.",
+                    checkpoint, previous_checkpoint
+                ));
+            }
+            previous_checkpoint = checkpoint;
+        }
+        if previous_checkpoint.doc_range.end != max_doc {
+            return Some(format!(
+                "The blocks span {} documents, expected max_doc={}.",
+                previous_checkpoint.doc_range.end, max_doc
+            ));
+        }
+        if previous_checkpoint.byte_range.end != self.data.len() {
+            return Some(format!(
+                "The blocks span {} bytes, but the store data has {} bytes.",
+                previous_checkpoint.byte_range.end,
+                self.data.len()
+            ));
+        }
+        None
+    }
+
     pub(crate) fn compressor(&self) -> Compressor {
         self.compressor
     }
 
+    /// Returns true if the blocks are compressed with a dictionary.
+    pub(crate) fn has_dictionary(&self) -> bool {
+        !self.dictionary.is_empty()
+    }
+
     fn block_checkpoint(&self, doc_id: DocId) -> Option<Checkpoint> {
         self.skip_index.seek(doc_id)
     }
@@ -69,24 +178,56 @@ impl StoreReader {
         self.data.slice(checkpoint.byte_range.clone()).read_bytes()
     }
 
-    fn read_block(&self, checkpoint: &Checkpoint) -> io::Result<Block> {
+    fn cached_block(&self, checkpoint: &Checkpoint) -> Option<Block> {
         if let Some(block) = self.cache.lock().unwrap().get(&checkpoint.byte_range.start) {
             self.cache_hits.fetch_add(1, Ordering::SeqCst);
-            return Ok(block.clone());
+            return Some(block.clone());
         }
-
         self.cache_misses.fetch_add(1, Ordering::SeqCst);
+        None
+    }
 
+    fn read_block(&self, checkpoint: &Checkpoint) -> io::Result<Block> {
+        if let Some(block) = self.cached_block(checkpoint) {
+            return Ok(block);
+        }
         let compressed_block = self.compressed_block(checkpoint)?;
+        self.decompress_block(checkpoint, compressed_block)
+    }
+
+    async fn read_block_async(&self, checkpoint: &Checkpoint) -> io::Result<Block> {
+        if let Some(block) = self.cached_block(checkpoint) {
+            return Ok(block);
+        }
+        let compressed_block = self
+            .data
+            .read_bytes_slice_async(checkpoint.byte_range.clone())
+            .await?;
+        self.decompress_block(checkpoint, compressed_block)
+    }
+
+    /// Decompresses a block read from the store, and caches it.
+    fn decompress_block(
+        &self,
+        checkpoint: &Checkpoint,
+        compressed_block: OwnedBytes,
+    ) -> io::Result<Block> {
         let mut decompressed_block = vec![];
-        self.compressor
-            .decompress(compressed_block.as_slice(), &mut decompressed_block)?;
+        self.compressor.decompress(
+            compressed_block.as_slice(),
+            &mut decompressed_block,
+            self.dictionary.as_slice(),
+        )?;
 
         let block = OwnedBytes::new(decompressed_block);
-        self.cache
-            .lock()
-            .unwrap()
-            .put(checkpoint.byte_range.start, block.clone());
+        let mut cache = self.cache.lock().unwrap();
+        if cache.cap() > 0
+            && cache.len() == cache.cap()
+            && !cache.contains(&checkpoint.byte_range.start)
+        {
+            self.cache_evictions.fetch_add(1, Ordering::SeqCst);
+        }
+        cache.put(checkpoint.byte_range.start, block.clone());
 
         Ok(block)
     }
@@ -105,6 +246,19 @@ impl StoreReader {
         Ok(Document::deserialize(&mut doc_bytes)?)
     }
 
+    /// Reads the values of some `fields` of a given document.
+    ///
+    /// The values of the other fields are skipped without being decoded, which is
+    /// cheaper than [`.get(doc)`](#method.get) when the document has large stored fields
+    /// that are not needed.
+    pub fn get_fields(&self, doc_id: DocId, fields: &[Field]) -> crate::Result<Document> {
+        let doc_bytes = self.get_document_bytes(doc_id)?;
+        Ok(Document::deserialize_fields(
+            &mut doc_bytes.as_slice(),
+            fields,
+        )?)
+    }
+
     /// Reads raw bytes of a given document. Returns `RawDocument`, which contains the block of a document and its start and end
     /// position within the block.
     ///
@@ -114,21 +268,34 @@ impl StoreReader {
     /// For that reason a store reader should be kept and reused.
     ///
     pub fn get_document_bytes(&self, doc_id: DocId) -> crate::Result<OwnedBytes> {
-        let checkpoint = self.block_checkpoint(doc_id).ok_or_else(|| {
-            crate::TantivyError::InvalidArgument(format!("Failed to lookup Doc #{}.", doc_id))
-        })?;
+        let checkpoint = self.document_checkpoint(doc_id)?;
         let block = self.read_block(&checkpoint)?;
-        let mut cursor = &block[..];
-        let cursor_len_before = cursor.len();
-        for _ in checkpoint.doc_range.start..doc_id {
-            let doc_length = VInt::deserialize(&mut cursor)?.val() as usize;
-            cursor = &cursor[doc_length..];
-        }
+        document_bytes(&block, &checkpoint, doc_id)
+    }
 
-        let doc_length = VInt::deserialize(&mut cursor)?.val() as usize;
-        let start_pos = cursor_len_before - cursor.len();
-        let end_pos = cursor_len_before - cursor.len() + doc_length;
-        Ok(block.slice(start_pos..end_pos))
+    /// Reads a given document asynchronously.
+    ///
+    /// The compressed block of the document is read with
+    /// [`FileSlice::read_bytes_slice_async`](../directory/struct.FileSlice.html#method.read_bytes_slice_async),
+    /// so that a `Directory` backed by a remote storage does not block the executor.
+    pub async fn get_async(&self, doc_id: DocId) -> crate::Result<Document> {
+        let mut doc_bytes = self.get_document_bytes_async(doc_id).await?;
+        Ok(Document::deserialize(&mut doc_bytes)?)
+    }
+
+    /// Reads raw bytes of a given document asynchronously.
+    ///
+    /// See [`.get_async(doc)`](#method.get_async).
+    pub async fn get_document_bytes_async(&self, doc_id: DocId) -> crate::Result<OwnedBytes> {
+        let checkpoint = self.document_checkpoint(doc_id)?;
+        let block = self.read_block_async(&checkpoint).await?;
+        document_bytes(&block, &checkpoint, doc_id)
+    }
+
+    fn document_checkpoint(&self, doc_id: DocId) -> crate::Result<Checkpoint> {
+        self.block_checkpoint(doc_id).ok_or_else(|| {
+            crate::TantivyError::InvalidArgument(format!("Failed to lookup Doc #{}.", doc_id))
+        })
     }
 
     /// Iterator over all Documents in their order as they are stored in the doc store.
@@ -238,9 +405,13 @@ impl StoreReader {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::directory::FileHandle;
     use crate::schema::Document;
     use crate::schema::Field;
     use crate::{directory::RamDirectory, store::tests::write_lorem_ipsum_store, Directory};
+    use futures::executor::block_on;
+    use futures::future::BoxFuture;
+    use std::ops::Range;
     use std::path::Path;
 
     fn get_text_field<'a>(doc: &'a Document, field: &'a Field) -> Option<&'a str> {
@@ -312,4 +483,121 @@ mod tests {
 
         Ok(())
     }
+
+    /// A file handle counting its synchronous and asynchronous reads.
+    #[derive(Debug, Default)]
+    struct ReadCounts {
+        sync_reads: AtomicUsize,
+        async_reads: AtomicUsize,
+    }
+
+    #[derive(Debug)]
+    struct CountingFileHandle {
+        file_slice: FileSlice,
+        read_counts: Arc<ReadCounts>,
+    }
+
+    impl HasLen for CountingFileHandle {
+        fn len(&self) -> usize {
+            self.file_slice.len()
+        }
+    }
+
+    impl FileHandle for CountingFileHandle {
+        fn read_bytes(&self, range: Range<usize>) -> io::Result<OwnedBytes> {
+            self.read_counts.sync_reads.fetch_add(1, Ordering::SeqCst);
+            self.file_slice.read_bytes_slice(range)
+        }
+
+        fn read_bytes_async(&self, range: Range<usize>) -> BoxFuture<'_, io::Result<OwnedBytes>> {
+            self.read_counts.async_reads.fetch_add(1, Ordering::SeqCst);
+            Box::pin(self.file_slice.read_bytes_slice_async(range))
+        }
+    }
+
+    #[test]
+    fn test_store_get_async() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let path = Path::new("store");
+        let writer = directory.open_write(path)?;
+        let schema = write_lorem_ipsum_store(writer, 500, Compressor::default());
+        let title = schema.get_field("title").unwrap();
+        let read_counts = Arc::new(ReadCounts::default());
+        let store_file = FileSlice::new(Box::new(CountingFileHandle {
+            file_slice: directory.open_read(path)?,
+            read_counts: read_counts.clone(),
+        }));
+        let store = StoreReader::open(store_file)?;
+        let num_sync_reads = read_counts.sync_reads.load(Ordering::SeqCst);
+
+        let doc = block_on(store.get_async(321))?;
+        assert_eq!(get_text_field(&doc, &title), Some("Doc 321"));
+        assert_eq!(doc.field_values(), store.get(321)?.field_values());
+        let doc = block_on(store.get_async(499))?;
+        assert_eq!(get_text_field(&doc, &title), Some("Doc 499"));
+        assert_eq!(read_counts.async_reads.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            read_counts.sync_reads.load(Ordering::SeqCst),
+            num_sync_reads
+        );
+        assert_eq!(store.cache_stats().hits, 1);
+        assert!(block_on(store.get_async(500)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_get_fields() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let path = Path::new("store");
+        let writer = directory.open_write(path)?;
+        let schema = write_lorem_ipsum_store(writer, 500, Compressor::default());
+        let title = schema.get_field("title").unwrap();
+        let body = schema.get_field("body").unwrap();
+        let store = StoreReader::open(directory.open_read(path)?)?;
+        let doc = store.get_fields(321, &[title])?;
+        assert_eq!(doc.field_values().len(), 1);
+        assert_eq!(get_text_field(&doc, &title), Some("Doc 321"));
+        let doc = store.get_fields(321, &[body, title])?;
+        assert_eq!(doc.field_values(), store.get(321)?.field_values());
+        assert!(store.get_fields(321, &[])?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_cache_capacity() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let path = Path::new("store");
+        let writer = directory.open_write(path)?;
+        let schema = write_lorem_ipsum_store(writer, 500, Compressor::default());
+        let title = schema.get_field("title").unwrap();
+        let store_file = directory.open_read(path)?;
+        let store = StoreReader::open_with_cache_capacity(store_file.clone(), 1)?;
+        let store_clone = store.clone();
+
+        store.get(0)?;
+        store_clone.get(1)?;
+        let doc = store_clone.get(499)?;
+        assert_eq!(get_text_field(&doc, &title), Some("Doc 499"));
+        store.get(0)?;
+        let expected_stats = CacheStats {
+            hits: 1,
+            misses: 3,
+            evictions: 2,
+            num_blocks: 1,
+        };
+        assert_eq!(store.cache_stats(), expected_stats);
+        assert_eq!(store_clone.cache_stats(), expected_stats);
+
+        let store = StoreReader::open_with_cache_capacity(store_file, 0)?;
+        store.get(0)?;
+        store.get(0)?;
+        assert_eq!(
+            store.cache_stats(),
+            CacheStats {
+                misses: 2,
+                ..CacheStats::default()
+            }
+        );
+        Ok(())
+    }
 }
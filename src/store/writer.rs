@@ -5,12 +5,67 @@ use crate::directory::TerminatingWrite;
 use crate::directory::WritePtr;
 use crate::schema::Document;
 use crate::store::index::Checkpoint;
-use crate::DocId;
+use crate::{DocId, IndexSettings};
 use common::CountingWriter;
 use common::{BinarySerializable, VInt};
 use std::io::{self, Write};
 
-const BLOCK_SIZE: usize = 16_384;
+/// The dictionary is trained once the uncompressed blocks of the store weigh
+/// `DICTIONARY_TRAINING_RATIO` times the size of the dictionary.
+const DICTIONARY_TRAINING_RATIO: usize = 16;
+
+/// Returns the serialized documents of `blocks`.
+fn block_documents(blocks: &[(Vec<u8>, DocId)]) -> io::Result<Vec<&[u8]>> {
+    let mut documents = Vec::new();
+    for (block, _) in blocks {
+        let mut cursor = &block[..];
+        while !cursor.is_empty() {
+            let doc_num_bytes = VInt::deserialize(&mut cursor)?.val() as usize;
+            let (doc_bytes, remaining) = cursor.split_at(doc_num_bytes);
+            documents.push(doc_bytes);
+            cursor = remaining;
+        }
+    }
+    Ok(documents)
+}
+
+/// Builds a dictionary of at most `dictionary_size` bytes out of the documents of
+/// `blocks`, for `compressor`.
+///
+/// zstd trains its dictionary on the documents. Otherwise, or if there are too few
+/// documents to train a zstd dictionary, the dictionary is the concatenation of
+/// documents sampled evenly over the blocks, so that the structure and the values they
+/// share are found in the dictionary.
+fn train_dictionary(
+    compressor: Compressor,
+    blocks: &[(Vec<u8>, DocId)],
+    dictionary_size: usize,
+) -> io::Result<Vec<u8>> {
+    let documents = block_documents(blocks)?;
+    #[cfg(feature = "zstd-compression")]
+    {
+        if compressor == Compressor::Zstd {
+            if let Ok(dictionary) =
+                super::compression_zstd::train_dictionary(&documents, dictionary_size)
+            {
+                return Ok(dictionary);
+            }
+        }
+    }
+    #[cfg(not(feature = "zstd-compression"))]
+    let _ = compressor;
+    let num_bytes: usize = documents.iter().map(|doc_bytes| doc_bytes.len()).sum();
+    let sampling_step = (num_bytes / dictionary_size).max(1);
+    let mut dictionary = Vec::with_capacity(dictionary_size);
+    for doc_bytes in documents.into_iter().step_by(sampling_step) {
+        let num_sampled_bytes = (dictionary_size - dictionary.len()).min(doc_bytes.len());
+        dictionary.extend_from_slice(&doc_bytes[..num_sampled_bytes]);
+        if dictionary.len() == dictionary_size {
+            break;
+        }
+    }
+    Ok(dictionary)
+}
 
 /// Write tantivy's [`Store`](./index.html)
 ///
@@ -20,8 +75,18 @@ const BLOCK_SIZE: usize = 16_384;
 ///
 /// The skip list index on the other hand, is built in memory.
 ///
+/// If the index settings define a `docstore_dictionary_size`, the first blocks
+/// are kept in memory until a dictionary is trained on their documents.
 pub struct StoreWriter {
     compressor: Compressor,
+    compress_level: Option<i32>,
+    block_size: usize,
+    // Size of the dictionary to train, until it is trained.
+    dictionary_size: Option<usize>,
+    dictionary: Vec<u8>,
+    // Blocks waiting for the dictionary, with the end of their doc range.
+    pending_blocks: Vec<(Vec<u8>, DocId)>,
+    num_pending_bytes: usize,
     doc: DocId,
     first_doc_in_block: DocId,
     offset_index_writer: SkipIndexBuilder,
@@ -34,10 +99,23 @@ impl StoreWriter {
     /// Create a store writer.
     ///
     /// The store writer will writes blocks on disc as
-    /// document are added.
-    pub fn new(writer: WritePtr, compressor: Compressor) -> StoreWriter {
+    /// document are added. A block is compressed once it exceeds
+    /// `block_size` bytes, with the compression level `compress_level`
+    /// (or the default level of the compressor if `None`).
+    pub fn new(
+        writer: WritePtr,
+        compressor: Compressor,
+        compress_level: Option<i32>,
+        block_size: usize,
+    ) -> StoreWriter {
         StoreWriter {
             compressor,
+            compress_level,
+            block_size,
+            dictionary_size: None,
+            dictionary: Vec::new(),
+            pending_blocks: Vec::new(),
+            num_pending_bytes: 0,
             doc: 0,
             first_doc_in_block: 0,
             offset_index_writer: SkipIndexBuilder::new(),
@@ -47,13 +125,33 @@ impl StoreWriter {
         }
     }
 
+    /// Creates a store writer with the doc store settings of an index.
+    pub(crate) fn for_settings(writer: WritePtr, settings: &IndexSettings) -> StoreWriter {
+        let mut store_writer = StoreWriter::new(
+            writer,
+            settings.docstore_compression,
+            settings.docstore_compress_level,
+            settings.docstore_blocksize,
+        );
+        store_writer.dictionary_size = settings.docstore_dictionary_size;
+        store_writer
+    }
+
     pub(crate) fn compressor(&self) -> Compressor {
         self.compressor
     }
 
+    /// Returns true if the blocks are compressed with a dictionary.
+    pub(crate) fn has_dictionary(&self) -> bool {
+        self.dictionary_size.is_some() || !self.dictionary.is_empty()
+    }
+
     /// The memory used (inclusive childs)
     pub fn mem_usage(&self) -> usize {
-        self.intermediary_buffer.capacity() + self.current_block.capacity()
+        self.intermediary_buffer.capacity()
+            + self.current_block.capacity()
+            + self.dictionary.capacity()
+            + self.num_pending_bytes
     }
 
     /// Store bytes of a serialized document.
@@ -66,8 +164,9 @@ impl StoreWriter {
         VInt(doc_num_bytes as u64).serialize(&mut self.current_block)?;
         self.current_block.write_all(serialized_document)?;
         self.doc += 1;
-        if self.current_block.len() > BLOCK_SIZE {
+        if self.current_block.len() > self.block_size {
             self.write_and_compress_block()?;
+            self.train_dictionary_if_ready(false)?;
         }
         Ok(())
     }
@@ -88,8 +187,9 @@ impl StoreWriter {
         self.current_block
             .write_all(&self.intermediary_buffer[..])?;
         self.doc += 1;
-        if self.current_block.len() > BLOCK_SIZE {
+        if self.current_block.len() > self.block_size {
             self.write_and_compress_block()?;
+            self.train_dictionary_if_ready(false)?;
         }
         Ok(())
     }
@@ -98,7 +198,15 @@ impl StoreWriter {
     /// This method is an optimization compared to iterating over the documents
     /// in the store and adding them one by one, as the store's data will
     /// not be decompressed and then recompressed.
+    ///
+    /// The blocks of a store compressed with a dictionary cannot be stacked.
     pub fn stack(&mut self, store_reader: &StoreReader) -> io::Result<()> {
+        if self.has_dictionary() || store_reader.has_dictionary() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Doc stores compressed with a dictionary cannot be stacked.",
+            ));
+        }
         if !self.current_block.is_empty() {
             self.write_and_compress_block()?;
         }
@@ -130,18 +238,54 @@ impl StoreWriter {
 
     fn write_and_compress_block(&mut self) -> io::Result<()> {
         assert!(self.doc > 0);
+        let block = std::mem::take(&mut self.current_block);
+        if self.dictionary_size.is_some() {
+            self.num_pending_bytes += block.len();
+            self.pending_blocks.push((block, self.doc));
+            return Ok(());
+        }
+        self.compress_and_write_block(&block, self.doc)?;
+        self.current_block = block;
+        self.current_block.clear();
+        Ok(())
+    }
+
+    fn compress_and_write_block(&mut self, block: &[u8], end_doc: DocId) -> io::Result<()> {
         self.intermediary_buffer.clear();
-        self.compressor
-            .compress(&self.current_block[..], &mut self.intermediary_buffer)?;
+        self.compressor.compress(
+            block,
+            &mut self.intermediary_buffer,
+            self.compress_level,
+            &self.dictionary,
+        )?;
         let start_offset = self.writer.written_bytes() as usize;
         self.writer.write_all(&self.intermediary_buffer)?;
         let end_offset = self.writer.written_bytes() as usize;
-        let end_doc = self.doc;
-        self.register_checkpoint(Checkpoint {
+        self.offset_index_writer.insert(Checkpoint {
             doc_range: self.first_doc_in_block..end_doc,
             byte_range: start_offset..end_offset,
         });
-        self.current_block.clear();
+        self.first_doc_in_block = end_doc;
+        Ok(())
+    }
+
+    /// Trains the dictionary once enough blocks are pending, or if `force` is true, and
+    /// writes the pending blocks.
+    fn train_dictionary_if_ready(&mut self, force: bool) -> io::Result<()> {
+        let dictionary_size = if let Some(dictionary_size) = self.dictionary_size {
+            dictionary_size
+        } else {
+            return Ok(());
+        };
+        if !force && self.num_pending_bytes < dictionary_size * DICTIONARY_TRAINING_RATIO {
+            return Ok(());
+        }
+        self.dictionary = train_dictionary(self.compressor, &self.pending_blocks, dictionary_size)?;
+        self.dictionary_size = None;
+        for (block, end_doc) in std::mem::take(&mut self.pending_blocks) {
+            self.compress_and_write_block(&block, end_doc)?;
+        }
+        self.num_pending_bytes = 0;
         Ok(())
     }
 
@@ -153,9 +297,12 @@ impl StoreWriter {
         if !self.current_block.is_empty() {
             self.write_and_compress_block()?;
         }
+        self.train_dictionary_if_ready(true)?;
         let header_offset: u64 = self.writer.written_bytes() as u64;
-        let footer = DocStoreFooter::new(header_offset, self.compressor);
+        let footer =
+            DocStoreFooter::new(header_offset, self.compressor, self.dictionary.len() as u32);
         self.offset_index_writer.write(&mut self.writer)?;
+        self.writer.write_all(&self.dictionary)?;
         footer.serialize(&mut self.writer)?;
         self.writer.terminate()
     }
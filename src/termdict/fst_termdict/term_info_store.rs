@@ -2,7 +2,7 @@ use crate::directory::{FileSlice, OwnedBytes};
 use crate::postings::TermInfo;
 use crate::termdict::TermOrdinal;
 use byteorder::{ByteOrder, LittleEndian};
-use common::{BinarySerializable, FixedSize};
+use common::{BinarySerializable, FixedSize, HasLen};
 use std::cmp;
 use std::io::{self, Read, Write};
 use tantivy_bitpacker::compute_num_bits;
@@ -10,6 +10,16 @@ use tantivy_bitpacker::BitPacker;
 
 const BLOCK_LEN: usize = 256;
 
+/// Marks the end of the total term frequencies, written after the term infos.
+///
+/// The stores written before total term frequencies were recorded end with the term infos.
+/// Readers unaware of the total term frequencies ignore them, as they locate the term infos
+/// from the start of the store.
+const TOTAL_TERM_FREQS_MAGIC_NUMBER: u64 = 0x7474_665f_7374_6f72;
+
+/// Size of the meta of a block of total term frequencies: its offset and its number of bits.
+const TOTAL_TERM_FREQ_BLOCK_META_NUM_BYTES: usize = 9;
+
 #[derive(Debug, Eq, PartialEq, Default)]
 struct TermInfoBlockMeta {
     offset: u64,
@@ -88,14 +98,66 @@ impl TermInfoBlockMeta {
             doc_freq,
             postings_range: postings_start_offset..postings_end_offset,
             positions_range: positions_start_offset..positions_end_offset,
+            total_term_freq: None,
         }
     }
 }
 
+/// The total term frequencies of the terms, bitpacked by blocks of `BLOCK_LEN` terms.
+///
+/// The difference between the total term frequency and the document frequency of a term
+/// is stored, as it requires fewer bits.
+struct TotalTermFreqs {
+    block_meta_bytes: OwnedBytes,
+    data: OwnedBytes,
+}
+
+impl TotalTermFreqs {
+    /// Splits the total term frequencies at the end of a term info store, if there are some.
+    fn open(file: FileSlice) -> io::Result<(FileSlice, Option<TotalTermFreqs>)> {
+        if file.len() < 16 + 3 * u64::SIZE_IN_BYTES {
+            return Ok((file, None));
+        }
+        let (main_slice, trailer_slice) = file.clone().split_from_end(3 * u64::SIZE_IN_BYTES);
+        let mut trailer_bytes = trailer_slice.read_bytes()?;
+        let block_metas_len = u64::deserialize(&mut trailer_bytes)? as usize;
+        let data_len = u64::deserialize(&mut trailer_bytes)? as usize;
+        if u64::deserialize(&mut trailer_bytes)? != TOTAL_TERM_FREQS_MAGIC_NUMBER
+            || block_metas_len.saturating_add(data_len) > main_slice.len()
+        {
+            return Ok((file, None));
+        }
+        let (term_info_store_file, total_term_freqs_file) =
+            main_slice.split_from_end(block_metas_len + data_len);
+        let (block_metas_file, data_file) = total_term_freqs_file.split(block_metas_len);
+        let total_term_freqs = TotalTermFreqs {
+            block_meta_bytes: block_metas_file.read_bytes()?,
+            data: data_file.read_bytes()?,
+        };
+        Ok((term_info_store_file, Some(total_term_freqs)))
+    }
+
+    fn get(&self, term_ord: TermOrdinal, doc_freq: u32) -> u64 {
+        let block_id = (term_ord as usize) / BLOCK_LEN;
+        let mut block_meta =
+            &self.block_meta_bytes.as_slice()[block_id * TOTAL_TERM_FREQ_BLOCK_META_NUM_BYTES..];
+        let offset = u64::deserialize(&mut block_meta).expect("Failed to deserialize offset");
+        let num_bits = block_meta[0];
+        let inner_offset = (term_ord as usize) % BLOCK_LEN;
+        let excess = extract_bits(
+            &self.data.as_slice()[offset as usize..],
+            inner_offset * num_bits as usize,
+            num_bits,
+        );
+        u64::from(doc_freq) + excess
+    }
+}
+
 pub struct TermInfoStore {
     num_terms: usize,
     block_meta_bytes: OwnedBytes,
     term_info_bytes: OwnedBytes,
+    total_term_freqs: Option<TotalTermFreqs>,
 }
 
 fn extract_bits(data: &[u8], addr_bits: usize, num_bits: u8) -> u64 {
@@ -121,6 +183,7 @@ fn extract_bits(data: &[u8], addr_bits: usize, num_bits: u8) -> u64 {
 
 impl TermInfoStore {
     pub fn open(term_info_store_file: FileSlice) -> crate::Result<TermInfoStore> {
+        let (term_info_store_file, total_term_freqs) = TotalTermFreqs::open(term_info_store_file)?;
         let (len_slice, main_slice) = term_info_store_file.split(16);
         let mut bytes = len_slice.read_bytes()?;
         let len = u64::deserialize(&mut bytes)? as usize;
@@ -131,10 +194,19 @@ impl TermInfoStore {
             num_terms,
             block_meta_bytes: block_meta_file.read_bytes()?,
             term_info_bytes,
+            total_term_freqs,
         })
     }
 
     pub fn get(&self, term_ord: TermOrdinal) -> TermInfo {
+        let mut term_info = self.get_without_total_term_freq(term_ord);
+        if let Some(total_term_freqs) = self.total_term_freqs.as_ref() {
+            term_info.total_term_freq = Some(total_term_freqs.get(term_ord, term_info.doc_freq));
+        }
+        term_info
+    }
+
+    fn get_without_total_term_freq(&self, term_ord: TermOrdinal) -> TermInfo {
         let block_id = (term_ord as usize) / BLOCK_LEN;
         let buffer = self.block_meta_bytes.as_slice();
         let mut block_data: &[u8] = &buffer[block_id * TermInfoBlockMeta::SIZE_IN_BYTES..];
@@ -159,6 +231,10 @@ impl TermInfoStore {
 pub struct TermInfoStoreWriter {
     buffer_block_metas: Vec<u8>,
     buffer_term_infos: Vec<u8>,
+    buffer_total_term_freq_block_metas: Vec<u8>,
+    buffer_total_term_freqs: Vec<u8>,
+    // Whether the terms have a total term frequency, known from the first term.
+    has_total_term_freqs: Option<bool>,
     term_infos: Vec<TermInfo>,
     num_terms: u64,
 }
@@ -192,12 +268,39 @@ impl TermInfoStoreWriter {
         TermInfoStoreWriter {
             buffer_block_metas: Vec::new(),
             buffer_term_infos: Vec::new(),
+            buffer_total_term_freq_block_metas: Vec::new(),
+            buffer_total_term_freqs: Vec::new(),
+            has_total_term_freqs: None,
             term_infos: Vec::with_capacity(BLOCK_LEN),
             num_terms: 0u64,
         }
     }
 
+    fn flush_total_term_freqs_block(&mut self) -> io::Result<()> {
+        let excesses: Vec<u64> = self
+            .term_infos
+            .iter()
+            .map(|term_info| {
+                let total_term_freq = term_info.total_term_freq.unwrap_or(0);
+                total_term_freq.saturating_sub(u64::from(term_info.doc_freq))
+            })
+            .collect();
+        let num_bits = compute_num_bits(excesses.iter().cloned().max().unwrap_or(0));
+        (self.buffer_total_term_freqs.len() as u64)
+            .serialize(&mut self.buffer_total_term_freq_block_metas)?;
+        num_bits.serialize(&mut self.buffer_total_term_freq_block_metas)?;
+        let mut bit_packer = BitPacker::new();
+        for excess in excesses {
+            bit_packer.write(excess, num_bits, &mut self.buffer_total_term_freqs)?;
+        }
+        bit_packer.flush(&mut self.buffer_total_term_freqs)?;
+        Ok(())
+    }
+
     fn flush_block(&mut self) -> io::Result<()> {
+        if self.has_total_term_freqs == Some(true) {
+            self.flush_total_term_freqs_block()?;
+        }
         let mut bit_packer = BitPacker::new();
         let ref_term_info = self.term_infos[0].clone();
 
@@ -263,6 +366,12 @@ impl TermInfoStoreWriter {
     }
 
     pub fn write_term_info(&mut self, term_info: &TermInfo) -> io::Result<()> {
+        let has_total_term_freq = term_info.total_term_freq.is_some();
+        let has_total_term_freqs = *self.has_total_term_freqs.get_or_insert(has_total_term_freq);
+        assert_eq!(
+            has_total_term_freqs, has_total_term_freq,
+            "Either all of the terms or none of them have a total term frequency."
+        );
         self.num_terms += 1u64;
         self.term_infos.push(term_info.clone());
         if self.term_infos.len() >= BLOCK_LEN {
@@ -280,6 +389,13 @@ impl TermInfoStoreWriter {
         self.num_terms.serialize(write)?;
         write.write_all(&self.buffer_block_metas)?;
         write.write_all(&self.buffer_term_infos)?;
+        if self.has_total_term_freqs == Some(true) {
+            write.write_all(&self.buffer_total_term_freq_block_metas)?;
+            write.write_all(&self.buffer_total_term_freqs)?;
+            (self.buffer_total_term_freq_block_metas.len() as u64).serialize(write)?;
+            (self.buffer_total_term_freqs.len() as u64).serialize(write)?;
+            TOTAL_TERM_FREQS_MAGIC_NUMBER.serialize(write)?;
+        }
         Ok(())
     }
 }
@@ -289,6 +405,7 @@ mod tests {
 
     use super::extract_bits;
     use super::TermInfoBlockMeta;
+    use super::TotalTermFreqs;
     use super::{TermInfoStore, TermInfoStoreWriter};
     use crate::directory::FileSlice;
     use crate::postings::TermInfo;
@@ -327,6 +444,7 @@ mod tests {
                 doc_freq: 512,
                 postings_range: 51..57,
                 positions_range: 110..134,
+                total_term_freq: None,
             },
             doc_freq_nbits: 10,
             postings_offset_nbits: 5,
@@ -349,6 +467,7 @@ mod tests {
                 doc_freq: i as u32,
                 postings_range: offset(i)..offset(i + 1),
                 positions_range: offset(i) * 3..offset(i + 1) * 3,
+                total_term_freq: None,
             };
             store_writer.write_term_info(&term_info)?;
             term_infos.push(term_info);
@@ -366,4 +485,37 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_pack_total_term_freqs() -> crate::Result<()> {
+        let mut store_writer = TermInfoStoreWriter::new();
+        let mut term_infos = vec![];
+        for i in 0usize..1000usize {
+            let term_info = TermInfo {
+                doc_freq: i as u32,
+                postings_range: i..i + 1,
+                positions_range: 0..0,
+                total_term_freq: Some((i + i * i % 17) as u64),
+            };
+            store_writer.write_term_info(&term_info)?;
+            term_infos.push(term_info);
+        }
+        let mut buffer = Vec::new();
+        store_writer.serialize(&mut buffer)?;
+        let term_info_store = TermInfoStore::open(FileSlice::from(buffer.clone()))?;
+        for i in 0..1000 {
+            assert_eq!(term_info_store.get(i as u64), term_infos[i]);
+        }
+
+        // the stores written before total term frequencies were recorded end with the term
+        // infos.
+        let (term_info_store_file, _) = TotalTermFreqs::open(FileSlice::from(buffer))?;
+        let term_info_store = TermInfoStore::open(term_info_store_file)?;
+        for i in 0..1000 {
+            let term_info = term_info_store.get(i as u64);
+            assert_eq!(term_info.doc_freq, i as u32);
+            assert_eq!(term_info.total_term_freq, None);
+        }
+        Ok(())
+    }
 }
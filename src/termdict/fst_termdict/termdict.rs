@@ -3,7 +3,7 @@ use super::{TermStreamer, TermStreamerBuilder};
 use crate::directory::{FileSlice, OwnedBytes};
 use crate::error::DataCorruption;
 use crate::postings::TermInfo;
-use crate::termdict::TermOrdinal;
+use crate::termdict::{prefix_successor, TermOrdinal};
 use common::{BinarySerializable, CountingWriter};
 use once_cell::sync::Lazy;
 use std::io::{self, Write};
@@ -172,6 +172,39 @@ impl TermDictionary {
         Ok(true)
     }
 
+    /// Calls `callback` with each of the given ordinals and its associated term.
+    ///
+    /// The ordinals must be sorted. Unlike a call to `ord_to_term` per ordinal,
+    /// the dictionary is streamed once, from the first to the last ordinal.
+    ///
+    /// Returns true iff all of the terms have been found.
+    pub fn sorted_ords_to_term_cb<F: FnMut(TermOrdinal, &[u8])>(
+        &self,
+        ords: &[TermOrdinal],
+        mut callback: F,
+    ) -> io::Result<bool> {
+        let first_ord = match ords.first() {
+            Some(first_ord) => *first_ord,
+            None => return Ok(true),
+        };
+        let mut first_term = Vec::new();
+        if !self.ord_to_term(first_ord, &mut first_term)? {
+            return Ok(false);
+        }
+        let mut stream = self.range().ge(&first_term).into_stream()?;
+        let mut has_term = stream.advance();
+        for &ord in ords {
+            while has_term && stream.term_ord() < ord {
+                has_term = stream.advance();
+            }
+            if !has_term || stream.term_ord() != ord {
+                return Ok(false);
+            }
+            callback(ord, stream.key());
+        }
+        Ok(true)
+    }
+
     /// Returns the number of terms in the dictionary.
     pub fn term_info_from_ord(&self, term_ord: TermOrdinal) -> TermInfo {
         self.term_info_store.get(term_ord)
@@ -190,6 +223,17 @@ impl TermDictionary {
         TermStreamerBuilder::new(self, self.fst_index.range())
     }
 
+    /// Returns a range builder, to stream all of the terms
+    /// starting with `prefix`.
+    pub fn prefix_range<K: AsRef<[u8]>>(&self, prefix: K) -> TermStreamerBuilder<'_> {
+        let prefix = prefix.as_ref();
+        let range = self.range().ge(prefix);
+        match prefix_successor(prefix) {
+            Some(successor) => range.lt(successor),
+            None => range,
+        }
+    }
+
     /// A stream of all the sorted terms. [See also `.stream_field()`](#method.stream_field)
     pub fn stream(&self) -> io::Result<TermStreamer<'_>> {
         self.range().into_stream()
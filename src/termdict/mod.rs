@@ -18,9 +18,40 @@ and then treated as a `u64`.
 as `u64`.
 
 A second datastructure makes it possible to access a [`TermInfo`](../postings/struct.TermInfo.html).
+
+The terms of a field in a segment are streamed in order, within a range, by prefix, or
+matching an [`Automaton`](./trait.Automaton.html), such as a [`Regex`](./struct.Regex.html)
+or the Levenshtein automaton of a
+[`FuzzyTermQuery`](../query/struct.FuzzyTermQuery.html#method.automaton).
+
+```rust
+use tantivy::schema::{Schema, TEXT};
+use tantivy::{doc, Index};
+
+# fn main() -> tantivy::Result<()> {
+let mut schema_builder = Schema::builder();
+let title = schema_builder.add_text_field("title", TEXT);
+let index = Index::create_in_ram(schema_builder.build());
+let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+index_writer.add_document(doc!(title => "the old man and the sea"));
+index_writer.add_document(doc!(title => "the sea wolf"));
+index_writer.commit()?;
+
+let searcher = index.reader()?.searcher();
+let inverted_index = searcher.segment_reader(0).inverted_index(title)?;
+let mut stream = inverted_index.terms().prefix_range("th").into_stream()?;
+let mut terms = Vec::new();
+while let Some((term_bytes, term_info)) = stream.next() {
+    terms.push((String::from_utf8(term_bytes.to_vec()).unwrap(), term_info.doc_freq));
+}
+assert_eq!(terms, vec![("the".to_string(), 2)]);
+# Ok(())
+# }
+```
 */
 
 use tantivy_fst::automaton::AlwaysMatch;
+pub use tantivy_fst::{Automaton, Regex};
 
 mod fst_termdict;
 use fst_termdict as termdict;
@@ -54,3 +85,16 @@ pub type TermMerger<'a> = self::merger::TermMerger<'a>;
 /// `TermStreamer` acts as a cursor over a range of terms of a segment.
 /// Terms are guaranteed to be sorted.
 pub type TermStreamer<'a, A = AlwaysMatch> = self::termdict::TermStreamer<'a, A>;
+
+/// `TermStreamerBuilder` is a helper object used to define
+/// a range of terms that should be streamed.
+pub type TermStreamerBuilder<'a, A = AlwaysMatch> = self::termdict::TermStreamerBuilder<'a, A>;
+
+/// Returns the smallest byte string greater than all of the byte strings
+/// starting with `prefix`, if there is one.
+pub(crate) fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last_incrementable = prefix.iter().rposition(|&byte| byte != u8::MAX)?;
+    let mut successor = prefix[..=last_incrementable].to_vec();
+    successor[last_incrementable] += 1;
+    Some(successor)
+}
@@ -14,6 +14,7 @@ fn make_term_info(term_ord: u64) -> TermInfo {
         doc_freq: term_ord as u32,
         postings_range: offset(term_ord)..offset(term_ord + 1),
         positions_range: offset(term_ord) * 2..offset(term_ord + 1) * 2,
+        total_term_freq: None,
     }
 }
 
@@ -375,6 +376,30 @@ fn test_ord_to_term() -> crate::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_sorted_ords_to_term_cb() -> crate::Result<()> {
+    let termdict = stream_range_test_dict()?;
+    let mut terms = vec![];
+    assert!(
+        termdict.sorted_ords_to_term_cb(&[2, 3, 3, 7, 9], |ord, term| {
+            terms.push((ord, term.to_vec()))
+        })?
+    );
+    assert_eq!(
+        terms,
+        vec![
+            (2, vec![2u8]),
+            (3, vec![3u8]),
+            (3, vec![3u8]),
+            (7, vec![7u8]),
+            (9, vec![9u8])
+        ]
+    );
+    assert!(termdict.sorted_ords_to_term_cb(&[], |_, _| panic!())?);
+    assert!(!termdict.sorted_ords_to_term_cb(&[8, 10], |_, _| {})?);
+    Ok(())
+}
+
 #[test]
 fn test_stream_term_ord() -> crate::Result<()> {
     let termdict = stream_range_test_dict()?;
@@ -428,3 +453,70 @@ fn test_automaton_search() -> crate::Result<()> {
     assert!(!range.advance());
     Ok(())
 }
+
+#[test]
+fn test_prefix_successor() {
+    use super::prefix_successor;
+    assert_eq!(prefix_successor(b"abc"), Some(b"abd".to_vec()));
+    assert_eq!(prefix_successor(&[b'a', 255u8, 255u8]), Some(b"b".to_vec()));
+    assert_eq!(prefix_successor(&[255u8]), None);
+    assert_eq!(prefix_successor(b""), None);
+}
+
+#[test]
+fn test_prefix_range_and_search() -> crate::Result<()> {
+    use super::Regex;
+    use crate::query::FuzzyTermQuery;
+    use crate::schema::{Field, Term};
+
+    const WORDS: [&str; 7] = ["car", "card", "care", "cart", "cat", "dog", "dot"];
+    let directory = RamDirectory::create();
+    let path = PathBuf::from("TermDictionary");
+    {
+        let write = directory.open_write(&path)?;
+        let mut term_dictionary_builder = TermDictionaryBuilder::create(write)?;
+        for (term_ord, word) in WORDS.iter().enumerate() {
+            term_dictionary_builder.insert(word.as_bytes(), &make_term_info(term_ord as u64))?;
+        }
+        term_dictionary_builder.finish()?.terminate()?;
+    }
+    let term_dict = TermDictionary::open(directory.open_read(&path)?)?;
+    fn collect_terms<A: super::Automaton>(mut stream: TermStreamer<'_, A>) -> Vec<(String, u32)> {
+        let mut terms = Vec::new();
+        while let Some((term_bytes, term_info)) = stream.next() {
+            terms.push((
+                str::from_utf8(term_bytes).unwrap().to_string(),
+                term_info.doc_freq,
+            ));
+        }
+        terms
+    }
+    let terms = collect_terms(term_dict.prefix_range("car").into_stream()?);
+    assert_eq!(
+        terms,
+        vec![
+            ("car".to_string(), 0),
+            ("card".to_string(), 1),
+            ("care".to_string(), 2),
+            ("cart".to_string(), 3)
+        ]
+    );
+    assert!(collect_terms(term_dict.prefix_range("z").into_stream()?).is_empty());
+    assert_eq!(
+        collect_terms(term_dict.prefix_range("").into_stream()?).len(),
+        WORDS.len()
+    );
+
+    let regex = Regex::new("do.").unwrap();
+    let terms = collect_terms(term_dict.search(regex).into_stream()?);
+    assert_eq!(terms, vec![("dog".to_string(), 5), ("dot".to_string(), 6)]);
+
+    let fuzzy_query = FuzzyTermQuery::new(
+        Term::from_field_text(Field::from_field_id(0), "cor"),
+        1,
+        true,
+    );
+    let terms = collect_terms(term_dict.search(fuzzy_query.automaton()?).into_stream()?);
+    assert_eq!(terms, vec![("car".to_string(), 0)]);
+    Ok(())
+}
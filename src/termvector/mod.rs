@@ -0,0 +1,267 @@
+//! The term vector of a document lists the terms of one of its fields, with
+//! the positions and the offsets of each of their occurrences.
+//!
+//! It is the forward counterpart of the inverted index: while the postings
+//! associate a term to the documents containing it, the term vector associates
+//! a document to the terms it contains. This makes it possible to highlight a document,
+//! or to extract its most significant terms, without tokenizing its stored text again.
+//!
+//! Term vectors are opt-in, through
+//! [`TextFieldIndexing::set_store_term_vectors`](../schema/struct.TextFieldIndexing.html#method.set_store_term_vectors),
+//! and are read through the [`TermVectorReader`](./struct.TermVectorReader.html)
+//! of a segment.
+//!
+//! ```rust
+//! use tantivy::schema::{Schema, TextFieldIndexing, TextOptions};
+//! use tantivy::{doc, Index};
+//!
+//! # fn main() -> tantivy::Result<()> {
+//! let mut schema_builder = Schema::builder();
+//! let indexing = TextFieldIndexing::default().set_store_term_vectors();
+//! let body = schema_builder.add_text_field(
+//!     "body",
+//!     TextOptions::default().set_indexing_options(indexing),
+//! );
+//! let index = Index::create_in_ram(schema_builder.build());
+//! let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+//! index_writer.add_document(doc!(body => "to be or not to be"));
+//! index_writer.commit()?;
+//!
+//! let searcher = index.reader()?.searcher();
+//! let term_vector_reader = searcher.segment_reader(0).term_vectors(body)?;
+//! let term_vector = term_vector_reader.get(0)?;
+//! let entry = term_vector.get("be").unwrap();
+//! assert_eq!(entry.term_freq(), 2);
+//! assert_eq!(entry.positions(), &[1, 5]);
+//! assert_eq!(entry.offsets(), &[3..5, 16..18]);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Format
+//!
+//! The term vectors of a segment are stored in the `.termvec` file, one section
+//! per field. A section is the concatenation of the serialized term vectors of
+//! the documents, followed by the bitpacked offsets of the term vectors, and by a footer
+//! holding the length of the term vectors, the number of documents and the number of bits of
+//! the offsets.
+//!
+//! A term vector is serialized as its number of terms, followed by the terms in
+//! lexicographical order. Each term is prefix-compressed with the previous term, and
+//! followed by its term frequency and by the position, the start offset and the length
+//! of each of its occurrences. All of these are `VInt`s.
+mod reader;
+mod serializer;
+mod writer;
+
+pub use self::reader::{TermVectorReader, TermVectorReaders};
+pub use self::serializer::TermVectorsSerializer;
+pub(crate) use self::writer::RecordingTokenStream;
+pub use self::writer::TermVectorsWriter;
+
+use crate::schema::{Field, FieldType, Schema};
+use std::ops::Range;
+
+/// The terms of a field of a document, in lexicographical order.
+///
+/// A document without any token in the field has an empty term vector.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TermVector {
+    entries: Vec<TermVectorEntry>,
+}
+
+impl TermVector {
+    /// Returns the entries of the term vector, one per term, in lexicographical order.
+    pub fn entries(&self) -> &[TermVectorEntry] {
+        &self.entries
+    }
+
+    /// Returns the entry of a term, or `None` if the document does not contain it.
+    pub fn get(&self, term: &str) -> Option<&TermVectorEntry> {
+        self.entries
+            .binary_search_by(|entry| entry.term.as_str().cmp(term))
+            .ok()
+            .map(|ord| &self.entries[ord])
+    }
+
+    /// Returns the number of distinct terms of the document.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true iff the document has no term.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// A term of a term vector, with its occurrences in the document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TermVectorEntry {
+    term: String,
+    positions: Vec<u32>,
+    offsets: Vec<Range<usize>>,
+}
+
+impl TermVectorEntry {
+    /// Returns the text of the term.
+    pub fn term(&self) -> &str {
+        &self.term
+    }
+
+    /// Returns the number of occurrences of the term in the document.
+    pub fn term_freq(&self) -> u32 {
+        self.positions.len() as u32
+    }
+
+    /// Returns the positions of the occurrences of the term, in increasing order.
+    pub fn positions(&self) -> &[u32] {
+        &self.positions
+    }
+
+    /// Returns the byte ranges of the occurrences of the term in the text of the
+    /// document, in the order of their positions.
+    ///
+    /// The offsets of the values of a multivalued field are shifted by the length of
+    /// the previous values.
+    pub fn offsets(&self) -> &[Range<usize>] {
+        &self.offsets
+    }
+}
+
+/// Returns the text fields storing term vectors.
+pub(crate) fn fields_with_term_vectors(schema: &Schema) -> Vec<Field> {
+    schema
+        .fields()
+        .filter(|(_, field_entry)| has_term_vectors(field_entry.field_type()))
+        .map(|(field, _)| field)
+        .collect()
+}
+
+pub(crate) fn has_term_vectors(field_type: &FieldType) -> bool {
+    if let FieldType::Str(text_options) = field_type {
+        text_options
+            .get_indexing_options()
+            .map(|indexing| indexing.store_term_vectors())
+            .unwrap_or(false)
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TermVector;
+    use crate::collector::TopDocs;
+    use crate::query::TermQuery;
+    use crate::schema::{
+        IndexRecordOption, Schema, TextFieldIndexing, TextOptions, FAST, STORED, TEXT,
+    };
+    use crate::{doc, Index, IndexSettings, IndexSortByField, Order, Term};
+
+    fn term_vector_text_options() -> TextOptions {
+        let indexing = TextFieldIndexing::default()
+            .set_index_option(IndexRecordOption::Basic)
+            .set_store_term_vectors();
+        TextOptions::default()
+            .set_indexing_options(indexing)
+            .set_stored()
+    }
+
+    fn terms(term_vector: &TermVector) -> Vec<(&str, u32)> {
+        term_vector
+            .entries()
+            .iter()
+            .map(|entry| (entry.term(), entry.term_freq()))
+            .collect()
+    }
+
+    #[test]
+    fn test_term_vectors() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let body = schema_builder.add_text_field("body", term_vector_text_options());
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(body => "a b a", title => "title"));
+        index_writer.add_document(doc!(title => "no body"));
+        index_writer.add_document(doc!(body => "Hello", body => "hello world"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        assert!(segment_reader.term_vectors(title).is_err());
+        let term_vector_reader = segment_reader.term_vectors(body)?;
+
+        let term_vector = term_vector_reader.get(0)?;
+        assert_eq!(terms(&term_vector), vec![("a", 2), ("b", 1)]);
+        let entry = term_vector.get("a").unwrap();
+        assert_eq!(entry.positions(), &[0, 2]);
+        assert_eq!(entry.offsets(), &[0..1, 4..5]);
+        assert!(term_vector.get("c").is_none());
+
+        assert!(term_vector_reader.get(1)?.is_empty());
+
+        // the values of a multivalued field are chained.
+        let term_vector = term_vector_reader.get(2)?;
+        assert_eq!(terms(&term_vector), vec![("hello", 2), ("world", 1)]);
+        let entry = term_vector.get("hello").unwrap();
+        assert_eq!(entry.positions(), &[0, 2]);
+        assert_eq!(entry.offsets(), &[0..5, 5..10]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_term_vectors_merge_and_sort() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let body = schema_builder.add_text_field("body", term_vector_text_options());
+        let rank = schema_builder.add_u64_field("rank", FAST | STORED);
+        let settings = IndexSettings {
+            sort_by_field: Some(IndexSortByField {
+                field: "rank".to_string(),
+                order: Order::Desc,
+            }),
+            ..Default::default()
+        };
+        let index = Index::builder()
+            .schema(schema_builder.build())
+            .settings(settings)
+            .create_in_ram()?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(body => "one", rank => 1u64));
+        index_writer.add_document(doc!(body => "three three three", rank => 3u64));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(body => "two two", rank => 2u64));
+        index_writer.add_document(doc!(body => "deleted", rank => 4u64));
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_text(body, "deleted"));
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        let term_vector_reader = segment_reader.term_vectors(body)?;
+        let term_vectors = (0..segment_reader.max_doc())
+            .map(|doc| term_vector_reader.get(doc))
+            .collect::<crate::Result<Vec<_>>>()?;
+        let term_vector_terms: Vec<Vec<(&str, u32)>> = term_vectors.iter().map(terms).collect();
+        assert_eq!(
+            term_vector_terms,
+            vec![vec![("three", 3)], vec![("two", 2)], vec![("one", 1)]]
+        );
+
+        // the term vectors follow the documents.
+        let query = TermQuery::new(Term::from_field_text(body, "two"), IndexRecordOption::Basic);
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(1))?;
+        let doc_address = top_docs[0].1;
+        assert_eq!(
+            terms(&term_vector_reader.get(doc_address.doc_id)?),
+            vec![("two", 2)]
+        );
+        let space_usage = searcher.space_usage()?;
+        assert!(space_usage.segments()[0].term_vectors().total() > 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use super::{TermVector, TermVectorEntry};
+use crate::directory::{CompositeFile, FileSlice, OwnedBytes};
+use crate::error::DataCorruption;
+use crate::schema::Field;
+use crate::space_usage::PerFieldSpaceUsage;
+use crate::DocId;
+use common::{BinarySerializable, HasLen, VInt};
+use std::io;
+use std::sync::Arc;
+use tantivy_bitpacker::BitUnpacker;
+
+/// Size of the footer of the term vectors of a field: the length of the term vectors,
+/// the number of documents and the number of bits of the offsets.
+const FOOTER_NUM_BYTES: usize = 8 + 4 + 1;
+
+/// Reader for the term vectors of all of the fields storing term vectors in a segment.
+#[derive(Clone)]
+pub struct TermVectorReaders {
+    data: Arc<CompositeFile>,
+}
+
+impl TermVectorReaders {
+    /// Creates a term vector reader.
+    pub fn open(file: FileSlice) -> crate::Result<TermVectorReaders> {
+        let data = CompositeFile::open(&file)?;
+        Ok(TermVectorReaders {
+            data: Arc::new(data),
+        })
+    }
+
+    /// Creates a term vector reader for a segment without term vectors.
+    pub fn empty() -> TermVectorReaders {
+        TermVectorReaders {
+            data: Arc::new(CompositeFile::empty()),
+        }
+    }
+
+    /// Returns the `TermVectorReader` for a specific field.
+    pub fn get_field(&self, field: Field) -> crate::Result<Option<TermVectorReader>> {
+        if let Some(file) = self.data.open_read(field) {
+            let term_vector_reader = TermVectorReader::open(file)?;
+            Ok(Some(term_vector_reader))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Return a break down of the space usage per field.
+    pub fn space_usage(&self) -> PerFieldSpaceUsage {
+        self.data.space_usage()
+    }
+}
+
+/// Reads the term vectors of the documents of a segment for a field.
+#[derive(Clone)]
+pub struct TermVectorReader {
+    data: OwnedBytes,
+    doc_offsets: OwnedBytes,
+    bit_unpacker: BitUnpacker,
+    num_docs: u32,
+}
+
+impl TermVectorReader {
+    /// Opens the term vectors of a field.
+    pub fn open(file: FileSlice) -> crate::Result<TermVectorReader> {
+        if file.len() < FOOTER_NUM_BYTES {
+            return Err(DataCorruption::comment_only(format!(
+                "The term vectors are smaller than their footer (len={}).",
+                file.len()
+            ))
+            .into());
+        }
+        let (body, footer) = file.split_from_end(FOOTER_NUM_BYTES);
+        let mut footer_bytes = footer.read_bytes()?;
+        let data_len = u64::deserialize(&mut footer_bytes)? as usize;
+        let num_docs = u32::deserialize(&mut footer_bytes)?;
+        let num_bits = u8::deserialize(&mut footer_bytes)?;
+        if data_len > body.len() {
+            return Err(DataCorruption::comment_only(format!(
+                "The length of the term vectors ({}) exceeds their file (len={}).",
+                data_len,
+                body.len()
+            ))
+            .into());
+        }
+        let (data, doc_offsets) = body.split(data_len);
+        Ok(TermVectorReader {
+            data: data.read_bytes()?,
+            doc_offsets: doc_offsets.read_bytes()?,
+            bit_unpacker: BitUnpacker::new(num_bits),
+            num_docs,
+        })
+    }
+
+    /// Creates a reader returning an empty term vector for all of the documents.
+    pub fn empty() -> TermVectorReader {
+        TermVectorReader {
+            data: OwnedBytes::empty(),
+            doc_offsets: OwnedBytes::empty(),
+            bit_unpacker: BitUnpacker::new(0),
+            num_docs: 0,
+        }
+    }
+
+    /// Returns the serialized term vector of `doc`.
+    ///
+    /// The documents without term vector have an empty serialized term vector.
+    pub(crate) fn term_vector_bytes(&self, doc: DocId) -> &[u8] {
+        if doc >= self.num_docs {
+            return &[];
+        }
+        let start = self
+            .bit_unpacker
+            .get(u64::from(doc), self.doc_offsets.as_slice());
+        let end = self
+            .bit_unpacker
+            .get(u64::from(doc) + 1, self.doc_offsets.as_slice());
+        &self.data.as_slice()[start as usize..end as usize]
+    }
+
+    /// Returns the term vector of `doc`.
+    ///
+    /// The documents without any token in the field, or written before the field
+    /// stored term vectors, have an empty term vector.
+    pub fn get(&self, doc: DocId) -> crate::Result<TermVector> {
+        let term_vector = deserialize_term_vector(self.term_vector_bytes(doc))
+            .map_err(|err| DataCorruption::comment_only(err.to_string()))?;
+        Ok(term_vector)
+    }
+}
+
+fn read_vint(cursor: &mut &[u8]) -> io::Result<usize> {
+    Ok(VInt::deserialize(cursor)?.val() as usize)
+}
+
+fn deserialize_term_vector(mut cursor: &[u8]) -> io::Result<TermVector> {
+    if cursor.is_empty() {
+        return Ok(TermVector::default());
+    }
+    let num_terms = read_vint(&mut cursor)?;
+    let mut entries: Vec<TermVectorEntry> = Vec::with_capacity(num_terms);
+    let mut term_bytes: Vec<u8> = Vec::new();
+    for _ in 0..num_terms {
+        let common_prefix_len = read_vint(&mut cursor)?;
+        let suffix_len = read_vint(&mut cursor)?;
+        if common_prefix_len > term_bytes.len() || suffix_len > cursor.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Term vector corrupted: invalid term length.",
+            ));
+        }
+        term_bytes.truncate(common_prefix_len);
+        term_bytes.extend_from_slice(&cursor[..suffix_len]);
+        cursor = &cursor[suffix_len..];
+        let term = String::from_utf8(term_bytes.clone())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let term_freq = read_vint(&mut cursor)?;
+        let mut positions = Vec::with_capacity(term_freq);
+        let mut offsets = Vec::with_capacity(term_freq);
+        let mut position = 0;
+        for _ in 0..term_freq {
+            position += read_vint(&mut cursor)? as u32;
+            let offset_from = read_vint(&mut cursor)?;
+            let offset_len = read_vint(&mut cursor)?;
+            positions.push(position);
+            offsets.push(offset_from..offset_from + offset_len);
+        }
+        entries.push(TermVectorEntry {
+            term,
+            positions,
+            offsets,
+        });
+    }
+    Ok(TermVector { entries })
+}
@@ -0,0 +1,55 @@
+use crate::directory::CompositeWrite;
+use crate::directory::WritePtr;
+use crate::schema::Field;
+use common::BinarySerializable;
+use std::io;
+use std::io::Write;
+use tantivy_bitpacker::{compute_num_bits, BitPacker};
+
+/// The term vectors serializer is in charge of
+/// the serialization of the term vectors of all fields.
+pub struct TermVectorsSerializer {
+    composite_write: CompositeWrite,
+}
+
+impl TermVectorsSerializer {
+    /// Constructor
+    pub fn from_write(write: WritePtr) -> io::Result<TermVectorsSerializer> {
+        let composite_write = CompositeWrite::wrap(write);
+        Ok(TermVectorsSerializer { composite_write })
+    }
+
+    /// Serialize the serialized term vectors of the documents of the given field,
+    /// in the order of their doc ids.
+    pub fn serialize_field<'a>(
+        &mut self,
+        field: Field,
+        term_vectors: impl Iterator<Item = &'a [u8]>,
+    ) -> io::Result<()> {
+        let write = self.composite_write.for_field(field);
+        let mut doc_offsets: Vec<u64> = vec![0];
+        let mut num_bytes = 0u64;
+        for term_vector in term_vectors {
+            write.write_all(term_vector)?;
+            num_bytes += term_vector.len() as u64;
+            doc_offsets.push(num_bytes);
+        }
+        let num_bits = compute_num_bits(num_bytes);
+        let mut bit_packer = BitPacker::new();
+        for &doc_offset in &doc_offsets {
+            bit_packer.write(doc_offset, num_bits, write)?;
+        }
+        bit_packer.close(write)?;
+        num_bytes.serialize(write)?;
+        ((doc_offsets.len() - 1) as u32).serialize(write)?;
+        num_bits.serialize(write)?;
+        write.flush()?;
+        Ok(())
+    }
+
+    /// Clean up / flush / close
+    pub fn close(self) -> io::Result<()> {
+        self.composite_write.close()?;
+        Ok(())
+    }
+}
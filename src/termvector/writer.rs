@@ -0,0 +1,215 @@
+use super::{fields_with_term_vectors, TermVectorsSerializer};
+use crate::indexer::doc_id_mapping::DocIdMapping;
+use crate::schema::{Field, Schema};
+use crate::tokenizer::{Token, TokenStream, MAX_TOKEN_LEN};
+use crate::DocId;
+use common::{BinarySerializable, VInt};
+use std::io;
+
+/// Serializes the term vector of a document out of its tokens.
+///
+/// The tokens are sorted by term, and then by position.
+fn serialize_term_vector(tokens: &mut [Token], output: &mut Vec<u8>) -> io::Result<()> {
+    tokens.sort_by(|left, right| {
+        (left.text.as_str(), left.position, left.offset_from).cmp(&(
+            right.text.as_str(),
+            right.position,
+            right.offset_from,
+        ))
+    });
+    let num_terms = 1 + tokens
+        .windows(2)
+        .filter(|pair| pair[0].text != pair[1].text)
+        .count();
+    VInt(num_terms as u64).serialize(output)?;
+    let mut previous_term: &str = "";
+    let mut term_start = 0;
+    while term_start < tokens.len() {
+        let term = tokens[term_start].text.as_str();
+        let term_len = tokens[term_start..]
+            .iter()
+            .take_while(|token| token.text == term)
+            .count();
+        let term_tokens = &tokens[term_start..term_start + term_len];
+        term_start += term_len;
+        let common_prefix_len = previous_term
+            .bytes()
+            .zip(term.bytes())
+            .take_while(|(left, right)| left == right)
+            .count();
+        let suffix = &term.as_bytes()[common_prefix_len..];
+        VInt(common_prefix_len as u64).serialize(output)?;
+        VInt(suffix.len() as u64).serialize(output)?;
+        output.extend_from_slice(suffix);
+        VInt(term_tokens.len() as u64).serialize(output)?;
+        let mut previous_position = 0;
+        for token in term_tokens {
+            VInt((token.position - previous_position) as u64).serialize(output)?;
+            VInt(token.offset_from as u64).serialize(output)?;
+            VInt((token.offset_to - token.offset_from) as u64).serialize(output)?;
+            previous_position = token.position;
+        }
+        previous_term = term;
+    }
+    Ok(())
+}
+
+/// Wraps a token stream, to collect its tokens while they are indexed.
+pub(crate) struct RecordingTokenStream<'a> {
+    token_stream: &'a mut dyn TokenStream,
+    tokens: &'a mut Vec<Token>,
+}
+
+impl<'a> RecordingTokenStream<'a> {
+    pub fn new(
+        token_stream: &'a mut dyn TokenStream,
+        tokens: &'a mut Vec<Token>,
+    ) -> RecordingTokenStream<'a> {
+        RecordingTokenStream {
+            token_stream,
+            tokens,
+        }
+    }
+}
+
+impl<'a> TokenStream for RecordingTokenStream<'a> {
+    fn advance(&mut self) -> bool {
+        if self.token_stream.advance() {
+            self.tokens.push(self.token_stream.token().clone());
+            true
+        } else {
+            false
+        }
+    }
+
+    fn token(&self) -> &Token {
+        self.token_stream.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.token_stream.token_mut()
+    }
+}
+
+/// The term vectors of the documents of a field, serialized in memory.
+#[derive(Default)]
+struct FieldTermVectorsWriter {
+    data: Vec<u8>,
+    // The start of the term vector of each document in `data`.
+    doc_offsets: Vec<usize>,
+}
+
+impl FieldTermVectorsWriter {
+    fn fill_up_to(&mut self, num_docs: usize) {
+        let num_bytes = self.data.len();
+        self.doc_offsets.resize(num_docs, num_bytes);
+    }
+
+    fn term_vector_bytes(&self, doc: DocId) -> &[u8] {
+        let start = self.doc_offsets[doc as usize];
+        let end = self
+            .doc_offsets
+            .get(doc as usize + 1)
+            .cloned()
+            .unwrap_or(self.data.len());
+        &self.data[start..end]
+    }
+}
+
+/// The `TermVectorsWriter` is in charge of recording the term vectors
+/// of the documents of a segment, for the fields storing term vectors.
+pub struct TermVectorsWriter {
+    fields: Vec<Field>,
+    // One writer per field id. `None` if the field does not store term vectors.
+    field_writers: Vec<Option<FieldTermVectorsWriter>>,
+}
+
+impl TermVectorsWriter {
+    /// Initialize with state for tracking the term vectors of the fields
+    /// specified in the schema.
+    pub fn for_schema(schema: &Schema) -> TermVectorsWriter {
+        let fields = fields_with_term_vectors(schema);
+        let mut field_writers: Vec<Option<FieldTermVectorsWriter>> =
+            std::iter::repeat_with(|| None)
+                .take(schema.fields().count())
+                .collect();
+        for field in &fields {
+            field_writers[field.field_id() as usize] = Some(FieldTermVectorsWriter::default());
+        }
+        TermVectorsWriter {
+            fields,
+            field_writers,
+        }
+    }
+
+    /// Returns true iff the term vectors of `field` are recorded.
+    pub fn has_term_vectors(&self, field: Field) -> bool {
+        self.field_writers[field.field_id() as usize].is_some()
+    }
+
+    /// The memory used inclusive childs
+    pub fn mem_usage(&self) -> usize {
+        self.field_writers
+            .iter()
+            .flatten()
+            .map(|field_writer| {
+                field_writer.data.capacity()
+                    + field_writer.doc_offsets.capacity() * std::mem::size_of::<usize>()
+            })
+            .sum()
+    }
+
+    /// Records the term vector of `doc` for `field`, out of the tokens of the field.
+    ///
+    /// The documents have to be recorded in order. The tokens longer than `MAX_TOKEN_LEN`
+    /// are ignored, as in the inverted index.
+    pub fn record(&mut self, doc: DocId, field: Field, tokens: &mut Vec<Token>) -> io::Result<()> {
+        let field_writer = self.field_writers[field.field_id() as usize]
+            .as_mut()
+            .expect("The field does not store term vectors.");
+        assert!(
+            field_writer.doc_offsets.len() <= doc as usize,
+            "Cannot register a given term vector twice"
+        );
+        field_writer.fill_up_to(doc as usize + 1);
+        tokens.retain(|token| token.text.len() <= MAX_TOKEN_LEN);
+        if !tokens.is_empty() {
+            serialize_term_vector(tokens, &mut field_writer.data)?;
+        }
+        Ok(())
+    }
+
+    /// Ensures that all documents in 0..max_doc have a term vector, empty if they
+    /// have not been recorded.
+    pub fn fill_up_to_max_doc(&mut self, max_doc: DocId) {
+        for field_writer in self.field_writers.iter_mut().flatten() {
+            field_writer.fill_up_to(max_doc as usize);
+        }
+    }
+
+    /// Serialize the term vectors of all of the fields, in the order of the new doc ids
+    /// if a `doc_id_map` is given.
+    pub fn serialize(
+        &self,
+        mut term_vectors_serializer: TermVectorsSerializer,
+        doc_id_map: Option<&DocIdMapping>,
+    ) -> io::Result<()> {
+        for &field in &self.fields {
+            let field_writer = self.field_writers[field.field_id() as usize]
+                .as_ref()
+                .expect("The field does not store term vectors.");
+            if let Some(doc_id_map) = doc_id_map {
+                let term_vectors = doc_id_map
+                    .iter_old_doc_ids()
+                    .map(|old_doc| field_writer.term_vector_bytes(old_doc));
+                term_vectors_serializer.serialize_field(field, term_vectors)?;
+            } else {
+                let num_docs = field_writer.doc_offsets.len() as DocId;
+                let term_vectors = (0..num_docs).map(|doc| field_writer.term_vector_bytes(doc));
+                term_vectors_serializer.serialize_field(field, term_vectors)?;
+            }
+        }
+        term_vectors_serializer.close()?;
+        Ok(())
+    }
+}
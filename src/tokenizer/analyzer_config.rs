@@ -0,0 +1,525 @@
+//! Configuration of a [`TextAnalyzer`](./struct.TextAnalyzer.html), built at runtime
+//! by [`TokenizerManager::build_analyzer`](./struct.TokenizerManager.html#method.build_analyzer).
+//!
+//! # Example
+//! ```rust
+//! use tantivy::tokenizer::*;
+//!
+//! let config: AnalyzerConfig = serde_json::from_str(r#"{
+//!     "tokenizer": {"type": "ngram", "min_gram": 2, "max_gram": 3},
+//!     "filters": ["lowercase", {"type": "stop_words", "words": ["he"]}]
+//! }"#).unwrap();
+//!
+//! let tokenizer_manager = TokenizerManager::default();
+//! let analyzer = tokenizer_manager.build_analyzer(&config).unwrap();
+//! let mut stream = analyzer.token_stream("Hey");
+//! assert_eq!(stream.next().unwrap().text, "hey");
+//! assert_eq!(stream.next().unwrap().text, "ey");
+//! assert!(stream.next().is_none());
+//! ```
+use super::{
+    AlphaNumOnlyFilter, AsciiFoldingFilter, BoxTokenFilter, CjkBigramTokenizer, EdgeNgramFilter,
+    EdgeNgramSide, Language, LowerCaser, NgramTokenizer, RawTokenizer, RegexTokenizer,
+    RegexTokenizerMode, RemoveLongFilter, ShingleFilter, SimpleTokenizer, Stemmer, StopWordFilter,
+    TextAnalyzer, TokenizerManager, WhitespaceTokenizer,
+};
+use crate::TantivyError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Configuration of a `TextAnalyzer`: a tokenizer, followed by a list of token filters.
+///
+/// In JSON, a component without parameters can be given by its name alone,
+/// e.g. `"lowercase"`, and a component with parameters is an object with its name
+/// as `type`, e.g. `{"type": "remove_long", "limit": 40}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzerConfig {
+    /// The tokenizer.
+    pub tokenizer: AnalyzerComponentConfig,
+    /// The token filters, applied in order.
+    #[serde(default)]
+    pub filters: Vec<AnalyzerComponentConfig>,
+}
+
+/// A tokenizer or a token filter of an [`AnalyzerConfig`](./struct.AnalyzerConfig.html),
+/// given by the name of its factory and its parameters.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    from = "AnalyzerComponentConfigRepr",
+    into = "AnalyzerComponentConfigRepr"
+)]
+pub struct AnalyzerComponentConfig {
+    /// The name of the factory of the component.
+    pub name: String,
+    /// The parameters given to the factory.
+    pub params: Map<String, Value>,
+}
+
+impl AnalyzerComponentConfig {
+    /// Creates the configuration of a component without parameters.
+    pub fn new(name: &str) -> AnalyzerComponentConfig {
+        AnalyzerComponentConfig {
+            name: name.to_string(),
+            params: Map::new(),
+        }
+    }
+
+    /// Creates the configuration of a component, given its parameters.
+    pub fn with_params(name: &str, params: Map<String, Value>) -> AnalyzerComponentConfig {
+        AnalyzerComponentConfig {
+            name: name.to_string(),
+            params,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum AnalyzerComponentConfigRepr {
+    Name(String),
+    WithParams {
+        #[serde(rename = "type")]
+        name: String,
+        #[serde(flatten)]
+        params: Map<String, Value>,
+    },
+}
+
+impl From<AnalyzerComponentConfigRepr> for AnalyzerComponentConfig {
+    fn from(repr: AnalyzerComponentConfigRepr) -> AnalyzerComponentConfig {
+        match repr {
+            AnalyzerComponentConfigRepr::Name(name) => AnalyzerComponentConfig {
+                name,
+                params: Map::new(),
+            },
+            AnalyzerComponentConfigRepr::WithParams { name, params } => {
+                AnalyzerComponentConfig { name, params }
+            }
+        }
+    }
+}
+
+impl From<AnalyzerComponentConfig> for AnalyzerComponentConfigRepr {
+    fn from(config: AnalyzerComponentConfig) -> AnalyzerComponentConfigRepr {
+        if config.params.is_empty() {
+            AnalyzerComponentConfigRepr::Name(config.name)
+        } else {
+            AnalyzerComponentConfigRepr::WithParams {
+                name: config.name,
+                params: config.params,
+            }
+        }
+    }
+}
+
+/// Deserializes the parameters of a component, given to its factory.
+///
+/// This is a helper for the implementation of tokenizer and token filter factories:
+/// a `serde` error is returned as a `TantivyError::InvalidArgument`.
+pub fn parse_analyzer_params<T: DeserializeOwned>(
+    component_name: &str,
+    params: &Value,
+) -> crate::Result<T> {
+    serde_json::from_value(params.clone()).map_err(|err| {
+        TantivyError::InvalidArgument(format!(
+            "Invalid parameters for {:?}: {}",
+            component_name, err
+        ))
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NoParams {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NgramParams {
+    min_gram: usize,
+    max_gram: usize,
+    #[serde(default)]
+    prefix_only: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RegexModeParam {
+    Match,
+    Split,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegexParams {
+    pattern: String,
+    mode: Option<RegexModeParam>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RemoveLongParams {
+    limit: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum EdgeNgramSideParam {
+    Front,
+    Back,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EdgeNgramParams {
+    min_gram: usize,
+    max_gram: usize,
+    side: Option<EdgeNgramSideParam>,
+    #[serde(default)]
+    keep_short_tokens: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StemmerParams {
+    #[serde(default = "default_language")]
+    language: Language,
+}
+
+fn default_language() -> Language {
+    Language::English
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StopWordsParams {
+    language: Option<Language>,
+    words: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShingleParams {
+    min_size: usize,
+    max_size: usize,
+    #[serde(default = "default_output_unigrams")]
+    output_unigrams: bool,
+    separator: Option<String>,
+}
+
+fn default_output_unigrams() -> bool {
+    true
+}
+
+fn invalid_params(component_name: &str, reason: &str) -> TantivyError {
+    TantivyError::InvalidArgument(format!(
+        "Invalid parameters for {:?}: {}",
+        component_name, reason
+    ))
+}
+
+fn without_params<T>(
+    component_name: &'static str,
+    build: fn() -> T,
+) -> impl Fn(&Value) -> crate::Result<T> {
+    move |params| {
+        parse_analyzer_params::<NoParams>(component_name, params)?;
+        Ok(build())
+    }
+}
+
+/// Registers the factories of the tokenizers and token filters of tantivy.
+pub(crate) fn register_builtin_factories(manager: &TokenizerManager) {
+    manager.register_tokenizer_factory(
+        "simple",
+        without_params("simple", || TextAnalyzer::from(SimpleTokenizer)),
+    );
+    manager.register_tokenizer_factory(
+        "raw",
+        without_params("raw", || TextAnalyzer::from(RawTokenizer)),
+    );
+    manager.register_tokenizer_factory(
+        "whitespace",
+        without_params("whitespace", || TextAnalyzer::from(WhitespaceTokenizer)),
+    );
+    manager.register_tokenizer_factory(
+        "cjk_bigram",
+        without_params("cjk_bigram", || TextAnalyzer::from(CjkBigramTokenizer)),
+    );
+    manager.register_tokenizer_factory("ngram", |params| {
+        let params: NgramParams = parse_analyzer_params("ngram", params)?;
+        if params.min_gram == 0 || params.min_gram > params.max_gram {
+            return Err(invalid_params(
+                "ngram",
+                "min_gram must be greater than 0, and not greater than max_gram",
+            ));
+        }
+        Ok(TextAnalyzer::from(NgramTokenizer::new(
+            params.min_gram,
+            params.max_gram,
+            params.prefix_only,
+        )))
+    });
+    manager.register_tokenizer_factory("regex", |params| {
+        let params: RegexParams = parse_analyzer_params("regex", params)?;
+        let mode = match params.mode {
+            None | Some(RegexModeParam::Match) => RegexTokenizerMode::Match,
+            Some(RegexModeParam::Split) => RegexTokenizerMode::Split,
+        };
+        Ok(TextAnalyzer::from(RegexTokenizer::new(
+            &params.pattern,
+            mode,
+        )?))
+    });
+
+    manager.register_filter_factory(
+        "lowercase",
+        without_params("lowercase", || BoxTokenFilter::from(LowerCaser)),
+    );
+    manager.register_filter_factory(
+        "ascii_folding",
+        without_params("ascii_folding", || BoxTokenFilter::from(AsciiFoldingFilter)),
+    );
+    manager.register_filter_factory(
+        "alphanum_only",
+        without_params("alphanum_only", || BoxTokenFilter::from(AlphaNumOnlyFilter)),
+    );
+    manager.register_filter_factory("remove_long", |params| {
+        let params: RemoveLongParams = parse_analyzer_params("remove_long", params)?;
+        Ok(BoxTokenFilter::from(RemoveLongFilter::limit(params.limit)))
+    });
+    manager.register_filter_factory("edge_ngram", |params| {
+        let params: EdgeNgramParams = parse_analyzer_params("edge_ngram", params)?;
+        if params.min_gram == 0 || params.min_gram > params.max_gram {
+            return Err(invalid_params(
+                "edge_ngram",
+                "min_gram must be greater than 0, and not greater than max_gram",
+            ));
+        }
+        let side = match params.side {
+            None | Some(EdgeNgramSideParam::Front) => EdgeNgramSide::Front,
+            Some(EdgeNgramSideParam::Back) => EdgeNgramSide::Back,
+        };
+        Ok(BoxTokenFilter::from(
+            EdgeNgramFilter::new(params.min_gram, params.max_gram)
+                .side(side)
+                .keep_short_tokens(params.keep_short_tokens),
+        ))
+    });
+    manager.register_filter_factory("stemmer", |params| {
+        let params: StemmerParams = parse_analyzer_params("stemmer", params)?;
+        Ok(BoxTokenFilter::from(Stemmer::new(params.language)))
+    });
+    manager.register_filter_factory("stop_words", |params| {
+        let params: StopWordsParams = parse_analyzer_params("stop_words", params)?;
+        let filter = match (params.language, params.words) {
+            (None, None) => StopWordFilter::default(),
+            (None, Some(words)) => StopWordFilter::remove(words),
+            (Some(language), None) => StopWordFilter::new(language).ok_or_else(|| {
+                invalid_params(
+                    "stop_words",
+                    &format!("no stop words are available for {:?}", language),
+                )
+            })?,
+            (Some(_), Some(_)) => {
+                return Err(invalid_params(
+                    "stop_words",
+                    "language and words cannot be both set",
+                ));
+            }
+        };
+        Ok(BoxTokenFilter::from(filter))
+    });
+    manager.register_filter_factory("shingle", |params| {
+        let params: ShingleParams = parse_analyzer_params("shingle", params)?;
+        if params.min_size < 2 || params.min_size > params.max_size {
+            return Err(invalid_params(
+                "shingle",
+                "min_size must be at least 2, and not greater than max_size",
+            ));
+        }
+        let mut filter = ShingleFilter::new(params.min_size, params.max_size)
+            .output_unigrams(params.output_unigrams);
+        if let Some(separator) = params.separator {
+            filter = filter.separator(&separator);
+        }
+        Ok(BoxTokenFilter::from(filter))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnalyzerComponentConfig, AnalyzerConfig};
+    use crate::tokenizer::{BoxTokenFilter, TokenFilter, TokenStream, TokenizerManager};
+    use crate::tokenizer::{BoxTokenStream, Token};
+    use crate::TantivyError;
+
+    fn texts(manager: &TokenizerManager, config: &str, text: &str) -> crate::Result<Vec<String>> {
+        let config: AnalyzerConfig = serde_json::from_str(config).unwrap();
+        let analyzer = manager.build_analyzer(&config)?;
+        let mut texts = vec![];
+        analyzer
+            .token_stream(text)
+            .process(&mut |token| texts.push(token.text.clone()));
+        Ok(texts)
+    }
+
+    #[test]
+    fn test_analyzer_config_serde() {
+        let config: AnalyzerConfig = serde_json::from_str(
+            r#"{"tokenizer": "simple", "filters": ["lowercase", {"type": "remove_long", "limit": 40}]}"#,
+        )
+        .unwrap();
+        let mut params = serde_json::Map::new();
+        params.insert("limit".to_string(), serde_json::json!(40));
+        assert_eq!(
+            config,
+            AnalyzerConfig {
+                tokenizer: AnalyzerComponentConfig::new("simple"),
+                filters: vec![
+                    AnalyzerComponentConfig::new("lowercase"),
+                    AnalyzerComponentConfig::with_params("remove_long", params),
+                ],
+            }
+        );
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(
+            json,
+            r#"{"tokenizer":"simple","filters":["lowercase",{"type":"remove_long","limit":40}]}"#
+        );
+        let config: AnalyzerConfig = serde_json::from_str(r#"{"tokenizer": "raw"}"#).unwrap();
+        assert!(config.filters.is_empty());
+    }
+
+    #[test]
+    fn test_build_analyzer_builtins() -> crate::Result<()> {
+        let manager = TokenizerManager::default();
+        assert_eq!(
+            texts(
+                &manager,
+                r#"{"tokenizer": "simple", "filters": ["lowercase", {"type": "stop_words", "language": "French"}, "ascii_folding", {"type": "stemmer", "language": "French"}]}"#,
+                "Les Châteaux de la Loire"
+            )?,
+            vec!["chateau", "loir"]
+        );
+        assert_eq!(
+            texts(
+                &manager,
+                r#"{"tokenizer": {"type": "regex", "pattern": "[,;]", "mode": "split"}, "filters": [{"type": "remove_long", "limit": 4}]}"#,
+                "a,bb;cccc,dd"
+            )?,
+            vec!["a", "bb", "dd"]
+        );
+        assert_eq!(
+            texts(
+                &manager,
+                r#"{"tokenizer": "whitespace", "filters": [{"type": "shingle", "min_size": 2, "max_size": 2, "output_unigrams": false, "separator": "+"}]}"#,
+                "new york city"
+            )?,
+            vec!["new+york", "york+city"]
+        );
+        assert_eq!(
+            texts(
+                &manager,
+                r#"{"tokenizer": "simple", "filters": [{"type": "edge_ngram", "min_gram": 2, "max_gram": 3, "side": "back"}]}"#,
+                "a word"
+            )?,
+            vec!["rd", "ord"]
+        );
+        assert_eq!(
+            texts(
+                &manager,
+                r#"{"tokenizer": "raw", "filters": ["stop_words"]}"#,
+                "the"
+            )?,
+            Vec::<String>::new()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_analyzer_errors() {
+        let manager = TokenizerManager::default();
+        let is_invalid_argument = |config: &str| {
+            matches!(
+                texts(&manager, config, ""),
+                Err(TantivyError::InvalidArgument(_))
+            )
+        };
+        assert!(is_invalid_argument(r#"{"tokenizer": "unknown"}"#));
+        assert!(is_invalid_argument(
+            r#"{"tokenizer": "simple", "filters": ["unknown"]}"#
+        ));
+        assert!(is_invalid_argument(
+            r#"{"tokenizer": {"type": "simple", "unexpected": 1}}"#
+        ));
+        assert!(is_invalid_argument(
+            r#"{"tokenizer": {"type": "ngram", "min_gram": 3, "max_gram": 2}}"#
+        ));
+        assert!(is_invalid_argument(
+            r#"{"tokenizer": {"type": "regex", "pattern": "[a-"}}"#
+        ));
+        assert!(is_invalid_argument(
+            r#"{"tokenizer": "simple", "filters": [{"type": "stemmer", "language": "Klingon"}]}"#
+        ));
+        assert!(is_invalid_argument(
+            r#"{"tokenizer": "simple", "filters": [{"type": "stop_words", "language": "Tamil"}]}"#
+        ));
+    }
+
+    #[derive(Clone)]
+    struct ReverseFilter;
+
+    impl TokenFilter for ReverseFilter {
+        fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+            BoxTokenStream::from(ReverseTokenStream { tail: token_stream })
+        }
+    }
+
+    struct ReverseTokenStream<'a> {
+        tail: BoxTokenStream<'a>,
+    }
+
+    impl<'a> TokenStream for ReverseTokenStream<'a> {
+        fn advance(&mut self) -> bool {
+            if !self.tail.advance() {
+                return false;
+            }
+            let reversed = self.tail.token().text.chars().rev().collect();
+            self.tail.token_mut().text = reversed;
+            true
+        }
+
+        fn token(&self) -> &Token {
+            self.tail.token()
+        }
+
+        fn token_mut(&mut self) -> &mut Token {
+            self.tail.token_mut()
+        }
+    }
+
+    #[test]
+    fn test_build_analyzer_custom_filter() -> crate::Result<()> {
+        let manager = TokenizerManager::default();
+        manager
+            .register_filter_factory("reverse", |_params| Ok(BoxTokenFilter::from(ReverseFilter)));
+        assert_eq!(
+            texts(
+                &manager,
+                r#"{"tokenizer": "simple", "filters": ["lowercase", "reverse"]}"#,
+                "Hello World"
+            )?,
+            vec!["olleh", "dlrow"]
+        );
+        // the factories are shared by the clones of the manager.
+        let manager_clone = manager.clone();
+        assert!(texts(
+            &manager_clone,
+            r#"{"tokenizer": "raw", "filters": ["reverse"]}"#,
+            "ab"
+        )
+        .is_ok());
+        Ok(())
+    }
+}
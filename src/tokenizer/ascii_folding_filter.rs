@@ -4,6 +4,23 @@ use std::mem;
 /// This class converts alphabetic, numeric, and symbolic Unicode characters
 /// which are not in the first 127 ASCII characters (the "Basic Latin" Unicode
 /// block) into their ASCII equivalents, if one exists.
+///
+/// The offsets of the tokens are left unchanged, and still point to the original text
+/// even when the length of the token changes, e.g. when `ß` is folded into `ss`.
+/// Tokens with no character to fold are not rewritten.
+///
+/// ```rust
+/// use tantivy::tokenizer::*;
+///
+/// let tokenizer = TextAnalyzer::from(SimpleTokenizer)
+///     .filter(AsciiFoldingFilter);
+/// let mut stream = tokenizer.token_stream("une façade");
+/// assert_eq!(stream.next().unwrap().text, "une");
+/// let token = stream.next().unwrap();
+/// assert_eq!(token.text, "facade");
+/// assert_eq!((token.offset_from, token.offset_to), (4, 11));
+/// assert!(stream.next().is_none());
+/// ```
 #[derive(Clone)]
 pub struct AsciiFoldingFilter;
 
@@ -26,9 +43,9 @@ impl<'a> TokenStream for AsciiFoldingFilterTokenStream<'a> {
         if !self.tail.advance() {
             return false;
         }
-        if !self.token_mut().text.is_ascii() {
-            // ignore its already ascii
-            to_ascii(&mut self.tail.token_mut().text, &mut self.buffer);
+        // ignore its already ascii
+        if !self.token_mut().text.is_ascii() && to_ascii(&self.tail.token().text, &mut self.buffer)
+        {
             mem::swap(&mut self.tail.token_mut().text, &mut self.buffer);
         }
         true
@@ -1526,25 +1543,38 @@ fn fold_non_ascii_char(c: char) -> Option<&'static str> {
 }
 
 // https://github.com/apache/lucene-solr/blob/master/lucene/analysis/common/src/java/org/apache/lucene/analysis/miscellaneous/ASCIIFoldingFilter.java#L187
-fn to_ascii(text: &mut String, output: &mut String) {
+//
+// Writes the folded version of `text` into `output`, and returns `true`,
+// unless `text` has no character to fold: `output` is then left untouched.
+fn to_ascii(text: &str, output: &mut String) -> bool {
+    let first_folded = match text
+        .char_indices()
+        .find(|&(_, c)| fold_non_ascii_char(c).is_some())
+    {
+        Some((offset, _)) => offset,
+        None => return false,
+    };
     output.clear();
-
-    for c in text.chars() {
+    output.push_str(&text[..first_folded]);
+    for c in text[first_folded..].chars() {
         if let Some(folded) = fold_non_ascii_char(c) {
             output.push_str(folded);
         } else {
             output.push(c);
         }
     }
+    true
 }
 
 #[cfg(test)]
 mod tests {
     use super::to_ascii;
+    use crate::tokenizer::tests::assert_token;
     use crate::tokenizer::AsciiFoldingFilter;
     use crate::tokenizer::RawTokenizer;
     use crate::tokenizer::SimpleTokenizer;
     use crate::tokenizer::TextAnalyzer;
+    use crate::tokenizer::Token;
     use std::iter;
 
     #[test]
@@ -1626,12 +1656,45 @@ mod tests {
 
     #[test]
     fn test_to_ascii() {
-        let mut input = "Rámon".to_string();
         let mut buffer = String::new();
-        to_ascii(&mut input, &mut buffer);
+        assert!(to_ascii("Rámon", &mut buffer));
         assert_eq!("Ramon", buffer);
     }
 
+    #[test]
+    fn test_to_ascii_nothing_to_fold() {
+        let mut buffer = String::new();
+        assert!(!to_ascii("東京", &mut buffer));
+        assert!(!to_ascii("§¦", &mut buffer));
+        // the buffer is not even allocated.
+        assert_eq!(buffer.capacity(), 0);
+    }
+
+    #[test]
+    fn test_ascii_folding_mixed_scripts() {
+        assert_eq!(
+            &folding_helper("Straße東京 Ωmega café"),
+            &["Strasse東京", "Ωmega", "cafe"]
+        );
+        assert_eq!(
+            folding_using_raw_tokenizer_helper("ǅ馬 œuvre"),
+            "Dz馬 oeuvre"
+        );
+    }
+
+    #[test]
+    fn test_ascii_folding_offsets() {
+        let mut tokens = Vec::new();
+        TextAnalyzer::from(SimpleTokenizer)
+            .filter(AsciiFoldingFilter)
+            .token_stream("große Œuvre 東京")
+            .process(&mut |token: &Token| tokens.push(token.clone()));
+        assert_eq!(tokens.len(), 3);
+        assert_token(&tokens[0], 0, "grosse", 0, 6);
+        assert_token(&tokens[1], 1, "OEuvre", 7, 13);
+        assert_token(&tokens[2], 2, "東京", 14, 20);
+    }
+
     #[test]
     fn test_all_foldings() {
         // those folding is a copy of
@@ -0,0 +1,172 @@
+use std::ops::Deref;
+
+/// `CharFilter` rewrites the text given to a [`TextAnalyzer`](./struct.TextAnalyzer.html),
+/// before it is tokenized.
+///
+/// Char filters keep track of the offsets of the original text, so that the offsets of the
+/// tokens still refer to the original text, e.g. for the highlighting of snippets.
+///
+/// ```rust
+/// use tantivy::tokenizer::*;
+///
+/// let tokenizer = TextAnalyzer::from(SimpleTokenizer)
+///     .char_filter(HtmlStripCharFilter)
+///     .filter(LowerCaser);
+///
+/// let text = "<p>Fish &amp; <b>Chips</b></p>";
+/// let mut stream = tokenizer.token_stream(text);
+/// let fish = stream.next().unwrap();
+/// assert_eq!(fish.text, "fish");
+/// assert_eq!(&text[fish.offset_from..fish.offset_to], "Fish");
+/// let chips = stream.next().unwrap();
+/// assert_eq!(chips.text, "chips");
+/// assert_eq!(&text[chips.offset_from..chips.offset_to], "Chips");
+/// assert!(stream.next().is_none());
+/// ```
+pub trait CharFilter: 'static + Send + Sync + CharFilterClone {
+    /// Rewrites `text`, recording where each part of the result comes from.
+    fn filter(&self, text: &str) -> FilteredText;
+}
+
+/// The output of a [`CharFilter`](./trait.CharFilter.html): a text, and the mapping
+/// of its offsets to the offsets of the text it was filtered from.
+///
+/// It is built by walking the original text from its start, either keeping
+/// or replacing each of its parts.
+#[derive(Clone, Debug, Default)]
+pub struct FilteredText {
+    text: String,
+    segments: Vec<Segment>,
+    original_len: usize,
+}
+
+/// A part of the filtered text, and the part of the original text it comes from.
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+    filtered_from: usize,
+    filtered_to: usize,
+    original_from: usize,
+    original_to: usize,
+    kept: bool,
+}
+
+impl FilteredText {
+    /// Creates an empty `FilteredText`.
+    pub fn new() -> FilteredText {
+        FilteredText::default()
+    }
+
+    /// Appends the next part of the original text, unchanged.
+    pub fn keep(&mut self, original: &str) {
+        if original.is_empty() {
+            return;
+        }
+        self.text.push_str(original);
+        self.original_len += original.len();
+        match self.segments.last_mut() {
+            Some(last_segment) if last_segment.kept => {
+                last_segment.filtered_to = self.text.len();
+                last_segment.original_to = self.original_len;
+            }
+            _ => self.segments.push(Segment {
+                filtered_from: self.text.len() - original.len(),
+                filtered_to: self.text.len(),
+                original_from: self.original_len - original.len(),
+                original_to: self.original_len,
+                kept: true,
+            }),
+        }
+    }
+
+    /// Replaces the next `original_len` bytes of the original text by `replacement`,
+    /// which may be empty.
+    pub fn replace(&mut self, original_len: usize, replacement: &str) {
+        let filtered_from = self.text.len();
+        let original_from = self.original_len;
+        self.text.push_str(replacement);
+        self.original_len += original_len;
+        self.segments.push(Segment {
+            filtered_from,
+            filtered_to: self.text.len(),
+            original_from,
+            original_to: self.original_len,
+            kept: false,
+        });
+    }
+
+    /// Returns the filtered text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the offset in the original text of a token starting at `offset`
+    /// in the filtered text.
+    pub fn original_offset_from(&self, offset: usize) -> usize {
+        // the segments are contiguous: this is the segment with
+        // `filtered_from <= offset < filtered_to`.
+        let segment_id = self
+            .segments
+            .partition_point(|segment| segment.filtered_to <= offset);
+        self.segments
+            .get(segment_id)
+            .map(|segment| segment.original_offset(offset))
+            .unwrap_or(self.original_len)
+    }
+
+    /// Returns the offset in the original text of a token ending at `offset`
+    /// in the filtered text.
+    pub fn original_offset_to(&self, offset: usize) -> usize {
+        // the segments are contiguous: this is the segment with
+        // `filtered_from < offset <= filtered_to`.
+        let num_segments_before = self
+            .segments
+            .partition_point(|segment| segment.filtered_from < offset);
+        num_segments_before
+            .checked_sub(1)
+            .map(|segment_id| self.segments[segment_id].original_offset(offset))
+            .unwrap_or(0)
+    }
+}
+
+impl Segment {
+    /// Maps an offset of the segment in the filtered text to the original text.
+    ///
+    /// The offsets within a kept segment are mapped exactly, those within
+    /// a replaced segment are mapped to its start.
+    fn original_offset(&self, offset: usize) -> usize {
+        if offset == self.filtered_to {
+            self.original_to
+        } else if self.kept {
+            self.original_from + offset - self.filtered_from
+        } else {
+            self.original_from
+        }
+    }
+}
+
+pub trait CharFilterClone {
+    fn box_clone(&self) -> BoxCharFilter;
+}
+
+impl<T: CharFilter + Clone> CharFilterClone for T {
+    fn box_clone(&self) -> BoxCharFilter {
+        BoxCharFilter::from(self.clone())
+    }
+}
+
+/// Simple wrapper of `Box<dyn CharFilter + 'a>`.
+pub struct BoxCharFilter(Box<dyn CharFilter>);
+
+impl Deref for BoxCharFilter {
+    type Target = dyn CharFilter;
+
+    fn deref(&self) -> &dyn CharFilter {
+        &*self.0
+    }
+}
+
+impl<T: CharFilter> From<T> for BoxCharFilter {
+    fn from(char_filter: T) -> BoxCharFilter {
+        BoxCharFilter(Box::new(char_filter))
+    }
+}
@@ -0,0 +1,226 @@
+use super::BoxTokenStream;
+use super::{Token, TokenStream, Tokenizer};
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// Tokenize Chinese, Japanese and Korean text into overlapping bigrams of characters.
+///
+/// Runs of Han, Hiragana, Katakana and Hangul characters are split into all of their
+/// bigrams, e.g. `東京都` gives `東京` and `京都`, while a run of a single character is
+/// emitted as is. Other words are split on whitespaces and punctuation,
+/// like with the [`SimpleTokenizer`](./struct.SimpleTokenizer.html).
+///
+/// The bigrams of a run have consecutive positions, so that a phrase query over the bigrams
+/// of a query text matches the documents containing this text.
+///
+/// # Example
+///
+/// ```rust
+/// use tantivy::tokenizer::*;
+///
+/// let tokenizer = CjkBigramTokenizer;
+/// let mut stream = tokenizer.token_stream("rust入門ガイド");
+/// let mut tokens = vec![];
+/// while let Some(token) = stream.next() {
+///     tokens.push((token.text.clone(), token.offset_from, token.offset_to));
+/// }
+/// assert_eq!(
+///     tokens,
+///     vec![
+///         ("rust".to_string(), 0, 4),
+///         ("入門".to_string(), 4, 10),
+///         ("門ガ".to_string(), 7, 13),
+///         ("ガイ".to_string(), 10, 16),
+///         ("イド".to_string(), 13, 19),
+///     ]
+/// );
+/// ```
+#[derive(Clone)]
+pub struct CjkBigramTokenizer;
+
+/// Returns true if `c` is a Han, Hiragana, Katakana or Hangul character.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{1100}'..='\u{11FF}' // Hangul Jamo
+        | '\u{3005}'..='\u{3007}' // Ideographic iteration mark, closing mark and number zero
+        | '\u{3040}'..='\u{309F}' // Hiragana
+        | '\u{30A0}'..='\u{30FF}' // Katakana
+        | '\u{3130}'..='\u{318F}' // Hangul Compatibility Jamo
+        | '\u{31F0}'..='\u{31FF}' // Katakana Phonetic Extensions
+        | '\u{3400}'..='\u{4DBF}' // CJK Unified Ideographs Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}' // Hangul Syllables
+        | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+        | '\u{FF66}'..='\u{FF9F}' // Halfwidth Katakana
+        | '\u{20000}'..='\u{2FA1F}' // CJK Unified Ideographs Extensions B to F, and supplement
+    )
+}
+
+pub struct CjkBigramTokenStream<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    /// Byte range of the previous character of the current CJK run, if any.
+    previous_cjk_char: Option<(usize, usize)>,
+    /// Whether a bigram was emitted for the current CJK run.
+    emitted_bigram: bool,
+    token: Token,
+}
+
+impl Tokenizer for CjkBigramTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(CjkBigramTokenStream {
+            text,
+            chars: text.char_indices().peekable(),
+            previous_cjk_char: None,
+            emitted_bigram: false,
+            token: Token::default(),
+        })
+    }
+}
+
+impl<'a> CjkBigramTokenStream<'a> {
+    fn emit(&mut self, offset_from: usize, offset_to: usize) -> bool {
+        self.token.text.clear();
+        self.token.payload = 0;
+        self.token.position = self.token.position.wrapping_add(1);
+        self.token.offset_from = offset_from;
+        self.token.offset_to = offset_to;
+        self.token.text.push_str(&self.text[offset_from..offset_to]);
+        true
+    }
+
+    // search for the end of the current non CJK word.
+    fn search_word_end(&mut self) -> usize {
+        while let Some(&(offset, c)) = self.chars.peek() {
+            if !c.is_alphanumeric() || is_cjk(c) {
+                return offset;
+            }
+            self.chars.next();
+        }
+        self.text.len()
+    }
+}
+
+impl<'a> TokenStream for CjkBigramTokenStream<'a> {
+    fn advance(&mut self) -> bool {
+        if let Some((previous_offset, previous_len)) = self.previous_cjk_char {
+            match self.chars.peek() {
+                Some(&(offset, c)) if is_cjk(c) => {
+                    self.chars.next();
+                    self.previous_cjk_char = Some((offset, c.len_utf8()));
+                    self.emitted_bigram = true;
+                    return self.emit(previous_offset, offset + c.len_utf8());
+                }
+                _ => {
+                    self.previous_cjk_char = None;
+                    if !self.emitted_bigram {
+                        return self.emit(previous_offset, previous_offset + previous_len);
+                    }
+                }
+            }
+        }
+        while let Some((offset_from, c)) = self.chars.next() {
+            if is_cjk(c) {
+                self.previous_cjk_char = Some((offset_from, c.len_utf8()));
+                self.emitted_bigram = false;
+                return self.advance();
+            }
+            if c.is_alphanumeric() {
+                let offset_to = self.search_word_end();
+                return self.emit(offset_from, offset_to);
+            }
+        }
+        false
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CjkBigramTokenizer;
+    use crate::collector::Count;
+    use crate::query::{PhraseQuery, QueryParser};
+    use crate::schema::{IndexRecordOption, Schema, TextFieldIndexing, TextOptions};
+    use crate::tokenizer::tests::assert_token;
+    use crate::tokenizer::{LowerCaser, TextAnalyzer, Token, Tokenizer};
+    use crate::{Index, Term};
+
+    fn tokens(text: &str) -> Vec<Token> {
+        let mut tokens = vec![];
+        CjkBigramTokenizer
+            .token_stream(text)
+            .process(&mut |token: &Token| tokens.push(token.clone()));
+        tokens
+    }
+
+    #[test]
+    fn test_cjk_bigram_tokenizer() {
+        let tokens = tokens("rust入門ガイド");
+        assert_eq!(tokens.len(), 5);
+        assert_token(&tokens[0], 0, "rust", 0, 4);
+        assert_token(&tokens[1], 1, "入門", 4, 10);
+        assert_token(&tokens[2], 2, "門ガ", 7, 13);
+        assert_token(&tokens[3], 3, "ガイ", 10, 16);
+        assert_token(&tokens[4], 4, "イド", 13, 19);
+    }
+
+    #[test]
+    fn test_cjk_bigram_tokenizer_single_characters() {
+        let tokens = tokens("猫、 犬とcat. 한국어");
+        assert_eq!(tokens.len(), 5);
+        assert_token(&tokens[0], 0, "猫", 0, 3);
+        assert_token(&tokens[1], 1, "犬と", 7, 13);
+        assert_token(&tokens[2], 2, "cat", 13, 16);
+        assert_token(&tokens[3], 3, "한국", 18, 24);
+        assert_token(&tokens[4], 4, "국어", 21, 27);
+    }
+
+    #[test]
+    fn test_cjk_bigram_tokenizer_empty() {
+        assert!(tokens("").is_empty());
+        assert!(tokens(" 。、").is_empty());
+    }
+
+    #[test]
+    fn test_cjk_bigram_tokenizer_search() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("cjk")
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let text = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        index.tokenizers().register(
+            "cjk",
+            TextAnalyzer::from(CjkBigramTokenizer).filter(LowerCaser),
+        );
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "Rust入門ガイド"));
+        index_writer.add_document(doc!(text => "東京都に住んでいます"));
+        index_writer.add_document(doc!(text => "ガイドブック"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term = |bigram: &str| Term::from_field_text(text, bigram);
+
+        let phrase_query = PhraseQuery::new(vec![term("入門"), term("門ガ")]);
+        assert_eq!(searcher.search(&phrase_query, &Count)?, 1);
+        let phrase_query = PhraseQuery::new(vec![term("入門"), term("ガイ")]);
+        assert_eq!(searcher.search(&phrase_query, &Count)?, 0);
+
+        let query_parser = QueryParser::for_index(&index, vec![text]);
+        let count = |query: &str| searcher.search(&query_parser.parse_query(query)?, &Count);
+        assert_eq!(count("ガイド")?, 2);
+        assert_eq!(count("東京")?, 1);
+        assert_eq!(count("京東")?, 0);
+        assert_eq!(count("rust 住ん")?, 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+//! # Example
+//! ```rust
+//! use tantivy::tokenizer::*;
+//!
+//! let tokenizer = TextAnalyzer::from(SimpleTokenizer)
+//!   .filter(LowerCaser)
+//!   .filter(EdgeNgramFilter::new(2, 4));
+//!
+//! let mut stream = tokenizer.token_stream("Search");
+//! assert_eq!(stream.next().unwrap().text, "se");
+//! assert_eq!(stream.next().unwrap().text, "sea");
+//! assert_eq!(stream.next().unwrap().text, "sear");
+//! assert!(stream.next().is_none());
+//! ```
+use super::{Token, TokenFilter, TokenStream};
+use crate::tokenizer::BoxTokenStream;
+
+/// The edge of the tokens from which an [`EdgeNgramFilter`](./struct.EdgeNgramFilter.html)
+/// builds its n-grams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeNgramSide {
+    /// Emit the prefixes of the tokens.
+    Front,
+    /// Emit the suffixes of the tokens.
+    Back,
+}
+
+/// `TokenFilter` that replaces each token by its edge n-grams, i.e. its prefixes
+/// (or its suffixes) of `min_gram` to `max_gram` characters.
+///
+/// The n-grams are emitted from the shortest to the longest, with the position and the
+/// offsets of the original token. Tokens shorter than `min_gram` characters are removed,
+/// unless [`keep_short_tokens`](#method.keep_short_tokens) is set.
+///
+/// This is typically used at indexing time for search-as-you-type, while the query
+/// is not split into n-grams.
+#[derive(Clone, Debug)]
+pub struct EdgeNgramFilter {
+    min_gram: usize,
+    max_gram: usize,
+    side: EdgeNgramSide,
+    keep_short_tokens: bool,
+}
+
+impl EdgeNgramFilter {
+    /// Creates an `EdgeNgramFilter` emitting the prefixes of `min_gram` to `max_gram`
+    /// characters of the tokens.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min_gram` is 0, or greater than `max_gram`.
+    pub fn new(min_gram: usize, max_gram: usize) -> EdgeNgramFilter {
+        assert!(min_gram > 0, "min_gram must be greater than 0");
+        assert!(
+            min_gram <= max_gram,
+            "min_gram must not be greater than max_gram"
+        );
+        EdgeNgramFilter {
+            min_gram,
+            max_gram,
+            side: EdgeNgramSide::Front,
+            keep_short_tokens: false,
+        }
+    }
+
+    /// Sets the edge of the tokens from which the n-grams are built.
+    pub fn side(mut self, side: EdgeNgramSide) -> EdgeNgramFilter {
+        self.side = side;
+        self
+    }
+
+    /// Sets whether the tokens shorter than `min_gram` characters are kept as is,
+    /// instead of being removed.
+    pub fn keep_short_tokens(mut self, keep_short_tokens: bool) -> EdgeNgramFilter {
+        self.keep_short_tokens = keep_short_tokens;
+        self
+    }
+}
+
+impl TokenFilter for EdgeNgramFilter {
+    fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(EdgeNgramFilterStream {
+            filter: self.clone(),
+            tail: token_stream,
+            original_text: String::new(),
+            char_boundaries: Vec::new(),
+            next_gram: 0,
+            last_gram: 0,
+        })
+    }
+}
+
+pub struct EdgeNgramFilterStream<'a> {
+    filter: EdgeNgramFilter,
+    tail: BoxTokenStream<'a>,
+    /// The text of the current token of the tail.
+    original_text: String,
+    /// The byte offsets of the char boundaries of `original_text`, including its end.
+    char_boundaries: Vec<usize>,
+    /// The number of chars of the next n-gram to emit.
+    next_gram: usize,
+    /// The number of chars of the last n-gram to emit.
+    last_gram: usize,
+}
+
+impl<'a> EdgeNgramFilterStream<'a> {
+    fn emit_gram(&mut self, gram: usize) {
+        let num_chars = self.char_boundaries.len() - 1;
+        let (from, to) = match self.filter.side {
+            EdgeNgramSide::Front => (0, self.char_boundaries[gram]),
+            EdgeNgramSide::Back => (
+                self.char_boundaries[num_chars - gram],
+                self.original_text.len(),
+            ),
+        };
+        let text = &mut self.tail.token_mut().text;
+        text.clear();
+        text.push_str(&self.original_text[from..to]);
+    }
+}
+
+impl<'a> TokenStream for EdgeNgramFilterStream<'a> {
+    fn advance(&mut self) -> bool {
+        loop {
+            if self.next_gram <= self.last_gram && self.next_gram > 0 {
+                let gram = self.next_gram;
+                self.next_gram += 1;
+                self.emit_gram(gram);
+                return true;
+            }
+            if !self.tail.advance() {
+                return false;
+            }
+            let token_text = &self.tail.token().text;
+            self.char_boundaries.clear();
+            self.char_boundaries
+                .extend(token_text.char_indices().map(|(offset, _)| offset));
+            self.char_boundaries.push(token_text.len());
+            let num_chars = self.char_boundaries.len() - 1;
+            if num_chars < self.filter.min_gram {
+                self.next_gram = 0;
+                if self.filter.keep_short_tokens && num_chars > 0 {
+                    return true;
+                }
+                continue;
+            }
+            self.original_text.clone_from(token_text);
+            self.next_gram = self.filter.min_gram;
+            self.last_gram = self.filter.max_gram.min(num_chars);
+        }
+    }
+
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EdgeNgramFilter, EdgeNgramSide};
+    use crate::tokenizer::tests::assert_token;
+    use crate::tokenizer::{LowerCaser, SimpleTokenizer, TextAnalyzer, Token};
+
+    fn tokens(filter: EdgeNgramFilter, text: &str) -> Vec<Token> {
+        let mut tokens = vec![];
+        TextAnalyzer::from(SimpleTokenizer)
+            .filter(LowerCaser)
+            .filter(filter)
+            .token_stream(text)
+            .process(&mut |token: &Token| tokens.push(token.clone()));
+        tokens
+    }
+
+    #[test]
+    fn test_edge_ngram_filter() {
+        let tokens = tokens(EdgeNgramFilter::new(2, 3), "Hello a World");
+        assert_eq!(tokens.len(), 4);
+        assert_token(&tokens[0], 0, "he", 0, 5);
+        assert_token(&tokens[1], 0, "hel", 0, 5);
+        assert_token(&tokens[2], 2, "wo", 8, 13);
+        assert_token(&tokens[3], 2, "wor", 8, 13);
+    }
+
+    #[test]
+    fn test_edge_ngram_filter_back() {
+        let filter = EdgeNgramFilter::new(1, 10).side(EdgeNgramSide::Back);
+        let tokens = tokens(filter, "tree");
+        assert_eq!(tokens.len(), 4);
+        assert_token(&tokens[0], 0, "e", 0, 4);
+        assert_token(&tokens[1], 0, "ee", 0, 4);
+        assert_token(&tokens[2], 0, "ree", 0, 4);
+        assert_token(&tokens[3], 0, "tree", 0, 4);
+    }
+
+    #[test]
+    fn test_edge_ngram_filter_short_tokens() {
+        let filter = EdgeNgramFilter::new(3, 4).keep_short_tokens(true);
+        let tokens = tokens(filter, "an apple");
+        assert_eq!(tokens.len(), 3);
+        assert_token(&tokens[0], 0, "an", 0, 2);
+        assert_token(&tokens[1], 1, "app", 3, 8);
+        assert_token(&tokens[2], 1, "appl", 3, 8);
+    }
+
+    #[test]
+    fn test_edge_ngram_filter_multibyte() {
+        let tokens = tokens(EdgeNgramFilter::new(1, 3), "Été 東京");
+        assert_eq!(tokens.len(), 5);
+        assert_token(&tokens[0], 0, "é", 0, 5);
+        assert_token(&tokens[1], 0, "ét", 0, 5);
+        assert_token(&tokens[2], 0, "été", 0, 5);
+        assert_token(&tokens[3], 1, "東", 6, 12);
+        assert_token(&tokens[4], 1, "東京", 6, 12);
+        let filter = EdgeNgramFilter::new(2, 2).side(EdgeNgramSide::Back);
+        let tokens = self::tokens(filter, "café");
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].text, "fé");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_edge_ngram_filter_invalid_min_gram() {
+        EdgeNgramFilter::new(0, 2);
+    }
+}
@@ -0,0 +1,237 @@
+use super::{CharFilter, FilteredText};
+
+/// Elements whose content is not text, and is removed along with their tags.
+const SKIPPED_ELEMENTS: [&str; 2] = ["script", "style"];
+
+/// Elements separating the text before and after them,
+/// replaced by a line break instead of being removed.
+const BLOCK_ELEMENTS: [&str; 31] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "tr",
+];
+
+/// `CharFilter` removing the HTML markup of a text, and decoding its character entities.
+///
+/// - Tags and comments are removed. Block elements, like `<p>` or `<br>`,
+///   are replaced by a line break, so that they separate the words around them.
+/// - The content of the `<script>` and `<style>` elements is removed.
+/// - The named entities `&amp;`, `&lt;`, `&gt;`, `&quot;`, `&apos;` and `&nbsp;`,
+///   as well as the numeric entities, like `&#233;` or `&#xE9;`, are decoded.
+///   Other entities are kept as is.
+///
+/// A `<` that does not start a tag, like in `a < b`, is kept.
+///
+/// ```rust
+/// use tantivy::tokenizer::*;
+///
+/// let tokenizer = TextAnalyzer::from(SimpleTokenizer)
+///     .char_filter(HtmlStripCharFilter);
+/// let text = "<p>Fish &amp; chips</p><p>cr&#232;me</p><script>var a = 1;</script>";
+/// let mut stream = tokenizer.token_stream(text);
+/// let token = stream.next().unwrap();
+/// assert_eq!(token.text, "Fish");
+/// assert_eq!(&text[token.offset_from..token.offset_to], "Fish");
+/// let token = stream.next().unwrap();
+/// assert_eq!(token.text, "chips");
+/// let token = stream.next().unwrap();
+/// assert_eq!(token.text, "crème");
+/// assert_eq!(&text[token.offset_from..token.offset_to], "cr&#232;me");
+/// assert!(stream.next().is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct HtmlStripCharFilter;
+
+/// Returns the length of the tag or comment at the start of `text`,
+/// or `None` if `text` does not start with a tag.
+fn tag_len(text: &str) -> Option<usize> {
+    if text.starts_with("<!--") {
+        return Some(text.find("-->").map_or(text.len(), |end| end + 3));
+    }
+    let mut chars = text[1..].chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '/' || c == '!' || c == '?' => {}
+        _ => return None,
+    }
+    text.find('>').map(|end| end + 1)
+}
+
+/// Returns the lowercased name of a tag, and whether it is a closing tag.
+fn tag_name(tag: &str) -> (String, bool) {
+    let tag = tag.trim_start_matches('<');
+    let (tag, is_closing) = match tag.strip_prefix('/') {
+        Some(tag) => (tag, true),
+        None => (tag, false),
+    };
+    let name = tag
+        .chars()
+        .take_while(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    (name, is_closing)
+}
+
+/// Returns the length of the entity at the start of `text` and its decoded value,
+/// or `None` if `text` does not start with a supported entity.
+fn decode_entity(text: &str) -> Option<(usize, char)> {
+    let end = text[1..]
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '#')
+        .map(|end| end + 1)?;
+    if !text[end..].starts_with(';') {
+        return None;
+    }
+    let name = &text[1..end];
+    let decoded = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        _ => {
+            let code = name.strip_prefix('#')?;
+            let code = match code.strip_prefix('x').or_else(|| code.strip_prefix('X')) {
+                Some(hex_code) => u32::from_str_radix(hex_code, 16).ok()?,
+                None => code.parse::<u32>().ok()?,
+            };
+            std::char::from_u32(code)?
+        }
+    };
+    Some((end + 1, decoded))
+}
+
+impl CharFilter for HtmlStripCharFilter {
+    fn filter(&self, text: &str) -> FilteredText {
+        let mut filtered_text = FilteredText::new();
+        let mut kept_from = 0;
+        let mut offset = 0;
+        let mut replacement_buffer = [0u8; 4];
+        while let Some(special_offset) = text[offset..].find(['<', '&']) {
+            offset += special_offset;
+            let rest = &text[offset..];
+            let (len, replacement) = if rest.starts_with('<') {
+                let len = match tag_len(rest) {
+                    Some(len) => len,
+                    None => {
+                        offset += 1;
+                        continue;
+                    }
+                };
+                let (name, is_closing) = tag_name(&rest[..len]);
+                let mut len = len;
+                if !is_closing && SKIPPED_ELEMENTS.contains(&name.as_str()) {
+                    // skips the content of the element, up to its closing tag.
+                    let closing_tag = format!("</{}", name);
+                    len = rest[len..]
+                        .to_ascii_lowercase()
+                        .find(&closing_tag)
+                        .and_then(|closing_offset| {
+                            let closing_offset = len + closing_offset;
+                            tag_len(&rest[closing_offset..])
+                                .map(|closing_len| closing_offset + closing_len)
+                        })
+                        .unwrap_or(rest.len());
+                }
+                if BLOCK_ELEMENTS.contains(&name.as_str()) {
+                    (len, "\n")
+                } else {
+                    (len, "")
+                }
+            } else {
+                match decode_entity(rest) {
+                    Some((len, decoded)) => (len, &*decoded.encode_utf8(&mut replacement_buffer)),
+                    None => {
+                        offset += 1;
+                        continue;
+                    }
+                }
+            };
+            filtered_text.keep(&text[kept_from..offset]);
+            filtered_text.replace(len, replacement);
+            offset += len;
+            kept_from = offset;
+        }
+        filtered_text.keep(&text[kept_from..]);
+        filtered_text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HtmlStripCharFilter;
+    use crate::tokenizer::tests::assert_token;
+    use crate::tokenizer::{CharFilter, LowerCaser, SimpleTokenizer, TextAnalyzer, Token};
+
+    fn filter(text: &str) -> String {
+        HtmlStripCharFilter.filter(text).text().to_string()
+    }
+
+    #[test]
+    fn test_html_strip_char_filter() {
+        assert_eq!(filter("<p>Hello <b>World</b></p>"), "\nHello World\n");
+        assert_eq!(filter("a<br/>b<BR>c"), "a\nb\nc");
+        assert_eq!(filter("<i>Wo</i>rd"), "Word");
+        assert_eq!(
+            filter("fish &amp; chips &lt;3 &#233;&#xE9;"),
+            "fish & chips <3 éé"
+        );
+        assert_eq!(
+            filter("&unknown; &amp &#xZZ; &#1234567890;"),
+            "&unknown; &amp &#xZZ; &#1234567890;"
+        );
+        assert_eq!(filter("a < b and c<d"), "a < b and c<d");
+        assert_eq!(filter("a<!-- comment <b> -->b"), "ab");
+        assert_eq!(
+            filter("<SCRIPT type=\"text/javascript\">if (a<b) {}</Script>text"),
+            "text"
+        );
+        assert_eq!(filter("<style>p { color: red }"), "");
+        assert_eq!(filter("unclosed <a href="), "unclosed <a href=");
+        assert_eq!(filter("<!doctype html>éà"), "éà");
+    }
+
+    #[test]
+    fn test_html_strip_char_filter_offsets() {
+        let text = "<h1>Fish &amp; Chips</h1><p>cr&#232;me<br>br&#xFB;l&#233;e</p>";
+        let analyzer = TextAnalyzer::from(SimpleTokenizer)
+            .char_filter(HtmlStripCharFilter)
+            .filter(LowerCaser);
+        let mut tokens = vec![];
+        analyzer
+            .token_stream(text)
+            .process(&mut |token: &Token| tokens.push(token.clone()));
+        assert_eq!(tokens.len(), 4);
+        assert_token(&tokens[0], 0, "fish", 4, 8);
+        assert_token(&tokens[1], 1, "chips", 15, 20);
+        assert_token(&tokens[2], 2, "crème", 28, 38);
+        assert_token(&tokens[3], 3, "brûlée", 42, 58);
+    }
+}
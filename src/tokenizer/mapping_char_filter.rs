@@ -0,0 +1,76 @@
+use super::{CharFilter, FilteredText};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// `CharFilter` replacing characters by other characters,
+/// e.g. curly quotes by straight quotes.
+///
+/// ```rust
+/// use tantivy::tokenizer::*;
+///
+/// let tokenizer = TextAnalyzer::from(SimpleTokenizer)
+///     .char_filter(MappingCharFilter::new(vec![('’', '\''), ('œ', 'e')]));
+/// let text = "L’œuvre";
+/// let mut stream = tokenizer.token_stream(text);
+/// let token = stream.next().unwrap();
+/// assert_eq!(token.text, "L");
+/// let token = stream.next().unwrap();
+/// assert_eq!(token.text, "euvre");
+/// assert_eq!(&text[token.offset_from..token.offset_to], "œuvre");
+/// assert!(stream.next().is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct MappingCharFilter {
+    mapping: Arc<HashMap<char, char>>,
+}
+
+impl MappingCharFilter {
+    /// Creates a `MappingCharFilter` given a list of characters and their replacement.
+    pub fn new(mapping: Vec<(char, char)>) -> MappingCharFilter {
+        MappingCharFilter {
+            mapping: Arc::new(mapping.into_iter().collect()),
+        }
+    }
+}
+
+impl CharFilter for MappingCharFilter {
+    fn filter(&self, text: &str) -> FilteredText {
+        let mut filtered_text = FilteredText::new();
+        let mut kept_from = 0;
+        let mut replacement_buffer = [0u8; 4];
+        for (offset, c) in text.char_indices() {
+            if let Some(&replacement) = self.mapping.get(&c) {
+                filtered_text.keep(&text[kept_from..offset]);
+                filtered_text.replace(
+                    c.len_utf8(),
+                    replacement.encode_utf8(&mut replacement_buffer),
+                );
+                kept_from = offset + c.len_utf8();
+            }
+        }
+        filtered_text.keep(&text[kept_from..]);
+        filtered_text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MappingCharFilter;
+    use crate::tokenizer::CharFilter;
+
+    #[test]
+    fn test_mapping_char_filter() {
+        let filter = MappingCharFilter::new(vec![('“', '"'), ('”', '"'), ('é', 'e')]);
+        let filtered_text = filter.filter("a “quoted” café");
+        assert_eq!(filtered_text.text(), "a \"quoted\" cafe");
+        // "“" and "”" are 3 bytes long, "é" is 2 bytes long.
+        assert_eq!(filtered_text.original_offset_from(0), 0);
+        assert_eq!(filtered_text.original_offset_from(2), 2);
+        assert_eq!(filtered_text.original_offset_from(3), 5);
+        assert_eq!(filtered_text.original_offset_to(9), 11);
+        assert_eq!(filtered_text.original_offset_to(10), 14);
+        assert_eq!(filtered_text.original_offset_from(11), 15);
+        assert_eq!(filtered_text.original_offset_to(15), 20);
+        assert_eq!(filter.filter("").text(), "");
+    }
+}
@@ -118,15 +118,24 @@
 //! ```
 //!
 mod alphanum_only;
+mod analyzer_config;
 mod ascii_folding_filter;
+mod char_filter;
+mod cjk_bigram_tokenizer;
+mod edge_ngram_filter;
 mod facet_tokenizer;
+mod html_strip_char_filter;
 mod lower_caser;
+mod mapping_char_filter;
 mod ngram_tokenizer;
 mod raw_tokenizer;
+mod regex_tokenizer;
 mod remove_long;
+mod shingle_filter;
 mod simple_tokenizer;
 mod stemmer;
 mod stop_word_filter;
+mod synonym_filter;
 mod token_stream_chain;
 mod tokenized_string;
 mod tokenizer;
@@ -134,15 +143,24 @@ mod tokenizer_manager;
 mod whitespace_tokenizer;
 
 pub use self::alphanum_only::AlphaNumOnlyFilter;
+pub use self::analyzer_config::{parse_analyzer_params, AnalyzerComponentConfig, AnalyzerConfig};
 pub use self::ascii_folding_filter::AsciiFoldingFilter;
+pub use self::char_filter::{BoxCharFilter, CharFilter, FilteredText};
+pub use self::cjk_bigram_tokenizer::CjkBigramTokenizer;
+pub use self::edge_ngram_filter::{EdgeNgramFilter, EdgeNgramSide};
 pub use self::facet_tokenizer::FacetTokenizer;
+pub use self::html_strip_char_filter::HtmlStripCharFilter;
 pub use self::lower_caser::LowerCaser;
+pub use self::mapping_char_filter::MappingCharFilter;
 pub use self::ngram_tokenizer::NgramTokenizer;
 pub use self::raw_tokenizer::RawTokenizer;
+pub use self::regex_tokenizer::{RegexTokenizer, RegexTokenizerMode};
 pub use self::remove_long::RemoveLongFilter;
+pub use self::shingle_filter::ShingleFilter;
 pub use self::simple_tokenizer::SimpleTokenizer;
 pub use self::stemmer::{Language, Stemmer};
 pub use self::stop_word_filter::StopWordFilter;
+pub use self::synonym_filter::SynonymFilter;
 pub(crate) use self::token_stream_chain::TokenStreamChain;
 pub use self::whitespace_tokenizer::WhitespaceTokenizer;
 
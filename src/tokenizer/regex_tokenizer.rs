@@ -0,0 +1,257 @@
+use super::{BoxTokenStream, Token, TokenStream, Tokenizer};
+use crate::TantivyError;
+use regex::Regex;
+
+/// Defines which parts of the text a [`RegexTokenizer`](./struct.RegexTokenizer.html) emits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegexTokenizerMode {
+    /// Emit the matches of the pattern.
+    Match,
+    /// Emit the text between the matches of the pattern,
+    /// using the pattern as a separator.
+    Split,
+}
+
+/// Tokenize the text with a regular expression.
+///
+/// Depending on its [mode](./enum.RegexTokenizerMode.html), the tokenizer emits
+/// either the matches of the pattern, or the pieces of text between them.
+/// Empty tokens are never emitted.
+///
+/// The offsets of the tokens are byte offsets in the original text.
+///
+/// Note that the unicode character classes, like `\w` or `\p{Greek}`, are not supported.
+///
+/// # Example
+///
+/// ```rust
+/// use tantivy::tokenizer::*;
+///
+/// // Splits log lines on anything that is not part of an identifier.
+/// let tokenizer = RegexTokenizer::new("[^A-Za-z0-9_.-]+", RegexTokenizerMode::Split).unwrap();
+/// let mut stream = tokenizer.token_stream("GET /api/v1.2 [user_id=42]");
+/// let mut tokens = vec![];
+/// while let Some(token) = stream.next() {
+///     tokens.push((token.text.clone(), token.offset_from, token.offset_to));
+/// }
+/// assert_eq!(
+///     tokens,
+///     vec![
+///         ("GET".to_string(), 0, 3),
+///         ("api".to_string(), 5, 8),
+///         ("v1.2".to_string(), 9, 13),
+///         ("user_id".to_string(), 15, 22),
+///         ("42".to_string(), 23, 25),
+///     ]
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct RegexTokenizer {
+    regex: Regex,
+    mode: RegexTokenizerMode,
+}
+
+impl RegexTokenizer {
+    /// Creates a new `RegexTokenizer`.
+    ///
+    /// Returns an error if `pattern` is not a valid regular expression.
+    pub fn new(pattern: &str, mode: RegexTokenizerMode) -> crate::Result<RegexTokenizer> {
+        let regex = Regex::new(pattern).map_err(|err| {
+            TantivyError::InvalidArgument(format!("Invalid regex pattern {:?}: {}", pattern, err))
+        })?;
+        Ok(RegexTokenizer { regex, mode })
+    }
+}
+
+impl Tokenizer for RegexTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(RegexTokenStream {
+            regex: self.regex.clone(),
+            mode: self.mode,
+            text,
+            cursor: 0,
+            token: Token::default(),
+        })
+    }
+}
+
+pub struct RegexTokenStream<'a> {
+    regex: Regex,
+    mode: RegexTokenizerMode,
+    text: &'a str,
+    /// Byte offset from which the next token is searched,
+    /// greater than the length of the text once the stream is exhausted.
+    cursor: usize,
+    token: Token,
+}
+
+impl<'a> RegexTokenStream<'a> {
+    /// Returns the byte range of the first non-empty match starting at or after `start`.
+    fn next_match(&self, mut start: usize) -> Option<(usize, usize)> {
+        while start <= self.text.len() {
+            let regex_match = self.regex.find_at(self.text, start)?;
+            if regex_match.start() < regex_match.end() {
+                return Some((regex_match.start(), regex_match.end()));
+            }
+            // skip the empty match, moving to the next char boundary.
+            start = regex_match.end()
+                + self.text[regex_match.end()..]
+                    .chars()
+                    .next()
+                    .map_or(1, char::len_utf8);
+        }
+        None
+    }
+
+    /// Returns the byte range of the next token, moving the cursor after it.
+    fn next_token(&mut self) -> Option<(usize, usize)> {
+        match self.mode {
+            RegexTokenizerMode::Match => {
+                let (offset_from, offset_to) = self.next_match(self.cursor)?;
+                self.cursor = offset_to;
+                Some((offset_from, offset_to))
+            }
+            RegexTokenizerMode::Split => {
+                while self.cursor <= self.text.len() {
+                    let offset_from = self.cursor;
+                    let (offset_to, next_cursor) = self
+                        .next_match(offset_from)
+                        .unwrap_or((self.text.len(), self.text.len() + 1));
+                    self.cursor = next_cursor;
+                    if offset_from < offset_to {
+                        return Some((offset_from, offset_to));
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+impl<'a> TokenStream for RegexTokenStream<'a> {
+    fn advance(&mut self) -> bool {
+        self.token.text.clear();
+        self.token.payload = 0;
+        if let Some((offset_from, offset_to)) = self.next_token() {
+            self.token.position = self.token.position.wrapping_add(1);
+            self.token.offset_from = offset_from;
+            self.token.offset_to = offset_to;
+            self.token.text.push_str(&self.text[offset_from..offset_to]);
+            true
+        } else {
+            self.cursor = self.text.len() + 1;
+            false
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RegexTokenizer, RegexTokenizerMode};
+    use crate::tokenizer::tests::assert_token;
+    use crate::tokenizer::{LowerCaser, StopWordFilter, TextAnalyzer, Token, Tokenizer};
+
+    fn tokens(tokenizer: &dyn Tokenizer, text: &str) -> Vec<Token> {
+        let mut tokens = vec![];
+        tokenizer
+            .token_stream(text)
+            .process(&mut |token: &Token| tokens.push(token.clone()));
+        tokens
+    }
+
+    #[test]
+    fn test_regex_tokenizer_split() {
+        let tokenizer = RegexTokenizer::new("[^A-Za-z0-9_.-]+", RegexTokenizerMode::Split).unwrap();
+        // "é" and "ü" are 2 bytes long, "→" is 3 bytes long.
+        let tokens = tokens(&tokenizer, " café→über_42  x.y-z→");
+        assert_eq!(tokens.len(), 3);
+        assert_token(&tokens[0], 0, "caf", 1, 4);
+        assert_token(&tokens[1], 1, "ber_42", 11, 17);
+        assert_token(&tokens[2], 2, "x.y-z", 19, 24);
+    }
+
+    #[test]
+    fn test_regex_tokenizer_match() {
+        let tokenizer = RegexTokenizer::new("[^ ,]+", RegexTokenizerMode::Match).unwrap();
+        let tokens = tokens(&tokenizer, "naïve, 日本語 text");
+        assert_eq!(tokens.len(), 3);
+        assert_token(&tokens[0], 0, "naïve", 0, 6);
+        assert_token(&tokens[1], 1, "日本語", 8, 17);
+        assert_token(&tokens[2], 2, "text", 18, 22);
+    }
+
+    #[test]
+    fn test_regex_tokenizer_empty_matches() {
+        let tokenizer = RegexTokenizer::new("a*", RegexTokenizerMode::Match).unwrap();
+        let tokens_match = tokens(&tokenizer, "ébaaé");
+        assert_eq!(tokens_match.len(), 1);
+        assert_token(&tokens_match[0], 0, "aa", 3, 5);
+
+        let tokenizer = RegexTokenizer::new("a*", RegexTokenizerMode::Split).unwrap();
+        let tokens_split = tokens(&tokenizer, "ébaaé");
+        assert_eq!(tokens_split.len(), 2);
+        assert_token(&tokens_split[0], 0, "éb", 0, 3);
+        assert_token(&tokens_split[1], 1, "é", 5, 7);
+
+        assert!(tokens(&tokenizer, "").is_empty());
+        assert!(tokens(&tokenizer, "aaa").is_empty());
+    }
+
+    #[test]
+    fn test_regex_tokenizer_with_filters() {
+        let tokenizer =
+            RegexTokenizer::new(r"[^A-Za-z0-9_.-]+", RegexTokenizerMode::Split).unwrap();
+        let analyzer = TextAnalyzer::from(tokenizer)
+            .filter(LowerCaser)
+            .filter(StopWordFilter::remove(vec!["the".to_string()]));
+        let mut tokens = vec![];
+        analyzer
+            .token_stream("The Server.Error: the DISK")
+            .process(&mut |token: &Token| tokens.push(token.clone()));
+        assert_eq!(tokens.len(), 2);
+        assert_token(&tokens[0], 1, "server.error", 4, 16);
+        assert_token(&tokens[1], 3, "disk", 22, 26);
+    }
+
+    #[test]
+    fn test_regex_tokenizer_registered() -> crate::Result<()> {
+        use crate::collector::Count;
+        use crate::query::QueryParser;
+        use crate::schema::{Schema, TextFieldIndexing, TextOptions};
+        use crate::Index;
+
+        let mut schema_builder = Schema::builder();
+        let text_options = TextOptions::default()
+            .set_indexing_options(TextFieldIndexing::default().set_tokenizer("log"));
+        let line = schema_builder.add_text_field("line", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let tokenizer = RegexTokenizer::new("[^A-Za-z0-9_.-]+", RegexTokenizerMode::Split)?;
+        index
+            .tokenizers()
+            .register("log", TextAnalyzer::from(tokenizer).filter(LowerCaser));
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(line => "ERROR [db.pool] connection_lost"));
+        index_writer.add_document(doc!(line => "INFO [db] connection.lost"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![line]);
+        let count = |query: &str| searcher.search(&query_parser.parse_query(query)?, &Count);
+        assert_eq!(count("db.pool")?, 1);
+        assert_eq!(count("connection_lost")?, 1);
+        assert_eq!(count("db")?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_regex_tokenizer_invalid_pattern() {
+        assert!(RegexTokenizer::new("[a-z", RegexTokenizerMode::Match).is_err());
+    }
+}
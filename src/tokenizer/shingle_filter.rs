@@ -0,0 +1,239 @@
+//! # Example
+//! ```rust
+//! use tantivy::tokenizer::*;
+//!
+//! let tokenizer = TextAnalyzer::from(SimpleTokenizer)
+//!   .filter(ShingleFilter::new(2, 2));
+//!
+//! let mut stream = tokenizer.token_stream("please divide this");
+//! assert_eq!(stream.next().unwrap().text, "please");
+//! assert_eq!(stream.next().unwrap().text, "please divide");
+//! assert_eq!(stream.next().unwrap().text, "divide");
+//! assert_eq!(stream.next().unwrap().text, "divide this");
+//! assert_eq!(stream.next().unwrap().text, "this");
+//! assert!(stream.next().is_none());
+//! ```
+use super::{Token, TokenFilter, TokenStream};
+use crate::tokenizer::BoxTokenStream;
+use std::collections::VecDeque;
+
+/// `TokenFilter` that combines adjacent tokens into shingles, i.e. word n-grams.
+///
+/// A shingle has the position of its first token, and spans from the start offset
+/// of its first token to the end offset of its last token. For every position,
+/// the original token is emitted first (unless unigrams are disabled), followed by
+/// the shingles starting at this position, from the shortest to the longest.
+///
+/// Only tokens at consecutive positions are combined: shingles are never built across
+/// the gaps left by a filter removing tokens, like the `StopWordFilter`.
+/// For instance, with the stop word `the`, `over the lazy dog` gives the shingle
+/// `lazy dog`, but neither `over lazy` nor `over the`.
+#[derive(Clone, Debug)]
+pub struct ShingleFilter {
+    min_size: usize,
+    max_size: usize,
+    output_unigrams: bool,
+    separator: String,
+}
+
+impl ShingleFilter {
+    /// Creates a `ShingleFilter` emitting the shingles of `min_size` to `max_size` tokens,
+    /// joined by a space, as well as the original tokens.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min_size` is lower than 2, or greater than `max_size`.
+    pub fn new(min_size: usize, max_size: usize) -> ShingleFilter {
+        assert!(min_size >= 2, "min_size must be at least 2");
+        assert!(
+            min_size <= max_size,
+            "min_size must not be greater than max_size"
+        );
+        ShingleFilter {
+            min_size,
+            max_size,
+            output_unigrams: true,
+            separator: " ".to_string(),
+        }
+    }
+
+    /// Sets whether the original tokens are emitted along with the shingles.
+    ///
+    /// If not, the tokens which are not part of any shingle are removed.
+    pub fn output_unigrams(mut self, output_unigrams: bool) -> ShingleFilter {
+        self.output_unigrams = output_unigrams;
+        self
+    }
+
+    /// Sets the separator between the tokens of a shingle.
+    pub fn separator(mut self, separator: &str) -> ShingleFilter {
+        self.separator = separator.to_string();
+        self
+    }
+}
+
+impl TokenFilter for ShingleFilter {
+    fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(ShingleFilterStream {
+            filter: self.clone(),
+            tail: token_stream,
+            tail_exhausted: false,
+            window: VecDeque::with_capacity(self.max_size),
+            pending_tokens: Vec::new(),
+            token: Token::default(),
+        })
+    }
+}
+
+pub struct ShingleFilterStream<'a> {
+    filter: ShingleFilter,
+    tail: BoxTokenStream<'a>,
+    tail_exhausted: bool,
+    /// The next tokens of the tail, starting with the first token of the next shingles.
+    window: VecDeque<Token>,
+    /// Tokens left to emit, in reverse order.
+    pending_tokens: Vec<Token>,
+    token: Token,
+}
+
+impl<'a> ShingleFilterStream<'a> {
+    fn fill_window(&mut self) {
+        while !self.tail_exhausted && self.window.len() < self.filter.max_size {
+            if self.tail.advance() {
+                self.window.push_back(self.tail.token().clone());
+            } else {
+                self.tail_exhausted = true;
+            }
+        }
+    }
+
+    /// Returns the number of tokens at consecutive positions at the start of the window.
+    fn num_adjacent_tokens(&self) -> usize {
+        let mut num_tokens = 1;
+        while num_tokens < self.window.len()
+            && self.window[num_tokens].position
+                == self.window[num_tokens - 1].position.wrapping_add(1)
+        {
+            num_tokens += 1;
+        }
+        num_tokens
+    }
+
+    fn shingle(&self, size: usize) -> Token {
+        let first_token = &self.window[0];
+        let mut text = first_token.text.clone();
+        for token in self.window.iter().take(size).skip(1) {
+            text.push_str(&self.filter.separator);
+            text.push_str(&token.text);
+        }
+        Token {
+            offset_from: first_token.offset_from,
+            offset_to: self.window[size - 1].offset_to,
+            position: first_token.position,
+            text,
+            position_length: size,
+            payload: first_token.payload,
+        }
+    }
+}
+
+impl<'a> TokenStream for ShingleFilterStream<'a> {
+    fn advance(&mut self) -> bool {
+        loop {
+            if let Some(token) = self.pending_tokens.pop() {
+                self.token = token;
+                return true;
+            }
+            self.fill_window();
+            if self.window.is_empty() {
+                return false;
+            }
+            let num_adjacent_tokens = self.num_adjacent_tokens();
+            for size in (self.filter.min_size..=num_adjacent_tokens).rev() {
+                let shingle = self.shingle(size);
+                self.pending_tokens.push(shingle);
+            }
+            let unigram = self.window.pop_front();
+            if self.filter.output_unigrams {
+                self.pending_tokens.extend(unigram);
+            }
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShingleFilter;
+    use crate::tokenizer::tests::assert_token;
+    use crate::tokenizer::{SimpleTokenizer, StopWordFilter, TextAnalyzer, Token};
+
+    fn tokens(analyzer: TextAnalyzer, text: &str) -> Vec<Token> {
+        let mut tokens = vec![];
+        analyzer
+            .token_stream(text)
+            .process(&mut |token: &Token| tokens.push(token.clone()));
+        tokens
+    }
+
+    #[test]
+    fn test_shingle_filter() {
+        let analyzer = TextAnalyzer::from(SimpleTokenizer).filter(ShingleFilter::new(2, 3));
+        let tokens = tokens(analyzer, "the quick brown fox");
+        assert_eq!(tokens.len(), 9);
+        assert_token(&tokens[0], 0, "the", 0, 3);
+        assert_token(&tokens[1], 0, "the quick", 0, 9);
+        assert_token(&tokens[2], 0, "the quick brown", 0, 15);
+        assert_token(&tokens[3], 1, "quick", 4, 9);
+        assert_token(&tokens[4], 1, "quick brown", 4, 15);
+        assert_token(&tokens[5], 1, "quick brown fox", 4, 19);
+        assert_token(&tokens[6], 2, "brown", 10, 15);
+        assert_token(&tokens[7], 2, "brown fox", 10, 19);
+        assert_token(&tokens[8], 3, "fox", 16, 19);
+        assert_eq!(tokens[2].position_length, 3);
+    }
+
+    #[test]
+    fn test_shingle_filter_without_unigrams() {
+        let filter = ShingleFilter::new(2, 2)
+            .output_unigrams(false)
+            .separator("_");
+        let analyzer = TextAnalyzer::from(SimpleTokenizer).filter(filter);
+        let shingles = tokens(analyzer, "to be, or not");
+        assert_eq!(shingles.len(), 3);
+        assert_token(&shingles[0], 0, "to_be", 0, 5);
+        assert_token(&shingles[1], 1, "be_or", 3, 9);
+        assert_token(&shingles[2], 2, "or_not", 7, 13);
+        let analyzer = TextAnalyzer::from(SimpleTokenizer)
+            .filter(ShingleFilter::new(2, 2).output_unigrams(false));
+        assert!(tokens(analyzer, "alone").is_empty());
+    }
+
+    #[test]
+    fn test_shingle_filter_after_stop_words() {
+        let analyzer = TextAnalyzer::from(SimpleTokenizer)
+            .filter(StopWordFilter::remove(vec!["the".to_string()]))
+            .filter(ShingleFilter::new(2, 3));
+        let tokens = tokens(analyzer, "jumps over the lazy dog");
+        assert_eq!(tokens.len(), 6);
+        assert_token(&tokens[0], 0, "jumps", 0, 5);
+        assert_token(&tokens[1], 0, "jumps over", 0, 10);
+        assert_token(&tokens[2], 1, "over", 6, 10);
+        assert_token(&tokens[3], 3, "lazy", 15, 19);
+        assert_token(&tokens[4], 3, "lazy dog", 15, 23);
+        assert_token(&tokens[5], 4, "dog", 20, 23);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_shingle_filter_invalid_sizes() {
+        ShingleFilter::new(3, 2);
+    }
+}
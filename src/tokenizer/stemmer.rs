@@ -59,6 +59,7 @@ impl Language {
 #[derive(Clone)]
 pub struct Stemmer {
     stemmer_algorithm: Algorithm,
+    preserve_original: bool,
 }
 
 impl Stemmer {
@@ -66,8 +67,36 @@ impl Stemmer {
     pub fn new(language: Language) -> Stemmer {
         Stemmer {
             stemmer_algorithm: language.algorithm(),
+            preserve_original: false,
         }
     }
+
+    /// Emits the original tokens as well as their stems.
+    ///
+    /// The stem is emitted right after the original token, with the same position
+    /// and offsets, unless it is equal to the original token. Phrase queries then match
+    /// either the exact text, or its stems.
+    ///
+    /// ```rust
+    /// use tantivy::tokenizer::*;
+    ///
+    /// let tokenizer = TextAnalyzer::from(SimpleTokenizer)
+    ///     .filter(Stemmer::new(Language::English).with_preserve_original());
+    ///
+    /// let mut stream = tokenizer.token_stream("running fast");
+    /// let running = stream.next().unwrap().clone();
+    /// let run = stream.next().unwrap().clone();
+    /// assert_eq!(running.text, "running");
+    /// assert_eq!(run.text, "run");
+    /// assert_eq!(running.position, run.position);
+    /// // the stem of `fast` is `fast`: it is emitted only once.
+    /// assert_eq!(stream.next().unwrap().text, "fast");
+    /// assert!(stream.next().is_none());
+    /// ```
+    pub fn with_preserve_original(mut self) -> Stemmer {
+        self.preserve_original = true;
+        self
+    }
 }
 
 impl Default for Stemmer {
@@ -83,6 +112,8 @@ impl TokenFilter for Stemmer {
         BoxTokenStream::from(StemmerTokenStream {
             tail: token_stream,
             stemmer: inner_stemmer,
+            preserve_original: self.preserve_original,
+            pending_stem: None,
         })
     }
 }
@@ -90,15 +121,28 @@ impl TokenFilter for Stemmer {
 pub struct StemmerTokenStream<'a> {
     tail: BoxTokenStream<'a>,
     stemmer: rust_stemmers::Stemmer,
+    preserve_original: bool,
+    /// The stem of the current token, emitted after it when the original token is preserved.
+    pending_stem: Option<String>,
 }
 
 impl<'a> TokenStream for StemmerTokenStream<'a> {
     fn advance(&mut self) -> bool {
+        if let Some(stem) = self.pending_stem.take() {
+            self.token_mut().text = stem;
+            return true;
+        }
         if !self.tail.advance() {
             return false;
         }
         // TODO remove allocation
         let stemmed_str: String = self.stemmer.stem(&self.token().text).into_owned();
+        if self.preserve_original {
+            if stemmed_str != self.token().text {
+                self.pending_stem = Some(stemmed_str);
+            }
+            return true;
+        }
         self.token_mut().text.clear();
         self.token_mut().text.push_str(&stemmed_str);
         true
@@ -112,3 +156,101 @@ impl<'a> TokenStream for StemmerTokenStream<'a> {
         self.tail.token_mut()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Language, Stemmer};
+    use crate::collector::Count;
+    use crate::query::{PhraseQuery, Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TextFieldIndexing, TextOptions};
+    use crate::tokenizer::tests::assert_token;
+    use crate::tokenizer::{
+        LowerCaser, RemoveLongFilter, SimpleTokenizer, SynonymFilter, TextAnalyzer, Token,
+    };
+    use crate::{Index, Term};
+
+    fn tokens(analyzer: TextAnalyzer, text: &str) -> Vec<Token> {
+        let mut tokens = vec![];
+        analyzer
+            .token_stream(text)
+            .process(&mut |token: &Token| tokens.push(token.clone()));
+        tokens
+    }
+
+    fn preserving_stemmer() -> Stemmer {
+        Stemmer::new(Language::English).with_preserve_original()
+    }
+
+    #[test]
+    fn test_stemmer_preserve_original() {
+        let analyzer = TextAnalyzer::from(SimpleTokenizer)
+            .filter(LowerCaser)
+            .filter(preserving_stemmer());
+        let tokens = tokens(analyzer, "The Cats were running");
+        assert_eq!(tokens.len(), 6);
+        assert_token(&tokens[0], 0, "the", 0, 3);
+        assert_token(&tokens[1], 1, "cats", 4, 8);
+        assert_token(&tokens[2], 1, "cat", 4, 8);
+        assert_token(&tokens[3], 2, "were", 9, 13);
+        assert_token(&tokens[4], 3, "running", 14, 21);
+        assert_token(&tokens[5], 3, "run", 14, 21);
+    }
+
+    #[test]
+    fn test_stemmer_preserve_original_downstream_filters() {
+        // `RemoveLongFilter` drops some of the original tokens, but not their stems.
+        let analyzer = TextAnalyzer::from(SimpleTokenizer)
+            .filter(preserving_stemmer())
+            .filter(RemoveLongFilter::limit(6))
+            .filter(SynonymFilter::new(vec![(
+                "run".to_string(),
+                vec!["jog".to_string()],
+            )]));
+        let tokens = tokens(analyzer, "running cats");
+        assert_eq!(tokens.len(), 4);
+        assert_token(&tokens[0], 0, "run", 0, 7);
+        assert_token(&tokens[1], 0, "jog", 0, 7);
+        assert_token(&tokens[2], 1, "cats", 8, 12);
+        assert_token(&tokens[3], 1, "cat", 8, 12);
+    }
+
+    #[test]
+    fn test_stemmer_preserve_original_search() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("en_stem_preserve")
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let text = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        index.tokenizers().register(
+            "en_stem_preserve",
+            TextAnalyzer::from(SimpleTokenizer)
+                .filter(LowerCaser)
+                .filter(preserving_stemmer()),
+        );
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "dogs running in the park"));
+        index_writer.add_document(doc!(text => "the dog runs"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count = |query: &dyn Query| searcher.search(query, &Count);
+        let term = |word: &str| Term::from_field_text(text, word);
+        let phrase = |words: &[&str]| PhraseQuery::new(words.iter().map(|w| term(w)).collect());
+
+        assert_eq!(
+            count(&TermQuery::new(term("running"), IndexRecordOption::Basic))?,
+            1
+        );
+        assert_eq!(
+            count(&TermQuery::new(term("run"), IndexRecordOption::Basic))?,
+            2
+        );
+        assert_eq!(count(&phrase(&["dogs", "running"]))?, 1);
+        assert_eq!(count(&phrase(&["dog", "run"]))?, 2);
+        assert_eq!(count(&phrase(&["dogs", "run", "in"]))?, 1);
+        assert_eq!(count(&phrase(&["dog", "runs"]))?, 1);
+        Ok(())
+    }
+}
@@ -10,20 +10,25 @@
 //! assert_eq!(stream.next().unwrap().text, "crafty");
 //! assert!(stream.next().is_none());
 //! ```
-use super::{Token, TokenFilter, TokenStream};
+use super::{Language, Token, TokenFilter, TokenStream};
 use crate::tokenizer::BoxTokenStream;
 use fnv::FnvHasher;
 use std::collections::HashSet;
 use std::hash::BuildHasherDefault;
+use std::io::{self, BufRead};
+use std::sync::Arc;
 
 // configure our hashers for SPEED
 type StopWordHasher = BuildHasherDefault<FnvHasher>;
 type StopWordHashSet = HashSet<String, StopWordHasher>;
 
 /// `TokenFilter` that removes stop words from a token stream
+///
+/// The text of the tokens is compared as is to the stop words: the filter is usually placed
+/// after a `LowerCaser`, with lowercase stop words.
 #[derive(Clone)]
 pub struct StopWordFilter {
-    words: StopWordHashSet,
+    words: Arc<StopWordHashSet>,
 }
 
 impl StopWordFilter {
@@ -35,7 +40,57 @@ impl StopWordFilter {
             set.insert(word);
         }
 
-        StopWordFilter { words: set }
+        StopWordFilter {
+            words: Arc::new(set),
+        }
+    }
+
+    /// Creates a `StopWordFilter` removing the lowercase stop words of a given language,
+    /// from the lists of the [Snowball project](https://snowballstem.org/).
+    ///
+    /// Returns `None` if no list of stop words is bundled for this language.
+    /// Lists are bundled for Danish, Dutch, English, Finnish, French, German, Hungarian,
+    /// Italian, Norwegian, Portuguese, Russian, Spanish and Swedish.
+    ///
+    /// Note that the English list is much longer than the one of `StopWordFilter::default()`.
+    pub fn new(language: Language) -> Option<StopWordFilter> {
+        let words = match language {
+            Language::Danish => include_str!("stop_words/danish.txt"),
+            Language::Dutch => include_str!("stop_words/dutch.txt"),
+            Language::English => include_str!("stop_words/english.txt"),
+            Language::Finnish => include_str!("stop_words/finnish.txt"),
+            Language::French => include_str!("stop_words/french.txt"),
+            Language::German => include_str!("stop_words/german.txt"),
+            Language::Hungarian => include_str!("stop_words/hungarian.txt"),
+            Language::Italian => include_str!("stop_words/italian.txt"),
+            Language::Norwegian => include_str!("stop_words/norwegian.txt"),
+            Language::Portuguese => include_str!("stop_words/portuguese.txt"),
+            Language::Russian => include_str!("stop_words/russian.txt"),
+            Language::Spanish => include_str!("stop_words/spanish.txt"),
+            Language::Swedish => include_str!("stop_words/swedish.txt"),
+            Language::Arabic
+            | Language::Greek
+            | Language::Romanian
+            | Language::Tamil
+            | Language::Turkish => return None,
+        };
+        StopWordFilter::from_reader(words.as_bytes()).ok()
+    }
+
+    /// Creates a `StopWordFilter` from a list of stop words, with one word per line.
+    ///
+    /// Everything following a `#` on a line is a comment. Leading and trailing
+    /// whitespaces are trimmed, and empty lines are ignored.
+    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<StopWordFilter> {
+        let mut words = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let word = line.split('#').next().unwrap_or("").trim();
+            if !word.is_empty() {
+                words.push(word.to_string());
+            }
+        }
+        Ok(StopWordFilter::remove(words))
     }
 
     fn english() -> StopWordFilter {
@@ -50,14 +105,14 @@ impl StopWordFilter {
 }
 
 pub struct StopWordFilterStream<'a> {
-    words: StopWordHashSet,
+    words: Arc<StopWordHashSet>,
     tail: BoxTokenStream<'a>,
 }
 
 impl TokenFilter for StopWordFilter {
     fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
         BoxTokenStream::from(StopWordFilterStream {
-            words: self.words.clone(),
+            words: Arc::clone(&self.words),
             tail: token_stream,
         })
     }
@@ -93,3 +148,66 @@ impl Default for StopWordFilter {
         StopWordFilter::english()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::StopWordFilter;
+    use crate::tokenizer::{Language, LowerCaser, SimpleTokenizer, TextAnalyzer};
+
+    fn texts(filter: StopWordFilter, text: &str) -> Vec<String> {
+        let mut texts = vec![];
+        TextAnalyzer::from(SimpleTokenizer)
+            .filter(LowerCaser)
+            .filter(filter)
+            .token_stream(text)
+            .process(&mut |token| texts.push(token.text.clone()));
+        texts
+    }
+
+    #[test]
+    fn test_stop_word_filter_languages() {
+        let french = StopWordFilter::new(Language::French).unwrap();
+        assert_eq!(
+            texts(french, "Le chat est sur la table, où il était"),
+            vec!["chat", "table", "où"]
+        );
+        let german = StopWordFilter::new(Language::German).unwrap();
+        assert_eq!(
+            texts(german, "Der Hund und die Katze sind über der Mauer"),
+            vec!["hund", "katze", "mauer"]
+        );
+        let english = StopWordFilter::new(Language::English).unwrap();
+        assert_eq!(
+            texts(english, "They haven't been to the sea"),
+            vec!["haven", "t", "sea"]
+        );
+        assert!(StopWordFilter::new(Language::Tamil).is_none());
+    }
+
+    #[test]
+    fn test_stop_word_filter_from_reader() -> std::io::Result<()> {
+        let stop_words = "# a comment\n\
+                          \n\
+                          the\n\
+                          \t  of   \r\n\
+                          an # the indefinite article\n\
+                          #and\n";
+        let filter = StopWordFilter::from_reader(stop_words.as_bytes())?;
+        assert_eq!(
+            texts(filter, "The end of an era and a beginning"),
+            vec!["end", "era", "and", "a", "beginning"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stop_word_filter_compares_token_text_as_is() {
+        let filter = StopWordFilter::remove(vec!["the".to_string()]);
+        let mut texts = vec![];
+        TextAnalyzer::from(SimpleTokenizer)
+            .filter(filter)
+            .token_stream("The cat and the hat")
+            .process(&mut |token| texts.push(token.text.clone()));
+        assert_eq!(texts, vec!["The", "cat", "and", "hat"]);
+    }
+}
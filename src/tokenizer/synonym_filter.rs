@@ -0,0 +1,384 @@
+//! # Example
+//! ```rust
+//! use tantivy::tokenizer::*;
+//!
+//! let tokenizer = TextAnalyzer::from(SimpleTokenizer)
+//!   .filter(LowerCaser)
+//!   .filter(SynonymFilter::new(vec![
+//!       ("tv".to_string(), vec!["television".to_string()]),
+//!   ]));
+//!
+//! let mut stream = tokenizer.token_stream("a TV show");
+//! assert_eq!(stream.next().unwrap().text, "a");
+//! let tv = stream.next().unwrap().clone();
+//! let television = stream.next().unwrap().clone();
+//! assert_eq!(tv.text, "tv");
+//! assert_eq!(television.text, "television");
+//! assert_eq!(tv.position, television.position);
+//! assert_eq!(stream.next().unwrap().text, "show");
+//! assert!(stream.next().is_none());
+//! ```
+use super::{Token, TokenFilter, TokenStream};
+use crate::tokenizer::BoxTokenStream;
+use crate::TantivyError;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The synonyms of a token.
+#[derive(Clone, Debug, Default)]
+struct SynonymEntry {
+    /// The words of each synonym.
+    synonyms: Vec<Vec<String>>,
+    /// Whether the original token is emitted as well.
+    keep_original: bool,
+}
+
+impl SynonymEntry {
+    fn add_synonym(&mut self, synonym: &str) {
+        let words: Vec<String> = synonym.split_whitespace().map(str::to_string).collect();
+        if !words.is_empty() && !self.synonyms.contains(&words) {
+            self.synonyms.push(words);
+        }
+    }
+}
+
+/// `TokenFilter` that adds the synonyms of the tokens to a token stream,
+/// at index time.
+///
+/// The synonyms of a token are emitted right after it, with the same position
+/// and offsets, so that phrase queries still match the original text as well as
+/// the text with the token replaced by one of its synonyms.
+///
+/// The words of a multi word synonym, like `united states of america` for `usa`,
+/// are emitted at consecutive positions, starting from the position of the original token.
+/// A phrase query over the synonym then matches, but a phrase query mixing its
+/// words with the tokens following the original token may match as well. For instance, both
+/// `"usa today"` and `"united today"` match `usa today`.
+///
+/// Tokens are matched on their exact text: the filter is usually placed
+/// after a `LowerCaser`, with a lowercase mapping.
+/// Note that the synonyms count as tokens of the field for scoring.
+#[derive(Clone, Debug)]
+pub struct SynonymFilter {
+    synonyms: Arc<HashMap<String, SynonymEntry>>,
+}
+
+impl SynonymFilter {
+    /// Creates a `SynonymFilter` given a list of tokens and their synonyms.
+    ///
+    /// The original tokens are kept. A synonym made of several words separated
+    /// by whitespaces is a multi word synonym.
+    pub fn new(synonyms: Vec<(String, Vec<String>)>) -> SynonymFilter {
+        let mut entries: HashMap<String, SynonymEntry> = HashMap::new();
+        for (token, token_synonyms) in synonyms {
+            let entry = entries.entry(token).or_default();
+            entry.keep_original = true;
+            for synonym in &token_synonyms {
+                entry.add_synonym(synonym);
+            }
+        }
+        SynonymFilter {
+            synonyms: Arc::new(entries),
+        }
+    }
+
+    /// Creates a `SynonymFilter` from synonyms in the Solr format.
+    ///
+    /// Each line is a rule, empty lines and lines starting with `#` are ignored.
+    /// - `tv, television, telly` defines equivalent synonyms: each of them is expanded to
+    ///   all of the others, and is kept.
+    /// - `tv, telly => television` defines an explicit mapping: the tokens on the left are
+    ///   replaced by the synonyms on the right.
+    ///
+    /// Only single words are matched: multi word synonyms can appear on the right
+    /// of an explicit mapping, or in equivalent synonyms, where they are not expanded.
+    /// An error is returned if the left of an explicit mapping is not a single word.
+    pub fn from_solr_synonyms(rules: &str) -> crate::Result<SynonymFilter> {
+        let mut entries: HashMap<String, SynonymEntry> = HashMap::new();
+        let split_synonyms = |synonyms: &str| -> Vec<String> {
+            synonyms
+                .split(',')
+                .map(|synonym| synonym.split_whitespace().collect::<Vec<_>>().join(" "))
+                .filter(|synonym| !synonym.is_empty())
+                .collect()
+        };
+        for (line_id, line) in rules.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid_rule = || {
+                TantivyError::InvalidArgument(format!(
+                    "Invalid synonym rule on line {}: {:?}",
+                    line_id + 1,
+                    line
+                ))
+            };
+            let mut sides = line.split("=>");
+            let left = split_synonyms(sides.next().unwrap_or(""));
+            match (sides.next(), sides.next()) {
+                (None, _) => {
+                    for token in left.iter().filter(|token| !token.contains(' ')) {
+                        let entry = entries.entry(token.clone()).or_default();
+                        entry.keep_original = true;
+                        for synonym in left.iter().filter(|synonym| *synonym != token) {
+                            entry.add_synonym(synonym);
+                        }
+                    }
+                }
+                (Some(right), None) => {
+                    let right = split_synonyms(right);
+                    if left.is_empty()
+                        || right.is_empty()
+                        || left.iter().any(|token| token.contains(' '))
+                    {
+                        return Err(invalid_rule());
+                    }
+                    for token in &left {
+                        let entry = entries.entry(token.clone()).or_default();
+                        for synonym in &right {
+                            if synonym == token {
+                                entry.keep_original = true;
+                            } else {
+                                entry.add_synonym(synonym);
+                            }
+                        }
+                    }
+                }
+                (Some(_), Some(_)) => return Err(invalid_rule()),
+            }
+        }
+        Ok(SynonymFilter {
+            synonyms: Arc::new(entries),
+        })
+    }
+}
+
+impl TokenFilter for SynonymFilter {
+    fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(SynonymFilterStream {
+            synonyms: Arc::clone(&self.synonyms),
+            tail: token_stream,
+            pending_tokens: Vec::new(),
+            token: Token::default(),
+        })
+    }
+}
+
+pub struct SynonymFilterStream<'a> {
+    synonyms: Arc<HashMap<String, SynonymEntry>>,
+    tail: BoxTokenStream<'a>,
+    /// Synonym tokens left to emit, in reverse order.
+    pending_tokens: Vec<Token>,
+    token: Token,
+}
+
+impl<'a> SynonymFilterStream<'a> {
+    fn push_synonyms(&mut self, entry: &SynonymEntry) {
+        let original_token = self.tail.token();
+        for synonym in entry.synonyms.iter().rev() {
+            for (word_id, word) in synonym.iter().enumerate().rev() {
+                self.pending_tokens.push(Token {
+                    offset_from: original_token.offset_from,
+                    offset_to: original_token.offset_to,
+                    position: original_token.position.wrapping_add(word_id),
+                    text: word.clone(),
+                    position_length: 1,
+                    payload: original_token.payload,
+                });
+            }
+        }
+    }
+}
+
+impl<'a> TokenStream for SynonymFilterStream<'a> {
+    fn advance(&mut self) -> bool {
+        loop {
+            if let Some(token) = self.pending_tokens.pop() {
+                self.token = token;
+                return true;
+            }
+            if !self.tail.advance() {
+                return false;
+            }
+            let synonyms = Arc::clone(&self.synonyms);
+            match synonyms.get(&self.tail.token().text) {
+                Some(entry) => {
+                    self.push_synonyms(entry);
+                    if entry.keep_original {
+                        self.token.clone_from(self.tail.token());
+                        return true;
+                    }
+                }
+                None => {
+                    self.token.clone_from(self.tail.token());
+                    return true;
+                }
+            }
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SynonymFilter;
+    use crate::collector::Count;
+    use crate::query::{PhraseQuery, Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TextFieldIndexing, TextOptions};
+    use crate::tokenizer::tests::assert_token;
+    use crate::tokenizer::{LowerCaser, SimpleTokenizer, TextAnalyzer, Token};
+    use crate::{Index, Term};
+
+    fn tokens(filter: SynonymFilter, text: &str) -> Vec<Token> {
+        let mut tokens = vec![];
+        TextAnalyzer::from(SimpleTokenizer)
+            .filter(LowerCaser)
+            .filter(filter)
+            .token_stream(text)
+            .process(&mut |token: &Token| tokens.push(token.clone()));
+        tokens
+    }
+
+    fn synonyms(synonyms: &[(&str, &[&str])]) -> SynonymFilter {
+        SynonymFilter::new(
+            synonyms
+                .iter()
+                .map(|(token, synonyms)| {
+                    let synonyms = synonyms.iter().map(|synonym| synonym.to_string());
+                    (token.to_string(), synonyms.collect())
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_synonym_filter() {
+        let filter = synonyms(&[("tv", &["television", "telly"]), ("big", &["large"])]);
+        let tokens = tokens(filter, "Big TV, small tv");
+        assert_eq!(tokens.len(), 9);
+        assert_token(&tokens[0], 0, "big", 0, 3);
+        assert_token(&tokens[1], 0, "large", 0, 3);
+        assert_token(&tokens[2], 1, "tv", 4, 6);
+        assert_token(&tokens[3], 1, "television", 4, 6);
+        assert_token(&tokens[4], 1, "telly", 4, 6);
+        assert_token(&tokens[5], 2, "small", 8, 13);
+        assert_token(&tokens[6], 3, "tv", 14, 16);
+        assert_token(&tokens[7], 3, "television", 14, 16);
+        assert_token(&tokens[8], 3, "telly", 14, 16);
+    }
+
+    #[test]
+    fn test_synonym_filter_multi_word() {
+        let filter = synonyms(&[("usa", &["united states of america", "us"])]);
+        let tokens = tokens(filter, "USA today");
+        assert_eq!(tokens.len(), 7);
+        assert_token(&tokens[0], 0, "usa", 0, 3);
+        assert_token(&tokens[1], 0, "united", 0, 3);
+        assert_token(&tokens[2], 1, "states", 0, 3);
+        assert_token(&tokens[3], 2, "of", 0, 3);
+        assert_token(&tokens[4], 3, "america", 0, 3);
+        assert_token(&tokens[5], 0, "us", 0, 3);
+        assert_token(&tokens[6], 1, "today", 4, 9);
+    }
+
+    #[test]
+    fn test_synonym_filter_solr_format() -> crate::Result<()> {
+        let filter = SynonymFilter::from_solr_synonyms(
+            "# comment\n\
+             \n\
+             tv, television ,telly\n\
+             usa, united   states\n\
+             colour => color\n\
+             i-pod, ipod => ipod\n",
+        )?;
+        let texts = |text: &str| -> Vec<(String, usize)> {
+            tokens(filter.clone(), text)
+                .into_iter()
+                .map(|token| (token.text, token.position))
+                .collect()
+        };
+        let texts_ref = |texts: &[(&str, usize)]| -> Vec<(String, usize)> {
+            texts.iter().map(|(t, p)| (t.to_string(), *p)).collect()
+        };
+        assert_eq!(
+            texts("telly"),
+            texts_ref(&[("telly", 0), ("tv", 0), ("television", 0)])
+        );
+        assert_eq!(
+            texts("usa"),
+            texts_ref(&[("usa", 0), ("united", 0), ("states", 1)])
+        );
+        // Multi word synonyms are not expanded.
+        assert_eq!(
+            texts("united states"),
+            texts_ref(&[("united", 0), ("states", 1)])
+        );
+        assert_eq!(
+            texts("colour color"),
+            texts_ref(&[("color", 0), ("color", 1)])
+        );
+        assert_eq!(texts("ipod"), texts_ref(&[("ipod", 0)]));
+        assert!(SynonymFilter::from_solr_synonyms("a => b => c").is_err());
+        assert!(SynonymFilter::from_solr_synonyms("a b => c").is_err());
+        assert!(SynonymFilter::from_solr_synonyms("a =>").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_synonym_filter_search() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("synonyms")
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let text = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let filter = synonyms(&[
+            ("tv", &["television"]),
+            ("usa", &["united states of america"]),
+        ]);
+        index.tokenizers().register(
+            "synonyms",
+            TextAnalyzer::from(SimpleTokenizer)
+                .filter(LowerCaser)
+                .filter(filter),
+        );
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "watching tv in the usa today"));
+        index_writer.add_document(doc!(text => "watching television"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count = |query: &dyn Query| searcher.search(query, &Count);
+        let term = |word: &str| Term::from_field_text(text, word);
+        let phrase = |words: &[&str]| PhraseQuery::new(words.iter().map(|w| term(w)).collect());
+
+        assert_eq!(
+            count(&TermQuery::new(
+                term("television"),
+                IndexRecordOption::Basic
+            ))?,
+            2
+        );
+        assert_eq!(
+            count(&TermQuery::new(term("tv"), IndexRecordOption::Basic))?,
+            1
+        );
+        assert_eq!(count(&phrase(&["watching", "television"]))?, 2);
+        assert_eq!(count(&phrase(&["television", "in", "the"]))?, 1);
+        assert_eq!(count(&phrase(&["the", "usa", "today"]))?, 1);
+        assert_eq!(
+            count(&phrase(&["the", "united", "states", "of", "america"]))?,
+            1
+        );
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
-use crate::tokenizer::TokenStreamChain;
+use crate::tokenizer::{BoxCharFilter, FilteredText, TokenStreamChain};
+use crate::tokenizer::{PreTokenizedStream, PreTokenizedString};
 use serde::{Deserialize, Serialize};
 /// The tokenizer module contains all of the tools used to process
 /// text in `tantivy`.
@@ -21,6 +22,16 @@ pub struct Token {
     pub text: String,
     /// Is the length expressed in term of number of original tokens.
     pub position_length: usize,
+    /// Payload attached to the token by a token filter, `0` if none.
+    ///
+    /// It is only indexed if the field is indexed with
+    /// `IndexRecordOption::WithFreqsAndPositionsAndPayloads`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub payload: u32,
+}
+
+fn is_zero(payload: &u32) -> bool {
+    *payload == 0
 }
 
 impl Default for Token {
@@ -31,14 +42,17 @@ impl Default for Token {
             position: usize::max_value(),
             text: String::with_capacity(200),
             position_length: 1,
+            payload: 0,
         }
     }
 }
 
 /// `TextAnalyzer` tokenizes an input text into tokens and modifies the resulting `TokenStream`.
 ///
-/// It simply wraps a `Tokenizer` and a list of `TokenFilter` that are applied sequentially.
+/// It simply wraps a `Tokenizer` and a list of `TokenFilter` that are applied sequentially,
+/// possibly after a list of `CharFilter` rewriting the input text.
 pub struct TextAnalyzer {
+    char_filters: Vec<BoxCharFilter>,
     tokenizer: Box<dyn Tokenizer>,
     token_filters: Vec<BoxTokenFilter>,
 }
@@ -56,11 +70,34 @@ impl TextAnalyzer {
     /// `TextAnalyzer::from(tokenizer)`.
     pub fn new<T: Tokenizer>(tokenizer: T, token_filters: Vec<BoxTokenFilter>) -> TextAnalyzer {
         TextAnalyzer {
+            char_filters: Vec::new(),
             tokenizer: Box::new(tokenizer),
             token_filters,
         }
     }
 
+    /// Appends a char filter, rewriting the text before it is tokenized.
+    ///
+    /// The char filters are applied in order. The offsets of the tokens
+    /// refer to the text before it was filtered.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tantivy::tokenizer::*;
+    ///
+    /// let tokenizer = TextAnalyzer::from(WhitespaceTokenizer)
+    ///     .char_filter(MappingCharFilter::new(vec![('’', '\'')]));
+    /// let mut stream = tokenizer.token_stream("l’été");
+    /// let token = stream.next().unwrap();
+    /// assert_eq!(token.text, "l'été");
+    /// assert_eq!((token.offset_from, token.offset_to), (0, 9));
+    /// ```
+    pub fn char_filter<F: Into<BoxCharFilter>>(mut self, char_filter: F) -> Self {
+        self.char_filters.push(char_filter.into());
+        self
+    }
+
     /// Appends a token filter to the current tokenizer.
     ///
     /// The method consumes the current `TokenStream` and returns a
@@ -109,7 +146,11 @@ impl TextAnalyzer {
 
     /// Creates a token stream for a given `str`.
     pub fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
-        let mut token_stream = self.tokenizer.token_stream(text);
+        let mut token_stream = if self.char_filters.is_empty() {
+            self.tokenizer.token_stream(text)
+        } else {
+            self.char_filtered_token_stream(text)
+        };
         for token_filter in &self.token_filters {
             token_stream = token_filter.transform(token_stream);
         }
@@ -117,9 +158,50 @@ impl TextAnalyzer {
     }
 }
 
+impl TextAnalyzer {
+    /// Tokenizes the text filtered by the char filters, mapping the offsets of
+    /// the tokens back to `text`.
+    ///
+    /// The tokens are collected eagerly, as they cannot borrow the filtered text.
+    fn char_filtered_token_stream<'a>(&self, text: &str) -> BoxTokenStream<'a> {
+        let mut filtered_texts: Vec<FilteredText> = Vec::with_capacity(self.char_filters.len());
+        for char_filter in &self.char_filters {
+            let filtered_text = match filtered_texts.last() {
+                Some(filtered_text) => char_filter.filter(filtered_text.text()),
+                None => char_filter.filter(text),
+            };
+            filtered_texts.push(filtered_text);
+        }
+        let filtered_text = filtered_texts.last().map_or(text, FilteredText::text);
+        let mut tokens = Vec::new();
+        self.tokenizer
+            .token_stream(filtered_text)
+            .process(&mut |token: &Token| {
+                let mut token = token.clone();
+                for filtered_text in filtered_texts.iter().rev() {
+                    token.offset_from = filtered_text.original_offset_from(token.offset_from);
+                    token.offset_to = filtered_text
+                        .original_offset_to(token.offset_to)
+                        .max(token.offset_from);
+                }
+                tokens.push(token);
+            });
+        PreTokenizedStream::from(PreTokenizedString {
+            text: String::new(),
+            tokens,
+        })
+        .into()
+    }
+}
+
 impl Clone for TextAnalyzer {
     fn clone(&self) -> Self {
         TextAnalyzer {
+            char_filters: self
+                .char_filters
+                .iter()
+                .map(|char_filter| char_filter.box_clone())
+                .collect(),
             tokenizer: self.tokenizer.box_clone(),
             token_filters: self
                 .token_filters
@@ -322,6 +404,7 @@ mod test {
             offset_to: 3,
             text: "abc".to_string(),
             position_length: 1,
+            payload: 0,
         };
         let t2 = t1.clone();
 
@@ -1,14 +1,21 @@
+use crate::tokenizer::analyzer_config::{register_builtin_factories, AnalyzerConfig};
 use crate::tokenizer::stemmer::Language;
 use crate::tokenizer::tokenizer::TextAnalyzer;
+use crate::tokenizer::BoxTokenFilter;
 use crate::tokenizer::LowerCaser;
 use crate::tokenizer::RawTokenizer;
 use crate::tokenizer::RemoveLongFilter;
 use crate::tokenizer::SimpleTokenizer;
 use crate::tokenizer::Stemmer;
 use crate::tokenizer::WhitespaceTokenizer;
+use crate::TantivyError;
+use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
+type TokenizerFactory = Arc<dyn Fn(&Value) -> crate::Result<TextAnalyzer> + Send + Sync>;
+type FilterFactory = Arc<dyn Fn(&Value) -> crate::Result<BoxTokenFilter> + Send + Sync>;
+
 /// The tokenizer manager serves as a store for
 /// all of the pre-configured tokenizer pipelines.
 ///
@@ -21,9 +28,23 @@ use std::sync::{Arc, RwLock};
 ///  * `en_stem` : Like `default`, but also applies stemming on the
 ///  resulting tokens. Stemming can improve the recall of your
 ///  search engine.
+///
+/// It also stores the factories of tokenizers and token filters used to build
+/// a `TextAnalyzer` from an [`AnalyzerConfig`](./struct.AnalyzerConfig.html) at runtime.
+/// The following factories are registered by default:
+///
+///  * tokenizers: `simple`, `raw`, `whitespace`, `cjk_bigram`,
+///    `ngram` (`min_gram`, `max_gram`, `prefix_only`) and `regex` (`pattern`, and `mode`,
+///    either `match` or `split`).
+///  * token filters: `lowercase`, `ascii_folding`, `alphanum_only`, `remove_long` (`limit`),
+///    `edge_ngram` (`min_gram`, `max_gram`, `side`, either `front` or `back`, `keep_short_tokens`),
+///    `stemmer` (`language`), `stop_words` (`language` or `words`, English by default) and
+///    `shingle` (`min_size`, `max_size`, `output_unigrams`, `separator`).
 #[derive(Clone)]
 pub struct TokenizerManager {
     tokenizers: Arc<RwLock<HashMap<String, TextAnalyzer>>>,
+    tokenizer_factories: Arc<RwLock<HashMap<String, TokenizerFactory>>>,
+    filter_factories: Arc<RwLock<HashMap<String, FilterFactory>>>,
 }
 
 impl TokenizerManager {
@@ -47,6 +68,69 @@ impl TokenizerManager {
             .get(tokenizer_name)
             .cloned()
     }
+
+    /// Registers a factory of tokenizers, given the JSON object of their parameters.
+    ///
+    /// The factory returns a `TextAnalyzer`, to which the token filters of the
+    /// configuration are then appended.
+    pub fn register_tokenizer_factory<F>(&self, name: &str, factory: F)
+    where
+        F: Fn(&Value) -> crate::Result<TextAnalyzer> + Send + Sync + 'static,
+    {
+        self.tokenizer_factories
+            .write()
+            .expect("Acquiring the lock should never fail")
+            .insert(name.to_string(), Arc::new(factory));
+    }
+
+    /// Registers a factory of token filters, given the JSON object of their parameters.
+    pub fn register_filter_factory<F>(&self, name: &str, factory: F)
+    where
+        F: Fn(&Value) -> crate::Result<BoxTokenFilter> + Send + Sync + 'static,
+    {
+        self.filter_factories
+            .write()
+            .expect("Acquiring the lock should never fail")
+            .insert(name.to_string(), Arc::new(factory));
+    }
+
+    /// Builds a `TextAnalyzer` from its configuration, using the registered factories.
+    ///
+    /// Returns an error if a factory is unknown, or if it fails, e.g. because of
+    /// invalid parameters. The analyzer is not registered.
+    pub fn build_analyzer(&self, config: &AnalyzerConfig) -> crate::Result<TextAnalyzer> {
+        let tokenizer_factory = self
+            .tokenizer_factories
+            .read()
+            .expect("Acquiring the lock should never fail")
+            .get(&config.tokenizer.name)
+            .cloned()
+            .ok_or_else(|| {
+                TantivyError::InvalidArgument(format!(
+                    "Unknown tokenizer {:?}",
+                    config.tokenizer.name
+                ))
+            })?;
+        let mut analyzer = tokenizer_factory(&Value::Object(config.tokenizer.params.clone()))?;
+        for filter_config in &config.filters {
+            let filter_factory = self
+                .filter_factories
+                .read()
+                .expect("Acquiring the lock should never fail")
+                .get(&filter_config.name)
+                .cloned()
+                .ok_or_else(|| {
+                    TantivyError::InvalidArgument(format!(
+                        "Unknown token filter {:?}",
+                        filter_config.name
+                    ))
+                })?;
+            analyzer = analyzer.filter(filter_factory(&Value::Object(
+                filter_config.params.clone(),
+            ))?);
+        }
+        Ok(analyzer)
+    }
 }
 
 impl Default for TokenizerManager {
@@ -58,6 +142,8 @@ impl Default for TokenizerManager {
     fn default() -> TokenizerManager {
         let manager = TokenizerManager {
             tokenizers: Arc::new(RwLock::new(HashMap::new())),
+            tokenizer_factories: Arc::new(RwLock::new(HashMap::new())),
+            filter_factories: Arc::new(RwLock::new(HashMap::new())),
         };
         manager.register("raw", RawTokenizer);
         manager.register(
@@ -74,6 +160,7 @@ impl Default for TokenizerManager {
                 .filter(Stemmer::new(Language::English)),
         );
         manager.register("whitespace", WhitespaceTokenizer);
+        register_builtin_factories(&manager);
         manager
     }
 }
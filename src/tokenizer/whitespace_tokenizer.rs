@@ -36,6 +36,7 @@ impl<'a> WhitespaceTokenStream<'a> {
 impl<'a> TokenStream for WhitespaceTokenStream<'a> {
     fn advance(&mut self) -> bool {
         self.token.text.clear();
+        self.token.payload = 0;
         self.token.position = self.token.position.wrapping_add(1);
         while let Some((offset_from, c)) = self.chars.next() {
             if !c.is_ascii_whitespace() {